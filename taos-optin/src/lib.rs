@@ -9,8 +9,10 @@ use once_cell::sync::OnceCell;
 use raw::{ApiEntry, RawRes, RawTaos, SharedState};
 // use taos_error::Error as RawError;
 use taos_query::{
-    prelude::{Field, Precision, RawError, RawMeta},
-    DsnError, RawBlock, TBuilder,
+    common::{BlockPool, SmlData},
+    prelude::{tokio, Code, Field, LogConfig, Precision, RateLimit, RawError, RawMeta},
+    util::{generate_req_id, RateLimiter},
+    ConnState, ConnStateNotifier, DsnError, RawBlock, StateListener, TBuilder,
 };
 
 mod version {
@@ -72,6 +74,7 @@ mod version {
 }
 mod into_c_str;
 mod raw;
+mod set_config;
 mod stmt;
 
 #[allow(non_camel_case_types)]
@@ -127,11 +130,111 @@ macro_rules! err_or {
 #[derive(Debug)]
 pub struct Taos {
     raw: RawTaos,
+    state: ConnStateNotifier,
+    /// Held while [Taos::query_in] switches the current database.
+    scope: tokio::sync::Mutex<()>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    block_pool: Option<BlockPool>,
 }
 
 impl Drop for Taos {
     fn drop(&mut self) {
         self.raw.close();
+        self.state.close("closed by client");
+    }
+}
+
+impl Taos {
+    /// Current connection state.
+    pub fn state(&self) -> ConnState {
+        self.state.state()
+    }
+
+    /// The write limiter of the builder, see [TaosBuilder::with_rate_limit].
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    /// Track connection state by query results.
+    ///
+    /// The native client re-establishes lost connections internally, so a disconnection error
+    /// is reported as a reconnecting attempt, and the next successful query means connected.
+    fn track<T>(&self, res: Result<T, RawError>) -> Result<T, RawError> {
+        match &res {
+            Ok(_) => {
+                if let ConnState::Reconnecting { .. } = self.state.state() {
+                    self.state.set(ConnState::Connected {
+                        server_version: self.raw.server_version().to_string_lossy().to_string(),
+                    });
+                }
+            }
+            Err(err) if ConnState::is_disconnected_code(err.code()) => {
+                let attempt = match self.state.state() {
+                    ConnState::Reconnecting { attempt } => attempt + 1,
+                    _ => 1,
+                };
+                self.state.set(ConnState::Reconnecting { attempt });
+            }
+            Err(_) => (),
+        }
+        res
+    }
+
+    /// Query in database `db` without changing the current database of the connection.
+    ///
+    /// The native client has no per-query database, so it switches by `taos_select_db` and
+    /// restores the previous database afterwards, holding a per-connection lock in between.
+    /// Concurrent `query_in` and `exec_in` calls on a connection are serialized and each one
+    /// costs two more requests, `select database()` and the switch back. Queries by
+    /// [taos_query::AsyncQueryable::query] are not locked, so they may run in `db` while it is selected;
+    /// prefer `db.table` names or a connection per database in hot paths.
+    pub async fn query_in(&self, db: &str, sql: &str) -> Result<ResultSet, RawError> {
+        let _scope = self.scope.lock().await;
+        let previous =
+            taos_query::AsyncQueryable::query_one::<_, Option<String>>(self, "select database()")
+                .await?
+                .flatten();
+        self.track(self.raw.select_db(db))?;
+        let res = taos_query::AsyncQueryable::query(self, sql).await;
+        match previous {
+            Some(previous) => self.raw.select_db(previous)?,
+            None => self.raw.reset_current_db(),
+        }
+        res
+    }
+
+    /// Query with a request id, which is logged by the server to correlate with client logs.
+    ///
+    /// The id is not sent by clients without `taos_query_with_reqid`, before 3.0.
+    pub async fn query_with_req_id(&self, sql: &str, req_id: u64) -> Result<ResultSet, RawError> {
+        log::debug!("[req id: {req_id:#x}] Async query with SQL: {sql}");
+        let res = loop {
+            match self.raw.query_async_with_req_id(sql, req_id).await {
+                Err(err) if err.code() == 0x2603 => continue,
+                Err(err) => break Err(err),
+                Ok(raw) => break Ok(ResultSet::new(raw).with_req_id(req_id)),
+            }
+        };
+        self.track(res)
+            .map(|rs| rs.with_block_pool(self.block_pool.clone()))
+    }
+
+    /// Execute in database `db`, see [Taos::query_in].
+    pub async fn exec_in(&self, db: &str, sql: &str) -> Result<usize, RawError> {
+        self.query_in(db, sql)
+            .await
+            .map(|res| taos_query::AsyncFetchable::affected_rows(&res) as _)
+    }
+
+    /// Write schemaless data, tables are created or altered as needed.
+    ///
+    /// It needs `taos_schemaless_insert_raw_ttl_with_reqid` of the client, since 3.0.3.
+    pub fn put(&self, data: &SmlData) -> Result<(), RawError> {
+        if let Some(limiter) = &self.rate_limiter {
+            let bytes = data.lines().iter().map(String::len).sum();
+            limiter.acquire_blocking(data.lines().len(), bytes);
+        }
+        self.track(self.raw.put(data))
     }
 }
 
@@ -141,8 +244,11 @@ impl taos_query::Queryable for Taos {
     type ResultSet = ResultSet;
 
     fn query<T: AsRef<str>>(&self, sql: T) -> Result<Self::ResultSet, Self::Error> {
-        log::debug!("Query with SQL: {}", sql.as_ref());
-        self.raw.query(sql.as_ref()).map(ResultSet::new)
+        let req_id = generate_req_id();
+        log::debug!("[req id: {req_id:#x}] Query with SQL: {}", sql.as_ref());
+        self.track(self.raw.query_with_req_id(sql.as_ref(), req_id))
+            .map(|raw| ResultSet::new(raw).with_req_id(req_id))
+            .map(|rs| rs.with_block_pool(self.block_pool.clone()))
     }
 
     fn write_raw_meta(&self, meta: &RawMeta) -> Result<(), Self::Error> {
@@ -151,6 +257,9 @@ impl taos_query::Queryable for Taos {
     }
 
     fn write_raw_block(&self, raw: &RawBlock) -> Result<(), Self::Error> {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire_blocking(raw.nrows(), raw.as_raw_bytes().len());
+        }
         self.raw.write_raw_block(raw)
     }
 }
@@ -165,14 +274,8 @@ impl taos_query::AsyncQueryable for Taos {
         &self,
         sql: T,
     ) -> Result<Self::AsyncResultSet, Self::Error> {
-        log::debug!("Async query with SQL: {}", sql.as_ref());
-        loop {
-            match self.raw.query_async(sql.as_ref()).await {
-                Err(err) if err.code() == 0x2603 => continue,
-                Err(err) => break Err(err),
-                Ok(raw) => break Ok(ResultSet::new(raw)),
-            }
-        }
+        self.query_with_req_id(sql.as_ref(), generate_req_id())
+            .await
     }
 
     async fn write_raw_meta(&self, meta: &taos_query::common::RawMeta) -> Result<(), Self::Error> {
@@ -180,6 +283,11 @@ impl taos_query::AsyncQueryable for Taos {
     }
 
     async fn write_raw_block(&self, block: &RawBlock) -> Result<(), Self::Error> {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter
+                .acquire(block.nrows(), block.as_raw_bytes().len())
+                .await;
+        }
         self.raw.write_raw_block(block)
     }
 }
@@ -228,14 +336,67 @@ pub struct TaosBuilder {
     lib: Arc<ApiEntry>,
     inner_conn: OnceCell<Taos>,
     server_version: OnceCell<String>,
+    state_listener: Option<StateListener>,
+    /// Write budget shared by connections of the builder, see [TaosBuilder::with_rate_limit].
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Buffers of fetched blocks, see [TaosBuilder::with_block_pool].
+    block_pool: Option<BlockPool>,
 }
 impl TaosBuilder {
+    /// Set a callback to receive connection state changes of connections built by this builder.
+    pub fn on_state_change(mut self, f: impl Fn(ConnState) + Send + Sync + 'static) -> Self {
+        self.state_listener = Some(StateListener::new(f));
+        self
+    }
+
+    /// Set log options of the native client, instead of editing taos.cfg.
+    ///
+    /// ```rust,no_run
+    /// # use taos_optin::{prelude::*, Error};
+    /// let builder = TaosBuilder::from_dsn("taos://")?.native_log(LogConfig {
+    ///     dir: Some("/tmp/taos-debug".into()),
+    ///     debug_flag: Some(LogConfig::DEBUG),
+    ///     ..Default::default()
+    /// })?;
+    /// # Ok::<_, Error>(())
+    /// ```
+    ///
+    /// The options are read once when the native client initializes, so it fails after the first
+    /// connection or consumer in the process, or with clients without `taos_set_config`.
+    pub fn native_log(self, config: LogConfig) -> Result<Self, Error> {
+        self.lib.set_log_config(&config)?;
+        Ok(self)
+    }
+
+    /// Cap write throughput of connections and statements built by this builder.
+    ///
+    /// Raw block writes, schemaless writes and stmt executions take their rows and bytes from a
+    /// token bucket shared by all of them, a line or JSON item of schemaless data counts as a row.
+    /// Synchronous writes block the calling thread when the budget is spent,
+    /// asynchronous writes wait without blocking.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(limit)));
+        self
+    }
+
+    /// The limiter set by [TaosBuilder::with_rate_limit], eg. to report its utilization.
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    /// Copy blocks fetched from the native client into buffers of `pool` instead of new
+    /// allocations, buffers go back to the pool when the blocks are dropped.
+    pub fn with_block_pool(mut self, pool: BlockPool) -> Self {
+        self.block_pool = Some(pool);
+        self
+    }
+
     fn inner_connection(&self) -> Result<&Taos, Error> {
         self.inner_conn.get_or_try_init(|| self.build())
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Default)]
 struct Auth {
     host: Option<CString>,
     user: Option<CString>,
@@ -244,6 +405,18 @@ struct Auth {
     port: u16,
 }
 
+impl std::fmt::Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Auth")
+            .field("host", &self.host)
+            .field("user", &self.user)
+            .field("pass", &self.pass.as_ref().map(|_| "***"))
+            .field("db", &self.db)
+            .field("port", &self.port)
+            .finish()
+    }
+}
+
 impl Auth {
     pub(crate) fn host(&self) -> Option<&CStr> {
         self.host.as_deref()
@@ -277,6 +450,12 @@ impl Auth {
 #[derive(Debug)]
 pub struct Error(RawError);
 
+impl Error {
+    pub fn code(&self) -> Code {
+        self.0.code()
+    }
+}
+
 impl From<DsnError> for Error {
     fn from(err: DsnError) -> Self {
         Self(RawError::from_string(err.to_string()))
@@ -287,7 +466,16 @@ impl From<RawError> for Error {
         Self(err)
     }
 }
-impl std::error::Error for Error {}
+impl From<taos_query::stmt::CoerceError> for Error {
+    fn from(err: taos_query::stmt::CoerceError) -> Self {
+        Self(err.into())
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -307,8 +495,16 @@ impl TBuilder for TaosBuilder {
 
     fn from_dsn<D: taos_query::IntoDsn>(dsn: D) -> Result<Self, Self::Error> {
         let mut dsn = dsn.into_dsn()?;
+        // TLS of websocket connections, native connections have no such settings.
+        if let Some(param) = dsn.params.keys().find(|key| key.starts_with("ssl.")) {
+            return Err(DsnError::InvalidParam(
+                param.clone(),
+                "not supported for taos:// scheme".to_string(),
+            )
+            .into());
+        }
 
-        let lib = if let Some(path) = dsn.params.remove("libraryPath") {
+        let lib = if let Some(path) = dsn.remove("libraryPath") {
             log::trace!("using library path: {path}");
             ApiEntry::dlopen(path).unwrap()
         } else {
@@ -347,6 +543,9 @@ impl TBuilder for TaosBuilder {
             lib: Arc::new(lib),
             inner_conn: OnceCell::new(),
             server_version: OnceCell::new(),
+            state_listener: None,
+            rate_limiter: None,
+            block_pool: None,
         })
     }
 
@@ -364,10 +563,24 @@ impl TBuilder for TaosBuilder {
     }
 
     fn build(&self) -> Result<Self::Target, Self::Error> {
+        let state = ConnStateNotifier::new(self.state_listener.clone());
+        state.set(ConnState::Connecting);
         let ptr = self.lib.connect(&self.auth);
 
-        let raw = RawTaos::new(self.lib.clone(), ptr)?;
-        Ok(Taos { raw })
+        let raw = RawTaos::new(self.lib.clone(), ptr).map_err(|err| {
+            state.close(err.to_string());
+            err
+        })?;
+        state.set(ConnState::Connected {
+            server_version: raw.server_version().to_string_lossy().to_string(),
+        });
+        Ok(Taos {
+            raw,
+            state,
+            scope: Default::default(),
+            rate_limiter: self.rate_limiter.clone(),
+            block_pool: self.block_pool.clone(),
+        })
     }
 
     fn server_version(&self) -> Result<&str, Self::Error> {
@@ -426,6 +639,8 @@ pub struct ResultSet {
     fields: OnceCell<Vec<Field>>,
     summary: UnsafeCell<(usize, usize)>,
     state: Arc<UnsafeCell<SharedState>>,
+    block_pool: Option<BlockPool>,
+    req_id: Option<u64>,
 }
 
 impl ResultSet {
@@ -435,9 +650,26 @@ impl ResultSet {
             fields: OnceCell::new(),
             summary: UnsafeCell::new((0, 0)),
             state: Arc::new(UnsafeCell::new(SharedState::default())),
+            block_pool: None,
+            req_id: None,
         }
     }
 
+    fn with_req_id(mut self, req_id: u64) -> Self {
+        self.req_id = Some(req_id);
+        self
+    }
+
+    /// Request id of the query, `None` for results of stmt.
+    pub fn req_id(&self) -> Option<u64> {
+        self.req_id
+    }
+
+    fn with_block_pool(mut self, pool: Option<BlockPool>) -> Self {
+        self.block_pool = pool;
+        self
+    }
+
     fn precision(&self) -> Precision {
         self.raw.precision()
     }
@@ -459,6 +691,18 @@ impl ResultSet {
     pub(crate) fn affected_rows(&self) -> i32 {
         self.raw.affected_rows() as _
     }
+
+    /// Fetch blocks in background into a channel of `buffer` blocks, instead of polling them.
+    ///
+    /// The next block is fetched only when the channel has capacity, the result is freed when all
+    /// blocks are sent or the receiver is dropped, see [RawRes::stream_blocks].
+    pub fn stream_blocks(
+        mut self,
+        buffer: usize,
+    ) -> tokio::sync::mpsc::Receiver<Result<RawBlock, RawError>> {
+        let pool = self.block_pool.clone();
+        self.raw.take().stream_blocks(buffer, pool)
+    }
 }
 
 impl taos_query::Fetchable for ResultSet {
@@ -484,7 +728,8 @@ impl taos_query::Fetchable for ResultSet {
     }
 
     fn fetch_raw_block(&mut self) -> Result<Option<RawBlock>, Self::Error> {
-        self.raw.fetch_raw_block(self.fields())
+        self.raw
+            .fetch_raw_block(self.fields(), self.block_pool.as_ref())
     }
 }
 
@@ -511,8 +756,13 @@ impl taos_query::AsyncFetchable for ResultSet {
         &mut self,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<Option<RawBlock>, Self::Error>> {
-        self.raw
-            .fetch_raw_block_async(self.fields(), self.precision(), &self.state, cx)
+        self.raw.fetch_raw_block_async(
+            self.fields(),
+            self.precision(),
+            &self.state,
+            self.block_pool.as_ref(),
+            cx,
+        )
     }
 
     fn update_summary(&mut self, nrows: usize) {
@@ -522,7 +772,10 @@ impl taos_query::AsyncFetchable for ResultSet {
 
 impl Drop for ResultSet {
     fn drop(&mut self) {
-        self.raw.free_result();
+        // taken by `stream_blocks`.
+        if !self.raw.as_ptr().is_null() {
+            self.raw.free_result();
+        }
     }
 }
 
@@ -541,6 +794,16 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn ssl_params_rejected() {
+        let err = TaosBuilder::from_dsn("taos://localhost:6030?ssl.ca=/path/ca.pem").unwrap_err();
+        let err = err.to_string();
+        assert!(
+            err.contains("ssl.ca: not supported for taos:// scheme"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn show_databases() -> Result<(), Error> {
         use taos_query::prelude::sync::*;
@@ -550,7 +813,7 @@ mod tests {
 
         for raw in &mut set.blocks() {
             let raw = raw?;
-            for (col, view) in raw.columns().into_iter().enumerate() {
+            for (col, (_, view)) in raw.columns().enumerate() {
                 for (row, value) in view.iter().enumerate().take(10) {
                     println!("Value at (row: {}, col: {}) is: {}", row, col, value);
                 }
@@ -576,7 +839,7 @@ mod tests {
 
         for raw in &mut set.blocks() {
             let raw = raw?;
-            for (col, view) in raw.columns().into_iter().enumerate() {
+            for (col, (_, view)) in raw.columns().enumerate() {
                 for (row, value) in view.iter().enumerate().take(10) {
                     println!("Value at (row: {}, col: {}) is: {}", row, col, value);
                 }
@@ -653,7 +916,7 @@ mod tests {
 
         for raw in &mut set.blocks() {
             let raw = raw?;
-            for (col, view) in raw.columns().into_iter().enumerate() {
+            for (col, (_, view)) in raw.columns().enumerate() {
                 for (row, value) in view.iter().enumerate().take(10) {
                     println!("Value at (row: {}, col: {}) is: {}", row, col, value);
                 }
@@ -690,4 +953,78 @@ mod tests {
         println!("summary: {:?}", set.summary());
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stream_blocks_backpressure() -> anyhow::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        use bytes::{Bytes, BytesMut};
+        use taos_query::common::BlockBufferPool;
+        use taos_query::prelude::*;
+
+        /// Counts buffers of blocks alive, fetched but not dropped.
+        #[derive(Default)]
+        struct Live {
+            live: AtomicUsize,
+            max: AtomicUsize,
+        }
+
+        impl BlockBufferPool for Live {
+            fn acquire(&self, len: usize) -> BytesMut {
+                let live = self.live.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max.fetch_max(live, Ordering::SeqCst);
+                BytesMut::with_capacity(len)
+            }
+
+            fn recycle(&self, _: Bytes) {
+                self.live.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        const ROWS: usize = 10_000_000;
+        const BUFFER: usize = 4;
+        let live = Arc::new(Live::default());
+        let taos = TaosBuilder::from_dsn(DSN_V3)?
+            .with_block_pool(BlockPool::new(live.clone()))
+            .build()?;
+        taos.exec_many([
+            "drop database if exists stream_blocks",
+            "create database stream_blocks",
+            "create table stream_blocks.t (ts timestamp, v int)",
+        ])
+        .await?;
+        for batch in (0..ROWS).collect::<Vec<_>>().chunks(10_000) {
+            let values = batch
+                .iter()
+                .map(|i| format!("({}, {i})", 1_600_000_000_000i64 + *i as i64))
+                .join(" ");
+            taos.exec(format!("insert into stream_blocks.t values {values}"))
+                .await?;
+        }
+
+        let set = taos.query("select * from stream_blocks.t").await?;
+        let mut blocks = set.stream_blocks(BUFFER);
+        let mut rows = 0;
+        while let Some(block) = blocks.recv().await {
+            rows += block?.nrows();
+            // slow consumer.
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(rows, ROWS);
+        // the buffered blocks, the received one and the one being copied.
+        let max = live.max.load(Ordering::SeqCst);
+        assert!(max <= BUFFER + 2, "{max} blocks alive");
+
+        // dropping the receiver stops fetching.
+        let set = taos.query("select * from stream_blocks.t").await?;
+        let mut blocks = set.stream_blocks(BUFFER);
+        blocks.recv().await.unwrap()?;
+        drop(blocks);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(live.live.load(Ordering::SeqCst), 0);
+
+        taos.exec("drop database stream_blocks").await?;
+        Ok(())
+    }
 }
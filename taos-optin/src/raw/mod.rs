@@ -6,23 +6,27 @@ use std::{
     collections::HashMap,
     ffi::{c_char, c_int, c_ulong, c_void, CStr, CString},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     task::{Context, Poll, Waker},
 };
 
 use taos_query::{
-    common::{c_field_t, raw_data_t},
-    prelude::{Code, Field, Precision, RawError},
+    common::{c_field_t, raw_data_t, BlockPool, SmlData},
+    prelude::{Code, Field, LogConfig, Precision, RawError},
     RawBlock,
 };
 
 use crate::{
     err_or,
     into_c_str::IntoCStr,
+    set_config::{taos_set_config_t, SET_CONF_RET_SUCC},
     types::{
         from_raw_fields, taos_async_fetch_cb, taos_async_query_cb, tmq_commit_cb, tmq_conf_res_t,
-        tmq_conf_t, tmq_list_t, tmq_res_t, tmq_resp_err_t, tmq_t, TaosMultiBind, TAOS, TAOS_RES,
-        TAOS_ROW, TAOS_STMT, TSDB_OPTION,
+        tmq_conf_t, tmq_list_t, tmq_res_t, tmq_resp_err_t, tmq_t, tmq_topic_assignment,
+        TaosMultiBind, TAOS, TAOS_FIELD_E, TAOS_RES, TAOS_ROW, TAOS_STMT, TSDB_OPTION,
     },
     Auth,
 };
@@ -30,11 +34,15 @@ use crate::{
 use self::query_future::QueryFuture;
 
 mod query_future;
+mod stream;
 
 lazy_static::lazy_static! {
     static ref RAW_LIBRARIES: Mutex<HashMap<PathBuf, Arc<Library>>> = Mutex::new(HashMap::new());
 }
 
+/// Set when the native client is initialized by the first connection or consumer.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct ApiEntry {
@@ -43,6 +51,7 @@ pub struct ApiEntry {
     taos_cleanup: unsafe extern "C" fn(),
     taos_get_client_info: unsafe extern "C" fn() -> *const c_char,
     taos_options: unsafe extern "C" fn(option: TSDB_OPTION, arg: *const c_void, ...) -> c_int,
+    taos_set_config: Option<taos_set_config_t>,
     taos_connect: unsafe extern "C" fn(
         ip: *const c_char,
         user: *const c_char,
@@ -51,6 +60,9 @@ pub struct ApiEntry {
         port: u16,
     ) -> *mut TAOS,
     taos_close: unsafe extern "C" fn(taos: *mut TAOS),
+    taos_select_db: unsafe extern "C" fn(taos: *mut TAOS, db: *const c_char) -> c_int,
+    taos_reset_current_db: Option<unsafe extern "C" fn(taos: *mut TAOS)>,
+    taos_get_server_info: unsafe extern "C" fn(taos: *mut TAOS) -> *const c_char,
 
     // error handler
     taos_errno: unsafe extern "C" fn(taos: *const TAOS) -> c_int,
@@ -65,6 +77,16 @@ pub struct ApiEntry {
         fp: taos_async_query_cb,
         param: *mut c_void,
     ),
+    taos_query_a_with_reqid: Option<
+        unsafe extern "C" fn(
+            taos: *mut TAOS,
+            sql: *const c_char,
+            fp: taos_async_query_cb,
+            param: *mut c_void,
+            reqid: i64,
+        ),
+    >,
+    taos_kill_query: Option<unsafe extern "C" fn(taos: *mut TAOS)>,
     taos_result_block: Option<unsafe extern "C" fn(taos: *mut TAOS_RES) -> *mut *mut c_void>,
     taos_get_raw_block: Option<unsafe extern "C" fn(taos: *mut TAOS_RES) -> *mut c_void>,
     taos_fetch_raw_block_a: Option<
@@ -90,9 +112,24 @@ pub struct ApiEntry {
             fields_count: i32,
         ) -> i32,
     >,
+    taos_schemaless_insert_raw_ttl_with_reqid: Option<
+        unsafe extern "C" fn(
+            taos: *mut TAOS,
+            lines: *mut c_char,
+            len: c_int,
+            total_rows: *mut i32,
+            protocol: c_int,
+            precision: c_int,
+            ttl: i32,
+            reqid: i64,
+        ) -> *mut TAOS_RES,
+    >,
 
     // query
     taos_query: unsafe extern "C" fn(taos: *mut TAOS, sql: *const c_char) -> *mut TAOS_RES,
+    taos_query_with_reqid: Option<
+        unsafe extern "C" fn(taos: *mut TAOS, sql: *const c_char, reqid: i64) -> *mut TAOS_RES,
+    >,
     taos_free_result: unsafe extern "C" fn(res: *mut TAOS_RES),
     taos_result_precision: unsafe extern "C" fn(res: *mut TAOS_RES) -> c_int,
     taos_field_count: unsafe extern "C" fn(res: *mut TAOS_RES) -> c_int,
@@ -117,55 +154,16 @@ pub struct ApiEntry {
     pub(crate) tmq: Option<TmqApi>,
 }
 
+/// Functions of `tmq_list_t`, use them by the safe wrapper `TmqList` of consumers.
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct TmqListApi {
-    tmq_list_new: unsafe extern "C" fn() -> *mut tmq_list_t,
-    tmq_list_append: unsafe extern "C" fn(arg1: *mut tmq_list_t, arg2: *const c_char) -> i32,
-    tmq_list_destroy: unsafe extern "C" fn(list: *mut tmq_list_t),
-    tmq_list_get_size: unsafe extern "C" fn(list: *const tmq_list_t) -> i32,
-    tmq_list_to_c_array: unsafe extern "C" fn(list: *const tmq_list_t) -> *const *mut c_char,
-}
-
-impl TmqListApi {
-    pub(crate) unsafe fn new(&self) -> *mut tmq_list_t {
-        (self.tmq_list_new)()
-    }
-    pub(crate) unsafe fn destroy(&self, list: *mut tmq_list_t) {
-        (self.tmq_list_destroy)(list)
-    }
-
-    pub(crate) unsafe fn from_c_str_iter<'a, T: IntoCStr<'a>>(
-        &self,
-        iter: impl IntoIterator<Item = T>,
-    ) -> Result<*mut tmq_list_t, RawError> {
-        let list = self.new();
-        for item in iter {
-            self.append(list, item)?;
-        }
-        Ok(list)
-    }
-
-    pub(crate) fn append<'a>(
-        &self,
-        list: *mut tmq_list_t,
-        item: impl IntoCStr<'a>,
-    ) -> Result<(), RawError> {
-        let code = unsafe { (self.tmq_list_append)(list, item.into_c_str().as_ptr()) };
-        if code == 0 {
-            Ok(())
-        } else {
-            Err(RawError::new(Code::Failed, "append tmq list error"))
-        }
-    }
-
-    pub(crate) fn len(&self, list: *mut tmq_list_t) -> i32 {
-        unsafe { (self.tmq_list_get_size)(list) }
-    }
-    pub(crate) fn as_c_str_slice(&self, list: *mut tmq_list_t) -> &[*mut c_char] {
-        let len = self.len(list) as usize;
-        let data = unsafe { (self.tmq_list_to_c_array)(list) };
-        unsafe { std::slice::from_raw_parts(data, len) }
-    }
+    pub(crate) tmq_list_new: unsafe extern "C" fn() -> *mut tmq_list_t,
+    pub(crate) tmq_list_append:
+        unsafe extern "C" fn(arg1: *mut tmq_list_t, arg2: *const c_char) -> i32,
+    pub(crate) tmq_list_destroy: unsafe extern "C" fn(list: *mut tmq_list_t),
+    pub(crate) tmq_list_get_size: unsafe extern "C" fn(list: *const tmq_list_t) -> i32,
+    pub(crate) tmq_list_to_c_array:
+        unsafe extern "C" fn(list: *const tmq_list_t) -> *const *mut c_char,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -219,6 +217,7 @@ impl TmqConfApi {
     // }
 
     pub(crate) unsafe fn consumer(&self, conf: *mut tmq_conf_t) -> Result<*mut tmq_t, RawError> {
+        INITIALIZED.store(true, Ordering::SeqCst);
         let mut err = [0; 256];
         let tmq = (self.tmq_consumer_new)(conf, err.as_mut_ptr() as _, 255);
         if err[0] != 0 {
@@ -242,11 +241,11 @@ pub(crate) struct TmqApi {
     tmq_get_topic_name: unsafe extern "C" fn(res: *mut TAOS_RES) -> *const c_char,
     tmq_get_vgroup_id: unsafe extern "C" fn(res: *mut TAOS_RES) -> i32,
     tmq_get_raw: unsafe extern "C" fn(res: *mut TAOS_RES, raw: *mut raw_data_t) -> i32,
+    tmq_get_vgroup_offset: Option<unsafe extern "C" fn(res: *mut TAOS_RES) -> i64>,
 
     pub(crate) tmq_subscribe:
         unsafe extern "C" fn(tmq: *mut tmq_t, topics: *mut tmq_list_t) -> tmq_resp_err_t,
     pub(crate) tmq_unsubscribe: unsafe extern "C" fn(tmq: *mut tmq_t) -> tmq_resp_err_t,
-    #[allow(dead_code)]
     pub(crate) tmq_subscription:
         unsafe extern "C" fn(tmq: *mut tmq_t, topic_list: *mut *mut tmq_list_t) -> tmq_resp_err_t,
     pub(crate) tmq_consumer_poll:
@@ -260,6 +259,34 @@ pub(crate) struct TmqApi {
         cb: tmq_commit_cb,
         param: *mut c_void,
     ),
+    pub(crate) tmq_get_topic_assignment: Option<
+        unsafe extern "C" fn(
+            tmq: *mut tmq_t,
+            topic: *const c_char,
+            assignment: *mut *mut tmq_topic_assignment,
+            num: *mut i32,
+        ) -> i32,
+    >,
+    pub(crate) tmq_free_assignment:
+        Option<unsafe extern "C" fn(assignment: *mut tmq_topic_assignment)>,
+    pub(crate) tmq_offset_seek: Option<
+        unsafe extern "C" fn(
+            tmq: *mut tmq_t,
+            topic: *const c_char,
+            vg_id: i32,
+            offset: i64,
+        ) -> i32,
+    >,
+    pub(crate) tmq_commit_offset_async: Option<
+        unsafe extern "C" fn(
+            tmq: *mut tmq_t,
+            topic: *const c_char,
+            vg_id: i32,
+            offset: i64,
+            cb: tmq_commit_cb,
+            param: *mut c_void,
+        ),
+    >,
 
     pub(crate) conf_api: TmqConfApi,
     pub(crate) list_api: TmqListApi,
@@ -282,6 +309,17 @@ pub(crate) struct StmtApi {
     pub(crate) taos_stmt_set_tags:
         Option<unsafe extern "C" fn(stmt: *mut TAOS_STMT, tags: *mut c_void) -> c_int>,
 
+    pub(crate) taos_stmt_get_tag_fields: Option<
+        unsafe extern "C" fn(
+            stmt: *mut TAOS_STMT,
+            field_num: *mut c_int,
+            fields: *mut *mut TAOS_FIELD_E,
+        ) -> c_int,
+    >,
+
+    pub(crate) taos_stmt_reclaim_fields:
+        Option<unsafe extern "C" fn(stmt: *mut TAOS_STMT, fields: *mut TAOS_FIELD_E)>,
+
     pub(crate) taos_stmt_set_sub_tbname:
         unsafe extern "C" fn(stmt: *mut TAOS_STMT, name: *const c_char) -> c_int,
 
@@ -392,6 +430,8 @@ impl ApiEntry {
                 taos_options,
                 taos_connect,
                 taos_close,
+                taos_select_db,
+                taos_get_server_info,
                 taos_errno,
                 taos_errstr,
                 taos_fetch_rows_a,
@@ -406,6 +446,8 @@ impl ApiEntry {
                 taos_fetch_block
             );
             optional_symbol!(
+                taos_set_config,
+                taos_reset_current_db,
                 taos_fetch_block_s,
                 taos_fetch_raw_block,
                 taos_fetch_raw_block_a,
@@ -413,7 +455,11 @@ impl ApiEntry {
                 taos_write_raw_block,
                 taos_write_raw_block_with_fields,
                 taos_get_raw_block,
-                taos_result_block
+                taos_result_block,
+                taos_schemaless_insert_raw_ttl_with_reqid,
+                taos_query_with_reqid,
+                taos_query_a_with_reqid,
+                taos_kill_query
             );
 
             // stmt
@@ -436,7 +482,11 @@ impl ApiEntry {
                 taos_stmt_close,
                 taos_stmt_errstr
             );
-            optional_symbol!(taos_stmt_set_tags);
+            optional_symbol!(
+                taos_stmt_set_tags,
+                taos_stmt_get_tag_fields,
+                taos_stmt_reclaim_fields
+            );
 
             let stmt = StmtApi {
                 taos_stmt_init,
@@ -444,6 +494,8 @@ impl ApiEntry {
                 taos_stmt_set_tbname_tags,
                 taos_stmt_set_tbname,
                 taos_stmt_set_tags,
+                taos_stmt_get_tag_fields,
+                taos_stmt_reclaim_fields,
                 taos_stmt_set_sub_tbname,
                 taos_stmt_is_insert,
                 taos_stmt_num_params,
@@ -486,6 +538,16 @@ impl ApiEntry {
                     tmq_commit_async,
                     tmq_consumer_new
                 );
+                // since 3.0.4
+                optional_symbol!(
+                    tmq_get_vgroup_offset,
+                    tmq_get_topic_assignment,
+                    tmq_free_assignment
+                );
+                // since 3.0.5
+                optional_symbol!(tmq_offset_seek);
+                // since 3.1.0
+                optional_symbol!(tmq_commit_offset_async);
 
                 let conf_api = TmqConfApi {
                     tmq_conf_new,
@@ -510,6 +572,7 @@ impl ApiEntry {
                     tmq_get_topic_name,
                     tmq_get_vgroup_id,
                     tmq_get_raw,
+                    tmq_get_vgroup_offset,
                     tmq_subscribe,
                     tmq_unsubscribe,
                     tmq_subscription,
@@ -517,6 +580,10 @@ impl ApiEntry {
                     tmq_consumer_close,
                     tmq_commit_sync,
                     tmq_commit_async,
+                    tmq_get_topic_assignment,
+                    tmq_free_assignment,
+                    tmq_offset_seek,
+                    tmq_commit_offset_async,
 
                     conf_api,
                     list_api,
@@ -531,18 +598,26 @@ impl ApiEntry {
                 taos_cleanup,
                 taos_get_client_info,
                 taos_options,
+                taos_set_config,
                 taos_connect,
                 taos_close,
+                taos_select_db,
+                taos_reset_current_db,
+                taos_get_server_info,
 
                 taos_errno,
                 taos_errstr,
 
                 taos_fetch_rows_a,
                 taos_query_a,
+                taos_query_a_with_reqid,
+                taos_kill_query,
                 taos_query,
+                taos_query_with_reqid,
                 tmq_write_raw,
                 taos_write_raw_block,
                 taos_write_raw_block_with_fields,
+                taos_schemaless_insert_raw_ttl_with_reqid,
                 taos_result_block,
 
                 taos_free_result,
@@ -578,7 +653,34 @@ impl ApiEntry {
         self
     }
 
+    /// Apply log options by `taos_set_config`, which only works before initialization.
+    pub(super) fn set_log_config(&self, config: &LogConfig) -> Result<(), RawError> {
+        if INITIALIZED.load(Ordering::SeqCst) {
+            return Err(RawError::from_string(
+                "native log config must be set before the first connection",
+            ));
+        }
+        let set_config = self.taos_set_config.ok_or_else(|| {
+            RawError::from_string(format!(
+                "taos_set_config is not supported by client {}",
+                self.version
+            ))
+        })?;
+        let json = CString::new(config.to_json()).unwrap();
+        let ret = unsafe { set_config(json.as_ptr()) };
+        if ret.code == SET_CONF_RET_SUCC {
+            Ok(())
+        } else {
+            let msg = unsafe { CStr::from_ptr(ret.msg.as_ptr()) }.to_string_lossy();
+            Err(RawError::from_string(format!(
+                "set native log config failed with {:?}: {msg}",
+                ret.code
+            )))
+        }
+    }
+
     pub(super) fn connect(&self, auth: &Auth) -> *mut TAOS {
+        INITIALIZED.store(true, Ordering::SeqCst);
         unsafe {
             (self.taos_connect)(
                 auth.host_as_ptr(),
@@ -687,11 +789,51 @@ impl RawTaos {
         })
     }
 
+    /// Query with a request id to correlate with server logs.
+    ///
+    /// The id is not sent if the client has no `taos_query_with_reqid`, which is since 3.0.
+    #[inline]
+    pub fn query_with_req_id<'a, S: IntoCStr<'a>>(
+        &self,
+        sql: S,
+        req_id: u64,
+    ) -> Result<RawRes, RawError> {
+        let Some(query) = self.c.taos_query_with_reqid else {
+            return self.query(sql);
+        };
+        let sql = sql.into_c_str();
+        log::trace!("[req id: {req_id:#x}] query with sql: {:?}", sql);
+        Ok(RawRes {
+            c: self.c.clone(),
+            ptr: unsafe { query(self.as_ptr(), sql.as_ptr(), req_id as i64) },
+        })
+    }
+
     #[inline]
     pub fn query_async<'a, S: IntoCStr<'a>>(&self, sql: S) -> QueryFuture<'a> {
         QueryFuture::new(self.clone(), sql)
     }
 
+    /// Async version of [RawTaos::query_with_req_id].
+    #[inline]
+    pub fn query_async_with_req_id<'a, S: IntoCStr<'a>>(
+        &self,
+        sql: S,
+        req_id: u64,
+    ) -> QueryFuture<'a> {
+        QueryFuture::new_with_req_id(self.clone(), sql, req_id)
+    }
+
+    /// Stop all the requests in flight of the connection by `taos_kill_query`.
+    ///
+    /// It's a no-op if the client has no `taos_kill_query`, which is since 3.0.
+    #[inline]
+    pub fn kill_query(&self) {
+        if let Some(kill) = self.c.taos_kill_query {
+            unsafe { kill(self.as_ptr()) }
+        }
+    }
+
     #[inline]
     pub fn query_a<'a, S: IntoCStr<'a>>(
         &self,
@@ -702,6 +844,28 @@ impl RawTaos {
         unsafe { (self.c.taos_query_a)(self.as_ptr(), sql.into_c_str().as_ptr(), fp, param) }
     }
 
+    #[inline]
+    pub fn query_a_with_req_id<'a, S: IntoCStr<'a>>(
+        &self,
+        sql: S,
+        fp: taos_async_query_cb,
+        param: *mut c_void,
+        req_id: u64,
+    ) {
+        let Some(query_a) = self.c.taos_query_a_with_reqid else {
+            return self.query_a(sql, fp, param);
+        };
+        unsafe {
+            query_a(
+                self.as_ptr(),
+                sql.into_c_str().as_ptr(),
+                fp,
+                param,
+                req_id as i64,
+            )
+        }
+    }
+
     //     #[inline]
     //     pub fn validate_sql(self, sql: *const c_char) -> Result<(), RawError> {
     //         let code: Code = unsafe { taos_validate_sql(self.as_ptr(), sql) }.into();
@@ -714,10 +878,26 @@ impl RawTaos {
     //         }
     //     }
 
-    //     #[inline]
-    //     pub fn reset_current_db(&self) {
-    //         unsafe { taos_reset_current_db(self.as_ptr()) }
-    //     }
+    #[inline]
+    pub fn select_db<'a, S: IntoCStr<'a>>(&self, db: S) -> Result<(), RawError> {
+        let db = db.into_c_str();
+        let code: Code = unsafe { (self.c.taos_select_db)(self.as_ptr(), db.as_ptr()) }.into();
+        if code.success() {
+            Ok(())
+        } else {
+            let err = unsafe { (self.c.taos_errstr)(std::ptr::null_mut()) };
+            let err = unsafe { std::str::from_utf8_unchecked(CStr::from_ptr(err).to_bytes()) };
+            Err(RawError::new(code, err))
+        }
+    }
+
+    /// Unset the current database, a no-op for clients without `taos_reset_current_db`.
+    #[inline]
+    pub fn reset_current_db(&self) {
+        if let Some(reset) = self.c.taos_reset_current_db {
+            unsafe { reset(self.as_ptr()) }
+        }
+    }
 
     //     #[inline]
     //     pub fn server_version(&self) -> &CStr {
@@ -788,6 +968,46 @@ impl RawTaos {
         }
     }
 
+    /// Write schemaless data in one request.
+    pub fn put(&self, data: &SmlData) -> Result<(), RawError> {
+        let insert = self.c.taos_schemaless_insert_raw_ttl_with_reqid.ok_or_else(|| {
+            RawError::from_string(format!(
+                "schemaless insert is not supported by client {}",
+                self.c.version
+            ))
+        })?;
+        let mut payload = data.payload().into_bytes();
+        let mut total_rows = 0;
+        let mut res = unsafe {
+            RawRes::from_ptr_unchecked(
+                self.c.clone(),
+                insert(
+                    self.as_ptr(),
+                    payload.as_mut_ptr() as _,
+                    payload.len() as _,
+                    &mut total_rows,
+                    data.protocol() as _,
+                    data.precision() as _,
+                    data.ttl().unwrap_or(0),
+                    0,
+                ),
+            )
+        };
+        let code = res.errno();
+        let ok = if code.success() {
+            Ok(())
+        } else {
+            Err(RawError::new(code, res.err_as_str()))
+        };
+        res.free_result();
+        ok
+    }
+
+    #[inline]
+    pub fn server_version(&self) -> &CStr {
+        unsafe { CStr::from_ptr((self.c.taos_get_server_info)(self.as_ptr())) }
+    }
+
     #[inline]
     pub fn close(&mut self) {
         log::trace!("call taos_close");
@@ -907,9 +1127,13 @@ impl RawRes {
     }
 
     #[inline]
-    pub fn fetch_raw_block(&self, fields: &[Field]) -> Result<Option<RawBlock>, RawError> {
+    pub fn fetch_raw_block(
+        &self,
+        fields: &[Field],
+        pool: Option<&BlockPool>,
+    ) -> Result<Option<RawBlock>, RawError> {
         if self.c.is_v3() {
-            self.fetch_raw_block_v3(fields)
+            self.fetch_raw_block_v3(fields, pool)
         } else {
             self.fetch_raw_block_v2(fields)
         }
@@ -959,7 +1183,11 @@ impl RawRes {
         }
     }
     #[inline]
-    fn fetch_raw_block_v3(&self, fields: &[Field]) -> Result<Option<RawBlock>, RawError> {
+    fn fetch_raw_block_v3(
+        &self,
+        fields: &[Field],
+        pool: Option<&BlockPool>,
+    ) -> Result<Option<RawBlock>, RawError> {
         let mut block: *mut c_void = std::ptr::null_mut();
         let mut num = 0;
         crate::err_or!(
@@ -968,7 +1196,8 @@ impl RawRes {
             if num > 0 {
                 match self.tmq_message_type() {
                     tmq_res_t::TMQ_RES_INVALID => {
-                        let mut raw = RawBlock::parse_from_ptr(block as _, self.precision());
+                        let mut raw =
+                            RawBlock::parse_from_ptr_with_pool(block as _, self.precision(), pool);
                         raw.with_field_names(fields.iter().map(Field::name));
                         Some(raw)
                     }
@@ -1087,10 +1316,11 @@ impl RawRes {
         fields: &[Field],
         precision: Precision,
         state: &Arc<UnsafeCell<SharedState>>,
+        pool: Option<&BlockPool>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Option<RawBlock>, RawError>> {
         if self.c.is_v3() {
-            self.fetch_raw_block_async_v3(fields, precision, state, cx)
+            self.fetch_raw_block_async_v3(fields, precision, state, pool, cx)
         } else {
             self.fetch_raw_block_async_v2(fields, precision, state, cx)
         }
@@ -1100,6 +1330,7 @@ impl RawRes {
         fields: &[Field],
         precision: Precision,
         state: &Arc<UnsafeCell<SharedState>>,
+        pool: Option<&BlockPool>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Option<RawBlock>, RawError>> {
         let current = unsafe { &mut *state.get() };
@@ -1118,7 +1349,9 @@ impl RawRes {
 
             if current.num > 0 {
                 // has next block.
-                let mut raw = unsafe { RawBlock::parse_from_ptr(current.block as _, precision) };
+                let mut raw = unsafe {
+                    RawBlock::parse_from_ptr_with_pool(current.block as _, precision, pool)
+                };
                 raw.with_field_names(fields.iter().map(|f| f.name()));
                 // if current.num == 0 {
                 //     // finish fetch loop.
@@ -1235,6 +1468,12 @@ impl RawRes {
             }
         }
     }
+    /// Offset of the message in its vgroup, `None` if the client does not support it.
+    #[inline]
+    pub fn tmq_vgroup_offset(&self) -> Option<i64> {
+        let f = self.c.tmq.as_ref()?.tmq_get_vgroup_offset?;
+        Some(unsafe { f(self.as_ptr()) })
+    }
     #[inline]
     pub(crate) fn tmq_get_json_meta(&self) -> CString {
         unsafe {
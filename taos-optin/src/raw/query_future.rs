@@ -1,30 +1,77 @@
 use std::borrow::Cow;
-use std::cell::UnsafeCell;
 
+use std::collections::BTreeMap;
 use std::ffi::CStr;
 use std::future::Future;
 use std::os::raw::{c_int, c_void};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll, Waker};
 use std::time::Instant;
 
+use once_cell::sync::Lazy;
+
 use crate::into_c_str::IntoCStr;
+use crate::raw::ApiEntry;
 use crate::types::TAOS_RES;
 use crate::{RawRes, RawTaos};
 use taos_query::prelude::RawError;
 
+/// Queries waiting for callbacks, by the address of their connections.
+///
+/// `taos_kill_query` stops all the requests of a connection, so a dropped future only kills the
+/// query when it's the only one in flight.
+static IN_FLIGHT: Lazy<Mutex<BTreeMap<usize, usize>>> = Lazy::new(Default::default);
+
+fn in_flight_start(conn: usize) {
+    *IN_FLIGHT.lock().unwrap().entry(conn).or_default() += 1;
+}
+
+fn in_flight_finish(conn: usize) {
+    let mut in_flight = IN_FLIGHT.lock().unwrap();
+    if let Some(n) = in_flight.get_mut(&conn) {
+        *n -= 1;
+        if *n == 0 {
+            in_flight.remove(&conn);
+        }
+    }
+}
+
+fn in_flight(conn: usize) -> usize {
+    IN_FLIGHT.lock().unwrap().get(&conn).copied().unwrap_or(0)
+}
+
+/// Future of `taos_query_a`.
+///
+/// Dropping it before the callback cancels the query: the query is stopped by `taos_kill_query`
+/// unless others are in flight on the same connection, and the result is freed as soon as it
+/// arrives, instead of being leaked.
 pub struct QueryFuture<'a> {
     raw: RawTaos,
     sql: Cow<'a, CStr>,
-    state: UnsafeCell<State>,
+    /// Sent by `taos_query_a_with_reqid` if set.
+    req_id: Option<u64>,
+    shared: Arc<Shared>,
+}
+
+/// Shared between the future and the callback, which holds a count until it runs.
+struct Shared {
+    state: Mutex<State>,
+    /// The future is dropped while waiting for the callback.
+    cancelled: AtomicBool,
+    /// Address of the connection.
+    conn: usize,
+    c: Arc<ApiEntry>,
 }
 
-/// Shared state between the future and the waiting thread
 struct State {
     result: *mut TAOS_RES,
     code: i32,
     done: bool,
     waiting: bool,
+    /// Waker of the latest poll.
+    waker: Option<Waker>,
     time: Instant,
 }
 
@@ -36,68 +83,137 @@ impl<'a> Unpin for QueryFuture<'a> {}
 impl<'a> Future for QueryFuture<'a> {
     type Output = Result<RawRes, RawError>;
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let state = unsafe { &mut *self.state.get() };
+        let mut state = self.shared.state.lock().unwrap();
 
         if state.done {
-            Poll::Ready(RawRes::from_ptr_with_code(
+            // taken by the future, not to be freed on drop.
+            let result = std::mem::replace(&mut state.result, std::ptr::null_mut());
+            return Poll::Ready(RawRes::from_ptr_with_code(
                 self.raw.c.clone(),
-                state.result,
+                result,
                 state.code.into(),
-            ))
-        } else {
-            if state.waiting {
-                log::trace!("waken, still waiting for taos_query_a callback.");
-                return Poll::Pending;
-            } else {
-                state.waiting = true;
+            ));
+        }
+        // the task may move between polls.
+        if !matches!(&state.waker, Some(waker) if waker.will_wake(cx.waker())) {
+            state.waker = Some(cx.waker().clone());
+        }
+        if state.waiting {
+            log::trace!("waken, still waiting for taos_query_a callback.");
+            return Poll::Pending;
+        }
+        state.waiting = true;
+        drop(state);
+
+        #[no_mangle]
+        unsafe extern "C" fn taos_optin_query_future_callback(
+            param: *mut c_void,
+            res: *mut TAOS_RES,
+            code: c_int,
+        ) {
+            let shared = Arc::from_raw(param as *const Shared);
+            in_flight_finish(shared.conn);
+            let mut s = shared.state.lock().unwrap();
+            log::debug!("Receive query callback in {:?}", s.time.elapsed());
+
+            if shared.cancelled.load(Ordering::Acquire) {
+                log::trace!("query is cancelled, free the result");
+                if !res.is_null() {
+                    (shared.c.taos_free_result)(res);
+                }
+                return;
             }
-            #[no_mangle]
-            unsafe extern "C" fn taos_optin_query_future_callback(
-                param: *mut c_void,
-                res: *mut TAOS_RES,
-                code: c_int,
-            ) {
-                let param = param as *mut (&UnsafeCell<State>, Waker);
-                let state = param.read();
-                let mut s = { &mut *state.0.get() };
-                log::debug!("Receive query callback in {:?}", s.time.elapsed());
-
-                s.result = res;
-                s.code = code;
-                s.done = true;
-                state.1.wake();
+            s.result = res;
+            s.code = code;
+            s.done = true;
+            let waker = s.waker.take();
+            drop(s);
+            if let Some(waker) = waker {
+                waker.wake();
             }
+        }
 
-            let param = Box::new((&self.state, cx.waker().clone()));
-            log::trace!("calling taos_query_a");
-            self.raw.query_a(
-                self.sql.as_ref(),
+        let param = Arc::into_raw(self.shared.clone());
+        in_flight_start(self.shared.conn);
+        log::trace!("calling taos_query_a");
+        let (sql, param) = (self.sql.as_ref(), param as *mut _);
+        match self.req_id {
+            Some(req_id) => self.raw.query_a_with_req_id(
+                sql,
                 taos_optin_query_future_callback as _,
-                Box::into_raw(param) as *mut _,
-            );
-            log::trace!("waiting taos_query_a callback");
-            Poll::Pending
+                param,
+                req_id,
+            ),
+            None => self
+                .raw
+                .query_a(sql, taos_optin_query_future_callback as _, param),
         }
+        log::trace!("waiting taos_query_a callback");
+        Poll::Pending
     }
 }
+
+impl<'a> Drop for QueryFuture<'a> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.done {
+            // completed but never polled again.
+            if !state.result.is_null() {
+                unsafe { (self.raw.c.taos_free_result)(state.result) };
+                state.result = std::ptr::null_mut();
+            }
+        } else if state.waiting {
+            self.shared.cancelled.store(true, Ordering::Release);
+            // the callback may run in `taos_kill_query`, so no lock is held then.
+            drop(state);
+            if in_flight(self.shared.conn) == 1 {
+                log::trace!("query future is dropped, kill the query");
+                self.raw.kill_query();
+            }
+        }
+    }
+}
+
 impl<'a> QueryFuture<'a> {
     /// Create a new `TimerFuture` which will complete after the provided
     /// timeout.
     pub fn new(taos: RawTaos, sql: impl IntoCStr<'a>) -> Self {
-        let state = UnsafeCell::new(State {
-            result: std::ptr::null_mut(),
-            code: 0,
-            done: false,
-            waiting: false,
-            time: Instant::now(),
+        Self::with_req_id(taos, sql, None)
+    }
+
+    /// A query future that sends `req_id` along with the sql.
+    pub fn new_with_req_id(taos: RawTaos, sql: impl IntoCStr<'a>, req_id: u64) -> Self {
+        Self::with_req_id(taos, sql, Some(req_id))
+    }
+
+    fn with_req_id(taos: RawTaos, sql: impl IntoCStr<'a>, req_id: Option<u64>) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                result: std::ptr::null_mut(),
+                code: 0,
+                done: false,
+                waiting: false,
+                waker: None,
+                time: Instant::now(),
+            }),
+            cancelled: AtomicBool::new(false),
+            conn: taos.as_ptr() as usize,
+            c: taos.c.clone(),
         });
         let sql = sql.into_c_str();
-        log::trace!("query with: {}", sql.to_str().unwrap_or("<...>"));
+        match req_id {
+            Some(req_id) => log::trace!(
+                "[req id: {req_id:#x}] query with: {}",
+                sql.to_str().unwrap_or("<...>")
+            ),
+            None => log::trace!("query with: {}", sql.to_str().unwrap_or("<...>")),
+        }
 
         QueryFuture {
             raw: taos,
             sql,
-            state,
+            req_id,
+            shared,
         }
     }
 }
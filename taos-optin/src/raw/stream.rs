@@ -0,0 +1,136 @@
+use std::ffi::{c_int, c_void};
+
+use taos_query::{
+    common::BlockPool,
+    global_tokio_runtime,
+    prelude::{tokio::sync::mpsc, Field, Precision, RawError},
+    RawBlock,
+};
+
+use crate::types::TAOS_RES;
+
+use super::RawRes;
+
+type BlockSender = mpsc::Sender<Result<RawBlock, RawError>>;
+type BlockPermit = mpsc::OwnedPermit<Result<RawBlock, RawError>>;
+
+/// A result fetched by the callback chain of [RawRes::stream_blocks].
+struct BlockStream {
+    res: RawRes,
+    fields: Vec<Field>,
+    precision: Precision,
+    pool: Option<BlockPool>,
+}
+
+impl RawRes {
+    /// Fetch blocks of the result in background and send them to a channel of `buffer` blocks.
+    ///
+    /// Each fetch callback schedules the next fetch, only when the channel has capacity, so at
+    /// most `buffer` blocks are fetched but not received. The result is freed at the end of it,
+    /// or when the receiver is dropped, so it must not be used or freed after this.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is 0.
+    pub fn stream_blocks(
+        self,
+        buffer: usize,
+        pool: Option<BlockPool>,
+    ) -> mpsc::Receiver<Result<RawBlock, RawError>> {
+        let (tx, rx) = mpsc::channel(buffer);
+        let stream = BlockStream {
+            fields: self.fetch_fields(),
+            precision: self.precision(),
+            res: self,
+            pool,
+        };
+        Box::new(stream).schedule(tx);
+        rx
+    }
+
+    /// Move the result out, leaving a null one that's not freed.
+    pub(crate) fn take(&mut self) -> RawRes {
+        let ptr = std::mem::replace(&mut self.ptr, std::ptr::null_mut());
+        RawRes {
+            c: self.c.clone(),
+            ptr,
+        }
+    }
+}
+
+impl BlockStream {
+    /// Fetch the next block when there's capacity, or wait for it in the global runtime.
+    fn schedule(self: Box<Self>, tx: BlockSender) {
+        match tx.try_reserve_owned() {
+            Ok(permit) => self.fetch(permit),
+            Err(mpsc::error::TrySendError::Full(tx)) => {
+                global_tokio_runtime().spawn(async move {
+                    match tx.reserve_owned().await {
+                        Ok(permit) => self.fetch(permit),
+                        Err(_) => self.finish(),
+                    }
+                });
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => self.finish(),
+        }
+    }
+
+    fn fetch(self: Box<Self>, permit: BlockPermit) {
+        unsafe extern "C" fn taos_optin_stream_blocks_callback(
+            param: *mut c_void,
+            res: *mut TAOS_RES,
+            num_of_rows: c_int,
+        ) {
+            let (stream, permit): (Box<BlockStream>, BlockPermit) = *Box::from_raw(param as *mut _);
+            if num_of_rows < 0 {
+                let err = RawError::new(num_of_rows, stream.res.err_as_str());
+                permit.send(Err(err));
+                stream.finish();
+            } else if num_of_rows == 0 {
+                stream.finish();
+            } else {
+                let block = stream.parse_block(res, num_of_rows as usize);
+                stream.schedule(permit.send(Ok(block)));
+            }
+        }
+
+        let ptr = self.res.as_ptr();
+        let c = self.res.c.clone();
+        let param = Box::into_raw(Box::new((self, permit))) as *mut c_void;
+        unsafe {
+            if c.is_v3() {
+                (c.taos_fetch_raw_block_a.unwrap())(
+                    ptr,
+                    taos_optin_stream_blocks_callback as _,
+                    param,
+                )
+            } else {
+                (c.taos_fetch_rows_a)(ptr, taos_optin_stream_blocks_callback as _, param)
+            }
+        }
+    }
+
+    /// Copy the block just fetched, before the next fetch reuses it.
+    unsafe fn parse_block(&self, res: *mut TAOS_RES, rows: usize) -> RawBlock {
+        let c = &self.res.c;
+        let mut raw = if c.is_v3() {
+            let block = (c.taos_get_raw_block.unwrap())(res);
+            RawBlock::parse_from_ptr_with_pool(block, self.precision, self.pool.as_ref())
+        } else {
+            let block = (c.taos_result_block.unwrap())(res).read();
+            RawBlock::parse_from_ptr_v2(
+                block as _,
+                &self.fields,
+                self.res.fetch_lengths(),
+                rows,
+                self.precision,
+            )
+        };
+        raw.with_field_names(self.fields.iter().map(Field::name));
+        raw
+    }
+
+    fn finish(mut self: Box<Self>) {
+        self.res.free_result();
+    }
+}
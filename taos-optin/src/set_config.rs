@@ -27,6 +27,5 @@ pub enum SET_CONF_RET_CODE {
     ErrTooLong = -6,
 }
 
-extern "C" {
-    pub fn taos_set_config(config: *const c_char) -> SetConfRet;
-}
+/// Signature of `taos_set_config`, loaded from the library as an optional symbol.
+pub type taos_set_config_t = unsafe extern "C" fn(config: *const c_char) -> SetConfRet;
@@ -16,6 +16,8 @@ use taos_query::prelude::{
     sync::{Bindable, Queryable, RawError as Error },
     Code,
 };
+use taos_query::stmt::{broadcast_bind, StmtField};
+use taos_query::util::RateLimiter;
 
 use crate::types::*;
 
@@ -25,6 +27,11 @@ mod multi;
 #[derive(Debug)]
 pub struct Stmt {
     raw: RawStmt,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Rows and bytes bound since the last execution, taken from the rate limit on execution.
+    pending: (usize, usize),
+    /// Tags of the prepared statement, `None` until they are known.
+    tags: Option<Vec<StmtField>>,
 }
 
 impl Bindable<super::Taos> for Stmt {
@@ -33,25 +40,36 @@ impl Bindable<super::Taos> for Stmt {
     fn init(taos: &super::Taos) -> Result<Self, Self::Error> {
         Ok(Self {
             raw: RawStmt::from_raw_taos(&taos.raw),
+            rate_limiter: taos.rate_limiter.clone(),
+            pending: (0, 0),
+            tags: None,
         })
     }
 
     fn prepare<S: AsRef<str>>(&mut self, sql: S) -> Result<&mut Self, Self::Error> {
         self.raw.prepare(sql.as_ref())?;
+        self.tags = None;
+        self.load_tags();
         Ok(self)
     }
 
     fn set_tbname<S: AsRef<str>>(&mut self, sql: S) -> Result<&mut Self, Self::Error> {
         self.raw.set_tbname(sql.as_ref())?;
+        self.load_tags();
         Ok(self)
     }
 
     fn set_tags(&mut self, tags: &[taos_query::common::Value]) -> Result<&mut Self, Self::Error> {
+        // normal tables have no tags to set.
+        if tags.is_empty() {
+            return Ok(self);
+        }
+        let mut json = Vec::new();
         if self.raw.is_v3() {
-            let tags = tags.iter().map(TaosBindV3::from_value).collect_vec();
+            let tags = TaosBindV3::from_tags(tags, &mut json);
             self.raw.set_tags(tags.as_ptr() as _)?;
         } else {
-            let tags = tags.iter().map(TaosBindV2::from_value).collect_vec();
+            let tags = TaosBindV2::from_tags(tags, &mut json);
             self.raw.set_tags(tags.as_ptr() as _)?;
         }
         Ok(self)
@@ -61,7 +79,25 @@ impl Bindable<super::Taos> for Stmt {
         &mut self,
         params: &[taos_query::common::ColumnView],
     ) -> Result<&mut Self, Self::Error> {
-        let params: Vec<DropMultiBind> = params.iter().map(|c| c.into()).collect_vec();
+        let broadcast = broadcast_bind(params);
+        let params = broadcast.as_deref().unwrap_or(params);
+        if self.rate_limiter.is_some() {
+            self.pending.0 += params.first().map_or(0, |c| c.len());
+            self.pending.1 += params.iter().map(|c| c.raw_len()).sum::<usize>();
+        }
+        // null views are allocated to be bound, they're kept until the params are copied.
+        let nulls = params
+            .iter()
+            .map(|c| match c {
+                taos_query::common::ColumnView::Null(view) => Some(view.to_column_view()),
+                _ => None,
+            })
+            .collect_vec();
+        let params: Vec<DropMultiBind> = params
+            .iter()
+            .zip(&nulls)
+            .map(|(c, null)| null.as_ref().unwrap_or(c).into())
+            .collect_vec();
         self.raw.bind_param_batch(unsafe { std::mem::transmute(params.as_slice()) })?;
         Ok(self)
     }
@@ -72,16 +108,44 @@ impl Bindable<super::Taos> for Stmt {
     }
 
     fn execute(&mut self) -> Result<usize, Self::Error> {
+        let (rows, bytes) = std::mem::take(&mut self.pending);
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire_blocking(rows, bytes);
+        }
         self.raw.execute().map_err(Into::into)
     }
 
     fn result_set(&mut self) -> Result<<super::Taos as Queryable>::ResultSet, Self::Error> {
+        if self.raw.is_insert()? {
+            return Err(Error::from_string(
+                "insert statements have no result set, use affected rows instead",
+            )
+            .into());
+        }
         self.raw.use_result().map_err(Into::into)
     }
 
     fn affected_rows(&self) -> usize {
         self.raw.affected_rows() as _
     }
+
+    fn bound_tags(&self) -> &[StmtField] {
+        self.tags.as_deref().unwrap_or_default()
+    }
+}
+
+impl Stmt {
+    /// Get tags if they are not known yet, they're unavailable before the table name is set
+    /// for `insert into ? ...`.
+    fn load_tags(&mut self) {
+        if self.tags.is_none() {
+            match self.raw.tag_fields() {
+                Ok(tags) if !tags.is_empty() => self.tags = Some(tags),
+                Ok(_) => (),
+                Err(err) => log::trace!("tag fields of stmt are unknown: {err}"),
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -205,6 +269,31 @@ impl RawStmt {
         }
     }
 
+    /// Tags of the statement, empty if they are unknown or not supported by the library.
+    pub fn tag_fields(&self) -> Result<Vec<StmtField>, Error> {
+        let (Some(get_tag_fields), Some(reclaim_fields)) = (
+            self.api.taos_stmt_get_tag_fields,
+            self.api.taos_stmt_reclaim_fields,
+        ) else {
+            return Ok(Vec::new());
+        };
+        let mut num = 0;
+        let mut fields: *mut TAOS_FIELD_E = std::ptr::null_mut();
+        err_or!(
+            self,
+            get_tag_fields(self.as_ptr(), &mut num as _, &mut fields as _)
+        )?;
+        if fields.is_null() {
+            return Ok(Vec::new());
+        }
+        let res = unsafe { std::slice::from_raw_parts(fields, num as usize) }
+            .iter()
+            .map(StmtField::from)
+            .collect();
+        unsafe { reclaim_fields(self.as_ptr(), fields) };
+        Ok(res)
+    }
+
     #[inline]
     pub fn use_result(&mut self) -> Result<ResultSet, Error> {
         unsafe {
@@ -234,15 +323,15 @@ impl RawStmt {
         err_or!(self, (self.api.taos_stmt_add_batch)(self.as_ptr()))
     }
 
-    // #[inline]
-    // pub fn is_insert(&self) -> Result<bool, Error> {
-    //     let mut is_insert = 0;
-    //     err_or!(
-    //         self,
-    //         (self.api.taos_stmt_is_insert)(self.as_ptr(), &mut is_insert as _),
-    //         is_insert != 0
-    //     )
-    // }
+    #[inline]
+    pub fn is_insert(&self) -> Result<bool, Error> {
+        let mut is_insert = 0;
+        err_or!(
+            self,
+            (self.api.taos_stmt_is_insert)(self.as_ptr(), &mut is_insert as _),
+            is_insert != 0
+        )
+    }
 
     // #[inline]
     // pub fn num_params(&self) -> Result<usize, Error> {
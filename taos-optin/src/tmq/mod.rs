@@ -1,16 +1,20 @@
 // pub(crate) mod ffi;
 
-use std::{fmt::Debug, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    fmt::Debug,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
 
 // pub(crate) use ffi::*;
 
 use itertools::Itertools;
 use taos_query::{
     common::{raw_data_t, RawMeta},
-    prelude::RawError,
+    prelude::{tokio, RawError},
     tmq::{
         AsAsyncConsumer, AsConsumer, AsyncOnSync, IsAsyncData, IsMeta, IsOffset, MessageSet,
-        Timeout, VGroupId,
+        PollInterval, Timeout, VGroupId, Watermarks,
     },
     IntoDsn, RawBlock, TBuilder,
 };
@@ -23,7 +27,7 @@ mod raw;
 
 use raw::RawTmq;
 
-use self::raw::{Conf, Topics};
+use self::raw::{Conf, TmqList};
 
 pub struct TmqBuilder {
     // dsn: Dsn,
@@ -31,6 +35,8 @@ pub struct TmqBuilder {
     lib: Arc<ApiEntry>,
     conf: Conf,
     timeout: Timeout,
+    poll_interval: Duration,
+    watermark_interval: Option<Duration>,
 }
 
 unsafe impl Send for TmqBuilder {}
@@ -42,21 +48,31 @@ impl TBuilder for TmqBuilder {
     type Error = RawError;
 
     fn available_params() -> &'static [&'static str] {
-        &["group.id", "client.id", "timeout", "enable.auto.commit"]
+        &[
+            "group.id",
+            "client.id",
+            "timeout",
+            "enable.auto.commit",
+            Watermarks::PARAM,
+            PollInterval::PARAM,
+        ]
     }
 
     fn from_dsn<D: IntoDsn>(dsn: D) -> Result<Self, Self::Error> {
         let mut dsn = dsn
             .into_dsn()
             .map_err(|e| RawError::from_string(format!("Parse dsn error: {}", e)))?;
-        let lib = if let Some(path) = dsn.params.remove("libraryPath") {
+        let lib = if let Some(path) = dsn.remove("libraryPath") {
             ApiEntry::dlopen(path).unwrap()
         } else {
             ApiEntry::default()
         };
+        // not a native config.
+        let watermarks = Watermarks::from_param(dsn.remove(Watermarks::PARAM).as_deref())?;
+        let poll_interval = PollInterval::from_param(dsn.remove(PollInterval::PARAM).as_deref())?;
         let conf = Conf::from_dsn(&dsn, lib.tmq.unwrap().conf_api)?;
-        let timeout = if let Some(timeout) = dsn.params.remove("timeout") {
-            Timeout::from_str(&timeout).map_err(RawError::from_any)?
+        let timeout = if let Some(timeout) = dsn.remove("timeout") {
+            Timeout::from_param("timeout", &timeout)?
         } else {
             Timeout::from_millis(500)
         };
@@ -66,6 +82,8 @@ impl TBuilder for TmqBuilder {
             lib: Arc::new(lib),
             conf,
             timeout,
+            poll_interval: poll_interval.interval(),
+            watermark_interval: watermarks.interval(),
         })
     }
 
@@ -91,6 +109,9 @@ impl TBuilder for TmqBuilder {
         Ok(Consumer {
             tmq,
             timeout: self.timeout,
+            poll_interval: self.poll_interval,
+            watermarks: Watermarks::new(self.watermark_interval),
+            closed: Arc::default(),
         })
     }
 
@@ -101,10 +122,10 @@ impl TBuilder for TmqBuilder {
     }
 }
 
-/// Consumer offset.
+/// Consumer offset, with the begin offset and high watermark of the vgroup at receive time.
 ///
 /// When offset is dropped, the message is destroyed.
-pub struct Offset(RawRes);
+pub struct Offset(RawRes, Option<(i64, i64)>);
 
 unsafe impl Send for Offset {}
 unsafe impl Sync for Offset {}
@@ -136,6 +157,15 @@ impl IsOffset for Offset {
             .tmq_vgroup_id()
             .expect("a message should belong to a vgroup")
     }
+    fn offset(&self) -> Option<i64> {
+        self.0.tmq_vgroup_offset()
+    }
+    fn begin(&self) -> Option<i64> {
+        self.1.map(|(begin, _)| begin)
+    }
+    fn high_watermark(&self) -> Option<i64> {
+        self.1.map(|(_, end)| end)
+    }
 }
 
 impl Drop for Offset {
@@ -148,6 +178,10 @@ impl Drop for Offset {
 pub struct Consumer {
     tmq: RawTmq,
     timeout: Timeout,
+    poll_interval: Duration,
+    watermarks: Watermarks,
+    /// Set when the handle is closed, polls in blocking threads hold it to not run after that.
+    closed: Arc<RwLock<bool>>,
 }
 
 unsafe impl Send for Consumer {}
@@ -155,11 +189,86 @@ unsafe impl Sync for Consumer {}
 
 impl Drop for Consumer {
     fn drop(&mut self) {
+        // wait for the poll in flight, at most a poll interval.
+        let mut closed = self.closed.write().unwrap_or_else(PoisonError::into_inner);
+        *closed = true;
         self.tmq.unsubscribe();
         self.tmq.close();
     }
 }
 
+impl Consumer {
+    /// Topics subscribed by the consumer, eg. to log what a consumer built elsewhere reads.
+    pub fn subscription(&self) -> Result<Vec<String>, RawError> {
+        Ok(self.tmq.subscription()?.to_vec())
+    }
+
+    /// Offsets of each vgroup of a subscribed topic, empty if the client does not support it.
+    pub fn assignments(&self, topic: &str) -> Result<Vec<taos_query::tmq::Assignment>, RawError> {
+        self.tmq.assignments(topic)
+    }
+
+    /// Move the offset of the consumer in a vgroup of `topic`, eg. to its begin offset to
+    /// consume it again. It fails if the client does not support it.
+    pub fn offset_seek(
+        &self,
+        topic: &str,
+        vgroup_id: VGroupId,
+        offset: i64,
+    ) -> Result<(), RawError> {
+        self.tmq.offset_seek(topic, vgroup_id, offset)
+    }
+
+    /// Commit `offset` of a vgroup of `topic`, eg. an offset persisted by the application after
+    /// its sink acknowledged the messages before it. It fails if the client does not support it.
+    pub async fn commit_offset(
+        &self,
+        topic: &str,
+        vgroup_id: VGroupId,
+        offset: i64,
+    ) -> Result<(), RawError> {
+        self.tmq.commit_offset(topic, vgroup_id, offset).await
+    }
+
+    /// Commit the offset of exactly the received message of `offset`, other messages polled
+    /// after it are not committed.
+    pub async fn commit_message(&self, offset: &Offset) -> Result<(), RawError> {
+        let (Some(topic), Some(position)) = (offset.0.tmq_topic_name(), offset.offset()) else {
+            return Err(RawError::from_string("offset of the message is unknown"));
+        };
+        self.commit_offset(topic, offset.vgroup_id(), position)
+            .await
+    }
+
+    /// Poll in a blocking thread, `None` if no message in `timeout` or the consumer is closed.
+    async fn poll_blocking(&self, timeout: Duration) -> Option<RawRes> {
+        let (tmq, closed) = (self.tmq.clone(), self.closed.clone());
+        tokio::task::spawn_blocking(move || {
+            let closed = closed.read().unwrap_or_else(PoisonError::into_inner);
+            if *closed {
+                return None;
+            }
+            tmq.poll_timeout(timeout.as_millis() as i64)
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Offset of a received message, the watermarks of the topic are refreshed if stale.
+    fn offset_of(&self, raw: &RawRes) -> Offset {
+        let watermark = match (raw.tmq_topic_name(), raw.tmq_vgroup_id()) {
+            (Some(topic), Some(vgroup_id)) => {
+                if self.watermarks.is_stale(topic) {
+                    self.watermarks.update(topic, self.tmq.assignments(topic));
+                }
+                self.watermarks.get(topic, vgroup_id)
+            }
+            _ => None,
+        };
+        Offset(raw.clone(), watermark)
+    }
+}
+
 pub struct Messages {
     tmq: RawTmq,
     timeout: Option<Duration>,
@@ -171,7 +280,7 @@ impl Iterator for Messages {
     fn next(&mut self) -> Option<Self::Item> {
         self.tmq
             .poll_timeout(self.timeout.map(|t| t.as_millis() as i64).unwrap_or(-1))
-            .map(|raw| (Offset(raw.clone()), MessageSet::from(raw)))
+            .map(|raw| (Offset(raw.clone(), None), MessageSet::from(raw)))
     }
 }
 
@@ -294,9 +403,8 @@ impl AsConsumer for Consumer {
         &mut self,
         topics: I,
     ) -> Result<(), Self::Error> {
-        let topics = topics.into_iter().map(|item| item.into()).collect_vec();
-        let topics = Topics::from_topics(self.tmq.tmq.list_api, &topics)?;
-        dbg!(&topics);
+        let topics = topics.into_iter().map(|item| item.into());
+        let topics = TmqList::from_topics(self.tmq.tmq.list_api, topics.collect_vec())?;
         self.tmq.subscribe(&topics)
     }
 
@@ -312,7 +420,7 @@ impl AsConsumer for Consumer {
     > {
         Ok(self.tmq.poll_timeout(timeout.as_raw_timeout()).map(|raw| {
             (
-                Offset(raw.clone()),
+                self.offset_of(&raw),
                 match raw.tmq_message_type() {
                     tmq_res_t::TMQ_RES_INVALID => unreachable!(),
                     tmq_res_t::TMQ_RES_DATA => taos_query::tmq::MessageSet::Data(Data::new(raw)),
@@ -349,8 +457,8 @@ impl AsAsyncConsumer for Consumer {
         &mut self,
         topics: I,
     ) -> Result<(), Self::Error> {
-        let topics =
-            Topics::from_topics(self.tmq.tmq.list_api, topics.into_iter().map(|s| s.into()))?;
+        let topics = topics.into_iter().map(|item| item.into());
+        let topics = TmqList::from_topics(self.tmq.tmq.list_api, topics.collect_vec())?;
         self.tmq.subscribe(&topics)
     }
 
@@ -364,67 +472,16 @@ impl AsAsyncConsumer for Consumer {
         )>,
         Self::Error,
     > {
-        use taos_query::prelude::tokio;
         log::trace!("Waiting for next message");
-        let res = match timeout {
-            Timeout::Never | Timeout::None => {
-                let timeout = Duration::MAX;
-                let sleep = tokio::time::sleep(timeout);
-                tokio::pin!(sleep);
-                tokio::select! {
-                    _ = &mut sleep, if !sleep.is_elapsed() => {
-                       Ok(None)
-                    }
-                    raw = self.tmq.poll_async() => {
-                        let message =    (
-                            Offset(raw.clone()),
-                            match raw.tmq_message_type() {
-                                tmq_res_t::TMQ_RES_INVALID => unreachable!(),
-                                tmq_res_t::TMQ_RES_DATA => taos_query::tmq::MessageSet::Data(Data::new(raw)),
-                                tmq_res_t::TMQ_RES_TABLE_META => {
-                                    taos_query::tmq::MessageSet::Meta(Meta::new(raw))
-                                }
-                                tmq_res_t::TMQ_RES_METADATA => taos_query::tmq::MessageSet::MetaData(Meta::new(raw.clone()), Data::new(raw))
-                            },
-                        );
-                        Ok(Some(message))
-                    }
-                }
-            }
-            Timeout::Duration(timeout) => {
-                let sleep = tokio::time::sleep(timeout);
-                tokio::pin!(sleep);
-                tokio::select! {
-                    _ = &mut sleep, if !sleep.is_elapsed() => {
-                       Ok(None)
-                    }
-                    raw = self.tmq.poll_async() => {
-                        let message =    (
-                            Offset(raw.clone()),
-                            match raw.tmq_message_type() {
-                                tmq_res_t::TMQ_RES_INVALID => unreachable!(),
-                                tmq_res_t::TMQ_RES_DATA => taos_query::tmq::MessageSet::Data(Data::new(raw)),
-                                tmq_res_t::TMQ_RES_TABLE_META => {
-                                    taos_query::tmq::MessageSet::Meta(Meta::new(raw))
-                                }
-                                tmq_res_t::TMQ_RES_METADATA => taos_query::tmq::MessageSet::MetaData(Meta::new(raw.clone()), Data::new(raw))
-                            },
-                        );
-                        Ok(Some(message))
-                    }
+        let raw = match timeout {
+            Timeout::Never | Timeout::None => loop {
+                if let Some(raw) = self.poll_blocking(self.poll_interval).await {
+                    break Some(raw);
                 }
-            }
+            },
+            Timeout::Duration(timeout) => self.poll_blocking(timeout).await,
         };
-        match res {
-            Ok(res) => {
-                log::trace!("Got a new message");
-                Ok(res)
-            }
-            Err(err) => {
-                log::warn!("Polling message error: {err:?}");
-                Err(err)
-            }
-        }
+        Ok(raw.map(|raw| (self.offset_of(&raw), MessageSet::from(raw))))
     }
 
     async fn commit(&self, offset: Self::Offset) -> Result<(), Self::Error> {
@@ -434,6 +491,10 @@ impl AsAsyncConsumer for Consumer {
     fn default_timeout(&self) -> Timeout {
         self.timeout
     }
+
+    fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -488,7 +549,7 @@ mod tests {
                         let raw = raw?;
                         dbg!(raw.table_name().unwrap());
                         let (_nrows, _ncols) = (raw.nrows(), raw.ncols());
-                        for col in raw.columns() {
+                        for (_, col) in raw.columns() {
                             for value in col {
                                 print!("{}\t", value);
                             }
@@ -508,7 +569,7 @@ mod tests {
                         let raw = raw?;
                         dbg!(raw.table_name().unwrap());
                         let (_nrows, _ncols) = (raw.nrows(), raw.ncols());
-                        for col in raw.columns() {
+                        for (_, col) in raw.columns() {
                             for value in col {
                                 print!("{}\t", value);
                             }
@@ -605,7 +666,7 @@ mod tests {
                     for raw in data {
                         let raw = raw?;
                         let (_nrows, _ncols) = (raw.nrows(), raw.ncols());
-                        for col in raw.columns() {
+                        for (_, col) in raw.columns() {
                             for value in col {
                                 print!("{}\t", value);
                             }
@@ -620,7 +681,7 @@ mod tests {
                     for raw in data {
                         let raw = raw?;
                         let (_nrows, _ncols) = (raw.nrows(), raw.ncols());
-                        for col in raw.columns() {
+                        for (_, col) in raw.columns() {
                             for value in col {
                                 print!("{}\t", value);
                             }
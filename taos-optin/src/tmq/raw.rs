@@ -1,19 +1,20 @@
 pub(super) use conf::Conf;
-pub(super) use list::Topics;
+pub(super) use list::TmqList;
 pub(super) use tmq::RawTmq;
 
 pub(super) mod tmq {
-    use std::{sync::Arc, time::Duration};
+    use std::sync::Arc;
 
-    use itertools::Itertools;
+    use taos_query::tmq::{Assignment, VGroupId};
 
     use crate::{
+        into_c_str::IntoCStr,
         raw::{ApiEntry, TmqApi},
         types::{tmq_resp_err_t, tmq_t},
         RawError, RawRes,
     };
 
-    use super::Topics;
+    use super::TmqList;
 
     #[derive(Debug, Clone)]
     pub(crate) struct RawTmq {
@@ -29,15 +30,23 @@ pub(super) mod tmq {
         fn as_ptr(&self) -> *mut tmq_t {
             self.ptr
         }
-        pub(crate) fn subscribe(&mut self, topics: &Topics) -> Result<(), RawError> {
+        pub(crate) fn subscribe(&mut self, topics: &TmqList) -> Result<(), RawError> {
             unsafe {
                 (self.tmq.tmq_subscribe)(self.as_ptr(), topics.as_ptr()).ok_or(format!(
                     "subscribe failed with topics: [{}]",
-                    topics.iter().join(",")
+                    topics.to_vec().join(",")
                 ))
             }
         }
 
+        /// Topics subscribed by the consumer.
+        pub(crate) fn subscription(&self) -> Result<TmqList, RawError> {
+            let mut topics = TmqList::new(self.tmq.list_api)?;
+            unsafe { (self.tmq.tmq_subscription)(self.as_ptr(), topics.as_mut_ptr()) }
+                .ok_or("get subscription failed")?;
+            Ok(topics)
+        }
+
         pub fn commit_sync(&self, msg: RawRes) -> Result<(), RawError> {
             unsafe { (self.tmq.tmq_commit_sync)(self.as_ptr(), msg.as_ptr() as _) }
                 .ok_or("commit failed")
@@ -71,6 +80,116 @@ pub(super) mod tmq {
             rx.recv().unwrap()
         }
 
+        /// Commit `offset` of a vgroup of `topic`, the result of the callback is awaited.
+        pub async fn commit_offset(
+            &self,
+            topic: &str,
+            vgroup_id: VGroupId,
+            offset: i64,
+        ) -> Result<(), RawError> {
+            use taos_query::prelude::tokio::sync::oneshot::{channel, Sender};
+
+            let Some(commit) = self.tmq.tmq_commit_offset_async else {
+                return Err(RawError::from_string(
+                    "commit offset is not supported by the native client",
+                ));
+            };
+            unsafe extern "C" fn tmq_commit_offset_cb(
+                _tmq: *mut tmq_t,
+                resp: tmq_resp_err_t,
+                param: *mut std::os::raw::c_void,
+            ) {
+                let sender = Box::from_raw(param as *mut Sender<tmq_resp_err_t>);
+                log::trace!("commit offset callback: {resp:?}");
+                let _ = sender.send(resp);
+            }
+
+            let (sender, rx) = channel::<tmq_resp_err_t>();
+            unsafe {
+                commit(
+                    self.as_ptr(),
+                    topic.into_c_str().as_ptr(),
+                    vgroup_id,
+                    offset,
+                    tmq_commit_offset_cb,
+                    Box::into_raw(Box::new(sender)) as *mut _,
+                )
+            }
+            let resp = rx.await.map_err(RawError::from_any)?;
+            resp.ok_or(format!(
+                "commit offset {offset} of vgroup {vgroup_id} of topic {topic} failed"
+            ))
+        }
+
+        /// Offsets of each vgroup of a subscribed topic, empty if the client does not support it.
+        pub fn assignments(&self, topic: &str) -> Result<Vec<Assignment>, RawError> {
+            let (Some(get), Some(free)) = (
+                self.tmq.tmq_get_topic_assignment,
+                self.tmq.tmq_free_assignment,
+            ) else {
+                return Ok(Vec::new());
+            };
+            let mut ptr = std::ptr::null_mut();
+            let mut num = 0;
+            let code = unsafe {
+                get(
+                    self.as_ptr(),
+                    topic.into_c_str().as_ptr(),
+                    &mut ptr,
+                    &mut num,
+                )
+            };
+            if code != 0 {
+                return Err(RawError::new(
+                    code,
+                    format!("get assignment of topic {topic} failed"),
+                ));
+            }
+            if ptr.is_null() {
+                return Ok(Vec::new());
+            }
+            let assignments = unsafe { std::slice::from_raw_parts(ptr, num as usize) }
+                .iter()
+                .map(|a| Assignment {
+                    vgroup_id: a.vgId,
+                    offset: a.currentOffset,
+                    begin: a.begin,
+                    end: a.end,
+                })
+                .collect();
+            unsafe { free(ptr) };
+            Ok(assignments)
+        }
+
+        /// Move the offset of the consumer in a vgroup of `topic`, the next poll of the vgroup
+        /// starts from `offset`. Offsets out of the range kept by the vgroup are rejected.
+        pub fn offset_seek(
+            &self,
+            topic: &str,
+            vgroup_id: VGroupId,
+            offset: i64,
+        ) -> Result<(), RawError> {
+            let Some(seek) = self.tmq.tmq_offset_seek else {
+                return Err(RawError::from_string(
+                    "offset seek is not supported by the native client",
+                ));
+            };
+            let code = unsafe {
+                seek(
+                    self.as_ptr(),
+                    topic.into_c_str().as_ptr(),
+                    vgroup_id,
+                    offset,
+                )
+            };
+            if code != 0 {
+                return Err(RawError::new(
+                    code,
+                    format!("seek vgroup {vgroup_id} of topic {topic} to offset {offset} failed"),
+                ));
+            }
+            Ok(())
+        }
 
         pub fn poll_timeout(&self, timeout: i64) -> Option<RawRes> {
             log::trace!("poll next message with timeout {}", timeout);
@@ -82,37 +201,6 @@ pub(super) mod tmq {
             }
         }
 
-        pub async fn poll_async(&self) -> RawRes {
-            let elapsed = std::time::Instant::now();
-            #[cfg(not(test))]
-            use taos_query::prelude::tokio;
-
-            loop {
-                // poll with 50ms timeout.
-                // let ptr = UnsafeCell::new(self.0);
-                log::trace!("try poll next message with 200ms timeout");
-                let raw = self.clone();
-                let res = tokio::task::spawn_blocking(move || {
-                    let raw = raw;
-                    let res = unsafe { (raw.tmq.tmq_consumer_poll)(raw.as_ptr(), 200) };
-                    if res.is_null() {
-                        None
-                    } else {
-                        Some(unsafe { RawRes::from_ptr_unchecked(raw.c.clone(), res) })
-                    }
-                    // result
-                })
-                .await
-                .unwrap_or_default();
-                if let Some(res) = res {
-                    log::trace!("received tmq message in {:?}", elapsed.elapsed());
-                    break res;
-                } else {
-                    tokio::time::sleep(Duration::from_millis(1)).await;
-                }
-            }
-        }
-
         pub fn unsubscribe(&mut self) {
             unsafe {
                 log::trace!("unsubscribe {:p}", self.as_ptr());
@@ -287,112 +375,204 @@ pub(super) mod conf {
 }
 
 pub(super) mod list {
-    use std::ffi::CStr;
-    use std::os::raw::c_char;
+    use std::ffi::{CStr, CString};
 
-    use taos_query::prelude::RawError;
+    use taos_query::prelude::{Code, RawError};
 
-    use crate::{into_c_str::IntoCStr, raw::TmqListApi, types::tmq_list_t};
+    use crate::{raw::TmqListApi, types::tmq_list_t};
 
     type Result<T> = std::result::Result<T, RawError>;
 
+    /// An owned `tmq_list_t` of topics, destroyed on drop.
     #[derive(Debug)]
-    pub(crate) struct Topics {
+    pub(crate) struct TmqList {
         api: TmqListApi,
         ptr: *mut tmq_list_t,
     }
 
-    impl Topics {
-        pub(super) fn as_ptr(&self) -> *mut tmq_list_t {
-            self.ptr
+    impl TmqList {
+        pub(crate) fn new(api: TmqListApi) -> Result<Self> {
+            let ptr = unsafe { (api.tmq_list_new)() };
+            if ptr.is_null() {
+                return Err(RawError::new(Code::Failed, "failed to create tmq list"));
+            }
+            Ok(Self { api, ptr })
         }
-        // pub(crate) fn new(api: TmqListApi) -> Self {
-        //     Self {
-        //         api,
-        //         ptr: unsafe { api.new() },
-        //     }
-        // }
 
-        // pub(crate) fn append<'a>(&mut self, c_str: impl IntoCStr<'a>) -> Result<()> {
-        //     self.api.append(self.as_ptr(), c_str)
-        // }
-
-        pub(crate) fn from_topics<'a, T: IntoCStr<'a>>(
+        /// A list of `topics`, the list is destroyed if any of them fails to append.
+        pub(crate) fn from_topics<T: AsRef<str>>(
             api: TmqListApi,
             topics: impl IntoIterator<Item = T>,
         ) -> Result<Self> {
-            let ptr = unsafe { api.from_c_str_iter(topics)? };
-            Ok(Self { api, ptr })
-        }
-
-        pub fn iter(&self) -> Iter {
-            let inner = self.api.as_c_str_slice(self.as_ptr());
-            let len = inner.len();
-            Iter {
-                len,
-                inner,
-                index: 0,
+            let mut list = Self::new(api)?;
+            for topic in topics {
+                list.append(topic.as_ref())?;
             }
+            Ok(list)
         }
 
-        // pub fn to_strings(&self) -> Vec<String> {
-        //     self.iter().map(|s| s.to_string()).collect()
-        // }
-    }
-
-    impl<'a> IntoIterator for &'a Topics {
-        type Item = &'a str;
-
-        type IntoIter = Iter<'a>;
+        pub(super) fn as_ptr(&self) -> *mut tmq_list_t {
+            self.ptr
+        }
 
-        fn into_iter(self) -> Self::IntoIter {
-            self.iter()
+        /// Pointer to the list pointer for functions filling the list, eg. `tmq_subscription`.
+        pub(super) fn as_mut_ptr(&mut self) -> *mut *mut tmq_list_t {
+            &mut self.ptr
         }
-    }
 
-    ///
-    pub struct Iter<'a> {
-        inner: &'a [*mut c_char],
-        len: usize,
-        index: usize,
-    }
+        pub(crate) fn append(&mut self, topic: &str) -> Result<()> {
+            let c_topic = CString::new(topic).map_err(|_| {
+                RawError::new(
+                    Code::Failed,
+                    format!("invalid topic `{topic}` with a nul byte"),
+                )
+            })?;
+            let code = unsafe { (self.api.tmq_list_append)(self.ptr, c_topic.as_ptr()) };
+            if code == 0 {
+                Ok(())
+            } else {
+                Err(RawError::new(
+                    code,
+                    format!("failed to append topic `{topic}` to tmq list"),
+                ))
+            }
+        }
 
-    impl<'a> Iterator for Iter<'a> {
-        type Item = &'a str;
+        pub(crate) fn len(&self) -> usize {
+            unsafe { (self.api.tmq_list_get_size)(self.ptr) }.max(0) as usize
+        }
 
-        fn next(&mut self) -> Option<Self::Item> {
-            self.inner
-                .get(self.index)
+        /// Topics in the list, in the order they're appended.
+        pub(crate) fn to_vec(&self) -> Vec<String> {
+            let len = self.len();
+            let data = unsafe { (self.api.tmq_list_to_c_array)(self.ptr) };
+            if len == 0 || data.is_null() {
+                return Vec::new();
+            }
+            unsafe { std::slice::from_raw_parts(data, len) }
+                .iter()
                 .map(|ptr| {
                     unsafe { CStr::from_ptr(*ptr) }
-                        .to_str()
-                        .expect("topic name must be valid utf8 str")
-                })
-                .map(|s| {
-                    self.index += 1;
-                    s
+                        .to_string_lossy()
+                        .into_owned()
                 })
+                .collect()
         }
     }
 
-    impl<'a> ExactSizeIterator for Iter<'a> {
-        fn len(&self) -> usize {
-            if self.len >= self.index {
-                self.len - self.index
-            } else {
-                0
-            }
+    impl Drop for TmqList {
+        fn drop(&mut self) {
+            log::trace!("tmq list destroy {:p}", self.ptr);
+            unsafe { (self.api.tmq_list_destroy)(self.ptr) };
         }
     }
 
-    // todo: tmq_list_destroy cause double free error.
-    impl Drop for Topics {
-        fn drop(&mut self) {
-            unsafe {
-                log::trace!("list destroy");
-                self.api.destroy(self.as_ptr());
-                log::trace!("list destroy destroyed");
+    #[cfg(test)]
+    mod tests {
+        use std::cell::Cell;
+        use std::os::raw::c_char;
+
+        use super::*;
+
+        thread_local! {
+            /// Lists created and not destroyed yet by the fake api of this thread.
+            static LIVE: Cell<i32> = Cell::new(0);
+        }
+
+        /// A list of the fake api, fails to append topics starting with `fail`.
+        struct FakeList {
+            topics: Vec<CString>,
+            ptrs: Vec<*mut c_char>,
+        }
+
+        unsafe extern "C" fn fake_new() -> *mut tmq_list_t {
+            LIVE.with(|live| live.set(live.get() + 1));
+            let list = FakeList {
+                topics: Vec::new(),
+                ptrs: Vec::new(),
+            };
+            Box::into_raw(Box::new(list)) as _
+        }
+
+        unsafe extern "C" fn fake_append(list: *mut tmq_list_t, topic: *const c_char) -> i32 {
+            let list = &mut *(list as *mut FakeList);
+            let topic = CStr::from_ptr(topic).to_owned();
+            if topic.to_bytes().starts_with(b"fail") {
+                return -1;
             }
+            list.topics.push(topic);
+            list.ptrs = list.topics.iter().map(|t| t.as_ptr() as _).collect();
+            0
+        }
+
+        unsafe extern "C" fn fake_destroy(list: *mut tmq_list_t) {
+            assert!(
+                LIVE.with(|live| live.replace(live.get() - 1)) > 0,
+                "double free"
+            );
+            drop(Box::from_raw(list as *mut FakeList));
+        }
+
+        unsafe extern "C" fn fake_size(list: *const tmq_list_t) -> i32 {
+            (*(list as *const FakeList)).topics.len() as _
+        }
+
+        unsafe extern "C" fn fake_to_c_array(list: *const tmq_list_t) -> *const *mut c_char {
+            (*(list as *const FakeList)).ptrs.as_ptr()
+        }
+
+        const API: TmqListApi = TmqListApi {
+            tmq_list_new: fake_new,
+            tmq_list_append: fake_append,
+            tmq_list_destroy: fake_destroy,
+            tmq_list_get_size: fake_size,
+            tmq_list_to_c_array: fake_to_c_array,
+        };
+
+        fn live() -> i32 {
+            LIVE.with(Cell::get)
+        }
+
+        #[test]
+        fn append_and_read_back() -> Result<()> {
+            let mut list = TmqList::new(API)?;
+            assert!(list.len() == 0 && list.to_vec().is_empty());
+            list.append("meters")?;
+            list.append("主题")?;
+            assert_eq!(list.len(), 2);
+            assert_eq!(list.to_vec(), ["meters", "主题"]);
+            assert_eq!(live(), 1);
+            drop(list);
+            assert_eq!(live(), 0);
+            Ok(())
+        }
+
+        #[test]
+        fn append_failures() -> Result<()> {
+            let err = TmqList::from_topics(API, ["a", "failed", "b"]).unwrap_err();
+            assert_eq!(err.code(), Code::from(-1));
+            assert!(err.to_string().contains("`failed`"), "{err}");
+            assert_eq!(live(), 0, "the list of failed topics is leaked");
+
+            let err = TmqList::from_topics(API, ["a\0b"]).unwrap_err();
+            assert!(err.to_string().contains("invalid topic `a\0b`"), "{err}");
+            assert_eq!(live(), 0);
+
+            // a failed append keeps the list as is.
+            let mut list = TmqList::from_topics(API, ["a"])?;
+            assert!(list.append("fail").is_err());
+            assert_eq!(list.to_vec(), ["a"]);
+            drop(list);
+            assert_eq!(live(), 0);
+            Ok(())
+        }
+
+        #[test]
+        fn early_drops() {
+            let lists: Vec<_> = (0..10).map(|_| TmqList::new(API).unwrap()).collect();
+            assert_eq!(live(), 10);
+            drop(lists);
+            assert_eq!(live(), 0);
         }
     }
 }
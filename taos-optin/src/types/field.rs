@@ -1,7 +1,8 @@
 use std::ffi::{c_void, CStr};
 
 // use super::Ty;
-use taos_query::common::{Field, Ty};
+use taos_query::common::{Field, Precision, Ty};
+use taos_query::stmt::StmtField;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -73,6 +74,31 @@ impl From<&CFieldV3> for Field {
     }
 }
 
+/// Fields of stmt parameters, `TAOS_FIELD_E` of v3.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+pub struct TAOS_FIELD_E {
+    pub name: [u8; 65usize],
+    pub type_: i8,
+    pub precision: u8,
+    pub scale: u8,
+    pub bytes: i32,
+}
+
+impl From<&TAOS_FIELD_E> for StmtField {
+    fn from(field: &TAOS_FIELD_E) -> Self {
+        let name = unsafe { CStr::from_ptr(field.name.as_ptr() as _) };
+        let ty = (field.type_ as u8).into();
+        let stmt_field = Self::new(name.to_string_lossy(), ty, field.bytes as _);
+        if ty == Ty::Timestamp && field.precision <= 2 {
+            stmt_field.with_precision(Precision::from_u8(field.precision))
+        } else {
+            stmt_field
+        }
+    }
+}
+
 pub(crate) fn from_raw_fields(version: &str, ptr: *const c_void, len: usize) -> Vec<Field> {
     if version.starts_with('3') {
         unsafe { std::slice::from_raw_parts(ptr as *const CFieldV3, len) }
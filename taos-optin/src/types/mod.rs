@@ -3,6 +3,7 @@ use std::{fmt::Debug, mem::ManuallyDrop, os::raw::*, ptr};
 mod field;
 use derive_more::Deref;
 pub(crate) use field::from_raw_fields;
+pub use field::TAOS_FIELD_E;
 pub use taos_query::common::{Precision, Ty};
 
 use taos_query::common::{itypes::*, ColumnView, Value};
@@ -225,6 +226,22 @@ impl BindFrom for TaosBindV3 {
         param.is_null = box_into_raw(0i8) as _;
         Self(param)
     }
+    fn from_varbinary(v: &[u8]) -> Self {
+        let mut param = TaosMultiBind::new(Ty::VarBinary);
+        param.buffer_length = v.len();
+        param.buffer = v.as_ptr() as _;
+        param.length = box_into_raw(param.buffer_length) as _;
+        param.is_null = box_into_raw(0i8) as _;
+        Self(param)
+    }
+    fn from_geometry(v: &[u8]) -> Self {
+        let mut param = TaosMultiBind::new(Ty::Geometry);
+        param.buffer_length = v.len();
+        param.buffer = v.as_ptr() as _;
+        param.length = box_into_raw(param.buffer_length) as _;
+        param.is_null = box_into_raw(0i8) as _;
+        Self(param)
+    }
 }
 
 #[derive(Debug, Deref)]
@@ -331,6 +348,8 @@ pub trait BindFrom: Sized {
     fn from_varchar(v: &str) -> Self;
     fn from_nchar(v: &str) -> Self;
     fn from_json(v: &str) -> Self;
+    fn from_varbinary(v: &[u8]) -> Self;
+    fn from_geometry(v: &[u8]) -> Self;
     fn from_binary(v: &str) -> Self {
         Self::from_varchar(v)
     }
@@ -352,9 +371,29 @@ pub trait BindFrom: Sized {
             Value::UInt(v) => Self::from_primitive(v),
             Value::UBigInt(v) => Self::from_primitive(v),
             Value::Json(v) => Self::from_json(&v.to_string()),
+            Value::VarBinary(v) => Self::from_varbinary(v),
+            Value::Geometry(v) => Self::from_geometry(v),
             _ => unimplemented!(),
         }
     }
+
+    /// Bind tag values.
+    ///
+    /// Binds refer to the text of json tags by pointer, so the text is kept in `json` which
+    /// must live until the tags are set. `Value::Json(Null)` binds the json literal `null`,
+    /// use `Value::Null(Ty::Json)` for a NULL json tag.
+    fn from_tags(tags: &[Value], json: &mut Vec<String>) -> Vec<Self> {
+        tags.iter()
+            .map(|tag| match tag {
+                Value::Json(v) => {
+                    // Heap buffer of the string is not moved when the vector grows.
+                    json.push(v.to_string());
+                    Self::from_json(json.last().expect("just pushed"))
+                }
+                tag => Self::from_value(tag),
+            })
+            .collect()
+    }
 }
 
 fn box_into_raw<T>(v: T) -> *mut T {
@@ -398,6 +437,20 @@ impl BindFrom for TaosBindV2 {
         param.length = box_into_raw(param.buffer_length) as _;
         param
     }
+    fn from_varbinary(v: &[u8]) -> Self {
+        let mut param = Self::new(Ty::VarBinary);
+        param.buffer_length = v.len();
+        param.buffer = v.as_ptr() as _;
+        param.length = box_into_raw(param.buffer_length) as _;
+        param
+    }
+    fn from_geometry(v: &[u8]) -> Self {
+        let mut param = Self::new(Ty::Geometry);
+        param.buffer_length = v.len();
+        param.buffer = v.as_ptr() as _;
+        param.length = box_into_raw(param.buffer_length) as _;
+        param
+    }
 
     fn from_primitive<T: IsValue>(v: &T) -> Self {
         let mut param = Self::new(T::TY);
@@ -500,6 +553,18 @@ impl TaosMultiBind {
         s
     }
 
+    pub(crate) fn from_varbinary_vec(values: &[Option<impl AsRef<[u8]>>]) -> Self {
+        let mut s = Self::from_binary_vec(values);
+        s.buffer_type = Ty::VarBinary as _;
+        s
+    }
+
+    pub(crate) fn from_geometry_vec(values: &[Option<impl AsRef<[u8]>>]) -> Self {
+        let mut s = Self::from_binary_vec(values);
+        s.buffer_type = Ty::Geometry as _;
+        s
+    }
+
     #[cfg(test)]
     pub(crate) fn buffer(&self) -> *const c_void {
         self.buffer
@@ -572,6 +637,12 @@ impl<'b> From<&'b ColumnView> for DropMultiBind {
                 DropMultiBind::new(TaosMultiBind::from_primitives_ptr(nulls, view.as_raw_ptr()))
             }
             Json(view) => DropMultiBind::new(TaosMultiBind::from_json(&view.to_vec())),
+            VarBinary(view) => {
+                DropMultiBind::new(TaosMultiBind::from_varbinary_vec(&view.to_vec()))
+            }
+            Geometry(view) => DropMultiBind::new(TaosMultiBind::from_geometry_vec(&view.to_vec())),
+            // binds point to data of views, null views have none.
+            Null(_) => unreachable!("null views should be bound as allocated views"),
         }
     }
 }
@@ -588,7 +659,7 @@ impl DropMultiBind {
 impl Drop for DropMultiBind {
     fn drop(&mut self) {
         let ty = Ty::from(self.0.buffer_type as u8);
-        if ty == Ty::VarChar || ty == Ty::NChar {
+        if ty.is_var_type() {
             let len = self.0.buffer_length * self.0.num as usize;
             unsafe { Vec::from_raw_parts(self.0.buffer as *mut u8, len, len as _) };
             unsafe { Vec::from_raw_parts(self.0.length as *mut i32, self.0.num as _, self.0.num as _) };
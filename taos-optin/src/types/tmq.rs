@@ -16,7 +16,7 @@ impl tmq_resp_err_t {
     pub fn ok_or(self, s: impl Into<Cow<'static, str>>) -> Result<(), RawError> {
         match self {
             Self(0) => Ok(()),
-            _ => Err(RawError::from_string(s.into())),
+            Self(code) => Err(RawError::new(code, s.into())),
         }
     }
 }
@@ -43,6 +43,17 @@ pub struct tmq_message_t {
     _unused: [u8; 0],
 }
 
+/// Offsets of a vgroup, from `tmq_get_topic_assignment`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_snake_case)]
+pub struct tmq_topic_assignment {
+    pub vgId: i32,
+    pub currentOffset: i64,
+    pub begin: i64,
+    pub end: i64,
+}
+
 #[repr(C)]
 #[allow(dead_code)]
 pub enum tmq_conf_res_t {
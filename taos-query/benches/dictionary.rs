@@ -0,0 +1,41 @@
+#![feature(test)]
+
+extern crate test;
+
+use taos_query::common::ColumnView;
+use test::Bencher;
+
+const ROWS: usize = 1_000_000;
+const STATUS: [&str; 10] = [
+    "online", "offline", "idle", "busy", "error", "warning", "booting", "updating", "sleeping",
+    "unknown",
+];
+
+fn status_column() -> ColumnView {
+    ColumnView::from_varchar::<&str, _, _, _>(
+        (0..ROWS)
+            .map(|i| (i % 97 != 0).then(|| STATUS[i % STATUS.len()]))
+            .collect::<Vec<_>>(),
+    )
+}
+
+#[bench]
+fn bench_to_dictionary(b: &mut Bencher) {
+    let column = status_column();
+    b.iter(|| column.to_dictionary().unwrap());
+}
+
+#[bench]
+fn bench_cardinality_estimate(b: &mut Bencher) {
+    let column = status_column();
+    b.iter(|| column.cardinality_estimate(16));
+}
+
+#[bench]
+fn bench_to_vec(b: &mut Bencher) {
+    let column = status_column();
+    let ColumnView::VarChar(view) = &column else {
+        unreachable!()
+    };
+    b.iter(|| view.to_vec());
+}
@@ -0,0 +1,59 @@
+#![feature(test)]
+
+extern crate test;
+
+use bytes::Bytes;
+use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+use test::Bencher;
+
+const ROWS: usize = 4096;
+const COLS: usize = 30;
+
+/// A block of 30 columns, bigint, double and varchar in turn.
+fn wide_block() -> Bytes {
+    let views: Vec<_> = (0..COLS)
+        .map(|col| match col % 3 {
+            0 => ColumnView::from_big_ints((0..ROWS as i64).collect()),
+            1 => ColumnView::from_doubles((0..ROWS).map(|i| i as f64).collect()),
+            _ => ColumnView::from_varchar::<String, _, _, _>(
+                (0..ROWS).map(|i| format!("value {i}")).collect::<Vec<_>>(),
+            ),
+        })
+        .collect();
+    views_to_raw_block(&views).into()
+}
+
+fn parse(bytes: &Bytes) -> RawBlock {
+    let mut block = RawBlock::parse_from_raw_block(bytes.clone(), Precision::Millisecond);
+    block.with_field_names((0..COLS).map(|col| format!("c{col}")));
+    block
+}
+
+#[bench]
+fn bench_wide_block_two_columns(b: &mut Bencher) {
+    let bytes = wide_block();
+    b.iter(|| {
+        let block = parse(&bytes);
+        let (_, c0) = block.column("c0").unwrap();
+        let (_, c2) = block.column("c2").unwrap();
+        c0.iter().count() + c2.iter().count()
+    });
+}
+
+#[bench]
+fn bench_wide_block_all_columns(b: &mut Bencher) {
+    let bytes = wide_block();
+    b.iter(|| {
+        let block = parse(&bytes);
+        block
+            .columns()
+            .map(|(_, view)| view.iter().count())
+            .sum::<usize>()
+    });
+}
+
+#[bench]
+fn bench_wide_block_parse_only(b: &mut Bencher) {
+    let bytes = wide_block();
+    b.iter(|| parse(&bytes).nrows());
+}
@@ -0,0 +1,44 @@
+#![feature(test)]
+
+extern crate test;
+
+use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+use test::Bencher;
+
+const ROWS: usize = 100_000;
+const COLS: usize = 10;
+
+/// A block of 10 varchar columns.
+fn string_block() -> RawBlock {
+    let views: Vec<_> = (0..COLS)
+        .map(|col| {
+            ColumnView::from_varchar::<String, _, _, _>(
+                (0..ROWS)
+                    .map(|i| format!("column {col} value {i}"))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    let mut block =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    block.with_field_names((0..COLS).map(|col| format!("c{col}")));
+    block
+}
+
+#[bench]
+fn bench_string_block_row_into_values(b: &mut Bencher) {
+    let block = string_block();
+    b.iter(|| {
+        block
+            .rows()
+            .map(|row| row.into_values())
+            .collect::<Vec<_>>()
+            .len()
+    });
+}
+
+#[bench]
+fn bench_string_block_rows_to_values(b: &mut Bencher) {
+    let block = string_block();
+    b.iter(|| block.rows_to_values().len());
+}
@@ -0,0 +1,82 @@
+#![feature(test)]
+
+extern crate test;
+
+use serde::Deserialize;
+use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+use test::Bencher;
+
+const ROWS: usize = 4096;
+const COLS: usize = 40;
+
+/// A block of 40 columns, bigint, double and varchar in turn, named `c0` to `c39`.
+fn wide_block() -> RawBlock {
+    let views: Vec<_> = (0..COLS)
+        .map(|col| match col % 3 {
+            0 => ColumnView::from_big_ints((0..ROWS as i64).collect()),
+            1 => ColumnView::from_doubles((0..ROWS).map(|i| i as f64).collect()),
+            _ => ColumnView::from_varchar::<String, _, _, _>(
+                (0..ROWS).map(|i| format!("value {i}")).collect::<Vec<_>>(),
+            ),
+        })
+        .collect();
+    let mut block =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    block.with_field_names((0..COLS).map(|col| format!("c{col}")));
+    block.column_views();
+    block
+}
+
+#[derive(Deserialize)]
+struct Three {
+    c0: i64,
+    c20: String,
+    c37: f64,
+}
+
+#[derive(Deserialize)]
+struct Twelve {
+    c0: i64,
+    c1: f64,
+    c2: String,
+    c3: i64,
+    c4: f64,
+    c5: String,
+    c18: i64,
+    c19: f64,
+    c20: String,
+    c36: i64,
+    c37: f64,
+    c38: String,
+}
+
+#[bench]
+fn bench_wide_block_three_fields(b: &mut Bencher) {
+    let block = wide_block();
+    b.iter(|| {
+        block
+            .deserialize::<Three>()
+            .map(|row| row.unwrap())
+            .map(|row| row.c0 as usize + row.c20.len() + row.c37 as usize)
+            .sum::<usize>()
+    });
+}
+
+#[bench]
+fn bench_wide_block_twelve_fields(b: &mut Bencher) {
+    let block = wide_block();
+    b.iter(|| {
+        block
+            .deserialize::<Twelve>()
+            .map(|row| row.unwrap())
+            .map(|row| {
+                (row.c0 + row.c3 + row.c18 + row.c36) as usize
+                    + (row.c1 + row.c4 + row.c19 + row.c37) as usize
+                    + row.c2.len()
+                    + row.c5.len()
+                    + row.c20.len()
+                    + row.c38.len()
+            })
+            .sum::<usize>()
+    });
+}
@@ -0,0 +1,49 @@
+#![feature(test)]
+
+extern crate test;
+
+use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+use test::Bencher;
+
+const ROWS: usize = 1_000_000;
+
+fn bigint_column() -> ColumnView {
+    ColumnView::from_big_ints((0..ROWS as i64).collect())
+}
+
+#[bench]
+fn bench_bigint_aligned_iter(b: &mut Bencher) {
+    let column = bigint_column();
+    let ColumnView::BigInt(view) = &column else {
+        unreachable!()
+    };
+    b.iter(|| view.iter().flatten().sum::<i64>());
+}
+
+#[bench]
+fn bench_bigint_aligned_raw_slice(b: &mut Bencher) {
+    let column = bigint_column();
+    let ColumnView::BigInt(view) = &column else {
+        unreachable!()
+    };
+    b.iter(|| view.as_raw_slice().unwrap().iter().sum::<i64>());
+}
+
+#[bench]
+fn bench_bigint_misaligned_iter(b: &mut Bencher) {
+    // a block received after a framing header of odd length.
+    let raw = views_to_raw_block(&[bigint_column()]);
+    let view = (0..2)
+        .find_map(|pad| {
+            let mut buf = vec![0u8; pad];
+            buf.extend(&raw);
+            let bytes = bytes::Bytes::from(buf).slice(pad..);
+            let block = RawBlock::parse_from_raw_block(bytes, Precision::Millisecond);
+            match &block.column_views()[0] {
+                ColumnView::BigInt(view) if view.as_raw_slice().is_none() => Some(view.clone()),
+                _ => None,
+            }
+        })
+        .unwrap();
+    b.iter(|| view.iter().flatten().sum::<i64>());
+}
@@ -0,0 +1,85 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+use test::Bencher;
+
+/// Allocator tracking the peak of live heap bytes, as the peak memory of sending a block.
+struct PeakAlloc;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+/// Heap bytes allocated by `f` at its peak.
+fn peak_of<T>(f: impl FnOnce() -> T) -> usize {
+    let base = LIVE.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    drop(f());
+    PEAK.load(Ordering::Relaxed) - base
+}
+
+/// A block of 64MB.
+fn block() -> RawBlock {
+    let views = [ColumnView::from_big_ints((0..8 << 20).collect())];
+    RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond)
+}
+
+/// A write raw block message as the ws sender did: the block is copied into a growing buffer.
+fn message_copying(block: &RawBlock, fields: &[u8]) -> Vec<u8> {
+    let mut message = Vec::new();
+    message.write_all(&[0; 30]).unwrap();
+    message.write_all(block.as_raw_bytes()).unwrap();
+    message.write_all(fields).unwrap();
+    message
+}
+
+/// A write raw block message sized up front, the block is written once into it.
+fn message_vectored(block: &RawBlock, fields: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(30 + block.raw_len() + fields.len());
+    message.write_all(&[0; 30]).unwrap();
+    block.write_vectored(&mut message).unwrap();
+    message.write_all(fields).unwrap();
+    message
+}
+
+#[bench]
+fn bench_message_copying(b: &mut Bencher) {
+    let block = block();
+    let fields = [0; 72];
+    let peak = peak_of(|| message_copying(&block, &fields));
+    eprintln!("peak heap of copying a {}B block: {peak}B", block.raw_len());
+    b.iter(|| message_copying(&block, &fields));
+}
+
+#[bench]
+fn bench_message_vectored(b: &mut Bencher) {
+    let block = block();
+    let fields = [0; 72];
+    let peak = peak_of(|| message_vectored(&block, &fields));
+    eprintln!(
+        "peak heap of vectored write of a {}B block: {peak}B",
+        block.raw_len()
+    );
+    b.iter(|| message_vectored(&block, &fields));
+}
@@ -4,6 +4,7 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use crate::helpers::ColumnMeta;
+use crate::util::quote_ident;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Describe(pub(crate) Vec<ColumnMeta>);
@@ -63,7 +64,9 @@ impl Describe {
             .filter(|f| f.is_tag())
             .map(|f| f.field())
     }
+    /// Sql to create the table, the table name is quoted by [quote_ident].
     pub fn to_create_table_sql(&self, table: &str) -> String {
+        let table = quote_ident(table);
         let (cols, tags): (Vec<_>, Vec<_>) = self.fields().iter().partition(|f| !f.is_tag());
         let col_sql = cols.into_iter().map(|f| f.sql_repr()).join(",");
 
@@ -1,8 +1,9 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display};
 
 use serde::{Deserialize, Serialize};
 
-use crate::util::{Inlinable, InlinableRead, InlinableWrite};
+use crate::util::{quote_ident, Inlinable, InlinableRead, InlinableWrite};
 
 use super::ty::Ty;
 
@@ -101,7 +102,7 @@ impl Field {
 
     /// Escaped file name
     pub fn escaped_name(&self) -> String {
-        format!("`{}`", self.name())
+        quote_ident(self.name()).into_owned()
     }
 
     /// Data type of the field.
@@ -126,9 +127,14 @@ impl Field {
     pub fn sql_repr(&self) -> String {
         let ty = self.ty();
         if ty.is_var_type() {
-            format!("`{}` {}({})", self.name(), ty.name(), self.bytes())
+            format!(
+                "{} {}({})",
+                quote_ident(self.name()),
+                ty.name(),
+                self.bytes()
+            )
         } else {
-            format!("`{}` {}", self.name(), ty.name())
+            format!("{} {}", quote_ident(self.name()), ty.name())
         }
     }
 }
@@ -137,9 +143,77 @@ impl Display for Field {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let ty = self.ty();
         if ty.is_var_type() {
-            write!(f, "`{}` {}({})", self.name(), ty.name(), self.bytes())
+            write!(
+                f,
+                "{} {}({})",
+                quote_ident(self.name()),
+                ty.name(),
+                self.bytes()
+            )
         } else {
-            write!(f, "`{}` {}", self.name(), ty.name())
+            write!(f, "{} {}", quote_ident(self.name()), ty.name())
         }
     }
 }
+
+/// How to rename duplicated field names, eg. `ts` of both tables in a join.
+///
+/// Neither native nor websocket results tell the table of a field, so duplicates are numbered
+/// by their order. Generated names never clash with other names of the fields.
+///
+/// ```rust
+/// # use taos_query::common::DedupStrategy;
+/// let names = ["ts", "v", "ts", "ts_1"];
+/// assert_eq!(DedupStrategy::Suffix.dedup(names), ["ts_2", "v", "ts_3", "ts_1"]);
+/// assert_eq!(DedupStrategy::KeepFirst.dedup(names), ["ts", "v", "ts_2", "ts_1"]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupStrategy {
+    /// Suffix every occurrence of a duplicated name by `_1`, `_2` and so on.
+    #[default]
+    Suffix,
+    /// Keep the first occurrence and suffix the others.
+    KeepFirst,
+}
+
+impl DedupStrategy {
+    /// Unique names of `names` in the same order, unique names are kept.
+    pub fn dedup<'a>(self, names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let names: Vec<&str> = names.into_iter().collect();
+        let mut counts = HashMap::<&str, usize>::new();
+        for name in &names {
+            *counts.entry(name).or_default() += 1;
+        }
+        let mut used = HashSet::new();
+        let mut kept = vec![false; names.len()];
+        for (i, name) in names.iter().enumerate() {
+            let keep = match self {
+                _ if counts[name] == 1 => true,
+                Self::Suffix => false,
+                Self::KeepFirst => !used.contains(*name),
+            };
+            if keep {
+                used.insert(name.to_string());
+                kept[i] = true;
+            }
+        }
+        let mut next = HashMap::<&str, usize>::new();
+        names
+            .iter()
+            .zip(kept)
+            .map(|(name, kept)| {
+                if kept {
+                    return name.to_string();
+                }
+                let n = next.entry(name).or_insert(0);
+                loop {
+                    *n += 1;
+                    let candidate = format!("{name}_{n}");
+                    if used.insert(candidate.clone()) {
+                        return candidate;
+                    }
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Log settings of the native client, as `logDir`, `debugFlag` and `numOfLogLines` in taos.cfg.
+///
+/// Unset options keep the values of taos.cfg.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Directory of the log files.
+    pub dir: Option<PathBuf>,
+    /// Log level flag: 131 for info, 135 for debug and 143 for trace.
+    pub debug_flag: Option<u32>,
+    /// Max lines of a log file before rotating to a new one.
+    pub rotate_size: Option<u64>,
+}
+
+impl LogConfig {
+    /// Debug flag of debug level.
+    pub const DEBUG: u32 = 135;
+    /// Debug flag of trace level.
+    pub const TRACE: u32 = 143;
+
+    /// Check if the debug flag enables debug level logs.
+    pub fn is_debug(&self) -> bool {
+        self.debug_flag
+            .map_or(false, |flag| flag & Self::DEBUG == Self::DEBUG)
+    }
+
+    /// Json config string for `taos_set_config`, eg. `{"logDir":"/tmp/taos","debugFlag":"135"}`.
+    pub fn to_json(&self) -> String {
+        let mut config = serde_json::Map::new();
+        if let Some(dir) = &self.dir {
+            config.insert("logDir".into(), dir.to_string_lossy().into());
+        }
+        if let Some(flag) = self.debug_flag {
+            config.insert("debugFlag".into(), flag.to_string().into());
+        }
+        if let Some(lines) = self.rotate_size {
+            config.insert("numOfLogLines".into(), lines.to_string().into());
+        }
+        serde_json::Value::Object(config).to_string()
+    }
+}
+
+#[test]
+fn log_config_json() {
+    assert_eq!(LogConfig::default().to_json(), "{}");
+    let config = LogConfig {
+        dir: Some("/tmp/taos log".into()),
+        debug_flag: Some(LogConfig::DEBUG),
+        rotate_size: Some(100000),
+    };
+    assert!(config.is_debug());
+    let json: serde_json::Value = serde_json::from_str(&config.to_json()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "logDir": "/tmp/taos log",
+            "debugFlag": "135",
+            "numOfLogLines": "100000"
+        })
+    );
+    let info = LogConfig {
+        debug_flag: Some(131),
+        ..Default::default()
+    };
+    assert!(!info.is_debug());
+}
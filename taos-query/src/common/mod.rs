@@ -1,19 +1,25 @@
 mod describe;
 mod field;
+mod log_config;
 // mod opts;
 mod precision;
 pub mod raw;
+mod sml;
 mod timestamp;
 mod ty;
 mod value;
+mod warning;
 
 pub use describe::*;
 pub use field::*;
+pub use log_config::*;
 // pub use opts::*;
 pub use precision::*;
 pub use raw::*;
+pub use sml::*;
 pub use timestamp::*;
 pub use ty::*;
 pub use value::*;
+pub use warning::*;
 
 pub mod itypes;
@@ -3,12 +3,15 @@ use std::{
     str::FromStr,
 };
 
+use chrono::{DateTime, Timelike, Utc};
 use serde::Deserialize;
 
 #[derive(Debug, thiserror::Error)]
 pub enum PrecisionError {
     #[error("invalid precision repr: {0}")]
     Invalid(String),
+    #[error("timestamp {value} is out of range of precision {precision}")]
+    TimestampOutOfRange { value: String, precision: Precision },
 }
 
 /// The precision of a timestamp or a database.
@@ -71,6 +74,40 @@ impl Precision {
             Precision::Nanosecond => chrono::SecondsFormat::Nanos,
         }
     }
+
+    /// Ticks of the precision in a second.
+    pub const fn ticks_per_second(self) -> i64 {
+        match self {
+            Precision::Millisecond => 1_000,
+            Precision::Microsecond => 1_000_000,
+            Precision::Nanosecond => 1_000_000_000,
+        }
+    }
+
+    /// The earliest datetime a timestamp of the precision could represent.
+    ///
+    /// Timestamps are i64 ticks since the epoch, the range of nanoseconds is 1677-09-21 to
+    /// 2262-04-11, others are limited by the range of [chrono::DateTime].
+    pub fn min_datetime(self) -> DateTime<Utc> {
+        match self {
+            Precision::Nanosecond => DateTime::from_timestamp_nanos(i64::MIN),
+            _ => DateTime::<Utc>::MIN_UTC,
+        }
+    }
+
+    /// The latest datetime a timestamp of the precision could represent, see
+    /// [Precision::min_datetime].
+    pub fn max_datetime(self) -> DateTime<Utc> {
+        match self {
+            Precision::Nanosecond => DateTime::from_timestamp_nanos(i64::MAX),
+            _ => {
+                let max = DateTime::<Utc>::MAX_UTC;
+                let tick = (1_000_000_000 / self.ticks_per_second()) as u32;
+                max.with_nanosecond(max.nanosecond() / tick * tick)
+                    .expect("a tick of the max datetime")
+            }
+        }
+    }
 }
 
 macro_rules! _impl_from {
@@ -243,4 +280,33 @@ mod tests {
         let json = serde_json::to_string(&precision).unwrap();
         assert_eq!(json, "0");
     }
+
+    #[test]
+    fn datetime_range() {
+        let ns = Precision::Nanosecond;
+        assert_eq!(
+            ns.min_datetime().to_rfc3339(),
+            "1677-09-21T00:12:43.145224192+00:00"
+        );
+        assert_eq!(
+            ns.max_datetime().to_rfc3339(),
+            "2262-04-11T23:47:16.854775807+00:00"
+        );
+        assert_eq!(
+            Precision::Millisecond
+                .max_datetime()
+                .timestamp_subsec_nanos(),
+            999_000_000
+        );
+        assert_eq!(
+            Precision::Microsecond
+                .max_datetime()
+                .timestamp_subsec_nanos(),
+            999_999_000
+        );
+        assert_eq!(
+            Precision::Microsecond.min_datetime(),
+            chrono::DateTime::<chrono::Utc>::MIN_UTC
+        );
+    }
 }
@@ -0,0 +1,249 @@
+//! Numeric columns as [ndarray] matrices, enabled by `ndarray` feature.
+use ndarray::{Array2, ShapeBuilder};
+
+use crate::common::{Field, Ty};
+
+use super::RawBlock;
+
+/// How NULL values are converted in [RawBlock::to_array2_f64_with].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayNulls {
+    /// NULL becomes `f64::NAN`.
+    #[default]
+    NaN,
+    /// NULL is an [ArrayError::Null].
+    Error,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArrayError {
+    #[error("column `{0}` not found")]
+    ColumnNotFound(String),
+    #[error("column `{column}` of type {ty} is not numeric")]
+    NotNumeric { column: String, ty: Ty },
+    #[error("NULL value in column `{column}` at row {row}")]
+    Null { column: String, row: usize },
+}
+
+impl From<ArrayError> for taos_error::Error {
+    fn from(err: ArrayError) -> Self {
+        Self::from_any(err)
+    }
+}
+
+/// Integer and float types, bool and timestamp columns are not numeric here.
+const fn is_numeric(ty: Ty) -> bool {
+    use Ty::*;
+    matches!(
+        ty,
+        TinyInt | SmallInt | Int | BigInt | UTinyInt | USmallInt | UInt | UBigInt | Float | Double
+    )
+}
+
+/// Column-major `f64` matrix built from blocks of the same fields.
+pub(crate) struct ArrayBuilder {
+    indices: Vec<usize>,
+    names: Vec<String>,
+    columns: Vec<Vec<f64>>,
+    nulls: ArrayNulls,
+    rows: usize,
+}
+
+impl ArrayBuilder {
+    /// Select the named `columns` in order, or all numeric columns if `None`.
+    pub(crate) fn new(
+        fields: &[Field],
+        columns: Option<&[&str]>,
+        nulls: ArrayNulls,
+    ) -> Result<Self, ArrayError> {
+        let indices: Vec<usize> = match columns {
+            Some(columns) => columns
+                .iter()
+                .map(|&name| {
+                    let index = fields
+                        .iter()
+                        .position(|field| field.name() == name)
+                        .ok_or_else(|| ArrayError::ColumnNotFound(name.to_string()))?;
+                    let ty = fields[index].ty();
+                    if is_numeric(ty) {
+                        Ok(index)
+                    } else {
+                        Err(ArrayError::NotNumeric {
+                            column: name.to_string(),
+                            ty,
+                        })
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+            None => (0..fields.len())
+                .filter(|&index| is_numeric(fields[index].ty()))
+                .collect(),
+        };
+        Ok(Self {
+            names: indices
+                .iter()
+                .map(|&index| fields[index].name().to_string())
+                .collect(),
+            columns: vec![Vec::new(); indices.len()],
+            indices,
+            nulls,
+            rows: 0,
+        })
+    }
+
+    pub(crate) fn push(&mut self, block: &RawBlock) -> Result<(), ArrayError> {
+        let views = block.column_views();
+        for ((&index, name), column) in self.indices.iter().zip(&self.names).zip(&mut self.columns)
+        {
+            column.reserve(block.nrows());
+            for (row, value) in views[index].iter().enumerate() {
+                match (value.to_f64(), self.nulls) {
+                    (Some(value), _) => column.push(value),
+                    (None, ArrayNulls::NaN) => column.push(f64::NAN),
+                    (None, ArrayNulls::Error) => {
+                        return Err(ArrayError::Null {
+                            column: name.clone(),
+                            row: self.rows + row,
+                        })
+                    }
+                }
+            }
+        }
+        self.rows += block.nrows();
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> (Array2<f64>, Vec<String>) {
+        let shape = (self.rows, self.names.len()).f();
+        let array = Array2::from_shape_vec(shape, self.columns.concat())
+            .expect("each column has a value for every row");
+        (array, self.names)
+    }
+}
+
+impl RawBlock {
+    /// Convert numeric columns to a rows × columns `f64` matrix in column-major order, with
+    /// names of the selected columns. NULL values become NaN.
+    ///
+    /// `columns` selects columns by name in order, a non-numeric or unknown name is an error.
+    /// With `None`, all integer and float columns are selected. Values are converted as in
+    /// deserializing to `f64`, so 64-bit integers beyond 2^53 are rounded.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = [
+    ///     ColumnView::from_millis_timestamp(vec![0, 1]),
+    ///     ColumnView::from_ints(vec![Some(1), None]),
+    ///     ColumnView::from_doubles(vec![0.5, 1.5]),
+    /// ];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "v", "d"]);
+    /// let (array, names) = block.to_array2_f64(None).unwrap();
+    /// assert_eq!(names, ["v", "d"]);
+    /// assert_eq!(array.dim(), (2, 2));
+    /// assert_eq!(array[[1, 1]], 1.5);
+    /// assert!(array[[1, 0]].is_nan());
+    /// ```
+    pub fn to_array2_f64(
+        &self,
+        columns: Option<&[&str]>,
+    ) -> Result<(Array2<f64>, Vec<String>), ArrayError> {
+        self.to_array2_f64_with(columns, ArrayNulls::NaN)
+    }
+
+    /// Convert numeric columns to a matrix like [RawBlock::to_array2_f64], with NULL values
+    /// converted by `nulls`.
+    pub fn to_array2_f64_with(
+        &self,
+        columns: Option<&[&str]>,
+        nulls: ArrayNulls,
+    ) -> Result<(Array2<f64>, Vec<String>), ArrayError> {
+        let mut builder = ArrayBuilder::new(&self.fields(), columns, nulls)?;
+        builder.push(self)?;
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, concatenate, Axis};
+
+    use super::*;
+    use crate::common::{views::views_to_raw_block, ColumnView, Precision};
+
+    fn block(v: Vec<Option<i32>>) -> RawBlock {
+        let rows = v.len();
+        let views = [
+            ColumnView::from_millis_timestamp((0..rows as i64).collect()),
+            ColumnView::from_ints(v),
+            ColumnView::from_varchar::<String, _, _, _>(
+                (0..rows).map(|i| i.to_string()).collect::<Vec<_>>(),
+            ),
+            ColumnView::from_unsigned_big_ints((1..=rows as u64).collect()),
+            ColumnView::from_floats((0..rows).map(|i| i as f32 + 0.5).collect()),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        block.with_field_names(["ts", "v", "s", "u", "f"]);
+        block
+    }
+
+    #[test]
+    fn block_to_array2() {
+        let block = block(vec![Some(10), None, Some(12)]);
+        let (array, names) = block.to_array2_f64(Some(&["f", "u"])).unwrap();
+        assert_eq!(names, ["f", "u"]);
+        assert_eq!(array, array![[0.5, 1.], [1.5, 2.], [2.5, 3.]]);
+        assert!(array.t().is_standard_layout());
+
+        let (array, names) = block.to_array2_f64(None).unwrap();
+        assert_eq!(names, ["v", "u", "f"]);
+        assert_eq!(array.column(0)[0], 10.);
+        assert!(array.column(0)[1].is_nan());
+
+        let err = block
+            .to_array2_f64_with(None, ArrayNulls::Error)
+            .unwrap_err();
+        assert!(matches!(err, ArrayError::Null { ref column, row: 1 } if column == "v"));
+
+        let err = block.to_array2_f64(Some(&["u", "s"])).unwrap_err();
+        assert!(
+            matches!(err, ArrayError::NotNumeric { ref column, ty: Ty::VarChar } if column == "s")
+        );
+        assert_eq!(err.to_string(), "column `s` of type BINARY is not numeric");
+        let err = block.to_array2_f64(Some(&["x"])).unwrap_err();
+        assert!(matches!(err, ArrayError::ColumnNotFound(ref column) if column == "x"));
+
+        let (array, names) = block.to_array2_f64(Some(&[])).unwrap();
+        assert!(names.is_empty());
+        assert_eq!(array.dim(), (3, 0));
+    }
+
+    #[test]
+    fn stack_blocks_to_array2() {
+        let blocks = [
+            block(vec![Some(1), Some(2)]),
+            block(vec![Some(3), None, Some(5)]),
+        ];
+        let columns = Some(&["v", "f"][..]);
+        let mut builder = ArrayBuilder::new(&blocks[0].fields(), columns, ArrayNulls::NaN).unwrap();
+        for block in &blocks {
+            builder.push(block).unwrap();
+        }
+        let (array, _) = builder.finish();
+        let parts: Vec<_> = blocks
+            .iter()
+            .map(|block| block.to_array2_f64(columns).unwrap().0)
+            .collect();
+        let expected = concatenate(Axis(0), &[parts[0].view(), parts[1].view()]).unwrap();
+        assert_eq!(array.dim(), (5, 2));
+        assert_eq!(array.column(1), expected.column(1));
+        assert_eq!(array.column(0).slice(ndarray::s![..3]), array![1., 2., 3.]);
+        assert!(array[[3, 0]].is_nan());
+
+        let mut builder = ArrayBuilder::new(&blocks[0].fields(), None, ArrayNulls::Error).unwrap();
+        builder.push(&blocks[0]).unwrap();
+        let err = builder.push(&blocks[1]).unwrap_err();
+        assert!(matches!(err, ArrayError::Null { row: 3, .. }));
+    }
+}
@@ -0,0 +1,325 @@
+//! Raw blocks as [arrow_array::RecordBatch], enabled by `arrow` feature.
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use arrow_array::types::*;
+use arrow_array::{
+    ArrayRef, ArrowPrimitiveType, BinaryArray, BooleanArray, PrimitiveArray, RecordBatch,
+    RecordBatchOptions, StringArray,
+};
+use arrow_buffer::{BooleanBuffer, Buffer, NullBuffer, ScalarBuffer};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use bytes::Bytes;
+
+use crate::common::Precision;
+
+use super::views::{ColumnView, NullBits, NullView};
+use super::RawBlock;
+
+/// Timezone of timestamp columns, raw timestamps are since the unix epoch in UTC.
+const TIMEZONE: &str = "UTC";
+
+impl RawBlock {
+    /// Arrow schema of the block, all fields are nullable.
+    ///
+    /// - Integers, floats and bools are the Arrow types of the same width and sign.
+    /// - VarChar, NChar and Json columns are `Utf8`.
+    /// - VarBinary and Geometry columns are `Binary`, geometries in WKB.
+    /// - Timestamps are `Timestamp` in the unit of the precision, in UTC.
+    ///
+    /// Field names are empty if the block has no field names.
+    pub fn arrow_schema(&self) -> Schema {
+        Schema::new(
+            self.columns()
+                .map(|(field, view)| Field::new(field.name(), data_type(view), true))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Convert the block to an Arrow record batch, see [RawBlock::arrow_schema] for types.
+    ///
+    /// Numeric and timestamp columns share buffers of the block if they are aligned for the
+    /// type, eg. blocks fetched by a query, and are copied otherwise. Bools and strings are
+    /// copied, their layouts differ from Arrow.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// # use arrow_array::{cast::AsArray, types::Int32Type, Array};
+    /// let views = [ColumnView::from_ints(vec![Some(1), None])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["v"]);
+    /// let batch = block.to_record_batch();
+    /// let v = batch.column_by_name("v").unwrap().as_primitive::<Int32Type>();
+    /// assert_eq!(v.value(0), 1);
+    /// assert!(v.is_null(1));
+    /// ```
+    pub fn to_record_batch(&self) -> RecordBatch {
+        let columns: Vec<ArrayRef> = self.column_views().iter().map(to_array).collect();
+        let options = RecordBatchOptions::new().with_row_count(Some(self.nrows()));
+        RecordBatch::try_new_with_options(Arc::new(self.arrow_schema()), columns, &options)
+            .expect("arrays match the schema of the block")
+    }
+}
+
+fn data_type(view: &ColumnView) -> DataType {
+    match view {
+        ColumnView::Bool(_) => DataType::Boolean,
+        ColumnView::TinyInt(_) => DataType::Int8,
+        ColumnView::SmallInt(_) => DataType::Int16,
+        ColumnView::Int(_) => DataType::Int32,
+        ColumnView::BigInt(_) => DataType::Int64,
+        ColumnView::UTinyInt(_) => DataType::UInt8,
+        ColumnView::USmallInt(_) => DataType::UInt16,
+        ColumnView::UInt(_) => DataType::UInt32,
+        ColumnView::UBigInt(_) => DataType::UInt64,
+        ColumnView::Float(_) => DataType::Float32,
+        ColumnView::Double(_) => DataType::Float64,
+        ColumnView::VarChar(_) | ColumnView::NChar(_) | ColumnView::Json(_) => DataType::Utf8,
+        ColumnView::VarBinary(_) | ColumnView::Geometry(_) => DataType::Binary,
+        ColumnView::Timestamp(view) => {
+            DataType::Timestamp(time_unit(view.precision()), Some(TIMEZONE.into()))
+        }
+        ColumnView::Null(view) => data_type(&NullView::new(view.ty(), 0).to_column_view()),
+    }
+}
+
+const fn time_unit(precision: Precision) -> TimeUnit {
+    match precision {
+        Precision::Millisecond => TimeUnit::Millisecond,
+        Precision::Microsecond => TimeUnit::Microsecond,
+        Precision::Nanosecond => TimeUnit::Nanosecond,
+    }
+}
+
+fn to_array(view: &ColumnView) -> ArrayRef {
+    let len = view.len();
+    match view {
+        ColumnView::Bool(view) => Arc::new(BooleanArray::from_iter(view.iter())),
+        ColumnView::TinyInt(view) => primitive::<Int8Type>(&view.nulls, &view.data, len),
+        ColumnView::SmallInt(view) => primitive::<Int16Type>(&view.nulls, &view.data, len),
+        ColumnView::Int(view) => primitive::<Int32Type>(&view.nulls, &view.data, len),
+        ColumnView::BigInt(view) => primitive::<Int64Type>(&view.nulls, &view.data, len),
+        ColumnView::UTinyInt(view) => primitive::<UInt8Type>(&view.nulls, &view.data, len),
+        ColumnView::USmallInt(view) => primitive::<UInt16Type>(&view.nulls, &view.data, len),
+        ColumnView::UInt(view) => primitive::<UInt32Type>(&view.nulls, &view.data, len),
+        ColumnView::UBigInt(view) => primitive::<UInt64Type>(&view.nulls, &view.data, len),
+        ColumnView::Float(view) => primitive::<Float32Type>(&view.nulls, &view.data, len),
+        ColumnView::Double(view) => primitive::<Float64Type>(&view.nulls, &view.data, len),
+        ColumnView::Timestamp(view) => {
+            let (nulls, data) = (&view.nulls, &view.data);
+            match view.precision() {
+                Precision::Millisecond => primitive::<TimestampMillisecondType>(nulls, data, len),
+                Precision::Microsecond => primitive::<TimestampMicrosecondType>(nulls, data, len),
+                Precision::Nanosecond => primitive::<TimestampNanosecondType>(nulls, data, len),
+            }
+        }
+        ColumnView::VarChar(view) => Arc::new(StringArray::from_iter(
+            view.iter().map(|s| s.map(|s| s.as_str())),
+        )),
+        ColumnView::NChar(view) => Arc::new(StringArray::from_iter(view.iter())),
+        ColumnView::Json(view) => Arc::new(StringArray::from_iter(
+            view.iter().map(|json| json.map(|json| json.as_str())),
+        )),
+        ColumnView::VarBinary(view) => Arc::new(BinaryArray::from_iter(view.iter())),
+        ColumnView::Geometry(view) => Arc::new(BinaryArray::from_iter(view.iter())),
+        ColumnView::Null(_) => arrow_array::new_null_array(&data_type(view), len),
+    }
+}
+
+/// A primitive array of fixed-size values, sharing `data` if it's aligned for the type.
+fn primitive<T: ArrowPrimitiveType>(nulls: &NullBits, data: &Bytes, len: usize) -> ArrayRef {
+    let aligned = data
+        .as_ptr()
+        .align_offset(std::mem::align_of::<T::Native>())
+        == 0;
+    let buffer = match NonNull::new(data.as_ptr() as *mut u8) {
+        // SAFETY: the memory is kept alive by the clone of `data` and never mutated.
+        Some(ptr) if aligned => unsafe {
+            Buffer::from_custom_allocation(ptr, data.len(), Arc::new(data.clone()))
+        },
+        _ => Buffer::from_slice_ref(data.as_ref()),
+    };
+    let values = ScalarBuffer::<T::Native>::new(buffer, 0, len);
+    let array = PrimitiveArray::<T>::new(values, validity(nulls, len));
+    match T::DATA_TYPE {
+        DataType::Timestamp(unit, _) => {
+            Arc::new(array.with_data_type(DataType::Timestamp(unit, Some(TIMEZONE.into()))))
+        }
+        _ => Arc::new(array),
+    }
+}
+
+/// Arrow validity bitmap of `nulls`, `None` if there's no null.
+///
+/// TDengine sets bits of null rows from the most significant bit of a byte, Arrow sets bits of
+/// valid rows from the least significant bit, so each byte is reversed and inverted.
+fn validity(nulls: &NullBits, len: usize) -> Option<NullBuffer> {
+    let bytes = &nulls.0[..(len + 7) / 8];
+    if bytes.iter().all(|byte| *byte == 0) {
+        return None;
+    }
+    let bits: Vec<u8> = bytes.iter().map(|byte| !byte.reverse_bits()).collect();
+    Some(NullBuffer::new(BooleanBuffer::new(
+        Buffer::from_vec(bits),
+        0,
+        len,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::cast::AsArray;
+    use arrow_array::Array;
+
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{Timestamp, Ty, Value};
+
+    use super::*;
+
+    /// Values of an Arrow array as values of the column type `ty`.
+    fn values(array: &dyn Array, ty: Ty) -> Vec<Value> {
+        (0..array.len())
+            .map(|row| {
+                if array.is_null(row) {
+                    return Value::Null(ty);
+                }
+                match array.data_type() {
+                    DataType::Boolean => Value::Bool(array.as_boolean().value(row)),
+                    DataType::Int8 => Value::TinyInt(array.as_primitive::<Int8Type>().value(row)),
+                    DataType::Int16 => {
+                        Value::SmallInt(array.as_primitive::<Int16Type>().value(row))
+                    }
+                    DataType::Int32 => Value::Int(array.as_primitive::<Int32Type>().value(row)),
+                    DataType::Int64 => Value::BigInt(array.as_primitive::<Int64Type>().value(row)),
+                    DataType::UInt8 => {
+                        Value::UTinyInt(array.as_primitive::<UInt8Type>().value(row))
+                    }
+                    DataType::UInt16 => {
+                        Value::USmallInt(array.as_primitive::<UInt16Type>().value(row))
+                    }
+                    DataType::UInt32 => Value::UInt(array.as_primitive::<UInt32Type>().value(row)),
+                    DataType::UInt64 => {
+                        Value::UBigInt(array.as_primitive::<UInt64Type>().value(row))
+                    }
+                    DataType::Float32 => {
+                        Value::Float(array.as_primitive::<Float32Type>().value(row))
+                    }
+                    DataType::Float64 => {
+                        Value::Double(array.as_primitive::<Float64Type>().value(row))
+                    }
+                    DataType::Timestamp(TimeUnit::Microsecond, _) => {
+                        Value::Timestamp(Timestamp::Microseconds(
+                            array.as_primitive::<TimestampMicrosecondType>().value(row),
+                        ))
+                    }
+                    DataType::Utf8 => {
+                        let s = array.as_string::<i32>().value(row).to_string();
+                        match ty {
+                            Ty::NChar => Value::NChar(s),
+                            Ty::Json => Value::Json(serde_json::from_str(&s).unwrap()),
+                            _ => Value::VarChar(s),
+                        }
+                    }
+                    DataType::Binary => {
+                        let bytes = array.as_binary::<i32>().value(row).to_vec();
+                        match ty {
+                            Ty::Geometry => Value::Geometry(bytes),
+                            _ => Value::VarBinary(bytes),
+                        }
+                    }
+                    ty => panic!("unexpected arrow type {ty}"),
+                }
+            })
+            .collect()
+    }
+
+    /// A block of every column type, with NULLs in every column.
+    fn block() -> RawBlock {
+        let views = vec![
+            ColumnView::from_micros_timestamp(vec![Some(1), None, Some(3)]),
+            ColumnView::from_bools(vec![Some(true), None, Some(false)]),
+            ColumnView::from_tiny_ints(vec![Some(i8::MIN), None, Some(i8::MAX)]),
+            ColumnView::from_small_ints(vec![Some(i16::MIN), None, Some(i16::MAX)]),
+            ColumnView::from_ints(vec![Some(i32::MIN), None, Some(i32::MAX)]),
+            ColumnView::from_big_ints(vec![Some(i64::MIN), None, Some(i64::MAX)]),
+            ColumnView::from_unsigned_tiny_ints(vec![Some(0), None, Some(u8::MAX)]),
+            ColumnView::from_unsigned_small_ints(vec![Some(0), None, Some(u16::MAX)]),
+            ColumnView::from_unsigned_ints(vec![Some(0), None, Some(u32::MAX)]),
+            ColumnView::from_unsigned_big_ints(vec![Some(0), None, Some(u64::MAX)]),
+            ColumnView::from_floats(vec![Some(1.5), None, Some(f32::MAX)]),
+            ColumnView::from_doubles(vec![Some(-2.5), None, Some(f64::MIN)]),
+            ColumnView::from_varchar::<&str, _, _, _>(vec![Some("abc"), None, Some("")]),
+            ColumnView::from_nchar::<&str, _, _, _>(vec![Some("涛思"), None, Some("数据")]),
+            ColumnView::from_json::<&str, _, _, _>(vec![Some(r#"{"k":1}"#), None, Some("{}")]),
+            ColumnView::from_bytes::<&[u8], _, _, _>(vec![Some(&b"\x00\xff"[..]), None, Some(b"")]),
+            ColumnView::from_geobytes::<&[u8], _, _, _>(vec![
+                Some(&b"\x01\x01"[..]),
+                None,
+                Some(b""),
+            ]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        block.with_field_names((0..views.len()).map(|i| format!("c{i}")));
+        block
+    }
+
+    #[test]
+    fn record_batch_round_trip() {
+        let block = block();
+        let batch = block.to_record_batch();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch.num_columns(), block.ncols());
+
+        let schema = batch.schema();
+        let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, block.field_names());
+        assert_eq!(
+            schema.field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        );
+        assert_eq!(schema.field(12).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(15).data_type(), &DataType::Binary);
+        assert!(schema.fields().iter().all(|f| f.is_nullable()));
+        assert_eq!(*schema, block.arrow_schema());
+
+        let expected = block.to_values();
+        for (col, (field, _)) in block.columns().enumerate() {
+            let array = batch.column(col);
+            assert_eq!(array.null_count(), 1, "{}", field.name());
+            let actual = values(array, field.ty());
+            for (row, value) in actual.into_iter().enumerate() {
+                assert_eq!(value, expected[row][col], "{} at row {row}", field.name());
+            }
+        }
+    }
+
+    #[test]
+    fn record_batch_buffers() {
+        // more than 8 rows, so nulls span bitmap bytes.
+        let values: Vec<_> = (0..20).map(|i| (i % 3 != 0).then_some(i as i64)).collect();
+        let views = [ColumnView::from_big_ints(values.clone())];
+        let block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        let batch = block.to_record_batch();
+        let array = batch.column(0).as_primitive::<Int64Type>();
+        let actual: Vec<_> = array.iter().collect();
+        assert_eq!(actual, values);
+
+        // an aligned view shares the memory of the block.
+        let ColumnView::BigInt(view) = &block.column_views()[0] else {
+            unreachable!()
+        };
+        if view.as_raw_slice().is_some() {
+            assert_eq!(array.values().as_ptr(), view.data.as_ptr() as *const i64);
+        }
+
+        let empty = RawBlock::parse_from_raw_block(
+            views_to_raw_block(&[ColumnView::from_ints(Vec::<i32>::new())]),
+            Precision::Millisecond,
+        );
+        let batch = empty.to_record_batch();
+        assert_eq!(batch.num_rows(), 0);
+        assert_eq!(batch.column(0).null_count(), 0);
+    }
+}
@@ -0,0 +1,377 @@
+use std::cell::{RefCell, UnsafeCell};
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::common::{Precision, Ty};
+
+use super::layout::Layout;
+use super::views::*;
+use super::RawBlock;
+
+/// Buffers of a column without copying, by [ColumnView::buffers], eg. for an Arrow FFI bridge or
+/// a shared-memory exporter. The buffers are refcounted clones of the ones of the view, which
+/// stay valid after the view or block is dropped.
+///
+/// The layout is part of the public API, as follows.
+///
+/// - `nulls` of fixed-size types is a bitmap of `(len + 7) / 8` bytes, most significant bit
+///   first: row `i` is NULL if bit `7 - i % 8` of byte `i / 8` is set. Trailing bits are
+///   unspecified.
+/// - `data` of fixed-size types is `len` little-endian values of [Ty::fixed_length] bytes each,
+///   `0` or `1` for bools, timestamps are `i64` in `precision`. Bytes of NULL rows are
+///   unspecified, and `data` may not be aligned for the type.
+/// - `offsets` of variable-length types (VarChar, NChar, Json, VarBinary and Geometry) is `len`
+///   little-endian `i32`, `-1` for NULL, or the position in `data` of the value, which is a
+///   little-endian `u16` length in bytes followed by the bytes. Values may be shared by rows or
+///   in any order, and `data` may have bytes not referred by any row. Texts are UTF-8, NChar is
+///   decoded from UTF-32 in place first, so its buffers are the same as VarChar.
+/// - A column of all NULLs without data, eg. [ColumnView::null], has neither `nulls` nor
+///   `offsets`, and `data` is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnBuffers {
+    pub ty: Ty,
+    /// Rows of the column.
+    pub len: usize,
+    pub data: Bytes,
+    /// Null bitmap of fixed-size types.
+    pub nulls: Option<Bytes>,
+    /// Offsets of variable-length types.
+    pub offsets: Option<Bytes>,
+    /// Precision of timestamps, `None` for other types.
+    pub precision: Option<Precision>,
+}
+
+/// Buffers rejected by [ColumnView::from_buffers], as they don't follow the layout of
+/// [ColumnBuffers].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BuffersError {
+    #[error("{buffer} of {ty} column of {len} rows is {actual} bytes, expect {expected}")]
+    Length {
+        ty: Ty,
+        len: usize,
+        buffer: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("{buffer} is required by {ty} column")]
+    Missing { ty: Ty, buffer: &'static str },
+    #[error("value of row {row} at offset {offset} is out of data")]
+    Offset { row: usize, offset: i32 },
+    #[error("value of row {row} is not UTF-8")]
+    Utf8 { row: usize },
+    #[error("byte {0} of bool data is neither 0 nor 1")]
+    Bool(usize),
+    #[error("{0} columns have no buffers")]
+    Unsupported(Ty),
+}
+
+impl ColumnView {
+    /// Buffers of the column, cheap clones of the ones of the view, see [ColumnBuffers] for
+    /// the layout.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{ColumnView, Ty};
+    /// let view = ColumnView::from_ints(vec![Some(1), None, Some(3)]);
+    /// let buffers = view.buffers();
+    /// assert_eq!((buffers.ty, buffers.len), (Ty::Int, 3));
+    /// assert_eq!(buffers.data.len(), 12);
+    /// assert_eq!(buffers.nulls.as_deref(), Some(&[0b0100_0000u8][..]));
+    /// ```
+    pub fn buffers(&self) -> ColumnBuffers {
+        let ty = self.as_ty();
+        let len = self.len();
+        let fixed = |nulls: &NullBits, data: &Bytes| ColumnBuffers {
+            ty,
+            len,
+            data: data.clone(),
+            nulls: Some(nulls.0.clone()),
+            offsets: None,
+            precision: None,
+        };
+        let var = |offsets: &Offsets, data: &Bytes| ColumnBuffers {
+            ty,
+            len,
+            data: data.clone(),
+            nulls: None,
+            offsets: Some(offsets.0.clone()),
+            precision: None,
+        };
+        match self {
+            ColumnView::Bool(view) => fixed(&view.nulls, &view.data),
+            ColumnView::TinyInt(view) => fixed(&view.nulls, &view.data),
+            ColumnView::SmallInt(view) => fixed(&view.nulls, &view.data),
+            ColumnView::Int(view) => fixed(&view.nulls, &view.data),
+            ColumnView::BigInt(view) => fixed(&view.nulls, &view.data),
+            ColumnView::Float(view) => fixed(&view.nulls, &view.data),
+            ColumnView::Double(view) => fixed(&view.nulls, &view.data),
+            ColumnView::UTinyInt(view) => fixed(&view.nulls, &view.data),
+            ColumnView::USmallInt(view) => fixed(&view.nulls, &view.data),
+            ColumnView::UInt(view) => fixed(&view.nulls, &view.data),
+            ColumnView::UBigInt(view) => fixed(&view.nulls, &view.data),
+            ColumnView::Timestamp(view) => ColumnBuffers {
+                precision: Some(view.precision),
+                ..fixed(&view.nulls, &view.data)
+            },
+            ColumnView::VarChar(view) => var(&view.offsets, &view.data),
+            ColumnView::NChar(view) => {
+                // SAFETY: the same decoding as reading a value of the view.
+                unsafe { view.nchar_to_utf8() };
+                var(&view.offsets, &view.data)
+            }
+            ColumnView::Json(view) => var(&view.offsets, &view.data),
+            ColumnView::VarBinary(view) => var(&view.offsets, &view.data),
+            ColumnView::Geometry(view) => var(&view.offsets, &view.data),
+            ColumnView::Null(_) => ColumnBuffers {
+                ty,
+                len,
+                data: Bytes::new(),
+                nulls: None,
+                offsets: None,
+                precision: None,
+            },
+        }
+    }
+
+    /// Rebuild a view from buffers of the layout of [ColumnBuffers] without copying, eg.
+    /// received from another process. Offsets not aligned for `i32` are copied.
+    ///
+    /// Buffers are checked before any value is read: lengths of the buffers, offsets and
+    /// lengths of values in data, UTF-8 of texts and bools. Timestamps without precision are in
+    /// milliseconds.
+    pub fn from_buffers(buffers: ColumnBuffers) -> Result<Self, BuffersError> {
+        let ColumnBuffers {
+            ty,
+            len,
+            data,
+            nulls,
+            offsets,
+            precision,
+        } = buffers;
+        let check = |buffer, expected: usize, actual: usize| {
+            if expected == actual {
+                Ok(())
+            } else {
+                Err(BuffersError::Length {
+                    ty,
+                    len,
+                    buffer,
+                    expected,
+                    actual,
+                })
+            }
+        };
+        if !NullView::is_supported(ty) {
+            return Err(BuffersError::Unsupported(ty));
+        }
+        if nulls.is_none() && offsets.is_none() {
+            check("data", 0, data.len())?;
+            return Ok(ColumnView::null(ty, len));
+        }
+
+        if matches!(
+            ty,
+            Ty::VarChar | Ty::NChar | Ty::Json | Ty::VarBinary | Ty::Geometry
+        ) {
+            let offsets = offsets.ok_or(BuffersError::Missing {
+                ty,
+                buffer: "offsets",
+            })?;
+            check("offsets", len * std::mem::size_of::<i32>(), offsets.len())?;
+            let offsets = Offsets::from(offsets);
+            let text = matches!(ty, Ty::VarChar | Ty::NChar | Ty::Json);
+            for (row, offset) in offsets.iter().enumerate() {
+                check_value(&data, row, i32::from_le(*offset), text)?;
+            }
+            return Ok(match ty {
+                Ty::VarChar => ColumnView::VarChar(VarCharView { offsets, data }),
+                Ty::NChar => ColumnView::NChar(NCharView {
+                    offsets,
+                    data,
+                    is_chars: UnsafeCell::new(false),
+                    version: Version::V3,
+                    layout: Arc::new(RefCell::new({
+                        let mut layout = Layout::default();
+                        layout.with_nchar_decoded();
+                        layout
+                    })),
+                }),
+                Ty::Json => ColumnView::Json(JsonView { offsets, data }),
+                Ty::VarBinary => ColumnView::VarBinary(VarBinaryView { offsets, data }),
+                _ => ColumnView::Geometry(GeometryView { offsets, data }),
+            });
+        }
+
+        let nulls = nulls.ok_or(BuffersError::Missing {
+            ty,
+            buffer: "nulls",
+        })?;
+        check("nulls", (len + 7) / 8, nulls.len())?;
+        check("data", len * ty.fixed_length(), data.len())?;
+        if ty == Ty::Bool {
+            if let Some(byte) = data.iter().position(|b| *b > 1) {
+                return Err(BuffersError::Bool(byte));
+            }
+        }
+        let nulls = NullBits(nulls);
+        Ok(match ty {
+            Ty::Bool => ColumnView::Bool(BoolView { nulls, data }),
+            Ty::TinyInt => ColumnView::TinyInt(TinyIntView { nulls, data }),
+            Ty::SmallInt => ColumnView::SmallInt(SmallIntView { nulls, data }),
+            Ty::Int => ColumnView::Int(IntView { nulls, data }),
+            Ty::BigInt => ColumnView::BigInt(BigIntView { nulls, data }),
+            Ty::Float => ColumnView::Float(FloatView { nulls, data }),
+            Ty::Double => ColumnView::Double(DoubleView { nulls, data }),
+            Ty::UTinyInt => ColumnView::UTinyInt(UTinyIntView { nulls, data }),
+            Ty::USmallInt => ColumnView::USmallInt(USmallIntView { nulls, data }),
+            Ty::UInt => ColumnView::UInt(UIntView { nulls, data }),
+            Ty::UBigInt => ColumnView::UBigInt(UBigIntView { nulls, data }),
+            _ => ColumnView::Timestamp(TimestampView {
+                nulls,
+                data,
+                precision: precision.unwrap_or_default(),
+            }),
+        })
+    }
+}
+
+/// Check the value of `row` at `offset` is in `data`, and is UTF-8 if it's `text`.
+fn check_value(data: &Bytes, row: usize, offset: i32, text: bool) -> Result<(), BuffersError> {
+    if offset < 0 {
+        return Ok(());
+    }
+    let out = || BuffersError::Offset { row, offset };
+    let start = offset as usize;
+    let header = data.get(start..start + 2).ok_or_else(out)?;
+    let length = u16::from_le_bytes([header[0], header[1]]) as usize;
+    let value = data.get(start + 2..start + 2 + length).ok_or_else(out)?;
+    if text && std::str::from_utf8(value).is_err() {
+        return Err(BuffersError::Utf8 { row });
+    }
+    Ok(())
+}
+
+impl RawBlock {
+    /// Buffers of all columns, see [ColumnView::buffers]. The buffers keep the memory of the
+    /// block alive after it's consumed.
+    pub fn into_buffers(self) -> Vec<ColumnBuffers> {
+        self.column_views()
+            .iter()
+            .map(ColumnView::buffers)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Value;
+
+    use super::*;
+
+    fn values(view: &ColumnView) -> Vec<Value> {
+        view.iter().map(|v| v.to_value()).collect()
+    }
+
+    fn views() -> Vec<ColumnView> {
+        vec![
+            ColumnView::from_micros_timestamp(vec![Some(1), None, Some(3)]),
+            ColumnView::from_bools(vec![Some(true), None, Some(false)]),
+            ColumnView::from_tiny_ints(vec![Some(-1i8), None, Some(3)]),
+            ColumnView::from_small_ints(vec![Some(-1i16), None, Some(3)]),
+            ColumnView::from_ints(vec![Some(-1), None, Some(3)]),
+            ColumnView::from_big_ints(vec![Some(-1i64), None, Some(3)]),
+            ColumnView::from_unsigned_tiny_ints(vec![Some(1u8), None, Some(3)]),
+            ColumnView::from_unsigned_small_ints(vec![Some(1u16), None, Some(3)]),
+            ColumnView::from_unsigned_ints(vec![Some(1u32), None, Some(3)]),
+            ColumnView::from_unsigned_big_ints(vec![Some(1u64), None, Some(3)]),
+            ColumnView::from_floats(vec![Some(1.5f32), None, Some(3.)]),
+            ColumnView::from_doubles(vec![Some(1.5), None, Some(3.)]),
+            ColumnView::from_varchar::<&str, _, _, _>(vec![Some("a"), None, Some("abc")]),
+            ColumnView::from_nchar::<&str, _, _, _>(vec![Some("涛思"), None, Some("")]),
+            ColumnView::from_json::<&str, _, _, _>(vec![Some("{\"k\":1}"), None, Some("{}")]),
+            ColumnView::from_bytes::<&[u8], _, _, _>(vec![Some(&[0, 1][..]), None, Some(&[])]),
+            ColumnView::from_geobytes::<&[u8], _, _, _>(vec![Some(&[1, 1][..]), None, None]),
+            ColumnView::null(Ty::Int, 3),
+        ]
+    }
+
+    #[test]
+    fn rebuild_views() {
+        for view in views() {
+            let buffers = view.buffers();
+            assert_eq!((buffers.ty, buffers.len), (view.as_ty(), 3));
+            let rebuilt = ColumnView::from_buffers(buffers.clone()).unwrap();
+            assert_eq!(values(&rebuilt), values(&view), "{:?}", view.as_ty());
+            assert_eq!(rebuilt.buffers(), buffers);
+        }
+        let ts = ColumnView::from_micros_timestamp(vec![1]).buffers();
+        assert_eq!(ts.precision, Some(Precision::Microsecond));
+        assert_eq!(&ts.data[..], 1i64.to_le_bytes());
+    }
+
+    #[test]
+    fn block_buffers_share_memory() {
+        let views = views();
+        let raw = views_to_raw_block(&views);
+        let block = RawBlock::parse_from_raw_block(raw.clone(), Precision::Microsecond);
+        let expected: Vec<_> = block.column_views().iter().map(values).collect();
+        let bytes = block.as_raw_bytes().as_ptr_range();
+        let buffers = block.into_buffers();
+        assert_eq!(buffers.len(), views.len());
+        for (buffers, expected) in buffers.into_iter().zip(expected) {
+            if !buffers.data.is_empty() {
+                assert!(bytes.contains(&buffers.data.as_ptr()), "{:?}", buffers.ty);
+            }
+            let view = ColumnView::from_buffers(buffers).unwrap();
+            assert_eq!(values(&view), expected);
+        }
+    }
+
+    #[test]
+    fn reject_invalid_buffers() {
+        let buffers = ColumnView::from_ints(vec![1, 2]).buffers();
+        let short = ColumnBuffers {
+            data: buffers.data.slice(0..4),
+            ..buffers.clone()
+        };
+        assert_eq!(
+            ColumnView::from_buffers(short).unwrap_err().to_string(),
+            "data of INT column of 2 rows is 4 bytes, expect 8"
+        );
+        let missing = ColumnBuffers {
+            nulls: None,
+            offsets: Some(Bytes::new()),
+            ..buffers
+        };
+        assert!(matches!(
+            ColumnView::from_buffers(missing),
+            Err(BuffersError::Missing { .. })
+        ));
+
+        let text = ColumnView::from_varchar::<&str, _, _, _>(vec!["ab"]).buffers();
+        let out = ColumnBuffers {
+            offsets: Some(Bytes::copy_from_slice(&1i32.to_le_bytes())),
+            ..text.clone()
+        };
+        assert_eq!(
+            ColumnView::from_buffers(out).unwrap_err(),
+            BuffersError::Offset { row: 0, offset: 1 }
+        );
+        let latin1 = ColumnBuffers {
+            data: Bytes::from_static(&[2, 0, b'a', 0xe9]),
+            ..text
+        };
+        assert_eq!(
+            ColumnView::from_buffers(latin1).unwrap_err(),
+            BuffersError::Utf8 { row: 0 }
+        );
+        let bools = ColumnBuffers {
+            data: Bytes::from_static(&[0, 2]),
+            ..ColumnView::from_bools(vec![true, false]).buffers()
+        };
+        assert_eq!(
+            ColumnView::from_buffers(bools).unwrap_err(),
+            BuffersError::Bool(1)
+        );
+    }
+}
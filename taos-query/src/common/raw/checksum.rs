@@ -0,0 +1,256 @@
+//! Integrity framing for inlined [RawData] and [RawBlock] bytes.
+//!
+//! A framed payload is the inlined bytes with a small header and a checksum appended:
+//!
+//! | algorithm: u8 | payload length: u32 LE | payload | checksum: u32 LE |
+//!
+//! The payload is verified in a single pass before it's parsed, so a corrupted payload is
+//! reported as [Error::ChecksumMismatch] instead of failing somewhere in parsing. The algorithm
+//! tag makes the frame readable by a build with another default algorithm, as long as the
+//! algorithm feature is enabled.
+use std::io::{Read, Write};
+
+use crate::util::{Inlinable, InlinableRead, InlinableWrite};
+
+use super::{RawBlock, RawData};
+
+/// Bytes added to the payload by the framing.
+pub const FRAME_OVERHEAD: usize = std::mem::size_of::<u8>() + std::mem::size_of::<u32>() * 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("checksum mismatch, expected {expected:#010x}, actual {actual:#010x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+    #[error("unsupported checksum algorithm: {0}")]
+    UnsupportedAlgorithm(u8),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Checksum algorithm, selected by `crc32c` (default) and `xxhash` features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ChecksumAlgorithm {
+    #[cfg(feature = "crc32c")]
+    Crc32c = 1,
+    #[cfg(feature = "xxhash")]
+    XxHash32 = 2,
+}
+
+impl Default for ChecksumAlgorithm {
+    #[cfg(feature = "crc32c")]
+    fn default() -> Self {
+        Self::Crc32c
+    }
+    #[cfg(not(feature = "crc32c"))]
+    fn default() -> Self {
+        Self::XxHash32
+    }
+}
+
+impl TryFrom<u8> for ChecksumAlgorithm {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            #[cfg(feature = "crc32c")]
+            1 => Ok(Self::Crc32c),
+            #[cfg(feature = "xxhash")]
+            2 => Ok(Self::XxHash32),
+            v => Err(Error::UnsupportedAlgorithm(v)),
+        }
+    }
+}
+
+enum Hasher {
+    #[cfg(feature = "crc32c")]
+    Crc32c(u32),
+    #[cfg(feature = "xxhash")]
+    XxHash32(Box<xxhash_rust::xxh32::Xxh32>),
+}
+
+impl ChecksumAlgorithm {
+    /// Checksum of `bytes`.
+    pub fn checksum(self, bytes: &[u8]) -> u32 {
+        let mut hasher = Hasher::new(self);
+        hasher.update(bytes);
+        hasher.finish()
+    }
+}
+
+impl Hasher {
+    fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            #[cfg(feature = "crc32c")]
+            ChecksumAlgorithm::Crc32c => Self::Crc32c(0),
+            #[cfg(feature = "xxhash")]
+            ChecksumAlgorithm::XxHash32 => {
+                Self::XxHash32(Box::new(xxhash_rust::xxh32::Xxh32::new(0)))
+            }
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            #[cfg(feature = "crc32c")]
+            Self::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, bytes),
+            #[cfg(feature = "xxhash")]
+            Self::XxHash32(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        match self {
+            #[cfg(feature = "crc32c")]
+            Self::Crc32c(crc) => *crc,
+            #[cfg(feature = "xxhash")]
+            Self::XxHash32(hasher) => hasher.digest(),
+        }
+    }
+}
+
+fn write_framed<T: Inlinable, W: Write>(value: &T, wtr: &mut W) -> std::io::Result<usize> {
+    let algorithm = ChecksumAlgorithm::default();
+    let payload = value.inlined();
+    wtr.write_u8_le(algorithm as u8)?;
+    wtr.write_u32_le(payload.len() as u32)?;
+    wtr.write_all(&payload)?;
+    wtr.write_u32_le(algorithm.checksum(&payload))?;
+    Ok(payload.len() + FRAME_OVERHEAD)
+}
+
+fn read_framed<T: Inlinable, R: Read>(reader: &mut R, verify: bool) -> Result<T, Error> {
+    let algorithm = ChecksumAlgorithm::try_from(reader.read_u8()?)?;
+    let len = reader.read_u32()? as usize;
+    // Don't trust the length to pre-allocate, it may be corrupted too.
+    let mut payload = Vec::new();
+    reader.take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() != len {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    let expected = reader.read_u32()?;
+    if verify {
+        let actual = algorithm.checksum(&payload);
+        if expected != actual {
+            return Err(Error::ChecksumMismatch { expected, actual });
+        }
+    }
+    Ok(T::read_inlined(&mut payload.as_slice())?)
+}
+
+impl RawData {
+    /// Write inlined bytes with integrity framing, see [module docs](self).
+    pub fn write_inlined_with_crc<W: Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
+        write_framed(self, wtr)
+    }
+
+    /// Read bytes written by [RawData::write_inlined_with_crc] and verify the checksum.
+    pub fn read_inlined_verified<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        read_framed(reader, true)
+    }
+
+    /// Read bytes written by [RawData::write_inlined_with_crc] and skip the verification.
+    pub fn read_inlined_unverified<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        read_framed(reader, false)
+    }
+}
+
+impl RawBlock {
+    /// Write inlined bytes with integrity framing, see [module docs](self).
+    pub fn write_inlined_with_crc<W: Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
+        write_framed(self, wtr)
+    }
+
+    /// Read bytes written by [RawBlock::write_inlined_with_crc] and verify the checksum.
+    pub fn read_inlined_verified<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        read_framed(reader, true)
+    }
+
+    /// Read bytes written by [RawBlock::write_inlined_with_crc] and skip the verification.
+    pub fn read_inlined_unverified<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        read_framed(reader, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{views::views_to_raw_block, ColumnView, Precision};
+
+    fn assert_corrupted<T: std::fmt::Debug>(offset: usize, res: Result<T, Error>) {
+        match res {
+            Err(Error::ChecksumMismatch { expected, actual }) => assert_ne!(expected, actual),
+            Err(Error::UnsupportedAlgorithm(_)) => assert_eq!(offset, 0),
+            Err(Error::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof),
+            Ok(v) => panic!("corruption at offset {offset} is not detected: {v:?}"),
+        }
+    }
+
+    #[test]
+    fn raw_data_corrupted() {
+        let mut bytes = Vec::new();
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend((0..16u8).collect::<Vec<_>>());
+        let raw = RawData::new(bytes.into());
+
+        let mut framed = Vec::new();
+        let len = raw.write_inlined_with_crc(&mut framed).unwrap();
+        assert_eq!(len, framed.len());
+        assert_eq!(len, raw.inlined().len() + FRAME_OVERHEAD);
+        let read = RawData::read_inlined_verified(&mut framed.as_slice()).unwrap();
+        assert_eq!(read.as_bytes(), raw.as_bytes());
+
+        for offset in 0..framed.len() {
+            let mut corrupted = framed.clone();
+            corrupted[offset] ^= 0xFF;
+            assert_corrupted(
+                offset,
+                RawData::read_inlined_verified(&mut corrupted.as_slice()),
+            );
+        }
+
+        // Verification is skippable, a corrupted raw data payload is read as is.
+        let mut corrupted = framed.clone();
+        corrupted[FRAME_OVERHEAD + 8] ^= 0xFF;
+        let read = RawData::read_inlined_unverified(&mut corrupted.as_slice()).unwrap();
+        assert_ne!(read.as_bytes(), raw.as_bytes());
+    }
+
+    #[test]
+    fn raw_block_corrupted() {
+        let views = [
+            ColumnView::from_millis_timestamp(vec![0, 1, 2, 3]),
+            ColumnView::from_ints(vec![1, 2, 3, 4]),
+            ColumnView::from_varchar::<&str, _, _, _>(vec![Some("a"), None, Some("bc"), None]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        block.with_table_name("tb1");
+        block.with_field_names(["ts", "v", "s"]);
+
+        let mut framed = Vec::new();
+        block.write_inlined_with_crc(&mut framed).unwrap();
+        let mut reader = framed.as_slice();
+        let read = RawBlock::read_inlined_verified(&mut reader).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(read.as_raw_bytes(), block.as_raw_bytes());
+        assert_eq!(read.table_name(), Some("tb1"));
+        assert_eq!(read.field_names(), block.field_names());
+
+        let step = (framed.len() / 64).max(1);
+        let offsets = (0..framed.len()).step_by(step).chain([framed.len() - 1]);
+        for offset in offsets {
+            let mut corrupted = framed.clone();
+            corrupted[offset] ^= 0x5A;
+            assert_corrupted(
+                offset,
+                RawBlock::read_inlined_verified(&mut corrupted.as_slice()),
+            );
+        }
+
+        // Truncated frame.
+        let truncated = &framed[..framed.len() - 2];
+        assert_corrupted(1, RawBlock::read_inlined_verified(&mut &truncated[..]));
+    }
+}
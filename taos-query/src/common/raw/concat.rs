@@ -0,0 +1,231 @@
+use crate::common::{Precision, Ty};
+
+use super::{views, RawBlock};
+
+/// Blocks that can't be concatenated by [RawBlock::try_concat].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConcatError {
+    #[error("concat blocks of {0} and {1} columns")]
+    Columns(usize, usize),
+    #[error("column {index} is `{lhs}` and `{rhs}`")]
+    Name {
+        index: usize,
+        lhs: String,
+        rhs: String,
+    },
+    #[error("column {index} is {lhs} and {rhs}")]
+    Type { index: usize, lhs: Ty, rhs: Ty },
+    #[error("concat blocks of precision {0} and {1}")]
+    Precision(Precision, Precision),
+}
+
+impl RawBlock {
+    /// Append rows of `rhs` to a new block, like [RawBlock::concat] but fails if the schemas
+    /// differ instead of panicking.
+    ///
+    /// Field names are compared only if both blocks have them. Blocks with no rows are
+    /// identities, so their precision does not matter.
+    pub fn try_concat(&self, rhs: &RawBlock) -> Result<RawBlock, ConcatError> {
+        if self.ncols() != rhs.ncols() {
+            return Err(ConcatError::Columns(self.ncols(), rhs.ncols()));
+        }
+        if !self.field_names().is_empty() && !rhs.field_names().is_empty() {
+            let names = self.field_names().iter().zip(rhs.field_names());
+            if let Some((index, (lhs, rhs))) = names.enumerate().find(|(_, (l, r))| l != r) {
+                return Err(ConcatError::Name {
+                    index,
+                    lhs: lhs.clone(),
+                    rhs: rhs.clone(),
+                });
+            }
+        }
+        let types = self.schemas().iter().zip(rhs.schemas());
+        if let Some((index, (lhs, rhs))) = types.enumerate().find(|(_, (l, r))| l.ty() != r.ty()) {
+            return Err(ConcatError::Type {
+                index,
+                lhs: lhs.ty(),
+                rhs: rhs.ty(),
+            });
+        }
+        if self.nrows() > 0 && rhs.nrows() > 0 && self.precision() != rhs.precision() {
+            return Err(ConcatError::Precision(self.precision(), rhs.precision()));
+        }
+        Ok(self.concat(rhs))
+    }
+
+    /// Split into blocks of the first `mid` rows and the rest, names and column lengths are
+    /// kept.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > nrows`.
+    pub fn split_at(&self, mid: usize) -> (RawBlock, RawBlock) {
+        assert!(
+            mid <= self.nrows(),
+            "split block of {} rows at {mid}",
+            self.nrows()
+        );
+        (self.slice_rows(0..mid), self.slice_rows(mid..self.nrows()))
+    }
+
+    /// Split into blocks of `size` rows, the last one may be shorter. A block with no rows has
+    /// no chunks.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = [ColumnView::from_ints((0..10).collect::<Vec<_>>())];
+    /// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// let rows: Vec<_> = block.chunks(4).map(|chunk| chunk.nrows()).collect();
+    /// assert_eq!(rows, [4, 4, 2]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    pub fn chunks(&self, size: usize) -> impl Iterator<Item = RawBlock> + '_ {
+        assert!(size > 0, "chunk size must be non-zero");
+        (0..self.nrows())
+            .step_by(size)
+            .map(move |start| self.slice_rows(start..(start + size).min(self.nrows())))
+    }
+
+    /// A new block of rows in `range`, which is in bounds.
+    fn slice_rows(&self, range: std::ops::Range<usize>) -> RawBlock {
+        let mut block = if range.is_empty() {
+            RawBlock::empty(self.schemas(), self.precision())
+        } else {
+            let views: Vec<_> = self
+                .column_views()
+                .iter()
+                .map(|view| view.slice(range.clone()).expect("rows in range"))
+                .collect();
+            let bytes = views::views_to_raw_block_with_schemas(&views, self.schemas());
+            RawBlock::parse_from_raw_block(bytes, self.precision())
+        };
+        self.copy_names_to(&mut block);
+        block
+    }
+
+    /// Copy field, table and database names and the group id to a block of rows of self.
+    pub(super) fn copy_names_to(&self, block: &mut RawBlock) {
+        block.with_field_names(self.field_names());
+        if let Some(name) = self.table_name() {
+            block.with_table_name(name);
+        }
+        if let Some(name) = self.tmq_db_name() {
+            block.with_database_name(name);
+        }
+        block.group_id = self.group_id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::views::{views_to_raw_block, ColumnView};
+    use crate::common::Value;
+
+    use super::*;
+
+    fn block(start: i64, rows: usize, precision: Precision) -> RawBlock {
+        let rows = rows as i64;
+        let views = [
+            ColumnView::from_micros_timestamp((start..start + rows).collect()),
+            ColumnView::from_ints(
+                (start..start + rows)
+                    .map(|i| (i % 3 != 0).then_some(i as i32))
+                    .collect(),
+            ),
+            ColumnView::from_varchar::<String, _, _, _>(
+                (start..start + rows)
+                    .map(|i| (i % 5 != 0).then(|| "v".repeat(i as usize % 4 + 1)))
+                    .collect::<Vec<_>>(),
+            ),
+        ];
+        let mut raw = RawBlock::parse_from_raw_block(views_to_raw_block(&views), precision);
+        raw.with_field_names(["ts", "v", "s"]);
+        raw
+    }
+
+    #[test]
+    fn concat_and_split() {
+        let blocks = [
+            block(0, 5, Precision::Microsecond),
+            block(5, 0, Precision::Millisecond),
+            block(5, 7, Precision::Microsecond),
+        ];
+        let concat = blocks[0]
+            .try_concat(&blocks[1])
+            .and_then(|block| block.try_concat(&blocks[2]))
+            .unwrap();
+        assert_eq!(concat.nrows(), 12);
+        assert_eq!(concat.field_names(), ["ts", "v", "s"]);
+        let expected = block(0, 12, Precision::Microsecond).to_values();
+        assert_eq!(concat.to_values(), expected);
+
+        // write columns out and parse them back.
+        let bytes = views_to_raw_block(concat.column_views());
+        let parsed = RawBlock::parse_from_raw_block(bytes, Precision::Microsecond);
+        assert_eq!(parsed.to_values(), expected);
+
+        let (head, tail) = concat.split_at(5);
+        assert_eq!(head.to_values(), expected[..5]);
+        assert_eq!(tail.to_values(), expected[5..]);
+        assert_eq!(tail.field_names(), ["ts", "v", "s"]);
+        assert_eq!(tail.schemas()[2].len(), concat.schemas()[2].len());
+        let (empty, all) = concat.split_at(0);
+        assert_eq!(empty.nrows(), 0);
+        assert_eq!(all.to_values(), expected);
+
+        let chunks: Vec<_> = concat.chunks(5).collect();
+        assert_eq!(
+            chunks.iter().map(RawBlock::nrows).collect::<Vec<_>>(),
+            [5, 5, 2]
+        );
+        let rows: Vec<Vec<Value>> = chunks.iter().flat_map(RawBlock::to_values).collect();
+        assert_eq!(rows, expected);
+        let rejoined = chunks
+            .iter()
+            .try_fold(chunks[0].split_at(0).0, |acc, chunk| acc.try_concat(chunk))
+            .unwrap();
+        assert_eq!(rejoined.to_values(), expected);
+        assert_eq!(blocks[1].chunks(3).count(), 0);
+    }
+
+    #[test]
+    fn concat_mismatch() {
+        let lhs = block(0, 2, Precision::Microsecond);
+        let rhs = block(2, 2, Precision::Nanosecond);
+        assert_eq!(
+            lhs.try_concat(&rhs).unwrap_err(),
+            ConcatError::Precision(Precision::Microsecond, Precision::Nanosecond)
+        );
+
+        let mut renamed = block(2, 2, Precision::Microsecond);
+        renamed.with_field_names(["ts", "v", "s2"]);
+        let err = lhs.try_concat(&renamed).unwrap_err();
+        assert_eq!(err.to_string(), "column 2 is `s` and `s2`");
+
+        let views = [
+            ColumnView::from_micros_timestamp(vec![0]),
+            ColumnView::from_big_ints(vec![0]),
+            ColumnView::from_varchar(vec!["a"]),
+        ];
+        let retyped =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        assert_eq!(
+            lhs.try_concat(&retyped).unwrap_err(),
+            ConcatError::Type {
+                index: 1,
+                lhs: Ty::Int,
+                rhs: Ty::BigInt
+            }
+        );
+
+        let narrow =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views[..2]), Precision::Microsecond);
+        assert_eq!(
+            lhs.try_concat(&narrow).unwrap_err(),
+            ConcatError::Columns(3, 2)
+        );
+    }
+}
@@ -1,11 +1,40 @@
-use std::{borrow::Cow, ffi::c_void};
+use std::{
+    borrow::Cow,
+    ffi::c_void,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use bytes::Bytes;
 
+use super::{data_rsp::DataRsp, RawBlock};
 use crate::util::{Inlinable, InlinableRead};
 
 const RAW_PTR_OFFSET: usize = std::mem::size_of::<u32>() + std::mem::size_of::<u16>();
 
+/// Default limit of raw data payload read by [Inlinable::read_inlined], 1 GiB.
+pub const DEFAULT_MAX_INLINED_RAW_LEN: usize = 1 << 30;
+
+static MAX_INLINED_RAW_LEN: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_INLINED_RAW_LEN);
+
+/// Raw data that breaks the `| raw_len: u32 | raw_type: u16 | payload |` layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RawDataError {
+    #[error("raw data of {0} bytes is shorter than its 6 bytes header")]
+    Truncated(usize),
+    #[error("raw data declares {declared} bytes of payload but has {actual}")]
+    LengthMismatch { declared: u32, actual: usize },
+    #[error("raw data of {len} bytes exceeds the limit of {limit} bytes")]
+    TooLarge { len: usize, limit: usize },
+    #[error("raw data of {0} bytes points to null")]
+    NullPointer(u32),
+}
+
+impl From<RawDataError> for std::io::Error {
+    fn from(err: RawDataError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
 /// C-struct for raw data, just a data view from native library.
 ///
 /// It can be copy/cloned, but should not use it outbound away a offset lifetime.
@@ -35,11 +64,14 @@ impl raw_data_t {
         // next 2 bytes: raw_type
         data.extend(self.raw_type.to_le_bytes());
 
-        unsafe {
-            let ptr = data.as_mut_ptr().offset(RAW_PTR_OFFSET as isize);
-            std::ptr::copy_nonoverlapping(self.raw, ptr as _, self.raw_len as _);
-            data.set_len(cap);
+        // Null pointer is invalid to copy from even for zero bytes.
+        if self.raw_len > 0 {
+            unsafe {
+                let ptr = data.as_mut_ptr().add(RAW_PTR_OFFSET);
+                std::ptr::copy_nonoverlapping(self.raw, ptr as _, self.raw_len as _);
+            }
         }
+        unsafe { data.set_len(cap) };
         Bytes::from(data)
     }
 }
@@ -51,27 +83,66 @@ enum RawDataInner {
 }
 
 impl RawDataInner {
+    /// Length in the header, it may not be the actual payload length.
+    fn declared_len(&self) -> u32 {
+        match self {
+            RawDataInner::Raw(raw) => raw.raw_len,
+            RawDataInner::Data(bytes) => bytes
+                .get(..4)
+                .map_or(0, |len| u32::from_le_bytes(len.try_into().unwrap())),
+        }
+    }
+    /// Length of the payload available after the header.
     fn raw_len(&self) -> u32 {
         match self {
             RawDataInner::Raw(raw) => raw.raw_len,
-            RawDataInner::Data(bytes) => unsafe { *(bytes.as_ptr() as *const u32) },
+            RawDataInner::Data(bytes) => {
+                let payload = bytes.len().saturating_sub(RAW_PTR_OFFSET);
+                self.declared_len()
+                    .min(payload.try_into().unwrap_or(u32::MAX))
+            }
         }
     }
     fn raw_type(&self) -> u16 {
         match self {
             RawDataInner::Raw(raw) => raw.raw_type,
-            RawDataInner::Data(bytes) => unsafe {
-                *(bytes.as_ptr().offset(std::mem::size_of::<u32>() as _) as *const u16)
-            },
+            RawDataInner::Data(bytes) => bytes
+                .get(4..RAW_PTR_OFFSET)
+                .map_or(0, |ty| u16::from_le_bytes(ty.try_into().unwrap())),
         }
     }
     fn raw(&self) -> *const c_void {
         match self {
             RawDataInner::Raw(raw) => raw.raw,
-            RawDataInner::Data(bytes) => unsafe { bytes.as_ptr().offset(RAW_PTR_OFFSET as _) as _ },
+            RawDataInner::Data(bytes) => {
+                // Points to the end of a truncated buffer, where `raw_len` is 0.
+                let offset = RAW_PTR_OFFSET.min(bytes.len());
+                unsafe { bytes.as_ptr().add(offset) as _ }
+            }
         }
     }
 
+    fn validate(&self) -> Result<(), RawDataError> {
+        match self {
+            RawDataInner::Raw(raw) => {
+                if raw.raw.is_null() && raw.raw_len > 0 {
+                    return Err(RawDataError::NullPointer(raw.raw_len));
+                }
+            }
+            RawDataInner::Data(bytes) => {
+                if bytes.len() < RAW_PTR_OFFSET {
+                    return Err(RawDataError::Truncated(bytes.len()));
+                }
+                let declared = self.declared_len();
+                let actual = bytes.len() - RAW_PTR_OFFSET;
+                if declared as usize != actual {
+                    return Err(RawDataError::LengthMismatch { declared, actual });
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn as_bytes(&self) -> Cow<Bytes> {
         match self {
             Self::Raw(raw) => Cow::Owned(raw.to_bytes()),
@@ -80,28 +151,78 @@ impl RawDataInner {
     }
 }
 
+/// Raw type of TMQ data message, see `RES_TYPE__TMQ` in TDengine.
+pub(crate) const RAW_TYPE_DATA: u16 = 2;
+/// Raw type of TMQ meta-data message, see `RES_TYPE__TMQ_METADATA` in TDengine.
+pub(crate) const RAW_TYPE_META_DATA: u16 = 4;
+
+/// Index of the next block of [RawData::fetch_raw_block], a clone starts from the first block.
+#[derive(Debug, Default)]
+struct BlockCursor(AtomicUsize);
+
+impl Clone for BlockCursor {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct RawData(RawDataInner);
+pub struct RawData(RawDataInner, BlockCursor);
 
 unsafe impl Send for RawData {}
 unsafe impl Sync for RawData {}
 
 impl From<raw_data_t> for RawData {
     fn from(raw: raw_data_t) -> Self {
-        RawData(RawDataInner::Raw(raw))
+        RawData(RawDataInner::Raw(raw), BlockCursor::default())
     }
 }
 
 impl<T: Into<Bytes>> From<T> for RawData {
     fn from(bytes: T) -> Self {
-        RawData(RawDataInner::Data(bytes.into()))
+        RawData(RawDataInner::Data(bytes.into()), BlockCursor::default())
     }
 }
 
 impl RawData {
+    /// Wrap bytes of `| raw_len: u32 | raw_type: u16 | payload |` without validation.
+    ///
+    /// Accessors never read out of a malformed buffer, a truncated header reads as zeros and
+    /// [RawData::raw_len] is capped to the payload, use [RawData::try_new] to reject it instead.
     pub fn new(raw: Bytes) -> Self {
         raw.into()
     }
+    /// Wrap bytes of `| raw_len: u32 | raw_type: u16 | payload |`, checking the header is
+    /// complete and `raw_len` matches the payload.
+    ///
+    /// ```
+    /// use taos_query::common::{RawData, RawDataError};
+    ///
+    /// let raw = RawData::try_new(b"\x03\x00\x00\x00\x10\x02abc".to_vec()).unwrap();
+    /// assert_eq!(raw.raw_len(), 3);
+    ///
+    /// let err = RawData::try_new(b"\x04\x00\x00\x00\x10\x02abc".to_vec()).unwrap_err();
+    /// assert_eq!(err, RawDataError::LengthMismatch { declared: 4, actual: 3 });
+    /// ```
+    pub fn try_new(raw: impl Into<Bytes>) -> Result<Self, RawDataError> {
+        let raw = RawData(RawDataInner::Data(raw.into()), BlockCursor::default());
+        raw.validate()?;
+        Ok(raw)
+    }
+    /// Check the invariants [RawData::try_new] checks on construction, and that data from
+    /// native library has a non-null pointer.
+    pub fn validate(&self) -> Result<(), RawDataError> {
+        self.0.validate()
+    }
+    /// Limit of the payload length accepted by [Inlinable::read_inlined] and its async
+    /// variant, [DEFAULT_MAX_INLINED_RAW_LEN] by default.
+    pub fn max_inlined_len() -> usize {
+        MAX_INLINED_RAW_LEN.load(Ordering::Relaxed)
+    }
+    /// Set the limit of [RawData::max_inlined_len] process wide.
+    pub fn set_max_inlined_len(limit: usize) {
+        MAX_INLINED_RAW_LEN.store(limit, Ordering::Relaxed)
+    }
     pub fn raw(&self) -> *const c_void {
         self.0.raw()
     }
@@ -123,24 +244,95 @@ impl RawData {
     pub fn as_bytes(&self) -> Cow<Bytes> {
         self.0.as_bytes()
     }
+
+    /// Whether the raw data is a TMQ data or meta-data message, which carries blocks.
+    pub fn has_blocks(&self) -> bool {
+        matches!(self.raw_type(), RAW_TYPE_DATA | RAW_TYPE_META_DATA)
+    }
+
+    /// Parse all blocks of a TMQ data or meta-data message, other raw types have none.
+    ///
+    /// Blocks have field names and table name if the message was consumed with them.
+    pub fn blocks(&self) -> std::io::Result<Vec<RawBlock>> {
+        if !self.has_blocks() {
+            return Ok(Vec::new());
+        }
+        let bytes = self.as_bytes();
+        let mut rsp = DataRsp::new(&bytes[RAW_PTR_OFFSET..])?;
+        std::iter::from_fn(|| rsp.next_block().transpose()).collect()
+    }
+
+    /// Parse the next block of a TMQ data or meta-data message, `None` after the last one.
+    pub fn fetch_raw_block(&self) -> std::io::Result<Option<RawBlock>> {
+        if !self.has_blocks() {
+            return Ok(None);
+        }
+        let index = self.1 .0.fetch_add(1, Ordering::Relaxed);
+        let bytes = self.as_bytes();
+        let mut rsp = DataRsp::new(&bytes[RAW_PTR_OFFSET..])?;
+        for _ in 0..index {
+            if !rsp.skip()? {
+                return Ok(None);
+            }
+        }
+        rsp.next_block()
+    }
 }
 
-impl Inlinable for RawData {
-    fn read_inlined<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let mut data = Vec::new();
+fn check_inlined_len(len: u32, limit: usize) -> std::io::Result<usize> {
+    let len = len as usize;
+    if len > limit {
+        return Err(RawDataError::TooLarge { len, limit }.into());
+    }
+    Ok(len)
+}
+
+fn read_inlined_limited<R: std::io::Read>(
+    reader: &mut R,
+    limit: usize,
+) -> std::io::Result<RawData> {
+    use std::io::Read;
+    let mut data = Vec::new();
 
-        let len = reader.read_u32()?;
-        data.extend(len.to_le_bytes());
+    let len = reader.read_u32()?;
+    let len = check_inlined_len(len, limit)?;
+    data.extend((len as u32).to_le_bytes());
 
-        let meta_type = reader.read_u16()?;
-        data.extend(meta_type.to_le_bytes());
+    let meta_type = reader.read_u16()?;
+    data.extend(meta_type.to_le_bytes());
 
-        data.resize(data.len() + len as usize, 0);
+    // Don't trust the length to pre-allocate, the payload may be shorter.
+    reader.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != RAW_PTR_OFFSET + len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data.into())
+}
 
-        let buf = &mut data[RAW_PTR_OFFSET..];
+async fn read_inlined_limited_async<R: tokio::io::AsyncRead + Send + Unpin>(
+    reader: &mut R,
+    limit: usize,
+) -> std::io::Result<RawData> {
+    use tokio::io::*;
+    let mut data = Vec::new();
 
-        reader.read_exact(buf)?;
-        Ok(data.into())
+    let len = reader.read_u32_le().await?;
+    let len = check_inlined_len(len, limit)?;
+    data.extend((len as u32).to_le_bytes());
+
+    let meta_type = reader.read_u16_le().await?;
+    data.extend(meta_type.to_le_bytes());
+
+    reader.take(len as u64).read_to_end(&mut data).await?;
+    if data.len() != RAW_PTR_OFFSET + len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data.into())
+}
+
+impl Inlinable for RawData {
+    fn read_inlined<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        read_inlined_limited(reader, RawData::max_inlined_len())
     }
 
     fn write_inlined<W: std::io::Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
@@ -155,21 +347,7 @@ impl crate::util::AsyncInlinable for RawData {
     async fn read_inlined<R: tokio::io::AsyncRead + Send + Unpin>(
         reader: &mut R,
     ) -> std::io::Result<Self> {
-        use tokio::io::*;
-        let mut data = Vec::new();
-
-        let len = reader.read_u32_le().await?;
-        data.extend(len.to_le_bytes());
-
-        let meta_type = reader.read_u16_le().await?;
-        data.extend(meta_type.to_le_bytes());
-
-        data.resize(data.len() + len as usize, 0);
-
-        let buf = &mut data[RAW_PTR_OFFSET..];
-
-        reader.read_exact(buf).await?;
-        Ok(data.into())
+        read_inlined_limited_async(reader, RawData::max_inlined_len()).await
     }
 
     async fn write_inlined<W: tokio::io::AsyncWrite + Send + Unpin>(
@@ -182,3 +360,149 @@ impl crate::util::AsyncInlinable for RawData {
         Ok(bytes.len())
     }
 }
+
+#[test]
+fn raw_data_little_endian() {
+    let raw = RawData::new(Bytes::from_static(b"\x03\x00\x00\x00\x10\x02abc"));
+    assert_eq!(raw.raw_len(), 3);
+    assert_eq!(raw.raw_type(), 0x0210);
+    let raw_t = raw.as_raw_data_t();
+    assert_eq!(raw_t.to_bytes(), raw.as_bytes().as_ref());
+}
+
+#[test]
+fn raw_data_truncated() {
+    let full = b"\x03\x00\x00\x00\x10\x02abc";
+    for len in 0..full.len() {
+        let bytes = Bytes::copy_from_slice(&full[..len]);
+        let raw = RawData::new(bytes.clone());
+        assert!(raw.raw_len() as usize <= len.saturating_sub(RAW_PTR_OFFSET));
+        assert_eq!(
+            raw.as_raw_data_t().to_bytes().len(),
+            raw.raw_len() as usize + 6
+        );
+        let err = raw.validate().unwrap_err();
+        assert_eq!(RawData::try_new(bytes).unwrap_err(), err);
+        if len < RAW_PTR_OFFSET {
+            assert_eq!(err, RawDataError::Truncated(len));
+            assert_eq!(raw.raw_type(), 0);
+        } else {
+            assert_eq!(raw.raw_type(), 0x0210);
+        }
+
+        let err = RawData::read_inlined(&mut &full[..len]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+    let raw = RawData::try_new(full.to_vec()).unwrap();
+    assert_eq!(raw.inlined(), full);
+}
+
+#[test]
+fn raw_data_lying_length() {
+    use rand::{Rng, SeedableRng};
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0x7a05);
+    for _ in 0..1000 {
+        let declared: u32 = match rng.gen_range(0..3) {
+            0 => rng.gen(),
+            1 => rng.gen_range(0..64),
+            _ => u32::MAX - rng.gen_range(0..64),
+        };
+        let payload = rng.gen_range(0..64usize);
+        let mut bytes = Vec::with_capacity(RAW_PTR_OFFSET + payload);
+        bytes.extend(declared.to_le_bytes());
+        bytes.extend(rng.gen::<u16>().to_le_bytes());
+        bytes.extend((0..payload).map(|_| rng.gen::<u8>()));
+
+        let raw = RawData::new(bytes.clone().into());
+        assert!(raw.raw_len() as usize <= payload);
+        let copied = raw.as_raw_data_t().to_bytes();
+        assert_eq!(
+            copied[RAW_PTR_OFFSET..],
+            bytes[RAW_PTR_OFFSET..][..raw.raw_len() as usize]
+        );
+
+        let valid = declared as usize == payload;
+        assert_eq!(raw.validate().is_ok(), valid);
+        assert_eq!(RawData::try_new(bytes.clone()).is_ok(), valid);
+
+        let read = read_inlined_limited(&mut bytes.as_slice(), 64);
+        match read {
+            // Reading a stream stops at the declared length.
+            Ok(read) => {
+                assert!(declared as usize <= payload);
+                assert!(read.validate().is_ok());
+                let len = RAW_PTR_OFFSET + declared as usize;
+                assert_eq!(read.as_bytes().as_ref(), &bytes[..len]);
+            }
+            Err(err) if declared > 64 => {
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+                let err = err
+                    .into_inner()
+                    .unwrap()
+                    .downcast::<RawDataError>()
+                    .unwrap();
+                assert_eq!(
+                    *err,
+                    RawDataError::TooLarge {
+                        len: declared as usize,
+                        limit: 64
+                    }
+                );
+            }
+            Err(err) => {
+                assert!((declared as usize) > payload);
+                assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+            }
+        }
+    }
+}
+
+#[test]
+fn raw_data_inlined_limit() {
+    // A corrupted length beyond the default limit is rejected before allocation.
+    let bytes = b"\xff\xff\xff\xff\x10\x02abc";
+    let err = RawData::read_inlined(&mut bytes.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let bytes = b"\x03\x00\x00\x00\x10\x02abc";
+    let err = read_inlined_limited(&mut bytes.as_slice(), 2).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(read_inlined_limited(&mut bytes.as_slice(), 3).is_ok());
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let err = read_inlined_limited_async(&mut bytes.as_slice(), 2)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let err = read_inlined_limited_async(&mut &bytes[..7], 3)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        let raw = read_inlined_limited_async(&mut bytes.as_slice(), 3)
+            .await
+            .unwrap();
+        assert_eq!(raw.as_bytes().as_ref(), &bytes[..]);
+    });
+}
+
+#[test]
+fn raw_data_null_pointer() {
+    let raw = RawData::from(raw_data_t {
+        raw: std::ptr::null(),
+        raw_len: 0,
+        raw_type: 2,
+    });
+    assert!(raw.validate().is_ok());
+    assert_eq!(raw.as_bytes().len(), RAW_PTR_OFFSET);
+
+    let raw = RawData::from(raw_data_t {
+        raw: std::ptr::null(),
+        raw_len: 3,
+        raw_type: 2,
+    });
+    assert_eq!(raw.validate(), Err(RawDataError::NullPointer(3)));
+}
@@ -0,0 +1,483 @@
+//! Blocks of TMQ raw data, the payload `tmq_get_raw` encodes from a data response.
+//!
+//! The payload is TDengine's encoding of `SMqDataRsp` (or `SMqTaosxRsp` for meta-data messages),
+//! optionally prefixed by `| version: i8 | len: i32 |` since `MQ_DATA_RSP_VERSION` 100:
+//!
+//! ```text,ignore
+//! | req_offset | rsp_offset | block_num: i32 | with_tb_name: i8 | with_schema: i8 | block... |
+//! ```
+//!
+//! Each block is a binary of `SRetrieveTableRspForTmq` holding a v3 raw block, followed by its
+//! schema if `with_schema` and its table name if `with_tb_name`.
+
+use std::io::{Error, ErrorKind, Result};
+
+use bytes::Bytes;
+
+use super::{validate, RawBlock};
+use crate::common::{Precision, Ty};
+
+/// Raw data prefixed by its version and length starts with a version of at least this.
+const MQ_DATA_RSP_VERSION: i8 = 100;
+/// Version of `SRetrieveTableRspForTmq`, older servers send an unsupported `SRetrieveTableRsp`.
+const RETRIEVE_TABLE_RSP_TMQ_VERSION: i64 = 1;
+/// `| version: i64 | rows: i64 | compressed: i8 | precision: i8 |` before the raw block.
+const RETRIEVE_HEADER_LEN: usize = 18;
+
+const TMQ_OFFSET_LOG: i8 = 1;
+const TMQ_OFFSET_SNAPSHOT_DATA: i8 = 2;
+const TMQ_OFFSET_SNAPSHOT_META: i8 = 3;
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+/// Reader of TDengine's encoder, fixed-width integers are little-endian and varints are LEB128.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or_else(|| {
+                invalid(format!(
+                    "tmq raw data truncated at {} of {} bytes",
+                    self.pos,
+                    self.bytes.len()
+                ))
+            })?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn i8(&mut self) -> Result<i8> {
+        Ok(self.take(1)?[0] as i8)
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u64v(&mut self, bits: u32) -> Result<u64> {
+        let mut v = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.take(1)?[0];
+            v |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                break;
+            }
+            shift += 7;
+            if shift >= bits {
+                return Err(invalid(format!(
+                    "tmq raw data has a varint over {bits} bits"
+                )));
+            }
+        }
+        Ok(v)
+    }
+
+    fn u32v(&mut self) -> Result<u32> {
+        Ok(self.u64v(32)? as u32)
+    }
+
+    /// Zigzag encoded varint.
+    fn i32v(&mut self) -> Result<i32> {
+        let v = self.u32v()?;
+        Ok((v >> 1) as i32 ^ -((v & 1) as i32))
+    }
+
+    fn i16v(&mut self) -> Result<i16> {
+        let v = self.u64v(16)? as u16;
+        Ok((v >> 1) as i16 ^ -((v & 1) as i16))
+    }
+
+    fn binary(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32v()? as usize;
+        self.take(len)
+    }
+
+    /// Null-terminated string in a binary.
+    fn cstr(&mut self) -> Result<String> {
+        let bytes = self.binary()?;
+        let bytes = bytes.strip_suffix(b"\0").unwrap_or(bytes);
+        String::from_utf8(bytes.to_vec())
+            .map_err(|err| invalid(format!("tmq raw data has an invalid string: {err}")))
+    }
+
+    /// Skip `STqOffsetVal`, its type carries the encoding version in the high 4 bits.
+    fn skip_offset(&mut self) -> Result<()> {
+        let ty = self.i8()?;
+        let (version, ty) = if ty < 0 {
+            (0, ty)
+        } else {
+            (ty >> 4, ty & 0x0f)
+        };
+        match ty {
+            TMQ_OFFSET_SNAPSHOT_DATA | TMQ_OFFSET_SNAPSHOT_META => {
+                // uid, ts
+                self.take(16)?;
+                if version >= 1 {
+                    let pk = self.i8()?;
+                    match Ty::try_from_u8(pk as u8) {
+                        Ok(pk) if pk.is_var_type() || pk == Ty::Json => {
+                            self.binary()?;
+                        }
+                        _ => {
+                            self.i64()?;
+                        }
+                    }
+                }
+            }
+            TMQ_OFFSET_LOG => {
+                self.i64()?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Column names of `SSchemaWrapper`.
+    fn schema_names(&mut self) -> Result<Vec<String>> {
+        let cols = self.i32v()?;
+        let _version = self.i32v()?;
+        if cols < 0 || cols as usize > self.bytes.len() {
+            return Err(invalid(format!(
+                "tmq raw data has a schema of {cols} columns"
+            )));
+        }
+        (0..cols)
+            .map(|_| {
+                // type, flags, bytes, col id
+                self.i8()?;
+                self.i8()?;
+                self.i32v()?;
+                self.i16v()?;
+                self.cstr()
+            })
+            .collect()
+    }
+}
+
+/// Binary of `SRetrieveTableRspForTmq` with its column names and table name.
+struct BlockParts<'a> {
+    data: &'a [u8],
+    names: Vec<String>,
+    table: Option<String>,
+}
+
+/// Cursor over blocks of the payload of a TMQ data or meta-data message.
+pub(super) struct DataRsp<'a> {
+    decoder: Decoder<'a>,
+    remaining: usize,
+    with_tb_name: bool,
+    with_schema: bool,
+}
+
+impl<'a> DataRsp<'a> {
+    pub(super) fn new(payload: &'a [u8]) -> Result<Self> {
+        let mut decoder = Decoder {
+            bytes: payload,
+            pos: 0,
+        };
+        if payload
+            .first()
+            .map_or(false, |v| *v as i8 >= MQ_DATA_RSP_VERSION)
+        {
+            decoder.i8()?;
+            decoder.i32()?;
+        }
+        decoder.skip_offset()?;
+        decoder.skip_offset()?;
+        let num = decoder.i32()?;
+        if num < 0 {
+            return Err(invalid(format!("tmq raw data has {num} blocks")));
+        }
+        let (with_tb_name, with_schema) = if num > 0 {
+            (decoder.i8()? != 0, decoder.i8()? != 0)
+        } else {
+            (false, false)
+        };
+        Ok(Self {
+            decoder,
+            remaining: num as usize,
+            with_tb_name,
+            with_schema,
+        })
+    }
+
+    /// Move over the next block without parsing it, returns false if there's none.
+    pub(super) fn skip(&mut self) -> Result<bool> {
+        Ok(self.next_parts()?.is_some())
+    }
+
+    fn next_parts(&mut self) -> Result<Option<BlockParts<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        let data = self.decoder.binary()?;
+        let names = if self.with_schema {
+            self.decoder.schema_names()?
+        } else {
+            Vec::new()
+        };
+        let table = if self.with_tb_name {
+            Some(self.decoder.cstr()?)
+        } else {
+            None
+        };
+        Ok(Some(BlockParts { data, names, table }))
+    }
+
+    /// Parse the next block, with field names and table name if the message has them.
+    pub(super) fn next_block(&mut self) -> Result<Option<RawBlock>> {
+        let Some(BlockParts { data, names, table }) = self.next_parts()? else {
+            return Ok(None);
+        };
+        let mut block = parse_retrieved(data)?;
+        if !names.is_empty() {
+            if names.len() != block.ncols() {
+                return Err(invalid(format!(
+                    "tmq raw data has {} column names for a block of {} columns",
+                    names.len(),
+                    block.ncols()
+                )));
+            }
+            block.with_field_names(names);
+        }
+        if let Some(table) = table {
+            block.with_table_name(table);
+        }
+        Ok(Some(block))
+    }
+}
+
+/// Parse the raw block in `SRetrieveTableRspForTmq`.
+fn parse_retrieved(data: &[u8]) -> Result<RawBlock> {
+    let mut decoder = Decoder {
+        bytes: data,
+        pos: 0,
+    };
+    let version = decoder.i64()?;
+    if version != RETRIEVE_TABLE_RSP_TMQ_VERSION {
+        return Err(invalid(format!(
+            "tmq raw data block of version {version} is not supported"
+        )));
+    }
+    // rows in big-endian, the block has them as well.
+    decoder.i64()?;
+    let compressed = decoder.i8()?;
+    if compressed != 0 {
+        return Err(invalid("tmq raw data block is compressed"));
+    }
+    let precision = match decoder.i8()? {
+        precision @ 0..=2 => Precision::from_u8(precision as u8),
+        precision => {
+            return Err(invalid(format!(
+                "tmq raw data block has an invalid precision {precision}"
+            )))
+        }
+    };
+    debug_assert_eq!(decoder.pos, RETRIEVE_HEADER_LEN);
+    // The binary may be padded after the block, take the length in block header.
+    let raw = &data[RETRIEVE_HEADER_LEN..];
+    let len = raw
+        .get(4..8)
+        .map(|len| u32::from_le_bytes(len.try_into().unwrap()) as usize)
+        .filter(|len| *len <= raw.len())
+        .ok_or_else(|| invalid("tmq raw data has a truncated block"))?;
+    let raw = &raw[..len];
+    validate::check_raw_block(raw, false)?;
+    Ok(RawBlock::parse_from_raw_block(
+        Bytes::copy_from_slice(raw),
+        precision,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put_u32v(bytes: &mut Vec<u8>, mut v: u32) {
+        while v >= 0x80 {
+            bytes.push((v & 0x7f) as u8 | 0x80);
+            v >>= 7;
+        }
+        bytes.push(v as u8);
+    }
+
+    fn put_binary(bytes: &mut Vec<u8>, data: &[u8]) {
+        put_u32v(bytes, data.len() as u32);
+        bytes.extend(data);
+    }
+
+    fn put_cstr(bytes: &mut Vec<u8>, s: &str) {
+        put_u32v(bytes, s.len() as u32 + 1);
+        bytes.extend(s.as_bytes());
+        bytes.push(0);
+    }
+
+    /// Encode blocks as `tmq_get_raw` of a data message, with the version prefix if `versioned`.
+    fn encode_data_rsp(blocks: &[RawBlock], versioned: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        // req offset of log at version 10, rsp offset of snapshot data with a bigint key.
+        bytes.push(0x10 | TMQ_OFFSET_LOG as u8);
+        bytes.extend(10i64.to_le_bytes());
+        bytes.push(0x10 | TMQ_OFFSET_SNAPSHOT_DATA as u8);
+        bytes.extend(1i64.to_le_bytes());
+        bytes.extend(2i64.to_le_bytes());
+        bytes.push(Ty::BigInt as u8);
+        bytes.extend(3i64.to_le_bytes());
+
+        bytes.extend((blocks.len() as i32).to_le_bytes());
+        bytes.extend([1, 1]);
+        for block in blocks {
+            let raw = block.encode_nchar();
+            let mut data = Vec::new();
+            data.extend(RETRIEVE_TABLE_RSP_TMQ_VERSION.to_le_bytes());
+            data.extend((block.nrows() as i64).to_be_bytes());
+            data.push(0);
+            data.push(block.precision().as_u8());
+            data.extend(&raw);
+            // padding of the unpacked struct.
+            data.extend([0; 6]);
+            put_binary(&mut bytes, &data);
+
+            // nCols and version in zigzag
+            put_u32v(&mut bytes, block.ncols() as u32 * 2);
+            put_u32v(&mut bytes, 2);
+            for (i, (field, schema)) in block.fields().iter().zip(block.schemas()).enumerate() {
+                bytes.push(field.ty() as u8);
+                bytes.push(0);
+                put_u32v(&mut bytes, schema.len() * 2);
+                put_u32v(&mut bytes, (i as u32 + 1) * 2);
+                put_cstr(&mut bytes, field.name());
+            }
+            put_cstr(&mut bytes, block.table_name().unwrap_or_default());
+        }
+        // sleep time
+        bytes.extend(0i64.to_le_bytes());
+
+        if versioned {
+            let mut prefixed = vec![MQ_DATA_RSP_VERSION as u8];
+            prefixed.extend((bytes.len() as i32 + 5).to_le_bytes());
+            prefixed.extend(bytes);
+            prefixed
+        } else {
+            bytes
+        }
+    }
+
+    fn raw_message(raw_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend((payload.len() as u32).to_le_bytes());
+        bytes.extend(raw_type.to_le_bytes());
+        bytes.extend(payload);
+        bytes
+    }
+
+    fn blocks() -> Vec<RawBlock> {
+        use crate::common::views::views_to_raw_block;
+        use crate::common::ColumnView;
+
+        (0..3)
+            .map(|i| {
+                let views = [
+                    ColumnView::from_millis_timestamp(vec![i, i + 1]),
+                    ColumnView::from_ints(vec![Some(i as i32), None]),
+                    ColumnView::from_varchar::<String, _, _, _>(vec![Some(format!("v{i}")), None]),
+                    ColumnView::from_nchar::<String, _, _, _>(vec![None, Some(format!("涛思{i}"))]),
+                ];
+                let mut block = RawBlock::parse_from_raw_block(
+                    views_to_raw_block(&views),
+                    Precision::Microsecond,
+                );
+                block.with_field_names(["ts", "v", "s", "n"]);
+                block.with_table_name(format!("t{i}"));
+                block
+            })
+            .collect()
+    }
+
+    #[test]
+    fn raw_blocks_round_trip() {
+        use crate::common::{RawData, RAW_TYPE_DATA, RAW_TYPE_META_DATA};
+        use crate::tmq::{IsData, MessageSet};
+
+        let expected = blocks();
+        for versioned in [false, true] {
+            let bytes = raw_message(RAW_TYPE_DATA, &encode_data_rsp(&expected, versioned));
+            let mut message = MessageSet::from_raw_bytes(bytes.clone()).unwrap();
+            assert_eq!(message.raw_bytes().as_deref(), Some(bytes.as_slice()));
+
+            let data = message.data().unwrap();
+            let mut fetched = Vec::new();
+            while let Some(block) = data.fetch_raw_block().unwrap() {
+                fetched.push(block);
+            }
+            assert!(data.fetch_raw_block().unwrap().is_none());
+            assert_eq!(fetched.len(), expected.len());
+            for (block, expected) in fetched.iter().zip(&expected) {
+                assert_eq!(block.to_values(), expected.to_values());
+                assert_eq!(block.field_names(), expected.field_names());
+                assert_eq!(block.table_name(), expected.table_name());
+                assert_eq!(block.precision(), expected.precision());
+            }
+
+            // a clone fetches from the first block again.
+            let raw: RawData = data.as_raw_data().unwrap();
+            assert_eq!(
+                raw.fetch_raw_block().unwrap().unwrap().table_name(),
+                Some("t0")
+            );
+            assert_eq!(raw.blocks().unwrap().len(), expected.len());
+        }
+
+        // meta-data message has tables to create after the blocks.
+        let mut payload = encode_data_rsp(&expected, true);
+        payload.extend(0i32.to_le_bytes());
+        let message =
+            MessageSet::from_raw_bytes(raw_message(RAW_TYPE_META_DATA, &payload)).unwrap();
+        let blocks = message.into_data().unwrap().blocks().unwrap();
+        assert_eq!(blocks.len(), expected.len());
+        assert_eq!(blocks[2].to_values(), expected[2].to_values());
+
+        // meta has no blocks.
+        let message = MessageSet::from_raw_bytes(raw_message(1, b"meta")).unwrap();
+        assert!(message.meta().unwrap().blocks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated_payload() {
+        let payload = encode_data_rsp(&[], false);
+        assert!(DataRsp::new(&payload)
+            .unwrap()
+            .next_block()
+            .unwrap()
+            .is_none());
+        for len in 0..payload.len() - 12 {
+            assert!(DataRsp::new(&payload[..len]).is_err(), "truncated at {len}");
+        }
+
+        let payload = encode_data_rsp(&blocks(), false);
+        for len in 0..payload.len() - 8 {
+            let mut rsp = match DataRsp::new(&payload[..len]) {
+                Ok(rsp) => rsp,
+                Err(_) => continue,
+            };
+            let blocks: Result<Vec<_>> =
+                std::iter::from_fn(|| rsp.next_block().transpose()).collect();
+            assert!(blocks.is_err(), "truncated at {len}");
+        }
+    }
+}
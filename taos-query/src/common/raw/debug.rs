@@ -0,0 +1,180 @@
+use std::fmt::{self, Debug};
+
+use crate::common::BorrowedValue;
+
+use super::{ColumnView, RawBlock};
+
+/// Rows printed from the beginning of a block in debug summary.
+pub const DEBUG_HEAD_ROWS: usize = 5;
+/// Rows printed from the end of a block in debug summary.
+pub const DEBUG_TAIL_ROWS: usize = 5;
+/// Max columns printed in debug summary.
+pub const DEBUG_MAX_COLUMNS: usize = 32;
+
+/// A [Debug] wrapper of [RawBlock] with configurable output bounds.
+///
+/// `{:?}` of [RawBlock] uses [DebugBlock] with the default bounds, use [RawBlock::debug_full]
+/// to print all the rows and columns.
+///
+/// ```rust
+/// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+/// let bytes = views_to_raw_block(&[ColumnView::from_ints((0..100).collect::<Vec<i32>>())]);
+/// let block = RawBlock::parse_from_raw_block(bytes, Precision::Millisecond);
+/// let summary = format!("{:?}", block.debug_full().with_head_rows(2).with_tail_rows(1));
+/// assert!(summary.contains("head: [[0], [1]], tail: [[99]]"));
+/// ```
+pub struct DebugBlock<'a> {
+    raw: &'a RawBlock,
+    head: usize,
+    tail: usize,
+    max_columns: usize,
+}
+
+impl<'a> DebugBlock<'a> {
+    pub(super) fn summary(raw: &'a RawBlock) -> Self {
+        Self {
+            raw,
+            head: DEBUG_HEAD_ROWS,
+            tail: DEBUG_TAIL_ROWS,
+            max_columns: DEBUG_MAX_COLUMNS,
+        }
+    }
+
+    pub(super) fn full(raw: &'a RawBlock) -> Self {
+        Self {
+            raw,
+            head: usize::MAX,
+            tail: 0,
+            max_columns: usize::MAX,
+        }
+    }
+
+    /// Set rows to print from the beginning of the block.
+    pub fn with_head_rows(mut self, rows: usize) -> Self {
+        self.head = rows;
+        self
+    }
+
+    /// Set rows to print from the end of the block.
+    pub fn with_tail_rows(mut self, rows: usize) -> Self {
+        self.tail = rows;
+        self
+    }
+
+    /// Set max columns to print, the rest columns are omitted.
+    pub fn with_max_columns(mut self, cols: usize) -> Self {
+        self.max_columns = cols;
+        self
+    }
+
+    fn rows(&self, rows: std::ops::Range<usize>) -> Vec<DebugRow<'_>> {
+        let cols = self.raw.ncols().min(self.max_columns);
+        rows.map(|row| DebugRow {
+            raw: self.raw,
+            row,
+            cols,
+        })
+        .collect()
+    }
+}
+
+impl<'a> Debug for DebugBlock<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let raw = self.raw;
+        let (nrows, ncols) = (raw.nrows(), raw.ncols());
+        let cols = ncols.min(self.max_columns);
+
+        let (fields, nulls): (Vec<_>, Vec<_>) = raw
+            .columns()
+            .take(cols)
+            .map(|(field, view)| {
+                let name = match field.name() {
+                    "" => "?",
+                    name => name,
+                };
+                let field = match (field.ty(), field.bytes()) {
+                    (ty, len) if ty.is_var_type() && len > 0 => {
+                        format!("{name} {}({len})", ty.name())
+                    }
+                    (ty, _) => format!("{name} {}", ty.name()),
+                };
+                (field, null_count(view))
+            })
+            .unzip();
+
+        let mut s = f.debug_struct("RawBlock");
+        s.field("shape", &(nrows, ncols))
+            .field("precision", &raw.precision)
+            .field("table", &raw.table)
+            .field("group_id", &raw.group_id)
+            .field("fields", &fields)
+            .field("nulls", &nulls);
+        if self.head.saturating_add(self.tail) >= nrows {
+            s.field("rows", &self.rows(0..nrows));
+        } else {
+            s.field("head", &self.rows(0..self.head))
+                .field("tail", &self.rows(nrows - self.tail..nrows));
+        }
+        if cols < ncols {
+            s.field("omitted_columns", &(ncols - cols));
+        }
+        s.finish()
+    }
+}
+
+struct DebugRow<'a> {
+    raw: &'a RawBlock,
+    row: usize,
+    cols: usize,
+}
+
+impl<'a> Debug for DebugRow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                (0..self.cols)
+                    .map(|col| DebugValue(self.raw.view(col).get(self.row).expect("row in range"))),
+            )
+            .finish()
+    }
+}
+
+/// Print values in a compact form, strings are quoted.
+struct DebugValue<'a>(BorrowedValue<'a>);
+
+impl<'a> Debug for DebugValue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            BorrowedValue::VarChar(v) => Debug::fmt(v, f),
+            BorrowedValue::NChar(v) => Debug::fmt(v, f),
+            v => write!(f, "{v}"),
+        }
+    }
+}
+
+fn null_count(view: &ColumnView) -> usize {
+    (0..view.len())
+        .filter(|&row| unsafe { view.is_null_unchecked(row) })
+        .count()
+}
+
+/// Debug format a large column view as a bounded summary.
+pub(super) fn fmt_column_summary(
+    name: &str,
+    view: &ColumnView,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    let len = view.len();
+    let head: Vec<_> = (0..DEBUG_HEAD_ROWS)
+        .map(|row| DebugValue(view.get(row).expect("row in range")))
+        .collect();
+    let tail: Vec<_> = (len - DEBUG_TAIL_ROWS..len)
+        .map(|row| DebugValue(view.get(row).expect("row in range")))
+        .collect();
+    f.debug_struct(name)
+        .field("len", &len)
+        .field("nulls", &null_count(view))
+        .field("head", &head)
+        .field("tail", &tail)
+        .finish()
+}
@@ -0,0 +1,174 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{ColumnView, RawBlock};
+
+/// Dictionary encoded string column, the layout is the same as Arrow dictionary arrays.
+///
+/// `keys[row]` is the index of the value in `values`, or `None` for NULL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictionaryColumn {
+    /// Column index in the block.
+    pub index: usize,
+    /// Column name, empty when the block has no field names.
+    pub name: String,
+    /// Distinct values, in the order of their first appearance.
+    pub values: Vec<String>,
+    /// Per-row index into `values`.
+    pub keys: Vec<Option<u32>>,
+}
+
+impl DictionaryColumn {
+    /// Number of distinct non-null values.
+    pub fn cardinality(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+/// Build dictionary values and keys from string rows in one pass.
+pub(crate) fn dictionary_from_iter<'a>(
+    iter: impl ExactSizeIterator<Item = Option<&'a str>>,
+) -> (Vec<String>, Vec<Option<u32>>) {
+    let mut map: HashMap<&str, u32> = HashMap::new();
+    let mut values = Vec::new();
+    let mut keys = Vec::with_capacity(iter.len());
+    for value in iter {
+        keys.push(value.map(|s| {
+            *map.entry(s).or_insert_with(|| {
+                values.push(s.to_string());
+                (values.len() - 1) as u32
+            })
+        }));
+    }
+    (values, keys)
+}
+
+impl ColumnView {
+    /// Count distinct non-null values in the column, stop early once more than `limit` distinct
+    /// values are seen.
+    ///
+    /// The returned value is exact when it's not greater than `limit`, otherwise it's `limit + 1`.
+    pub fn cardinality_estimate(&self, limit: usize) -> usize {
+        let mut seen: HashSet<&[u8]> = HashSet::new();
+        for row in 0..self.len() {
+            let (_, len, ptr) = unsafe { self.get_raw_value_unchecked(row) };
+            if ptr.is_null() {
+                continue;
+            }
+            seen.insert(unsafe { std::slice::from_raw_parts(ptr as *const u8, len as usize) });
+            if seen.len() > limit {
+                break;
+            }
+        }
+        seen.len()
+    }
+
+    /// Dictionary encode a varchar or nchar column, returns `None` for other types.
+    pub fn to_dictionary(&self) -> Option<(Vec<String>, Vec<Option<u32>>)> {
+        match self {
+            ColumnView::VarChar(view) => Some(view.to_dictionary()),
+            ColumnView::NChar(view) => Some(view.to_dictionary()),
+            _ => None,
+        }
+    }
+}
+
+impl RawBlock {
+    /// Dictionary encode the varchar/nchar columns at `columns` index.
+    ///
+    /// Columns out of range or not in string types are skipped.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let status = ColumnView::from_varchar::<&str, _, _, _>(vec![Some("on"), None, Some("off"), Some("on")]);
+    /// let bytes = views_to_raw_block(&[ColumnView::from_ints(vec![1, 2, 3, 4]), status]);
+    /// let block = RawBlock::parse_from_raw_block(bytes, Precision::Millisecond);
+    /// let encoded = block.dictionary_encode([0, 1]);
+    /// assert_eq!(encoded.len(), 1);
+    /// assert_eq!(encoded[0].values, ["on", "off"]);
+    /// assert_eq!(encoded[0].keys, [Some(0), None, Some(1), Some(0)]);
+    /// ```
+    pub fn dictionary_encode(
+        &self,
+        columns: impl IntoIterator<Item = usize>,
+    ) -> Vec<DictionaryColumn> {
+        columns
+            .into_iter()
+            .filter_map(|index| {
+                let (field, view) = self.columns().nth(index)?;
+                let (values, keys) = view.to_dictionary()?;
+                Some(DictionaryColumn {
+                    index,
+                    name: field.name().to_string(),
+                    values,
+                    keys,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "arrow")]
+mod arrow {
+    use std::sync::Arc;
+
+    use arrow_array::{types::UInt32Type, DictionaryArray, StringArray, UInt32Array};
+
+    use super::DictionaryColumn;
+
+    impl From<DictionaryColumn> for DictionaryArray<UInt32Type> {
+        fn from(column: DictionaryColumn) -> Self {
+            let keys = UInt32Array::from(column.keys);
+            let values = StringArray::from(column.values);
+            DictionaryArray::try_new(keys, Arc::new(values)).expect("keys are in range of values")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+
+    #[test]
+    fn dictionary_encode() {
+        let rows: Vec<_> = (0..100)
+            .map(|i| match i % 4 {
+                0 => None,
+                1 => Some("online"),
+                2 => Some("offline"),
+                _ => Some("unknown"),
+            })
+            .collect();
+        let status = ColumnView::from_varchar::<&str, _, _, _>(rows.clone());
+        let (values, keys) = status.to_dictionary().unwrap();
+        assert_eq!(values, ["online", "offline", "unknown"]);
+        for (row, key) in rows.iter().zip(&keys) {
+            assert_eq!(*row, key.map(|k| values[k as usize].as_str()));
+        }
+
+        assert_eq!(status.cardinality_estimate(10), 3);
+        assert_eq!(status.cardinality_estimate(1), 2);
+        let ints = ColumnView::from_ints((0..100).collect::<Vec<i32>>());
+        assert_eq!(ints.cardinality_estimate(1000), 100);
+        assert_eq!(ints.cardinality_estimate(9), 10);
+        assert!(ints.to_dictionary().is_none());
+
+        let nchar = ColumnView::from_nchar::<&str, _, _, _>(rows);
+        assert_eq!(nchar.to_dictionary(), status.to_dictionary());
+
+        let bytes = views_to_raw_block(&[ints, status]);
+        let block = RawBlock::parse_from_raw_block(bytes, Precision::Millisecond);
+        let encoded = block.dictionary_encode(0..3);
+        assert_eq!(encoded.len(), 1);
+        assert_eq!(encoded[0].index, 1);
+        assert_eq!(encoded[0].values, values);
+        assert_eq!(encoded[0].cardinality(), 3);
+    }
+}
@@ -0,0 +1,522 @@
+use std::borrow::Cow;
+use std::io::Write;
+
+use chrono::{SecondsFormat, TimeZone, Utc};
+
+use crate::common::{BorrowedValue, Precision, Timestamp};
+
+#[cfg(feature = "chrono-tz")]
+use super::views::{views_to_raw_block_with_schemas, ColSchema, ColumnView};
+use super::RawBlock;
+#[cfg(feature = "chrono-tz")]
+use crate::common::Ty;
+
+/// How timestamps are written by [RawBlock::write_csv] and [RawBlock::write_ndjson].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC3339 in UTC, with fractional seconds of the precision, eg.
+    /// `2024-01-01T00:00:00.000001Z` in microseconds.
+    #[default]
+    Rfc3339,
+    /// Integers since the unix epoch in the precision.
+    Raw,
+}
+
+/// Options of [RawBlock::write_csv].
+///
+/// ```rust
+/// # use taos_query::common::{CsvOptions, TimestampFormat};
+/// let options = CsvOptions::new()
+///     .header(false)
+///     .null("NULL")
+///     .timestamp(TimestampFormat::Raw)
+///     .delimiter(b'\t');
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    header: bool,
+    null: String,
+    timestamp: TimestampFormat,
+    delimiter: u8,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            header: true,
+            null: String::new(),
+            timestamp: TimestampFormat::Rfc3339,
+            delimiter: b',',
+        }
+    }
+}
+
+impl CsvOptions {
+    /// A header of field names, NULL as empty fields, RFC3339 timestamps and commas.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write field names as the first line or not.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Text of NULL values, strings equal to it are quoted so they are still distinct.
+    pub fn null(mut self, null: impl Into<String>) -> Self {
+        self.null = null.into();
+        self
+    }
+
+    pub fn timestamp(mut self, format: TimestampFormat) -> Self {
+        self.timestamp = format;
+        self
+    }
+
+    /// An ASCII delimiter other than a quote, `\r` or `\n`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+}
+
+impl RawBlock {
+    /// Write rows of the block as CSV by RFC 4180, with `\n` line endings.
+    ///
+    /// Fields containing the delimiter, quotes or line breaks are quoted, quotes in them are
+    /// doubled. Strings are written as is in UTF-8, binary values in hex like `\x0a0b`.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, CsvOptions, Precision, RawBlock};
+    /// let views = [
+    ///     ColumnView::from_ints(vec![Some(1), None]),
+    ///     ColumnView::from_varchar::<&str, _, _, _>(vec![Some("a,b"), Some("say \"hi\"")]),
+    /// ];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["v", "s"]);
+    /// let mut csv = Vec::new();
+    /// block.write_csv(&mut csv, &CsvOptions::new()).unwrap();
+    /// assert_eq!(csv, b"v,s\n1,\"a,b\"\n,\"say \"\"hi\"\"\"\n");
+    /// ```
+    pub fn write_csv<W: Write>(&self, mut wtr: W, options: &CsvOptions) -> std::io::Result<()> {
+        let names = self.columns().map(|(field, _)| field.name());
+        write_csv_header(&mut wtr, names, options)?;
+        self.write_csv_rows(wtr, options)
+    }
+
+    /// Write rows of the block as CSV without the header.
+    pub(crate) fn write_csv_rows<W: Write>(
+        &self,
+        mut wtr: W,
+        options: &CsvOptions,
+    ) -> std::io::Result<()> {
+        let precision = self.precision();
+        for row in 0..self.nrows() {
+            for col in 0..self.ncols() {
+                if col > 0 {
+                    wtr.write_all(&[options.delimiter])?;
+                }
+                let value = unsafe { self.get_ref_unchecked(row, col) };
+                match to_text(&value, options.timestamp, precision) {
+                    Some(text) => write_csv_field(&mut wtr, &text, options)?,
+                    None => wtr.write_all(options.null.as_bytes())?,
+                }
+            }
+            wtr.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Write rows of the block as newline-delimited JSON, an object of field names per line.
+    ///
+    /// NULL values and non-finite floats are `null`, json columns are embedded as JSON values,
+    /// timestamps are integers or strings by `timestamp`.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock, TimestampFormat};
+    /// let views = [
+    ///     ColumnView::from_millis_timestamp(vec![0]),
+    ///     ColumnView::from_ints(vec![None::<i32>]),
+    /// ];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "v"]);
+    /// let mut json = Vec::new();
+    /// block.write_ndjson(&mut json, TimestampFormat::Rfc3339).unwrap();
+    /// assert_eq!(json, b"{\"ts\":\"1970-01-01T00:00:00.000Z\",\"v\":null}\n");
+    /// ```
+    pub fn write_ndjson<W: Write>(
+        &self,
+        mut wtr: W,
+        timestamp: TimestampFormat,
+    ) -> std::io::Result<()> {
+        let precision = self.precision();
+        let names: Vec<_> = self.columns().map(|(field, _)| field.name()).collect();
+        for row in 0..self.nrows() {
+            wtr.write_all(b"{")?;
+            for (col, name) in names.iter().enumerate() {
+                if col > 0 {
+                    wtr.write_all(b",")?;
+                }
+                serde_json::to_writer(&mut wtr, name)?;
+                wtr.write_all(b":")?;
+                let value = unsafe { self.get_ref_unchecked(row, col) };
+                write_json_value(&mut wtr, &value, timestamp, precision)?;
+            }
+            wtr.write_all(b"}\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "chrono-tz")]
+impl RawBlock {
+    /// A derived block with timestamp columns replaced by varchar columns of them formatted in
+    /// timezone `tz`, eg. to export by [RawBlock::write_csv] in the timezone of a tenant. Other
+    /// columns, names and the precision are kept, their views are written to the new block as is.
+    ///
+    /// Timestamps are instants so each one has exactly one local time, offsets around DST
+    /// transitions follow the rules of `chrono-tz`: local times skipped by a transition are never
+    /// rendered, and the repeated hour is told apart by the offset in RFC3339.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock, TimestampFormat, Ty};
+    /// let views = [
+    ///     ColumnView::from_millis_timestamp(vec![Some(1_710_054_000_000), None]),
+    ///     ColumnView::from_ints(vec![Some(1), None]),
+    /// ];
+    /// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// let rendered = block.render_timestamps(chrono_tz::America::New_York, TimestampFormat::Rfc3339);
+    /// assert_eq!(rendered.schemas()[0].ty(), Ty::VarChar);
+    /// assert_eq!(
+    ///     rendered.to_values()[0][0].to_string().unwrap(),
+    ///     "2024-03-10T03:00:00.000-04:00"
+    /// );
+    /// ```
+    pub fn render_timestamps(&self, tz: chrono_tz::Tz, format: TimestampFormat) -> RawBlock {
+        let mut schemas = self.schemas().to_vec();
+        let rendered: Vec<_> = self
+            .column_views()
+            .iter()
+            .zip(&mut schemas)
+            .map(|(view, schema)| {
+                let ColumnView::Timestamp(view) = view else {
+                    return None;
+                };
+                let texts = view.to_formatted_in_tz(tz, format);
+                let len = texts.iter().flatten().map(String::len).max().unwrap_or(0);
+                *schema = ColSchema::new(Ty::VarChar, len as u32);
+                Some(ColumnView::from_varchar::<String, _, _, _>(texts))
+            })
+            .collect();
+        // other columns are written from their views directly.
+        let views: Vec<&ColumnView> = rendered
+            .iter()
+            .zip(self.column_views())
+            .map(|(rendered, view)| rendered.as_ref().unwrap_or(view))
+            .collect();
+        let bytes = views_to_raw_block_with_schemas(&views, &schemas);
+        let mut block = RawBlock::parse_from_raw_block(bytes, self.precision());
+        self.copy_names_to(&mut block);
+        block
+    }
+}
+
+/// Write the CSV header of field `names`, nothing if it's disabled in `options`.
+pub(crate) fn write_csv_header<'a, W: Write>(
+    mut wtr: W,
+    names: impl IntoIterator<Item = &'a str>,
+    options: &CsvOptions,
+) -> std::io::Result<()> {
+    if !options.header {
+        return Ok(());
+    }
+    for (col, name) in names.into_iter().enumerate() {
+        if col > 0 {
+            wtr.write_all(&[options.delimiter])?;
+        }
+        write_csv_field(&mut wtr, name, options)?;
+    }
+    wtr.write_all(b"\n")
+}
+
+fn write_csv_field<W: Write>(mut wtr: W, text: &str, options: &CsvOptions) -> std::io::Result<()> {
+    let quoted = text == options.null
+        || text
+            .bytes()
+            .any(|b| b == options.delimiter || matches!(b, b'"' | b'\r' | b'\n'));
+    if !quoted {
+        return wtr.write_all(text.as_bytes());
+    }
+    wtr.write_all(b"\"")?;
+    wtr.write_all(text.replace('"', "\"\"").as_bytes())?;
+    wtr.write_all(b"\"")
+}
+
+/// Text of a value in CSV, `None` if it's NULL.
+fn to_text<'a>(
+    value: &'a BorrowedValue,
+    timestamp: TimestampFormat,
+    precision: Precision,
+) -> Option<Cow<'a, str>> {
+    use BorrowedValue::*;
+    Some(match value {
+        Null(_) => return None,
+        Bool(v) => v.to_string().into(),
+        TinyInt(v) => v.to_string().into(),
+        SmallInt(v) => v.to_string().into(),
+        Int(v) => v.to_string().into(),
+        BigInt(v) => v.to_string().into(),
+        UTinyInt(v) => v.to_string().into(),
+        USmallInt(v) => v.to_string().into(),
+        UInt(v) => v.to_string().into(),
+        UBigInt(v) => v.to_string().into(),
+        Float(v) => v.to_string().into(),
+        Double(v) => v.to_string().into(),
+        Decimal(v) => v.to_string().into(),
+        Timestamp(ts) => format_timestamp(*ts, timestamp, precision).into(),
+        VarChar(v) => Cow::Borrowed(*v),
+        NChar(v) => Cow::Borrowed(v.as_ref()),
+        Json(v) => String::from_utf8_lossy(v),
+        VarBinary(v) | Geometry(v) => to_hex(v).into(),
+        Blob(v) | MediumBlob(v) => to_hex(v).into(),
+    })
+}
+
+fn write_json_value<W: Write>(
+    mut wtr: W,
+    value: &BorrowedValue,
+    timestamp: TimestampFormat,
+    precision: Precision,
+) -> std::io::Result<()> {
+    use BorrowedValue::*;
+    match value {
+        Null(_) => wtr.write_all(b"null"),
+        Float(v) if !v.is_finite() => wtr.write_all(b"null"),
+        Double(v) if !v.is_finite() => wtr.write_all(b"null"),
+        Bool(_) | TinyInt(_) | SmallInt(_) | Int(_) | BigInt(_) | UTinyInt(_) | USmallInt(_)
+        | UInt(_) | UBigInt(_) | Float(_) | Double(_) => {
+            let text = to_text(value, timestamp, precision).unwrap_or_default();
+            wtr.write_all(text.as_bytes())
+        }
+        Timestamp(ts) => match timestamp {
+            TimestampFormat::Raw => write!(wtr, "{}", ts.as_raw_i64()),
+            TimestampFormat::Rfc3339 => {
+                serde_json::to_writer(wtr, &format_timestamp(*ts, timestamp, precision))
+                    .map_err(Into::into)
+            }
+        },
+        Json(v) => wtr.write_all(v),
+        _ => {
+            let text = to_text(value, timestamp, precision).unwrap_or_default();
+            serde_json::to_writer(wtr, &text).map_err(Into::into)
+        }
+    }
+}
+
+/// Fractional seconds of RFC3339 timestamps are of `precision`.
+fn format_timestamp(ts: Timestamp, format: TimestampFormat, precision: Precision) -> String {
+    format_timestamp_in(ts, format, precision, &Utc)
+}
+
+/// RFC3339 timestamps are in the local time of `tz` with its offset at the instant, `Z` for UTC.
+pub(crate) fn format_timestamp_in<Tz: TimeZone>(
+    ts: Timestamp,
+    format: TimestampFormat,
+    precision: Precision,
+    tz: &Tz,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    match format {
+        TimestampFormat::Raw => ts.as_raw_i64().to_string(),
+        TimestampFormat::Rfc3339 => {
+            let secs = match precision {
+                Precision::Millisecond => SecondsFormat::Millis,
+                Precision::Microsecond => SecondsFormat::Micros,
+                Precision::Nanosecond => SecondsFormat::Nanos,
+            };
+            tz.from_utc_datetime(&ts.to_naive_datetime())
+                .to_rfc3339_opts(secs, true)
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("\\x");
+    for b in bytes {
+        let _ = write!(hex, "{b:02x}");
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::views::views_to_raw_block;
+    use crate::common::ColumnView;
+
+    use super::*;
+
+    /// A block of every column type, the second row is all NULLs.
+    fn block() -> RawBlock {
+        let views = vec![
+            ColumnView::from_micros_timestamp(vec![Some(1_704_067_200_000_001), None, Some(-1)]),
+            ColumnView::from_bools(vec![Some(true), None, Some(false)]),
+            ColumnView::from_tiny_ints(vec![Some(i8::MIN), None, Some(i8::MAX)]),
+            ColumnView::from_small_ints(vec![Some(i16::MIN), None, Some(i16::MAX)]),
+            ColumnView::from_ints(vec![Some(i32::MIN), None, Some(i32::MAX)]),
+            ColumnView::from_big_ints(vec![Some(i64::MIN), None, Some(i64::MAX)]),
+            ColumnView::from_unsigned_tiny_ints(vec![Some(0), None, Some(u8::MAX)]),
+            ColumnView::from_unsigned_small_ints(vec![Some(0), None, Some(u16::MAX)]),
+            ColumnView::from_unsigned_ints(vec![Some(0), None, Some(u32::MAX)]),
+            ColumnView::from_unsigned_big_ints(vec![Some(0), None, Some(u64::MAX)]),
+            ColumnView::from_floats(vec![Some(1.5), None, Some(f32::NAN)]),
+            ColumnView::from_doubles(vec![Some(-2.25e10), None, Some(0.1)]),
+            ColumnView::from_varchar::<&str, _, _, _>(vec![
+                Some("a,b"),
+                None,
+                Some("line\nbreak \"quoted\""),
+            ]),
+            ColumnView::from_nchar::<&str, _, _, _>(vec![Some("涛思数据"), None, Some("")]),
+            ColumnView::from_json::<&str, _, _, _>(vec![Some(r#"{"k":"v,1"}"#), None, Some("{}")]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        block.with_field_names([
+            "ts",
+            "c_bool",
+            "c_i8",
+            "c_i16",
+            "c_i32",
+            "c_i64",
+            "c_u8",
+            "c_u16",
+            "c_u32",
+            "c_u64",
+            "c_f32",
+            "c_f64",
+            "c_varchar",
+            "c_nchar",
+            "c_json",
+        ]);
+        block
+    }
+
+    #[test]
+    fn csv_golden() {
+        let mut csv = Vec::new();
+        block().write_csv(&mut csv, &CsvOptions::new()).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv, include_str!("../../../tests/export.csv"));
+    }
+
+    #[test]
+    fn ndjson_golden() {
+        let mut json = Vec::new();
+        block()
+            .write_ndjson(&mut json, TimestampFormat::Rfc3339)
+            .unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json, include_str!("../../../tests/export.ndjson"));
+        for line in json.lines() {
+            let row: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(line).unwrap();
+            assert_eq!(row.len(), 15);
+        }
+    }
+
+    #[test]
+    fn csv_options() {
+        let views = [
+            ColumnView::from_nanos_timestamp(vec![Some(1_000_000_001), None]),
+            ColumnView::from_varchar::<&str, _, _, _>(vec![Some("NULL"), Some("a\tb")]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Nanosecond);
+        block.with_field_names(["ts", "s"]);
+
+        let options = CsvOptions::new()
+            .header(false)
+            .null("NULL")
+            .timestamp(TimestampFormat::Raw)
+            .delimiter(b'\t');
+        let mut csv = Vec::new();
+        block.write_csv(&mut csv, &options).unwrap();
+        assert_eq!(csv, b"1000000001\t\"NULL\"\nNULL\t\"a\tb\"\n");
+
+        let mut csv = Vec::new();
+        block.write_csv(&mut csv, &CsvOptions::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "ts,s\n1970-01-01T00:00:01.000000001Z,NULL\n,a\tb\n"
+        );
+
+        let mut json = Vec::new();
+        block.write_ndjson(&mut json, TimestampFormat::Raw).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "{\"ts\":1000000001,\"s\":\"NULL\"}\n{\"ts\":null,\"s\":\"a\\tb\"}\n"
+        );
+    }
+
+    #[cfg(feature = "chrono-tz")]
+    #[test]
+    fn render_timestamps_around_dst() {
+        use crate::common::views::TimestampView;
+
+        let ny = chrono_tz::America::New_York;
+        // spring forward at 2024-03-10 07:00:00Z, fall back at 2024-11-03 06:00:00Z.
+        let millis = vec![
+            Some(1_710_053_999_999),
+            Some(1_710_054_000_000),
+            None,
+            Some(1_730_611_800_000),
+            Some(1_730_615_400_000),
+        ];
+        let views = [
+            ColumnView::from_millis_timestamp(millis.clone()),
+            ColumnView::from_ints(vec![Some(1), Some(2), None, Some(4), Some(5)]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        block.with_field_names(["ts", "v"]);
+
+        let expected = [
+            Some("2024-03-10T01:59:59.999-05:00"),
+            Some("2024-03-10T03:00:00.000-04:00"),
+            None,
+            Some("2024-11-03T01:30:00.000-04:00"),
+            Some("2024-11-03T01:30:00.000-05:00"),
+        ];
+        let texts =
+            TimestampView::from_millis(millis).to_formatted_in_tz(ny, TimestampFormat::Rfc3339);
+        assert_eq!(
+            texts.iter().map(Option::as_deref).collect::<Vec<_>>(),
+            expected
+        );
+
+        let rendered = block.render_timestamps(ny, TimestampFormat::Rfc3339);
+        assert_eq!(rendered.field_names(), ["ts", "v"]);
+        assert_eq!(rendered.schemas()[0].ty(), Ty::VarChar);
+        assert_eq!(rendered.schemas()[1].ty(), Ty::Int);
+        assert_eq!(rendered.nrows(), 5);
+        let mut csv = Vec::new();
+        rendered.write_csv(&mut csv, &CsvOptions::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "ts,v\n\
+             2024-03-10T01:59:59.999-05:00,1\n\
+             2024-03-10T03:00:00.000-04:00,2\n\
+             ,\n\
+             2024-11-03T01:30:00.000-04:00,4\n\
+             2024-11-03T01:30:00.000-05:00,5\n"
+        );
+
+        // raw timestamps are kept as the integers.
+        let raw = block.render_timestamps(ny, TimestampFormat::Raw);
+        assert_eq!(raw.to_values()[1][0].to_string().unwrap(), "1710054000000");
+    }
+}
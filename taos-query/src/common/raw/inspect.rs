@@ -0,0 +1,244 @@
+use std::fmt::{self, Display};
+
+use crate::common::{Ty, Value};
+
+use super::views::Version;
+use super::{ColumnView, RawBlock};
+
+/// Bytes of a cell as they are in a block with the value decoded from them, see
+/// [RawBlock::inspect].
+///
+/// [Display] is a one-line dump to paste into issues, eg.
+/// `(0, 2) BINARY @0+2 02006162 = "ab"`: the cell, the type, offset and length of the value in
+/// the column data, the raw bytes in hex, and the value. Set a precision to elide bytes after
+/// it, eg. `{:.16}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellInspection {
+    pub row: usize,
+    pub col: usize,
+    pub ty: Ty,
+    pub null: bool,
+    /// Bytes of the cell, with the `u16` length prefix for variable types. Slots of NULL fixed
+    /// types are kept as is, NULL variable types have no bytes.
+    ///
+    /// NChar of raw blocks is UCS-4 until the column is decoded to UTF-8 in place by reading any
+    /// value of it, the bytes are the ones in the block at the time of inspection.
+    pub raw_bytes: Vec<u8>,
+    /// Value of the cell, `None` if it's NULL.
+    pub decoded: Option<Value>,
+    /// Offset of the cell in the data of the column, after the null bitmap of fixed types and
+    /// offsets of variable types. `None` if there's no data of the cell.
+    pub data_offset: Option<usize>,
+    /// Length of the value in bytes, without the length prefix of variable types.
+    pub length: usize,
+}
+
+impl RawBlock {
+    /// Bytes and value of the cell at `(row, col)`, `None` if it's out of the block. It's for
+    /// debugging values that look wrong, eg. to check the bytes received from the server.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = [ColumnView::from_ints(vec![Some(1), None])];
+    /// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// assert_eq!(block.inspect(0, 0).unwrap().to_string(), "(0, 0) INT @0+4 01000000 = 1");
+    /// assert_eq!(block.inspect(1, 0).unwrap().to_string(), "(1, 0) INT @4+4 00000000 = NULL");
+    /// assert!(block.inspect(2, 0).is_none());
+    /// ```
+    pub fn inspect(&self, row: usize, col: usize) -> Option<CellInspection> {
+        if row >= self.nrows() || col >= self.ncols() {
+            return None;
+        }
+        let view = self.view(col);
+        let ty = view.as_ty();
+        // bytes are read before decoding, which may decode nchar in place.
+        let (data_offset, raw_bytes) = match view.raw_bytes_at(row) {
+            Some((offset, bytes)) => (Some(offset), bytes.to_vec()),
+            None => (None, Vec::new()),
+        };
+        let length = if ty.is_var_type() || ty.is_json() {
+            raw_bytes.len().saturating_sub(std::mem::size_of::<u16>())
+        } else {
+            raw_bytes.len()
+        };
+        let decoded = match view {
+            ColumnView::NChar(view) if is_ucs4(view) => raw_bytes.get(2..).map(|chars| {
+                let chars = chars.chunks_exact(std::mem::size_of::<char>());
+                Value::NChar(
+                    chars
+                        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                        .map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect(),
+                )
+            }),
+            _ => Some(unsafe { self.get_ref_unchecked(row, col) }.to_value())
+                .filter(|value| !value.is_null()),
+        };
+        Some(CellInspection {
+            row,
+            col,
+            ty,
+            null: decoded.is_none(),
+            raw_bytes,
+            decoded,
+            data_offset,
+            length,
+        })
+    }
+}
+
+/// The nchar view is not decoded yet, so it's read without changing the block.
+fn is_ucs4(view: &super::views::NCharView) -> bool {
+    view.version == Version::V3 && unsafe { *view.is_chars.get() }
+}
+
+impl Display for CellInspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}) {}", self.row, self.col, self.ty)?;
+        let Some(offset) = self.data_offset else {
+            return f.write_str(" NULL");
+        };
+        write!(f, " @{offset}+{} ", self.length)?;
+        let shown = f
+            .precision()
+            .unwrap_or(usize::MAX)
+            .min(self.raw_bytes.len());
+        for byte in &self.raw_bytes[..shown] {
+            write!(f, "{byte:02x}")?;
+        }
+        if shown < self.raw_bytes.len() {
+            write!(f, "..(+{} bytes)", self.raw_bytes.len() - shown)?;
+        }
+        match &self.decoded {
+            None => f.write_str(" = NULL"),
+            Some(Value::VarChar(v) | Value::NChar(v)) => write!(f, " = {v:?}"),
+            Some(value) => write!(f, " = {value}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Precision;
+
+    use super::*;
+
+    /// A block of bool, int, varchar and nchar columns written byte by byte, with offsets of the
+    /// data of each cell noted.
+    fn crafted() -> RawBlock {
+        let mut bytes = Vec::new();
+        let schemas: [(u8, u32); 4] = [(1, 1), (4, 4), (8, 8), (10, 16)];
+        let lengths: [u32; 4] = [3, 12, 6, 16];
+        let len = 28 + 5 * 4 + 4 * 4 + (1 + 3) + (1 + 12) + (12 + 6) + (12 + 16);
+        for v in [1u32, len as u32, 3, 4, 0] {
+            bytes.extend(v.to_le_bytes());
+        }
+        bytes.extend(0u64.to_le_bytes());
+        for (ty, len) in schemas {
+            bytes.push(ty);
+            bytes.extend(len.to_le_bytes());
+        }
+        for len in lengths {
+            bytes.extend(len.to_le_bytes());
+        }
+        // bool [true, NULL, false], the bit of row 1 is set in the bitmap.
+        bytes.extend([0b0100_0000, 1, 0, 0]);
+        // int [7, NULL, -1]
+        bytes.push(0b0100_0000);
+        for v in [7i32, 0, -1] {
+            bytes.extend(v.to_le_bytes());
+        }
+        // varchar ["ab", NULL, ""] at 0 and 4.
+        for offset in [0i32, -1, 4] {
+            bytes.extend(offset.to_le_bytes());
+        }
+        bytes.extend([2, 0, b'a', b'b', 0, 0]);
+        // nchar ["é", NULL, "xy"] in UCS-4 at 0 and 6.
+        for offset in [0i32, -1, 6] {
+            bytes.extend(offset.to_le_bytes());
+        }
+        bytes.extend([4, 0, 0xe9, 0, 0, 0]);
+        bytes.extend([8, 0, b'x', 0, 0, 0, b'y', 0, 0, 0]);
+        assert_eq!(bytes.len(), len);
+        RawBlock::parse_from_raw_block(bytes, Precision::Millisecond)
+    }
+
+    #[test]
+    fn inspect_crafted_block() {
+        let block = crafted();
+        let cell = |row, col| {
+            let cell = block.inspect(row, col).unwrap();
+            (cell.data_offset, cell.length, cell.raw_bytes, cell.decoded)
+        };
+        assert_eq!(cell(0, 0), (Some(0), 1, vec![1], Some(Value::Bool(true))));
+        assert_eq!(cell(1, 0), (Some(1), 1, vec![0], None));
+        assert_eq!(cell(2, 0), (Some(2), 1, vec![0], Some(Value::Bool(false))));
+        assert_eq!(cell(1, 1), (Some(4), 4, vec![0; 4], None));
+        assert_eq!(
+            cell(2, 1),
+            (Some(8), 4, vec![0xff; 4], Some(Value::Int(-1)))
+        );
+        assert_eq!(
+            cell(0, 2),
+            (
+                Some(0),
+                2,
+                b"\x02\x00ab".to_vec(),
+                Some(Value::VarChar("ab".into()))
+            )
+        );
+        assert_eq!(cell(1, 2), (None, 0, vec![], None));
+        assert_eq!(
+            cell(2, 2),
+            (Some(4), 0, vec![0, 0], Some(Value::VarChar(String::new())))
+        );
+        assert_eq!(
+            cell(2, 3),
+            (
+                Some(6),
+                8,
+                vec![8, 0, b'x', 0, 0, 0, b'y', 0, 0, 0],
+                Some(Value::NChar("xy".into()))
+            )
+        );
+        assert!(block.inspect(3, 0).is_none() && block.inspect(0, 4).is_none());
+
+        // inspection doesn't decode nchar in place, reading a value does.
+        let cell = block.inspect(0, 3).unwrap();
+        assert_eq!(cell.to_string(), "(0, 3) NCHAR @0+4 0400e9000000 = \"é\"");
+        assert_eq!(
+            block.get_ref(0, 3).unwrap().to_value(),
+            cell.decoded.unwrap()
+        );
+        let decoded = block.inspect(0, 3).unwrap();
+        assert_eq!(decoded.raw_bytes, [2, 0, 0xc3, 0xa9]);
+        assert_eq!(decoded.length, 2);
+    }
+
+    #[test]
+    fn inspection_dump() {
+        let block = crafted();
+        let dump: Vec<String> = block
+            .rows()
+            .nth(1)
+            .unwrap()
+            .inspect_all()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            dump,
+            [
+                "(1, 0) BOOL @1+1 00 = NULL",
+                "(1, 1) INT @4+4 00000000 = NULL",
+                "(1, 2) BINARY NULL",
+                "(1, 3) NCHAR NULL",
+            ]
+        );
+        let cell = block.inspect(2, 3).unwrap();
+        assert_eq!(
+            format!("{cell:.4}"),
+            "(2, 3) NCHAR @6+8 08007800..(+6 bytes) = \"xy\""
+        );
+    }
+}
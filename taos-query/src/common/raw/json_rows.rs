@@ -0,0 +1,295 @@
+//! Blocks of rows encoded in JSON, as taosAdapter returns results in its JSON fallback mode.
+//!
+//! Timestamps arrive as RFC3339 strings in the timezone of the adapter, they're converted to
+//! the precision of the result at decode time, so the block is the same as the binary one.
+
+use chrono::{DateTime, NaiveDateTime};
+use serde_json::Value as JsonValue;
+
+use crate::common::{Field, Precision, Timestamp, Ty, Value};
+
+use super::views::{views_to_raw_block_with_schemas, ColumnView};
+use super::RawBlock;
+
+/// Rows that can't be decoded by [RawBlock::from_json_rows].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum JsonRowsError {
+    #[error("row {row} has {len} values but the result has {ncols} columns")]
+    Width {
+        row: usize,
+        len: usize,
+        ncols: usize,
+    },
+    #[error("invalid {ty} value at row {row} of column `{name}`: {value}")]
+    Value {
+        row: usize,
+        name: String,
+        ty: Ty,
+        value: String,
+    },
+}
+
+/// Parse a timestamp of JSON rows in `precision`.
+///
+/// Strings are RFC3339 with any offset and up to 9 sub-second digits, or
+/// `YYYY-MM-DD HH:MM:SS[.f]` without offset in UTC. Digits finer than `precision` are truncated.
+/// Integers are the raw epoch in `precision`.
+///
+/// ```rust
+/// # use taos_query::common::{raw::parse_json_timestamp, Precision};
+/// # use serde_json::json;
+/// let ts = parse_json_timestamp(&json!("1970-01-01T08:00:00.000000001+08:00"), Precision::Nanosecond);
+/// assert_eq!(ts.unwrap().as_raw_i64(), 1);
+/// let ts = parse_json_timestamp(&json!(1500), Precision::Millisecond);
+/// assert_eq!(ts.unwrap().as_raw_i64(), 1500);
+/// ```
+pub fn parse_json_timestamp(value: &JsonValue, precision: Precision) -> Option<Timestamp> {
+    match value {
+        JsonValue::Number(n) => n.as_i64().map(|raw| Timestamp::new(raw, precision)),
+        JsonValue::String(s) => {
+            if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+                return Timestamp::from_datetime(&datetime, precision).ok();
+            }
+            let naive = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())?;
+            Timestamp::from_naive_datetime(&naive, precision).ok()
+        }
+        _ => None,
+    }
+}
+
+impl RawBlock {
+    /// Decode rows of JSON values of `fields`, eg. rows fetched in the JSON fallback mode of
+    /// taosAdapter. Field names are set, table and database names are not.
+    ///
+    /// Values are converted to the column types without loss like [ColumnView::try_from_values],
+    /// timestamps are parsed by [parse_json_timestamp] into `precision`, and strings of varbinary
+    /// and geometry columns are hex.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{Field, Precision, RawBlock, Timestamp, Ty, Value};
+    /// # use serde_json::json;
+    /// let fields = [Field::new("ts", Ty::Timestamp, 8), Field::new("v", Ty::Int, 4)];
+    /// let rows = [
+    ///     vec![json!("2022-01-01T08:00:00.001+08:00"), json!(1)],
+    ///     vec![json!(1640995200002i64), json!(null)],
+    /// ];
+    /// let block = RawBlock::from_json_rows(&fields, &rows, Precision::Millisecond).unwrap();
+    /// let ts = Timestamp::new(1640995200001, Precision::Millisecond);
+    /// assert_eq!(block.get_ref(0, 0).unwrap().to_value(), Value::Timestamp(ts));
+    /// assert_eq!(block.get_ref(1, 1).unwrap().to_value(), Value::Null(Ty::Int));
+    /// ```
+    pub fn from_json_rows(
+        fields: &[Field],
+        rows: &[Vec<JsonValue>],
+        precision: Precision,
+    ) -> Result<RawBlock, JsonRowsError> {
+        if let Some((row, values)) = rows
+            .iter()
+            .enumerate()
+            .find(|(_, values)| values.len() != fields.len())
+        {
+            return Err(JsonRowsError::Width {
+                row,
+                len: values.len(),
+                ncols: fields.len(),
+            });
+        }
+        let views = fields
+            .iter()
+            .enumerate()
+            .map(|(col, field)| {
+                let invalid = |row: usize| JsonRowsError::Value {
+                    row,
+                    name: field.name().to_string(),
+                    ty: field.ty(),
+                    value: rows[row][col].to_string(),
+                };
+                let values = rows
+                    .iter()
+                    .enumerate()
+                    .map(|(row, values)| {
+                        json_to_value(&values[col], field.ty(), precision)
+                            .ok_or_else(|| invalid(row))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                ColumnView::try_from_values(field.ty(), &values).map_err(|err| invalid(err.row))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let views: Vec<&ColumnView> = views.iter().collect();
+        let schemas: Vec<_> = fields.iter().map(Field::to_column_schema).collect();
+        let bytes = views_to_raw_block_with_schemas(&views, &schemas);
+        let mut block = RawBlock::parse_from_raw_block(bytes, precision);
+        block.with_field_names(fields.iter().map(Field::name));
+        Ok(block)
+    }
+}
+
+/// The value of a JSON value for a column of `ty`, `None` if it's invalid.
+fn json_to_value(value: &JsonValue, ty: Ty, precision: Precision) -> Option<Value> {
+    let value = match (ty, value) {
+        (_, JsonValue::Null) => Value::Null(ty),
+        (Ty::Timestamp, value) => Value::Timestamp(parse_json_timestamp(value, precision)?),
+        (Ty::VarBinary, JsonValue::String(s)) => Value::VarBinary(parse_hex(s)?),
+        (Ty::Geometry, JsonValue::String(s)) => Value::Geometry(parse_hex(s)?),
+        (Ty::Json, JsonValue::String(s)) => Value::VarChar(s.clone()),
+        (Ty::Json, value) => Value::Json(value.clone()),
+        (_, JsonValue::Bool(v)) => Value::Bool(*v),
+        (_, JsonValue::Number(n)) => match (n.as_i64(), n.as_u64()) {
+            (Some(v), _) => Value::BigInt(v),
+            (None, Some(v)) => Value::UBigInt(v),
+            (None, None) => Value::Double(n.as_f64()?),
+        },
+        (_, JsonValue::String(s)) => Value::VarChar(s.clone()),
+        (_, value) => Value::Json(value.clone()),
+    };
+    Some(value)
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// A block of the query in binary mode.
+    fn binary_block(precision: Precision, ts: Vec<i64>) -> RawBlock {
+        let ts = match precision {
+            Precision::Millisecond => ColumnView::from_millis_timestamp(ts),
+            Precision::Microsecond => ColumnView::from_micros_timestamp(ts),
+            Precision::Nanosecond => ColumnView::from_nanos_timestamp(ts),
+        };
+        let views = [
+            ts,
+            ColumnView::from_doubles(vec![Some(10.3), None]),
+            ColumnView::from_varchar::<&str, _, _, _>(vec![Some("California.SanFrancisco"), None]),
+            ColumnView::from_bytes::<&[u8], _, _, _>(vec![Some(&b"\x00\xff"[..]), None]),
+            ColumnView::from_bools(vec![Some(true), Some(false)]),
+        ];
+        let views: Vec<&ColumnView> = views.iter().collect();
+        let schemas: Vec<_> = fields().iter().map(Field::to_column_schema).collect();
+        let bytes = views_to_raw_block_with_schemas(&views, &schemas);
+        let mut block = RawBlock::parse_from_raw_block(bytes, precision);
+        block.with_field_names(["ts", "current", "location", "payload", "ok"]);
+        block
+    }
+
+    fn fields() -> Vec<Field> {
+        vec![
+            Field::new("ts", Ty::Timestamp, 8),
+            Field::new("current", Ty::Double, 8),
+            Field::new("location", Ty::VarChar, 64),
+            Field::new("payload", Ty::VarBinary, 16),
+            Field::new("ok", Ty::Bool, 1),
+        ]
+    }
+
+    /// Rows of a captured JSON mode response of the same query.
+    fn captured_rows(ts: [&str; 2]) -> Vec<Vec<JsonValue>> {
+        let frame = format!(
+            r#"[["{}",10.3,"California.SanFrancisco","00ff",true],["{}",null,null,null,false]]"#,
+            ts[0], ts[1]
+        );
+        serde_json::from_str(&frame).unwrap()
+    }
+
+    #[test]
+    fn same_as_binary_in_all_precisions() {
+        let cases = [
+            (
+                Precision::Millisecond,
+                ["2022-10-01T08:00:00.123+08:00", "2022-10-01T00:00:00.124Z"],
+                vec![1664582400123, 1664582400124],
+            ),
+            (
+                Precision::Microsecond,
+                [
+                    "2022-10-01T08:00:00.123456+08:00",
+                    "2022-09-30T19:00:00.123457-05:00",
+                ],
+                vec![1664582400123456, 1664582400123457],
+            ),
+            (
+                Precision::Nanosecond,
+                [
+                    "2022-10-01T08:00:00.123456789+08:00",
+                    "2022-10-01 00:00:00.123456790",
+                ],
+                vec![1664582400123456789, 1664582400123456790],
+            ),
+        ];
+        for (precision, ts, raw) in cases {
+            let decoded =
+                RawBlock::from_json_rows(&fields(), &captured_rows(ts), precision).unwrap();
+            let binary = binary_block(precision, raw);
+            assert_eq!(decoded.precision(), precision);
+            assert_eq!(decoded.fields(), binary.fields());
+            assert_eq!(decoded.to_values(), binary.to_values(), "{precision}");
+            assert_eq!(decoded.as_raw_bytes(), binary.as_raw_bytes(), "{precision}");
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        let ns = |value: JsonValue| {
+            parse_json_timestamp(&value, Precision::Nanosecond).map(|ts| ts.as_raw_i64())
+        };
+        assert_eq!(
+            ns(json!("1970-01-01T00:00:01.000000001Z")),
+            Some(1_000_000_001)
+        );
+        assert_eq!(
+            ns(json!("1970-01-01T00:00:01+00:30")),
+            Some(-1_799_000_000_000)
+        );
+        assert_eq!(ns(json!(7)), Some(7));
+        assert_eq!(ns(json!("yesterday")), None);
+        assert_eq!(ns(json!(true)), None);
+        // finer digits are truncated
+        let ms = parse_json_timestamp(
+            &json!("1970-01-01T00:00:00.001999Z"),
+            Precision::Millisecond,
+        );
+        assert_eq!(ms.unwrap().as_raw_i64(), 1);
+    }
+
+    #[test]
+    fn invalid_rows() {
+        let fields = fields();
+        let err = RawBlock::from_json_rows(&fields, &[vec![json!(1)]], Precision::Millisecond);
+        assert_eq!(
+            err.unwrap_err(),
+            JsonRowsError::Width {
+                row: 0,
+                len: 1,
+                ncols: 5
+            }
+        );
+        let mut rows = captured_rows(["2022-10-01T00:00:00Z", "2022-10-01T00:00:01Z"]);
+        rows[1][0] = json!("not a time");
+        let err = RawBlock::from_json_rows(&fields, &rows, Precision::Millisecond).unwrap_err();
+        assert!(matches!(err, JsonRowsError::Value { row: 1, ref name, .. } if name == "ts"));
+        rows[1][0] = json!(0);
+        rows[0][4] = json!("maybe");
+        let err = RawBlock::from_json_rows(&fields, &rows, Precision::Millisecond).unwrap_err();
+        assert!(matches!(
+            err,
+            JsonRowsError::Value {
+                row: 0,
+                ty: Ty::Bool,
+                ..
+            }
+        ));
+    }
+}
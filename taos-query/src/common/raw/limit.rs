@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+use super::RawBlock;
+
+/// What to do when a result fetches more rows or bytes than [QueryOptions] allow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitMode {
+    /// Stop fetching and fail with the rows and bytes fetched so far.
+    #[default]
+    Error,
+    /// Stop fetching and end the result with the rows within the limits, the result is marked
+    /// as truncated.
+    Truncate,
+}
+
+/// Options of queries, eg. guardrails against results too large to be kept in memory.
+///
+/// Rows and bytes are counted by block headers as blocks are fetched, the limits are unset by
+/// default.
+///
+/// ```rust
+/// # use taos_query::common::{LimitMode, QueryOptions};
+/// let options = QueryOptions::new()
+///     .max_result_rows(1_000_000)
+///     .max_result_bytes(256 << 20)
+///     .on_limit(LimitMode::Truncate);
+/// assert_eq!(options.result_rows(), Some(1_000_000));
+/// assert_eq!(options.limit_mode(), LimitMode::Truncate);
+/// ```
+///
+/// Unset fields are the defaults when it's deserialized, eg. from `{"max_result_rows": 1000}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueryOptions {
+    #[serde(rename = "max_result_rows")]
+    max_rows: Option<usize>,
+    #[serde(rename = "max_result_bytes")]
+    max_bytes: Option<usize>,
+    on_limit: LimitMode,
+}
+
+impl QueryOptions {
+    pub const fn new() -> Self {
+        Self {
+            max_rows: None,
+            max_bytes: None,
+            on_limit: LimitMode::Error,
+        }
+    }
+
+    /// Max rows fetched of a result.
+    pub const fn max_result_rows(mut self, rows: usize) -> Self {
+        self.max_rows = Some(rows);
+        self
+    }
+
+    pub const fn result_rows(&self) -> Option<usize> {
+        self.max_rows
+    }
+
+    /// Max bytes of raw blocks fetched of a result, see [RawBlock::raw_len].
+    pub const fn max_result_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    pub const fn result_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    /// Set what to do when the limits are exceeded, [LimitMode::Error] by default.
+    pub const fn on_limit(mut self, mode: LimitMode) -> Self {
+        self.on_limit = mode;
+        self
+    }
+
+    pub const fn limit_mode(&self) -> LimitMode {
+        self.on_limit
+    }
+
+    /// Either limit is set.
+    pub const fn is_limited(&self) -> bool {
+        self.max_rows.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// A fetched block checked by [ResultBudget::admit].
+#[derive(Debug)]
+pub enum Admitted {
+    /// The block is within the limits.
+    Block(RawBlock),
+    /// The block exceeds the limits in [LimitMode::Truncate], the rows within the row limit are
+    /// kept, `None` if there's none. The result ends after it.
+    Truncated(Option<RawBlock>),
+    /// The block exceeds the limits in [LimitMode::Error], with the rows and bytes fetched so
+    /// far including the block.
+    Exceeded { rows: usize, bytes: usize },
+}
+
+/// Rows and bytes fetched of a result, checked against the limits of [QueryOptions].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResultBudget {
+    options: QueryOptions,
+    rows: usize,
+    bytes: usize,
+    exhausted: bool,
+}
+
+impl ResultBudget {
+    pub const fn new(options: QueryOptions) -> Self {
+        Self {
+            options,
+            rows: 0,
+            bytes: 0,
+            exhausted: false,
+        }
+    }
+
+    /// Rows and bytes counted so far.
+    pub const fn fetched(&self) -> (usize, usize) {
+        (self.rows, self.bytes)
+    }
+
+    /// The limits are hit, no more blocks should be fetched.
+    pub const fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// The limits are hit in [LimitMode::Truncate], rows after them are not delivered.
+    pub const fn is_truncated(&self) -> bool {
+        self.exhausted && matches!(self.options.on_limit, LimitMode::Truncate)
+    }
+
+    /// Count a fetched block against the limits.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, Admitted, ColumnView, LimitMode};
+    /// # use taos_query::common::{Precision, QueryOptions, RawBlock, ResultBudget};
+    /// let views = [ColumnView::from_ints((0..10).collect::<Vec<_>>())];
+    /// let block = || RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// let options = QueryOptions::new().max_result_rows(15).on_limit(LimitMode::Truncate);
+    /// let mut budget = ResultBudget::new(options);
+    /// assert!(matches!(budget.admit(block()), Admitted::Block(_)));
+    /// let Admitted::Truncated(Some(last)) = budget.admit(block()) else { unreachable!() };
+    /// assert_eq!(last.nrows(), 5);
+    /// assert!(budget.is_exhausted());
+    /// ```
+    pub fn admit(&mut self, block: RawBlock) -> Admitted {
+        let (rows, bytes) = (self.rows + block.nrows(), self.bytes + block.raw_len());
+        let rows_exceeded = self.options.max_rows.map_or(false, |max| rows > max);
+        let bytes_exceeded = self.options.max_bytes.map_or(false, |max| bytes > max);
+        if !rows_exceeded && !bytes_exceeded {
+            (self.rows, self.bytes) = (rows, bytes);
+            return Admitted::Block(block);
+        }
+        self.exhausted = true;
+        match self.options.on_limit {
+            LimitMode::Error => {
+                (self.rows, self.bytes) = (rows, bytes);
+                Admitted::Exceeded { rows, bytes }
+            }
+            // blocks exceeding the byte limit are dropped as a whole.
+            LimitMode::Truncate if bytes_exceeded => Admitted::Truncated(None),
+            LimitMode::Truncate => {
+                let keep = self.options.max_rows.unwrap_or_default() - self.rows;
+                if keep == 0 {
+                    return Admitted::Truncated(None);
+                }
+                let (head, _) = block.split_at(keep);
+                self.rows += head.nrows();
+                self.bytes += head.raw_len();
+                Admitted::Truncated(Some(head))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::views::{views_to_raw_block, ColumnView};
+    use crate::common::Precision;
+
+    use super::*;
+
+    fn block(rows: usize) -> RawBlock {
+        let views = [
+            ColumnView::from_millis_timestamp((0..rows as i64).collect()),
+            ColumnView::from_varchar::<String, _, _, _>(
+                (0..rows).map(|i| format!("v{i}")).collect::<Vec<_>>(),
+            ),
+        ];
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond)
+    }
+
+    #[test]
+    fn unlimited() {
+        let mut budget = ResultBudget::default();
+        for _ in 0..10 {
+            assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        }
+        assert_eq!(budget.fetched().0, 1000);
+        assert!(!budget.is_exhausted());
+    }
+
+    #[test]
+    fn error_mode() {
+        let mut budget = ResultBudget::new(QueryOptions::new().max_result_rows(250));
+        assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        let bytes = budget.fetched().1 + block(100).raw_len();
+        match budget.admit(block(100)) {
+            Admitted::Exceeded { rows, bytes: b } => assert_eq!((rows, b), (300, bytes)),
+            admitted => panic!("{admitted:?}"),
+        }
+        assert!(budget.is_exhausted());
+
+        let one = block(100).raw_len();
+        let mut budget = ResultBudget::new(QueryOptions::new().max_result_bytes(one * 2));
+        assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        assert!(matches!(
+            budget.admit(block(1)),
+            Admitted::Exceeded { rows: 201, .. }
+        ));
+    }
+
+    #[test]
+    fn truncate_mode() {
+        let options = QueryOptions::new().on_limit(LimitMode::Truncate);
+        let mut budget = ResultBudget::new(options.max_result_rows(250));
+        assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        match budget.admit(block(100)) {
+            Admitted::Truncated(Some(last)) => assert_eq!(last.nrows(), 50),
+            admitted => panic!("{admitted:?}"),
+        }
+        assert_eq!(budget.fetched().0, 250);
+        assert!(budget.is_truncated());
+
+        let mut budget = ResultBudget::new(options.max_result_rows(100));
+        assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        assert!(matches!(budget.admit(block(1)), Admitted::Truncated(None)));
+
+        let mut budget = ResultBudget::new(options.max_result_bytes(block(100).raw_len()));
+        assert!(matches!(budget.admit(block(100)), Admitted::Block(_)));
+        assert!(matches!(
+            budget.admit(block(100)),
+            Admitted::Truncated(None)
+        ));
+        assert_eq!(budget.fetched().0, 100);
+    }
+}
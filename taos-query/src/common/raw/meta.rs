@@ -11,7 +11,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::{
     common::{Field, Ty},
-    util::Inlinable,
+    util::{quote_ident, Inlinable},
 };
 
 use super::RawData;
@@ -199,7 +199,7 @@ impl Display for MetaCreate {
                 debug_assert!(columns.len() > 0, "{:?}", self);
                 debug_assert!(tags.len() > 0);
 
-                f.write_fmt(format_args!("`{}`", table_name))?;
+                f.write_str(&quote_ident(table_name))?;
                 f.write_char('(')?;
                 f.write_str(&columns.iter().map(|f| f.sql_repr()).join(", "))?;
                 f.write_char(')')?;
@@ -216,9 +216,9 @@ impl Display for MetaCreate {
             } => {
                 if tags.len() > 0 {
                     f.write_fmt(format_args!(
-                        "`{}` USING `{}` ({}) TAGS({})",
-                        table_name,
-                        using,
+                        "{} USING {} ({}) TAGS({})",
+                        quote_ident(table_name),
+                        quote_ident(using),
                         tags.iter().map(|t| t.field.escaped_name()).join(", "),
                         tags.iter()
                             .map(|t| {
@@ -234,9 +234,9 @@ impl Display for MetaCreate {
                     ))?;
                 } else {
                     f.write_fmt(format_args!(
-                        "`{}` USING `{}` TAGS({})",
-                        table_name,
-                        using,
+                        "{} USING {} TAGS({})",
+                        quote_ident(table_name),
+                        quote_ident(using),
                         std::iter::repeat("NULL").take(tag_num.unwrap()).join(",")
                     ))?;
                 }
@@ -247,7 +247,7 @@ impl Display for MetaCreate {
             } => {
                 debug_assert!(columns.len() > 0);
 
-                f.write_fmt(format_args!("`{}`", table_name))?;
+                f.write_str(&quote_ident(table_name))?;
                 f.write_char('(')?;
                 f.write_str(&columns.iter().map(|f| f.sql_repr()).join(", "))?;
                 f.write_char(')')?;
@@ -309,26 +309,26 @@ impl Display for MetaAlter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.alter_type {
             AlterType::AddTag => f.write_fmt(format_args!(
-                "ALTER TABLE `{}` ADD TAG {}",
-                self.table_name,
+                "ALTER TABLE {} ADD TAG {}",
+                quote_ident(&self.table_name),
                 self.field.sql_repr()
             )),
             AlterType::DropTag => f.write_fmt(format_args!(
-                "ALTER TABLE `{}` DROP TAG `{}`",
-                self.table_name,
-                self.field.name()
+                "ALTER TABLE {} DROP TAG {}",
+                quote_ident(&self.table_name),
+                quote_ident(self.field.name())
             )),
             AlterType::RenameTag => f.write_fmt(format_args!(
-                "ALTER TABLE `{}` RENAME TAG `{}` `{}`",
-                self.table_name,
-                self.field.name(),
-                self.col_new_name.as_ref().unwrap()
+                "ALTER TABLE {} RENAME TAG {} {}",
+                quote_ident(&self.table_name),
+                quote_ident(self.field.name()),
+                quote_ident(self.col_new_name.as_ref().unwrap())
             )),
             AlterType::SetTagValue => {
                 f.write_fmt(format_args!(
-                    "ALTER TABLE `{}` SET TAG `{}` ",
-                    self.table_name,
-                    self.field.name()
+                    "ALTER TABLE {} SET TAG {} ",
+                    quote_ident(&self.table_name),
+                    quote_ident(self.field.name())
                 ))?;
                 if self.col_value_null.unwrap_or(false) {
                     f.write_str("NULL")
@@ -339,31 +339,31 @@ impl Display for MetaAlter {
                 }
             }
             AlterType::AddColumn => f.write_fmt(format_args!(
-                "ALTER TABLE `{}` ADD COLUMN {}",
-                self.table_name,
+                "ALTER TABLE {} ADD COLUMN {}",
+                quote_ident(&self.table_name),
                 self.field.sql_repr()
             )),
             AlterType::DropColumn => f.write_fmt(format_args!(
-                "ALTER TABLE `{}` DROP COLUMN `{}`",
-                self.table_name,
-                self.field.name()
+                "ALTER TABLE {} DROP COLUMN {}",
+                quote_ident(&self.table_name),
+                quote_ident(self.field.name())
             )),
             AlterType::ModifyColumnLength => f.write_fmt(format_args!(
-                "ALTER TABLE `{}` MODIFY COLUMN {}",
-                self.table_name,
+                "ALTER TABLE {} MODIFY COLUMN {}",
+                quote_ident(&self.table_name),
                 self.field.sql_repr(),
             )),
             AlterType::ModifyTagLength => f.write_fmt(format_args!(
-                "ALTER TABLE `{}` MODIFY TAG {}",
-                self.table_name,
+                "ALTER TABLE {} MODIFY TAG {}",
+                quote_ident(&self.table_name),
                 self.field.sql_repr(),
             )),
             AlterType::ModifyTableOption => todo!(),
             AlterType::RenameColumn => f.write_fmt(format_args!(
-                "ALTER TABLE `{}` RENAME COLUMN `{}` `{}`",
-                self.table_name,
-                self.field.name(),
-                self.col_new_name.as_ref().unwrap()
+                "ALTER TABLE {} RENAME COLUMN {} {}",
+                quote_ident(&self.table_name),
+                quote_ident(self.field.name()),
+                quote_ident(self.col_new_name.as_ref().unwrap())
             )),
         }
     }
@@ -388,12 +388,13 @@ pub enum MetaDrop {
 impl Display for MetaDrop {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            MetaDrop::Super { table_name } => {
-                f.write_fmt(format_args!("DROP TABLE IF EXISTS `{}`", table_name))
-            }
+            MetaDrop::Super { table_name } => f.write_fmt(format_args!(
+                "DROP TABLE IF EXISTS {}",
+                quote_ident(table_name)
+            )),
             MetaDrop::Other { table_name_list } => f.write_fmt(format_args!(
                 "DROP TABLE IF EXISTS {}",
-                table_name_list.iter().map(|n| format!("`{n}`")).join(" ")
+                table_name_list.iter().map(|n| quote_ident(n)).join(" ")
             )),
         }
     }
@@ -1,39 +1,98 @@
-use crate::common::{BorrowedValue, Field, Precision, Ty, Value};
+use crate::common::{BorrowedValue, DedupStrategy, Field, Precision, Ty, Value};
+use crate::diagnostics::{ObjectKind, Scope, Tracked};
 
 use bytes::Bytes;
 use itertools::Itertools;
+use once_cell::sync::OnceCell;
 
 use serde::Deserialize;
 
+use std::fmt::Debug;
 use std::{
     cell::{Cell, RefCell, UnsafeCell},
+    collections::HashMap,
     ffi::c_void,
     fmt::Display,
     ops::Deref,
-    ptr::NonNull,
     sync::Arc,
 };
-use std::{fmt::Debug, mem::transmute};
 
+#[cfg(any(feature = "crc32c", feature = "xxhash"))]
+pub mod checksum;
 pub mod layout;
 pub mod meta;
-
+pub mod pool;
+#[cfg(any(feature = "crc32c", feature = "xxhash"))]
+pub mod stream;
+
+#[cfg(feature = "ndarray")]
+mod array;
+#[cfg(feature = "arrow")]
+mod arrow;
+mod buffers;
+mod concat;
 mod data;
+mod data_rsp;
+mod debug;
+mod dictionary;
+mod export;
+mod inspect;
+mod json_rows;
+mod limit;
+mod order;
+mod profile;
+mod reshape;
+mod typed;
+mod validate;
+mod vectored;
+mod widen;
 
 use layout::Layout;
 
 pub mod views;
 
-pub use views::ColumnView;
+pub use views::{ColumnView, ConvertError};
 use views::*;
 
+#[cfg(feature = "ndarray")]
+pub(crate) use array::ArrayBuilder;
+#[cfg(feature = "ndarray")]
+pub use array::{ArrayError, ArrayNulls};
+
+pub use buffers::{BuffersError, ColumnBuffers};
+#[cfg(any(feature = "crc32c", feature = "xxhash"))]
+pub use checksum::ChecksumAlgorithm;
+pub use concat::ConcatError;
 pub use data::*;
+pub use debug::{DebugBlock, DEBUG_HEAD_ROWS, DEBUG_MAX_COLUMNS, DEBUG_TAIL_ROWS};
+pub use dictionary::DictionaryColumn;
+pub(crate) use export::write_csv_header;
+pub use export::{CsvOptions, TimestampFormat};
+pub use inspect::CellInspection;
+pub use json_rows::{parse_json_timestamp, JsonRowsError};
+pub use limit::{Admitted, LimitMode, QueryOptions, ResultBudget};
 pub use meta::*;
+pub use order::{NonFinitePolicy, TsOrder, UnsortedPolicy, WriteOptions};
+pub use pool::{BlockBufferPool, BlockPool};
+pub use profile::{
+    BlockProfile, ColumnProfile, ColumnStats, LengthStats, ProfileOptions, Profiler,
+};
+pub use reshape::ReshapeError;
+pub use typed::{ColumnError, ColumnIndex, FixedColumnType, FromColumn, FromColumnView};
+pub use widen::WidenError;
+#[cfg(feature = "buffer-pool")]
+pub use pool::SizeClassPool;
+#[cfg(any(feature = "crc32c", feature = "xxhash"))]
+pub use stream::{BlockStreamReader, BlockStreamWriter, PartialFrame};
 
 mod de;
 mod rows;
 pub use rows::*;
 
+/// Block version of raw blocks with the layout below, newer versions are parsed the same way.
+const BLOCK_VERSION: u32 = 1;
+
+/// Raw block header, multi-byte fields are little-endian in bytes, use the accessors to read.
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed(1))]
 struct Header {
@@ -48,7 +107,7 @@ struct Header {
 impl Default for Header {
     fn default() -> Self {
         Self {
-            version: 1,
+            version: BLOCK_VERSION.to_le(),
             length: Default::default(),
             nrows: Default::default(),
             ncols: Default::default(),
@@ -67,15 +126,47 @@ impl Header {
         }
     }
 
+    fn version(&self) -> u32 {
+        u32::from_le(self.version)
+    }
+
     fn len(&self) -> usize {
-        self.length as _
+        u32::from_le(self.length) as _
     }
 
     fn nrows(&self) -> usize {
-        self.nrows as _
+        u32::from_le(self.nrows) as _
     }
     fn ncols(&self) -> usize {
-        self.ncols as _
+        u32::from_le(self.ncols) as _
+    }
+
+    fn group_id(&self) -> u64 {
+        u64::from_le(self.group_id)
+    }
+
+    fn set_len(&mut self, len: usize) {
+        self.length = (len as u32).to_le();
+    }
+
+    fn set_nrows(&mut self, nrows: usize) {
+        self.nrows = (nrows as u32).to_le();
+    }
+
+    fn set_ncols(&mut self, ncols: usize) {
+        self.ncols = (ncols as u32).to_le();
+    }
+
+    fn set_group_id(&mut self, group_id: u64) {
+        self.group_id = group_id.to_le();
+    }
+}
+
+/// Warn once for blocks newer than [BLOCK_VERSION], eg. after the server or adapter upgraded.
+fn check_block_version(version: u32) {
+    static WARNED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+    if version > BLOCK_VERSION && !WARNED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+        log::warn!("raw block version {version} is newer than {BLOCK_VERSION}, parse it as v{BLOCK_VERSION}");
     }
 }
 
@@ -95,12 +186,14 @@ pub struct RawBlock {
     /// Layout is auto detected.
     layout: Arc<RefCell<Layout>>,
     /// Raw bytes version, may be v2 or v3.
+    #[allow(dead_code)]
     version: Version,
     /// Data is required, which could be v2 websocket block or a v3 raw block.
     data: Cell<Bytes>,
     /// Number of rows in current data block.
     rows: usize,
     /// Number of columns (or fields) in current data block.
+    #[allow(dead_code)]
     cols: usize,
     /// Timestamp precision in current data block.
     precision: Precision,
@@ -110,46 +203,76 @@ pub struct RawBlock {
     table: Option<String>,
     /// Field names of current data block.
     fields: Vec<String>,
+    /// Typed fields paired with columns, names are empty if the block has no field names.
+    typed_fields: Vec<Field>,
+    /// Column index by field name, the first one wins for duplicated names.
+    name_index: HashMap<String, usize>,
     /// Group id in current data block, it always be 0 in v2 block, and be meaningful in v3.
     group_id: u64,
     /// Column schemas of current data block, contains only data type and the length defined in `create table`.
     schemas: Schemas,
     /// Data lengths collection for all columns.
+    #[allow(dead_code)]
     lengths: Lengths,
-    /// A vector of [ColumnView] that represent column of values efficiently.
-    columns: Vec<ColumnView>,
+    /// Views of columns, parsed on first access of each column, see [RawBlock::view].
+    ///
+    /// Unparsed columns hold an empty placeholder view.
+    columns: Box<[UnsafeCell<ColumnView>]>,
+    /// Set once the view in `columns` at the same index is parsed.
+    parsed: Box<[OnceCell<()>]>,
+    /// Offset of each column data in v3 raw bytes, located in parsing the header.
+    offsets: Vec<usize>,
+    /// What non-`Option` fields receive on NULL when deserializing rows.
+    null_policy: NullPolicy,
+    /// Registration in live objects, see [crate::diagnostics].
+    tracked: Tracked,
 }
 
 unsafe impl Send for RawBlock {}
 unsafe impl Sync for RawBlock {}
 
+/// Length of column data in v3 raw bytes, `length` is the one recorded in the block.
+const fn column_data_len(ty: Ty, rows: usize, length: usize) -> usize {
+    if ty.is_var_type() || ty.is_json() {
+        std::mem::size_of::<i32>() * rows + length
+    } else {
+        ((rows + 7) >> 3) + rows * ty.fixed_length()
+    }
+}
+
+/// Placeholder of a column view not parsed yet.
+fn empty_view() -> ColumnView {
+    ColumnView::Bool(BoolView {
+        nulls: NullBits(Bytes::new()),
+        data: Bytes::new(),
+    })
+}
+
 impl Debug for RawBlock {
+    /// Print a bounded summary of the block, see [DebugBlock].
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // todo: more helpful debug impl.
-        f.debug_struct("Raw")
-            .field("layout", &self.layout)
-            .field("version", &self.version)
-            .field("data", &"...")
-            .field("rows", &self.rows)
-            .field("cols", &self.cols)
-            .field("precision", &self.precision)
-            .field("table", &self.table)
-            .field("fields", &self.fields)
-            // .field("raw_fields", &self.raw_fields)
-            .field("group_id", &self.group_id)
-            .field("schemas", &self.schemas)
-            .field("lengths", &self.lengths)
-            .field("columns", &self.columns)
-            .finish()
+        Debug::fmt(&DebugBlock::summary(self), f)
     }
 }
 
 impl RawBlock {
     pub unsafe fn parse_from_ptr(ptr: *mut c_void, precision: Precision) -> Self {
+        Self::parse_from_ptr_with_pool(ptr, precision, None)
+    }
+
+    /// Copy the block at `ptr` into a buffer of `pool` if any, see [BlockPool].
+    pub unsafe fn parse_from_ptr_with_pool(
+        ptr: *mut c_void,
+        precision: Precision,
+        pool: Option<&BlockPool>,
+    ) -> Self {
         let header = &*(ptr as *const Header);
-        let len = header.length as usize;
+        let len = header.len();
         let bytes = std::slice::from_raw_parts(ptr as *const u8, len);
-        let bytes = Bytes::from(bytes.to_vec());
+        let bytes = match pool {
+            Some(pool) => pool.copy_from_slice(bytes),
+            None => Bytes::from(bytes.to_vec()),
+        };
         Self::parse_from_raw_block(bytes, precision).with_layout(Layout::default())
     }
 
@@ -183,38 +306,39 @@ impl RawBlock {
         use bytes::BufMut;
         debug_assert_eq!(fields.len(), lengths.len());
 
-        const fn bool_is_null(v: *const bool) -> bool {
-            unsafe { *(v as *const u8) == 0x02 }
+        // Values of v2 blocks are not aligned, read NULL markers from bytes.
+        fn bool_is_null(v: &[u8]) -> bool {
+            v[0] == 0x02
         }
-        const fn tiny_int_is_null(v: *const i8) -> bool {
-            unsafe { *(v as *const u8) == 0x80 }
+        fn tiny_int_is_null(v: &[u8]) -> bool {
+            v[0] == 0x80
         }
-        const fn small_int_is_null(v: *const i16) -> bool {
-            unsafe { *(v as *const u16) == 0x8000 }
+        fn small_int_is_null(v: &[u8]) -> bool {
+            u16::from_le_bytes([v[0], v[1]]) == 0x8000
         }
-        const fn int_is_null(v: *const i32) -> bool {
-            unsafe { *(v as *const u32) == 0x80000000 }
+        fn int_is_null(v: &[u8]) -> bool {
+            u32::from_le_bytes(v.try_into().unwrap()) == 0x80000000
         }
-        const fn big_int_is_null(v: *const i64) -> bool {
-            unsafe { *(v as *const u64) == 0x8000000000000000 }
+        fn big_int_is_null(v: &[u8]) -> bool {
+            u64::from_le_bytes(v.try_into().unwrap()) == 0x8000000000000000
         }
-        const fn u_tiny_int_is_null(v: *const u8) -> bool {
-            unsafe { *(v as *const u8) == 0xFF }
+        fn u_tiny_int_is_null(v: &[u8]) -> bool {
+            v[0] == 0xFF
         }
-        const fn u_small_int_is_null(v: *const u16) -> bool {
-            unsafe { *(v as *const u16) == 0xFFFF }
+        fn u_small_int_is_null(v: &[u8]) -> bool {
+            u16::from_le_bytes([v[0], v[1]]) == 0xFFFF
         }
-        const fn u_int_is_null(v: *const u32) -> bool {
-            unsafe { *(v as *const u32) == 0xFFFFFFFF }
+        fn u_int_is_null(v: &[u8]) -> bool {
+            u32::from_le_bytes(v.try_into().unwrap()) == 0xFFFFFFFF
         }
-        const fn u_big_int_is_null(v: *const u64) -> bool {
-            unsafe { *(v as *const u64) == 0xFFFFFFFFFFFFFFFF }
+        fn u_big_int_is_null(v: &[u8]) -> bool {
+            u64::from_le_bytes(v.try_into().unwrap()) == 0xFFFFFFFFFFFFFFFF
         }
-        const fn float_is_null(v: *const f32) -> bool {
-            unsafe { *(v as *const u32) == 0x7FF00000 }
+        fn float_is_null(v: &[u8]) -> bool {
+            u32::from_le_bytes(v.try_into().unwrap()) == 0x7FF00000
         }
-        const fn double_is_null(v: *const f64) -> bool {
-            unsafe { *(v as *const u64) == 0x7FFFFF0000000000 }
+        fn double_is_null(v: &[u8]) -> bool {
+            u64::from_le_bytes(v.try_into().unwrap()) == 0x7FFFFF0000000000
         }
 
         // const BOOL_NULL: u8 = 0x2;
@@ -259,14 +383,10 @@ impl RawBlock {
                     offset += rows * std::mem::size_of::<$prim>() as usize;
                     // byte slice from start to end: `[start, end)`.
                     let data = bytes.slice(start..offset);
-                    let nulls = NullBits::from_iter((0..rows).map(|row| unsafe {
-                        paste::paste!{ [<$ty:snake _is_null>] (
-                            data
-                                .as_ptr()
-                                .offset(row as isize * std::mem::size_of::<$prim>() as isize)
-                                as *const $prim,
-                        ) }
-                    }));
+                    let nulls = NullBits::from_iter(
+                        data.chunks_exact(std::mem::size_of::<$prim>())
+                            .map(|v| paste::paste! { [<$ty:snake _is_null>](v) }),
+                    );
                     // value as target type
                     // let value_slice = unsafe {
                     //     std::slice::from_raw_parts(
@@ -275,7 +395,7 @@ impl RawBlock {
                     //     )
                     // };
                     // Set data lengths for v3-compatible block.
-                    data_lengths[i] = data.len() as u32;
+                    data_lengths.set(i, data.len() as u32);
 
                     // generate nulls bitmap.
                     // let nulls = NullsMut::from_bools(
@@ -317,7 +437,7 @@ impl RawBlock {
                     let offsets = Offsets::from_offsets((0..rows).into_iter().map(|row| unsafe {
                         let offset = row as i32 * *length as i32;
                         let ptr = data_ptr.offset(offset as isize);
-                        let len = *transmute::<*const u8, *const u16>(ptr);
+                        let len = u16::from_le((ptr as *const u16).read_unaligned());
                         if len == 1 && *ptr.offset(2) == 0xFF {
                             -1
                         } else {
@@ -327,7 +447,7 @@ impl RawBlock {
 
                     columns.push(ColumnView::VarChar(VarCharView { offsets, data }));
 
-                    data_lengths[i] = *length as u32 * rows as u32;
+                    data_lengths.set(i, *length as u32 * rows as u32);
                 }
                 Ty::Timestamp => {
                     // column start
@@ -343,17 +463,12 @@ impl RawBlock {
                     //         rows,
                     //     )
                     // };
-                    let nulls = NullBits::from_iter((0..rows).map(|row| unsafe {
-                        big_int_is_null(
-                            &(data
-                                .as_ptr()
-                                .offset(row as isize * std::mem::size_of::<i64>() as isize)
-                                as *const i64)
-                                .read_unaligned() as _,
-                        )
-                    }));
+                    let nulls = NullBits::from_iter(
+                        data.chunks_exact(std::mem::size_of::<i64>())
+                            .map(big_int_is_null),
+                    );
                     // Set data lengths for v3-compatible block.
-                    data_lengths[i] = data.len() as u32;
+                    data_lengths.set(i, data.len() as u32);
 
                     // generate nulls bitmap.
                     // let nulls =
@@ -376,8 +491,9 @@ impl RawBlock {
                     let offsets = Offsets::from_offsets((0..rows).into_iter().map(|row| unsafe {
                         let offset = row as i32 * *length as i32;
                         let ptr = data_ptr.offset(offset as isize);
-                        let len = *transmute::<*const u8, *const u16>(ptr);
-                        if len == 4 && *(ptr.offset(2) as *const u32) == 0xFFFFFFFF {
+                        let len = u16::from_le((ptr as *const u16).read_unaligned());
+                        if len == 4 && (ptr.offset(2) as *const u32).read_unaligned() == 0xFFFFFFFF
+                        {
                             -1
                         } else {
                             offset
@@ -392,7 +508,7 @@ impl RawBlock {
                         layout: layout.clone(),
                     }));
 
-                    data_lengths[i] = *length as u32 * rows as u32;
+                    data_lengths.set(i, *length as u32 * rows as u32);
                 }
                 Ty::Json => {
                     let start = offset;
@@ -403,8 +519,9 @@ impl RawBlock {
                     let offsets = Offsets::from_offsets((0..rows).into_iter().map(|row| unsafe {
                         let offset = row as i32 * *length as i32;
                         let ptr = data_ptr.offset(offset as isize);
-                        let len = *transmute::<*const u8, *const u16>(ptr);
-                        if len == 4 && *(ptr.offset(2) as *const u32) == 0xFFFFFFFF {
+                        let len = u16::from_le((ptr as *const u16).read_unaligned());
+                        if len == 4 && (ptr.offset(2) as *const u32).read_unaligned() == 0xFFFFFFFF
+                        {
                             -1
                         } else {
                             offset
@@ -413,16 +530,16 @@ impl RawBlock {
 
                     columns.push(ColumnView::Json(JsonView { offsets, data }));
 
-                    data_lengths[i] = *length as u32 * rows as u32;
+                    data_lengths.set(i, *length as u32 * rows as u32);
                 }
-                Ty::VarBinary => todo!(),
+                Ty::VarBinary | Ty::Geometry => unreachable!("v2 blocks have no {}", field.ty()),
                 Ty::Decimal => todo!(),
                 Ty::Blob => todo!(),
                 Ty::MediumBlob => todo!(),
             }
         }
 
-        Self {
+        let mut block = Self {
             layout,
             version: Version::V2,
             data: Cell::new(bytes),
@@ -434,12 +551,134 @@ impl RawBlock {
             database: None,
             table: None,
             fields: fields.iter().map(|s| s.name().to_string()).collect(),
-            columns,
+            typed_fields: Vec::new(),
+            name_index: HashMap::new(),
+            parsed: columns.iter().map(|_| OnceCell::with_value(())).collect(),
+            columns: columns.into_iter().map(UnsafeCell::new).collect(),
+            offsets: Vec::new(),
             group_id: 0,
+            null_policy: NullPolicy::default(),
+            tracked: Tracked::new(ObjectKind::RawBlock),
             // raw_fields: Vec::new(),
+        };
+        block.index_fields();
+        block
+    }
+
+    /// Serialize the block in the layout of 2.x servers, the inverse of
+    /// [RawBlock::parse_from_raw_block_v2], eg. to replay blocks by a mock of a 2.x adapter.
+    ///
+    /// Returns the bytes and the length of each value of columns. Columns are of fixed-length
+    /// values with NULL markers instead of bitmaps, variable-length values are prefixed with
+    /// `u16` lengths and padded to the longest one of the column.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock, Value};
+    /// let views = [ColumnView::from_ints(vec![Some(1), None]), ColumnView::from_varchar::<&str, _, _, _>(["a", "bc"])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["v", "s"]);
+    /// let (bytes, lengths) = block.to_raw_block_v2();
+    /// assert_eq!(lengths, [4, 4]);
+    /// let v2 = RawBlock::parse_from_raw_block_v2(bytes, &block.fields(), &lengths, 2, Precision::Millisecond);
+    /// assert_eq!(v2.get_ref(1, 0).unwrap().to_value(), Value::Null(taos_query::common::Ty::Int));
+    /// assert_eq!(v2.get_ref(1, 1).unwrap().to_value(), Value::VarChar("bc".to_string()));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a column is of types 2.x servers do not support, like `VARBINARY` or `GEOMETRY`.
+    pub fn to_raw_block_v2(&self) -> (Vec<u8>, Vec<u32>) {
+        use std::borrow::Cow;
+
+        // `u16` length and `0xFF` of NULL varchar, or `0xFFFFFFFF` of NULL nchar and json.
+        const VARCHAR_NULL: &[u8] = &[1, 0, 0xFF];
+        const NCHAR_NULL: &[u8] = &[4, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+
+        let mut bytes = Vec::new();
+        let mut lengths = Vec::with_capacity(self.ncols());
+        for view in self.column_views() {
+            let ty = view.as_ty();
+            let null = match ty {
+                Ty::VarChar => Some(VARCHAR_NULL),
+                Ty::NChar | Ty::Json => Some(NCHAR_NULL),
+                _ => None,
+            };
+            if let Some(null) = null {
+                let values: Vec<Option<Cow<[u8]>>> = view
+                    .iter()
+                    .map(|value| match value {
+                        BorrowedValue::Null(_) => None,
+                        BorrowedValue::VarChar(v) => Some(Cow::Borrowed(v.as_bytes())),
+                        BorrowedValue::NChar(Cow::Borrowed(v)) => Some(Cow::Borrowed(v.as_bytes())),
+                        BorrowedValue::NChar(Cow::Owned(v)) => Some(Cow::Owned(v.into_bytes())),
+                        BorrowedValue::Json(v) => Some(v),
+                        value => unreachable!("{} in {ty} column", value.ty()),
+                    })
+                    .collect();
+                let length = values
+                    .iter()
+                    .flatten()
+                    .map(|v| v.len() + 2)
+                    .max()
+                    .unwrap_or_default()
+                    .max(null.len());
+                for value in &values {
+                    let start = bytes.len();
+                    match value {
+                        Some(v) => {
+                            bytes.extend_from_slice(&(v.len() as u16).to_le_bytes());
+                            bytes.extend_from_slice(v);
+                        }
+                        None => bytes.extend_from_slice(null),
+                    }
+                    bytes.resize(start + length, 0);
+                }
+                lengths.push(length as u32);
+                continue;
+            }
+            for value in view.iter() {
+                match value {
+                    BorrowedValue::Null(Ty::Bool) => bytes.push(0x02),
+                    BorrowedValue::Null(Ty::TinyInt) => bytes.push(0x80),
+                    BorrowedValue::Null(Ty::SmallInt) => bytes.extend(i16::MIN.to_le_bytes()),
+                    BorrowedValue::Null(Ty::Int) => bytes.extend(i32::MIN.to_le_bytes()),
+                    BorrowedValue::Null(Ty::BigInt | Ty::Timestamp) => {
+                        bytes.extend(i64::MIN.to_le_bytes())
+                    }
+                    BorrowedValue::Null(Ty::UTinyInt) => bytes.push(u8::MAX),
+                    BorrowedValue::Null(Ty::USmallInt) => bytes.extend(u16::MAX.to_le_bytes()),
+                    BorrowedValue::Null(Ty::UInt) => bytes.extend(u32::MAX.to_le_bytes()),
+                    BorrowedValue::Null(Ty::UBigInt) => bytes.extend(u64::MAX.to_le_bytes()),
+                    BorrowedValue::Null(Ty::Float) => bytes.extend(0x7FF00000u32.to_le_bytes()),
+                    BorrowedValue::Null(Ty::Double) => {
+                        bytes.extend(0x7FFFFF0000000000u64.to_le_bytes())
+                    }
+                    BorrowedValue::Bool(v) => bytes.push(v as u8),
+                    BorrowedValue::TinyInt(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::SmallInt(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::Int(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::BigInt(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::Timestamp(v) => bytes.extend(v.as_raw_i64().to_le_bytes()),
+                    BorrowedValue::UTinyInt(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::USmallInt(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::UInt(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::UBigInt(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::Float(v) => bytes.extend(v.to_le_bytes()),
+                    BorrowedValue::Double(v) => bytes.extend(v.to_le_bytes()),
+                    _ => panic!("v2 blocks have no {ty}"),
+                }
+            }
+            lengths.push(ty.fixed_length() as u32);
         }
+        (bytes, lengths)
     }
 
+    /// Parse v3 raw bytes of a block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a column is of a type unknown to this version, use
+    /// [RawBlock::try_parse_from_raw_block] for blocks of servers that may be newer.
     pub fn parse_from_raw_block(bytes: impl Into<Bytes>, precision: Precision) -> Self {
         let schema_start: usize = std::mem::size_of::<Header>();
 
@@ -449,12 +688,13 @@ impl RawBlock {
         let ptr = bytes.as_ptr();
 
         let header = unsafe { &*(ptr as *const Header) };
+        check_block_version(header.version());
 
         let rows = header.nrows();
         let cols = header.ncols();
         let len = header.len();
         debug_assert_eq!(bytes.len(), len);
-        let group_id = header.group_id as u64;
+        let group_id = header.group_id();
 
         let schema_end = schema_start + cols * std::mem::size_of::<ColSchema>();
         let schemas = Schemas::from(bytes.slice(schema_start..schema_end));
@@ -462,96 +702,17 @@ impl RawBlock {
         let lengths_end = schema_end + std::mem::size_of::<u32>() * cols;
         let lengths = Lengths::from(bytes.slice(schema_end..lengths_end));
         // dbg!(&lengths);
+        // Locate columns eagerly to validate lengths, views are parsed on access.
         let mut data_offset = lengths_end;
-        let mut columns = Vec::with_capacity(cols);
+        let mut offsets = Vec::with_capacity(cols);
         for col in 0..cols {
-            // go for each column
             let length = unsafe { lengths.get_unchecked(col) } as usize;
             let schema = unsafe { schemas.get_unchecked(col) };
-
-            macro_rules! _primitive_value {
-                ($ty:ident, $prim:ty) => {{
-                    let o1 = data_offset;
-                    let o2 = data_offset + ((rows + 7) >> 3); // null bitmap len.
-                    data_offset = o2 + rows * std::mem::size_of::<$prim>();
-                    let nulls = bytes.slice(o1..o2);
-                    let data = bytes.slice(o2..data_offset);
-                    ColumnView::$ty(paste::paste! {[<$ty View>] {
-                        nulls: NullBits(nulls),
-                        data,
-                    }})
-                }};
-            }
-
-            let column = match schema.ty {
-                Ty::Null => unreachable!("raw block does not contains type NULL"),
-                Ty::Bool => _primitive_value!(Bool, i8),
-                Ty::TinyInt => _primitive_value!(TinyInt, i8),
-                Ty::SmallInt => _primitive_value!(SmallInt, i16),
-                Ty::Int => _primitive_value!(Int, i32),
-                Ty::BigInt => _primitive_value!(BigInt, i64),
-                Ty::Float => _primitive_value!(Float, f32),
-                Ty::Double => _primitive_value!(Double, f64),
-                Ty::VarChar => {
-                    let o1 = data_offset;
-                    let o2 = data_offset + std::mem::size_of::<i32>() * rows;
-                    data_offset = o2 + length;
-
-                    let offsets = Offsets::from(bytes.slice(o1..o2));
-                    let data = bytes.slice(o2..data_offset);
-
-                    ColumnView::VarChar(VarCharView { offsets, data })
-                }
-                Ty::Timestamp => {
-                    let o1 = data_offset;
-                    let o2 = data_offset + ((rows + 7) >> 3);
-                    data_offset = o2 + rows * std::mem::size_of::<i64>();
-                    let nulls = bytes.slice(o1..o2);
-                    let data = bytes.slice(o2..data_offset);
-                    ColumnView::Timestamp(TimestampView {
-                        nulls: NullBits(nulls),
-                        data,
-                        precision: precision,
-                    })
-                }
-                Ty::NChar => {
-                    let o1 = data_offset;
-                    let o2 = data_offset + std::mem::size_of::<i32>() * rows;
-                    data_offset = o2 + length;
-
-                    let offsets = Offsets::from(bytes.slice(o1..o2));
-                    let data = bytes.slice(o2..data_offset);
-
-                    ColumnView::NChar(NCharView {
-                        offsets,
-                        data,
-                        is_chars: UnsafeCell::new(true),
-                        version: Version::V3,
-                        layout: layout.clone(),
-                    })
-                }
-                Ty::UTinyInt => _primitive_value!(UTinyInt, u8),
-                Ty::USmallInt => _primitive_value!(USmallInt, u16),
-                Ty::UInt => _primitive_value!(UInt, u32),
-                Ty::UBigInt => _primitive_value!(UBigInt, u64),
-                Ty::Json => {
-                    let o1 = data_offset;
-                    let o2 = data_offset + std::mem::size_of::<i32>() * rows;
-                    data_offset = o2 + length;
-
-                    let offsets = Offsets::from(bytes.slice(o1..o2));
-                    let data = bytes.slice(o2..data_offset);
-
-                    ColumnView::Json(JsonView { offsets, data })
-                }
-                ty => {
-                    unreachable!("unsupported type: {ty}")
-                }
-            };
-            columns.push(column);
+            offsets.push(data_offset);
+            data_offset += column_data_len(schema.ty(), rows, length);
             debug_assert!(data_offset <= len);
         }
-        RawBlock {
+        let mut block = RawBlock {
             layout,
             version: Version::V3,
             data: Cell::new(bytes),
@@ -564,8 +725,173 @@ impl RawBlock {
             database: None,
             table: None,
             fields: Vec::new(),
-            columns,
+            typed_fields: Vec::new(),
+            name_index: HashMap::new(),
+            columns: (0..cols).map(|_| UnsafeCell::new(empty_view())).collect(),
+            parsed: (0..cols).map(|_| OnceCell::new()).collect(),
+            offsets,
+            null_policy: NullPolicy::default(),
+            tracked: Tracked::new(ObjectKind::RawBlock),
+        };
+        block.index_fields();
+        block
+    }
+
+    /// Parse v3 raw bytes of a block from a server, fails with [std::io::ErrorKind::InvalidData]
+    /// if a column is of a type unknown to this version, eg. added by a newer server.
+    ///
+    /// Column data is trusted as by [RawBlock::parse_from_raw_block], untrusted bytes, eg. of a
+    /// spool file, are fully validated when read as inlined blocks.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let mut bytes = views_to_raw_block(&[ColumnView::from_ints(vec![1])]);
+    /// bytes[28] = 42; // type id of the first column.
+    /// let err = RawBlock::try_parse_from_raw_block(bytes, Precision::Millisecond).unwrap_err();
+    /// assert_eq!(err.to_string(), "unknown data type 42 of column 0");
+    /// ```
+    pub fn try_parse_from_raw_block(
+        bytes: impl Into<Bytes>,
+        precision: Precision,
+    ) -> std::io::Result<Self> {
+        let bytes = bytes.into();
+        validate::check_column_types(&bytes)?;
+        Ok(Self::parse_from_raw_block(bytes, precision))
+    }
+
+    /// A valid block with no rows, eg. the initial value to fold blocks with [RawBlock::concat].
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::{views_to_raw_block, ColSchema}, ColumnView, Precision, RawBlock, Ty};
+    /// let views = [ColumnView::from_millis_timestamp(vec![0, 1]), ColumnView::from_ints(vec![1, 2])];
+    /// let blocks = (0..3)
+    ///     .map(|_| RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond));
+    /// let schema = [ColSchema::new(Ty::Timestamp, 8), ColSchema::new(Ty::Int, 4)];
+    /// let block = blocks.fold(RawBlock::empty(&schema, Precision::Millisecond), |acc, block| {
+    ///     acc.concat(&block)
+    /// });
+    /// assert_eq!(block.nrows(), 6);
+    /// ```
+    pub fn empty(schema: &[ColSchema], precision: Precision) -> Self {
+        let cols = schema.len();
+        // data of each column is empty in zero rows, eg. the null bitmap has no bytes.
+        let len = std::mem::size_of::<Header>()
+            + cols * (std::mem::size_of::<ColSchema>() + std::mem::size_of::<u32>());
+        let mut header = Header::default();
+        header.set_len(len);
+        header.set_ncols(cols);
+
+        let mut bytes = Vec::with_capacity(len);
+        bytes.extend(header.as_bytes());
+        for col in schema {
+            bytes.extend(col.as_bytes());
         }
+        bytes.resize(len, 0);
+        Self::parse_from_raw_block(bytes, precision)
+    }
+
+    /// Parse view of column `col` in v3 raw bytes.
+    fn parse_column(&self, col: usize) -> ColumnView {
+        let bytes = unsafe { &*self.data.as_ptr() };
+        let rows = self.rows;
+        let length = unsafe { self.lengths.get_unchecked(col) } as usize;
+        let schema = unsafe { self.schemas.get_unchecked(col) };
+        let o1 = self.offsets[col];
+
+        macro_rules! _primitive_value {
+            ($ty:ident, $prim:ty) => {{
+                let o2 = o1 + ((rows + 7) >> 3); // null bitmap len.
+                let nulls = bytes.slice(o1..o2);
+                let data = bytes.slice(o2..o2 + rows * std::mem::size_of::<$prim>());
+                ColumnView::$ty(paste::paste! {[<$ty View>] {
+                    nulls: NullBits(nulls),
+                    data,
+                }})
+            }};
+        }
+
+        match schema.ty() {
+            Ty::Null => unreachable!("raw block does not contains type NULL"),
+            Ty::Bool => _primitive_value!(Bool, i8),
+            Ty::TinyInt => _primitive_value!(TinyInt, i8),
+            Ty::SmallInt => _primitive_value!(SmallInt, i16),
+            Ty::Int => _primitive_value!(Int, i32),
+            Ty::BigInt => _primitive_value!(BigInt, i64),
+            Ty::Float => _primitive_value!(Float, f32),
+            Ty::Double => _primitive_value!(Double, f64),
+            Ty::VarChar => {
+                let o2 = o1 + std::mem::size_of::<i32>() * rows;
+                let offsets = Offsets::from(bytes.slice(o1..o2));
+                let data = bytes.slice(o2..o2 + length);
+
+                ColumnView::VarChar(VarCharView { offsets, data })
+            }
+            Ty::Timestamp => {
+                let o2 = o1 + ((rows + 7) >> 3);
+                let nulls = bytes.slice(o1..o2);
+                let data = bytes.slice(o2..o2 + rows * std::mem::size_of::<i64>());
+                ColumnView::Timestamp(TimestampView {
+                    nulls: NullBits(nulls),
+                    data,
+                    precision: self.precision,
+                })
+            }
+            Ty::NChar => {
+                let o2 = o1 + std::mem::size_of::<i32>() * rows;
+                let offsets = Offsets::from(bytes.slice(o1..o2));
+                let data = bytes.slice(o2..o2 + length);
+
+                ColumnView::NChar(NCharView {
+                    offsets,
+                    data,
+                    is_chars: UnsafeCell::new(true),
+                    version: Version::V3,
+                    layout: self.layout.clone(),
+                })
+            }
+            Ty::UTinyInt => _primitive_value!(UTinyInt, u8),
+            Ty::USmallInt => _primitive_value!(USmallInt, u16),
+            Ty::UInt => _primitive_value!(UInt, u32),
+            Ty::UBigInt => _primitive_value!(UBigInt, u64),
+            Ty::Json => {
+                let o2 = o1 + std::mem::size_of::<i32>() * rows;
+                let offsets = Offsets::from(bytes.slice(o1..o2));
+                let data = bytes.slice(o2..o2 + length);
+
+                ColumnView::Json(JsonView { offsets, data })
+            }
+            Ty::VarBinary => {
+                let o2 = o1 + std::mem::size_of::<i32>() * rows;
+                let offsets = Offsets::from(bytes.slice(o1..o2));
+                let data = bytes.slice(o2..o2 + length);
+
+                ColumnView::VarBinary(VarBinaryView { offsets, data })
+            }
+            Ty::Geometry => {
+                let o2 = o1 + std::mem::size_of::<i32>() * rows;
+                let offsets = Offsets::from(bytes.slice(o1..o2));
+                let data = bytes.slice(o2..o2 + length);
+
+                ColumnView::Geometry(GeometryView { offsets, data })
+            }
+            ty => {
+                unreachable!("unsupported type: {ty}")
+            }
+        }
+    }
+
+    /// View of column `col`, parsed on first access.
+    ///
+    /// Blocks are shared between threads, the first access parses the view and the others wait
+    /// for it.
+    #[inline]
+    fn view(&self, col: usize) -> &ColumnView {
+        let cell = &self.columns[col];
+        self.parsed[col].get_or_init(|| unsafe {
+            // no reference to an unparsed view is handed out, so it's written exclusively.
+            *cell.get() = self.parse_column(col);
+        });
+        unsafe { &*cell.get() }
     }
 
     /// Set table name of the block
@@ -588,9 +914,65 @@ impl RawBlock {
     ) -> &mut Self {
         self.fields = names.into_iter().map(|name| name.into()).collect();
         self.layout.borrow_mut().with_field_names();
+        self.index_fields();
         self
     }
 
+    /// Rename duplicated field names by `strategy`, so rows deserialize into distinct fields.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, DedupStrategy, Precision, RawBlock};
+    /// let views = [
+    ///     ColumnView::from_millis_timestamp(vec![0]),
+    ///     ColumnView::from_millis_timestamp(vec![1]),
+    /// ];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "ts"]);
+    /// block.dedup_field_names(DedupStrategy::Suffix);
+    /// assert_eq!(block.field_names(), ["ts_1", "ts_2"]);
+    /// ```
+    pub fn dedup_field_names(&mut self, strategy: DedupStrategy) -> &mut Self {
+        let names = strategy.dedup(self.fields.iter().map(String::as_str));
+        if names != self.fields {
+            self.with_field_names(names);
+        }
+        self
+    }
+
+    /// Rebuild typed fields and name index from schemas and field names.
+    fn index_fields(&mut self) {
+        self.typed_fields = self
+            .schemas
+            .iter()
+            .enumerate()
+            .map(|(i, schema)| {
+                let name = self.fields.get(i).map(String::as_str).unwrap_or_default();
+                Field::new(name, schema.ty(), schema.len())
+            })
+            .collect();
+        self.name_index.clear();
+        for (i, name) in self.fields.iter().enumerate() {
+            self.name_index.entry(name.clone()).or_insert(i);
+        }
+    }
+
+    /// Set what non-`Option` fields receive on NULL in [RawBlock::deserialize], see [NullPolicy].
+    pub fn with_null_policy(&mut self, policy: NullPolicy) -> &mut Self {
+        self.null_policy = policy;
+        self
+    }
+
+    /// The [NullPolicy] used in deserializing.
+    pub fn null_policy(&self) -> &NullPolicy {
+        &self.null_policy
+    }
+
+    /// Count the block in live objects of a connection, see [crate::diagnostics].
+    #[doc(hidden)]
+    pub fn track_in(&mut self, scope: &Scope) {
+        self.tracked.set_scope(scope);
+    }
+
     fn with_layout(mut self, layout: Layout) -> Self {
         self.layout = Arc::new(RefCell::new(layout));
         self
@@ -640,38 +1022,148 @@ impl RawBlock {
         &self.fields
     }
 
-    /// Data view in columns.
+    /// Columns paired with their fields, without copying any data.
+    ///
+    /// Field names are empty if the block has no field names. All columns are parsed, use
+    /// [RawBlock::column] to parse only the ones in use.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = [
+    ///     ColumnView::from_millis_timestamp(vec![0, 1]),
+    ///     ColumnView::from_ints(vec![1, 2]),
+    /// ];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "v"]);
+    /// let header: Vec<_> = block.columns().map(|(field, _)| field.name()).collect();
+    /// assert_eq!(header, ["ts", "v"]);
+    /// for (field, view) in &block {
+    ///     assert!(!field.ty().is_var_type());
+    ///     assert_eq!(view.len(), block.nrows());
+    /// }
+    /// ```
     #[inline]
-    pub fn columns(&self) -> std::slice::Iter<ColumnView> {
-        self.columns.iter()
+    pub fn columns(&self) -> ColumnsIter<'_> {
+        ColumnsIter(self.typed_fields.iter().zip(self.column_views().iter()))
+    }
+
+    /// Find a column by field name.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock, Ty};
+    /// let views = [ColumnView::from_ints(vec![1, 2]), ColumnView::from_bools(vec![true, false])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["v", "b"]);
+    /// let (field, view) = block.column("b").unwrap();
+    /// assert_eq!(field.ty(), Ty::Bool);
+    /// assert_eq!(view.len(), 2);
+    /// assert!(block.column("c").is_none());
+    /// ```
+    pub fn column(&self, name: &str) -> Option<(&Field, &ColumnView)> {
+        self.name_index
+            .get(name)
+            .map(|&i| (&self.typed_fields[i], self.view(i)))
     }
 
+    /// Views of all columns, parsing the ones not accessed yet.
     pub fn column_views(&self) -> &[ColumnView] {
-        &self.columns
+        for col in 0..self.ncols() {
+            self.view(col);
+        }
+        // SAFETY: all views are parsed, and `UnsafeCell` has the same layout as its value.
+        unsafe { &*(&*self.columns as *const [UnsafeCell<ColumnView>] as *const [ColumnView]) }
     }
 
     /// Data view in rows.
     #[inline]
-    pub fn rows<'a>(&self) -> RowsIter<'a> {
-        RowsIter {
-            raw: NonNull::new(self as *const Self as *mut Self).unwrap(),
-            row: 0,
-            _marker: std::marker::PhantomData,
-        }
+    pub fn rows(&self) -> RowsIter<'_> {
+        RowsIter { raw: self, row: 0 }
     }
 
+    /// Data view in rows of the owned block, the rows share the block so they're still valid
+    /// after the iterator is advanced or dropped.
     #[inline]
     pub fn into_rows<'a>(self) -> IntoRowsIter<'a>
     where
         Self: 'a,
     {
         IntoRowsIter {
-            raw: self,
+            raw: Arc::new(self),
             row: 0,
             _marker: std::marker::PhantomData,
         }
     }
 
+    /// Append rows of `rhs` to a new block.
+    ///
+    /// Blocks with no rows are identities, so the precision and field names are taken from the
+    /// block with rows. Lengths of columns are the larger one of the two blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the blocks have different column types, or rows in different precisions, use
+    /// [RawBlock::try_concat] to check them.
+    pub fn concat(&self, rhs: &RawBlock) -> RawBlock {
+        assert_eq!(
+            self.ncols(),
+            rhs.ncols(),
+            "concat blocks of different number of columns"
+        );
+        let schemas = self
+            .schemas()
+            .iter()
+            .zip(rhs.schemas())
+            .map(|(l, r)| {
+                assert_eq!(l.ty(), r.ty(), "concat blocks of different column types");
+                ColSchema::new(l.ty(), l.len().max(r.len()))
+            })
+            .collect_vec();
+        let (base, precision) = match (self.nrows(), rhs.nrows()) {
+            (0, _) => (rhs, rhs.precision()),
+            (_, 0) => (self, self.precision()),
+            _ => {
+                assert_eq!(
+                    self.precision(),
+                    rhs.precision(),
+                    "concat blocks of different precisions"
+                );
+                (self, self.precision())
+            }
+        };
+        let views = self
+            .column_views()
+            .iter()
+            .zip(rhs.column_views())
+            .map(|(l, r)| l.concat(r))
+            .collect_vec();
+        let bytes = views::views_to_raw_block_with_schemas(&views, &schemas);
+        let mut block = RawBlock::parse_from_raw_block(bytes, precision);
+        let names = if base.field_names().is_empty() {
+            self.field_names()
+        } else {
+            base.field_names()
+        };
+        block.with_field_names(names);
+        if let Some(name) = self.table_name().or(rhs.table_name()) {
+            block.with_table_name(name);
+        }
+        if let Some(name) = self.tmq_db_name().or(rhs.tmq_db_name()) {
+            block.with_database_name(name);
+        }
+        block
+    }
+
+    /// Deserialize rows into `T`, eg. tuples by column order or structs by field names.
+    ///
+    /// Structs only read the columns of their fields, in the order of the fields, so a struct of
+    /// a few fields costs the same on a wide block. Columns not in the struct are skipped, even
+    /// with `#[serde(deny_unknown_fields)]`, and the first column wins if names are duplicated.
+    /// Use [RawBlock::deserialize_buffered] for untagged enums or to deny unknown columns.
+    ///
+    /// Timestamps keep the precision of the block: `i64` is the raw epoch in the precision,
+    /// `String` is RFC3339 with all its sub-second digits, and chrono `DateTime`,
+    /// `NaiveDateTime` (in UTC) and `chrono::serde::ts_*` fields are supported with the
+    /// default feature `chrono-serde`.
     #[inline]
     pub fn deserialize<'de, 'a: 'de, T>(
         &'a self,
@@ -682,9 +1174,21 @@ impl RawBlock {
         self.rows().map(|mut row| T::deserialize(&mut row))
     }
 
+    /// Deserialize rows materialized by [RowView::into_buffered], for types that buffer their
+    /// input like untagged enums, at the cost of an allocation per row.
+    #[inline]
+    pub fn deserialize_buffered<'de, 'a: 'de, T>(
+        &'a self,
+    ) -> std::iter::Map<rows::RowsIter<'_>, fn(RowView<'a>) -> Result<T, DeError>>
+    where
+        T: Deserialize<'de>,
+    {
+        self.rows().map(|row| T::deserialize(row.into_buffered()))
+    }
+
     pub fn as_raw_bytes(&self) -> &[u8] {
         if self.layout.borrow().schema_changed() {
-            let bytes = views_to_raw_block(&self.columns);
+            let bytes = views_to_raw_block(self.column_views());
             let bytes = bytes.into();
             self.data.replace(bytes);
             self.layout.borrow_mut().set_schema_changed(false);
@@ -698,7 +1202,7 @@ impl RawBlock {
         if row >= self.nrows() || col >= self.ncols() {
             return true;
         }
-        unsafe { self.columns.get_unchecked(col).is_null_unchecked(row) }
+        unsafe { self.view(col).is_null_unchecked(row) }
     }
 
     #[inline]
@@ -708,7 +1212,7 @@ impl RawBlock {
         row: usize,
         col: usize,
     ) -> (Ty, u32, *const c_void) {
-        let view = self.columns.get_unchecked(col);
+        let view = self.view(col);
         view.get_raw_value_unchecked(row)
     }
 
@@ -722,15 +1226,54 @@ impl RawBlock {
     #[inline]
     /// Get one value at `(row, col)` of the block.
     pub unsafe fn get_ref_unchecked(&self, row: usize, col: usize) -> BorrowedValue {
-        self.columns.get_unchecked(col).get_ref_unchecked(row)
+        self.view(col).get_ref_unchecked(row)
     }
 
     // unsafe fn get_col_unchecked(&self, col: usize) -> &ColumnView {
     //     self.columns.get_unchecked(col)
     // }
 
+    /// Row-based 2-dimension matrix of owned values, same as [RawBlock::rows_to_values].
     pub fn to_values(&self) -> Vec<Vec<Value>> {
-        self.rows().map(|row| row.into_values()).collect_vec()
+        self.rows_to_values()
+    }
+
+    /// Convert all rows into owned values, column by column.
+    ///
+    /// Each row vector is allocated once with the column count of the schema, and values are
+    /// converted by the typed column view so the type dispatch is done per column instead of
+    /// per value. Strings are copied straight from the block into exactly sized buffers. It
+    /// yields the same values as collecting [RowView::into_values] of all [rows](Self::rows).
+    pub fn rows_to_values(&self) -> Vec<Vec<Value>> {
+        let (nrows, ncols) = (self.nrows(), self.ncols());
+        let mut rows: Vec<Vec<Value>> = (0..nrows).map(|_| Vec::with_capacity(ncols)).collect();
+        for col in 0..ncols {
+            let view = self.view(col);
+            match view {
+                ColumnView::VarChar(view) => {
+                    for (row, s) in rows.iter_mut().zip(view.iter()) {
+                        row.push(match s {
+                            Some(s) => Value::VarChar(s.as_str().to_owned()),
+                            None => Value::Null(Ty::VarChar),
+                        });
+                    }
+                }
+                ColumnView::NChar(view) => {
+                    for (row, s) in rows.iter_mut().zip(view.iter()) {
+                        row.push(match s {
+                            Some(s) => Value::NChar(s.to_owned()),
+                            None => Value::Null(Ty::NChar),
+                        });
+                    }
+                }
+                _ => {
+                    for (row, value) in rows.iter_mut().zip(view.iter()) {
+                        row.push(value.into_value());
+                    }
+                }
+            }
+        }
+        rows
     }
 
     pub fn write<W: std::io::Write>(&self, _wtr: W) -> std::io::Result<usize> {
@@ -745,14 +1288,24 @@ impl RawBlock {
         self.schemas()
             .iter()
             .zip(self.field_names())
-            .map(|(schema, name)| Field::new(name, schema.ty, schema.len))
+            .map(|(schema, name)| Field::new(name, schema.ty(), schema.len()))
             .collect_vec()
     }
 
+    /// A stable hash of the fields and precision of the block, see [crate::helpers::schema_hash].
+    pub fn schema_hash(&self) -> u64 {
+        crate::helpers::schema_hash(&self.fields(), self.precision())
+    }
+
     pub fn pretty_format(&self) -> PrettyBlock {
         PrettyBlock::new(self)
     }
 
+    /// Debug wrapper to print all the rows and columns of the block.
+    pub fn debug_full(&self) -> DebugBlock<'_> {
+        DebugBlock::full(self)
+    }
+
     // pub fn fields_iter(&self) -> impl Iterator<Item = Field> + '_ {
     //     self.schemas()
     //         .iter()
@@ -768,6 +1321,46 @@ impl RawBlock {
     }
 }
 
+/// Iterator of `(&Field, &ColumnView)` pairs, see [RawBlock::columns].
+#[derive(Debug, Clone)]
+pub struct ColumnsIter<'a>(
+    std::iter::Zip<std::slice::Iter<'a, Field>, std::slice::Iter<'a, ColumnView>>,
+);
+
+impl<'a> Iterator for ColumnsIter<'a> {
+    type Item = (&'a Field, &'a ColumnView);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for ColumnsIter<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+impl<'a> ExactSizeIterator for ColumnsIter<'a> {}
+
+impl<'a> std::iter::FusedIterator for ColumnsIter<'a> {}
+
+impl<'a> IntoIterator for &'a RawBlock {
+    type Item = (&'a Field, &'a ColumnView);
+    type IntoIter = ColumnsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.columns()
+    }
+}
+
 pub struct PrettyBlock<'a> {
     raw: &'a RawBlock,
 }
@@ -808,6 +1401,32 @@ impl<'a> Display for PrettyBlock<'a> {
 
 struct InlineBlock(Bytes);
 
+impl InlineBlock {
+    /// Bytes of the version and length read, the length includes both of them.
+    fn prefix(version: u32, len: u32) -> std::io::Result<Vec<u8>> {
+        if (len as usize) < std::mem::size_of::<Header>() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("raw block length {len} is shorter than its header"),
+            ));
+        }
+        let mut bytes = Vec::with_capacity(std::mem::size_of::<u32>() * 2);
+        bytes.extend(version.to_le_bytes());
+        bytes.extend(len.to_le_bytes());
+        Ok(bytes)
+    }
+
+    fn complete(bytes: Vec<u8>, len: u32) -> std::io::Result<Self> {
+        if bytes.len() != len as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("raw block truncated at {} of {len} bytes", bytes.len()),
+            ));
+        }
+        Ok(Self(bytes.into()))
+    }
+}
+
 impl From<InlineBlock> for Bytes {
     fn from(raw: InlineBlock) -> Self {
         raw.0
@@ -820,25 +1439,14 @@ impl crate::prelude::sync::Inlinable for InlineBlock {
         Self: Sized,
     {
         use crate::prelude::sync::InlinableRead;
+        use std::io::Read;
         let version = reader.read_u32()?;
         let len = reader.read_u32()?;
-        let mut bytes = Vec::with_capacity(len as usize);
-        bytes.resize(len as usize, 0);
-        unsafe {
-            std::ptr::copy_nonoverlapping(
-                version.to_le_bytes().as_ptr(),
-                bytes.as_mut_ptr(),
-                std::mem::size_of::<u32>(),
-            );
-            std::ptr::copy_nonoverlapping(
-                len.to_le_bytes().as_ptr(),
-                bytes.as_mut_ptr().offset(4),
-                std::mem::size_of::<u32>(),
-            );
-        }
-        let buf = &mut bytes[8..];
-        reader.read_exact(buf)?;
-        Ok(Self(bytes.into()))
+        let mut bytes = InlineBlock::prefix(version, len)?;
+        // the length is untrusted, the buffer grows as bytes are read.
+        let rest = len as u64 - bytes.len() as u64;
+        reader.take(rest).read_to_end(&mut bytes)?;
+        InlineBlock::complete(bytes, len)
     }
 
     fn write_inlined<W: std::io::Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
@@ -858,23 +1466,11 @@ impl crate::prelude::AsyncInlinable for InlineBlock {
 
         let version = reader.read_u32_le().await?;
         let len = reader.read_u32_le().await?;
-        let mut bytes = Vec::with_capacity(len as usize);
-        bytes.resize(len as usize, 0);
-        unsafe {
-            std::ptr::copy_nonoverlapping(
-                version.to_le_bytes().as_ptr(),
-                bytes.as_mut_ptr(),
-                std::mem::size_of::<u32>(),
-            );
-            std::ptr::copy_nonoverlapping(
-                len.to_le_bytes().as_ptr(),
-                bytes.as_mut_ptr().offset(4),
-                std::mem::size_of::<u32>(),
-            );
-        }
-        let buf = &mut bytes[8..];
-        reader.read_exact(buf).await?;
-        Ok(Self(bytes.into()))
+        let mut bytes = InlineBlock::prefix(version, len)?;
+        // the length is untrusted, the buffer grows as bytes are read.
+        let rest = len as u64 - bytes.len() as u64;
+        reader.take(rest).read_to_end(&mut bytes).await?;
+        InlineBlock::complete(bytes, len)
     }
 
     async fn write_inlined<W: tokio::io::AsyncWrite + Send + Unpin>(
@@ -887,15 +1483,79 @@ impl crate::prelude::AsyncInlinable for InlineBlock {
     }
 }
 
+/// Inlined raw block, the format of [Inlinable](crate::prelude::sync::Inlinable) and
+/// [AsyncInlinable](crate::prelude::AsyncInlinable):
+///
+/// ```text,ignore
+/// +--------+-----------+------------------------------+----------------------------------+
+/// | layout | raw block | table name, optional         | field names, optional            |
+/// | 4 B    | length B  | 2 B length + bytes           | (1 B length + bytes) * cols      |
+/// +--------+-----------+------------------------------+----------------------------------+
+/// ```
+///
+/// The layout records the precision and the optional parts following the raw block, which
+/// starts with its version and length, see [Header]. Layout `0xFFFFFFFF` marks the end of blocks.
+impl RawBlock {
+    /// Layout and v3 raw bytes to inline, NChar columns decoded in place are encoded again.
+    fn inlined_parts(&self) -> (Layout, std::borrow::Cow<'_, [u8]>) {
+        let raw = self.as_raw_bytes();
+        let mut layout = self.layout();
+        layout.with_precision(self.precision);
+        if !layout.nchar_is_decoded() {
+            return (layout, raw.into());
+        }
+        layout.set(Layout::NCHAR_IS_DECODED, false);
+        (layout, self.encode_nchar().into())
+    }
+
+    /// V3 raw bytes of the column views, with NChar values in chars.
+    fn encode_nchar(&self) -> Vec<u8> {
+        let mut bytes = views_to_raw_block_with_schemas(self.column_views(), &self.schemas);
+        let header = unsafe { &mut *(bytes.as_mut_ptr() as *mut Header) };
+        header.set_group_id(self.group_id);
+        bytes
+    }
+
+    fn inlined_layout(bits: u32) -> std::io::Result<Layout> {
+        Layout::from_bits(bits)
+            .filter(|layout| {
+                *layout & Layout::PRECISION_MASK != Layout::PRECISION_MASK
+                    && *layout & Layout::FORMAT_MASK == Layout::IS_RAW_DATA
+            })
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid raw block layout: {bits:#x}"),
+                )
+            })
+    }
+
+    /// Parse an inlined raw block, which is validated first as it may be corrupted.
+    fn from_inlined(layout: Layout, raw: InlineBlock) -> std::io::Result<Self> {
+        validate::check_raw_block(&raw.0, layout.nchar_is_decoded())?;
+        let block = Self::parse_from_raw_block(raw.0, layout.precision());
+        if !layout.nchar_is_decoded() {
+            return Ok(block);
+        }
+        // Inlined with NChar in UTF-8 by earlier versions, parse them as decoded.
+        for view in block.column_views() {
+            if let ColumnView::NChar(view) = view {
+                unsafe { *view.is_chars.get() = false };
+            }
+        }
+        Ok(Self::parse_from_raw_block(
+            block.encode_nchar(),
+            layout.precision(),
+        ))
+    }
+}
+
 impl crate::prelude::sync::Inlinable for RawBlock {
     fn read_inlined<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
         use crate::prelude::sync::InlinableRead;
-        let layout = reader.read_u32()?;
-        let layout = Layout::from_bits(layout).expect("should be layout");
-
-        let precision = layout.precision();
+        let layout = Self::inlined_layout(reader.read_u32()?)?;
         let raw: InlineBlock = reader.read_inlinable()?;
-        let mut raw = Self::parse_from_raw_block(raw.0, precision);
+        let mut raw = Self::from_inlined(layout, raw)?;
         let cols = raw.ncols();
 
         if layout.expect_table_name() {
@@ -918,11 +1578,9 @@ impl crate::prelude::sync::Inlinable for RawBlock {
         if layout == 0xFFFFFFFF {
             return Ok(None);
         }
-        let layout = Layout::from_bits(layout).expect("should be layout");
-
-        let precision = layout.precision();
+        let layout = Self::inlined_layout(layout)?;
         let raw: InlineBlock = reader.read_inlinable()?;
-        let mut raw = Self::parse_from_raw_block(raw.0, precision);
+        let mut raw = Self::from_inlined(layout, raw)?;
 
         if layout.expect_table_name() {
             let name = reader.read_inlined_str::<2>()?;
@@ -946,11 +1604,10 @@ impl crate::prelude::sync::Inlinable for RawBlock {
 
     fn write_inlined<W: std::io::Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
         use crate::prelude::sync::InlinableWrite;
-        let layout = self.layout();
+        let (layout, raw) = self.inlined_parts();
         let mut l = wtr.write_u32_le(layout.as_inner())?;
 
-        let raw = self.as_raw_bytes();
-        wtr.write_all(raw)?;
+        wtr.write_all(&raw)?;
         l += raw.len();
 
         if layout.expect_table_name() {
@@ -978,13 +1635,10 @@ impl crate::prelude::AsyncInlinable for RawBlock {
         if layout == 0xFFFFFFFF {
             return Ok(None);
         }
-        let layout = Layout::from_bits(layout).expect("invalid layout");
-
-        let precision = layout.precision();
-
+        let layout = Self::inlined_layout(layout)?;
         let raw: InlineBlock =
             <InlineBlock as crate::prelude::AsyncInlinable>::read_inlined(reader).await?;
-        let mut raw = Self::parse_from_raw_block(raw.0, precision);
+        let mut raw = Self::from_inlined(layout, raw)?;
 
         if layout.expect_table_name() {
             let name = reader.read_inlined_str::<2>().await?;
@@ -1019,11 +1673,10 @@ impl crate::prelude::AsyncInlinable for RawBlock {
         use crate::util::AsyncInlinableWrite;
         use tokio::io::*;
 
-        let layout = self.layout();
+        let (layout, raw) = self.inlined_parts();
         wtr.write_u32_le(layout.as_inner()).await?;
 
-        let raw = self.as_raw_bytes();
-        wtr.write_all(raw).await?;
+        wtr.write_all(&raw).await?;
 
         let mut l = std::mem::size_of::<u32>() + raw.len();
 
@@ -1088,9 +1741,23 @@ async fn test_raw_from_v2() {
         location: String,
     }
     let rows: Vec<Record> = block.deserialize().try_collect().unwrap();
+    use crate::common::Timestamp;
+    let voltages: Vec<_> = rows.iter().map(|r| r.voltage).collect();
+    assert_eq!(voltages, [115, 113, 115, 116, 117, 116, 110, 110, 110, 114]);
+    assert!(rows
+        .iter()
+        .all(|r| r.group_id == 9 && r.location == "Los Angles"));
+    assert_eq!(rows[0].current, 9.65773);
+    assert_eq!(rows[9].phase, 0.32396626);
+    for row in 0..10 {
+        assert_eq!(
+            block.get_ref(row, 0).unwrap().to_value(),
+            Value::Timestamp(Timestamp::Milliseconds(1500000000000 + row as i64 * 900000))
+        );
+    }
     dbg!(rows);
     // dbg!(block);
-    let bytes = views_to_raw_block(&block.columns);
+    let bytes = views_to_raw_block(block.column_views());
     let raw2 = RawBlock::parse_from_raw_block(bytes, block.precision);
     dbg!(&raw2);
     let inlined = raw2.inlined().await;
@@ -1157,9 +1824,83 @@ fn test_v2_full() {
         4,
         Precision::Millisecond,
     );
-    let bytes = views_to_raw_block(&block.columns);
+    let values = |block: &RawBlock| -> Vec<Vec<Value>> {
+        (0..block.nrows())
+            .map(|row| {
+                (0..block.ncols())
+                    .map(|col| block.get_ref(row, col).unwrap().to_value())
+                    .collect()
+            })
+            .collect()
+    };
+    use crate::common::Timestamp;
+    let rows = values(&block);
+    let json = Value::Json(serde_json::json!({"key": "数据"}));
+    let first = |ts, json: &Value| {
+        vec![
+            Value::Timestamp(Timestamp::Milliseconds(ts)),
+            Value::Bool(true),
+            Value::TinyInt(-1),
+            Value::SmallInt(-2),
+            Value::Int(-3),
+            Value::BigInt(-4),
+            Value::UTinyInt(1),
+            Value::USmallInt(2),
+            Value::UInt(3),
+            Value::UBigInt(4),
+            Value::VarChar("abc".to_string()),
+            Value::NChar("涛思".to_string()),
+            Value::Bool(false),
+            Value::TinyInt(-5),
+            Value::SmallInt(-6),
+            Value::Int(-7),
+            Value::BigInt(-8),
+            Value::UTinyInt(5),
+            Value::USmallInt(6),
+            Value::UInt(7),
+            Value::UBigInt(8),
+            Value::VarChar("def".to_string()),
+            Value::NChar("数据".to_string()),
+            json.clone(),
+        ]
+    };
+    assert_eq!(rows[0], first(0, &json));
+    assert_eq!(rows[1], first(1, &Value::Null(Ty::Json)));
+    for (row, ts) in [(2, 65535), (3, 65536)] {
+        assert_eq!(rows[row][0], Value::Timestamp(Timestamp::Milliseconds(ts)));
+        let fields = block.fields();
+        for (col, field) in fields.iter().enumerate().skip(1).take(22) {
+            assert_eq!(rows[row][col], Value::Null(field.ty()), "{}", field.name());
+        }
+    }
+    assert_eq!(rows[2][23], json);
+    assert_eq!(rows[3][23], Value::Null(Ty::Json));
+
+    let (bytes, lengths) = block.to_raw_block_v2();
+    assert_eq!(&lengths[..10], [8, 1, 1, 2, 4, 8, 1, 2, 4, 8]);
+    // Padded to the longest values with `u16` lengths, `def`, `数据` and `{"key":"数据"}`.
+    assert_eq!(lengths[21..], [5, 8, 18]);
+    let emitted = RawBlock::parse_from_raw_block_v2(
+        bytes,
+        &block.fields(),
+        &lengths,
+        4,
+        Precision::Millisecond,
+    );
+    assert_eq!(values(&emitted), rows);
+
+    let bytes = views_to_raw_block(block.column_views());
     let raw2 = RawBlock::parse_from_raw_block(bytes, block.precision);
-    dbg!(raw2);
+    assert_eq!(values(&raw2), rows);
+    let (bytes, lengths) = raw2.to_raw_block_v2();
+    let emitted = RawBlock::parse_from_raw_block_v2(
+        bytes,
+        &block.fields(),
+        &lengths,
+        4,
+        Precision::Millisecond,
+    );
+    assert_eq!(values(&emitted), rows);
 }
 
 #[test]
@@ -1172,7 +1913,7 @@ fn test_v2_null() {
         Precision::Millisecond,
     );
     dbg!(&raw);
-    let bytes = views_to_raw_block(&raw.columns);
+    let bytes = views_to_raw_block(raw.column_views());
     let raw2 = RawBlock::parse_from_raw_block(bytes, raw.precision);
     dbg!(raw2);
     let (_ty, _len, null) = unsafe { raw.get_raw_value_unchecked(0, 0) };
@@ -1276,3 +2017,982 @@ fn test_from_v2() {
 
     println!("{}", raw.pretty_format());
 }
+
+#[test]
+fn test_debug_summary() {
+    let views = [
+        ColumnView::from_ints((0..20).map(|i| (i % 7 != 0).then_some(i)).collect()),
+        ColumnView::from_varchar::<String, _, _, _>((0..20).map(|i| format!("v{i}"))),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names(["a", "b"]).with_table_name("tb1");
+
+    assert_eq!(
+        format!("{raw:?}"),
+        "RawBlock { shape: (20, 2), precision: Millisecond, table: Some(\"tb1\"), group_id: 0, \
+         fields: [\"a INT\", \"b BINARY\"], nulls: [3, 0], \
+         head: [[NULL, \"v0\"], [1, \"v1\"], [2, \"v2\"], [3, \"v3\"], [4, \"v4\"]], \
+         tail: [[15, \"v15\"], [16, \"v16\"], [17, \"v17\"], [18, \"v18\"], [19, \"v19\"]] }"
+    );
+    assert_eq!(
+        format!(
+            "{:?}",
+            raw.debug_full()
+                .with_head_rows(1)
+                .with_tail_rows(1)
+                .with_max_columns(1)
+        ),
+        "RawBlock { shape: (20, 2), precision: Millisecond, table: Some(\"tb1\"), group_id: 0, \
+         fields: [\"a INT\"], nulls: [3], head: [[NULL]], tail: [[19]], omitted_columns: 1 }"
+    );
+    assert_eq!(format!("{:?}", raw.debug_full()).matches("\"v").count(), 20);
+    assert_eq!(
+        format!("{:?}", raw.column_views()[0]),
+        "Int { len: 20, nulls: 3, head: [NULL, 1, 2, 3, 4], tail: [15, 16, 17, 18, 19] }"
+    );
+}
+
+#[test]
+fn test_null_policy() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Record {
+        ts: i64,
+        v: i32,
+        name: String,
+        opt: Option<i32>,
+    }
+    let views = [
+        ColumnView::from_millis_timestamp(vec![Some(1), None]),
+        ColumnView::from_ints(vec![Some(2), None]),
+        ColumnView::from_varchar::<&str, _, _, _>(vec![Some("abc"), None]),
+        ColumnView::from_ints(vec![Some(3), None]),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names(["ts", "v", "name", "opt"]);
+    let first = Record {
+        ts: 1,
+        v: 2,
+        name: "abc".to_string(),
+        opt: Some(3),
+    };
+
+    assert_eq!(raw.null_policy(), &NullPolicy::Error);
+    let records: Vec<Result<Record, _>> = raw.deserialize().collect();
+    assert_eq!(records[0].as_ref().unwrap(), &first);
+    assert!(records[1].is_err());
+
+    raw.with_null_policy(NullPolicy::Default);
+    let records: Vec<Record> = raw.deserialize().try_collect().unwrap();
+    assert_eq!(records[0], first);
+    assert_eq!(
+        records[1],
+        Record {
+            ts: 0,
+            v: 0,
+            name: String::new(),
+            opt: None,
+        }
+    );
+
+    // Numeric value into string column is deserialized as its string representation.
+    raw.with_null_policy(NullPolicy::Value(Value::Int(-1)));
+    let records: Vec<Record> = raw.deserialize().try_collect().unwrap();
+    assert_eq!(records[0], first);
+    assert_eq!(
+        records[1],
+        Record {
+            ts: -1,
+            v: -1,
+            name: "-1".to_string(),
+            opt: None,
+        }
+    );
+
+    // Tuples go through the policy as well.
+    raw.with_null_policy(NullPolicy::Default);
+    let values: Vec<(i64, i32, String, Option<i32>)> = raw.deserialize().try_collect().unwrap();
+    assert_eq!(values[1], (0, 0, String::new(), None));
+}
+
+#[test]
+fn test_deserialize_requested_fields() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Record {
+        name: String,
+        #[serde(alias = "value")]
+        v: i32,
+        ts: i64,
+        missing: Option<i32>,
+    }
+    let views = [
+        ColumnView::from_millis_timestamp(vec![1, 2]),
+        ColumnView::from_bools(vec![true, false]),
+        ColumnView::from_ints(vec![Some(10), Some(20)]),
+        ColumnView::from_varchar::<&str, _, _, _>(vec![Some("a"), Some("b")]),
+        ColumnView::from_ints(vec![Some(30), Some(40)]),
+        ColumnView::from_doubles(vec![Some(0.5), None]),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    // columns are out of order, with a duplicated name and some not in the struct.
+    raw.with_field_names(["ts", "b", "v", "name", "v", "d"]);
+    let records: Vec<Record> = raw.deserialize().try_collect().unwrap();
+    assert_eq!(
+        records,
+        [
+            Record {
+                name: "a".to_string(),
+                v: 10,
+                ts: 1,
+                missing: None,
+            },
+            Record {
+                name: "b".to_string(),
+                v: 20,
+                ts: 2,
+                missing: None,
+            },
+        ]
+    );
+
+    // found by alias.
+    raw.with_field_names(["ts", "b", "value", "name", "c", "d"]);
+    let records: Vec<Record> = raw.deserialize().try_collect().unwrap();
+    assert_eq!(records[1].v, 20);
+
+    // columns not in the struct are skipped, even with `deny_unknown_fields`.
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Narrow {
+        ts: i64,
+    }
+    let records: Vec<Narrow> = raw.deserialize().try_collect().unwrap();
+    assert_eq!(records, [Narrow { ts: 1 }, Narrow { ts: 2 }]);
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Unknown {
+        ts: i64,
+        unknown: i32,
+    }
+    let err = raw.deserialize::<Unknown>().next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("missing field `unknown`"), "{err}");
+}
+
+#[test]
+fn test_deserialize_timestamp_precision() {
+    use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+
+    #[derive(Debug, Deserialize)]
+    struct Ts(DateTime<Utc>);
+
+    #[derive(Debug, Deserialize)]
+    struct Row {
+        raw: i64,
+        text: String,
+        naive: NaiveDateTime,
+        utc: DateTime<Utc>,
+        fixed: DateTime<FixedOffset>,
+        opt: Option<DateTime<Utc>>,
+        newtype: Ts,
+        #[serde(with = "chrono::serde::ts_nanoseconds")]
+        nanos: DateTime<Utc>,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        millis: DateTime<Utc>,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        secs: DateTime<Utc>,
+    }
+    let names = [
+        "raw", "text", "naive", "utc", "fixed", "opt", "newtype", "nanos", "millis", "secs",
+    ];
+
+    // nanoseconds of the values, the last digits of each precision are not zeros.
+    for (precision, digits, nanos) in [
+        (
+            Precision::Millisecond,
+            3,
+            [1_700_000_000_123_000_000, -1_000_000],
+        ),
+        (
+            Precision::Microsecond,
+            6,
+            [1_700_000_000_123_456_000, -1_000],
+        ),
+        (Precision::Nanosecond, 9, [1_700_000_000_123_456_789, -1]),
+    ] {
+        let per_tick = 1_000_000_000 / precision.ticks_per_second();
+        let raws = nanos.map(|ns: i64| ns / per_tick);
+        let views = names.map(|_| match precision {
+            Precision::Millisecond => ColumnView::from_millis_timestamp(raws.to_vec()),
+            Precision::Microsecond => ColumnView::from_micros_timestamp(raws.to_vec()),
+            Precision::Nanosecond => ColumnView::from_nanos_timestamp(raws.to_vec()),
+        });
+        let mut raw = RawBlock::parse_from_raw_block(views_to_raw_block(&views), precision);
+        raw.with_field_names(names);
+        let rows: Vec<Row> = raw.deserialize().try_collect().unwrap();
+
+        for ((row, ns), raw) in rows.iter().zip(nanos).zip(raws) {
+            let expected = Utc.timestamp_nanos(ns);
+            assert_eq!(row.raw, raw, "{precision}");
+            assert_eq!(row.utc, expected, "{precision}");
+            assert_eq!(row.fixed, expected, "{precision}");
+            assert_eq!(row.naive, expected.naive_utc(), "{precision}");
+            assert_eq!(row.opt, Some(expected), "{precision}");
+            assert_eq!(row.newtype.0, expected, "{precision}");
+            assert_eq!(row.nanos, expected, "{precision}");
+            // truncated to the unit of the field, towards negative infinity as chrono does.
+            assert_eq!(
+                row.millis.timestamp_millis(),
+                ns.div_euclid(1_000_000),
+                "{precision}"
+            );
+            assert_eq!(
+                row.secs.timestamp(),
+                ns.div_euclid(1_000_000_000),
+                "{precision}"
+            );
+
+            // RFC3339 with all sub-second digits of the precision.
+            assert_eq!(
+                DateTime::parse_from_rfc3339(&row.text).unwrap(),
+                expected,
+                "{precision}"
+            );
+            let fraction = row.text.split_once('.').unwrap().1;
+            assert_eq!(fraction.find(['+', '-']), Some(digits), "{}", row.text);
+        }
+    }
+}
+
+#[test]
+fn test_deserialize_buffered() {
+    use crate::common::Timestamp;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Location {
+        name: String,
+        #[serde(flatten)]
+        reading: Reading,
+    }
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Reading {
+        Current { current: f64, phase: Option<i32> },
+        Voltage { voltage: i32 },
+    }
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Record {
+        ts: Timestamp,
+        #[serde(flatten)]
+        location: Location,
+    }
+    let views = [
+        ColumnView::from_millis_timestamp(vec![1, 2]),
+        ColumnView::from_varchar::<&str, _, _, _>(vec![Some("a"), Some("b")]),
+        ColumnView::from_doubles(vec![Some(0.5), None]),
+        ColumnView::from_ints(vec![None, None]),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names(["ts", "name", "current", "phase"]);
+    let record: Record = raw.deserialize_buffered().next().unwrap().unwrap();
+    assert_eq!(
+        record,
+        Record {
+            ts: Timestamp::Milliseconds(1),
+            location: Location {
+                name: "a".to_string(),
+                reading: Reading::Current {
+                    current: 0.5,
+                    phase: None
+                },
+            },
+        }
+    );
+    // a NULL current matches neither variant.
+    assert!(raw.deserialize_buffered::<Record>().nth(1).unwrap().is_err());
+
+    raw.with_field_names(["ts", "name", "current", "voltage"]);
+    raw.with_null_policy(NullPolicy::Value(Value::Int(220)));
+    let records: Vec<Record> = raw.deserialize_buffered().try_collect().unwrap();
+    assert_eq!(
+        records[1].location.reading,
+        Reading::Current {
+            current: 220.,
+            phase: None
+        }
+    );
+    raw.with_null_policy(NullPolicy::Error);
+
+    // unknown columns are denied with buffered rows only.
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Narrow {
+        ts: i64,
+        name: String,
+    }
+    assert!(raw.deserialize::<Narrow>().all(|row| row.is_ok()));
+    let err = raw.deserialize_buffered::<Narrow>().next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("unknown field `current`"), "{err}");
+
+    // tuples and single values.
+    let values: Vec<(Timestamp, String, Option<f64>, Option<i32>)> =
+        raw.deserialize_buffered().try_collect().unwrap();
+    assert_eq!(values[1].2, None);
+    let views = [ColumnView::from_ints(vec![1, 2])];
+    let raw = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    let values: Vec<i32> = raw.deserialize_buffered().try_collect().unwrap();
+    assert_eq!(values, [1, 2]);
+}
+
+#[test]
+fn test_dedup_field_names() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Joined {
+        ts_1: i64,
+        ts_2: i64,
+        v: i32,
+    }
+    let views = [
+        ColumnView::from_millis_timestamp(vec![1, 2]),
+        ColumnView::from_ints(vec![10, 20]),
+        ColumnView::from_millis_timestamp(vec![3, 4]),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names(["ts", "v", "ts"]);
+    raw.dedup_field_names(DedupStrategy::Suffix);
+    assert_eq!(raw.field_names(), ["ts_1", "v", "ts_2"]);
+    assert_eq!(raw.column("ts_2").unwrap().0.ty(), Ty::Timestamp);
+    let rows: Vec<Joined> = raw.deserialize().try_collect().unwrap();
+    assert_eq!(
+        rows,
+        [
+            Joined {
+                ts_1: 1,
+                ts_2: 3,
+                v: 10,
+            },
+            Joined {
+                ts_1: 2,
+                ts_2: 4,
+                v: 20,
+            },
+        ]
+    );
+
+    // unique names are untouched.
+    raw.with_field_names(["a", "b", "c"]);
+    raw.dedup_field_names(DedupStrategy::KeepFirst);
+    assert_eq!(raw.field_names(), ["a", "b", "c"]);
+}
+
+#[test]
+fn test_deserialize_json() {
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Record {
+        ts: i64,
+        tags: serde_json::Value,
+        labels: HashMap<String, String>,
+        extra: Option<serde_json::Value>,
+        v: i32,
+    }
+    let labels = r#"{"location":"beijing","group":"2"}"#;
+    let views = [
+        ColumnView::from_millis_timestamp(vec![1, 2]),
+        ColumnView::from_json(vec![r#"{"id":1,"on":true}"#, r#"[1,"a",null]"#]),
+        ColumnView::from_json(vec![labels, "{}"]),
+        ColumnView::from_json::<&str, _, _, _>(vec![Some(r#"{"k":0.5}"#), None]),
+        ColumnView::from_ints(vec![10, 20]),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names(["ts", "tags", "labels", "extra", "v"]);
+    let records: Vec<Record> = raw.deserialize().try_collect().unwrap();
+    assert_eq!(
+        records,
+        [
+            Record {
+                ts: 1,
+                tags: serde_json::json!({"id": 1, "on": true}),
+                labels: serde_json::from_str(labels).unwrap(),
+                extra: Some(serde_json::json!({"k": 0.5})),
+                v: 10,
+            },
+            Record {
+                ts: 2,
+                tags: serde_json::json!([1, "a", null]),
+                labels: HashMap::new(),
+                extra: None,
+                v: 20,
+            },
+        ]
+    );
+
+    let ColumnView::Json(view) = &views[3] else {
+        unreachable!()
+    };
+    assert_eq!(view.get(0).map(|json| json.as_str()), Some(r#"{"k":0.5}"#));
+    assert!(view.get(1).is_none() && view.get(2).is_none());
+    let null = ColumnView::null(Ty::Json, 2);
+    assert_eq!(null.iter().filter(|v| v.is_null()).count(), 2);
+}
+
+#[test]
+fn test_columns_with_fields() {
+    let views = [
+        ColumnView::from_millis_timestamp(vec![0, 1, 2]),
+        ColumnView::from_ints(vec![1, 2, 3]),
+        ColumnView::from_varchar::<&str, _, _, _>(vec![Some("a"), None, Some("c")]),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+
+    // Fields are paired without names.
+    let columns = raw.columns();
+    assert_eq!(columns.len(), 3);
+    let tys: Vec<_> = columns
+        .map(|(field, _)| (field.name(), field.ty()))
+        .collect();
+    assert_eq!(tys, [("", Ty::Timestamp), ("", Ty::Int), ("", Ty::VarChar)]);
+    assert!(raw.column("v").is_none());
+
+    // Name index is rebuilt with field names, the first one wins for duplicated names.
+    raw.with_field_names(["ts", "v", "v"]);
+    let (field, view) = raw.column("v").unwrap();
+    assert_eq!(field.ty(), Ty::Int);
+    assert_eq!(view.get(2).unwrap().to_value(), Value::Int(3));
+
+    let (field, view) = (&raw).into_iter().next_back().unwrap();
+    assert_eq!(field.name(), "v");
+    assert!(view.get(1).unwrap().is_null());
+}
+
+// Header layout is fixed, fields are read at the byte offsets.
+const _: () = assert!(std::mem::size_of::<Header>() == 28);
+const _: () = assert!(std::mem::size_of::<ColSchema>() == 5);
+
+#[test]
+fn test_little_endian_block() {
+    // A block of (bool, varchar(10)) with 2 rows, all multi-byte fields are asymmetric so that
+    // a big-endian read gets garbage.
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend(2u32.to_le_bytes()); // version, newer than known
+    bytes.extend(0u32.to_le_bytes()); // length, set later
+    bytes.extend(2u32.to_le_bytes()); // rows
+    bytes.extend(2u32.to_le_bytes()); // cols
+    bytes.extend(0u32.to_le_bytes()); // flag
+    bytes.extend(0x0102_0304_0506_0708u64.to_le_bytes()); // group id
+    bytes.extend([Ty::Bool as u8, 1, 0, 0, 0]);
+    bytes.extend([Ty::VarChar as u8, 0x0A, 0x01, 0, 0]); // varchar(266)
+    bytes.extend(2u32.to_le_bytes()); // bool data length
+    bytes.extend(5u32.to_le_bytes()); // varchar data length
+    bytes.extend([0b0100_0000, 1, 0]); // bool column: nulls bitmap and values
+    bytes.extend((-1i32).to_le_bytes()); // varchar offsets
+    bytes.extend(0i32.to_le_bytes());
+    bytes.extend([3, 0, b'a', b'b', b'c']);
+    let len = bytes.len() as u32;
+    bytes[4..8].copy_from_slice(&len.to_le_bytes());
+
+    let mut raw = RawBlock::parse_from_raw_block(bytes.clone(), Precision::Millisecond);
+    raw.with_field_names(["b", "s"]);
+    assert_eq!(raw.nrows(), 2);
+    assert_eq!(raw.ncols(), 2);
+    assert_eq!(raw.group_id, 0x0102_0304_0506_0708);
+    assert_eq!(raw.schemas()[1].len(), 266);
+    let (field, _) = raw.columns().nth(1).unwrap();
+    assert_eq!(field.bytes(), 266);
+    assert_eq!(unsafe { raw.lengths.get_unchecked(1) }, 5);
+    let values: Vec<_> = raw.to_values();
+    assert_eq!(
+        values,
+        [
+            vec![Value::Bool(true), Value::Null(Ty::VarChar)],
+            vec![Value::Null(Ty::Bool), Value::VarChar("abc".to_string())]
+        ]
+    );
+
+    // Written blocks are little-endian too.
+    let views = [ColumnView::from_varchar::<&str, _, _, _>(vec![
+        None,
+        Some("abc"),
+    ])];
+    let written = views_to_raw_block(&views);
+    assert_eq!(written[0..4], 1u32.to_le_bytes());
+    assert_eq!(written[4..8], (written.len() as u32).to_le_bytes());
+    assert_eq!(written[8..12], 2u32.to_le_bytes());
+    assert_eq!(written[28], Ty::VarChar as u8);
+    assert_eq!(written[33..37], 5u32.to_le_bytes());
+    assert_eq!(written[37..45], bytes[bytes.len() - 13..bytes.len() - 5]);
+    assert_eq!(written[45..], [3, 0, b'a', b'b', b'c']);
+}
+
+#[test]
+fn test_lazy_column_views() {
+    let rows = 100;
+    let views = [
+        ColumnView::from_millis_timestamp((0..rows as i64).collect()),
+        ColumnView::from_ints((0..rows as i32).map(Some).collect()),
+        ColumnView::from_varchar::<String, _, _, _>(
+            (0..rows).map(|i| format!("v{i}")).collect::<Vec<_>>(),
+        ),
+        ColumnView::from_doubles((0..rows).map(|i| i as f64).collect()),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names(["ts", "i", "s", "d"]);
+    let parsed = |raw: &RawBlock| {
+        raw.parsed
+            .iter()
+            .map(|cell| cell.get().is_some())
+            .collect_vec()
+    };
+    assert_eq!(parsed(&raw), [false; 4]);
+
+    // Only accessed columns are parsed.
+    let (_, view) = raw.column("s").unwrap();
+    assert_eq!(
+        view.get(3).unwrap().to_value(),
+        Value::VarChar("v3".to_string())
+    );
+    assert_eq!(raw.get_ref(5, 1).unwrap().to_value(), Value::Int(5));
+    assert_eq!(parsed(&raw), [false, true, true, false]);
+
+    // Parsed concurrently by threads sharing the block.
+    let raw = Arc::new(raw);
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            let raw = raw.clone();
+            s.spawn(move || {
+                for row in 0..rows {
+                    let value = raw.get_ref(row, 3).unwrap().to_value();
+                    assert_eq!(value, Value::Double(row as f64));
+                }
+            });
+        }
+    });
+    assert_eq!(parsed(&raw), [false, true, true, true]);
+
+    // All columns are parsed for views of all columns.
+    assert_eq!(raw.columns().len(), 4);
+    assert_eq!(parsed(&raw), [true; 4]);
+    assert_eq!(raw.as_raw_bytes(), views_to_raw_block(&views));
+    let values = raw.to_values();
+    assert_eq!(
+        values[7][0],
+        Value::Timestamp(crate::common::Timestamp::Milliseconds(7))
+    );
+    assert_eq!(values[99][2], Value::VarChar("v99".to_string()));
+}
+
+#[test]
+fn test_rows_to_values() {
+    // 4-byte aligned lengths of columns, see the offsets of varchar/nchar views.
+    let rows = 64;
+    let nullable = |i: usize| (i % 7 != 0).then_some(i);
+    let views = [
+        ColumnView::from_millis_timestamp((0..rows as i64).collect()),
+        ColumnView::from_bools((0..rows).map(|i| nullable(i).map(|i| i % 2 == 0)).collect()),
+        ColumnView::from_tiny_ints((0..rows).map(|i| nullable(i).map(|i| i as i8)).collect()),
+        ColumnView::from_unsigned_big_ints((0..rows as u64).collect()),
+        ColumnView::from_floats((0..rows).map(|i| nullable(i).map(|i| i as f32)).collect()),
+        ColumnView::from_doubles((0..rows).map(|i| i as f64 / 2.).collect()),
+        ColumnView::from_varchar::<String, _, _, _>(
+            (0..rows)
+                .map(|i| nullable(i).map(|i| format!("varchar {i:02}")))
+                .collect::<Vec<_>>(),
+        ),
+        ColumnView::from_varchar::<String, _, _, _>(
+            (0..rows)
+                .map(|i| nullable(i).map(|i| format!("涛思 {i:04}")))
+                .collect::<Vec<_>>(),
+        ),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names((0..views.len()).map(|col| format!("c{col}")));
+
+    let values = raw.rows_to_values();
+    let expected = raw.rows().map(|row| row.into_values()).collect_vec();
+    assert_eq!(values, expected);
+    assert_eq!(values.len(), rows);
+    assert!(values.iter().all(|row| row.capacity() == views.len()));
+    assert_eq!(values[0][6], Value::Null(Ty::VarChar));
+    assert_eq!(values[0][7], Value::Null(Ty::VarChar));
+    assert_eq!(values[8][7], Value::VarChar("涛思 0008".to_string()));
+    assert_eq!(raw.to_values(), expected);
+
+    let empty = RawBlock::parse_from_raw_block(
+        views_to_raw_block(&[ColumnView::from_ints(Vec::<i32>::new())]),
+        Precision::Millisecond,
+    );
+    assert!(empty.rows_to_values().is_empty());
+}
+
+/// Rows are valid after the iterator is advanced or dropped, it's clean under Miri with
+/// `MIRIFLAGS=-Zmiri-tree-borrows`, as inlined strings are read past their length headers.
+#[test]
+fn test_rows_read_after_advancing() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Row {
+        v: Option<i32>,
+        s: Option<String>,
+    }
+    let views = [
+        ColumnView::from_ints(vec![Some(1), None, Some(3)]),
+        ColumnView::from_varchar::<&str, _, _, _>(vec![Some("a"), Some("涛思"), None]),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names(["v", "s"]);
+    let expected = raw.to_values();
+
+    // views of a borrowed block are read after the iterator is exhausted.
+    let rows: Vec<_> = raw.rows().collect();
+    let values = rows.into_iter().map(RowView::into_values).collect_vec();
+    assert_eq!(values, expected);
+
+    // views of an owned block outlive the iterator and each other.
+    let mut rows = raw.into_rows();
+    assert_eq!(rows.len(), 3);
+    let first = rows.next().unwrap();
+    let others: Vec<_> = rows.collect();
+    let names = first.map(|(name, _)| name.to_string()).collect_vec();
+    assert_eq!(names, ["v", "s"]);
+    let mut rows = others.into_iter().rev();
+    let last = rows.next().unwrap();
+    let mut second = rows.next().unwrap();
+    drop(rows);
+    assert_eq!(last.into_values(), expected[2]);
+    assert_eq!(
+        Row::deserialize(&mut second).unwrap(),
+        Row {
+            v: None,
+            s: Some("涛思".to_string())
+        }
+    );
+}
+
+#[test]
+fn test_empty_block() {
+    use crate::prelude::sync::Inlinable;
+
+    let schema = [
+        ColSchema::new(Ty::Timestamp, 8),
+        ColSchema::new(Ty::Bool, 1),
+        ColSchema::new(Ty::Int, 4),
+        ColSchema::new(Ty::UBigInt, 8),
+        ColSchema::new(Ty::Double, 8),
+        ColSchema::new(Ty::VarChar, 20),
+        ColSchema::new(Ty::NChar, 80),
+        ColSchema::new(Ty::Json, 4096),
+    ];
+    let mut empty = RawBlock::empty(&schema, Precision::Microsecond);
+    empty.with_field_names((0..schema.len()).map(|col| format!("c{col}")));
+    assert_eq!((empty.nrows(), empty.ncols()), (0, schema.len()));
+    assert_eq!(empty.precision(), Precision::Microsecond);
+    let lengths = empty.schemas().iter().map(ColSchema::len).collect_vec();
+    assert_eq!(lengths, [8, 1, 4, 8, 8, 20, 80, 4096]);
+    assert_eq!(empty.fields()[5].bytes(), 20);
+
+    for view in empty.column_views() {
+        assert_eq!(view.len(), 0);
+        assert!(view.slice(0..1).is_none());
+        let mut iter = view.iter();
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert!(iter.nth(3).is_none());
+        assert_eq!(view.iter().count(), 0);
+        assert_eq!(view.to_nulls_vec(), Vec::<bool>::new());
+    }
+    assert!(unsafe { empty.column_views()[0].as_timestamp_view() }
+        .iter()
+        .last()
+        .is_none());
+    assert_eq!(empty.rows().count(), 0);
+    assert!(empty.to_values().is_empty());
+    assert!(empty.rows_to_values().is_empty());
+    assert!(empty.get_ref(0, 0).is_none());
+    assert!(empty.is_null(0, 0));
+    let rows: Vec<(i64, bool)> = empty.deserialize().try_collect().unwrap();
+    assert!(rows.is_empty());
+
+    let pretty = empty.pretty_format().to_string();
+    assert!(
+        pretty.starts_with("Table view with 0 rows, 8 columns"),
+        "{pretty}"
+    );
+    assert!(pretty.contains("c7"), "{pretty}");
+    let debug = format!("{:?}", empty.debug_full());
+    assert!(debug.contains("shape: (0, 8)"), "{debug}");
+
+    // raw bytes of empty views are the same as the empty block.
+    let schemas = empty.schemas().to_vec();
+    let written = views::views_to_raw_block_with_schemas(empty.column_views(), &schemas);
+    assert_eq!(written, empty.as_raw_bytes());
+    let inlined = empty.inlined();
+    let read = RawBlock::read_inlined(&mut inlined.as_slice()).unwrap();
+    assert_eq!(read.nrows(), 0);
+    assert_eq!(read.field_names(), empty.field_names());
+    assert_eq!(read.as_raw_bytes(), empty.as_raw_bytes());
+}
+
+#[test]
+fn test_concat_empty_identity() {
+    let block = |start: i64, rows: usize| {
+        let views = [
+            ColumnView::Timestamp(TimestampView::from_micros(
+                (start..start + rows as i64).collect(),
+            )),
+            ColumnView::from_ints(
+                (0..rows as i32)
+                    .map(|i| (i % 3 != 0).then_some(i))
+                    .collect(),
+            ),
+            ColumnView::from_varchar::<String, _, _, _>(
+                (0..rows).map(|i| format!("v{start}-{i}")).collect_vec(),
+            ),
+        ];
+        let mut raw =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        raw.with_field_names(["ts", "v", "s"])
+            .with_table_name("tb1");
+        raw
+    };
+    let schema = [
+        ColSchema::new(Ty::Timestamp, 8),
+        ColSchema::new(Ty::Int, 4),
+        ColSchema::new(Ty::VarChar, 16),
+    ];
+    // precision of an empty block does not matter.
+    let empty = RawBlock::empty(&schema, Precision::Millisecond);
+
+    let one = block(0, 5);
+    for concat in [empty.concat(&one), one.concat(&empty)] {
+        assert_eq!(concat.precision(), Precision::Microsecond);
+        assert_eq!(concat.field_names(), ["ts", "v", "s"]);
+        assert_eq!(concat.table_name(), Some("tb1"));
+        assert_eq!(concat.schemas()[2].len(), 16);
+        assert_eq!(concat.to_values(), one.to_values());
+    }
+    let concat = empty.concat(&empty);
+    assert_eq!(concat.nrows(), 0);
+    assert_eq!(concat.schemas()[2].len(), 16);
+
+    let folded = [block(0, 5), block(5, 0), block(5, 7), block(12, 1)]
+        .iter()
+        .fold(
+            RawBlock::empty(&schema, Precision::Microsecond),
+            |acc, block| acc.concat(block),
+        );
+    assert_eq!(folded.nrows(), 13);
+    let expected = [block(0, 5), block(5, 7), block(12, 1)]
+        .iter()
+        .flat_map(RawBlock::to_values)
+        .collect_vec();
+    assert_eq!(folded.to_values(), expected);
+    let ts = folded.column_views()[0]
+        .iter()
+        .map(|v| v.to_timestamp())
+        .collect_vec();
+    assert!(ts
+        .iter()
+        .all(|ts| ts.unwrap().precision() == Precision::Microsecond));
+}
+#[cfg(test)]
+fn inlined_test_block(rows: usize) -> RawBlock {
+    let views = [
+        ColumnView::from_micros_timestamp((0..rows as i64).collect_vec()),
+        ColumnView::from_bools(
+            (0..rows)
+                .map(|i| (i % 3 != 0).then_some(i % 2 == 0))
+                .collect(),
+        ),
+        ColumnView::from_ints(
+            (0..rows as i32)
+                .map(|i| (i % 2 == 0).then_some(i))
+                .collect(),
+        ),
+        ColumnView::from_varchar::<String, _, _, _>(
+            (0..rows)
+                .map(|i| (i % 3 != 1).then(|| "v".repeat(i)))
+                .collect_vec(),
+        ),
+        ColumnView::from_nchar::<String, _, _, _>(
+            (0..rows)
+                .map(|i| (i % 3 != 2).then(|| "涛思".repeat(i)))
+                .collect_vec(),
+        ),
+        ColumnView::from_json::<String, _, _, _>(
+            (0..rows)
+                .map(|i| (i % 2 == 1).then(|| format!(r#"{{"k":{i}}}"#)))
+                .collect_vec(),
+        ),
+    ];
+    let mut block =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+    block
+        .with_field_names(["ts", "b", "i", "s", "n", "j"])
+        .with_table_name("tb1");
+    block
+}
+
+#[test]
+fn test_inlined_round_trip() {
+    use crate::prelude::sync::Inlinable;
+    for rows in [0, 1, 5] {
+        let block = inlined_test_block(rows);
+        let read = RawBlock::read_inlined(&mut block.inlined().as_slice()).unwrap();
+        assert_eq!(read.nrows(), rows);
+        assert_eq!(read.precision(), Precision::Microsecond);
+        assert_eq!(read.table_name(), Some("tb1"));
+        assert_eq!(read.field_names(), block.field_names());
+        assert_eq!(read.to_values(), block.to_values());
+    }
+
+    // NChar decoded in place is encoded again.
+    let block = inlined_test_block(5);
+    let values = block.to_values();
+    assert!(block.layout().nchar_is_decoded());
+    let inlined = block.inlined();
+    let read = RawBlock::read_inlined(&mut inlined.as_slice()).unwrap();
+    assert!(!read.layout().nchar_is_decoded());
+    assert_eq!(read.as_raw_bytes(), inlined_test_block(5).as_raw_bytes());
+    assert_eq!(read.to_values(), values);
+
+    // NChar in UTF-8 inlined by earlier versions.
+    let mut legacy = inlined.clone();
+    let layout = Layout::from_bits(u32::from_le_bytes(legacy[..4].try_into().unwrap())).unwrap();
+    legacy[..4].copy_from_slice(&(layout | Layout::NCHAR_IS_DECODED).as_inner().to_le_bytes());
+    // values of `read` are decoded in place above.
+    assert!(read.layout().nchar_is_decoded());
+    legacy[4..4 + read.as_raw_bytes().len()].copy_from_slice(read.as_raw_bytes());
+    let legacy = RawBlock::read_inlined(&mut legacy.as_slice()).unwrap();
+    assert_eq!(legacy.to_values(), values);
+}
+
+#[tokio::test]
+async fn test_inlined_round_trip_async() {
+    use crate::prelude::AsyncInlinable;
+    for rows in [0, 1, 5] {
+        let block = inlined_test_block(rows);
+        let mut inlined = block.inlined().await;
+        inlined.extend(0xFFFFFFFFu32.to_le_bytes());
+        let mut reader = inlined.as_slice();
+        let read = RawBlock::read_optional_inlined(&mut reader)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(read.precision(), Precision::Microsecond);
+        assert_eq!(read.table_name(), Some("tb1"));
+        assert_eq!(read.to_values(), block.to_values());
+        assert!(RawBlock::read_optional_inlined(&mut reader)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
+
+#[test]
+fn test_inlined_corrupted() {
+    use crate::prelude::sync::Inlinable;
+    let inlined = inlined_test_block(5).inlined();
+    for len in 0..inlined.len() {
+        let err = RawBlock::read_inlined(&mut &inlined[..len]).unwrap_err();
+        assert!(
+            matches!(
+                err.kind(),
+                std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::InvalidData
+            ),
+            "truncated at {len}: {err}"
+        );
+    }
+    // any corrupted byte is an error or a block safe to access.
+    for pos in 0..inlined.len() {
+        for value in [0x00, 0x01, 0x7F, 0x80, 0xFF] {
+            let mut corrupted = inlined.clone();
+            corrupted[pos] ^= value;
+            if let Ok(block) = RawBlock::read_inlined(&mut corrupted.as_slice()) {
+                // out of range timestamps are not a matter of parsing.
+                let values = block.column_views().iter().flat_map(|view| view.iter());
+                for value in values.filter(|v| !matches!(v, BorrowedValue::Timestamp(_))) {
+                    let _ = format!("{value:?}");
+                }
+            }
+        }
+    }
+
+    let header = 4;
+    let schema = header + std::mem::size_of::<Header>();
+    let lengths = schema + 6 * std::mem::size_of::<ColSchema>();
+    let set = |pos: usize, bytes: &[u8]| {
+        let mut corrupted = inlined.clone();
+        corrupted[pos..pos + bytes.len()].copy_from_slice(bytes);
+        RawBlock::read_inlined(&mut corrupted.as_slice()).unwrap_err()
+    };
+    // precision bits 0b11.
+    set(0, &0x0300u32.to_le_bytes());
+    // huge block length.
+    set(header + 4, &u32::MAX.to_le_bytes());
+    // huge rows and columns.
+    set(header + 8, &u32::MAX.to_le_bytes());
+    set(header + 12, &u32::MAX.to_le_bytes());
+    // type NULL and an unknown type.
+    set(schema, &[0]);
+    assert!(set(schema, &[42])
+        .to_string()
+        .contains("unknown data type 42"));
+    // length of the last column out of the block.
+    set(lengths + 5 * 4, &u32::MAX.to_le_bytes());
+}
+
+#[test]
+fn test_deserialize_bytes() {
+    use bytes::Bytes;
+    use serde_bytes::ByteBuf;
+
+    #[derive(Debug, Deserialize)]
+    struct Record {
+        payload: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        raw: Vec<u8>,
+        shared: Bytes,
+        buf: Option<ByteBuf>,
+        bin: ByteBuf,
+    }
+    // a protobuf message of `1: 150` and `2: [0xff, 0xfe]`, which is not valid UTF-8.
+    let payload: &[u8] = &[0x08, 0x96, 0x01, 0x12, 0x02, 0xff, 0xfe];
+    let payloads = || ColumnView::from_varchar_bytes::<&[u8], _, _, _>([Some(payload), None]);
+    let views = [
+        payloads(),
+        payloads(),
+        payloads(),
+        payloads(),
+        ColumnView::from_bytes(vec![payload, b""]),
+    ];
+    let mut raw =
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    raw.with_field_names(["payload", "raw", "shared", "buf", "bin"]);
+
+    assert_eq!(raw.get_ref(0, 0).unwrap().as_bytes(), Some(payload));
+    assert_eq!(
+        raw.get_ref(0, 0).unwrap().as_bytes().map(<[u8]>::len),
+        Some(7)
+    );
+    assert_eq!(raw.get_ref(1, 0).unwrap().as_bytes(), None);
+
+    let records: Vec<Record> = raw.deserialize().try_collect().unwrap();
+    let record = &records[0];
+    assert_eq!(record.payload, payload);
+    assert_eq!(record.raw, payload);
+    assert_eq!(record.shared, payload);
+    assert_eq!(record.buf.as_deref().map(|buf| &buf[..]), Some(payload));
+    assert_eq!(&record.bin[..], payload);
+    // NULLs are empty for non-nullable fields.
+    let record = &records[1];
+    assert!(record.payload.is_empty() && record.raw.is_empty() && record.shared.is_empty());
+    assert!(record.buf.is_none());
+}
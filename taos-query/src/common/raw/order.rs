@@ -0,0 +1,357 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::BorrowedValue;
+
+use super::{views, ColumnView, RawBlock};
+
+/// Order of timestamps in the first column of a block, see [RawBlock::ts_order].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TsOrder {
+    /// The first row whose timestamp is less than the one of the row before it.
+    pub first_violation_row: Option<usize>,
+    /// Rows whose timestamp equals the one of the row before it.
+    pub duplicates: usize,
+}
+
+impl TsOrder {
+    /// Timestamps are non-decreasing, duplicates are allowed.
+    pub fn is_sorted(&self) -> bool {
+        self.first_violation_row.is_none()
+    }
+}
+
+/// What to do with a raw block whose timestamps are not ascending before it's written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnsortedPolicy {
+    /// Write the block as is, without checking the order.
+    #[default]
+    Write,
+    /// Sort rows of the block by the timestamp column, see [RawBlock::sort_by_column].
+    Sort,
+    /// Fail before the block is sent to the server.
+    Reject,
+}
+
+/// What to do with NaN and infinite floats before they are sent by stmt binds or schemaless
+/// writes, whose results differ between server versions and backends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NonFinitePolicy {
+    /// Fail with the column and row of the first non-finite value.
+    #[default]
+    Error,
+    /// Write non-finite values as NULL.
+    Null,
+    /// Write infinities as the max or min finite value of the type, NaN as NULL.
+    Clamp,
+}
+
+/// Options of raw block writes, stmt binds, schemaless writes and deletes.
+///
+/// ```rust
+/// # use taos_query::common::{NonFinitePolicy, UnsortedPolicy, WriteOptions};
+/// let options = WriteOptions::new()
+///     .on_unsorted(UnsortedPolicy::Reject)
+///     .on_non_finite(NonFinitePolicy::Clamp)
+///     .normalize_precision(true);
+/// assert_eq!(options.unsorted(), UnsortedPolicy::Reject);
+/// assert_eq!(options.non_finite(), NonFinitePolicy::Clamp);
+/// assert!(options.normalizes_precision());
+/// ```
+///
+/// Unset fields are the defaults when it's deserialized, eg. from `{"unsorted": "sort"}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WriteOptions {
+    unsorted: UnsortedPolicy,
+    non_finite: NonFinitePolicy,
+    normalize_precision: bool,
+    allow_full_table: bool,
+}
+
+impl WriteOptions {
+    pub const fn new() -> Self {
+        Self {
+            unsorted: UnsortedPolicy::Write,
+            non_finite: NonFinitePolicy::Error,
+            normalize_precision: false,
+            allow_full_table: false,
+        }
+    }
+
+    /// Convert bound timestamps to the precisions of stmt parameters, instead of failing the
+    /// bind, see [normalize_bind](crate::stmt::normalize_bind).
+    pub const fn normalize_precision(mut self, normalize: bool) -> Self {
+        self.normalize_precision = normalize;
+        self
+    }
+
+    pub const fn normalizes_precision(&self) -> bool {
+        self.normalize_precision
+    }
+
+    /// Set how to write blocks whose timestamps are not ascending.
+    pub const fn on_unsorted(mut self, policy: UnsortedPolicy) -> Self {
+        self.unsorted = policy;
+        self
+    }
+
+    pub const fn unsorted(&self) -> UnsortedPolicy {
+        self.unsorted
+    }
+
+    /// Set how to write NaN and infinite floats of stmt binds and schemaless data, see
+    /// [finite_bind](crate::stmt::finite_bind) and
+    /// [SmlData::finite](crate::common::SmlData::finite).
+    pub const fn on_non_finite(mut self, policy: NonFinitePolicy) -> Self {
+        self.non_finite = policy;
+        self
+    }
+
+    pub const fn non_finite(&self) -> NonFinitePolicy {
+        self.non_finite
+    }
+
+    /// Allow deletes without condition, which delete whole tables, see
+    /// [delete_sql](crate::helpers::delete_sql).
+    pub const fn allow_full_table(mut self, allow: bool) -> Self {
+        self.allow_full_table = allow;
+        self
+    }
+
+    pub const fn allows_full_table(&self) -> bool {
+        self.allow_full_table
+    }
+}
+
+impl RawBlock {
+    /// Check the order of timestamps in the first column in a single pass.
+    ///
+    /// Nulls are less than any timestamp. Blocks whose first column is not a timestamp are
+    /// taken as sorted.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = [ColumnView::from_millis_timestamp(vec![1, 2, 2, 1])];
+    /// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// let order = block.ts_order();
+    /// assert_eq!(order.first_violation_row, Some(3));
+    /// assert_eq!(order.duplicates, 1);
+    /// ```
+    pub fn ts_order(&self) -> TsOrder {
+        let mut order = TsOrder::default();
+        let Some(ColumnView::Timestamp(view)) = self.column_views().first() else {
+            return order;
+        };
+        let mut prev = None;
+        for (row, ts) in view.iter().enumerate() {
+            let ts = ts.map(|ts| ts.as_raw_i64());
+            if row > 0 {
+                match ts.cmp(&prev) {
+                    Ordering::Less if order.first_violation_row.is_none() => {
+                        order.first_violation_row = Some(row);
+                    }
+                    Ordering::Equal if ts.is_some() => order.duplicates += 1,
+                    _ => (),
+                }
+            }
+            prev = ts;
+        }
+        order
+    }
+
+    /// Timestamps of the first column are non-decreasing, see [RawBlock::ts_order].
+    pub fn is_sorted_by_first_column(&self) -> bool {
+        self.ts_order().is_sorted()
+    }
+
+    /// A new block with rows sorted by column `col`, the sort is stable so rows of equal
+    /// values keep their order. Nulls come first. Names of the block are kept.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `col` is out of bounds.
+    pub fn sort_by_column(&self, col: usize) -> RawBlock {
+        assert!(
+            col < self.ncols(),
+            "sort block of {} columns by column {col}",
+            self.ncols()
+        );
+        let key = &self.column_views()[col];
+        let mut indices: Vec<usize> = (0..self.nrows()).collect();
+        indices.sort_by(|&a, &b| cmp_values(&key.get(a).unwrap(), &key.get(b).unwrap()));
+
+        let views: Vec<_> = self
+            .column_views()
+            .iter()
+            .map(|view| {
+                let ty = view.as_ty();
+                let rows = indices.iter().map(|&row| view.get(row).unwrap());
+                ColumnView::null(ty, 0).concat_iter(rows, ty)
+            })
+            .collect();
+        let bytes = views::views_to_raw_block_with_schemas(&views, self.schemas());
+        let mut block = RawBlock::parse_from_raw_block(bytes, self.precision());
+        self.copy_names_to(&mut block);
+        block
+    }
+}
+
+/// Order of values in a column, values of other types are equal.
+fn cmp_values(lhs: &BorrowedValue, rhs: &BorrowedValue) -> Ordering {
+    use BorrowedValue::*;
+    match (lhs, rhs) {
+        (Null(_), Null(_)) => Ordering::Equal,
+        (Null(_), _) => Ordering::Less,
+        (_, Null(_)) => Ordering::Greater,
+        (Bool(l), Bool(r)) => l.cmp(r),
+        (TinyInt(l), TinyInt(r)) => l.cmp(r),
+        (SmallInt(l), SmallInt(r)) => l.cmp(r),
+        (Int(l), Int(r)) => l.cmp(r),
+        (BigInt(l), BigInt(r)) => l.cmp(r),
+        (UTinyInt(l), UTinyInt(r)) => l.cmp(r),
+        (USmallInt(l), USmallInt(r)) => l.cmp(r),
+        (UInt(l), UInt(r)) => l.cmp(r),
+        (UBigInt(l), UBigInt(r)) => l.cmp(r),
+        (Float(l), Float(r)) => l.total_cmp(r),
+        (Double(l), Double(r)) => l.total_cmp(r),
+        (Timestamp(l), Timestamp(r)) => l.as_raw_i64().cmp(&r.as_raw_i64()),
+        (VarChar(l), VarChar(r)) => l.cmp(r),
+        (NChar(l), NChar(r)) => l.cmp(r),
+        (VarBinary(l), VarBinary(r)) => l.cmp(r),
+        _ => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{Precision, Value};
+
+    use super::*;
+
+    /// A block of timestamps and values of them as ints and strings.
+    fn block(ts: &[i64]) -> RawBlock {
+        let views = [
+            ColumnView::from_micros_timestamp(ts.to_vec()),
+            ColumnView::from_ints(ts.iter().map(|&ts| ts as i32).collect()),
+            ColumnView::from_varchar::<String, _, _, _>(
+                ts.iter().map(|ts| Some(ts.to_string())).collect::<Vec<_>>(),
+            ),
+        ];
+        let mut raw =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        raw.with_field_names(["ts", "v", "s"]);
+        raw.with_table_name("tb1");
+        raw
+    }
+
+    fn timestamps(block: &RawBlock) -> Vec<i64> {
+        block
+            .to_values()
+            .into_iter()
+            .map(|row| match &row[0] {
+                Value::Timestamp(ts) => ts.as_raw_i64(),
+                v => panic!("expect a timestamp, got {v:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sorted_block() {
+        let sorted = block(&[1, 2, 3, 5]);
+        assert_eq!(sorted.ts_order(), TsOrder::default());
+        assert!(sorted.is_sorted_by_first_column());
+        assert!(block(&[]).is_sorted_by_first_column());
+        assert!(block(&[7]).is_sorted_by_first_column());
+
+        let values = [ColumnView::from_ints(vec![3, 1])];
+        let not_ts =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&values), Precision::Millisecond);
+        assert!(not_ts.is_sorted_by_first_column());
+    }
+
+    #[test]
+    fn unsorted_block() {
+        let unsorted = block(&[1, 3, 2, 4, 0]);
+        assert_eq!(
+            unsorted.ts_order(),
+            TsOrder {
+                first_violation_row: Some(2),
+                duplicates: 0,
+            }
+        );
+        assert!(!unsorted.is_sorted_by_first_column());
+
+        let sorted = unsorted.sort_by_column(0);
+        assert!(sorted.is_sorted_by_first_column());
+        assert_eq!(timestamps(&sorted), [0, 1, 2, 3, 4]);
+        assert_eq!(sorted.to_values(), block(&[0, 1, 2, 3, 4]).to_values());
+        assert_eq!(sorted.field_names(), ["ts", "v", "s"]);
+        assert_eq!(sorted.table_name(), Some("tb1"));
+        assert_eq!(sorted.precision(), Precision::Microsecond);
+    }
+
+    #[test]
+    fn reverse_sorted_block() {
+        let reversed = block(&[5, 4, 3, 2, 1]);
+        assert_eq!(reversed.ts_order().first_violation_row, Some(1));
+
+        let sorted = reversed.sort_by_column(0);
+        assert_eq!(sorted.to_values(), block(&[1, 2, 3, 4, 5]).to_values());
+        // sort by strings.
+        assert_eq!(
+            timestamps(&block(&[10, 9, 1]).sort_by_column(2)),
+            [1, 10, 9]
+        );
+    }
+
+    #[test]
+    fn duplicate_timestamps() {
+        let duplicated = block(&[1, 2, 2, 2, 3]);
+        let order = duplicated.ts_order();
+        assert!(order.is_sorted());
+        assert_eq!(order.duplicates, 2);
+
+        let views = [
+            ColumnView::from_micros_timestamp(vec![2, 1, 2, 1]),
+            ColumnView::from_ints(vec![0, 1, 2, 3]),
+        ];
+        let raw =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        assert_eq!(
+            raw.ts_order(),
+            TsOrder {
+                first_violation_row: Some(1),
+                duplicates: 0,
+            }
+        );
+        // the sort is stable.
+        let values: Vec<_> = raw
+            .sort_by_column(0)
+            .to_values()
+            .into_iter()
+            .map(|row| row[1].clone())
+            .collect();
+        assert_eq!(
+            values,
+            [Value::Int(1), Value::Int(3), Value::Int(0), Value::Int(2)]
+        );
+    }
+
+    #[test]
+    fn null_timestamps() {
+        let views = [ColumnView::from_millis_timestamp(vec![Some(1), None, None])];
+        let raw =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        let order = raw.ts_order();
+        assert_eq!(order.first_violation_row, Some(1));
+        assert_eq!(order.duplicates, 0);
+        let sorted = raw.sort_by_column(0);
+        assert!(sorted.get_ref(0, 0).unwrap().is_null());
+        assert!(sorted.is_sorted_by_first_column());
+    }
+}
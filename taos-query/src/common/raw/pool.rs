@@ -0,0 +1,243 @@
+//! Reusable buffers for fetched blocks.
+//!
+//! Each fetch copies a block into a new buffer of about the same size, services polling for
+//! days fragment the allocator by that. A [BlockBufferPool] installed on the connection builder
+//! supplies the buffers instead, and gets them back when the last [RawBlock] referring to it is
+//! dropped.
+//!
+//! [RawBlock]: super::RawBlock
+use std::fmt::Debug;
+use std::sync::{Arc, Weak};
+
+use bytes::{Bytes, BytesMut};
+
+/// Source of buffers for fetched blocks.
+pub trait BlockBufferPool: Send + Sync {
+    /// An empty buffer to copy a block of `len` bytes into.
+    ///
+    /// The capacity should be at least `len`, or the buffer grows on copying.
+    fn acquire(&self, len: usize) -> BytesMut;
+
+    /// Take back a buffer from [BlockBufferPool::acquire] when its block is dropped.
+    ///
+    /// `bytes` is not shared any more, so [Bytes::try_into_mut] succeeds unless it is a buffer
+    /// from elsewhere.
+    fn recycle(&self, bytes: Bytes);
+}
+
+/// Keep an `Arc` of the pool to inspect it while connections use it.
+impl<T: BlockBufferPool + ?Sized> BlockBufferPool for Arc<T> {
+    fn acquire(&self, len: usize) -> BytesMut {
+        (**self).acquire(len)
+    }
+
+    fn recycle(&self, bytes: Bytes) {
+        (**self).recycle(bytes)
+    }
+}
+
+/// A [BlockBufferPool] shared by connections, set by `TaosBuilder::with_block_pool`.
+///
+/// Blocks only keep a weak reference to the pool, a block outliving all the handles of its pool
+/// frees its buffer as usual.
+#[derive(Clone)]
+pub struct BlockPool(Arc<dyn BlockBufferPool>);
+
+impl BlockPool {
+    pub fn new(pool: impl BlockBufferPool + 'static) -> Self {
+        Self(Arc::new(pool))
+    }
+
+    /// Copy `data` into a buffer of the pool, the buffer is recycled when all the clones of the
+    /// returned bytes are dropped.
+    pub fn copy_from_slice(&self, data: &[u8]) -> Bytes {
+        let mut buf = self.0.acquire(data.len());
+        buf.clear();
+        buf.extend_from_slice(data);
+        Bytes::from_owner(Pooled {
+            bytes: buf.freeze(),
+            pool: Arc::downgrade(&self.0),
+        })
+    }
+}
+
+impl Debug for BlockPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BlockPool")
+    }
+}
+
+/// Owner of a pooled buffer, returns it to the pool on drop.
+struct Pooled {
+    bytes: Bytes,
+    pool: Weak<dyn BlockBufferPool>,
+}
+
+impl AsRef<[u8]> for Pooled {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Drop for Pooled {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.upgrade() {
+            pool.recycle(std::mem::take(&mut self.bytes));
+        }
+    }
+}
+
+#[cfg(feature = "buffer-pool")]
+pub use size_class::SizeClassPool;
+
+#[cfg(feature = "buffer-pool")]
+mod size_class {
+    use std::sync::Mutex;
+
+    use bytes::{Bytes, BytesMut};
+
+    use super::BlockBufferPool;
+
+    /// A [BlockBufferPool] keeping idle buffers by power-of-two size classes.
+    ///
+    /// Classes are from [SizeClassPool::MIN_SIZE] to [SizeClassPool::MAX_SIZE], larger blocks
+    /// are allocated and freed as usual.
+    #[derive(Debug)]
+    pub struct SizeClassPool {
+        classes: Vec<Mutex<Vec<BytesMut>>>,
+        max_idle: usize,
+    }
+
+    impl Default for SizeClassPool {
+        fn default() -> Self {
+            Self::new(8)
+        }
+    }
+
+    impl SizeClassPool {
+        pub const MIN_SIZE: usize = 4 << 10;
+        pub const MAX_SIZE: usize = 64 << 20;
+
+        /// A pool keeping at most `max_idle` idle buffers of each size class.
+        pub fn new(max_idle: usize) -> Self {
+            let n = (Self::MAX_SIZE / Self::MIN_SIZE).trailing_zeros() as usize + 1;
+            Self {
+                classes: (0..n).map(|_| Mutex::new(Vec::new())).collect(),
+                max_idle,
+            }
+        }
+
+        /// Number of idle buffers in the pool.
+        pub fn idle(&self) -> usize {
+            self.classes.iter().map(|c| c.lock().unwrap().len()).sum()
+        }
+
+        /// The smallest class fits `len`, `None` if it's too large.
+        fn class_of(&self, len: usize) -> Option<usize> {
+            let size = len.max(Self::MIN_SIZE).checked_next_power_of_two()?;
+            let class = (size / Self::MIN_SIZE).trailing_zeros() as usize;
+            (class < self.classes.len()).then_some(class)
+        }
+    }
+
+    impl BlockBufferPool for SizeClassPool {
+        fn acquire(&self, len: usize) -> BytesMut {
+            match self.class_of(len) {
+                Some(class) => self.classes[class]
+                    .lock()
+                    .unwrap()
+                    .pop()
+                    .unwrap_or_else(|| BytesMut::with_capacity(Self::MIN_SIZE << class)),
+                None => BytesMut::with_capacity(len),
+            }
+        }
+
+        fn recycle(&self, bytes: Bytes) {
+            let Ok(mut buf) = bytes.try_into_mut() else {
+                return;
+            };
+            let capacity = buf.capacity();
+            if capacity < Self::MIN_SIZE {
+                return;
+            }
+            // the largest class the buffer fits.
+            let class = (usize::BITS - 1 - (capacity / Self::MIN_SIZE).leading_zeros()) as usize;
+            if let Some(idle) = self.classes.get(class) {
+                let mut idle = idle.lock().unwrap();
+                if idle.len() < self.max_idle {
+                    buf.clear();
+                    idle.push(buf);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Counting {
+        acquired: AtomicUsize,
+        recycled: AtomicUsize,
+    }
+
+    impl BlockBufferPool for Counting {
+        fn acquire(&self, len: usize) -> BytesMut {
+            self.acquired.fetch_add(1, Ordering::SeqCst);
+            BytesMut::with_capacity(len)
+        }
+
+        fn recycle(&self, bytes: Bytes) {
+            assert!(bytes.try_into_mut().is_ok());
+            self.recycled.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn recycle_on_last_drop() {
+        let counting = Arc::new(Counting::default());
+        let pool = BlockPool::new(counting.clone());
+        let bytes = pool.copy_from_slice(b"block");
+        let cloned = bytes.slice(1..);
+        drop(bytes);
+        assert_eq!(counting.recycled.load(Ordering::SeqCst), 0);
+        assert_eq!(cloned, &b"lock"[..]);
+        drop(cloned);
+        assert_eq!(counting.acquired.load(Ordering::SeqCst), 1);
+        assert_eq!(counting.recycled.load(Ordering::SeqCst), 1);
+
+        // pool is gone, buffer is freed as usual.
+        let bytes = pool.copy_from_slice(b"block");
+        drop(pool);
+        assert_eq!(bytes, &b"block"[..]);
+        drop(bytes);
+        assert_eq!(counting.recycled.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "buffer-pool")]
+    #[test]
+    fn size_classes() {
+        let pool = SizeClassPool::new(2);
+        assert_eq!(pool.acquire(1).capacity(), SizeClassPool::MIN_SIZE);
+        assert_eq!(pool.acquire(5000).capacity(), 8 << 10);
+        assert_eq!(
+            pool.acquire(SizeClassPool::MAX_SIZE + 1).capacity(),
+            SizeClassPool::MAX_SIZE + 1
+        );
+
+        let pool = Arc::new(SizeClassPool::new(2));
+        let shared = BlockPool::new(pool.clone());
+        let data = vec![1u8; 5000];
+        let blocks: Vec<_> = (0..3).map(|_| shared.copy_from_slice(&data)).collect();
+        drop(blocks);
+        // at most 2 idle buffers are kept.
+        assert_eq!(pool.idle(), 2);
+        let bytes = shared.copy_from_slice(&data);
+        assert_eq!(pool.idle(), 1);
+        assert_eq!(bytes, data);
+    }
+}
@@ -0,0 +1,629 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+
+use crate::common::{BorrowedValue, Ty, Value};
+
+use super::{ColumnView, RawBlock};
+
+/// Options of [RawBlock::profile] and [Profiler].
+///
+/// ```rust
+/// # use taos_query::common::ProfileOptions;
+/// let options = ProfileOptions::new().sample_rows(1000).samples(3).seed(42);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileOptions {
+    sample_rows: usize,
+    samples: usize,
+    distinct_limit: usize,
+    seed: u64,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        Self {
+            sample_rows: 10_000,
+            samples: 5,
+            distinct_limit: 10_000,
+            seed: 0,
+        }
+    }
+}
+
+impl ProfileOptions {
+    /// A sample of 10000 rows with 5 sample values per column, seed 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max rows kept in the reservoir sample.
+    pub fn sample_rows(mut self, rows: usize) -> Self {
+        self.sample_rows = rows;
+        self
+    }
+
+    /// Sample values of each column in the profile.
+    pub fn samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Stop counting distinct values of a column once more than `limit` are seen, see
+    /// [ColumnView::cardinality_estimate].
+    pub fn distinct_limit(mut self, limit: usize) -> Self {
+        self.distinct_limit = limit;
+        self
+    }
+
+    /// Seed of the sampling, profiles of the same rows with the same seed are equal.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Length distribution of string values, in bytes for varchar and json and in chars for nchar.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LengthStats {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    /// Counts of lengths by power of two buckets, `histogram[0]` is of empty values and
+    /// `histogram[i]` of lengths in `[2^(i-1), 2^i)`.
+    pub histogram: Vec<usize>,
+}
+
+/// Statistics of a column view, see [ColumnView::stats].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ColumnStats {
+    pub rows: usize,
+    pub nulls: usize,
+    /// Min value, `None` if the column is all null or its type is not ordered, like json.
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+    /// Lengths of non-null values of varchar, nchar and json columns.
+    pub lengths: Option<LengthStats>,
+}
+
+/// Order of values of the same type, floats are ordered except NaN.
+fn compare(a: &BorrowedValue, b: &BorrowedValue) -> Option<Ordering> {
+    use BorrowedValue::*;
+    match (a, b) {
+        (Bool(a), Bool(b)) => Some(a.cmp(b)),
+        (TinyInt(a), TinyInt(b)) => Some(a.cmp(b)),
+        (SmallInt(a), SmallInt(b)) => Some(a.cmp(b)),
+        (Int(a), Int(b)) => Some(a.cmp(b)),
+        (BigInt(a), BigInt(b)) => Some(a.cmp(b)),
+        (UTinyInt(a), UTinyInt(b)) => Some(a.cmp(b)),
+        (USmallInt(a), USmallInt(b)) => Some(a.cmp(b)),
+        (UInt(a), UInt(b)) => Some(a.cmp(b)),
+        (UBigInt(a), UBigInt(b)) => Some(a.cmp(b)),
+        (Float(a), Float(b)) => a.partial_cmp(b),
+        (Double(a), Double(b)) => a.partial_cmp(b),
+        (Timestamp(a), Timestamp(b)) => Some(a.as_raw_i64().cmp(&b.as_raw_i64())),
+        (VarChar(a), VarChar(b)) => Some(a.cmp(b)),
+        (NChar(a), NChar(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn is_ordered(value: &BorrowedValue) -> bool {
+    compare(value, value) == Some(Ordering::Equal)
+}
+
+fn length_of(value: &BorrowedValue) -> Option<usize> {
+    match value {
+        BorrowedValue::VarChar(v) => Some(v.len()),
+        BorrowedValue::NChar(v) => Some(v.chars().count()),
+        BorrowedValue::Json(v) => Some(v.len()),
+        _ => None,
+    }
+}
+
+fn bucket_of(len: usize) -> usize {
+    (usize::BITS - len.leading_zeros()) as usize
+}
+
+impl LengthStats {
+    fn merge(&mut self, other: &LengthStats, count: usize, other_count: usize) {
+        let total = count + other_count;
+        if total > 0 {
+            self.mean = (self.mean * count as f64 + other.mean * other_count as f64) / total as f64;
+        }
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        if self.histogram.len() < other.histogram.len() {
+            self.histogram.resize(other.histogram.len(), 0);
+        }
+        for (count, other) in self.histogram.iter_mut().zip(&other.histogram) {
+            *count += other;
+        }
+    }
+}
+
+impl ColumnStats {
+    fn new(rows: usize) -> Self {
+        Self {
+            rows,
+            nulls: 0,
+            min: None,
+            max: None,
+            lengths: None,
+        }
+    }
+
+    /// Merge statistics of another part of the column.
+    pub fn merge(&mut self, other: &ColumnStats) {
+        let pick = |this: &mut Option<Value>, other: &Option<Value>, keep: Ordering| {
+            if let Some(other) = other {
+                let replace = match this {
+                    Some(this) => {
+                        compare(&other.to_borrowed_value(), &this.to_borrowed_value()) == Some(keep)
+                    }
+                    None => true,
+                };
+                if replace {
+                    *this = Some(other.clone());
+                }
+            }
+        };
+        pick(&mut self.min, &other.min, Ordering::Less);
+        pick(&mut self.max, &other.max, Ordering::Greater);
+        match (&mut self.lengths, &other.lengths) {
+            (Some(lengths), Some(other_lengths)) => lengths.merge(
+                other_lengths,
+                self.rows - self.nulls,
+                other.rows - other.nulls,
+            ),
+            (lengths @ None, Some(other)) => *lengths = Some(other.clone()),
+            _ => (),
+        }
+        self.rows += other.rows;
+        self.nulls += other.nulls;
+    }
+}
+
+impl ColumnView {
+    /// Null count, min and max values and the length distribution of strings of the column.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{ColumnView, Value};
+    /// let stats = ColumnView::from_ints(vec![Some(3), None, Some(-1)]).stats();
+    /// assert_eq!((stats.rows, stats.nulls), (3, 1));
+    /// assert_eq!(stats.min, Some(Value::Int(-1)));
+    /// assert_eq!(stats.max, Some(Value::Int(3)));
+    /// ```
+    pub fn stats(&self) -> ColumnStats {
+        let mut stats = ColumnStats::new(self.len());
+        let mut min: Option<BorrowedValue> = None;
+        let mut max: Option<BorrowedValue> = None;
+        let mut lengths: Option<(LengthStats, usize)> = None;
+        for value in self.iter() {
+            if value.is_null() {
+                stats.nulls += 1;
+                continue;
+            }
+            if let Some(len) = length_of(&value) {
+                let (lengths, total) = lengths.get_or_insert_with(|| {
+                    let stats = LengthStats {
+                        min: usize::MAX,
+                        max: 0,
+                        mean: 0.,
+                        histogram: Vec::new(),
+                    };
+                    (stats, 0)
+                });
+                lengths.min = lengths.min.min(len);
+                lengths.max = lengths.max.max(len);
+                *total += len;
+                let bucket = bucket_of(len);
+                if lengths.histogram.len() <= bucket {
+                    lengths.histogram.resize(bucket + 1, 0);
+                }
+                lengths.histogram[bucket] += 1;
+            }
+            if !is_ordered(&value) {
+                continue;
+            }
+            if min
+                .as_ref()
+                .map_or(true, |min| compare(&value, min) == Some(Ordering::Less))
+            {
+                min = Some(value.clone());
+            }
+            if max
+                .as_ref()
+                .map_or(true, |max| compare(&value, max) == Some(Ordering::Greater))
+            {
+                max = Some(value);
+            }
+        }
+        stats.min = min.map(|v| v.to_value());
+        stats.max = max.map(|v| v.to_value());
+        let values = stats.rows - stats.nulls;
+        stats.lengths = lengths.map(|(mut lengths, total)| {
+            lengths.mean = total as f64 / values as f64;
+            lengths
+        });
+        stats
+    }
+}
+
+/// Profile of a column, see [BlockProfile].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ColumnProfile {
+    /// Column name, empty when the blocks have no field names.
+    pub name: String,
+    pub ty: Ty,
+    pub nulls: usize,
+    /// Nulls in all rows, 0 if there are no rows.
+    pub null_ratio: f64,
+    /// Distinct non-null values in the sample, `None` if the type can't be counted.
+    pub distinct: Option<usize>,
+    /// Distinct values in non-null values of the sample.
+    pub distinct_ratio: Option<f64>,
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+    /// Non-null values of the sample.
+    pub samples: Vec<Value>,
+    pub lengths: Option<LengthStats>,
+}
+
+/// Per-column profile of blocks, by [RawBlock::profile] or a [Profiler].
+///
+/// Null counts, min and max values and lengths are of all rows, distinct counts and sample
+/// values are of a reservoir sample of the rows.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockProfile {
+    pub rows: usize,
+    pub sampled_rows: usize,
+    pub columns: Vec<ColumnProfile>,
+}
+
+/// Deterministic generator of sampling, by SplitMix64.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// Profile blocks of the same schema, eg. of a result set, into one [BlockProfile].
+///
+/// ```rust
+/// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, Profiler, ProfileOptions, RawBlock};
+/// let mut profiler = Profiler::new(ProfileOptions::new().sample_rows(10));
+/// for i in 0..3 {
+///     let bytes = views_to_raw_block(&[ColumnView::from_ints(vec![Some(i), None])]);
+///     profiler.push(&RawBlock::parse_from_raw_block(bytes, Precision::Millisecond));
+/// }
+/// let profile = profiler.finish();
+/// assert_eq!((profile.rows, profile.columns[0].nulls), (6, 3));
+/// assert_eq!(profile.columns[0].distinct, Some(3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    options: ProfileOptions,
+    rng: SplitMix64,
+    columns: Vec<(String, Ty)>,
+    stats: Vec<ColumnStats>,
+    rows: usize,
+    sample: Vec<Vec<Value>>,
+}
+
+impl Profiler {
+    pub fn new(options: ProfileOptions) -> Self {
+        Self {
+            rng: SplitMix64(options.seed),
+            options,
+            columns: Vec::new(),
+            stats: Vec::new(),
+            rows: 0,
+            sample: Vec::new(),
+        }
+    }
+
+    /// Add rows of `block`, columns of the first block are profiled.
+    pub fn push(&mut self, block: &RawBlock) {
+        if self.columns.is_empty() && self.rows == 0 {
+            let names = block.field_names();
+            self.columns = block
+                .schemas()
+                .iter()
+                .enumerate()
+                .map(|(i, schema)| (names.get(i).cloned().unwrap_or_default(), schema.ty()))
+                .collect();
+        }
+        let views = &block.column_views()[..self.columns.len().min(block.ncols())];
+        for (i, view) in views.iter().enumerate() {
+            let stats = view.stats();
+            match self.stats.get_mut(i) {
+                Some(merged) => merged.merge(&stats),
+                None => self.stats.push(stats),
+            }
+        }
+
+        // Reservoir sampling by algorithm R.
+        let capacity = self.options.sample_rows;
+        for row in 0..block.nrows() {
+            let slot = if self.sample.len() < capacity {
+                self.sample.push(Vec::new());
+                Some(self.sample.len() - 1)
+            } else {
+                let slot = self.rng.below(self.rows as u64 + 1) as usize;
+                (slot < capacity).then_some(slot)
+            };
+            if let Some(slot) = slot {
+                self.sample[slot] = views
+                    .iter()
+                    .map(|view| view.get(row).expect("row in range").to_value())
+                    .collect();
+            }
+            self.rows += 1;
+        }
+    }
+
+    pub fn finish(self) -> BlockProfile {
+        let Self {
+            options,
+            columns,
+            stats,
+            rows,
+            sample,
+            ..
+        } = self;
+        let columns = columns
+            .into_iter()
+            .zip(stats)
+            .enumerate()
+            .map(|(i, ((name, ty), stats))| {
+                let values = sample.iter().filter_map(|row| row.get(i));
+                let non_null = values.clone().filter(|v| !v.is_null()).count();
+                let distinct = ColumnView::try_from_values(ty, values.clone())
+                    .ok()
+                    .map(|view| view.cardinality_estimate(options.distinct_limit));
+                ColumnProfile {
+                    name,
+                    ty,
+                    nulls: stats.nulls,
+                    null_ratio: ratio(stats.nulls, stats.rows),
+                    distinct,
+                    distinct_ratio: distinct.map(|distinct| ratio(distinct, non_null)),
+                    min: stats.min,
+                    max: stats.max,
+                    samples: values
+                        .filter(|v| !v.is_null())
+                        .take(options.samples)
+                        .cloned()
+                        .collect(),
+                    lengths: stats.lengths,
+                }
+            })
+            .collect();
+        BlockProfile {
+            rows,
+            sampled_rows: sample.len(),
+            columns,
+        }
+    }
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+impl RawBlock {
+    /// Profile columns of the block, see [BlockProfile].
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, ProfileOptions, RawBlock, Value};
+    /// let views = [ColumnView::from_ints(vec![Some(1), None, Some(7), None])];
+    /// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// let profile = block.profile(&ProfileOptions::new());
+    /// assert_eq!(profile.columns[0].null_ratio, 0.5);
+    /// assert_eq!(profile.columns[0].max, Some(Value::Int(7)));
+    /// ```
+    pub fn profile(&self, options: &ProfileOptions) -> BlockProfile {
+        let mut profiler = Profiler::new(options.clone());
+        profiler.push(self);
+        profiler.finish()
+    }
+}
+
+impl Display for BlockProfile {
+    /// A table of a line per column, with the rows and sampled rows in the first line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn cell(value: &Option<Value>) -> String {
+            let mut s = match value {
+                Some(v) => format!("{v}"),
+                None => "-".to_string(),
+            };
+            if s.chars().count() > 32 {
+                s = s.chars().take(31).chain(Some('…')).collect();
+            }
+            s
+        }
+
+        writeln!(f, "rows: {}, sampled: {}", self.rows, self.sampled_rows)?;
+        let header = ["column", "type", "nulls", "null%", "distinct", "min", "max"];
+        let lines: Vec<[String; 7]> = self
+            .columns
+            .iter()
+            .map(|c| {
+                [
+                    c.name.clone(),
+                    c.ty.name().to_string(),
+                    c.nulls.to_string(),
+                    format!("{:.1}", c.null_ratio * 100.),
+                    c.distinct
+                        .map_or_else(|| "-".to_string(), |d| d.to_string()),
+                    cell(&c.min),
+                    cell(&c.max),
+                ]
+            })
+            .collect();
+        let mut widths = header.map(|h| h.len());
+        for line in &lines {
+            for (width, cell) in widths.iter_mut().zip(line) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let header = header.map(str::to_string);
+        for line in std::iter::once(&header).chain(&lines) {
+            let mut cells = line.iter().zip(widths).peekable();
+            while let Some((cell, width)) = cells.next() {
+                if cells.peek().is_some() {
+                    write!(f, "{cell:width$}  ")?;
+                } else {
+                    writeln!(f, "{cell}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock, Ty, Value};
+
+    use super::*;
+
+    fn block(offset: i32) -> RawBlock {
+        let ints: Vec<_> = (0..100)
+            .map(|i| (i % 4 != 0).then_some(i + offset))
+            .collect();
+        let names: Vec<_> = (0..100)
+            .map(|i| match i % 5 {
+                0 => None,
+                1 => Some(""),
+                2 => Some("a"),
+                3 => Some("bcd"),
+                _ => Some("efghijkl"),
+            })
+            .collect();
+        let views = [
+            ColumnView::from_millis_timestamp((0..100).map(|i| Some(i + offset as i64)).collect()),
+            ColumnView::from_ints(ints),
+            ColumnView::from_doubles(vec![None::<f64>; 100]),
+            ColumnView::from_varchar::<&str, _, _, _>(names.clone()),
+            ColumnView::from_nchar::<&str, _, _, _>(names),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        block.with_field_names(["ts", "v", "d", "s", "n"]);
+        block
+    }
+
+    #[test]
+    fn profile_block() {
+        let profile = block(0).profile(&ProfileOptions::new());
+        assert_eq!((profile.rows, profile.sampled_rows), (100, 100));
+        let [ts, v, d, s, n] = &profile.columns[..] else {
+            panic!("5 columns expected");
+        };
+
+        assert_eq!((ts.name.as_str(), ts.ty), ("ts", Ty::Timestamp));
+        assert_eq!((ts.nulls, ts.null_ratio), (0, 0.));
+        assert_eq!((ts.distinct, ts.distinct_ratio), (Some(100), Some(1.)));
+        let raw = |v: &Option<Value>| match v {
+            Some(Value::Timestamp(ts)) => ts.as_raw_i64(),
+            v => panic!("timestamp expected, got {v:?}"),
+        };
+        assert_eq!((raw(&ts.min), raw(&ts.max)), (0, 99));
+
+        assert_eq!((v.nulls, v.null_ratio), (25, 0.25));
+        assert_eq!(
+            (v.min.clone(), v.max.clone()),
+            (Some(Value::Int(1)), Some(Value::Int(99)))
+        );
+        assert_eq!((v.distinct, v.distinct_ratio), (Some(75), Some(1.)));
+        assert_eq!(v.samples.len(), 5);
+        assert!(v.lengths.is_none());
+
+        assert_eq!((d.nulls, d.null_ratio), (100, 1.));
+        assert_eq!(
+            (d.min.clone(), d.max.clone(), d.distinct),
+            (None, None, Some(0))
+        );
+        assert!(d.samples.is_empty());
+
+        assert_eq!((s.nulls, s.null_ratio), (20, 0.2));
+        assert_eq!((s.distinct, s.distinct_ratio), (Some(4), Some(0.05)));
+        assert_eq!(s.min, Some(Value::VarChar(String::new())));
+        assert_eq!(s.max, Some(Value::VarChar("efghijkl".to_string())));
+        let lengths = s.lengths.as_ref().unwrap();
+        assert_eq!((lengths.min, lengths.max, lengths.mean), (0, 8, 3.));
+        assert_eq!(lengths.histogram, [20, 20, 20, 0, 20]);
+        assert_eq!(n.lengths, s.lengths);
+        assert_eq!(n.max, Some(Value::NChar("efghijkl".to_string())));
+
+        let table = profile.to_string();
+        assert!(table.starts_with("rows: 100, sampled: 100\ncolumn"));
+        let cells = ["v", "INT", "25", "25.0", "75", "1", "99"];
+        assert!(
+            table.lines().any(|line| line.split_whitespace().eq(cells)),
+            "{table}"
+        );
+
+        let json = serde_json::to_value(&profile).unwrap();
+        assert_eq!(json["columns"][1]["null_ratio"], 0.25);
+        assert_eq!(json["columns"][1]["max"], serde_json::json!({ "Int": 99 }));
+    }
+
+    #[test]
+    fn profile_sample() {
+        let profile = |seed| {
+            let mut profiler =
+                Profiler::new(ProfileOptions::new().sample_rows(50).samples(50).seed(seed));
+            for offset in [0, 100, 200] {
+                profiler.push(&block(offset));
+            }
+            profiler.finish()
+        };
+        let a = profile(7);
+        assert_eq!(a, profile(7));
+        assert_ne!(a.columns[0].samples, profile(8).columns[0].samples);
+
+        assert_eq!((a.rows, a.sampled_rows), (300, 50));
+        let v = &a.columns[1];
+        assert_eq!((v.nulls, v.null_ratio), (75, 0.25));
+        assert_eq!(
+            (v.min.clone(), v.max.clone()),
+            (Some(Value::Int(1)), Some(Value::Int(299)))
+        );
+        // Sampled across blocks, all timestamps are distinct.
+        let ts = &a.columns[0];
+        assert_eq!(ts.distinct, Some(50));
+        let raws: Vec<_> = ts
+            .samples
+            .iter()
+            .map(|v| match v {
+                Value::Timestamp(ts) => ts.as_raw_i64(),
+                v => panic!("timestamp expected, got {v:?}"),
+            })
+            .collect();
+        assert!(raws.iter().any(|&raw| raw >= 100), "{raws:?}");
+        assert_eq!(
+            a.columns[3].lengths.as_ref().unwrap().histogram,
+            [60, 60, 60, 0, 60]
+        );
+    }
+}
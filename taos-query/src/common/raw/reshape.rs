@@ -0,0 +1,263 @@
+use crate::common::Ty;
+
+use super::views::{views_to_raw_block_with_schemas, ColSchema, ColumnView};
+use super::RawBlock;
+
+/// Columns that can't be reshaped by [RawBlock::append_column], [RawBlock::drop_column] or
+/// [RawBlock::rename_column].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReshapeError {
+    #[error("no column `{0}` in the block")]
+    NotFound(String),
+    #[error("column `{0}` is already in the block")]
+    Duplicated(String),
+    #[error("column `{name}` has {rows} rows but the block has {nrows}")]
+    Rows {
+        name: String,
+        rows: usize,
+        nrows: usize,
+    },
+}
+
+impl RawBlock {
+    /// A new block with `view` appended as the last column named `name`, eg. a region derived
+    /// from a device tag before writing blocks to a wider table with
+    /// [write_raw_block](crate::AsyncQueryable::write_raw_block).
+    ///
+    /// The length of the column is the longest value for variable types. Columns of the block
+    /// are copied as is, with the table and database names kept, empty field names are used if
+    /// the block has none.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock, Value};
+    /// let views = [ColumnView::from_millis_timestamp(vec![0, 1]), ColumnView::from_ints(vec![1, 2])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "device"]);
+    /// let region = ColumnView::from_varchar::<&str, _, _, _>(vec!["north", "south"]);
+    /// let enriched = block.append_column("region", region).unwrap();
+    /// assert_eq!(enriched.field_names(), ["ts", "device", "region"]);
+    /// assert_eq!(enriched.get_ref(1, 2).unwrap().to_value(), Value::VarChar("south".into()));
+    /// ```
+    pub fn append_column(&self, name: &str, view: ColumnView) -> Result<RawBlock, ReshapeError> {
+        if self.column(name).is_some() {
+            return Err(ReshapeError::Duplicated(name.to_string()));
+        }
+        if view.len() != self.nrows() {
+            return Err(ReshapeError::Rows {
+                name: name.to_string(),
+                rows: view.len(),
+                nrows: self.nrows(),
+            });
+        }
+        let mut schemas = self.schemas().to_vec();
+        schemas.push(column_schema(&view));
+        let views: Vec<&ColumnView> = self.column_views().iter().chain([&view]).collect();
+        let mut names: Vec<&str> = if self.field_names().is_empty() {
+            vec![""; self.ncols()]
+        } else {
+            self.field_names().iter().map(String::as_str).collect()
+        };
+        names.push(name);
+        Ok(self.reshaped(&views, &schemas, names))
+    }
+
+    /// A new block without the column named `name`, other columns are copied as is.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = [ColumnView::from_millis_timestamp(vec![0]), ColumnView::from_ints(vec![1])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "debug"]);
+    /// assert_eq!(block.drop_column("debug").unwrap().field_names(), ["ts"]);
+    /// assert!(block.drop_column("missing").is_err());
+    /// ```
+    pub fn drop_column(&self, name: &str) -> Result<RawBlock, ReshapeError> {
+        let dropped = self.column_index(name)?;
+        let keep = |col: &usize| *col != dropped;
+        let views: Vec<&ColumnView> = (0..self.ncols())
+            .filter(keep)
+            .map(|col| self.view(col))
+            .collect();
+        let schemas: Vec<ColSchema> = (0..self.ncols())
+            .filter(keep)
+            .map(|col| self.schemas()[col])
+            .collect();
+        let names: Vec<&str> = (0..self.ncols())
+            .filter(keep)
+            .map(|col| self.field_names()[col].as_str())
+            .collect();
+        Ok(self.reshaped(&views, &schemas, names))
+    }
+
+    /// A new block with the column `old` named `new`, sharing the data of the block.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = [ColumnView::from_millis_timestamp(vec![0]), ColumnView::from_ints(vec![1])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "v"]);
+    /// let renamed = block.rename_column("v", "voltage").unwrap();
+    /// assert_eq!(renamed.field_names(), ["ts", "voltage"]);
+    /// assert!(renamed.column("voltage").is_some() && renamed.column("v").is_none());
+    /// ```
+    pub fn rename_column(&self, old: &str, new: &str) -> Result<RawBlock, ReshapeError> {
+        let col = self.column_index(old)?;
+        if old != new && self.column(new).is_some() {
+            return Err(ReshapeError::Duplicated(new.to_string()));
+        }
+        // rebuilt raw bytes of schema changes are taken before sharing.
+        self.as_raw_bytes();
+        let bytes = self.data.take();
+        self.data.set(bytes.clone());
+        let mut block = RawBlock::parse_from_raw_block(bytes, self.precision());
+        self.copy_names_to(&mut block);
+        let mut names = self.fields.clone();
+        names[col] = new.to_string();
+        block.with_field_names(names);
+        Ok(block)
+    }
+
+    /// Index of the column named `name`.
+    fn column_index(&self, name: &str) -> Result<usize, ReshapeError> {
+        self.name_index
+            .get(name)
+            .copied()
+            .ok_or_else(|| ReshapeError::NotFound(name.to_string()))
+    }
+
+    /// A new block of `views` with names of the block.
+    fn reshaped<'a>(
+        &self,
+        views: &[&ColumnView],
+        schemas: &[ColSchema],
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> RawBlock {
+        let bytes = views_to_raw_block_with_schemas(views, schemas);
+        let mut block = RawBlock::parse_from_raw_block(bytes, self.precision());
+        self.copy_names_to(&mut block);
+        block.with_field_names(names);
+        block
+    }
+}
+
+/// Schema of an appended column, variable types are as long as the longest value.
+fn column_schema(view: &ColumnView) -> ColSchema {
+    let ty: Ty = view.as_ty();
+    let len = if ty.is_var_type() || ty.is_json() {
+        view.iter()
+            .filter_map(|value| value.as_bytes().map(<[u8]>::len))
+            .max()
+            .unwrap_or_default()
+            .max(1)
+    } else {
+        ty.fixed_length()
+    };
+    ColSchema::new(ty, len as _)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{Precision, Value};
+
+    use super::*;
+
+    /// A consumed block of a device table.
+    fn consumed() -> RawBlock {
+        let views = [
+            ColumnView::from_millis_timestamp(vec![0, 1, 2]),
+            ColumnView::from_ints(vec![Some(3), None, Some(5)]),
+            ColumnView::from_doubles(vec![0.5, 1.5, 2.5]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        block.with_field_names(["ts", "device", "debug"]);
+        block.with_table_name("d0");
+        block
+    }
+
+    #[test]
+    fn reshape_and_serialize() {
+        let region =
+            ColumnView::from_varchar::<&str, _, _, _>(vec![Some("north"), None, Some("se")]);
+        let block = consumed()
+            .append_column("region", region)
+            .unwrap()
+            .drop_column("debug")
+            .unwrap()
+            .rename_column("device", "device_id")
+            .unwrap();
+        assert_eq!(block.field_names(), ["ts", "device_id", "region"]);
+        assert_eq!(block.table_name(), Some("d0"));
+        assert_eq!(block.schemas()[2].ty(), Ty::VarChar);
+        assert_eq!(block.schemas()[2].len(), 5);
+        assert_eq!(block.column("device_id").unwrap().0.ty(), Ty::Int);
+        assert!(block.column("device").is_none());
+
+        // the reshaped block is parsed back from its raw bytes as is.
+        let parsed =
+            RawBlock::parse_from_raw_block(block.as_raw_bytes().to_vec(), block.precision());
+        let schemas = |block: &RawBlock| {
+            let schemas = block.schemas().iter();
+            schemas.map(|s| (s.ty(), s.len())).collect::<Vec<_>>()
+        };
+        assert_eq!(schemas(&parsed), schemas(&block));
+        assert_eq!(parsed.nrows(), 3);
+        let rows: Vec<Vec<Value>> = parsed.to_values();
+        assert_eq!(
+            rows[0],
+            [
+                Value::Timestamp(crate::common::Timestamp::Microseconds(0)),
+                Value::Int(3),
+                Value::VarChar("north".to_string())
+            ]
+        );
+        assert_eq!(
+            rows[1][1..],
+            [Value::Null(Ty::Int), Value::Null(Ty::VarChar)]
+        );
+    }
+
+    #[test]
+    fn rename_shares_data() {
+        let block = consumed();
+        let renamed = block.rename_column("debug", "trace").unwrap();
+        assert_eq!(
+            renamed.as_raw_bytes().as_ptr(),
+            block.as_raw_bytes().as_ptr()
+        );
+        assert_eq!(renamed.field_names(), ["ts", "device", "trace"]);
+        assert_eq!(
+            renamed.get_ref(2, 2).unwrap().to_value(),
+            Value::Double(2.5)
+        );
+    }
+
+    #[test]
+    fn reshape_errors() {
+        let block = consumed();
+        assert_eq!(
+            block
+                .append_column("tag", ColumnView::from_ints(vec![1]))
+                .unwrap_err(),
+            ReshapeError::Rows {
+                name: "tag".to_string(),
+                rows: 1,
+                nrows: 3
+            }
+        );
+        let err = block
+            .append_column("debug", ColumnView::from_ints(vec![1; 3]))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "column `debug` is already in the block");
+        assert_eq!(
+            block.drop_column("nope").unwrap_err(),
+            ReshapeError::NotFound("nope".to_string())
+        );
+        assert_eq!(
+            block.rename_column("debug", "ts").unwrap_err(),
+            ReshapeError::Duplicated("ts".to_string())
+        );
+        assert!(block.rename_column("debug", "debug").is_ok());
+    }
+}
@@ -1,24 +1,93 @@
-use std::{marker::PhantomData, ptr::NonNull};
+use std::{borrow::Cow, marker::PhantomData, sync::Arc};
 
 use serde::{
-    de::{DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor},
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    },
     Deserializer,
 };
 
 use crate::{
-    common::{BorrowedValue, Value},
+    common::{BorrowedValue, CellInspection, Precision, Timestamp, Ty, Value},
     RawBlock,
 };
 
+/// Controls what non-`Option` fields receive when deserializing a NULL value.
+///
+/// `Option<T>` fields always receive `None` on NULL, whatever the policy is.
+///
+/// Note that `#[serde(default)]` only works for fields missing in the row, a NULL column is
+/// still present so it goes through the policy, use `Option<T>` with `unwrap_or_default` if you
+/// want serde defaults for NULL values.
+///
+/// ```rust
+/// # use taos_query::common::{views::views_to_raw_block, ColumnView, NullPolicy, Precision, RawBlock};
+/// let bytes = views_to_raw_block(&[ColumnView::from_ints(vec![Some(1), None])]);
+/// let mut block = RawBlock::parse_from_raw_block(bytes, Precision::Millisecond);
+/// assert!(block.deserialize::<(i32,)>().nth(1).unwrap().is_err());
+///
+/// block.with_null_policy(NullPolicy::Default);
+/// let values: Vec<(i32,)> = block.deserialize().collect::<Result<_, _>>().unwrap();
+/// assert_eq!(values, [(1,), (0,)]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum NullPolicy {
+    /// Keep the default behavior: NULL into numeric, bool or timestamp fields is an error.
+    #[default]
+    Error,
+    /// Use the zero value of the column type: `0` for numeric types, `false` for bool, empty
+    /// string for varchar/nchar, and unix epoch for timestamp.
+    Default,
+    /// Use the value for all NULL values, it's deserialized as if it's read from the column.
+    Value(Value),
+}
+
+impl NullPolicy {
+    fn zero_value<'b>(ty: Ty, precision: Precision) -> Option<BorrowedValue<'b>> {
+        use BorrowedValue::*;
+        Some(match ty {
+            Ty::Bool => Bool(false),
+            Ty::TinyInt => TinyInt(0),
+            Ty::SmallInt => SmallInt(0),
+            Ty::Int => Int(0),
+            Ty::BigInt => BigInt(0),
+            Ty::UTinyInt => UTinyInt(0),
+            Ty::USmallInt => USmallInt(0),
+            Ty::UInt => UInt(0),
+            Ty::UBigInt => UBigInt(0),
+            Ty::Float => Float(0.),
+            Ty::Double => Double(0.),
+            Ty::VarChar => VarChar(""),
+            Ty::NChar => NChar(Cow::Borrowed("")),
+            Ty::Timestamp => Timestamp(crate::common::Timestamp::new(0, precision)),
+            _ => return None,
+        })
+    }
+
+    /// Replace NULL `value` with the policy value.
+    fn resolve<'b>(&'b self, value: BorrowedValue<'b>, precision: Precision) -> BorrowedValue<'b> {
+        match (self, &value) {
+            (NullPolicy::Default, BorrowedValue::Null(ty)) => {
+                Self::zero_value(*ty, precision).unwrap_or(value)
+            }
+            (NullPolicy::Value(v), BorrowedValue::Null(_)) => v.to_borrowed_value(),
+            _ => value,
+        }
+    }
+}
+
+/// Rows of an owned block, see [RawBlock::into_rows].
+///
+/// The block is shared by the rows, so they are still valid after the iterator is advanced or
+/// dropped. Names and values read from a row borrow the block and must not outlive the last
+/// row or iterator of it.
 pub struct IntoRowsIter<'a> {
-    pub(crate) raw: RawBlock,
+    pub(crate) raw: Arc<RawBlock>,
     pub(crate) row: usize,
-    pub(crate) _marker: PhantomData<&'a bool>,
+    pub(crate) _marker: PhantomData<&'a RawBlock>,
 }
 
-unsafe impl<'a> Send for IntoRowsIter<'a> {}
-unsafe impl<'a> Sync for IntoRowsIter<'a> {}
-
 impl<'a> Iterator for IntoRowsIter<'a> {
     type Item = RowView<'a>;
 
@@ -28,56 +97,58 @@ impl<'a> Iterator for IntoRowsIter<'a> {
         } else {
             let row = self.row;
             self.row += 1;
-            Some(RowView {
-                raw: unsafe { &*(&self.raw as *const RawBlock) },
-                row: row,
-                col: 0,
-            })
+            // SAFETY: the block is on the heap and only shared from now on, each row keeps an
+            // owner of it so the block is not dropped while any row is alive.
+            let raw = unsafe { &*Arc::as_ptr(&self.raw) };
+            Some(RowView::new(raw, row, Some(self.raw.clone())))
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.raw.nrows().saturating_sub(self.row);
+        (len, Some(len))
+    }
 }
 
+impl<'a> ExactSizeIterator for IntoRowsIter<'a> {}
+
+/// Rows of a borrowed block, see [RawBlock::rows].
 pub struct RowsIter<'a> {
-    pub(super) raw: NonNull<RawBlock>,
+    pub(super) raw: &'a RawBlock,
     pub(super) row: usize,
-    pub(crate) _marker: PhantomData<&'a usize>,
 }
 
-unsafe impl<'a> Send for RowsIter<'a> {}
-unsafe impl<'a> Sync for RowsIter<'a> {}
-
 impl<'a> Iterator for RowsIter<'a> {
     type Item = RowView<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.row >= unsafe { self.raw.as_mut() }.nrows() {
+        if self.row >= self.raw.nrows() {
             None
         } else {
             let row = self.row;
             self.row += 1;
-            Some(RowView {
-                raw: unsafe { self.raw.as_mut() },
-                row: row,
-                col: 0,
-            })
+            Some(RowView::new(self.raw, row, None))
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.raw.nrows().saturating_sub(self.row);
+        (len, Some(len))
+    }
 }
 
+impl<'a> ExactSizeIterator for RowsIter<'a> {}
+
 impl<'a> RowsIter<'a> {
-    pub fn values(&mut self) -> ValueIter {
+    pub fn values(&self) -> ValueIter<'a> {
         ValueIter {
-            raw: unsafe { self.raw.as_mut() },
+            raw: self.raw,
             row: self.row,
             col: 0,
         }
     }
-    pub fn named_values(&mut self) -> RowView {
-        RowView {
-            raw: unsafe { self.raw.as_mut() },
-            row: self.row,
-            col: 0,
-        }
+    pub fn named_values(&self) -> RowView<'a> {
+        RowView::new(self.raw, self.row, None)
     }
 }
 
@@ -105,8 +176,16 @@ impl<'a> Iterator for ValueIter<'a> {
 
 pub struct RowView<'a> {
     raw: &'a RawBlock,
+    /// Owner of the block of [IntoRowsIter], which keeps `raw` alive.
+    _owner: Option<Arc<RawBlock>>,
     row: usize,
     col: usize,
+    /// Values may be buffered by the visitor, eg. `#[serde(flatten)]`, see [RowView::deserialize_map].
+    buffered: bool,
+    /// Field names of the struct being deserialized, only these columns are read.
+    requested: Option<&'static [&'static str]>,
+    /// Next field in `requested` to look up.
+    field: usize,
 }
 
 impl<'a> Iterator for RowView<'a> {
@@ -119,8 +198,9 @@ impl<'a> Iterator for RowView<'a> {
             unsafe {
                 let col = self.col;
                 self.col += 1;
+                // blocks parsed without field names have no names to yield.
                 Some((
-                    self.raw.fields.get_unchecked(col).as_str(),
+                    self.raw.fields.get(col).map_or("", String::as_str),
                     self.raw.get_ref_unchecked(self.row, col),
                 ))
             }
@@ -146,6 +226,8 @@ impl<'a> std::fmt::Debug for RowView<'a> {
             .field("raw", &self.raw)
             .field("row", &self.row)
             .field("col", &self.col)
+            .field("buffered", &self.buffered)
+            .field("requested", &self.requested)
             .finish()
     }
 }
@@ -168,6 +250,18 @@ impl<'a> Iterator for RowViewOfValue<'a> {
 }
 
 impl<'a> RowView<'a> {
+    fn new(raw: &'a RawBlock, row: usize, owner: Option<Arc<RawBlock>>) -> Self {
+        Self {
+            raw,
+            _owner: owner,
+            row,
+            col: 0,
+            buffered: false,
+            requested: None,
+            field: 0,
+        }
+    }
+
     pub fn into_value_iter(self) -> RowViewOfValue<'a> {
         RowViewOfValue(self)
     }
@@ -175,6 +269,22 @@ impl<'a> RowView<'a> {
         self.next().map(|(_, v)| v)
     }
 
+    fn walk_next_with_policy(&mut self) -> Option<NullPolicyValue<'a>> {
+        let (policy, precision) = (&self.raw.null_policy, self.raw.precision());
+        let buffered = self.buffered;
+        self.walk_next().map(|value| NullPolicyValue {
+            value,
+            policy,
+            precision,
+            buffered,
+        })
+    }
+
+    fn expect_next_with_policy(&mut self) -> Result<NullPolicyValue<'a>, DeError> {
+        self.walk_next_with_policy()
+            .ok_or_else(|| <DeError as serde::de::Error>::custom("expect value, not none"))
+    }
+
     // fn walk(&mut self) {
     //     self.col += 1;
     // }
@@ -186,9 +296,58 @@ impl<'a> RowView<'a> {
     //     self.raw.get_ref(self.row, self.col)
     // }
 
+    /// Bytes and values of all the cells of the row, see [RawBlock::inspect].
+    pub fn inspect_all(&self) -> Vec<CellInspection> {
+        (0..self.raw.ncols())
+            .filter_map(|col| self.raw.inspect(self.row, col))
+            .collect()
+    }
+
     pub fn into_values(self) -> Vec<Value> {
         self.map(|(_, b)| b.to_value()).collect()
     }
+
+    /// Materialize all the columns of the row, so it can be deserialized by visitors that buffer
+    /// their input, eg. untagged enums, `#[serde(flatten)]` fields of any depth and structs with
+    /// `#[serde(deny_unknown_fields)]`, which fail on columns not in the struct.
+    ///
+    /// It costs an allocation per row and reads every column even for structs of a few fields,
+    /// so use it only for the types that need it. Timestamps buffered by such visitors are in
+    /// the form of [Timestamp] serialization to keep the precision, eg. `{"Milliseconds": 0}`,
+    /// so the fields should be [Timestamp] or [Value](crate::common::Value).
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// # use serde::Deserialize;
+    /// #[derive(Debug, PartialEq, Deserialize)]
+    /// #[serde(untagged)]
+    /// enum Reading {
+    ///     Current { current: f32 },
+    ///     Voltage { voltage: i32 },
+    /// }
+    /// let views = [ColumnView::from_ints(vec![220])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["voltage"]);
+    /// let row = block.rows().next().unwrap();
+    /// let reading = Reading::deserialize(row.into_buffered()).unwrap();
+    /// assert_eq!(reading, Reading::Voltage { voltage: 220 });
+    /// ```
+    pub fn into_buffered(self) -> BufferedRow<'a> {
+        let values = (0..self.raw.ncols())
+            .map(|col| unsafe {
+                (
+                    self.raw.fields.get(col).map_or("", String::as_str),
+                    self.raw.get_ref_unchecked(self.row, col),
+                )
+            })
+            .collect();
+        BufferedRow {
+            values,
+            named: !self.raw.fields.is_empty(),
+            policy: &self.raw.null_policy,
+            precision: self.raw.precision(),
+        }
+    }
 }
 
 pub(super) type DeError = taos_error::Error;
@@ -200,8 +359,8 @@ impl<'de, 'a: 'de> SeqAccess<'de> for RowView<'a> {
     where
         S: DeserializeSeed<'de>,
     {
-        match self.next() {
-            Some((_, v)) => seed
+        match self.walk_next_with_policy() {
+            Some(v) => seed
                 .deserialize(v)
                 .map_err(<Self::Error as serde::de::Error>::custom)
                 .map(Some),
@@ -217,6 +376,17 @@ impl<'de, 'a: 'de> MapAccess<'de> for RowView<'a> {
     where
         K: DeserializeSeed<'de>,
     {
+        if let Some(requested) = self.requested {
+            // Jump to the column of the next requested field, other columns are never read.
+            while let Some(&name) = requested.get(self.field) {
+                self.field += 1;
+                if let Some(&col) = self.raw.name_index.get(name) {
+                    self.col = col;
+                    return seed.deserialize(name.into_deserializer()).map(Some);
+                }
+            }
+            return Ok(None);
+        }
         match self.peek_name() {
             Some(name) => seed.deserialize(name.into_deserializer()).map(Some),
             _ => Ok(None),
@@ -227,17 +397,29 @@ impl<'de, 'a: 'de> MapAccess<'de> for RowView<'a> {
     where
         V: DeserializeSeed<'de>,
     {
-        // let value = self
-        //     .walk_next()
-        //     .ok_or(<Self::Error as serde::de::Error>::custom(
-        //         "expect a value but no value remains",
-        //     ))?; // always be here, so it's safe to unwrap
-
-        seed.deserialize(&mut *self)
+        // Deserialize the value itself, so maps and sequences, eg. json as `HashMap` fields, are
+        // read from the value but not the rest of the row.
+        seed.deserialize(self.expect_next_with_policy()?)
             .map_err(<Self::Error as serde::de::Error>::custom)
     }
 }
 
+/// Deserialize the next value with the typed method, so the typed value is never buffered.
+macro_rules! forward_to_next_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.expect_next_with_policy()?
+                    .$method(visitor)
+                    .map_err(<Self::Error as serde::de::Error>::custom)
+            }
+        )*
+    };
+}
+
 impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
     type Error = DeError;
 
@@ -248,7 +430,7 @@ impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
     where
         V: Visitor<'de>,
     {
-        match self.walk_next() {
+        match self.walk_next_with_policy() {
             Some(v) => v
                 .deserialize_any(visitor)
                 .map_err(<Self::Error as serde::de::Error>::custom),
@@ -258,9 +440,25 @@ impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
         }
     }
 
-    serde::forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char bytes byte_buf enum
-        identifier ignored_any
+    forward_to_next_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_bytes deserialize_byte_buf
+        deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect_next_with_policy()?
+            .deserialize_enum(name, variants, visitor)
+            .map_err(<Self::Error as serde::de::Error>::custom)
     }
 
     // Refer to the "Understanding deserializer lifetimes" page for information
@@ -269,7 +467,7 @@ impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
     where
         V: Visitor<'de>,
     {
-        match self.walk_next() {
+        match self.walk_next_with_policy() {
             Some(v) => v
                 .deserialize_str(visitor)
                 .map_err(<Self::Error as serde::de::Error>::custom),
@@ -290,9 +488,9 @@ impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
     where
         V: Visitor<'de>,
     {
-        match self.walk_next() {
+        match self.walk_next_with_policy() {
             Some(v) => {
-                if v.is_null() {
+                if v.value.is_null() {
                     visitor.visit_none()
                 } else {
                     visitor
@@ -380,6 +578,11 @@ impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
     // Much like `deserialize_seq` but calls the visitors `visit_map` method
     // with a `MapAccess` implementation, rather than the visitor's `visit_seq`
     // method with a `SeqAccess` implementation.
+    //
+    // Serde calls `deserialize_map` for maps and structs with `#[serde(flatten)]` fields, which
+    // buffer the values with `deserialize_any` before deserializing flattened fields. Timestamps
+    // are buffered in the form of [Timestamp](crate::common::Timestamp) serialization, eg.
+    // `{"Milliseconds": 0}`, to keep the precision.
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
@@ -388,6 +591,7 @@ impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
         if self.raw.fields.is_empty() {
             return visitor.visit_seq(self);
         }
+        self.buffered = true;
         visitor.visit_map(self)
     }
 
@@ -397,6 +601,337 @@ impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
     // that the `Deserialize` implementation is required to know what the fields
     // are before even looking at the input data. Any key-value pairing in which
     // the fields cannot be known ahead of time is probably a map.
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        // No field names, just access as sequence.
+        if self.raw.fields.is_empty() {
+            return visitor.visit_seq(self);
+        }
+        // Visit the known fields only, so the cost of a row is in the number of fields but not
+        // the width of the block. Buffered visitors may want any column.
+        if !self.buffered && self.requested.is_none() && !fields.is_empty() {
+            self.requested = Some(fields);
+            self.field = 0;
+        }
+        visitor.visit_map(self)
+    }
+}
+
+/// Value deserializer that applies [NullPolicy] to NULL values except for `Option` fields.
+struct NullPolicyValue<'a> {
+    value: BorrowedValue<'a>,
+    policy: &'a NullPolicy,
+    precision: Precision,
+    /// Deserialize timestamps as `{"<precision variant>": raw}` in `deserialize_any`.
+    buffered: bool,
+}
+
+impl<'a> NullPolicyValue<'a> {
+    fn resolve(self) -> BorrowedValue<'a> {
+        self.policy.resolve(self.value, self.precision)
+    }
+}
+
+macro_rules! forward_to_resolved {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.resolve().$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for NullPolicyValue<'de> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let buffered = self.buffered;
+        match self.resolve() {
+            BorrowedValue::Timestamp(ts) if buffered => {
+                let variant = match ts {
+                    Timestamp::Milliseconds(_) => "Milliseconds",
+                    Timestamp::Microseconds(_) => "Microseconds",
+                    Timestamp::Nanoseconds(_) => "Nanoseconds",
+                };
+                visitor.visit_map(MapDeserializer::new(std::iter::once((
+                    variant,
+                    ts.as_raw_i64(),
+                ))))
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_resolved! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_seq deserialize_map
+        deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.value.is_null() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.resolve().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.resolve().deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.resolve().deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.resolve().deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.resolve().deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.resolve().deserialize_enum(name, variants, visitor)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, serde::de::value::Error> for NullPolicyValue<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+/// A row of named values, deserialized as a map for structs or a sequence for tuples, see
+/// [RowView::into_buffered].
+pub struct BufferedRow<'a> {
+    values: Vec<(&'a str, BorrowedValue<'a>)>,
+    /// Blocks parsed without field names are only deserialized as sequences.
+    named: bool,
+    policy: &'a NullPolicy,
+    precision: Precision,
+}
+
+impl<'a> std::fmt::Debug for BufferedRow<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferedRow")
+            .field("values", &self.values)
+            .field("named", &self.named)
+            .finish()
+    }
+}
+
+impl<'de> BufferedRow<'de> {
+    fn into_values(self) -> impl Iterator<Item = (&'de str, NullPolicyValue<'de>)> {
+        let (policy, precision) = (self.policy, self.precision);
+        self.values.into_iter().map(move |(name, value)| {
+            let value = NullPolicyValue {
+                value,
+                policy,
+                precision,
+                buffered: true,
+            };
+            (name, value)
+        })
+    }
+
+    fn visit_map<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        if !self.named {
+            return self.visit_seq(visitor);
+        }
+        visitor
+            .visit_map(MapDeserializer::new(self.into_values()))
+            .map_err(<DeError as serde::de::Error>::custom)
+    }
+
+    fn visit_seq<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        let values = self.into_values().map(|(_, value)| value);
+        visitor
+            .visit_seq(SeqDeserializer::new(values))
+            .map_err(<DeError as serde::de::Error>::custom)
+    }
+}
+
+/// Deserialize the value of single column rows, or the row as a map otherwise, which fails for
+/// scalars as expected.
+macro_rules! forward_to_single_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                if self.values.len() != 1 {
+                    return self.visit_map(visitor);
+                }
+                let (_, value) = self.into_values().next().unwrap();
+                value
+                    .$method(visitor)
+                    .map_err(<Self::Error as serde::de::Error>::custom)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for BufferedRow<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit_map(visitor)
+    }
+
+    forward_to_single_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_identifier
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit_seq(visitor)
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit_map(visitor)
+    }
+
     fn deserialize_struct<V>(
         self,
         _name: &'static str,
@@ -406,6 +941,31 @@ impl<'de, 'a: 'de> Deserializer<'de> for &mut RowView<'a> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        self.visit_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.values.len() != 1 {
+            return self.visit_map(visitor);
+        }
+        let (_, value) = self.into_values().next().unwrap();
+        value
+            .deserialize_enum(name, variants, visitor)
+            .map_err(<Self::Error as serde::de::Error>::custom)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
     }
 }
@@ -0,0 +1,429 @@
+//! Length-framed streams of [RawBlock]s over any async transport.
+//!
+//! A stream starts with a 5-byte header, magic `TBLK` and the stream version, followed by
+//! frames of inlined blocks (precision, table name, field names and block bytes):
+//!
+//! | tag: u8 | payload length: u32 LE | payload | checksum: u32 LE, if tag is an algorithm |
+//!
+//! Tag `0` is a frame without checksum, [ChecksumAlgorithm] values are frames with checksum,
+//! and `0xFF` ends the stream. A stream closed without the end marker is truncated, it's
+//! reported as [std::io::ErrorKind::UnexpectedEof] with a [PartialFrame] inside.
+//!
+//! ```rust
+//! # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+//! # use taos_query::common::stream::{BlockStreamReader, BlockStreamWriter};
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> std::io::Result<()> {
+//! let views = [ColumnView::from_ints(vec![1, 2, 3])];
+//! let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+//! block.with_field_names(["v"]);
+//!
+//! let mut writer = BlockStreamWriter::new(Vec::new());
+//! writer.write_block(&block).await?;
+//! let bytes = writer.finish().await?;
+//!
+//! let mut reader = BlockStreamReader::new(bytes.as_slice());
+//! let read = reader.read_block().await?.unwrap();
+//! assert_eq!(read.to_values(), block.to_values());
+//! assert!(reader.read_block().await?.is_none());
+//! # Ok(())
+//! # }
+//! ```
+use std::io::{Error, ErrorKind};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::util::Inlinable;
+
+use super::{ChecksumAlgorithm, RawBlock};
+
+const MAGIC: [u8; 4] = *b"TBLK";
+const STREAM_VERSION: u8 = 1;
+
+const TAG_NO_CHECKSUM: u8 = 0;
+const TAG_END: u8 = 0xFF;
+
+/// The stream ends in the middle of a frame, or without the end marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("stream ends in frame {frame} after {consumed} bytes")]
+pub struct PartialFrame {
+    /// Index of the incomplete frame, the stream header is not counted.
+    pub frame: u64,
+    /// Bytes consumed from the stream, including the incomplete frame.
+    pub consumed: u64,
+}
+
+/// Writes blocks as frames, call [BlockStreamWriter::finish] to write the end marker.
+#[derive(Debug)]
+pub struct BlockStreamWriter<W> {
+    inner: W,
+    checksum: Option<ChecksumAlgorithm>,
+    frames: u64,
+    bytes_written: u64,
+}
+
+impl<W: AsyncWrite + Unpin> BlockStreamWriter<W> {
+    /// Frames without checksum, the transport is trusted.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            checksum: None,
+            frames: 0,
+            bytes_written: 0,
+        }
+    }
+
+    /// Append a checksum of the algorithm to each frame.
+    pub fn with_checksum(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        self.checksum = Some(algorithm);
+        self
+    }
+
+    /// Frames written.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Bytes written, including the stream header.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    async fn write_header(&mut self) -> std::io::Result<()> {
+        if self.bytes_written == 0 {
+            self.inner.write_all(&MAGIC).await?;
+            self.inner.write_u8(STREAM_VERSION).await?;
+            self.bytes_written += MAGIC.len() as u64 + 1;
+        }
+        Ok(())
+    }
+
+    /// Write a block as a frame, returns bytes of the frame.
+    pub async fn write_block(&mut self, block: &RawBlock) -> std::io::Result<usize> {
+        self.write_header().await?;
+        let payload = block.inlined();
+        let len = u32::try_from(payload.len())
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "block is too large for a frame"))?;
+
+        let tag = self
+            .checksum
+            .map_or(TAG_NO_CHECKSUM, |algorithm| algorithm as u8);
+        self.inner.write_u8(tag).await?;
+        self.inner.write_u32_le(len).await?;
+        self.inner.write_all(&payload).await?;
+        let mut size = 1 + 4 + payload.len();
+        if let Some(algorithm) = self.checksum {
+            self.inner
+                .write_u32_le(algorithm.checksum(&payload))
+                .await?;
+            size += 4;
+        }
+        self.frames += 1;
+        self.bytes_written += size as u64;
+        Ok(size)
+    }
+
+    /// Write the end marker and flush, returns the transport.
+    pub async fn finish(mut self) -> std::io::Result<W> {
+        self.write_header().await?;
+        self.inner.write_u8(TAG_END).await?;
+        self.inner.flush().await?;
+        Ok(self.inner)
+    }
+}
+
+/// Reads blocks from frames written by [BlockStreamWriter].
+#[derive(Debug)]
+pub struct BlockStreamReader<R> {
+    inner: R,
+    verify: bool,
+    frames: u64,
+    bytes_read: u64,
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> BlockStreamReader<R> {
+    /// Checksums of frames are verified if any.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            verify: true,
+            frames: 0,
+            bytes_read: 0,
+            finished: false,
+        }
+    }
+
+    /// Skip checksum verification.
+    pub fn unverified(mut self) -> Self {
+        self.verify = false;
+        self
+    }
+
+    /// Frames read.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Bytes consumed, including the stream header.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn partial_frame(&self) -> Error {
+        Error::new(
+            ErrorKind::UnexpectedEof,
+            PartialFrame {
+                frame: self.frames,
+                consumed: self.bytes_read,
+            },
+        )
+    }
+
+    /// Read exactly `buf.len()` bytes, counting partial reads.
+    async fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let n = self.inner.read(&mut buf[filled..]).await?;
+            if n == 0 {
+                return Err(self.partial_frame());
+            }
+            filled += n;
+            self.bytes_read += n as u64;
+        }
+        Ok(())
+    }
+
+    async fn read_u8(&mut self) -> std::io::Result<u8> {
+        let mut buf = [0; 1];
+        self.read_exact(&mut buf).await?;
+        Ok(buf[0])
+    }
+
+    async fn read_u32(&mut self) -> std::io::Result<u32> {
+        let mut buf = [0; 4];
+        self.read_exact(&mut buf).await?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    async fn read_header(&mut self) -> std::io::Result<()> {
+        let mut header = [0; MAGIC.len() + 1];
+        self.read_exact(&mut header).await?;
+        if header[..MAGIC.len()] != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a block stream"));
+        }
+        if header[MAGIC.len()] != STREAM_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported block stream version {}", header[MAGIC.len()]),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Read the next block, `None` after the end marker.
+    pub async fn read_block(&mut self) -> std::io::Result<Option<RawBlock>> {
+        if self.finished {
+            return Ok(None);
+        }
+        if self.bytes_read == 0 {
+            self.read_header().await?;
+        }
+        let algorithm = match self.read_u8().await? {
+            TAG_END => {
+                self.finished = true;
+                return Ok(None);
+            }
+            TAG_NO_CHECKSUM => None,
+            tag => Some(
+                ChecksumAlgorithm::try_from(tag)
+                    .map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+            ),
+        };
+        let len = self.read_u32().await? as usize;
+        // Don't trust the length to pre-allocate, it may be corrupted.
+        let mut payload = Vec::new();
+        let n = (&mut self.inner)
+            .take(len as u64)
+            .read_to_end(&mut payload)
+            .await?;
+        self.bytes_read += n as u64;
+        if n != len {
+            return Err(self.partial_frame());
+        }
+        if let Some(algorithm) = algorithm {
+            let expected = self.read_u32().await?;
+            if self.verify {
+                let actual = algorithm.checksum(&payload);
+                if expected != actual {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        super::checksum::Error::ChecksumMismatch { expected, actual },
+                    ));
+                }
+            }
+        }
+        let mut slice = payload.as_slice();
+        let block = RawBlock::read_inlined(&mut slice)?;
+        if !slice.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} trailing bytes in frame {}", slice.len(), self.frames),
+            ));
+        }
+        self.frames += 1;
+        Ok(Some(block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::views::ColSchema;
+    use crate::common::Ty;
+    use crate::testing::{random_block, RandomOpts};
+
+    fn blocks(n: u64) -> Vec<RawBlock> {
+        let schemas = [
+            vec![
+                ColSchema::new(Ty::Timestamp, 8),
+                ColSchema::new(Ty::Int, 4),
+                ColSchema::new(Ty::Double, 8),
+                ColSchema::new(Ty::VarChar, 16),
+            ],
+            vec![
+                ColSchema::new(Ty::Timestamp, 8),
+                ColSchema::new(Ty::BigInt, 8),
+                ColSchema::new(Ty::UInt, 4),
+                ColSchema::new(Ty::Float, 4),
+                ColSchema::new(Ty::Int, 4),
+                ColSchema::new(Ty::Double, 8),
+                ColSchema::new(Ty::UBigInt, 8),
+                ColSchema::new(Ty::VarChar, 64),
+            ],
+        ];
+        let opts = RandomOpts::new().with_null_probability(0.1);
+        (0..n)
+            .map(|seed| {
+                let schema = &schemas[seed as usize % schemas.len()];
+                let rows = (seed as usize * 7) % 64;
+                let mut block = random_block(schema, rows, seed, &opts);
+                if seed % 3 == 0 {
+                    block.with_table_name(format!("t{seed}"));
+                }
+                block
+            })
+            .collect()
+    }
+
+    async fn round_trip(checksum: Option<ChecksumAlgorithm>) {
+        let blocks = blocks(2000);
+        let (client, server) = tokio::io::duplex(4096);
+
+        let writer = tokio::spawn(async move {
+            let mut writer = BlockStreamWriter::new(client);
+            if let Some(algorithm) = checksum {
+                writer = writer.with_checksum(algorithm);
+            }
+            // Blocks are deterministic, generate them again in the writer task.
+            for block in &self::blocks(2000) {
+                writer.write_block(block).await?;
+            }
+            let (frames, bytes) = (writer.frames(), writer.bytes_written());
+            writer.finish().await?;
+            std::io::Result::Ok((frames, bytes))
+        });
+
+        let mut reader = BlockStreamReader::new(server);
+        let mut read = Vec::new();
+        while let Some(block) = reader.read_block().await.unwrap() {
+            read.push(block);
+        }
+        let (frames, bytes) = writer.await.unwrap().unwrap();
+        assert_eq!(reader.frames(), frames);
+        assert_eq!(reader.bytes_read(), bytes + 1);
+        assert!(reader.read_block().await.unwrap().is_none());
+
+        assert_eq!(read.len(), blocks.len());
+        for (read, block) in read.iter().zip(&blocks) {
+            assert_eq!(read.as_raw_bytes(), block.as_raw_bytes());
+            assert_eq!(read.precision(), block.precision());
+            assert_eq!(read.table_name(), block.table_name());
+            assert_eq!(read.field_names(), block.field_names());
+        }
+    }
+
+    #[tokio::test]
+    async fn round_trip_over_duplex() {
+        round_trip(None).await;
+        round_trip(Some(ChecksumAlgorithm::default())).await;
+    }
+
+    async fn framed(checksum: bool) -> Vec<u8> {
+        let mut writer = BlockStreamWriter::new(Vec::new());
+        if checksum {
+            writer = writer.with_checksum(ChecksumAlgorithm::default());
+        }
+        for block in blocks(3) {
+            writer.write_block(&block).await.unwrap();
+        }
+        writer.finish().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn truncated_stream() {
+        let bytes = framed(false).await;
+        let mut reader = BlockStreamReader::new(bytes.as_slice());
+        let first = loop {
+            reader.read_block().await.unwrap();
+            if reader.frames() == 1 {
+                break reader.bytes_read() as usize;
+            }
+        };
+
+        // Cut in the second frame, or right before the end marker.
+        for end in [first + 3, first + 20, bytes.len() - 1] {
+            let mut reader = BlockStreamReader::new(&bytes[..end]);
+            let err = loop {
+                match reader.read_block().await {
+                    Ok(block) => assert!(block.is_some()),
+                    Err(err) => break err,
+                }
+            };
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+            let partial = err.get_ref().unwrap().downcast_ref::<PartialFrame>();
+            assert_eq!(partial.unwrap().consumed, end as u64);
+            assert_eq!(reader.bytes_read(), end as u64);
+        }
+
+        let err = BlockStreamReader::new(&b"TBL"[..]).read_block().await;
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        let err = BlockStreamReader::new(&b"TBLK\x02"[..]).read_block().await;
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn corrupted_frame() {
+        let mut bytes = framed(true).await;
+        // In the payload of the second frame.
+        let at = bytes.len() / 2;
+        bytes[at] ^= 0x5A;
+
+        let mut reader = BlockStreamReader::new(bytes.as_slice());
+        let err = loop {
+            match reader.read_block().await {
+                Ok(block) => assert!(block.is_some()),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
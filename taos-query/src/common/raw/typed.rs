@@ -0,0 +1,541 @@
+//! Whole columns as typed vectors and zero-copy slices, without a value per cell.
+use std::borrow::Cow;
+
+use chrono::NaiveDateTime;
+
+use crate::common::{Timestamp, Ty};
+
+use super::views::LePrimitive;
+use super::{ColumnView, RawBlock};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ColumnError {
+    #[error("column `{0}` not found")]
+    NotFound(String),
+    #[error("column index {index} out of range of {ncols} columns")]
+    OutOfRange { index: usize, ncols: usize },
+    #[error("column of type {ty} can't be read as {expected}")]
+    TypeMismatch { ty: Ty, expected: &'static str },
+    #[error("column {index} of type {ty} can't be read as {expected}")]
+    ColumnTypeMismatch {
+        index: usize,
+        ty: Ty,
+        expected: &'static str,
+    },
+    #[error("expected {expected} columns, the block has {ncols}")]
+    ColumnCount { expected: usize, ncols: usize },
+    #[error("column {index} is NULL at row {row}")]
+    UnexpectedNull { index: usize, row: usize },
+}
+
+impl ColumnError {
+    /// Name the column of a type mismatch by its index.
+    fn at(self, index: usize) -> Self {
+        match self {
+            Self::TypeMismatch { ty, expected } => Self::ColumnTypeMismatch {
+                index,
+                ty,
+                expected,
+            },
+            err => err,
+        }
+    }
+}
+
+impl From<ColumnError> for taos_error::Error {
+    fn from(err: ColumnError) -> Self {
+        Self::from_any(err)
+    }
+}
+
+/// A column selected by index or by name.
+///
+/// Names are matched as [RawBlock::column] does, so the first column wins if names are
+/// duplicated, eg. in joins. Use [RawBlock::dedup_field_names] or indices to reach the others.
+pub trait ColumnIndex {
+    fn column_index(&self, block: &RawBlock) -> Result<usize, ColumnError>;
+}
+
+impl ColumnIndex for usize {
+    fn column_index(&self, block: &RawBlock) -> Result<usize, ColumnError> {
+        if *self < block.ncols() {
+            Ok(*self)
+        } else {
+            Err(ColumnError::OutOfRange {
+                index: *self,
+                ncols: block.ncols(),
+            })
+        }
+    }
+}
+
+impl ColumnIndex for str {
+    fn column_index(&self, block: &RawBlock) -> Result<usize, ColumnError> {
+        block
+            .name_index
+            .get(self)
+            .copied()
+            .ok_or_else(|| ColumnError::NotFound(self.to_string()))
+    }
+}
+
+impl ColumnIndex for String {
+    fn column_index(&self, block: &RawBlock) -> Result<usize, ColumnError> {
+        self.as_str().column_index(block)
+    }
+}
+
+impl<T: ColumnIndex + ?Sized> ColumnIndex for &T {
+    fn column_index(&self, block: &RawBlock) -> Result<usize, ColumnError> {
+        (**self).column_index(block)
+    }
+}
+
+/// Types a whole column can be read as, with `None` for NULL.
+///
+/// Types must match the column exactly, except that timestamps are also read as `i64` in the
+/// precision of the column, or as [NaiveDateTime] in UTC, and strings are read from varchar,
+/// nchar and json columns.
+pub trait FromColumnView: Sized {
+    fn from_column_view(view: &ColumnView) -> Result<Vec<Option<Self>>, ColumnError>;
+}
+
+/// Fixed-size types a column can be borrowed as by [ColumnView::as_slice].
+pub trait FixedColumnType: FromColumnView + Copy {
+    fn as_slice(view: &ColumnView) -> Result<Cow<'_, [Self]>, ColumnError>;
+}
+
+/// The raw slice of a view if it's aligned, or a copy of its data decoded from little-endian.
+fn slice_or_copy<'a, T: LePrimitive>(slice: Option<&'a [T]>, data: &[u8]) -> Cow<'a, [T]> {
+    if let Some(slice) = slice {
+        return Cow::Borrowed(slice);
+    }
+    let len = data.len() / std::mem::size_of::<T>();
+    let mut values = Vec::<T>::with_capacity(len);
+    // SAFETY: `T` is a number valid for any bits, and `values` has room for `len` of them.
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            data.as_ptr(),
+            values.as_mut_ptr() as *mut u8,
+            len * std::mem::size_of::<T>(),
+        );
+        values.set_len(len);
+    }
+    values.iter_mut().for_each(|v| *v = v.decode_le());
+    Cow::Owned(values)
+}
+
+fn mismatch<T>(view: &ColumnView) -> ColumnError {
+    ColumnError::TypeMismatch {
+        ty: view.as_ty(),
+        expected: std::any::type_name::<T>(),
+    }
+}
+
+macro_rules! impl_fixed_column {
+    ($($ty:ty: $variant:ident => |$v:ident| $slice:expr;)*) => {
+        $(
+            impl FromColumnView for $ty {
+                fn from_column_view(view: &ColumnView) -> Result<Vec<Option<Self>>, ColumnError> {
+                    match view {
+                        ColumnView::$variant($v) => Ok($v.to_vec()),
+                        _ => Err(mismatch::<Self>(view)),
+                    }
+                }
+            }
+
+            impl FixedColumnType for $ty {
+                fn as_slice(view: &ColumnView) -> Result<Cow<'_, [Self]>, ColumnError> {
+                    match view {
+                        ColumnView::$variant($v) => Ok(slice_or_copy($slice, &$v.data)),
+                        _ => Err(mismatch::<Self>(view)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_column! {
+    i8: TinyInt => |v| Some(v.as_raw_slice());
+    i16: SmallInt => |v| v.as_raw_slice();
+    i32: Int => |v| v.as_raw_slice();
+    u8: UTinyInt => |v| Some(v.as_raw_slice());
+    u16: USmallInt => |v| v.as_raw_slice();
+    u32: UInt => |v| v.as_raw_slice();
+    u64: UBigInt => |v| v.as_raw_slice();
+    f32: Float => |v| v.as_raw_slice();
+    f64: Double => |v| v.as_raw_slice();
+}
+
+impl FromColumnView for i64 {
+    fn from_column_view(view: &ColumnView) -> Result<Vec<Option<Self>>, ColumnError> {
+        match view {
+            ColumnView::BigInt(v) => Ok(v.to_vec()),
+            ColumnView::Timestamp(v) => {
+                Ok(v.iter().map(|ts| ts.map(|ts| ts.as_raw_i64())).collect())
+            }
+            _ => Err(mismatch::<Self>(view)),
+        }
+    }
+}
+
+impl FixedColumnType for i64 {
+    fn as_slice(view: &ColumnView) -> Result<Cow<'_, [Self]>, ColumnError> {
+        match view {
+            ColumnView::BigInt(v) => Ok(slice_or_copy(v.as_raw_slice(), &v.data)),
+            ColumnView::Timestamp(v) => Ok(slice_or_copy(v.as_raw_slice(), &v.data)),
+            _ => Err(mismatch::<Self>(view)),
+        }
+    }
+}
+
+impl FromColumnView for bool {
+    fn from_column_view(view: &ColumnView) -> Result<Vec<Option<Self>>, ColumnError> {
+        match view {
+            ColumnView::Bool(v) => Ok(v.to_vec()),
+            _ => Err(mismatch::<Self>(view)),
+        }
+    }
+}
+
+impl FromColumnView for Timestamp {
+    fn from_column_view(view: &ColumnView) -> Result<Vec<Option<Self>>, ColumnError> {
+        match view {
+            ColumnView::Timestamp(v) => Ok(v.to_vec()),
+            _ => Err(mismatch::<Self>(view)),
+        }
+    }
+}
+
+impl FromColumnView for NaiveDateTime {
+    fn from_column_view(view: &ColumnView) -> Result<Vec<Option<Self>>, ColumnError> {
+        match view {
+            ColumnView::Timestamp(v) => Ok(v
+                .iter()
+                .map(|ts| ts.map(|ts| ts.to_naive_datetime()))
+                .collect()),
+            _ => Err(mismatch::<Self>(view)),
+        }
+    }
+}
+
+impl FromColumnView for String {
+    fn from_column_view(view: &ColumnView) -> Result<Vec<Option<Self>>, ColumnError> {
+        match view {
+            ColumnView::VarChar(v) => Ok(v.to_vec()),
+            ColumnView::NChar(v) => Ok(v.iter().map(|s| s.map(|s| s.to_string())).collect()),
+            ColumnView::Json(v) => Ok(v.to_vec()),
+            _ => Err(mismatch::<Self>(view)),
+        }
+    }
+}
+
+impl ColumnView {
+    /// Values of a fixed-size column as a slice, borrowed without copying if the data is aligned
+    /// for `T`, or copied at once otherwise, eg. for blocks parsed from received bytes.
+    ///
+    /// Values at NULL rows are unspecified, check [RawBlock::is_null] if the column is
+    /// nullable. It fails if `T` is not the type of the column.
+    pub fn as_slice<T: FixedColumnType>(&self) -> Result<Cow<'_, [T]>, ColumnError> {
+        T::as_slice(self)
+    }
+}
+
+impl RawBlock {
+    /// Values of a column by index or name as `T`, with `None` for NULL, see [FromColumnView]
+    /// for the types of each column.
+    ///
+    /// It does not build a [BorrowedValue](crate::common::BorrowedValue) per cell, so it's much
+    /// faster than deserializing rows when only a few columns are needed. Names are matched as
+    /// [RawBlock::column], the first column wins if names are duplicated.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// use chrono::NaiveDateTime;
+    ///
+    /// let views = [
+    ///     ColumnView::from_millis_timestamp(vec![0, 1500]),
+    ///     ColumnView::from_doubles(vec![Some(0.5), None]),
+    /// ];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "v"]);
+    ///
+    /// let values: Vec<Option<f64>> = block.column_vec("v").unwrap();
+    /// assert_eq!(values, [Some(0.5), None]);
+    /// let epochs: Vec<Option<i64>> = block.column_vec(0).unwrap();
+    /// assert_eq!(epochs, [Some(0), Some(1500)]);
+    /// let times = block.column_vec::<NaiveDateTime>("ts").unwrap();
+    /// assert_eq!(times[1].unwrap().to_string(), "1970-01-01 00:00:01.500");
+    ///
+    /// let err = block.column_vec::<i32>("v").unwrap_err();
+    /// assert_eq!(err.to_string(), "column of type DOUBLE can't be read as i32");
+    /// ```
+    pub fn column_vec<T: FromColumnView>(
+        &self,
+        index: impl ColumnIndex,
+    ) -> Result<Vec<Option<T>>, ColumnError> {
+        T::from_column_view(self.view(index.column_index(self)?))
+    }
+
+    /// Values of a fixed-size column by index or name as a slice, see [ColumnView::as_slice].
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = [ColumnView::from_doubles(vec![0.5, 1.5])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["v"]);
+    /// let sum: f64 = block.column_slice::<f64>("v").unwrap().iter().sum();
+    /// assert_eq!(sum, 2.);
+    /// ```
+    pub fn column_slice<T: FixedColumnType>(
+        &self,
+        index: impl ColumnIndex,
+    ) -> Result<Cow<'_, [T]>, ColumnError> {
+        self.view(index.column_index(self)?).as_slice()
+    }
+}
+
+/// A whole column as `Vec<Option<T>>`, or `Vec<T>` if it has no NULL, see [FromColumnView] for
+/// the types of each column.
+///
+/// Tuples of up to 12 of them are read from blocks of as many columns by `TryFrom<&RawBlock>`,
+/// column by column in order, without serde.
+///
+/// ```rust
+/// # use taos_query::common::{views::views_to_raw_block, ColumnError, ColumnView, Precision, RawBlock, Ty};
+/// let views = [
+///     ColumnView::from_millis_timestamp(vec![0, 1000]),
+///     ColumnView::from_doubles(vec![Some(0.5), None]),
+/// ];
+/// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+///
+/// let (ts, values): (Vec<i64>, Vec<Option<f64>>) = (&block).try_into().unwrap();
+/// assert_eq!(ts, [0, 1000]);
+/// assert_eq!(values, [Some(0.5), None]);
+///
+/// let err = <(Vec<i64>, Vec<f64>)>::try_from(&block).unwrap_err();
+/// assert_eq!(err, ColumnError::UnexpectedNull { index: 1, row: 1 });
+/// let err = <(Vec<i64>, Vec<i32>)>::try_from(&block).unwrap_err();
+/// assert_eq!(err.to_string(), "column 1 of type DOUBLE can't be read as i32");
+/// ```
+pub trait FromColumn: Sized {
+    fn from_column(block: &RawBlock, index: usize) -> Result<Self, ColumnError>;
+}
+
+impl<T: FromColumnView> FromColumn for Vec<Option<T>> {
+    fn from_column(block: &RawBlock, index: usize) -> Result<Self, ColumnError> {
+        T::from_column_view(block.view(index)).map_err(|err| err.at(index))
+    }
+}
+
+impl<T: FromColumnView> FromColumn for Vec<T> {
+    fn from_column(block: &RawBlock, index: usize) -> Result<Self, ColumnError> {
+        Vec::<Option<T>>::from_column(block, index)?
+            .into_iter()
+            .enumerate()
+            .map(|(row, value)| value.ok_or(ColumnError::UnexpectedNull { index, row }))
+            .collect()
+    }
+}
+
+macro_rules! impl_try_from_block {
+    ($($n:literal => ($($t:ident $i:tt),+);)*) => {
+        $(
+            impl<$($t: FromColumn),+> TryFrom<&RawBlock> for ($($t,)+) {
+                type Error = ColumnError;
+
+                fn try_from(block: &RawBlock) -> Result<Self, Self::Error> {
+                    if block.ncols() != $n {
+                        return Err(ColumnError::ColumnCount {
+                            expected: $n,
+                            ncols: block.ncols(),
+                        });
+                    }
+                    Ok(($($t::from_column(block, $i)?,)+))
+                }
+            }
+
+            impl<$($t: FromColumn),+> TryFrom<RawBlock> for ($($t,)+) {
+                type Error = ColumnError;
+
+                fn try_from(block: RawBlock) -> Result<Self, Self::Error> {
+                    Self::try_from(&block)
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_block! {
+    1 => (A 0);
+    2 => (A 0, B 1);
+    3 => (A 0, B 1, C 2);
+    4 => (A 0, B 1, C 2, D 3);
+    5 => (A 0, B 1, C 2, D 3, E 4);
+    6 => (A 0, B 1, C 2, D 3, E 4, F 5);
+    7 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+    8 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+    9 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+    10 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+    11 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+    12 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::views::views_to_raw_block;
+    use crate::common::Precision;
+
+    #[test]
+    fn typed_columns() {
+        let views = [
+            ColumnView::from_micros_timestamp(vec![1_000_001, 2_000_002]),
+            ColumnView::from_ints(vec![Some(1), None]),
+            ColumnView::from_tiny_ints(vec![-1, 1]),
+            ColumnView::from_bools(vec![Some(true), None]),
+            ColumnView::from_nchar::<&str, _, _, _>(vec![Some("a"), None]),
+            ColumnView::from_ints(vec![3, 4]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        block.with_field_names(["ts", "v", "t", "b", "s", "v"]);
+
+        // timestamps in the precision of the block.
+        assert_eq!(
+            block.column_vec::<i64>("ts").unwrap(),
+            [Some(1_000_001), Some(2_000_002)]
+        );
+        let times = block.column_vec::<NaiveDateTime>("ts").unwrap();
+        assert_eq!(times[0].unwrap().to_string(), "1970-01-01 00:00:01.000001");
+        assert_eq!(
+            block.column_vec::<Timestamp>(0).unwrap()[1],
+            Some(Timestamp::Microseconds(2_000_002))
+        );
+
+        // the first of duplicated names, the others by index.
+        assert_eq!(block.column_vec::<i32>("v").unwrap(), [Some(1), None]);
+        assert_eq!(block.column_vec::<i32>(5).unwrap(), [Some(3), Some(4)]);
+        assert_eq!(block.column_vec::<bool>("b").unwrap(), [Some(true), None]);
+        assert_eq!(
+            block.column_vec::<String>(&"s".to_string()).unwrap(),
+            [Some("a".to_string()), None]
+        );
+
+        assert_eq!(*block.column_slice::<i8>("t").unwrap(), [-1, 1]);
+        assert_eq!(*block.column_slice::<i32>(5).unwrap(), [3, 4]);
+        // views built in memory are aligned, so borrowed.
+        let view = ColumnView::from_doubles(vec![0.5, 1.5]);
+        assert!(matches!(
+            view.as_slice::<f64>().unwrap(),
+            Cow::Borrowed([0.5, 1.5])
+        ));
+
+        assert_eq!(
+            block.column_vec::<i64>("v").unwrap_err(),
+            ColumnError::TypeMismatch {
+                ty: Ty::Int,
+                expected: "i64"
+            }
+        );
+        assert_eq!(
+            block.column_slice::<u32>("v").unwrap_err(),
+            ColumnError::TypeMismatch {
+                ty: Ty::Int,
+                expected: "u32"
+            }
+        );
+        assert_eq!(
+            block.column_vec::<i32>("x").unwrap_err(),
+            ColumnError::NotFound("x".to_string())
+        );
+        assert_eq!(
+            block.column_vec::<i32>(6).unwrap_err(),
+            ColumnError::OutOfRange { index: 6, ncols: 6 }
+        );
+    }
+
+    #[test]
+    fn column_tuples() {
+        let views = [
+            ColumnView::from_millis_timestamp(vec![1000, 2000, 3000]),
+            ColumnView::from_bools(vec![true, false, true]),
+            ColumnView::from_tiny_ints(vec![Some(-1), None, Some(1)]),
+            ColumnView::from_small_ints(vec![-2, 0, 2]),
+            ColumnView::from_ints(vec![-3, 0, 3]),
+            ColumnView::from_big_ints(vec![Some(-4), Some(0), None]),
+            ColumnView::from_unsigned_tiny_ints(vec![1, 2, 3]),
+            ColumnView::from_unsigned_ints(vec![4, 5, 6]),
+            ColumnView::from_unsigned_big_ints(vec![7, 8, 9]),
+            ColumnView::from_floats(vec![0.5, 1.5, 2.5]),
+            ColumnView::from_doubles(vec![None, Some(1.25), None]),
+            ColumnView::from_varchar::<&str, _, _, _>(vec![Some("a"), None, Some("c")]),
+        ];
+        let block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+
+        type Row12 = (
+            Vec<Timestamp>,
+            Vec<bool>,
+            Vec<Option<i8>>,
+            Vec<i16>,
+            Vec<i32>,
+            Vec<Option<i64>>,
+            Vec<u8>,
+            Vec<u32>,
+            Vec<u64>,
+            Vec<f32>,
+            Vec<Option<f64>>,
+            Vec<Option<String>>,
+        );
+        let (ts, b, ti, si, i, bi, uti, ui, ubi, f, d, s): Row12 = (&block).try_into().unwrap();
+        assert_eq!(ts[2], Timestamp::Milliseconds(3000));
+        assert_eq!(b, [true, false, true]);
+        assert_eq!(ti, [Some(-1), None, Some(1)]);
+        assert_eq!((si, i), (vec![-2, 0, 2], vec![-3, 0, 3]));
+        assert_eq!(bi, [Some(-4), Some(0), None]);
+        assert_eq!(
+            (uti, ui, ubi),
+            (vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9])
+        );
+        assert_eq!(f, [0.5, 1.5, 2.5]);
+        assert_eq!(d, [None, Some(1.25), None]);
+        assert_eq!(s, [Some("a".to_string()), None, Some("c".to_string())]);
+
+        // an owned block, and timestamps as epochs.
+        let single =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views[..1]), Precision::Millisecond);
+        let (epochs,): (Vec<i64>,) = single.try_into().unwrap();
+        assert_eq!(epochs, [1000, 2000, 3000]);
+
+        let columns = |n: usize| {
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views[..n]), Precision::Millisecond)
+        };
+        let (ts, b, ti): (Vec<NaiveDateTime>, Vec<Option<bool>>, Vec<Option<i8>>) =
+            (&columns(3)).try_into().unwrap();
+        assert_eq!(ts[0].to_string(), "1970-01-01 00:00:01");
+        assert_eq!(b[1], Some(false));
+        assert_eq!(ti[1], None);
+
+        assert_eq!(
+            <(Vec<i64>, Vec<bool>)>::try_from(&columns(3)).unwrap_err(),
+            ColumnError::ColumnCount {
+                expected: 2,
+                ncols: 3
+            }
+        );
+        assert_eq!(
+            <(Vec<i64>, Vec<bool>, Vec<i8>)>::try_from(&columns(3)).unwrap_err(),
+            ColumnError::UnexpectedNull { index: 2, row: 1 }
+        );
+        assert_eq!(
+            <(Vec<i64>, Vec<bool>, Vec<Option<i8>>, Vec<i32>)>::try_from(&columns(4)).unwrap_err(),
+            ColumnError::ColumnTypeMismatch {
+                index: 3,
+                ty: Ty::SmallInt,
+                expected: "i32"
+            }
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Validation of v3 raw bytes from untrusted sources, eg. blocks read back from a spool file.
+//!
+//! Parsing trusts the raw bytes, views slice column data by the recorded lengths and read
+//! strings without checks, so bytes not produced by the server or by a [super::RawBlock] must pass
+//! [check_raw_block] first.
+
+use std::io::{Error, ErrorKind, Result};
+use std::mem::size_of;
+
+use super::views::ColSchema;
+use super::Header;
+use crate::common::Ty;
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+/// Types of columns which could be parsed from raw blocks.
+fn is_parsable(ty: Ty) -> bool {
+    !matches!(ty, Ty::Null | Ty::Decimal | Ty::Blob | Ty::MediumBlob)
+}
+
+/// Type of column `col` by its schema, ids unknown to this version are named in the error.
+fn column_type(schema: &ColSchema, col: usize) -> Result<Ty> {
+    match schema.try_ty() {
+        Ok(ty) if is_parsable(ty) => Ok(ty),
+        Ok(ty) => Err(invalid(format!("unsupported type {ty} of column {col}"))),
+        Err(err) => Err(invalid(format!("{err} of column {col}"))),
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn schema_at(bytes: &[u8], header_len: usize, col: usize) -> &ColSchema {
+    let offset = header_len + col * size_of::<ColSchema>();
+    // SAFETY: the schema is packed, any 5 bytes in bounds are a valid one.
+    unsafe { &*(bytes[offset..offset + size_of::<ColSchema>()].as_ptr() as *const ColSchema) }
+}
+
+/// Check columns of the raw block from a server are of types known to this version, eg. not of
+/// types added by a newer server. Data is trusted and not checked as by [check_raw_block].
+pub(super) fn check_column_types(bytes: &[u8]) -> Result<()> {
+    let header_len = size_of::<Header>();
+    if bytes.len() < header_len {
+        return Err(invalid(format!(
+            "raw block of {} bytes is shorter than its header",
+            bytes.len()
+        )));
+    }
+    // SAFETY: the header is packed, any 28 bytes are a valid one.
+    let header = unsafe { &*(bytes.as_ptr() as *const Header) };
+    let cols = header.ncols();
+    cols.checked_mul(size_of::<ColSchema>())
+        .and_then(|len| len.checked_add(header_len))
+        .filter(|end| *end <= bytes.len())
+        .ok_or_else(|| invalid(format!("raw block is too short for {cols} columns")))?;
+    for col in 0..cols {
+        column_type(schema_at(bytes, header_len, col), col)?;
+    }
+    Ok(())
+}
+
+/// Check `bytes` is a well-formed v3 raw block, so that parsing and views stay in bounds.
+///
+/// With `nchar_is_decoded`, NChar values are expected to be UTF-8 as [super::layout::Layout]
+/// flags, otherwise UCS-4 chars.
+pub(super) fn check_raw_block(bytes: &[u8], nchar_is_decoded: bool) -> Result<()> {
+    let header_len = size_of::<Header>();
+    if bytes.len() < header_len {
+        return Err(invalid(format!(
+            "raw block of {} bytes is shorter than its header",
+            bytes.len()
+        )));
+    }
+    // SAFETY: the header is packed, any 28 bytes are a valid one.
+    let header = unsafe { &*(bytes.as_ptr() as *const Header) };
+    if header.len() != bytes.len() {
+        return Err(invalid(format!(
+            "raw block length {} in header mismatches the {} bytes",
+            header.len(),
+            bytes.len()
+        )));
+    }
+    let (rows, cols) = (header.nrows(), header.ncols());
+
+    let schema_end = cols
+        .checked_mul(size_of::<ColSchema>())
+        .and_then(|len| len.checked_add(header_len));
+    let lengths_end = cols
+        .checked_mul(size_of::<u32>())
+        .and_then(|len| len.checked_add(schema_end?));
+    let (schema_end, lengths_end) = match (schema_end, lengths_end) {
+        (Some(schema_end), Some(lengths_end)) if lengths_end <= bytes.len() => {
+            (schema_end, lengths_end)
+        }
+        _ => {
+            return Err(invalid(format!(
+                "raw block is too short for {cols} columns"
+            )))
+        }
+    };
+
+    let mut offset = lengths_end;
+    for col in 0..cols {
+        let ty = column_type(schema_at(bytes, header_len, col), col)?;
+        let length = read_u32(bytes, schema_end + col * size_of::<u32>()) as usize;
+        let is_var = ty.is_var_type() || ty.is_json();
+        let data_len = if is_var {
+            rows.checked_mul(size_of::<i32>())
+                .and_then(|len| len.checked_add(length))
+        } else {
+            rows.checked_mul(ty.fixed_length())
+                .and_then(|len| len.checked_add((rows + 7) >> 3))
+        };
+        let end = data_len
+            .and_then(|len| len.checked_add(offset))
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| invalid(format!("data of column {col} is out of the raw block")))?;
+        let data = &bytes[offset..end];
+        if is_var {
+            check_var_column(ty, rows, data, nchar_is_decoded)
+                .map_err(|err| invalid(format!("column {col}: {err}")))?;
+        } else if ty == Ty::Bool {
+            let (nulls, values) = data.split_at((rows + 7) >> 3);
+            let is_null = |row: usize| nulls[row >> 3] >> (7 - (row & 7)) & 1 == 1;
+            if let Some(row) = (0..rows).find(|&row| !is_null(row) && values[row] > 1) {
+                return Err(invalid(format!(
+                    "invalid bool at row {row} of column {col}"
+                )));
+            }
+        }
+        offset = end;
+    }
+    Ok(())
+}
+
+/// Check offsets of a var type column are in its data, and values are valid for the type.
+fn check_var_column(ty: Ty, rows: usize, data: &[u8], nchar_is_decoded: bool) -> Result<()> {
+    let (offsets, data) = data.split_at(rows * size_of::<i32>());
+    // NChar is decoded in place, overlapped values would be decoded twice.
+    let mut last: Option<(usize, usize)> = None;
+    for row in 0..rows {
+        let offset = read_u32(offsets, row * size_of::<i32>()) as i32;
+        if offset < 0 {
+            continue;
+        }
+        let start = offset as usize;
+        let value = data
+            .get(start..start + 2)
+            .map(|len| u16::from_le_bytes([len[0], len[1]]) as usize)
+            .and_then(|len| data.get(start + 2..start + 2 + len))
+            .ok_or_else(|| invalid(format!("value at row {row} is out of data")))?;
+        let end = start + 2 + value.len();
+        let valid = match ty {
+            Ty::NChar if nchar_is_decoded => std::str::from_utf8(value).is_ok(),
+            Ty::NChar => {
+                let sequential = match last {
+                    Some((last_start, last_end)) => start == last_start || start >= last_end,
+                    None => true,
+                };
+                last = Some((start, end));
+                sequential
+                    && value.len() % 4 == 0
+                    && value.chunks_exact(4).all(|c| {
+                        char::from_u32(u32::from_le_bytes(c.try_into().unwrap())).is_some()
+                    })
+            }
+            Ty::VarBinary | Ty::Geometry => true,
+            _ => std::str::from_utf8(value).is_ok(),
+        };
+        if !valid {
+            return Err(invalid(format!("invalid value at row {row}")));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! Vectored writes of raw blocks, so large blocks are written from the bytes they are kept in
+//! instead of being assembled into another buffer.
+
+use std::io::{Error, ErrorKind, IoSlice, Result, Write};
+
+use bytes::{Buf, Bytes};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::{views, RawBlock};
+
+/// Blocks smaller than this are copied into one buffer and written at once, which is cheaper
+/// than a vectored write of their parts.
+const VECTORED_MIN_LEN: usize = 64 * 1024;
+
+/// Slices passed to one vectored write, under `IOV_MAX` of common platforms.
+const MAX_SLICES: usize = 1024;
+
+impl RawBlock {
+    /// Parts of [RawBlock::as_raw_bytes] in order, shared without copying.
+    ///
+    /// The block bytes are one part, unless the schema changed, eg. of v2 blocks, where the
+    /// parts are built from the columns.
+    fn raw_parts(&self) -> Vec<Bytes> {
+        if self.layout.borrow().schema_changed() {
+            views::views_to_raw_parts(self.column_views())
+        } else {
+            vec![unsafe { &*self.data.as_ptr() }.clone()]
+        }
+    }
+
+    /// Length of [RawBlock::as_raw_bytes] without assembling the bytes, eg. to size a buffer
+    /// for [RawBlock::write_vectored].
+    pub fn raw_len(&self) -> usize {
+        if self.layout.borrow().schema_changed() {
+            let ncols = self.ncols();
+            std::mem::size_of::<super::Header>()
+                + ncols * (std::mem::size_of::<views::ColSchema>() + std::mem::size_of::<u32>())
+                + self
+                    .column_views()
+                    .iter()
+                    .map(|view| view.raw_len())
+                    .sum::<usize>()
+        } else {
+            unsafe { &*self.data.as_ptr() }.len()
+        }
+    }
+
+    /// Write the same bytes as [RawBlock::as_raw_bytes] into `wtr` with vectored writes,
+    /// without assembling the block into a buffer first. Returns the number of bytes written.
+    ///
+    /// Blocks under 64KiB are copied into one buffer and written at once. Writers without
+    /// vectored I/O write each part in turn, wrap them in a [std::io::BufWriter] to coalesce the
+    /// small ones.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// let views = vec![ColumnView::from_ints(vec![1, 2, 3])];
+    /// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    ///
+    /// let mut bytes = Vec::new();
+    /// block.write_vectored(&mut bytes)?;
+    /// assert_eq!(bytes, block.as_raw_bytes());
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn write_vectored<W: Write>(&self, mut wtr: W) -> Result<usize> {
+        let mut parts = self.raw_parts();
+        let len = parts.iter().map(Bytes::len).sum();
+        if len < VECTORED_MIN_LEN {
+            wtr.write_all(self.as_raw_bytes())?;
+            return Ok(len);
+        }
+        let mut parts = &mut parts[..];
+        while !parts.is_empty() {
+            match wtr.write_vectored(&io_slices(parts)) {
+                Ok(0) => return Err(write_zero()),
+                Ok(n) => advance(&mut parts, n),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(len)
+    }
+
+    /// Async version of [RawBlock::write_vectored].
+    ///
+    /// Writers without [vectored I/O](AsyncWrite::is_write_vectored) write each part in turn.
+    pub async fn write_vectored_async<W: AsyncWrite + Unpin>(&self, mut wtr: W) -> Result<usize> {
+        let mut parts = self.raw_parts();
+        let len = parts.iter().map(Bytes::len).sum();
+        if len < VECTORED_MIN_LEN {
+            wtr.write_all(self.as_raw_bytes()).await?;
+            return Ok(len);
+        }
+        if !wtr.is_write_vectored() {
+            for part in &parts {
+                wtr.write_all(part).await?;
+            }
+            return Ok(len);
+        }
+        let mut parts = &mut parts[..];
+        while !parts.is_empty() {
+            match wtr.write_vectored(&io_slices(parts)).await? {
+                0 => return Err(write_zero()),
+                n => advance(&mut parts, n),
+            }
+        }
+        Ok(len)
+    }
+}
+
+fn io_slices(parts: &[Bytes]) -> Vec<IoSlice<'_>> {
+    parts
+        .iter()
+        .take(MAX_SLICES)
+        .map(|part| IoSlice::new(part))
+        .collect()
+}
+
+/// Skip `n` written bytes of `parts`, parts written in whole are dropped.
+fn advance(parts: &mut &mut [Bytes], mut n: usize) {
+    while n > 0 {
+        let first = &mut parts[0];
+        if n < first.len() {
+            first.advance(n);
+            return;
+        }
+        n -= first.len();
+        *parts = &mut std::mem::take(parts)[1..];
+    }
+}
+
+fn write_zero() -> Error {
+    Error::new(ErrorKind::WriteZero, "failed to write whole raw block")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{ColumnView, Field, Precision, Ty};
+
+    use super::*;
+
+    /// A block over 64KiB of fixed and var type columns.
+    fn block() -> RawBlock {
+        const ROWS: usize = 10_000;
+        let views = vec![
+            ColumnView::from_millis_timestamp((0..ROWS as i64).collect()),
+            ColumnView::from_ints(
+                (0..ROWS as i32)
+                    .map(|i| (i % 3 != 0).then_some(i))
+                    .collect(),
+            ),
+            ColumnView::from_varchar::<String, _, _, _>(
+                (0..ROWS).map(|i| (i % 5 != 0).then(|| format!("v{i}"))),
+            ),
+            ColumnView::from_nchar::<String, _, _, _>((0..ROWS).map(|i| Some(format!("涛思{i}")))),
+        ];
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond)
+    }
+
+    /// A v2 block, of which the schema changed and parts are built from the columns.
+    fn v2_block() -> RawBlock {
+        const ROWS: usize = 10_000;
+        let mut bytes = Vec::new();
+        bytes.extend((0..ROWS as i64).flat_map(i64::to_le_bytes));
+        bytes.extend((0..ROWS as i32).flat_map(i32::to_le_bytes));
+        for i in 0..ROWS {
+            let s = format!("{:08}", i);
+            bytes.extend((s.len() as u16).to_le_bytes());
+            bytes.extend(s.as_bytes());
+            bytes.extend([0; 2]);
+        }
+        RawBlock::parse_from_raw_block_v2(
+            bytes,
+            &[
+                Field::new("ts", Ty::Timestamp, 8),
+                Field::new("v", Ty::Int, 4),
+                Field::new("s", Ty::VarChar, 10),
+            ],
+            &[8, 4, 12],
+            ROWS,
+            Precision::Millisecond,
+        )
+    }
+
+    /// A writer taking at most 7 bytes of a few slices at a time, to test partial writes.
+    struct Trickle(Vec<u8>);
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let n = buf.len().min(7);
+            self.0.extend(&buf[..n]);
+            Ok(n)
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+            let mut n = 0;
+            for buf in bufs.iter().take(3) {
+                let len = buf.len().min(7 - n);
+                self.0.extend(&buf[..len]);
+                n += len;
+                if n == 7 {
+                    break;
+                }
+            }
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_vectored_as_raw_bytes() {
+        for make in [block, v2_block] {
+            let expected = make().as_raw_bytes().to_vec();
+            assert!(expected.len() >= VECTORED_MIN_LEN);
+
+            let block = make();
+            assert_eq!(block.raw_len(), expected.len());
+            let mut bytes = Vec::new();
+            assert_eq!(block.write_vectored(&mut bytes).unwrap(), expected.len());
+            assert_eq!(bytes, expected);
+
+            let block = make();
+            let mut trickle = Trickle(Vec::new());
+            block.write_vectored(&mut trickle).unwrap();
+            assert_eq!(trickle.0, expected);
+        }
+
+        // blocks of the server are written as they are kept, v2 ones from the columns.
+        let block = block();
+        let parts = block.raw_parts();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].as_ptr(), block.as_raw_bytes().as_ptr());
+        assert!(v2_block().raw_parts().len() > 1);
+    }
+
+    #[test]
+    fn write_vectored_small_block() {
+        let views = vec![ColumnView::from_ints(vec![1, 2, 3])];
+        let block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        let mut bytes = Vec::new();
+        block.write_vectored(&mut bytes).unwrap();
+        assert_eq!(bytes, block.as_raw_bytes());
+    }
+
+    #[tokio::test]
+    async fn write_vectored_async_as_raw_bytes() {
+        for make in [block, v2_block] {
+            let expected = make().as_raw_bytes().to_vec();
+
+            let block = make();
+            let mut bytes = Vec::new();
+            let len = block.write_vectored_async(&mut bytes).await.unwrap();
+            assert_eq!(len, expected.len());
+            assert_eq!(bytes, expected);
+
+            let block = make();
+            let (mut client, mut server) = tokio::io::duplex(4096);
+            let read = tokio::spawn(async move {
+                let mut bytes = Vec::new();
+                tokio::io::AsyncReadExt::read_to_end(&mut server, &mut bytes)
+                    .await
+                    .map(|_| bytes)
+            });
+            block.write_vectored_async(&mut client).await.unwrap();
+            drop(client);
+            assert_eq!(read.await.unwrap().unwrap(), expected);
+        }
+    }
+}
@@ -2,7 +2,9 @@ use std::ffi::c_void;
 
 use crate::common::{BorrowedValue, Ty};
 
-use super::{IsColumnView, NullBits, NullsIter};
+use super::{
+    bytes_from_vec, try_convert, ConvertError, IsColumnView, LePrimitive, NullBits, NullsIter,
+};
 
 use bytes::Bytes;
 
@@ -23,6 +25,16 @@ impl IsColumnView for View {
     fn from_borrowed_value_iter<'b>(iter: impl Iterator<Item = BorrowedValue<'b>>) -> Self {
         Self::from_iter(iter.map(|v| v.to_u64()))
     }
+
+    fn try_from_borrowed_value_iter<'b>(
+        iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError> {
+        Ok(Self::from_iter(try_convert(
+            iter,
+            Ty::UBigInt,
+            BorrowedValue::try_to_int,
+        )?))
+    }
 }
 
 impl std::ops::Add for View {
@@ -57,17 +69,30 @@ impl std::ops::Add<&View> for View {
 }
 
 impl UBigIntView {
+    /// Offset and bytes of the value at `row` in the data, the slot of a NULL value included.
+    pub(crate) fn raw_bytes_at(&self, row: usize) -> Option<(usize, &[u8])> {
+        let offset = row * ITEM_SIZE;
+        let bytes = self.data.get(offset..offset + ITEM_SIZE)?;
+        Some((offset, bytes))
+    }
+
     /// Rows
     pub fn len(&self) -> usize {
         self.data.len() / std::mem::size_of::<Item>()
     }
 
-    /// Raw slice of target type.
-    pub fn as_raw_slice(&self) -> &[Item] {
-        unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const Item, self.len()) }
+    /// Raw slice of target type, `None` if the data is not aligned for it, eg. a block sliced at
+    /// an odd offset of a received frame, or on big-endian hosts as values are little-endian.
+    /// Values are still readable by [Self::get] then.
+    pub fn as_raw_slice(&self) -> Option<&[Item]> {
+        let ptr = self.data.as_ptr() as *const Item;
+        if cfg!(target_endian = "big") || ptr.align_offset(std::mem::align_of::<Item>()) != 0 {
+            return None;
+        }
+        Some(unsafe { std::slice::from_raw_parts(ptr, self.len()) })
     }
 
-    /// Raw pointer of the slice.
+    /// Raw pointer of the slice, values are little-endian as in raw blocks.
     pub fn as_raw_ptr(&self) -> *const Item {
         self.data.as_ptr() as *const Item
     }
@@ -119,7 +144,7 @@ impl UBigIntView {
         if self.nulls.is_null_unchecked(row) {
             None
         } else {
-            Some(*self.get_raw_at(row))
+            Some(self.get_raw_at(row).read_unaligned().decode_le())
         }
     }
 
@@ -229,20 +254,16 @@ impl<'a> ExactSizeIterator for UBigIntViewIter<'a> {
 
 impl<A: Into<Option<Item>>> FromIterator<A> for UBigIntView {
     fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
-        let (nulls, mut values): (Vec<bool>, Vec<_>) = iter
+        let (nulls, values): (Vec<bool>, Vec<_>) = iter
             .into_iter()
             .map(|v| match v.into() {
-                Some(v) => (false, v),
+                Some(v) => (false, v.encode_le()),
                 None => (true, Item::default()),
             })
             .unzip();
         Self {
             nulls: NullBits::from_iter(nulls),
-            data: Bytes::from({
-                let (ptr, len, cap) = (values.as_mut_ptr(), values.len(), values.capacity());
-                std::mem::forget(values);
-                unsafe { Vec::from_raw_parts(ptr as *mut u8, len * ITEM_SIZE, cap * ITEM_SIZE) }
-            }),
+            data: bytes_from_vec(values),
         }
     }
 }
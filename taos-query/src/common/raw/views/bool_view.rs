@@ -2,7 +2,7 @@ use std::{ffi::c_void, io::Write, ops::Range};
 
 use crate::common::{BorrowedValue, Ty};
 
-use super::{IsColumnView, NullBits, NullsIter};
+use super::{try_convert, ConvertError, IsColumnView, NullBits, NullsIter};
 
 use bytes::Bytes;
 
@@ -19,6 +19,16 @@ impl IsColumnView for BoolView {
     fn from_borrowed_value_iter<'b>(iter: impl Iterator<Item = BorrowedValue<'b>>) -> Self {
         Self::from_iter(iter.map(|v| v.to_bool()))
     }
+
+    fn try_from_borrowed_value_iter<'b>(
+        iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError> {
+        Ok(Self::from_iter(try_convert(
+            iter,
+            Ty::Bool,
+            BorrowedValue::try_to_bool,
+        )?))
+    }
 }
 
 impl std::ops::Add for BoolView {
@@ -53,6 +63,13 @@ impl std::ops::Add<&BoolView> for BoolView {
 }
 
 impl BoolView {
+    /// Offset and bytes of the value at `row` in the data, the slot of a NULL value included.
+    pub(crate) fn raw_bytes_at(&self, row: usize) -> Option<(usize, &[u8])> {
+        let offset = row * std::mem::size_of::<bool>();
+        let bytes = self.data.get(offset..offset + std::mem::size_of::<bool>())?;
+        Some((offset, bytes))
+    }
+
     /// Rows
     pub fn len(&self) -> usize {
         self.data.len()
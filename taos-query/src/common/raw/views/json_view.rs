@@ -1,6 +1,6 @@
 use std::{ffi::c_void, fmt::Debug};
 
-use super::{Offsets, IsColumnView};
+use super::{bytes_from_vec, try_convert, ConvertError, IsColumnView, Offsets};
 use crate::{
     common::{BorrowedValue, Ty},
     prelude::InlinableWrite,
@@ -23,12 +23,29 @@ impl IsColumnView for View {
         Ty::Json
     }
     fn from_borrowed_value_iter<'b>(iter: impl Iterator<Item = BorrowedValue<'b>>) -> Self {
-        // Self::from_iter(iter.map(|v| v.to_str()))
-        todo!()
+        Self::from_iter::<String, _, _, _>(
+            iter.map(|v| v.to_str().map(|v| v.into_owned()))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn try_from_borrowed_value_iter<'b>(
+        iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError> {
+        let values = try_convert(iter, Ty::Json, |v| {
+            v.try_to_json().map(|v| v.map(|v| v.into_owned()))
+        })?;
+        Ok(Self::from_iter::<String, _, _, _>(values))
     }
 }
 
 impl JsonView {
+    /// Offset and bytes of the value at `row` in the data, with the length prefix, `None` if
+    /// it's NULL.
+    pub(crate) fn raw_bytes_at(&self, row: usize) -> Option<(usize, &[u8])> {
+        super::inline_bytes_at(&self.offsets, &self.data, row)
+    }
+
     pub fn len(&self) -> usize {
         self.offsets.len()
     }
@@ -47,6 +64,15 @@ impl JsonView {
         self.offsets.get_unchecked(row) < 0
     }
 
+    /// Json text at `row`, `None` if it's NULL or out of range.
+    pub fn get(&self, row: usize) -> Option<&InlineJson> {
+        if row < self.len() {
+            unsafe { self.get_unchecked(row) }
+        } else {
+            None
+        }
+    }
+
     pub unsafe fn get_unchecked(&self, row: usize) -> Option<&InlineJson> {
         let offset = self.offsets.get_unchecked(row);
         if offset >= 0 {
@@ -109,7 +135,7 @@ impl JsonView {
         let mut bytes: Vec<u8> = Vec::new();
         for v in self.iter() {
             if let Some(v) = v {
-                offsets.push(bytes.len() as i32);
+                offsets.push((bytes.len() as i32).to_le());
                 bytes.write_inlined_str::<2>(v.as_str()).unwrap();
             } else {
                 offsets.push(-1);
@@ -146,23 +172,14 @@ impl JsonView {
         for i in iter.map(|v| v.into()) {
             if let Some(s) = i {
                 let s: &str = s.as_ref();
-                offsets.push(data.len() as i32);
+                offsets.push((data.len() as i32).to_le());
                 data.write_inlined_str::<2>(&s).unwrap();
             } else {
                 offsets.push(-1);
             }
         }
-        // dbg!(&offsets);
-        let offsets_bytes = unsafe {
-            Vec::from_raw_parts(
-                offsets.as_mut_ptr() as *mut u8,
-                offsets.len() * 4,
-                offsets.capacity() * 4,
-            )
-        };
-        std::mem::forget(offsets);
         Self {
-            offsets: Offsets(offsets_bytes.into()),
+            offsets: Offsets(bytes_from_vec(offsets)),
             data: data.into(),
         }
     }
@@ -29,14 +29,15 @@ impl Lengths {
         }
     }
 
+    /// Get length at `index`, lengths are little-endian in bytes.
     pub unsafe fn get_unchecked(&self, index: usize) -> u32 {
-        unsafe {
+        u32::from_le(unsafe {
             std::ptr::read_unaligned(
                 self.0
                     .as_ptr()
                     .offset((index * std::mem::size_of::<u32>()) as isize) as _,
             )
-        }
+        })
     }
 
     // /// As a [u8] slice.
@@ -87,11 +88,16 @@ impl LengthsMut {
     fn as_slice_mut(&mut self) -> &mut [u32] {
         unsafe {
             std::slice::from_raw_parts_mut(
-                self.0.as_ptr() as *mut u32,
+                self.0.as_mut_ptr() as *mut u32,
                 self.0.len() / std::mem::size_of::<u32>(),
             )
         }
     }
+    /// Set length at `index` in little-endian.
+    pub fn set(&mut self, index: usize, length: u32) {
+        self.as_slice_mut()[index] = length.to_le();
+    }
+
     pub fn into_lengths(self) -> Lengths {
         Lengths::from(self.0)
     }
@@ -46,7 +46,17 @@ pub use n_char_view::NCharView;
 mod json_view;
 pub use json_view::JsonView;
 
+mod var_binary_view;
+pub use var_binary_view::VarBinaryView;
+
+mod geometry_view;
+pub use geometry_view::GeometryView;
+
+mod null_view;
+pub use null_view::NullView;
+
 mod schema;
+pub use schema::ColSchema;
 pub(crate) use schema::*;
 
 mod nulls;
@@ -60,10 +70,19 @@ pub(crate) use lengths::*;
 
 mod from;
 
-use crate::common::{BorrowedValue, Ty, Value};
+use crate::common::{BorrowedValue, Precision, PrecisionError, Timestamp, Ty, Value};
 
 use std::{ffi::c_void, fmt::Debug, io::Write, iter::FusedIterator};
 
+/// A value that can't be converted to the type of a view without loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("cannot convert {from} value at row {row} to {to}")]
+pub struct ConvertError {
+    pub row: usize,
+    pub from: Ty,
+    pub to: Ty,
+}
+
 pub(crate) trait IsColumnView: Sized {
     /// View item data type.
     fn ty(&self) -> Ty;
@@ -72,7 +91,138 @@ pub(crate) trait IsColumnView: Sized {
         Self::from_borrowed_value_iter::<'a>(iter.map(|v| v.to_borrowed_value()))
     }
 
+    /// Build a view from values of any type, converting them by lossy rules:
+    ///
+    /// - Nulls are nulls.
+    /// - Integer views cast numbers by `as`, which wraps out of range integers and truncates and
+    ///   saturates floats. Bools are 1 or 0 and timestamps are raw integers. Strings are parsed
+    ///   and are null if not a number, json is null if not a number.
+    /// - Float views cast numbers and timestamps by `as`, otherwise as integer views.
+    /// - Bool views are true for signed numbers above zero, so negative numbers are false, and
+    ///   for unsigned numbers other than zero. Empty strings are null, `false`, `f` and their
+    ///   capitalized forms are false, other strings, timestamps and json are true.
+    /// - VarChar and NChar views use the display form, timestamps in the local timezone.
+    /// - Timestamp views panic on any value other than timestamps.
+    /// - VarBinary views take binary values, and strings and json as their bytes. Geometry views
+    ///   take binary values only. Both panic on other values.
+    /// - Json views, and binary or decimal values of other views are not supported and panic.
+    ///
+    /// Use [IsColumnView::try_from_borrowed_value_iter] to fail instead.
     fn from_borrowed_value_iter<'b>(iter: impl Iterator<Item = BorrowedValue<'b>>) -> Self;
+
+    fn try_from_value_iter<'a>(
+        iter: impl Iterator<Item = &'a Value>,
+    ) -> Result<Self, ConvertError> {
+        Self::try_from_borrowed_value_iter::<'a>(iter.map(|v| v.to_borrowed_value()))
+    }
+
+    /// Build a view from values without loss, the first value that can't be converted is a
+    /// [ConvertError]:
+    ///
+    /// - Integer views take integers in range, bools as 1 or 0, raw timestamps, floats without
+    ///   fraction and strings of integers.
+    /// - Float views take floats, integers exactly representable, bools and strings of numbers.
+    ///   Doubles are rounded to float views but must be in range.
+    /// - Bool views take bools, integers 1 or 0 and strings `true` or `false` in any case.
+    /// - VarChar and NChar views take strings, json, and bools and numbers in display form.
+    /// - Timestamp views take timestamps only.
+    /// - Json views take json and strings of valid json.
+    /// - VarBinary views take binary values, and strings and json as their bytes. Geometry views
+    ///   take binary values, which are not checked to be WKB.
+    fn try_from_borrowed_value_iter<'b>(
+        iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError>;
+}
+
+/// Values of primitive views, which are little-endian in raw blocks whatever the host is.
+pub(crate) trait LePrimitive: Copy {
+    /// The value of little-endian bits `self`.
+    fn decode_le(self) -> Self;
+    /// Bits of the value in little-endian.
+    fn encode_le(self) -> Self;
+}
+
+macro_rules! impl_le_primitive {
+    ($($ty:ty),*) => {
+        $(impl LePrimitive for $ty {
+            #[inline(always)]
+            fn decode_le(self) -> Self {
+                <$ty>::from_le(self)
+            }
+            #[inline(always)]
+            fn encode_le(self) -> Self {
+                self.to_le()
+            }
+        })*
+    };
+    ($($ty:ty: $bits:ty),*) => {
+        $(impl LePrimitive for $ty {
+            #[inline(always)]
+            fn decode_le(self) -> Self {
+                <$ty>::from_bits(<$bits>::from_le(self.to_bits()))
+            }
+            #[inline(always)]
+            fn encode_le(self) -> Self {
+                <$ty>::from_bits(self.to_bits().to_le())
+            }
+        })*
+    };
+}
+
+impl_le_primitive!(i8, i16, i32, i64, u8, u16, u32, u64);
+impl_le_primitive!(f32: u32, f64: u64);
+
+/// Bytes of fixed-size `values` without copying them.
+///
+/// The vec is kept as the owner of the bytes, so it's deallocated with the layout it was
+/// allocated with, which is not the one of a `Vec<u8>` for types aligned to more than a byte.
+pub(crate) fn bytes_from_vec<T: Copy + Send + 'static>(values: Vec<T>) -> bytes::Bytes {
+    struct Owner<T>(Vec<T>);
+
+    impl<T: Copy> AsRef<[u8]> for Owner<T> {
+        fn as_ref(&self) -> &[u8] {
+            // SAFETY: views are of primitive types without padding, any of them are valid bytes.
+            unsafe {
+                std::slice::from_raw_parts(
+                    self.0.as_ptr() as *const u8,
+                    std::mem::size_of_val(self.0.as_slice()),
+                )
+            }
+        }
+    }
+
+    bytes::Bytes::from_owner(Owner(values))
+}
+
+/// Offset and bytes of the inline value at `row` of a variable type view, with the `u16` length
+/// prefix, `None` if it's NULL.
+pub(crate) fn inline_bytes_at<'a>(
+    offsets: &Offsets,
+    data: &'a [u8],
+    row: usize,
+) -> Option<(usize, &'a [u8])> {
+    let offset = usize::try_from(*offsets.get(row)?).ok()?;
+    let prefix = data.get(offset..offset + std::mem::size_of::<u16>())?;
+    let len = u16::from_le_bytes([prefix[0], prefix[1]]) as usize;
+    let bytes = data.get(offset..offset + prefix.len() + len)?;
+    Some((offset, bytes))
+}
+
+/// Convert values by `f` for a view of type `to`.
+pub(crate) fn try_convert<'b, T>(
+    iter: impl Iterator<Item = BorrowedValue<'b>>,
+    to: Ty,
+    f: impl Fn(&BorrowedValue<'b>) -> Result<Option<T>, ()>,
+) -> Result<Vec<Option<T>>, ConvertError> {
+    iter.enumerate()
+        .map(|(row, v)| {
+            f(&v).map_err(|_| ConvertError {
+                row,
+                from: v.ty(),
+                to,
+            })
+        })
+        .collect()
 }
 
 /// Compatible version for var char.
@@ -99,12 +249,19 @@ pub enum ColumnView {
     UInt(UIntView),           // 13
     UBigInt(UBigIntView),     // 14
     Json(JsonView),           // 15
+    VarBinary(VarBinaryView), // 16
+    Geometry(GeometryView),   // 20
+    /// All NULLs of a type without data, see [ColumnView::null].
+    Null(NullView),
 }
 unsafe impl Send for ColumnView {}
 unsafe impl Sync for ColumnView {}
 
 impl Debug for ColumnView {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.len() > super::DEBUG_HEAD_ROWS + super::DEBUG_TAIL_ROWS {
+            return super::debug::fmt_column_summary(&format!("{:?}", self.as_ty()), self, f);
+        }
         match self {
             Self::Bool(view) => f.debug_tuple("Bool").field(&view.to_vec()).finish(),
             Self::TinyInt(view) => f.debug_tuple("TinyInt").field(&view.to_vec()).finish(),
@@ -121,6 +278,9 @@ impl Debug for ColumnView {
             Self::UInt(view) => f.debug_tuple("UInt").field(&view.to_vec()).finish(),
             Self::UBigInt(view) => f.debug_tuple("UBigInt").field(&view.to_vec()).finish(),
             Self::Json(view) => f.debug_tuple("Json").field(&view.to_vec()).finish(),
+            Self::VarBinary(view) => f.debug_tuple("VarBinary").field(&view.to_vec()).finish(),
+            Self::Geometry(view) => f.debug_tuple("Geometry").field(&view.to_vec()).finish(),
+            Self::Null(view) => f.debug_tuple("Null").field(&view.to_vec()).finish(),
         }
     }
 }
@@ -151,21 +311,23 @@ impl<'c> Iterator for ColumnViewIter<'c> {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.row, Some(self.view.len() - self.row))
+        let len = self.view.len().saturating_sub(self.row);
+        (len, Some(len))
     }
 
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        let row = self.row + n;
+        let row = self.row.saturating_add(n);
         if row >= self.view.len() {
+            self.row = self.view.len();
             return None;
         }
-        self.row += n;
+        self.row = row + 1;
         Some(unsafe { self.view.get_ref_unchecked(row) })
     }
 
     #[inline]
     fn count(self) -> usize {
-        self.view.len()
+        self.view.len().saturating_sub(self.row)
     }
 }
 
@@ -191,6 +353,26 @@ impl ColumnView {
     pub fn from_millis_timestamp(values: Vec<impl Into<Option<i64>>>) -> Self {
         ColumnView::Timestamp(TimestampView::from_millis(values))
     }
+    pub fn from_micros_timestamp(values: Vec<impl Into<Option<i64>>>) -> Self {
+        ColumnView::Timestamp(TimestampView::from_micros(values))
+    }
+    pub fn from_nanos_timestamp(values: Vec<impl Into<Option<i64>>>) -> Self {
+        ColumnView::Timestamp(TimestampView::from_nanos(values))
+    }
+    /// Timestamps of `precision` from datetimes, fails if any is out of range of the precision.
+    pub fn from_datetimes<Tz: chrono::TimeZone>(
+        values: Vec<impl Into<Option<chrono::DateTime<Tz>>>>,
+        precision: Precision,
+    ) -> Result<Self, PrecisionError> {
+        TimestampView::from_datetimes(values, precision).map(ColumnView::Timestamp)
+    }
+    /// Timestamps of mixed precisions normalized to `precision`, fails if any overflows in it.
+    pub fn try_from_timestamps(
+        values: Vec<impl Into<Option<Timestamp>>>,
+        precision: Precision,
+    ) -> Result<Self, PrecisionError> {
+        TimestampView::try_from_timestamps(values, precision).map(ColumnView::Timestamp)
+    }
     pub fn from_bools(values: Vec<impl Into<Option<bool>>>) -> Self {
         ColumnView::Bool(BoolView::from_iter(values))
     }
@@ -234,6 +416,18 @@ impl ColumnView {
     ) -> Self {
         ColumnView::VarChar(VarCharView::from_iter(iter))
     }
+    /// VarChar column of raw bytes, which are not validated as UTF-8, eg. protobuf payloads
+    /// stored in `BINARY` columns.
+    pub fn from_varchar_bytes<
+        S: AsRef<[u8]>,
+        T: Into<Option<S>>,
+        I: ExactSizeIterator<Item = T>,
+        V: IntoIterator<Item = T, IntoIter = I>,
+    >(
+        iter: V,
+    ) -> Self {
+        ColumnView::VarChar(VarCharView::from_bytes_iter(iter))
+    }
     pub fn from_nchar<
         S: AsRef<str>,
         T: Into<Option<S>>,
@@ -244,6 +438,123 @@ impl ColumnView {
     ) -> Self {
         ColumnView::NChar(NCharView::from_iter(iter))
     }
+    /// Json column of json texts, eg. for json tags, `None` for NULL. Texts are not validated.
+    ///
+    /// ```rust
+    /// # use taos_query::common::ColumnView;
+    /// let view = ColumnView::from_json::<&str, _, _, _>(vec![Some(r#"{"k":"v"}"#), None]);
+    /// assert!(matches!(&view, ColumnView::Json(json) if json.len() == 2));
+    /// ```
+    pub fn from_json<
+        S: AsRef<str>,
+        T: Into<Option<S>>,
+        I: ExactSizeIterator<Item = T>,
+        V: IntoIterator<Item = T, IntoIter = I>,
+    >(
+        iter: V,
+    ) -> Self {
+        ColumnView::Json(JsonView::from_iter(iter))
+    }
+    /// Varbinary column of bytes, `None` for NULL.
+    ///
+    /// ```rust
+    /// # use taos_query::common::ColumnView;
+    /// let view = ColumnView::from_bytes::<&[u8], _, _, _>(vec![Some(&b"\x00\xff"[..]), Some(b""), None]);
+    /// assert!(matches!(&view, ColumnView::VarBinary(bytes) if bytes.get(1) == Some(b"")));
+    /// ```
+    pub fn from_bytes<
+        S: AsRef<[u8]>,
+        T: Into<Option<S>>,
+        I: ExactSizeIterator<Item = T>,
+        V: IntoIterator<Item = T, IntoIter = I>,
+    >(
+        iter: V,
+    ) -> Self {
+        ColumnView::VarBinary(VarBinaryView::from_iter(iter))
+    }
+    /// Geometry column of WKB bytes, `None` for NULL. Bytes are not validated, the server rejects
+    /// invalid ones.
+    pub fn from_geobytes<
+        S: AsRef<[u8]>,
+        T: Into<Option<S>>,
+        I: ExactSizeIterator<Item = T>,
+        V: IntoIterator<Item = T, IntoIter = I>,
+    >(
+        iter: V,
+    ) -> Self {
+        ColumnView::Geometry(GeometryView::from_iter(iter))
+    }
+
+    /// Build a view of type `ty` from values without loss, see
+    /// [IsColumnView::try_from_borrowed_value_iter] for the conversions.
+    ///
+    /// Types without views, like [Ty::Decimal], fail at the first row.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{ColumnView, Ty, Value};
+    /// let values = [Value::Int(1), Value::VarChar("2".to_string()), Value::Double(0.5)];
+    /// let err = ColumnView::try_from_values(Ty::BigInt, &values).unwrap_err();
+    /// assert_eq!((err.row, err.from, err.to), (2, Ty::Double, Ty::BigInt));
+    /// ```
+    pub fn try_from_values<'a>(
+        ty: Ty,
+        values: impl IntoIterator<Item = &'a Value>,
+    ) -> Result<Self, ConvertError> {
+        Self::try_from_borrowed_values(ty, values.into_iter().map(Value::to_borrowed_value))
+    }
+
+    /// Convert the view to type `ty` without loss, like [ColumnView::try_from_values].
+    ///
+    /// ```rust
+    /// # use taos_query::common::{ColumnView, Ty, Value};
+    /// let view = ColumnView::from_ints(vec![Some(1), None]).cast(Ty::BigInt).unwrap();
+    /// assert!(matches!(view, ColumnView::BigInt(_)));
+    /// let values: Vec<_> = view.iter().map(|v| v.to_value()).collect();
+    /// assert_eq!(values, [Value::BigInt(1), Value::Null(Ty::BigInt)]);
+    /// let err = ColumnView::from_ints(vec![1, 300]).cast(Ty::TinyInt).unwrap_err();
+    /// assert_eq!(err.row, 1);
+    /// ```
+    pub fn cast(&self, ty: Ty) -> Result<Self, ConvertError> {
+        Self::try_from_borrowed_values(ty, self.iter())
+    }
+
+    fn try_from_borrowed_values<'b>(
+        ty: Ty,
+        mut iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError> {
+        Ok(match ty {
+            Ty::Bool => ColumnView::Bool(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::TinyInt => ColumnView::TinyInt(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::SmallInt => ColumnView::SmallInt(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::Int => ColumnView::Int(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::BigInt => ColumnView::BigInt(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::UTinyInt => ColumnView::UTinyInt(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::USmallInt => {
+                ColumnView::USmallInt(IsColumnView::try_from_borrowed_value_iter(iter)?)
+            }
+            Ty::UInt => ColumnView::UInt(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::UBigInt => ColumnView::UBigInt(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::Float => ColumnView::Float(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::Double => ColumnView::Double(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::Timestamp => {
+                ColumnView::Timestamp(IsColumnView::try_from_borrowed_value_iter(iter)?)
+            }
+            Ty::VarChar => ColumnView::VarChar(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::NChar => ColumnView::NChar(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::Json => ColumnView::Json(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::VarBinary => {
+                ColumnView::VarBinary(IsColumnView::try_from_borrowed_value_iter(iter)?)
+            }
+            Ty::Geometry => ColumnView::Geometry(IsColumnView::try_from_borrowed_value_iter(iter)?),
+            Ty::Null | Ty::Decimal | Ty::Blob | Ty::MediumBlob => {
+                return Err(ConvertError {
+                    row: 0,
+                    from: iter.next().map_or(Ty::Null, |v| v.ty()),
+                    to: ty,
+                })
+            }
+        })
+    }
 
     #[inline]
     pub fn concat_iter<'b, 'a: 'b>(
@@ -298,7 +609,12 @@ impl ColumnView {
             Ty::Json => ColumnView::Json(IsColumnView::from_borrowed_value_iter(
                 self.iter().chain(rhs),
             )),
-            Ty::VarBinary => todo!(),
+            Ty::VarBinary => ColumnView::VarBinary(IsColumnView::from_borrowed_value_iter(
+                self.iter().chain(rhs),
+            )),
+            Ty::Geometry => ColumnView::Geometry(IsColumnView::from_borrowed_value_iter(
+                self.iter().chain(rhs),
+            )),
             Ty::Decimal => todo!(),
             Ty::Blob => todo!(),
             Ty::MediumBlob => todo!(),
@@ -314,32 +630,53 @@ impl ColumnView {
     /// Concatenate another column view, output a new column view with specified type `ty`.
     #[inline]
     pub fn concat_as(&self, rhs: &ColumnView, ty: Ty) -> ColumnView {
+        if let (ColumnView::Null(lhs), ColumnView::Null(rhs)) = (self, rhs) {
+            if lhs.ty() == ty && rhs.ty() == ty {
+                return ColumnView::null(ty, lhs.len() + rhs.len());
+            }
+        }
         self.concat_iter(rhs.iter(), ty)
     }
 
-    /// Generate single element view for specified type `ty`.
-    pub fn null(n: usize, ty: Ty) -> Self {
-        match ty {
-            Ty::Null => panic!("type should be known"),
-            Ty::Bool => Self::from_bools(vec![None; n]),
-            Ty::TinyInt => Self::from_tiny_ints(vec![None; n]),
-            Ty::SmallInt => Self::from_small_ints(vec![None; n]),
-            Ty::Int => Self::from_ints(vec![None; n]),
-            Ty::BigInt => Self::from_big_ints(vec![None; n]),
-            Ty::UTinyInt => Self::from_unsigned_tiny_ints(vec![None; n]),
-            Ty::USmallInt => Self::from_unsigned_small_ints(vec![None; n]),
-            Ty::UInt => Self::from_unsigned_ints(vec![None; n]),
-            Ty::UBigInt => Self::from_unsigned_big_ints(vec![None; n]),
-            Ty::Float => Self::from_floats(vec![None; n]),
-            Ty::Double => Self::from_doubles(vec![None; n]),
-            Ty::Timestamp => Self::from_millis_timestamp(vec![None; n]),
-            Ty::VarChar => Self::from_varchar::<&'static str, _, _, _>(vec![None; n]),
-            Ty::NChar => Self::from_nchar::<&'static str, _, _, _>(vec![None; n]),
-            Ty::Json => todo!(),
-            Ty::VarBinary => todo!(),
-            Ty::Decimal => todo!(),
-            Ty::Blob => todo!(),
-            Ty::MediumBlob => todo!(),
+    /// A view of `len` NULLs of type `ty` without allocating data, eg. to fill columns missing
+    /// in a block, see [RawBlock::widen_to](super::RawBlock::widen_to).
+    ///
+    /// ```rust
+    /// # use taos_query::common::{ColumnView, Ty, Value};
+    /// let view = ColumnView::null(Ty::Int, 2);
+    /// assert_eq!(view.len(), 2);
+    /// assert_eq!(view.get(1).unwrap().to_value(), Value::Null(Ty::Int));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` is [Ty::Null] or not supported, see [NullView::is_supported].
+    pub fn null(ty: Ty, len: usize) -> Self {
+        ColumnView::Null(NullView::new(ty, len))
+    }
+
+    /// The single row of the view repeated to `len` rows, eg. to broadcast a constant bound
+    /// next to columns of `len` rows, see [broadcast_bind](crate::stmt::broadcast_bind).
+    ///
+    /// A NULL is repeated as a [ColumnView::null] view without allocating data. It's `None`
+    /// if the view doesn't have exactly one row.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{ColumnView, Value};
+    /// let view = ColumnView::from_varchar::<&str, _, _, _>(vec!["batch-1"]);
+    /// let view = view.repeat(2).unwrap();
+    /// assert_eq!(view.len(), 2);
+    /// assert_eq!(view.get(1).unwrap().to_value(), Value::VarChar("batch-1".to_string()));
+    /// assert!(ColumnView::from_ints(vec![1, 2]).repeat(4).is_none());
+    /// ```
+    pub fn repeat(&self, len: usize) -> Option<Self> {
+        if self.len() != 1 {
+            return None;
+        }
+        let ty = self.as_ty();
+        match self.get(0)? {
+            value if value.is_null() => Some(Self::null(ty, len)),
+            value => Self::try_from_borrowed_values(ty, std::iter::repeat(value).take(len)).ok(),
         }
     }
 
@@ -361,6 +698,9 @@ impl ColumnView {
             ColumnView::UInt(view) => view.len(),
             ColumnView::UBigInt(view) => view.len(),
             ColumnView::Json(view) => view.len(),
+            ColumnView::VarBinary(view) => view.len(),
+            ColumnView::Geometry(view) => view.len(),
+            ColumnView::Null(view) => view.len(),
         }
     }
 
@@ -383,6 +723,9 @@ impl ColumnView {
             ColumnView::UInt(view) => view.is_null_unchecked(row),
             ColumnView::UBigInt(view) => view.is_null_unchecked(row),
             ColumnView::Json(view) => view.is_null_unchecked(row),
+            ColumnView::VarBinary(view) => view.is_null_unchecked(row),
+            ColumnView::Geometry(view) => view.is_null_unchecked(row),
+            ColumnView::Null(view) => view.is_null_unchecked(row),
         }
     }
 
@@ -413,6 +756,9 @@ impl ColumnView {
             ColumnView::UInt(view) => view.get_value_unchecked(row),
             ColumnView::UBigInt(view) => view.get_value_unchecked(row),
             ColumnView::Json(view) => view.get_value_unchecked(row),
+            ColumnView::VarBinary(view) => view.get_value_unchecked(row),
+            ColumnView::Geometry(view) => view.get_value_unchecked(row),
+            ColumnView::Null(view) => view.get_value_unchecked(row),
         }
     }
 
@@ -435,6 +781,9 @@ impl ColumnView {
             ColumnView::UInt(view) => view.get_raw_value_unchecked(row),
             ColumnView::UBigInt(view) => view.get_raw_value_unchecked(row),
             ColumnView::Json(view) => view.get_raw_value_unchecked(row),
+            ColumnView::VarBinary(view) => view.get_raw_value_unchecked(row),
+            ColumnView::Geometry(view) => view.get_raw_value_unchecked(row),
+            ColumnView::Null(view) => view.get_raw_value_unchecked(row),
         }
     }
 
@@ -459,6 +808,9 @@ impl ColumnView {
             ColumnView::UInt(view) => view.slice(range).map(ColumnView::UInt),
             ColumnView::UBigInt(view) => view.slice(range).map(ColumnView::UBigInt),
             ColumnView::Json(view) => view.slice(range).map(ColumnView::Json),
+            ColumnView::VarBinary(view) => view.slice(range).map(ColumnView::VarBinary),
+            ColumnView::Geometry(view) => view.slice(range).map(ColumnView::Geometry),
+            ColumnView::Null(view) => view.slice(range).map(ColumnView::Null),
         }
     }
 
@@ -522,6 +874,12 @@ impl ColumnView {
     //     }
     // }
 
+    /// Bytes of the column in raw block layout, eg. to account for the size of binding it.
+    pub fn raw_len(&self) -> usize {
+        self.write_raw_into(&mut std::io::sink())
+            .expect("writing to sink never fails")
+    }
+
     pub(super) fn write_raw_into<W: Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
         match self {
             ColumnView::Bool(view) => view.write_raw_into(wtr),
@@ -539,10 +897,68 @@ impl ColumnView {
             ColumnView::UInt(view) => view.write_raw_into(wtr),
             ColumnView::UBigInt(view) => view.write_raw_into(wtr),
             ColumnView::Json(view) => view.write_raw_into(wtr),
+            ColumnView::VarBinary(view) => view.write_raw_into(wtr),
+            ColumnView::Geometry(view) => view.write_raw_into(wtr),
+            ColumnView::Null(view) => view.write_raw_into(wtr),
         }
     }
 
-    fn as_ty(&self) -> Ty {
+    /// Nulls and data of a fixed-size column shared as they are, var type columns are packed into
+    /// a buffer of their own as [write_raw_into](Self::write_raw_into) does.
+    fn raw_parts(&self) -> [bytes::Bytes; 2] {
+        match self {
+            ColumnView::Bool(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::TinyInt(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::SmallInt(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::Int(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::BigInt(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::Float(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::Double(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::Timestamp(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::UTinyInt(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::USmallInt(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::UInt(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::UBigInt(view) => [view.nulls.0.clone(), view.data.clone()],
+            ColumnView::VarChar(_)
+            | ColumnView::NChar(_)
+            | ColumnView::Json(_)
+            | ColumnView::VarBinary(_)
+            | ColumnView::Geometry(_)
+            | ColumnView::Null(_) => {
+                let mut bytes = Vec::new();
+                self.write_raw_into(&mut bytes)
+                    .expect("writing to vec never fails");
+                [bytes.into(), bytes::Bytes::new()]
+            }
+        }
+    }
+
+    /// Offset and bytes of the value at `row` in the data of the view, see
+    /// [RawBlock::inspect](crate::common::RawBlock::inspect).
+    pub(crate) fn raw_bytes_at(&self, row: usize) -> Option<(usize, &[u8])> {
+        match self {
+            ColumnView::Bool(view) => view.raw_bytes_at(row),
+            ColumnView::TinyInt(view) => view.raw_bytes_at(row),
+            ColumnView::SmallInt(view) => view.raw_bytes_at(row),
+            ColumnView::Int(view) => view.raw_bytes_at(row),
+            ColumnView::BigInt(view) => view.raw_bytes_at(row),
+            ColumnView::Float(view) => view.raw_bytes_at(row),
+            ColumnView::Double(view) => view.raw_bytes_at(row),
+            ColumnView::VarChar(view) => view.raw_bytes_at(row),
+            ColumnView::Timestamp(view) => view.raw_bytes_at(row),
+            ColumnView::NChar(view) => view.raw_bytes_at(row),
+            ColumnView::UTinyInt(view) => view.raw_bytes_at(row),
+            ColumnView::USmallInt(view) => view.raw_bytes_at(row),
+            ColumnView::UInt(view) => view.raw_bytes_at(row),
+            ColumnView::UBigInt(view) => view.raw_bytes_at(row),
+            ColumnView::Json(view) => view.raw_bytes_at(row),
+            ColumnView::VarBinary(view) => view.raw_bytes_at(row),
+            ColumnView::Geometry(view) => view.raw_bytes_at(row),
+            ColumnView::Null(view) => view.raw_bytes_at(row),
+        }
+    }
+
+    pub(crate) fn as_ty(&self) -> Ty {
         match self {
             ColumnView::Bool(_) => Ty::Bool,
             ColumnView::TinyInt(_) => Ty::TinyInt,
@@ -559,6 +975,9 @@ impl ColumnView {
             ColumnView::UInt(_) => Ty::UInt,
             ColumnView::UBigInt(_) => Ty::UBigInt,
             ColumnView::Json(_) => Ty::Json,
+            ColumnView::VarBinary(_) => Ty::VarBinary,
+            ColumnView::Geometry(_) => Ty::Geometry,
+            ColumnView::Null(view) => view.ty(),
         }
     }
 
@@ -577,26 +996,32 @@ impl ColumnView {
 }
 
 pub fn views_to_raw_block(views: &[ColumnView]) -> Vec<u8> {
+    let schemas = views
+        .iter()
+        .map(|view| {
+            let ty = view.as_ty();
+            ColSchema::new(ty, ty.fixed_length() as _)
+        })
+        .collect_vec();
+    views_to_raw_block_with_schemas(views, &schemas)
+}
+
+/// Raw block of `views`, with lengths of columns in `schemas` kept, eg. `varchar(20)`.
+pub(crate) fn views_to_raw_block_with_schemas<V: std::borrow::Borrow<ColumnView>>(
+    views: &[V],
+    schemas: &[ColSchema],
+) -> Vec<u8> {
+    debug_assert_eq!(views.len(), schemas.len());
     let mut header = super::Header::default();
 
-    header.nrows = views.first().map(|v| v.len()).unwrap_or(0) as _;
-    header.ncols = views.len() as _;
+    header.set_nrows(views.first().map(|v| v.borrow().len()).unwrap_or(0));
+    header.set_ncols(views.len());
 
     let ncols = views.len();
 
     let mut bytes = Vec::new();
     bytes.extend(header.as_bytes());
 
-    let schemas = views
-        .iter()
-        .map(|view| {
-            let ty = view.as_ty();
-            ColSchema {
-                ty,
-                len: ty.fixed_length() as _,
-            }
-        })
-        .collect_vec();
     let schema_bytes = unsafe {
         std::slice::from_raw_parts(
             schemas.as_ptr() as *const u8,
@@ -608,9 +1033,8 @@ pub fn views_to_raw_block(views: &[ColumnView]) -> Vec<u8> {
     let length_offset = bytes.len();
     bytes.resize(bytes.len() + ncols * std::mem::size_of::<u32>(), 0);
 
-    let mut lengths = Vec::with_capacity(ncols);
-    lengths.resize(ncols, 0);
-    for (i, view) in views.iter().enumerate() {
+    let mut lengths = vec![0u32; ncols];
+    for (i, view) in views.iter().map(V::borrow).enumerate() {
         let cur = bytes.len();
         let n = view.write_raw_into(&mut bytes).unwrap();
         let len = bytes.len();
@@ -621,21 +1045,62 @@ pub fn views_to_raw_block(views: &[ColumnView]) -> Vec<u8> {
             lengths[i] = (header.nrows() * view.as_ty().fixed_length()) as _;
         }
     }
-    unsafe {
-        (*(bytes.as_mut_ptr() as *mut super::Header)).length = bytes.len() as _;
-        std::ptr::copy(
-            lengths.as_ptr(),
-            bytes.as_mut_ptr().offset(length_offset as isize) as *mut u32,
-            lengths.len(),
-        );
+    let len = bytes.len();
+    unsafe { (*(bytes.as_mut_ptr() as *mut super::Header)).set_len(len) };
+    for (i, length) in lengths.into_iter().enumerate() {
+        let offset = length_offset + i * std::mem::size_of::<u32>();
+        bytes[offset..offset + std::mem::size_of::<u32>()].copy_from_slice(&length.to_le_bytes());
     }
     bytes
 }
 
+/// Parts of the raw block of `views` in order, the same bytes as [views_to_raw_block] for
+/// vectored writes: header, schemas and lengths in a small buffer, then
+/// [parts](ColumnView::raw_parts) of each column.
+pub(crate) fn views_to_raw_parts(views: &[ColumnView]) -> Vec<bytes::Bytes> {
+    let ncols = views.len();
+    let nrows = views.first().map(|v| v.len()).unwrap_or(0);
+    let columns = views.iter().map(ColumnView::raw_parts).collect_vec();
+
+    let schemas_len = ncols * std::mem::size_of::<ColSchema>();
+    let head_len = std::mem::size_of::<super::Header>() + schemas_len + ncols * 4;
+    let len = head_len
+        + columns
+            .iter()
+            .flatten()
+            .map(|part| part.len())
+            .sum::<usize>();
+    let mut header = super::Header::default();
+    header.set_nrows(nrows);
+    header.set_ncols(ncols);
+    header.set_len(len);
+
+    let mut head = Vec::with_capacity(head_len);
+    head.extend(header.as_bytes());
+    for view in views {
+        let ty = view.as_ty();
+        head.extend(ColSchema::new(ty, ty.fixed_length() as _).as_bytes());
+    }
+    for (view, [data, rest]) in views.iter().zip(&columns) {
+        let length = if view.as_ty().is_primitive() {
+            nrows * view.as_ty().fixed_length()
+        } else {
+            data.len() + rest.len() - nrows * 4
+        };
+        head.extend((length as u32).to_le_bytes());
+    }
+    debug_assert_eq!(head.len(), head_len);
+
+    std::iter::once(bytes::Bytes::from(head))
+        .chain(columns.into_iter().flatten())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
 impl From<Value> for ColumnView {
     fn from(value: Value) -> Self {
         match value {
-            Value::Null(ty) => ColumnView::null(1, ty),
+            Value::Null(ty) => ColumnView::null(ty, 1),
             Value::Bool(v) => vec![v].into(),
             Value::TinyInt(v) => vec![v].into(),
             Value::SmallInt(v) => vec![v].into(),
@@ -650,7 +1115,263 @@ impl From<Value> for ColumnView {
             Value::USmallInt(v) => vec![v].into(),
             Value::UInt(v) => vec![v].into(),
             Value::UBigInt(v) => vec![v].into(),
+            Value::VarBinary(v) => ColumnView::from_bytes::<Vec<u8>, _, _, _>(vec![v]),
+            Value::Geometry(v) => ColumnView::from_geobytes::<Vec<u8>, _, _, _>(vec![v]),
             _ => todo!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::common::Timestamp;
+
+    use super::*;
+
+    /// Copy of `data` starting at an odd address, like a block sliced after a framing header.
+    fn misaligned(data: &Bytes) -> Bytes {
+        let mut buf = vec![0u8; data.len() + 1];
+        let pad = (buf.as_ptr() as usize % 2 == 0) as usize;
+        buf[pad..pad + data.len()].copy_from_slice(data);
+        let bytes = Bytes::from(buf).slice(pad..pad + data.len());
+        assert_eq!(bytes.as_ptr() as usize % 2, 1);
+        bytes
+    }
+
+    macro_rules! test_misaligned {
+        ($($view:ident: $values:expr),+ $(,)?) => {
+            $(
+                let values = $values;
+                let view = $view::from_iter(values.clone());
+                assert_eq!(view.as_raw_slice().is_some(), cfg!(target_endian = "little"));
+                let view = $view {
+                    data: misaligned(&view.data),
+                    ..view
+                };
+                assert!(view.as_raw_slice().is_none(), "{}", stringify!($view));
+                assert_eq!(view.to_vec(), values);
+                assert_eq!(view.get(2), values[2]);
+            )+
+        };
+    }
+
+    #[test]
+    fn misaligned_views() {
+        test_misaligned!(
+            SmallIntView: vec![Some(i16::MIN), None, Some(i16::MAX)],
+            USmallIntView: vec![Some(1u16), None, Some(u16::MAX)],
+            IntView: vec![Some(i32::MIN), None, Some(i32::MAX)],
+            UIntView: vec![Some(1u32), None, Some(u32::MAX)],
+            BigIntView: vec![Some(i64::MIN), None, Some(i64::MAX)],
+            UBigIntView: vec![Some(1u64), None, Some(u64::MAX)],
+            FloatView: vec![Some(0.5f32), None, Some(f32::MAX)],
+            DoubleView: vec![Some(0.5f64), None, Some(f64::MAX)],
+        );
+
+        let view = TimestampView::from_millis(vec![Some(1), None, Some(i64::MAX)]);
+        let view = TimestampView {
+            data: misaligned(&view.data),
+            ..view
+        };
+        assert!(view.as_raw_slice().is_none());
+        assert_eq!(view.get(2).flatten().unwrap().as_raw_i64(), i64::MAX);
+        let column = ColumnView::Timestamp(view);
+        assert!(column.get(1).unwrap().is_null());
+    }
+
+    #[test]
+    fn little_endian_views() {
+        let view = IntView::from_iter([Some(0x0102_0304), None]);
+        assert_eq!(view.data[..4], [4, 3, 2, 1]);
+        let view = DoubleView::from_iter([Some(1.5f64)]);
+        assert_eq!(view.data[..], 1.5f64.to_le_bytes());
+        let view = TimestampView::from_millis(vec![Some(0x0102)]);
+        assert_eq!(view.data[..2], [2, 1]);
+
+        let view = IntView {
+            nulls: NullBits::from_iter([false]),
+            data: Bytes::from_static(&[4, 3, 2, 1]),
+        };
+        assert_eq!(view.get(0), Some(0x0102_0304));
+        let view = FloatView {
+            nulls: NullBits::from_iter([false]),
+            data: Bytes::copy_from_slice(&0.5f32.to_le_bytes()),
+        };
+        assert_eq!(view.get(0), Some(0.5));
+    }
+
+    fn values(column: &ColumnView) -> Vec<Value> {
+        column.iter().map(|v| v.to_value()).collect()
+    }
+
+    /// Pin the lossy rules of `from_borrowed_value_iter`.
+    #[test]
+    fn lossy_conversions() {
+        let inputs = [
+            Value::Int(300),
+            Value::Double(-1.9),
+            Value::Bool(true),
+            Value::VarChar("abc".to_string()),
+            Value::NChar("7".to_string()),
+            Value::Timestamp(Timestamp::new(5, Precision::Millisecond)),
+            Value::Null(Ty::Int),
+        ];
+        assert_eq!(
+            TinyIntView::from_value_iter(inputs.iter()).to_vec(),
+            [Some(44), Some(-1), Some(1), None, Some(7), Some(5), None]
+        );
+        assert_eq!(
+            UBigIntView::from_value_iter(inputs.iter()).to_vec(),
+            [Some(300), Some(0), Some(1), None, Some(7), Some(5), None]
+        );
+        assert_eq!(
+            DoubleView::from_value_iter(inputs.iter()).to_vec(),
+            [
+                Some(300.),
+                Some(-1.9),
+                Some(1.),
+                None,
+                Some(7.),
+                Some(5.),
+                None
+            ]
+        );
+        assert_eq!(
+            BoolView::from_value_iter(inputs.iter()).to_vec(),
+            [
+                Some(true),
+                Some(false),
+                Some(true),
+                Some(true),
+                Some(true),
+                Some(true),
+                None
+            ]
+        );
+        let strings = [
+            Value::VarChar("".to_string()),
+            Value::VarChar("F".to_string()),
+            Value::NChar("no".to_string()),
+        ];
+        assert_eq!(
+            BoolView::from_value_iter(strings.iter()).to_vec(),
+            [None, Some(false), Some(true)]
+        );
+        let view = VarCharView::from_value_iter(inputs[..5].iter());
+        assert_eq!(
+            values(&ColumnView::VarChar(view)),
+            ["300", "-1.9", "true", "abc", "7"].map(|s| Value::VarChar(s.to_string()))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported conversion from INT to timestamp")]
+    fn lossy_timestamps_panic() {
+        TimestampView::from_value_iter([Value::Int(1)].iter());
+    }
+
+    #[test]
+    fn fallible_conversions() {
+        let err = |ty: Ty, values: &[Value]| ColumnView::try_from_values(ty, values).unwrap_err();
+        let s = |s: &str| Value::VarChar(s.to_string());
+
+        let column = ColumnView::try_from_values(
+            Ty::TinyInt,
+            &[
+                Value::UBigInt(127),
+                Value::Double(-3.),
+                s(" 12 "),
+                Value::Bool(true),
+                Value::Null(Ty::VarChar),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            values(&column),
+            [
+                Value::TinyInt(127),
+                Value::TinyInt(-3),
+                Value::TinyInt(12),
+                Value::TinyInt(1),
+                Value::Null(Ty::TinyInt)
+            ]
+        );
+        let e = err(Ty::TinyInt, &[Value::Int(1), Value::Int(300)]);
+        assert_eq!(
+            e,
+            ConvertError {
+                row: 1,
+                from: Ty::Int,
+                to: Ty::TinyInt
+            }
+        );
+        assert_eq!(
+            e.to_string(),
+            "cannot convert INT value at row 1 to TINYINT"
+        );
+        assert_eq!(err(Ty::UBigInt, &[Value::BigInt(-1)]).row, 0);
+        assert_eq!(err(Ty::UBigInt, &[s("abc")]).from, Ty::VarChar);
+        assert_eq!(err(Ty::BigInt, &[Value::Double(0.5)]).from, Ty::Double);
+        assert_eq!(err(Ty::BigInt, &[Value::Float(f32::NAN)]).from, Ty::Float);
+
+        let column = ColumnView::try_from_values(
+            Ty::Float,
+            &[Value::Double(0.1), Value::Int(16_777_216), s("1.5")],
+        )
+        .unwrap();
+        assert_eq!(
+            values(&column),
+            [0.1, 16_777_216., 1.5].map(Value::Float).to_vec()
+        );
+        assert_eq!(err(Ty::Float, &[Value::Double(1e40)]).to, Ty::Float);
+        assert_eq!(err(Ty::Float, &[Value::Int(16_777_217)]).from, Ty::Int);
+        assert_eq!(err(Ty::Double, &[Value::BigInt(i64::MAX)]).from, Ty::BigInt);
+        let ts = Value::Timestamp(Timestamp::new(1, Precision::Millisecond));
+        assert_eq!(err(Ty::Double, &[ts.clone()]).from, Ty::Timestamp);
+        assert!(ColumnView::try_from_values(Ty::BigInt, &[ts.clone()]).is_ok());
+
+        let column =
+            ColumnView::try_from_values(Ty::Bool, &[Value::Int(0), s("TRUE"), Value::UTinyInt(1)])
+                .unwrap();
+        assert_eq!(
+            values(&column),
+            [false, true, true].map(Value::Bool).to_vec()
+        );
+        assert_eq!(err(Ty::Bool, &[Value::Int(1), Value::Int(-1)]).row, 1);
+        assert_eq!(err(Ty::Bool, &[s("")]).from, Ty::VarChar);
+
+        let column = ColumnView::try_from_values(
+            Ty::NChar,
+            &[
+                Value::Int(1),
+                s("温度"),
+                Value::Json(serde_json::json!({"a": 1})),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            values(&column),
+            ["1", "温度", r#"{"a":1}"#].map(|s| Value::NChar(s.to_string()))
+        );
+        assert_eq!(err(Ty::VarChar, &[ts.clone()]).from, Ty::Timestamp);
+
+        let column = ColumnView::try_from_values(Ty::Timestamp, &[ts.clone()]).unwrap();
+        assert_eq!(values(&column), [ts]);
+        assert_eq!(err(Ty::Timestamp, &[Value::BigInt(1)]).from, Ty::BigInt);
+
+        let column = ColumnView::try_from_values(Ty::Json, &[s(r#"{"a":1}"#)]).unwrap();
+        assert_eq!(values(&column), [Value::Json(serde_json::json!({"a": 1}))]);
+        assert_eq!(err(Ty::Json, &[s("{")]).from, Ty::VarChar);
+
+        assert_eq!(
+            err(Ty::VarBinary, &[Value::Int(1)]),
+            ConvertError {
+                row: 0,
+                from: Ty::Int,
+                to: Ty::VarBinary
+            }
+        );
+    }
+}
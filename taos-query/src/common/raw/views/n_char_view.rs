@@ -7,7 +7,7 @@ use std::{
     sync::Arc,
 };
 
-use super::{IsColumnView, Offsets, Version};
+use super::{bytes_from_vec, try_convert, ConvertError, IsColumnView, Offsets, Version};
 
 use crate::{
     common::{layout::Layout, BorrowedValue, Ty},
@@ -41,9 +41,24 @@ impl IsColumnView for NCharView {
                 .into_iter(),
         )
     }
+
+    fn try_from_borrowed_value_iter<'b>(
+        iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError> {
+        let values = try_convert(iter, Ty::NChar, |v| {
+            v.try_to_str().map(|v| v.map(|v| v.into_owned()))
+        })?;
+        Ok(Self::from_iter::<String, _, _, _>(values))
+    }
 }
 
 impl NCharView {
+    /// Offset and bytes of the value at `row` in the data, with the length prefix, `None` if
+    /// it's NULL.
+    pub(crate) fn raw_bytes_at(&self, row: usize) -> Option<(usize, &[u8])> {
+        super::inline_bytes_at(&self.offsets, &self.data, row)
+    }
+
     pub fn len(&self) -> usize {
         self.offsets.len()
     }
@@ -143,6 +158,8 @@ impl NCharView {
         if range.len() == 0 {
             return None;
         }
+        // the slice shares the data, which must be decoded before both of them read it as UTF-8.
+        unsafe { self.nchar_to_utf8() };
         let (offsets, range) = unsafe { self.offsets.slice_unchecked(range.clone()) };
         let range = if let Some(range) = range {
             range.0 as usize..range.1.map(|v| v as usize).unwrap_or(self.data.len())
@@ -170,6 +187,11 @@ impl NCharView {
         self.iter().collect()
     }
 
+    /// Dictionary encode the column, returns distinct values and per-row indices of the values.
+    pub fn to_dictionary(&self) -> (Vec<String>, Vec<Option<u32>>) {
+        crate::common::raw::dictionary::dictionary_from_iter(self.iter())
+    }
+
     /// Write column data as raw bytes.
     pub(crate) fn write_raw_into<W: std::io::Write>(&self, mut wtr: W) -> std::io::Result<usize> {
         // if self.layout.borrow().nchar_is_decoded() {
@@ -179,7 +201,7 @@ impl NCharView {
             if let Some(v) = v {
                 // dbg!(v);
                 let chars = v.chars().collect_vec();
-                offsets.push(bytes.len() as i32);
+                offsets.push((bytes.len() as i32).to_le());
                 let chars = unsafe {
                     std::slice::from_raw_parts(
                         chars.as_ptr() as *const u8,
@@ -223,22 +245,14 @@ impl NCharView {
         for i in iter.into_iter().map(|v| v.into()) {
             if let Some(s) = i {
                 let s: &str = s.as_ref();
-                offsets.push(data.len() as i32);
+                offsets.push((data.len() as i32).to_le());
                 data.write_inlined_str::<2>(&s).unwrap();
             } else {
                 offsets.push(-1);
             }
         }
-        let offsets_bytes = unsafe {
-            Vec::from_raw_parts(
-                offsets.as_mut_ptr() as *mut u8,
-                offsets.len() * 4,
-                offsets.capacity() * 4,
-            )
-        };
-        std::mem::forget(offsets);
         NCharView {
-            offsets: Offsets(offsets_bytes.into()),
+            offsets: Offsets(bytes_from_vec(offsets)),
             data: data.into(),
             is_chars: UnsafeCell::new(false),
             version: Version::V2,
@@ -0,0 +1,150 @@
+use std::ffi::c_void;
+
+use super::ColumnView;
+use crate::common::{BorrowedValue, Ty};
+
+/// View of a column of `len` NULLs of type `ty`, eg. for columns added to a table after a
+/// block was written. No data is allocated until it's serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NullView {
+    pub(crate) ty: Ty,
+    pub(crate) len: usize,
+}
+
+impl NullView {
+    /// NULLs of type `ty`, which must be known.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` is [Ty::Null], or a type without column views, see
+    /// [NullView::is_supported].
+    pub fn new(ty: Ty, len: usize) -> Self {
+        assert!(ty != Ty::Null, "type of null view should be known");
+        assert!(Self::is_supported(ty), "{ty} columns are not supported");
+        Self { ty, len }
+    }
+
+    /// If NULLs of `ty` can be viewed, [Ty::Decimal], [Ty::Blob] and [Ty::MediumBlob] columns
+    /// are not supported yet.
+    pub fn is_supported(ty: Ty) -> bool {
+        !matches!(ty, Ty::Null | Ty::Decimal | Ty::Blob | Ty::MediumBlob)
+    }
+
+    pub fn ty(&self) -> Ty {
+        self.ty
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// There's no data of a NULL view, so no bytes of any row.
+    pub(crate) fn raw_bytes_at(&self, _row: usize) -> Option<(usize, &[u8])> {
+        None
+    }
+
+    pub(crate) unsafe fn is_null_unchecked(&self, _row: usize) -> bool {
+        true
+    }
+
+    pub(crate) unsafe fn get_value_unchecked(&self, _row: usize) -> BorrowedValue {
+        BorrowedValue::Null(self.ty)
+    }
+
+    pub(crate) unsafe fn get_raw_value_unchecked(&self, _row: usize) -> (Ty, u32, *const c_void) {
+        let len = if self.ty.is_primitive() {
+            self.ty.fixed_length()
+        } else {
+            0
+        };
+        (self.ty, len as _, std::ptr::null())
+    }
+
+    pub fn slice(&self, mut range: std::ops::Range<usize>) -> Option<Self> {
+        if range.start >= self.len {
+            return None;
+        }
+        if range.end > self.len {
+            range.end = self.len;
+        }
+        if range.is_empty() {
+            return None;
+        }
+        Some(Self {
+            ty: self.ty,
+            len: range.len(),
+        })
+    }
+
+    /// A view of the type with the NULLs allocated, timestamps are in milliseconds.
+    pub fn to_column_view(&self) -> ColumnView {
+        let n = self.len;
+        match self.ty {
+            Ty::Null | Ty::Decimal | Ty::Blob | Ty::MediumBlob => {
+                unreachable!("checked by NullView::new")
+            }
+            Ty::Bool => ColumnView::from_bools(vec![None; n]),
+            Ty::TinyInt => ColumnView::from_tiny_ints(vec![None; n]),
+            Ty::SmallInt => ColumnView::from_small_ints(vec![None; n]),
+            Ty::Int => ColumnView::from_ints(vec![None; n]),
+            Ty::BigInt => ColumnView::from_big_ints(vec![None; n]),
+            Ty::UTinyInt => ColumnView::from_unsigned_tiny_ints(vec![None; n]),
+            Ty::USmallInt => ColumnView::from_unsigned_small_ints(vec![None; n]),
+            Ty::UInt => ColumnView::from_unsigned_ints(vec![None; n]),
+            Ty::UBigInt => ColumnView::from_unsigned_big_ints(vec![None; n]),
+            Ty::Float => ColumnView::from_floats(vec![None; n]),
+            Ty::Double => ColumnView::from_doubles(vec![None; n]),
+            Ty::Timestamp => ColumnView::from_millis_timestamp(vec![None; n]),
+            Ty::VarChar => ColumnView::from_varchar::<&'static str, _, _, _>(vec![None; n]),
+            Ty::NChar => ColumnView::from_nchar::<&'static str, _, _, _>(vec![None; n]),
+            Ty::Json => ColumnView::from_json::<&'static str, _, _, _>(vec![None; n]),
+            Ty::VarBinary => ColumnView::from_bytes::<&'static [u8], _, _, _>(vec![None; n]),
+            Ty::Geometry => ColumnView::from_geobytes::<&'static [u8], _, _, _>(vec![None; n]),
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Option<()>> {
+        vec![None; self.len]
+    }
+
+    /// Write column data as raw bytes, a null bitmap with all bits set and zeroed data for
+    /// fixed-size types, or offsets of `-1` for var types.
+    pub(crate) fn write_raw_into<W: std::io::Write>(&self, mut wtr: W) -> std::io::Result<usize> {
+        if self.ty.is_primitive() {
+            let nulls = vec![0xff; (self.len + 7) / 8];
+            let data = vec![0; self.len * self.ty.fixed_length()];
+            wtr.write_all(&nulls)?;
+            wtr.write_all(&data)?;
+            Ok(nulls.len() + data.len())
+        } else {
+            let offsets = (-1i32).to_le_bytes().repeat(self.len);
+            wtr.write_all(&offsets)?;
+            Ok(offsets.len())
+        }
+    }
+}
+
+#[test]
+fn test_write_raw() {
+    use crate::common::views::{views_to_raw_block, ColumnView};
+    use crate::common::{Precision, RawBlock};
+
+    let views = [
+        ColumnView::from_ints(vec![1, 2, 3]),
+        ColumnView::null(Ty::Double, 3),
+        ColumnView::null(Ty::VarChar, 3),
+    ];
+    let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    let parsed = block.column_views();
+    assert!(matches!(&parsed[1], ColumnView::Double(view) if view.to_vec() == [None; 3]));
+    assert!(matches!(&parsed[2], ColumnView::VarChar(view) if view.iter().all(|v| v.is_none())));
+    for (view, parsed) in views.iter().zip(parsed) {
+        assert_eq!(view.raw_len(), parsed.raw_len());
+    }
+    assert_eq!(views[1].slice(1..5).unwrap().len(), 2);
+    assert!(views[1].slice(3..4).is_none());
+}
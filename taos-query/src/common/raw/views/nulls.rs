@@ -1,4 +1,4 @@
-use std::{alloc::Layout, fmt::Debug, ops::Range};
+use std::{fmt::Debug, ops::Range};
 
 use bytes::Bytes;
 
@@ -29,19 +29,21 @@ impl<T: Into<Bytes>> From<T> for NullBits {
 impl FromIterator<bool> for NullBits {
     fn from_iter<T: IntoIterator<Item = bool>>(iter: T) -> Self {
         let booleans = iter.into_iter().collect::<Vec<_>>();
-        let len = null_bits_len(booleans.len());
-        let mut inner = Vec::with_capacity(len);
-        inner.resize(len, 0);
-        let nulls = NullBits(inner.into());
+        let mut inner = vec![0; null_bits_len(booleans.len())];
         booleans.into_iter().enumerate().for_each(|(i, is_null)| {
             if is_null {
-                unsafe { nulls.set_null_unchecked(i) };
+                set_null(&mut inner, i);
             }
         });
-        nulls
+        NullBits(inner.into())
     }
 }
 
+/// Set the bit of `index` in bytes owned by the caller, the bytes of a [NullBits] are shared.
+fn set_null(bytes: &mut [u8], index: usize) {
+    bytes[index >> 3] |= 1 << (7 - (index & 7));
+}
+
 impl NullBits {
     pub unsafe fn is_null_unchecked(&self, row: usize) -> bool {
         const BIT_LOC_SHIFT: usize = 3;
@@ -66,17 +68,13 @@ impl NullBits {
 
     pub unsafe fn slice(&self, range: Range<usize>) -> Self {
         let len = range.end - range.start;
-        let bytes_len = null_bits_len(len);
-        let inner = std::alloc::alloc(Layout::from_size_align_unchecked(bytes_len, 1));
-        inner.write_bytes(0, bytes_len);
-        let bytes: Box<[u8]> = Box::from_raw(std::slice::from_raw_parts_mut(inner, bytes_len));
-        let nulls = NullBits(bytes.into());
+        let mut inner = vec![0; null_bits_len(len)];
         for i in 0..len {
             if self.is_null_unchecked(i + range.start) {
-                unsafe { nulls.set_null_unchecked(i) };
+                set_null(&mut inner, i);
             }
         }
-        nulls
+        NullBits(inner.into())
     }
 
     pub fn iter(&self) -> NullsIter {
@@ -3,14 +3,22 @@ use std::{fmt::Debug, ops::DerefMut};
 
 use bytes::{Bytes, BytesMut};
 
-const ITEM_SIZE: usize = std::mem::size_of::<i32>();
+use super::bytes_from_vec;
+
 /// A [i32] slice offsets, which will represent the value is NULL (if offset is `-1`) or not.
 #[derive(Clone)]
-pub struct Offsets(pub(super) Bytes);
+pub struct Offsets(pub(crate) Bytes);
 
 impl<T: Into<Bytes>> From<T> for Offsets {
+    /// Offsets are copied if the bytes are not aligned for [i32], eg. sliced from a raw block
+    /// after columns of odd sizes.
     fn from(value: T) -> Self {
-        Offsets(value.into())
+        let bytes = value.into();
+        if bytes.is_empty() || bytes.as_ptr().align_offset(std::mem::align_of::<i32>()) == 0 {
+            return Offsets(bytes);
+        }
+        let offsets = bytes.chunks_exact(std::mem::size_of::<i32>());
+        Self::from_offsets(offsets.map(|offset| i32::from_le_bytes(offset.try_into().unwrap())))
     }
 }
 
@@ -22,7 +30,7 @@ impl Debug for Offsets {
 
 impl Offsets {
     pub fn from_offsets(iter: impl ExactSizeIterator<Item = i32>) -> Self {
-        OffsetsMut::from_offsets(iter).into_offsets()
+        Offsets(bytes_from_vec(iter.map(i32::to_le).collect::<Vec<_>>()))
     }
     /// As a i32 slice.
     pub fn as_slice(&self) -> &[i32] {
@@ -38,40 +46,32 @@ impl Offsets {
         self.0.len() / std::mem::size_of::<i32>()
     }
 
+    /// Get offset at `index`, offsets are little-endian in bytes.
     pub unsafe fn get_unchecked(&self, index: usize) -> i32 {
-        unsafe {
+        i32::from_le(unsafe {
             std::ptr::read_unaligned(
                 self.0
                     .as_ptr()
                     .offset((index * std::mem::size_of::<i32>()) as isize) as _,
             )
-        }
+        })
     }
 
     pub unsafe fn slice_unchecked(
         &self,
         range: std::ops::Range<usize>,
     ) -> (Self, Option<(i32, Option<i32>)>) {
-        let len = range.len();
-        use std::alloc::{alloc, Layout};
-        let ptr = alloc(Layout::from_size_align_unchecked(
-            len * ITEM_SIZE,
-            ITEM_SIZE,
-        ));
-        ptr.write_bytes(0, len * ITEM_SIZE);
-        let slice = ptr as *mut i32;
+        let mut offsets = Vec::with_capacity(range.len());
         let mut offset0 = None;
-        let start = range.start;
         for i in range.clone() {
             let offset = self.get_unchecked(i);
-            let ptr = slice.offset(i as isize - start as isize);
             if offset == -1 {
-                ptr.write(-1);
+                offsets.push(-1);
             } else if offset0.is_none() {
-                ptr.write(0);
+                offsets.push(0);
                 offset0.replace(offset);
             } else if let Some(offset0) = offset0 {
-                ptr.write(offset - offset0);
+                offsets.push((offset - offset0).to_le());
             }
         }
 
@@ -89,9 +89,8 @@ impl Offsets {
         if offset0 == offset1 {
             offset1.take();
         }
-        let bytes: Box<[u8]> = Box::from_raw(std::slice::from_raw_parts_mut(ptr, len * ITEM_SIZE));
         (
-            Self(bytes.into()),
+            Self(bytes_from_vec(offsets)),
             offset0.map(|offset0| (offset0, offset1)),
         )
     }
@@ -142,7 +141,7 @@ impl OffsetsMut {
         let mut offsets = Self::new(iter.len());
 
         iter.enumerate().for_each(|(i, offset)| unsafe {
-            *offsets.get_unchecked_mut(i) = offset;
+            *offsets.get_unchecked_mut(i) = offset.to_le();
         });
 
         offsets
@@ -161,14 +160,14 @@ impl OffsetsMut {
     pub fn as_slice_mut(&mut self) -> &mut [i32] {
         unsafe {
             std::slice::from_raw_parts_mut(
-                self.0.as_ptr() as *mut i32,
+                self.0.as_mut_ptr() as *mut i32,
                 self.0.len() / std::mem::size_of::<i32>(),
             )
         }
     }
 
     pub fn into_offsets(self) -> Offsets {
-        Offsets::from(self.0)
+        Offsets(self.0.freeze())
     }
 }
 
@@ -2,28 +2,59 @@ use std::{fmt::Debug, ops::Deref};
 
 use bytes::Bytes;
 
-use crate::common::{Field, Ty};
+use crate::common::{Field, Ty, UnknownTypeError};
 
 /// Represent column basics information: type, length.
+///
+/// The length is little-endian as in raw block bytes, use [ColSchema::len] to read it. The type
+/// is kept as its id, so schemas of any bytes are valid.
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 #[repr(packed(1))]
 pub struct ColSchema {
-    pub(crate) ty: Ty,
+    pub(crate) ty: u8,
     pub(crate) len: u32,
 }
 
 impl Field {
+    /// Type and length of the field as in raw block.
     #[inline]
-    pub(crate) fn to_column_schema(&self) -> ColSchema {
+    pub fn to_column_schema(&self) -> ColSchema {
         ColSchema::new(self.ty(), self.bytes() as _)
     }
 }
 
 impl ColSchema {
     #[inline]
-    pub(crate) const fn new(ty: Ty, len: u32) -> Self {
-        Self { ty, len }
+    pub const fn new(ty: Ty, len: u32) -> Self {
+        Self {
+            ty: ty as u8,
+            len: len.to_le(),
+        }
+    }
+
+    /// Type of the column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type id is unknown, see [Ty::try_from_u8].
+    #[inline]
+    pub fn ty(&self) -> Ty {
+        Ty::from(self.ty)
+    }
+
+    /// Type of the column, fails if the type id is unknown, eg. of a type added by a newer
+    /// server.
+    #[inline]
+    pub fn try_ty(&self) -> Result<Ty, UnknownTypeError> {
+        Ty::try_from_u8(self.ty)
+    }
+
+    /// Length defined in `create table`.
+    #[inline]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u32 {
+        u32::from_le(self.len)
     }
     #[inline]
     pub(crate) fn as_bytes(&self) -> &[u8] {
@@ -39,7 +70,7 @@ impl ColSchema {
 #[test]
 fn col_schema() {
     let col = ColSchema {
-        ty: Ty::BigInt,
+        ty: Ty::BigInt as u8,
         len: 1,
     };
     let bytes: [u8; 5] = unsafe { std::mem::transmute_copy(&col) };
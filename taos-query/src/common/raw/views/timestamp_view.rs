@@ -1,8 +1,10 @@
 use std::ffi::c_void;
 
-use crate::common::{BorrowedValue, Precision, Timestamp, Ty};
+use crate::common::{BorrowedValue, Precision, PrecisionError, Timestamp, Ty};
 
-use super::{IsColumnView, NullBits, NullsIter};
+use super::{
+    bytes_from_vec, try_convert, ConvertError, IsColumnView, LePrimitive, NullBits, NullsIter,
+};
 
 use bytes::Bytes;
 use itertools::Itertools;
@@ -25,8 +27,25 @@ impl IsColumnView for View {
     fn from_borrowed_value_iter<'b>(iter: impl Iterator<Item = BorrowedValue<'b>>) -> Self {
         Self::from_nullable_timestamp(iter.map(|v| v.to_timestamp()).collect_vec())
     }
+
+    fn try_from_borrowed_value_iter<'b>(
+        iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError> {
+        Ok(Self::from_nullable_timestamp(try_convert(
+            iter,
+            Ty::Timestamp,
+            BorrowedValue::try_to_timestamp,
+        )?))
+    }
 }
 impl TimestampView {
+    /// Offset and bytes of the value at `row` in the data, the slot of a NULL value included.
+    pub(crate) fn raw_bytes_at(&self, row: usize) -> Option<(usize, &[u8])> {
+        let offset = row * ITEM_SIZE;
+        let bytes = self.data.get(offset..offset + ITEM_SIZE)?;
+        Some((offset, bytes))
+    }
+
     pub fn from_millis(values: Vec<impl Into<Option<i64>>>) -> Self {
         TimestampMillisecondView::from_iter(values).into_inner()
     }
@@ -39,6 +58,28 @@ impl TimestampView {
         TimestampNanosecondView::from_iter(values).into_inner()
     }
 
+    /// Timestamps of `precision` from datetimes, see [Timestamp::from_datetime].
+    ///
+    /// It fails if any datetime is out of range of the precision.
+    pub fn from_datetimes<Tz: chrono::TimeZone>(
+        values: Vec<impl Into<Option<chrono::DateTime<Tz>>>>,
+        precision: Precision,
+    ) -> Result<Self, PrecisionError> {
+        let values: Vec<Option<i64>> = values
+            .into_iter()
+            .map(|v| {
+                v.into()
+                    .map(|dt| Timestamp::from_datetime(&dt, precision).map(|ts| ts.as_raw_i64()))
+                    .transpose()
+            })
+            .try_collect()?;
+        Ok(match precision {
+            Precision::Millisecond => Self::from_millis(values),
+            Precision::Microsecond => Self::from_micros(values),
+            Precision::Nanosecond => Self::from_nanos(values),
+        })
+    }
+
     pub fn from_timestamp(values: Vec<Timestamp>) -> Self {
         let precision = values.first().map(|ts| ts.precision()).unwrap_or_default();
         let values = values.into_iter().map(|ts| ts.as_raw_i64()).collect_vec();
@@ -48,6 +89,9 @@ impl TimestampView {
             Precision::Nanosecond => Self::from_nanos(values),
         }
     }
+
+    /// Values are taken in the precision of the first non-null one, use
+    /// [TimestampView::try_from_timestamps] for timestamps of mixed precisions.
     pub fn from_nullable_timestamp(values: Vec<Option<Timestamp>>) -> Self {
         let precision = values
             .iter()
@@ -73,17 +117,55 @@ impl TimestampView {
         self.precision
     }
 
+    /// Timestamps of mixed precisions normalized to `precision`, see [Timestamp::to_precision].
+    ///
+    /// It fails if any timestamp overflows in the precision.
+    pub fn try_from_timestamps(
+        values: Vec<impl Into<Option<Timestamp>>>,
+        precision: Precision,
+    ) -> Result<Self, PrecisionError> {
+        let values: Vec<Option<i64>> = values
+            .into_iter()
+            .map(|ts| {
+                ts.into()
+                    .map(|ts| ts.to_precision(precision).map(|ts| ts.as_raw_i64()))
+                    .transpose()
+            })
+            .try_collect()?;
+        Ok(match precision {
+            Precision::Millisecond => Self::from_millis(values),
+            Precision::Microsecond => Self::from_micros(values),
+            Precision::Nanosecond => Self::from_nanos(values),
+        })
+    }
+
+    /// Convert values to `precision`, a clone if it's the precision of the view already.
+    ///
+    /// Nulls are kept. It fails if any value overflows in the precision.
+    pub fn normalize_to(&self, precision: Precision) -> Result<Self, PrecisionError> {
+        if precision == self.precision {
+            return Ok(self.clone());
+        }
+        Self::try_from_timestamps(self.to_vec(), precision)
+    }
+
     /// Rows
     pub fn len(&self) -> usize {
         self.data.len() / std::mem::size_of::<Item>()
     }
 
-    /// Raw slice of target type.
-    pub fn as_raw_slice(&self) -> &[Item] {
-        unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const Item, self.len()) }
+    /// Raw slice of target type, `None` if the data is not aligned for it, eg. a block sliced at
+    /// an odd offset of a received frame, or on big-endian hosts as values are little-endian.
+    /// Values are still readable by [Self::get] then.
+    pub fn as_raw_slice(&self) -> Option<&[Item]> {
+        let ptr = self.data.as_ptr() as *const Item;
+        if cfg!(target_endian = "big") || ptr.align_offset(std::mem::align_of::<Item>()) != 0 {
+            return None;
+        }
+        Some(unsafe { std::slice::from_raw_parts(ptr, self.len()) })
     }
 
-    /// Raw pointer of the slice.
+    /// Raw pointer of the slice, values are little-endian as in raw blocks.
     pub fn as_raw_ptr(&self) -> *const Item {
         self.data.as_ptr() as *const Item
     }
@@ -136,12 +218,13 @@ impl TimestampView {
             None
         } else {
             Some(Timestamp::new(
-                std::ptr::read_unaligned(
+                std::ptr::read_unaligned::<Item>(
                     self.data
                         .as_ptr()
                         .offset((row * std::mem::size_of::<Item>()) as isize)
                         as _,
-                ),
+                )
+                .decode_le(),
                 // *self.get_raw_at(row),
                 self.precision,
             ))
@@ -211,6 +294,19 @@ impl TimestampView {
         self.iter().collect()
     }
 
+    /// Format values in timezone `tz`, see [RawBlock::render_timestamps](crate::RawBlock::render_timestamps).
+    #[cfg(feature = "chrono-tz")]
+    pub fn to_formatted_in_tz(
+        &self,
+        tz: chrono_tz::Tz,
+        format: crate::common::TimestampFormat,
+    ) -> Vec<Option<String>> {
+        use crate::common::raw::export::format_timestamp_in;
+        self.iter()
+            .map(|ts| ts.map(|ts| format_timestamp_in(ts, format, self.precision, &tz)))
+            .collect()
+    }
+
     /// Write column data as raw bytes.
     pub(crate) fn write_raw_into<W: std::io::Write>(&self, mut wtr: W) -> std::io::Result<usize> {
         let nulls = self.nulls.0.as_ref();
@@ -242,7 +338,11 @@ impl<'a> Iterator for TimestampViewIter<'a> {
     where
         Self: Sized,
     {
-        self.view.get(self.view.len() - 1)
+        if self.row < self.view.len() {
+            self.view.get(self.view.len() - 1)
+        } else {
+            None
+        }
     }
 }
 
@@ -256,20 +356,16 @@ impl TimestampMillisecondView {
 
 impl<A: Into<Option<Item>>> FromIterator<A> for TimestampMillisecondView {
     fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
-        let (nulls, mut values): (Vec<bool>, Vec<_>) = iter
+        let (nulls, values): (Vec<bool>, Vec<_>) = iter
             .into_iter()
             .map(|v| match v.into() {
-                Some(v) => (false, v),
+                Some(v) => (false, v.encode_le()),
                 None => (true, Item::default()),
             })
             .unzip();
         Self(View {
             nulls: NullBits::from_iter(nulls),
-            data: Bytes::from({
-                let (ptr, len, cap) = (values.as_mut_ptr(), values.len(), values.capacity());
-                std::mem::forget(values);
-                unsafe { Vec::from_raw_parts(ptr as *mut u8, len * ITEM_SIZE, cap * ITEM_SIZE) }
-            }),
+            data: bytes_from_vec(values),
             precision: Precision::Millisecond,
         })
     }
@@ -283,20 +379,16 @@ impl TimestampMicrosecondView {
 
 impl<A: Into<Option<Item>>> FromIterator<A> for TimestampMicrosecondView {
     fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
-        let (nulls, mut values): (Vec<bool>, Vec<_>) = iter
+        let (nulls, values): (Vec<bool>, Vec<_>) = iter
             .into_iter()
             .map(|v| match v.into() {
-                Some(v) => (false, v),
+                Some(v) => (false, v.encode_le()),
                 None => (true, Item::default()),
             })
             .unzip();
         Self(View {
             nulls: NullBits::from_iter(nulls),
-            data: Bytes::from({
-                let (ptr, len, cap) = (values.as_mut_ptr(), values.len(), values.capacity());
-                std::mem::forget(values);
-                unsafe { Vec::from_raw_parts(ptr as *mut u8, len * ITEM_SIZE, cap * ITEM_SIZE) }
-            }),
+            data: bytes_from_vec(values),
             precision: Precision::Microsecond,
         })
     }
@@ -310,20 +402,16 @@ impl TimestampNanosecondView {
 
 impl<A: Into<Option<Item>>> FromIterator<A> for TimestampNanosecondView {
     fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
-        let (nulls, mut values): (Vec<bool>, Vec<_>) = iter
+        let (nulls, values): (Vec<bool>, Vec<_>) = iter
             .into_iter()
             .map(|v| match v.into() {
-                Some(v) => (false, v),
+                Some(v) => (false, v.encode_le()),
                 None => (true, Item::default()),
             })
             .unzip();
         Self(View {
             nulls: NullBits::from_iter(nulls),
-            data: Bytes::from({
-                let (ptr, len, cap) = (values.as_mut_ptr(), values.len(), values.capacity());
-                std::mem::forget(values);
-                unsafe { Vec::from_raw_parts(ptr as *mut u8, len * ITEM_SIZE, cap * ITEM_SIZE) }
-            }),
+            data: bytes_from_vec(values),
             precision: Precision::Nanosecond,
         })
     }
@@ -350,3 +438,61 @@ fn test_slice() {
         dbg!(inner.to_naive_datetime());
     }
 }
+
+#[test]
+fn test_from_datetimes() {
+    for precision in [
+        Precision::Millisecond,
+        Precision::Microsecond,
+        Precision::Nanosecond,
+    ] {
+        let (min, max) = (precision.min_datetime(), precision.max_datetime());
+        let view =
+            TimestampView::from_datetimes(vec![Some(min), None, Some(max)], precision).unwrap();
+        assert_eq!(view.precision(), precision);
+        let values: Vec<_> = view
+            .iter()
+            .map(|ts| ts.map(|ts| ts.to_naive_datetime()))
+            .collect();
+        assert_eq!(values, [Some(min.naive_utc()), None, Some(max.naive_utc())]);
+    }
+
+    let over = Precision::Nanosecond.max_datetime() + chrono::Duration::nanoseconds(1);
+    assert!(TimestampView::from_datetimes(vec![over], Precision::Nanosecond).is_err());
+    assert!(TimestampView::from_datetimes(vec![over], Precision::Microsecond).is_ok());
+}
+
+#[test]
+fn test_normalize_mixed_precisions() {
+    let ms = TimestampView::from_millis(vec![Some(1_704_067_200_001), None]);
+    let us = TimestampView::from_micros(vec![1_704_067_200_001_002]);
+    assert_eq!(ms.precision(), Precision::Millisecond);
+    assert_eq!(us.precision(), Precision::Microsecond);
+
+    let values = ms.iter().chain(us.iter()).collect_vec();
+    let ns = TimestampView::try_from_timestamps(values, Precision::Nanosecond).unwrap();
+    assert_eq!(ns.precision(), Precision::Nanosecond);
+    assert_eq!(
+        ns.to_vec(),
+        [
+            Some(Timestamp::Nanoseconds(1_704_067_200_001_000_000)),
+            None,
+            Some(Timestamp::Nanoseconds(1_704_067_200_001_002_000)),
+        ]
+    );
+
+    let normalized = ms.normalize_to(Precision::Nanosecond).unwrap();
+    assert_eq!(normalized.to_vec(), ns.slice(0..2).unwrap().to_vec());
+    assert_eq!(
+        us.normalize_to(Precision::Millisecond).unwrap().to_vec(),
+        [Some(Timestamp::Milliseconds(1_704_067_200_001))]
+    );
+    assert_eq!(
+        ms.normalize_to(Precision::Millisecond).unwrap().to_vec(),
+        ms.to_vec()
+    );
+
+    let far = TimestampView::from_millis(vec![i64::MAX / 1000]);
+    assert!(far.normalize_to(Precision::Microsecond).is_ok());
+    assert!(far.normalize_to(Precision::Nanosecond).is_err());
+}
@@ -0,0 +1,275 @@
+use std::{borrow::Cow, ffi::c_void, fmt::Debug};
+
+use super::{bytes_from_vec, try_convert, ConvertError, IsColumnView, Offsets};
+use crate::{
+    common::{BorrowedValue, Ty},
+    prelude::InlinableWrite,
+    util::InlineBytes,
+};
+
+use bytes::Bytes;
+use itertools::Itertools;
+
+/// View of a `varbinary` column, values are length-prefixed bytes as [super::VarCharView].
+#[derive(Debug, Clone)]
+pub struct VarBinaryView {
+    pub(crate) offsets: Offsets,
+    pub(crate) data: Bytes,
+}
+
+impl IsColumnView for VarBinaryView {
+    fn ty(&self) -> Ty {
+        Ty::VarBinary
+    }
+    fn from_borrowed_value_iter<'b>(iter: impl Iterator<Item = BorrowedValue<'b>>) -> Self {
+        Self::from_iter::<Vec<u8>, _, _, _>(
+            iter.map(|v| {
+                v.try_to_bytes()
+                    .unwrap_or_else(|_| {
+                        panic!("Unsupported conversion from {} to varbinary", v.ty())
+                    })
+                    .map(Cow::into_owned)
+            })
+            .collect_vec(),
+        )
+    }
+
+    fn try_from_borrowed_value_iter<'b>(
+        iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError> {
+        let values = try_convert(iter, Ty::VarBinary, |v| {
+            v.try_to_bytes().map(|v| v.map(Cow::into_owned))
+        })?;
+        Ok(Self::from_iter::<Vec<u8>, _, _, _>(values))
+    }
+}
+
+impl VarBinaryView {
+    /// Offset and bytes of the value at `row` in the data, with the length prefix, `None` if
+    /// it's NULL.
+    pub(crate) fn raw_bytes_at(&self, row: usize) -> Option<(usize, &[u8])> {
+        super::inline_bytes_at(&self.offsets, &self.data, row)
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// A iterator only decide if the value at some row index is NULL or not.
+    pub fn is_null_iter(&self) -> VarBinaryNullsIter<'_> {
+        VarBinaryNullsIter { view: self, row: 0 }
+    }
+
+    /// Build a nulls vector.
+    pub fn to_nulls_vec(&self) -> Vec<bool> {
+        self.is_null_iter().collect()
+    }
+
+    /// Check if the value at `row` index is NULL or not.
+    ///
+    /// Returns null when `row` index out of bound.
+    pub fn is_null(&self, row: usize) -> bool {
+        if row < self.len() {
+            unsafe { self.is_null_unchecked(row) }
+        } else {
+            false
+        }
+    }
+
+    /// Unsafe version for [is_null](#method.is_null)
+    pub(crate) unsafe fn is_null_unchecked(&self, row: usize) -> bool {
+        self.offsets.get_unchecked(row) < 0
+    }
+
+    /// Bytes at `row`, `None` if it's NULL or out of bound.
+    pub fn get(&self, row: usize) -> Option<&[u8]> {
+        if row < self.len() {
+            unsafe { self.get_unchecked(row) }.map(|v| v.as_bytes())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) unsafe fn get_unchecked(&self, row: usize) -> Option<&InlineBytes> {
+        let offset = self.offsets.get_unchecked(row);
+        if offset >= 0 {
+            Some(InlineBytes::<u16>::from_ptr(
+                self.data.as_ptr().offset(offset as isize),
+            ))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) unsafe fn get_value_unchecked(&self, row: usize) -> BorrowedValue<'_> {
+        self.get_unchecked(row)
+            .map(|v| BorrowedValue::VarBinary(v.as_bytes().into()))
+            .unwrap_or(BorrowedValue::Null(Ty::VarBinary))
+    }
+
+    pub(crate) unsafe fn get_raw_value_unchecked(&self, row: usize) -> (Ty, u32, *const c_void) {
+        match self.get_unchecked(row) {
+            Some(v) => (Ty::VarBinary, v.len() as _, v.as_bytes().as_ptr() as _),
+            None => (Ty::VarBinary, 0, std::ptr::null()),
+        }
+    }
+
+    pub fn slice(&self, mut range: std::ops::Range<usize>) -> Option<Self> {
+        if range.start >= self.len() {
+            return None;
+        }
+        if range.end > self.len() {
+            range.end = self.len();
+        }
+        if range.is_empty() {
+            return None;
+        }
+        let (offsets, range) = unsafe { self.offsets.slice_unchecked(range.clone()) };
+        if let Some(range) = range {
+            let range = range.0 as usize..range.1.map(|v| v as usize).unwrap_or(self.data.len());
+            let data = self.data.slice(range);
+            Some(Self { offsets, data })
+        } else {
+            let data = self.data.slice(0..0);
+            Some(Self { offsets, data })
+        }
+    }
+
+    pub fn iter(&self) -> VarBinaryIter<'_> {
+        VarBinaryIter { view: self, row: 0 }
+    }
+
+    pub fn to_vec(&self) -> Vec<Option<Vec<u8>>> {
+        self.iter().map(|v| v.map(<[u8]>::to_vec)).collect()
+    }
+
+    /// Write column data as raw bytes.
+    pub(crate) fn write_raw_into<W: std::io::Write>(&self, mut wtr: W) -> std::io::Result<usize> {
+        let mut offsets = Vec::with_capacity(self.len());
+        let mut bytes: Vec<u8> = Vec::new();
+        for v in self.iter() {
+            if let Some(v) = v {
+                offsets.push((bytes.len() as i32).to_le());
+                bytes.write_inlined_bytes::<2>(v).unwrap();
+            } else {
+                offsets.push(-1);
+            }
+        }
+        let offsets_bytes = unsafe {
+            std::slice::from_raw_parts(
+                offsets.as_ptr() as *const u8,
+                offsets.len() * std::mem::size_of::<i32>(),
+            )
+        };
+        wtr.write_all(offsets_bytes)?;
+        wtr.write_all(&bytes)?;
+        Ok(offsets_bytes.len() + bytes.len())
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_iter<
+        S: AsRef<[u8]>,
+        T: Into<Option<S>>,
+        I: ExactSizeIterator<Item = T>,
+        V: IntoIterator<Item = T, IntoIter = I>,
+    >(
+        iter: V,
+    ) -> Self {
+        let iter = iter.into_iter();
+        let mut offsets = Vec::with_capacity(iter.len());
+        let mut data = Vec::new();
+
+        for v in iter.map(|v| v.into()) {
+            if let Some(v) = v {
+                offsets.push((data.len() as i32).to_le());
+                data.write_inlined_bytes::<2>(v.as_ref()).unwrap();
+            } else {
+                offsets.push(-1);
+            }
+        }
+        VarBinaryView {
+            offsets: Offsets(bytes_from_vec(offsets)),
+            data: data.into(),
+        }
+    }
+}
+
+pub struct VarBinaryIter<'a> {
+    view: &'a VarBinaryView,
+    row: usize,
+}
+
+impl<'a> Iterator for VarBinaryIter<'a> {
+    type Item = Option<&'a [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.row < self.view.len() {
+            let row = self.row;
+            self.row += 1;
+            Some(unsafe { self.view.get_unchecked(row) }.map(|v| v.as_bytes()))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.view.len() - self.row;
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for VarBinaryIter<'a> {}
+
+pub struct VarBinaryNullsIter<'a> {
+    view: &'a VarBinaryView,
+    row: usize,
+}
+
+impl<'a> Iterator for VarBinaryNullsIter<'a> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.row < self.view.len() {
+            let row = self.row;
+            self.row += 1;
+            Some(unsafe { self.view.is_null_unchecked(row) })
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for VarBinaryNullsIter<'a> {
+    fn len(&self) -> usize {
+        self.view.len() - self.row
+    }
+}
+
+#[test]
+fn test_slice() {
+    let data: [Option<&[u8]>; 6] = [None, Some(b""), Some(b"\x00\xff"), None, Some(b"abc"), None];
+    let view = VarBinaryView::from_iter::<&[u8], _, _, _>(data);
+    assert_eq!(view.get(1), Some(&b""[..]));
+    assert_eq!(view.get(2), Some(&b"\x00\xff"[..]));
+    assert_eq!(view.get(3), None);
+    assert_eq!(view.get(6), None);
+    assert!(view.slice(0..0).is_none());
+    assert!(view.slice(100..1000).is_none());
+
+    for start in 0..data.len() {
+        for end in start + 1..data.len() {
+            let slice = view.slice(start..end).unwrap();
+            assert_eq!(
+                slice.to_vec(),
+                data[start..end]
+                    .iter()
+                    .map(|v| v.map(<[u8]>::to_vec))
+                    .collect_vec()
+            );
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{ffi::c_void, fmt::Debug};
 
-use super::{Offsets, IsColumnView};
+use super::{bytes_from_vec, try_convert, ConvertError, IsColumnView, Offsets};
 use crate::{
     common::{BorrowedValue, Ty},
     prelude::InlinableWrite,
@@ -28,9 +28,24 @@ impl IsColumnView for VarCharView {
                 .into_iter(),
         )
     }
+
+    fn try_from_borrowed_value_iter<'b>(
+        iter: impl Iterator<Item = BorrowedValue<'b>>,
+    ) -> Result<Self, ConvertError> {
+        let values = try_convert(iter, Ty::VarChar, |v| {
+            v.try_to_str().map(|v| v.map(|v| v.into_owned()))
+        })?;
+        Ok(Self::from_iter::<String, _, _, _>(values))
+    }
 }
 
 impl VarCharView {
+    /// Offset and bytes of the value at `row` in the data, with the length prefix, `None` if
+    /// it's NULL.
+    pub(crate) fn raw_bytes_at(&self, row: usize) -> Option<(usize, &[u8])> {
+        super::inline_bytes_at(&self.offsets, &self.data, row)
+    }
+
     pub fn len(&self) -> usize {
         self.offsets.len()
     }
@@ -113,6 +128,15 @@ impl VarCharView {
         VarCharIter { view: self, row: 0 }
     }
 
+    /// Dictionary encode the column, returns distinct values and per-row indices of the values.
+    ///
+    /// NULL values are kept as `None` in the indices.
+    pub fn to_dictionary(&self) -> (Vec<String>, Vec<Option<u32>>) {
+        crate::common::raw::dictionary::dictionary_from_iter(
+            self.iter().map(|s| s.map(|s| s.as_str())),
+        )
+    }
+
     pub fn to_vec(&self) -> Vec<Option<String>> {
         (0..self.len())
             .map(|row| unsafe { self.get_unchecked(row) }.map(|s| s.to_string()))
@@ -132,7 +156,7 @@ impl VarCharView {
         let mut bytes: Vec<u8> = Vec::new();
         for v in self.iter() {
             if let Some(v) = v {
-                offsets.push(bytes.len() as i32);
+                offsets.push((bytes.len() as i32).to_le());
                 bytes.write_inlined_str::<2>(v.as_str()).unwrap();
             } else {
                 offsets.push(-1);
@@ -169,23 +193,42 @@ impl VarCharView {
         for i in iter.map(|v| v.into()) {
             if let Some(s) = i {
                 let s: &str = s.as_ref();
-                offsets.push(data.len() as i32);
+                offsets.push((data.len() as i32).to_le());
                 data.write_inlined_str::<2>(&s).unwrap();
             } else {
                 offsets.push(-1);
             }
         }
-        // dbg!(&offsets);
-        let offsets_bytes = unsafe {
-            Vec::from_raw_parts(
-                offsets.as_mut_ptr() as *mut u8,
-                offsets.len() * 4,
-                offsets.capacity() * 4,
-            )
-        };
-        std::mem::forget(offsets);
         VarCharView {
-            offsets: Offsets(offsets_bytes.into()),
+            offsets: Offsets(bytes_from_vec(offsets)),
+            data: data.into(),
+        }
+    }
+
+    /// Build from raw bytes, which are not validated as UTF-8, eg. binary payloads in `BINARY`
+    /// columns.
+    pub fn from_bytes_iter<
+        S: AsRef<[u8]>,
+        T: Into<Option<S>>,
+        I: ExactSizeIterator<Item = T>,
+        V: IntoIterator<Item = T, IntoIter = I>,
+    >(
+        iter: V,
+    ) -> Self {
+        let iter = iter.into_iter();
+        let mut offsets = Vec::with_capacity(iter.len());
+        let mut data = Vec::new();
+
+        for i in iter.map(|v| v.into()) {
+            if let Some(bytes) = i {
+                offsets.push((data.len() as i32).to_le());
+                data.write_inlined_bytes::<2>(bytes.as_ref()).unwrap();
+            } else {
+                offsets.push(-1);
+            }
+        }
+        VarCharView {
+            offsets: Offsets(bytes_from_vec(offsets)),
             data: data.into(),
         }
     }
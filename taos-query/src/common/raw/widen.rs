@@ -0,0 +1,201 @@
+use crate::common::{Field, Ty};
+
+use super::views::{views_to_raw_block_with_schemas, ColumnView, NullView};
+use super::RawBlock;
+
+/// Blocks that can't be widened by [RawBlock::widen_to].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WidenError {
+    #[error("widen a block without field names")]
+    NoFieldNames,
+    #[error("column `{0}` is not in the target schema")]
+    Dropped(String),
+    #[error("column `{name}` is {from} but {to} in the target schema")]
+    Type { name: String, from: Ty, to: Ty },
+    #[error("column `{name}` of {ty} can't be filled with NULLs")]
+    Unsupported { name: String, ty: Ty },
+}
+
+impl RawBlock {
+    /// Rearrange columns of the block to `schema` by name, columns missing in the block are
+    /// filled with NULLs, eg. to replay blocks archived before columns were added to a table.
+    ///
+    /// Column lengths and names are of `schema`, table and database names are kept. It fails if
+    /// the block has no field names, or if a column of the block is not in `schema` or is of
+    /// another type, as rows would be lost or changed. Missing columns of types without null
+    /// views are rejected too, see [NullView::is_supported](super::views::NullView::is_supported).
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Field, Precision, RawBlock, Ty, Value};
+    /// let views = [ColumnView::from_millis_timestamp(vec![0]), ColumnView::from_ints(vec![1])];
+    /// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// block.with_field_names(["ts", "v"]);
+    /// let schema = [
+    ///     Field::new("ts", Ty::Timestamp, 8),
+    ///     Field::new("v", Ty::Int, 4),
+    ///     Field::new("note", Ty::VarChar, 20),
+    /// ];
+    /// let widened = block.widen_to(&schema).unwrap();
+    /// assert_eq!(widened.field_names(), ["ts", "v", "note"]);
+    /// assert_eq!(widened.get_ref(0, 2).unwrap().to_value(), Value::Null(Ty::VarChar));
+    /// ```
+    pub fn widen_to(&self, schema: &[Field]) -> Result<RawBlock, WidenError> {
+        if self.field_names().is_empty() {
+            return Err(WidenError::NoFieldNames);
+        }
+        let fields = self.fields();
+        if let Some(field) = fields
+            .iter()
+            .find(|field| !schema.iter().any(|target| target.name() == field.name()))
+        {
+            return Err(WidenError::Dropped(field.name().to_string()));
+        }
+        for target in schema {
+            if let Some(field) = fields.iter().find(|field| field.name() == target.name()) {
+                if field.ty() != target.ty() {
+                    return Err(WidenError::Type {
+                        name: field.name().to_string(),
+                        from: field.ty(),
+                        to: target.ty(),
+                    });
+                }
+            } else if !NullView::is_supported(target.ty()) {
+                return Err(WidenError::Unsupported {
+                    name: target.name().to_string(),
+                    ty: target.ty(),
+                });
+            }
+        }
+
+        let nulls: Vec<_> = schema
+            .iter()
+            .map(|target| match self.column(target.name()) {
+                Some(_) => None,
+                None => Some(ColumnView::null(target.ty(), self.nrows())),
+            })
+            .collect();
+        let views: Vec<&ColumnView> = schema
+            .iter()
+            .zip(&nulls)
+            .map(|(target, null)| match null {
+                Some(null) => null,
+                None => self.column(target.name()).expect("column of the block").1,
+            })
+            .collect();
+        let schemas: Vec<_> = schema.iter().map(Field::to_column_schema).collect();
+        let bytes = views_to_raw_block_with_schemas(&views, &schemas);
+        let mut block = RawBlock::parse_from_raw_block(bytes, self.precision());
+        self.copy_names_to(&mut block);
+        block.with_field_names(schema.iter().map(Field::name));
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{Precision, Value};
+
+    use super::*;
+
+    /// A block of the first version of a table, before `voltage` and `note` were added.
+    fn v1_block() -> RawBlock {
+        let views = [
+            ColumnView::from_millis_timestamp(vec![0, 1, 2]),
+            ColumnView::from_doubles(vec![Some(0.5), None, Some(1.5)]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        block.with_field_names(["ts", "current"]);
+        block.with_table_name("d0");
+        block
+    }
+
+    fn v2_schema() -> Vec<Field> {
+        vec![
+            Field::new("ts", Ty::Timestamp, 8),
+            Field::new("voltage", Ty::Int, 4),
+            Field::new("current", Ty::Double, 8),
+            Field::new("note", Ty::VarChar, 32),
+        ]
+    }
+
+    #[test]
+    fn replay_into_evolved_table() {
+        let block = v1_block().widen_to(&v2_schema()).unwrap();
+        assert_eq!(block.field_names(), ["ts", "voltage", "current", "note"]);
+        assert_eq!(block.table_name(), Some("d0"));
+        assert_eq!(block.schemas()[3].len(), 32);
+        let nulls = |ty| vec![Value::Null(ty); 3];
+        let values: Vec<Vec<Value>> = (0..block.ncols())
+            .map(|col| {
+                block.column_views()[col]
+                    .iter()
+                    .map(|v| v.to_value())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(values[1], nulls(Ty::Int));
+        assert_eq!(
+            values[2],
+            [
+                Value::Double(0.5),
+                Value::Null(Ty::Double),
+                Value::Double(1.5)
+            ]
+        );
+        assert_eq!(values[3], nulls(Ty::VarChar));
+        assert_eq!(
+            block.get_ref(2, 0).unwrap().to_value(),
+            Value::Timestamp(crate::common::Timestamp::Milliseconds(2))
+        );
+    }
+
+    #[test]
+    fn append_source_column() {
+        let block = v1_block();
+        let source = ColumnView::from_ints(vec![7; 3]);
+        let tagged = block.append_column("source", source).unwrap();
+        assert_eq!(tagged.field_names(), ["ts", "current", "source"]);
+        assert_eq!(tagged.table_name(), Some("d0"));
+        assert_eq!(
+            tagged.get_ref(1, 1).unwrap().to_value(),
+            Value::Null(Ty::Double)
+        );
+        assert_eq!(tagged.get_ref(2, 2).unwrap().to_value(), Value::Int(7));
+    }
+
+    #[test]
+    fn widen_mismatch() {
+        let mut schema = v2_schema();
+        schema.remove(2);
+        assert_eq!(
+            v1_block().widen_to(&schema).unwrap_err(),
+            WidenError::Dropped("current".to_string())
+        );
+
+        let mut schema = v2_schema();
+        schema[2] = Field::new("current", Ty::Float, 4);
+        let err = v1_block().widen_to(&schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column `current` is DOUBLE but FLOAT in the target schema"
+        );
+
+        let mut schema = v2_schema();
+        schema.push(Field::new("price", Ty::Decimal, 16));
+        let err = v1_block().widen_to(&schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column `price` of DECIMAL can't be filled with NULLs"
+        );
+
+        let views = [ColumnView::from_ints(vec![1])];
+        let unnamed =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        assert_eq!(
+            unnamed.widen_to(&v2_schema()).unwrap_err(),
+            WidenError::NoFieldNames
+        );
+    }
+}
@@ -0,0 +1,580 @@
+use super::{NonFinitePolicy, Precision};
+
+/// Formats of schemaless writes, see [SmlData].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum SchemalessProtocol {
+    /// InfluxDB line protocol, which is also the output of telegraf, eg.
+    /// `meters,location=SF current=10.3,voltage=219i32 1648432611249`.
+    #[doc(alias = "telegraf")]
+    Line = 1,
+    /// OpenTSDB telnet lines, eg. `meters.current 1648432611249 10.3 location=SF`.
+    Telnet = 2,
+    /// OpenTSDB JSON, each item is a data point object or an array of them.
+    Json = 3,
+}
+
+/// Precision of timestamps in schemaless data.
+///
+/// Only line protocol timestamps are parsed with it, OpenTSDB timestamps are seconds or
+/// milliseconds by their length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum SchemalessPrecision {
+    /// Let the server detect it, nanoseconds for line protocol.
+    #[default]
+    NonConfigured = 0,
+    Hours,
+    Minutes,
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+impl SchemalessPrecision {
+    /// The precision in the websocket protocol, the same as InfluxDB.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            SchemalessPrecision::NonConfigured => "",
+            SchemalessPrecision::Hours => "h",
+            SchemalessPrecision::Minutes => "m",
+            SchemalessPrecision::Seconds => "s",
+            SchemalessPrecision::Milliseconds => "ms",
+            SchemalessPrecision::Microseconds => "u",
+            SchemalessPrecision::Nanoseconds => "ns",
+        }
+    }
+}
+
+impl From<Precision> for SchemalessPrecision {
+    fn from(precision: Precision) -> Self {
+        match precision {
+            Precision::Millisecond => SchemalessPrecision::Milliseconds,
+            Precision::Microsecond => SchemalessPrecision::Microseconds,
+            Precision::Nanosecond => SchemalessPrecision::Nanoseconds,
+        }
+    }
+}
+
+/// Schemaless data rejected by [SmlDataBuilder::build].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SmlError {
+    #[error("no schemaless data to write")]
+    Empty,
+    /// `line` is 1-based in lines of all data for line and telnet protocols, or the item for
+    /// JSON.
+    #[error("schemaless line {line} is invalid, {reason}: {text}")]
+    Invalid {
+        line: usize,
+        reason: String,
+        text: String,
+    },
+}
+
+/// A batch of schemaless data of one protocol and precision, built by [SmlData::builder].
+///
+/// Lines are checked when it's built, so a malformed line is reported by its number before
+/// anything is written.
+///
+/// ```rust
+/// # use taos_query::common::{SchemalessPrecision, SchemalessProtocol, SmlData, SmlError};
+/// let data = SmlData::builder(SchemalessProtocol::Line)
+///     .precision(SchemalessPrecision::Milliseconds)
+///     .ttl(30)
+///     .line("meters,location=SF current=10.3,voltage=219i32 1648432611249")
+///     .build()?;
+/// assert_eq!(data.lines().len(), 1);
+///
+/// let err = SmlData::builder(SchemalessProtocol::Line)
+///     .lines(["meters current=10.3 1648432611249", "meters,location=SF 1648432611250"])
+///     .build()
+///     .unwrap_err();
+/// assert!(matches!(err, SmlError::Invalid { line: 2, .. }));
+/// # Ok::<_, SmlError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmlData {
+    protocol: SchemalessProtocol,
+    precision: SchemalessPrecision,
+    ttl: Option<i32>,
+    lines: Vec<String>,
+}
+
+impl SmlData {
+    pub fn builder(protocol: SchemalessProtocol) -> SmlDataBuilder {
+        SmlDataBuilder {
+            protocol,
+            precision: SchemalessPrecision::default(),
+            ttl: None,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn protocol(&self) -> SchemalessProtocol {
+        self.protocol
+    }
+
+    pub fn precision(&self) -> SchemalessPrecision {
+        self.precision
+    }
+
+    /// Days to keep auto-created tables, `None` for the default of the database.
+    pub fn ttl(&self) -> Option<i32> {
+        self.ttl
+    }
+
+    /// Lines of line and telnet protocols without blank lines and comments, or JSON items.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// The data to send, lines are joined by `\n` and JSON items are merged into an array.
+    pub fn payload(&self) -> String {
+        match self.protocol {
+            SchemalessProtocol::Json => {
+                let points = self.lines.iter().flat_map(|item| {
+                    match serde_json::from_str(item).expect("checked by build") {
+                        serde_json::Value::Array(points) => points,
+                        point => vec![point],
+                    }
+                });
+                serde_json::Value::Array(points.collect()).to_string()
+            }
+            _ => self.lines.join("\n"),
+        }
+    }
+}
+
+impl SmlData {
+    /// Convert NaN and infinite float values by `policy` before the data is written, `None` if
+    /// all values are finite.
+    ///
+    /// As there's no NULL in the protocols, a field written as NULL is removed from its line, and
+    /// a line without any finite value, eg. a telnet line of `NaN`, is removed. It fails with
+    /// [SmlError::Empty] if no line is left. `line` of errors is 1-based in [SmlData::lines].
+    /// JSON numbers are always finite, so JSON items are kept as is.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{NonFinitePolicy, SchemalessProtocol, SmlData};
+    /// let data = SmlData::builder(SchemalessProtocol::Line)
+    ///     .line("meters current=inf,voltage=219i32 1648432611249")
+    ///     .build()?;
+    /// assert!(data.finite(NonFinitePolicy::Error).is_err());
+    /// let finite = data.finite(NonFinitePolicy::Null)?.unwrap();
+    /// assert_eq!(finite.lines(), ["meters voltage=219i32 1648432611249"]);
+    /// # Ok::<_, taos_query::common::SmlError>(())
+    /// ```
+    pub fn finite(&self, policy: NonFinitePolicy) -> Result<Option<SmlData>, SmlError> {
+        let convert = match self.protocol {
+            SchemalessProtocol::Line => finite_line,
+            SchemalessProtocol::Telnet => finite_telnet,
+            SchemalessProtocol::Json => return Ok(None),
+        };
+        let mut lines = Vec::with_capacity(self.lines.len());
+        let mut changed = false;
+        for (index, line) in self.lines.iter().enumerate() {
+            match convert(line, policy).map_err(|reason| invalid(index + 1, reason, line))? {
+                Finite::Same => lines.push(line.clone()),
+                Finite::Line(line) => {
+                    changed = true;
+                    lines.push(line);
+                }
+                Finite::Drop => changed = true,
+            }
+        }
+        if !changed {
+            return Ok(None);
+        }
+        if lines.is_empty() {
+            return Err(SmlError::Empty);
+        }
+        Ok(Some(SmlData {
+            lines,
+            ..self.clone()
+        }))
+    }
+}
+
+/// Builder of [SmlData].
+#[derive(Debug, Clone)]
+pub struct SmlDataBuilder {
+    protocol: SchemalessProtocol,
+    precision: SchemalessPrecision,
+    ttl: Option<i32>,
+    lines: Vec<String>,
+}
+
+impl SmlDataBuilder {
+    /// Precision of line protocol timestamps, not configured by default.
+    pub fn precision(mut self, precision: impl Into<SchemalessPrecision>) -> Self {
+        self.precision = precision.into();
+        self
+    }
+
+    /// Days to keep tables created by this batch.
+    pub fn ttl(mut self, days: i32) -> Self {
+        self.ttl = Some(days);
+        self
+    }
+
+    /// Add a line, or a JSON item. A line with `\n` is split.
+    pub fn line(mut self, line: impl Into<String>) -> Self {
+        self.lines.push(line.into());
+        self
+    }
+
+    pub fn lines<I: IntoIterator<Item = S>, S: Into<String>>(mut self, lines: I) -> Self {
+        self.lines.extend(lines.into_iter().map(Into::into));
+        self
+    }
+
+    /// Check the lines, and fail with the first malformed one.
+    ///
+    /// Only the syntax is checked, eg. a type conflict with an existing table is left to the
+    /// server.
+    pub fn build(self) -> Result<SmlData, SmlError> {
+        let lines = match self.protocol {
+            SchemalessProtocol::Json => {
+                for (index, item) in self.lines.iter().enumerate() {
+                    check_json(item).map_err(|reason| invalid(index + 1, reason, item))?;
+                }
+                self.lines
+            }
+            protocol => {
+                let check = match protocol {
+                    SchemalessProtocol::Line => check_line,
+                    _ => check_telnet,
+                };
+                let mut lines = Vec::with_capacity(self.lines.len());
+                let all = self.lines.iter().flat_map(|line| line.split('\n'));
+                for (index, line) in all.enumerate() {
+                    let line = line.trim_end_matches('\r');
+                    if line.trim().is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    check(line).map_err(|reason| invalid(index + 1, reason, line))?;
+                    lines.push(line.to_string());
+                }
+                lines
+            }
+        };
+        if lines.is_empty() {
+            return Err(SmlError::Empty);
+        }
+        Ok(SmlData {
+            protocol: self.protocol,
+            precision: self.precision,
+            ttl: self.ttl,
+            lines,
+        })
+    }
+}
+
+fn invalid(line: usize, reason: impl Into<String>, text: &str) -> SmlError {
+    SmlError::Invalid {
+        line,
+        reason: reason.into(),
+        text: text.to_string(),
+    }
+}
+
+/// Split by `sep` that's not escaped by `\`, nor in double quotes if `quoted`.
+fn split_unescaped(s: &str, sep: char, quoted: bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut escaped, mut in_quotes) = (0, false, false);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' if quoted => in_quotes = !in_quotes,
+            _ if c == sep && !in_quotes => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn check_pairs(pairs: &[&str], kind: &str, quoted: bool) -> Result<(), String> {
+    for pair in pairs {
+        match split_unescaped(pair, '=', quoted)[..] {
+            [key, value] if !key.is_empty() && !value.is_empty() => (),
+            _ => return Err(format!("{kind} `{pair}` is not key=value")),
+        }
+    }
+    Ok(())
+}
+
+fn check_timestamp(ts: &str) -> Result<(), String> {
+    let digits = ts.strip_prefix('-').unwrap_or(ts);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("timestamp `{ts}` is not an integer"));
+    }
+    Ok(())
+}
+
+/// `measurement[,tag=value...] field=value[,field=value...] [timestamp]`
+fn check_line(line: &str) -> Result<(), String> {
+    let quotes = split_unescaped(line, '"', false).len() - 1;
+    if quotes % 2 == 1 {
+        return Err("unclosed quote".to_string());
+    }
+    let sections = split_unescaped(line, ' ', true);
+    let (series, fields, ts) = match sections[..] {
+        [series, fields] => (series, fields, None),
+        [series, fields, ts] => (series, fields, Some(ts)),
+        [_] => return Err("no fields".to_string()),
+        _ => return Err("unexpected content after timestamp".to_string()),
+    };
+    let series = split_unescaped(series, ',', false);
+    if series[0].is_empty() {
+        return Err("no measurement".to_string());
+    }
+    check_pairs(&series[1..], "tag", false)?;
+    if fields.is_empty() {
+        return Err("no fields".to_string());
+    }
+    check_pairs(&split_unescaped(fields, ',', true), "field", true)?;
+    ts.map_or(Ok(()), check_timestamp)
+}
+
+/// `metric timestamp value [tag=value...]`
+fn check_telnet(line: &str) -> Result<(), String> {
+    let words: Vec<_> = line.split_whitespace().collect();
+    match words[..] {
+        [_, ts, _, ref tags @ ..] => {
+            check_timestamp(ts)?;
+            check_pairs(tags, "tag", false)
+        }
+        _ => Err("expect metric, timestamp and value".to_string()),
+    }
+}
+
+/// A line converted by a [NonFinitePolicy].
+enum Finite {
+    Same,
+    Line(String),
+    Drop,
+}
+
+/// The value of a float field if it's NaN or infinite, with its type suffix, eg. `-inf` or
+/// `NaNf32`.
+fn non_finite(value: &str) -> Option<(f64, &str)> {
+    let (number, suffix) = match value.len().checked_sub(3) {
+        Some(at) if value.is_char_boundary(at) && matches!(&value[at..], "f64" | "f32") => {
+            value.split_at(at)
+        }
+        _ => (value, ""),
+    };
+    let number: f64 = number.parse().ok()?;
+    (!number.is_finite()).then_some((number, suffix))
+}
+
+/// The value a non-finite one is converted to, `None` for NULL.
+fn finite_value(
+    name: &str,
+    value: &str,
+    number: f64,
+    suffix: &str,
+    policy: NonFinitePolicy,
+) -> Result<Option<String>, String> {
+    match policy {
+        NonFinitePolicy::Error => Err(format!(
+            "{name} is {value}, which is not written the same by all servers"
+        )),
+        NonFinitePolicy::Clamp if number.is_infinite() => {
+            let sign = if number < 0. { "-" } else { "" };
+            Ok(Some(match suffix {
+                "f32" => format!("{sign}{:e}f32", f32::MAX),
+                suffix => format!("{sign}{:e}{suffix}", f64::MAX),
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn finite_line(line: &str, policy: NonFinitePolicy) -> Result<Finite, String> {
+    let sections = split_unescaped(line, ' ', true);
+    let Some(fields) = sections.get(1) else {
+        return Ok(Finite::Same);
+    };
+    let mut kept = Vec::new();
+    let mut changed = false;
+    for field in split_unescaped(fields, ',', true) {
+        let (key, value) = match split_unescaped(field, '=', true)[..] {
+            [key, value] => (key, value),
+            _ => (field, ""),
+        };
+        match non_finite(value) {
+            Some((number, suffix)) => {
+                changed = true;
+                let name = format!("field `{key}`");
+                if let Some(value) = finite_value(&name, value, number, suffix, policy)? {
+                    kept.push(format!("{key}={value}"));
+                }
+            }
+            None => kept.push(field.to_string()),
+        }
+    }
+    if !changed {
+        return Ok(Finite::Same);
+    }
+    if kept.is_empty() {
+        return Ok(Finite::Drop);
+    }
+    let mut sections: Vec<_> = sections.iter().map(|s| s.to_string()).collect();
+    sections[1] = kept.join(",");
+    Ok(Finite::Line(sections.join(" ")))
+}
+
+fn finite_telnet(line: &str, policy: NonFinitePolicy) -> Result<Finite, String> {
+    let mut words: Vec<_> = line.split_whitespace().map(str::to_string).collect();
+    let Some((number, suffix)) = words.get(2).and_then(|value| non_finite(value)) else {
+        return Ok(Finite::Same);
+    };
+    match finite_value("value", &words[2], number, suffix, policy)? {
+        Some(value) => {
+            words[2] = value;
+            Ok(Finite::Line(words.join(" ")))
+        }
+        None => Ok(Finite::Drop),
+    }
+}
+
+fn check_json(item: &str) -> Result<(), String> {
+    match serde_json::from_str(item).map_err(|err| err.to_string())? {
+        serde_json::Value::Object(_) => Ok(()),
+        serde_json::Value::Array(points) if points.iter().all(|p| p.is_object()) => Ok(()),
+        _ => Err("expect a data point object or an array of them".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_lines() {
+        let data = SmlData::builder(SchemalessProtocol::Line)
+            .precision(Precision::Microsecond)
+            .line("# comment\nst,t1=a\\ b,t2=1 c1=\"x, y=\\\"z\\\"\",c2=1i32 1648432611249000\n")
+            .line("st\\,1 c1=true")
+            .build()
+            .unwrap();
+        assert_eq!(data.precision(), SchemalessPrecision::Microseconds);
+        assert_eq!(data.lines().len(), 2);
+        assert_eq!(
+            data.payload(),
+            "st,t1=a\\ b,t2=1 c1=\"x, y=\\\"z\\\"\",c2=1i32 1648432611249000\nst\\,1 c1=true"
+        );
+
+        let cases = [
+            ("st,t1=1", "no fields"),
+            (",t1=1 c1=1", "no measurement"),
+            ("st,t1 c1=1", "tag `t1` is not key=value"),
+            ("st c1=1,c2 1", "field `c2` is not key=value"),
+            ("st c1=1 16484x", "timestamp `16484x` is not an integer"),
+            ("st c1=1 1 2", "unexpected content after timestamp"),
+            ("st c1=\"a 1", "unclosed quote"),
+        ];
+        for (line, reason) in cases {
+            let err = SmlData::builder(SchemalessProtocol::Line)
+                .lines(["st c1=1", "", line])
+                .build()
+                .unwrap_err();
+            assert_eq!(err, invalid(3, reason, line), "{line}");
+        }
+        assert_eq!(
+            SmlData::builder(SchemalessProtocol::Line)
+                .line("\n# only comments")
+                .build(),
+            Err(SmlError::Empty)
+        );
+    }
+
+    #[test]
+    fn check_telnet_and_json() {
+        let telnet = SmlData::builder(SchemalessProtocol::Telnet)
+            .lines(["meters.current 1648432611249 10.3 location=SF group=2"])
+            .build()
+            .unwrap();
+        assert_eq!(telnet.lines().len(), 1);
+        let err = SmlData::builder(SchemalessProtocol::Telnet)
+            .lines(["meters.current 1648432611249 10.3", "meters.current 10.3"])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, SmlError::Invalid { line: 2, .. }));
+
+        let point = r#"{"metric":"m","timestamp":1648432611,"value":1,"tags":{"t":"a"}}"#;
+        let json = SmlData::builder(SchemalessProtocol::Json)
+            .lines([point.to_string(), format!("[{point},{point}]")])
+            .build()
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&json.payload()).unwrap();
+        assert_eq!(payload.as_array().unwrap().len(), 3);
+        let err = SmlData::builder(SchemalessProtocol::Json)
+            .lines([point, "[1]"])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "schemaless line 2 is invalid, expect a data point object or an array of them: [1]"
+        );
+    }
+
+    #[test]
+    fn finite_policies() {
+        let line = SmlData::builder(SchemalessProtocol::Line)
+            .lines([
+                "st,t1=a c1=1.5,c2=-inff32,c3=\"inf\",c4=NaN 1648432611249",
+                "st,t1=a c1=infinity",
+                "st,t1=a c1=2.5 1648432611250",
+            ])
+            .build()
+            .unwrap();
+        let err = line.finite(NonFinitePolicy::Error).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("schemaless line 1 is invalid, field `c2` is -inff32"),
+            "{err}"
+        );
+        let null = line.finite(NonFinitePolicy::Null).unwrap().unwrap();
+        assert_eq!(
+            null.lines(),
+            [
+                "st,t1=a c1=1.5,c3=\"inf\" 1648432611249",
+                "st,t1=a c1=2.5 1648432611250"
+            ]
+        );
+        let clamp = line.finite(NonFinitePolicy::Clamp).unwrap().unwrap();
+        assert_eq!(
+            clamp.lines(),
+            [
+                "st,t1=a c1=1.5,c2=-3.4028235e38f32,c3=\"inf\" 1648432611249",
+                "st,t1=a c1=1.7976931348623157e308",
+                "st,t1=a c1=2.5 1648432611250"
+            ]
+        );
+        assert_eq!(clamp.precision(), line.precision());
+
+        let telnet = SmlData::builder(SchemalessProtocol::Telnet)
+            .lines(["m 1648432611249 -inf t=a", "m 1648432611250 nan t=a"])
+            .build()
+            .unwrap();
+        let clamp = telnet.finite(NonFinitePolicy::Clamp).unwrap().unwrap();
+        assert_eq!(
+            clamp.lines(),
+            ["m 1648432611249 -1.7976931348623157e308 t=a"]
+        );
+        assert_eq!(telnet.finite(NonFinitePolicy::Null), Err(SmlError::Empty));
+        let finite = SmlData::builder(SchemalessProtocol::Telnet)
+            .lines(["m 1648432611249 1e308 t=a"])
+            .build()
+            .unwrap();
+        assert_eq!(finite.finite(NonFinitePolicy::Error), Ok(None));
+    }
+}
@@ -1,9 +1,9 @@
 use std::fmt::{self, Debug, Display};
 
-use chrono::Local;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
 use serde::{Deserialize, Serialize};
 
-use super::Precision;
+use super::{Precision, PrecisionError};
 
 #[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Timestamp {
@@ -41,6 +41,71 @@ impl Timestamp {
         }
     }
 
+    /// Like [Timestamp::new], but fails if `raw` is out of [Precision::min_datetime] and
+    /// [Precision::max_datetime], which could not be converted to a datetime.
+    pub fn try_new(raw: i64, precision: Precision) -> Result<Self, PrecisionError> {
+        let min = Self::from_datetime(&precision.min_datetime(), precision)?;
+        let max = Self::from_datetime(&precision.max_datetime(), precision)?;
+        if (min.as_raw_i64()..=max.as_raw_i64()).contains(&raw) {
+            Ok(Self::new(raw, precision))
+        } else {
+            Err(PrecisionError::TimestampOutOfRange {
+                value: raw.to_string(),
+                precision,
+            })
+        }
+    }
+
+    /// Timestamp of `datetime` in `precision`, truncated to a tick.
+    ///
+    /// It fails rather than wraps if the datetime is out of [Precision::min_datetime] and
+    /// [Precision::max_datetime], eg. after 2262-04-11 in nanoseconds.
+    pub fn from_datetime<Tz: TimeZone>(
+        datetime: &DateTime<Tz>,
+        precision: Precision,
+    ) -> Result<Self, PrecisionError> {
+        let ticks = precision.ticks_per_second();
+        let subsec = datetime.timestamp_subsec_nanos() as i64 / (1_000_000_000 / ticks);
+        let raw = datetime.timestamp() as i128 * ticks as i128 + subsec as i128;
+        i64::try_from(raw)
+            .map(|raw| Self::new(raw, precision))
+            .map_err(|_| PrecisionError::TimestampOutOfRange {
+                value: datetime.to_rfc3339(),
+                precision,
+            })
+    }
+
+    /// Timestamp of a naive datetime in UTC, see [Timestamp::from_datetime].
+    pub fn from_naive_datetime(
+        datetime: &NaiveDateTime,
+        precision: Precision,
+    ) -> Result<Self, PrecisionError> {
+        Self::from_datetime(&datetime.and_utc(), precision)
+    }
+
+    /// The same instant in `precision`.
+    ///
+    /// Coarser precisions are truncated towards the past like [Timestamp::from_datetime], finer
+    /// ones fail rather than overflow, eg. milliseconds after 2262-04-11 in nanoseconds.
+    pub fn to_precision(self, precision: Precision) -> Result<Self, PrecisionError> {
+        let (from, to) = (
+            self.precision().ticks_per_second(),
+            precision.ticks_per_second(),
+        );
+        let raw = self.as_raw_i64();
+        let converted = if to >= from {
+            raw.checked_mul(to / from)
+        } else {
+            Some(raw.div_euclid(from / to))
+        };
+        converted
+            .map(|raw| Self::new(raw, precision))
+            .ok_or_else(|| PrecisionError::TimestampOutOfRange {
+                value: format!("{raw}{}", self.precision()),
+                precision,
+            })
+    }
+
     pub fn precision(&self) -> Precision {
         match self {
             Timestamp::Milliseconds(_) => Precision::Millisecond,
@@ -91,6 +156,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ts_from_datetime_boundaries() {
+        use Precision::*;
+        for prec in [Millisecond, Microsecond, Nanosecond] {
+            let max = prec.max_datetime();
+            let ts = Timestamp::from_datetime(&max, prec).unwrap();
+            assert_eq!(ts.precision(), prec);
+            assert_eq!(ts.to_naive_datetime(), max.naive_utc());
+            let min = prec.min_datetime();
+            let ts = Timestamp::from_datetime(&min, prec).unwrap();
+            assert_eq!(ts.to_naive_datetime(), min.naive_utc());
+            assert_eq!(Timestamp::try_new(ts.as_raw_i64(), prec).unwrap(), ts);
+        }
+
+        let max = Nanosecond.max_datetime();
+        assert_eq!(
+            Timestamp::from_datetime(&max, Nanosecond).unwrap(),
+            Timestamp::Nanoseconds(i64::MAX)
+        );
+        let over = max + chrono::Duration::nanoseconds(1);
+        let err = Timestamp::from_datetime(&over, Nanosecond).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "timestamp 2262-04-11T23:47:16.854775808+00:00 is out of range of precision ns"
+        );
+        // the same datetime is fine in coarser precisions.
+        assert!(Timestamp::from_datetime(&over, Microsecond).is_ok());
+
+        let min = Nanosecond.min_datetime();
+        assert_eq!(
+            Timestamp::from_datetime(&min, Nanosecond).unwrap(),
+            Timestamp::Nanoseconds(i64::MIN)
+        );
+        let under = min - chrono::Duration::nanoseconds(1);
+        assert!(Timestamp::from_datetime(&under, Nanosecond).is_err());
+
+        // truncated to ticks, towards the past.
+        let dt = chrono::DateTime::parse_from_rfc3339("1969-12-31T23:59:59.9999999Z").unwrap();
+        assert_eq!(
+            Timestamp::from_datetime(&dt, Millisecond).unwrap(),
+            Timestamp::Milliseconds(-1)
+        );
+        assert_eq!(
+            Timestamp::from_datetime(&dt, Microsecond).unwrap(),
+            Timestamp::Microseconds(-1)
+        );
+
+        assert!(Timestamp::try_new(i64::MAX, Millisecond).is_err());
+        assert!(Timestamp::try_new(i64::MIN, Microsecond).is_err());
+        assert!(Timestamp::try_new(i64::MAX, Nanosecond).is_ok());
+    }
+
+    #[test]
+    fn ts_to_precision() {
+        use Precision::*;
+        let ms = Timestamp::Milliseconds(1_704_067_200_123);
+        assert_eq!(
+            ms.to_precision(Nanosecond).unwrap(),
+            Timestamp::Nanoseconds(1_704_067_200_123_000_000)
+        );
+        assert_eq!(ms.to_precision(Millisecond).unwrap(), ms);
+        assert_eq!(
+            Timestamp::Nanoseconds(1_999_999)
+                .to_precision(Millisecond)
+                .unwrap(),
+            Timestamp::Milliseconds(1)
+        );
+        assert_eq!(
+            Timestamp::Microseconds(-1)
+                .to_precision(Millisecond)
+                .unwrap(),
+            Timestamp::Milliseconds(-1)
+        );
+
+        let err = Timestamp::Microseconds(i64::MAX / 100)
+            .to_precision(Nanosecond)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "timestamp 92233720368547758us is out of range of precision ns"
+        );
+        assert!(Timestamp::Milliseconds(i64::MIN)
+            .to_precision(Microsecond)
+            .is_err());
+    }
+
     #[test]
     fn ts_debug() {
         let ts = Timestamp::new(0, Precision::Millisecond);
@@ -25,9 +25,12 @@ use serde::de::Visitor;
 /// | UInt       | 13  | INT UNSIGNED     | u32               |
 /// | UBigInt    | 14  | BIGINT UNSIGNED  | u64               |
 /// | Json       | 15  | JSON             | serde_json::Value |
+/// | VarBinary  | 16  | VARBINARY        | [u8]/Vec<u8>      |
+/// | Geometry   | 20  | GEOMETRY         | [u8]/Vec<u8>      |
 ///
 /// Note:
 /// - VarChar sql name is BINARY in v2, and VARCHAR in v3.
+/// - VarBinary and Geometry are supported since 3.1, geometries are in WKB.
 /// - Decimal/Blob/MediumBlob is not supported in 2.0/3.0 .
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, serde_repr::Serialize_repr)]
 #[repr(u8)]
@@ -71,8 +74,7 @@ pub enum Ty {
     /// 15: Json, `json` tag in sql, will be represented as [serde_json::value::Value] in Rust.
     Json = 15, // 15
 
-    /// 16, VarBinary, `varbinary` in sql, [Vec<u8>] in Rust, which is supported since TDengine 3.0.
-    #[doc(hidden)]
+    /// 16: VarBinary, `varbinary` in sql, [Vec<u8>] in Rust, which is supported since TDengine 3.1.
     VarBinary, // 16
     /// 17, Not supported now.
     #[doc(hidden)]
@@ -83,8 +85,16 @@ pub enum Ty {
     /// 19, Not supported now.
     #[doc(hidden)]
     MediumBlob, // 19
+    /// 20: Geometry, `geometry` in sql, WKB bytes as [Vec<u8>] in Rust, which is supported since
+    /// TDengine 3.1.
+    Geometry = 20,
 }
 
+/// A type id unknown to the connector, eg. of a type added by a newer server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("unknown data type {0}")]
+pub struct UnknownTypeError(pub u8);
+
 impl Default for Ty {
     fn default() -> Self {
         Ty::Null
@@ -108,21 +118,27 @@ impl<'de> serde::Deserialize<'de> for Ty {
             where
                 E: serde::de::Error,
             {
-                Ok(Ty::from_u8(v as u8))
+                u8::try_from(v)
+                    .ok()
+                    .and_then(|v| Ty::try_from_u8(v).ok())
+                    .ok_or_else(|| E::custom(format_args!("unknown data type {v}")))
             }
 
             fn visit_u8<E>(self, v: u8) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
             {
-                Ok(Ty::from_u8(v))
+                Ty::try_from_u8(v).map_err(<E as serde::de::Error>::custom)
             }
 
             fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
             {
-                Ok(Ty::from_u8(v as u8))
+                u8::try_from(v)
+                    .ok()
+                    .and_then(|v| Ty::try_from_u8(v).ok())
+                    .ok_or_else(|| E::custom(format_args!("unknown data type {v}")))
             }
 
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -181,6 +197,7 @@ impl FromStr for Ty {
             "decimal" => Ok(Ty::Decimal),
             "blob" => Ok(Ty::Blob),
             "mediumblob" => Ok(Ty::MediumBlob),
+            "geometry" => Ok(Ty::Geometry),
             _ => Err("not a valid data type string"),
         }
     }
@@ -192,10 +209,10 @@ impl Ty {
         matches!(self, Ty::Null)
     }
 
-    /// Var type is one of [Ty::VarChar], [Ty::VarBinary], [Ty::NChar].
+    /// Var type is one of [Ty::VarChar], [Ty::VarBinary], [Ty::NChar], [Ty::Geometry].
     pub const fn is_var_type(&self) -> bool {
         use Ty::*;
-        matches!(self, VarChar | VarBinary | NChar)
+        matches!(self, VarChar | VarBinary | NChar | Geometry)
     }
 
     // /// Check if the data type need quotes, means one of [Ty::VarChar], [Ty::NChar], [Ty::Json].
@@ -273,6 +290,7 @@ impl Ty {
             Decimal => "DECIMAL",
             Blob => "BLOB",
             MediumBlob => "MEDIUMBLOB",
+            Geometry => "GEOMETRY",
         }
     }
 
@@ -299,6 +317,7 @@ impl Ty {
             Decimal => "decimal",
             Blob => "blob",
             MediumBlob => "mediumblob",
+            Geometry => "geometry",
         }
     }
 
@@ -315,14 +334,21 @@ impl Ty {
         }
         return _var_str!(
             Null Bool TinyInt SmallInt Int BigInt UTinyInt USmallInt UInt UBigInt
-            Float Double VarChar NChar Timestamp Json VarBinary Decimal Blob MediumBlob
+            Float Double VarChar NChar Timestamp Json VarBinary Decimal Blob MediumBlob Geometry
         );
     }
 
+    /// The type of id `v`, fails for ids unknown to this version.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{Ty, UnknownTypeError};
+    /// assert_eq!(Ty::try_from_u8(20), Ok(Ty::Geometry));
+    /// assert_eq!(Ty::try_from_u8(42), Err(UnknownTypeError(42)));
+    /// ```
     #[inline]
-    const fn from_u8(v: u8) -> Self {
+    pub const fn try_from_u8(v: u8) -> Result<Self, UnknownTypeError> {
         use Ty::*;
-        match v {
+        Ok(match v {
             0 => Null,
             1 => Bool,
             2 => TinyInt,
@@ -343,14 +369,29 @@ impl Ty {
             17 => Decimal,
             18 => Blob,
             19 => MediumBlob,
-            _ => panic!("unknown data type"),
+            20 => Geometry,
+            _ => return Err(UnknownTypeError(v)),
+        })
+    }
+
+    #[inline]
+    fn from_u8(v: u8) -> Self {
+        match Self::try_from_u8(v) {
+            Ok(ty) => ty,
+            Err(err) => panic!("{err}"),
         }
     }
 }
+
+/// Type of a known id, see [Ty::try_from_u8] to check ids of untrusted sources.
+///
+/// # Panics
+///
+/// Panics if the id is unknown.
 impl From<u8> for Ty {
     #[inline]
     fn from(v: u8) -> Self {
-        unsafe { std::mem::transmute(v) }
+        Self::from_u8(v)
     }
 }
 
@@ -23,10 +23,11 @@ pub enum BorrowedValue<'b> {
     UInt(u32),
     UBigInt(u64), // 14
     Json(Cow<'b, [u8]>),
-    VarBinary(&'b [u8]),
+    VarBinary(Cow<'b, [u8]>),
     Decimal(Decimal),
     Blob(&'b [u8]),
     MediumBlob(&'b [u8]),
+    Geometry(Cow<'b, [u8]>), // 20
 }
 
 macro_rules! borrowed_value_to_native {
@@ -48,7 +49,7 @@ macro_rules! borrowed_value_to_native {
             BorrowedValue::UInt(v) => Some(*v as _),
             BorrowedValue::UBigInt(v) => Some(*v as _),
             BorrowedValue::Json(v) => serde_json::from_slice(&v).ok(),
-            BorrowedValue::VarBinary(_) => todo!(),
+            BorrowedValue::VarBinary(_) | BorrowedValue::Geometry(_) => None,
             BorrowedValue::Decimal(_) => todo!(),
             BorrowedValue::Blob(_) => todo!(),
             BorrowedValue::MediumBlob(_) => todo!(),
@@ -75,7 +76,7 @@ macro_rules! borrowed_value_to_float {
             BorrowedValue::UInt(v) => Some(*v as _),
             BorrowedValue::UBigInt(v) => Some(*v as _),
             BorrowedValue::Json(v) => serde_json::from_slice(&v).ok(),
-            BorrowedValue::VarBinary(_) => todo!(),
+            BorrowedValue::VarBinary(_) | BorrowedValue::Geometry(_) => None,
             BorrowedValue::Decimal(_) => todo!(),
             BorrowedValue::Blob(_) => todo!(),
             BorrowedValue::MediumBlob(_) => todo!(),
@@ -108,6 +109,7 @@ impl<'b> BorrowedValue<'b> {
             Decimal(_) => Ty::Decimal,
             Blob(_) => Ty::Blob,
             MediumBlob(_) => Ty::MediumBlob,
+            Geometry(_) => Ty::Geometry,
         }
     }
 
@@ -130,7 +132,7 @@ impl<'b> BorrowedValue<'b> {
             UInt(v) => format!("{v}"),
             UBigInt(v) => format!("{v}"),
             Json(v) => format!("\"{}\"", unsafe { std::str::from_utf8_unchecked(v) }),
-            VarBinary(_) => todo!(),
+            VarBinary(v) | Geometry(v) => format!("\"{}\"", hex(v)),
             Decimal(_) => todo!(),
             Blob(_) => todo!(),
             MediumBlob(_) => todo!(),
@@ -141,6 +143,21 @@ impl<'b> BorrowedValue<'b> {
     pub const fn is_null(&self) -> bool {
         matches!(self, BorrowedValue::Null(_))
     }
+    /// Raw bytes of strings, json and binary values, strings are not validated as UTF-8, eg.
+    /// binary payloads in `BINARY` columns. The length is of bytes in the column.
+    ///
+    /// Returns `None` for NULL and other types.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        use BorrowedValue::*;
+        match self {
+            VarChar(v) => Some(v.as_bytes()),
+            NChar(v) => Some(v.as_bytes()),
+            Json(v) | VarBinary(v) | Geometry(v) => Some(v),
+            Blob(v) | MediumBlob(v) => Some(v),
+            _ => None,
+        }
+    }
+
     /// Only VarChar, NChar, Json could be treated as [&str].
     fn strict_as_str(&self) -> &str {
         use BorrowedValue::*;
@@ -196,7 +213,8 @@ impl<'b> BorrowedValue<'b> {
                 Value::Json(serde_json::from_slice(v).expect("json should always be deserialized"))
             }
             NChar(str) => Value::NChar(str.to_string()),
-            VarBinary(_) => todo!(),
+            VarBinary(v) => Value::VarBinary(v.to_vec()),
+            Geometry(v) => Value::Geometry(v.to_vec()),
             Decimal(_) => todo!(),
             Blob(_) => todo!(),
             MediumBlob(_) => todo!(),
@@ -226,7 +244,7 @@ impl<'b> BorrowedValue<'b> {
                 serde_json::from_slice(v).expect("json should always be deserialized"),
             ),
             NChar(str) => serde_json::Value::String(str.to_string()),
-            VarBinary(_) => todo!(),
+            VarBinary(v) | Geometry(v) => serde_json::Value::String(hex(v)),
             Decimal(_) => todo!(),
             Blob(_) => todo!(),
             MediumBlob(_) => todo!(),
@@ -255,7 +273,8 @@ impl<'b> BorrowedValue<'b> {
                 Value::Json(serde_json::from_slice(&v).expect("json should always be deserialized"))
             }
             NChar(str) => Value::NChar(str.to_string()),
-            VarBinary(_) => todo!(),
+            VarBinary(v) => Value::VarBinary(v.into_owned()),
+            Geometry(v) => Value::Geometry(v.into_owned()),
             Decimal(_) => todo!(),
             Blob(_) => todo!(),
             MediumBlob(_) => todo!(),
@@ -290,7 +309,7 @@ impl<'b> BorrowedValue<'b> {
             BorrowedValue::UInt(v) => Some(*v != 0),
             BorrowedValue::UBigInt(v) => Some(*v != 0),
             BorrowedValue::Json(v) => Some(true),
-            BorrowedValue::VarBinary(_) => todo!(),
+            BorrowedValue::VarBinary(_) | BorrowedValue::Geometry(_) => None,
             BorrowedValue::Decimal(_) => todo!(),
             BorrowedValue::Blob(_) => todo!(),
             BorrowedValue::MediumBlob(_) => todo!(),
@@ -349,7 +368,7 @@ impl<'b> BorrowedValue<'b> {
             BorrowedValue::UInt(v) => Some(v.to_string().into()),
             BorrowedValue::UBigInt(v) => Some(v.to_string().into()),
             BorrowedValue::Json(v) => Some(unsafe { std::str::from_utf8_unchecked(&v) }.into()),
-            BorrowedValue::VarBinary(_) => todo!(),
+            BorrowedValue::VarBinary(v) | BorrowedValue::Geometry(v) => Some(hex(v).into()),
             BorrowedValue::Decimal(_) => todo!(),
             BorrowedValue::Blob(_) => todo!(),
             BorrowedValue::MediumBlob(_) => todo!(),
@@ -362,6 +381,163 @@ impl<'b> BorrowedValue<'b> {
             _ => panic!("Unsupported conversion from {} to timestamp", self.ty()),
         }
     }
+
+    /// Integer of the value without loss: integers, bools as 1 or 0, raw timestamps, floats
+    /// without fraction and strings of integers.
+    fn try_to_integer(&self) -> Result<Option<i128>, ()> {
+        fn float(v: f64) -> Result<Option<i128>, ()> {
+            if v.is_finite() && v.fract() == 0. && v.abs() < 2f64.powi(127) {
+                Ok(Some(v as i128))
+            } else {
+                Err(())
+            }
+        }
+        match self {
+            BorrowedValue::Null(_) => Ok(None),
+            BorrowedValue::Bool(v) => Ok(Some(*v as _)),
+            BorrowedValue::TinyInt(v) => Ok(Some(*v as _)),
+            BorrowedValue::SmallInt(v) => Ok(Some(*v as _)),
+            BorrowedValue::Int(v) => Ok(Some(*v as _)),
+            BorrowedValue::BigInt(v) => Ok(Some(*v as _)),
+            BorrowedValue::UTinyInt(v) => Ok(Some(*v as _)),
+            BorrowedValue::USmallInt(v) => Ok(Some(*v as _)),
+            BorrowedValue::UInt(v) => Ok(Some(*v as _)),
+            BorrowedValue::UBigInt(v) => Ok(Some(*v as _)),
+            BorrowedValue::Float(v) => float(*v as _),
+            BorrowedValue::Double(v) => float(*v),
+            BorrowedValue::Timestamp(v) => Ok(Some(v.as_raw_i64() as _)),
+            BorrowedValue::VarChar(s) => s.trim().parse().map(Some).map_err(|_| ()),
+            BorrowedValue::NChar(s) => s.trim().parse().map(Some).map_err(|_| ()),
+            _ => Err(()),
+        }
+    }
+
+    /// Integer of type `T` without loss, `Err` if it's not an integer or out of range.
+    pub(crate) fn try_to_int<T: TryFrom<i128>>(&self) -> Result<Option<T>, ()> {
+        self.try_to_integer()?
+            .map(T::try_from)
+            .transpose()
+            .map_err(|_| ())
+    }
+
+    /// Float of the value: floats, bools as 1 or 0, integers exactly representable and strings
+    /// of numbers.
+    pub(crate) fn try_to_f64(&self) -> Result<Option<f64>, ()> {
+        match self {
+            BorrowedValue::Null(_) => Ok(None),
+            BorrowedValue::Float(v) => Ok(Some(*v as _)),
+            BorrowedValue::Double(v) => Ok(Some(*v)),
+            BorrowedValue::VarChar(s) => s.trim().parse().map(Some).map_err(|_| ()),
+            BorrowedValue::NChar(s) => s.trim().parse().map(Some).map_err(|_| ()),
+            BorrowedValue::Timestamp(_) => Err(()),
+            _ => match self.try_to_integer()? {
+                Some(v) if (v as f64) as i128 == v => Ok(Some(v as f64)),
+                Some(_) => Err(()),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Float of the value as [BorrowedValue::try_to_f64], doubles and strings are rounded to the
+    /// nearest float but must be in range.
+    pub(crate) fn try_to_f32(&self) -> Result<Option<f32>, ()> {
+        match self {
+            BorrowedValue::Float(v) => Ok(Some(*v)),
+            BorrowedValue::Double(_) | BorrowedValue::VarChar(_) | BorrowedValue::NChar(_) => {
+                match self.try_to_f64()? {
+                    Some(v) if v.is_finite() && v.abs() > f32::MAX as f64 => Err(()),
+                    v => Ok(v.map(|v| v as f32)),
+                }
+            }
+            BorrowedValue::Timestamp(_) => Err(()),
+            _ => match self.try_to_integer()? {
+                Some(v) if (v as f32) as i128 == v => Ok(Some(v as f32)),
+                Some(_) => Err(()),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Bool of the value: bools, integers 1 or 0 and strings `true` or `false` in any case.
+    pub(crate) fn try_to_bool(&self) -> Result<Option<bool>, ()> {
+        let s = match self {
+            BorrowedValue::Null(_) => return Ok(None),
+            BorrowedValue::Bool(v) => return Ok(Some(*v)),
+            BorrowedValue::VarChar(s) => *s,
+            BorrowedValue::NChar(s) => s.as_ref(),
+            _ => {
+                return match self.try_to_integer()? {
+                    Some(0) => Ok(Some(false)),
+                    Some(1) => Ok(Some(true)),
+                    _ => Err(()),
+                }
+            }
+        };
+        match s.trim().to_ascii_lowercase().as_str() {
+            "true" => Ok(Some(true)),
+            "false" => Ok(Some(false)),
+            _ => Err(()),
+        }
+    }
+
+    /// String of the value: strings, json, and bools and numbers in their display form.
+    /// Timestamps fail as the format is not defined.
+    pub(crate) fn try_to_str(&self) -> Result<Option<Cow<'_, str>>, ()> {
+        match self {
+            BorrowedValue::Timestamp(_) => Err(()),
+            BorrowedValue::Json(v) => std::str::from_utf8(v)
+                .map(|s| Some(s.into()))
+                .map_err(|_| ()),
+            BorrowedValue::VarBinary(_)
+            | BorrowedValue::Decimal(_)
+            | BorrowedValue::Blob(_)
+            | BorrowedValue::MediumBlob(_)
+            | BorrowedValue::Geometry(_) => Err(()),
+            _ => Ok(self.to_str()),
+        }
+    }
+
+    /// Bytes of the value: binary values, and strings and json as their bytes.
+    pub(crate) fn try_to_bytes(&self) -> Result<Option<Cow<'_, [u8]>>, ()> {
+        match self {
+            BorrowedValue::VarChar(v) => Ok(Some(v.as_bytes().into())),
+            BorrowedValue::NChar(v) => Ok(Some(v.as_bytes().into())),
+            BorrowedValue::Json(v) => Ok(Some(v.as_ref().into())),
+            _ => self.try_to_geometry(),
+        }
+    }
+
+    /// Bytes of binary values only, geometries are not checked to be WKB.
+    pub(crate) fn try_to_geometry(&self) -> Result<Option<Cow<'_, [u8]>>, ()> {
+        match self {
+            BorrowedValue::Null(_) => Ok(None),
+            BorrowedValue::VarBinary(v) | BorrowedValue::Geometry(v) => Ok(Some(v.as_ref().into())),
+            BorrowedValue::Blob(v) | BorrowedValue::MediumBlob(v) => Ok(Some((*v).into())),
+            _ => Err(()),
+        }
+    }
+
+    /// Only timestamps are timestamps, raw integers have no precision.
+    pub(crate) fn try_to_timestamp(&self) -> Result<Option<Timestamp>, ()> {
+        match self {
+            BorrowedValue::Null(_) => Ok(None),
+            BorrowedValue::Timestamp(v) => Ok(Some(*v)),
+            _ => Err(()),
+        }
+    }
+
+    /// Json text of the value: json, and strings that are valid json.
+    pub(crate) fn try_to_json(&self) -> Result<Option<Cow<'_, str>>, ()> {
+        let s = match self {
+            BorrowedValue::Null(_) => return Ok(None),
+            BorrowedValue::Json(v) => std::str::from_utf8(v).map_err(|_| ())?,
+            BorrowedValue::VarChar(s) => *s,
+            BorrowedValue::NChar(s) => s.as_ref(),
+            _ => return Err(()),
+        };
+        serde_json::from_str::<serde::de::IgnoredAny>(s).map_err(|_| ())?;
+        Ok(Some(s.into()))
+    }
 }
 
 impl<'b> Display for BorrowedValue<'b> {
@@ -384,7 +560,7 @@ impl<'b> Display for BorrowedValue<'b> {
             UInt(v) => f.write_fmt(format_args!("{v}")),
             UBigInt(v) => f.write_fmt(format_args!("{v}")),
             Json(v) => f.write_fmt(format_args!("{}", v.as_ref().escape_ascii())),
-            VarBinary(_) => todo!(),
+            VarBinary(v) | Geometry(v) => f.write_str(&hex(v)),
             Decimal(_) => todo!(),
             Blob(_) => todo!(),
             MediumBlob(_) => todo!(),
@@ -417,6 +593,7 @@ pub enum Value {
     Decimal(Decimal),
     Blob(Vec<u8>),
     MediumBlob(Vec<u8>),
+    Geometry(Vec<u8>), // 20
 }
 
 impl Display for Value {
@@ -439,7 +616,7 @@ impl Display for Value {
             UInt(v) => f.write_fmt(format_args!("{v}")),
             UBigInt(v) => f.write_fmt(format_args!("{v}")),
             Json(v) => f.write_fmt(format_args!("{v}")),
-            VarBinary(_) => todo!(),
+            VarBinary(v) | Geometry(v) => f.write_str(&hex(v)),
             Decimal(_) => todo!(),
             Blob(_) => todo!(),
             MediumBlob(_) => todo!(),
@@ -472,6 +649,7 @@ impl Value {
             Decimal(_) => Ty::Decimal,
             Blob(_) => Ty::Blob,
             MediumBlob(_) => Ty::MediumBlob,
+            Geometry(_) => Ty::Geometry,
         }
     }
 
@@ -494,10 +672,11 @@ impl Value {
             Timestamp(v) => BorrowedValue::Timestamp(*v),
             Json(j) => BorrowedValue::Json(j.to_string().into_bytes().into()),
             NChar(v) => BorrowedValue::NChar(v.as_str().into()),
-            VarBinary(v) => BorrowedValue::VarBinary(v),
+            VarBinary(v) => BorrowedValue::VarBinary(v.as_slice().into()),
             Decimal(v) => BorrowedValue::Decimal(*v),
             Blob(v) => BorrowedValue::Blob(v),
             MediumBlob(v) => BorrowedValue::MediumBlob(v),
+            Geometry(v) => BorrowedValue::Geometry(v.as_slice().into()),
         }
     }
 
@@ -537,13 +716,48 @@ impl Value {
             UInt(v) => format!("{v}"),
             UBigInt(v) => format!("{v}"),
             Json(v) => format!("\"{}\"", v),
-            VarBinary(_) => todo!(),
+            VarBinary(v) | Geometry(v) => format!("\"{}\"", hex(v)),
             Decimal(_) => todo!(),
             Blob(_) => todo!(),
             MediumBlob(_) => todo!(),
         }
     }
 
+    /// The value as a sql literal, eg. for `ALTER TABLE ... SET TAG`.
+    ///
+    /// Strings and json are single quoted, with `\` and `'` escaped by a backslash, other
+    /// characters are kept as is. Binary values are hex strings like `'\x0aff'`, timestamps are
+    /// raw integers in the precision of the database.
+    ///
+    /// ```rust
+    /// # use taos_query::common::Value;
+    /// assert_eq!(Value::VarChar("it's".to_string()).to_sql_literal(), r"'it\'s'");
+    /// assert_eq!(Value::NChar("温度\\".to_string()).to_sql_literal(), r"'温度\\'");
+    /// assert_eq!(Value::VarBinary(vec![0x0a, 0xff]).to_sql_literal(), r"'\x0aff'");
+    /// ```
+    pub fn to_sql_literal(&self) -> String {
+        use Value::*;
+        fn quote(s: &str) -> String {
+            let mut quoted = String::with_capacity(s.len() + 2);
+            quoted.push('\'');
+            for c in s.chars() {
+                if c == '\'' || c == '\\' {
+                    quoted.push('\\');
+                }
+                quoted.push(c);
+            }
+            quoted.push('\'');
+            quoted
+        }
+        match self {
+            VarChar(v) | NChar(v) => quote(v),
+            Json(v) => quote(&v.to_string()),
+            VarBinary(v) | Blob(v) | MediumBlob(v) | Geometry(v) => format!("'{}'", hex(v)),
+            Decimal(v) => v.to_string(),
+            _ => self.to_sql_value(),
+        }
+    }
+
     pub fn to_string(&self) -> Result<String, Utf8Error> {
         use Value::*;
         match self {
@@ -587,7 +801,7 @@ impl Value {
             Timestamp(v) => serde_json::Value::Number(serde_json::Number::from(v.as_raw_i64())),
             Json(v) => v.clone(),
             NChar(str) => serde_json::Value::String(str.to_string()),
-            VarBinary(_) => todo!(),
+            VarBinary(v) | Geometry(v) => serde_json::Value::String(hex(v)),
             Decimal(_) => todo!(),
             Blob(_) => todo!(),
             MediumBlob(_) => todo!(),
@@ -620,10 +834,11 @@ impl<'b> PartialEq<Value> for BorrowedValue<'b> {
             (Self::UInt(l0), Value::UInt(r0)) => l0 == r0,
             (Self::UBigInt(l0), Value::UBigInt(r0)) => l0 == r0,
             (Self::Json(l0), Value::Json(r0)) => l0.as_ref() == &serde_json::to_vec(r0).unwrap(),
-            (Self::VarBinary(l0), Value::VarBinary(r0)) => l0 == r0,
+            (Self::VarBinary(l0), Value::VarBinary(r0)) => l0.as_ref() == r0.as_slice(),
             (Self::Decimal(l0), Value::Decimal(r0)) => l0 == r0,
             (Self::Blob(l0), Value::Blob(r0)) => l0 == r0,
             (Self::MediumBlob(l0), Value::MediumBlob(r0)) => l0 == r0,
+            (Self::Geometry(l0), Value::Geometry(r0)) => l0.as_ref() == r0.as_slice(),
             _ => false,
         }
     }
@@ -650,10 +865,11 @@ impl<'b> PartialEq<BorrowedValue<'b>> for Value {
             (BorrowedValue::Json(l0), Value::Json(r0)) => {
                 l0.as_ref() == &serde_json::to_vec(r0).unwrap()
             }
-            (BorrowedValue::VarBinary(l0), Value::VarBinary(r0)) => l0 == r0,
+            (BorrowedValue::VarBinary(l0), Value::VarBinary(r0)) => l0.as_ref() == r0.as_slice(),
             (BorrowedValue::Decimal(l0), Value::Decimal(r0)) => l0 == r0,
             (BorrowedValue::Blob(l0), Value::Blob(r0)) => l0 == r0,
             (BorrowedValue::MediumBlob(l0), Value::MediumBlob(r0)) => l0 == r0,
+            (BorrowedValue::Geometry(l0), Value::Geometry(r0)) => l0.as_ref() == r0.as_slice(),
             _ => false,
         }
     }
@@ -689,4 +905,15 @@ _impl_primitive_from!(u64, UBigInt);
 _impl_primitive_from!(f32, Float);
 _impl_primitive_from!(f64, Double);
 _impl_primitive_from!(Timestamp, Timestamp);
+
+/// Binary values as hex strings like `\x0aff`.
+fn hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("\\x");
+    for b in bytes {
+        let _ = write!(hex, "{b:02x}");
+    }
+    hex
+}
 mod de;
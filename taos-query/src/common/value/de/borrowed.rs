@@ -1,4 +1,5 @@
 use super::super::*;
+use super::timestamp::{timestamp_epoch, timestamp_str};
 use super::*;
 use serde::{
     de::{self, value::Error, DeserializeSeed, IntoDeserializer, Visitor},
@@ -145,21 +146,39 @@ impl<'de, 'b: 'de> serde::de::Deserializer<'de> for BorrowedValue<'de> {
                     .map_err(<Self::Error as de::Error>::custom),
             },
             Timestamp(v) => visitor.visit_i64(v.as_raw_i64()),
-            VarBinary(v) | Blob(v) | MediumBlob(v) => visitor.visit_borrowed_bytes(v),
+            VarBinary(v) | Geometry(v) => match v {
+                Cow::Borrowed(v) => visitor.visit_borrowed_bytes(v),
+                Cow::Owned(v) => visitor.visit_byte_buf(v),
+            },
+            Blob(v) | MediumBlob(v) => visitor.visit_borrowed_bytes(v),
             _ => Err(<Self::Error as de::Error>::custom(
                 "un supported type to deserialize",
             )),
         }
     }
 
-    forward_to_deserialize_any! {bool i8 u8 i16 u16 i32 u32 i64 u64 f32 f64 char}
+    forward_to_deserialize_any! {bool i8 u8 i16 u16 i32 u32 u64 f32 f64 char}
 
     forward_to_deserialize_any! {
         // unit
-        bytes byte_buf
         tuple identifier
     }
 
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self {
+            BorrowedValue::Timestamp(v) => match timestamp_epoch::<V>(v) {
+                Some(epoch) => visitor.visit_i64(epoch),
+                None => Err(<Self::Error as de::Error>::custom(format!(
+                    "timestamp {v} is out of range of the target"
+                ))),
+            },
+            value => value.deserialize_any(visitor),
+        }
+    }
+
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
@@ -191,7 +210,7 @@ impl<'de, 'b: 'de> serde::de::Deserializer<'de> for BorrowedValue<'de> {
                 Cow::Borrowed(v) => visitor.visit_borrowed_str(v),
                 Cow::Owned(v) => visitor.visit_str(&v),
             },
-            Timestamp(v) => visitor.visit_string(v.to_datetime_with_tz().to_rfc3339()),
+            Timestamp(v) => visitor.visit_string(timestamp_str::<V>(v)),
             _ => Err(<Self::Error as de::Error>::custom(
                 "unsupported type to deserialize",
             )),
@@ -256,8 +275,12 @@ impl<'de, 'b: 'de> serde::de::Deserializer<'de> for BorrowedValue<'de> {
                     .deserialize_newtype_struct(_name, visitor)
                     .map_err(<Self::Error as de::Error>::custom),
             },
-            Timestamp(v) => visitor.visit_i64(v.as_raw_i64()),
-            VarBinary(v) | Blob(v) | MediumBlob(v) => visitor.visit_borrowed_bytes(v),
+            Timestamp(v) => visitor.visit_newtype_struct(Timestamp(v)),
+            VarBinary(v) | Geometry(v) => match v {
+                Cow::Borrowed(v) => visitor.visit_borrowed_bytes(v),
+                Cow::Owned(v) => visitor.visit_byte_buf(v),
+            },
+            Blob(v) | MediumBlob(v) => visitor.visit_borrowed_bytes(v),
             _ => Err(<Self::Error as de::Error>::custom(
                 "un supported type to deserialize",
             )),
@@ -292,9 +315,10 @@ impl<'de, 'b: 'de> serde::de::Deserializer<'de> for BorrowedValue<'de> {
                 .to_vec()
                 .into_deserializer()
                 .deserialize_seq(visitor),
-            VarBinary(v) | Blob(v) | MediumBlob(v) => {
-                v.to_vec().into_deserializer().deserialize_seq(visitor)
+            VarBinary(v) | Geometry(v) => {
+                v.into_owned().into_deserializer().deserialize_seq(visitor)
             }
+            Blob(v) | MediumBlob(v) => v.to_vec().into_deserializer().deserialize_seq(visitor),
             _ => todo!(),
         }
     }
@@ -369,17 +393,35 @@ impl<'de, 'b: 'de> serde::de::Deserializer<'de> for BorrowedValue<'de> {
         }
     }
 
-    // fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    // where
-    //     V: Visitor<'de> {
-    //     todo!()
-    // }
+    // Bytes of strings are visited as they are in the column without UTF-8 validation, so binary
+    // payloads in `BINARY` columns could be deserialized, eg. with `serde_bytes`.
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        use BorrowedValue::*;
+        match self {
+            Null(_) => visitor.visit_borrowed_bytes(&[]),
+            VarChar(v) => visitor.visit_borrowed_bytes(v.as_bytes()),
+            NChar(v) => match v {
+                Cow::Borrowed(v) => visitor.visit_borrowed_bytes(v.as_bytes()),
+                Cow::Owned(v) => visitor.visit_byte_buf(v.into_bytes()),
+            },
+            Json(v) | VarBinary(v) | Geometry(v) => match v {
+                Cow::Borrowed(v) => visitor.visit_borrowed_bytes(v),
+                Cow::Owned(v) => visitor.visit_byte_buf(v),
+            },
+            Blob(v) | MediumBlob(v) => visitor.visit_borrowed_bytes(v),
+            value => value.deserialize_any(visitor),
+        }
+    }
 
-    // fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    // where
-    //     V: Visitor<'de> {
-    //     todo!()
-    // }
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
 
     fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
@@ -462,7 +504,11 @@ mod tests {
             VarChar(""), String, "".to_string()
             NChar("".into()), String, "".to_string()
             Timestamp(crate::Timestamp::Milliseconds(1)), crate::Timestamp, crate::Timestamp::Milliseconds(1)
-            VarBinary(&[0, 1,2]), Vec<u8>, vec![0, 1, 2]
+            VarBinary([0, 1, 2][..].into()), Vec<u8>, vec![0, 1, 2]
+            VarBinary(vec![0, 1, 2].into()), Vec<u8>, vec![0, 1, 2]
+            VarBinary([0, 1, 2][..].into()), bytes::Bytes, bytes::Bytes::from_static(&[0, 1, 2])
+            Geometry(vec![1, 2].into()), bytes::Bytes, bytes::Bytes::from_static(&[1, 2])
+            Geometry([][..].into()), Vec<u8>, Vec::<u8>::new()
             Blob(&[0, 1,2]), Vec<u8>, vec![0, 1, 2]
             MediumBlob(&[0, 1,2]), Vec<u8>, vec![0, 1, 2]
         );
@@ -554,7 +600,9 @@ mod tests {
 
             Null(Ty::VarChar), ""
             TinyInt(-1), "-1"
-            Timestamp(crate::Timestamp::Milliseconds(0)), "1970-01-01T08:00:00+08:00"
+            Timestamp(crate::Timestamp::Milliseconds(0)), crate::Timestamp::Milliseconds(0)
+                .to_datetime_with_tz()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, false)
             VarChar("String"), "String"
             VarChar("你好，世界"), "你好，世界"
             Json(json!("abc").to_string().into_bytes().into()), json!("abc").to_string()
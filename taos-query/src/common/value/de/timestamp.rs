@@ -0,0 +1,70 @@
+//! Timestamps deserialized in the form the target type expects.
+//!
+//! Strings are RFC3339 with the sub-second digits of the precision, and `i64`s are the raw
+//! epoch in the precision. With feature `chrono-serde`, chrono types are recognized by their
+//! visitors, so `NaiveDateTime` gets a string without the offset (in UTC) and the
+//! `chrono::serde::ts_*` helpers get the epoch in their own unit.
+
+use crate::common::{Precision, Timestamp};
+
+/// The form of a timestamp expected by a visitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "chrono-serde"), allow(dead_code))]
+enum Target {
+    /// RFC3339 string or the raw epoch.
+    Any,
+    /// Date time string without the offset.
+    NaiveDateTime,
+    /// Epoch of the units per second.
+    Epoch(i64),
+}
+
+#[cfg(feature = "chrono-serde")]
+fn target_of<V>() -> Target {
+    let name = std::any::type_name::<V>();
+    if !name.starts_with("chrono::") {
+        return Target::Any;
+    }
+    match name.rsplit("::").next().unwrap_or_default() {
+        "NaiveDateTimeVisitor" => Target::NaiveDateTime,
+        "SecondsTimestampVisitor" => Target::Epoch(1),
+        "MilliSecondsTimestampVisitor" => Target::Epoch(1_000),
+        "MicroSecondsTimestampVisitor" => Target::Epoch(1_000_000),
+        "NanoSecondsTimestampVisitor" => Target::Epoch(1_000_000_000),
+        _ => Target::Any,
+    }
+}
+
+#[cfg(not(feature = "chrono-serde"))]
+fn target_of<V>() -> Target {
+    Target::Any
+}
+
+/// The string of `ts` for visitor `V`.
+pub(super) fn timestamp_str<V>(ts: Timestamp) -> String {
+    let precision = ts.precision();
+    match target_of::<V>() {
+        Target::NaiveDateTime => {
+            let format = match precision {
+                Precision::Millisecond => "%Y-%m-%dT%H:%M:%S%.3f",
+                Precision::Microsecond => "%Y-%m-%dT%H:%M:%S%.6f",
+                Precision::Nanosecond => "%Y-%m-%dT%H:%M:%S%.9f",
+            };
+            ts.to_naive_datetime().format(format).to_string()
+        }
+        Target::Any | Target::Epoch(_) => ts
+            .to_datetime_with_tz()
+            .to_rfc3339_opts(precision.to_seconds_format(), false),
+    }
+}
+
+/// The epoch of `ts` for visitor `V`, `None` if it overflows the unit of the visitor.
+pub(super) fn timestamp_epoch<V>(ts: Timestamp) -> Option<i64> {
+    let raw = ts.as_raw_i64();
+    let native = ts.precision().ticks_per_second();
+    match target_of::<V>() {
+        Target::Epoch(units) if units >= native => raw.checked_mul(units / native),
+        Target::Epoch(units) => Some(raw.div_euclid(native / units)),
+        Target::Any | Target::NaiveDateTime => Some(raw),
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt::{Debug, Display};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use taos_error::Code;
+
+/// A notice returned by the server with a successful statement, eg. a type coercion in
+/// schemaless insert.
+///
+/// Warnings never fail the statement, they are kept in results for inspection.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Warning {
+    #[serde(default, deserialize_with = "deserialize_code")]
+    code: Code,
+    #[serde(default)]
+    message: String,
+}
+
+fn deserialize_code<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Code, D::Error> {
+    i32::deserialize(deserializer).map(Code::from)
+}
+
+impl Warning {
+    pub fn new(code: impl Into<Code>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+/// Result of a statement executed by `exec_with_warnings`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecResult {
+    affected_rows: usize,
+    warnings: Vec<Warning>,
+}
+
+impl ExecResult {
+    pub fn new(affected_rows: usize, warnings: Vec<Warning>) -> Self {
+        Self {
+            affected_rows,
+            warnings,
+        }
+    }
+
+    pub fn affected_rows(&self) -> usize {
+        self.affected_rows
+    }
+
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+}
+
+/// Callback for warnings of all statements, set by `TaosBuilder::on_warning`.
+#[derive(Clone)]
+pub struct WarningListener(Arc<dyn Fn(&Warning) + Send + Sync>);
+
+impl WarningListener {
+    pub fn new(f: impl Fn(&Warning) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Deliver warnings of a statement to the callback.
+    pub fn notify(&self, warnings: &[Warning]) {
+        for warning in warnings {
+            (self.0)(warning);
+        }
+    }
+}
+
+impl Debug for WarningListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WarningListener")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warning_de() {
+        let warning: Warning =
+            serde_json::from_str(r#"{"code":9750,"message":"column c1 coerced"}"#).unwrap();
+        assert_eq!(warning, Warning::new(0x2616, "column c1 coerced"));
+        assert_eq!(warning.to_string(), "[0x2616] column c1 coerced");
+
+        let warning: Warning = serde_json::from_str(r#"{"message":"deprecated"}"#).unwrap();
+        assert_eq!(warning.code(), 0);
+    }
+}
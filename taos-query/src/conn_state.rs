@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use taos_error::Code;
+
+/// Connection state reported to [StateListener].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ConnState {
+    /// Connection is being established.
+    #[default]
+    Connecting,
+    /// Connection is ready.
+    Connected { server_version: String },
+    /// Connection is lost and is being re-established, `attempt` starts from 1.
+    Reconnecting { attempt: u32 },
+    /// Connection is closed by client, or lost and could not be re-established.
+    Closed { reason: String },
+}
+
+impl ConnState {
+    /// Check if the connection is ready to use.
+    pub fn is_connected(&self) -> bool {
+        matches!(self, ConnState::Connected { .. })
+    }
+
+    /// Error codes that mean the connection to the server is lost.
+    pub fn is_disconnected_code(code: Code) -> bool {
+        // RPC_NETWORK_UNAVAIL, TSC_DISCONNECTED, TSC_CONN_KILLED
+        matches!(*code & 0xFFFF, 0x000B | 0x0213 | 0x0215)
+    }
+}
+
+/// Callback for connection state changes, set by `TaosBuilder::on_state_change`.
+#[derive(Clone)]
+pub struct StateListener(Arc<dyn Fn(ConnState) + Send + Sync>);
+
+impl StateListener {
+    pub fn new(f: impl Fn(ConnState) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl Debug for StateListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StateListener")
+    }
+}
+
+#[derive(Debug, Default)]
+struct Events {
+    current: ConnState,
+    pending: VecDeque<ConnState>,
+    delivering: bool,
+}
+
+/// Keeps the latest [ConnState] of a connection and delivers changes to the listener.
+///
+/// Events are delivered in the order of [ConnStateNotifier::set] calls, and the listener is never
+/// called with the internal lock held, so it's safe to call [ConnStateNotifier::state] in the
+/// listener.
+#[derive(Debug, Clone, Default)]
+pub struct ConnStateNotifier {
+    listener: Option<StateListener>,
+    events: Arc<Mutex<Events>>,
+}
+
+impl ConnStateNotifier {
+    pub fn new(listener: Option<StateListener>) -> Self {
+        Self {
+            listener,
+            events: Default::default(),
+        }
+    }
+
+    /// Snapshot of current state.
+    pub fn state(&self) -> ConnState {
+        self.events.lock().unwrap().current.clone()
+    }
+
+    /// Update current state and notify the listener.
+    ///
+    /// If another thread is delivering events, the state is queued and delivered by that thread.
+    pub fn set(&self, state: ConnState) {
+        let listener = {
+            let mut events = self.events.lock().unwrap();
+            events.current = state.clone();
+            let listener = match &self.listener {
+                Some(listener) => listener,
+                None => return,
+            };
+            events.pending.push_back(state);
+            if events.delivering {
+                return;
+            }
+            events.delivering = true;
+            listener.clone()
+        };
+        loop {
+            let next = {
+                let mut events = self.events.lock().unwrap();
+                match events.pending.pop_front() {
+                    Some(state) => state,
+                    None => {
+                        events.delivering = false;
+                        return;
+                    }
+                }
+            };
+            (listener.0)(next);
+        }
+    }
+
+    /// Set state to [ConnState::Closed] unless it's closed already.
+    pub fn close(&self, reason: impl Into<String>) {
+        if !matches!(self.state(), ConnState::Closed { .. }) {
+            self.set(ConnState::Closed {
+                reason: reason.into(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_in_order() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let cloned = events.clone();
+        let notifier = Arc::new(Mutex::new(None::<ConnStateNotifier>));
+        let inner = notifier.clone();
+        let listener = StateListener::new(move |state| {
+            // Re-entrant set is queued and delivered after the current one.
+            if state == ConnState::Connecting {
+                let notifier = inner.lock().unwrap().clone().unwrap();
+                assert_eq!(notifier.state(), ConnState::Connecting);
+                notifier.set(ConnState::Connected {
+                    server_version: "3.0".to_string(),
+                });
+            }
+            cloned.lock().unwrap().push(state);
+        });
+        let n = ConnStateNotifier::new(Some(listener));
+        notifier.lock().unwrap().replace(n.clone());
+
+        n.set(ConnState::Connecting);
+        n.close("closed by client");
+        n.close("closed twice");
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ConnState::Connecting,
+                ConnState::Connected {
+                    server_version: "3.0".to_string()
+                },
+                ConnState::Closed {
+                    reason: "closed by client".to_string()
+                }
+            ]
+        );
+        assert!(ConnState::is_disconnected_code(Code::new(0x0213)));
+        assert!(!ConnState::is_disconnected_code(Code::new(0x2603)));
+    }
+}
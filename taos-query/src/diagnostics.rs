@@ -0,0 +1,276 @@
+//! Live [RawBlock](crate::RawBlock)s and result sets for leak detection.
+//!
+//! Objects are counted with the `leak-track` feature only, otherwise tracking compiles to
+//! nothing. With the feature, live objects are counted globally and by connection, and kept in
+//! a registry with their creation time, so [live_objects] reports the oldest ones. Creation
+//! backtraces are captured if the environment variable [BACKTRACE_ENV] is `1` or `true` when
+//! the first object is tracked, symbols are resolved only when reported.
+
+/// Kinds of tracked objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectKind {
+    RawBlock,
+    ResultSet,
+}
+
+/// Environment variable to capture creation backtraces of tracked objects.
+pub const BACKTRACE_ENV: &str = "TAOS_LEAK_BACKTRACE";
+
+#[cfg(feature = "leak-track")]
+pub use track::*;
+
+#[cfg(feature = "leak-track")]
+mod track {
+    use std::backtrace::Backtrace;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use once_cell::sync::Lazy;
+
+    use super::{ObjectKind, BACKTRACE_ENV};
+
+    /// Live objects by kind.
+    #[derive(Debug, Default)]
+    struct Counters([AtomicUsize; 2]);
+
+    impl Counters {
+        fn of(&self, kind: ObjectKind) -> &AtomicUsize {
+            &self.0[kind as usize]
+        }
+
+        fn counts(&self) -> LiveCounts {
+            LiveCounts {
+                raw_blocks: self.of(ObjectKind::RawBlock).load(Ordering::Relaxed),
+                result_sets: self.of(ObjectKind::ResultSet).load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    struct Entry {
+        kind: ObjectKind,
+        created: Instant,
+        backtrace: Option<Backtrace>,
+    }
+
+    static LIVE: Counters = Counters([AtomicUsize::new(0), AtomicUsize::new(0)]);
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    /// Live objects by id, ids are increasing so the first ones are the oldest.
+    static REGISTRY: Lazy<Mutex<BTreeMap<u64, Entry>>> = Lazy::new(Default::default);
+
+    fn backtrace_enabled() -> bool {
+        static ENABLED: Lazy<bool> = Lazy::new(|| {
+            std::env::var(BACKTRACE_ENV).map_or(false, |value| {
+                value == "1" || value.eq_ignore_ascii_case("true")
+            })
+        });
+        *ENABLED
+    }
+
+    /// Numbers of live objects.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct LiveCounts {
+        pub raw_blocks: usize,
+        pub result_sets: usize,
+    }
+
+    /// A live object in [DiagnosticsReport].
+    #[derive(Debug, Clone)]
+    pub struct LiveObject {
+        pub kind: ObjectKind,
+        /// Time since it's created.
+        pub age: Duration,
+        /// Creation backtrace, `None` if backtraces are not enabled.
+        pub backtrace: Option<String>,
+    }
+
+    /// Live objects of the process, see [live_objects].
+    #[derive(Debug, Clone)]
+    pub struct DiagnosticsReport {
+        pub counts: LiveCounts,
+        /// The oldest live objects, oldest first, at most [DiagnosticsReport::OLDEST].
+        pub oldest: Vec<LiveObject>,
+    }
+
+    impl DiagnosticsReport {
+        /// Number of the oldest objects reported.
+        pub const OLDEST: usize = 10;
+    }
+
+    impl fmt::Display for DiagnosticsReport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let LiveCounts {
+                raw_blocks,
+                result_sets,
+            } = self.counts;
+            writeln!(
+                f,
+                "live raw blocks: {raw_blocks}, result sets: {result_sets}"
+            )?;
+            for object in &self.oldest {
+                writeln!(f, "{:?} created {:?} ago", object.kind, object.age)?;
+                if let Some(backtrace) = &object.backtrace {
+                    writeln!(f, "{backtrace}")?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Counts of live objects in the process.
+    pub fn live_counts() -> LiveCounts {
+        LIVE.counts()
+    }
+
+    /// Counts and the oldest live objects in the process.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// # use taos_query::diagnostics::live_objects;
+    /// let views = [ColumnView::from_ints(vec![1])];
+    /// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// let report = live_objects();
+    /// assert!(report.counts.raw_blocks >= 1);
+    /// assert!(!report.oldest.is_empty());
+    /// ```
+    pub fn live_objects() -> DiagnosticsReport {
+        let now = Instant::now();
+        let oldest = REGISTRY
+            .lock()
+            .unwrap()
+            .values()
+            .take(DiagnosticsReport::OLDEST)
+            .map(|entry| LiveObject {
+                kind: entry.kind,
+                age: now.duration_since(entry.created),
+                backtrace: entry.backtrace.as_ref().map(ToString::to_string),
+            })
+            .collect();
+        DiagnosticsReport {
+            counts: live_counts(),
+            oldest,
+        }
+    }
+
+    /// Live objects of a connection, clones share the counts.
+    #[derive(Debug, Clone, Default)]
+    pub struct Scope(Arc<Counters>);
+
+    impl Scope {
+        pub fn counts(&self) -> LiveCounts {
+            self.0.counts()
+        }
+    }
+
+    /// Registration of a live object, unregistered on drop.
+    #[derive(Debug)]
+    pub struct Tracked {
+        id: u64,
+        kind: ObjectKind,
+        scope: Option<Scope>,
+    }
+
+    impl Tracked {
+        pub fn new(kind: ObjectKind) -> Self {
+            let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            let entry = Entry {
+                kind,
+                created: Instant::now(),
+                backtrace: backtrace_enabled().then(Backtrace::force_capture),
+            };
+            REGISTRY.lock().unwrap().insert(id, entry);
+            LIVE.of(kind).fetch_add(1, Ordering::Relaxed);
+            Self {
+                id,
+                kind,
+                scope: None,
+            }
+        }
+
+        /// Count the object in `scope`, instead of the previous one if any.
+        pub fn set_scope(&mut self, scope: &Scope) {
+            if let Some(previous) = self.scope.replace(scope.clone()) {
+                previous.0.of(self.kind).fetch_sub(1, Ordering::Relaxed);
+            }
+            scope.0.of(self.kind).fetch_add(1, Ordering::Relaxed);
+        }
+
+        pub fn scope(&self) -> Option<&Scope> {
+            self.scope.as_ref()
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            REGISTRY.lock().unwrap().remove(&self.id);
+            LIVE.of(self.kind).fetch_sub(1, Ordering::Relaxed);
+            if let Some(scope) = &self.scope {
+                scope.0.of(self.kind).fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "leak-track"))]
+pub use noop::*;
+
+/// Tracking without the `leak-track` feature, it does nothing.
+#[cfg(not(feature = "leak-track"))]
+mod noop {
+    use super::ObjectKind;
+
+    #[derive(Debug, Clone, Default)]
+    pub struct Scope;
+
+    #[derive(Debug)]
+    pub struct Tracked;
+
+    impl Tracked {
+        #[inline]
+        pub fn new(_: ObjectKind) -> Self {
+            Self
+        }
+
+        #[inline]
+        pub fn set_scope(&mut self, _: &Scope) {}
+
+        #[inline]
+        pub fn scope(&self) -> Option<&Scope> {
+            None
+        }
+    }
+}
+
+#[cfg(all(test, feature = "leak-track"))]
+mod tests {
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{ColumnView, Precision};
+    use crate::RawBlock;
+
+    use super::*;
+
+    #[test]
+    fn blocks_in_scope() {
+        let block = || {
+            let views = [ColumnView::from_ints(vec![1, 2])];
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond)
+        };
+        let (conn1, conn2) = (Scope::default(), Scope::default());
+        let mut blocks: Vec<_> = (0..3).map(|_| block()).collect();
+        for block in &mut blocks {
+            block.track_in(&conn1);
+        }
+        assert_eq!(conn1.counts().raw_blocks, 3);
+        // other tests create blocks concurrently, the global count is at least the live ones.
+        assert!(live_counts().raw_blocks >= 3);
+
+        blocks[0].track_in(&conn2);
+        assert_eq!(conn1.counts().raw_blocks, 2);
+        assert_eq!(conn2.counts().raw_blocks, 1);
+        drop(blocks);
+        assert_eq!(conn1.counts(), LiveCounts::default());
+        assert_eq!(conn2.counts(), LiveCounts::default());
+    }
+}
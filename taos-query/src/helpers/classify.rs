@@ -0,0 +1,249 @@
+use crate::common::Field;
+
+use super::window::WINDOW_FIELDS;
+
+/// Kind of a SQL statement by its first keyword, see [classify_sql].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatementKind {
+    /// `select`, including ones in parentheses, eg. `(select ...) union all (select ...)`.
+    Select,
+    /// `insert`.
+    Insert,
+    /// `create`, `drop`, `alter` and `truncate`.
+    Ddl,
+    /// `delete`.
+    Delete,
+    /// `show` and `describe` (or `desc`).
+    Show,
+    /// `explain`.
+    Explain,
+    /// Others, eg. `use`, `grant`, `flush` or `kill`, and empty statements.
+    Other,
+}
+
+impl StatementKind {
+    /// Statements returning rows, ie. select, show and explain.
+    pub const fn is_query(&self) -> bool {
+        matches!(self, Self::Select | Self::Show | Self::Explain)
+    }
+}
+
+/// Classify a SQL statement by its first keyword, leading whitespaces, `-- ...` line comments,
+/// `/* ... */` block comments and opening parentheses are skipped.
+///
+/// ```rust
+/// # use taos_query::helpers::{classify_sql, StatementKind};
+/// assert_eq!(classify_sql("-- daily\n/* v2 */ SELECT * from meters"), StatementKind::Select);
+/// assert_eq!(classify_sql("insert into ? values(?, ?)"), StatementKind::Insert);
+/// assert_eq!(classify_sql("desc meters"), StatementKind::Show);
+/// ```
+pub fn classify_sql(sql: &str) -> StatementKind {
+    let keyword = first_keyword(sql);
+    let is = |word: &str| keyword.eq_ignore_ascii_case(word);
+    if is("select") {
+        StatementKind::Select
+    } else if is("insert") {
+        StatementKind::Insert
+    } else if is("create") || is("drop") || is("alter") || is("truncate") {
+        StatementKind::Ddl
+    } else if is("delete") {
+        StatementKind::Delete
+    } else if is("show") || is("describe") || is("desc") {
+        StatementKind::Show
+    } else if is("explain") {
+        StatementKind::Explain
+    } else {
+        StatementKind::Other
+    }
+}
+
+/// `sql` after leading whitespaces, comments and opening parentheses.
+pub(crate) fn skip_comments(mut sql: &str) -> &str {
+    loop {
+        sql = sql.trim_start_matches(|c: char| c.is_whitespace() || c == '(');
+        if let Some(rest) = sql.strip_prefix("--") {
+            sql = rest.split_once('\n').map_or("", |(_, rest)| rest);
+        } else if let Some(rest) = sql.strip_prefix("/*") {
+            sql = rest.split_once("*/").map_or("", |(_, rest)| rest);
+        } else {
+            break sql;
+        }
+    }
+}
+
+/// The first word of `sql`, empty if it doesn't start with a word after comments.
+fn first_keyword(sql: &str) -> &str {
+    let sql = skip_comments(sql);
+    let end = sql
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(sql.len());
+    &sql[..end]
+}
+
+/// Kind of the result of a query, see [query_kind].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    /// Rows of tables, including functions computed row by row, eg. `csum(v)`, `derivative(v)`
+    /// or `diff(v)`.
+    Projection,
+    /// Aggregations without windows, eg. `select avg(v) from meters`.
+    Aggregation,
+    /// Window queries, eg. interval, session, state or event windows.
+    Window,
+}
+
+/// Functions aggregating rows into one row.
+const AGGREGATES: &[&str] = &[
+    "apercentile",
+    "avg",
+    "count",
+    "elapsed",
+    "first",
+    "histogram",
+    "hyperloglog",
+    "irate",
+    "last",
+    "last_row",
+    "leastsquares",
+    "max",
+    "min",
+    "mode",
+    "percentile",
+    "spread",
+    "stddev",
+    "sum",
+    "twa",
+];
+
+/// Kind of a query result inferred from names of `fields`.
+///
+/// Results with window pseudo columns `_wstart`, `_wend` or `_wduration` are windows, and
+/// results with aggregate functions like `avg(v)` as column names are aggregations. Windows
+/// without pseudo columns or aggregates with aliases look like projections.
+pub fn query_kind(fields: &[Field]) -> QueryKind {
+    let names = || fields.iter().map(Field::name);
+    if names().any(|name| WINDOW_FIELDS.contains(&name)) {
+        return QueryKind::Window;
+    }
+    let is_aggregate = |name: &str| {
+        name.split_once('(').map_or(false, |(function, _)| {
+            let function = function.trim();
+            AGGREGATES
+                .iter()
+                .any(|aggregate| aggregate.eq_ignore_ascii_case(function))
+        })
+    };
+    if names().any(is_aggregate) {
+        QueryKind::Aggregation
+    } else {
+        QueryKind::Projection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Ty;
+
+    use super::*;
+
+    #[test]
+    fn classify_corpus() {
+        use StatementKind::*;
+
+        let corpus = [
+            ("select * from meters", Select),
+            (
+                "SELECT ts, current FROM power.meters WHERE voltage > 200",
+                Select,
+            ),
+            ("  \n\tselect 1", Select),
+            ("select count(*) from meters interval(10s)", Select),
+            (
+                "select _wstart, avg(v) from tb state_window(status)",
+                Select,
+            ),
+            ("select csum(v) from tb", Select),
+            ("select derivative(v, 1s, 0) from tb", Select),
+            ("(select ts from t1) union all (select ts from t2)", Select),
+            ("-- comment\nselect * from tb", Select),
+            ("/* block */ select * from tb", Select),
+            ("/* multi\nline */\n-- and line\n  Select 1", Select),
+            ("select/*+ para_tables_sort() */ * from tb", Select),
+            ("select * from tb where name = 'insert'", Select),
+            ("insert into tb values(now, 1)", Insert),
+            ("INSERT INTO ? USING st TAGS(?) VALUES(?, ?)", Insert),
+            ("-- load\ninsert into tb file '/tmp/a.csv'", Insert),
+            ("insert into tb select * from tb2", Insert),
+            ("create database db", Ddl),
+            ("create stable st (ts timestamp, v int) tags (t int)", Ddl),
+            ("CREATE TABLE tb USING st TAGS (1)", Ddl),
+            ("create topic tp as select * from st", Ddl),
+            (
+                "create stream s into out as select avg(v) from st interval(1s)",
+                Ddl,
+            ),
+            ("drop table if exists tb", Ddl),
+            ("DROP DATABASE db", Ddl),
+            ("alter table st add column c int", Ddl),
+            ("alter database db keep 3650", Ddl),
+            ("truncate table tb", Ddl),
+            ("create user u pass 'p'", Ddl),
+            ("delete from tb where ts < now - 1d", Delete),
+            ("/* cleanup */ DELETE FROM tb", Delete),
+            ("show databases", Show),
+            ("SHOW TABLES like 'd%'", Show),
+            ("show create table tb", Show),
+            ("describe tb", Show),
+            ("desc st", Show),
+            ("explain select * from tb", Explain),
+            (
+                "EXPLAIN ANALYZE VERBOSE true select count(*) from tb",
+                Explain,
+            ),
+            ("use db", Other),
+            ("grant all on db.* to u", Other),
+            ("revoke read on db.* from u", Other),
+            ("flush database db", Other),
+            ("compact database db", Other),
+            ("kill query '1:1'", Other),
+            ("reset query cache", Other),
+            ("balance vgroup", Other),
+            ("", Other),
+            ("   ", Other),
+            ("-- only a comment", Other),
+            ("/* unterminated select", Other),
+            ("selection", Other),
+        ];
+        assert_eq!(corpus.len(), 50);
+        for (sql, kind) in corpus {
+            assert_eq!(classify_sql(sql), kind, "{sql}");
+        }
+        assert!(Select.is_query() && Show.is_query() && Explain.is_query());
+        assert!(!Insert.is_query() && !Ddl.is_query() && !Other.is_query());
+    }
+
+    fn fields(names: &[&str]) -> Vec<Field> {
+        names
+            .iter()
+            .map(|name| Field::new(*name, Ty::Double, 8))
+            .collect()
+    }
+
+    #[test]
+    fn query_kind_of_fields() {
+        let kind = |names: &[&str]| query_kind(&fields(names));
+        assert_eq!(kind(&["ts", "v"]), QueryKind::Projection);
+        assert_eq!(kind(&["ts", "csum(v)"]), QueryKind::Projection);
+        assert_eq!(kind(&["ts", "derivative(v, 1s, 0)"]), QueryKind::Projection);
+        assert_eq!(kind(&["count(*)"]), QueryKind::Aggregation);
+        assert_eq!(
+            kind(&["tbname", "AVG(v)", "last_row(ts)"]),
+            QueryKind::Aggregation
+        );
+        assert_eq!(kind(&["_wstart", "count(*)"]), QueryKind::Window);
+        assert_eq!(kind(&["avg(v)", "_wend"]), QueryKind::Window);
+        // aliases are not recognized.
+        assert_eq!(kind(&["average"]), QueryKind::Projection);
+        assert_eq!(kind(&[]), QueryKind::Projection);
+    }
+}
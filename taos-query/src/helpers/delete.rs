@@ -0,0 +1,107 @@
+use crate::common::{BorrowedValue, RawBlock};
+use crate::prelude::RawError;
+
+/// Rows deleted by a `DELETE FROM` statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DeleteReport {
+    pub deleted_rows: u64,
+}
+
+impl DeleteReport {
+    /// The report of a delete, by the counts of the first row of `block` if any, or the affected
+    /// rows.
+    ///
+    /// Native clients report deleted rows as affected rows, while websocket servers of some
+    /// versions return a block of a single row with the counts instead.
+    ///
+    /// ```rust
+    /// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+    /// # use taos_query::helpers::DeleteReport;
+    /// let views = [ColumnView::from_big_ints(vec![42])];
+    /// let block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+    /// assert_eq!(DeleteReport::from_result(Some(&block), 0).deleted_rows, 42);
+    /// assert_eq!(DeleteReport::from_result(None, 7).deleted_rows, 7);
+    /// ```
+    pub fn from_result(block: Option<&RawBlock>, affected_rows: i32) -> Self {
+        let counted = block
+            .filter(|block| block.nrows() > 0)
+            .and_then(|block| (0..block.ncols()).find_map(|col| count(block.get_ref(0, col)?)));
+        Self {
+            deleted_rows: counted.unwrap_or(affected_rows.max(0) as u64),
+        }
+    }
+}
+
+fn count(value: BorrowedValue) -> Option<u64> {
+    use BorrowedValue::*;
+    match value {
+        TinyInt(v) => u64::try_from(v).ok(),
+        SmallInt(v) => u64::try_from(v).ok(),
+        Int(v) => u64::try_from(v).ok(),
+        BigInt(v) => u64::try_from(v).ok(),
+        UTinyInt(v) => Some(v as u64),
+        USmallInt(v) => Some(v as u64),
+        UInt(v) => Some(v as u64),
+        UBigInt(v) => Some(v),
+        _ => None,
+    }
+}
+
+/// Sql to delete rows of `table` matching `condition`, the table name is used as is, like
+/// `tb`, `db.tb` or `` `db`.`tb` ``.
+///
+/// An empty condition deletes the whole table, so it fails unless `allow_full_table` is set.
+///
+/// ```rust
+/// # use taos_query::helpers::delete_sql;
+/// assert_eq!(
+///     delete_sql("power.d1001", "ts < now - 30d", false).unwrap(),
+///     "delete from power.d1001 where ts < now - 30d"
+/// );
+/// assert!(delete_sql("power.d1001", " ", false).is_err());
+/// assert_eq!(delete_sql("power.d1001", "", true).unwrap(), "delete from power.d1001");
+/// ```
+pub fn delete_sql(
+    table: &str,
+    condition: &str,
+    allow_full_table: bool,
+) -> Result<String, RawError> {
+    let condition = condition.trim();
+    if !condition.is_empty() {
+        Ok(format!("delete from {table} where {condition}"))
+    } else if allow_full_table {
+        Ok(format!("delete from {table}"))
+    } else {
+        Err(RawError::from_string(format!(
+            "delete from {table} without condition deletes the whole table, \
+             allow it by WriteOptions::allow_full_table"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{ColumnView, Precision};
+
+    #[test]
+    fn delete_report_from_counts() {
+        let block = |views: &[ColumnView]| {
+            RawBlock::parse_from_raw_block(views_to_raw_block(views), Precision::Millisecond)
+        };
+        // the first integer of the first row.
+        let counts = block(&[
+            ColumnView::from_varchar(vec!["d1001"]),
+            ColumnView::from_unsigned_ints(vec![3u32]),
+            ColumnView::from_ints(vec![5]),
+        ]);
+        assert_eq!(DeleteReport::from_result(Some(&counts), 9).deleted_rows, 3);
+        // empty blocks or blocks without counts fall back to affected rows.
+        let empty = block(&[ColumnView::from_big_ints(Vec::<i64>::new())]);
+        assert_eq!(DeleteReport::from_result(Some(&empty), 9).deleted_rows, 9);
+        let names = block(&[ColumnView::from_varchar(vec!["d1001"])]);
+        assert_eq!(DeleteReport::from_result(Some(&names), 9).deleted_rows, 9);
+        assert_eq!(DeleteReport::from_result(None, -1).deleted_rows, 0);
+    }
+}
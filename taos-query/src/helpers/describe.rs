@@ -10,6 +10,7 @@ use serde::{
 };
 
 use crate::common::Ty;
+use crate::util::quote_ident;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct Described {
@@ -26,9 +27,9 @@ impl Described {
     pub fn sql_repr(&self) -> String {
         let ty = self.ty;
         if ty.is_var_type() {
-            format!("{} {}({})", self.field, ty, self.length)
+            format!("{} {}({})", quote_ident(&self.field), ty, self.length)
         } else {
-            format!("{} {}", self.field, self.ty)
+            format!("{} {}", quote_ident(&self.field), self.ty)
         }
     }
 }
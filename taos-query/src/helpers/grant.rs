@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+use crate::common::{Timestamp, Value};
+
+/// Edition name of community servers.
+pub const COMMUNITY_EDITION: &str = "community";
+
+/// License information from `show grants` or `information_schema.ins_grants`.
+///
+/// Columns differ across server versions, so only the known columns are parsed into typed
+/// fields, and every column with a limit-like value is kept in [GrantInfo::limits]. A limit of
+/// `None` means unlimited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrantInfo {
+    /// Edition name, eg. `community`, `enterprise`, `cloud`.
+    pub edition: String,
+    /// License expire time, `None` for never expire.
+    pub expire_time: Option<Timestamp>,
+    /// Server reported the license as expired.
+    pub expired: bool,
+    /// Max timeseries.
+    pub timeseries: Option<i64>,
+    /// Max dnodes.
+    pub dnodes: Option<i64>,
+    /// Max cpu cores.
+    pub cpu_cores: Option<i64>,
+    /// All limit columns by normalized column name, including the typed ones above.
+    pub limits: BTreeMap<String, Option<i64>>,
+}
+
+impl GrantInfo {
+    /// Grants of community servers, which never expire and have no limits.
+    pub fn community() -> Self {
+        Self {
+            edition: COMMUNITY_EDITION.to_string(),
+            expire_time: None,
+            expired: false,
+            timeseries: None,
+            dnodes: None,
+            cpu_cores: None,
+            limits: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_community(&self) -> bool {
+        self.edition.eq_ignore_ascii_case(COMMUNITY_EDITION)
+    }
+
+    /// Check if the license is expired or will expire in `duration` from now.
+    pub fn expires_within(&self, duration: Duration) -> bool {
+        if self.expired {
+            return true;
+        }
+        match self.expire_time {
+            Some(ts) => {
+                let duration =
+                    chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX);
+                match Local::now().checked_add_signed(duration) {
+                    Some(deadline) => ts.to_datetime_with_tz() <= deadline,
+                    None => true,
+                }
+            }
+            None => false,
+        }
+    }
+
+    /// Limit by column name, eg. `timeseries`, `vnodes`, `streams`.
+    ///
+    /// Returns `None` if the server does not report the column, `Some(None)` if it's unlimited.
+    pub fn limit(&self, name: &str) -> Option<Option<i64>> {
+        self.limits.get(&normalize(name)).copied()
+    }
+
+    /// Parse from field names and rows of a grants query, only the first row is used.
+    ///
+    /// No rows means the server has no grants, which is [GrantInfo::community].
+    pub fn from_rows<S: AsRef<str>>(names: &[S], rows: &[Vec<Value>]) -> Self {
+        let row = match rows.first() {
+            Some(row) => row,
+            None => return Self::community(),
+        };
+        let mut info = Self::community();
+        for (name, value) in names.iter().zip(row) {
+            let name = normalize(name.as_ref());
+            match name.as_str() {
+                "version" | "edition" => {
+                    if let Some(edition) = value_as_str(value).filter(|s| !s.is_empty()) {
+                        info.edition = edition.to_string();
+                    }
+                }
+                "expire_time" => info.expire_time = parse_expire_time(value),
+                "expired" => info.expired = parse_bool(value),
+                "service_time" | "state" | "mnode_id" => {}
+                _ => {
+                    if let Some(limit) = parse_limit(value) {
+                        info.limits.insert(name, limit);
+                    }
+                }
+            }
+        }
+        info.timeseries = info.limit("timeseries").flatten();
+        info.dnodes = info.limit("dnodes").flatten();
+        info.cpu_cores = info.limit("cpu_cores").flatten();
+        info
+    }
+}
+
+/// Normalize column names, eg. `expire time` in 2.x to `expire_time` in 3.x.
+pub(crate) fn normalize(name: &str) -> String {
+    name.trim().to_ascii_lowercase().replace([' ', '-'], "_")
+}
+
+pub(crate) fn value_as_str(value: &Value) -> Option<&str> {
+    match value {
+        Value::VarChar(s) | Value::NChar(s) => Some(s.trim()),
+        _ => None,
+    }
+}
+
+pub(crate) fn value_as_i64(value: &Value) -> Option<i64> {
+    match value {
+        Value::TinyInt(v) => Some(*v as _),
+        Value::SmallInt(v) => Some(*v as _),
+        Value::Int(v) => Some(*v as _),
+        Value::BigInt(v) => Some(*v),
+        Value::UTinyInt(v) => Some(*v as _),
+        Value::USmallInt(v) => Some(*v as _),
+        Value::UInt(v) => Some(*v as _),
+        Value::UBigInt(v) => i64::try_from(*v).ok(),
+        _ => None,
+    }
+}
+
+fn is_unlimited(s: &str) -> bool {
+    s.is_empty() || s.eq_ignore_ascii_case("unlimited")
+}
+
+/// Parse a limit like `1000`, `unlimited` or `used/total`.
+///
+/// Returns `None` if it's not a limit, and `Some(None)` if unlimited.
+fn parse_limit(value: &Value) -> Option<Option<i64>> {
+    if value.is_null() {
+        return Some(None);
+    }
+    if let Some(v) = value_as_i64(value) {
+        return Some(Some(v));
+    }
+    let s = value_as_str(value)?;
+    let total = s.rsplit_once('/').map_or(s, |(_, total)| total).trim();
+    if is_unlimited(total) {
+        return Some(None);
+    }
+    total.parse().ok().map(Some)
+}
+
+fn parse_expire_time(value: &Value) -> Option<Timestamp> {
+    if let Value::Timestamp(ts) = value {
+        return Some(*ts);
+    }
+    if let Some(v) = value_as_i64(value) {
+        return Some(Timestamp::Milliseconds(v));
+    }
+    let s = value_as_str(value)?;
+    if is_unlimited(s) {
+        return None;
+    }
+    let datetime = DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.timestamp_millis())
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+                .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+                .map(|dt| {
+                    Local
+                        .from_local_datetime(&dt)
+                        .earliest()
+                        .map_or(dt.and_utc().timestamp_millis(), |dt| dt.timestamp_millis())
+                })
+        })
+        .ok()?;
+    Some(Timestamp::Milliseconds(datetime))
+}
+
+fn parse_bool(value: &Value) -> bool {
+    match value {
+        Value::Bool(v) => *v,
+        v => match value_as_str(v) {
+            Some(s) => matches!(s.to_ascii_lowercase().as_str(), "true" | "yes" | "1"),
+            None => value_as_i64(v).map_or(false, |v| v != 0),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Ty;
+
+    fn s(v: &str) -> Value {
+        Value::VarChar(v.to_string())
+    }
+
+    #[test]
+    fn grant_info_v3() {
+        let names = [
+            "version",
+            "expire_time",
+            "service_time",
+            "expired",
+            "state",
+            "timeseries",
+            "dnodes",
+            "cpu_cores",
+        ];
+        let rows = vec![vec![
+            s("enterprise"),
+            s("2099-12-31 23:59:59"),
+            s("unlimited"),
+            s("false"),
+            s("normal"),
+            s("1234/100000"),
+            s("unlimited"),
+            s("2/64"),
+        ]];
+        let info = GrantInfo::from_rows(&names, &rows);
+        assert_eq!(info.edition, "enterprise");
+        assert!(!info.is_community());
+        assert!(info.expire_time.is_some());
+        assert!(!info.expired);
+        assert_eq!(info.timeseries, Some(100000));
+        assert_eq!(info.dnodes, None);
+        assert_eq!(info.cpu_cores, Some(64));
+        assert_eq!(info.limit("dnodes"), Some(None));
+        assert_eq!(info.limit("vnodes"), None);
+        assert!(!info.expires_within(Duration::from_secs(86400)));
+        assert!(info.expires_within(Duration::from_secs(200 * 365 * 86400)));
+    }
+
+    #[test]
+    fn grant_info_tolerant() {
+        // 2.x column names, typed values and unknown columns.
+        let names = [
+            "version",
+            "expire time",
+            "expired",
+            "timeseries",
+            "vnodes",
+            "extra",
+        ];
+        let rows = vec![vec![
+            s("cloud"),
+            Value::Timestamp(Timestamp::Milliseconds(0)),
+            Value::Bool(true),
+            Value::BigInt(10),
+            Value::Null(Ty::VarChar),
+            s("not a limit"),
+        ]];
+        let info = GrantInfo::from_rows(&names, &rows);
+        assert_eq!(info.edition, "cloud");
+        assert_eq!(info.expire_time, Some(Timestamp::Milliseconds(0)));
+        assert!(info.expired);
+        assert!(info.expires_within(Duration::ZERO));
+        assert_eq!(info.timeseries, Some(10));
+        assert_eq!(info.limit("vnodes"), Some(None));
+        assert_eq!(info.limit("extra"), None);
+        assert_eq!(info.dnodes, None);
+    }
+
+    #[test]
+    fn grant_info_community() {
+        let info = GrantInfo::from_rows::<&str>(&[], &[]);
+        assert_eq!(info, GrantInfo::community());
+        assert!(info.is_community());
+        assert!(!info.expires_within(Duration::MAX));
+
+        let names = ["version", "expire_time", "timeseries"];
+        let rows = vec![vec![s("community"), s("unlimited"), s("unlimited")]];
+        let info = GrantInfo::from_rows(&names, &rows);
+        assert!(info.is_community());
+        assert_eq!(info.expire_time, None);
+        assert_eq!(info.timeseries, None);
+    }
+}
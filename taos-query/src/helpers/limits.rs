@@ -0,0 +1,273 @@
+use crate::common::Value;
+
+use super::grant::{normalize, value_as_i64, value_as_str};
+
+/// Sql of the variables [ServerLimits] are read from.
+pub const SERVER_LIMITS_SQL: &str = "show local variables";
+
+/// Max bytes of a sql statement when it's not reported, the smallest of server versions.
+pub const DEFAULT_MAX_SQL_LENGTH: usize = 1024 * 1024;
+
+/// Max bytes of a websocket message when it's not configured.
+pub const DEFAULT_MAX_WS_PAYLOAD: usize = 16 * 1024 * 1024;
+
+/// Max columns of a table, including the timestamp column and tags.
+pub const DEFAULT_MAX_COLUMNS: usize = 4096;
+
+/// Effective limits of a connection, which sql batches and websocket messages are split by.
+///
+/// Limits not reported by the server keep the conservative defaults, [DEFAULT_MAX_SQL_LENGTH],
+/// [DEFAULT_MAX_WS_PAYLOAD] and [DEFAULT_MAX_COLUMNS].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerLimits {
+    /// Max bytes of a sql statement, `maxSQLLength` of the client config.
+    pub max_sql_length: usize,
+    /// Max bytes of a websocket message, eg. a raw block write.
+    pub max_ws_payload: usize,
+    /// Max columns of a table.
+    pub max_columns: usize,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        Self {
+            max_sql_length: DEFAULT_MAX_SQL_LENGTH,
+            max_ws_payload: DEFAULT_MAX_WS_PAYLOAD,
+            max_columns: DEFAULT_MAX_COLUMNS,
+        }
+    }
+}
+
+impl ServerLimits {
+    /// Parse from field names and rows of [SERVER_LIMITS_SQL], as `(name, value, ...)`.
+    ///
+    /// Unknown variables are ignored, and so are values which are not positive numbers.
+    ///
+    /// ```rust
+    /// # use taos_query::common::Value;
+    /// # use taos_query::helpers::{ServerLimits, DEFAULT_MAX_COLUMNS};
+    /// let s = |s: &str| Value::VarChar(s.to_string());
+    /// let limits = ServerLimits::from_rows(
+    ///     &["name", "value", "scope"],
+    ///     &[
+    ///         vec![s("maxSQLLength"), s("65536"), s("client")],
+    ///         vec![s("maxColumns"), s("0"), s("client")],
+    ///     ],
+    /// );
+    /// assert_eq!(limits.max_sql_length, 65536);
+    /// assert_eq!(limits.max_columns, DEFAULT_MAX_COLUMNS);
+    /// ```
+    pub fn from_rows<S: AsRef<str>>(names: &[S], rows: &[Vec<Value>]) -> Self {
+        let names: Vec<_> = names.iter().map(|name| normalize(name.as_ref())).collect();
+        let position = |name: &str| names.iter().position(|n| n == name);
+        let mut limits = Self::default();
+        let (Some(name), Some(value)) = (position("name"), position("value")) else {
+            return limits;
+        };
+        for row in rows {
+            let Some(name) = row.get(name).and_then(value_as_str) else {
+                continue;
+            };
+            let Some(value) = row.get(value).and_then(parse_limit) else {
+                continue;
+            };
+            match normalize(name).replace('_', "").as_str() {
+                "maxsqllength" => limits.max_sql_length = value,
+                "maxwspayload" | "maxwritesize" => limits.max_ws_payload = value,
+                "maxcolumns" => limits.max_columns = value,
+                _ => {}
+            }
+        }
+        limits
+    }
+}
+
+fn parse_limit(value: &Value) -> Option<usize> {
+    let n = match value_as_str(value) {
+        Some(s) => s.parse().ok()?,
+        None => value_as_i64(value)?,
+    };
+    usize::try_from(n).ok().filter(|n| *n > 0)
+}
+
+/// Statements of `prefix` followed by `values` separated by spaces, each of at most
+/// `max_sql_length` bytes, eg. `insert into tb values ` and `(now, 1)`.
+///
+/// A value too long to fit with the prefix is in a statement of its own, for the server to
+/// reject it.
+///
+/// ```rust
+/// # use taos_query::helpers::batch_values;
+/// let values = (0..4).map(|i| format!("({i})"));
+/// let batches = batch_values("insert into tb values ", values, 30);
+/// assert_eq!(
+///     batches,
+///     ["insert into tb values (0) (1)", "insert into tb values (2) (3)"]
+/// );
+/// ```
+pub fn batch_values<I, S>(prefix: &str, values: I, max_sql_length: usize) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut batches = Vec::new();
+    let mut sql = String::new();
+    for value in values {
+        let value = value.as_ref();
+        if !sql.is_empty() && sql.len() + 1 + value.len() > max_sql_length {
+            batches.push(std::mem::take(&mut sql));
+        }
+        if sql.is_empty() {
+            sql.push_str(prefix);
+        } else {
+            sql.push(' ');
+        }
+        sql.push_str(value);
+    }
+    if !sql.is_empty() {
+        batches.push(sql);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::common::{Field, Precision, RawBlock};
+    use crate::helpers::TAG_BATCH_SQL_BYTES;
+    use crate::prelude::*;
+
+    #[test]
+    fn parse_limits() {
+        let s = |s: &str| Value::VarChar(s.to_string());
+        let rows = [
+            vec![s("firstEp"), s("localhost:6030")],
+            vec![s("maxSQLLength"), s("4194304")],
+            vec![s("max_ws_payload"), Value::BigInt(1 << 20)],
+            vec![s("maxColumns"), s("-1")],
+        ];
+        let limits = ServerLimits::from_rows(&["NAME", "Value"], &rows);
+        assert_eq!(
+            limits,
+            ServerLimits {
+                max_sql_length: 4 << 20,
+                max_ws_payload: 1 << 20,
+                max_columns: DEFAULT_MAX_COLUMNS,
+            }
+        );
+        assert_eq!(
+            ServerLimits::from_rows(&["variable"], &rows),
+            ServerLimits::default()
+        );
+    }
+
+    #[test]
+    fn batches_fit_limit() {
+        let prefix = "insert into tb values ";
+        let values: Vec<_> = (0..1000).map(|i| format!("({i},{i})")).collect();
+        for max in [64, 1000, 4096] {
+            let batches = batch_values(prefix, &values, max);
+            assert!(batches.iter().all(|sql| sql.len() <= max), "{max}");
+            let rejoined: Vec<_> = batches
+                .iter()
+                .flat_map(|sql| sql[prefix.len()..].split(' '))
+                .collect();
+            assert_eq!(rejoined, values);
+        }
+        assert_eq!(
+            batch_values(prefix, ["(0)", "(1)"], 4),
+            ["insert into tb values (0)", "insert into tb values (1)"]
+        );
+        assert!(batch_values::<_, &str>(prefix, [], 64).is_empty());
+    }
+
+    /// A connection whose limits are set by the test, recording sql batches.
+    #[derive(Debug)]
+    struct Limited(ServerLimits, Mutex<Vec<usize>>);
+
+    struct Empty;
+
+    impl AsyncFetchable for Empty {
+        type Error = RawError;
+
+        fn affected_rows(&self) -> i32 {
+            0
+        }
+
+        fn precision(&self) -> Precision {
+            Precision::Millisecond
+        }
+
+        fn fields(&self) -> &[Field] {
+            &[]
+        }
+
+        fn summary(&self) -> (usize, usize) {
+            (0, 0)
+        }
+
+        fn update_summary(&mut self, _rows: usize) {}
+
+        fn fetch_raw_block(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<Option<RawBlock>, Self::Error>> {
+            std::task::Poll::Ready(Ok(None))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncQueryable for Limited {
+        type Error = RawError;
+
+        type AsyncResultSet = Empty;
+
+        async fn query<T: AsRef<str> + Send + Sync>(&self, _sql: T) -> Result<Empty, RawError> {
+            Ok(Empty)
+        }
+
+        async fn exec_many<T, I>(&self, input: I) -> Result<usize, Self::Error>
+        where
+            T: AsRef<str> + Send + Sync,
+            I::IntoIter: Send,
+            I: IntoIterator<Item = T> + Send,
+        {
+            let bytes = input.into_iter().map(|sql| sql.as_ref().len()).sum();
+            self.1.lock().unwrap().push(bytes);
+            Ok(0)
+        }
+
+        async fn write_raw_meta(&self, _: &RawMeta) -> Result<(), RawError> {
+            Ok(())
+        }
+
+        async fn write_raw_block(&self, _: &RawBlock) -> Result<(), RawError> {
+            Ok(())
+        }
+
+        async fn limits(&self) -> ServerLimits {
+            self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn tag_batches_adapt() {
+        let updates: Vec<_> = (0..2000)
+            .map(|i| (format!("d{i}"), "location".to_string(), Value::Int(i)))
+            .collect();
+        for max_sql_length in [4096, 16 * 1024, DEFAULT_MAX_SQL_LENGTH] {
+            let limits = ServerLimits {
+                max_sql_length,
+                ..Default::default()
+            };
+            let taos = Limited(limits, Mutex::default());
+            taos.set_tags_bulk(&updates).await.unwrap();
+            let batches = taos.1.into_inner().unwrap();
+            let limit = max_sql_length.min(TAG_BATCH_SQL_BYTES);
+            assert!(batches.iter().all(|bytes| *bytes <= limit), "{batches:?}");
+            assert!(batches.iter().any(|bytes| *bytes > limit / 2));
+        }
+    }
+}
@@ -1,7 +1,31 @@
+mod classify;
 mod database;
+mod delete;
 mod describe;
+mod grant;
+mod limits;
+mod page;
+mod params;
+mod schema;
+mod schema_cache;
+mod stream;
+mod tags;
+mod time_range;
 mod topic;
+mod window;
 
+pub use classify::*;
 pub use database::*;
+pub use delete::*;
 pub use describe::*;
+pub use grant::*;
+pub use limits::*;
+pub use page::*;
+pub use params::*;
+pub use schema::*;
+pub use schema_cache::*;
+pub use stream::*;
+pub use tags::*;
+pub use time_range::*;
 pub use topic::*;
+pub use window::*;
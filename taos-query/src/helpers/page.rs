@@ -0,0 +1,640 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::common::{BorrowedValue, RawBlock, Timestamp, Value};
+use crate::prelude::{AsyncFetchable, AsyncQueryable, RawError, TryStreamExt};
+
+/// How [PagedQuery] splits a query into pages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageBy {
+    /// Append `limit {page_rows} offset {offset}` to the query, which should be ordered to make
+    /// pages stable. The server scans all the skipped rows, so later pages get slower.
+    LimitOffset { page_rows: usize },
+    /// Order the query by the timestamp `column`, and query the next page after the last seen
+    /// timestamp, so each page costs the same.
+    ///
+    /// Rows of the same timestamp are told apart by `tie_breaker` like `tbname`, which must be
+    /// non-null and selected with `column`. Without it, timestamps must be unique in the result,
+    /// rows of a timestamp split by a page boundary are skipped otherwise.
+    Timestamp {
+        column: String,
+        tie_breaker: Option<String>,
+        page_rows: usize,
+    },
+}
+
+impl PageBy {
+    pub fn limit_offset(page_rows: usize) -> Self {
+        Self::LimitOffset { page_rows }
+    }
+
+    /// Pages by the unique timestamp `column`.
+    pub fn timestamp(column: impl Into<String>, page_rows: usize) -> Self {
+        Self::Timestamp {
+            column: column.into(),
+            tie_breaker: None,
+            page_rows,
+        }
+    }
+
+    /// Pages by timestamp `column`, then `tie_breaker` for rows of the same timestamp.
+    pub fn timestamp_with_tie_breaker(
+        column: impl Into<String>,
+        tie_breaker: impl Into<String>,
+        page_rows: usize,
+    ) -> Self {
+        Self::Timestamp {
+            column: column.into(),
+            tie_breaker: Some(tie_breaker.into()),
+            page_rows,
+        }
+    }
+
+    pub fn page_rows(&self) -> usize {
+        match self {
+            Self::LimitOffset { page_rows } | Self::Timestamp { page_rows, .. } => *page_rows,
+        }
+    }
+}
+
+/// Position of a [PagedQuery], serializable to resume it later by [PagedQuery::from_cursor].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum PageCursor {
+    /// No page is queried yet.
+    #[default]
+    Start,
+    /// Rows before the offset are paged, by [PageBy::LimitOffset].
+    Offset(u64),
+    /// The last paged row, by [PageBy::Timestamp].
+    After { ts: Timestamp, tie: Option<Value> },
+    /// All rows are paged.
+    End,
+}
+
+/// Keywords ending the `where` clause of a select.
+const CLAUSES_AFTER_WHERE: &[&str] = &[
+    "partition",
+    "group",
+    "interval",
+    "session",
+    "state_window",
+    "event_window",
+    "count_window",
+    "sliding",
+    "fill",
+    "having",
+    "slimit",
+    "soffset",
+];
+
+/// Byte ranges of words of `sql` out of parentheses, quoted strings, identifiers and comments,
+/// and if `sql` ends in a line comment.
+fn top_level_words(sql: &str) -> (Vec<(usize, usize)>, bool) {
+    enum State {
+        Sql,
+        Quoted(u8),
+        Escaped(u8),
+        LineComment,
+        BlockComment,
+    }
+    let bytes = sql.as_bytes();
+    let is_word = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
+    let mut words = Vec::new();
+    let mut state = State::Sql;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        state = match state {
+            State::Sql => match c {
+                b'\'' | b'"' | b'`' => State::Quoted(c),
+                b'-' if next == Some(b'-') => {
+                    i += 1;
+                    State::LineComment
+                }
+                b'/' if next == Some(b'*') => {
+                    i += 1;
+                    State::BlockComment
+                }
+                b'(' => {
+                    depth += 1;
+                    State::Sql
+                }
+                b')' => {
+                    depth = depth.saturating_sub(1);
+                    State::Sql
+                }
+                c if is_word(c) => {
+                    let start = i;
+                    while i + 1 < bytes.len() && is_word(bytes[i + 1]) {
+                        i += 1;
+                    }
+                    if depth == 0 {
+                        words.push((start, i + 1));
+                    }
+                    State::Sql
+                }
+                _ => State::Sql,
+            },
+            State::Quoted(q) if c == q => State::Sql,
+            State::Quoted(q) if q != b'`' && c == b'\\' => State::Escaped(q),
+            State::Quoted(q) | State::Escaped(q) => State::Quoted(q),
+            State::LineComment if c == b'\n' => State::Sql,
+            State::LineComment => State::LineComment,
+            State::BlockComment if c == b'*' && next == Some(b'/') => {
+                i += 1;
+                State::Sql
+            }
+            State::BlockComment => State::BlockComment,
+        };
+        i += 1;
+    }
+    (words, matches!(state, State::LineComment))
+}
+
+/// Trim trailing whitespaces but the newline ending a line comment.
+fn trim_end_in_line(sql: &str) -> &str {
+    sql.trim_end_matches(|c: char| c.is_whitespace() && c != '\n')
+}
+
+/// Sql of the page at `cursor` of the select `sql`, see [PagedQuery].
+///
+/// For [PageBy::Timestamp], the cursor condition is added to the `where` clause and the query
+/// is ordered by the timestamp and the tie-breaker, so `sql` must not have `order by` or `limit`
+/// of its own. For [PageBy::LimitOffset], `sql` must not have `limit`.
+///
+/// ```rust
+/// # use taos_query::common::{Timestamp, Value};
+/// # use taos_query::helpers::{page_sql, PageBy, PageCursor};
+/// let by = PageBy::timestamp_with_tie_breaker("ts", "tbname", 100);
+/// let cursor = PageCursor::After {
+///     ts: Timestamp::Milliseconds(1700000000000),
+///     tie: Some(Value::VarChar("d1001".to_string())),
+/// };
+/// assert_eq!(
+///     page_sql("select * from meters where v > 1 partition by tbname", &by, &cursor).unwrap(),
+///     "select * from meters where (v > 1) and (ts > 1700000000000 or \
+///      (ts = 1700000000000 and tbname > 'd1001')) partition by tbname \
+///      order by ts, tbname limit 100"
+/// );
+///
+/// let by = PageBy::limit_offset(100);
+/// assert_eq!(
+///     page_sql("select * from meters order by ts;", &by, &PageCursor::Offset(200)).unwrap(),
+///     "select * from meters order by ts limit 100 offset 200"
+/// );
+/// ```
+pub fn page_sql(sql: &str, by: &PageBy, cursor: &PageCursor) -> Result<String, RawError> {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let (words, in_comment) = top_level_words(sql);
+    // Clauses appended to a trailing line comment would be commented out.
+    let sql = &if in_comment {
+        format!("{sql}\n")
+    } else {
+        sql.to_string()
+    };
+    let find = |keyword: &str| {
+        words
+            .iter()
+            .find(|(start, end)| sql[*start..*end].eq_ignore_ascii_case(keyword))
+            .copied()
+    };
+    let reject = |keyword: &str| match find(keyword) {
+        Some(_) => Err(RawError::from_string(format!(
+            "paged query must not have `{keyword}` of its own: {sql}"
+        ))),
+        None => Ok(()),
+    };
+    reject("limit")?;
+    let offset = match cursor {
+        PageCursor::Offset(offset) => *offset,
+        _ => 0,
+    };
+    let (column, tie_breaker, page_rows) = match by {
+        PageBy::LimitOffset { page_rows } => {
+            return Ok(if offset > 0 {
+                format!("{sql} limit {page_rows} offset {offset}")
+            } else {
+                format!("{sql} limit {page_rows}")
+            });
+        }
+        PageBy::Timestamp {
+            column,
+            tie_breaker,
+            page_rows,
+        } => (column, tie_breaker.as_deref(), page_rows),
+    };
+    reject("order")?;
+    reject("union")?;
+
+    let order = match tie_breaker {
+        Some(tie_breaker) => format!("order by {column}, {tie_breaker} limit {page_rows}"),
+        None => format!("order by {column} limit {page_rows}"),
+    };
+    let condition = match cursor {
+        PageCursor::After { ts, tie } => {
+            let ts = ts.as_raw_i64();
+            match (tie_breaker, tie) {
+                (Some(tie_breaker), Some(tie)) => format!(
+                    "{column} > {ts} or ({column} = {ts} and {tie_breaker} > {})",
+                    tie.to_sql_literal()
+                ),
+                _ => format!("{column} > {ts}"),
+            }
+        }
+        _ => return Ok(format!("{sql} {order}")),
+    };
+
+    let from = find("from").map_or(0, |(_, end)| end);
+    let clause_end = words
+        .iter()
+        .filter(|(start, _)| *start >= from)
+        .find(|(start, end)| {
+            CLAUSES_AFTER_WHERE
+                .iter()
+                .any(|keyword| sql[*start..*end].eq_ignore_ascii_case(keyword))
+        })
+        .map_or(sql.len(), |(start, _)| *start);
+    let (head, tail) = sql.split_at(clause_end);
+    let (head, tail) = (trim_end_in_line(head), tail.trim_start());
+    let filtered = match find("where").filter(|(start, _)| *start >= from) {
+        Some((_, end)) => {
+            let existing = trim_end_in_line(head[end..].trim_start());
+            format!("{} ({existing}) and ({condition})", &head[..end])
+        }
+        None => format!("{head} where {condition}"),
+    };
+    Ok(if tail.is_empty() {
+        format!("{filtered} {order}")
+    } else {
+        format!("{filtered} {tail} {order}")
+    })
+}
+
+/// Query of large results page by page, see [PageBy] for the paging strategies.
+///
+/// ```rust,no_run
+/// # use taos_query::prelude::*;
+/// # use taos_query::helpers::{PageBy, PageCursor, PagedQuery};
+/// # async fn page<Q: AsyncQueryable>(taos: &Q) -> Result<(), Q::Error> {
+/// let by = PageBy::timestamp_with_tie_breaker("ts", "tbname", 1000);
+/// let mut pages = PagedQuery::new(taos, "select ts, tbname, v from meters", by.clone());
+/// let first = pages.next_page().await?;
+/// // Persist the cursor, and resume from it in another request.
+/// let cursor = serde_json::to_string(pages.cursor()).unwrap();
+///
+/// let cursor: PageCursor = serde_json::from_str(&cursor).unwrap();
+/// let mut pages = PagedQuery::from_cursor(taos, "select ts, tbname, v from meters", by, cursor);
+/// while let Some(blocks) = pages.next_page().await? {
+///     // ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PagedQuery<'q, Q> {
+    taos: &'q Q,
+    sql: String,
+    by: PageBy,
+    cursor: PageCursor,
+}
+
+impl<'q, Q: AsyncQueryable> PagedQuery<'q, Q> {
+    /// Page the select `sql` from the start.
+    pub fn new(taos: &'q Q, sql: impl Into<String>, by: PageBy) -> Self {
+        Self::from_cursor(taos, sql, by, PageCursor::Start)
+    }
+
+    /// Resume paging at `cursor` got by [PagedQuery::cursor] of the same `sql` and `by`.
+    pub fn from_cursor(
+        taos: &'q Q,
+        sql: impl Into<String>,
+        by: PageBy,
+        cursor: PageCursor,
+    ) -> Self {
+        Self {
+            taos,
+            sql: sql.into(),
+            by,
+            cursor,
+        }
+    }
+
+    /// Position after the last page.
+    pub fn cursor(&self) -> &PageCursor {
+        &self.cursor
+    }
+
+    /// Blocks of the next page, `None` if all rows are paged.
+    ///
+    /// A page of fewer rows than [PageBy::page_rows] is the last one, paging ends without
+    /// querying again.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<RawBlock>>, Q::Error> {
+        if self.cursor == PageCursor::End {
+            return Ok(None);
+        }
+        let sql = page_sql(&self.sql, &self.by, &self.cursor)
+            .map_err(<Q::AsyncResultSet as AsyncFetchable>::Error::from)?;
+        let mut rs = self.taos.query(sql).await?;
+        let (column, tie_breaker) = match &self.by {
+            PageBy::LimitOffset { .. } => (None, None),
+            PageBy::Timestamp {
+                column,
+                tie_breaker,
+                ..
+            } => (
+                Some(field_index::<Q>(&rs, column)?),
+                tie_breaker
+                    .as_deref()
+                    .map(|tie_breaker| field_index::<Q>(&rs, tie_breaker))
+                    .transpose()?,
+            ),
+        };
+        let blocks: Vec<RawBlock> = rs
+            .blocks()
+            .try_filter(|block| futures::future::ready(block.nrows() > 0))
+            .try_collect()
+            .await?;
+        let rows: usize = blocks.iter().map(RawBlock::nrows).sum();
+        if rows == 0 {
+            self.cursor = PageCursor::End;
+            return Ok(None);
+        }
+
+        self.cursor = if rows < self.by.page_rows() {
+            PageCursor::End
+        } else if let Some(column) = column {
+            let last = blocks.last().unwrap();
+            let row = last.nrows() - 1;
+            let ts = match last.get_ref(row, column) {
+                Some(BorrowedValue::Timestamp(ts)) => ts,
+                value => {
+                    return Err(not_pageable::<Q>(format!(
+                        "page column should be a non-null timestamp, but it's {value:?}"
+                    )))
+                }
+            };
+            let tie = match tie_breaker {
+                Some(tie_breaker) => match last.get_ref(row, tie_breaker) {
+                    Some(value) if !value.is_null() => Some(value.to_value()),
+                    _ => return Err(not_pageable::<Q>("tie-breaker should be non-null")),
+                },
+                None => None,
+            };
+            PageCursor::After { ts, tie }
+        } else {
+            let offset = match self.cursor {
+                PageCursor::Offset(offset) => offset,
+                _ => 0,
+            };
+            PageCursor::Offset(offset + rows as u64)
+        };
+        Ok(Some(blocks))
+    }
+
+    /// Rows of the next page deserialized into `T`, see [PagedQuery::next_page].
+    pub async fn next_page_as<T: DeserializeOwned>(&mut self) -> Result<Option<Vec<T>>, Q::Error> {
+        let Some(blocks) = self.next_page().await? else {
+            return Ok(None);
+        };
+        let mut records = Vec::new();
+        for block in &blocks {
+            for record in block.deserialize::<T>() {
+                records.push(record.map_err(|err| not_pageable::<Q>(err.to_string()))?);
+            }
+        }
+        Ok(Some(records))
+    }
+}
+
+/// Error of a query that can't be paged, in the error type of the queryable.
+fn not_pageable<Q: AsyncQueryable>(message: impl Into<String>) -> Q::Error {
+    <Q::AsyncResultSet as AsyncFetchable>::Error::from(RawError::from_string(message.into())).into()
+}
+
+fn field_index<Q: AsyncQueryable>(rs: &Q::AsyncResultSet, name: &str) -> Result<usize, Q::Error> {
+    let name = name.trim_matches('`');
+    rs.fields()
+        .iter()
+        .position(|field| field.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| not_pageable::<Q>(format!("page column `{name}` should be selected")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+
+    use super::*;
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{ColumnView, Field, Precision, RawMeta};
+
+    #[test]
+    fn page_sql_rewrite() {
+        let by = PageBy::timestamp("ts", 10);
+        let after = PageCursor::After {
+            ts: Timestamp::Milliseconds(5),
+            tie: None,
+        };
+        let sql = |sql: &str, cursor: &PageCursor| page_sql(sql, &by, cursor).unwrap();
+
+        assert_eq!(
+            sql("select * from tb", &PageCursor::Start),
+            "select * from tb order by ts limit 10"
+        );
+        assert_eq!(
+            sql("select * from tb ;\n", &after),
+            "select * from tb where ts > 5 order by ts limit 10"
+        );
+        assert_eq!(
+            sql("select * from tb where a = 1 or b = 2", &after),
+            "select * from tb where (a = 1 or b = 2) and (ts > 5) order by ts limit 10"
+        );
+        // Keywords in subqueries, strings and comments are not clauses of the query.
+        assert_eq!(
+            sql(
+                "select * from (select * from tb where v > 0 limit 9) where s = ' limit' \
+                 -- order\n partition by tbname slimit 2",
+                &after
+            ),
+            "select * from (select * from tb where v > 0 limit 9) where (s = ' limit' \
+             -- order\n) and (ts > 5) partition by tbname slimit 2 order by ts limit 10"
+        );
+        assert_eq!(
+            sql("select * from tb -- all", &after),
+            "select * from tb -- all\n where ts > 5 order by ts limit 10"
+        );
+        assert_eq!(
+            sql("SELECT * FROM tb INTERVAL(1s)", &after),
+            "SELECT * FROM tb where ts > 5 INTERVAL(1s) order by ts limit 10"
+        );
+
+        assert!(page_sql("select * from tb order by ts", &by, &after).is_err());
+        assert!(page_sql("select * from tb limit 1", &by, &after).is_err());
+        let by = PageBy::limit_offset(10);
+        assert!(page_sql("select * from tb LIMIT 1", &by, &PageCursor::Start).is_err());
+        assert_eq!(
+            page_sql("select * from tb order by ts", &by, &PageCursor::Start).unwrap(),
+            "select * from tb order by ts limit 10"
+        );
+    }
+
+    /// Rows `(ts, tb)` of a super table ordered by both, answering sqls of [page_sql] only.
+    struct Meters(Vec<(i64, String)>);
+
+    struct Page {
+        fields: Vec<Field>,
+        block: Option<RawBlock>,
+    }
+
+    impl AsyncFetchable for Page {
+        type Error = RawError;
+
+        fn affected_rows(&self) -> i32 {
+            0
+        }
+
+        fn precision(&self) -> Precision {
+            Precision::Millisecond
+        }
+
+        fn fields(&self) -> &[Field] {
+            &self.fields
+        }
+
+        fn summary(&self) -> (usize, usize) {
+            (0, 0)
+        }
+
+        fn update_summary(&mut self, _rows: usize) {}
+
+        fn fetch_raw_block(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<Option<RawBlock>, Self::Error>> {
+            Poll::Ready(Ok(self.block.take()))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncQueryable for Meters {
+        type Error = RawError;
+
+        type AsyncResultSet = Page;
+
+        async fn query<T: AsRef<str> + Send + Sync>(&self, sql: T) -> Result<Page, RawError> {
+            let (sql, limit) = sql.as_ref().rsplit_once(" limit ").unwrap();
+            let (limit, offset) = limit.split_once(" offset ").unwrap_or((limit, "0"));
+            let number = |s: &str| {
+                s.split(|c: char| !c.is_ascii_digit())
+                    .next()
+                    .unwrap()
+                    .parse::<i64>()
+                    .unwrap()
+            };
+            let after = sql.split_once("ts > ").map(|(_, rest)| number(rest));
+            let tie = sql
+                .split_once("tb > '")
+                .map(|(_, rest)| rest.split('\'').next().unwrap().to_string());
+            let (ts, tb): (Vec<_>, Vec<_>) = self
+                .0
+                .iter()
+                .filter(|(ts, tb)| match (after, &tie) {
+                    (Some(after), Some(tie)) => *ts > after || (*ts == after && tb > tie),
+                    (Some(after), None) => *ts > after,
+                    _ => true,
+                })
+                .skip(number(offset) as usize)
+                .take(number(limit) as usize)
+                .cloned()
+                .unzip();
+            let views = [
+                ColumnView::from_millis_timestamp(ts),
+                ColumnView::from_varchar::<String, _, _, _>(tb),
+            ];
+            let mut block =
+                RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+            block.with_field_names(["ts", "tb"]);
+            Ok(Page {
+                fields: block.fields(),
+                block: Some(block),
+            })
+        }
+
+        async fn exec<T: AsRef<str> + Send + Sync>(&self, _sql: T) -> Result<usize, RawError> {
+            Ok(0)
+        }
+
+        async fn write_raw_meta(&self, _: &RawMeta) -> Result<(), RawError> {
+            Ok(())
+        }
+
+        async fn write_raw_block(&self, _: &RawBlock) -> Result<(), RawError> {
+            Ok(())
+        }
+    }
+
+    async fn collect(pages: &mut PagedQuery<'_, Meters>) -> Vec<(i64, String)> {
+        let mut rows = Vec::new();
+        while let Some(page) = pages.next_page_as::<(i64, String)>().await.unwrap() {
+            assert!(page.len() <= pages.by.page_rows());
+            rows.extend(page);
+        }
+        rows
+    }
+
+    #[tokio::test]
+    async fn page_through_rows() {
+        // 3 sub-tables share each timestamp, so pages of 1000 rows split timestamps.
+        let rows: Vec<_> = (0..100_000)
+            .map(|i: i64| (i / 3, format!("d{}", i % 3)))
+            .collect();
+        let meters = Meters(rows.clone());
+
+        let mut pages = meters.query_iter_pages("select ts, tb from meters order by ts, tb", 1000);
+        assert_eq!(collect(&mut pages).await, rows);
+        assert_eq!(pages.cursor(), &PageCursor::End);
+        assert!(pages.next_page().await.unwrap().is_none());
+
+        let by = PageBy::timestamp_with_tie_breaker("ts", "tb", 1000);
+        let mut pages = PagedQuery::new(&meters, "select ts, tb from meters", by.clone());
+        assert_eq!(collect(&mut pages).await, rows);
+
+        // Resume from a persisted cursor.
+        let mut pages = PagedQuery::new(&meters, "select ts, tb from meters", by.clone());
+        let mut paged = Vec::new();
+        for _ in 0..10 {
+            paged.extend(
+                pages
+                    .next_page_as::<(i64, String)>()
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+        let cursor = serde_json::to_string(pages.cursor()).unwrap();
+        let cursor: PageCursor = serde_json::from_str(&cursor).unwrap();
+        assert_eq!(
+            cursor,
+            PageCursor::After {
+                ts: Timestamp::Milliseconds(3333),
+                tie: Some(Value::VarChar("d0".to_string()))
+            }
+        );
+        let mut pages = PagedQuery::from_cursor(&meters, "select ts, tb from meters", by, cursor);
+        paged.extend(collect(&mut pages).await);
+        assert_eq!(paged, rows);
+
+        // Unique timestamps need no tie-breaker.
+        let rows: Vec<_> = (0..100_000).map(|i| (i, "d0".to_string())).collect();
+        let meters = Meters(rows.clone());
+        let by = PageBy::timestamp("ts", 999);
+        let mut pages = PagedQuery::new(&meters, "select ts, tb from meters", by);
+        assert_eq!(collect(&mut pages).await, rows);
+
+        let by = PageBy::timestamp_with_tie_breaker("ts", "v", 10);
+        let mut pages = PagedQuery::new(&meters, "select ts, tb from meters", by);
+        let err = pages.next_page().await.unwrap_err();
+        assert!(err.to_string().contains("`v`"), "{err}");
+    }
+}
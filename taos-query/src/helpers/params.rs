@@ -0,0 +1,318 @@
+use crate::common::{BorrowedValue, Precision, PrecisionError, Timestamp, Value};
+use crate::prelude::RawError;
+
+/// Values to substitute `?` placeholders of sql client-side, see [bind_sql_params].
+///
+/// Implemented for [Value] and the types [crate::common::ColumnView] is built from, and
+/// `Option`s of them for NULL.
+pub trait ToSqlLiteral {
+    /// The value as a sql literal like [Value::to_sql_literal], timestamps are epoch integers in
+    /// `precision`, or in their own precision if `None`.
+    fn to_sql_literal_in(&self, precision: Option<Precision>) -> Result<String, PrecisionError>;
+}
+
+fn timestamp_literal(
+    ts: Timestamp,
+    precision: Option<Precision>,
+) -> Result<String, PrecisionError> {
+    let ts = match precision {
+        Some(precision) => ts.to_precision(precision)?,
+        None => ts,
+    };
+    Ok(ts.as_raw_i64().to_string())
+}
+
+impl ToSqlLiteral for Value {
+    fn to_sql_literal_in(&self, precision: Option<Precision>) -> Result<String, PrecisionError> {
+        match self {
+            Value::Timestamp(ts) => timestamp_literal(*ts, precision),
+            value => Ok(value.to_sql_literal()),
+        }
+    }
+}
+
+impl<'b> ToSqlLiteral for BorrowedValue<'b> {
+    fn to_sql_literal_in(&self, precision: Option<Precision>) -> Result<String, PrecisionError> {
+        self.to_value().to_sql_literal_in(precision)
+    }
+}
+
+impl ToSqlLiteral for Timestamp {
+    fn to_sql_literal_in(&self, precision: Option<Precision>) -> Result<String, PrecisionError> {
+        timestamp_literal(*self, precision)
+    }
+}
+
+macro_rules! _impl_to_sql_literal {
+    ($($ty:ty => $v:ident $value:expr),+ $(,)?) => {
+        $(
+            impl ToSqlLiteral for $ty {
+                fn to_sql_literal_in(&self, _: Option<Precision>) -> Result<String, PrecisionError> {
+                    let $v = self;
+                    Ok($value.to_sql_literal())
+                }
+            }
+        )+
+    };
+}
+
+_impl_to_sql_literal!(
+    bool => v Value::Bool(*v),
+    i8 => v Value::TinyInt(*v),
+    i16 => v Value::SmallInt(*v),
+    i32 => v Value::Int(*v),
+    i64 => v Value::BigInt(*v),
+    u8 => v Value::UTinyInt(*v),
+    u16 => v Value::USmallInt(*v),
+    u32 => v Value::UInt(*v),
+    u64 => v Value::UBigInt(*v),
+    f32 => v Value::Float(*v),
+    f64 => v Value::Double(*v),
+    str => v Value::VarChar(v.to_string()),
+    String => v Value::VarChar(v.clone()),
+    [u8] => v Value::VarBinary(v.to_vec()),
+    Vec<u8> => v Value::VarBinary(v.clone()),
+    serde_json::Value => v Value::Json(v.clone()),
+);
+
+impl<T: ToSqlLiteral> ToSqlLiteral for Option<T> {
+    fn to_sql_literal_in(&self, precision: Option<Precision>) -> Result<String, PrecisionError> {
+        match self {
+            Some(v) => v.to_sql_literal_in(precision),
+            None => Ok("NULL".to_string()),
+        }
+    }
+}
+
+impl<T: ToSqlLiteral + ?Sized> ToSqlLiteral for &T {
+    fn to_sql_literal_in(&self, precision: Option<Precision>) -> Result<String, PrecisionError> {
+        (**self).to_sql_literal_in(precision)
+    }
+}
+
+/// Byte offsets of `?` placeholders in `sql`, those in quoted strings and identifiers, `-- ...`
+/// line comments and `/* ... */` block comments are not.
+fn placeholders(sql: &str) -> Vec<usize> {
+    enum State {
+        Sql,
+        Quoted(u8),
+        Escaped(u8),
+        LineComment,
+        BlockComment,
+    }
+    let bytes = sql.as_bytes();
+    let mut offsets = Vec::new();
+    let mut state = State::Sql;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        state = match state {
+            State::Sql => match c {
+                b'\'' | b'"' | b'`' => State::Quoted(c),
+                b'-' if next == Some(b'-') => {
+                    i += 1;
+                    State::LineComment
+                }
+                b'/' if next == Some(b'*') => {
+                    i += 1;
+                    State::BlockComment
+                }
+                b'?' => {
+                    offsets.push(i);
+                    State::Sql
+                }
+                _ => State::Sql,
+            },
+            State::Quoted(q) if c == q => State::Sql,
+            State::Quoted(q) if q != b'`' && c == b'\\' => State::Escaped(q),
+            State::Quoted(q) | State::Escaped(q) => State::Quoted(q),
+            State::LineComment if c == b'\n' => State::Sql,
+            State::LineComment => State::LineComment,
+            State::BlockComment if c == b'*' && next == Some(b'/') => {
+                i += 1;
+                State::Sql
+            }
+            State::BlockComment => State::BlockComment,
+        };
+        i += 1;
+    }
+    offsets
+}
+
+/// Substitute `?` placeholders of `sql` by `params` as sql literals, for statements the stmt
+/// protocol does not support, like `CREATE`, `ALTER` and topic ddl.
+///
+/// Strings are single quoted with `'` and `\` escaped, NULL values are `NULL`, binary values
+/// are hex strings, see [Value::to_sql_literal]. Timestamps are epoch integers in `precision`,
+/// which should be the precision of the database as the server reads integers in it, or in
+/// their own precision if `None`. `?` in quoted strings, identifiers and comments are kept as
+/// is.
+///
+/// It fails if the number of placeholders and params mismatch, or a timestamp is out of range
+/// of `precision`.
+///
+/// ```rust
+/// # use taos_query::common::{Precision, Timestamp, Value};
+/// # use taos_query::helpers::{bind_sql_params, ToSqlLiteral};
+/// let sql = bind_sql_params(
+///     "insert into `t?` values(?, ?, '?')",
+///     &[Value::Timestamp(Timestamp::Milliseconds(1)), Value::VarChar("it's".to_string())],
+///     Some(Precision::Microsecond),
+/// )
+/// .unwrap();
+/// assert_eq!(sql, r"insert into `t?` values(1000, 'it\'s', '?')");
+///
+/// let params: [&dyn ToSqlLiteral; 2] = [&1, &None::<&str>];
+/// assert_eq!(bind_sql_params("select ?, ?", &params, None).unwrap(), "select 1, NULL");
+/// assert!(bind_sql_params("select ?", &[1, 2], None).is_err());
+/// ```
+pub fn bind_sql_params<P: ToSqlLiteral>(
+    sql: &str,
+    params: &[P],
+    precision: Option<Precision>,
+) -> Result<String, RawError> {
+    let offsets = placeholders(sql);
+    if offsets.len() != params.len() {
+        return Err(RawError::from_string(format!(
+            "sql has {} placeholders but {} params are given",
+            offsets.len(),
+            params.len()
+        )));
+    }
+    let mut bound = String::with_capacity(sql.len() + params.len() * 8);
+    let mut last = 0;
+    for (i, (offset, param)) in offsets.into_iter().zip(params).enumerate() {
+        let literal = param
+            .to_sql_literal_in(precision)
+            .map_err(|err| RawError::from_string(format!("param {i}: {err}")))?;
+        bound.push_str(&sql[last..offset]);
+        bound.push_str(&literal);
+        last = offset + 1;
+    }
+    bound.push_str(&sql[last..]);
+    Ok(bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_out_of_quotes() {
+        assert_eq!(placeholders("?, '?', \"?\", `?`, ?"), [0, 18]);
+        assert_eq!(placeholders(r"'a\'?' ?"), [7]);
+        assert_eq!(placeholders(r#""a\"?" ?"#), [7]);
+        assert_eq!(placeholders(r"`a\`?`"), [4]);
+        assert!(placeholders("select 1").is_empty());
+    }
+
+    #[test]
+    fn placeholders_out_of_comments() {
+        assert_eq!(placeholders("-- ?\n?"), [5]);
+        assert_eq!(placeholders("/* ? */ ?"), [8]);
+        assert_eq!(placeholders("/* ? -- */ ?"), [11]);
+        assert_eq!(placeholders("-- /* ?\n? */"), [8]);
+        assert_eq!(placeholders("'--' ? '/*' ?"), [5, 12]);
+        assert_eq!(placeholders("? - ? / ?"), [0, 4, 8]);
+        assert!(placeholders("-- ?").is_empty());
+        assert!(placeholders("/* ? unterminated").is_empty());
+        // Quotes doubled in sql are two strings next to each other.
+        assert_eq!(placeholders("'it''s ?' ?"), [10]);
+        assert_eq!(placeholders("```?``` ?"), [8]);
+    }
+
+    #[test]
+    fn bind_random_sql() {
+        use rand::seq::SliceRandom;
+        use rand::Rng;
+
+        // Fragments that never change how the next one is scanned, `?` is the only placeholder.
+        const FRAGMENTS: &[&str] = &[
+            "?",
+            " ",
+            "select",
+            ",",
+            "- 1",
+            "/ 2",
+            "* 3",
+            "'?'",
+            r"'it\'s ?'",
+            "'a''?'",
+            r"'\\'",
+            "'-- ?'",
+            "'/* ? */'",
+            "\"?\"",
+            r#""\"?\"""#,
+            "`?`",
+            "`a``?`",
+            "-- ? ' \" `\n",
+            "/* ? ' \" ` -- */",
+            "/**/",
+        ];
+        const ARGS: &[&str] = &["?", "'", r"\", "-- ?", "/* ?", "`", "\"", "'?'"];
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let fragments: Vec<&str> = (0..rng.gen_range(0..24))
+                .map(|_| *FRAGMENTS.choose(&mut rng).unwrap())
+                .collect();
+            let sql = fragments.concat();
+            let args: Vec<Value> = fragments
+                .iter()
+                .filter(|f| **f == "?")
+                .map(|_| Value::VarChar(ARGS.choose(&mut rng).unwrap().to_string()))
+                .collect();
+            let mut literals = args.iter().map(Value::to_sql_literal);
+            let expected: String = fragments
+                .iter()
+                .map(|f| match *f {
+                    "?" => literals.next().unwrap(),
+                    f => f.to_string(),
+                })
+                .collect();
+            assert_eq!(
+                bind_sql_params(&sql, &args, None).unwrap(),
+                expected,
+                "{sql}"
+            );
+            // A literal is a complete string, the bound sql has no placeholders left.
+            assert!(placeholders(&expected).is_empty(), "{expected}");
+            if !args.is_empty() {
+                assert!(bind_sql_params(&sql, &args[1..], None).is_err(), "{sql}");
+            }
+        }
+    }
+
+    #[test]
+    fn bind_literals() {
+        let params = [
+            Value::Null(crate::common::Ty::Int),
+            Value::VarChar(r"a'b\c".to_string()),
+            Value::NChar("涛思".to_string()),
+            Value::VarBinary(vec![0x00, 0xff]),
+            Value::Json(serde_json::json!({"k": "v'"})),
+            Value::Timestamp(Timestamp::Nanoseconds(1_500_000)),
+        ];
+        let sql = bind_sql_params("values(?, ?, ?, ?, ?, ?)", &params, None).unwrap();
+        assert_eq!(
+            sql,
+            r#"values(NULL, 'a\'b\\c', '涛思', '\x00ff', '{"k":"v\'"}', 1500000)"#
+        );
+        let sql = bind_sql_params("?", &params[5..], Some(Precision::Millisecond)).unwrap();
+        assert_eq!(sql, "1");
+
+        let err = bind_sql_params(
+            "?",
+            &[Timestamp::Milliseconds(i64::MAX)],
+            Some(Precision::Nanosecond),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("param 0"), "{err}");
+        let err = bind_sql_params::<i32>("select ?", &[], None).unwrap_err();
+        assert!(
+            err.to_string().contains("1 placeholders but 0 params"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use crate::common::{Field, Precision};
+
+/// A stable hash of a result schema: field names, types, lengths and the precision.
+///
+/// The hash is 64-bit FNV-1a over the precision as a byte, then for each field the length of
+/// its name as a little-endian u32, the name, the type as a byte and the length as a
+/// little-endian u32. It's kept the same across crate versions, so it's safe to persist.
+///
+/// Lengths in [RawBlock](crate::RawBlock) schemas are the lengths in the block, which may
+/// differ from the fields of a result set, so compare hashes from the same source only.
+pub fn schema_hash(fields: &[Field], precision: Precision) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+    write(&[precision.as_u8()]);
+    for field in fields {
+        write(&(field.name().len() as u32).to_le_bytes());
+        write(field.name().as_bytes());
+        write(&[field.ty() as u8]);
+        write(&field.bytes().to_le_bytes());
+    }
+    hash
+}
+
+/// Changes of a schema, columns are matched by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Columns only in the new schema.
+    pub added: Vec<Field>,
+    /// Columns only in the old schema.
+    pub removed: Vec<Field>,
+    /// Columns in both schemas with different types or lengths, as old and new.
+    pub retyped: Vec<(Field, Field)>,
+    /// Precision as old and new, if changed.
+    pub precision: Option<(Precision, Precision)>,
+    /// Columns are the same but in different order.
+    pub reordered: bool,
+}
+
+impl SchemaDiff {
+    /// Diff of schema `old` to `new`.
+    pub fn new(old: (&[Field], Precision), new: (&[Field], Precision)) -> Self {
+        let (old, old_precision) = old;
+        let (new, new_precision) = new;
+        let olds: HashMap<_, _> = old.iter().map(|f| (f.name(), f)).collect();
+        let news: HashMap<_, _> = new.iter().map(|f| (f.name(), f)).collect();
+
+        let mut diff = SchemaDiff {
+            added: new
+                .iter()
+                .filter(|f| !olds.contains_key(f.name()))
+                .cloned()
+                .collect(),
+            removed: old
+                .iter()
+                .filter(|f| !news.contains_key(f.name()))
+                .cloned()
+                .collect(),
+            retyped: old
+                .iter()
+                .filter_map(|f| {
+                    let n = news.get(f.name())?;
+                    (*n != f).then(|| (f.clone(), (*n).clone()))
+                })
+                .collect(),
+            precision: (old_precision != new_precision).then_some((old_precision, new_precision)),
+            reordered: false,
+        };
+        diff.reordered = diff.added.is_empty()
+            && diff.removed.is_empty()
+            && old.iter().map(Field::name).ne(new.iter().map(Field::name));
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.retyped.is_empty()
+            && self.precision.is_none()
+            && !self.reordered
+    }
+}
+
+/// Detect schema changes of repeated queries by [schema_hash], eg. to invalidate cached
+/// decoding plans after `ALTER TABLE`.
+///
+/// ```rust
+/// # use taos_query::common::{Field, Precision, Ty};
+/// # use taos_query::helpers::SchemaTracker;
+/// let mut changes = Vec::new();
+/// let mut tracker = SchemaTracker::new(|key: &str, diff| {
+///     changes.push((key.to_string(), diff.clone()));
+/// });
+///
+/// let v1 = [Field::new("ts", Ty::Timestamp, 8)];
+/// let v2 = [Field::new("ts", Ty::Timestamp, 8), Field::new("v", Ty::Int, 4)];
+/// assert!(!tracker.track("select * from tb1", &v1, Precision::Millisecond));
+/// assert!(!tracker.track("select * from tb1", &v1, Precision::Millisecond));
+/// assert!(tracker.track("select * from tb1", &v2, Precision::Millisecond));
+/// drop(tracker);
+/// assert_eq!(changes[0].1.added, [Field::new("v", Ty::Int, 4)]);
+/// ```
+pub struct SchemaTracker<F> {
+    schemas: HashMap<String, (u64, Vec<Field>, Precision)>,
+    on_change: F,
+}
+
+impl<F> std::fmt::Debug for SchemaTracker<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SchemaTracker")
+            .field("schemas", &self.schemas)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: FnMut(&str, &SchemaDiff)> SchemaTracker<F> {
+    /// Call `on_change` with the key and the diff when a tracked schema changes.
+    pub fn new(on_change: F) -> Self {
+        Self {
+            schemas: HashMap::new(),
+            on_change,
+        }
+    }
+
+    /// Track the schema of query `key`, returns `true` if it differs from the last one.
+    ///
+    /// The first schema of a key is not a change.
+    pub fn track(&mut self, key: &str, fields: &[Field], precision: Precision) -> bool {
+        let hash = schema_hash(fields, precision);
+        match self.schemas.get_mut(key) {
+            Some((old, _, _)) if *old == hash => false,
+            Some((old, old_fields, old_precision)) => {
+                let diff = SchemaDiff::new((old_fields, *old_precision), (fields, precision));
+                *old = hash;
+                *old_fields = fields.to_vec();
+                *old_precision = precision;
+                (self.on_change)(key, &diff);
+                true
+            }
+            None => {
+                self.schemas
+                    .insert(key.to_string(), (hash, fields.to_vec(), precision));
+                false
+            }
+        }
+    }
+
+    /// Hash of the last schema of `key`.
+    pub fn hash_of(&self, key: &str) -> Option<u64> {
+        self.schemas.get(key).map(|(hash, _, _)| *hash)
+    }
+
+    /// Stop tracking `key`.
+    pub fn forget(&mut self, key: &str) -> bool {
+        self.schemas.remove(key).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Ty;
+
+    use super::*;
+
+    fn fields() -> Vec<Field> {
+        vec![
+            Field::new("ts", Ty::Timestamp, 8),
+            Field::new("v", Ty::Int, 4),
+            Field::new("name", Ty::VarChar, 20),
+        ]
+    }
+
+    #[test]
+    fn hash_vector() {
+        // pinned, the hash must not change across versions.
+        assert_eq!(
+            schema_hash(&[], Precision::Millisecond),
+            0xaf63_bd4c_8601_b7df
+        );
+        assert_eq!(
+            schema_hash(&fields(), Precision::Millisecond),
+            0x6e50_c1c6_e674_e755
+        );
+        assert_ne!(
+            schema_hash(&fields(), Precision::Millisecond),
+            schema_hash(&fields(), Precision::Nanosecond)
+        );
+    }
+
+    #[test]
+    fn diffs() {
+        let old = fields();
+        let mut new = fields();
+        new[1] = Field::new("v", Ty::BigInt, 8);
+        new.remove(2);
+        new.push(Field::new("tag", Ty::NChar, 10));
+
+        let diff = SchemaDiff::new(
+            (&old, Precision::Millisecond),
+            (&new, Precision::Microsecond),
+        );
+        assert_eq!(diff.added, [Field::new("tag", Ty::NChar, 10)]);
+        assert_eq!(diff.removed, [Field::new("name", Ty::VarChar, 20)]);
+        assert_eq!(
+            diff.retyped,
+            [(Field::new("v", Ty::Int, 4), Field::new("v", Ty::BigInt, 8))]
+        );
+        assert_eq!(
+            diff.precision,
+            Some((Precision::Millisecond, Precision::Microsecond))
+        );
+        assert!(!diff.reordered);
+
+        let mut reversed = fields();
+        reversed.reverse();
+        let diff = SchemaDiff::new(
+            (&old, Precision::Millisecond),
+            (&reversed, Precision::Millisecond),
+        );
+        assert!(diff.reordered && !diff.is_empty());
+        assert!(SchemaDiff::new(
+            (&old, Precision::Millisecond),
+            (&old, Precision::Millisecond)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn tracker() {
+        let mut changes = Vec::new();
+        let mut tracker = SchemaTracker::new(|key: &str, diff: &SchemaDiff| {
+            changes.push((key.to_string(), diff.clone()))
+        });
+        let old = fields();
+        let new = &old[..2];
+
+        assert!(!tracker.track("q1", &old, Precision::Millisecond));
+        assert!(!tracker.track("q2", new, Precision::Millisecond));
+        assert!(!tracker.track("q1", &old, Precision::Millisecond));
+        assert!(tracker.track("q1", new, Precision::Millisecond));
+        assert!(!tracker.track("q1", new, Precision::Millisecond));
+        assert_eq!(
+            tracker.hash_of("q1"),
+            Some(schema_hash(new, Precision::Millisecond))
+        );
+        assert!(tracker.forget("q2"));
+        assert!(!tracker.track("q2", &old, Precision::Millisecond));
+        drop(tracker);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "q1");
+        assert_eq!(changes[0].1.removed, [Field::new("name", Ty::VarChar, 20)]);
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::common::Describe;
+
+use super::classify::{classify_sql, skip_comments, StatementKind};
+
+/// Time to live of tables in a [SchemaCache] by default.
+pub const DEFAULT_SCHEMA_TTL: Duration = Duration::from_secs(60);
+
+/// Counters of a [SchemaCache].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchemaCacheStats {
+    /// Lookups answered by the cache.
+    pub hits: u64,
+    /// Lookups of tables not cached or expired, which are described by the server.
+    pub misses: u64,
+    /// Tables removed by DDL, schema errors or [SchemaCache::invalidate].
+    pub invalidations: u64,
+    /// Tables cached now, including expired ones not looked up since.
+    pub tables: usize,
+}
+
+#[derive(Debug)]
+struct Tables {
+    ttl: Duration,
+    entries: HashMap<String, (Instant, Describe)>,
+}
+
+/// Schemas of tables by `DESCRIBE`, kept for a time to live, so helpers describing the same
+/// table again, eg. [export_table](crate::transfer::export_table), don't query it each time.
+///
+/// A connection caching schemas returns it by [AsyncQueryable::schema_cache], then
+/// [AsyncQueryable::describe] looks up the cache first. Statements executed through the
+/// connection are passed to [SchemaCache::observe], so DDL of a table invalidates it. DDL by
+/// other connections is not seen until the schema expires or fails a statement, see
+/// [is_schema_error].
+///
+/// [AsyncQueryable::schema_cache]: crate::AsyncQueryable::schema_cache
+/// [AsyncQueryable::describe]: crate::AsyncQueryable::describe
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use taos_query::helpers::SchemaCache;
+/// let cache = SchemaCache::new(Duration::from_secs(10));
+/// assert!(cache.get("meters").is_none());
+/// cache.observe("alter stable power.meters add column phase float");
+/// assert_eq!(cache.stats().misses, 1);
+/// ```
+#[derive(Debug)]
+pub struct SchemaCache {
+    tables: Mutex<Tables>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl Default for SchemaCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SCHEMA_TTL)
+    }
+}
+
+impl SchemaCache {
+    /// A cache keeping schemas for `ttl` after they are described.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            tables: Mutex::new(Tables {
+                ttl,
+                entries: HashMap::new(),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.tables.lock().unwrap().ttl
+    }
+
+    /// Change the time to live, of cached schemas too, zero to disable the cache.
+    pub fn set_ttl(&self, ttl: Duration) {
+        self.tables.lock().unwrap().ttl = ttl;
+    }
+
+    /// Schema of `table` as it's described, `None` if not cached or expired.
+    pub fn get(&self, table: &str) -> Option<Describe> {
+        let mut tables = self.tables.lock().unwrap();
+        let ttl = tables.ttl;
+        let describe = match tables.entries.get(table) {
+            Some((at, describe)) if at.elapsed() < ttl => Some(describe.clone()),
+            Some(_) => {
+                tables.entries.remove(table);
+                None
+            }
+            None => None,
+        };
+        let counter = if describe.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        describe
+    }
+
+    /// Cache the schema of `table` described now.
+    pub fn insert(&self, table: &str, describe: Describe) {
+        let mut tables = self.tables.lock().unwrap();
+        if !tables.ttl.is_zero() {
+            tables
+                .entries
+                .insert(table.to_string(), (Instant::now(), describe));
+        }
+    }
+
+    /// Remove `table`, as any name referring to it, eg. `meters`, `power.meters` or
+    /// `` `power`.`meters` ``, returns whether it was cached.
+    pub fn invalidate(&self, table: &str) -> bool {
+        let name = table_name(table);
+        let mut tables = self.tables.lock().unwrap();
+        let before = tables.entries.len();
+        tables
+            .entries
+            .retain(|key, _| !table_name(key).eq_ignore_ascii_case(name));
+        let removed = before - tables.entries.len();
+        self.invalidations
+            .fetch_add(removed as u64, Ordering::Relaxed);
+        removed > 0
+    }
+
+    /// Remove all tables.
+    pub fn clear(&self) {
+        let mut tables = self.tables.lock().unwrap();
+        self.invalidations
+            .fetch_add(tables.entries.len() as u64, Ordering::Relaxed);
+        tables.entries.clear();
+    }
+
+    pub fn stats(&self) -> SchemaCacheStats {
+        SchemaCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            tables: self.tables.lock().unwrap().entries.len(),
+        }
+    }
+
+    /// Invalidate tables changed by `sql` executed through the connection.
+    ///
+    /// DDL of tables, ie. `create`, `alter`, `drop` or `truncate` of a table or stable,
+    /// invalidates the tables, DDL of databases and `use` clear the cache. Others are ignored.
+    pub fn observe(&self, sql: &str) {
+        match classify_sql(sql) {
+            StatementKind::Ddl => {}
+            StatementKind::Other => {
+                if first_word_is(sql, "use") {
+                    self.clear();
+                }
+                return;
+            }
+            _ => return,
+        }
+        let words = words(skip_comments(sql));
+        let mut words = words.iter().skip(1).map(String::as_str);
+        match words.next() {
+            Some(object) if is(object, "database") || is(object, "db") => self.clear(),
+            #[cfg(feature = "sqlparser")]
+            Some(object) if is(object, "table") || is(object, "stable") => {
+                // tables touched by the statement, eg. the stable of `create table ... using`.
+                for table in crate::sql::analyze(sql).tables {
+                    self.invalidate(&table.to_string());
+                }
+            }
+            #[cfg(not(feature = "sqlparser"))]
+            Some(object) if is(object, "table") || is(object, "stable") => {
+                let names = words.skip_while(|w| is(w, "if") || is(w, "not") || is(w, "exists"));
+                // `drop table t1, t2` drops many, others change one.
+                let many = first_word_is(sql, "drop");
+                for name in names.take(if many { usize::MAX } else { 1 }) {
+                    self.invalidate(name);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether an error of a statement is probably caused by a stale schema, eg. a column dropped
+/// or renamed by another connection, so the statement may succeed once the schema is described
+/// again.
+pub fn is_schema_error(err: &impl Display) -> bool {
+    let message = err.to_string().to_ascii_lowercase();
+    ["invalid column", "column not exist", "schema"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+fn is(word: &str, keyword: &str) -> bool {
+    word.eq_ignore_ascii_case(keyword)
+}
+
+fn first_word_is(sql: &str, keyword: &str) -> bool {
+    words(skip_comments(sql))
+        .first()
+        .map_or(false, |word| is(word, keyword))
+}
+
+/// Words of the head of a statement, split by whitespaces and commas, up to a parenthesis.
+fn words(sql: &str) -> Vec<String> {
+    let head = sql.split(['(', ';']).next().unwrap_or_default();
+    head.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The table of a name without its database and backticks.
+fn table_name(name: &str) -> &str {
+    let name = name.trim();
+    let table = match name.strip_suffix('`') {
+        Some(quoted) => quoted.rsplit_once('`').map_or(quoted, |(_, table)| table),
+        None => name.rsplit_once('.').map_or(name, |(_, table)| table),
+    };
+    table.trim_matches('`')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use super::*;
+    use crate::common::{Field, Precision, RawBlock, Ty};
+    use crate::prelude::*;
+
+    fn describe(columns: &[&str]) -> Describe {
+        serde_json::from_value(serde_json::json!(columns
+            .iter()
+            .map(|c| serde_json::json!({"field": c, "type": "INT", "length": 4, "note": ""}))
+            .collect::<Vec<_>>()))
+        .unwrap()
+    }
+
+    #[test]
+    fn invalidate_by_ddl() {
+        let cache = SchemaCache::default();
+        for table in ["meters", "power.meters", "`power`.`d1001`", "d1002", "d.x"] {
+            cache.insert(table, describe(&["ts", "v"]));
+        }
+        assert!(cache.get("power.meters").is_some());
+
+        cache.observe("select * from meters");
+        cache.observe("insert into d1002 values(now, 1)");
+        assert_eq!(cache.stats().tables, 5);
+
+        cache.observe("-- add phase\nALTER STABLE `METERS` ADD COLUMN phase float");
+        assert!(cache.get("meters").is_none());
+        assert!(cache.get("power.meters").is_none());
+        assert_eq!(cache.stats().invalidations, 2);
+
+        cache.observe("drop table if exists d1001, power.d1002");
+        assert_eq!(cache.stats().tables, 1);
+        cache.observe("create table if not exists d1003 using meters tags(1)");
+        assert_eq!(cache.stats().tables, 1);
+        cache.observe("use power");
+        assert_eq!(cache.stats().tables, 0);
+
+        cache.insert("x", describe(&["ts"]));
+        cache.observe("drop database if exists d");
+        assert_eq!(
+            cache.stats(),
+            SchemaCacheStats {
+                hits: 1,
+                misses: 2,
+                invalidations: 6,
+                tables: 0
+            }
+        );
+
+        assert_eq!(table_name("`db.1`.`t.1`"), "t.1");
+        assert_eq!(table_name(" db.t1 "), "t1");
+        assert!(is_schema_error(&"Invalid column name: c1"));
+        assert!(is_schema_error(&"[0x2603] Invalid table schema version"));
+        assert!(!is_schema_error(&"Table does not exist"));
+    }
+
+    #[cfg(feature = "sqlparser")]
+    #[test]
+    fn invalidate_by_analysis() {
+        let cache = SchemaCache::default();
+        for table in ["`my meters`", "d0", "d1", "t1"] {
+            cache.insert(table, describe(&["ts", "v"]));
+        }
+        cache.observe("alter stable `power`.`my meters` add column phase float");
+        assert!(cache.get("`my meters`").is_none());
+        cache.observe("create table d0 using st tags(0) d1 using st tags(1)");
+        cache.observe("create table t2 as select * from t1");
+        assert_eq!(cache.stats().invalidations, 4);
+    }
+
+    #[test]
+    fn expire_by_ttl() {
+        let cache = SchemaCache::new(Duration::from_millis(20));
+        cache.insert("meters", describe(&["ts"]));
+        assert!(cache.get("meters").is_some());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get("meters").is_none());
+        assert_eq!(cache.stats().tables, 0);
+
+        cache.set_ttl(Duration::ZERO);
+        cache.insert("meters", describe(&["ts"]));
+        assert!(cache.get("meters").is_none());
+    }
+
+    /// A connection caching schemas, counting `DESCRIBE` statements.
+    #[derive(Debug, Default)]
+    struct Cached {
+        cache: SchemaCache,
+        describes: StdMutex<usize>,
+        columns: StdMutex<Vec<String>>,
+    }
+
+    struct Rows(Option<RawBlock>, Vec<Field>);
+
+    impl AsyncFetchable for Rows {
+        type Error = RawError;
+
+        fn affected_rows(&self) -> i32 {
+            0
+        }
+
+        fn precision(&self) -> Precision {
+            Precision::Millisecond
+        }
+
+        fn fields(&self) -> &[Field] {
+            &self.1
+        }
+
+        fn summary(&self) -> (usize, usize) {
+            (0, 0)
+        }
+
+        fn update_summary(&mut self, _rows: usize) {}
+
+        fn fetch_raw_block(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<Option<RawBlock>, Self::Error>> {
+            std::task::Poll::Ready(Ok(self.0.take()))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncQueryable for Cached {
+        type Error = RawError;
+
+        type AsyncResultSet = Rows;
+
+        async fn query<T: AsRef<str> + Send + Sync>(&self, sql: T) -> Result<Rows, RawError> {
+            let sql = sql.as_ref();
+            self.cache.observe(sql);
+            if let Some(column) = sql.strip_prefix("alter table tb add column ") {
+                self.columns.lock().unwrap().push(column.to_string());
+                return Ok(Rows(None, Vec::new()));
+            }
+            assert_eq!(sql, "DESCRIBE `tb`");
+            *self.describes.lock().unwrap() += 1;
+            let columns = self.columns.lock().unwrap().clone();
+            let n = columns.len();
+            let views = [
+                ColumnView::from_varchar::<String, _, _, _>(columns),
+                ColumnView::from_varchar::<&str, _, _, _>(vec!["INT"; n]),
+                ColumnView::from_ints(vec![4; n]),
+                ColumnView::from_varchar::<&str, _, _, _>(vec![""; n]),
+            ];
+            let mut block = RawBlock::parse_from_raw_block(
+                crate::common::views::views_to_raw_block(&views),
+                Precision::Millisecond,
+            );
+            block.with_field_names(["field", "type", "length", "note"]);
+            let fields = vec![
+                Field::new("field", Ty::VarChar, 64),
+                Field::new("type", Ty::VarChar, 64),
+                Field::new("length", Ty::Int, 4),
+                Field::new("note", Ty::VarChar, 64),
+            ];
+            Ok(Rows(Some(block), fields))
+        }
+
+        async fn write_raw_meta(&self, _: &RawMeta) -> Result<(), RawError> {
+            Ok(())
+        }
+
+        async fn write_raw_block(&self, _: &RawBlock) -> Result<(), RawError> {
+            Ok(())
+        }
+
+        fn schema_cache(&self) -> Option<&SchemaCache> {
+            Some(&self.cache)
+        }
+    }
+
+    #[tokio::test]
+    async fn describe_once() {
+        let taos = Cached::default();
+        taos.exec("alter table tb add column ts").await.unwrap();
+        for _ in 0..100 {
+            let describe = taos.describe("tb").await.unwrap();
+            assert_eq!(describe.names().collect::<Vec<_>>(), ["ts"]);
+        }
+        assert_eq!(*taos.describes.lock().unwrap(), 1);
+        assert_eq!(taos.cache.stats().hits, 99);
+
+        taos.exec("alter table tb add column v").await.unwrap();
+        let describe = taos.describe("tb").await.unwrap();
+        assert_eq!(describe.names().collect::<Vec<_>>(), ["ts", "v"]);
+        assert_eq!(*taos.describes.lock().unwrap(), 2);
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use crate::common::{Timestamp, Value};
+use crate::util::{quote_ident, quote_table_ref};
+
+use super::grant::{normalize, value_as_i64, value_as_str};
+
+/// When a stream computes and writes results, the `TRIGGER` option of `CREATE STREAM`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Compute as soon as data is written, the server default.
+    AtOnce,
+    /// Compute when a window closes, with the watermark.
+    WindowClose,
+    /// Compute when a window closes or the delay is reached since the last write.
+    MaxDelay(Duration),
+}
+
+impl Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::AtOnce => f.write_str("AT_ONCE"),
+            Trigger::WindowClose => f.write_str("WINDOW_CLOSE"),
+            Trigger::MaxDelay(delay) => write!(f, "MAX_DELAY {}", DurationLiteral(*delay)),
+        }
+    }
+}
+
+/// Duration in the largest exact time unit of sql, eg. `10s`, `1500a`.
+struct DurationLiteral(Duration);
+
+impl Display for DurationLiteral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [(u128, &str); 7] = [
+            (86_400_000_000_000, "d"),
+            (3_600_000_000_000, "h"),
+            (60_000_000_000, "m"),
+            (1_000_000_000, "s"),
+            (1_000_000, "a"),
+            (1_000, "u"),
+            (1, "b"),
+        ];
+        let nanos = self.0.as_nanos();
+        if nanos == 0 {
+            return f.write_str("0s");
+        }
+        let (size, unit) = UNITS
+            .iter()
+            .find(|(size, _)| nanos % size == 0)
+            .copied()
+            .unwrap_or((1, "b"));
+        write!(f, "{}{unit}", nanos / size)
+    }
+}
+
+/// Builder of `CREATE STREAM` sql for [create_stream](crate::AsyncQueryable::create_stream).
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use taos_query::helpers::{StreamBuilder, Trigger};
+/// let sql = StreamBuilder::new("avg_vol")
+///     .source_sql("select _wstart, avg(voltage) from meters interval(1m)")
+///     .into_table("avg_vol_1m")
+///     .trigger(Trigger::WindowClose)
+///     .watermark(Duration::from_secs(10))
+///     .build_sql();
+/// assert_eq!(
+///     sql,
+///     "CREATE STREAM IF NOT EXISTS `avg_vol` TRIGGER WINDOW_CLOSE WATERMARK 10s \
+///      INTO `avg_vol_1m` AS select _wstart, avg(voltage) from meters interval(1m)"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamBuilder {
+    name: String,
+    source_sql: String,
+    into: String,
+    trigger: Option<Trigger>,
+    watermark: Option<Duration>,
+    fill_history: Option<bool>,
+    ignore_expired: Option<bool>,
+    ignore_update: Option<bool>,
+    delete_mark: Option<Duration>,
+    if_not_exists: bool,
+}
+
+impl StreamBuilder {
+    /// A stream named `name`, [source_sql](Self::source_sql) and [into_table](Self::into_table)
+    /// are required, other options are left to server defaults.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source_sql: String::new(),
+            into: String::new(),
+            trigger: None,
+            watermark: None,
+            fill_history: None,
+            ignore_expired: None,
+            ignore_update: None,
+            delete_mark: None,
+            if_not_exists: true,
+        }
+    }
+
+    /// The query computed by the stream, after `AS`.
+    pub fn source_sql(mut self, sql: impl Into<String>) -> Self {
+        self.source_sql = sql.into();
+        self
+    }
+
+    /// Super table results are written into, created by the stream if not exists.
+    pub fn into_table(mut self, table: &str) -> Self {
+        self.into = quote_ident(table).into_owned();
+        self
+    }
+
+    /// Super table in database `db` that results are written into.
+    pub fn into_db_table(mut self, db: &str, table: &str) -> Self {
+        self.into = quote_table_ref(db, table);
+        self
+    }
+
+    pub fn trigger(mut self, trigger: Trigger) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    /// How long a window waits for out-of-order data before it closes.
+    pub fn watermark(mut self, watermark: Duration) -> Self {
+        self.watermark = Some(watermark);
+        self
+    }
+
+    /// Compute history data written before the stream is created.
+    pub fn fill_history(mut self, fill: bool) -> Self {
+        self.fill_history = Some(fill);
+        self
+    }
+
+    /// Ignore data written into closed windows.
+    pub fn ignore_expired(mut self, ignore: bool) -> Self {
+        self.ignore_expired = Some(ignore);
+        self
+    }
+
+    /// Ignore updates of existing rows.
+    pub fn ignore_update(mut self, ignore: bool) -> Self {
+        self.ignore_update = Some(ignore);
+        self
+    }
+
+    /// How long the stream keeps window states for deleted data.
+    pub fn delete_mark(mut self, mark: Duration) -> Self {
+        self.delete_mark = Some(mark);
+        self
+    }
+
+    /// Fail if the stream exists instead of doing nothing, it's `IF NOT EXISTS` by default.
+    pub fn fail_if_exists(mut self) -> Self {
+        self.if_not_exists = false;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The `CREATE STREAM` sql.
+    pub fn build_sql(&self) -> String {
+        let mut sql = String::from("CREATE STREAM ");
+        if self.if_not_exists {
+            sql.push_str("IF NOT EXISTS ");
+        }
+        sql.push_str(&quote_ident(&self.name));
+        if let Some(trigger) = self.trigger {
+            sql.push_str(&format!(" TRIGGER {trigger}"));
+        }
+        if let Some(watermark) = self.watermark {
+            sql.push_str(&format!(" WATERMARK {}", DurationLiteral(watermark)));
+        }
+        let flag = |b: bool| if b { 1 } else { 0 };
+        if let Some(ignore) = self.ignore_expired {
+            sql.push_str(&format!(" IGNORE EXPIRED {}", flag(ignore)));
+        }
+        if let Some(fill) = self.fill_history {
+            sql.push_str(&format!(" FILL_HISTORY {}", flag(fill)));
+        }
+        if let Some(mark) = self.delete_mark {
+            sql.push_str(&format!(" DELETE_MARK {}", DurationLiteral(mark)));
+        }
+        if let Some(ignore) = self.ignore_update {
+            sql.push_str(&format!(" IGNORE UPDATE {}", flag(ignore)));
+        }
+        sql.push_str(&format!(" INTO {} AS {}", self.into, self.source_sql));
+        sql
+    }
+}
+
+/// A stream from `information_schema.ins_streams`.
+///
+/// Columns differ across server versions, so only the known columns are parsed into typed
+/// fields, `None` if the server does not report it. All columns are kept in
+/// [StreamInfo::columns].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub name: String,
+    pub create_time: Option<Timestamp>,
+    /// The `CREATE STREAM` sql.
+    pub sql: String,
+    /// Status of the stream, eg. `ready`, `paused`, `failed`.
+    pub status: Option<String>,
+    pub source_db: Option<String>,
+    pub target_db: Option<String>,
+    pub target_table: Option<String>,
+    /// Watermark in milliseconds.
+    pub watermark: Option<i64>,
+    /// Trigger mode as reported, eg. `1` or `WINDOW_CLOSE`.
+    pub trigger: Option<String>,
+    /// Time of the last computation, only reported by some versions.
+    pub last_exec_time: Option<Timestamp>,
+    /// All columns by normalized column name, including the typed ones above.
+    pub columns: BTreeMap<String, Value>,
+}
+
+impl StreamInfo {
+    /// Parse streams from field names and rows of `information_schema.ins_streams`.
+    pub fn from_rows<S: AsRef<str>>(names: &[S], rows: &[Vec<Value>]) -> Vec<Self> {
+        rows.iter().map(|row| Self::from_row(names, row)).collect()
+    }
+
+    fn from_row<S: AsRef<str>>(names: &[S], row: &[Value]) -> Self {
+        let columns: BTreeMap<_, _> = names
+            .iter()
+            .map(|name| normalize(name.as_ref()))
+            .zip(row.iter().cloned())
+            .collect();
+        let get = |names: &[&str]| names.iter().find_map(|name| columns.get(*name));
+        let string = |names: &[&str]| {
+            get(names)
+                .and_then(value_as_str)
+                .filter(|s| !s.is_empty())
+                .map(ToString::to_string)
+        };
+        let timestamp = |names: &[&str]| match get(names)? {
+            Value::Timestamp(ts) => Some(*ts),
+            value => value_as_i64(value).map(Timestamp::Milliseconds),
+        };
+        Self {
+            name: string(&["stream_name", "name"]).unwrap_or_default(),
+            create_time: timestamp(&["create_time", "created_time"]),
+            sql: string(&["sql"]).unwrap_or_default(),
+            status: string(&["status"]),
+            source_db: string(&["source_db"]),
+            target_db: string(&["target_db"]),
+            target_table: string(&["target_table"]),
+            watermark: get(&["watermark"]).and_then(value_as_i64),
+            trigger: get(&["trigger"]).and_then(|value| match value_as_i64(value) {
+                Some(v) => Some(v.to_string()),
+                None => value_as_str(value).map(ToString::to_string),
+            }),
+            last_exec_time: timestamp(&["last_exec_time", "last_execute_time"]),
+            columns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Ty;
+
+    #[test]
+    fn stream_sql() {
+        let builder = StreamBuilder::new("s1")
+            .source_sql("select _wstart, count(*) from st interval(10s)")
+            .into_db_table("power", "out.1")
+            .trigger(Trigger::MaxDelay(Duration::from_millis(1500)))
+            .watermark(Duration::from_secs(120))
+            .fill_history(true)
+            .ignore_expired(false)
+            .ignore_update(true)
+            .delete_mark(Duration::from_secs(86400));
+        assert_eq!(builder.name(), "s1");
+        assert_eq!(
+            builder.build_sql(),
+            "CREATE STREAM IF NOT EXISTS `s1` TRIGGER MAX_DELAY 1500a WATERMARK 2m \
+             IGNORE EXPIRED 0 FILL_HISTORY 1 DELETE_MARK 1d IGNORE UPDATE 1 \
+             INTO `power`.`out.1` AS select _wstart, count(*) from st interval(10s)"
+        );
+
+        let sql = StreamBuilder::new("s`2")
+            .source_sql("select * from t")
+            .into_table("out")
+            .trigger(Trigger::AtOnce)
+            .fail_if_exists()
+            .build_sql();
+        assert_eq!(
+            sql,
+            "CREATE STREAM `s``2` TRIGGER AT_ONCE INTO `out` AS select * from t"
+        );
+
+        let literal = |d| DurationLiteral(d).to_string();
+        assert_eq!(literal(Duration::from_secs(7200)), "2h");
+        assert_eq!(literal(Duration::from_secs(90)), "90s");
+        assert_eq!(literal(Duration::from_micros(1001)), "1001u");
+        assert_eq!(literal(Duration::from_nanos(5)), "5b");
+        assert_eq!(literal(Duration::ZERO), "0s");
+    }
+
+    #[test]
+    fn stream_info_tolerant() {
+        let s = |v: &str| Value::VarChar(v.to_string());
+        // 3.0 columns.
+        let names = [
+            "stream_name",
+            "create_time",
+            "sql",
+            "status",
+            "source_db",
+            "target_db",
+            "target_table",
+            "watermark",
+            "trigger",
+        ];
+        let rows = vec![vec![
+            s("s1"),
+            Value::Timestamp(Timestamp::Milliseconds(10)),
+            s("create stream s1 into out as select * from t"),
+            s("ready"),
+            s("power"),
+            s("power"),
+            s("out"),
+            Value::BigInt(10000),
+            Value::Int(2),
+        ]];
+        let streams = StreamInfo::from_rows(&names, &rows);
+        assert_eq!(streams.len(), 1);
+        let stream = &streams[0];
+        assert_eq!(stream.name, "s1");
+        assert_eq!(stream.create_time, Some(Timestamp::Milliseconds(10)));
+        assert_eq!(stream.status.as_deref(), Some("ready"));
+        assert_eq!(stream.target_table.as_deref(), Some("out"));
+        assert_eq!(stream.watermark, Some(10000));
+        assert_eq!(stream.trigger.as_deref(), Some("2"));
+        assert_eq!(stream.last_exec_time, None);
+
+        // Renamed, missing and unknown columns of other versions.
+        let names = [
+            "Stream Name",
+            "SQL",
+            "trigger",
+            "last_exec_time",
+            "sink_quota",
+        ];
+        let rows = vec![vec![
+            s("s2"),
+            s("create stream s2 ..."),
+            s("WINDOW_CLOSE"),
+            Value::BigInt(20),
+            Value::Null(Ty::VarChar),
+        ]];
+        let stream = StreamInfo::from_rows(&names, &rows).remove(0);
+        assert_eq!(stream.name, "s2");
+        assert_eq!(stream.status, None);
+        assert_eq!(stream.create_time, None);
+        assert_eq!(stream.trigger.as_deref(), Some("WINDOW_CLOSE"));
+        assert_eq!(stream.last_exec_time, Some(Timestamp::Milliseconds(20)));
+        assert!(stream.columns["sink_quota"].is_null());
+    }
+}
@@ -0,0 +1,297 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+
+use crate::common::{Precision, Timestamp, Ty, Value};
+use crate::util::quote_ident;
+
+use super::grant::{normalize, value_as_str};
+
+/// Max bytes of sql in a batch of [set_tags_bulk](crate::Queryable::set_tags_bulk), or the
+/// max sql length of the connection if it's smaller.
+pub const TAG_BATCH_SQL_BYTES: usize = 64 * 1024;
+
+/// Sql to set tag `tag` of child table `table` to `value`.
+///
+/// ```rust
+/// # use taos_query::common::Value;
+/// # use taos_query::helpers::set_tag_sql;
+/// assert_eq!(
+///     set_tag_sql("d1001", "location", &Value::VarChar("San Francisco".to_string())),
+///     "ALTER TABLE `d1001` SET TAG `location`='San Francisco'"
+/// );
+/// ```
+pub fn set_tag_sql(table: &str, tag: &str, value: &Value) -> String {
+    format!(
+        "ALTER TABLE {} SET TAG {}={}",
+        quote_ident(table),
+        quote_ident(tag),
+        value.to_sql_literal()
+    )
+}
+
+/// Set tag statements of `updates` grouped into batches of at most [TAG_BATCH_SQL_BYTES] and
+/// `max_sql_length`, with the index of the update of each statement.
+pub(crate) fn tag_batches<S: AsRef<str>>(
+    updates: &[(S, S, Value)],
+    max_sql_length: usize,
+) -> Vec<Vec<(usize, String)>> {
+    let limit = TAG_BATCH_SQL_BYTES.min(max_sql_length);
+    let mut batches = Vec::new();
+    let mut batch: Vec<(usize, String)> = Vec::new();
+    let mut bytes = 0;
+    for (i, (table, tag, value)) in updates.iter().enumerate() {
+        let sql = set_tag_sql(table.as_ref(), tag.as_ref(), value);
+        if !batch.is_empty() && bytes + sql.len() > limit {
+            batches.push(std::mem::take(&mut batch));
+            bytes = 0;
+        }
+        bytes += sql.len();
+        batch.push((i, sql));
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+/// A failed update of [set_tags_bulk](crate::Queryable::set_tags_bulk), updates before it
+/// are applied.
+#[derive(Debug)]
+pub struct TagUpdateError<E> {
+    /// Index of the update in the input.
+    pub index: usize,
+    pub table: String,
+    pub tag: String,
+    pub source: E,
+}
+
+impl<E> TagUpdateError<E> {
+    pub(crate) fn new<S: AsRef<str>>(updates: &[(S, S, Value)], index: usize, source: E) -> Self {
+        let (table, tag, _) = &updates[index];
+        Self {
+            index,
+            table: table.as_ref().to_string(),
+            tag: tag.as_ref().to_string(),
+            source,
+        }
+    }
+}
+
+impl<E: Display> Display for TagUpdateError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "set tag {} of table {} error: {}",
+            self.tag, self.table, self.source
+        )
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for TagUpdateError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Tag names and values from field names and rows of `show tags from {table}`.
+///
+/// Values are parsed by `tag_type`. Timestamps are shown as raw integers, which are in the
+/// precision of the database, so `precision` should be the database one.
+pub fn tags_from_rows<S: AsRef<str>>(
+    names: &[S],
+    rows: &[Vec<Value>],
+    precision: Precision,
+) -> Vec<(String, Value)> {
+    let names: Vec<_> = names.iter().map(|name| normalize(name.as_ref())).collect();
+    let position = |name: &str| names.iter().position(|n| n == name);
+    let (Some(name), Some(ty), Some(value)) = (
+        position("tag_name"),
+        position("tag_type"),
+        position("tag_value"),
+    ) else {
+        return Vec::new();
+    };
+    rows.iter()
+        .filter_map(|row| {
+            let tag = value_as_str(row.get(name)?)?.to_string();
+            let ty = value_as_str(row.get(ty)?).unwrap_or_default();
+            let value = match row.get(value)? {
+                Value::VarChar(s) | Value::NChar(s) => Some(s.as_str()),
+                _ => None,
+            };
+            Some((tag, parse_tag_value(ty, value, precision)))
+        })
+        .collect()
+}
+
+/// Parse a tag value shown as `value` of type `ty`, eg. `VARCHAR(20)`.
+///
+/// Values that can't be parsed as the type are kept as [Value::VarChar].
+fn parse_tag_value(ty: &str, value: Option<&str>, precision: Precision) -> Value {
+    let name = ty.split('(').next().unwrap_or_default().trim();
+    let Ok(ty) = Ty::from_str(name) else {
+        return value.map_or(Value::Null(Ty::VarChar), |v| Value::VarChar(v.to_string()));
+    };
+    let Some(value) = value else {
+        return Value::Null(ty);
+    };
+    fn num<T: FromStr>(value: &str, f: impl FnOnce(T) -> Value) -> Option<Value> {
+        value.trim().parse().ok().map(f)
+    }
+    let parsed = match ty {
+        Ty::Bool => match value.trim() {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Ty::TinyInt => num(value, Value::TinyInt),
+        Ty::SmallInt => num(value, Value::SmallInt),
+        Ty::Int => num(value, Value::Int),
+        Ty::BigInt => num(value, Value::BigInt),
+        Ty::UTinyInt => num(value, Value::UTinyInt),
+        Ty::USmallInt => num(value, Value::USmallInt),
+        Ty::UInt => num(value, Value::UInt),
+        Ty::UBigInt => num(value, Value::UBigInt),
+        Ty::Float => num(value, Value::Float),
+        Ty::Double => num(value, Value::Double),
+        Ty::Decimal => num(value, Value::Decimal),
+        Ty::Timestamp => num(value, |raw| {
+            Value::Timestamp(Timestamp::new(raw, precision))
+        })
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+                .ok()
+                .and_then(|dt| Timestamp::from_naive_datetime(&dt, precision).ok())
+                .map(Value::Timestamp)
+        }),
+        Ty::VarChar => Some(Value::VarChar(value.to_string())),
+        Ty::NChar => Some(Value::NChar(value.to_string())),
+        Ty::Json => serde_json::from_str(value).ok().map(Value::Json),
+        Ty::VarBinary | Ty::Blob | Ty::MediumBlob | Ty::Geometry => {
+            let bytes = value
+                .strip_prefix("\\x")
+                .and_then(parse_hex)
+                .unwrap_or_else(|| value.as_bytes().to_vec());
+            Some(match ty {
+                Ty::Blob => Value::Blob(bytes),
+                Ty::MediumBlob => Value::MediumBlob(bytes),
+                Ty::Geometry => Value::Geometry(bytes),
+                _ => Value::VarBinary(bytes),
+            })
+        }
+        Ty::Null => Some(Value::Null(Ty::Null)),
+    };
+    parsed.unwrap_or_else(|| Value::VarChar(value.to_string()))
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::DEFAULT_MAX_SQL_LENGTH;
+
+    #[test]
+    fn literals() {
+        let s = |s: &str| Value::VarChar(s.to_string());
+        assert_eq!(s("a'b").to_sql_literal(), r"'a\'b'");
+        assert_eq!(s(r"C:\data").to_sql_literal(), r"'C:\\data'");
+        assert_eq!(s("\"北京\"").to_sql_literal(), "'\"北京\"'");
+        assert_eq!(Value::Int(-3).to_sql_literal(), "-3");
+        assert_eq!(Value::Bool(true).to_sql_literal(), "true");
+        assert_eq!(Value::Null(Ty::Int).to_sql_literal(), "NULL");
+        assert_eq!(
+            Value::Json(serde_json::json!({"k": "it's"})).to_sql_literal(),
+            r#"'{"k":"it\'s"}'"#
+        );
+        assert_eq!(
+            set_tag_sql("d`1", "loc", &s("x")),
+            "ALTER TABLE `d``1` SET TAG `loc`='x'"
+        );
+    }
+
+    #[test]
+    fn batches() {
+        let updates: Vec<_> = (0..2000)
+            .map(|i| (format!("d{i}"), "location".to_string(), Value::Int(i)))
+            .collect();
+        let batches = tag_batches(&updates, DEFAULT_MAX_SQL_LENGTH);
+        assert!(batches.len() > 1);
+        assert!(batches.iter().all(|batch| {
+            batch.iter().map(|(_, sql)| sql.len()).sum::<usize>() <= TAG_BATCH_SQL_BYTES
+        }));
+        let indices: Vec<_> = batches.iter().flatten().map(|(i, _)| *i).collect();
+        assert_eq!(indices, (0..2000).collect::<Vec<_>>());
+
+        let err = TagUpdateError::new(&updates, 7, "invalid tag value");
+        assert_eq!(
+            err.to_string(),
+            "set tag location of table d7 error: invalid tag value"
+        );
+        assert!(tag_batches::<String>(&[], DEFAULT_MAX_SQL_LENGTH).is_empty());
+    }
+
+    #[test]
+    fn parse_tags() {
+        let names = [
+            "table_name",
+            "db_name",
+            "stable_name",
+            "tag_name",
+            "tag_type",
+            "tag_value",
+        ];
+        let row = |name: &str, ty: &str, value: Option<&str>| {
+            vec![
+                Value::VarChar("d1".to_string()),
+                Value::VarChar("db".to_string()),
+                Value::VarChar("meters".to_string()),
+                Value::VarChar(name.to_string()),
+                Value::VarChar(ty.to_string()),
+                value.map_or(Value::Null(Ty::VarChar), |v| Value::VarChar(v.to_string())),
+            ]
+        };
+        let rows = [
+            row("group_id", "INT", Some("2")),
+            row("location", "VARCHAR(24)", Some(" it's ")),
+            row("name", "NCHAR(10)", Some("温度")),
+            row("enabled", "BOOL", Some("true")),
+            row("ratio", "DOUBLE", Some("0.5")),
+            row("id", "BIGINT UNSIGNED", Some("18446744073709551615")),
+            row("since", "TIMESTAMP", Some("1700000000000")),
+            row("raw", "VARBINARY(8)", Some("\\x0aff")),
+            row("missing", "SMALLINT", None),
+            row("bad", "INT", Some("x")),
+        ];
+        let tags = tags_from_rows(&names, &rows, Precision::Millisecond);
+        assert_eq!(
+            tags,
+            [
+                ("group_id".to_string(), Value::Int(2)),
+                ("location".to_string(), Value::VarChar(" it's ".to_string())),
+                ("name".to_string(), Value::NChar("温度".to_string())),
+                ("enabled".to_string(), Value::Bool(true)),
+                ("ratio".to_string(), Value::Double(0.5)),
+                ("id".to_string(), Value::UBigInt(u64::MAX)),
+                (
+                    "since".to_string(),
+                    Value::Timestamp(Timestamp::new(1_700_000_000_000, Precision::Millisecond))
+                ),
+                ("raw".to_string(), Value::VarBinary(vec![0x0a, 0xff])),
+                ("missing".to_string(), Value::Null(Ty::SmallInt)),
+                ("bad".to_string(), Value::VarChar("x".to_string())),
+            ]
+        );
+        assert!(tags_from_rows(&["name"], &rows, Precision::Millisecond).is_empty());
+    }
+}
@@ -0,0 +1,286 @@
+use std::ops::{Bound, Range, RangeBounds, RangeFrom, RangeInclusive};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+use crate::common::Precision;
+
+/// A range of time to filter a timestamp column, rendered by [TimeRange::to_sql_condition].
+///
+/// Bounds are rounded to the precision of the database, so a bound between two ticks never
+/// includes or excludes a row by mistake:
+///
+/// ```rust
+/// # use chrono::{TimeZone, Utc};
+/// # use taos_query::common::Precision;
+/// # use taos_query::helpers::TimeRange;
+/// let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+/// let range = TimeRange::from(start..start + chrono::Duration::hours(1));
+/// assert_eq!(
+///     range.to_sql_condition("ts", Precision::Millisecond),
+///     "ts >= '2024-01-01T00:00:00.000+00:00' and ts < '2024-01-01T01:00:00.000+00:00'"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    start: Bound<DateTime<Utc>>,
+    end: Bound<DateTime<Utc>>,
+}
+
+impl TimeRange {
+    pub fn new(start: Bound<DateTime<Utc>>, end: Bound<DateTime<Utc>>) -> Self {
+        Self { start, end }
+    }
+
+    /// From `duration` ago until now, now is excluded.
+    pub fn last(duration: Duration) -> Self {
+        let now = Utc::now();
+        let start = chrono::Duration::from_std(duration)
+            .ok()
+            .and_then(|duration| now.checked_sub_signed(duration))
+            .map_or(Bound::Unbounded, Bound::Included);
+        Self::new(start, Bound::Excluded(now))
+    }
+
+    /// The current day in timezone `tz`, see [TimeRange::day].
+    pub fn today<Tz: TimeZone>(tz: &Tz) -> Self {
+        Self::day(Utc::now().with_timezone(tz).date_naive(), tz)
+    }
+
+    /// From the start of `date` to the start of the next day in timezone `tz`.
+    ///
+    /// The day is 23 or 25 hours long on DST transitions. If midnight is skipped by a
+    /// transition, the day starts at the first local time after it.
+    pub fn day<Tz: TimeZone>(date: NaiveDate, tz: &Tz) -> Self {
+        let start = start_of_day(date, tz);
+        let end = date
+            .succ_opt()
+            .and_then(|next| start_of_day(next, tz))
+            .map_or(Bound::Unbounded, Bound::Excluded);
+        Self::new(start.map_or(Bound::Unbounded, Bound::Included), end)
+    }
+
+    pub fn start(&self) -> Bound<DateTime<Utc>> {
+        self.start
+    }
+
+    pub fn end(&self) -> Bound<DateTime<Utc>> {
+        self.end
+    }
+
+    /// Condition of `column` in the range, for a database of `precision`.
+    ///
+    /// `column` is written as is, quote it if needed. Bounds are RFC3339 literals with the
+    /// fractional digits of the precision. An unbounded range is `{column} is not null`.
+    pub fn to_sql_condition(&self, column: &str, precision: Precision) -> String {
+        let start = match self.start {
+            Bound::Included(t) => Some((">=", ceil(t, precision))),
+            Bound::Excluded(t) => Some((">", floor(t, precision))),
+            Bound::Unbounded => None,
+        };
+        let end = match self.end {
+            Bound::Included(t) => Some(("<=", floor(t, precision))),
+            Bound::Excluded(t) => Some(("<", ceil(t, precision))),
+            Bound::Unbounded => None,
+        };
+        let conditions: Vec<_> = [start, end]
+            .into_iter()
+            .flatten()
+            .map(|(op, t)| format!("{column} {op} '{}'", literal(t, precision)))
+            .collect();
+        if conditions.is_empty() {
+            format!("{column} is not null")
+        } else {
+            conditions.join(" and ")
+        }
+    }
+}
+
+impl RangeBounds<DateTime<Utc>> for TimeRange {
+    fn start_bound(&self) -> Bound<&DateTime<Utc>> {
+        self.start.as_ref()
+    }
+
+    fn end_bound(&self) -> Bound<&DateTime<Utc>> {
+        self.end.as_ref()
+    }
+}
+
+impl From<Range<DateTime<Utc>>> for TimeRange {
+    fn from(range: Range<DateTime<Utc>>) -> Self {
+        Self::new(Bound::Included(range.start), Bound::Excluded(range.end))
+    }
+}
+
+impl From<RangeInclusive<DateTime<Utc>>> for TimeRange {
+    fn from(range: RangeInclusive<DateTime<Utc>>) -> Self {
+        let (start, end) = range.into_inner();
+        Self::new(Bound::Included(start), Bound::Included(end))
+    }
+}
+
+impl From<RangeFrom<DateTime<Utc>>> for TimeRange {
+    fn from(range: RangeFrom<DateTime<Utc>>) -> Self {
+        Self::new(Bound::Included(range.start), Bound::Unbounded)
+    }
+}
+
+/// The first local time of `date` in `tz`, in UTC.
+fn start_of_day<Tz: TimeZone>(date: NaiveDate, tz: &Tz) -> Option<DateTime<Utc>> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    // skipped local times are in a DST gap, which is at most a few hours.
+    (0..24 * 60).find_map(|minutes| {
+        tz.from_local_datetime(&(midnight + chrono::Duration::minutes(minutes)))
+            .earliest()
+            .map(|t| t.with_timezone(&Utc))
+    })
+}
+
+/// Nanoseconds of a tick in `precision`.
+fn tick(precision: Precision) -> u32 {
+    match precision {
+        Precision::Millisecond => 1_000_000,
+        Precision::Microsecond => 1_000,
+        Precision::Nanosecond => 1,
+    }
+}
+
+/// The latest tick not after `t`.
+fn floor(t: DateTime<Utc>, precision: Precision) -> DateTime<Utc> {
+    let rem = t.timestamp_subsec_nanos() % tick(precision);
+    t - chrono::Duration::nanoseconds(rem as i64)
+}
+
+/// The earliest tick not before `t`.
+fn ceil(t: DateTime<Utc>, precision: Precision) -> DateTime<Utc> {
+    let floor = floor(t, precision);
+    if floor == t {
+        t
+    } else {
+        floor + chrono::Duration::nanoseconds(tick(precision) as i64)
+    }
+}
+
+fn literal(t: DateTime<Utc>, precision: Precision) -> String {
+    let format = match precision {
+        Precision::Millisecond => "%Y-%m-%dT%H:%M:%S%.3f%:z",
+        Precision::Microsecond => "%Y-%m-%dT%H:%M:%S%.6f%:z",
+        Precision::Nanosecond => "%Y-%m-%dT%H:%M:%S%.9f%:z",
+    };
+    t.format(format).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+    use chrono_tz::America::New_York;
+
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn precision_literals() {
+        let range = TimeRange::from(utc("2024-01-01T00:00:00Z")..utc("2024-01-02T00:00:00Z"));
+        assert_eq!(
+            range.to_sql_condition("ts", Precision::Millisecond),
+            "ts >= '2024-01-01T00:00:00.000+00:00' and ts < '2024-01-02T00:00:00.000+00:00'"
+        );
+        assert_eq!(
+            range.to_sql_condition("ts", Precision::Microsecond),
+            "ts >= '2024-01-01T00:00:00.000000+00:00' and ts < '2024-01-02T00:00:00.000000+00:00'"
+        );
+        assert_eq!(
+            range.to_sql_condition("`ts`", Precision::Nanosecond),
+            "`ts` >= '2024-01-01T00:00:00.000000000+00:00' \
+             and `ts` < '2024-01-02T00:00:00.000000000+00:00'"
+        );
+    }
+
+    #[test]
+    fn bounds() {
+        let start = utc("2024-01-01T08:00:00.001500+08:00");
+        let end = utc("2024-01-01T08:00:00.002500+08:00");
+
+        // half-open, bounds between ticks are rounded up.
+        assert_eq!(
+            TimeRange::from(start..end).to_sql_condition("ts", Precision::Millisecond),
+            "ts >= '2024-01-01T00:00:00.002+00:00' and ts < '2024-01-01T00:00:00.003+00:00'"
+        );
+        // closed, the upper bound is rounded down.
+        assert_eq!(
+            TimeRange::from(start..=end).to_sql_condition("ts", Precision::Millisecond),
+            "ts >= '2024-01-01T00:00:00.002+00:00' and ts <= '2024-01-01T00:00:00.002+00:00'"
+        );
+        assert_eq!(
+            TimeRange::from(start..=end).to_sql_condition("ts", Precision::Microsecond),
+            "ts >= '2024-01-01T00:00:00.001500+00:00' and ts <= '2024-01-01T00:00:00.002500+00:00'"
+        );
+        // excluded lower bound is rounded down.
+        assert_eq!(
+            TimeRange::new(Bound::Excluded(start), Bound::Unbounded)
+                .to_sql_condition("ts", Precision::Millisecond),
+            "ts > '2024-01-01T00:00:00.001+00:00'"
+        );
+        assert_eq!(
+            TimeRange::from(start..).to_sql_condition("ts", Precision::Millisecond),
+            "ts >= '2024-01-01T00:00:00.002+00:00'"
+        );
+        assert_eq!(
+            TimeRange::new(Bound::Unbounded, Bound::Unbounded)
+                .to_sql_condition("ts", Precision::Millisecond),
+            "ts is not null"
+        );
+
+        let range = TimeRange::from(start..end);
+        assert!(range.contains(&start));
+        assert!(!range.contains(&end));
+    }
+
+    #[test]
+    fn days() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let range = TimeRange::day(date, &FixedOffset::east_opt(8 * 3600).unwrap());
+        assert_eq!(
+            range.to_sql_condition("ts", Precision::Millisecond),
+            "ts >= '2023-12-31T16:00:00.000+00:00' and ts < '2024-01-01T16:00:00.000+00:00'"
+        );
+
+        let today = TimeRange::today(&Utc);
+        assert!(today.contains(&Utc::now()));
+
+        let last = TimeRange::last(Duration::from_secs(60));
+        let (Bound::Included(start), Bound::Excluded(end)) = (last.start(), last.end()) else {
+            panic!("unexpected bounds: {last:?}");
+        };
+        assert_eq!(end - start, chrono::Duration::seconds(60));
+        assert_eq!(TimeRange::last(Duration::MAX).start(), Bound::Unbounded);
+    }
+
+    #[test]
+    fn dst_days() {
+        // 2024-03-10 is 23 hours long in New York, clocks jump from 02:00 to 03:00.
+        let range = TimeRange::day(NaiveDate::from_ymd_opt(2024, 3, 10).unwrap(), &New_York);
+        assert_eq!(
+            range.to_sql_condition("ts", Precision::Millisecond),
+            "ts >= '2024-03-10T05:00:00.000+00:00' and ts < '2024-03-11T04:00:00.000+00:00'"
+        );
+        // 2024-11-03 is 25 hours long, 01:00 to 02:00 repeats.
+        let range = TimeRange::day(NaiveDate::from_ymd_opt(2024, 11, 3).unwrap(), &New_York);
+        assert_eq!(
+            range.to_sql_condition("ts", Precision::Millisecond),
+            "ts >= '2024-11-03T04:00:00.000+00:00' and ts < '2024-11-04T05:00:00.000+00:00'"
+        );
+        // midnight is skipped in Havana on 2024-03-10, the day starts at 01:00.
+        let range = TimeRange::day(
+            NaiveDate::from_ymd_opt(2024, 3, 10).unwrap(),
+            &chrono_tz::America::Havana,
+        );
+        assert_eq!(
+            range.start(),
+            Bound::Included(utc("2024-03-10T01:00:00-04:00"))
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+
+use crate::common::Timestamp;
+
+pub(super) const WINDOW_FIELDS: &[&str] = &["_wstart", "_wend", "_wduration"];
+
+/// Window pseudo columns `_wstart`, `_wend` and `_wduration` of window queries.
+///
+/// The columns are found by name wherever they are in the row, flatten it into the record
+/// struct with the aggregate fields:
+///
+/// ```rust
+/// # use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, RawBlock};
+/// # use taos_query::helpers::Window;
+/// #[derive(serde::Deserialize)]
+/// struct Record {
+///     #[serde(flatten)]
+///     window: Window,
+///     #[serde(rename = "avg(v)")]
+///     avg: f64,
+/// }
+///
+/// // select avg(v), _wstart, _wduration from tb interval(10a)
+/// let views = [
+///     ColumnView::from_doubles(vec![1.5]),
+///     ColumnView::from_millis_timestamp(vec![10]),
+///     ColumnView::from_big_ints(vec![10]),
+/// ];
+/// let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+/// block.with_field_names(["avg(v)", "_wstart", "_wduration"]);
+/// let record: Record = block.deserialize().next().unwrap().unwrap();
+/// assert_eq!(record.window.start.as_raw_i64(), 10);
+/// assert_eq!(record.window.duration, Some(std::time::Duration::from_millis(10)));
+/// assert_eq!(record.window.end, None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    /// Window start time, `_wstart`.
+    pub start: Timestamp,
+    /// Window end time, `_wend`, `None` if not selected.
+    pub end: Option<Timestamp>,
+    /// Window duration, `_wduration`, `None` if not selected.
+    pub duration: Option<Duration>,
+}
+
+/// Convert `_wduration` in the precision of `start`.
+fn duration_of(start: Timestamp, duration: i64) -> Option<Duration> {
+    let duration = u64::try_from(duration).ok()?;
+    Some(match start {
+        Timestamp::Milliseconds(_) => Duration::from_millis(duration),
+        Timestamp::Microseconds(_) => Duration::from_micros(duration),
+        Timestamp::Nanoseconds(_) => Duration::from_nanos(duration),
+    })
+}
+
+impl<'de> Deserialize<'de> for Window {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct WindowVisitor;
+
+        impl<'de> Visitor<'de> for WindowVisitor {
+            type Value = Window;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("window pseudo columns")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let (mut start, mut end, mut duration) = (None, None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "_wstart" => start = Some(map.next_value::<Timestamp>()?),
+                        "_wend" => end = map.next_value::<Option<Timestamp>>()?,
+                        "_wduration" => duration = map.next_value::<Option<i64>>()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                let start = start.ok_or_else(|| serde::de::Error::missing_field("_wstart"))?;
+                Ok(Window {
+                    start,
+                    end,
+                    duration: duration.and_then(|d| duration_of(start, d)),
+                })
+            }
+        }
+
+        deserializer.deserialize_struct("Window", WINDOW_FIELDS, WindowVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::common::views::{views_to_raw_block, TimestampView};
+    use crate::common::{ColumnView, Precision, RawBlock};
+
+    fn micros(values: Vec<impl Into<Option<i64>>>) -> ColumnView {
+        ColumnView::Timestamp(TimestampView::from_micros(values))
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Interval {
+        #[serde(rename = "count(*)")]
+        count: i64,
+        #[serde(flatten)]
+        window: Window,
+        #[serde(rename = "avg(v)")]
+        avg: Option<f64>,
+        ts: Timestamp,
+    }
+
+    #[test]
+    fn flatten_window() {
+        // select count(*), _wend, avg(v), _wduration, last(ts) as ts, _wstart ... interval(1s)
+        let views = [
+            ColumnView::from_big_ints(vec![2, 1]),
+            micros(vec![Some(1_000_000), None]),
+            ColumnView::from_doubles(vec![Some(1.5), None]),
+            ColumnView::from_big_ints(vec![1_000_000, 1_000_000]),
+            micros(vec![999_999, 1_999_999]),
+            micros(vec![0, 1_000_000]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Microsecond);
+        block.with_field_names(["count(*)", "_wend", "avg(v)", "_wduration", "ts", "_wstart"]);
+
+        let records: Vec<Interval> = block.deserialize().try_collect().unwrap();
+        assert_eq!(
+            records,
+            [
+                Interval {
+                    count: 2,
+                    window: Window {
+                        start: Timestamp::Microseconds(0),
+                        end: Some(Timestamp::Microseconds(1_000_000)),
+                        duration: Some(Duration::from_secs(1)),
+                    },
+                    avg: Some(1.5),
+                    ts: Timestamp::Microseconds(999_999),
+                },
+                Interval {
+                    count: 1,
+                    window: Window {
+                        start: Timestamp::Microseconds(1_000_000),
+                        end: None,
+                        duration: Some(Duration::from_secs(1)),
+                    },
+                    avg: None,
+                    ts: Timestamp::Microseconds(1_999_999),
+                },
+            ]
+        );
+
+        // Deserialize window only.
+        let windows: Vec<Window> = block.deserialize().try_collect().unwrap();
+        assert_eq!(windows[1], records[1].window);
+
+        // `_wstart` is required.
+        block.with_field_names(["count(*)", "_wend", "avg(v)", "_wduration", "ts", "start"]);
+        let err = block.deserialize::<Interval>().next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("_wstart"), "{err}");
+    }
+}
@@ -1,6 +1,5 @@
 //! This is the common query traits/types for TDengine connectors.
 //!
-#![cfg_attr(nightly, feature(const_slice_index))]
 
 use std::{
     collections::BTreeMap,
@@ -10,14 +9,18 @@ use std::{
     rc::Rc,
 };
 
-pub use mdsn::{Address, Dsn, DsnError, IntoDsn};
+pub use mdsn::{redact_secrets, Address, Dsn, DsnError, IntoDsn};
 pub use serde::de::value::Error as DeError;
+pub use serde::de::DeserializeOwned;
 
 mod error;
 pub use error::*;
 
 pub mod common;
+mod conn_state;
+pub use conn_state::{ConnState, ConnStateNotifier, StateListener};
 mod de;
+pub mod diagnostics;
 pub mod helpers;
 mod insert;
 
@@ -29,9 +32,17 @@ pub use iter::*;
 
 pub use common::RawBlock;
 
+#[cfg(feature = "sqlparser")]
+pub mod sql;
 pub mod stmt;
+pub mod testing;
 pub mod tmq;
 
+#[cfg(feature = "async")]
+pub mod migrate;
+#[cfg(feature = "async")]
+pub mod transfer;
+
 pub mod prelude;
 
 pub use prelude::sync::{Fetchable, Queryable};
@@ -204,14 +215,14 @@ impl<T: TBuilder> Manager<T> {
         let mut dsn = dsn.into_dsn()?;
 
         let params = T::available_params();
-        let (valid, not): (BTreeMap<_, _>, BTreeMap<_, _>) = dsn
+        let (valid, not): (Vec<_>, Vec<_>) = dsn
             .params
-            .into_iter()
+            .drain(..)
             .partition(|(key, _)| params.contains(&key.as_str()));
 
-        dsn.params = valid;
+        dsn.params.extend(valid);
 
-        T::from_dsn(dsn).map(|builder| (Manager::new(builder), not))
+        T::from_dsn(dsn).map(|builder| (Manager::new(builder), not.into_iter().collect()))
     }
 
     #[cfg(feature = "r2d2")]
@@ -382,6 +393,14 @@ mod tests {
             Ok(1)
         }
 
+        /// Overridden as `Taos` does, wrappers must forward to it.
+        fn exec_with_warnings<T: AsRef<str>>(
+            &self,
+            _sql: T,
+        ) -> Result<crate::common::ExecResult, Self::Error> {
+            Ok(crate::common::ExecResult::new(2, Vec::new()))
+        }
+
         fn write_raw_meta(&self, _: &RawMeta) -> Result<(), Self::Error> {
             Ok(())
         }
@@ -467,4 +486,212 @@ mod tests {
             }
         }
     }
+
+    /// Builds [Conn] for pool.
+    struct ConnBuilder;
+
+    impl TBuilder for ConnBuilder {
+        type Target = Conn;
+
+        type Error = Error;
+
+        fn available_params() -> &'static [&'static str] {
+            &[]
+        }
+
+        fn from_dsn<D: IntoDsn>(_dsn: D) -> Result<Self, Self::Error> {
+            Ok(Self)
+        }
+
+        fn client_version() -> &'static str {
+            "3"
+        }
+
+        fn ready(&self) -> bool {
+            true
+        }
+
+        fn build(&self) -> Result<Self::Target, Self::Error> {
+            Ok(Conn)
+        }
+
+        fn ping(&self, _: &mut Self::Target) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn server_version(&self) -> Result<&str, Self::Error> {
+            Ok("3")
+        }
+    }
+
+    #[test]
+    fn queryable_by_deref() {
+        fn load<T: Queryable>(taos: T) -> usize {
+            taos.exec("insert").unwrap_or_default()
+        }
+        let conn = Conn;
+        assert_eq!(load(&conn), 1);
+        assert_eq!(load(&&conn), 1);
+        assert_eq!(load(Box::new(Conn)), 1);
+        assert_eq!(load(std::sync::Arc::new(Conn)), 1);
+
+        let pool = ConnBuilder.pool().unwrap();
+        assert_eq!(load(pool.get().unwrap()), 1);
+        assert_eq!(load(&pool.get().unwrap()), 1);
+
+        fn exec_with_warnings<T: Queryable>(taos: T) -> usize {
+            taos.exec_with_warnings("insert")
+                .map(|res| res.affected_rows())
+                .unwrap_or_default()
+        }
+        assert_eq!(exec_with_warnings(&conn), 2);
+        assert_eq!(exec_with_warnings(&&conn), 2);
+        assert_eq!(exec_with_warnings(Box::new(Conn)), 2);
+        assert_eq!(exec_with_warnings(std::sync::Arc::new(Conn)), 2);
+        assert_eq!(exec_with_warnings(pool.get().unwrap()), 2);
+    }
+
+    #[cfg(feature = "async")]
+    mod asyn {
+        use std::task::{Context, Poll};
+
+        use super::*;
+
+        #[derive(Debug)]
+        struct AsyncConn;
+
+        struct AsyncResultSet;
+
+        impl AsyncFetchable for AsyncResultSet {
+            type Error = Error;
+
+            fn affected_rows(&self) -> i32 {
+                0
+            }
+
+            fn precision(&self) -> Precision {
+                Precision::Millisecond
+            }
+
+            fn fields(&self) -> &[Field] {
+                &[]
+            }
+
+            fn summary(&self) -> (usize, usize) {
+                (0, 0)
+            }
+
+            fn update_summary(&mut self, _rows: usize) {}
+
+            fn fetch_raw_block(
+                &mut self,
+                _cx: &mut Context<'_>,
+            ) -> Poll<Result<Option<RawBlock>, Self::Error>> {
+                Poll::Ready(Ok(None))
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl AsyncQueryable for AsyncConn {
+            type Error = anyhow::Error;
+
+            type AsyncResultSet = AsyncResultSet;
+
+            async fn query<T: AsRef<str> + Send + Sync>(
+                &self,
+                _sql: T,
+            ) -> Result<AsyncResultSet, Self::Error> {
+                Ok(AsyncResultSet)
+            }
+
+            async fn exec<T: AsRef<str> + Send + Sync>(
+                &self,
+                _sql: T,
+            ) -> Result<usize, Self::Error> {
+                Ok(1)
+            }
+
+            async fn exec_with_warnings<T: AsRef<str> + Send + Sync>(
+                &self,
+                _sql: T,
+            ) -> Result<crate::common::ExecResult, Self::Error> {
+                Ok(crate::common::ExecResult::new(2, Vec::new()))
+            }
+
+            async fn write_raw_meta(&self, _: &RawMeta) -> Result<(), Self::Error> {
+                Ok(())
+            }
+
+            async fn write_raw_block(&self, _: &RawBlock) -> Result<(), Self::Error> {
+                Ok(())
+            }
+        }
+
+        struct AsyncConnBuilder;
+
+        impl TBuilder for AsyncConnBuilder {
+            type Target = AsyncConn;
+
+            type Error = Error;
+
+            fn available_params() -> &'static [&'static str] {
+                &[]
+            }
+
+            fn from_dsn<D: IntoDsn>(_dsn: D) -> Result<Self, Self::Error> {
+                Ok(Self)
+            }
+
+            fn client_version() -> &'static str {
+                "3"
+            }
+
+            fn ready(&self) -> bool {
+                true
+            }
+
+            fn build(&self) -> Result<Self::Target, Self::Error> {
+                Ok(AsyncConn)
+            }
+
+            fn ping(&self, _: &mut Self::Target) -> Result<(), Self::Error> {
+                Ok(())
+            }
+
+            fn server_version(&self) -> Result<&str, Self::Error> {
+                Ok("3")
+            }
+        }
+
+        #[tokio::test]
+        async fn async_queryable_by_deref() {
+            async fn load<T: AsyncQueryable>(taos: T) -> usize {
+                taos.exec("insert").await.unwrap()
+            }
+            let conn = AsyncConn;
+            assert_eq!(load(&conn).await, 1);
+            assert_eq!(load(Box::new(AsyncConn)).await, 1);
+            assert_eq!(load(std::sync::Arc::new(AsyncConn)).await, 1);
+
+            let pool = AsyncConnBuilder.pool().unwrap();
+            assert_eq!(load(pool.get().unwrap()).await, 1);
+            assert_eq!(load(&pool.get().unwrap()).await, 1);
+
+            // Spawn with a shared connection.
+            let conn = std::sync::Arc::new(AsyncConn);
+            let task = tokio::spawn(load(conn.clone()));
+            assert_eq!(task.await.unwrap(), 1);
+
+            async fn exec_with_warnings<T: AsyncQueryable>(taos: T) -> usize {
+                taos.exec_with_warnings("insert")
+                    .await
+                    .map(|res| res.affected_rows())
+                    .unwrap_or_default()
+            }
+            assert_eq!(exec_with_warnings(&AsyncConn).await, 2);
+            assert_eq!(exec_with_warnings(Box::new(AsyncConn)).await, 2);
+            assert_eq!(exec_with_warnings(conn).await, 2);
+            assert_eq!(exec_with_warnings(pool.get().unwrap()).await, 2);
+        }
+    }
 }
@@ -0,0 +1,341 @@
+//! Schema migration of a table by `ALTER TABLE`, eg. to roll out new columns across
+//! environments.
+//!
+//! [plan] compares a target schema with the table by [describe](AsyncQueryable::describe) into
+//! a [SchemaDiff], whose [Display] is a dry-run of the statements to run, stable for diffing in
+//! CI. [apply] runs the statements. Only additive changes are run: new columns and tags, and
+//! longer variable types. Others, eg. type changes and drops, are [Incompatibility] reported
+//! in the diff and never run.
+//!
+//! ```rust,no_run
+//! # use taos_query::*;
+//! # use taos_query::common::{Describe, Ty};
+//! # use taos_query::helpers::{ColumnMeta, Described};
+//! # async fn migrate_meters<T: AsyncQueryable>(taos: &T) -> Result<(), T::Error> {
+//! let column = |field: &str, ty, length| Described { field: field.to_string(), ty, length };
+//! let target: Describe = [
+//!     ColumnMeta::Column(column("ts", Ty::Timestamp, 8)),
+//!     ColumnMeta::Column(column("current", Ty::Float, 4)),
+//!     ColumnMeta::Column(column("firmware", Ty::VarChar, 32)),
+//!     ColumnMeta::Tag(column("location", Ty::VarChar, 64)),
+//! ]
+//! .into_iter()
+//! .collect();
+//! let diff = taos_query::migrate::plan(taos, "meters", &target).await?;
+//! println!("{diff}");
+//! for applied in taos_query::migrate::apply(taos, &diff).await {
+//!     applied.result?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::{self, Display};
+
+use crate::common::Ty;
+use crate::helpers::{ColumnMeta, Described};
+use crate::prelude::AsyncQueryable;
+use crate::util::quote_ident;
+
+/// Length of a variable type column or tag to increase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LengthChange {
+    pub field: String,
+    pub ty: Ty,
+    pub is_tag: bool,
+    pub from: usize,
+    pub to: usize,
+}
+
+/// Changes of a schema that are not run by [apply].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// The column or tag is of another type in the target.
+    TypeChanged { field: String, from: Ty, to: Ty },
+    /// The variable type is shorter in the target.
+    Shortened {
+        field: String,
+        from: usize,
+        to: usize,
+    },
+    /// The column or tag is not in the target.
+    Dropped { field: String, is_tag: bool },
+    /// A column in the table is a tag in the target, or the other way around.
+    KindChanged { field: String, is_tag: bool },
+    /// Tags can't be added to a normal table.
+    TagOfNormalTable(String),
+}
+
+impl Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = |is_tag: bool| if is_tag { "tag" } else { "column" };
+        match self {
+            Self::TypeChanged { field, from, to } => {
+                write!(f, "`{field}` changes type from {from} to {to}")
+            }
+            Self::Shortened { field, from, to } => {
+                write!(f, "`{field}` shortens from {from} to {to}")
+            }
+            Self::Dropped { field, is_tag } => write!(f, "{} `{field}` is dropped", kind(*is_tag)),
+            Self::KindChanged { field, is_tag } => {
+                let (from, to) = (kind(!is_tag), kind(*is_tag));
+                write!(f, "`{field}` changes from {from} to {to}")
+            }
+            Self::TagOfNormalTable(field) => write!(f, "tag `{field}` of a normal table"),
+        }
+    }
+}
+
+/// Changes of a table to a target schema, fields are matched by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaDiff {
+    pub table: String,
+    /// The table is a super table, altered by `ALTER STABLE`.
+    pub is_stable: bool,
+    /// Columns only in the target, in the target order.
+    pub add_columns: Vec<Described>,
+    /// Columns and tags with longer variable types in the target.
+    pub modify_lengths: Vec<LengthChange>,
+    /// Tags only in the target, in the target order.
+    pub add_tags: Vec<Described>,
+    pub incompatible: Vec<Incompatibility>,
+}
+
+impl SchemaDiff {
+    /// Diff of `existing` fields of `table` to `target` ones.
+    pub fn new(table: &str, existing: &[ColumnMeta], target: &[ColumnMeta]) -> Self {
+        let is_stable = existing.iter().any(ColumnMeta::is_tag);
+        let mut diff = SchemaDiff {
+            table: table.to_string(),
+            is_stable,
+            add_columns: Vec::new(),
+            modify_lengths: Vec::new(),
+            add_tags: Vec::new(),
+            incompatible: Vec::new(),
+        };
+        for meta in target {
+            let field = meta.field().to_string();
+            let Some(old) = existing.iter().find(|old| old.field() == meta.field()) else {
+                match meta {
+                    ColumnMeta::Column(desc) => diff.add_columns.push(desc.clone()),
+                    ColumnMeta::Tag(desc) if is_stable => diff.add_tags.push(desc.clone()),
+                    ColumnMeta::Tag(_) => {
+                        diff.incompatible
+                            .push(Incompatibility::TagOfNormalTable(field));
+                    }
+                }
+                continue;
+            };
+            if old.is_tag() != meta.is_tag() {
+                let is_tag = meta.is_tag();
+                diff.incompatible
+                    .push(Incompatibility::KindChanged { field, is_tag });
+            } else if old.ty() != meta.ty() {
+                let (from, to) = (old.ty(), meta.ty());
+                diff.incompatible
+                    .push(Incompatibility::TypeChanged { field, from, to });
+            } else if meta.ty().is_var_type() && old.length() > meta.length() {
+                let (from, to) = (old.length(), meta.length());
+                diff.incompatible
+                    .push(Incompatibility::Shortened { field, from, to });
+            } else if meta.ty().is_var_type() && old.length() < meta.length() {
+                diff.modify_lengths.push(LengthChange {
+                    field,
+                    ty: meta.ty(),
+                    is_tag: meta.is_tag(),
+                    from: old.length(),
+                    to: meta.length(),
+                });
+            }
+        }
+        for old in existing {
+            if !target.iter().any(|meta| meta.field() == old.field()) {
+                diff.incompatible.push(Incompatibility::Dropped {
+                    field: old.field().to_string(),
+                    is_tag: old.is_tag(),
+                });
+            }
+        }
+        diff
+    }
+
+    /// Nothing to run and nothing incompatible, the table is of the target schema.
+    pub fn is_empty(&self) -> bool {
+        self.add_columns.is_empty()
+            && self.modify_lengths.is_empty()
+            && self.add_tags.is_empty()
+            && self.incompatible.is_empty()
+    }
+
+    /// The `ALTER` statements to run: columns added, lengths increased, then tags added.
+    pub fn statements(&self) -> Vec<String> {
+        let alter = if self.is_stable {
+            format!("ALTER STABLE {}", quote_ident(&self.table))
+        } else {
+            format!("ALTER TABLE {}", quote_ident(&self.table))
+        };
+        let columns = self
+            .add_columns
+            .iter()
+            .map(|desc| format!("{alter} ADD COLUMN {}", desc.sql_repr()));
+        let lengths = self.modify_lengths.iter().map(|change| {
+            let kind = if change.is_tag { "TAG" } else { "COLUMN" };
+            let desc = Described {
+                field: change.field.clone(),
+                ty: change.ty,
+                length: change.to,
+            };
+            format!("{alter} MODIFY {kind} {}", desc.sql_repr())
+        });
+        let tags = self
+            .add_tags
+            .iter()
+            .map(|desc| format!("{alter} ADD TAG {}", desc.sql_repr()));
+        columns.chain(lengths).chain(tags).collect()
+    }
+}
+
+/// A dry-run of the diff: one statement per line, then incompatible changes as comments.
+impl Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for sql in self.statements() {
+            writeln!(f, "{sql};")?;
+        }
+        for incompatible in &self.incompatible {
+            writeln!(f, "-- incompatible: {incompatible}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A statement run by [apply].
+#[derive(Debug)]
+pub struct Applied<E> {
+    pub sql: String,
+    pub result: Result<(), E>,
+}
+
+/// Diff of `table` to `target` by [describe](AsyncQueryable::describe) of the table.
+pub async fn plan<T: AsyncQueryable>(
+    taos: &T,
+    table: &str,
+    target: &[ColumnMeta],
+) -> Result<SchemaDiff, T::Error> {
+    let existing = taos.describe(table).await?;
+    Ok(SchemaDiff::new(table, &existing, target))
+}
+
+/// Run the statements of `diff` in order, with a result of each statement. Failed statements
+/// don't stop the later ones, incompatible changes are never run.
+pub async fn apply<T: AsyncQueryable>(taos: &T, diff: &SchemaDiff) -> Vec<Applied<T::Error>> {
+    let mut applied = Vec::new();
+    for sql in diff.statements() {
+        let result = taos.exec(&sql).await.map(|_| ());
+        if let Err(err) = &result {
+            log::warn!("migration of `{}` failed at `{sql}`: {err:?}", diff.table);
+        }
+        applied.push(Applied { sql, result });
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(field: &str, ty: Ty, length: usize) -> ColumnMeta {
+        ColumnMeta::Column(Described {
+            field: field.to_string(),
+            ty,
+            length,
+        })
+    }
+
+    fn tag(field: &str, ty: Ty, length: usize) -> ColumnMeta {
+        ColumnMeta::Tag(Described {
+            field: field.to_string(),
+            ty,
+            length,
+        })
+    }
+
+    fn v1() -> Vec<ColumnMeta> {
+        vec![
+            column("ts", Ty::Timestamp, 8),
+            column("current", Ty::Float, 4),
+            column("note", Ty::VarChar, 16),
+            tag("location", Ty::VarChar, 32),
+        ]
+    }
+
+    #[test]
+    fn additive_changes() {
+        let mut v2 = v1();
+        v2[2] = column("note", Ty::VarChar, 64);
+        v2.insert(3, column("voltage", Ty::Int, 4));
+        v2.push(tag("group id", Ty::Int, 4));
+        v2[4] = tag("location", Ty::VarChar, 64);
+
+        let diff = SchemaDiff::new("meters", &v1(), &v2);
+        assert!(diff.incompatible.is_empty());
+        assert_eq!(
+            diff.to_string(),
+            "ALTER STABLE `meters` ADD COLUMN `voltage` INT;\n\
+             ALTER STABLE `meters` MODIFY COLUMN `note` BINARY(64);\n\
+             ALTER STABLE `meters` MODIFY TAG `location` BINARY(64);\n\
+             ALTER STABLE `meters` ADD TAG `group id` INT;\n"
+        );
+        assert!(SchemaDiff::new("meters", &v2, &v2).is_empty());
+    }
+
+    #[test]
+    fn incompatible_changes() {
+        let target = vec![
+            column("ts", Ty::Timestamp, 8),
+            column("current", Ty::Double, 8),
+            column("note", Ty::VarChar, 8),
+            column("location", Ty::VarChar, 32),
+        ];
+        let diff = SchemaDiff::new("meters", &v1(), &target);
+        assert!(diff.statements().is_empty());
+        assert_eq!(
+            diff.to_string(),
+            "-- incompatible: `current` changes type from FLOAT to DOUBLE\n\
+             -- incompatible: `note` shortens from 16 to 8\n\
+             -- incompatible: `location` changes from tag to column\n"
+        );
+
+        let normal = &v1()[..3];
+        let diff = SchemaDiff::new(
+            "d0",
+            normal,
+            &[column("ts", Ty::Timestamp, 8), tag("t", Ty::Int, 4)],
+        );
+        assert!(!diff.is_stable);
+        assert_eq!(
+            diff.incompatible,
+            [
+                Incompatibility::TagOfNormalTable("t".to_string()),
+                Incompatibility::Dropped {
+                    field: "current".to_string(),
+                    is_tag: false
+                },
+                Incompatibility::Dropped {
+                    field: "note".to_string(),
+                    is_tag: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn normal_table_statements() {
+        let normal = &v1()[..3];
+        let mut target = normal.to_vec();
+        target.push(column("payload", Ty::VarBinary, 128));
+        let diff = SchemaDiff::new("d0", normal, &target);
+        assert_eq!(
+            diff.statements(),
+            ["ALTER TABLE `d0` ADD COLUMN `payload` VARBINARY(128)"]
+        );
+    }
+}
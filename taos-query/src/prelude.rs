@@ -1,10 +1,17 @@
 mod _priv {
     pub use crate::common::{
-        AlterType, BorrowedValue, ColumnView, Field, JsonMeta, MetaAlter, MetaCreate, MetaDrop,
-        Precision, RawBlock, RawMeta, TagWithValue, Ty, Value,
+        AlterType, BorrowedValue, ColumnView, Field, JsonMeta, LogConfig, MetaAlter, MetaCreate,
+        MetaDrop, NullPolicy, Precision, RawBlock, RawMeta, SchemalessPrecision,
+        SchemalessProtocol, SmlData, TagWithValue, Ty, UnsortedPolicy, Value, WriteOptions,
     };
-    pub use crate::util::{Inlinable, InlinableRead, InlinableWrite};
+    pub use crate::common::{BlockProfile, ProfileOptions, Profiler};
+    pub use crate::common::{CsvOptions, DedupStrategy, TimestampFormat};
+    pub use crate::common::{ExecResult, Warning, WarningListener};
+    pub use crate::common::{LimitMode, QueryOptions};
+    pub use crate::helpers::{GrantInfo, StreamBuilder, StreamInfo, Trigger};
+    pub use crate::util::{AuditEntry, Inlinable, InlinableRead, InlinableWrite, RateLimit};
     pub use crate::TBuilder;
+    pub use crate::{ConnState, StateListener};
     #[cfg(feature = "r2d2")]
     pub use crate::{Pool, PoolBuilder};
     #[cfg(feature = "r2d2")]
@@ -17,7 +24,7 @@ mod _priv {
     pub use crate::tmq::{IsOffset, MessageSet, Timeout};
 }
 
-pub use crate::tmq::{AsAsyncConsumer, IsAsyncData, IsAsyncMeta};
+pub use crate::tmq::{AsAsyncConsumer, IsAsyncData, IsAsyncMeta, OffsetStore, OffsetValue};
 pub use _priv::*;
 pub use futures::stream::{Stream, StreamExt, TryStreamExt};
 pub use r#async::*;
@@ -29,7 +36,7 @@ pub mod sync {
     pub use super::_priv::*;
 
     pub use crate::stmt::Bindable;
-    pub use crate::tmq::{AsConsumer, IsMeta};
+    pub use crate::tmq::{AsConsumer, IsData, IsMeta};
 
     use serde::de::DeserializeOwned;
 
@@ -38,6 +45,8 @@ pub mod sync {
 
     use crate::common::*;
     use crate::helpers::*;
+    use crate::tmq::admin::SubscriptionInfo;
+    use crate::util::quote_ident;
 
     use crate::common::RawBlock;
 
@@ -48,9 +57,8 @@ pub mod sync {
         T: Fetchable,
     {
         iter: IBlockIter<'a, T>,
-        block: Option<RawBlock>,
-        // row: usize,
-        rows: Option<RowsIter<'a>>,
+        rows: Option<IntoRowsIter<'a>>,
+        null_policy: Option<NullPolicy>,
     }
 
     impl<'a, T> IRowsIter<'a, T>
@@ -58,11 +66,12 @@ pub mod sync {
         T: Fetchable,
     {
         fn fetch(&mut self) -> Result<Option<RowView<'a>>, T::Error> {
-            if let Some(block) = self.iter.next().transpose()? {
-                self.block = Some(block);
-                self.rows = self.block.as_mut().map(|raw| raw.rows());
-                let row = self.rows.as_mut().unwrap().next();
-                Ok(row)
+            if let Some(mut block) = self.iter.next().transpose()? {
+                if let Some(policy) = &self.null_policy {
+                    block.with_null_policy(policy.clone());
+                }
+                let rows = self.rows.insert(block.into_rows());
+                Ok(rows.next())
             } else {
                 Ok(None)
             }
@@ -135,8 +144,24 @@ pub mod sync {
             self.fields().len()
         }
 
+        /// A stable hash of the fields and precision, see [crate::helpers::schema_hash].
+        fn schema_hash(&self) -> u64 {
+            crate::helpers::schema_hash(self.fields(), self.precision())
+        }
+
+        /// Kind of the query inferred from the fields, see [crate::helpers::query_kind].
+        fn query_kind(&self) -> crate::helpers::QueryKind {
+            crate::helpers::query_kind(self.fields())
+        }
+
         fn summary(&self) -> (usize, usize);
 
+        /// Warnings returned by the server with the result, empty if the connection does not
+        /// report warnings.
+        fn warnings(&self) -> &[Warning] {
+            &[]
+        }
+
         #[doc(hidden)]
         fn update_summary(&mut self, nrows: usize);
 
@@ -152,9 +177,8 @@ pub mod sync {
         fn rows(&mut self) -> IRowsIter<'_, Self> {
             IRowsIter {
                 iter: self.blocks(),
-                block: None,
-                // row: 0,
                 rows: None,
+                null_policy: None,
             }
         }
 
@@ -167,12 +191,128 @@ pub mod sync {
             self.rows().map(|row| Ok(T::deserialize(&mut row?)?))
         }
 
+        /// Deserialize rows with a [NullPolicy] for NULL values in non-`Option` fields.
+        #[allow(clippy::type_complexity)]
+        fn deserialize_with_null_policy<T: DeserializeOwned>(
+            &mut self,
+            policy: NullPolicy,
+        ) -> std::iter::Map<
+            IRowsIter<'_, Self>,
+            fn(Result<RowView, Self::Error>) -> Result<T, Self::Error>,
+        > {
+            let mut rows = self.rows();
+            rows.null_policy = Some(policy);
+            rows.map(|row| Ok(T::deserialize(&mut row?)?))
+        }
+
+        /// Deserialize rows materialized by [RowView::into_buffered], eg. into untagged enums
+        /// or structs denying unknown columns, at the cost of an allocation per row.
+        fn deserialize_buffered<T: DeserializeOwned>(
+            &mut self,
+        ) -> std::iter::Map<
+            IRowsIter<'_, Self>,
+            fn(Result<RowView, Self::Error>) -> Result<T, Self::Error>,
+        > {
+            self.rows().map(|row| Ok(T::deserialize(row?.into_buffered())?))
+        }
+
         fn to_rows_vec(&mut self) -> Result<Vec<Vec<Value>>, Self::Error> {
             self.blocks()
                 .map_ok(|raw| raw.to_values())
                 .flatten_ok()
                 .try_collect()
         }
+
+        /// Stack numeric columns of all blocks into a `f64` matrix, see [RawBlock::to_array2_f64].
+        #[cfg(feature = "ndarray")]
+        fn to_array2_f64(
+            &mut self,
+            columns: Option<&[&str]>,
+        ) -> Result<(ndarray::Array2<f64>, Vec<String>), Self::Error> {
+            self.to_array2_f64_with(columns, ArrayNulls::NaN)
+        }
+
+        /// Stack numeric columns of all blocks into a `f64` matrix, with NULL values converted
+        /// by `nulls`.
+        #[cfg(feature = "ndarray")]
+        fn to_array2_f64_with(
+            &mut self,
+            columns: Option<&[&str]>,
+            nulls: ArrayNulls,
+        ) -> Result<(ndarray::Array2<f64>, Vec<String>), Self::Error> {
+            let mut builder = ArrayBuilder::new(self.fields(), columns, nulls)
+                .map_err(taos_error::Error::from)?;
+            for block in self.blocks() {
+                builder.push(&block?).map_err(taos_error::Error::from)?;
+            }
+            Ok(builder.finish())
+        }
+
+        /// Write all rows as CSV into `wtr`, see [RawBlock::write_csv]. Returns the number of rows.
+        ///
+        /// The header is written from [Fetchable::fields] even if there is no rows.
+        fn write_csv<W: std::io::Write>(
+            &mut self,
+            mut wtr: W,
+            options: &CsvOptions,
+        ) -> Result<usize, Self::Error> {
+            let names = self.fields().iter().map(|f| f.name());
+            write_csv_header(&mut wtr, names, options).map_err(taos_error::Error::from_any)?;
+            let mut rows = 0;
+            for block in self.blocks() {
+                let block = block?;
+                block
+                    .write_csv_rows(&mut wtr, options)
+                    .map_err(taos_error::Error::from_any)?;
+                rows += block.nrows();
+            }
+            wtr.flush().map_err(taos_error::Error::from_any)?;
+            Ok(rows)
+        }
+
+        /// Write all rows as newline-delimited JSON into `wtr`, see [RawBlock::write_ndjson].
+        /// Returns the number of rows.
+        fn write_ndjson<W: std::io::Write>(
+            &mut self,
+            mut wtr: W,
+            timestamp: TimestampFormat,
+        ) -> Result<usize, Self::Error> {
+            let mut rows = 0;
+            for block in self.blocks() {
+                let block = block?;
+                block
+                    .write_ndjson(&mut wtr, timestamp)
+                    .map_err(taos_error::Error::from_any)?;
+                rows += block.nrows();
+            }
+            wtr.flush().map_err(taos_error::Error::from_any)?;
+            Ok(rows)
+        }
+
+        /// Create or truncate the file at `path` and write all rows into it as CSV.
+        fn to_csv_file<P: AsRef<std::path::Path>>(
+            &mut self,
+            path: P,
+            options: &CsvOptions,
+        ) -> Result<usize, Self::Error> {
+            let file = std::fs::File::create(path).map_err(taos_error::Error::from_any)?;
+            self.write_csv(std::io::BufWriter::new(file), options)
+        }
+
+        /// Profile columns of all rows with a reservoir sample of `sample_rows` rows, see
+        /// [BlockProfile].
+        fn profile(&mut self, sample_rows: usize) -> Result<BlockProfile, Self::Error> {
+            self.profile_with(&ProfileOptions::new().sample_rows(sample_rows))
+        }
+
+        /// Profile columns of all rows by `options`, eg. with a seed of the sampling.
+        fn profile_with(&mut self, options: &ProfileOptions) -> Result<BlockProfile, Self::Error> {
+            let mut profiler = Profiler::new(options.clone());
+            for block in self.blocks() {
+                profiler.push(&block?);
+            }
+            Ok(profiler.finish())
+        }
     }
 
     /// The synchronous query trait for TDengine connection.
@@ -190,6 +330,70 @@ pub mod sync {
             self.query(sql).map(|res| res.affected_rows() as _)
         }
 
+        /// Execute a statement and keep warnings returned by the server.
+        fn exec_with_warnings<T: AsRef<str>>(&self, sql: T) -> Result<ExecResult, Self::Error> {
+            self.query(sql)
+                .map(|res| ExecResult::new(res.affected_rows() as _, res.warnings().to_vec()))
+        }
+
+        /// Execute `sql` with `?` placeholders substituted by `params` client-side, see
+        /// [bind_sql_params].
+        ///
+        /// Timestamps are epoch integers in their own precision, which should be the precision of
+        /// the database. Mismatched placeholders and params fail before the sql is sent.
+        fn exec_with_params<T: AsRef<str>, P: ToSqlLiteral>(
+            &self,
+            sql: T,
+            params: &[P],
+        ) -> Result<usize, Self::Error> {
+            let sql = bind_sql_params(sql.as_ref(), params, None)
+                .map_err(<Self::ResultSet as Fetchable>::Error::from)?;
+            self.exec(sql)
+        }
+
+        /// Query `sql` with `?` placeholders substituted by `params` client-side, like
+        /// [Queryable::exec_with_params].
+        fn query_with_params<T: AsRef<str>, P: ToSqlLiteral>(
+            &self,
+            sql: T,
+            params: &[P],
+        ) -> Result<Self::ResultSet, Self::Error> {
+            let sql = bind_sql_params(sql.as_ref(), params, None)
+                .map_err(<Self::ResultSet as Fetchable>::Error::from)?;
+            self.query(sql)
+        }
+
+        /// Execute `sql` with `?` placeholders substituted by `args` of mixed types, like
+        /// [Queryable::exec_with_params].
+        fn exec_with_args<T: AsRef<str>>(
+            &self,
+            sql: T,
+            args: &[Value],
+        ) -> Result<usize, Self::Error> {
+            self.exec_with_params(sql, args)
+        }
+
+        /// Query `sql` with `?` placeholders substituted by `args` of mixed types, like
+        /// [Queryable::query_with_params].
+        ///
+        /// ```rust,no_run
+        /// # use taos_query::{common::{Timestamp, Value}, prelude::sync::*};
+        /// # fn query<Q: Queryable>(taos: &Q) -> Result<(), Q::Error> {
+        /// let rs = taos.query_with_args(
+        ///     "select * from meters where ts > ? and groupid = ?",
+        ///     &[Value::Timestamp(Timestamp::Milliseconds(1704067200000)), Value::Int(1)],
+        /// )?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        fn query_with_args<T: AsRef<str>>(
+            &self,
+            sql: T,
+            args: &[Value],
+        ) -> Result<Self::ResultSet, Self::Error> {
+            self.query_with_params(sql, args)
+        }
+
         fn write_raw_meta(&self, _: &RawMeta) -> Result<(), Self::Error>;
 
         fn write_raw_block(&self, _: &RawBlock) -> Result<(), Self::Error>;
@@ -231,7 +435,7 @@ pub mod sync {
             sql: impl AsRef<str>,
         ) -> Result<(), Self::Error> {
             let (name, sql) = (name.as_ref(), sql.as_ref());
-            let query = format!("create topic if not exists {name} as {sql}");
+            let query = format!("create topic if not exists {} as {sql}", quote_ident(name));
 
             self.query(query)?;
             Ok(())
@@ -242,8 +446,12 @@ pub mod sync {
             name: impl AsRef<str>,
             db: impl std::fmt::Display,
         ) -> Result<(), Self::Error> {
-            let name = name.as_ref();
-            let query = format!("create topic if not exists {name} as database `{db}`");
+            let (name, db) = (name.as_ref(), db.to_string());
+            let query = format!(
+                "create topic if not exists {} as database {}",
+                quote_ident(name),
+                quote_ident(&db)
+            );
 
             self.exec(query)?;
             Ok(())
@@ -268,19 +476,306 @@ pub mod sync {
                 .map_err(Into::into)
         }
 
+        /// Schema of `table` by `describe`, or from [Queryable::schema_cache] if it's cached.
         fn describe(&self, table: &str) -> Result<Describe, Self::Error> {
-            Ok(Describe(
-                self.query(format!("describe `{table}`"))?
+            let cache = self.schema_cache();
+            if let Some(describe) = cache.and_then(|cache| cache.get(table)) {
+                return Ok(describe);
+            }
+            let describe = Describe(
+                self.query(format!("describe {}", quote_ident(table)))?
                     .deserialize()
                     .try_collect()?,
-            ))
+            );
+            if let Some(cache) = cache {
+                cache.insert(table, describe.clone());
+            }
+            Ok(describe)
+        }
+
+        /// Schemas of tables cached by the connection, see [SchemaCache]. It's `None` by
+        /// default, ie. each [Queryable::describe] queries the server.
+        fn schema_cache(&self) -> Option<&SchemaCache> {
+            None
         }
 
         /// Check if database exists
         fn database_exists(&self, name: &str) -> Result<bool, Self::Error> {
-            Ok(self.exec(format!("show `{name}`.stables")).is_ok())
+            Ok(self
+                .exec(format!("show {}.stables", quote_ident(name)))
+                .is_ok())
         }
+
+        /// License information of the server, see [GrantInfo].
+        ///
+        /// Queries `information_schema.ins_grants` and falls back to `show grants` for servers
+        /// without it. Servers without grants, like community servers, return
+        /// [GrantInfo::community].
+        fn grant_info(&self) -> Result<GrantInfo, Self::Error> {
+            let mut rs = match self.query("SELECT * FROM information_schema.ins_grants") {
+                Ok(rs) => rs,
+                Err(_) => self.query("show grants")?,
+            };
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let rows = rs.to_rows_vec()?;
+            Ok(GrantInfo::from_rows(&names, &rows))
+        }
+
+        /// Limits of the connection by [SERVER_LIMITS_SQL], see [ServerLimits::from_rows].
+        fn fetch_limits(&self) -> Result<ServerLimits, Self::Error> {
+            let mut rs = self.query(SERVER_LIMITS_SQL)?;
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let rows = rs.to_rows_vec()?;
+            Ok(ServerLimits::from_rows(&names, &rows))
+        }
+
+        /// Limits that sql batches of the connection are split by, eg. in
+        /// [Queryable::set_tags_bulk].
+        ///
+        /// It's [ServerLimits::default] unless the connection caches its limits, as `taos::Taos`
+        /// does by [Queryable::fetch_limits] on the first call.
+        fn limits(&self) -> ServerLimits {
+            ServerLimits::default()
+        }
+
+        /// Create a stream by the sql of [StreamBuilder].
+        fn create_stream(&self, stream: &StreamBuilder) -> Result<(), Self::Error> {
+            self.exec(stream.build_sql())?;
+            Ok(())
+        }
+
+        /// Short for `DROP STREAM [IF EXISTS] {name}`.
+        fn drop_stream(&self, name: &str, if_exists: bool) -> Result<(), Self::Error> {
+            let if_exists = if if_exists { "IF EXISTS " } else { "" };
+            self.exec(format!("DROP STREAM {if_exists}{}", quote_ident(name)))?;
+            Ok(())
+        }
+
+        /// Streams information by `SELECT * FROM information_schema.ins_streams` sql.
+        ///
+        /// ## Compatibility
+        ///
+        /// This is a 3.x-only API.
+        fn streams(&self) -> Result<Vec<StreamInfo>, Self::Error> {
+            let mut rs = self.query("SELECT * FROM information_schema.ins_streams")?;
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let rows = rs.to_rows_vec()?;
+            Ok(StreamInfo::from_rows(&names, &rows))
+        }
+
+        /// Subscriptions by `SELECT * FROM information_schema.ins_subscriptions` sql, see
+        /// [SubscriptionInfo].
+        ///
+        /// ## Compatibility
+        ///
+        /// This is a 3.x-only API.
+        fn subscriptions(&self) -> Result<Vec<SubscriptionInfo>, Self::Error> {
+            let mut rs = self.query("SELECT * FROM information_schema.ins_subscriptions")?;
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let rows = rs.to_rows_vec()?;
+            Ok(SubscriptionInfo::from_rows(&names, &rows))
+        }
+
+        /// Set tag `tag` of child table `table` to `value`, see [set_tag_sql].
+        fn set_tag(&self, table: &str, tag: &str, value: &Value) -> Result<(), Self::Error> {
+            self.exec(set_tag_sql(table, tag, value))?;
+            Ok(())
+        }
+
+        /// Set tags of many child tables, as `(table, tag, value)`.
+        ///
+        /// Statements are executed by [Queryable::exec_many] in batches of at most
+        /// [TAG_BATCH_SQL_BYTES] and [Queryable::limits]. When a batch fails, its statements are executed one by one to
+        /// find the failed update, which is safe as setting a tag is idempotent. Updates before
+        /// the failed one are applied.
+        fn set_tags_bulk<S: AsRef<str>>(
+            &self,
+            updates: &[(S, S, Value)],
+        ) -> Result<(), TagUpdateError<Self::Error>> {
+            for batch in tag_batches(updates, self.limits().max_sql_length) {
+                if self.exec_many(batch.iter().map(|(_, sql)| sql)).is_ok() {
+                    continue;
+                }
+                for (i, sql) in &batch {
+                    self.exec(sql)
+                        .map_err(|err| TagUpdateError::new(updates, *i, err))?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Tag names and values of child table `table` by `show tags from {table}`, see
+        /// [tags_from_rows].
+        ///
+        /// Timestamp tags are in the precision of the result set.
+        fn read_tags(&self, table: &str) -> Result<Vec<(String, Value)>, Self::Error> {
+            let mut rs = self.query(format!("show tags from {}", quote_ident(table)))?;
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let precision = rs.precision();
+            let rows = rs.to_rows_vec()?;
+            Ok(tags_from_rows(&names, &rows, precision))
+        }
+    }
+
+    /// Forward [Queryable] of smart pointers and pool guards to the connection they point to.
+    macro_rules! _impl_queryable_deref {
+        ([$($g:tt)*] $ty:ty => $q:ty $(where $($w:tt)+)?) => {
+            impl<$($g)*> Queryable for $ty $(where $($w)+)? {
+                type Error = <$q as Queryable>::Error;
+
+                type ResultSet = <$q as Queryable>::ResultSet;
+
+                fn query<T: AsRef<str>>(&self, sql: T) -> Result<Self::ResultSet, Self::Error> {
+                    <$q as Queryable>::query(&**self, sql)
+                }
+
+                fn exec<T: AsRef<str>>(&self, sql: T) -> Result<usize, Self::Error> {
+                    <$q as Queryable>::exec(&**self, sql)
+                }
+
+                fn exec_with_warnings<T: AsRef<str>>(
+                    &self,
+                    sql: T,
+                ) -> Result<ExecResult, Self::Error> {
+                    <$q as Queryable>::exec_with_warnings(&**self, sql)
+                }
+
+                fn write_raw_meta(&self, meta: &RawMeta) -> Result<(), Self::Error> {
+                    <$q as Queryable>::write_raw_meta(&**self, meta)
+                }
+
+                fn write_raw_block(&self, block: &RawBlock) -> Result<(), Self::Error> {
+                    <$q as Queryable>::write_raw_block(&**self, block)
+                }
+
+                fn exec_many<T: AsRef<str>, I: IntoIterator<Item = T>>(
+                    &self,
+                    input: I,
+                ) -> Result<usize, Self::Error> {
+                    <$q as Queryable>::exec_many(&**self, input)
+                }
+
+                fn query_one<T: AsRef<str>, O: DeserializeOwned>(
+                    &self,
+                    sql: T,
+                ) -> Result<Option<O>, Self::Error> {
+                    <$q as Queryable>::query_one(&**self, sql)
+                }
+
+                fn server_version(&self) -> Result<Cow<'_, str>, Self::Error> {
+                    <$q as Queryable>::server_version(&**self)
+                }
+
+                fn create_topic(
+                    &self,
+                    name: impl AsRef<str>,
+                    sql: impl AsRef<str>,
+                ) -> Result<(), Self::Error> {
+                    <$q as Queryable>::create_topic(&**self, name, sql)
+                }
+
+                fn create_topic_as_database(
+                    &self,
+                    name: impl AsRef<str>,
+                    db: impl std::fmt::Display,
+                ) -> Result<(), Self::Error> {
+                    <$q as Queryable>::create_topic_as_database(&**self, name, db)
+                }
+
+                fn databases(&self) -> Result<Vec<ShowDatabase>, Self::Error> {
+                    <$q as Queryable>::databases(&**self)
+                }
+
+                fn topics(&self) -> Result<Vec<Topic>, Self::Error> {
+                    <$q as Queryable>::topics(&**self)
+                }
+
+                fn describe(&self, table: &str) -> Result<Describe, Self::Error> {
+                    <$q as Queryable>::describe(&**self, table)
+                }
+
+                fn schema_cache(&self) -> Option<&SchemaCache> {
+                    <$q as Queryable>::schema_cache(&**self)
+                }
+
+                fn database_exists(&self, name: &str) -> Result<bool, Self::Error> {
+                    <$q as Queryable>::database_exists(&**self, name)
+                }
+
+                fn grant_info(&self) -> Result<GrantInfo, Self::Error> {
+                    <$q as Queryable>::grant_info(&**self)
+                }
+
+                fn fetch_limits(&self) -> Result<ServerLimits, Self::Error> {
+                    <$q as Queryable>::fetch_limits(&**self)
+                }
+
+                fn limits(&self) -> ServerLimits {
+                    <$q as Queryable>::limits(&**self)
+                }
+
+                fn create_stream(&self, stream: &StreamBuilder) -> Result<(), Self::Error> {
+                    <$q as Queryable>::create_stream(&**self, stream)
+                }
+
+                fn drop_stream(&self, name: &str, if_exists: bool) -> Result<(), Self::Error> {
+                    <$q as Queryable>::drop_stream(&**self, name, if_exists)
+                }
+
+                fn streams(&self) -> Result<Vec<StreamInfo>, Self::Error> {
+                    <$q as Queryable>::streams(&**self)
+                }
+
+                fn subscriptions(&self) -> Result<Vec<SubscriptionInfo>, Self::Error> {
+                    <$q as Queryable>::subscriptions(&**self)
+                }
+
+                fn set_tag(&self, table: &str, tag: &str, value: &Value) -> Result<(), Self::Error> {
+                    <$q as Queryable>::set_tag(&**self, table, tag, value)
+                }
+
+                fn set_tags_bulk<S: AsRef<str>>(
+                    &self,
+                    updates: &[(S, S, Value)],
+                ) -> Result<(), TagUpdateError<Self::Error>> {
+                    <$q as Queryable>::set_tags_bulk(&**self, updates)
+                }
+
+                fn read_tags(&self, table: &str) -> Result<Vec<(String, Value)>, Self::Error> {
+                    <$q as Queryable>::read_tags(&**self, table)
+                }
+            }
+        };
     }
+
+    _impl_queryable_deref!(['q, Q: Queryable] &'q Q => Q);
+    _impl_queryable_deref!([Q: Queryable] Box<Q> => Q);
+    _impl_queryable_deref!([Q: Queryable] std::sync::Arc<Q> => Q);
+    #[cfg(feature = "r2d2")]
+    _impl_queryable_deref!(
+        [M: r2d2::ManageConnection] r2d2::PooledConnection<M> => M::Connection
+        where M::Connection: Queryable
+    );
 }
 
 mod r#async {
@@ -294,6 +789,8 @@ mod r#async {
     use crate::common::*;
     use crate::helpers::*;
     pub use crate::stmt::Bindable;
+    use crate::tmq::admin::SubscriptionInfo;
+    use crate::util::quote_ident;
 
     pub use super::_priv::*;
     pub use crate::util::AsyncInlinable;
@@ -333,8 +830,8 @@ mod r#async {
 
     pub struct AsyncRows<'a, T> {
         blocks: AsyncBlocks<'a, T>,
-        block: Option<RawBlock>,
-        rows: Option<RowsIter<'a>>,
+        rows: Option<IntoRowsIter<'a>>,
+        null_policy: Option<NullPolicy>,
     }
 
     impl<'a, T> AsyncRows<'a, T>
@@ -348,11 +845,12 @@ mod r#async {
             let poll = self.blocks.try_poll_next_unpin(cx);
             match poll {
                 Poll::Ready(block) => match block.transpose() {
-                    Ok(Some(block)) => {
-                        self.block = Some(block);
-                        self.rows = self.block.as_mut().map(|raw| raw.rows());
-                        let row = self.rows.as_mut().unwrap().next();
-                        Poll::Ready(Ok(row))
+                    Ok(Some(mut block)) => {
+                        if let Some(policy) = &self.null_policy {
+                            block.with_null_policy(policy.clone());
+                        }
+                        let rows = self.rows.insert(block.into_rows());
+                        Poll::Ready(Ok(rows.next()))
                     }
                     Ok(None) => Poll::Ready(Ok(None)),
                     Err(err) => Poll::Ready(Err(err)),
@@ -392,6 +890,8 @@ mod r#async {
 
     pub struct AsyncDeserialized<'a, T, V> {
         rows: AsyncRows<'a, T>,
+        /// Rows are deserialized by [RowView::into_buffered].
+        buffered: bool,
         _marker: PhantomData<V>,
     }
 
@@ -406,13 +906,24 @@ mod r#async {
 
         fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
             use futures::stream::*;
-            Pin::get_mut(self).rows.poll_next_unpin(cx).map(|row| {
-                row.map(|row| row.and_then(|mut row| V::deserialize(&mut row).map_err(Into::into)))
+            let this = Pin::get_mut(self);
+            let buffered = this.buffered;
+            this.rows.poll_next_unpin(cx).map(|row| {
+                row.map(|row| {
+                    row.and_then(|mut row| {
+                        if buffered {
+                            V::deserialize(row.into_buffered()).map_err(Into::into)
+                        } else {
+                            V::deserialize(&mut row).map_err(Into::into)
+                        }
+                    })
+                })
             })
         }
     }
 
     #[cfg(feature = "async")]
+    #[async_trait]
     pub trait AsyncFetchable: Sized + Send + Sync {
         type Error: From<taos_error::Error> + Send + Sync;
 
@@ -430,8 +941,24 @@ mod r#async {
             self.fields().len()
         }
 
+        /// A stable hash of the fields and precision, see [crate::helpers::schema_hash].
+        fn schema_hash(&self) -> u64 {
+            crate::helpers::schema_hash(self.fields(), self.precision())
+        }
+
+        /// Kind of the query inferred from the fields, see [crate::helpers::query_kind].
+        fn query_kind(&self) -> crate::helpers::QueryKind {
+            crate::helpers::query_kind(self.fields())
+        }
+
         fn summary(&self) -> (usize, usize);
 
+        /// Warnings returned by the server with the result, empty if the connection does not
+        /// report warnings.
+        fn warnings(&self) -> &[Warning] {
+            &[]
+        }
+
         #[doc(hidden)]
         fn update_summary(&mut self, nrows: usize);
 
@@ -450,8 +977,8 @@ mod r#async {
         fn rows(&mut self) -> AsyncRows<'_, Self> {
             AsyncRows {
                 blocks: self.blocks(),
-                block: None,
                 rows: None,
+                null_policy: None,
             }
         }
 
@@ -468,9 +995,129 @@ mod r#async {
         {
             AsyncDeserialized {
                 rows: self.rows(),
+                buffered: false,
                 _marker: PhantomData,
             }
         }
+
+        /// Deserialize rows with a [NullPolicy] for NULL values in non-`Option` fields.
+        fn deserialize_with_null_policy<R>(
+            &mut self,
+            policy: NullPolicy,
+        ) -> AsyncDeserialized<'_, Self, R>
+        where
+            R: serde::de::DeserializeOwned,
+        {
+            let mut rows = self.rows();
+            rows.null_policy = Some(policy);
+            AsyncDeserialized {
+                rows,
+                buffered: false,
+                _marker: PhantomData,
+            }
+        }
+
+        /// Deserialize rows materialized by [RowView::into_buffered], eg. into untagged enums
+        /// or structs denying unknown columns, at the cost of an allocation per row.
+        fn deserialize_buffered<R>(&mut self) -> AsyncDeserialized<'_, Self, R>
+        where
+            R: serde::de::DeserializeOwned,
+        {
+            AsyncDeserialized {
+                rows: self.rows(),
+                buffered: true,
+                _marker: PhantomData,
+            }
+        }
+
+        /// Write all rows as CSV into `wtr`, see [RawBlock::write_csv]. Returns the number of rows.
+        ///
+        /// Each block is formatted into a buffer before it's written.
+        async fn write_csv<W: tokio::io::AsyncWrite + Unpin + Send>(
+            &mut self,
+            mut wtr: W,
+            options: &CsvOptions,
+        ) -> Result<usize, Self::Error> {
+            use tokio::io::AsyncWriteExt;
+            let mut buf = Vec::new();
+            let names = self.fields().iter().map(|f| f.name());
+            write_csv_header(&mut buf, names, options).map_err(taos_error::Error::from_any)?;
+            let mut rows = 0;
+            let mut blocks = self.blocks();
+            while let Some(block) = blocks.try_next().await? {
+                block
+                    .write_csv_rows(&mut buf, options)
+                    .map_err(taos_error::Error::from_any)?;
+                rows += block.nrows();
+                wtr.write_all(&buf)
+                    .await
+                    .map_err(taos_error::Error::from_any)?;
+                buf.clear();
+            }
+            wtr.write_all(&buf)
+                .await
+                .map_err(taos_error::Error::from_any)?;
+            wtr.flush().await.map_err(taos_error::Error::from_any)?;
+            Ok(rows)
+        }
+
+        /// Write all rows as newline-delimited JSON into `wtr`, see [RawBlock::write_ndjson].
+        /// Returns the number of rows.
+        async fn write_ndjson<W: tokio::io::AsyncWrite + Unpin + Send>(
+            &mut self,
+            mut wtr: W,
+            timestamp: TimestampFormat,
+        ) -> Result<usize, Self::Error> {
+            use tokio::io::AsyncWriteExt;
+            let mut buf = Vec::new();
+            let mut rows = 0;
+            let mut blocks = self.blocks();
+            while let Some(block) = blocks.try_next().await? {
+                block
+                    .write_ndjson(&mut buf, timestamp)
+                    .map_err(taos_error::Error::from_any)?;
+                rows += block.nrows();
+                wtr.write_all(&buf)
+                    .await
+                    .map_err(taos_error::Error::from_any)?;
+                buf.clear();
+            }
+            wtr.flush().await.map_err(taos_error::Error::from_any)?;
+            Ok(rows)
+        }
+
+        /// Create or truncate the file at `path` and write all rows into it as CSV.
+        async fn to_csv_file<P: AsRef<std::path::Path> + Send>(
+            &mut self,
+            path: P,
+            options: &CsvOptions,
+        ) -> Result<usize, Self::Error> {
+            let file = tokio::fs::File::create(path)
+                .await
+                .map_err(taos_error::Error::from_any)?;
+            self.write_csv(tokio::io::BufWriter::new(file), options)
+                .await
+        }
+
+        /// Profile columns of all rows with a reservoir sample of `sample_rows` rows, see
+        /// [BlockProfile].
+        async fn profile(&mut self, sample_rows: usize) -> Result<BlockProfile, Self::Error> {
+            self.profile_with(&ProfileOptions::new().sample_rows(sample_rows))
+                .await
+        }
+
+        /// Profile columns of all rows by `options`, eg. with a seed of the sampling.
+        async fn profile_with(
+            &mut self,
+            options: &ProfileOptions,
+        ) -> Result<BlockProfile, Self::Error> {
+            let mut profiler = Profiler::new(options.clone());
+            let mut blocks = self.blocks();
+            while let Some(block) = blocks.try_next().await? {
+                profiler.push(&block);
+            }
+            Ok(profiler.finish())
+        }
     }
 
     #[cfg(feature = "async")]
@@ -492,6 +1139,73 @@ mod r#async {
             self.query(sql).await.map(|res| res.affected_rows() as _)
         }
 
+        /// Execute a statement and keep warnings returned by the server.
+        async fn exec_with_warnings<T: AsRef<str> + Send + Sync>(
+            &self,
+            sql: T,
+        ) -> Result<ExecResult, Self::Error> {
+            self.query(sql)
+                .await
+                .map(|res| ExecResult::new(res.affected_rows() as _, res.warnings().to_vec()))
+        }
+
+        /// Execute `sql` with `?` placeholders substituted by `params` client-side, see
+        /// [bind_sql_params].
+        ///
+        /// Timestamps are epoch integers in their own precision, which should be the precision of
+        /// the database. Mismatched placeholders and params fail before the sql is sent.
+        async fn exec_with_params<T: AsRef<str> + Send + Sync, P: ToSqlLiteral + Sync>(
+            &self,
+            sql: T,
+            params: &[P],
+        ) -> Result<usize, Self::Error> {
+            let sql = bind_sql_params(sql.as_ref(), params, None)
+                .map_err(<Self::AsyncResultSet as AsyncFetchable>::Error::from)?;
+            self.exec(sql).await
+        }
+
+        /// Query `sql` with `?` placeholders substituted by `params` client-side, like
+        /// [AsyncQueryable::exec_with_params].
+        async fn query_with_params<T: AsRef<str> + Send + Sync, P: ToSqlLiteral + Sync>(
+            &self,
+            sql: T,
+            params: &[P],
+        ) -> Result<Self::AsyncResultSet, Self::Error> {
+            let sql = bind_sql_params(sql.as_ref(), params, None)
+                .map_err(<Self::AsyncResultSet as AsyncFetchable>::Error::from)?;
+            self.query(sql).await
+        }
+
+        /// Execute `sql` with `?` placeholders substituted by `args` of mixed types, like
+        /// [AsyncQueryable::exec_with_params].
+        async fn exec_with_args<T: AsRef<str> + Send + Sync>(
+            &self,
+            sql: T,
+            args: &[Value],
+        ) -> Result<usize, Self::Error> {
+            self.exec_with_params(sql, args).await
+        }
+
+        /// Query `sql` with `?` placeholders substituted by `args` of mixed types, like
+        /// [AsyncQueryable::query_with_params].
+        async fn query_with_args<T: AsRef<str> + Send + Sync>(
+            &self,
+            sql: T,
+            args: &[Value],
+        ) -> Result<Self::AsyncResultSet, Self::Error> {
+            self.query_with_params(sql, args).await
+        }
+
+        /// Query the ordered select `sql` in pages of `page_rows` rows by `limit ... offset ...`,
+        /// use [PagedQuery::new] with [PageBy::Timestamp] for large results.
+        fn query_iter_pages<T: Into<String>>(
+            &self,
+            sql: T,
+            page_rows: usize,
+        ) -> PagedQuery<'_, Self> {
+            PagedQuery::new(self, sql, PageBy::limit_offset(page_rows))
+        }
+
         async fn write_raw_meta(&self, meta: &RawMeta) -> Result<(), Self::Error>;
 
         async fn write_raw_block(&self, block: &RawBlock) -> Result<(), Self::Error>;
@@ -550,9 +1264,66 @@ mod r#async {
                 .into())
         }
 
+        /// License information of the server, see [GrantInfo].
+        ///
+        /// Queries `information_schema.ins_grants` and falls back to `show grants` for servers
+        /// without it. Servers without grants, like community servers, return
+        /// [GrantInfo::community].
+        async fn grant_info(&self) -> Result<GrantInfo, Self::Error> {
+            let mut rs = match self
+                .query("SELECT * FROM information_schema.ins_grants")
+                .await
+            {
+                Ok(rs) => rs,
+                Err(_) => self.query("show grants").await?,
+            };
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let rows: Vec<_> = rs
+                .rows()
+                .map_ok(|row| row.into_values())
+                .try_collect()
+                .await?;
+            Ok(GrantInfo::from_rows(&names, &rows))
+        }
+
+        /// Limits of the connection by [SERVER_LIMITS_SQL], see [ServerLimits::from_rows].
+        async fn fetch_limits(&self) -> Result<ServerLimits, Self::Error> {
+            let mut rs = self.query(SERVER_LIMITS_SQL).await?;
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let rows: Vec<_> = rs
+                .rows()
+                .map_ok(|row| row.into_values())
+                .try_collect()
+                .await?;
+            Ok(ServerLimits::from_rows(&names, &rows))
+        }
+
+        /// Limits that sql batches of the connection are split by, eg. in
+        /// [AsyncQueryable::set_tags_bulk].
+        ///
+        /// It's [ServerLimits::default] unless the connection caches its limits, as `taos::Taos`
+        /// does by [AsyncQueryable::fetch_limits] on the first call.
+        async fn limits(&self) -> ServerLimits {
+            ServerLimits::default()
+        }
+
         /// Short for `CREATE DATABASE IF NOT EXISTS {name}`.
+        ///
+        /// The name is quoted by [quote_ident](crate::util::quote_ident), database options
+        /// should be set by [exec](Self::exec) with the full sql.
         async fn create_database<N: AsRef<str> + Send>(&self, name: N) -> Result<(), Self::Error> {
-            let query = format!("CREATE DATABASE IF NOT EXISTS {}", name.as_ref());
+            let query = format!(
+                "CREATE DATABASE IF NOT EXISTS {}",
+                quote_ident(name.as_ref())
+            );
 
             self.query(query).await?;
             Ok(())
@@ -560,7 +1331,7 @@ mod r#async {
 
         /// Short for `USE {name}`.
         async fn use_database<N: AsRef<str> + Send>(&self, name: N) -> Result<(), Self::Error> {
-            let query = format!("USE `{}`", name.as_ref());
+            let query = format!("USE {}", quote_ident(name.as_ref()));
 
             self.query(query).await?;
             Ok(())
@@ -573,7 +1344,7 @@ mod r#async {
             sql: S,
         ) -> Result<(), Self::Error> {
             let (name, sql) = (name.as_ref(), sql.as_ref());
-            let query = format!("CREATE TOPIC IF NOT EXISTS {name} AS {sql}");
+            let query = format!("CREATE TOPIC IF NOT EXISTS {} AS {sql}", quote_ident(name));
 
             self.query(query).await?;
             Ok(())
@@ -585,8 +1356,12 @@ mod r#async {
             name: impl AsRef<str> + Send + Sync + 'async_trait,
             db: impl std::fmt::Display + Send + 'async_trait,
         ) -> Result<(), Self::Error> {
-            let name = name.as_ref();
-            let query = format!("create topic if not exists {name} with meta as database `{db}`");
+            let (name, db) = (name.as_ref(), db.to_string());
+            let query = format!(
+                "create topic if not exists {} with meta as database {}",
+                quote_ident(name),
+                quote_ident(&db)
+            );
 
             // todo(@huolinhe): cannot set error. we should use a global error type here (?).
 
@@ -630,20 +1405,167 @@ mod r#async {
             Ok(self.query(sql).await?.deserialize().try_collect().await?)
         }
 
-        /// Get table meta information.
+        /// Get table meta information, from [AsyncQueryable::schema_cache] if it's cached.
         async fn describe(&self, table: &str) -> Result<Describe, Self::Error> {
-            Ok(Describe(
-                self.query(format!("DESCRIBE `{table}`"))
+            let cache = self.schema_cache();
+            if let Some(describe) = cache.and_then(|cache| cache.get(table)) {
+                return Ok(describe);
+            }
+            let describe = Describe(
+                self.query(format!("DESCRIBE {}", quote_ident(table)))
                     .await?
                     .deserialize()
                     .try_collect()
                     .await?,
-            ))
+            );
+            if let Some(cache) = cache {
+                cache.insert(table, describe.clone());
+            }
+            Ok(describe)
+        }
+
+        /// Schemas of tables cached by the connection, see [SchemaCache]. It's `None` by
+        /// default, ie. each [AsyncQueryable::describe] queries the server.
+        fn schema_cache(&self) -> Option<&SchemaCache> {
+            None
         }
 
         /// Check if database exists
         async fn database_exists(&self, name: &str) -> Result<bool, Self::Error> {
-            Ok(self.exec(format!("show `{name}`.stables")).await.is_ok())
+            Ok(self
+                .exec(format!("show {}.stables", quote_ident(name)))
+                .await
+                .is_ok())
+        }
+
+        /// Create a stream by the sql of [StreamBuilder].
+        ///
+        /// ```rust,no_run
+        /// # use std::time::Duration;
+        /// # use taos_query::helpers::{StreamBuilder, Trigger};
+        /// # async fn create<Q: taos_query::AsyncQueryable>(taos: Q) -> Result<(), Q::Error> {
+        /// let stream = StreamBuilder::new("avg_vol")
+        ///     .source_sql("select _wstart, avg(voltage) from meters interval(1m)")
+        ///     .into_table("avg_vol_1m")
+        ///     .trigger(Trigger::WindowClose)
+        ///     .watermark(Duration::from_secs(10));
+        /// taos.create_stream(&stream).await?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        async fn create_stream(&self, stream: &StreamBuilder) -> Result<(), Self::Error> {
+            self.exec(stream.build_sql()).await?;
+            Ok(())
+        }
+
+        /// Short for `DROP STREAM [IF EXISTS] {name}`.
+        async fn drop_stream(&self, name: &str, if_exists: bool) -> Result<(), Self::Error> {
+            let if_exists = if if_exists { "IF EXISTS " } else { "" };
+            self.exec(format!("DROP STREAM {if_exists}{}", quote_ident(name)))
+                .await?;
+            Ok(())
+        }
+
+        /// Streams information by `SELECT * FROM information_schema.ins_streams` sql, see
+        /// [StreamInfo].
+        ///
+        /// ## Compatibility
+        ///
+        /// This is a 3.x-only API.
+        async fn streams(&self) -> Result<Vec<StreamInfo>, Self::Error> {
+            let mut rs = self
+                .query("SELECT * FROM information_schema.ins_streams")
+                .await?;
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let rows: Vec<_> = rs
+                .rows()
+                .map_ok(|row| row.into_values())
+                .try_collect()
+                .await?;
+            Ok(StreamInfo::from_rows(&names, &rows))
+        }
+
+        /// Subscriptions by `SELECT * FROM information_schema.ins_subscriptions` sql, see
+        /// [SubscriptionInfo].
+        ///
+        /// ## Compatibility
+        ///
+        /// This is a 3.x-only API.
+        async fn subscriptions(&self) -> Result<Vec<SubscriptionInfo>, Self::Error> {
+            let mut rs = self
+                .query("SELECT * FROM information_schema.ins_subscriptions")
+                .await?;
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let rows: Vec<_> = rs
+                .rows()
+                .map_ok(|row| row.into_values())
+                .try_collect()
+                .await?;
+            Ok(SubscriptionInfo::from_rows(&names, &rows))
+        }
+
+        /// Set tag `tag` of child table `table` to `value`, see [set_tag_sql].
+        async fn set_tag(&self, table: &str, tag: &str, value: &Value) -> Result<(), Self::Error> {
+            self.exec(set_tag_sql(table, tag, value)).await?;
+            Ok(())
+        }
+
+        /// Set tags of many child tables, as `(table, tag, value)`.
+        ///
+        /// Statements are executed by [AsyncQueryable::exec_many] in batches of at most
+        /// [TAG_BATCH_SQL_BYTES] and [AsyncQueryable::limits]. When a batch fails, its statements are executed one by one to
+        /// find the failed update, which is safe as setting a tag is idempotent. Updates before
+        /// the failed one are applied.
+        async fn set_tags_bulk<S: AsRef<str> + Sync>(
+            &self,
+            updates: &[(S, S, Value)],
+        ) -> Result<(), TagUpdateError<Self::Error>> {
+            let max_sql_length = self.limits().await.max_sql_length;
+            for batch in tag_batches(updates, max_sql_length) {
+                if self
+                    .exec_many(batch.iter().map(|(_, sql)| sql))
+                    .await
+                    .is_ok()
+                {
+                    continue;
+                }
+                for (i, sql) in &batch {
+                    self.exec(sql)
+                        .await
+                        .map_err(|err| TagUpdateError::new(updates, *i, err))?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Tag names and values of child table `table` by `show tags from {table}`, see
+        /// [tags_from_rows].
+        ///
+        /// Timestamp tags are in the precision of the result set.
+        async fn read_tags(&self, table: &str) -> Result<Vec<(String, Value)>, Self::Error> {
+            let mut rs = self
+                .query(format!("show tags from {}", quote_ident(table)))
+                .await?;
+            let names = rs
+                .fields()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect_vec();
+            let precision = rs.precision();
+            let rows: Vec<_> = rs
+                .rows()
+                .map_ok(|row| row.into_values())
+                .try_collect()
+                .await?;
+            Ok(tags_from_rows(&names, &rows, precision))
         }
 
         /// Sync version of `exec`.
@@ -660,6 +1582,192 @@ mod r#async {
         }
     }
 
+    /// Forward [AsyncQueryable] of smart pointers and pool guards to the connection they point to.
+    #[cfg(feature = "async")]
+    macro_rules! _impl_async_queryable_deref {
+        ([$($g:tt)*] $ty:ty => $q:ty $(where $($w:tt)+)?) => {
+            #[async_trait]
+            impl<$($g)*> AsyncQueryable for $ty $(where $($w)+)? {
+                type Error = <$q as AsyncQueryable>::Error;
+
+                type AsyncResultSet = <$q as AsyncQueryable>::AsyncResultSet;
+
+                async fn query<T: AsRef<str> + Send + Sync>(
+                    &self,
+                    sql: T,
+                ) -> Result<Self::AsyncResultSet, Self::Error> {
+                    <$q as AsyncQueryable>::query(&**self, sql).await
+                }
+
+                async fn exec<T: AsRef<str> + Send + Sync>(
+                    &self,
+                    sql: T,
+                ) -> Result<usize, Self::Error> {
+                    <$q as AsyncQueryable>::exec(&**self, sql).await
+                }
+
+                async fn exec_with_warnings<T: AsRef<str> + Send + Sync>(
+                    &self,
+                    sql: T,
+                ) -> Result<ExecResult, Self::Error> {
+                    <$q as AsyncQueryable>::exec_with_warnings(&**self, sql).await
+                }
+
+                async fn write_raw_meta(&self, meta: &RawMeta) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::write_raw_meta(&**self, meta).await
+                }
+
+                async fn write_raw_block(&self, block: &RawBlock) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::write_raw_block(&**self, block).await
+                }
+
+                async fn exec_many<T, I>(&self, input: I) -> Result<usize, Self::Error>
+                where
+                    T: AsRef<str> + Send + Sync,
+                    I::IntoIter: Send,
+                    I: IntoIterator<Item = T> + Send,
+                {
+                    <$q as AsyncQueryable>::exec_many(&**self, input).await
+                }
+
+                async fn query_one<T: AsRef<str> + Send + Sync, O: DeserializeOwned + Send>(
+                    &self,
+                    sql: T,
+                ) -> Result<Option<O>, Self::Error> {
+                    <$q as AsyncQueryable>::query_one(&**self, sql).await
+                }
+
+                async fn server_version(&self) -> Result<Cow<'_, str>, Self::Error> {
+                    <$q as AsyncQueryable>::server_version(&**self).await
+                }
+
+                async fn grant_info(&self) -> Result<GrantInfo, Self::Error> {
+                    <$q as AsyncQueryable>::grant_info(&**self).await
+                }
+
+                async fn fetch_limits(&self) -> Result<ServerLimits, Self::Error> {
+                    <$q as AsyncQueryable>::fetch_limits(&**self).await
+                }
+
+                async fn limits(&self) -> ServerLimits {
+                    <$q as AsyncQueryable>::limits(&**self).await
+                }
+
+                async fn create_database<N: AsRef<str> + Send>(
+                    &self,
+                    name: N,
+                ) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::create_database(&**self, name).await
+                }
+
+                async fn use_database<N: AsRef<str> + Send>(
+                    &self,
+                    name: N,
+                ) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::use_database(&**self, name).await
+                }
+
+                async fn create_topic<N: AsRef<str> + Send + Sync, S: AsRef<str> + Send>(
+                    &self,
+                    name: N,
+                    sql: S,
+                ) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::create_topic(&**self, name, sql).await
+                }
+
+                async fn create_topic_as_database(
+                    &self,
+                    name: impl AsRef<str> + Send + Sync + 'async_trait,
+                    db: impl std::fmt::Display + Send + 'async_trait,
+                ) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::create_topic_as_database(&**self, name, db).await
+                }
+
+                async fn databases(&self) -> Result<Vec<ShowDatabase>, Self::Error> {
+                    <$q as AsyncQueryable>::databases(&**self).await
+                }
+
+                async fn topics(&self) -> Result<Vec<Topic>, Self::Error> {
+                    <$q as AsyncQueryable>::topics(&**self).await
+                }
+
+                async fn describe(&self, table: &str) -> Result<Describe, Self::Error> {
+                    <$q as AsyncQueryable>::describe(&**self, table).await
+                }
+
+                fn schema_cache(&self) -> Option<&SchemaCache> {
+                    <$q as AsyncQueryable>::schema_cache(&**self)
+                }
+
+                async fn database_exists(&self, name: &str) -> Result<bool, Self::Error> {
+                    <$q as AsyncQueryable>::database_exists(&**self, name).await
+                }
+
+                async fn create_stream(&self, stream: &StreamBuilder) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::create_stream(&**self, stream).await
+                }
+
+                async fn drop_stream(&self, name: &str, if_exists: bool) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::drop_stream(&**self, name, if_exists).await
+                }
+
+                async fn streams(&self) -> Result<Vec<StreamInfo>, Self::Error> {
+                    <$q as AsyncQueryable>::streams(&**self).await
+                }
+
+                async fn subscriptions(&self) -> Result<Vec<SubscriptionInfo>, Self::Error> {
+                    <$q as AsyncQueryable>::subscriptions(&**self).await
+                }
+
+                async fn set_tag(
+                    &self,
+                    table: &str,
+                    tag: &str,
+                    value: &Value,
+                ) -> Result<(), Self::Error> {
+                    <$q as AsyncQueryable>::set_tag(&**self, table, tag, value).await
+                }
+
+                async fn set_tags_bulk<S: AsRef<str> + Sync>(
+                    &self,
+                    updates: &[(S, S, Value)],
+                ) -> Result<(), TagUpdateError<Self::Error>> {
+                    <$q as AsyncQueryable>::set_tags_bulk(&**self, updates).await
+                }
+
+                async fn read_tags(&self, table: &str) -> Result<Vec<(String, Value)>, Self::Error> {
+                    <$q as AsyncQueryable>::read_tags(&**self, table).await
+                }
+
+                fn exec_sync<T: AsRef<str> + Send + Sync>(
+                    &self,
+                    sql: T,
+                ) -> Result<usize, Self::Error> {
+                    <$q as AsyncQueryable>::exec_sync(&**self, sql)
+                }
+
+                fn query_sync<T: AsRef<str> + Send + Sync>(
+                    &self,
+                    sql: T,
+                ) -> Result<Self::AsyncResultSet, Self::Error> {
+                    <$q as AsyncQueryable>::query_sync(&**self, sql)
+                }
+            }
+        };
+    }
+
+    #[cfg(feature = "async")]
+    _impl_async_queryable_deref!(['q, Q: AsyncQueryable] &'q Q => Q);
+    #[cfg(feature = "async")]
+    _impl_async_queryable_deref!([Q: AsyncQueryable] Box<Q> => Q);
+    #[cfg(feature = "async")]
+    _impl_async_queryable_deref!([Q: AsyncQueryable] std::sync::Arc<Q> => Q);
+    #[cfg(all(feature = "async", feature = "r2d2"))]
+    _impl_async_queryable_deref!(
+        [M: r2d2::ManageConnection] r2d2::PooledConnection<M> => M::Connection
+        where M::Connection: AsyncQueryable
+    );
+
     #[test]
     fn test() {
         assert!(true);
@@ -0,0 +1,547 @@
+//! Analysis of statements by [sqlparser], with the `sqlparser` feature.
+//!
+//! Statements are parsed by [TDengineDialect]. TDengine syntax sqlparser-rs doesn't know, eg.
+//! `interval(10s)`, `partition by` or `create table ... using ... tags`, falls back to scanning
+//! tokens of the statement, so [analyze] never fails.
+
+use std::fmt::Display;
+use std::ops::{Bound, ControlFlow};
+
+use chrono::{DateTime, Utc};
+use sqlparser::ast::{visit_relations, ObjectName, ObjectType, ShowCreateObject, Statement};
+use sqlparser::dialect::Dialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::helpers::{classify_sql, StatementKind, TimeRange};
+
+/// A [Dialect] of TDengine: identifiers are quoted by backticks and may start with `_`, eg.
+/// `_wstart`, double quoted texts are strings.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TDengineDialect;
+
+impl Dialect for TDengineDialect {
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        ch == '`'
+    }
+
+    fn is_identifier_start(&self, ch: char) -> bool {
+        ch.is_alphabetic() || ch == '_'
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        ch.is_alphanumeric() || ch == '_'
+    }
+}
+
+/// A table referred by a statement, names are without backticks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableRef {
+    pub database: Option<String>,
+    pub name: String,
+}
+
+impl TableRef {
+    /// From the parts of a name, eg. `power` and `meters` of `power.meters`.
+    fn from_parts<S: AsRef<str>>(parts: &[S]) -> Option<Self> {
+        match parts {
+            [.., database, name] => Some(Self {
+                database: Some(database.as_ref().to_string()),
+                name: name.as_ref().to_string(),
+            }),
+            [name] => Some(Self {
+                database: None,
+                name: name.as_ref().to_string(),
+            }),
+            [] => None,
+        }
+    }
+
+    fn eq_ignore_ascii_case(&self, other: &Self) -> bool {
+        let database = |table: &Self| table.database.as_deref().unwrap_or_default().to_string();
+        self.name.eq_ignore_ascii_case(&other.name)
+            && database(self).eq_ignore_ascii_case(&database(other))
+    }
+}
+
+impl Display for TableRef {
+    /// Quoted by backticks, eg. `` `power`.`meters` ``.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(database) = &self.database {
+            write!(f, "`{database}`.")?;
+        }
+        write!(f, "`{}`", self.name)
+    }
+}
+
+/// A statement analyzed by [analyze].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlAnalysis {
+    /// Kind of the statement, by [classify_sql].
+    pub kind: StatementKind,
+    /// Tables read or written by the statement, including ones of subqueries, in order of
+    /// appearance without duplicates.
+    pub tables: Vec<TableRef>,
+    /// Whether the statement has `?` placeholders to bind, ie. is a stmt.
+    pub has_placeholders: bool,
+    /// Range of timestamps the statement is limited to by its outermost `where`, see [analyze].
+    pub time_range_hint: Option<TimeRange>,
+    /// Whether the statement is parsed by sqlparser-rs, otherwise its tables are scanned from
+    /// tokens.
+    pub parsed: bool,
+}
+
+/// Analyze a statement, see [SqlAnalysis].
+///
+/// Statements not parsed by [TDengineDialect] fall back to scanning tokens: tables are names
+/// after `from`, `join`, `into`, `using`, `table`, `stable` and `describe`, and names before
+/// `using ... tags`, eg. `d1` of `insert into d0 using st tags(0) values(now, 0) d1 using st
+/// tags(1) values(now, 1)`.
+///
+/// The time range hint is a guess: comparisons of a column with RFC3339 literals, eg.
+/// `ts >= '2024-01-01T00:00:00+08:00'`, or `between` two of them, joined by `and` in the
+/// outermost `where`. A `where` with `or` or `not` has no hint, nor do literals without
+/// timezones, which are in the timezone of the server.
+///
+/// ```rust
+/// # use taos_query::sql::analyze;
+/// let analysis = analyze(
+///     "select avg(current) from power.meters \
+///      where ts >= '2024-01-01T00:00:00Z' and location = ? interval(1m)",
+/// );
+/// assert_eq!(analysis.tables[0].to_string(), "`power`.`meters`");
+/// assert!(analysis.has_placeholders && analysis.time_range_hint.is_some());
+/// assert!(!analysis.parsed);
+/// ```
+pub fn analyze(sql: &str) -> SqlAnalysis {
+    let dialect = TDengineDialect;
+    let tokens: Vec<_> = Tokenizer::new(&dialect, sql)
+        .tokenize()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .collect();
+    let (tables, parsed) = match Parser::parse_sql(&dialect, sql) {
+        Ok(statements) => (parsed_tables(&statements), true),
+        Err(err) => {
+            log::trace!("scan tables of statement not parsed: {err}");
+            (scanned_tables(&tokens), false)
+        }
+    };
+    SqlAnalysis {
+        kind: classify_sql(sql),
+        tables: tables.0,
+        has_placeholders: tokens
+            .iter()
+            .any(|token| matches!(token, Token::Placeholder(_))),
+        time_range_hint: time_range_hint(&tokens),
+        parsed,
+    }
+}
+
+#[derive(Default)]
+struct Tables(Vec<TableRef>);
+
+impl Tables {
+    fn push(&mut self, table: TableRef) {
+        if !self.0.iter().any(|t| t.eq_ignore_ascii_case(&table)) {
+            self.0.push(table);
+        }
+    }
+
+    fn push_object(&mut self, name: &ObjectName) {
+        let parts: Vec<_> = name.0.iter().map(|ident| ident.value.as_str()).collect();
+        if let Some(table) = TableRef::from_parts(&parts) {
+            self.push(table);
+        }
+    }
+}
+
+fn parsed_tables(statements: &[Statement]) -> Tables {
+    let mut tables = Tables::default();
+    for statement in statements {
+        // names of `drop table` and `show create table` are not relations of the visitor.
+        match statement {
+            Statement::Drop {
+                object_type: ObjectType::Table,
+                names,
+                ..
+            } => names.iter().for_each(|name| tables.push_object(name)),
+            Statement::ShowCreate {
+                obj_type: ShowCreateObject::Table,
+                obj_name,
+            } => tables.push_object(obj_name),
+            _ => {}
+        }
+        let _ = visit_relations(statement, |name| {
+            tables.push_object(name);
+            ControlFlow::<()>::Continue(())
+        });
+    }
+    tables
+}
+
+/// Words after which tables are scanned.
+const TABLE_PREFIXES: &[&str] = &[
+    "from", "join", "into", "using", "table", "stable", "describe", "desc",
+];
+
+/// The value of an unquoted word.
+fn word(token: Option<&Token>) -> Option<&str> {
+    match token {
+        Some(Token::Word(word)) if word.quote_style.is_none() => Some(&word.value),
+        _ => None,
+    }
+}
+
+fn is_word(token: Option<&Token>, words: &[&str]) -> bool {
+    word(token).map_or(false, |word| {
+        words.iter().any(|w| w.eq_ignore_ascii_case(word))
+    })
+}
+
+/// A table name at `i` as `name` or `database.name`, and the index after it.
+fn name_at(tokens: &[Token], i: usize) -> Option<(TableRef, usize)> {
+    let part = |i: usize| match tokens.get(i) {
+        Some(Token::Word(word)) => Some(word.value.as_str()),
+        _ => None,
+    };
+    let first = part(i)?;
+    match (tokens.get(i + 1), part(i + 2)) {
+        (Some(Token::Period), Some(name)) => Some((TableRef::from_parts(&[first, name])?, i + 3)),
+        _ => Some((TableRef::from_parts(&[first])?, i + 1)),
+    }
+}
+
+/// A table name ending before `i`.
+fn name_before(tokens: &[Token], i: usize) -> Option<TableRef> {
+    (i.saturating_sub(3)..i)
+        .filter_map(|start| name_at(tokens, start))
+        .find_map(|(table, end)| (end == i).then_some(table))
+}
+
+fn scanned_tables(tokens: &[Token]) -> Tables {
+    let mut tables = Tables::default();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        if is_word(Some(token), &["using"]) {
+            // subtables created by `insert` or `create table`, but not joins `using (id)`.
+            let tags =
+                name_at(tokens, i).map_or(false, |(_, end)| is_word(tokens.get(end), &["tags"]));
+            if let Some(table) = name_before(tokens, i - 1).filter(|_| tags) {
+                tables.push(table);
+            }
+        }
+        if !is_word(Some(token), TABLE_PREFIXES) {
+            continue;
+        }
+        while is_word(tokens.get(i), &["if", "not", "exists"]) {
+            i += 1;
+        }
+        // lists of tables, eg. `from t1 a, t2 b` or `drop table t1, t2`.
+        while let Some((table, end)) = name_at(tokens, i) {
+            tables.push(table);
+            i = end;
+            if is_word(tokens.get(i), &["as"]) {
+                i += 1;
+            }
+            if matches!(tokens.get(i), Some(Token::Word(alias)) if alias.keyword == Keyword::NoKeyword)
+            {
+                i += 1;
+            }
+            if tokens.get(i) != Some(&Token::Comma) {
+                break;
+            }
+            i += 1;
+        }
+    }
+    tables
+}
+
+/// Words ending a `where` clause.
+const WHERE_ENDS: &[&str] = &[
+    "group",
+    "partition",
+    "order",
+    "limit",
+    "slimit",
+    "interval",
+    "session",
+    "state_window",
+    "event_window",
+    "count_window",
+    "fill",
+    "sliding",
+    "having",
+    "union",
+];
+
+fn literal_time(token: Option<&Token>) -> Option<DateTime<Utc>> {
+    match token {
+        Some(Token::SingleQuotedString(s) | Token::DoubleQuotedString(s)) => {
+            DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|time| time.with_timezone(&Utc))
+        }
+        _ => None,
+    }
+}
+
+fn bound_time(bound: Bound<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match bound {
+        Bound::Included(time) | Bound::Excluded(time) => Some(time),
+        Bound::Unbounded => None,
+    }
+}
+
+/// The tighter of two bounds, later ones for starts and earlier ones for ends.
+fn tighter(a: Bound<DateTime<Utc>>, b: Bound<DateTime<Utc>>, later: bool) -> Bound<DateTime<Utc>> {
+    match (bound_time(a), bound_time(b)) {
+        (None, _) => b,
+        (_, None) => a,
+        (Some(x), Some(y)) if x == y => {
+            if matches!(a, Bound::Excluded(_)) {
+                a
+            } else {
+                b
+            }
+        }
+        (Some(x), Some(y)) => {
+            if (x > y) == later {
+                a
+            } else {
+                b
+            }
+        }
+    }
+}
+
+fn time_range_hint(tokens: &[Token]) -> Option<TimeRange> {
+    let mut depth = 0i32;
+    let mut condition = Vec::new();
+    let mut in_where = false;
+    for token in tokens {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            _ if depth > 0 => {}
+            Token::SemiColon => break,
+            token if in_where && is_word(Some(token), WHERE_ENDS) => break,
+            token if is_word(Some(token), &["where"]) => in_where = true,
+            _ => {}
+        }
+        if in_where {
+            // tokens of the outermost level only.
+            condition.push((depth == 0).then_some(token));
+        }
+    }
+    let at = |i: usize| condition.get(i).copied().flatten();
+    if (0..condition.len()).any(|i| is_word(at(i), &["or", "not"])) {
+        return None;
+    }
+
+    let (mut start, mut end) = (Bound::Unbounded, Bound::Unbounded);
+    for i in 1..condition.len() {
+        let (left, op, right) = (at(i - 1), at(i), at(i + 1));
+        if is_word(op, &["between"]) && word(left).is_some() && is_word(at(i + 2), &["and"]) {
+            if let (Some(low), Some(high)) = (literal_time(right), literal_time(at(i + 3))) {
+                start = tighter(start, Bound::Included(low), true);
+                end = tighter(end, Bound::Included(high), false);
+            }
+            continue;
+        }
+        // `ts > '...'` or `'...' < ts`.
+        let (op, time) = match (
+            word(left),
+            literal_time(right),
+            literal_time(left),
+            word(right),
+        ) {
+            (Some(_), Some(time), _, _) => (op, time),
+            (_, _, Some(time), Some(_)) => (
+                match op {
+                    Some(Token::Gt) => Some(&Token::Lt),
+                    Some(Token::GtEq) => Some(&Token::LtEq),
+                    Some(Token::Lt) => Some(&Token::Gt),
+                    Some(Token::LtEq) => Some(&Token::GtEq),
+                    _ => None,
+                },
+                time,
+            ),
+            _ => continue,
+        };
+        match op {
+            Some(Token::Gt) => start = tighter(start, Bound::Excluded(time), true),
+            Some(Token::GtEq) => start = tighter(start, Bound::Included(time), true),
+            Some(Token::Lt) => end = tighter(end, Bound::Excluded(time), false),
+            Some(Token::LtEq) => end = tighter(end, Bound::Included(time), false),
+            _ => {}
+        }
+    }
+    if start == Bound::Unbounded && end == Bound::Unbounded {
+        None
+    } else {
+        Some(TimeRange::new(start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables(sql: &str) -> Vec<String> {
+        analyze(sql)
+            .tables
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn tables_of_corpus() {
+        let corpus: &[(&str, &[&str])] = &[
+            ("select * from meters", &["`meters`"]),
+            (
+                "SELECT ts, current FROM power.meters WHERE voltage > 200",
+                &["`power`.`meters`"],
+            ),
+            ("select * from `power`.`Meters`", &["`power`.`Meters`"]),
+            ("select * from `my table`", &["`my table`"]),
+            (
+                "select count(*) from meters where ts > now - 1h interval(10s) fill(prev)",
+                &["`meters`"],
+            ),
+            (
+                "select _wstart, avg(current) from meters partition by tbname interval(1m) \
+                 sliding(30s)",
+                &["`meters`"],
+            ),
+            (
+                "select _wstart, count(*) from d1001 state_window(status)",
+                &["`d1001`"],
+            ),
+            (
+                "select * from (select ts, current from d1001 where current > 10) t",
+                &["`d1001`"],
+            ),
+            (
+                "select a.ts, b.v from d1 a join d2 b on a.ts = b.ts",
+                &["`d1`", "`d2`"],
+            ),
+            (
+                "select * from t1 where v in (select v from db2.t2)",
+                &["`t1`", "`db2`.`t2`"],
+            ),
+            (
+                "(select ts from t1) union all (select ts from t2)",
+                &["`t1`", "`t2`"],
+            ),
+            (
+                "select * from t1, t2 where t1.ts = t2.ts",
+                &["`t1`", "`t2`"],
+            ),
+            (
+                "select last_row(*) from meters partition by tbname slimit 10",
+                &["`meters`"],
+            ),
+            ("select server_version()", &[]),
+            ("select * from tb where name = 'from t2'", &["`tb`"]),
+            ("insert into d1001 values(now, 1)", &["`d1001`"]),
+            (
+                "insert into ? using meters tags(?) values(?, ?)",
+                &["`meters`"],
+            ),
+            (
+                "insert into d0 using power.meters tags(0) values(now, 0) \
+                 d1 using power.meters tags(1) values(now, 1)",
+                &["`d0`", "`power`.`meters`", "`d1`"],
+            ),
+            ("insert into tb file '/tmp/a.csv'", &["`tb`"]),
+            (
+                "insert into tb(ts, v) select ts, v from tb2",
+                &["`tb`", "`tb2`"],
+            ),
+            ("delete from tb where ts < now - 1d", &["`tb`"]),
+            (
+                "create stable st (ts timestamp, v int) tags (t int)",
+                &["`st`"],
+            ),
+            (
+                "create table if not exists d1003 using meters tags(1)",
+                &["`d1003`", "`meters`"],
+            ),
+            (
+                "create topic tp as select ts, current from power.meters",
+                &["`power`.`meters`"],
+            ),
+            ("create topic tp with meta as stable st", &["`st`"]),
+            ("create topic tp as database power", &[]),
+            (
+                "create stream s into out_st as select _wstart, avg(v) from st interval(1s)",
+                &["`out_st`", "`st`"],
+            ),
+            (
+                "drop table if exists d1001, power.d1002",
+                &["`d1001`", "`power`.`d1002`"],
+            ),
+            (
+                "alter stable `power`.`meters` add column phase float",
+                &["`power`.`meters`"],
+            ),
+            ("alter table d1001 set tag location = 'x'", &["`d1001`"]),
+            ("describe power.meters", &["`power`.`meters`"]),
+            ("show create table tb", &["`tb`"]),
+            ("show tables", &[]),
+            ("use power", &[]),
+            ("", &[]),
+        ];
+        for (sql, expected) in corpus {
+            assert_eq!(tables(sql), *expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn analysis() {
+        let analysis = analyze("select * from meters where ts > ? and v < ?");
+        assert_eq!(analysis.kind, StatementKind::Select);
+        assert!(analysis.parsed && analysis.has_placeholders);
+        assert!(analysis.time_range_hint.is_none());
+        assert!(!analyze("select '?' from tb").has_placeholders);
+        assert!(!analyze("create table t using st tags(1)").parsed);
+
+        let time = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let hint = |sql: &str| analyze(sql).time_range_hint;
+        assert_eq!(
+            hint(
+                "select * from tb where ts >= '2024-01-01T00:00:00+08:00' \
+                 and v > 1 and '2024-01-02T00:00:00Z' > ts interval(1h)"
+            ),
+            Some(TimeRange::new(
+                Bound::Included(time("2023-12-31T16:00:00Z")),
+                Bound::Excluded(time("2024-01-02T00:00:00Z"))
+            ))
+        );
+        assert_eq!(
+            hint(
+                "select * from tb where ts between '2024-01-01T00:00:00Z' and \
+                 '2024-01-02T00:00:00Z' and ts > '2024-01-01T12:00:00Z'"
+            ),
+            Some(TimeRange::new(
+                Bound::Excluded(time("2024-01-01T12:00:00Z")),
+                Bound::Included(time("2024-01-02T00:00:00Z"))
+            ))
+        );
+        for sql in [
+            "select * from tb where ts > '2024-01-01T00:00:00Z' or v > 1",
+            "select * from tb where ts > '2024-01-01 00:00:00'",
+            "select * from (select * from tb where ts > '2024-01-01T00:00:00Z')",
+            "select * from tb where v in (select v from t2 where ts > '2024-01-01T00:00:00Z')",
+            "select * from tb",
+        ] {
+            assert_eq!(hint(sql), None, "{sql}");
+        }
+    }
+}
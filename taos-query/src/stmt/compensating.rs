@@ -0,0 +1,557 @@
+use std::fmt::Display;
+
+use crate::{
+    common::{views::ColumnView, Value},
+    util::quote_ident,
+    Fetchable, Queryable,
+};
+
+use super::Bindable;
+
+/// Timestamp range written to a table by a successful [CompensatingBatch::execute].
+///
+/// Timestamps are raw values in the precision of the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrittenRange {
+    pub table: String,
+    pub min_ts: i64,
+    pub max_ts: i64,
+}
+
+impl WrittenRange {
+    /// The `DELETE` statement to remove rows in this range.
+    ///
+    /// The table is used as written in the insert sql or [set_tbname](CompensatingBatch::set_tbname),
+    /// the timestamp column is quoted by [quote_ident].
+    pub fn delete_sql(&self, ts_column: &str) -> String {
+        format!(
+            "DELETE FROM {} WHERE {} BETWEEN {} AND {}",
+            self.table,
+            quote_ident(ts_column),
+            self.min_ts,
+            self.max_ts
+        )
+    }
+
+    /// Merge ranges of a table in one execution.
+    fn merge(ranges: &mut Vec<WrittenRange>, table: &str, min_ts: i64, max_ts: i64) {
+        match ranges.iter_mut().find(|r| r.table == table) {
+            Some(range) => {
+                range.min_ts = range.min_ts.min(min_ts);
+                range.max_ts = range.max_ts.max(max_ts);
+            }
+            None => ranges.push(WrittenRange {
+                table: table.to_string(),
+                min_ts,
+                max_ts,
+            }),
+        }
+    }
+}
+
+/// Transaction-like batch over any [Bindable] statement, undone by deleting what it wrote.
+///
+/// TDengine has no transactions. This helper records the timestamp range written to each table
+/// by every successful [execute](CompensatingBatch::execute), and [rollback](CompensatingBatch::rollback)
+/// issues `DELETE FROM <table> WHERE <ts> BETWEEN <min> AND <max>` for them, in reverse order.
+/// [commit](CompensatingBatch::commit) discards the records.
+///
+/// **It's not atomic**, be aware of that:
+///
+/// - Rows written by the batch are visible to other clients until they are deleted.
+/// - Rows written by others in the same table and time range are deleted too, and rows that are
+///   overwritten by the batch (same timestamp) are deleted rather than restored.
+/// - A failed `execute` may have written part of its rows, they are not recorded.
+/// - Rollback itself may fail part way, the remaining ranges are kept for a retry.
+///
+/// The table name is taken from [set_tbname](CompensatingBatch::set_tbname), or from the prepared
+/// `INSERT INTO <table>` statement. The first bound column must be the primary timestamp column,
+/// named `ts` by default, see [with_ts_column](CompensatingBatch::with_ts_column).
+///
+/// Set [with_dry_run](CompensatingBatch::with_dry_run) to list the compensation SQL without
+/// executing it.
+#[derive(Debug)]
+pub struct CompensatingBatch<'q, Q, S>
+where
+    Q: Queryable,
+    S: Bindable<Q>,
+{
+    taos: &'q Q,
+    stmt: S,
+    ts_column: String,
+    dry_run: bool,
+    /// Table parsed from the prepared sql.
+    sql_table: Option<String>,
+    /// Table set by `set_tbname`.
+    table: Option<String>,
+    /// Ranges bound but not executed yet.
+    pending: Vec<WrittenRange>,
+    /// Ranges of successful executions.
+    written: Vec<WrittenRange>,
+    /// Rows are bound to a table not known by name.
+    untracked: bool,
+}
+
+impl<'q, Q, S> CompensatingBatch<'q, Q, S>
+where
+    Q: Queryable,
+    S: Bindable<Q>,
+{
+    /// Create a batch with a new statement of the connection.
+    pub fn new(taos: &'q Q) -> Result<Self, S::Error> {
+        Ok(Self::from_stmt(taos, S::init(taos)?))
+    }
+
+    /// Create a batch with an initialized statement of the connection.
+    pub fn from_stmt(taos: &'q Q, stmt: S) -> Self {
+        Self {
+            taos,
+            stmt,
+            ts_column: "ts".to_string(),
+            dry_run: false,
+            sql_table: None,
+            table: None,
+            pending: Vec::new(),
+            written: Vec::new(),
+            untracked: false,
+        }
+    }
+
+    /// Name of the primary timestamp column used in the `DELETE` conditions, `ts` by default.
+    pub fn with_ts_column(mut self, name: impl Into<String>) -> Self {
+        self.ts_column = name.into();
+        self
+    }
+
+    /// Don't execute the compensation SQL on rollback, only return it.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn prepare<T: AsRef<str>>(&mut self, sql: T) -> Result<&mut Self, S::Error> {
+        self.stmt.prepare(sql.as_ref())?;
+        self.sql_table = table_of_insert(sql.as_ref());
+        self.table = None;
+        self.pending.clear();
+        Ok(self)
+    }
+
+    pub fn set_tbname<T: AsRef<str>>(&mut self, name: T) -> Result<&mut Self, S::Error> {
+        self.stmt.set_tbname(name.as_ref())?;
+        self.table = Some(name.as_ref().to_string());
+        Ok(self)
+    }
+
+    pub fn set_tags(&mut self, tags: &[Value]) -> Result<&mut Self, S::Error> {
+        self.stmt.set_tags(tags)?;
+        Ok(self)
+    }
+
+    pub fn set_tbname_tags<T: AsRef<str>>(
+        &mut self,
+        name: T,
+        tags: &[Value],
+    ) -> Result<&mut Self, S::Error> {
+        self.set_tbname(name)?.set_tags(tags)
+    }
+
+    /// Bind columns, the first one is the timestamp column to record.
+    pub fn bind(&mut self, params: &[ColumnView]) -> Result<&mut Self, S::Error> {
+        self.stmt.bind(params)?;
+        let range = match params.first() {
+            Some(ColumnView::Timestamp(view)) => view
+                .iter()
+                .flatten()
+                .map(|ts| ts.as_raw_i64())
+                .fold(None, |range, ts| match range {
+                    Some((min, max)) => Some((ts.min(min), ts.max(max))),
+                    None => Some((ts, ts)),
+                }),
+            _ => None,
+        };
+        if let Some((min_ts, max_ts)) = range {
+            match self.table.as_ref().or(self.sql_table.as_ref()) {
+                Some(table) => WrittenRange::merge(&mut self.pending, table, min_ts, max_ts),
+                None => self.untracked = true,
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn add_batch(&mut self) -> Result<&mut Self, S::Error> {
+        self.stmt.add_batch()?;
+        Ok(self)
+    }
+
+    /// Execute the statement, record the written ranges if succeeded.
+    pub fn execute(&mut self) -> Result<usize, S::Error> {
+        let pending = std::mem::take(&mut self.pending);
+        let affected = self.stmt.execute()?;
+        self.written.extend(pending);
+        Ok(affected)
+    }
+
+    /// Ranges written by successful executions since created or last commit, one per table of
+    /// each execution.
+    pub fn written(&self) -> &[WrittenRange] {
+        &self.written
+    }
+
+    /// The SQL to be executed by [rollback](CompensatingBatch::rollback), in order.
+    pub fn compensation_sql(&self) -> Vec<String> {
+        self.written
+            .iter()
+            .rev()
+            .map(|range| range.delete_sql(&self.ts_column))
+            .collect()
+    }
+
+    /// Keep all the written rows, discard the records.
+    pub fn commit(&mut self) {
+        self.written.clear();
+        self.pending.clear();
+        self.untracked = false;
+    }
+
+    /// Delete rows written by the batch, returns the executed SQL.
+    ///
+    /// In dry-run mode, the SQL is returned without execution and the records are kept.
+    /// On error, the ranges not deleted yet are kept, so rollback could be retried.
+    pub fn rollback(&mut self) -> Result<Vec<String>, Q::Error> {
+        if self.untracked {
+            return Err(compensation_error::<Q>(
+                "rows bound without a known table name can not be rolled back",
+            ));
+        }
+        if self.dry_run {
+            return Ok(self.compensation_sql());
+        }
+        self.pending.clear();
+        let mut executed = Vec::with_capacity(self.written.len());
+        while let Some(range) = self.written.last() {
+            let sql = range.delete_sql(&self.ts_column);
+            self.taos.exec(&sql)?;
+            self.written.pop();
+            executed.push(sql);
+        }
+        Ok(executed)
+    }
+
+    /// The inner statement.
+    pub fn stmt(&self) -> &S {
+        &self.stmt
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stmt
+    }
+}
+
+fn compensation_error<Q: Queryable>(err: impl Display) -> Q::Error {
+    <Q::ResultSet as Fetchable>::Error::from(taos_error::Error::from_any(err)).into()
+}
+
+/// Parse the table name of `INSERT INTO <table> ...`.
+///
+/// Returns `None` if the table is bound by `?`, or by a `tbname` column of a super table insert.
+fn table_of_insert(sql: &str) -> Option<String> {
+    let lower = sql.to_ascii_lowercase();
+    let into = lower.find("into")? + "into".len();
+    let rest = sql[into..].trim_start();
+    let end = rest
+        .find(|c: char| c.is_whitespace() || c == '(')
+        .unwrap_or(rest.len());
+    let table = &rest[..end];
+    if table.is_empty() || table == "?" {
+        return None;
+    }
+    let columns = rest[end..].trim_start();
+    if columns.starts_with('(') {
+        let columns = &columns[..columns.find(')').unwrap_or(columns.len())];
+        if columns
+            .trim_start_matches('(')
+            .split(',')
+            .any(|c| c.trim().trim_matches('`').eq_ignore_ascii_case("tbname"))
+        {
+            return None;
+        }
+    }
+    Some(table.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::rc::Rc;
+
+    use taos_error::Error;
+
+    use super::*;
+    use crate::common::{views::TimestampView, Field, Precision, RawBlock, RawMeta};
+
+    type Tables = Rc<RefCell<BTreeMap<String, BTreeSet<i64>>>>;
+
+    /// In-memory connection, supports `DELETE FROM <tb> WHERE ts BETWEEN <a> AND <b>` only.
+    #[derive(Debug, Default)]
+    struct MockTaos {
+        tables: Tables,
+        fail_at_execute: Cell<Option<usize>>,
+        executed: RefCell<Vec<String>>,
+    }
+
+    struct MockResultSet;
+
+    impl Fetchable for MockResultSet {
+        type Error = Error;
+
+        fn affected_rows(&self) -> i32 {
+            0
+        }
+
+        fn precision(&self) -> Precision {
+            Precision::Millisecond
+        }
+
+        fn fields(&self) -> &[Field] {
+            &[]
+        }
+
+        fn summary(&self) -> (usize, usize) {
+            (0, 0)
+        }
+
+        fn update_summary(&mut self, _: usize) {}
+
+        fn fetch_raw_block(&mut self) -> Result<Option<RawBlock>, Self::Error> {
+            Ok(None)
+        }
+    }
+
+    impl Queryable for MockTaos {
+        type Error = Error;
+
+        type ResultSet = MockResultSet;
+
+        fn query<T: AsRef<str>>(&self, _: T) -> Result<Self::ResultSet, Self::Error> {
+            Ok(MockResultSet)
+        }
+
+        fn exec<T: AsRef<str>>(&self, sql: T) -> Result<usize, Self::Error> {
+            let sql = sql.as_ref();
+            self.executed.borrow_mut().push(sql.to_string());
+            let words: Vec<_> = sql.split_whitespace().collect();
+            match words.as_slice() {
+                ["DELETE", "FROM", table, "WHERE", "`ts`", "BETWEEN", min, "AND", max] => {
+                    let (min, max): (i64, i64) = (min.parse().unwrap(), max.parse().unwrap());
+                    let mut tables = self.tables.borrow_mut();
+                    let rows = tables.get_mut(*table).ok_or(Error::from_code(0x2662))?;
+                    let before = rows.len();
+                    rows.retain(|ts| !(min..=max).contains(ts));
+                    Ok(before - rows.len())
+                }
+                _ => Err(Error::from_string(format!("unsupported sql: {sql}"))),
+            }
+        }
+
+        fn write_raw_meta(&self, _: &RawMeta) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn write_raw_block(&self, _: &RawBlock) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct MockStmt {
+        tables: Tables,
+        fail_at_execute: Option<usize>,
+        executions: usize,
+        table: String,
+        rows: Vec<(String, i64)>,
+    }
+
+    impl Bindable<MockTaos> for MockStmt {
+        type Error = Error;
+
+        fn init(taos: &MockTaos) -> Result<Self, Self::Error> {
+            Ok(Self {
+                tables: taos.tables.clone(),
+                fail_at_execute: taos.fail_at_execute.get(),
+                executions: 0,
+                table: String::new(),
+                rows: Vec::new(),
+            })
+        }
+
+        fn prepare<T: AsRef<str>>(&mut self, sql: T) -> Result<&mut Self, Self::Error> {
+            self.table = table_of_insert(sql.as_ref()).unwrap_or_default();
+            Ok(self)
+        }
+
+        fn set_tbname<T: AsRef<str>>(&mut self, name: T) -> Result<&mut Self, Self::Error> {
+            self.table = name.as_ref().to_string();
+            Ok(self)
+        }
+
+        fn set_tags(&mut self, _: &[Value]) -> Result<&mut Self, Self::Error> {
+            Ok(self)
+        }
+
+        fn bind(&mut self, params: &[ColumnView]) -> Result<&mut Self, Self::Error> {
+            if let Some(ColumnView::Timestamp(view)) = params.first() {
+                let table = self.table.clone();
+                self.rows.extend(
+                    view.iter()
+                        .flatten()
+                        .map(|ts| (table.clone(), ts.as_raw_i64())),
+                );
+            }
+            Ok(self)
+        }
+
+        fn add_batch(&mut self) -> Result<&mut Self, Self::Error> {
+            Ok(self)
+        }
+
+        fn execute(&mut self) -> Result<usize, Self::Error> {
+            self.executions += 1;
+            let rows = std::mem::take(&mut self.rows);
+            if self.fail_at_execute == Some(self.executions) {
+                return Err(Error::from_string("execute failed"));
+            }
+            let mut tables = self.tables.borrow_mut();
+            for (table, ts) in &rows {
+                tables.entry(table.clone()).or_default().insert(*ts);
+            }
+            Ok(rows.len())
+        }
+
+        fn affected_rows(&self) -> usize {
+            0
+        }
+    }
+
+    fn ts(values: Vec<impl Into<Option<i64>>>) -> ColumnView {
+        ColumnView::Timestamp(TimestampView::from_millis(values))
+    }
+
+    fn rows(taos: &MockTaos, table: &str) -> Vec<i64> {
+        let tables = taos.tables.borrow();
+        tables.get(table).into_iter().flatten().copied().collect()
+    }
+
+    #[test]
+    fn rollback_on_failure() -> anyhow::Result<()> {
+        let taos = MockTaos::default();
+        taos.tables
+            .borrow_mut()
+            .entry("d0".into())
+            .or_default()
+            .insert(0);
+        taos.fail_at_execute.set(Some(3));
+
+        let mut batch = CompensatingBatch::<_, MockStmt>::new(&taos)?;
+        batch.prepare("insert into ? using meters tags(?) values(?, ?)")?;
+        let mut failed = None;
+        for (table, values) in [
+            ("d0", vec![10, 12, 11]),
+            ("d1", vec![20, 21]),
+            ("d2", vec![30]),
+        ] {
+            batch
+                .set_tbname_tags(table, &[Value::Int(1)])?
+                .bind(&[ts(values)])?
+                .add_batch()?;
+            if let Err(err) = batch.execute() {
+                failed = Some(err);
+                break;
+            }
+        }
+        assert!(failed.is_some());
+        assert_eq!(rows(&taos, "d0"), [0, 10, 11, 12]);
+        assert_eq!(rows(&taos, "d1"), [20, 21]);
+        assert_eq!(
+            batch.written(),
+            [
+                WrittenRange {
+                    table: "d0".to_string(),
+                    min_ts: 10,
+                    max_ts: 12,
+                },
+                WrittenRange {
+                    table: "d1".to_string(),
+                    min_ts: 20,
+                    max_ts: 21,
+                },
+            ]
+        );
+
+        let sql = batch.rollback()?;
+        assert_eq!(
+            sql,
+            [
+                "DELETE FROM d1 WHERE `ts` BETWEEN 20 AND 21",
+                "DELETE FROM d0 WHERE `ts` BETWEEN 10 AND 12",
+            ]
+        );
+        assert_eq!(*taos.executed.borrow(), sql);
+        assert!(batch.written().is_empty());
+        // Rows not written by the batch are kept.
+        assert_eq!(rows(&taos, "d0"), [0]);
+        assert!(rows(&taos, "d1").is_empty());
+        assert!(rows(&taos, "d2").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_and_commit() -> anyhow::Result<()> {
+        let taos = MockTaos::default();
+        let mut batch = CompensatingBatch::<_, MockStmt>::new(&taos)?
+            .with_dry_run(true)
+            .with_ts_column("ts");
+        batch.prepare("INSERT INTO db.tb VALUES(?, ?)")?;
+        batch
+            .bind(&[ts(vec![Some(2), None, Some(1)])])?
+            .add_batch()?;
+        batch.execute()?;
+
+        let sql = batch.rollback()?;
+        assert_eq!(sql, ["DELETE FROM db.tb WHERE `ts` BETWEEN 1 AND 2"]);
+        assert_eq!(batch.compensation_sql(), sql);
+        assert!(taos.executed.borrow().is_empty());
+        assert_eq!(rows(&taos, "db.tb"), [1, 2]);
+
+        batch.commit();
+        assert!(batch.compensation_sql().is_empty());
+        assert_eq!(rows(&taos, "db.tb"), [1, 2]);
+
+        // Rows bound to super table by tbname column can not be compensated.
+        batch.prepare("insert into meters(tbname, ts, v) values(?, ?, ?)")?;
+        batch.bind(&[ts(vec![3])])?.add_batch()?;
+        batch.execute()?;
+        assert!(batch.rollback().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_insert_table() {
+        assert_eq!(table_of_insert("insert into tb values(?,?)").unwrap(), "tb");
+        assert_eq!(
+            table_of_insert("INSERT INTO `db`.`tb`(ts, v) VALUES(?,?)").unwrap(),
+            "`db`.`tb`"
+        );
+        assert_eq!(table_of_insert("insert into ? values(?,?)"), None);
+        assert_eq!(
+            table_of_insert("insert into ? using stb tags(?) values(?,?)"),
+            None
+        );
+        assert_eq!(
+            table_of_insert("insert into stb (`tbname`, ts) values(?,?)"),
+            None
+        );
+        assert_eq!(table_of_insert("select 1"), None);
+    }
+}
@@ -0,0 +1,520 @@
+use serde::Deserialize;
+
+use crate::common::{views::ColumnView, NonFinitePolicy, Precision, Ty};
+use crate::prelude::RawError;
+
+/// A column parameter of a prepared insert statement.
+///
+/// Parameters are in the order of the column list in sql, eg. `ts, c3, c7` for
+/// `insert into tb (ts, c3, c7) values (?, ?, ?)`, or all columns of the table without a list.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StmtField {
+    name: String,
+    #[serde(rename = "field_type")]
+    ty: Ty,
+    #[serde(default)]
+    bytes: u32,
+    #[serde(default, deserialize_with = "de_precision")]
+    precision: Precision,
+}
+
+/// The precision is the number of digits for decimals, so only 0/1/2 are taken as precisions.
+fn de_precision<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Precision, D::Error> {
+    let precision = u8::deserialize(deserializer)?;
+    Ok(match precision {
+        0..=2 => Precision::from_u8(precision),
+        _ => Precision::Millisecond,
+    })
+}
+
+impl StmtField {
+    pub fn new(name: impl Into<String>, ty: Ty, bytes: u32) -> Self {
+        Self {
+            name: name.into(),
+            ty,
+            bytes,
+            precision: Precision::Millisecond,
+        }
+    }
+
+    /// Set the precision of a timestamp field.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn ty(&self) -> Ty {
+        self.ty
+    }
+
+    /// Length defined in `create table`.
+    pub fn bytes(&self) -> u32 {
+        self.bytes
+    }
+
+    /// Precision of the database for a timestamp field, milliseconds if it's unknown.
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+}
+
+/// Check bound columns against parameters of the prepared statement, in count, types, rows and
+/// timestamp precisions, so a wrong bind fails with a clear message instead of a server error
+/// or timestamps written in a wrong precision. Use [normalize_bind] to convert timestamps and
+/// [broadcast_bind] to repeat single-row columns first.
+///
+/// Nothing is checked if `fields` is empty, eg. when the parameters are unknown.
+pub fn validate_bind(fields: &[StmtField], params: &[ColumnView]) -> Result<(), RawError> {
+    if fields.is_empty() {
+        return Ok(());
+    }
+    if fields.len() != params.len() {
+        let names = fields.iter().map(StmtField::name).collect::<Vec<_>>();
+        return Err(RawError::from_string(format!(
+            "bind {} columns to {} parameters ({})",
+            params.len(),
+            fields.len(),
+            names.join(", ")
+        )));
+    }
+    let rows = params.first().map_or(0, ColumnView::len);
+    for (field, param) in fields.iter().zip(params) {
+        if param.as_ty() != field.ty() {
+            return Err(RawError::from_string(format!(
+                "column `{}` is {}, but bound with {}",
+                field.name(),
+                field.ty().name(),
+                param.as_ty().name()
+            )));
+        }
+        if param.len() != rows {
+            return Err(RawError::from_string(format!(
+                "column `{}` is bound with {} rows, expect {rows}",
+                field.name(),
+                param.len()
+            )));
+        }
+        if let ColumnView::Timestamp(view) = param {
+            // columns of all nulls are in milliseconds, they are fine in any precision.
+            if view.precision() != field.precision() && view.iter().any(|ts| ts.is_some()) {
+                return Err(RawError::from_string(format!(
+                    "column `{}` is in {} precision, but bound with timestamps in {}",
+                    field.name(),
+                    field.precision(),
+                    view.precision()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Repeat single-row columns bound next to columns of more rows to the same rows, eg. a batch id
+/// bound once with columns of data, see [ColumnView::repeat].
+///
+/// It's `None` if nothing is to be repeated. Columns of other rows are left to [validate_bind]
+/// to fail with the column name.
+///
+/// ```rust
+/// # use taos_query::common::ColumnView;
+/// # use taos_query::stmt::broadcast_bind;
+/// let ts = || ColumnView::from_millis_timestamp(vec![0, 1, 2]);
+/// let batch = ColumnView::from_ints(vec![7]);
+/// let params = broadcast_bind(&[ts(), batch]).unwrap();
+/// assert_eq!(params[1].len(), 3);
+/// assert!(broadcast_bind(&[ts()]).is_none());
+/// ```
+pub fn broadcast_bind(params: &[ColumnView]) -> Option<Vec<ColumnView>> {
+    let rows = params.iter().map(ColumnView::len).max()?;
+    if rows <= 1 || params.iter().all(|param| param.len() != 1) {
+        return None;
+    }
+    let params = params
+        .iter()
+        .map(|param| {
+            param
+                .repeat(rows)
+                .or_else(|| param.slice(0..param.len()))
+                .unwrap_or_else(|| ColumnView::null(param.as_ty(), 0))
+        })
+        .collect();
+    Some(params)
+}
+
+/// Convert bound timestamp columns to the precisions of their parameters, see
+/// [TimestampView::normalize_to](crate::common::views::TimestampView::normalize_to).
+///
+/// It's `None` if nothing is to be converted, or the columns don't match the parameters,
+/// which is left to [validate_bind]. It fails if any timestamp overflows in the precision.
+///
+/// ```rust
+/// # use taos_query::common::{ColumnView, Precision, Ty};
+/// # use taos_query::stmt::{normalize_bind, validate_bind, StmtField};
+/// let fields = [StmtField::new("ts", Ty::Timestamp, 8).with_precision(Precision::Nanosecond)];
+/// let params = [ColumnView::from_millis_timestamp(vec![1])];
+/// assert!(validate_bind(&fields, &params).is_err());
+///
+/// let params = normalize_bind(&fields, &params).unwrap().unwrap();
+/// validate_bind(&fields, &params).unwrap();
+/// ```
+pub fn normalize_bind(
+    fields: &[StmtField],
+    params: &[ColumnView],
+) -> Result<Option<Vec<ColumnView>>, RawError> {
+    let mismatched = |(field, param): (&StmtField, &ColumnView)| matches!(param, ColumnView::Timestamp(view) if view.precision() != field.precision());
+    if fields.len() != params.len() || !fields.iter().zip(params).any(mismatched) {
+        return Ok(None);
+    }
+    fields
+        .iter()
+        .zip(params)
+        .map(|(field, param)| match param {
+            ColumnView::Timestamp(view) => view
+                .normalize_to(field.precision())
+                .map(ColumnView::Timestamp)
+                .map_err(|err| RawError::from_string(format!("column `{}`: {err}", field.name()))),
+            param => Ok(param
+                .slice(0..param.len())
+                .unwrap_or_else(|| ColumnView::null(param.as_ty(), 0))),
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+/// Convert NaN and infinite floats of bound columns by `policy`, before they are sent to the
+/// server.
+///
+/// It's `None` if all floats are finite. It fails with the column and row of the first
+/// non-finite value for [NonFinitePolicy::Error], columns are named by `fields`, or by their
+/// index if the parameters are unknown.
+///
+/// ```rust
+/// # use taos_query::common::{ColumnView, NonFinitePolicy};
+/// # use taos_query::stmt::finite_bind;
+/// let params = [ColumnView::from_doubles(vec![1.0, f64::INFINITY, f64::NAN])];
+/// let err = finite_bind(&[], &params, NonFinitePolicy::Error).unwrap_err();
+/// assert!(err.message().contains("column #0 is bound with inf at row 1"));
+///
+/// let params = finite_bind(&[], &params, NonFinitePolicy::Clamp).unwrap().unwrap();
+/// assert_eq!(params[0].len(), 3);
+/// assert!(params[0].get(2).unwrap().is_null());
+/// ```
+pub fn finite_bind(
+    fields: &[StmtField],
+    params: &[ColumnView],
+    policy: NonFinitePolicy,
+) -> Result<Option<Vec<ColumnView>>, RawError> {
+    let first = params
+        .iter()
+        .enumerate()
+        .find_map(|(index, param)| match param {
+            ColumnView::Float(view) => view
+                .first_non_finite()
+                .map(|row| (index, row, view.get(row).map(f64::from))),
+            ColumnView::Double(view) => view
+                .first_non_finite()
+                .map(|row| (index, row, view.get(row))),
+            _ => None,
+        });
+    let Some((index, row, value)) = first else {
+        return Ok(None);
+    };
+    if policy == NonFinitePolicy::Error {
+        let column = fields.get(index).map_or_else(
+            || format!("#{index}"),
+            |field| format!("`{}`", field.name()),
+        );
+        return Err(RawError::from_string(format!(
+            "column {column} is bound with {} at row {row}, which is not written the same by all servers",
+            value.unwrap_or(f64::NAN)
+        )));
+    }
+    let clamp = policy == NonFinitePolicy::Clamp;
+    let params = params
+        .iter()
+        .map(|param| {
+            let finite = match param {
+                ColumnView::Float(view) => view
+                    .map_non_finite(|v| (clamp && v.is_infinite()).then(|| v.signum() * f32::MAX))
+                    .map(ColumnView::Float),
+                ColumnView::Double(view) => view
+                    .map_non_finite(|v| (clamp && v.is_infinite()).then(|| v.signum() * f64::MAX))
+                    .map(ColumnView::Double),
+                _ => None,
+            };
+            finite.unwrap_or_else(|| {
+                param
+                    .slice(0..param.len())
+                    .unwrap_or_else(|| ColumnView::null(param.as_ty(), 0))
+            })
+        })
+        .collect();
+    Ok(Some(params))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Timestamp, Value};
+
+    use super::*;
+
+    #[test]
+    fn validate_projection() {
+        let fields = [
+            StmtField::new("ts", Ty::Timestamp, 8),
+            StmtField::new("c3", Ty::SmallInt, 2),
+            StmtField::new("c7", Ty::USmallInt, 2),
+        ];
+        let ts = || ColumnView::from_millis_timestamp(vec![0, 1]);
+        let c3 = || ColumnView::from_small_ints(vec![1, 2]);
+        let c7 = || ColumnView::from_unsigned_small_ints(vec![Some(1), None]);
+        validate_bind(&fields, &[ts(), c3(), c7()]).unwrap();
+        validate_bind(&[], &[ts()]).unwrap();
+
+        let err = validate_bind(&fields, &[ts(), c3()]).unwrap_err();
+        assert!(err
+            .message()
+            .contains("bind 2 columns to 3 parameters (ts, c3, c7)"));
+
+        let err = validate_bind(&fields, &[ts(), c7(), c3()]).unwrap_err();
+        assert!(err.message().contains("column `c3` is SMALLINT"), "{err}");
+
+        let short = ColumnView::from_unsigned_small_ints(vec![1]);
+        let err = validate_bind(&fields, &[ts(), c3(), short]).unwrap_err();
+        assert!(
+            err.message().contains("bound with 1 rows, expect 2"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn broadcast_single_rows() {
+        let fields = [
+            StmtField::new("ts", Ty::Timestamp, 8).with_precision(Precision::Microsecond),
+            StmtField::new("batch", Ty::VarChar, 8),
+            StmtField::new("v", Ty::Int, 4),
+            StmtField::new("flag", Ty::Bool, 1),
+        ];
+        let ts = || ColumnView::from_micros_timestamp(vec![1, 2, 3]);
+        let batch = || ColumnView::from_varchar::<&str, _, _, _>(vec!["b1"]);
+        let v = || ColumnView::from_ints(vec![Some(1), None, Some(3)]);
+        let flag = || ColumnView::from_bools(vec![None::<bool>]);
+        assert!(validate_bind(&fields, &[ts(), batch(), v(), flag()]).is_err());
+
+        let params = broadcast_bind(&[ts(), batch(), v(), flag()]).unwrap();
+        validate_bind(&fields, &params).unwrap();
+        let rows: Vec<Vec<Value>> = (0..3)
+            .map(|row| {
+                params
+                    .iter()
+                    .map(|p| p.get(row).unwrap().to_value())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows[2],
+            [
+                Value::Timestamp(Timestamp::Microseconds(3)),
+                Value::VarChar("b1".to_string()),
+                Value::Int(3),
+                Value::Null(Ty::Bool),
+            ]
+        );
+        assert!(matches!(params[3], ColumnView::Null(_)));
+
+        let once = ColumnView::from_micros_timestamp(vec![7]);
+        let params = broadcast_bind(&[once, ColumnView::from_ints(vec![1, 2])]).unwrap();
+        assert_eq!(
+            params[0].get(1).unwrap().to_value(),
+            Value::Timestamp(Timestamp::Microseconds(7))
+        );
+
+        let short = ColumnView::from_bools(vec![true, false]);
+        let params = broadcast_bind(&[ts(), batch(), v(), short]).unwrap();
+        let err = validate_bind(&fields, &params).unwrap_err();
+        assert!(
+            err.message()
+                .contains("column `flag` is bound with 2 rows, expect 3"),
+            "{err}"
+        );
+        assert!(broadcast_bind(&[batch(), flag()]).is_none());
+        assert!(broadcast_bind(&[]).is_none());
+    }
+
+    #[test]
+    fn validate_precisions() {
+        let fields = [
+            StmtField::new("ts", Ty::Timestamp, 8).with_precision(Precision::Nanosecond),
+            StmtField::new("v", Ty::Int, 4),
+        ];
+        let v = || ColumnView::from_ints(vec![1, 2]);
+        let ms = ColumnView::from_millis_timestamp(vec![1_704_067_200_001, 1_704_067_200_002]);
+        let err = validate_bind(&fields, &[ms, v()]).unwrap_err();
+        assert!(
+            err.message()
+                .contains("column `ts` is in ns precision, but bound with timestamps in ms"),
+            "{err}"
+        );
+        let nulls = ColumnView::from_millis_timestamp(vec![None::<i64>, None]);
+        validate_bind(&fields, &[nulls, v()]).unwrap();
+
+        let ns = ColumnView::from_nanos_timestamp(vec![1, 2]);
+        assert!(normalize_bind(&fields, &[ns, v()]).unwrap().is_none());
+        let us = ColumnView::from_micros_timestamp(vec![1]);
+        assert!(normalize_bind(&fields, &[us]).unwrap().is_none());
+    }
+
+    #[test]
+    fn normalize_mixed_precisions() {
+        let fields = [StmtField::new("ts", Ty::Timestamp, 8).with_precision(Precision::Nanosecond)];
+        let ms = ColumnView::from_millis_timestamp(vec![Some(1_704_067_200_001), None]);
+        let us = ColumnView::from_micros_timestamp(vec![1_704_067_200_001_002]);
+
+        let mut merged = Vec::new();
+        for source in [ms, us] {
+            assert!(validate_bind(&fields, std::slice::from_ref(&source)).is_err());
+            let params = normalize_bind(&fields, &[source]).unwrap().unwrap();
+            validate_bind(&fields, &params).unwrap();
+            merged.extend(
+                params
+                    .into_iter()
+                    .flat_map(|view| view.iter().map(|v| v.to_value()).collect::<Vec<_>>()),
+            );
+        }
+        assert_eq!(
+            merged,
+            [
+                Value::Timestamp(Timestamp::Nanoseconds(1_704_067_200_001_000_000)),
+                Value::Null(Ty::Timestamp),
+                Value::Timestamp(Timestamp::Nanoseconds(1_704_067_200_001_002_000)),
+            ]
+        );
+
+        let fields = [
+            StmtField::new("ts", Ty::Timestamp, 8).with_precision(Precision::Nanosecond),
+            StmtField::new("s", Ty::NChar, 8),
+        ];
+        let far = ColumnView::from_millis_timestamp(vec![i64::MAX / 1000]);
+        let s = ColumnView::from_nchar::<String, _, _, _>(vec![Some("a".to_string())]);
+        let err = normalize_bind(&fields, &[far, s]).unwrap_err();
+        assert!(err.message().starts_with("column `ts`: timestamp"), "{err}");
+
+        let ts = ColumnView::from_micros_timestamp(vec![1]);
+        let s = ColumnView::from_nchar::<String, _, _, _>(vec![Some("a".to_string())]);
+        let params = normalize_bind(&fields, &[ts, s]).unwrap().unwrap();
+        assert_eq!(
+            params[1].get(0).unwrap().to_value(),
+            Value::NChar("a".to_string())
+        );
+    }
+
+    #[test]
+    fn finite_policies() {
+        let fields = [
+            StmtField::new("ts", Ty::Timestamp, 8),
+            StmtField::new("current", Ty::Float, 4),
+            StmtField::new("voltage", Ty::Double, 8),
+        ];
+        let ts = || ColumnView::from_millis_timestamp(vec![0, 1, 2, 3]);
+        let current = || {
+            ColumnView::from_floats(vec![
+                Some(1.5),
+                Some(f32::NEG_INFINITY),
+                None,
+                Some(f32::NAN),
+            ])
+        };
+        let voltage = || ColumnView::from_doubles(vec![220., f64::INFINITY, 219., 218.]);
+        let finite = ColumnView::from_floats(vec![1.0f32, 2.0, 3.0, 4.0]);
+        for policy in [
+            NonFinitePolicy::Error,
+            NonFinitePolicy::Null,
+            NonFinitePolicy::Clamp,
+        ] {
+            assert!(
+                finite_bind(&fields, &[ts(), finite.slice(0..4).unwrap()], policy)
+                    .unwrap()
+                    .is_none()
+            );
+        }
+
+        let err = finite_bind(
+            &fields,
+            &[ts(), current(), voltage()],
+            NonFinitePolicy::Error,
+        )
+        .unwrap_err();
+        assert!(
+            err.message()
+                .starts_with("column `current` is bound with -inf at row 1"),
+            "{err}"
+        );
+        let nan = ColumnView::from_doubles(vec![f64::NAN]);
+        let err = finite_bind(&[], &[nan], NonFinitePolicy::Error).unwrap_err();
+        assert!(err
+            .message()
+            .starts_with("column #0 is bound with NaN at row 0"));
+
+        let values = |params: Vec<ColumnView>| -> Vec<Vec<Value>> {
+            params[1..]
+                .iter()
+                .map(|p| p.iter().map(|v| v.to_value()).collect())
+                .collect()
+        };
+        let params = finite_bind(
+            &fields,
+            &[ts(), current(), voltage()],
+            NonFinitePolicy::Null,
+        )
+        .unwrap()
+        .unwrap();
+        validate_bind(&fields, &params).unwrap();
+        assert_eq!(
+            values(params),
+            [
+                vec![
+                    Value::Float(1.5),
+                    Value::Null(Ty::Float),
+                    Value::Null(Ty::Float),
+                    Value::Null(Ty::Float)
+                ],
+                vec![
+                    Value::Double(220.),
+                    Value::Null(Ty::Double),
+                    Value::Double(219.),
+                    Value::Double(218.)
+                ],
+            ]
+        );
+        let params = finite_bind(
+            &fields,
+            &[ts(), current(), voltage()],
+            NonFinitePolicy::Clamp,
+        )
+        .unwrap()
+        .unwrap();
+        let values = values(params);
+        assert_eq!(values[0][1], Value::Float(f32::MIN));
+        assert_eq!(values[0][3], Value::Null(Ty::Float));
+        assert_eq!(values[1][1], Value::Double(f64::MAX));
+    }
+
+    #[test]
+    fn field_de() {
+        let field: StmtField = serde_json::from_str(
+            r#"{"name":"c3","field_type":3,"precision":0,"scale":0,"bytes":2}"#,
+        )
+        .unwrap();
+        assert_eq!(field, StmtField::new("c3", Ty::SmallInt, 2));
+
+        let field: StmtField = serde_json::from_str(
+            r#"{"name":"ts","field_type":9,"precision":1,"scale":0,"bytes":8}"#,
+        )
+        .unwrap();
+        assert_eq!(field.precision(), Precision::Microsecond);
+    }
+}
@@ -4,7 +4,17 @@ use crate::{
 };
 
 mod column;
+mod compensating;
+mod field;
+mod query;
+mod table;
+mod tags;
 pub use column::*;
+pub use compensating::*;
+pub use field::*;
+pub use query::*;
+pub use table::*;
+pub use tags::*;
 
 pub trait Bindable<Q>
 where
@@ -17,6 +27,10 @@ where
 
     fn prepare<S: AsRef<str>>(&mut self, sql: S) -> Result<&mut Self, Self::Error>;
 
+    /// Set the table name, used as is, like `tb`, `db.tb` or `` `db`.`tb` ``.
+    ///
+    /// Names with dots, dashes or uppercase letters must be quoted by
+    /// [quote_ident](crate::util::quote_ident) or [quote_table_ref](crate::util::quote_table_ref).
     fn set_tbname<S: AsRef<str>>(&mut self, name: S) -> Result<&mut Self, Self::Error>;
 
     fn set_tags(&mut self, tags: &[Value]) -> Result<&mut Self, Self::Error>;
@@ -29,6 +43,25 @@ where
         self.set_tbname(name)?.set_tags(tags)
     }
 
+    /// Set tags from strings, eg. of config files, each parsed by the type of its tag with
+    /// [coerce_tags].
+    ///
+    /// It fails if the tags of the statement are unknown, see [Bindable::bound_tags].
+    fn set_tags_from_strs(&mut self, tags: &[&str]) -> Result<&mut Self, Self::Error>
+    where
+        Self::Error: From<CoerceError>,
+    {
+        if self.bound_tags().is_empty() && !tags.is_empty() {
+            return Err(CoerceError::Unknown.into());
+        }
+        let tags = coerce_tags(self.bound_tags(), tags)?;
+        self.set_tags(&tags)
+    }
+
+    /// Bind columns of rows, in the order of [Bindable::bound_columns].
+    ///
+    /// Columns of a single row are repeated to the rows of the others, eg. to bind a batch id
+    /// once with columns of data, see [broadcast_bind].
     fn bind(&mut self, params: &[ColumnView]) -> Result<&mut Self, Self::Error>;
 
     fn add_batch(&mut self) -> Result<&mut Self, Self::Error>;
@@ -37,6 +70,26 @@ where
 
     fn affected_rows(&self) -> usize;
 
+    /// Parameters of the prepared insert statement that columns are bound to, empty if they
+    /// are unknown.
+    ///
+    /// With a column list like `insert into tb (ts, c3, c7) values (?, ?, ?)`, only the listed
+    /// columns are bound, in the listed order; the others are written as NULL or defaults.
+    fn bound_columns(&self) -> &[StmtField] {
+        &[]
+    }
+
+    /// Tags of the prepared insert statement, empty if they are unknown, eg. before the table
+    /// name is set for `insert into ? using st tags(?, ?) ...`.
+    fn bound_tags(&self) -> &[StmtField] {
+        &[]
+    }
+
+    /// Result set of the executed query statement, eg. bind a single-row column for each
+    /// parameter of `select * from meters where ts > ? and current > ?`, execute and fetch
+    /// the result set.
+    ///
+    /// Insert statements have no result set and fail with an error.
     fn result_set(&mut self) -> Result<Q::ResultSet, Self::Error> {
         todo!()
     }
@@ -0,0 +1,192 @@
+use crate::common::{views::ColumnView, Value};
+use crate::helpers::{classify_sql, StatementKind};
+use crate::prelude::RawError;
+
+/// Check if `sql` is an insert statement by its first keyword, see [classify_sql].
+pub fn is_insert_sql(sql: &str) -> bool {
+    classify_sql(sql) == StatementKind::Insert
+}
+
+/// Super table of an insert statement with auto table creation as written, eg. `st` of
+/// `insert into ? using st tags(?) values(?)`, `None` for other statements.
+///
+/// ```rust
+/// # use taos_query::stmt::using_stable;
+/// assert_eq!(using_stable("insert into ? using `db`.`st` tags(?) values(?)"), Some("`db`.`st`"));
+/// assert_eq!(using_stable("insert into ? values(?, 'using x')"), None);
+/// ```
+pub fn using_stable(sql: &str) -> Option<&str> {
+    let bytes = sql.as_bytes();
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, &c) in bytes.iter().enumerate() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(b'\'' | b'"') if c == b'\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if matches!(c, b'\'' | b'"' | b'`') => quote = Some(c),
+            None if i > 0 && bytes[i - 1].is_ascii_whitespace() && is_using(&bytes[i..]) => {
+                return table_ref(sql[i + 5..].trim_start());
+            }
+            None => (),
+        }
+    }
+    None
+}
+
+fn is_using(bytes: &[u8]) -> bool {
+    bytes.len() > 5 && bytes[..5].eq_ignore_ascii_case(b"using") && bytes[5].is_ascii_whitespace()
+}
+
+/// The table name at the start of `sql`, with dots and quoted parts.
+fn table_ref(sql: &str) -> Option<&str> {
+    let mut quoted = false;
+    let end = sql
+        .char_indices()
+        .find(|&(_, c)| {
+            if c == '`' {
+                quoted = !quoted;
+            }
+            !quoted && (c.is_whitespace() || c == '(')
+        })
+        .map_or(sql.len(), |(i, _)| i);
+    Some(&sql[..end]).filter(|name| !name.is_empty())
+}
+
+/// Replace `?` placeholders of query `sql` with the first row of each parameter as sql
+/// literals, for connections that can't bind parameters of queries.
+///
+/// Each parameter must have exactly one row. Placeholders in quoted strings and names are
+/// kept. Timestamps are written as RFC3339 strings, so they're compared correctly in
+/// databases of any precision.
+///
+/// ```rust
+/// # use taos_query::common::ColumnView;
+/// # use taos_query::stmt::bind_query_sql;
+/// let params = [
+///     ColumnView::from_millis_timestamp(vec![0]),
+///     ColumnView::from_varchar(vec!["it's"]),
+/// ];
+/// let sql = bind_query_sql("select * from tb where ts > ? and s = ? and c = '?'", &params);
+/// assert!(sql.unwrap().ends_with(r"and s = 'it\'s' and c = '?'"));
+/// ```
+pub fn bind_query_sql(sql: &str, params: &[ColumnView]) -> Result<String, RawError> {
+    let mut bound = String::with_capacity(sql.len());
+    let mut placeholders = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in sql.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some('\'' | '"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if matches!(c, '\'' | '"' | '`') => quote = Some(c),
+            None if c == '?' => {
+                if let Some(param) = params.get(placeholders) {
+                    bound.push_str(&param_literal(placeholders, param)?);
+                }
+                placeholders += 1;
+                continue;
+            }
+            None => (),
+        }
+        bound.push(c);
+    }
+    if placeholders != params.len() {
+        return Err(RawError::from_string(format!(
+            "bind {} parameters to {placeholders} placeholders",
+            params.len()
+        )));
+    }
+    Ok(bound)
+}
+
+fn param_literal(index: usize, param: &ColumnView) -> Result<String, RawError> {
+    if param.len() != 1 {
+        return Err(RawError::from_string(format!(
+            "query parameter {index} is bound with {} rows, expect 1",
+            param.len()
+        )));
+    }
+    Ok(match param.get(0).map(|value| value.to_value()) {
+        Some(Value::Timestamp(ts)) => format!(
+            "'{}'",
+            ts.to_datetime_with_tz()
+                .to_rfc3339_opts(ts.precision().to_seconds_format(), false)
+        ),
+        Some(value) => value.to_sql_literal(),
+        None => "NULL".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_sql() {
+        assert!(is_insert_sql("insert into tb values(?, ?)"));
+        assert!(is_insert_sql("  INSERT into ? using st tags(?) values(?)"));
+        assert!(!is_insert_sql("select * from tb where ts > ?"));
+        assert!(!is_insert_sql("ins"));
+        assert!(is_insert_sql(
+            "-- load\n/* tables */ insert into tb values(?)"
+        ));
+        assert!(!is_insert_sql("inserted"));
+    }
+
+    #[test]
+    fn using_stable_of_sql() {
+        assert_eq!(
+            using_stable("insert into ? using st tags(?) values(?)"),
+            Some("st")
+        );
+        assert_eq!(
+            using_stable("INSERT INTO ? USING\tdb.`my st`(t1) TAGS(?) VALUES(?)"),
+            Some("db.`my st`")
+        );
+        assert_eq!(using_stable("insert into tb values(?, ?)"), None);
+        assert_eq!(using_stable("insert into `using st` values(?)"), None);
+        assert_eq!(using_stable("insert into ? using"), None);
+    }
+
+    #[test]
+    fn bind_params() {
+        let ts = || ColumnView::from_micros_timestamp(vec![1_700_000_000_123_456]);
+        let sql = bind_query_sql(
+            "select * from `t?` where ts > ? and c > ? and s in (?, \"a\\\"?\")",
+            &[
+                ts(),
+                ColumnView::from_doubles(vec![0.5]),
+                ColumnView::from_nchar::<&str, _, _, _>(vec![None]),
+            ],
+        )
+        .unwrap();
+        let expected = ts().get(0).unwrap().to_value();
+        let Value::Timestamp(expected) = expected else {
+            unreachable!()
+        };
+        let expected = expected
+            .to_datetime_with_tz()
+            .to_rfc3339_opts(chrono::SecondsFormat::Micros, false);
+        assert_eq!(
+            sql,
+            format!(
+                "select * from `t?` where ts > '{expected}' and c > 0.5 and s in (NULL, \"a\\\"?\")"
+            )
+        );
+
+        let err = bind_query_sql("select * from tb where c > ?", &[]).unwrap_err();
+        assert!(err
+            .message()
+            .contains("bind 0 parameters to 1 placeholders"));
+        let err = bind_query_sql(
+            "select * from tb where c > ?",
+            &[ColumnView::from_ints(vec![1, 2])],
+        )
+        .unwrap_err();
+        assert!(err.message().contains("parameter 0 is bound with 2 rows"));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::common::{views::ColumnView, Value};
+use crate::prelude::RawError;
+
+/// Name, tags and columns of a sub-table to bind in one execution with other sub-tables of the
+/// same super table, eg. for `insert into ? using meters tags(?, ?) values(?, ?, ?)`.
+#[derive(Debug, Clone, Copy)]
+pub struct TableBind<'a> {
+    /// The table name, used as is like [Bindable::set_tbname](super::Bindable::set_tbname).
+    pub name: &'a str,
+    /// Tags of the table, none to skip setting tags, eg. for `insert into ? values(...)`.
+    pub tags: &'a [Value],
+    pub columns: &'a [ColumnView],
+}
+
+/// Check that all tables bind the same number and types of columns as the first one.
+///
+/// It fails with the index of the first mismatched table.
+///
+/// ```rust
+/// # use taos_query::common::ColumnView;
+/// # use taos_query::stmt::{validate_tables, TableBind};
+/// let columns = || [
+///     ColumnView::from_millis_timestamp(vec![0]),
+///     ColumnView::from_ints(vec![1]),
+/// ];
+/// let (d0, d1) = (columns(), columns());
+/// let d2 = [ColumnView::from_millis_timestamp(vec![0])];
+/// let tables = [
+///     TableBind { name: "d0", tags: &[], columns: &d0 },
+///     TableBind { name: "d1", tags: &[], columns: &d1 },
+///     TableBind { name: "d2", tags: &[], columns: &d2 },
+/// ];
+/// let (index, err) = validate_tables(&tables).unwrap_err();
+/// assert_eq!(index, 2);
+/// assert_eq!(err.to_string(), "table `d2` binds 1 columns, but `d0` binds 2");
+/// ```
+pub fn validate_tables(tables: &[TableBind]) -> Result<(), (usize, RawError)> {
+    let Some((first, others)) = tables.split_first() else {
+        return Ok(());
+    };
+    for (index, table) in others.iter().enumerate() {
+        let index = index + 1;
+        if table.columns.len() != first.columns.len() {
+            let err = RawError::from_string(format!(
+                "table `{}` binds {} columns, but `{}` binds {}",
+                table.name,
+                table.columns.len(),
+                first.name,
+                first.columns.len()
+            ));
+            return Err((index, err));
+        }
+        let mismatched = first
+            .columns
+            .iter()
+            .zip(table.columns)
+            .position(|(a, b)| a.as_ty() != b.as_ty());
+        if let Some(column) = mismatched {
+            let err = RawError::from_string(format!(
+                "column {column} of table `{}` is {}, but {} in `{}`",
+                table.name,
+                table.columns[column].as_ty().name(),
+                first.columns[column].as_ty().name(),
+                first.name
+            ));
+            return Err((index, err));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,269 @@
+use std::num::IntErrorKind;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+use crate::common::{Precision, Timestamp, Ty, Value};
+use crate::prelude::RawError;
+
+use super::StmtField;
+
+/// Tags that can't be parsed by [coerce_tags].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CoerceError {
+    #[error("tags of the statement are unknown")]
+    Unknown,
+    #[error("set {got} tags to {expected} tag fields")]
+    Count { expected: usize, got: usize },
+    #[error("tag `{name}` is {ty}, but got `{input}`: {reason}")]
+    Invalid {
+        name: String,
+        ty: Ty,
+        input: String,
+        reason: String,
+    },
+}
+
+impl From<CoerceError> for RawError {
+    fn from(err: CoerceError) -> Self {
+        RawError::from_string(err.to_string())
+    }
+}
+
+/// Parse tags from strings, eg. of TOML or YAML configs, by the types of `schema`.
+///
+/// - Bools are `true`/`false` or `1`/`0`, case insensitive.
+/// - Integers and floats are checked against the range of the type.
+/// - Timestamps are RFC3339, `YYYY-MM-DD[ HH:MM:SS[.f]]` in local time, or integers in the
+///   precision of the field.
+/// - JSON tags must be objects.
+/// - Strings are taken as is, varbinary tags as the bytes of the string.
+///
+/// ```rust
+/// # use taos_query::common::{Ty, Value};
+/// # use taos_query::stmt::{coerce_tags, StmtField};
+/// let schema = [
+///     StmtField::new("enabled", Ty::Bool, 1),
+///     StmtField::new("group", Ty::TinyInt, 1),
+/// ];
+/// let tags = coerce_tags(&schema, &["true", "42"]).unwrap();
+/// assert_eq!(tags, [Value::Bool(true), Value::TinyInt(42)]);
+///
+/// let err = coerce_tags(&schema, &["true", "300"]).unwrap_err();
+/// assert_eq!(err.to_string(), "tag `group` is TINYINT, but got `300`: out of range");
+/// ```
+pub fn coerce_tags<S: AsRef<str>>(
+    schema: &[StmtField],
+    raw: &[S],
+) -> Result<Vec<Value>, CoerceError> {
+    if schema.len() != raw.len() {
+        return Err(CoerceError::Count {
+            expected: schema.len(),
+            got: raw.len(),
+        });
+    }
+    schema
+        .iter()
+        .zip(raw)
+        .map(|(field, input)| {
+            coerce_tag(field, input.as_ref()).map_err(|reason| CoerceError::Invalid {
+                name: field.name().to_string(),
+                ty: field.ty(),
+                input: input.as_ref().to_string(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+fn coerce_tag(field: &StmtField, input: &str) -> Result<Value, String> {
+    let trimmed = input.trim();
+    Ok(match field.ty() {
+        Ty::Bool => match trimmed.to_ascii_lowercase().as_str() {
+            "true" | "1" => Value::Bool(true),
+            "false" | "0" => Value::Bool(false),
+            _ => return Err("expect true/false or 1/0".to_string()),
+        },
+        Ty::TinyInt => Value::TinyInt(parse_int(trimmed)?),
+        Ty::SmallInt => Value::SmallInt(parse_int(trimmed)?),
+        Ty::Int => Value::Int(parse_int(trimmed)?),
+        Ty::BigInt => Value::BigInt(parse_int(trimmed)?),
+        Ty::UTinyInt => Value::UTinyInt(parse_int(trimmed)?),
+        Ty::USmallInt => Value::USmallInt(parse_int(trimmed)?),
+        Ty::UInt => Value::UInt(parse_int(trimmed)?),
+        Ty::UBigInt => Value::UBigInt(parse_int(trimmed)?),
+        Ty::Float => {
+            let v = parse_float(trimmed)?;
+            if v.is_finite() && v.abs() > f32::MAX as f64 {
+                return Err("out of range".to_string());
+            }
+            Value::Float(v as f32)
+        }
+        Ty::Double => Value::Double(parse_float(trimmed)?),
+        Ty::Timestamp => Value::Timestamp(parse_timestamp(trimmed, field.precision())?),
+        Ty::VarChar => Value::VarChar(input.to_string()),
+        Ty::NChar => Value::NChar(input.to_string()),
+        Ty::VarBinary => Value::VarBinary(input.as_bytes().to_vec()),
+        Ty::Json => match serde_json::from_str(input).map_err(|err| err.to_string())? {
+            json @ serde_json::Value::Object(_) => Value::Json(json),
+            _ => return Err("expect a json object".to_string()),
+        },
+        ty => return Err(format!("{ty} tags are not supported")),
+    })
+}
+
+fn parse_int<T: FromStr<Err = std::num::ParseIntError>>(input: &str) -> Result<T, String> {
+    input.parse().map_err(|err: std::num::ParseIntError| {
+        match err.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => "out of range",
+            _ => "not an integer",
+        }
+        .to_string()
+    })
+}
+
+fn parse_float(input: &str) -> Result<f64, String> {
+    input.parse().map_err(|_| "not a number".to_string())
+}
+
+fn parse_timestamp(input: &str, precision: Precision) -> Result<Timestamp, String> {
+    if let Ok(raw) = input.parse::<i64>() {
+        return Timestamp::try_new(raw, precision).map_err(|err| err.to_string());
+    }
+    let datetime = if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        datetime.with_timezone(&Local)
+    } else {
+        let naive = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+            .ok_or("expect RFC3339, `YYYY-MM-DD[ HH:MM:SS]` or an integer")?;
+        Local
+            .from_local_datetime(&naive)
+            .single()
+            .ok_or("ambiguous local time")?
+    };
+    Timestamp::from_datetime(&datetime, precision).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coerce_all_types() {
+        let schema = [
+            StmtField::new("b", Ty::Bool, 1),
+            StmtField::new("i8", Ty::TinyInt, 1),
+            StmtField::new("i16", Ty::SmallInt, 2),
+            StmtField::new("i32", Ty::Int, 4),
+            StmtField::new("i64", Ty::BigInt, 8),
+            StmtField::new("u8", Ty::UTinyInt, 1),
+            StmtField::new("u16", Ty::USmallInt, 2),
+            StmtField::new("u32", Ty::UInt, 4),
+            StmtField::new("u64", Ty::UBigInt, 8),
+            StmtField::new("f32", Ty::Float, 4),
+            StmtField::new("f64", Ty::Double, 8),
+            StmtField::new("ts", Ty::Timestamp, 8).with_precision(Precision::Microsecond),
+            StmtField::new("epoch", Ty::Timestamp, 8),
+            StmtField::new("date", Ty::Timestamp, 8),
+            StmtField::new("s", Ty::VarChar, 16),
+            StmtField::new("n", Ty::NChar, 16),
+            StmtField::new("bin", Ty::VarBinary, 16),
+        ];
+        let raw = [
+            "TRUE",
+            "-128",
+            "32767",
+            " -2147483648 ",
+            "9223372036854775807",
+            "255",
+            "65535",
+            "4294967295",
+            "18446744073709551615",
+            "1.5",
+            "-2.25e10",
+            "2024-01-01T08:00:00.000001+08:00",
+            "1704067200000",
+            "2024-01-01",
+            " a b ",
+            "中文",
+            "\\x01",
+        ];
+        let midnight = Local
+            .from_local_datetime(&NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().into())
+            .unwrap();
+        assert_eq!(
+            coerce_tags(&schema, &raw).unwrap(),
+            [
+                Value::Bool(true),
+                Value::TinyInt(i8::MIN),
+                Value::SmallInt(i16::MAX),
+                Value::Int(i32::MIN),
+                Value::BigInt(i64::MAX),
+                Value::UTinyInt(u8::MAX),
+                Value::USmallInt(u16::MAX),
+                Value::UInt(u32::MAX),
+                Value::UBigInt(u64::MAX),
+                Value::Float(1.5),
+                Value::Double(-2.25e10),
+                Value::Timestamp(Timestamp::Microseconds(1_704_067_200_000_001)),
+                Value::Timestamp(Timestamp::Milliseconds(1_704_067_200_000)),
+                Value::Timestamp(Timestamp::Milliseconds(midnight.timestamp_millis())),
+                Value::VarChar(" a b ".to_string()),
+                Value::NChar("中文".to_string()),
+                Value::VarBinary(b"\\x01".to_vec()),
+            ]
+        );
+
+        let json = [StmtField::new("j", Ty::Json, 4096)];
+        assert_eq!(
+            coerce_tags(&json, &[r#"{"k": [1, "v"]}"#]).unwrap(),
+            [Value::Json(serde_json::json!({"k": [1, "v"]}))]
+        );
+    }
+
+    #[test]
+    fn coerce_malformed() {
+        let field = |ty| [StmtField::new("t", ty, 8)];
+        let cases = [
+            (Ty::Bool, "yes", "expect true/false or 1/0"),
+            (Ty::TinyInt, "128", "out of range"),
+            (Ty::UTinyInt, "-1", "not an integer"),
+            (Ty::UBigInt, "18446744073709551616", "out of range"),
+            (Ty::Int, "4.2", "not an integer"),
+            (Ty::Float, "1e39", "out of range"),
+            (Ty::Double, "one", "not a number"),
+            (
+                Ty::Timestamp,
+                "2024-13-01",
+                "expect RFC3339, `YYYY-MM-DD[ HH:MM:SS]` or an integer",
+            ),
+            (Ty::Json, "[1, 2]", "expect a json object"),
+            (Ty::Decimal, "1.0", "DECIMAL tags are not supported"),
+        ];
+        for (ty, input, reason) in cases {
+            assert_eq!(
+                coerce_tags(&field(ty), &[input]).unwrap_err(),
+                CoerceError::Invalid {
+                    name: "t".to_string(),
+                    ty,
+                    input: input.to_string(),
+                    reason: reason.to_string(),
+                },
+                "{input}"
+            );
+        }
+
+        let err = coerce_tags(&field(Ty::Json), &["{"]).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("tag `t` is JSON, but got `{`: EOF"));
+        let err = coerce_tags(&field(Ty::Int), &["1".to_string(), "2".to_string()]).unwrap_err();
+        assert_eq!(err.to_string(), "set 2 tags to 1 tag fields");
+    }
+}
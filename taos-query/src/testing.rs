@@ -0,0 +1,422 @@
+//! Random data generation for load testing and fuzzing.
+//!
+//! All data is deterministic for a given seed, so a failure found by random data could be
+//! reproduced by the seed.
+//!
+//! ```rust
+//! # use taos_query::common::{views::ColSchema, Ty};
+//! # use taos_query::testing::{random_block, RandomOpts};
+//! let schema = [
+//!     ColSchema::new(Ty::Timestamp, 8),
+//!     ColSchema::new(Ty::Int, 4),
+//!     ColSchema::new(Ty::VarChar, 16),
+//! ];
+//! let opts = RandomOpts::new().with_null_probability(0.1);
+//! let block = random_block(&schema, 1000, 42, &opts);
+//! assert_eq!(block.nrows(), 1000);
+//! assert_eq!(block.field_names(), ["c0", "c1", "c2"]);
+//! assert_eq!(
+//!     block.to_values(),
+//!     random_block(&schema, 1000, 42, &opts).to_values()
+//! );
+//! ```
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use crate::common::views::{views_to_raw_block, ColSchema, JsonView, TimestampView};
+use crate::common::{ColumnView, Precision, RawBlock, Ty};
+
+/// Options of [random_block] and [random_column].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomOpts {
+    precision: Precision,
+    base_timestamp: i64,
+    max_timestamp_step: i64,
+    string_len: RangeInclusive<usize>,
+    null_probability: f64,
+    column_null_probability: BTreeMap<usize, f64>,
+}
+
+impl Default for RandomOpts {
+    fn default() -> Self {
+        Self {
+            precision: Precision::Millisecond,
+            base_timestamp: 1_640_995_200_000,
+            max_timestamp_step: 1,
+            string_len: 0..=16,
+            null_probability: 0.,
+            column_null_probability: BTreeMap::new(),
+        }
+    }
+}
+
+impl RandomOpts {
+    /// Millisecond timestamps from `2022-01-01T00:00:00Z` in steps of 1, strings of 0 to 16
+    /// chars and no nulls.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Precision of the block and timestamp columns.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// The first timestamp of timestamp columns, in the precision of the options.
+    pub fn with_base_timestamp(mut self, base: i64) -> Self {
+        self.base_timestamp = base;
+        self
+    }
+
+    /// Timestamps increase by a random step in `1..=max`, so they're unique and ordered.
+    pub fn with_max_timestamp_step(mut self, max: i64) -> Self {
+        self.max_timestamp_step = max.max(1);
+        self
+    }
+
+    /// Range of chars of varchar/nchar/json strings, limited by the column length of schema.
+    pub fn with_string_len(mut self, len: RangeInclusive<usize>) -> Self {
+        self.string_len = len;
+        self
+    }
+
+    /// Probability of NULL values of all columns, `0.0` for no NULL.
+    ///
+    /// The first timestamp column of a block is the primary key, it's never NULL.
+    pub fn with_null_probability(mut self, probability: f64) -> Self {
+        self.null_probability = probability;
+        self
+    }
+
+    /// Probability of NULL values of the column at index `col` of [random_block].
+    pub fn with_column_null_probability(mut self, col: usize, probability: f64) -> Self {
+        self.column_null_probability.insert(col, probability);
+        self
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    fn null_probability_of(&self, col: usize) -> f64 {
+        self.column_null_probability
+            .get(&col)
+            .copied()
+            .unwrap_or(self.null_probability)
+    }
+}
+
+/// SplitMix64, it's small and the output is stable across versions, unlike rand's `StdRng`.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `range`.
+    fn range(&mut self, range: RangeInclusive<usize>) -> usize {
+        let (start, end) = range.into_inner();
+        if end <= start {
+            return start;
+        }
+        start + (self.next_u64() % (end - start + 1) as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0. && self.next_f64() < probability
+    }
+}
+
+const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const WIDE_CHARS: [char; 8] = ['涛', '思', '数', '据', 'α', 'β', 'é', 'ü'];
+
+/// Generates a column, `len` is the column length of schema, `0` for no limit.
+struct ColumnGen<'a> {
+    opts: &'a RandomOpts,
+    rng: Rng,
+    rows: usize,
+    null_probability: f64,
+}
+
+impl<'a> ColumnGen<'a> {
+    fn values<T>(&mut self, mut f: impl FnMut(&mut Rng) -> T) -> Vec<Option<T>> {
+        (0..self.rows)
+            .map(|_| {
+                if self.rng.chance(self.null_probability) {
+                    None
+                } else {
+                    Some(f(&mut self.rng))
+                }
+            })
+            .collect()
+    }
+
+    fn strings(&mut self, max_len: usize, wide: bool) -> Vec<Option<String>> {
+        let (start, end) = self.opts.string_len.clone().into_inner();
+        let range = if max_len > 0 {
+            start.min(max_len)..=end.min(max_len)
+        } else {
+            start..=end
+        };
+        self.values(|rng| {
+            let len = rng.range(range.clone());
+            (0..len)
+                .map(|_| {
+                    let r = rng.next_u64() as usize;
+                    if wide && r % 4 == 0 {
+                        WIDE_CHARS[r / 4 % WIDE_CHARS.len()]
+                    } else {
+                        ALPHANUMERIC[r % ALPHANUMERIC.len()] as char
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn timestamps(&mut self) -> Vec<Option<i64>> {
+        let (mut ts, step) = (
+            self.opts.base_timestamp,
+            self.opts.max_timestamp_step as u64,
+        );
+        (0..self.rows)
+            .map(|_| {
+                let value = ts;
+                ts += 1 + (self.rng.next_u64() % step) as i64;
+                if self.rng.chance(self.null_probability) {
+                    None
+                } else {
+                    Some(value)
+                }
+            })
+            .collect()
+    }
+
+    fn column(&mut self, ty: Ty, len: u32) -> ColumnView {
+        let len = len as usize;
+        match ty {
+            Ty::Bool => ColumnView::from_bools(self.values(|rng| rng.next_u64() & 1 == 1)),
+            Ty::TinyInt => ColumnView::from_tiny_ints(self.values(|rng| rng.next_u64() as i8)),
+            Ty::SmallInt => ColumnView::from_small_ints(self.values(|rng| rng.next_u64() as i16)),
+            Ty::Int => ColumnView::from_ints(self.values(|rng| rng.next_u64() as i32)),
+            Ty::BigInt => ColumnView::from_big_ints(self.values(|rng| rng.next_u64() as i64)),
+            Ty::UTinyInt => {
+                ColumnView::from_unsigned_tiny_ints(self.values(|rng| rng.next_u64() as u8))
+            }
+            Ty::USmallInt => {
+                ColumnView::from_unsigned_small_ints(self.values(|rng| rng.next_u64() as u16))
+            }
+            Ty::UInt => ColumnView::from_unsigned_ints(self.values(|rng| rng.next_u64() as u32)),
+            Ty::UBigInt => ColumnView::from_unsigned_big_ints(self.values(Rng::next_u64)),
+            Ty::Float => {
+                ColumnView::from_floats(self.values(|rng| (rng.next_f64() * 2e6 - 1e6) as f32))
+            }
+            Ty::Double => ColumnView::from_doubles(self.values(|rng| rng.next_f64() * 2e6 - 1e6)),
+            Ty::Timestamp => {
+                let values = self.timestamps();
+                ColumnView::Timestamp(match self.opts.precision {
+                    Precision::Millisecond => TimestampView::from_millis(values),
+                    Precision::Microsecond => TimestampView::from_micros(values),
+                    Precision::Nanosecond => TimestampView::from_nanos(values),
+                })
+            }
+            Ty::VarChar => ColumnView::from_varchar::<String, _, _, _>(self.strings(len, false)),
+            Ty::NChar => ColumnView::from_nchar::<String, _, _, _>(self.strings(len / 4, true)),
+            Ty::Json => {
+                let keys = self.strings(len.saturating_sub(10) / 2, false);
+                let values = keys
+                    .into_iter()
+                    .map(|key| key.map(|key| format!(r#"{{"k{key}":"{key}"}}"#)))
+                    .collect::<Vec<_>>();
+                ColumnView::Json(JsonView::from_iter::<String, _, _, _>(values))
+            }
+            ty => panic!("random column of {ty:?} is not supported"),
+        }
+    }
+}
+
+/// A random column of `rows` values of type `ty`.
+///
+/// Strings are not limited by column length, and NULL values are generated by
+/// [RandomOpts::with_null_probability].
+///
+/// # Panics
+///
+/// Panics if there's no [ColumnView] for the type, eg. [Ty::Decimal].
+pub fn random_column(ty: Ty, rows: usize, seed: u64, opts: &RandomOpts) -> ColumnView {
+    ColumnGen {
+        opts,
+        rng: Rng(seed),
+        rows,
+        null_probability: opts.null_probability,
+    }
+    .column(ty, 0)
+}
+
+/// A random block of `rows` rows matching `schema`, field names are `c0`, `c1`, ...
+///
+/// - Timestamps increase monotonically from [RandomOpts::with_base_timestamp].
+/// - Varchar strings are limited to the column length in bytes, nchar strings to a quarter of
+///   it, so a block with the schema of a table could be written into the table.
+/// - NULL values are generated by the null probability of each column, except for the first
+///   column if it's a timestamp.
+///
+/// Use [Field::to_column_schema](crate::common::Field::to_column_schema) to get the schema
+/// from fields of a table.
+///
+/// # Panics
+///
+/// Panics if there's no [ColumnView] for a type in schema, eg. [Ty::Decimal].
+pub fn random_block(schema: &[ColSchema], rows: usize, seed: u64, opts: &RandomOpts) -> RawBlock {
+    let mut seeds = Rng(seed);
+    let views: Vec<_> = schema
+        .iter()
+        .enumerate()
+        .map(|(col, schema)| {
+            let null_probability = if col == 0 && schema.ty() == Ty::Timestamp {
+                0.
+            } else {
+                opts.null_probability_of(col)
+            };
+            ColumnGen {
+                opts,
+                rng: Rng(seeds.next_u64()),
+                rows,
+                null_probability,
+            }
+            .column(schema.ty(), schema.len())
+        })
+        .collect();
+    let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), opts.precision);
+    block.with_field_names((0..schema.len()).map(|col| format!("c{col}")));
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Value;
+
+    const TYPES: [Ty; 14] = [
+        Ty::Timestamp,
+        Ty::Bool,
+        Ty::TinyInt,
+        Ty::SmallInt,
+        Ty::Int,
+        Ty::BigInt,
+        Ty::UTinyInt,
+        Ty::USmallInt,
+        Ty::UInt,
+        Ty::UBigInt,
+        Ty::Float,
+        Ty::Double,
+        Ty::VarChar,
+        Ty::NChar,
+    ];
+
+    fn values(view: &ColumnView) -> Vec<Value> {
+        view.iter().map(|v| v.to_value()).collect()
+    }
+
+    #[test]
+    fn random_columns() {
+        let opts = RandomOpts::new()
+            .with_null_probability(0.2)
+            .with_string_len(2..=8)
+            .with_max_timestamp_step(10);
+        for ty in TYPES {
+            let view = random_column(ty, 500, 7, &opts);
+            assert_eq!(view.len(), 500);
+            assert_eq!(view.as_ty(), ty);
+            assert_eq!(values(&view), values(&random_column(ty, 500, 7, &opts)));
+            assert_ne!(values(&view), values(&random_column(ty, 500, 8, &opts)));
+            let nulls = view.iter().filter(|v| v.is_null()).count();
+            assert!((50..150).contains(&nulls), "{ty:?}: {nulls} nulls");
+        }
+
+        let ts = random_column(
+            Ty::Timestamp,
+            1000,
+            1,
+            &RandomOpts::new().with_max_timestamp_step(5),
+        );
+        let ts: Vec<_> = ts
+            .iter()
+            .map(|v| v.to_timestamp().unwrap().as_raw_i64())
+            .collect();
+        assert_eq!(ts[0], 1_640_995_200_000);
+        assert!(ts.windows(2).all(|w| (1..=5).contains(&(w[1] - w[0]))));
+
+        let s = random_column(
+            Ty::VarChar,
+            1000,
+            1,
+            &RandomOpts::new().with_string_len(3..=5),
+        );
+        assert!(s
+            .iter()
+            .all(|v| (3..=5).contains(&v.to_str().unwrap().len())));
+    }
+
+    #[test]
+    fn random_block_schema() {
+        let schema = [
+            ColSchema::new(Ty::Timestamp, 8),
+            ColSchema::new(Ty::VarChar, 4),
+            ColSchema::new(Ty::Int, 4),
+            ColSchema::new(Ty::Double, 8),
+        ];
+        let opts = RandomOpts::new()
+            .with_precision(Precision::Microsecond)
+            .with_null_probability(0.5)
+            .with_column_null_probability(2, 0.)
+            .with_string_len(0..=100);
+        let block = random_block(&schema, 256, 3, &opts);
+        assert_eq!(block.nrows(), 256);
+        assert_eq!(block.precision(), Precision::Microsecond);
+        let views = block.column_views();
+        assert_eq!(views[0].iter().filter(|v| v.is_null()).count(), 0);
+        assert!(views[1].iter().any(|v| v.is_null()));
+        assert!(views[1]
+            .iter()
+            .all(|v| v.is_null() || v.to_str().unwrap().len() <= 4));
+        assert_eq!(views[2].iter().filter(|v| v.is_null()).count(), 0);
+        assert!(views[3].iter().any(|v| v.is_null()));
+    }
+
+    /// Slicing and concatenating random columns keep values.
+    #[test]
+    fn slice_and_concat_random_columns() {
+        let opts = RandomOpts::new()
+            .with_null_probability(0.3)
+            .with_string_len(0..=12);
+        for seed in 0..20 {
+            let mut rng = Rng(seed);
+            let rows = rng.range(1..=300);
+            for ty in TYPES {
+                let view = random_column(ty, rows, seed, &opts);
+                let all = values(&view);
+
+                let start = rng.range(0..=rows - 1);
+                let end = rng.range(start + 1..=rows + 10);
+                let slice = view.slice(start..end).unwrap();
+                assert_eq!(values(&slice), all[start..end.min(rows)], "{ty:?} {seed}");
+
+                let rhs = random_column(ty, rng.range(0..=50), seed + 100, &opts);
+                let concat = view.concat(&rhs);
+                assert_eq!(values(&concat), [all.clone(), values(&rhs)].concat());
+            }
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Introspection of subscriptions, see [SubscriptionInfo] and [StaleDetector].
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use crate::common::Value;
+use crate::helpers::{normalize, value_as_i64, value_as_str};
+
+use super::{Assignment, VGroupId};
+
+/// A vgroup of a topic subscribed by a consumer group, from
+/// `information_schema.ins_subscriptions`.
+///
+/// Columns differ across server versions, so only the known columns are parsed into typed
+/// fields, `None` if the server does not report it. All columns are kept in
+/// [SubscriptionInfo::columns].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionInfo {
+    pub topic: String,
+    pub consumer_group: String,
+    /// The consumer the vgroup is assigned to, `None` if not assigned.
+    pub consumer_id: Option<String>,
+    pub vgroup_id: VGroupId,
+    /// Committed offset, `None` if nothing is committed.
+    pub offset: Option<i64>,
+    /// Rows consumed, only reported by some versions.
+    pub rows: Option<i64>,
+    /// All columns by normalized column name, including the typed ones above.
+    pub columns: BTreeMap<String, Value>,
+}
+
+impl SubscriptionInfo {
+    /// Parse subscriptions from field names and rows of `information_schema.ins_subscriptions`.
+    pub fn from_rows<S: AsRef<str>>(names: &[S], rows: &[Vec<Value>]) -> Vec<Self> {
+        rows.iter().map(|row| Self::from_row(names, row)).collect()
+    }
+
+    fn from_row<S: AsRef<str>>(names: &[S], row: &[Value]) -> Self {
+        let columns: BTreeMap<_, _> = names
+            .iter()
+            .map(|name| normalize(name.as_ref()))
+            .zip(row.iter().cloned())
+            .collect();
+        let get = |names: &[&str]| names.iter().find_map(|name| columns.get(*name));
+        let string = |names: &[&str]| {
+            get(names)
+                .and_then(value_as_str)
+                .filter(|s| !s.is_empty())
+                .map(ToString::to_string)
+        };
+        // consumer id is a bigint in 3.0 and a hex string later.
+        let consumer_id = get(&["consumer_id"]).and_then(|value| match value_as_i64(value) {
+            Some(0) => None,
+            Some(id) => Some(id.to_string()),
+            None => value_as_str(value)
+                .filter(|s| !s.is_empty())
+                .map(ToString::to_string),
+        });
+        Self {
+            topic: string(&["topic_name", "topic"]).unwrap_or_default(),
+            consumer_group: string(&["consumer_group", "group_id"]).unwrap_or_default(),
+            consumer_id,
+            vgroup_id: get(&["vgroup_id", "vgroup"])
+                .and_then(value_as_i64)
+                .unwrap_or_default() as _,
+            offset: get(&["offset"]).and_then(|value| {
+                value_as_i64(value).or_else(|| value_as_str(value).and_then(parse_offset))
+            }),
+            rows: get(&["rows"]).and_then(value_as_i64),
+            columns,
+        }
+    }
+}
+
+/// Offset in formats like `log:13` or `offset(log) ver:13`, `None` for `earliest`, `N/A` etc.
+fn parse_offset(s: &str) -> Option<i64> {
+    let digits = s.len() - s.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    s[s.len() - digits..].parse().ok()
+}
+
+/// A subscription whose offset has not advanced while there are messages to consume.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaleSubscription {
+    pub subscription: SubscriptionInfo,
+    /// High watermark of the vgroup.
+    pub high_watermark: i64,
+    /// Messages behind the high watermark.
+    pub backlog: i64,
+    /// Time since the offset was seen advancing.
+    pub stalled: Duration,
+}
+
+/// Find consumers that hold assignments but stopped polling.
+///
+/// Call [StaleDetector::find_stale] periodically with the current subscriptions and high
+/// watermarks of vgroups, eg. the `end` of [Assignment]s of a monitoring consumer. A
+/// subscription is stale if it's assigned to a consumer, behind the high watermark and its
+/// offset has not changed for the threshold.
+///
+/// The offset is the committed one, so consumers that poll without committing look stale.
+#[derive(Debug)]
+pub struct StaleDetector {
+    threshold: Duration,
+    seen: HashMap<(String, String, VGroupId), (Option<i64>, Instant)>,
+}
+
+impl StaleDetector {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            seen: HashMap::new(),
+        }
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Check subscriptions against `high_watermarks` of topics and vgroups.
+    ///
+    /// Subscriptions first seen or without a known high watermark are never stale.
+    pub fn find_stale(
+        &mut self,
+        subscriptions: &[SubscriptionInfo],
+        high_watermarks: &HashMap<(String, VGroupId), i64>,
+    ) -> Vec<StaleSubscription> {
+        self.find_stale_at(subscriptions, high_watermarks, Instant::now())
+    }
+
+    fn find_stale_at(
+        &mut self,
+        subscriptions: &[SubscriptionInfo],
+        high_watermarks: &HashMap<(String, VGroupId), i64>,
+        now: Instant,
+    ) -> Vec<StaleSubscription> {
+        let mut seen = HashMap::with_capacity(subscriptions.len());
+        let mut stale = Vec::new();
+        for sub in subscriptions {
+            let key = (sub.consumer_group.clone(), sub.topic.clone(), sub.vgroup_id);
+            let since = match self.seen.get(&key) {
+                Some((offset, since)) if *offset == sub.offset => *since,
+                _ => now,
+            };
+            seen.insert(key, (sub.offset, since));
+
+            let Some(high_watermark) = high_watermarks
+                .get(&(sub.topic.clone(), sub.vgroup_id))
+                .copied()
+            else {
+                continue;
+            };
+            let backlog = high_watermark - sub.offset.unwrap_or_default();
+            let stalled = now.saturating_duration_since(since);
+            if sub.consumer_id.is_some() && backlog > 0 && stalled >= self.threshold {
+                stale.push(StaleSubscription {
+                    subscription: sub.clone(),
+                    high_watermark,
+                    backlog,
+                    stalled,
+                });
+            }
+        }
+        self.seen = seen;
+        stale
+    }
+}
+
+/// High watermarks of vgroups of `topic` for [StaleDetector::find_stale].
+pub fn high_watermarks<'a>(
+    topic: &str,
+    assignments: impl IntoIterator<Item = &'a Assignment>,
+) -> HashMap<(String, VGroupId), i64> {
+    assignments
+        .into_iter()
+        .map(|a| ((topic.to_string(), a.vgroup_id), a.end))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<&'static str> {
+        vec![
+            "topic_name",
+            "consumer_group",
+            "vgroup_id",
+            "consumer_id",
+            "offset",
+            "rows",
+        ]
+    }
+
+    fn row(consumer_id: Value, offset: &str) -> Vec<Value> {
+        vec![
+            Value::VarChar("t1".to_string()),
+            Value::VarChar("g1".to_string()),
+            Value::Int(2),
+            consumer_id,
+            Value::VarChar(offset.to_string()),
+            Value::BigInt(10),
+        ]
+    }
+
+    #[test]
+    fn parse_rows() {
+        let rows = [
+            row(Value::BigInt(-8_000_000), "log:13"),
+            row(
+                Value::VarChar("0x7c1ba2d5d7d4a2".to_string()),
+                "offset(log) ver:7",
+            ),
+            row(Value::Null(crate::common::Ty::BigInt), "earliest"),
+        ];
+        let subs = SubscriptionInfo::from_rows(&names(), &rows);
+        assert_eq!(subs.len(), 3);
+        assert_eq!(subs[0].topic, "t1");
+        assert_eq!(subs[0].consumer_group, "g1");
+        assert_eq!(subs[0].vgroup_id, 2);
+        assert_eq!(subs[0].consumer_id.as_deref(), Some("-8000000"));
+        assert_eq!(subs[0].offset, Some(13));
+        assert_eq!(subs[0].rows, Some(10));
+        assert_eq!(subs[1].consumer_id.as_deref(), Some("0x7c1ba2d5d7d4a2"));
+        assert_eq!(subs[1].offset, Some(7));
+        assert_eq!(subs[2].consumer_id, None);
+        assert_eq!(subs[2].offset, None);
+
+        // 3.0 without rows, in upper case.
+        let names = [
+            "TOPIC_NAME",
+            "CONSUMER_GROUP",
+            "VGROUP_ID",
+            "CONSUMER_ID",
+            "OFFSET",
+        ];
+        let mut row = row(Value::BigInt(1), "log:3");
+        row.pop();
+        let subs = SubscriptionInfo::from_rows(&names, &[row]);
+        assert_eq!(subs[0].rows, None);
+        assert_eq!(subs[0].offset, Some(3));
+        assert!(subs[0].columns.contains_key("consumer_id"));
+    }
+
+    #[test]
+    fn stale() {
+        let names = names();
+        let sub =
+            |offset: &str| SubscriptionInfo::from_rows(&names, &[row(Value::BigInt(1), offset)]);
+        let assignment = Assignment {
+            vgroup_id: 2,
+            offset: 0,
+            begin: 0,
+            end: 20,
+        };
+        let watermarks = high_watermarks("t1", [&assignment]);
+        let mut detector = StaleDetector::new(Duration::from_secs(10));
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        assert!(detector
+            .find_stale_at(&sub("log:5"), &watermarks, at(0))
+            .is_empty());
+        assert!(detector
+            .find_stale_at(&sub("log:5"), &watermarks, at(5))
+            .is_empty());
+        let stale = detector.find_stale_at(&sub("log:5"), &watermarks, at(10));
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].backlog, 15);
+        assert_eq!(stale[0].stalled, Duration::from_secs(10));
+
+        // advancing resets the timer.
+        assert!(detector
+            .find_stale_at(&sub("log:6"), &watermarks, at(11))
+            .is_empty());
+        assert!(detector
+            .find_stale_at(&sub("log:6"), &watermarks, at(20))
+            .is_empty());
+        assert_eq!(
+            detector
+                .find_stale_at(&sub("log:6"), &watermarks, at(21))
+                .len(),
+            1
+        );
+
+        // caught up, or the high watermark is unknown.
+        assert!(detector
+            .find_stale_at(&sub("log:20"), &watermarks, at(40))
+            .is_empty());
+        assert!(detector
+            .find_stale_at(&sub("log:20"), &watermarks, at(60))
+            .is_empty());
+        assert!(detector
+            .find_stale_at(&sub("log:5"), &HashMap::new(), at(90))
+            .is_empty());
+
+        // not assigned.
+        let unassigned = SubscriptionInfo::from_rows(&names, &[row(Value::BigInt(0), "log:1")]);
+        assert!(detector
+            .find_stale_at(&unassigned, &watermarks, at(100))
+            .is_empty());
+        assert!(detector
+            .find_stale_at(&unassigned, &watermarks, at(200))
+            .is_empty());
+    }
+}
@@ -0,0 +1,634 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use crate::util::Shutdown;
+
+use super::{AsAsyncConsumer, IsOffset, MessageSet, Timeout, VGroupId};
+
+/// Identity of a message of [process_concurrently], eg. for a dead letter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MessageId {
+    pub topic: String,
+    pub vgroup_id: VGroupId,
+    /// Offset of the message, `None` if the backend does not report offsets.
+    pub offset: Option<i64>,
+}
+
+impl MessageId {
+    fn of(offset: &impl IsOffset) -> Self {
+        Self {
+            topic: offset.topic().to_string(),
+            vgroup_id: offset.vgroup_id(),
+            offset: offset.offset(),
+        }
+    }
+}
+
+/// Callback of messages failed by the handler of [process_concurrently], eg. to save them to a
+/// dead-letter table. The message is counted as processed after it returns.
+type DeadLetterFn<E> = dyn Fn(&MessageId, &E) + Send + Sync;
+
+pub struct DeadLetter<E>(Arc<DeadLetterFn<E>>);
+
+impl<E> DeadLetter<E> {
+    pub fn new(f: impl Fn(&MessageId, &E) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl<E> Clone for DeadLetter<E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<E> Debug for DeadLetter<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DeadLetter")
+    }
+}
+
+/// What to do when the handler of [process_concurrently] fails a message.
+#[derive(Debug, Clone)]
+pub enum OnHandlerError<E> {
+    /// Stop polling, wait for messages in flight and return the error. Offsets are committed up
+    /// to the failed message, so it's consumed again by the group.
+    Stop,
+    /// Pass the message to the dead-letter callback and go on.
+    Skip(DeadLetter<E>),
+}
+
+// Derived `Default` would require `E: Default`.
+#[allow(clippy::derivable_impls)]
+impl<E> Default for OnHandlerError<E> {
+    fn default() -> Self {
+        Self::Stop
+    }
+}
+
+/// Options of [process_concurrently].
+///
+/// ```rust
+/// # use taos_query::tmq::{OnHandlerError, DeadLetter, ProcessOptions};
+/// # use taos_query::util::Shutdown;
+/// let shutdown = Shutdown::new();
+/// let options = ProcessOptions::<String>::new(8)
+///     .max_in_flight_per_vgroup(4)
+///     .on_error(OnHandlerError::Skip(DeadLetter::new(|id, err| {
+///         eprintln!("skip message {id:?}: {err}")
+///     })))
+///     .shutdown(shutdown.clone());
+/// assert_eq!(options.workers(), 8);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProcessOptions<E> {
+    workers: usize,
+    max_in_flight_per_vgroup: usize,
+    on_error: OnHandlerError<E>,
+    shutdown: Shutdown,
+}
+
+impl<E> ProcessOptions<E> {
+    /// Process at most `workers` messages at a time, at least one. Each vgroup may take all of
+    /// them by default.
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        Self {
+            workers,
+            max_in_flight_per_vgroup: workers,
+            on_error: OnHandlerError::Stop,
+            shutdown: Shutdown::new(),
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Messages of a vgroup received but not committed yet at most, at least one. It bounds the
+    /// messages consumed again after a crash.
+    ///
+    /// A message of a full vgroup waits, and no more messages are polled until it's dispatched.
+    pub fn max_in_flight_per_vgroup(mut self, max: usize) -> Self {
+        self.max_in_flight_per_vgroup = max.max(1);
+        self
+    }
+
+    pub fn on_error(mut self, on_error: OnHandlerError<E>) -> Self {
+        self.on_error = on_error;
+        self
+    }
+
+    /// Stop polling when `shutdown` is triggered, and return after messages in flight are
+    /// processed and committed.
+    pub fn shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+}
+
+/// Counters of [process_concurrently].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessReport {
+    /// Messages processed by the handler.
+    pub processed: u64,
+    /// Messages failed by the handler and passed to the dead-letter callback.
+    pub skipped: u64,
+    /// Offsets committed.
+    pub commits: u64,
+}
+
+/// Error of [process_concurrently], by the consumer or the handler.
+#[derive(Debug, thiserror::Error)]
+pub enum ProcessError<C, E> {
+    #[error("consumer error: {0}")]
+    Consumer(C),
+    #[error("process message of topic {} in vgroup {} error: {error}", .id.topic, .id.vgroup_id)]
+    Handler { id: MessageId, error: E },
+}
+
+/// Messages of a vgroup received but not committed, in the order of offsets.
+struct Window<O> {
+    /// Sequence of the first message.
+    first: u64,
+    /// Offsets and whether they are processed.
+    messages: VecDeque<(O, bool)>,
+}
+
+impl<O> Default for Window<O> {
+    fn default() -> Self {
+        Self {
+            first: 0,
+            messages: VecDeque::new(),
+        }
+    }
+}
+
+impl<O> Window<O> {
+    /// Mark message `seq` processed, and pop the offset of the last message processed with all
+    /// the messages before it.
+    fn complete(&mut self, seq: u64) -> Option<O> {
+        self.messages[(seq - self.first) as usize].1 = true;
+        let mut last = None;
+        while self.messages.front().map_or(false, |(_, done)| *done) {
+            last = self.messages.pop_front().map(|(offset, _)| offset);
+            self.first += 1;
+        }
+        last
+    }
+}
+
+/// Process messages of `consumer` by `handler` concurrently, and commit offsets in order.
+///
+/// Up to [workers](ProcessOptions::workers) handler futures run at a time in the calling task,
+/// spawn CPU heavy work in the handler. Messages complete in any order, but the offset of a
+/// vgroup is committed only when all the messages before it are processed, so a crash never
+/// loses a message: the messages after the committed offset are consumed again, at least once.
+///
+/// It runs until the consumer or the handler fails, see [OnHandlerError], or the
+/// [shutdown](ProcessOptions::shutdown) is triggered. Messages in flight are drained in all cases
+/// before it returns, a message polled but not dispatched yet is dropped without commit.
+pub async fn process_concurrently<C, F, Fut, E>(
+    consumer: &C,
+    options: ProcessOptions<E>,
+    handler: F,
+) -> Result<ProcessReport, ProcessError<C::Error, E>>
+where
+    C: AsAsyncConsumer,
+    F: Fn(MessageSet<C::Meta, C::Data>) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    type Polled<'a, C> = BoxFuture<
+        'a,
+        Result<
+            Option<(
+                <C as AsAsyncConsumer>::Offset,
+                MessageSet<<C as AsAsyncConsumer>::Meta, <C as AsAsyncConsumer>::Data>,
+            )>,
+            <C as AsAsyncConsumer>::Error,
+        >,
+    >;
+
+    let ProcessOptions {
+        workers,
+        max_in_flight_per_vgroup,
+        on_error,
+        shutdown,
+    } = options;
+    let timeout = Timeout::Duration(consumer.poll_interval());
+    let mut windows: HashMap<(String, VGroupId), Window<C::Offset>> = HashMap::new();
+    let mut in_flight = FuturesUnordered::new();
+    let mut pending = None;
+    let mut poll: Option<Polled<'_, C>> = None;
+    let mut failure = None;
+    let mut report = ProcessReport::default();
+
+    loop {
+        let stopping = failure.is_some() || shutdown.is_triggered();
+        if stopping {
+            poll = None;
+            pending = None;
+        }
+        if let Some((offset, message)) = pending.take() {
+            let id = MessageId::of(&offset);
+            let window = windows.entry((id.topic.clone(), id.vgroup_id)).or_default();
+            if in_flight.len() < workers && window.messages.len() < max_in_flight_per_vgroup {
+                let seq = window.first + window.messages.len() as u64;
+                window.messages.push_back((offset, false));
+                let processed = handler(message);
+                in_flight.push(async move { (id, seq, processed.await) });
+            } else {
+                pending = Some((offset, message));
+            }
+        }
+        if stopping && in_flight.is_empty() {
+            break;
+        }
+        if !stopping && pending.is_none() && in_flight.len() < workers && poll.is_none() {
+            poll = Some(consumer.recv_timeout(timeout));
+        }
+
+        tokio::select! {
+            Some((id, seq, result)) = in_flight.next(), if !in_flight.is_empty() => {
+                match result {
+                    Ok(()) => report.processed += 1,
+                    Err(error) => match &on_error {
+                        OnHandlerError::Skip(dead_letter) => {
+                            (dead_letter.0)(&id, &error);
+                            report.skipped += 1;
+                        }
+                        OnHandlerError::Stop => {
+                            log::warn!("stop processing messages at {id:?}");
+                            failure.get_or_insert(ProcessError::Handler { id, error });
+                            continue;
+                        }
+                    },
+                }
+                let window = windows.get_mut(&(id.topic, id.vgroup_id)).unwrap();
+                if let Some(offset) = window.complete(seq) {
+                    match consumer.commit(offset).await {
+                        Ok(()) => report.commits += 1,
+                        Err(err) => {
+                            failure.get_or_insert(ProcessError::Consumer(err));
+                        }
+                    }
+                }
+            }
+            polled = async { poll.as_mut().unwrap().await }, if poll.is_some() => {
+                poll = None;
+                match polled {
+                    Ok(message) => pending = message,
+                    Err(err) => {
+                        failure.get_or_insert(ProcessError::Consumer(err));
+                    }
+                }
+            }
+            _ = shutdown.triggered(), if !stopping => (),
+        }
+    }
+
+    match failure {
+        Some(err) => Err(err),
+        None => Ok(report),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashSet};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use rand::{Rng, SeedableRng};
+
+    use crate::common::{JsonMeta, RawData, RawMeta};
+    use crate::tmq::{IsAsyncData, IsAsyncMeta};
+    use crate::RawBlock;
+
+    use super::*;
+
+    type Id = (VGroupId, i64);
+
+    /// Broker of `vgroups` with `messages` each, interleaved, keeping offsets committed by
+    /// sessions of [Mock].
+    #[derive(Default)]
+    struct Broker {
+        messages: Vec<Id>,
+        committed: Mutex<BTreeMap<VGroupId, i64>>,
+        /// Messages processed, checked on each commit.
+        processed: Mutex<HashSet<Id>>,
+        times: Mutex<HashMap<Id, usize>>,
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+    }
+
+    impl Broker {
+        fn new(vgroups: i32, messages: i64) -> Self {
+            Self {
+                messages: (0..messages)
+                    .flat_map(|offset| (0..vgroups).map(move |vgroup| (vgroup, offset)))
+                    .collect(),
+                ..Default::default()
+            }
+        }
+
+        /// A consumer session from the committed offsets.
+        fn session(&self) -> Mock<'_> {
+            let committed = self.committed.lock().unwrap().clone();
+            let messages = self
+                .messages
+                .iter()
+                .filter(|(vgroup, offset)| committed.get(vgroup).map_or(true, |c| offset > c))
+                .rev()
+                .copied()
+                .collect();
+            Mock {
+                broker: self,
+                messages: Mutex::new(messages),
+            }
+        }
+
+        fn process(&self, id: Id) {
+            self.processed.lock().unwrap().insert(id);
+            *self.times.lock().unwrap().entry(id).or_default() += 1;
+        }
+    }
+
+    struct Mock<'b> {
+        broker: &'b Broker,
+        messages: Mutex<Vec<Id>>,
+    }
+
+    struct Offset(Id);
+
+    impl IsOffset for Offset {
+        fn database(&self) -> &str {
+            "db"
+        }
+        fn topic(&self) -> &str {
+            "topic"
+        }
+        fn vgroup_id(&self) -> VGroupId {
+            self.0 .0
+        }
+        fn offset(&self) -> Option<i64> {
+            Some(self.0 .1)
+        }
+    }
+
+    struct Message(Id);
+
+    #[async_trait::async_trait]
+    impl IsAsyncMeta for Message {
+        type Error = String;
+
+        async fn as_raw_meta(&self) -> Result<RawMeta, Self::Error> {
+            Err("no meta".to_string())
+        }
+
+        async fn as_json_meta(&self) -> Result<JsonMeta, Self::Error> {
+            Err("no meta".to_string())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IsAsyncData for Message {
+        type Error = String;
+
+        async fn as_raw_data(&self) -> Result<RawData, Self::Error> {
+            Err("no data".to_string())
+        }
+
+        async fn fetch_raw_block(&self) -> Result<Option<RawBlock>, Self::Error> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AsAsyncConsumer for Mock<'_> {
+        type Error = String;
+        type Offset = Offset;
+        type Meta = Message;
+        type Data = Message;
+
+        fn default_timeout(&self) -> Timeout {
+            Timeout::Never
+        }
+
+        async fn subscribe<T: Into<String>, I: IntoIterator<Item = T> + Send>(
+            &mut self,
+            _: I,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn recv_timeout(
+            &self,
+            timeout: Timeout,
+        ) -> Result<Option<(Self::Offset, MessageSet<Self::Meta, Self::Data>)>, Self::Error>
+        {
+            let message = self.messages.lock().unwrap().pop();
+            match message {
+                Some(id) => Ok(Some((Offset(id), MessageSet::Data(Message(id))))),
+                None => {
+                    tokio::time::sleep(timeout.as_duration()).await;
+                    Ok(None)
+                }
+            }
+        }
+
+        /// Commits are in order, and all messages before them are processed.
+        async fn commit(&self, offset: Self::Offset) -> Result<(), Self::Error> {
+            let (vgroup, offset) = offset.0;
+            let mut committed = self.broker.committed.lock().unwrap();
+            let previous = committed.get(&vgroup).copied().unwrap_or(-1);
+            assert!(offset > previous, "commit {offset} after {previous}");
+            let processed = self.broker.processed.lock().unwrap();
+            assert!((0..=offset).all(|offset| processed.contains(&(vgroup, offset))));
+            committed.insert(vgroup, offset);
+            Ok(())
+        }
+    }
+
+    /// Process a message after a random delay, failing `fail` if set.
+    async fn handle(
+        broker: &Broker,
+        message: MessageSet<Message, Message>,
+        delay: Duration,
+        fail: Option<Id>,
+    ) -> Result<(), String> {
+        let id = message.into_data().unwrap().0;
+        let running = broker.running.fetch_add(1, Ordering::SeqCst) + 1;
+        broker.max_running.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(delay).await;
+        broker.running.fetch_sub(1, Ordering::SeqCst);
+        if Some(id) == fail {
+            return Err(format!("fail {id:?}"));
+        }
+        broker.process(id);
+        Ok(())
+    }
+
+    fn delays(seed: u64) -> impl FnMut() -> Duration {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        move || Duration::from_millis(rng.gen_range(0..20))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ordered_commits_with_random_delays() {
+        let broker = Broker::new(4, 250);
+        let consumer = broker.session();
+        let delay = Mutex::new(delays(1));
+        let shutdown = Shutdown::new();
+        let options = ProcessOptions::new(8)
+            .max_in_flight_per_vgroup(3)
+            .shutdown(shutdown.clone());
+        let done = async {
+            while broker.processed.lock().unwrap().len() < 1000 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            shutdown.trigger();
+        };
+        let processing = process_concurrently(&consumer, options, |message| {
+            let delay = (delay.lock().unwrap())();
+            handle(&broker, message, delay, None)
+        });
+        let (report, ()) = tokio::join!(processing, done);
+        let report = report.unwrap();
+        assert_eq!(report.processed, 1000);
+        assert_eq!(report.skipped, 0);
+        assert!(report.commits >= 4 && report.commits <= 1000);
+        assert_eq!(
+            *broker.committed.lock().unwrap(),
+            (0..4).map(|vgroup| (vgroup, 249)).collect()
+        );
+        assert!(broker
+            .times
+            .lock()
+            .unwrap()
+            .values()
+            .all(|times| *times == 1));
+        let max_running = broker.max_running.load(Ordering::SeqCst);
+        assert!((2..=8).contains(&max_running), "{max_running} running");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn crash_and_restart_at_least_once() {
+        let broker = Broker::new(3, 200);
+        let delay = Mutex::new(delays(2));
+
+        // The handler fails a message, messages in flight are drained and committed before it.
+        let consumer = broker.session();
+        let err = process_concurrently(&consumer, ProcessOptions::new(6), |message| {
+            let delay = (delay.lock().unwrap())();
+            handle(&broker, message, delay, Some((1, 100)))
+        })
+        .await
+        .unwrap_err();
+        match err {
+            ProcessError::Handler { id, error } => {
+                assert_eq!((id.vgroup_id, id.offset), (1, Some(100)));
+                assert_eq!(error, "fail (1, 100)");
+            }
+            err => panic!("unexpected error {err}"),
+        }
+        assert_eq!(broker.running.load(Ordering::SeqCst), 0, "drained");
+        assert!(broker.committed.lock().unwrap()[&1] < 100);
+
+        // The process crashes, the future is dropped with messages in flight.
+        let consumer = broker.session();
+        let crashed = tokio::time::timeout(
+            Duration::from_millis(150),
+            process_concurrently(&consumer, ProcessOptions::new(6), |message| {
+                let delay = (delay.lock().unwrap())();
+                handle(&broker, message, delay, None)
+            }),
+        )
+        .await;
+        assert!(crashed.is_err());
+        broker.running.store(0, Ordering::SeqCst);
+        let committed = broker.committed.lock().unwrap().clone();
+        assert!(
+            committed.values().all(|offset| *offset < 199),
+            "{committed:?}"
+        );
+
+        // Restart from the committed offsets.
+        let consumer = broker.session();
+        let shutdown = Shutdown::new();
+        let done = async {
+            while broker.committed.lock().unwrap().values().any(|c| *c < 199) {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            shutdown.trigger();
+        };
+        let options = ProcessOptions::new(6).shutdown(shutdown.clone());
+        let processing = process_concurrently(&consumer, options, |message| {
+            let delay = (delay.lock().unwrap())();
+            handle(&broker, message, delay, None)
+        });
+        let (report, ()) = tokio::join!(processing, done);
+        assert!(report.unwrap().processed > 0);
+        let times = broker.times.lock().unwrap();
+        assert!(broker.messages.iter().all(|id| times.get(id) >= Some(&1)));
+        assert!(times.values().any(|times| *times > 1), "consumed again");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skip_to_dead_letter() {
+        let broker = Broker::new(2, 100);
+        let consumer = broker.session();
+        let dead = Arc::new(Mutex::new(Vec::new()));
+        let shutdown = Shutdown::new();
+        let options = ProcessOptions::new(4)
+            .on_error(OnHandlerError::Skip(DeadLetter::new({
+                let dead = dead.clone();
+                move |id: &MessageId, err: &String| {
+                    dead.lock()
+                        .unwrap()
+                        .push((id.vgroup_id, id.offset, err.clone()))
+                }
+            })))
+            .shutdown(shutdown.clone());
+        let delay = Mutex::new(delays(3));
+        let done = async {
+            while broker
+                .committed
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|c| **c == 99)
+                .count()
+                < 2
+            {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            shutdown.trigger();
+        };
+        let processing = process_concurrently(&consumer, options, |message| {
+            let id = match &message {
+                MessageSet::Data(Message(id)) => *id,
+                _ => unreachable!(),
+            };
+            // Skipped messages are counted as processed for commits.
+            if id == (0, 50) {
+                broker.process(id);
+            }
+            let delay = (delay.lock().unwrap())();
+            handle(&broker, message, delay, Some((0, 50)))
+        });
+        let (report, ()) = tokio::join!(processing, done);
+        let report = report.unwrap();
+        assert_eq!((report.processed, report.skipped), (199, 1));
+        assert_eq!(
+            *dead.lock().unwrap(),
+            [(0, Some(50), "fail (0, 50)".to_string())]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use serde::de::DeserializeOwned;
+
+use crate::common::IntoRowsIter;
+use crate::RawBlock;
+
+/// Error of deserializing rows of a data message, with where it happened.
+///
+/// Blocks and rows are indexed from 0, rows in their block.
+#[derive(Debug, thiserror::Error)]
+pub enum DeserializeDataError<E> {
+    /// Fetching the block failed.
+    #[error("failed to fetch block {block} of data: {source}")]
+    Fetch { block: usize, source: E },
+    /// Deserializing the row failed.
+    #[error("failed to deserialize row {row} of block {block}: {source}")]
+    Row {
+        block: usize,
+        row: usize,
+        source: taos_error::Error,
+    },
+}
+
+/// Rows of the blocks of a data message, walked as they are fetched.
+#[derive(Default)]
+pub(super) struct DataRows {
+    rows: Option<IntoRowsIter<'static>>,
+    /// Blocks fetched.
+    blocks: usize,
+    /// Index of the next row in the current block.
+    row: usize,
+    done: bool,
+}
+
+impl DataRows {
+    pub(super) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Deserialize the next row of the current block, `None` if a block is to fetch.
+    pub(super) fn next_row<T, E>(&mut self) -> Option<Result<T, DeserializeDataError<E>>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut view = self.rows.as_mut()?.next()?;
+        let (block, row) = (self.blocks - 1, self.row);
+        self.row += 1;
+        Some(
+            T::deserialize(&mut view).map_err(|source| DeserializeDataError::Row {
+                block,
+                row,
+                source,
+            }),
+        )
+    }
+
+    /// Walk rows of a fetched block, the data ends with no more blocks or the fetch error.
+    pub(super) fn fetched<E>(
+        &mut self,
+        block: Result<Option<RawBlock>, E>,
+    ) -> Option<DeserializeDataError<E>> {
+        match block {
+            Ok(Some(block)) => {
+                self.rows = Some(block.into_rows());
+                self.blocks += 1;
+                self.row = 0;
+                None
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(source) => {
+                self.done = true;
+                Some(DeserializeDataError::Fetch {
+                    block: self.blocks,
+                    source,
+                })
+            }
+        }
+    }
+
+    /// Next row with blocks fetched by `fetch`.
+    pub(super) fn next_with<T, E>(
+        &mut self,
+        mut fetch: impl FnMut() -> Result<Option<RawBlock>, E>,
+    ) -> Option<Result<T, DeserializeDataError<E>>>
+    where
+        T: DeserializeOwned,
+    {
+        while !self.done {
+            if let Some(row) = self.next_row() {
+                return Some(row);
+            }
+            if let Some(err) = self.fetched(fetch()) {
+                return Some(Err(err));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::views::views_to_raw_block;
+    use crate::common::{ColumnView, Precision};
+
+    use super::*;
+
+    fn block(values: Vec<Option<i32>>) -> RawBlock {
+        let mut block = RawBlock::parse_from_raw_block(
+            views_to_raw_block(&[ColumnView::from_ints(values)]),
+            Precision::Millisecond,
+        );
+        block.with_field_names(["v"]);
+        block
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Record {
+        v: i32,
+    }
+
+    #[test]
+    fn rows_of_all_blocks() {
+        let mut blocks = vec![
+            Ok(Some(block(vec![Some(1), Some(2)]))),
+            Ok(Some(block(vec![]))),
+            Ok(Some(block(vec![Some(3)]))),
+            Ok(None),
+        ]
+        .into_iter();
+        let mut rows = DataRows::default();
+        let records: Vec<Record> = std::iter::from_fn(|| rows.next_with(|| blocks.next().unwrap()))
+            .collect::<Result<_, DeserializeDataError<std::io::Error>>>()
+            .unwrap();
+        assert_eq!(records, [Record { v: 1 }, Record { v: 2 }, Record { v: 3 }]);
+        assert!(rows.is_done());
+    }
+
+    #[test]
+    fn errors_at_block_and_row() {
+        let mut blocks = vec![
+            Ok(Some(block(vec![Some(1)]))),
+            Ok(Some(block(vec![Some(2), None]))),
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "broken")),
+        ]
+        .into_iter();
+        let mut rows = DataRows::default();
+        let results: Vec<Result<Record, _>> =
+            std::iter::from_fn(|| rows.next_with(|| blocks.next().unwrap())).collect();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[1].as_ref().unwrap(), &Record { v: 2 });
+        assert!(matches!(
+            results[2],
+            Err(DeserializeDataError::Row {
+                block: 1,
+                row: 1,
+                ..
+            })
+        ));
+        let err = results[3].as_ref().unwrap_err();
+        assert!(matches!(err, DeserializeDataError::Fetch { block: 2, .. }));
+        assert_eq!(err.to_string(), "failed to fetch block 2 of data: broken");
+    }
+}
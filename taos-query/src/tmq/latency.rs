@@ -0,0 +1,148 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::common::{ColumnView, Timestamp};
+use crate::RawBlock;
+
+use super::IsOffset;
+
+/// When a message was written, to measure the end-to-end latency of consuming it.
+///
+/// It's the creation time reported by the server, see [IsOffset::timestamp], or approximated by
+/// the max row timestamp of the first data block if the server does not report it, see
+/// [MessageTime::resolve].
+///
+/// ```rust
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// # use taos_query::common::Timestamp;
+/// # use taos_query::tmq::MessageTime;
+/// let time = MessageTime::new(Timestamp::Milliseconds(1_000));
+/// let now = UNIX_EPOCH + Duration::from_millis(1_250);
+/// assert_eq!(time.latency(now), Duration::from_millis(250));
+/// assert!(!time.is_approximate());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageTime {
+    timestamp: Timestamp,
+    approximate: bool,
+}
+
+impl MessageTime {
+    /// Creation time of a message reported by the server.
+    pub const fn new(timestamp: Timestamp) -> Self {
+        Self {
+            timestamp,
+            approximate: false,
+        }
+    }
+
+    /// Max timestamp of the first column of `block`, `None` if it's not a timestamp column or
+    /// all rows are NULL.
+    ///
+    /// Rows written with timestamps of the past or future make it far from the creation time.
+    pub fn from_block(block: &RawBlock) -> Option<Self> {
+        let ColumnView::Timestamp(view) = block.column_views().first()? else {
+            return None;
+        };
+        let max = view.iter().flatten().map(|ts| ts.as_raw_i64()).max()?;
+        Some(Self {
+            timestamp: Timestamp::new(max, view.precision()),
+            approximate: true,
+        })
+    }
+
+    /// Time of the message of `offset`, or approximated by `first_block` of the message if the
+    /// server does not report it.
+    pub fn resolve(offset: &impl IsOffset, first_block: Option<&RawBlock>) -> Option<Self> {
+        offset
+            .timestamp()
+            .map(Self::new)
+            .or_else(|| first_block.and_then(Self::from_block))
+    }
+
+    pub const fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// If it's the max row timestamp instead of the creation time of the message.
+    pub const fn is_approximate(&self) -> bool {
+        self.approximate
+    }
+
+    /// Time from writing the message to `now`, zero if clocks of the server and client are so
+    /// skewed that the message is from the future.
+    pub fn latency(&self, now: SystemTime) -> Duration {
+        let raw = self.timestamp.as_raw_i64().max(0) as u64;
+        let since_epoch = match self.timestamp {
+            Timestamp::Milliseconds(_) => Duration::from_millis(raw),
+            Timestamp::Microseconds(_) => Duration::from_micros(raw),
+            Timestamp::Nanoseconds(_) => Duration::from_nanos(raw),
+        };
+        now.duration_since(UNIX_EPOCH + since_epoch)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{views::views_to_raw_block, Precision};
+
+    struct Offset(Option<Timestamp>);
+
+    impl IsOffset for Offset {
+        fn database(&self) -> &str {
+            "db"
+        }
+
+        fn topic(&self) -> &str {
+            "topic"
+        }
+
+        fn vgroup_id(&self) -> crate::tmq::VGroupId {
+            2
+        }
+
+        fn timestamp(&self) -> Option<Timestamp> {
+            self.0
+        }
+    }
+
+    fn block(view: ColumnView, precision: Precision) -> RawBlock {
+        RawBlock::parse_from_raw_block(views_to_raw_block(&[view]), precision)
+    }
+
+    #[test]
+    fn message_time_fallback() {
+        let micros = block(
+            ColumnView::from_micros_timestamp(vec![
+                Some(3_000_000),
+                None,
+                Some(5_000_000),
+                Some(4_000_000),
+            ]),
+            Precision::Microsecond,
+        );
+        let time = MessageTime::from_block(&micros).unwrap();
+        assert_eq!(time.timestamp(), Timestamp::Microseconds(5_000_000));
+        assert!(time.is_approximate());
+        let now = UNIX_EPOCH + Duration::from_secs(7);
+        assert_eq!(time.latency(now), Duration::from_secs(2));
+        assert_eq!(time.latency(UNIX_EPOCH), Duration::ZERO);
+
+        let ints = block(ColumnView::from_ints(vec![1]), Precision::Millisecond);
+        assert!(MessageTime::from_block(&ints).is_none());
+        let nulls = block(
+            ColumnView::from_millis_timestamp(vec![None::<i64>]),
+            Precision::Millisecond,
+        );
+        assert!(MessageTime::from_block(&nulls).is_none());
+
+        let server = Offset(Some(Timestamp::Nanoseconds(6_000_000_000)));
+        let time = MessageTime::resolve(&server, Some(&micros)).unwrap();
+        assert!(!time.is_approximate());
+        assert_eq!(time.latency(now), Duration::from_secs(1));
+        let time = MessageTime::resolve(&Offset(None), Some(&micros)).unwrap();
+        assert!(time.is_approximate());
+        assert!(MessageTime::resolve(&Offset(None), None).is_none());
+    }
+}
@@ -1,13 +1,56 @@
-use std::{fmt::Debug, pin::Pin, str::FromStr, time::Duration};
+use std::{borrow::Cow, fmt::Debug, pin::Pin, str::FromStr, time::Duration};
+
+use bytes::Bytes;
 
 use itertools::Itertools;
+use serde::de::DeserializeOwned;
 
 use crate::{
-    common::{JsonMeta, RawData, RawMeta},
-    RawBlock,
+    common::{JsonMeta, RawData, RawMeta, Timestamp, RAW_TYPE_DATA, RAW_TYPE_META_DATA},
+    DsnError, RawBlock,
 };
 
-#[derive(Debug, Clone, Copy)]
+pub mod admin;
+
+mod concurrent;
+pub use concurrent::*;
+
+mod deserialize;
+use deserialize::DataRows;
+pub use deserialize::DeserializeDataError;
+
+mod latency;
+pub use latency::*;
+
+mod offset_store;
+pub use offset_store::*;
+
+mod parallel;
+pub use parallel::*;
+
+mod report;
+pub use report::*;
+
+mod stream;
+pub use stream::*;
+
+mod watermark;
+pub use watermark::*;
+
+/// Timeout of DSN parameters and builder methods, parsed from:
+///
+/// - a duration with `ms`, `s` or `m` suffix, eg. `500ms`, `5s`, `1m`;
+/// - bare integers as milliseconds, eg. `500`;
+/// - `never` to wait forever;
+/// - `0` or `none` to not wait.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use taos_query::tmq::Timeout;
+/// assert_eq!("500".parse::<Timeout>().unwrap().as_duration(), Duration::from_millis(500));
+/// assert_eq!("1m".parse::<Timeout>().unwrap().as_duration(), Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Timeout {
     /// Wait forever.
     Never,
@@ -18,6 +61,10 @@ pub enum Timeout {
 }
 
 impl Timeout {
+    /// Accepted formats of timeout values, for error messages.
+    pub const FORMATS: &'static str = "expect a duration with ms, s or m suffix \
+        (eg. `500ms`, `5s`, `1m`), integer milliseconds, `never` or `0`";
+
     pub fn from_secs(secs: u64) -> Self {
         Self::Duration(Duration::from_secs(secs))
     }
@@ -33,6 +80,14 @@ impl Timeout {
     pub fn none() -> Self {
         Self::None
     }
+
+    /// Parse `value` of DSN parameter `key`, the error names the parameter.
+    pub fn from_param(key: &str, value: &str) -> Result<Self, DsnError> {
+        value
+            .parse()
+            .map_err(|err: TimeoutError| DsnError::InvalidParam(key.to_string(), err.to_string()))
+    }
+
     pub fn as_raw_timeout(&self) -> i64 {
         match self {
             Timeout::Never => -1,
@@ -48,29 +103,57 @@ impl Timeout {
             Timeout::Duration(t) => *t,
         }
     }
+
+    /// The time limit, `None` for [Timeout::Never].
+    pub fn as_limit(&self) -> Option<Duration> {
+        match self {
+            Timeout::Never => None,
+            _ => Some(self.as_duration()),
+        }
+    }
+}
+
+impl From<Duration> for Timeout {
+    fn from(duration: Duration) -> Self {
+        Self::Duration(duration)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum TimeoutError {
-    #[error("empty timeout value")]
+    #[error("empty timeout value, {}", Timeout::FORMATS)]
     Empty,
-    #[error("invalid timeout expression `{0}`: {1}")]
-    Invalid(String, String),
+    #[error("invalid timeout expression `{0}`, {}", Timeout::FORMATS)]
+    Invalid(String),
 }
 
 impl FromStr for Timeout {
     type Err = TimeoutError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
         if s.is_empty() {
             return Err(TimeoutError::Empty);
         }
+        let invalid = || TimeoutError::Invalid(s.to_string());
         match s.to_lowercase().as_str() {
             "never" => Ok(Timeout::Never),
             "none" => Ok(Timeout::None),
-            _ => parse_duration::parse(s)
-                .map(Timeout::Duration)
-                .map_err(|err| TimeoutError::Invalid(s.to_string(), err.to_string())),
+            s if s.bytes().all(|b| b.is_ascii_digit()) => match s.parse().map_err(|_| invalid())? {
+                0 => Ok(Timeout::None),
+                millis => Ok(Timeout::from_millis(millis)),
+            },
+            s => {
+                let (n, unit) = s.split_at(s.trim_end_matches(char::is_alphabetic).len());
+                let n: u64 = n.trim().parse().map_err(|_| invalid())?;
+                let duration = match unit {
+                    "ms" => Duration::from_millis(n),
+                    "s" => Duration::from_secs(n),
+                    "m" => Duration::from_secs(n.saturating_mul(60)),
+                    _ => return Err(invalid()),
+                };
+                Ok(Timeout::Duration(duration))
+            }
         }
     }
 }
@@ -133,6 +216,73 @@ impl<M, D> MessageSet<M, D> {
     }
 }
 
+impl<M, D: IsData> MessageSet<M, D> {
+    /// Deserialize rows of the data, see [IsData::deserialize], it's empty for meta messages.
+    pub fn deserialize_data<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+    ) -> Box<dyn 'a + Iterator<Item = Result<T, DeserializeDataError<D::Error>>>> {
+        match self {
+            MessageSet::Meta(_) => Box::new(std::iter::empty()),
+            MessageSet::Data(d) | MessageSet::MetaData(_, d) => d.deserialize(),
+        }
+    }
+}
+
+impl<M, D: IsAsyncData + Sync> MessageSet<M, D>
+where
+    D::Error: Send,
+{
+    /// Deserialize rows of the data, see [IsAsyncData::deserialize], it's empty for meta
+    /// messages.
+    #[allow(clippy::type_complexity)]
+    pub fn deserialize_data_async<T: DeserializeOwned + Send + 'static>(
+        &self,
+    ) -> Pin<Box<dyn '_ + Send + futures::Stream<Item = Result<T, DeserializeDataError<D::Error>>>>>
+    {
+        match self {
+            MessageSet::Meta(_) => Box::pin(futures::stream::empty()),
+            MessageSet::Data(d) | MessageSet::MetaData(_, d) => d.deserialize(),
+        }
+    }
+}
+
+impl<M: IsMeta, D: IsData> MessageSet<M, D> {
+    /// The complete serialized payload of the message, in the inlined [RawData] layout, for
+    /// pass-through forwarding without decoding blocks.
+    ///
+    /// Returns `None` if the payload could not be fetched. Use [MessageSet::from_raw_bytes] to
+    /// reconstruct the message.
+    pub fn raw_bytes(&self) -> Option<Cow<'_, [u8]>> {
+        let raw = match self {
+            MessageSet::Meta(m) | MessageSet::MetaData(m, _) => match m.as_raw_meta() {
+                Ok(meta) => meta.as_bytes().into_owned(),
+                Err(_) => return None,
+            },
+            MessageSet::Data(d) => match d.as_raw_data() {
+                Ok(data) => data.as_bytes().into_owned(),
+                Err(_) => return None,
+            },
+        };
+        Some(Cow::Owned(raw.into()))
+    }
+}
+
+impl MessageSet<RawMeta, RawData> {
+    /// Reconstruct a message from bytes of [MessageSet::raw_bytes].
+    ///
+    /// The message kind is derived from the raw type. Meta of the message could be replayed
+    /// with `write_raw_meta`, which accepts all raw types including data, and blocks of the data
+    /// are parsed from the bytes by [IsData::fetch_raw_block].
+    pub fn from_raw_bytes(bytes: impl Into<Bytes>) -> std::io::Result<Self> {
+        let raw = RawData::try_new(bytes)?;
+        Ok(match raw.raw_type() {
+            RAW_TYPE_DATA => MessageSet::Data(raw),
+            RAW_TYPE_META_DATA => MessageSet::MetaData(RawMeta::from(raw.clone()), raw),
+            _ => MessageSet::Meta(RawMeta::from(raw)),
+        })
+    }
+}
+
 #[async_trait::async_trait]
 pub trait IsAsyncMeta {
     type Error;
@@ -187,6 +337,54 @@ pub trait IsAsyncData {
 
     async fn as_raw_data(&self) -> Result<RawData, Self::Error>;
     async fn fetch_raw_block(&self) -> Result<Option<RawBlock>, Self::Error>;
+
+    /// Deserialize rows of all blocks, blocks are fetched as the rows are taken.
+    ///
+    /// ```rust,no_run
+    /// # use taos_query::prelude::*;
+    /// # use taos_query::tmq::DeserializeDataError;
+    /// #[derive(Debug, serde::Deserialize)]
+    /// struct Record {
+    ///     ts: String,
+    ///     current: f32,
+    /// }
+    ///
+    /// # async fn consume<D: IsAsyncData + Sync>(data: &D) -> Result<(), DeserializeDataError<D::Error>>
+    /// # where D::Error: Send,
+    /// # {
+    /// let mut records = data.deserialize::<Record>();
+    /// while let Some(record) = records.try_next().await? {
+    ///     println!("{record:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    fn deserialize<T>(
+        &self,
+    ) -> Pin<
+        Box<dyn '_ + Send + futures::Stream<Item = Result<T, DeserializeDataError<Self::Error>>>>,
+    >
+    where
+        Self: Sync,
+        Self::Error: Send,
+        T: DeserializeOwned + Send + 'static,
+    {
+        Box::pin(futures::stream::unfold(
+            DataRows::default(),
+            move |mut rows| async move {
+                while !rows.is_done() {
+                    if let Some(row) = rows.next_row() {
+                        return Some((row, rows));
+                    }
+                    if let Some(err) = rows.fetched(self.fetch_raw_block().await) {
+                        return Some((Err(err), rows));
+                    }
+                }
+                None
+            },
+        ))
+    }
 }
 
 pub trait IsData {
@@ -194,6 +392,65 @@ pub trait IsData {
 
     fn as_raw_data(&self) -> Result<RawData, Self::Error>;
     fn fetch_raw_block(&self) -> Result<Option<RawBlock>, Self::Error>;
+
+    /// Deserialize rows of all blocks, blocks are fetched as the rows are taken.
+    ///
+    /// Errors carry the index of the block and row, it ends after a fetch error.
+    fn deserialize<T: DeserializeOwned>(
+        &self,
+    ) -> Box<dyn '_ + Iterator<Item = Result<T, DeserializeDataError<Self::Error>>>> {
+        let mut rows = DataRows::default();
+        Box::new(std::iter::from_fn(move || {
+            rows.next_with(|| self.fetch_raw_block())
+        }))
+    }
+}
+
+impl<T> IsData for T
+where
+    T: IsAsyncData + SyncOnAsync,
+{
+    type Error = T::Error;
+
+    fn as_raw_data(&self) -> Result<RawData, Self::Error> {
+        crate::block_in_place_or_global(T::as_raw_data(self))
+    }
+
+    fn fetch_raw_block(&self) -> Result<Option<RawBlock>, Self::Error> {
+        crate::block_in_place_or_global(T::fetch_raw_block(self))
+    }
+}
+
+impl IsMeta for RawMeta {
+    type Error = std::io::Error;
+
+    fn as_raw_meta(&self) -> Result<RawMeta, Self::Error> {
+        Ok(self.clone())
+    }
+
+    /// Meta in raw bytes is TDengine's internal encoding of vnode requests, which is not
+    /// decoded, replay it with `write_raw_meta` instead.
+    fn as_json_meta(&self) -> Result<JsonMeta, Self::Error> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "json meta of raw type {} is not decoded from raw bytes",
+                self.raw_type()
+            ),
+        ))
+    }
+}
+
+impl IsData for RawData {
+    type Error = std::io::Error;
+
+    fn as_raw_data(&self) -> Result<RawData, Self::Error> {
+        Ok(self.clone())
+    }
+
+    fn fetch_raw_block(&self) -> Result<Option<RawBlock>, Self::Error> {
+        RawData::fetch_raw_block(self)
+    }
 }
 
 #[async_trait::async_trait]
@@ -225,6 +482,58 @@ pub trait IsOffset {
 
     /// VGroup id for current message.
     fn vgroup_id(&self) -> VGroupId;
+
+    /// Offset of current message in the vgroup, `None` if the backend does not report it.
+    fn offset(&self) -> Option<i64> {
+        None
+    }
+
+    /// Time the message was created in the server, `None` if the backend does not report it,
+    /// see [MessageTime] for the end-to-end latency.
+    fn timestamp(&self) -> Option<Timestamp> {
+        None
+    }
+
+    /// First offset still kept in the vgroup when the message was received, `None` if unknown.
+    ///
+    /// It's approximate as [IsOffset::high_watermark].
+    fn begin(&self) -> Option<i64> {
+        None
+    }
+
+    /// Offset after the last message written to the vgroup when the message was received,
+    /// `None` if unknown.
+    ///
+    /// Consumers refresh it periodically, see [Watermarks], so it's approximate and may lag
+    /// behind writes.
+    fn high_watermark(&self) -> Option<i64> {
+        None
+    }
+
+    /// How far the message is behind the high watermark, never negative.
+    ///
+    /// ```rust
+    /// # use taos_query::tmq::{IsOffset, VGroupId};
+    /// struct Received(i64);
+    ///
+    /// impl IsOffset for Received {
+    ///     # fn database(&self) -> &str { "db" }
+    ///     # fn topic(&self) -> &str { "topic" }
+    ///     # fn vgroup_id(&self) -> VGroupId { 2 }
+    ///     fn offset(&self) -> Option<i64> {
+    ///         Some(self.0)
+    ///     }
+    ///     fn high_watermark(&self) -> Option<i64> {
+    ///         Some(100)
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(Received(40).backlog(), Some(60));
+    /// assert_eq!(Received(120).backlog(), Some(0));
+    /// ```
+    fn backlog(&self) -> Option<i64> {
+        Some((self.high_watermark()? - self.offset()?).max(0))
+    }
 }
 
 pub trait AsConsumer: Sized {
@@ -351,6 +660,26 @@ pub trait AsAsyncConsumer: Sized + Send + Sync {
         self.stream_with_timeout(self.default_timeout())
     }
 
+    /// Interval of polls of [AsAsyncConsumer::into_stream], see [PollInterval].
+    fn poll_interval(&self) -> Duration {
+        PollInterval::DEFAULT
+    }
+
+    /// Consume messages by an owned [MessageStream], which skips empty polls and ends when closed
+    /// by its [MessageStreamHandle]. Unlike [AsAsyncConsumer::stream], it does not end on
+    /// timeout.
+    fn into_stream(self) -> MessageStream<Self>
+    where
+        Self: 'static,
+        Self::Offset: Send,
+        Self::Meta: Send,
+        Self::Data: Send,
+        Self::Error: Send,
+    {
+        let interval = self.poll_interval();
+        MessageStream::new(self, interval)
+    }
+
     async fn commit(&self, offset: Self::Offset) -> Result<(), Self::Error>;
 
     async fn unsubscribe(self) {
@@ -430,3 +759,73 @@ where
 //         <C as AsConsumer>::commit(self, offset)
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_message(raw_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend((payload.len() as u32).to_le_bytes());
+        bytes.extend(raw_type.to_le_bytes());
+        bytes.extend(payload);
+        bytes
+    }
+
+    #[test]
+    fn raw_bytes_round_trip() {
+        let data = raw_message(RAW_TYPE_DATA, b"data payload");
+        let message = MessageSet::from_raw_bytes(data.clone()).unwrap();
+        assert!(message.has_data() && !message.has_meta());
+        assert_eq!(message.raw_bytes().as_deref(), Some(data.as_slice()));
+
+        let meta_data = raw_message(RAW_TYPE_META_DATA, b"meta and data");
+        let message = MessageSet::from_raw_bytes(meta_data.clone()).unwrap();
+        assert!(message.has_data() && message.has_meta());
+        assert_eq!(message.raw_bytes().as_deref(), Some(meta_data.as_slice()));
+
+        // Create super table
+        let meta = raw_message(1, b"meta payload");
+        let message = MessageSet::from_raw_bytes(meta.clone()).unwrap();
+        assert!(!message.has_data() && message.has_meta());
+        let raw = message.meta().unwrap().as_raw_meta().unwrap();
+        assert_eq!(raw.raw_type(), 1);
+        assert_eq!(message.raw_bytes().as_deref(), Some(meta.as_slice()));
+        assert!(message.meta().unwrap().as_json_meta().is_err());
+
+        assert!(MessageSet::from_raw_bytes(meta[..meta.len() - 1].to_vec()).is_err());
+        assert!(MessageSet::from_raw_bytes(meta[..3].to_vec()).is_err());
+    }
+
+    #[test]
+    fn parse_timeout() {
+        let cases = [
+            ("500ms", Some(Timeout::from_millis(500))),
+            ("5s", Some(Timeout::from_secs(5))),
+            ("2m", Some(Timeout::from_secs(120))),
+            (" 10 s ", Some(Timeout::from_secs(10))),
+            ("1500", Some(Timeout::from_millis(1500))),
+            ("0", Some(Timeout::None)),
+            ("0ms", Some(Timeout::from_millis(0))),
+            ("none", Some(Timeout::None)),
+            ("Never", Some(Timeout::Never)),
+            ("", None),
+            ("-1", None),
+            ("1.5s", None),
+            ("5h", None),
+            ("ms", None),
+            ("soon", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(value.parse::<Timeout>().ok(), expected, "{value:?}");
+        }
+
+        let err = Timeout::from_param("timeout", "5x")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("timeout"), "{err}");
+        assert!(err.contains(Timeout::FORMATS), "{err}");
+        assert_eq!(Timeout::Never.as_limit(), None);
+        assert_eq!(Timeout::None.as_limit(), Some(Duration::ZERO));
+    }
+}
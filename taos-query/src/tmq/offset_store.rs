@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use taos_error::Error as RawError;
+
+use super::{IsOffset, VGroupId};
+
+/// Offset of a consumer group in a vgroup of a topic, as kept by an [OffsetStore].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffsetValue {
+    pub group_id: String,
+    pub topic: String,
+    pub vgroup_id: VGroupId,
+    /// Offset of the last processed message in the vgroup.
+    pub offset: i64,
+}
+
+impl OffsetValue {
+    pub fn new(
+        group_id: impl Into<String>,
+        topic: impl Into<String>,
+        vgroup_id: VGroupId,
+        offset: i64,
+    ) -> Self {
+        Self {
+            group_id: group_id.into(),
+            topic: topic.into(),
+            vgroup_id,
+            offset,
+        }
+    }
+
+    /// Offset of a consumed message, `None` if the backend does not report offsets.
+    pub fn from_offset(group_id: &str, offset: &impl IsOffset) -> Option<Self> {
+        offset
+            .offset()
+            .map(|value| Self::new(group_id, offset.topic(), offset.vgroup_id(), value))
+    }
+}
+
+/// External storage of consumer offsets, eg. etcd or a database, for checkpoints that are
+/// coordinated with other systems instead of committed to the server.
+///
+/// Offsets are saved when the application commits, and loaded on restart to resume from them
+/// with [StoredOffsets].
+#[async_trait::async_trait]
+pub trait OffsetStore: Send + Sync {
+    /// Stored offsets of `group_id` in each vgroup of `topic`, empty if none was saved.
+    async fn load(&self, group_id: &str, topic: &str) -> Result<Vec<OffsetValue>, RawError>;
+
+    /// Save offsets, replacing the stored ones of the same group, topic and vgroup.
+    async fn save(&self, offsets: &[OffsetValue]) -> Result<(), RawError>;
+}
+
+type OffsetKey = (String, String, VGroupId);
+
+fn merge(map: &mut BTreeMap<OffsetKey, i64>, offsets: &[OffsetValue]) {
+    for value in offsets {
+        map.insert(
+            (value.group_id.clone(), value.topic.clone(), value.vgroup_id),
+            value.offset,
+        );
+    }
+}
+
+fn select(map: &BTreeMap<OffsetKey, i64>, group_id: &str, topic: &str) -> Vec<OffsetValue> {
+    map.iter()
+        .filter(|((group, t, _), _)| group == group_id && t == topic)
+        .map(|((group, topic, vgroup_id), &offset)| {
+            OffsetValue::new(group.as_str(), topic.as_str(), *vgroup_id, offset)
+        })
+        .collect()
+}
+
+/// Offsets kept in memory, mostly for tests.
+#[derive(Debug, Default)]
+pub struct MemoryOffsetStore {
+    offsets: Mutex<BTreeMap<OffsetKey, i64>>,
+}
+
+impl MemoryOffsetStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl OffsetStore for MemoryOffsetStore {
+    async fn load(&self, group_id: &str, topic: &str) -> Result<Vec<OffsetValue>, RawError> {
+        Ok(select(&self.offsets.lock().unwrap(), group_id, topic))
+    }
+
+    async fn save(&self, offsets: &[OffsetValue]) -> Result<(), RawError> {
+        merge(&mut self.offsets.lock().unwrap(), offsets);
+        Ok(())
+    }
+}
+
+/// Offsets kept in a JSON file, a reference implementation of [OffsetStore].
+///
+/// Each save rewrites the file into a temporary one next to it and renames it over, so a crash
+/// while saving leaves the previous offsets intact. The file is not locked, do not share it
+/// between processes.
+#[derive(Debug)]
+pub struct FileOffsetStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileOffsetStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<BTreeMap<OffsetKey, i64>, RawError> {
+        let mut map = BTreeMap::new();
+        match std::fs::read(&self.path) {
+            Ok(bytes) => {
+                let offsets: Vec<OffsetValue> =
+                    serde_json::from_slice(&bytes).map_err(RawError::from_any)?;
+                merge(&mut map, &offsets);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => return Err(RawError::from_any(err)),
+        }
+        Ok(map)
+    }
+}
+
+#[async_trait::async_trait]
+impl OffsetStore for FileOffsetStore {
+    async fn load(&self, group_id: &str, topic: &str) -> Result<Vec<OffsetValue>, RawError> {
+        let _lock = self.lock.lock().unwrap();
+        Ok(select(&self.read()?, group_id, topic))
+    }
+
+    async fn save(&self, offsets: &[OffsetValue]) -> Result<(), RawError> {
+        let _lock = self.lock.lock().unwrap();
+        let mut map = self.read()?;
+        merge(&mut map, offsets);
+        let all: Vec<_> = map
+            .iter()
+            .map(|((group, topic, vgroup_id), &offset)| {
+                OffsetValue::new(group.as_str(), topic.as_str(), *vgroup_id, offset)
+            })
+            .collect();
+        let bytes = serde_json::to_vec(&all).map_err(RawError::from_any)?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, bytes).map_err(RawError::from_any)?;
+        std::fs::rename(&tmp, &self.path).map_err(RawError::from_any)
+    }
+}
+
+/// Offsets loaded from an [OffsetStore] on start, to resume after them.
+///
+/// Without a server side seek, the server redelivers messages after its own committed offsets,
+/// use [StoredOffsets::is_consumed] to skip the ones already processed by a previous run.
+#[derive(Debug, Clone, Default)]
+pub struct StoredOffsets {
+    offsets: HashMap<(String, VGroupId), i64>,
+}
+
+impl StoredOffsets {
+    pub async fn load<S: OffsetStore + ?Sized>(
+        store: &S,
+        group_id: &str,
+        topics: &[&str],
+    ) -> Result<Self, RawError> {
+        let mut offsets = HashMap::new();
+        for topic in topics {
+            for value in store.load(group_id, topic).await? {
+                offsets.insert((value.topic, value.vgroup_id), value.offset);
+            }
+        }
+        Ok(Self { offsets })
+    }
+
+    /// Stored offset of a vgroup of `topic`.
+    pub fn get(&self, topic: &str, vgroup_id: VGroupId) -> Option<i64> {
+        self.offsets.get(&(topic.to_string(), vgroup_id)).copied()
+    }
+
+    /// Whether the message at `offset` was processed before the stored offset was saved.
+    ///
+    /// Always false if the backend does not report offsets.
+    pub fn is_consumed(&self, offset: &impl IsOffset) -> bool {
+        match (
+            offset.offset(),
+            self.get(offset.topic(), offset.vgroup_id()),
+        ) {
+            (Some(offset), Some(stored)) => offset <= stored,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Offset(&'static str, VGroupId, i64);
+
+    impl IsOffset for Offset {
+        fn database(&self) -> &str {
+            "db"
+        }
+
+        fn topic(&self) -> &str {
+            self.0
+        }
+
+        fn vgroup_id(&self) -> VGroupId {
+            self.1
+        }
+
+        fn offset(&self) -> Option<i64> {
+            Some(self.2)
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_offset_store() {
+        let store = MemoryOffsetStore::new();
+        assert!(store.load("g1", "t1").await.unwrap().is_empty());
+        store
+            .save(&[
+                OffsetValue::new("g1", "t1", 2, 10),
+                OffsetValue::new("g1", "t1", 1, 5),
+                OffsetValue::new("g2", "t1", 1, 7),
+            ])
+            .await
+            .unwrap();
+        store
+            .save(&[OffsetValue::new("g1", "t1", 2, 12)])
+            .await
+            .unwrap();
+        assert_eq!(
+            store.load("g1", "t1").await.unwrap(),
+            [
+                OffsetValue::new("g1", "t1", 1, 5),
+                OffsetValue::new("g1", "t1", 2, 12)
+            ]
+        );
+        assert_eq!(store.load("g2", "t1").await.unwrap().len(), 1);
+        assert!(store.load("g1", "t2").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn file_offset_store_resume() {
+        let path = std::env::temp_dir().join(format!("taos-offsets-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // consume and save offsets, then crash.
+        {
+            let store = FileOffsetStore::new(&path);
+            for offset in [Offset("t1", 1, 3), Offset("t1", 2, 8), Offset("t1", 1, 4)] {
+                let value = OffsetValue::from_offset("g1", &offset).unwrap();
+                store.save(&[value]).await.unwrap();
+            }
+            // a save interrupted before the rename.
+            let mut tmp = path.clone().into_os_string();
+            tmp.push(".tmp");
+            std::fs::write(tmp, b"[{\"group_id\":").unwrap();
+        }
+
+        // restart and resume after the saved offsets.
+        let store: Box<dyn OffsetStore> = Box::new(FileOffsetStore::new(&path));
+        let stored = StoredOffsets::load(store.as_ref(), "g1", &["t1", "t2"])
+            .await
+            .unwrap();
+        assert_eq!(stored.get("t1", 1), Some(4));
+        assert_eq!(stored.get("t1", 2), Some(8));
+        assert!(stored.is_consumed(&Offset("t1", 1, 4)));
+        assert!(!stored.is_consumed(&Offset("t1", 1, 5)));
+        assert!(!stored.is_consumed(&Offset("t1", 3, 0)));
+        assert!(!stored.is_consumed(&Offset("t2", 1, 0)));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
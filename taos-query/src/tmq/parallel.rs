@@ -0,0 +1,650 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::stream::{self, Stream, StreamExt};
+
+use crate::common::Timestamp;
+use crate::util::Shutdown;
+use crate::TBuilder;
+
+use super::{AsAsyncConsumer, IsOffset, MessageSet, Timeout, VGroupId};
+
+/// Offset of a message of a [ParallelConsumer], with the instance which received it, so it's
+/// committed by the same instance.
+#[derive(Debug)]
+pub struct ParallelOffset<O> {
+    instance: usize,
+    offset: O,
+}
+
+impl<O> ParallelOffset<O> {
+    /// Id of the instance which received the message.
+    pub fn instance(&self) -> usize {
+        self.instance
+    }
+
+    pub fn inner(&self) -> &O {
+        &self.offset
+    }
+
+    pub fn into_inner(self) -> O {
+        self.offset
+    }
+}
+
+impl<O: IsOffset> IsOffset for ParallelOffset<O> {
+    fn database(&self) -> &str {
+        self.offset.database()
+    }
+
+    fn topic(&self) -> &str {
+        self.offset.topic()
+    }
+
+    fn vgroup_id(&self) -> VGroupId {
+        self.offset.vgroup_id()
+    }
+
+    fn offset(&self) -> Option<i64> {
+        self.offset.offset()
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        self.offset.timestamp()
+    }
+
+    fn begin(&self) -> Option<i64> {
+        self.offset.begin()
+    }
+
+    fn high_watermark(&self) -> Option<i64> {
+        self.offset.high_watermark()
+    }
+}
+
+/// Counters of an instance of a [ParallelConsumer].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstanceMetrics {
+    pub instance: usize,
+    /// Messages received.
+    pub messages: u64,
+    /// Offsets committed.
+    pub commits: u64,
+    /// Errors of polls and commits.
+    pub errors: u64,
+    /// Vgroups of the messages received, which move between instances by rebalances.
+    pub vgroups: BTreeSet<(String, VGroupId)>,
+}
+
+/// Metrics of a [ParallelConsumer], the totals include removed instances.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParallelMetrics {
+    pub messages: u64,
+    pub commits: u64,
+    pub errors: u64,
+    /// Instances removed by [ParallelConsumer::scale_down].
+    pub removed: usize,
+    /// Counters of instances running, in the order of ids.
+    pub instances: Vec<InstanceMetrics>,
+}
+
+/// Error of a [ParallelConsumer].
+#[derive(Debug, thiserror::Error)]
+pub enum ParallelError<E> {
+    #[error("consumer instance {instance} error: {error}")]
+    Consumer { instance: usize, error: E },
+    /// The offset is of an instance removed by [ParallelConsumer::scale_down], its messages not
+    /// committed are consumed again by the others.
+    #[error("consumer instance {0} is removed")]
+    Removed(usize),
+}
+
+impl<E> ParallelError<E> {
+    /// Id of the instance of the error.
+    pub fn instance(&self) -> usize {
+        match self {
+            ParallelError::Consumer { instance, .. } | ParallelError::Removed(instance) => {
+                *instance
+            }
+        }
+    }
+}
+
+struct Instance<C> {
+    id: usize,
+    consumer: Arc<C>,
+    closed: Shutdown,
+    metrics: Arc<Mutex<InstanceMetrics>>,
+}
+
+impl<C: AsAsyncConsumer> Instance<C> {
+    /// Unsubscribe the consumer, or leave it to the stream polling it, which unsubscribes it
+    /// once the poll in flight is cancelled.
+    async fn close(self) {
+        self.closed.trigger();
+        if let Ok(consumer) = Arc::try_unwrap(self.consumer) {
+            consumer.unsubscribe().await;
+        }
+    }
+}
+
+type Message<C> = (
+    ParallelOffset<<C as AsAsyncConsumer>::Offset>,
+    MessageSet<<C as AsAsyncConsumer>::Meta, <C as AsAsyncConsumer>::Data>,
+);
+
+/// Messages of all instances of a [ParallelConsumer], see [ParallelConsumer::stream].
+pub type ParallelStream<C> = Pin<
+    Box<dyn Stream<Item = Result<Message<C>, ParallelError<<C as AsAsyncConsumer>::Error>>> + Send>,
+>;
+
+/// Consumers of the same group polled at the same time, so the server balances vgroups of the
+/// topics across them, eg. to consume a topic of 8 vgroups by more than one connection.
+///
+/// Messages of all instances are merged by [stream](ParallelConsumer::stream), and their
+/// offsets are tagged with the instance which received them, so
+/// [commit](ParallelConsumer::commit) routes each offset back to its owner.
+///
+/// Instances are removed by [scale_down](ParallelConsumer::scale_down) or
+/// [unsubscribe](ParallelConsumer::unsubscribe), then their vgroups are rebalanced to the
+/// others. Dropping it closes all instances, which leave the group as well.
+///
+/// ```rust,no_run
+/// # use futures::StreamExt;
+/// # use taos_query::tmq::ParallelConsumer;
+/// # async fn consume<B>(builder: B) -> Result<(), Box<dyn std::error::Error>>
+/// # where
+/// #     B: taos_query::TBuilder,
+/// #     B::Target: taos_query::tmq::AsAsyncConsumer<Error = B::Error> + 'static,
+/// #     <B::Target as taos_query::tmq::AsAsyncConsumer>::Offset: Send,
+/// #     <B::Target as taos_query::tmq::AsAsyncConsumer>::Meta: Send,
+/// #     <B::Target as taos_query::tmq::AsAsyncConsumer>::Data: Send,
+/// #     B::Error: Send + 'static,
+/// # {
+/// let consumer = ParallelConsumer::new(&builder, ["topic"], 4).await?;
+/// let mut messages = consumer.stream();
+/// while let Some(message) = messages.next().await {
+///     let (offset, message) = message?;
+///     // process `message`, eg. decode its blocks.
+///     # drop(message);
+///     consumer.commit(offset).await?;
+/// }
+/// println!("{:?}", consumer.metrics());
+/// # Ok(())
+/// # }
+/// ```
+pub struct ParallelConsumer<C: AsAsyncConsumer> {
+    instances: Mutex<Vec<Instance<C>>>,
+    removed: Mutex<Vec<InstanceMetrics>>,
+    interval: Duration,
+}
+
+impl<C> ParallelConsumer<C>
+where
+    C: AsAsyncConsumer + 'static,
+    C::Offset: Send,
+    C::Meta: Send,
+    C::Data: Send,
+    C::Error: Send,
+{
+    /// Build `parallelism` consumers by `builder`, at least one, and subscribe each of them to
+    /// `topics`. It fails with the first instance failed to build or subscribe.
+    ///
+    /// Consumers of a group more than the vgroups of the topics receive nothing.
+    pub async fn new<B, T, I>(
+        builder: &B,
+        topics: I,
+        parallelism: usize,
+    ) -> Result<Self, ParallelError<C::Error>>
+    where
+        B: TBuilder<Target = C>,
+        C::Error: From<B::Error>,
+        T: Into<String>,
+        I: IntoIterator<Item = T>,
+    {
+        let topics: Vec<String> = topics.into_iter().map(Into::into).collect();
+        let mut instances = Vec::with_capacity(parallelism.max(1));
+        for id in 0..parallelism.max(1) {
+            let failed = |error| ParallelError::Consumer {
+                instance: id,
+                error,
+            };
+            let mut consumer = builder.build().map_err(|err| failed(err.into()))?;
+            consumer.subscribe(topics.clone()).await.map_err(failed)?;
+            instances.push(Instance {
+                id,
+                consumer: Arc::new(consumer),
+                closed: Shutdown::new(),
+                metrics: Arc::new(Mutex::new(InstanceMetrics {
+                    instance: id,
+                    ..Default::default()
+                })),
+            });
+        }
+        let interval = instances[0].consumer.poll_interval();
+        Ok(Self {
+            instances: Mutex::new(instances),
+            removed: Mutex::new(Vec::new()),
+            interval,
+        })
+    }
+
+    /// Messages of all running instances, in the order they are received.
+    ///
+    /// Each instance is polled in [PollInterval](super::PollInterval)s, empty polls are
+    /// skipped. Messages of an instance end when it's removed, and the stream ends when all
+    /// instances are removed.
+    pub fn stream(&self) -> ParallelStream<C> {
+        let timeout = Timeout::Duration(self.interval);
+        let instances = self.instances.lock().unwrap();
+        let streams = instances.iter().map(|instance| {
+            let state = (
+                instance.id,
+                instance.consumer.clone(),
+                instance.closed.clone(),
+                instance.metrics.clone(),
+            );
+            stream::unfold(state, move |(id, consumer, closed, metrics)| async move {
+                loop {
+                    match closed.guard(consumer.recv_timeout(timeout)).await {
+                        Some(Ok(Some((offset, message)))) => {
+                            let mut counters = metrics.lock().unwrap();
+                            counters.messages += 1;
+                            let vgroup = (offset.topic().to_string(), offset.vgroup_id());
+                            counters.vgroups.insert(vgroup);
+                            drop(counters);
+                            let offset = ParallelOffset {
+                                instance: id,
+                                offset,
+                            };
+                            break Some((Ok((offset, message)), (id, consumer, closed, metrics)));
+                        }
+                        Some(Ok(None)) => continue,
+                        Some(Err(error)) => {
+                            metrics.lock().unwrap().errors += 1;
+                            let err = ParallelError::Consumer {
+                                instance: id,
+                                error,
+                            };
+                            break Some((Err(err), (id, consumer, closed, metrics)));
+                        }
+                        None => {
+                            log::trace!("instance {id} of parallel consumer closed");
+                            if let Ok(consumer) = Arc::try_unwrap(consumer) {
+                                consumer.unsubscribe().await;
+                            }
+                            break None;
+                        }
+                    }
+                }
+            })
+            .boxed()
+        });
+        Box::pin(stream::select_all(streams))
+    }
+
+    /// Commit `offset` by the instance which received it.
+    pub async fn commit(
+        &self,
+        offset: ParallelOffset<C::Offset>,
+    ) -> Result<(), ParallelError<C::Error>> {
+        let id = offset.instance;
+        let owner = self
+            .instances
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|instance| instance.id == id)
+            .map(|instance| (instance.consumer.clone(), instance.metrics.clone()));
+        let Some((consumer, metrics)) = owner else {
+            return Err(ParallelError::Removed(id));
+        };
+        let committed = consumer.commit(offset.offset).await;
+        let mut counters = metrics.lock().unwrap();
+        match committed {
+            Ok(()) => {
+                counters.commits += 1;
+                Ok(())
+            }
+            Err(error) => {
+                counters.errors += 1;
+                Err(ParallelError::Consumer {
+                    instance: id,
+                    error,
+                })
+            }
+        }
+    }
+
+    /// Instances running.
+    pub fn parallelism(&self) -> usize {
+        self.instances.lock().unwrap().len()
+    }
+
+    /// Remove the instances of the largest ids to keep `parallelism` running, at least one,
+    /// returns the number removed.
+    ///
+    /// Removed instances are unsubscribed and the server rebalances their vgroups to the
+    /// others. Their messages not committed yet are consumed again by others, and commits of
+    /// their offsets fail with [ParallelError::Removed].
+    pub async fn scale_down(&self, parallelism: usize) -> usize {
+        let removed: Vec<_> = {
+            let mut instances = self.instances.lock().unwrap();
+            let keep = parallelism.max(1).min(instances.len());
+            instances.drain(keep..).collect()
+        };
+        let n = removed.len();
+        for instance in removed {
+            let metrics = instance.metrics.lock().unwrap().clone();
+            self.removed.lock().unwrap().push(metrics);
+            instance.close().await;
+        }
+        n
+    }
+
+    /// Unsubscribe all instances, messages of [stream](ParallelConsumer::stream) end.
+    pub async fn unsubscribe(self) {
+        let instances = std::mem::take(&mut *self.instances.lock().unwrap());
+        for instance in instances {
+            instance.close().await;
+        }
+    }
+
+    /// Counters of each instance and the totals.
+    pub fn metrics(&self) -> ParallelMetrics {
+        let removed = self.removed.lock().unwrap();
+        let instances: Vec<_> = self
+            .instances
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|instance| instance.metrics.lock().unwrap().clone())
+            .collect();
+        let mut metrics = ParallelMetrics {
+            removed: removed.len(),
+            ..Default::default()
+        };
+        for counters in removed.iter().chain(&instances) {
+            metrics.messages += counters.messages;
+            metrics.commits += counters.commits;
+            metrics.errors += counters.errors;
+        }
+        metrics.instances = instances;
+        metrics
+    }
+}
+
+impl<C: AsAsyncConsumer> Drop for ParallelConsumer<C> {
+    fn drop(&mut self) {
+        for instance in self.instances.lock().unwrap().iter() {
+            instance.closed.trigger();
+        }
+    }
+}
+
+impl<C: AsAsyncConsumer> Debug for ParallelConsumer<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParallelConsumer")
+            .field("parallelism", &self.instances.lock().unwrap().len())
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, VecDeque};
+
+    use crate::common::{JsonMeta, RawData, RawMeta};
+    use crate::tmq::{IsAsyncData, IsAsyncMeta};
+    use crate::{DsnError, IntoDsn, RawBlock};
+
+    use super::*;
+
+    /// Broker of vgroups assigned to subscribed consumers by turns, a vgroup `v` is assigned to
+    /// the consumer at `v % consumers` of the subscribed ones.
+    #[derive(Debug, Default)]
+    struct Broker {
+        queues: Mutex<BTreeMap<VGroupId, VecDeque<i64>>>,
+        subscribed: Mutex<Vec<usize>>,
+        built: Mutex<usize>,
+        commits: Mutex<Vec<(usize, VGroupId, i64)>>,
+    }
+
+    impl Broker {
+        fn produce(&self, vgroups: VGroupId, messages: i64) {
+            let mut queues = self.queues.lock().unwrap();
+            for vgroup in 0..vgroups {
+                queues.entry(vgroup).or_default().extend(0..messages);
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct Builder(Arc<Broker>);
+
+    impl TBuilder for Builder {
+        type Target = Mock;
+        type Error = DsnError;
+
+        fn available_params() -> &'static [&'static str] {
+            &[]
+        }
+
+        fn from_dsn<D: IntoDsn>(_: D) -> Result<Self, Self::Error> {
+            Ok(Self::default())
+        }
+
+        fn client_version() -> &'static str {
+            "mock"
+        }
+
+        fn server_version(&self) -> Result<&str, Self::Error> {
+            Ok("mock")
+        }
+
+        fn ping(&self, _: &mut Mock) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn ready(&self) -> bool {
+            true
+        }
+
+        fn build(&self) -> Result<Mock, Self::Error> {
+            let mut built = self.0.built.lock().unwrap();
+            *built += 1;
+            Ok(Mock(*built - 1, self.0.clone()))
+        }
+    }
+
+    /// Consumer `.0` of a broker.
+    #[derive(Debug)]
+    struct Mock(usize, Arc<Broker>);
+
+    impl Drop for Mock {
+        fn drop(&mut self) {
+            self.1.subscribed.lock().unwrap().retain(|id| *id != self.0);
+        }
+    }
+
+    /// Message `.2` of vgroup `.1` received by consumer `.0`.
+    #[derive(Debug)]
+    struct Offset(usize, VGroupId, i64);
+
+    impl IsOffset for Offset {
+        fn database(&self) -> &str {
+            "db"
+        }
+        fn topic(&self) -> &str {
+            "topic"
+        }
+        fn vgroup_id(&self) -> VGroupId {
+            self.1
+        }
+        fn offset(&self) -> Option<i64> {
+            Some(self.2)
+        }
+    }
+
+    struct Empty;
+
+    #[async_trait::async_trait]
+    impl IsAsyncMeta for Empty {
+        type Error = DsnError;
+
+        async fn as_raw_meta(&self) -> Result<RawMeta, Self::Error> {
+            unimplemented!()
+        }
+
+        async fn as_json_meta(&self) -> Result<JsonMeta, Self::Error> {
+            unimplemented!()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IsAsyncData for Empty {
+        type Error = DsnError;
+
+        async fn as_raw_data(&self) -> Result<RawData, Self::Error> {
+            unimplemented!()
+        }
+
+        async fn fetch_raw_block(&self) -> Result<Option<RawBlock>, Self::Error> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AsAsyncConsumer for Mock {
+        type Error = DsnError;
+        type Offset = Offset;
+        type Meta = Empty;
+        type Data = Empty;
+
+        fn default_timeout(&self) -> Timeout {
+            Timeout::Never
+        }
+
+        async fn subscribe<T: Into<String>, I: IntoIterator<Item = T> + Send>(
+            &mut self,
+            _: I,
+        ) -> Result<(), Self::Error> {
+            self.1.subscribed.lock().unwrap().push(self.0);
+            Ok(())
+        }
+
+        async fn recv_timeout(
+            &self,
+            timeout: Timeout,
+        ) -> Result<Option<(Self::Offset, MessageSet<Self::Meta, Self::Data>)>, Self::Error>
+        {
+            let subscribed = self.1.subscribed.lock().unwrap().clone();
+            if let Some(turn) = subscribed.iter().position(|id| *id == self.0) {
+                let mut queues = self.1.queues.lock().unwrap();
+                for (vgroup, queue) in queues.iter_mut() {
+                    if *vgroup as usize % subscribed.len() != turn {
+                        continue;
+                    }
+                    if let Some(offset) = queue.pop_front() {
+                        let offset = Offset(self.0, *vgroup, offset);
+                        return Ok(Some((offset, MessageSet::Data(Empty))));
+                    }
+                }
+            }
+            tokio::time::sleep(timeout.as_duration()).await;
+            Ok(None)
+        }
+
+        async fn commit(&self, offset: Self::Offset) -> Result<(), Self::Error> {
+            assert_eq!(offset.0, self.0, "committed by another consumer");
+            let commit = (self.0, offset.1, offset.2);
+            self.1.commits.lock().unwrap().push(commit);
+            Ok(())
+        }
+
+        async fn unsubscribe(self) {
+            self.1.subscribed.lock().unwrap().retain(|id| *id != self.0);
+        }
+    }
+
+    async fn consume(consumer: &ParallelConsumer<Mock>, n: usize) -> Vec<ParallelOffset<Offset>> {
+        let mut stream = consumer.stream();
+        let mut offsets = Vec::new();
+        while offsets.len() < n {
+            let (offset, _) = stream.next().await.unwrap().unwrap();
+            offsets.push(offset);
+        }
+        offsets
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn route_commits() {
+        let builder = Builder::default();
+        let broker = builder.0.clone();
+        broker.produce(4, 5);
+        let consumer = ParallelConsumer::new(&builder, ["topic"], 2).await.unwrap();
+        assert_eq!(consumer.parallelism(), 2);
+        assert_eq!(*broker.subscribed.lock().unwrap(), [0, 1]);
+
+        let offsets = consume(&consumer, 20).await;
+        for offset in offsets {
+            assert_eq!(offset.instance(), offset.inner().0);
+            consumer.commit(offset).await.unwrap();
+        }
+        let metrics = consumer.metrics();
+        assert_eq!((metrics.messages, metrics.commits), (20, 20));
+        assert_eq!(broker.commits.lock().unwrap().len(), 20);
+        for (instance, vgroups) in metrics.instances.iter().zip([[0, 2], [1, 3]]) {
+            assert_eq!(instance.messages, 10);
+            let vgroups: BTreeSet<_> = vgroups.iter().map(|v| ("topic".to_string(), *v)).collect();
+            assert_eq!(instance.vgroups, vgroups);
+        }
+
+        // the stream is dropped, so the removed instance is unsubscribed at once.
+        broker.produce(4, 1);
+        let offsets = consume(&consumer, 2).await;
+        assert_eq!(consumer.scale_down(1).await, 1);
+        assert_eq!(*broker.subscribed.lock().unwrap(), [0]);
+        for offset in offsets {
+            match consumer.commit(offset).await {
+                Ok(()) => {}
+                Err(err) => assert!(matches!(err, ParallelError::Removed(1)), "{err}"),
+            }
+        }
+
+        // vgroups of the removed instance are rebalanced to the one left.
+        let offsets = consume(&consumer, 2).await;
+        assert!(offsets.iter().all(|offset| offset.instance() == 0));
+        let metrics = consumer.metrics();
+        assert_eq!((metrics.removed, metrics.instances.len()), (1, 1));
+        assert_eq!(metrics.messages, 24);
+
+        consumer.unsubscribe().await;
+        assert!(broker.subscribed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stream_ends_when_removed() {
+        let builder = Builder::default();
+        let broker = builder.0.clone();
+        let consumer = ParallelConsumer::new(&builder, ["topic"], 3).await.unwrap();
+        let mut stream = consumer.stream();
+        let idle = tokio::time::timeout(Duration::from_secs(2), stream.next()).await;
+        assert!(idle.is_err());
+
+        // removed while polling: the stream unsubscribes them when the polls are cancelled.
+        assert_eq!(consumer.scale_down(0).await, 2);
+        broker.produce(1, 1);
+        let (offset, _) = stream.next().await.unwrap().unwrap();
+        assert_eq!(offset.instance(), 0);
+        assert_eq!(*broker.subscribed.lock().unwrap(), [0]);
+
+        drop(consumer);
+        assert!(stream.next().await.is_none());
+        assert!(broker.subscribed.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Serialize;
+
+use crate::RawBlock;
+
+use super::{IsOffset, MessageSet, MessageTime, VGroupId};
+
+/// Offset range seen in a vgroup, `None` if the backend does not report offsets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OffsetRange {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl OffsetRange {
+    fn update(&mut self, offset: Option<i64>) {
+        if let Some(offset) = offset {
+            self.min = Some(self.min.map_or(offset, |min| min.min(offset)));
+            self.max = Some(self.max.map_or(offset, |max| max.max(offset)));
+        }
+    }
+}
+
+/// End-to-end latency of messages of a topic in [ConsumeReport], see [MessageTime].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LatencyReport {
+    /// Messages measured.
+    pub measured: u64,
+    /// Messages measured by the max row timestamp of the first block, as the server does not
+    /// report the creation time of them.
+    pub approximate: u64,
+    /// Latency of the last measured message.
+    pub last: Duration,
+    /// Max latency of measured messages.
+    pub max: Duration,
+}
+
+impl LatencyReport {
+    fn update(&mut self, time: MessageTime, now: SystemTime) {
+        let latency = time.latency(now);
+        self.measured += 1;
+        self.approximate += time.is_approximate() as u64;
+        self.last = latency;
+        self.max = self.max.max(latency);
+    }
+}
+
+/// Counters of a topic in [ConsumeReport].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TopicReport {
+    /// Messages consumed.
+    pub messages: u64,
+    /// Messages with meta.
+    pub meta: u64,
+    /// Messages with data.
+    pub data: u64,
+    /// Rows of recorded blocks.
+    pub rows: u64,
+    /// Raw bytes of recorded blocks.
+    pub bytes: u64,
+    /// Offsets committed.
+    pub commits: u64,
+    /// Committed offset range by vgroup.
+    pub committed: BTreeMap<VGroupId, OffsetRange>,
+    /// Latency of messages with data.
+    pub latency: LatencyReport,
+}
+
+/// Summary of a consuming job, for logging or monitoring at the end of batch jobs.
+///
+/// Feed it with each message by [record](ConsumeReport::record), each block of data by
+/// [record_block](ConsumeReport::record_block), and each commit by
+/// [record_commit](ConsumeReport::record_commit). Rows are counted from the block header, blocks
+/// are not parsed further.
+///
+/// Latency of data messages is measured when they are recorded, by the creation time reported by
+/// the server, or by the max row timestamp of the first recorded block of them otherwise.
+///
+/// ```rust,ignore
+/// let mut report = ConsumeReport::new();
+/// for message in consumer.iter() {
+///     let (offset, message) = message?;
+///     report.record(&offset, &message);
+///     if let Some(data) = message.into_data() {
+///         for block in data {
+///             let block = block?;
+///             report.record_block(&offset, &block);
+///             // write block
+///         }
+///     }
+///     consumer.commit(offset)?;
+/// }
+/// log::info!("{report}");
+/// ```
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConsumeReport {
+    /// Messages consumed.
+    pub messages: u64,
+    /// Rows of recorded blocks.
+    pub rows: u64,
+    /// Raw bytes of recorded blocks.
+    pub bytes: u64,
+    /// Time from the first record to the last one.
+    pub duration: Duration,
+    /// Counters by topic.
+    pub topics: BTreeMap<String, TopicReport>,
+    #[serde(skip)]
+    started: Option<Instant>,
+    /// The last recorded message is waiting for its first block to measure the latency.
+    #[serde(skip)]
+    latency_pending: bool,
+}
+
+impl ConsumeReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn topic(&mut self, offset: &impl IsOffset) -> &mut TopicReport {
+        let now = Instant::now();
+        let started = *self.started.get_or_insert(now);
+        self.duration = now - started;
+        if !self.topics.contains_key(offset.topic()) {
+            self.topics
+                .insert(offset.topic().to_string(), TopicReport::default());
+        }
+        self.topics.get_mut(offset.topic()).unwrap()
+    }
+
+    /// Record a consumed message.
+    pub fn record<M, D>(&mut self, offset: &impl IsOffset, message: &MessageSet<M, D>) {
+        self.messages += 1;
+        let topic = self.topic(offset);
+        topic.messages += 1;
+        topic.meta += message.has_meta() as u64;
+        topic.data += message.has_data() as u64;
+        self.latency_pending = false;
+        if message.has_data() {
+            match offset.timestamp() {
+                Some(ts) => self.record_latency(offset, MessageTime::new(ts), SystemTime::now()),
+                None => self.latency_pending = true,
+            }
+        }
+    }
+
+    /// Record a block of data message.
+    pub fn record_block(&mut self, offset: &impl IsOffset, block: &RawBlock) {
+        let (rows, bytes) = (block.nrows() as u64, block.as_raw_bytes().len() as u64);
+        self.rows += rows;
+        self.bytes += bytes;
+        let topic = self.topic(offset);
+        topic.rows += rows;
+        topic.bytes += bytes;
+        if std::mem::take(&mut self.latency_pending) {
+            if let Some(time) = MessageTime::from_block(block) {
+                self.record_latency(offset, time, SystemTime::now());
+            }
+        }
+    }
+
+    /// Record the latency of a message at `now`, messages recorded by
+    /// [record](ConsumeReport::record) are measured already.
+    pub fn record_latency(&mut self, offset: &impl IsOffset, time: MessageTime, now: SystemTime) {
+        self.topic(offset).latency.update(time, now);
+    }
+
+    /// Record a committed offset.
+    pub fn record_commit(&mut self, offset: &impl IsOffset) {
+        let position = offset.offset();
+        let topic = self.topic(offset);
+        topic.commits += 1;
+        topic
+            .committed
+            .entry(offset.vgroup_id())
+            .or_default()
+            .update(position);
+    }
+}
+
+impl Display for ConsumeReport {
+    /// One line summary, eg.
+    /// `3 messages, 20 rows, 640 bytes in 1.5s; topic1: 2 messages (1 meta, 1 data), 20 rows, 640 bytes, 2 commits`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} messages, {} rows, {} bytes in {:?}",
+            self.messages, self.rows, self.bytes, self.duration
+        )?;
+        for (name, topic) in &self.topics {
+            write!(
+                f,
+                "; {name}: {} messages ({} meta, {} data), {} rows, {} bytes, {} commits",
+                topic.messages, topic.meta, topic.data, topic.rows, topic.bytes, topic.commits
+            )?;
+            for (vgroup, range) in &topic.committed {
+                if let (Some(min), Some(max)) = (range.min, range.max) {
+                    write!(f, ", vgroup {vgroup} committed {min}..={max}")?;
+                }
+            }
+            let latency = &topic.latency;
+            if latency.measured > 0 {
+                write!(f, ", latency last {:?} max {:?}", latency.last, latency.max)?;
+                if latency.approximate > 0 {
+                    write!(f, " ({} approximate)", latency.approximate)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::common::{
+        views::views_to_raw_block, ColumnView, Precision, RawData, RawMeta, Timestamp,
+    };
+
+    struct Offset {
+        topic: &'static str,
+        vgroup_id: VGroupId,
+        offset: Option<i64>,
+        timestamp: Option<Timestamp>,
+    }
+
+    impl IsOffset for Offset {
+        fn database(&self) -> &str {
+            "db"
+        }
+
+        fn topic(&self) -> &str {
+            self.topic
+        }
+
+        fn vgroup_id(&self) -> VGroupId {
+            self.vgroup_id
+        }
+
+        fn offset(&self) -> Option<i64> {
+            self.offset
+        }
+
+        fn timestamp(&self) -> Option<Timestamp> {
+            self.timestamp
+        }
+    }
+
+    fn block(rows: usize) -> RawBlock {
+        let views = [
+            ColumnView::from_millis_timestamp((0..rows as i64).collect()),
+            ColumnView::from_ints((0..rows as i32).collect()),
+        ];
+        RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond)
+    }
+
+    fn message(raw_type: u16) -> MessageSet<RawMeta, RawData> {
+        let mut bytes = Vec::new();
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(raw_type.to_le_bytes());
+        bytes.push(0);
+        MessageSet::from_raw_bytes(bytes).unwrap()
+    }
+
+    #[test]
+    fn consume_report() {
+        let mut report = ConsumeReport::new();
+        // topic1: a meta message, a data message of 2 blocks, in vgroup 2 and 3.
+        // topic2: a meta-data message without offsets.
+        let dataset = [
+            ("topic1", 2, Some(10), message(1), vec![]),
+            ("topic1", 3, Some(20), message(2), vec![block(3), block(4)]),
+            ("topic1", 2, Some(11), message(2), vec![block(5)]),
+            ("topic2", 2, None, message(4), vec![block(1)]),
+        ];
+        for (topic, vgroup_id, offset, message, blocks) in &dataset {
+            let offset = Offset {
+                topic,
+                vgroup_id: *vgroup_id,
+                offset: *offset,
+                timestamp: None,
+            };
+            report.record(&offset, message);
+            for block in blocks {
+                report.record_block(&offset, block);
+            }
+            report.record_commit(&offset);
+        }
+
+        let block_bytes = |rows| block(rows).as_raw_bytes().len() as u64;
+        assert_eq!(report.messages, 4);
+        assert_eq!(report.rows, 13);
+        assert_eq!(
+            report.bytes,
+            block_bytes(3) + block_bytes(4) + block_bytes(5) + block_bytes(1)
+        );
+
+        let topic1 = &report.topics["topic1"];
+        assert_eq!((topic1.messages, topic1.meta, topic1.data), (3, 1, 2));
+        assert_eq!((topic1.rows, topic1.commits), (12, 3));
+        assert_eq!(
+            topic1.committed[&2],
+            OffsetRange {
+                min: Some(10),
+                max: Some(11)
+            }
+        );
+        assert_eq!(topic1.committed[&3].max, Some(20));
+
+        let topic2 = &report.topics["topic2"];
+        assert_eq!((topic2.messages, topic2.meta, topic2.data), (1, 1, 1));
+        assert_eq!(topic2.rows, 1);
+        assert_eq!(topic2.committed[&2], OffsetRange::default());
+
+        let line = report.to_string();
+        assert!(line.starts_with("4 messages, 13 rows, "), "{line}");
+        assert!(line.contains("; topic1: 3 messages (1 meta, 2 data), 12 rows, "));
+        assert!(line.contains("3 commits, vgroup 2 committed 10..=11, vgroup 3 committed 20..=20"));
+        assert!(line.contains("; topic2: 1 messages (1 meta, 1 data), 1 rows, "));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["messages"], 4);
+        assert_eq!(json["topics"]["topic1"]["committed"]["3"]["min"], 20);
+        assert!(json.get("started").is_none());
+    }
+
+    #[test]
+    fn consume_latency() {
+        let mut report = ConsumeReport::new();
+        let now = || {
+            let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            since_epoch.as_millis() as i64
+        };
+        let offset = |timestamp| Offset {
+            topic: "topic",
+            vgroup_id: 2,
+            offset: None,
+            timestamp,
+        };
+        let rows_at = |ts: Vec<i64>| {
+            let views = [ColumnView::from_millis_timestamp(ts)];
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond)
+        };
+
+        // Created 2s ago in the server, blocks don't matter.
+        let created = offset(Some(Timestamp::Milliseconds(now() - 2000)));
+        report.record(&created, &message(2));
+        report.record_block(&created, &rows_at(vec![0]));
+        let latency = report.topics["topic"].latency;
+        assert_eq!((latency.measured, latency.approximate), (1, 0));
+        assert!(latency.last >= Duration::from_secs(2), "{latency:?}");
+        assert!(latency.last < Duration::from_secs(60), "{latency:?}");
+
+        // Measured by the max row timestamp of the first block only.
+        let unknown = offset(None);
+        let ts = now();
+        report.record(&unknown, &message(2));
+        report.record_block(&unknown, &rows_at(vec![ts - 9000, ts - 5000, ts - 7000]));
+        report.record_block(&unknown, &rows_at(vec![0]));
+        let latency = report.topics["topic"].latency;
+        assert_eq!((latency.measured, latency.approximate), (2, 1));
+        assert!(latency.last >= Duration::from_secs(5), "{latency:?}");
+        assert!(latency.last < Duration::from_secs(60), "{latency:?}");
+        assert_eq!(latency.max, latency.last);
+
+        // Meta messages are not measured.
+        report.record(&unknown, &message(1));
+        report.record_block(&unknown, &rows_at(vec![0]));
+        assert_eq!(report.topics["topic"].latency.measured, 2);
+
+        report.record_latency(
+            &unknown,
+            MessageTime::new(Timestamp::Milliseconds(ts)),
+            UNIX_EPOCH + Duration::from_millis(ts as u64 + 10),
+        );
+        let latency = report.topics["topic"].latency;
+        assert_eq!(latency.last, Duration::from_millis(10));
+        assert!(latency.max >= Duration::from_secs(5));
+        let line = report.to_string();
+        assert!(line.ends_with(" (1 approximate)"), "{line}");
+        assert!(line.contains(", latency last 10ms max "), "{line}");
+    }
+}
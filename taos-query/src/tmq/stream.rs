@@ -0,0 +1,348 @@
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+
+use crate::util::Shutdown;
+use crate::DsnError;
+
+use super::{AsAsyncConsumer, MessageSet, Timeout};
+
+/// Interval of polls of [MessageStream], each poll waits for a message at most the interval.
+///
+/// Set by DSN parameter `poll.interval` (eg. `200ms`, `1s`) and 500ms by default. `never` and `0`
+/// are rejected, streams should neither block without checking to close nor spin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollInterval(Duration);
+
+impl Default for PollInterval {
+    fn default() -> Self {
+        Self(Self::DEFAULT)
+    }
+}
+
+impl PollInterval {
+    /// DSN parameter of the poll interval.
+    pub const PARAM: &'static str = "poll.interval";
+    pub const DEFAULT: Duration = Duration::from_millis(500);
+
+    /// Parse the value of [PollInterval::PARAM], the default interval if it's not set.
+    pub fn from_param(value: Option<&str>) -> Result<Self, DsnError> {
+        match value.map(|value| Timeout::from_param(Self::PARAM, value)) {
+            None => Ok(Self::default()),
+            Some(Ok(Timeout::Duration(interval))) if !interval.is_zero() => Ok(Self(interval)),
+            Some(Ok(_)) => Err(DsnError::InvalidParam(
+                Self::PARAM.to_string(),
+                "expect a positive duration".to_string(),
+            )),
+            Some(Err(err)) => Err(err),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.0
+    }
+}
+
+type Message<C> = (
+    <C as AsAsyncConsumer>::Offset,
+    MessageSet<<C as AsAsyncConsumer>::Meta, <C as AsAsyncConsumer>::Data>,
+);
+
+type BoxedStream<C> =
+    Pin<Box<dyn Stream<Item = Result<Message<C>, <C as AsAsyncConsumer>::Error>> + Send>>;
+
+/// Messages of a consumer as a [Stream], see [AsAsyncConsumer::into_stream].
+///
+/// The stream owns the consumer and polls it with [AsAsyncConsumer::recv_timeout] in
+/// [PollInterval]s, empty polls are skipped. Each poll waits for messages up to the interval
+/// (natively in a blocking thread), so an idle stream wakes once per interval instead of spinning.
+///
+/// It ends when closed by [MessageStreamHandle::close], eg. from another task, and the consumer
+/// is unsubscribed and closed then. Dropping the stream drops the consumer as well, so the
+/// consumer is always closed exactly once. Messages received but not yet yielded when closing
+/// are dropped without commit, so they are consumed again by the group.
+pub struct MessageStream<C: AsAsyncConsumer> {
+    shutdown: Shutdown,
+    inner: BoxedStream<C>,
+}
+
+/// Handle to close a [MessageStream], clones close the same stream.
+#[derive(Debug, Clone)]
+pub struct MessageStreamHandle(Shutdown);
+
+impl MessageStreamHandle {
+    /// End the stream, the consumer is unsubscribed and closed by the stream, a poll in flight
+    /// is cancelled. Returns `false` if it's already closed.
+    pub fn close(&self) -> bool {
+        self.0.trigger()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.is_triggered()
+    }
+}
+
+impl<C> MessageStream<C>
+where
+    C: AsAsyncConsumer + 'static,
+    C::Offset: Send,
+    C::Meta: Send,
+    C::Data: Send,
+    C::Error: Send,
+{
+    /// Stream messages of `consumer`, polled every `interval` at most.
+    pub fn new(consumer: C, interval: Duration) -> Self {
+        let shutdown = Shutdown::new();
+        let closed = shutdown.clone();
+        let timeout = Timeout::Duration(interval);
+        let inner = futures::stream::unfold(consumer, move |consumer| {
+            let closed = closed.clone();
+            async move {
+                loop {
+                    match closed.guard(consumer.recv_timeout(timeout)).await {
+                        Some(Ok(Some(message))) => break Some((Ok(message), consumer)),
+                        Some(Ok(None)) => continue,
+                        Some(Err(err)) => break Some((Err(err), consumer)),
+                        None => {
+                            log::trace!("message stream closed");
+                            consumer.unsubscribe().await;
+                            break None;
+                        }
+                    }
+                }
+            }
+        })
+        .fuse();
+        Self {
+            shutdown,
+            inner: Box::pin(inner),
+        }
+    }
+}
+
+impl<C: AsAsyncConsumer> MessageStream<C> {
+    /// A handle to close the stream from other tasks.
+    pub fn handle(&self) -> MessageStreamHandle {
+        MessageStreamHandle(self.shutdown.clone())
+    }
+
+    /// End the stream, same as [MessageStreamHandle::close].
+    pub fn close(&self) -> bool {
+        self.shutdown.trigger()
+    }
+}
+
+impl<C: AsAsyncConsumer> Stream for MessageStream<C> {
+    type Item = Result<Message<C>, C::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl<C: AsAsyncConsumer> Debug for MessageStream<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MessageStream")
+            .field("closed", &self.shutdown.is_triggered())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::common::{JsonMeta, RawData, RawMeta};
+    use crate::tmq::{IsAsyncData, IsAsyncMeta, IsOffset, VGroupId};
+    use crate::RawBlock;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Counters {
+        polls: AtomicUsize,
+        unsubscribes: AtomicUsize,
+        drops: AtomicUsize,
+    }
+
+    /// Consumer of `messages` offsets, polls wait for the timeout when no more messages.
+    struct Mock {
+        messages: std::sync::Mutex<Vec<Result<i64, String>>>,
+        counters: Arc<Counters>,
+    }
+
+    impl Drop for Mock {
+        fn drop(&mut self) {
+            self.counters.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    struct Offset(i64);
+
+    impl IsOffset for Offset {
+        fn database(&self) -> &str {
+            "db"
+        }
+        fn topic(&self) -> &str {
+            "topic"
+        }
+        fn vgroup_id(&self) -> VGroupId {
+            1
+        }
+        fn offset(&self) -> Option<i64> {
+            Some(self.0)
+        }
+    }
+
+    struct Empty;
+
+    #[async_trait::async_trait]
+    impl IsAsyncMeta for Empty {
+        type Error = String;
+
+        async fn as_raw_meta(&self) -> Result<RawMeta, Self::Error> {
+            Err("no meta".to_string())
+        }
+
+        async fn as_json_meta(&self) -> Result<JsonMeta, Self::Error> {
+            Err("no meta".to_string())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IsAsyncData for Empty {
+        type Error = String;
+
+        async fn as_raw_data(&self) -> Result<RawData, Self::Error> {
+            Err("no data".to_string())
+        }
+
+        async fn fetch_raw_block(&self) -> Result<Option<RawBlock>, Self::Error> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AsAsyncConsumer for Mock {
+        type Error = String;
+        type Offset = Offset;
+        type Meta = Empty;
+        type Data = Empty;
+
+        fn default_timeout(&self) -> Timeout {
+            Timeout::Never
+        }
+
+        async fn subscribe<T: Into<String>, I: IntoIterator<Item = T> + Send>(
+            &mut self,
+            _: I,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn recv_timeout(
+            &self,
+            timeout: Timeout,
+        ) -> Result<Option<(Self::Offset, MessageSet<Self::Meta, Self::Data>)>, Self::Error>
+        {
+            self.counters.polls.fetch_add(1, Ordering::SeqCst);
+            let message = self.messages.lock().unwrap().pop();
+            match message {
+                Some(Ok(offset)) => Ok(Some((Offset(offset), MessageSet::Data(Empty)))),
+                Some(Err(err)) => Err(err),
+                None => {
+                    tokio::time::sleep(timeout.as_duration()).await;
+                    Ok(None)
+                }
+            }
+        }
+
+        async fn commit(&self, _: Self::Offset) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn unsubscribe(self) {
+            self.counters.unsubscribes.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn mock(messages: Vec<Result<i64, String>>) -> (Mock, Arc<Counters>) {
+        let counters = Arc::new(Counters::default());
+        let messages = messages.into_iter().rev().collect();
+        let mock = Mock {
+            messages: std::sync::Mutex::new(messages),
+            counters: counters.clone(),
+        };
+        (mock, counters)
+    }
+
+    #[test]
+    fn poll_interval_params() {
+        let parse = |value| PollInterval::from_param(value).map(|p| p.interval());
+        assert_eq!(parse(None).unwrap(), PollInterval::DEFAULT);
+        assert_eq!(parse(Some("200ms")).unwrap(), Duration::from_millis(200));
+        assert_eq!(parse(Some("1s")).unwrap(), Duration::from_secs(1));
+        for value in ["0", "never", "0ms", "1h"] {
+            let err = PollInterval::from_param(Some(value)).unwrap_err();
+            assert!(err.to_string().contains(PollInterval::PARAM), "{err}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stream_skips_empty_polls() {
+        let (consumer, counters) = mock(vec![Ok(1), Err("lost".to_string()), Ok(2)]);
+        let mut stream = consumer.into_stream();
+        for expected in [1, -1, 2] {
+            match stream.next().await.unwrap() {
+                Ok((offset, _)) => assert_eq!(offset.offset(), Some(expected)),
+                Err(err) => assert_eq!((err.as_str(), expected), ("lost", -1)),
+            }
+        }
+        assert_eq!(counters.polls.load(Ordering::SeqCst), 3);
+
+        // no data for 30 seconds: polls wait in intervals instead of spinning.
+        let idle = tokio::time::timeout(Duration::from_secs(30), stream.next()).await;
+        assert!(idle.is_err());
+        let polls = counters.polls.load(Ordering::SeqCst) - 3;
+        let expected = (30_000 / PollInterval::DEFAULT.as_millis()) as usize;
+        assert!(
+            (expected..=expected + 1).contains(&polls),
+            "{polls} polls in 30s"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn close_from_another_task() {
+        let (consumer, counters) = mock(Vec::new());
+        let mut stream = MessageStream::new(consumer, Duration::from_millis(100));
+        let handle = stream.handle();
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            handle.close()
+        });
+        assert!(stream.next().await.is_none());
+        assert!(task.await.unwrap());
+        assert!(stream.next().await.is_none());
+        assert!(!stream.close());
+        drop(stream);
+        assert_eq!(counters.unsubscribes.load(Ordering::SeqCst), 1);
+        assert_eq!(counters.drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drop_closes_once() {
+        let (consumer, counters) = mock(Vec::new());
+        let mut stream = consumer.into_stream();
+        // dropped while a poll is pending.
+        let pending = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(pending.is_err());
+        drop(stream);
+        assert_eq!(counters.polls.load(Ordering::SeqCst), 1);
+        assert_eq!(counters.drops.load(Ordering::SeqCst), 1);
+        assert_eq!(counters.unsubscribes.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::DsnError;
+
+use super::{Timeout, VGroupId};
+
+/// Offsets of a vgroup of a subscribed topic, as `tmq_get_topic_assignment` reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Assignment {
+    pub vgroup_id: VGroupId,
+    /// Current offset of the consumer in the vgroup.
+    pub offset: i64,
+    /// First offset still kept in the vgroup.
+    pub begin: i64,
+    /// Offset after the last message written to the vgroup.
+    pub end: i64,
+}
+
+/// Begin offsets and high watermarks of vgroups, cached by consumers to annotate received
+/// messages, see [IsOffset::backlog](super::IsOffset::backlog).
+///
+/// Assignments of a topic are refreshed at most once in the interval, set by DSN parameter
+/// `watermark.refresh.interval` (eg. `1s`, `500ms`) and 5 seconds by default, `never` disables
+/// it. Values are as old as the interval, so they are approximate.
+#[derive(Debug)]
+pub struct Watermarks {
+    interval: Option<Duration>,
+    topics: Mutex<HashMap<String, (Instant, Vec<Assignment>)>>,
+}
+
+impl Default for Watermarks {
+    fn default() -> Self {
+        Self::new(Some(Self::DEFAULT_INTERVAL))
+    }
+}
+
+impl Watermarks {
+    /// DSN parameter of the refresh interval.
+    pub const PARAM: &'static str = "watermark.refresh.interval";
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// Refresh every `interval`, `None` to never fetch assignments.
+    pub fn new(interval: Option<Duration>) -> Self {
+        Self {
+            interval,
+            topics: Mutex::default(),
+        }
+    }
+
+    /// Parse the value of [Watermarks::PARAM], the default interval if it's not set.
+    pub fn from_param(value: Option<&str>) -> Result<Self, DsnError> {
+        let interval = value.map(|value| Timeout::from_param(Self::PARAM, value));
+        match interval.transpose()? {
+            None => Ok(Self::default()),
+            Some(Timeout::Duration(interval)) => Ok(Self::new(Some(interval))),
+            Some(Timeout::Never | Timeout::None) => Ok(Self::new(None)),
+        }
+    }
+
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    /// Check if assignments of `topic` should be fetched, always `false` if disabled.
+    pub fn is_stale(&self, topic: &str) -> bool {
+        let Some(interval) = self.interval else {
+            return false;
+        };
+        self.topics
+            .lock()
+            .unwrap()
+            .get(topic)
+            .map_or(true, |(refreshed, _)| refreshed.elapsed() >= interval)
+    }
+
+    /// Save fetched assignments of `topic`, an error keeps the previous ones until the next
+    /// refresh.
+    pub fn update<E: Display>(&self, topic: &str, assignments: Result<Vec<Assignment>, E>) {
+        let mut topics = self.topics.lock().unwrap();
+        let entry = topics
+            .entry(topic.to_string())
+            .or_insert_with(|| (Instant::now(), Vec::new()));
+        entry.0 = Instant::now();
+        match assignments {
+            Ok(assignments) => entry.1 = assignments,
+            Err(err) => log::warn!("fetch assignments of topic {topic} error: {err}"),
+        }
+    }
+
+    /// Begin offset and high watermark of a vgroup, `None` if not fetched.
+    pub fn get(&self, topic: &str, vgroup_id: VGroupId) -> Option<(i64, i64)> {
+        self.topics
+            .lock()
+            .unwrap()
+            .get(topic)?
+            .1
+            .iter()
+            .find(|a| a.vgroup_id == vgroup_id)
+            .map(|a| (a.begin, a.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh() {
+        let watermarks = Watermarks::from_param(Some("50ms")).unwrap();
+        assert!(watermarks.is_stale("t1"));
+        assert_eq!(watermarks.get("t1", 2), None);
+
+        let assignment = Assignment {
+            vgroup_id: 2,
+            offset: 3,
+            begin: 0,
+            end: 10,
+        };
+        watermarks.update::<&str>("t1", Ok(vec![assignment]));
+        assert!(!watermarks.is_stale("t1"));
+        assert!(watermarks.is_stale("t2"));
+        assert_eq!(watermarks.get("t1", 2), Some((0, 10)));
+        assert_eq!(watermarks.get("t1", 3), None);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(watermarks.is_stale("t1"));
+        watermarks.update::<&str>("t1", Err("timeout"));
+        assert!(!watermarks.is_stale("t1"));
+        assert_eq!(watermarks.get("t1", 2), Some((0, 10)), "kept on errors");
+
+        let disabled = Watermarks::from_param(Some("never")).unwrap();
+        assert!(!disabled.is_stale("t1"));
+        assert_eq!(
+            Watermarks::from_param(None).unwrap().interval(),
+            Some(Watermarks::DEFAULT_INTERVAL)
+        );
+        assert!(Watermarks::from_param(Some("soon")).is_err());
+
+        let json = r#"{"vgroup_id":2,"offset":3,"begin":0,"end":10}"#;
+        assert_eq!(
+            serde_json::from_str::<Assignment>(json).unwrap(),
+            assignment
+        );
+    }
+}
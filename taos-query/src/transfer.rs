@@ -0,0 +1,1201 @@
+//! Table level export and import, like `taosdump` for a single table.
+//!
+//! [export_table] streams the schema of a table and its data blocks to a [BlockSink], and
+//! [import_table] recreates the table from a [BlockSource] and writes the blocks back. The
+//! provided [WriteSink] and [ReadSource] adapt any [Write] and [Read], eg. a file or an object
+//! store upload, to the dump format:
+//!
+//! - magic `TAOSDUMP` and a `u32` format version,
+//! - the [TableSchema] as a `u32` length prefixed JSON,
+//! - each block as a `u32` length prefixed inlined block, see [Inlinable], ended by a zero
+//!   length. Dumps of version 1 have raw blocks instead, which are still read.
+//!
+//! All integers are little-endian.
+//!
+//! [copy_table] copies a table between two connections directly, with columns renamed or cast
+//! on the way.
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use futures::{StreamExt, TryStreamExt};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::common::views::views_to_raw_block;
+use crate::common::{
+    BorrowedValue, ColumnView, ConvertError, Precision, PrecisionError, RawBlock, Timestamp, Ty,
+};
+use crate::helpers::{batch_values, is_schema_error, ColumnMeta, Described, TimeRange};
+use crate::prelude::{AsyncFetchable, AsyncQueryable};
+use crate::util::{quote_ident, Inlinable};
+
+const MAGIC: &[u8; 8] = b"TAOSDUMP";
+/// Blocks are inlined since version 2, so NChar values decoded in place are written as chars.
+const VERSION: u32 = 2;
+/// Rows per `insert` statement when falling back from raw block writing.
+const INSERT_BATCH_ROWS: usize = 500;
+/// Rows per query of [copy_table] by default.
+const COPY_BATCH_ROWS: usize = 10_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransferError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid dump: {0}")]
+    Format(String),
+    #[error("table `{0}` has no timestamp primary key")]
+    NoPrimaryKey(String),
+    #[error("column `{column}` is not in table `{table}`")]
+    NoColumn { table: String, column: String },
+    #[error(transparent)]
+    Convert(#[from] ConvertError),
+    #[error(transparent)]
+    Precision(#[from] PrecisionError),
+    #[error("{actual} rows in the destination, but {expected} rows in the source")]
+    Verify { expected: i64, actual: i64 },
+    #[error(transparent)]
+    Query(anyhow::Error),
+}
+
+impl From<TransferError> for taos_error::Error {
+    fn from(err: TransferError) -> Self {
+        Self::from_any(err)
+    }
+}
+
+fn query_error<E: Into<anyhow::Error>>(err: E) -> TransferError {
+    TransferError::Query(err.into())
+}
+
+/// Schema of an exported table, the header of a dump.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSchema {
+    pub table: String,
+    pub precision: Precision,
+    /// Data columns, the first one is the timestamp primary key.
+    pub columns: Vec<Described>,
+    /// Tags of the super table for child tables, informational only, tags are not imported.
+    #[serde(default)]
+    pub tags: Vec<Described>,
+}
+
+impl TableSchema {
+    /// Sql to create the table of the data columns as a normal table named `table`.
+    pub fn to_create_table_sql(&self, table: &str) -> String {
+        self.columns
+            .iter()
+            .cloned()
+            .map(ColumnMeta::Column)
+            .collect::<crate::common::Describe>()
+            .to_create_table_sql(table)
+    }
+}
+
+/// Destination of [export_table].
+#[async_trait::async_trait]
+pub trait BlockSink: Send {
+    async fn write_schema(&mut self, schema: &TableSchema) -> Result<(), TransferError>;
+
+    async fn write_block(&mut self, block: &RawBlock) -> Result<(), TransferError>;
+
+    /// Called once after the last block.
+    async fn finish(&mut self) -> Result<(), TransferError> {
+        Ok(())
+    }
+}
+
+/// Origin of [import_table].
+#[async_trait::async_trait]
+pub trait BlockSource: Send {
+    /// Read the schema, called once before reading blocks.
+    async fn read_schema(&mut self) -> Result<TableSchema, TransferError>;
+
+    /// Next block with field names of the schema, `None` at the end.
+    async fn read_block(&mut self) -> Result<Option<RawBlock>, TransferError>;
+}
+
+fn write_frame<W: Write>(wtr: &mut W, bytes: &[u8]) -> std::io::Result<()> {
+    wtr.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    wtr.write_all(bytes)
+}
+
+fn read_u32<R: Read>(rdr: &mut R) -> std::io::Result<u32> {
+    let mut buf = [0; 4];
+    rdr.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_frame<R: Read>(rdr: &mut R) -> std::io::Result<Vec<u8>> {
+    let len = read_u32(rdr)? as usize;
+    let mut bytes = vec![0; len];
+    rdr.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// [BlockSink] writing the dump format to a [Write].
+///
+/// Writes are blocking, wrap the writer in a [std::io::BufWriter] for files.
+#[derive(Debug)]
+pub struct WriteSink<W> {
+    wtr: W,
+}
+
+impl<W: Write> WriteSink<W> {
+    pub fn new(wtr: W) -> Self {
+        Self { wtr }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.wtr
+    }
+}
+
+#[async_trait::async_trait]
+impl<W: Write + Send> BlockSink for WriteSink<W> {
+    async fn write_schema(&mut self, schema: &TableSchema) -> Result<(), TransferError> {
+        self.wtr.write_all(MAGIC)?;
+        self.wtr.write_all(&VERSION.to_le_bytes())?;
+        let json =
+            serde_json::to_vec(schema).map_err(|err| TransferError::Format(err.to_string()))?;
+        write_frame(&mut self.wtr, &json)?;
+        Ok(())
+    }
+
+    async fn write_block(&mut self, block: &RawBlock) -> Result<(), TransferError> {
+        if block.nrows() > 0 {
+            write_frame(&mut self.wtr, &block.inlined())?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<(), TransferError> {
+        self.wtr.write_all(&0u32.to_le_bytes())?;
+        self.wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// [BlockSource] reading the dump format from a [Read].
+#[derive(Debug)]
+pub struct ReadSource<R> {
+    rdr: R,
+    schema: Option<TableSchema>,
+    version: u32,
+}
+
+impl<R: Read> ReadSource<R> {
+    pub fn new(rdr: R) -> Self {
+        Self {
+            rdr,
+            schema: None,
+            version: VERSION,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<R: Read + Send> BlockSource for ReadSource<R> {
+    async fn read_schema(&mut self) -> Result<TableSchema, TransferError> {
+        let mut magic = [0; 8];
+        self.rdr.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(TransferError::Format("not a table dump".to_string()));
+        }
+        let version = read_u32(&mut self.rdr)?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(TransferError::Format(format!(
+                "unsupported version {version}"
+            )));
+        }
+        let json = read_frame(&mut self.rdr)?;
+        let schema: TableSchema =
+            serde_json::from_slice(&json).map_err(|err| TransferError::Format(err.to_string()))?;
+        self.schema = Some(schema.clone());
+        self.version = version;
+        Ok(schema)
+    }
+
+    async fn read_block(&mut self) -> Result<Option<RawBlock>, TransferError> {
+        let schema = self
+            .schema
+            .as_ref()
+            .ok_or_else(|| TransferError::Format("schema is not read".to_string()))?;
+        let bytes = read_frame(&mut self.rdr)?;
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+        let mut block = if self.version == 1 {
+            RawBlock::parse_from_raw_block(bytes, schema.precision)
+        } else {
+            let mut slice = bytes.as_slice();
+            let block = RawBlock::read_inlined(&mut slice)?;
+            if !slice.is_empty() {
+                return Err(TransferError::Format(format!(
+                    "{} trailing bytes after a block",
+                    slice.len()
+                )));
+            }
+            block
+        };
+        if block.ncols() != schema.columns.len() {
+            return Err(TransferError::Format(format!(
+                "block of {} columns in table of {} columns",
+                block.ncols(),
+                schema.columns.len()
+            )));
+        }
+        block.with_field_names(schema.columns.iter().map(|c| c.field.as_str()));
+        Ok(Some(block))
+    }
+}
+
+/// Rows and blocks transferred by [export_table] or [import_table].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferSummary {
+    pub blocks: usize,
+    pub rows: usize,
+    /// Rows skipped by [import_table] as they were imported before.
+    pub skipped: usize,
+}
+
+/// Export data columns of `table` in the current database to `sink`, ordered by timestamp.
+///
+/// If the connection caches schemas and the columns fail as a cached schema is stale, the
+/// table is described again once.
+pub async fn export_table<Q, S>(
+    taos: &Q,
+    table: &str,
+    sink: &mut S,
+) -> Result<TransferSummary, TransferError>
+where
+    Q: AsyncQueryable,
+    Q::Error: Into<anyhow::Error>,
+    S: BlockSink + ?Sized,
+{
+    // a cached schema may be stale, so it's described again once if the columns fail.
+    let mut retried = false;
+    let (columns, tags, mut rs) = loop {
+        let describe = taos.describe(table).await.map_err(query_error)?;
+        let (columns, tags): (Vec<_>, Vec<_>) = describe.iter().partition(|c| !c.is_tag());
+        let columns: Vec<Described> = columns.into_iter().map(|c| (**c).clone()).collect();
+        let tags: Vec<Described> = tags.into_iter().map(|c| (**c).clone()).collect();
+        let ts = columns
+            .first()
+            .filter(|c| c.ty == crate::common::Ty::Timestamp)
+            .ok_or_else(|| TransferError::NoPrimaryKey(table.to_string()))?;
+        let sql = format!(
+            "select {} from {} order by {}",
+            columns.iter().map(|c| quote_ident(&c.field)).join(", "),
+            quote_ident(table),
+            quote_ident(&ts.field),
+        );
+        let err: anyhow::Error = match taos.query(sql).await {
+            Ok(rs) => break (columns, tags, rs),
+            Err(err) => err.into(),
+        };
+        match taos.schema_cache() {
+            Some(cache) if !retried && is_schema_error(&err) => {
+                cache.invalidate(table);
+                retried = true;
+            }
+            _ => return Err(TransferError::Query(err)),
+        }
+    };
+    let schema = TableSchema {
+        table: table.to_string(),
+        precision: rs.precision(),
+        columns,
+        tags,
+    };
+    sink.write_schema(&schema).await?;
+
+    let mut summary = TransferSummary::default();
+    let mut blocks = rs.blocks();
+    while let Some(block) = blocks
+        .try_next()
+        .await
+        .map_err(|err| query_error(Q::Error::from(err)))?
+    {
+        summary.blocks += 1;
+        summary.rows += block.nrows();
+        sink.write_block(&block).await?;
+    }
+    sink.finish().await?;
+    Ok(summary)
+}
+
+/// Timestamp of the primary key at `row`.
+fn ts_at(block: &RawBlock, row: usize) -> Option<i64> {
+    match block.get_ref(row, 0) {
+        Some(BorrowedValue::Timestamp(ts)) => Some(ts.as_raw_i64()),
+        _ => None,
+    }
+}
+
+/// Rows of `block` after timestamp `last`, `None` if there is none.
+fn rows_after(block: RawBlock, last: Option<i64>) -> Option<RawBlock> {
+    let last = match last {
+        Some(last) => last,
+        None => return Some(block),
+    };
+    let nrows = block.nrows();
+    // rows are ordered by timestamp, find the first one after `last`.
+    let (mut start, mut end) = (0, nrows);
+    while start < end {
+        let mid = (start + end) / 2;
+        if ts_at(&block, mid).map_or(true, |ts| ts <= last) {
+            start = mid + 1;
+        } else {
+            end = mid;
+        }
+    }
+    if start == 0 {
+        return Some(block);
+    }
+    if start == nrows {
+        return None;
+    }
+    let views: Vec<ColumnView> = block
+        .column_views()
+        .iter()
+        .map(|view| view.slice(start..nrows).expect("range is not empty"))
+        .collect();
+    let mut sliced = RawBlock::parse_from_raw_block(views_to_raw_block(&views), block.precision());
+    sliced.with_field_names(block.field_names());
+    Some(sliced)
+}
+
+/// Write rows of `block` by `insert` statements, split by the max sql length of `taos`.
+async fn insert_block<Q>(taos: &Q, table: &str, block: &RawBlock) -> Result<(), TransferError>
+where
+    Q: AsyncQueryable,
+    Q::Error: Into<anyhow::Error>,
+{
+    let prefix = format!(
+        "insert into {} ({}) values ",
+        quote_ident(table),
+        block
+            .field_names()
+            .iter()
+            .map(|c| quote_ident(c))
+            .join(", ")
+    );
+    let max_sql_length = taos.limits().await.max_sql_length;
+    for rows in &(0..block.nrows()).chunks(INSERT_BATCH_ROWS) {
+        let values = rows.map(|row| {
+            let row = (0..block.ncols())
+                .map(|col| block.get_ref(row, col).unwrap().to_sql_value())
+                .join(", ");
+            format!("({row})")
+        });
+        for sql in batch_values(&prefix, values, max_sql_length) {
+            taos.exec(sql).await.map_err(query_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `block` by `write_raw_block`, or by `insert` statements since the server rejects it once.
+async fn write_block<Q>(
+    taos: &Q,
+    table: &str,
+    block: &RawBlock,
+    fallback: &mut bool,
+) -> Result<(), TransferError>
+where
+    Q: AsyncQueryable,
+    Q::Error: Into<anyhow::Error>,
+{
+    if !*fallback {
+        match taos.write_raw_block(block).await {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                log::warn!("write raw block failed, fallback to insert: {err:?}");
+                *fallback = true;
+            }
+        }
+    }
+    insert_block(taos, table, block).await
+}
+
+/// Import a dump to the table of its name in the current database, see [import_table_as].
+pub async fn import_table<Q, S>(taos: &Q, source: &mut S) -> Result<TransferSummary, TransferError>
+where
+    Q: AsyncQueryable,
+    Q::Error: Into<anyhow::Error>,
+    S: BlockSource + ?Sized,
+{
+    import_table_as(taos, source, None).await
+}
+
+/// Import a dump to `table`, or the table of its name if `None`, in the current database.
+///
+/// The table is created as a normal table of the exported data columns if it does not exist,
+/// tags are not imported. The database must have the precision of the dump.
+///
+/// Import is resumable: rows up to the last timestamp in the table are skipped, so an interrupted
+/// import could be run again from the beginning of the dump. Blocks are written by
+/// `write_raw_block`, if the server rejects it, by `insert` statements.
+pub async fn import_table_as<Q, S>(
+    taos: &Q,
+    source: &mut S,
+    table: Option<&str>,
+) -> Result<TransferSummary, TransferError>
+where
+    Q: AsyncQueryable,
+    Q::Error: Into<anyhow::Error>,
+    S: BlockSource + ?Sized,
+{
+    let schema = source.read_schema().await?;
+    let table = table.unwrap_or(&schema.table).to_string();
+    let ts = schema
+        .columns
+        .first()
+        .ok_or_else(|| TransferError::NoPrimaryKey(table.clone()))?;
+    taos.exec(schema.to_create_table_sql(&table))
+        .await
+        .map_err(query_error)?;
+    let last: Option<i64> = taos
+        .query_one(format!(
+            "select cast(last({}) as bigint) from {}",
+            quote_ident(&ts.field),
+            quote_ident(&table)
+        ))
+        .await
+        .map_err(query_error)?
+        .flatten();
+    if let Some(last) = last {
+        log::debug!("resume import of `{table}` after timestamp {last}");
+    }
+
+    let mut summary = TransferSummary::default();
+    let mut fallback = false;
+    while let Some(block) = source.read_block().await? {
+        let nrows = block.nrows();
+        let mut block = match rows_after(block, last) {
+            Some(block) => block,
+            None => {
+                summary.skipped += nrows;
+                continue;
+            }
+        };
+        summary.skipped += nrows - block.nrows();
+        block.with_table_name(table.as_str());
+        write_block(taos, &table, &block, &mut fallback).await?;
+        summary.blocks += 1;
+        summary.rows += block.nrows();
+    }
+    Ok(summary)
+}
+
+/// Progress of [copy_table], reported after each batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyProgress {
+    /// Index of the time range of the batch, in `0..ranges`.
+    pub range: usize,
+    pub ranges: usize,
+    /// The last timestamp copied in the range, in the precision of the source.
+    pub last: Timestamp,
+    /// Rows copied in all ranges so far.
+    pub rows: usize,
+}
+
+/// Callback for the progress of [copy_table], set by [CopySpec::on_progress].
+#[derive(Clone)]
+pub struct ProgressListener(Arc<dyn Fn(&CopyProgress) + Send + Sync>);
+
+impl ProgressListener {
+    pub fn new(f: impl Fn(&CopyProgress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn notify(&self, progress: &CopyProgress) {
+        (self.0)(progress)
+    }
+}
+
+impl Debug for ProgressListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressListener")
+    }
+}
+
+/// What [copy_table] copies and how.
+///
+/// ```rust
+/// # use taos_query::common::Ty;
+/// # use taos_query::transfer::CopySpec;
+/// let spec = CopySpec::new("meters", "meters_v2")
+///     .rename("voltage", "volt")
+///     .cast("current", Ty::Double, 0)
+///     .cast("location", Ty::VarChar, 64)
+///     .batch_size(50_000)
+///     .parallelism(4)
+///     .on_progress(|progress| println!("{} rows copied", progress.rows));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CopySpec {
+    source: String,
+    destination: String,
+    renames: Vec<(String, String)>,
+    casts: Vec<(String, Ty, usize)>,
+    time_range: Option<TimeRange>,
+    batch_rows: usize,
+    parallelism: usize,
+    verify: bool,
+    progress: Option<ProgressListener>,
+}
+
+impl CopySpec {
+    /// Copy all rows of `source` to `destination`, by batches of 10000 rows in one time range.
+    pub fn new(source: impl Into<String>, destination: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            destination: destination.into(),
+            renames: Vec::new(),
+            casts: Vec::new(),
+            time_range: None,
+            batch_rows: COPY_BATCH_ROWS,
+            parallelism: 1,
+            verify: true,
+            progress: None,
+        }
+    }
+
+    /// Name the source column `column` as `to` in the destination.
+    pub fn rename(mut self, column: impl Into<String>, to: impl Into<String>) -> Self {
+        self.renames.push((column.into(), to.into()));
+        self
+    }
+
+    /// Convert the source column `column` to `ty` without loss, see [ColumnView::cast].
+    ///
+    /// `length` is the size of var types, like 64 of `VARCHAR(64)`, it's ignored for others.
+    pub fn cast(mut self, column: impl Into<String>, ty: Ty, length: usize) -> Self {
+        self.casts.push((column.into(), ty, length));
+        self
+    }
+
+    /// Copy rows in `range` only.
+    pub fn time_range(mut self, range: TimeRange) -> Self {
+        self.time_range = Some(range);
+        self
+    }
+
+    /// Rows of each query and progress report, at least 1.
+    pub fn batch_size(mut self, rows: usize) -> Self {
+        self.batch_rows = rows.max(1);
+        self
+    }
+
+    /// Split the time range of the rows into `n` ranges, copied concurrently.
+    pub fn parallelism(mut self, n: usize) -> Self {
+        self.parallelism = n.max(1);
+        self
+    }
+
+    /// Compare `count(*)` of both tables in the time range after copying or not, on by default.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    pub fn on_progress(mut self, f: impl Fn(&CopyProgress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(ProgressListener::new(f));
+        self
+    }
+
+    /// Columns in the destination of the source `columns`.
+    fn translate(&self, columns: &[Described]) -> Result<Vec<Described>, TransferError> {
+        let no_column = |column: &String| TransferError::NoColumn {
+            table: self.source.clone(),
+            column: column.clone(),
+        };
+        let has = |column: &String| columns.iter().any(|c| &c.field == column);
+        if let Some(column) = self
+            .renames
+            .iter()
+            .map(|(column, _)| column)
+            .chain(self.casts.iter().map(|(column, _, _)| column))
+            .find(|column| !has(column))
+        {
+            return Err(no_column(column));
+        }
+        Ok(columns
+            .iter()
+            .map(|column| {
+                let mut to = column.clone();
+                if let Some((_, name)) = self.renames.iter().rfind(|(c, _)| c == &column.field) {
+                    to.field = name.clone();
+                }
+                if let Some((_, ty, length)) =
+                    self.casts.iter().rfind(|(c, _, _)| c == &column.field)
+                {
+                    to.ty = *ty;
+                    to.length = if ty.is_var_type() {
+                        *length
+                    } else {
+                        ty.fixed_length()
+                    };
+                }
+                to
+            })
+            .collect())
+    }
+}
+
+/// Ranges of timestamps `first..=last` split into at most `n` ranges of the same span.
+fn split_range(first: i64, last: i64, n: usize, precision: Precision) -> Vec<TimeRange> {
+    let datetime = |raw| Timestamp::new(raw, precision).to_naive_datetime().and_utc();
+    let span = last as i128 - first as i128 + 1;
+    let n = (n as i128).clamp(1, span.max(1));
+    let step = (span + n - 1) / n;
+    (0..n)
+        .map(|i| first as i128 + i * step)
+        .take_while(|&start| start <= last as i128)
+        .map(|start| {
+            let end = start + step;
+            let end = if end > last as i128 {
+                Bound::Included(datetime(last))
+            } else {
+                Bound::Excluded(datetime(end as i64))
+            };
+            TimeRange::new(Bound::Included(datetime(start as i64)), end)
+        })
+        .collect()
+}
+
+/// Block of `to` columns in `precision` from a non-empty block of `from` columns.
+fn translate_block(
+    block: &RawBlock,
+    from: &[Described],
+    to: &[Described],
+    precision: Precision,
+) -> Result<RawBlock, TransferError> {
+    let views = block
+        .column_views()
+        .iter()
+        .zip(from.iter().zip(to))
+        .map(|(view, (from, to))| match view {
+            ColumnView::Timestamp(ts) if to.ty == Ty::Timestamp => {
+                Ok(ColumnView::Timestamp(ts.normalize_to(precision)?))
+            }
+            _ if from.ty != to.ty => Ok(view.cast(to.ty)?),
+            _ => Ok(view.slice(0..view.len()).expect("block is not empty")),
+        })
+        .collect::<Result<Vec<_>, TransferError>>()?;
+    let mut block = RawBlock::parse_from_raw_block(views_to_raw_block(&views), precision);
+    block.with_field_names(to.iter().map(|c| c.field.as_str()));
+    Ok(block)
+}
+
+/// Source and destination of [copy_table], shared by the ranges.
+struct TableCopy<'a, S, D> {
+    src: &'a S,
+    dst: &'a D,
+    spec: &'a CopySpec,
+    columns: Vec<Described>,
+    translated: Vec<Described>,
+    src_precision: Precision,
+    dst_precision: Precision,
+    ranges: usize,
+    rows: AtomicUsize,
+}
+
+impl<'a, S, D> TableCopy<'a, S, D>
+where
+    S: AsyncQueryable,
+    S::Error: Into<anyhow::Error>,
+    D: AsyncQueryable,
+    D::Error: Into<anyhow::Error>,
+{
+    /// Copy rows in `range` by batches, after the last timestamp of the range in destination.
+    async fn copy_range(
+        &self,
+        index: usize,
+        range: TimeRange,
+    ) -> Result<TransferSummary, TransferError> {
+        let (src_ts, dst_ts) = (
+            quote_ident(&self.columns[0].field),
+            quote_ident(&self.translated[0].field),
+        );
+        let resumed: Option<i64> = self
+            .dst
+            .query_one(format!(
+                "select cast(last({dst_ts}) as bigint) from {} where {}",
+                quote_ident(&self.spec.destination),
+                range.to_sql_condition(&dst_ts, self.dst_precision)
+            ))
+            .await
+            .map_err(query_error)?
+            .flatten();
+        let mut start = match resumed {
+            Some(raw) => {
+                log::debug!("resume copy of range {index} after timestamp {raw}");
+                let last = Timestamp::new(raw, self.dst_precision).to_naive_datetime();
+                Bound::Excluded(last.and_utc())
+            }
+            None => range.start(),
+        };
+        let select = format!(
+            "select {} from {}",
+            self.columns
+                .iter()
+                .map(|c| quote_ident(&c.field))
+                .join(", "),
+            quote_ident(&self.spec.source),
+        );
+
+        let mut summary = TransferSummary::default();
+        let mut fallback = false;
+        loop {
+            let page = TimeRange::new(start, range.end());
+            let sql = format!(
+                "{select} where {} order by {src_ts} limit {}",
+                page.to_sql_condition(&src_ts, self.src_precision),
+                self.spec.batch_rows
+            );
+            let mut rs = self.src.query(sql).await.map_err(query_error)?;
+            let (mut rows, mut last) = (0, None);
+            let mut blocks = rs.blocks();
+            while let Some(block) = blocks
+                .try_next()
+                .await
+                .map_err(|err| query_error(S::Error::from(err)))?
+            {
+                if block.nrows() == 0 {
+                    continue;
+                }
+                last = ts_at(&block, block.nrows() - 1);
+                let mut translated =
+                    translate_block(&block, &self.columns, &self.translated, self.dst_precision)?;
+                translated.with_table_name(self.spec.destination.as_str());
+                write_block(self.dst, &self.spec.destination, &translated, &mut fallback).await?;
+                rows += block.nrows();
+                summary.blocks += 1;
+            }
+            let last = match last {
+                Some(last) => Timestamp::new(last, self.src_precision),
+                None => break,
+            };
+            summary.rows += rows;
+            let total = self.rows.fetch_add(rows, Ordering::Relaxed) + rows;
+            if let Some(progress) = &self.spec.progress {
+                progress.notify(&CopyProgress {
+                    range: index,
+                    ranges: self.ranges,
+                    last,
+                    rows: total,
+                });
+            }
+            if rows < self.spec.batch_rows {
+                break;
+            }
+            start = Bound::Excluded(last.to_naive_datetime().and_utc());
+        }
+        Ok(summary)
+    }
+}
+
+/// Rows of `table` in `range`.
+async fn count_rows<Q>(
+    taos: &Q,
+    table: &str,
+    ts: &str,
+    range: &TimeRange,
+    precision: Precision,
+) -> Result<i64, TransferError>
+where
+    Q: AsyncQueryable,
+    Q::Error: Into<anyhow::Error>,
+{
+    let count: Option<i64> = taos
+        .query_one(format!(
+            "select count(*) from {} where {}",
+            quote_ident(table),
+            range.to_sql_condition(&quote_ident(ts), precision)
+        ))
+        .await
+        .map_err(query_error)?;
+    Ok(count.unwrap_or_default())
+}
+
+/// Precision of the database of `table`.
+async fn precision_of<Q>(taos: &Q, table: &str) -> Result<Precision, TransferError>
+where
+    Q: AsyncQueryable,
+    Q::Error: Into<anyhow::Error>,
+{
+    let rs = taos
+        .query(format!("select * from {} limit 0", quote_ident(table)))
+        .await
+        .map_err(query_error)?;
+    Ok(rs.precision())
+}
+
+/// Copy data columns of a table from `src` to `dst`, both in the current database, by `spec`.
+///
+/// The destination is created as a normal table of the translated columns if it does not
+/// exist, tags are not copied. Timestamps are converted to the precision of the destination
+/// database, rows of the same timestamp in a coarser precision overwrite each other.
+///
+/// Rows are copied by batches in timestamp order and written like [import_table_as]. With
+/// [CopySpec::parallelism], the time range of the rows is split into ranges copied
+/// concurrently. Copy is resumable: each range starts after its last timestamp in the
+/// destination, so run it again with the same spec after an interruption.
+///
+/// The returned summary does not count skipped rows, as they are not read.
+pub async fn copy_table<S, D>(
+    src: &S,
+    dst: &D,
+    spec: CopySpec,
+) -> Result<TransferSummary, TransferError>
+where
+    S: AsyncQueryable,
+    S::Error: Into<anyhow::Error>,
+    D: AsyncQueryable,
+    D::Error: Into<anyhow::Error>,
+{
+    let describe = src.describe(&spec.source).await.map_err(query_error)?;
+    let columns: Vec<Described> = describe
+        .iter()
+        .filter(|c| !c.is_tag())
+        .map(|c| (**c).clone())
+        .collect();
+    if columns.first().map(|c| c.ty) != Some(Ty::Timestamp) {
+        return Err(TransferError::NoPrimaryKey(spec.source.clone()));
+    }
+    let translated = spec.translate(&columns)?;
+    if translated[0].ty != Ty::Timestamp {
+        return Err(TransferError::NoPrimaryKey(spec.destination.clone()));
+    }
+    dst.exec(
+        translated
+            .iter()
+            .cloned()
+            .map(ColumnMeta::Column)
+            .collect::<crate::common::Describe>()
+            .to_create_table_sql(&spec.destination),
+    )
+    .await
+    .map_err(query_error)?;
+    let src_precision = precision_of(src, &spec.source).await?;
+    let dst_precision = precision_of(dst, &spec.destination).await?;
+
+    let range = spec
+        .time_range
+        .unwrap_or_else(|| TimeRange::new(Bound::Unbounded, Bound::Unbounded));
+    let src_ts = quote_ident(&columns[0].field);
+    let bounds: Option<(Option<i64>, Option<i64>)> = src
+        .query_one(format!(
+            "select cast(first({src_ts}) as bigint), cast(last({src_ts}) as bigint) from {} where {}",
+            quote_ident(&spec.source),
+            range.to_sql_condition(&src_ts, src_precision)
+        ))
+        .await
+        .map_err(query_error)?;
+    let ranges = match bounds {
+        Some((Some(first), Some(last))) => {
+            split_range(first, last, spec.parallelism, src_precision)
+        }
+        _ => Vec::new(),
+    };
+
+    let copy = TableCopy {
+        src,
+        dst,
+        spec: &spec,
+        columns,
+        translated,
+        src_precision,
+        dst_precision,
+        ranges: ranges.len(),
+        rows: AtomicUsize::new(0),
+    };
+    let summaries: Vec<TransferSummary> = futures::stream::iter(ranges.into_iter().enumerate())
+        .map(|(index, range)| copy.copy_range(index, range))
+        .buffer_unordered(spec.parallelism)
+        .try_collect()
+        .await?;
+    let summary = summaries
+        .into_iter()
+        .fold(TransferSummary::default(), |sum, s| TransferSummary {
+            blocks: sum.blocks + s.blocks,
+            rows: sum.rows + s.rows,
+            skipped: sum.skipped + s.skipped,
+        });
+
+    if spec.verify {
+        let expected = count_rows(
+            src,
+            &spec.source,
+            &copy.columns[0].field,
+            &range,
+            src_precision,
+        );
+        let actual = count_rows(
+            dst,
+            &spec.destination,
+            &copy.translated[0].field,
+            &range,
+            dst_precision,
+        );
+        let (expected, actual) = futures::try_join!(expected, actual)?;
+        if expected != actual {
+            return Err(TransferError::Verify { expected, actual });
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Value;
+
+    const ROWS: usize = 300_000;
+    const BLOCK_ROWS: usize = 4096;
+
+    fn schema() -> TableSchema {
+        let column = |field: &str, ty, length| Described {
+            field: field.to_string(),
+            ty,
+            length,
+        };
+        TableSchema {
+            table: "t1".to_string(),
+            precision: Precision::Millisecond,
+            columns: vec![
+                column("ts", Ty::Timestamp, 8),
+                column("b", Ty::Bool, 1),
+                column("i", Ty::Int, 4),
+                column("u", Ty::UBigInt, 8),
+                column("d", Ty::Double, 8),
+                column("s", Ty::VarChar, 16),
+                column("n", Ty::NChar, 16),
+            ],
+            tags: vec![column("t", Ty::Int, 4)],
+        }
+    }
+
+    fn block(rows: std::ops::Range<usize>) -> RawBlock {
+        let views = [
+            ColumnView::from_millis_timestamp(rows.clone().map(|i| i as i64).collect()),
+            ColumnView::from_bools(rows.clone().map(|i| i % 2 == 0).collect()),
+            ColumnView::from_ints(
+                rows.clone()
+                    .map(|i| (i % 7 != 0).then_some(i as i32))
+                    .collect(),
+            ),
+            ColumnView::from_unsigned_big_ints(rows.clone().map(|i| i as u64).collect()),
+            ColumnView::from_doubles(rows.clone().map(|i| i as f64 / 2.).collect()),
+            ColumnView::from_varchar::<String, _, _, _>(
+                rows.clone().map(|i| format!("s{i}")).collect::<Vec<_>>(),
+            ),
+            ColumnView::from_nchar::<String, _, _, _>(
+                rows.map(|i| (i % 5 != 0).then(|| format!("n{i}")))
+                    .collect::<Vec<_>>(),
+            ),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        block.with_field_names(schema().columns.iter().map(|c| c.field.as_str()));
+        block
+    }
+
+    fn blocks() -> Vec<RawBlock> {
+        (0..ROWS)
+            .step_by(BLOCK_ROWS)
+            .map(|start| block(start..(start + BLOCK_ROWS).min(ROWS)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn dump_round_trip() {
+        let blocks = blocks();
+        // reading a nchar value decodes the column in place.
+        assert_eq!(
+            blocks[0].get_ref(1, 6).unwrap().to_value(),
+            Value::NChar("n1".to_string())
+        );
+        let mut sink = WriteSink::new(Vec::new());
+        sink.write_schema(&schema()).await.unwrap();
+        for block in &blocks {
+            sink.write_block(block).await.unwrap();
+        }
+        sink.finish().await.unwrap();
+        let bytes = sink.into_inner();
+
+        let mut source = ReadSource::new(bytes.as_slice());
+        assert_eq!(source.read_schema().await.unwrap(), schema());
+        let mut rows = 0;
+        let mut expected = blocks.iter();
+        while let Some(block) = source.read_block().await.unwrap() {
+            let expected = expected.next().unwrap();
+            assert_eq!(block.field_names(), expected.field_names());
+            assert_eq!(block.to_values(), expected.to_values());
+            rows += block.nrows();
+        }
+        assert!(expected.next().is_none());
+        assert_eq!(rows, ROWS);
+
+        assert!(matches!(
+            ReadSource::new(&bytes[1..]).read_schema().await,
+            Err(TransferError::Format(_))
+        ));
+        let mut truncated = ReadSource::new(&bytes[..bytes.len() - 10]);
+        truncated.read_schema().await.unwrap();
+        let err = loop {
+            match truncated.read_block().await {
+                Ok(Some(_)) => continue,
+                Ok(None) => unreachable!("truncated dump has no end"),
+                Err(err) => break err,
+            }
+        };
+        assert!(matches!(err, TransferError::Io(_)));
+    }
+
+    /// Dumps of version 1 have raw blocks.
+    #[tokio::test]
+    async fn read_v1_dump() {
+        let block = block(0..10);
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(1u32.to_le_bytes());
+        write_frame(&mut bytes, &serde_json::to_vec(&schema()).unwrap()).unwrap();
+        write_frame(&mut bytes, block.as_raw_bytes()).unwrap();
+        bytes.extend(0u32.to_le_bytes());
+
+        let mut source = ReadSource::new(bytes.as_slice());
+        source.read_schema().await.unwrap();
+        let read = source.read_block().await.unwrap().unwrap();
+        assert_eq!(read.to_values(), block.to_values());
+        assert!(source.read_block().await.unwrap().is_none());
+    }
+
+    #[test]
+    fn resume_after_last_timestamp() {
+        let first = BLOCK_ROWS;
+        let block = || block(first..first * 2);
+        let first = first as i64;
+
+        assert_eq!(rows_after(block(), None).unwrap().nrows(), BLOCK_ROWS);
+        assert_eq!(
+            rows_after(block(), Some(first - 1)).unwrap().nrows(),
+            BLOCK_ROWS
+        );
+        assert!(rows_after(block(), Some(first * 2)).is_none());
+
+        let rest = rows_after(block(), Some(first + 99)).unwrap();
+        assert_eq!(rest.nrows(), BLOCK_ROWS - 100);
+        assert_eq!(ts_at(&rest, 0), Some(first + 100));
+        assert_eq!(rest.field_names(), block().field_names());
+        let block = block();
+        for (rest, view) in rest.column_views().iter().zip(block.column_views()) {
+            assert_eq!(
+                rest.get(0).map(|v| v.to_value()),
+                view.get(100).map(|v| v.to_value())
+            );
+        }
+    }
+
+    #[test]
+    fn split_ranges() {
+        use chrono::{TimeZone, Utc};
+        let ms = |raw| Utc.timestamp_millis_opt(raw).unwrap();
+
+        let ranges = split_range(0, 9, 3, Precision::Millisecond);
+        assert_eq!(
+            ranges,
+            [
+                TimeRange::new(Bound::Included(ms(0)), Bound::Excluded(ms(4))),
+                TimeRange::new(Bound::Included(ms(4)), Bound::Excluded(ms(8))),
+                TimeRange::new(Bound::Included(ms(8)), Bound::Included(ms(9))),
+            ]
+        );
+        // never more ranges than ticks.
+        assert_eq!(split_range(5, 6, 4, Precision::Millisecond).len(), 2);
+        assert_eq!(
+            split_range(5, 5, 1, Precision::Millisecond),
+            [TimeRange::new(
+                Bound::Included(ms(5)),
+                Bound::Included(ms(5))
+            )]
+        );
+        let all = split_range(i64::MIN, i64::MAX, 7, Precision::Nanosecond);
+        assert_eq!(all.len(), 7);
+    }
+
+    #[test]
+    fn copy_translation() {
+        let columns = schema().columns;
+        let spec = CopySpec::new("t1", "t2")
+            .rename("i", "i2")
+            .cast("i", Ty::BigInt, 0)
+            .cast("s", Ty::VarChar, 64);
+        let translated = spec.translate(&columns).unwrap();
+        assert_eq!(translated.len(), columns.len());
+        assert_eq!(
+            (
+                translated[2].field.as_str(),
+                translated[2].ty,
+                translated[2].length
+            ),
+            ("i2", Ty::BigInt, 8)
+        );
+        assert_eq!((translated[5].ty, translated[5].length), (Ty::VarChar, 64));
+        assert_eq!(translated[6], columns[6]);
+
+        let err = CopySpec::new("t1", "t2")
+            .rename("x", "y")
+            .translate(&columns)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "column `x` is not in table `t1`");
+
+        let source = block(0..10);
+        let copied =
+            translate_block(&source, &columns, &translated, Precision::Microsecond).unwrap();
+        assert_eq!(copied.precision(), Precision::Microsecond);
+        assert_eq!(copied.field_names()[2], "i2");
+        let value = |block: &RawBlock, row, col| block.get_ref(row, col).unwrap().to_value();
+        for row in 0..10 {
+            assert_eq!(
+                value(&copied, row, 0),
+                Value::Timestamp(Timestamp::Microseconds(row as i64 * 1000))
+            );
+            let expected = match value(&source, row, 2) {
+                Value::Int(v) => Value::BigInt(v as i64),
+                _ => Value::Null(Ty::BigInt),
+            };
+            assert_eq!(value(&copied, row, 2), expected);
+            assert_eq!(value(&copied, row, 6), value(&source, row, 6));
+        }
+
+        let narrowed = CopySpec::new("t1", "t2")
+            .cast("u", Ty::TinyInt, 0)
+            .translate(&columns)
+            .unwrap();
+        let err = translate_block(
+            &block(100..200),
+            &columns,
+            &narrowed,
+            Precision::Millisecond,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            TransferError::Convert(ConvertError { row: 28, .. })
+        ));
+    }
+
+    #[test]
+    fn create_table_sql() {
+        assert_eq!(
+            schema().to_create_table_sql("t2"),
+            "create table if not exists `t2` (`ts` TIMESTAMP,`b` BOOL,`i` INT,`u` BIGINT UNSIGNED,\
+            `d` DOUBLE,`s` BINARY(16),`n` NCHAR(16))"
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Windows of writes settled right below a breached size before probing above it.
+const PROBE_AFTER_WINDOWS: usize = 5;
+
+/// Bounds and targets of [AdaptiveBatcher].
+///
+/// The batch size starts at `initial`, grows by `growth` times while the p95 latency of the last
+/// `samples` writes is within `target_latency`, and shrinks on timeouts by `backoff` times or on
+/// latency breaches in proportion to the breach, always within `min` and `max` rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveBatch {
+    /// Rows of the first batch.
+    pub initial: usize,
+    /// Floor of the batch size.
+    pub min: usize,
+    /// Ceiling of the batch size.
+    pub max: usize,
+    /// Max p95 latency of writes.
+    pub target_latency: Duration,
+    /// Multiplier to grow the batch size, above 1.
+    pub growth: f64,
+    /// Multiplier to shrink the batch size on timeouts, below 1.
+    pub backoff: f64,
+    /// Writes of a batch size to measure the p95 latency of before resizing.
+    pub samples: usize,
+}
+
+impl Default for AdaptiveBatch {
+    fn default() -> Self {
+        Self {
+            initial: 1000,
+            min: 100,
+            max: 100_000,
+            target_latency: Duration::from_secs(1),
+            growth: 2.,
+            backoff: 0.5,
+            samples: 10,
+        }
+    }
+}
+
+impl AdaptiveBatch {
+    /// Set the target latency, other settings are defaults.
+    pub fn target_latency(latency: Duration) -> Self {
+        Self {
+            target_latency: latency,
+            ..Default::default()
+        }
+    }
+
+    /// Set the floor and ceiling of the batch size.
+    pub fn with_bounds(mut self, min: usize, max: usize) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Set the rows of the first batch.
+    pub fn with_initial(mut self, initial: usize) -> Self {
+        self.initial = initial;
+        self
+    }
+}
+
+/// Result of a write fed to [AdaptiveBatcher::record].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Written,
+    TimedOut,
+    /// Failed other than timeouts, which does not tell the capacity of the target.
+    Failed,
+}
+
+/// Why [AdaptiveBatcher] shrinks the batch size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShrinkCause {
+    Timeout,
+    /// The p95 latency of the batch size is over the target.
+    Latency(Duration),
+}
+
+/// A decision of [AdaptiveBatcher] on a write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchDecision {
+    /// The p95 latency is within the target.
+    Grow {
+        from: usize,
+        to: usize,
+        p95: Duration,
+    },
+    Shrink {
+        from: usize,
+        to: usize,
+        cause: ShrinkCause,
+    },
+    /// Keep the batch size, until enough writes of it are measured or at a bound.
+    Hold { size: usize },
+}
+
+impl BatchDecision {
+    /// The batch size after the decision.
+    pub fn size(&self) -> usize {
+        match *self {
+            Self::Grow { to, .. } | Self::Shrink { to, .. } => to,
+            Self::Hold { size } => size,
+        }
+    }
+}
+
+/// Receives all decisions of [AdaptiveBatcher], eg. to export them as metrics.
+#[derive(Clone)]
+pub struct BatchHook(Arc<dyn Fn(&BatchDecision) + Send + Sync>);
+
+impl BatchHook {
+    pub fn new(f: impl Fn(&BatchDecision) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl Debug for BatchHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BatchHook")
+    }
+}
+
+/// Batch size controller of writes driven by their latency, see [AdaptiveBatch].
+///
+/// It's fed the batch size, latency and outcome of each write. Writes of a size other than the
+/// current one, eg. sent before the last resize, are ignored except timeouts. After a latency
+/// breach, growing stops halfway to the breached size, so the size settles below it. Once it's
+/// settled for a while, it probes an eighth of the growth above, 12.5% by default, and grows as
+/// usual from there if the probe is within the target, eg. the target got faster.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use taos_query::util::{AdaptiveBatch, AdaptiveBatcher, WriteOutcome};
+/// let config = AdaptiveBatch::target_latency(Duration::from_millis(100)).with_initial(1000);
+/// let mut batcher = AdaptiveBatcher::new(AdaptiveBatch { samples: 1, ..config });
+/// batcher.record(1000, Duration::from_millis(20), WriteOutcome::Written);
+/// assert_eq!(batcher.batch_size(), 2000);
+/// batcher.record(2000, Duration::from_secs(30), WriteOutcome::TimedOut);
+/// assert_eq!(batcher.batch_size(), 1000);
+/// ```
+#[derive(Debug)]
+pub struct AdaptiveBatcher {
+    config: AdaptiveBatch,
+    size: usize,
+    /// Latencies of writes of the current size.
+    latencies: Vec<Duration>,
+    /// The smallest size that breached the target.
+    breached: Option<usize>,
+    /// Windows of writes within the target right below the breached size.
+    settled: usize,
+    hook: Option<BatchHook>,
+}
+
+impl AdaptiveBatcher {
+    pub fn new(config: AdaptiveBatch) -> Self {
+        let min = config.min.max(1);
+        Self {
+            size: config.initial.clamp(min, config.max.max(min)),
+            latencies: Vec::with_capacity(config.samples),
+            breached: None,
+            settled: 0,
+            hook: None,
+            config,
+        }
+    }
+
+    /// Report decisions to `hook`.
+    pub fn with_hook(mut self, hook: BatchHook) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    pub fn config(&self) -> &AdaptiveBatch {
+        &self.config
+    }
+
+    /// Rows of the next batch.
+    pub fn batch_size(&self) -> usize {
+        self.size
+    }
+
+    /// Feed a write of `batch_size` rows, returns the decision, which is also reported to the
+    /// hook.
+    pub fn record(
+        &mut self,
+        batch_size: usize,
+        latency: Duration,
+        outcome: WriteOutcome,
+    ) -> BatchDecision {
+        let decision = self.decide(batch_size, latency, outcome);
+        if decision.size() != self.size {
+            log::debug!("adaptive batch: {decision:?}");
+            self.size = decision.size();
+            self.latencies.clear();
+            self.settled = 0;
+        }
+        if let Some(hook) = &self.hook {
+            (hook.0)(&decision);
+        }
+        decision
+    }
+
+    fn decide(
+        &mut self,
+        batch_size: usize,
+        latency: Duration,
+        outcome: WriteOutcome,
+    ) -> BatchDecision {
+        let size = self.size;
+        let hold = BatchDecision::Hold { size };
+        match outcome {
+            WriteOutcome::Failed => return hold,
+            WriteOutcome::TimedOut => {
+                self.breach(batch_size);
+                let to = self.bounded(size as f64 * self.config.backoff);
+                return self.shrink(to, ShrinkCause::Timeout);
+            }
+            WriteOutcome::Written if batch_size != size => return hold,
+            WriteOutcome::Written => self.latencies.push(latency),
+        }
+        if self.latencies.len() < self.config.samples.max(1) {
+            return hold;
+        }
+        let p95 = p95(&mut self.latencies);
+        let target = self.config.target_latency;
+        if p95 > target {
+            self.breach(size);
+            // latency is about linear in rows, scale the size to the target but not below backoff.
+            let ratio = target.as_secs_f64() / p95.as_secs_f64();
+            let to = self.bounded(size as f64 * ratio.max(self.config.backoff));
+            return self.shrink(
+                to.min(size.saturating_sub(1)).max(self.min()),
+                ShrinkCause::Latency(p95),
+            );
+        }
+        let mut to = self.bounded(size as f64 * self.config.growth);
+        if let Some(breached) = self.breached {
+            if breached.saturating_sub(size) >= 2 {
+                to = to.min(size + (breached - size) / 2);
+            } else if self.settled + 1 < PROBE_AFTER_WINDOWS {
+                self.settled += 1;
+                self.latencies.clear();
+                return hold;
+            } else {
+                // settled right below the breach for a while, probe above it in case the target
+                // got faster, it grows as usual if the probe is within the target.
+                self.breached = None;
+                to = self.bounded(size as f64 * (1. + (self.config.growth - 1.) / 8.));
+            }
+        }
+        if to <= size {
+            self.latencies.clear();
+            return hold;
+        }
+        BatchDecision::Grow {
+            from: size,
+            to,
+            p95,
+        }
+    }
+
+    fn shrink(&self, to: usize, cause: ShrinkCause) -> BatchDecision {
+        if to < self.size {
+            BatchDecision::Shrink {
+                from: self.size,
+                to,
+                cause,
+            }
+        } else {
+            BatchDecision::Hold { size: self.size }
+        }
+    }
+
+    fn breach(&mut self, size: usize) {
+        self.breached = Some(self.breached.map_or(size, |breached| breached.min(size)));
+    }
+
+    fn min(&self) -> usize {
+        self.config.min.max(1)
+    }
+
+    fn bounded(&self, size: f64) -> usize {
+        let max = self.config.max.max(self.min());
+        (size.round() as usize).clamp(self.min(), max)
+    }
+}
+
+/// The 95th percentile of `latencies`, which are sorted in place.
+fn p95(latencies: &mut [Duration]) -> Duration {
+    latencies.sort_unstable();
+    let rank = latencies.len() - latencies.len() / 20;
+    latencies[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// A target writing `rows_per_ms` rows per millisecond after a fixed overhead, timing out
+    /// after 1 second.
+    struct Target {
+        overhead: Duration,
+        rows_per_ms: f64,
+    }
+
+    impl Target {
+        fn write(&self, rows: usize, jitter: f64) -> (Duration, WriteOutcome) {
+            let ms = rows as f64 / self.rows_per_ms * jitter;
+            let latency = self.overhead + Duration::from_secs_f64(ms / 1000.);
+            if latency > Duration::from_secs(1) {
+                (Duration::from_secs(1), WriteOutcome::TimedOut)
+            } else {
+                (latency, WriteOutcome::Written)
+            }
+        }
+    }
+
+    /// Write `writes` batches to `target` with jitter of up to 10%, returns the batch sizes.
+    fn simulate(batcher: &mut AdaptiveBatcher, target: &Target, writes: usize) -> Vec<usize> {
+        let mut jitter = 0.9;
+        (0..writes)
+            .map(|_| {
+                jitter = if jitter >= 1.0 { 0.9 } else { jitter + 0.01 };
+                let size = batcher.batch_size();
+                let (latency, outcome) = target.write(size, jitter);
+                batcher.record(size, latency, outcome);
+                size
+            })
+            .collect()
+    }
+
+    /// Most `sizes` are within 10% of `capacity`, the rows written in the target latency, the
+    /// others are probes about 12.5% over it.
+    fn assert_settled(sizes: &[usize], capacity: usize) {
+        let near = sizes
+            .iter()
+            .filter(|&&s| (capacity * 9..=capacity * 11).contains(&(s * 10)))
+            .count();
+        assert!(near * 10 >= sizes.len() * 8, "{sizes:?}");
+        assert!(
+            sizes.iter().all(|&s| s * 100 <= capacity * 113),
+            "{sizes:?}"
+        );
+    }
+
+    #[test]
+    fn adaptive_batch_converges() {
+        // 100 rows per ms after 5ms, 9500 rows are written in 100ms.
+        let target = Target {
+            overhead: Duration::from_millis(5),
+            rows_per_ms: 100.,
+        };
+        let config = AdaptiveBatch::target_latency(Duration::from_millis(100))
+            .with_initial(500)
+            .with_bounds(100, 1_000_000);
+        let mut batcher = AdaptiveBatcher::new(config);
+        let sizes = simulate(&mut batcher, &target, 600);
+        assert_eq!(sizes[..10], [500; 10]);
+        assert_eq!(sizes[10], 1000);
+        assert_settled(&sizes[300..], 9500);
+
+        // the target slows down 4 times, it settles again at 2375 rows.
+        let slow = Target {
+            rows_per_ms: 25.,
+            ..target
+        };
+        let sizes = simulate(&mut batcher, &slow, 600);
+        assert_settled(&sizes[300..], 2375);
+
+        // and speeds up 4 times again.
+        let sizes = simulate(&mut batcher, &target, 600);
+        assert_settled(&sizes[300..], 9500);
+    }
+
+    #[test]
+    fn adaptive_batch_bounds() {
+        let fast = Target {
+            overhead: Duration::ZERO,
+            rows_per_ms: 1e6,
+        };
+        let config = AdaptiveBatch {
+            samples: 2,
+            ..AdaptiveBatch::target_latency(Duration::from_millis(100)).with_bounds(100, 5000)
+        };
+        let mut batcher = AdaptiveBatcher::new(config);
+        let sizes = simulate(&mut batcher, &fast, 20);
+        assert_eq!(sizes[..8], [1000, 1000, 2000, 2000, 4000, 4000, 5000, 5000]);
+        assert_eq!(batcher.batch_size(), 5000);
+
+        // timeouts shrink by half down to the floor, failures and stale writes are ignored.
+        let timeout = Duration::from_secs(1);
+        let decision = batcher.record(5000, timeout, WriteOutcome::TimedOut);
+        assert_eq!(
+            decision,
+            BatchDecision::Shrink {
+                from: 5000,
+                to: 2500,
+                cause: ShrinkCause::Timeout
+            }
+        );
+        for _ in 0..10 {
+            batcher.record(5000, timeout, WriteOutcome::TimedOut);
+        }
+        assert_eq!(batcher.batch_size(), 100);
+        let hold = BatchDecision::Hold { size: 100 };
+        assert_eq!(batcher.record(100, timeout, WriteOutcome::Failed), hold);
+        assert_eq!(
+            batcher.record(5000, Duration::ZERO, WriteOutcome::Written),
+            hold
+        );
+
+        let batcher = AdaptiveBatcher::new(AdaptiveBatch::default().with_initial(0));
+        assert_eq!(batcher.batch_size(), 100);
+    }
+
+    #[test]
+    fn adaptive_batch_hook() {
+        let decisions = Arc::new(Mutex::new(Vec::new()));
+        let hook = {
+            let decisions = decisions.clone();
+            BatchHook::new(move |d| decisions.lock().unwrap().push(*d))
+        };
+        let config = AdaptiveBatch {
+            samples: 2,
+            ..AdaptiveBatch::target_latency(Duration::from_millis(100)).with_initial(1000)
+        };
+        let mut batcher = AdaptiveBatcher::new(config).with_hook(hook);
+        let ms = Duration::from_millis;
+        batcher.record(1000, ms(20), WriteOutcome::Written);
+        batcher.record(1000, ms(30), WriteOutcome::Written);
+        batcher.record(2000, ms(150), WriteOutcome::Written);
+        batcher.record(2000, ms(250), WriteOutcome::Written);
+        assert_eq!(
+            *decisions.lock().unwrap(),
+            [
+                BatchDecision::Hold { size: 1000 },
+                BatchDecision::Grow {
+                    from: 1000,
+                    to: 2000,
+                    p95: ms(30)
+                },
+                BatchDecision::Hold { size: 2000 },
+                BatchDecision::Shrink {
+                    from: 2000,
+                    to: 1000,
+                    cause: ShrinkCause::Latency(ms(250))
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use taos_error::Code;
+
+/// A statement recorded by [AuditLog].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Time the statement started.
+    pub at: SystemTime,
+    /// The sql after redaction.
+    pub sql: String,
+    pub duration: Duration,
+    /// Affected rows, `None` for queries with a result set or failed statements.
+    pub rows: Option<usize>,
+    /// Error code, `None` if the statement succeeded.
+    pub code: Option<Code>,
+}
+
+/// Rewrites sql before it's recorded by [AuditLog], eg. to mask passwords or literals.
+#[derive(Clone)]
+pub struct Redactor(Arc<dyn Fn(&str) -> String + Send + Sync>);
+
+impl Redactor {
+    pub fn new(f: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn redact(&self, sql: &str) -> String {
+        (self.0)(sql)
+    }
+}
+
+impl Debug for Redactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Redactor")
+    }
+}
+
+/// The last statements executed by a connection, kept in a ring buffer of `capacity`
+/// entries, set by `TaosBuilder::audit_log`.
+///
+/// Entries are built and redacted before the lock is taken, which is only held to push one.
+#[derive(Debug)]
+pub struct AuditLog {
+    capacity: usize,
+    redactor: Option<Redactor>,
+    entries: Mutex<VecDeque<AuditEntry>>,
+}
+
+impl AuditLog {
+    pub fn new(capacity: usize, redactor: Option<Redactor>) -> Self {
+        Self {
+            capacity,
+            redactor,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Record a statement started at `started`, with affected rows or the error code.
+    pub fn record(&self, sql: &str, started: Instant, outcome: Result<Option<usize>, Code>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let duration = started.elapsed();
+        let (rows, code) = match outcome {
+            Ok(rows) => (rows, None),
+            Err(code) => (None, Some(code)),
+        };
+        let entry = AuditEntry {
+            at: SystemTime::now() - duration,
+            sql: self
+                .redactor
+                .as_ref()
+                .map_or_else(|| sql.to_string(), |r| r.redact(sql)),
+            duration,
+            rows,
+            code,
+        };
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Recorded entries, oldest first.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Log all entries as errors, eg. when the connection fails.
+    pub fn dump(&self) {
+        let entries = self.entries();
+        log::error!("last {} statements of the connection:", entries.len());
+        for entry in entries {
+            let at = chrono::DateTime::<chrono::Local>::from(entry.at);
+            log::error!(
+                "[{}] {:?} rows: {:?}, code: {}, sql: {}",
+                at.to_rfc3339(),
+                entry.duration,
+                entry.rows,
+                entry
+                    .code
+                    .map_or_else(|| "ok".to_string(), |c| c.to_string()),
+                entry.sql
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eviction() {
+        let log = AuditLog::new(3, None);
+        let started = Instant::now();
+        for i in 0..5 {
+            log.record(
+                &format!("insert into t{i} values(now, 1)"),
+                started,
+                Ok(Some(1)),
+            );
+        }
+        log.record("select * from t", started, Err(Code::new(0x2603)));
+        let entries = log.entries();
+        let sqls: Vec<_> = entries.iter().map(|e| e.sql.as_str()).collect();
+        assert_eq!(
+            sqls,
+            [
+                "insert into t3 values(now, 1)",
+                "insert into t4 values(now, 1)",
+                "select * from t"
+            ]
+        );
+        assert_eq!(entries[0].rows, Some(1));
+        assert_eq!(entries[0].code, None);
+        assert_eq!(entries[2].rows, None);
+        assert_eq!(entries[2].code, Some(Code::new(0x2603)));
+        assert!(entries.iter().all(|e| e.at <= SystemTime::now()));
+        log.dump();
+
+        let disabled = AuditLog::new(0, None);
+        disabled.record("select 1", started, Ok(None));
+        assert!(disabled.entries().is_empty());
+    }
+
+    #[test]
+    fn redaction() {
+        let redactor = Redactor::new(|sql| match sql.find(" pass ") {
+            Some(i) => format!("{} pass '***'", &sql[..i]),
+            None => sql.to_string(),
+        });
+        let log = AuditLog::new(2, Some(redactor));
+        log.record("create user u1 pass 'secret'", Instant::now(), Ok(Some(0)));
+        log.record("select 1", Instant::now(), Ok(None));
+        let sqls: Vec<_> = log.entries().into_iter().map(|e| e.sql).collect();
+        assert_eq!(sqls, ["create user u1 pass '***'", "select 1"]);
+    }
+}
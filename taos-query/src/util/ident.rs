@@ -0,0 +1,107 @@
+use std::borrow::Cow;
+
+/// Check if `name` is already a backtick quoted identifier, where every inner backtick is
+/// escaped by doubling it.
+fn is_quoted(name: &str) -> bool {
+    let inner = match name
+        .strip_prefix('`')
+        .and_then(|name| name.strip_suffix('`'))
+    {
+        Some(inner) => inner,
+        None => return false,
+    };
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '`' && chars.next() != Some('`') {
+            return false;
+        }
+    }
+    true
+}
+
+/// Quote a database, table, column or topic name with backticks, for use in sql.
+///
+/// Quoted names keep their case and may contain any characters, like dots and dashes in
+/// device ids. Embedded backticks are escaped by doubling them. Names already quoted are
+/// returned as is, so quoting twice is harmless.
+///
+/// ```rust
+/// # use taos_query::util::quote_ident;
+/// assert_eq!(quote_ident("d1001"), "`d1001`");
+/// assert_eq!(quote_ident("Dev-1.a"), "`Dev-1.a`");
+/// assert_eq!(quote_ident("a`b"), "`a``b`");
+/// assert_eq!(quote_ident("`a``b`"), "`a``b`");
+/// ```
+pub fn quote_ident(name: &str) -> Cow<'_, str> {
+    if is_quoted(name) {
+        return Cow::Borrowed(name);
+    }
+    let mut quoted = String::with_capacity(name.len() + 2);
+    quoted.push('`');
+    for c in name.chars() {
+        if c == '`' {
+            quoted.push('`');
+        }
+        quoted.push(c);
+    }
+    quoted.push('`');
+    Cow::Owned(quoted)
+}
+
+/// Quote a table name qualified by the database, as `` `db`.`table` ``.
+///
+/// ```rust
+/// # use taos_query::util::quote_table_ref;
+/// assert_eq!(quote_table_ref("power", "d1001.A"), "`power`.`d1001.A`");
+/// ```
+pub fn quote_table_ref(db: &str, table: &str) -> String {
+    format!("{}.{}", quote_ident(db), quote_ident(table))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    /// Reverse of [quote_ident].
+    fn unquote(quoted: &str) -> String {
+        quoted[1..quoted.len() - 1].replace("``", "`")
+    }
+
+    #[test]
+    fn quote_hostile_idents() {
+        for name in [
+            "", "`", "``", "```", "a`", "`a", "`a`b`", "a.b", "db.`tb`", "A-b", "x y", "中文",
+            "'\"\\;--",
+        ] {
+            let quoted = quote_ident(name);
+            assert!(is_quoted(&quoted), "{name} => {quoted}");
+            if !is_quoted(name) {
+                assert_eq!(unquote(&quoted), name);
+            }
+            assert_eq!(quote_ident(&quoted), quoted);
+        }
+        assert!(matches!(quote_ident("`tb`"), Cow::Borrowed("`tb`")));
+        assert_eq!(quote_ident("`a`b`"), "```a``b```");
+        assert_eq!(quote_table_ref("db", "`tb`"), "`db`.`tb`");
+    }
+
+    #[test]
+    fn quote_random_idents() {
+        const CHARS: &[char] = &['`', '.', '-', ' ', 'a', 'Z', '0', '\'', '"', '\\', '中'];
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let len = rng.gen_range(0..8);
+            let name: String = (0..len)
+                .map(|_| CHARS[rng.gen_range(0..CHARS.len())])
+                .collect();
+            let quoted = quote_ident(&name);
+            assert!(is_quoted(&quoted), "{name} => {quoted}");
+            assert_eq!(quote_ident(&quoted), quoted);
+            if !is_quoted(&name) {
+                assert_eq!(unquote(&quoted), name);
+            }
+        }
+    }
+}
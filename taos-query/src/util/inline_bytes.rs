@@ -50,7 +50,7 @@ macro_rules! _impl_inline_lines {
 
                 #[inline]
                 pub const fn len(&self) -> usize {
-                    self.len as _
+                    <$ty>::from_le(self.len) as _
                 }
 
                 #[inline]
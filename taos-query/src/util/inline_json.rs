@@ -39,7 +39,7 @@ macro_rules! _impl_inline_str {
             impl Inlinable for InlineJson<$ty> {
                 #[inline]
                 fn write_inlined<W: std::io::Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
-                    let l = wtr.write(&self.len.to_le_bytes())?;
+                    let l = wtr.write(&(self.len() as $ty).to_le_bytes())?;
                     Ok(l + wtr.write(self.as_bytes())?)
                 }
 
@@ -84,7 +84,7 @@ macro_rules! _impl_inline_str {
 
                 #[inline]
                 pub const fn len(&self) -> usize {
-                    self.len as _
+                    <$ty>::from_le(self.len) as _
                 }
             }
         )*
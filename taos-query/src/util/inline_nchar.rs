@@ -47,7 +47,7 @@ macro_rules! _impl_inline_str {
             impl Inlinable for InlineNChar<$ty> {
                 #[inline]
                 fn write_inlined<W: std::io::Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
-                    let l = wtr.write(&self.len.to_le_bytes())?;
+                    let l = wtr.write(&(self.len() as $ty).to_le_bytes())?;
                     Ok(l + wtr.write(self.as_bytes())?)
                 }
 
@@ -69,12 +69,21 @@ macro_rules! _impl_inline_str {
                 }
                 #[inline]
                 pub fn to_string(&self) -> String {
-                    self.chars().iter().collect()
+                    (0..self.chars_len()).map(|i| unsafe { self.char_unchecked(i) }).collect()
+                }
+
+                /// Char at `index`, read unaligned, the data of a raw block is not aligned for
+                /// [char] after columns of odd sizes.
+                #[inline]
+                unsafe fn char_unchecked(&self, index: usize) -> char {
+                    let ptr = self.data.as_ptr().add(index * std::mem::size_of::<char>());
+                    let c = u32::from_le(std::ptr::read_unaligned(ptr as *const u32));
+                    char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER)
                 }
 
                 #[inline]
                 pub const fn len(&self) -> usize {
-                    self.len as _
+                    <$ty>::from_le(self.len) as _
                 }
                 #[inline]
                 pub const fn chars_len(&self) -> usize {
@@ -95,9 +104,11 @@ macro_rules! _impl_inline_str {
                     }
                     let v: &mut super::InlineStr<$ty> = std::mem::transmute(self);
                     let ptr = self.data.as_ptr() as *mut u8;
-                    let chars = self.chars();
                     let mut len = 0usize;
-                    for c in chars {
+                    // utf-8 of a char is never longer than the char, so a char is read before
+                    // it's overwritten.
+                    for i in 0..self.chars_len() {
+                        let c = self.char_unchecked(i);
                         let mut b = [0; 4];
                         let s = c.encode_utf8(&mut b);
                         // dbg!(c, &s);
@@ -11,14 +11,11 @@ pub trait AsyncInlinableRead: AsyncRead + Unpin + Send {
     async fn read_len_with_width<const N: usize>(&mut self) -> std::io::Result<usize> {
         let mut bytes: [u8; N] = [0; N];
         self.read_exact(&mut bytes).await?;
-        let len = match N {
-            1 => bytes[0] as usize,
-            2 => unsafe { *std::mem::transmute::<*const u8, *const u16>(bytes.as_ptr()) as usize },
-            4 => unsafe { *std::mem::transmute::<*const u8, *const u32>(bytes.as_ptr()) as usize },
-            8 => unsafe { *std::mem::transmute::<*const u8, *const u64>(bytes.as_ptr()) as usize },
-            _ => unreachable!(),
-        };
-        Ok(len)
+        debug_assert!(matches!(N, 1 | 2 | 4 | 8));
+        // Lengths are little-endian.
+        let mut len = [0; 8];
+        len[..N].copy_from_slice(&bytes);
+        Ok(u64::from_le_bytes(len) as usize)
     }
 
     #[inline]
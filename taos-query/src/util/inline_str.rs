@@ -40,7 +40,7 @@ macro_rules! _impl_inline_str {
             impl super::Inlinable for InlineStr<$ty> {
                 #[inline]
                 fn write_inlined<W: std::io::Write>(&self, wtr: &mut W) -> std::io::Result<usize> {
-                    let l = wtr.write(&self.len.to_le_bytes())?;
+                    let l = wtr.write(&(self.len() as $ty).to_le_bytes())?;
                     Ok(l + wtr.write(self.as_bytes())?)
                 }
 
@@ -54,7 +54,7 @@ macro_rules! _impl_inline_str {
             impl super::AsyncInlinable for InlineStr<$ty> {
                 #[inline]
                 async fn write_inlined<W: AsyncWrite + Unpin + Send>(&self, wtr: &mut W) -> std::io::Result<usize> {
-                    let l = wtr.write(&self.len.to_le_bytes()).await?;
+                    let l = wtr.write(&(self.len() as $ty).to_le_bytes()).await?;
                     Ok(l + wtr.write(self.as_bytes()).await?)
                 }
 
@@ -91,7 +91,7 @@ macro_rules! _impl_inline_str {
 
                 #[inline]
                 pub const fn len(&self) -> usize {
-                    self.len as _
+                    <$ty>::from_le(self.len) as _
                 }
 
                 #[inline]
@@ -101,7 +101,7 @@ macro_rules! _impl_inline_str {
 
                 #[inline]
                 pub(crate) unsafe fn set_len(&mut self, len: usize) {
-                    self.len = len as _;
+                    self.len = (len as $ty).to_le();
                 }
             }
         )*
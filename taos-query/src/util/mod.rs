@@ -1,7 +1,13 @@
+mod adaptive_batch;
+mod audit;
+mod ident;
 mod inline_bytes;
 mod inline_json;
 mod inline_nchar;
 mod inline_str;
+mod rate_limit;
+mod req_id;
+mod shutdown;
 
 mod inline_read;
 mod inline_write;
@@ -13,10 +19,18 @@ use std::{
 
 use tokio::io::{AsyncRead, AsyncWrite};
 
+pub use adaptive_batch::{
+    AdaptiveBatch, AdaptiveBatcher, BatchDecision, BatchHook, ShrinkCause, WriteOutcome,
+};
+pub use audit::{AuditEntry, AuditLog, Redactor};
+pub use ident::{quote_ident, quote_table_ref};
 pub use inline_bytes::InlineBytes;
 pub use inline_json::InlineJson;
 pub use inline_nchar::InlineNChar;
 pub use inline_str::InlineStr;
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use req_id::generate_req_id;
+pub use shutdown::{Shutdown, ShutdownListener};
 
 pub use inline_read::AsyncInlinableRead;
 pub use inline_write::AsyncInlinableWrite;
@@ -125,14 +139,11 @@ pub trait InlinableRead: Read {
     fn read_len_with_width<const N: usize>(&mut self) -> std::io::Result<usize> {
         let mut bytes: [u8; N] = [0; N];
         self.read_exact(&mut bytes)?;
-        let len = match N {
-            1 => bytes[0] as usize,
-            2 => unsafe { *std::mem::transmute::<*const u8, *const u16>(bytes.as_ptr()) as usize },
-            4 => unsafe { *std::mem::transmute::<*const u8, *const u32>(bytes.as_ptr()) as usize },
-            8 => unsafe { *std::mem::transmute::<*const u8, *const u64>(bytes.as_ptr()) as usize },
-            _ => unreachable!(),
-        };
-        Ok(len)
+        debug_assert!(matches!(N, 1 | 2 | 4 | 8));
+        // Lengths are little-endian.
+        let mut len = [0; 8];
+        len[..N].copy_from_slice(&bytes);
+        Ok(u64::from_le_bytes(len) as usize)
     }
 
     fn read_f32(&mut self) -> std::io::Result<f32> {
@@ -348,3 +359,30 @@ fn inlined_bytes() -> std::io::Result<()> {
     assert_eq!(r, "abcd");
     Ok(())
 }
+
+#[test]
+fn read_len_little_endian() -> std::io::Result<()> {
+    let bytes = [0x04, 0x03, 0x02, 0x01, 0, 0, 0, 0];
+    assert_eq!(
+        InlinableRead::read_len_with_width::<1>(&mut bytes.as_slice())?,
+        0x04
+    );
+    assert_eq!(
+        InlinableRead::read_len_with_width::<2>(&mut bytes.as_slice())?,
+        0x0304
+    );
+    assert_eq!(
+        InlinableRead::read_len_with_width::<4>(&mut bytes.as_slice())?,
+        0x01020304
+    );
+    assert_eq!(
+        InlinableRead::read_len_with_width::<8>(&mut bytes.as_slice())?,
+        0x01020304
+    );
+
+    let inlined = unsafe { InlineStr::<u16>::from_ptr(b"\x03\x00abc".as_ptr()) };
+    assert_eq!(inlined.len(), 3);
+    assert_eq!(inlined.as_str(), "abc");
+    assert_eq!(Inlinable::inlined(inlined), b"\x03\x00abc");
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Client side throughput cap of writes, by rows and/or bytes per second.
+///
+/// Unset rates are unlimited. `burst` is the budget that can be spent at once after being idle,
+/// in seconds of the rates, 1 second by default. A zero burst spreads writes evenly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Max rows per second.
+    pub rows_per_sec: Option<u64>,
+    /// Max bytes per second.
+    pub bytes_per_sec: Option<u64>,
+    /// Budget of rows and bytes that can be written at once.
+    pub burst: Option<Duration>,
+}
+
+impl RateLimit {
+    /// Limit rows only.
+    pub fn rows_per_sec(rows: u64) -> Self {
+        Self {
+            rows_per_sec: Some(rows),
+            ..Default::default()
+        }
+    }
+
+    /// Limit bytes only.
+    pub fn bytes_per_sec(bytes: u64) -> Self {
+        Self {
+            bytes_per_sec: Some(bytes),
+            ..Default::default()
+        }
+    }
+
+    /// Set the burst budget.
+    pub fn with_burst(mut self, burst: Duration) -> Self {
+        self.burst = Some(burst);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    capacity: f64,
+    /// Tokens available, negative when writes are waiting for the budget.
+    tokens: f64,
+}
+
+impl Bucket {
+    fn new(rate: u64, burst: Duration) -> Self {
+        let rate = rate.max(1) as f64;
+        let capacity = rate * burst.as_secs_f64();
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.tokens = (self.tokens + self.rate * elapsed.as_secs_f64()).min(self.capacity);
+    }
+
+    /// Take `cost` tokens, returns the time to wait until they are earned.
+    fn take(&mut self, cost: usize) -> Duration {
+        self.tokens -= cost as f64;
+        if self.tokens < 0. {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    fn utilization(&self) -> f64 {
+        if self.capacity > 0. {
+            (self.capacity - self.tokens) / self.capacity
+        } else {
+            -self.tokens / self.rate
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    rows: Option<Bucket>,
+    bytes: Option<Bucket>,
+    updated: Instant,
+}
+
+impl Buckets {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.updated;
+        self.updated = now;
+        self.rows.iter_mut().for_each(|b| b.refill(elapsed));
+        self.bytes.iter_mut().for_each(|b| b.refill(elapsed));
+    }
+}
+
+/// Token bucket of a [RateLimit], shared by the connections of a builder.
+///
+/// Writes take their rows and bytes from the bucket before being dispatched. A write over the
+/// budget is not rejected or split, it waits until the budget is earned back, so a write larger
+/// than the burst is allowed and delays the following ones.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        let burst = limit.burst.unwrap_or(Duration::from_secs(1));
+        Self {
+            limit,
+            buckets: Mutex::new(Buckets {
+                rows: limit.rows_per_sec.map(|rate| Bucket::new(rate, burst)),
+                bytes: limit.bytes_per_sec.map(|rate| Bucket::new(rate, burst)),
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn limit(&self) -> &RateLimit {
+        &self.limit
+    }
+
+    /// Take the budget of a write, returns how long it must wait before being dispatched.
+    pub fn reserve(&self, rows: usize, bytes: usize) -> Duration {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.refill();
+        let rows = buckets.rows.as_mut().map(|b| b.take(rows));
+        let bytes = buckets.bytes.as_mut().map(|b| b.take(bytes));
+        rows.max(bytes).unwrap_or_default()
+    }
+
+    /// Wait asynchronously until a write of `rows` and `bytes` is within the budget.
+    pub async fn acquire(&self, rows: usize, bytes: usize) {
+        let wait = self.reserve(rows, bytes);
+        if !wait.is_zero() {
+            log::trace!("rate limited, wait {wait:?} for {rows} rows, {bytes} bytes");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Block the current thread until a write of `rows` and `bytes` is within the budget.
+    pub fn acquire_blocking(&self, rows: usize, bytes: usize) {
+        let wait = self.reserve(rows, bytes);
+        if !wait.is_zero() {
+            log::trace!("rate limited, wait {wait:?} for {rows} rows, {bytes} bytes");
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Used fraction of the burst budget of the busiest rate, above 1 when writes are waiting.
+    ///
+    /// With a zero burst, it's the seconds writes have to wait.
+    pub fn utilization(&self) -> f64 {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.refill();
+        let rows = buckets.rows.as_ref().map(Bucket::utilization);
+        let bytes = buckets.bytes.as_ref().map(Bucket::utilization);
+        rows.into_iter().chain(bytes).fold(0., f64::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write 5000 rows in batches of 500 and return the time it takes.
+    async fn write_5000_rows(limiter: &RateLimiter) -> Duration {
+        let start = Instant::now();
+        for _ in 0..10 {
+            limiter.acquire(500, 0).await;
+        }
+        start.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_rows() {
+        let limit = RateLimit::rows_per_sec(1000).with_burst(Duration::ZERO);
+        let limiter = RateLimiter::new(limit);
+        let elapsed = write_5000_rows(&limiter).await;
+        assert!(elapsed >= Duration::from_secs(5), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(5100), "{elapsed:?}");
+
+        // the first second is free with the default burst.
+        let limiter = RateLimiter::new(RateLimit::rows_per_sec(1000));
+        let elapsed = write_5000_rows(&limiter).await;
+        assert!(elapsed >= Duration::from_secs(4), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(4100), "{elapsed:?}");
+
+        // a larger burst is spent after being idle.
+        let limiter = RateLimiter::new(limit.with_burst(Duration::from_secs(3)));
+        assert_eq!(limiter.utilization(), 0.);
+        let elapsed = write_5000_rows(&limiter).await;
+        assert!(elapsed >= Duration::from_secs(2), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(2100), "{elapsed:?}");
+        assert!(limiter.utilization() > 0.99);
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert_eq!(limiter.utilization(), 0.);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_bytes() {
+        let limiter = RateLimiter::new(RateLimit {
+            rows_per_sec: Some(1000),
+            bytes_per_sec: Some(100),
+            burst: Some(Duration::ZERO),
+        });
+        // bytes are the busier rate.
+        assert_eq!(limiter.reserve(10, 200), Duration::from_secs(2));
+        assert!((limiter.utilization() - 2.).abs() < 1e-6);
+
+        let unlimited = RateLimiter::new(RateLimit::default());
+        assert_eq!(unlimited.reserve(usize::MAX, usize::MAX), Duration::ZERO);
+        assert_eq!(unlimited.utilization(), 0.);
+    }
+
+    #[test]
+    fn rate_limit_blocking() {
+        let limiter =
+            RateLimiter::new(RateLimit::bytes_per_sec(1000).with_burst(Duration::from_millis(100)));
+        let start = std::time::Instant::now();
+        limiter.acquire_blocking(0, 100);
+        assert!(start.elapsed() < Duration::from_millis(50));
+        limiter.acquire_blocking(0, 100);
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+}
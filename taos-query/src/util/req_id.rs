@@ -0,0 +1,57 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+
+/// Bits of the per-process counter, the upper bits are the process seed.
+const COUNTER_BITS: u32 = 48;
+
+/// Seed of this process, so ids of services sharing a server are unlikely to collide.
+static SEED: Lazy<u64> = Lazy::new(|| {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos() as u64);
+    // 15 bits, the ids are positive when passed to the client as `int64_t`.
+    ((std::process::id() as u64) ^ nanos) & 0x7fff
+});
+
+static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Generate a request id for a query that has none.
+///
+/// Ids are increasing in a process, with the upper 15 bits from the process id and the start
+/// time, so they are unique across connections and likely unique across processes.
+///
+/// ```rust
+/// # use taos_query::util::generate_req_id;
+/// let (a, b) = (generate_req_id(), generate_req_id());
+/// assert!(b > a);
+/// assert!(b <= i64::MAX as u64);
+/// ```
+pub fn generate_req_id() -> u64 {
+    let counter = COUNTER.fetch_add(1, Ordering::Relaxed) & ((1 << COUNTER_BITS) - 1);
+    (*SEED << COUNTER_BITS) | counter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn req_ids_are_unique() {
+        let ids: Vec<Vec<u64>> = (0..4)
+            .map(|_| std::thread::spawn(|| (0..1000).map(|_| generate_req_id()).collect()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        for ids in &ids {
+            assert!(ids.windows(2).all(|w| w[0] < w[1]));
+            assert!(ids.iter().all(|id| id >> COUNTER_BITS == *SEED));
+        }
+        let mut all: Vec<u64> = ids.concat();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), 4000);
+    }
+}
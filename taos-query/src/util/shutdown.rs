@@ -0,0 +1,147 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+
+/// A flag to abort operations of a connection and everything derived from it at once, eg. by
+/// `Taos::shutdown_now` of the `taos` crate.
+///
+/// Clones share the flag, so it could be triggered from another task, eg. a signal handler.
+/// Once triggered it's never reset.
+///
+/// ```rust
+/// # use taos_query::util::Shutdown;
+/// # #[tokio::main]
+/// # async fn main() {
+/// let shutdown = Shutdown::new();
+/// let pending = tokio::spawn({
+///     let shutdown = shutdown.clone();
+///     async move { shutdown.guard(std::future::pending::<()>()).await }
+/// });
+/// assert!(shutdown.trigger());
+/// assert_eq!(pending.await.unwrap(), None);
+/// assert!(!shutdown.trigger());
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    triggered: AtomicBool,
+    listeners: Mutex<Vec<Weak<Mutex<Option<Waker>>>>>,
+}
+
+/// Waker registration of a [Shutdown] flag, for types polled by hand like result sets.
+#[derive(Debug)]
+pub struct ShutdownListener {
+    shutdown: Shutdown,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trigger the flag and wake all listeners, returns `false` if it's already triggered.
+    pub fn trigger(&self) -> bool {
+        if self.0.triggered.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        let listeners = std::mem::take(&mut *self.0.listeners.lock().unwrap());
+        for waker in listeners.iter().filter_map(Weak::upgrade) {
+            if let Some(waker) = waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+        true
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.0.triggered.load(Ordering::SeqCst)
+    }
+
+    /// A listener to poll the flag by [ShutdownListener::poll_triggered].
+    pub fn listener(&self) -> ShutdownListener {
+        let waker = Arc::new(Mutex::new(None));
+        let mut listeners = self.0.listeners.lock().unwrap();
+        listeners.retain(|listener| listener.strong_count() > 0);
+        listeners.push(Arc::downgrade(&waker));
+        ShutdownListener {
+            shutdown: self.clone(),
+            waker,
+        }
+    }
+
+    /// Wait until the flag is triggered.
+    pub async fn triggered(&self) {
+        let listener = self.listener();
+        std::future::poll_fn(|cx| listener.poll_triggered(cx)).await
+    }
+
+    /// Run `fut` until it completes, or `None` if the flag is triggered before.
+    pub async fn guard<F: Future>(&self, fut: F) -> Option<F::Output> {
+        if self.is_triggered() {
+            return None;
+        }
+        tokio::select! {
+            biased;
+            _ = self.triggered() => None,
+            output = fut => Some(output),
+        }
+    }
+}
+
+impl ShutdownListener {
+    /// Ready if the flag is triggered, or the waker of `cx` is woken when it is.
+    pub fn poll_triggered(&self, cx: &mut Context<'_>) -> Poll<()> {
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+        // checked after the waker is set, so a trigger in between is not missed.
+        if self.shutdown.is_triggered() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.shutdown.is_triggered()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn trigger_wakes_listeners() {
+        let shutdown = Shutdown::new();
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let shutdown = shutdown.clone();
+                tokio::spawn(async move {
+                    shutdown
+                        .guard(tokio::time::sleep(Duration::from_secs(60)))
+                        .await
+                })
+            })
+            .collect();
+        let listener = shutdown.listener();
+        drop(shutdown.listener());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!listener.is_triggered());
+
+        assert!(shutdown.trigger());
+        for task in tasks {
+            let output = tokio::time::timeout(Duration::from_millis(100), task).await;
+            assert_eq!(output.unwrap().unwrap(), None);
+        }
+        assert!(listener.is_triggered());
+        assert_eq!(shutdown.guard(async { 1 }).await, None);
+        // listeners of the guards and the dropped one are released.
+        assert!(shutdown.0.listeners.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Allocations of copying fetched blocks, with and without a block pool.
+#![cfg(feature = "buffer-pool")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::Arc;
+
+use taos_query::common::views::views_to_raw_block;
+use taos_query::common::{BlockPool, ColumnView, Precision, RawBlock, SizeClassPool};
+
+/// Allocations of at least this size are counted, block buffers are larger than it.
+const LARGE: usize = 64 << 10;
+const ROWS: usize = 20_000;
+const FETCHES: usize = 1000;
+
+thread_local! {
+    static LARGE_ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+
+struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= LARGE {
+            let _ = LARGE_ALLOCS.try_with(|n| n.set(n.get() + 1));
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size >= LARGE {
+            let _ = LARGE_ALLOCS.try_with(|n| n.set(n.get() + 1));
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// Large allocations of the current thread in `f`.
+fn large_allocs(f: impl FnOnce()) -> usize {
+    let before = LARGE_ALLOCS.with(Cell::get);
+    f();
+    LARGE_ALLOCS.with(Cell::get) - before
+}
+
+/// Raw block as returned by the native client, at least 160KB.
+fn native_block() -> Vec<u8> {
+    let views = [
+        ColumnView::from_millis_timestamp((0..ROWS as i64).collect()),
+        ColumnView::from_big_ints((0..ROWS as i64).map(|i| (i % 5 != 0).then_some(i)).collect()),
+    ];
+    views_to_raw_block(&views)
+}
+
+/// Copy the block like a fetch and read it.
+fn fetch(raw: &[u8], pool: Option<&BlockPool>) -> RawBlock {
+    unsafe {
+        RawBlock::parse_from_ptr_with_pool(raw.as_ptr() as _, Precision::Millisecond, pool)
+    }
+}
+
+#[test]
+fn pooled_fetches_reuse_buffers() {
+    let raw = native_block();
+    assert!(raw.len() >= LARGE);
+    let expected = fetch(&raw, None).to_values();
+
+    let unpooled = large_allocs(|| {
+        for _ in 0..FETCHES {
+            assert_eq!(fetch(&raw, None).nrows(), ROWS);
+        }
+    });
+    assert!(unpooled >= FETCHES, "{unpooled} large allocations");
+
+    let pool = Arc::new(SizeClassPool::default());
+    let shared = BlockPool::new(pool.clone());
+    let pooled = large_allocs(|| {
+        for _ in 0..FETCHES {
+            assert_eq!(fetch(&raw, Some(&shared)).nrows(), ROWS);
+        }
+    });
+    assert_eq!(pooled, 1, "only the first fetch allocates");
+    assert_eq!(pool.idle(), 1);
+    assert_eq!(fetch(&raw, Some(&shared)).to_values(), expected);
+    println!("large allocations of {FETCHES} fetches: {unpooled} unpooled, {pooled} pooled");
+}
+
+#[test]
+fn blocks_outlive_pool() {
+    let raw = native_block();
+    let expected = fetch(&raw, None).to_values();
+
+    let pool = Arc::new(SizeClassPool::default());
+    let shared = BlockPool::new(pool.clone());
+    let blocks: Vec<_> = (0..3).map(|_| fetch(&raw, Some(&shared))).collect();
+    // like a connection closed before its blocks are dropped.
+    drop(shared);
+    for block in blocks {
+        assert_eq!(block.to_values(), expected);
+    }
+    assert_eq!(pool.idle(), 0, "buffers are freed without the pool");
+}
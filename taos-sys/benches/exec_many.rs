@@ -0,0 +1,56 @@
+#![feature(test)]
+
+extern crate test;
+
+#[cfg(test)]
+mod tests {
+    use taos_sys::*;
+    use test::Bencher;
+
+    const STATEMENTS: usize = 500;
+
+    fn statements(db: &str) -> Vec<String> {
+        (0..STATEMENTS)
+            .map(|i| format!("create table if not exists {db}.tb{i} using {db}.stb tags({i})"))
+            .collect()
+    }
+
+    async fn prepare(db: &str) -> anyhow::Result<Taos> {
+        let taos = TaosBuilder::from_dsn("taos:///")?.build()?;
+        taos.exec_many([
+            format!("drop database if exists {db}"),
+            format!("create database {db}"),
+            format!("create table {db}.stb (ts timestamp, v int) tags(t int)"),
+        ])
+        .await?;
+        Ok(taos)
+    }
+
+    #[bench]
+    fn bench_exec_sequential(b: &mut Bencher) {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let db = "bench_exec_sequential";
+        let taos = rt.block_on(prepare(db)).unwrap();
+        let sqls = statements(db);
+        b.iter(|| {
+            rt.block_on(async {
+                for sql in &sqls {
+                    taos.exec(sql).await.unwrap();
+                }
+            })
+        });
+        rt.block_on(taos.exec(format!("drop database {db}")))
+            .unwrap();
+    }
+
+    #[bench]
+    fn bench_exec_many_chained(b: &mut Bencher) {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let db = "bench_exec_many_chained";
+        let taos = rt.block_on(prepare(db)).unwrap();
+        let sqls = statements(db);
+        b.iter(|| rt.block_on(taos.exec_many(&sqls)).unwrap());
+        rt.block_on(taos.exec(format!("drop database {db}")))
+            .unwrap();
+    }
+}
@@ -77,6 +77,46 @@ fn taos_version() -> String {
     {
         println!("cargo:rustc-cfg=taos_write_raw_block_with_fields");
     }
+    if unsafe {
+        lib.symbol::<dlopen2::symbor::Symbol<unsafe extern "C" fn()>>("tmq_get_topic_assignment")
+    }
+    .is_ok()
+    {
+        println!("cargo:rustc-cfg=taos_tmq_assignment");
+    }
+    if unsafe { lib.symbol::<dlopen2::symbor::Symbol<unsafe extern "C" fn()>>("tmq_offset_seek") }
+        .is_ok()
+    {
+        println!("cargo:rustc-cfg=taos_tmq_offset_seek");
+    }
+    if unsafe {
+        lib.symbol::<dlopen2::symbor::Symbol<unsafe extern "C" fn()>>("tmq_commit_offset_async")
+    }
+    .is_ok()
+    {
+        println!("cargo:rustc-cfg=taos_tmq_commit_offset");
+    }
+    if unsafe {
+        lib.symbol::<dlopen2::symbor::Symbol<unsafe extern "C" fn()>>(
+            "taos_schemaless_insert_raw_ttl_with_reqid",
+        )
+    }
+    .is_ok()
+    {
+        println!("cargo:rustc-cfg=taos_schemaless_raw");
+    }
+    if unsafe {
+        lib.symbol::<dlopen2::symbor::Symbol<unsafe extern "C" fn()>>("taos_query_a_with_reqid")
+    }
+    .is_ok()
+    {
+        println!("cargo:rustc-cfg=taos_query_with_reqid");
+    }
+    if unsafe { lib.symbol::<dlopen2::symbor::Symbol<unsafe extern "C" fn()>>("taos_kill_query") }
+        .is_ok()
+    {
+        println!("cargo:rustc-cfg=taos_kill_query");
+    }
     let version = unsafe {
         let version: dlopen2::symbor::Symbol<
             unsafe extern "C" fn() -> *const std::os::raw::c_char,
@@ -1,12 +1,16 @@
 use std::{ffi::CStr, os::raw::*};
 
 use cfg_if::cfg_if;
-use taos_query::common::raw_data_t;
+use taos_query::common::{raw_data_t, SmlData};
 use taos_query::prelude::{Code, RawError as Error};
 use taos_query::RawBlock;
 
 use crate::tmq::*;
-use crate::{err_or, into_c_str::IntoCStr, query::QueryFuture};
+use crate::{
+    err_or,
+    into_c_str::IntoCStr,
+    query::{ExecManyFuture, QueryFuture},
+};
 use crate::{ffi::*, tmq::ffi::tmq_write_raw, RawRes, ResultSet};
 
 #[derive(Debug, Clone, Copy)]
@@ -34,6 +38,7 @@ impl RawTaos {
         db: *const c_char,
         port: u16,
     ) -> Result<Self, Error> {
+        crate::set_config::mark_initialized();
         let ptr = unsafe { taos_connect(host, user, pass, db, port) };
         log::trace!("call taos_connect: {ptr:?}");
         let null = std::ptr::null_mut();
@@ -66,6 +71,7 @@ impl RawTaos {
         db: *const c_char,
         port: u16,
     ) -> Result<Self, Error> {
+        crate::set_config::mark_initialized();
         let ptr = unsafe { taos_connect_auth(host, user, auth, db, port) };
         if ptr.is_null() {
             let null = std::ptr::null_mut();
@@ -91,11 +97,65 @@ impl RawTaos {
         RawRes::from_ptr(unsafe { taos_query(self.as_ptr(), sql.as_ptr()) }).map(ResultSet::new)
     }
 
+    /// Query with a request id to correlate with server logs.
+    ///
+    /// The id is not sent if the client has no `taos_query_with_reqid`, which is since 3.0.
+    #[inline]
+    pub fn query_with_req_id<'a, S: IntoCStr<'a>>(
+        &self,
+        sql: S,
+        req_id: u64,
+    ) -> Result<ResultSet, Error> {
+        let sql = sql.into_c_str();
+        log::trace!(
+            "[req id: {req_id:#x}] query with sql: {}",
+            sql.to_str().unwrap_or("<...>")
+        );
+        #[cfg(taos_query_with_reqid)]
+        let res = unsafe { taos_query_with_reqid(self.as_ptr(), sql.as_ptr(), req_id as _) };
+        #[cfg(not(taos_query_with_reqid))]
+        let res = unsafe { taos_query(self.as_ptr(), sql.as_ptr()) };
+        RawRes::from_ptr(res).map(|raw| ResultSet::new(raw).with_req_id(req_id))
+    }
+
     #[inline]
     pub fn query_async<'a, S: IntoCStr<'a>>(&'a self, sql: S) -> QueryFuture<'a> {
         QueryFuture::new(*self, sql)
     }
 
+    /// Async version of [RawTaos::query_with_req_id].
+    #[inline]
+    pub fn query_async_with_req_id<'a, S: IntoCStr<'a>>(
+        &'a self,
+        sql: S,
+        req_id: u64,
+    ) -> QueryFuture<'a> {
+        QueryFuture::new_with_req_id(*self, sql, req_id)
+    }
+
+    /// Execute statements one by one in a chain of `taos_query_a` callbacks.
+    ///
+    /// It stops at the first failed statement and the error message contains its index.
+    #[inline]
+    pub fn exec_many_async<T: AsRef<str>, I: IntoIterator<Item = T>>(
+        &self,
+        sqls: I,
+    ) -> ExecManyFuture {
+        ExecManyFuture::new(*self, sqls)
+    }
+
+    /// Stop all the requests in flight of the connection by `taos_kill_query`.
+    ///
+    /// It's a no-op if the client has no `taos_kill_query`, which is since 3.0.
+    #[inline]
+    pub fn kill_query(&self) {
+        cfg_if! {
+            if #[cfg(taos_kill_query)] {
+                unsafe { taos_kill_query(self.as_ptr()) }
+            }
+        }
+    }
+
     #[inline]
     pub fn query_a<'a, S: IntoCStr<'a>>(
         &self,
@@ -106,6 +166,32 @@ impl RawTaos {
         unsafe { taos_query_a(self.as_ptr(), sql.into_c_str().as_ptr(), fp, param) }
     }
 
+    #[inline]
+    pub fn query_a_with_req_id<'a, S: IntoCStr<'a>>(
+        &self,
+        sql: S,
+        fp: taos_async_query_cb,
+        param: *mut c_void,
+        req_id: u64,
+    ) {
+        cfg_if! {
+            if #[cfg(taos_query_with_reqid)] {
+                unsafe {
+                    taos_query_a_with_reqid(
+                        self.as_ptr(),
+                        sql.into_c_str().as_ptr(),
+                        fp,
+                        param,
+                        req_id as _,
+                    )
+                }
+            } else {
+                let _ = req_id;
+                self.query_a(sql, fp, param)
+            }
+        }
+    }
+
     #[inline]
     pub fn validate_sql(self, sql: *const c_char) -> Result<(), Error> {
         let code: Code = unsafe { taos_validate_sql(self.as_ptr(), sql) }.into();
@@ -118,6 +204,12 @@ impl RawTaos {
         }
     }
 
+    #[inline]
+    pub fn select_db<'a, S: IntoCStr<'a>>(&self, db: S) -> Result<(), Error> {
+        let db = db.into_c_str();
+        err_or!(self, taos_select_db(self.as_ptr(), db.as_ptr()))
+    }
+
     #[inline]
     pub fn reset_current_db(&self) {
         unsafe { taos_reset_current_db(self.as_ptr()) }
@@ -159,6 +251,44 @@ impl RawTaos {
         }
     }
 
+    /// Write schemaless data in one request.
+    pub fn put(&self, data: &SmlData) -> Result<(), Error> {
+        cfg_if! {
+            if #[cfg(taos_schemaless_raw)] {
+                let mut payload = data.payload().into_bytes();
+                let mut total_rows = 0;
+                let mut res = unsafe {
+                    RawRes::from_ptr_unchecked(
+                        crate::schemaless::taos_schemaless_insert_raw_ttl_with_reqid(
+                            self.as_ptr(),
+                            payload.as_mut_ptr() as _,
+                            payload.len() as _,
+                            &mut total_rows,
+                            data.protocol() as _,
+                            data.precision() as _,
+                            data.ttl().unwrap_or(0),
+                            0,
+                        ),
+                    )
+                };
+                let code = res.errno();
+                let ok = if code.success() {
+                    Ok(())
+                } else {
+                    Err(Error::new(code, res.err_as_str()))
+                };
+                res.free_result();
+                ok
+            } else {
+                let _ = data;
+                Err(Error::from_string(format!(
+                    "schemaless insert is not supported by client {}",
+                    Self::version()
+                )))
+            }
+        }
+    }
+
     #[inline]
     pub fn write_raw_block(&self, block: &RawBlock) -> Result<(), Error> {
         use itertools::Itertools;
@@ -77,6 +77,24 @@ extern "C" {
     );
 }
 
+#[cfg(taos_query_with_reqid)]
+extern "C" {
+    pub fn taos_query_with_reqid(taos: *mut TAOS, sql: *const c_char, reqid: i64) -> *mut TAOS_RES;
+
+    pub fn taos_query_a_with_reqid(
+        taos: *mut TAOS,
+        sql: *const c_char,
+        fp: taos_async_query_cb,
+        param: *mut c_void,
+        reqid: i64,
+    );
+}
+
+#[cfg(taos_kill_query)]
+extern "C" {
+    pub fn taos_kill_query(taos: *mut TAOS);
+}
+
 extern "C" {
     pub fn taos_load_table_info(taos: *mut TAOS, tableNameList: *const c_char) -> c_int;
 
@@ -188,6 +206,23 @@ extern "C" {
     pub fn taos_get_raw_block(taos: *mut TAOS_RES) -> *mut c_void;
 }
 
+#[cfg(not(taos_v2))]
+extern "C" {
+    pub fn taos_stmt_get_col_fields(
+        stmt: *mut TAOS_STMT,
+        field_num: *mut c_int,
+        fields: *mut *mut TAOS_FIELD_E,
+    ) -> c_int;
+
+    pub fn taos_stmt_get_tag_fields(
+        stmt: *mut TAOS_STMT,
+        field_num: *mut c_int,
+        fields: *mut *mut TAOS_FIELD_E,
+    ) -> c_int;
+
+    pub fn taos_stmt_reclaim_fields(stmt: *mut TAOS_STMT, fields: *mut TAOS_FIELD_E);
+}
+
 #[c_cfg(taos_result_block)]
 extern "C" {
     pub fn taos_result_block(res: *mut TAOS_RES) -> *mut TAOS_ROW;
@@ -15,7 +15,12 @@ use std::{
 
 use once_cell::sync::OnceCell;
 use query::blocks::SharedState;
+use taos_query::common::BlockPool;
 pub use taos_query::prelude::*;
+use taos_query::{
+    util::{generate_req_id, RateLimiter},
+    ConnStateNotifier,
+};
 // use taos_query::{AsyncFetchable, AsyncQueryable, DsnError, Fetchable, Queryable, TBuilder};
 
 pub mod sync {
@@ -88,11 +93,104 @@ macro_rules! err_or {
 #[derive(Debug)]
 pub struct Taos {
     raw: RawTaos,
+    state: ConnStateNotifier,
+    /// Held while [Taos::query_in] switches the current database.
+    scope: tokio::sync::Mutex<()>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    block_pool: Option<BlockPool>,
 }
 
 impl Drop for Taos {
     fn drop(&mut self) {
         self.raw.close();
+        self.state.close("closed by client");
+    }
+}
+
+impl Taos {
+    /// Current connection state.
+    pub fn state(&self) -> ConnState {
+        self.state.state()
+    }
+
+    /// The write limiter of the builder, see [TaosBuilder::with_rate_limit].
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    /// Track connection state by query results.
+    ///
+    /// The native client re-establishes lost connections internally, so a disconnection error
+    /// is reported as a reconnecting attempt, and the next successful query means connected.
+    fn track<T>(&self, res: Result<T, RawError>) -> Result<T, RawError> {
+        match &res {
+            Ok(_) => {
+                if let ConnState::Reconnecting { .. } = self.state.state() {
+                    self.state.set(ConnState::Connected {
+                        server_version: self.raw.server_version().to_string_lossy().to_string(),
+                    });
+                }
+            }
+            Err(err) if ConnState::is_disconnected_code(err.code()) => {
+                let attempt = match self.state.state() {
+                    ConnState::Reconnecting { attempt } => attempt + 1,
+                    _ => 1,
+                };
+                self.state.set(ConnState::Reconnecting { attempt });
+            }
+            Err(_) => (),
+        }
+        res
+    }
+
+    /// Query in database `db` without changing the current database of the connection.
+    ///
+    /// The native client has no per-query database, so it switches by `taos_select_db` and
+    /// restores the previous database afterwards, holding a per-connection lock in between.
+    /// Concurrent `query_in` and `exec_in` calls on a connection are serialized and each one
+    /// costs two more requests, `select database()` and the switch back. Queries by
+    /// [AsyncQueryable::query] are not locked, so they may run in `db` while it is selected;
+    /// prefer `db.table` names or a connection per database in hot paths.
+    pub async fn query_in(&self, db: &str, sql: &str) -> Result<ResultSet, RawError> {
+        let _scope = self.scope.lock().await;
+        let previous = AsyncQueryable::query_one::<_, Option<String>>(self, "select database()")
+            .await?
+            .flatten();
+        self.track(self.raw.select_db(db))?;
+        let res = AsyncQueryable::query(self, sql).await;
+        match previous {
+            Some(previous) => self.raw.select_db(previous)?,
+            None => self.raw.reset_current_db(),
+        }
+        res
+    }
+
+    /// Query with a request id, which is logged by the server to correlate with client logs.
+    ///
+    /// The id is not sent by clients without `taos_query_with_reqid`, before 3.0.
+    pub async fn query_with_req_id(&self, sql: &str, req_id: u64) -> Result<ResultSet, RawError> {
+        log::debug!("[req id: {req_id:#x}] Async query with SQL: {sql}");
+        self.track(
+            self.raw
+                .query_async_with_req_id(sql, req_id)
+                .await
+                .map(|raw| ResultSet::new(raw).with_req_id(req_id)),
+        )
+        .map(|rs| rs.with_block_pool(self.block_pool.clone()))
+    }
+
+    /// Execute in database `db`, see [Taos::query_in].
+    pub async fn exec_in(&self, db: &str, sql: &str) -> Result<usize, RawError> {
+        self.query_in(db, sql)
+            .await
+            .map(|res| AsyncFetchable::affected_rows(&res) as _)
+    }
+
+    /// Write schemaless data, tables are created or altered as needed.
+    ///
+    /// It needs `taos_schemaless_insert_raw_ttl_with_reqid` of the client, since 3.0.3.
+    pub fn put(&self, data: &SmlData) -> Result<(), RawError> {
+        self.track(self.raw.put(data))
     }
 }
 
@@ -102,8 +200,10 @@ impl taos_query::Queryable for Taos {
     type ResultSet = ResultSet;
 
     fn query<T: AsRef<str>>(&self, sql: T) -> Result<Self::ResultSet, Self::Error> {
-        log::debug!("Query with SQL: {}", sql.as_ref());
-        self.raw.query(sql.as_ref())
+        let req_id = generate_req_id();
+        log::debug!("[req id: {req_id:#x}] Query with SQL: {}", sql.as_ref());
+        self.track(self.raw.query_with_req_id(sql.as_ref(), req_id))
+            .map(|rs| rs.with_block_pool(self.block_pool.clone()))
     }
 
     fn write_raw_meta(&self, meta: &RawMeta) -> Result<(), Self::Error> {
@@ -112,6 +212,9 @@ impl taos_query::Queryable for Taos {
     }
 
     fn write_raw_block(&self, block: &RawBlock) -> Result<(), Self::Error> {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire_blocking(block.nrows(), block.as_raw_bytes().len());
+        }
         self.raw.write_raw_block(block)
     }
 }
@@ -126,8 +229,17 @@ impl AsyncQueryable for Taos {
         &self,
         sql: T,
     ) -> Result<Self::AsyncResultSet, Self::Error> {
-        log::debug!("Async query with SQL: {}", sql.as_ref());
-        self.raw.query_async(sql.as_ref()).await.map(ResultSet::new)
+        self.query_with_req_id(sql.as_ref(), generate_req_id())
+            .await
+    }
+
+    async fn exec_many<T, I>(&self, input: I) -> Result<usize, Self::Error>
+    where
+        T: AsRef<str> + Send + Sync,
+        I::IntoIter: Send,
+        I: IntoIterator<Item = T> + Send,
+    {
+        self.track(self.raw.exec_many_async(input).await)
     }
 
     async fn write_raw_meta(&self, meta: &taos_query::common::RawMeta) -> Result<(), Self::Error> {
@@ -135,6 +247,11 @@ impl AsyncQueryable for Taos {
     }
 
     async fn write_raw_block(&self, block: &RawBlock) -> Result<(), Self::Error> {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter
+                .acquire(block.nrows(), block.as_raw_bytes().len())
+                .await;
+        }
         self.raw.write_raw_block(block)
     }
 }
@@ -176,7 +293,7 @@ impl AsyncQueryable for Taos {
 /// }
 /// #
 /// ```
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct TaosBuilder {
     host: Option<CString>,
     user: Option<CString>,
@@ -185,9 +302,78 @@ pub struct TaosBuilder {
     port: u16,
     inner_conn: OnceCell<Taos>,
     server_version: OnceCell<String>,
+    state_listener: Option<StateListener>,
+    /// Write budget shared by connections of the builder, see [TaosBuilder::with_rate_limit].
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Buffers of fetched blocks, see [TaosBuilder::with_block_pool].
+    block_pool: Option<BlockPool>,
+}
+
+impl std::fmt::Debug for TaosBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaosBuilder")
+            .field("host", &self.host)
+            .field("user", &self.user)
+            .field("pass", &self.pass.as_ref().map(|_| "***"))
+            .field("db", &self.db)
+            .field("port", &self.port)
+            .field("inner_conn", &self.inner_conn)
+            .field("server_version", &self.server_version)
+            .field("state_listener", &self.state_listener)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("block_pool", &self.block_pool)
+            .finish()
+    }
 }
 
 impl TaosBuilder {
+    /// Set a callback to receive connection state changes of connections built by this builder.
+    pub fn on_state_change(mut self, f: impl Fn(ConnState) + Send + Sync + 'static) -> Self {
+        self.state_listener = Some(StateListener::new(f));
+        self
+    }
+
+    /// Set log options of the native client, instead of editing taos.cfg.
+    ///
+    /// ```rust,no_run
+    /// # use taos_sys::*;
+    /// let builder = TaosBuilder::from_dsn("taos://")?.native_log(LogConfig {
+    ///     dir: Some("/tmp/taos-debug".into()),
+    ///     debug_flag: Some(LogConfig::DEBUG),
+    ///     ..Default::default()
+    /// })?;
+    /// # Ok::<_, Error>(())
+    /// ```
+    ///
+    /// The options are read once when the native client initializes, so it fails after the first
+    /// connection or consumer in the process.
+    pub fn native_log(self, config: LogConfig) -> Result<Self, Error> {
+        set_config::set_log_config(&config)?;
+        Ok(self)
+    }
+
+    /// Cap write throughput of connections and statements built by this builder.
+    ///
+    /// Raw block writes and stmt executions take their rows and bytes from a token bucket shared
+    /// by all of them. Synchronous writes block the calling thread when the budget is spent,
+    /// asynchronous writes wait without blocking.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(limit)));
+        self
+    }
+
+    /// The limiter set by [TaosBuilder::with_rate_limit], eg. to report its utilization.
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    /// Copy blocks fetched from the native client into buffers of `pool` instead of new
+    /// allocations, buffers go back to the pool when the blocks are dropped.
+    pub fn with_block_pool(mut self, pool: BlockPool) -> Self {
+        self.block_pool = Some(pool);
+        self
+    }
+
     fn inner_connection(&self) -> Result<&Taos, Error> {
         self.inner_conn.get_or_try_init(|| self.build())
     }
@@ -196,6 +382,12 @@ impl TaosBuilder {
 #[derive(Debug)]
 pub struct Error(RawError);
 
+impl Error {
+    pub fn code(&self) -> Code {
+        self.0.code()
+    }
+}
+
 impl From<DsnError> for Error {
     fn from(err: DsnError) -> Self {
         Self(RawError::from_string(err.to_string()))
@@ -206,7 +398,16 @@ impl From<RawError> for Error {
         Self(err)
     }
 }
-impl std::error::Error for Error {}
+impl From<taos_query::stmt::CoerceError> for Error {
+    fn from(err: taos_query::stmt::CoerceError) -> Self {
+        Self(err.into())
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -226,6 +427,14 @@ impl TBuilder for TaosBuilder {
 
     fn from_dsn<D: taos_query::IntoDsn>(dsn: D) -> Result<Self, Self::Error> {
         let dsn = dsn.into_dsn()?;
+        // TLS of websocket connections, native connections have no such settings.
+        if let Some(param) = dsn.params.keys().find(|key| key.starts_with("ssl.")) {
+            return Err(DsnError::InvalidParam(
+                param.clone(),
+                "not supported for taos:// scheme".to_string(),
+            )
+            .into());
+        }
         let mut builder = TaosBuilder::default();
         if let Some(addr) = dsn.addresses.into_iter().next() {
             if let Some(host) = addr.host {
@@ -268,6 +477,8 @@ impl TBuilder for TaosBuilder {
     }
 
     fn build(&self) -> Result<Self::Target, Self::Error> {
+        let state = ConnStateNotifier::new(self.state_listener.clone());
+        state.set(ConnState::Connecting);
         let raw = RawTaos::connect(
             self.host
                 .as_ref()
@@ -286,9 +497,22 @@ impl TBuilder for TaosBuilder {
                 .map(|v| v.as_ptr())
                 .unwrap_or(std::ptr::null()),
             self.port,
-        )?;
-
-        Ok(Taos { raw })
+        )
+        .map_err(|err| {
+            state.close(err.to_string());
+            err
+        })?;
+        state.set(ConnState::Connected {
+            server_version: raw.server_version().to_string_lossy().to_string(),
+        });
+
+        Ok(Taos {
+            raw,
+            state,
+            scope: Default::default(),
+            rate_limiter: self.rate_limiter.clone(),
+            block_pool: self.block_pool.clone(),
+        })
     }
 
     fn server_version(&self) -> Result<&str, Self::Error> {
@@ -347,6 +571,8 @@ pub struct ResultSet {
     fields: OnceCell<Vec<Field>>,
     summary: UnsafeCell<(usize, usize)>,
     state: Arc<UnsafeCell<SharedState>>,
+    block_pool: Option<BlockPool>,
+    req_id: Option<u64>,
 }
 
 impl ResultSet {
@@ -356,9 +582,26 @@ impl ResultSet {
             fields: OnceCell::new(),
             summary: UnsafeCell::new((0, 0)),
             state: Arc::new(UnsafeCell::new(SharedState::default())),
+            block_pool: None,
+            req_id: None,
         }
     }
 
+    fn with_req_id(mut self, req_id: u64) -> Self {
+        self.req_id = Some(req_id);
+        self
+    }
+
+    /// Request id of the query, `None` for results of stmt.
+    pub fn req_id(&self) -> Option<u64> {
+        self.req_id
+    }
+
+    fn with_block_pool(mut self, pool: Option<BlockPool>) -> Self {
+        self.block_pool = pool;
+        self
+    }
+
     pub fn precision(&self) -> Precision {
         self.raw.precision()
     }
@@ -393,16 +636,35 @@ impl ResultSet {
         self.raw.affected_rows() as _
     }
 
+    /// Fetch blocks in background into a channel of `buffer` blocks, instead of polling them.
+    ///
+    /// The next block is fetched only when the channel has capacity, the result is freed when all
+    /// blocks are sent or the receiver is dropped, see [RawRes::stream_blocks].
+    pub fn stream_blocks(
+        mut self,
+        buffer: usize,
+    ) -> tokio::sync::mpsc::Receiver<Result<RawBlock, RawError>> {
+        let pool = self.block_pool.clone();
+        let raw = std::mem::replace(&mut self.raw, RawRes(std::ptr::null_mut()));
+        raw.stream_blocks(buffer, pool)
+    }
+
     pub(crate) fn fetch_raw_block(&self) -> Result<Option<RawBlock>, RawError> {
-        self.raw.fetch_raw_block(self.fields())
+        self.raw
+            .fetch_raw_block(self.fields(), self.block_pool.as_ref())
     }
 
     pub(crate) fn fetch_raw_block_async(
         &self,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Option<RawBlock>, RawError>> {
-        self.raw
-            .fetch_raw_block_async(self.fields(), self.precision(), &self.state, cx)
+        self.raw.fetch_raw_block_async(
+            self.fields(),
+            self.precision(),
+            &self.state,
+            self.block_pool.as_ref(),
+            cx,
+        )
     }
 }
 
@@ -411,7 +673,7 @@ impl Iterator for ResultSet {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.raw
-            .fetch_raw_block(self.fields())
+            .fetch_raw_block(self.fields(), self.block_pool.as_ref())
             .transpose()
             .map(|block| {
                 block.map(|raw| {
@@ -447,7 +709,8 @@ impl taos_query::Fetchable for ResultSet {
     }
 
     fn fetch_raw_block(&mut self) -> Result<Option<RawBlock>, Self::Error> {
-        self.raw.fetch_raw_block(self.fields())
+        self.raw
+            .fetch_raw_block(self.fields(), self.block_pool.as_ref())
     }
 }
 
@@ -484,7 +747,10 @@ impl AsyncFetchable for ResultSet {
 
 impl Drop for ResultSet {
     fn drop(&mut self) {
-        self.raw.drop();
+        // taken by `stream_blocks`.
+        if !self.raw.as_ptr().is_null() {
+            self.raw.drop();
+        }
     }
 }
 
@@ -503,7 +769,7 @@ mod tests {
 
         for raw in &mut set {
             let raw = raw?;
-            for (col, view) in raw.columns().into_iter().enumerate() {
+            for (col, (_, view)) in raw.columns().enumerate() {
                 for (row, value) in view.iter().enumerate() {
                     println!("Value at (row: {}, col: {}) is: {}", row, col, value);
                 }
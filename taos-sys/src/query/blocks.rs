@@ -25,7 +25,7 @@ impl Iterator for Blocks {
     type Item = Result<RawBlock, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.res.fetch_raw_block(&self.fields).transpose()
+        self.res.fetch_raw_block(&self.fields, None).transpose()
     }
 }
 
@@ -0,0 +1,141 @@
+use std::ffi::{CStr, CString};
+use std::future::Future;
+use std::os::raw::{c_int, c_void};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use crate::ffi::TAOS_RES;
+use crate::{RawRes, RawTaos};
+use taos_query::prelude::{Code, RawError};
+
+/// Execute a batch of statements by chaining `taos_query_a` calls.
+///
+/// The next statement is issued from the completion callback of the previous one, so the
+/// executor is only re-entered once when all statements are done or the first one fails.
+pub struct ExecManyFuture {
+    state: Arc<Mutex<State>>,
+}
+
+/// Shared state between the future and the callback chain.
+struct State {
+    raw: RawTaos,
+    sqls: Vec<CString>,
+    /// Index of the statement in flight.
+    current: usize,
+    affected_rows: usize,
+    started: bool,
+    result: Option<Result<usize, RawError>>,
+    waker: Option<Waker>,
+}
+
+impl State {
+    fn finish(&mut self, result: Result<usize, RawError>) {
+        self.result = Some(result);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+unsafe impl Send for State {}
+
+/// Issue the current statement, the callback will take the ownership of `state`.
+fn query_current(state: Arc<Mutex<State>>) {
+    let (raw, sql) = {
+        let s = state.lock().unwrap();
+        // CString buffers are owned by the state which lives as long as the callback chain.
+        (s.raw, s.sqls[s.current].as_c_str() as *const CStr)
+    };
+    log::trace!("exec_many chained query: {:?}", unsafe { &*sql });
+    raw.query_a(
+        unsafe { &*sql },
+        taos_sys_exec_many_callback as _,
+        Arc::into_raw(state) as *mut _,
+    );
+}
+
+unsafe extern "C" fn taos_sys_exec_many_callback(
+    param: *mut c_void,
+    res: *mut TAOS_RES,
+    code: c_int,
+) {
+    let state = Arc::from_raw(param as *const Mutex<State>);
+    let has_next = {
+        let mut s = state.lock().unwrap();
+        match RawRes::from_ptr_with_code(res, Code::from(code)) {
+            Ok(mut res) => {
+                s.affected_rows += res.affected_rows() as usize;
+                res.free_result();
+            }
+            Err(err) => {
+                let err = RawError::new(
+                    err.code(),
+                    format!("statement #{} failed: {}", s.current, err.message()),
+                );
+                s.finish(Err(err));
+                return;
+            }
+        }
+        s.current += 1;
+        if s.current < s.sqls.len() {
+            true
+        } else {
+            let affected_rows = s.affected_rows;
+            s.finish(Ok(affected_rows));
+            false
+        }
+    };
+    // Lock must be released before next call, the callback may be called in current thread.
+    if has_next {
+        query_current(state);
+    }
+}
+
+impl ExecManyFuture {
+    pub fn new<T: AsRef<str>, I: IntoIterator<Item = T>>(raw: RawTaos, sqls: I) -> Self {
+        let sqls: Result<Vec<_>, _> = sqls
+            .into_iter()
+            .enumerate()
+            .map(|(i, sql)| {
+                CString::new(sql.as_ref()).map_err(|err| {
+                    RawError::from_string(format!("statement #{i} is not a valid C string: {err}"))
+                })
+            })
+            .collect();
+        let (sqls, result) = match sqls {
+            Ok(sqls) if sqls.is_empty() => (sqls, Some(Ok(0))),
+            Ok(sqls) => (sqls, None),
+            Err(err) => (Vec::new(), Some(Err(err))),
+        };
+        Self {
+            state: Arc::new(Mutex::new(State {
+                raw,
+                sqls,
+                current: 0,
+                affected_rows: 0,
+                started: false,
+                result,
+                waker: None,
+            })),
+        }
+    }
+}
+
+impl Future for ExecManyFuture {
+    type Output = Result<usize, RawError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut s = self.state.lock().unwrap();
+        if let Some(result) = s.result.take() {
+            return Poll::Ready(result);
+        }
+        s.waker = Some(cx.waker().clone());
+        if !s.started {
+            s.started = true;
+            drop(s);
+            query_current(self.state.clone());
+        }
+        Poll::Pending
+    }
+}
@@ -1,31 +1,76 @@
 use std::borrow::Cow;
-use std::cell::UnsafeCell;
 
+use std::collections::BTreeMap;
 use std::ffi::CStr;
 use std::future::Future;
 use std::os::raw::{c_int, c_void};
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll, Waker};
 
-use crate::ffi::TAOS_RES;
+use once_cell::sync::Lazy;
+
+use crate::ffi::{taos_free_result, TAOS_RES};
 use crate::into_c_str::IntoCStr;
 use crate::{RawRes, RawTaos};
 use taos_query::prelude::RawError;
 
+/// Queries waiting for callbacks, by the address of their connections.
+///
+/// `taos_kill_query` stops all the requests of a connection, so a dropped future only kills the
+/// query when it's the only one in flight.
+static IN_FLIGHT: Lazy<Mutex<BTreeMap<usize, usize>>> = Lazy::new(Default::default);
+
+fn in_flight_start(conn: usize) {
+    *IN_FLIGHT.lock().unwrap().entry(conn).or_default() += 1;
+}
+
+fn in_flight_finish(conn: usize) {
+    let mut in_flight = IN_FLIGHT.lock().unwrap();
+    if let Some(n) = in_flight.get_mut(&conn) {
+        *n -= 1;
+        if *n == 0 {
+            in_flight.remove(&conn);
+        }
+    }
+}
+
+fn in_flight(conn: usize) -> usize {
+    IN_FLIGHT.lock().unwrap().get(&conn).copied().unwrap_or(0)
+}
+
+/// Future of `taos_query_a`.
+///
+/// Dropping it before the callback cancels the query: the query is stopped by `taos_kill_query`
+/// unless others are in flight on the same connection, and the result is freed as soon as it
+/// arrives, instead of being leaked.
 pub struct QueryFuture<'a> {
     raw: RawTaos,
     sql: Cow<'a, CStr>,
-    state: Arc<UnsafeCell<State>>,
+    /// Sent by `taos_query_a_with_reqid` if set.
+    req_id: Option<u64>,
+    shared: Arc<Shared>,
 }
 
 unsafe impl<'a> Send for QueryFuture<'a> {}
 
-/// Shared state between the future and the waiting thread
+/// Shared between the future and the callback, which holds a count until it runs.
+struct Shared {
+    state: Mutex<State>,
+    /// The future is dropped while waiting for the callback.
+    cancelled: AtomicBool,
+    /// Address of the connection.
+    conn: usize,
+}
+
 struct State {
     result: *mut TAOS_RES,
     code: i32,
     done: bool,
+    waiting: bool,
+    /// Waker of the latest poll.
+    waker: Option<Waker>,
 }
 
 unsafe impl Send for State {}
@@ -36,46 +81,109 @@ impl<'a> Unpin for QueryFuture<'a> {}
 impl<'a> Future for QueryFuture<'a> {
     type Output = Result<RawRes, RawError>;
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let ptr = self.state.get();
-        let state = unsafe { &*self.state.get() };
+        let mut state = self.shared.state.lock().unwrap();
+
         if state.done {
-            Poll::Ready(RawRes::from_ptr_with_code(state.result, state.code.into()))
-        } else {
-            #[no_mangle]
-            unsafe extern "C" fn taos_sys_async_query_callback(
-                param: *mut c_void,
-                res: *mut TAOS_RES,
-                code: c_int,
-            ) {
-                let state = Box::from_raw(param as *mut (Arc<UnsafeCell<State>>, Waker));
-                let mut s = { &mut *state.0.get() };
-
-                s.result = res;
-                s.code = code;
-                s.done = true;
-                state.1.wake();
+            // taken by the future, not to be freed on drop.
+            let result = std::mem::replace(&mut state.result, std::ptr::null_mut());
+            return Poll::Ready(RawRes::from_ptr_with_code(result, state.code.into()));
+        }
+        // the task may move between polls.
+        if !matches!(&state.waker, Some(waker) if waker.will_wake(cx.waker())) {
+            state.waker = Some(cx.waker().clone());
+        }
+        if state.waiting {
+            return Poll::Pending;
+        }
+        state.waiting = true;
+        drop(state);
+
+        #[no_mangle]
+        unsafe extern "C" fn taos_sys_async_query_callback(
+            param: *mut c_void,
+            res: *mut TAOS_RES,
+            code: c_int,
+        ) {
+            let shared = Arc::from_raw(param as *const Shared);
+            in_flight_finish(shared.conn);
+            let mut s = shared.state.lock().unwrap();
+
+            if shared.cancelled.load(Ordering::Acquire) {
+                if !res.is_null() {
+                    taos_free_result(res);
+                }
+                return;
             }
+            s.result = res;
+            s.code = code;
+            s.done = true;
+            let waker = s.waker.take();
+            drop(s);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
 
-            let param = Box::new((self.state.clone(), cx.waker().clone()));
+        let param = Arc::into_raw(self.shared.clone());
+        in_flight_start(self.shared.conn);
+        let (sql, param) = (self.sql.as_ref(), param as *mut _);
+        match self.req_id {
+            Some(req_id) => {
+                self.raw
+                    .query_a_with_req_id(sql, taos_sys_async_query_callback as _, param, req_id)
+            }
+            None => self
+                .raw
+                .query_a(sql, taos_sys_async_query_callback as _, param),
+        }
+        Poll::Pending
+    }
+}
 
-            self.raw.query_a(
-                self.sql.as_ref(),
-                taos_sys_async_query_callback as _,
-                Box::into_raw(param) as *mut _,
-            );
-            Poll::Pending
+impl<'a> Drop for QueryFuture<'a> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.done {
+            // completed but never polled again.
+            if !state.result.is_null() {
+                unsafe { taos_free_result(state.result) };
+                state.result = std::ptr::null_mut();
+            }
+        } else if state.waiting {
+            self.shared.cancelled.store(true, Ordering::Release);
+            // the callback may run in `taos_kill_query`, so no lock is held then.
+            drop(state);
+            if in_flight(self.shared.conn) == 1 {
+                self.raw.kill_query();
+            }
         }
     }
 }
+
 impl<'a> QueryFuture<'a> {
     /// Create a new `TimerFuture` which will complete after the provided
     /// timeout.
     pub fn new(taos: RawTaos, sql: impl IntoCStr<'a>) -> Self {
-        let state = Arc::new(UnsafeCell::new(State {
-            result: std::ptr::null_mut(),
-            code: 0,
-            done: false,
-        }));
+        Self::with_req_id(taos, sql, None)
+    }
+
+    /// A query future that sends `req_id` along with the sql.
+    pub fn new_with_req_id(taos: RawTaos, sql: impl IntoCStr<'a>, req_id: u64) -> Self {
+        Self::with_req_id(taos, sql, Some(req_id))
+    }
+
+    fn with_req_id(taos: RawTaos, sql: impl IntoCStr<'a>, req_id: Option<u64>) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                result: std::ptr::null_mut(),
+                code: 0,
+                done: false,
+                waiting: false,
+                waker: None,
+            }),
+            cancelled: AtomicBool::new(false),
+            conn: taos.as_ptr() as usize,
+        });
 
         let sql = sql.into_c_str();
         // log::trace!("async query with sql: {:?}", sql);
@@ -83,7 +191,8 @@ impl<'a> QueryFuture<'a> {
         QueryFuture {
             raw: taos,
             sql,
-            state,
+            req_id,
+            shared,
         }
     }
 }
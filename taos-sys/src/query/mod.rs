@@ -1,7 +1,10 @@
 pub mod blocks;
+mod exec_many;
 mod future;
 mod message;
 mod raw_res;
+mod stream;
 
+pub use exec_many::ExecManyFuture;
 pub use future::QueryFuture;
 pub use raw_res::RawRes;
@@ -7,7 +7,7 @@ use std::task::{Context, Poll, Waker};
 
 use taos_query::prelude::{Code, RawError as Error};
 use taos_query::{
-    common::{Field, Precision},
+    common::{BlockPool, Field, Precision},
     RawBlock,
 };
 
@@ -115,11 +115,18 @@ impl RawRes {
     }
 
     #[inline]
-    pub fn fetch_raw_block(&self, fields: &[Field]) -> Result<Option<RawBlock>, Error> {
+    pub fn fetch_raw_block(
+        &self,
+        fields: &[Field],
+        pool: Option<&BlockPool>,
+    ) -> Result<Option<RawBlock>, Error> {
         #[cfg(taos_v3)]
-        return self.fetch_raw_block_v3(fields);
+        return self.fetch_raw_block_v3(fields, pool);
         #[cfg(not(taos_v3))]
-        self.fetch_raw_block_v2(fields)
+        {
+            let _ = pool;
+            self.fetch_raw_block_v2(fields)
+        }
     }
 
     #[inline(never)]
@@ -128,12 +135,16 @@ impl RawRes {
         fields: &[Field],
         precision: Precision,
         state: &Arc<UnsafeCell<SharedState>>,
+        pool: Option<&BlockPool>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Option<RawBlock>, Error>> {
         #[cfg(not(taos_v3))]
-        return self.fetch_raw_block_async_v2(fields, precision, state, cx);
+        return {
+            let _ = pool;
+            self.fetch_raw_block_async_v2(fields, precision, state, cx)
+        };
         #[cfg(taos_v3)]
-        return self.fetch_raw_block_async_v3(fields, precision, state, cx);
+        return self.fetch_raw_block_async_v3(fields, precision, state, pool, cx);
     }
 
     #[inline(never)]
@@ -142,6 +153,7 @@ impl RawRes {
         fields: &[Field],
         precision: Precision,
         state: &Arc<UnsafeCell<SharedState>>,
+        pool: Option<&BlockPool>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Option<RawBlock>, Error>> {
         let cell = state.get();
@@ -160,7 +172,9 @@ impl RawRes {
 
             if current.num > 0 {
                 // has next block.
-                let mut raw = unsafe { RawBlock::parse_from_ptr(current.block as _, precision) };
+                let mut raw = unsafe {
+                    RawBlock::parse_from_ptr_with_pool(current.block as _, precision, pool)
+                };
                 raw.with_field_names(fields.iter().map(|f| f.name()));
                 if current.num == 0 {
                     // finish fetch loop.
@@ -306,7 +320,11 @@ impl RawRes {
     }
 
     #[inline]
-    pub fn fetch_raw_block_v3(&self, fields: &[Field]) -> Result<Option<RawBlock>, Error> {
+    pub fn fetch_raw_block_v3(
+        &self,
+        fields: &[Field],
+        pool: Option<&BlockPool>,
+    ) -> Result<Option<RawBlock>, Error> {
         let mut block: *mut c_void = std::ptr::null_mut();
         let mut num = 0;
         crate::err_or!(
@@ -315,7 +333,8 @@ impl RawRes {
             if num > 0 {
                 match self.tmq_message_type() {
                     tmq_res_t::TMQ_RES_INVALID => {
-                        let mut raw = RawBlock::parse_from_ptr(block as _, self.precision());
+                        let mut raw =
+                            RawBlock::parse_from_ptr_with_pool(block as _, self.precision(), pool);
                         raw.with_field_names(self.fetch_fields().iter().map(Field::name));
                         Some(raw)
                     }
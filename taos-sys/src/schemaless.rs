@@ -56,6 +56,20 @@ extern "C" {
     ) -> *mut TAOS_RES;
 }
 
+#[cfg(taos_schemaless_raw)]
+extern "C" {
+    pub fn taos_schemaless_insert_raw_ttl_with_reqid(
+        taos: *mut TAOS,
+        lines: *mut c_char,
+        len: c_int,
+        total_rows: *mut i32,
+        protocol: c_int,
+        precision: c_int,
+        ttl: i32,
+        reqid: i64,
+    ) -> *mut TAOS_RES;
+}
+
 #[test]
 #[cfg(taos_v2)] // TODO: SML in v3 is unimplemented.
 fn test_sml() {
@@ -1,4 +1,10 @@
-use std::os::raw::*;
+use std::{
+    ffi::{CStr, CString},
+    os::raw::*,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use taos_query::prelude::{LogConfig, RawError};
 
 pub const SET_CONF_RET_SUCC: SET_CONF_RET_CODE = SET_CONF_RET_CODE::Succ;
 pub const SET_CONF_RET_ERR_PART: SET_CONF_RET_CODE = SET_CONF_RET_CODE::ErrPart;
@@ -30,3 +36,30 @@ pub enum SET_CONF_RET_CODE {
 extern "C" {
     pub fn taos_set_config(config: *const c_char) -> SetConfRet;
 }
+
+/// Set when the native client is initialized by the first connection or consumer.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn mark_initialized() {
+    INITIALIZED.store(true, Ordering::SeqCst);
+}
+
+/// Apply log options by `taos_set_config`, which only works before initialization.
+pub(crate) fn set_log_config(config: &LogConfig) -> Result<(), RawError> {
+    if INITIALIZED.load(Ordering::SeqCst) {
+        return Err(RawError::from_string(
+            "native log config must be set before the first connection",
+        ));
+    }
+    let json = CString::new(config.to_json()).unwrap();
+    let ret = unsafe { taos_set_config(json.as_ptr()) };
+    if ret.code == SET_CONF_RET_SUCC {
+        Ok(())
+    } else {
+        let msg = unsafe { CStr::from_ptr(ret.msg.as_ptr()) }.to_string_lossy();
+        Err(RawError::from_string(format!(
+            "set native log config failed with {:?}: {msg}",
+            ret.code
+        )))
+    }
+}
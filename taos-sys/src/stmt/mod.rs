@@ -1,10 +1,12 @@
 use crate::{err_or, ffi::*, into_c_str::IntoCStr, RawRes, RawTaos, ResultSet};
 
 use std::ffi::CStr;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use taos_query::prelude::{Code, RawError};
-use taos_query::{common::Ty, stmt::Bindable, Queryable};
+use taos_query::stmt::{broadcast_bind, validate_bind, Bindable, StmtField};
+use taos_query::{common::Ty, util::RateLimiter, Queryable};
 
 use crate::types::*;
 
@@ -14,6 +16,13 @@ mod multi;
 #[derive(Debug)]
 pub struct Stmt {
     raw: RawStmt,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Rows and bytes bound since the last execution, taken from the rate limit on execution.
+    pending: (usize, usize),
+    /// Column parameters of the prepared statement, `None` until they are known.
+    fields: Option<Vec<StmtField>>,
+    /// Tags of the prepared statement, `None` until they are known.
+    tags: Option<Vec<StmtField>>,
 }
 
 unsafe impl Send for Stmt {}
@@ -24,21 +33,34 @@ impl Bindable<super::Taos> for Stmt {
     fn init(taos: &super::Taos) -> Result<Self, Self::Error> {
         Ok(Self {
             raw: RawStmt::from_raw_taos(&taos.raw),
+            rate_limiter: taos.rate_limiter.clone(),
+            pending: (0, 0),
+            fields: None,
+            tags: None,
         })
     }
 
     fn prepare<S: AsRef<str>>(&mut self, sql: S) -> Result<&mut Self, Self::Error> {
         self.raw.prepare(sql.as_ref())?;
+        self.fields = None;
+        self.tags = None;
+        self.load_fields();
         Ok(self)
     }
 
     fn set_tbname<S: AsRef<str>>(&mut self, sql: S) -> Result<&mut Self, Self::Error> {
         self.raw.set_tbname(sql.as_ref())?;
+        self.load_fields();
         Ok(self)
     }
 
     fn set_tags(&mut self, tags: &[taos_query::common::Value]) -> Result<&mut Self, Self::Error> {
-        let tags = tags.iter().map(TaosBind::from_value).collect_vec();
+        // normal tables have no tags to set.
+        if tags.is_empty() {
+            return Ok(self);
+        }
+        let mut json = Vec::new();
+        let tags = TaosBind::from_tags(tags, &mut json);
         self.raw.set_tags(&tags)?;
         Ok(self)
     }
@@ -47,7 +69,26 @@ impl Bindable<super::Taos> for Stmt {
         &mut self,
         params: &[taos_query::common::ColumnView],
     ) -> Result<&mut Self, Self::Error> {
-        let params: Vec<DropMultiBind> = params.iter().map(|c| c.into()).collect_vec();
+        let broadcast = broadcast_bind(params);
+        let params = broadcast.as_deref().unwrap_or(params);
+        validate_bind(self.bound_columns(), params)?;
+        if self.rate_limiter.is_some() {
+            self.pending.0 += params.first().map_or(0, |c| c.len());
+            self.pending.1 += params.iter().map(|c| c.raw_len()).sum::<usize>();
+        }
+        // null views are allocated to be bound, they're kept until the params are copied.
+        let nulls = params
+            .iter()
+            .map(|c| match c {
+                taos_query::common::ColumnView::Null(view) => Some(view.to_column_view()),
+                _ => None,
+            })
+            .collect_vec();
+        let params: Vec<DropMultiBind> = params
+            .iter()
+            .zip(&nulls)
+            .map(|(c, null)| null.as_ref().unwrap_or(c).into())
+            .collect_vec();
         self.raw
             .bind_param_batch(unsafe { std::mem::transmute(params.as_slice()) })?;
         Ok(self)
@@ -59,16 +100,54 @@ impl Bindable<super::Taos> for Stmt {
     }
 
     fn execute(&mut self) -> Result<usize, Self::Error> {
+        let (rows, bytes) = std::mem::take(&mut self.pending);
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire_blocking(rows, bytes);
+        }
         self.raw.execute().map_err(Into::into)
     }
 
     fn result_set(&mut self) -> Result<<super::Taos as Queryable>::ResultSet, Self::Error> {
+        if self.raw.is_insert()? {
+            return Err(RawError::from_string(
+                "insert statements have no result set, use affected rows instead",
+            )
+            .into());
+        }
         self.raw.use_result().map_err(Into::into)
     }
 
     fn affected_rows(&self) -> usize {
         self.raw.affected_rows() as _
     }
+
+    fn bound_columns(&self) -> &[StmtField] {
+        self.fields.as_deref().unwrap_or_default()
+    }
+
+    fn bound_tags(&self) -> &[StmtField] {
+        self.tags.as_deref().unwrap_or_default()
+    }
+}
+
+impl Stmt {
+    /// Get column parameters and tags if they are not known yet, they're unavailable before
+    /// the table name is set for `insert into ? ...`.
+    fn load_fields(&mut self) {
+        if self.fields.is_none() {
+            match self.raw.col_fields() {
+                Ok(fields) => self.fields = Some(fields),
+                Err(err) => log::trace!("column fields of stmt are unknown: {err}"),
+            }
+        }
+        if self.tags.is_none() {
+            match self.raw.tag_fields() {
+                Ok(tags) if !tags.is_empty() => self.tags = Some(tags),
+                Ok(_) => (),
+                Err(err) => log::trace!("tag fields of stmt are unknown: {err}"),
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -209,6 +288,58 @@ impl RawStmt {
             ((type_ as u8).into(), bytes)
         )
     }
+    /// Column parameters of the prepared insert statement.
+    #[cfg(not(taos_v2))]
+    pub fn col_fields(&self) -> Result<Vec<StmtField>, RawError> {
+        let mut num = 0;
+        let mut fields = std::ptr::null_mut();
+        err_or!(
+            self,
+            taos_stmt_get_col_fields(self.as_ptr(), &mut num as _, &mut fields as _)
+        )?;
+        if fields.is_null() {
+            return Ok(Vec::new());
+        }
+        let res = unsafe { std::slice::from_raw_parts(fields, num as usize) }
+            .iter()
+            .map(StmtField::from)
+            .collect();
+        unsafe { taos_stmt_reclaim_fields(self.as_ptr(), fields) };
+        Ok(res)
+    }
+
+    /// Column parameters are not available in v2.
+    #[cfg(taos_v2)]
+    pub fn col_fields(&self) -> Result<Vec<StmtField>, RawError> {
+        Ok(Vec::new())
+    }
+
+    /// Tags of the prepared insert statement with a super table.
+    #[cfg(not(taos_v2))]
+    pub fn tag_fields(&self) -> Result<Vec<StmtField>, RawError> {
+        let mut num = 0;
+        let mut fields = std::ptr::null_mut();
+        err_or!(
+            self,
+            taos_stmt_get_tag_fields(self.as_ptr(), &mut num as _, &mut fields as _)
+        )?;
+        if fields.is_null() {
+            return Ok(Vec::new());
+        }
+        let res = unsafe { std::slice::from_raw_parts(fields, num as usize) }
+            .iter()
+            .map(StmtField::from)
+            .collect();
+        unsafe { taos_stmt_reclaim_fields(self.as_ptr(), fields) };
+        Ok(res)
+    }
+
+    /// Tags are not available in v2.
+    #[cfg(taos_v2)]
+    pub fn tag_fields(&self) -> Result<Vec<StmtField>, RawError> {
+        Ok(Vec::new())
+    }
+
     #[inline]
     pub fn bind_param(&mut self, bind: &[TaosBind]) -> Result<(), RawError> {
         err_or!(self, taos_stmt_bind_param(self.as_ptr(), bind.as_ptr()))
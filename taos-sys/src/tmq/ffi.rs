@@ -19,7 +19,7 @@ impl tmq_resp_err_t {
     pub fn ok_or(self, s: impl Into<Cow<'static, str>>) -> Result<(), RawError> {
         match self {
             Self(0) => Ok(()),
-            _ => Err(RawError::from_string(s.into())),
+            Self(code) => Err(RawError::new(code, s.into())),
         }
     }
 }
@@ -46,6 +46,17 @@ pub struct tmq_message_t {
     _unused: [u8; 0],
 }
 
+/// Offsets of a vgroup, from `tmq_get_topic_assignment`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_snake_case)]
+pub struct tmq_topic_assignment {
+    pub vgId: i32,
+    pub currentOffset: i64,
+    pub begin: i64,
+    pub end: i64,
+}
+
 #[repr(C)]
 pub enum tmq_conf_res_t {
     Unknown = -2,
@@ -142,6 +153,35 @@ extern "C" {
     pub fn tmq_get_res_type(res: *mut TAOS_RES) -> tmq_res_t;
 }
 
+#[cfg(taos_tmq_assignment)]
+extern "C" {
+    pub fn tmq_get_vgroup_offset(res: *mut TAOS_RES) -> i64;
+    pub fn tmq_get_topic_assignment(
+        tmq: *mut tmq_t,
+        topic: *const c_char,
+        assignment: *mut *mut tmq_topic_assignment,
+        num: *mut i32,
+    ) -> i32;
+    pub fn tmq_free_assignment(assignment: *mut tmq_topic_assignment);
+}
+
+#[cfg(taos_tmq_offset_seek)]
+extern "C" {
+    pub fn tmq_offset_seek(tmq: *mut tmq_t, topic: *const c_char, vg_id: i32, offset: i64) -> i32;
+}
+
+#[cfg(taos_tmq_commit_offset)]
+extern "C" {
+    pub fn tmq_commit_offset_async(
+        tmq: *mut tmq_t,
+        topic: *const c_char,
+        vg_id: i32,
+        offset: i64,
+        cb: tmq_commit_cb,
+        param: *mut c_void,
+    );
+}
+
 #[cfg(taos_write_raw_block_with_fields)]
 extern "C" {
     pub fn taos_write_raw_block_with_fields(
@@ -3,7 +3,7 @@ pub(crate) mod ffi;
 use std::{
     ffi::{CStr, CString},
     fmt::Debug,
-    str::FromStr,
+    sync::{Arc, PoisonError, RwLock},
     time::Duration,
 };
 
@@ -14,7 +14,7 @@ use taos_query::{
     prelude::tokio,
     tmq::{
         AsAsyncConsumer, AsConsumer, AsyncOnSync, IsAsyncData, IsMeta, IsOffset, MessageSet,
-        Timeout, VGroupId,
+        PollInterval, Timeout, VGroupId, Watermarks,
     },
     Dsn, IntoDsn, RawBlock, TBuilder,
 };
@@ -53,6 +53,15 @@ impl RawRes {
         }
     }
 
+    /// Offset of the message in its vgroup, `None` if the client does not support it.
+    #[inline]
+    pub fn tmq_vgroup_offset(&self) -> Option<i64> {
+        #[cfg(taos_tmq_assignment)]
+        return Some(unsafe { tmq_get_vgroup_offset(self.as_ptr()) });
+        #[cfg(not(taos_tmq_assignment))]
+        return None;
+    }
+
     #[inline]
     pub fn tmq_table_name(&self) -> Option<&str> {
         unsafe {
@@ -113,6 +122,8 @@ pub struct TmqBuilder {
     dsn: Dsn,
     conf: Conf,
     timeout: Timeout,
+    poll_interval: Duration,
+    watermark_interval: Option<Duration>,
 }
 
 unsafe impl Send for TmqBuilder {}
@@ -124,20 +135,36 @@ impl TBuilder for TmqBuilder {
     type Error = RawError;
 
     fn available_params() -> &'static [&'static str] {
-        &["group.id", "client.id", "timeout", "enable.auto.commit"]
+        &[
+            "group.id",
+            "client.id",
+            "timeout",
+            "enable.auto.commit",
+            Watermarks::PARAM,
+            PollInterval::PARAM,
+        ]
     }
 
     fn from_dsn<D: IntoDsn>(dsn: D) -> Result<Self, Self::Error> {
         let mut dsn = dsn
             .into_dsn()
             .map_err(|e| RawError::from_string(format!("Parse dsn error: {}", e)))?;
+        // not a native config.
+        let watermarks = Watermarks::from_param(dsn.remove(Watermarks::PARAM).as_deref())?;
+        let poll_interval = PollInterval::from_param(dsn.remove(PollInterval::PARAM).as_deref())?;
         let conf = Conf::from_dsn(&dsn)?;
-        let timeout = if let Some(timeout) = dsn.params.remove("timeout") {
-            Timeout::from_str(&timeout).map_err(RawError::from_any)?
+        let timeout = if let Some(timeout) = dsn.remove("timeout") {
+            Timeout::from_param("timeout", &timeout)?
         } else {
             Timeout::from_millis(500)
         };
-        Ok(Self { dsn, conf, timeout })
+        Ok(Self {
+            dsn,
+            conf,
+            timeout,
+            poll_interval: poll_interval.interval(),
+            watermark_interval: watermarks.interval(),
+        })
     }
 
     fn client_version() -> &'static str {
@@ -156,6 +183,9 @@ impl TBuilder for TmqBuilder {
         self.conf.build().map(|tmq| Consumer {
             tmq,
             timeout: self.timeout,
+            poll_interval: self.poll_interval,
+            watermarks: Watermarks::new(self.watermark_interval),
+            closed: Arc::default(),
         })
     }
 
@@ -168,10 +198,10 @@ impl TBuilder for TmqBuilder {
     }
 }
 
-/// Consumer offset.
+/// Consumer offset, with the begin offset and high watermark of the vgroup at receive time.
 ///
 /// When offset is dropped, the message is destroyed.
-pub struct Offset(RawRes);
+pub struct Offset(RawRes, Option<(i64, i64)>);
 
 unsafe impl Send for Offset {}
 unsafe impl Sync for Offset {}
@@ -203,6 +233,15 @@ impl IsOffset for Offset {
             .tmq_vgroup_id()
             .expect("a message should belong to a vgroup")
     }
+    fn offset(&self) -> Option<i64> {
+        self.0.tmq_vgroup_offset()
+    }
+    fn begin(&self) -> Option<i64> {
+        self.1.map(|(begin, _)| begin)
+    }
+    fn high_watermark(&self) -> Option<i64> {
+        self.1.map(|(_, end)| end)
+    }
 }
 
 impl Drop for Offset {
@@ -215,6 +254,10 @@ impl Drop for Offset {
 pub struct Consumer {
     tmq: RawTmq,
     timeout: Timeout,
+    poll_interval: Duration,
+    watermarks: Watermarks,
+    /// Set when the handle is closed, polls in blocking threads hold it to not run after that.
+    closed: Arc<RwLock<bool>>,
 }
 
 unsafe impl Send for Consumer {}
@@ -222,11 +265,81 @@ unsafe impl Sync for Consumer {}
 
 impl Drop for Consumer {
     fn drop(&mut self) {
+        // wait for the poll in flight, at most a poll interval.
+        let mut closed = self.closed.write().unwrap_or_else(PoisonError::into_inner);
+        *closed = true;
         self.tmq.unsubscribe();
         self.tmq.close();
     }
 }
 
+impl Consumer {
+    /// Offsets of each vgroup of a subscribed topic, empty if the client does not support it.
+    pub fn assignments(&self, topic: &str) -> Result<Vec<taos_query::tmq::Assignment>, RawError> {
+        self.tmq.assignments(topic)
+    }
+
+    /// Move the offset of the consumer in a vgroup of `topic`, eg. to its begin offset to
+    /// consume it again. It fails if the client does not support it.
+    pub fn offset_seek(
+        &self,
+        topic: &str,
+        vgroup_id: VGroupId,
+        offset: i64,
+    ) -> Result<(), RawError> {
+        self.tmq.offset_seek(topic, vgroup_id, offset)
+    }
+
+    /// Commit `offset` of a vgroup of `topic`, eg. an offset persisted by the application after
+    /// its sink acknowledged the messages before it. It fails if the client does not support it.
+    pub async fn commit_offset(
+        &self,
+        topic: &str,
+        vgroup_id: VGroupId,
+        offset: i64,
+    ) -> Result<(), RawError> {
+        self.tmq.commit_offset(topic, vgroup_id, offset).await
+    }
+
+    /// Commit the offset of exactly the received message of `offset`, other messages polled
+    /// after it are not committed.
+    pub async fn commit_message(&self, offset: &Offset) -> Result<(), RawError> {
+        let (Some(topic), Some(position)) = (offset.0.tmq_topic_name(), offset.offset()) else {
+            return Err(RawError::from_string("offset of the message is unknown"));
+        };
+        self.commit_offset(topic, offset.vgroup_id(), position)
+            .await
+    }
+
+    /// Poll in a blocking thread, `None` if no message in `timeout` or the consumer is closed.
+    async fn poll_blocking(&self, timeout: Duration) -> Option<RawRes> {
+        let (tmq, closed) = (self.tmq, self.closed.clone());
+        tokio::task::spawn_blocking(move || {
+            let closed = closed.read().unwrap_or_else(PoisonError::into_inner);
+            if *closed {
+                return None;
+            }
+            tmq.poll_timeout(timeout.as_millis() as i64)
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Offset of a received message, the watermarks of the topic are refreshed if stale.
+    fn offset_of(&self, raw: RawRes) -> Offset {
+        let watermark = match (raw.tmq_topic_name(), raw.tmq_vgroup_id()) {
+            (Some(topic), Some(vgroup_id)) => {
+                if self.watermarks.is_stale(topic) {
+                    self.watermarks.update(topic, self.tmq.assignments(topic));
+                }
+                self.watermarks.get(topic, vgroup_id)
+            }
+            _ => None,
+        };
+        Offset(raw, watermark)
+    }
+}
+
 pub struct Messages {
     tmq: RawTmq,
     timeout: Option<Duration>,
@@ -238,7 +351,7 @@ impl Iterator for Messages {
     fn next(&mut self) -> Option<Self::Item> {
         self.tmq
             .poll_timeout(self.timeout.map(|t| t.as_millis() as i64).unwrap_or(-1))
-            .map(|raw| (Offset(raw), MessageSet::from(raw)))
+            .map(|raw| (Offset(raw, None), MessageSet::from(raw)))
     }
 }
 
@@ -388,7 +501,7 @@ impl AsConsumer for Consumer {
     > {
         Ok(self.tmq.poll_timeout(timeout.as_raw_timeout()).map(|raw| {
             (
-                Offset(raw),
+                self.offset_of(raw),
                 match raw.tmq_message_type() {
                     tmq_res_t::TMQ_RES_INVALID => unreachable!(),
                     tmq_res_t::TMQ_RES_DATA => taos_query::tmq::MessageSet::Data(Data::new(raw)),
@@ -438,58 +551,15 @@ impl AsAsyncConsumer for Consumer {
         Self::Error,
     > {
         log::trace!("waiting for next message");
-        let res = match timeout {
-            Timeout::Never | Timeout::None => {
-                let timeout = Duration::MAX;
-                let sleep = tokio::time::sleep(timeout);
-                tokio::pin!(sleep);
-                tokio::select! {
-                    _ = &mut sleep, if !sleep.is_elapsed() => {
-                       Ok(None)
-                    }
-                    raw = self.tmq.poll_async() => {
-                        let message =    (
-                            Offset(raw),
-                            match raw.tmq_message_type() {
-                                tmq_res_t::TMQ_RES_INVALID => unreachable!(),
-                                tmq_res_t::TMQ_RES_DATA => taos_query::tmq::MessageSet::Data(Data::new(raw)),
-                                tmq_res_t::TMQ_RES_TABLE_META => {
-                                    taos_query::tmq::MessageSet::Meta(Meta::new(raw))
-                                }
-                                tmq_res_t::TMQ_RES_METADATA => taos_query::tmq::MessageSet::MetaData(Meta::new(raw), Data::new(raw))
-                            },
-                        );
-                        Ok(Some(message))
-                    }
+        let raw = match timeout {
+            Timeout::Never | Timeout::None => loop {
+                if let Some(raw) = self.poll_blocking(self.poll_interval).await {
+                    break Some(raw);
                 }
-            }
-            Timeout::Duration(timeout) => {
-                let sleep = tokio::time::sleep(timeout);
-                tokio::pin!(sleep);
-                tokio::select! {
-                    _ = &mut sleep, if !sleep.is_elapsed() => {
-                       Ok(None)
-                    }
-                    raw = self.tmq.poll_async() => {
-                        let message =    (
-                            Offset(raw),
-                            match raw.tmq_message_type() {
-                                tmq_res_t::TMQ_RES_INVALID => unreachable!(),
-                                tmq_res_t::TMQ_RES_DATA => taos_query::tmq::MessageSet::Data(Data::new(raw)),
-                                tmq_res_t::TMQ_RES_TABLE_META => {
-                                    taos_query::tmq::MessageSet::Meta(Meta::new(raw))
-                                }
-                                tmq_res_t::TMQ_RES_METADATA => taos_query::tmq::MessageSet::MetaData(Meta::new(raw), Data::new(raw))
-                            },
-                        );
-                        Ok(Some(message))
-                    }
-                }
-            }
+            },
+            Timeout::Duration(timeout) => self.poll_blocking(timeout).await,
         };
-        log::trace!("waiting for next message");
-        res
-
+        Ok(raw.map(|raw| (self.offset_of(raw), MessageSet::from(raw))))
     }
 
     async fn commit(&self, offset: Self::Offset) -> Result<(), Self::Error> {
@@ -499,6 +569,10 @@ impl AsAsyncConsumer for Consumer {
     fn default_timeout(&self) -> Timeout {
         self.timeout
     }
+
+    fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -560,7 +634,7 @@ mod tests {
                         let raw = raw?;
                         dbg!(raw.table_name().unwrap());
                         let (nrows, ncols) = (raw.nrows(), raw.ncols());
-                        for col in raw.columns() {
+                        for (_, col) in raw.columns() {
                             for value in col {
                                 print!("{}\t", value);
                             }
@@ -580,7 +654,7 @@ mod tests {
                         let raw = raw?;
                         dbg!(raw.table_name().unwrap());
                         let (nrows, ncols) = (raw.nrows(), raw.ncols());
-                        for col in raw.columns() {
+                        for (_, col) in raw.columns() {
                             for value in col {
                                 print!("{}\t", value);
                             }
@@ -678,7 +752,7 @@ mod tests {
                     for raw in data {
                         let raw = raw?;
                         let (nrows, ncols) = (raw.nrows(), raw.ncols());
-                        for col in raw.columns() {
+                        for (_, col) in raw.columns() {
                             for value in col {
                                 print!("{}\t", value);
                             }
@@ -693,7 +767,7 @@ mod tests {
                     for raw in data {
                         let raw = raw?;
                         let (nrows, ncols) = (raw.nrows(), raw.ncols());
-                        for col in raw.columns() {
+                        for (_, col) in raw.columns() {
                             for value in col {
                                 print!("{}\t", value);
                             }
@@ -3,10 +3,11 @@ pub(super) use list::Topics;
 pub(super) use tmq::RawTmq;
 
 pub(super) mod tmq {
-    use std::{os::raw::c_void, time::Duration};
+    use std::os::raw::c_void;
 
     use itertools::Itertools;
     use taos_query::prelude::tokio;
+    use taos_query::tmq::{Assignment, VGroupId};
 
     use crate::{RawError, RawRes};
 
@@ -35,6 +36,123 @@ pub(super) mod tmq {
             tl
         }
 
+        /// Offsets of each vgroup of a subscribed topic, empty if the client does not support it.
+        #[cfg(taos_tmq_assignment)]
+        pub fn assignments(&self, topic: &str) -> Result<Vec<Assignment>, RawError> {
+            let topic = std::ffi::CString::new(topic).map_err(RawError::from_any)?;
+            let mut ptr = std::ptr::null_mut();
+            let mut num = 0;
+            let code =
+                unsafe { tmq_get_topic_assignment(self.0, topic.as_ptr(), &mut ptr, &mut num) };
+            if code != 0 {
+                return Err(RawError::new(
+                    code,
+                    format!("get assignment of topic {topic:?} failed"),
+                ));
+            }
+            if ptr.is_null() {
+                return Ok(Vec::new());
+            }
+            let assignments = unsafe { std::slice::from_raw_parts(ptr, num as usize) }
+                .iter()
+                .map(|a| Assignment {
+                    vgroup_id: a.vgId,
+                    offset: a.currentOffset,
+                    begin: a.begin,
+                    end: a.end,
+                })
+                .collect();
+            unsafe { tmq_free_assignment(ptr) };
+            Ok(assignments)
+        }
+
+        #[cfg(not(taos_tmq_assignment))]
+        pub fn assignments(&self, _topic: &str) -> Result<Vec<Assignment>, RawError> {
+            Ok(Vec::new())
+        }
+
+        /// Move the offset of the consumer in a vgroup of `topic`, the next poll of the vgroup
+        /// starts from `offset`. Offsets out of the range kept by the vgroup are rejected.
+        #[cfg(taos_tmq_offset_seek)]
+        pub fn offset_seek(
+            &self,
+            topic: &str,
+            vgroup_id: VGroupId,
+            offset: i64,
+        ) -> Result<(), RawError> {
+            let c_topic = std::ffi::CString::new(topic).map_err(RawError::from_any)?;
+            let code = unsafe { tmq_offset_seek(self.0, c_topic.as_ptr(), vgroup_id, offset) };
+            if code != 0 {
+                return Err(RawError::new(
+                    code,
+                    format!("seek vgroup {vgroup_id} of topic {topic} to offset {offset} failed"),
+                ));
+            }
+            Ok(())
+        }
+
+        #[cfg(not(taos_tmq_offset_seek))]
+        pub fn offset_seek(
+            &self,
+            _topic: &str,
+            _vgroup_id: VGroupId,
+            _offset: i64,
+        ) -> Result<(), RawError> {
+            Err(RawError::from_string(
+                "offset seek is not supported by the native client",
+            ))
+        }
+
+        /// Commit `offset` of a vgroup of `topic`, the result of the callback is awaited.
+        #[cfg(taos_tmq_commit_offset)]
+        pub async fn commit_offset(
+            &self,
+            topic: &str,
+            vgroup_id: VGroupId,
+            offset: i64,
+        ) -> Result<(), RawError> {
+            use tokio::sync::oneshot::{channel, Sender};
+
+            unsafe extern "C" fn tmq_commit_offset_cb(
+                _tmq: *mut tmq_t,
+                resp: tmq_resp_err_t,
+                param: *mut c_void,
+            ) {
+                let sender = Box::from_raw(param as *mut Sender<tmq_resp_err_t>);
+                log::trace!("commit offset callback: {resp:?}");
+                let _ = sender.send(resp);
+            }
+
+            let c_topic = std::ffi::CString::new(topic).map_err(RawError::from_any)?;
+            let (sender, rx) = channel::<tmq_resp_err_t>();
+            unsafe {
+                tmq_commit_offset_async(
+                    self.0,
+                    c_topic.as_ptr(),
+                    vgroup_id,
+                    offset,
+                    tmq_commit_offset_cb,
+                    Box::into_raw(Box::new(sender)) as _,
+                )
+            }
+            let resp = rx.await.map_err(RawError::from_any)?;
+            resp.ok_or(format!(
+                "commit offset {offset} of vgroup {vgroup_id} of topic {topic} failed"
+            ))
+        }
+
+        #[cfg(not(taos_tmq_commit_offset))]
+        pub async fn commit_offset(
+            &self,
+            _topic: &str,
+            _vgroup_id: VGroupId,
+            _offset: i64,
+        ) -> Result<(), RawError> {
+            Err(RawError::from_string(
+                "commit offset is not supported by the native client",
+            ))
+        }
+
         pub fn commit_sync(&self, msg: RawRes) -> Result<(), RawError> {
             unsafe { tmq_commit_sync(self.0, msg.0 as _) }.ok_or("commit failed")
         }
@@ -113,35 +231,6 @@ pub(super) mod tmq {
             }
         }
 
-        pub async fn poll_async(&self) -> RawRes {
-            let elapsed = std::time::Instant::now();
-
-            loop {
-                // poll with 50ms timeout.
-                // let ptr = UnsafeCell::new(self.0);
-                log::trace!("try poll next message with 200ms timeout");
-                let raw = *self;
-                let res = tokio::task::spawn_blocking(move || {
-                    let raw = raw;
-                    let res = unsafe { tmq_consumer_poll(raw.0, 200) };
-                    if res.is_null() {
-                        None
-                    } else {
-                        Some(RawRes(res))
-                    }
-                    // result
-                })
-                .await
-                .unwrap_or_default();
-                if let Some(res) = res {
-                    log::trace!("received tmq message in {:?}", elapsed.elapsed());
-                    break res;
-                } else {
-                    tokio::time::sleep(Duration::from_millis(1)).await;
-                }
-            }
-        }
-
         pub fn unsubscribe(&mut self) {
             unsafe {
                 log::trace!("close consumer");
@@ -307,6 +396,7 @@ pub(super) mod conf {
 
         pub(crate) fn build(&self) -> Result<RawTmq, RawError> {
             unsafe {
+                crate::set_config::mark_initialized();
                 let mut err = [0; 256];
                 let tmq = tmq_consumer_new(self.0, err.as_mut_ptr() as _, 255);
                 if err[0] != 0 {
@@ -65,3 +65,29 @@ impl From<Field> for TAOS_FIELD {
         field
     }
 }
+
+/// Parameter field of a prepared statement, returned by `taos_stmt_get_col_fields`.
+#[cfg(not(taos_v2))]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TAOS_FIELD_E {
+    pub name: [u8; 65usize],
+    pub type_: i8,
+    pub precision: u8,
+    pub scale: u8,
+    pub bytes: i32,
+}
+
+#[cfg(not(taos_v2))]
+impl From<&TAOS_FIELD_E> for taos_query::stmt::StmtField {
+    fn from(field: &TAOS_FIELD_E) -> Self {
+        let name = unsafe { CStr::from_ptr(field.name.as_ptr() as _) };
+        let ty = (field.type_ as u8).into();
+        let stmt_field = Self::new(name.to_string_lossy(), ty, field.bytes as _);
+        if ty == Ty::Timestamp && field.precision <= 2 {
+            stmt_field.with_precision(taos_query::common::Precision::from_u8(field.precision))
+        } else {
+            stmt_field
+        }
+    }
+}
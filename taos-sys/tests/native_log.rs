@@ -0,0 +1,28 @@
+use taos_sys::TaosBuilder;
+
+#[test]
+fn native_log_dir() -> anyhow::Result<()> {
+    use taos_query::prelude::sync::*;
+
+    let dir = std::env::temp_dir().join("taos-sys-native-log");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    let builder = TaosBuilder::from_dsn("taos://localhost:6030/")?.native_log(LogConfig {
+        dir: Some(dir.clone()),
+        debug_flag: Some(LogConfig::DEBUG),
+        ..Default::default()
+    })?;
+    let client = builder.build()?;
+    client.exec("select server_version()")?;
+
+    let files = std::fs::read_dir(&dir)?.count();
+    assert!(files > 0, "no log files in {}", dir.display());
+
+    // The native client is initialized.
+    let err = TaosBuilder::from_dsn("taos://localhost:6030/")?
+        .native_log(LogConfig::default())
+        .unwrap_err();
+    assert!(err.to_string().contains("before the first connection"));
+    Ok(())
+}
@@ -111,3 +111,35 @@ fn ws_sync_json() -> anyhow::Result<()> {
     assert_eq!(client.exec(format!("drop database {db}"))?, 0);
     Ok(())
 }
+
+#[tokio::test]
+async fn exec_many_chained() -> anyhow::Result<()> {
+    use taos_query::prelude::*;
+    let client = TaosBuilder::from_dsn("taos://localhost:6030/")?.build()?;
+    let db = "sys_exec_many_chained";
+    client
+        .exec_many([
+            format!("drop database if exists {db}"),
+            format!("create database {db}"),
+            format!("create table {db}.tb1(ts timestamp, v int)"),
+        ])
+        .await?;
+    let affected = client
+        .exec_many((0..10).map(|i| format!("insert into {db}.tb1 values({i}, {i})")))
+        .await?;
+    assert_eq!(affected, 10);
+
+    let err = client
+        .exec_many([
+            format!("insert into {db}.tb1 values(100, 100)"),
+            format!("insert into {db}.not_exists values(101, 101)"),
+            format!("insert into {db}.tb1 values(102, 102)"),
+        ])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("statement #1 failed"));
+
+    assert_eq!(client.exec_many(Vec::<String>::new()).await?, 0);
+    client.exec(format!("drop database {db}")).await?;
+    Ok(())
+}
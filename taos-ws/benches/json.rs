@@ -0,0 +1,64 @@
+#![feature(test)]
+
+extern crate test;
+
+use serde::Deserialize;
+use test::Bencher;
+
+/// Envelope of a query response, in the shape of the ones parsed by the websocket reader.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Recv {
+    code: i32,
+    message: Option<String>,
+    req_id: u64,
+    #[serde(flatten)]
+    data: Data,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Data {
+    Query {
+        id: u64,
+        fields_count: usize,
+        fields_names: Vec<String>,
+        fields_types: Vec<u8>,
+        fields_lengths: Vec<u32>,
+        precision: u8,
+        timing: u64,
+    },
+}
+
+/// A query response of 64 columns.
+fn query_frame() -> String {
+    let names: Vec<_> = (0..64).map(|i| format!("\"column_{i}\"")).collect();
+    let types: Vec<_> = (0..64).map(|i| (i % 14 + 1).to_string()).collect();
+    let lengths: Vec<_> = (0..64).map(|i| (8 + i % 4 * 16).to_string()).collect();
+    format!(
+        r#"{{"code":0,"message":"","action":"query","req_id":42,"id":7,"is_update":false,"affected_rows":0,"fields_count":64,"fields_names":[{}],"fields_types":[{}],"fields_lengths":[{}],"precision":0,"timing":1234567}}"#,
+        names.join(","),
+        types.join(","),
+        lengths.join(",")
+    )
+}
+
+#[bench]
+fn bench_query_frame_serde_json(b: &mut Bencher) {
+    let frame = query_frame();
+    b.iter(|| {
+        let bytes = frame.clone().into_bytes();
+        serde_json::from_slice::<Recv>(&bytes).unwrap()
+    });
+}
+
+#[cfg(feature = "simd-json")]
+#[bench]
+fn bench_query_frame_simd_json(b: &mut Bencher) {
+    let frame = query_frame();
+    b.iter(|| {
+        let mut bytes = frame.clone().into_bytes();
+        simd_json::serde::from_slice::<Recv>(&mut bytes).unwrap()
+    });
+}
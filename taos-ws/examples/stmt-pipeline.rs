@@ -0,0 +1,68 @@
+//! Insert into 1000 child tables by websocket stmt, sequentially and pipelined.
+//!
+//! ```sh
+//! cargo run -p taos-ws --example stmt-pipeline -- ws://localhost:6041
+//! ```
+use std::time::Instant;
+
+use taos_query::common::{ColumnView, Value};
+use taos_query::prelude::Bindable;
+use taos_query::{AsyncQueryable, TBuilder};
+use taos_ws::{Stmt, TaosBuilder};
+
+const TABLES: i32 = 1000;
+
+fn columns(i: i32) -> [ColumnView; 2] {
+    [
+        ColumnView::from_millis_timestamp(vec![1_700_000_000_000]),
+        ColumnView::from_ints(vec![i]),
+    ]
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> anyhow::Result<()> {
+    let dsn = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "ws://localhost:6041".to_string());
+    let taos = TaosBuilder::from_dsn(&dsn)?.build()?;
+    let sql = "insert into ? using meters tags(?) values(?, ?)";
+    for (database, pipelined) in [("stmt_sequential", false), ("stmt_pipelined", true)] {
+        taos.exec_many([
+            format!("drop database if exists {database}"),
+            format!("create database {database}"),
+            format!("create table {database}.meters (ts timestamp, v int) tags(t int)"),
+        ])
+        .await?;
+        let mut stmt = Stmt::from_dsn(format!("{dsn}/{database}")).await?;
+        stmt.s_stmt(sql).await?;
+
+        let start = Instant::now();
+        let affected = if pipelined {
+            let mut pipeline = stmt.pipeline();
+            for i in 0..TABLES {
+                pipeline.set_tbname(&format!("d{i}")).await;
+                pipeline.set_tags(&[Value::Int(i)]).await;
+                pipeline.bind(&columns(i)).await;
+                pipeline.add_batch().await.execute().await;
+            }
+            pipeline
+                .finish()
+                .await
+                .map_err(|(index, err)| anyhow::anyhow!("operation {index} failed: {err}"))?
+        } else {
+            let mut affected = 0;
+            for i in 0..TABLES {
+                stmt.set_tbname(format!("d{i}"))?;
+                stmt.set_tags(&[Value::Int(i)])?;
+                affected += stmt.bind(&columns(i))?.add_batch()?.execute()?;
+            }
+            affected
+        };
+        println!(
+            "{database}: {affected} rows of {TABLES} tables in {:?}",
+            start.elapsed()
+        );
+        taos.exec(format!("drop database {database}")).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Latency of small queries to a local adapter, with and without Nagle's algorithm.
+//!
+//! ```sh
+//! cargo run --release -p taos-ws --example tcp-nodelay -- ws://localhost:6041
+//! ```
+use std::time::{Duration, Instant};
+
+use futures::TryStreamExt;
+use taos_query::{AsyncFetchable, AsyncQueryable, TBuilder};
+use taos_ws::{SocketOptions, TaosBuilder};
+
+const QUERIES: usize = 2000;
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    sorted[(sorted.len() * p / 100).min(sorted.len() - 1)]
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> anyhow::Result<()> {
+    let dsn = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "ws://localhost:6041".to_string());
+    for nodelay in [true, false] {
+        let taos = TaosBuilder::from_dsn(&dsn)?
+            .with_socket_options(SocketOptions::default().with_nodelay(nodelay))
+            .build()?;
+        // warm up the connection.
+        taos.exec("select server_status()").await?;
+
+        let mut latencies = Vec::with_capacity(QUERIES);
+        for _ in 0..QUERIES {
+            let start = Instant::now();
+            let mut rs = taos.query("select server_status()").await?;
+            let _blocks: Vec<_> = rs.blocks().try_collect().await?;
+            latencies.push(start.elapsed());
+        }
+        latencies.sort_unstable();
+        println!(
+            "tcpNoDelay={nodelay}: p50 {:?}, p99 {:?}, max {:?}",
+            percentile(&latencies, 50),
+            percentile(&latencies, 99),
+            latencies[QUERIES - 1]
+        );
+    }
+    Ok(())
+}
@@ -10,7 +10,7 @@ use taos_query::common::Field;
 use taos_query::common::Precision;
 use taos_query::common::Ty;
 use taos_query::prelude::RawError;
-use taos_query::tmq::VGroupId;
+use taos_query::tmq::{Assignment, VGroupId};
 
 use crate::query::infra::ToMessage;
 use crate::query::infra::WsConnReq;
@@ -51,6 +51,9 @@ pub struct TmqInit {
     pub group_id: String,
     pub client_id: Option<String>,
     pub offset_reset: Option<String>,
+    /// `enable.auto.commit` of the consumer, the server default if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_commit: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
@@ -81,6 +84,22 @@ pub enum TmqSend {
     Fetch(MessageArgs),
     FetchBlock(MessageArgs),
     Commit(MessageArgs),
+    Assignment {
+        req_id: ReqId,
+        topic: String,
+    },
+    Seek {
+        req_id: ReqId,
+        topic: String,
+        vgroup_id: VGroupId,
+        offset: i64,
+    },
+    CommitOffset {
+        req_id: ReqId,
+        topic: String,
+        vgroup_id: VGroupId,
+        offset: i64,
+    },
 }
 
 unsafe impl Send for TmqSend {}
@@ -104,6 +123,9 @@ impl TmqSend {
             TmqSend::Fetch(args) => args.req_id,
             TmqSend::FetchBlock(args) => args.req_id,
             TmqSend::Commit(args) => args.req_id,
+            TmqSend::Assignment { req_id, topic: _ } => *req_id,
+            TmqSend::Seek { req_id, .. } => *req_id,
+            TmqSend::CommitOffset { req_id, .. } => *req_id,
         }
     }
 }
@@ -123,6 +145,10 @@ pub struct TmqPoll {
     pub topic: String,
     pub vgroup_id: VGroupId,
     pub message_type: MessageType,
+    /// Offset of the message, not sent by old servers.
+    pub offset: Option<i64>,
+    /// Creation time of the message in milliseconds, not sent by old servers.
+    pub timestamp: Option<i64>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -173,7 +199,18 @@ pub enum TmqRecvData {
     },
     Block(Vec<u32>),
     Commit,
+    Assignment {
+        assignment: Vec<Assignment>,
+    },
+    Seek,
+    CommitOffset,
     Close,
+    /// A response frame over the max message size, the connection is broken by it.
+    #[serde(skip)]
+    MessageTooLarge {
+        size: usize,
+        limit: usize,
+    },
 }
 
 #[serde_as]
@@ -213,6 +250,69 @@ fn test_serde_recv_data() {
     }"#;
     let d: TmqRecv = serde_json::from_str(&json).unwrap();
     let _ = dbg!(d.ok());
+
+    let json = r#"{
+        "code": 0,
+        "message": "",
+        "action": "assignment",
+        "req_id": 2,
+        "timing": 1000,
+        "assignment": [{"vgroup_id": 2, "offset": 3, "begin": 0, "end": 10}]
+    }"#;
+    let d: TmqRecv = serde_json::from_str(json).unwrap();
+    match d.data {
+        TmqRecvData::Assignment { assignment } => {
+            assert_eq!(assignment.len(), 1);
+            assert_eq!((assignment[0].vgroup_id, assignment[0].end), (2, 10));
+        }
+        data => panic!("unexpected {data:?}"),
+    }
+
+    let args = serde_json::to_value(TmqSend::Assignment {
+        req_id: 2,
+        topic: "topic".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+        args,
+        serde_json::json!({"action": "assignment", "args": {"req_id": 2, "topic": "topic"}})
+    );
+
+    let args = serde_json::to_value(TmqSend::Seek {
+        req_id: 3,
+        topic: "topic".to_string(),
+        vgroup_id: 2,
+        offset: 0,
+    })
+    .unwrap();
+    assert_eq!(
+        args,
+        serde_json::json!({
+            "action": "seek",
+            "args": {"req_id": 3, "topic": "topic", "vgroup_id": 2, "offset": 0}
+        })
+    );
+    let json = r#"{"code":0,"message":"","action":"seek","req_id":3,"timing":100}"#;
+    let d: TmqRecv = serde_json::from_str(json).unwrap();
+    assert!(matches!(d.ok(), (3, TmqRecvData::Seek, Ok(()))));
+
+    let args = serde_json::to_value(TmqSend::CommitOffset {
+        req_id: 4,
+        topic: "topic".to_string(),
+        vgroup_id: 2,
+        offset: 10,
+    })
+    .unwrap();
+    assert_eq!(
+        args,
+        serde_json::json!({
+            "action": "commit_offset",
+            "args": {"req_id": 4, "topic": "topic", "vgroup_id": 2, "offset": 10}
+        })
+    );
+    let json = r#"{"code":0,"message":"","action":"commit_offset","req_id":4,"timing":100,"topic":"topic","vgroup_id":2,"offset":10}"#;
+    let d: TmqRecv = serde_json::from_str(json).unwrap();
+    assert!(matches!(d.ok(), (4, TmqRecvData::CommitOffset, Ok(()))));
 }
 
 impl ToMessage for TmqSend {}
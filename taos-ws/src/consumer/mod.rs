@@ -7,36 +7,43 @@ use itertools::Itertools;
 use dashmap::DashMap as HashMap;
 
 use taos_query::block_in_place_or_global;
-use taos_query::common::{JsonMeta, RawMeta};
+use taos_query::common::{JsonMeta, RawData, RawMeta, Timestamp};
 use taos_query::prelude::{Code, RawError};
 use taos_query::tmq::{
-    AsAsyncConsumer, AsConsumer, IsAsyncData, IsAsyncMeta, IsOffset, MessageSet, SyncOnAsync,
-    Timeout,
+    AsAsyncConsumer, AsConsumer, Assignment, IsAsyncData, IsAsyncMeta, IsOffset, MessageSet,
+    PollInterval, SyncOnAsync, Timeout, VGroupId, Watermarks,
 };
 use taos_query::util::InlinableRead;
-use taos_query::{DeError, DsnError, IntoDsn, RawBlock, TBuilder};
+use taos_query::{ConnState, ConnStateNotifier, DeError, DsnError, IntoDsn, RawBlock, TBuilder};
 use thiserror::Error;
 
 use taos_query::prelude::tokio;
 use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
 
 use tokio::time;
+use tokio_tungstenite::tungstenite::error::CapacityError;
+use tokio_tungstenite::tungstenite::protocol::{Message, WebSocketConfig};
 use tokio_tungstenite::tungstenite::Error as WsError;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 
+use crate::json;
+use crate::proxy::{ConnectError, ProxyStage};
 use crate::query::asyn::WS_ERROR_NO;
 use crate::query::infra::{ToMessage, WsConnReq};
-use crate::TaosBuilder;
+use crate::{TaosBuilder, WsStream, CLOSE_TIMEOUT};
 use messages::*;
 
 use std::fmt::Debug;
 use std::result::Result as StdResult;
-use std::str::FromStr;
-use std::sync::atomic::AtomicU64;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 mod messages;
+mod oversized;
+
+use oversized::Oversized;
+pub use oversized::{OversizedMessage, SkippedMessage};
 
 type WsSender = tokio::sync::mpsc::Sender<Message>;
 type WsTmqAgent = Arc<HashMap<ReqId, oneshot::Sender<StdResult<TmqRecvData, RawError>>>>;
@@ -48,6 +55,8 @@ struct WsTmqSender {
     queries: WsTmqAgent,
     #[allow(dead_code)]
     timeout: Timeout,
+    /// Increased after each reconnect.
+    epoch: watch::Receiver<u64>,
 }
 
 impl WsTmqSender {
@@ -78,14 +87,28 @@ impl WsTmqSender {
                 message??
             }
         };
+        if let TmqRecvData::MessageTooLarge { size, limit } = data {
+            return Err(Error::MessageTooLarge { size, limit });
+        }
         Ok(data)
     }
+
+    /// Wait until the connection lost in `epoch` is re-established, `false` if it's closed.
+    async fn wait_reconnected(&self, epoch: u64) -> bool {
+        let mut listener = self.epoch.clone();
+        let reconnected = listener.wait_for(|current| *current > epoch).await;
+        reconnected.is_ok()
+    }
 }
 
 pub struct TmqBuilder {
     info: TaosBuilder,
     conf: TmqInit,
     timeout: Timeout,
+    poll_interval: Duration,
+    watermark_interval: Option<Duration>,
+    max_message_bytes: Option<usize>,
+    oversized: Oversized,
 }
 
 impl TBuilder for TmqBuilder {
@@ -94,7 +117,24 @@ impl TBuilder for TmqBuilder {
     type Error = Error;
 
     fn available_params() -> &'static [&'static str] {
-        &["token", "timeout", "group.id", "client.id"]
+        &[
+            "token",
+            "timeout",
+            "group.id",
+            "client.id",
+            "enable.auto.commit",
+            Watermarks::PARAM,
+            PollInterval::PARAM,
+            "reconnect",
+            "reconnect.max_retries",
+            "reconnect.interval",
+            "max.message.bytes",
+            OversizedMessage::PARAM,
+            "ssl.ca",
+            "ssl.cert",
+            "ssl.key",
+            "ssl.insecure_skip_verify",
+        ]
     }
 
     fn from_dsn<D: IntoDsn>(dsn: D) -> StdResult<Self, Self::Error> {
@@ -126,12 +166,90 @@ impl TBuilder for TmqBuilder {
     }
 }
 
+#[derive(Clone)]
 struct WsMessageBase {
     sender: WsTmqSender,
     message_id: MessageId,
+    topic: String,
+    vgroup_id: VGroupId,
+    offset: Option<i64>,
+    oversized: Oversized,
 }
 
 impl WsMessageBase {
+    /// Send a fetch request of the message, the inner error is the skipped message if its
+    /// response is oversized and the policy is [OversizedMessage::Skip].
+    async fn fetch(&self, msg: TmqSend) -> Result<StdResult<TmqRecvData, SkippedMessage>> {
+        let epoch = *self.sender.epoch.borrow();
+        match self.sender.send_recv(msg).await {
+            Err(Error::MessageTooLarge { size, limit })
+                if self.oversized.policy == OversizedMessage::Skip =>
+            {
+                self.skip(epoch, size, limit).await.map(Err)
+            }
+            data => data.map(Ok),
+        }
+    }
+
+    /// Fetch the whole payload of the message, which is missing if the message is skipped.
+    async fn fetch_payload(&self, msg: TmqSend) -> Result<TmqRecvData> {
+        self.fetch(msg)
+            .await?
+            .map_err(|skipped| Error::MessageTooLarge {
+                size: skipped.size,
+                limit: skipped.limit,
+            })
+    }
+
+    /// Move past the message on the connection re-established after the oversized response in
+    /// `epoch`.
+    async fn skip(&self, epoch: u64, size: usize, limit: usize) -> Result<SkippedMessage> {
+        let too_large = || Error::MessageTooLarge { size, limit };
+        let Some(offset) = self.offset else {
+            log::warn!(
+                "oversized message of {} could not be skipped without offset",
+                self.topic
+            );
+            return Err(too_large());
+        };
+        if !self.sender.wait_reconnected(epoch).await {
+            return Err(too_large());
+        }
+        let next = offset + 1;
+        let commit = TmqSend::CommitOffset {
+            req_id: self.sender.req_id(),
+            topic: self.topic.clone(),
+            vgroup_id: self.vgroup_id,
+            offset: next,
+        };
+        self.sender.send_recv(commit).await?;
+        let seek = TmqSend::Seek {
+            req_id: self.sender.req_id(),
+            topic: self.topic.clone(),
+            vgroup_id: self.vgroup_id,
+            offset: next,
+        };
+        self.sender.send_recv(seek).await?;
+
+        log::warn!(
+            "skipped message at offset {offset} of vgroup {} of topic {}: {}",
+            self.vgroup_id,
+            self.topic,
+            too_large()
+        );
+        let skipped = SkippedMessage {
+            topic: self.topic.clone(),
+            vgroup_id: self.vgroup_id,
+            offset,
+            size,
+            limit,
+        };
+        if let Some(on_skipped) = &self.oversized.on_skipped {
+            on_skipped(&skipped);
+        }
+        Ok(skipped)
+    }
+
     async fn fetch_raw_block(&self) -> Result<Option<RawBlock>> {
         let req_id = self.sender.req_id();
 
@@ -139,7 +257,9 @@ impl WsMessageBase {
             req_id,
             message_id: self.message_id,
         });
-        let data = self.sender.send_recv(msg).await?;
+        let Ok(data) = self.fetch(msg).await? else {
+            return Ok(None);
+        };
         let fetch = if let TmqRecvData::Fetch(fetch) = data {
             fetch
         } else {
@@ -154,9 +274,12 @@ impl WsMessageBase {
             req_id,
             message_id: self.message_id,
         });
-        let data = self.sender.send_recv(msg).await?;
+        let Ok(data) = self.fetch(msg).await? else {
+            return Ok(None);
+        };
         if let TmqRecvData::Bytes(bytes) = data {
-            let mut raw = RawBlock::parse_from_raw_block(bytes, fetch.precision);
+            let mut raw = RawBlock::try_parse_from_raw_block(bytes, fetch.precision)
+                .map_err(RawError::from_any)?;
 
             // for row in 0..raw.nrows() {
             //     for col in 0..raw.ncols() {
@@ -179,7 +302,7 @@ impl WsMessageBase {
             req_id,
             message_id: self.message_id,
         });
-        let data = self.sender.send_recv(msg).await?;
+        let data = self.fetch_payload(msg).await?;
         if let TmqRecvData::FetchJsonMeta { data } = data {
             let json: JsonMeta = serde_json::from_value(data)?;
             return Ok(json);
@@ -187,16 +310,22 @@ impl WsMessageBase {
         unreachable!()
     }
     async fn fetch_raw_meta(&self) -> Result<RawMeta> {
+        self.fetch_raw().await.map(RawMeta::from)
+    }
+    /// Fetch raw payload of the message, it's the same for meta and data messages.
+    async fn fetch_raw(&self) -> Result<RawData> {
         let req_id = self.sender.req_id();
         let msg = TmqSend::FetchRaw(MessageArgs {
             req_id,
             message_id: self.message_id,
         });
-        let data = self.sender.send_recv(msg).await?;
+        let data = self.fetch_payload(msg).await?;
         if let TmqRecvData::Bytes(bytes) = data {
             let message_type = bytes.as_ref().read_u64().unwrap();
-            debug_assert_eq!(message_type, 3, "should be meta message type");
-            let raw = RawMeta::new(bytes.slice(8..)); // first u64 is message type.
+            debug_assert_eq!(message_type, 3, "should be raw message type");
+            // first u64 is message type.
+            let raw = RawData::try_new(bytes.slice(8..))
+                .map_err(|err| RawError::from_string(err.to_string()))?;
             return Ok(raw);
         }
         unreachable!()
@@ -249,8 +378,8 @@ impl Iterator for Data {
 impl IsAsyncData for Data {
     type Error = Error;
 
-    async fn as_raw_data(&self) -> StdResult<taos_query::common::RawData, Self::Error> {
-        todo!()
+    async fn as_raw_data(&self) -> StdResult<RawData, Self::Error> {
+        self.0.fetch_raw().await
     }
 
     async fn fetch_raw_block(&self) -> StdResult<Option<RawBlock>, Self::Error> {
@@ -258,6 +387,8 @@ impl IsAsyncData for Data {
     }
 }
 
+impl SyncOnAsync for Data {}
+
 pub enum WsMessageSet {
     Meta(Meta),
     Data(Data),
@@ -305,7 +436,14 @@ impl Consumer {
                 blocking_time: 0,
             };
 
-            let data = self.sender.send_recv(action).await?;
+            let epoch = *self.sender.epoch.borrow();
+            let data = match self.sender.send_recv(action).await {
+                Err(err) if err.is_conn_lost() && self.sender.wait_reconnected(epoch).await => {
+                    log::warn!("poll again after reconnect: {err}");
+                    continue;
+                }
+                data => data?,
+            };
 
             match data {
                 TmqRecvData::Poll(TmqPoll {
@@ -315,18 +453,28 @@ impl Consumer {
                     topic,
                     vgroup_id,
                     message_type,
+                    offset,
+                    timestamp,
                 }) => {
                     if have_message {
                         let dur = elapsed.elapsed();
+                        let watermark = self.watermark(&topic, vgroup_id).await;
+                        let message = WsMessageBase {
+                            sender: self.sender.clone(),
+                            message_id,
+                            topic: topic.clone(),
+                            vgroup_id,
+                            offset,
+                            oversized: self.oversized.clone(),
+                        };
                         let offset = Offset {
                             message_id,
                             database,
                             topic,
                             vgroup_id,
-                        };
-                        let message = WsMessageBase {
-                            sender: self.sender.clone(),
-                            message_id,
+                            offset,
+                            watermark,
+                            timestamp,
                         };
                         log::trace!("Got message in {}ms", dur.as_millis());
                         break match message_type {
@@ -334,13 +482,7 @@ impl Consumer {
                             MessageType::Data => Ok((offset, MessageSet::Data(Data(message)))),
                             MessageType::MetaData => Ok((
                                 offset,
-                                MessageSet::MetaData(
-                                    Meta(message),
-                                    Data(WsMessageBase {
-                                        sender: self.sender.clone(),
-                                        message_id,
-                                    }),
-                                ),
+                                MessageSet::MetaData(Meta(message.clone()), Data(message)),
                             )),
                             MessageType::Invalid => unreachable!(),
                             // _ => unreachable!(),
@@ -354,6 +496,73 @@ impl Consumer {
             }
         }
     }
+    /// Offsets of each vgroup of a subscribed topic.
+    pub async fn assignments(&self, topic: &str) -> Result<Vec<Assignment>> {
+        let action = TmqSend::Assignment {
+            req_id: self.sender.req_id(),
+            topic: topic.to_string(),
+        };
+        match self.sender.send_recv(action).await? {
+            TmqRecvData::Assignment { assignment } => Ok(assignment),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Move the offset of the consumer in a vgroup of `topic`, the next poll of the vgroup
+    /// starts from `offset`. Offsets out of the range kept by the vgroup are rejected by the
+    /// server.
+    pub async fn offset_seek(
+        &self,
+        topic: &str,
+        vgroup_id: VGroupId,
+        offset: i64,
+    ) -> Result<()> {
+        let action = TmqSend::Seek {
+            req_id: self.sender.req_id(),
+            topic: topic.to_string(),
+            vgroup_id,
+            offset,
+        };
+        match self.sender.send_recv(action).await? {
+            TmqRecvData::Seek => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Commit `offset` of a vgroup of `topic`, eg. an offset persisted by the application after
+    /// its sink acknowledged the messages before it.
+    pub async fn commit_offset(&self, topic: &str, vgroup_id: VGroupId, offset: i64) -> Result<()> {
+        let action = TmqSend::CommitOffset {
+            req_id: self.sender.req_id(),
+            topic: topic.to_string(),
+            vgroup_id,
+            offset,
+        };
+        match self.sender.send_recv(action).await? {
+            TmqRecvData::CommitOffset => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Commit the offset of exactly the received message of `offset`, other messages polled
+    /// after it are not committed.
+    pub async fn commit_message(&self, offset: &Offset) -> Result<()> {
+        let Some(position) = offset.offset else {
+            return Err(RawError::from_string("offset of the message is unknown").into());
+        };
+        self.commit_offset(&offset.topic, offset.vgroup_id, position)
+            .await
+    }
+
+    /// Begin offset and high watermark of a vgroup, refreshed if stale.
+    async fn watermark(&self, topic: &str, vgroup_id: i32) -> Option<(i64, i64)> {
+        if self.watermarks.is_stale(topic) {
+            let assignments = self.assignments(topic).await;
+            self.watermarks.update(topic, assignments);
+        }
+        self.watermarks.get(topic, vgroup_id)
+    }
+
     pub(crate) async fn poll_timeout(
         &self,
         timeout: Duration,
@@ -386,13 +595,15 @@ impl AsAsyncConsumer for Consumer {
         topics: I,
     ) -> Result<()> {
         let req_id = self.sender.req_id();
+        let topics = topics.into_iter().map(Into::into).collect_vec();
         let action = TmqSend::Subscribe {
             req_id,
             req: self.tmq_conf.clone(),
-            topics: topics.into_iter().map(Into::into).collect_vec(),
+            topics: topics.clone(),
             conn: self.conn.clone(),
         };
         self.sender.send_recv(action).await?;
+        *self.topics.lock().unwrap() = Some(topics);
 
         Ok(())
     }
@@ -427,6 +638,14 @@ impl AsAsyncConsumer for Consumer {
     fn default_timeout(&self) -> Timeout {
         self.timeout
     }
+
+    fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    async fn unsubscribe(self) {
+        self.close().await
+    }
 }
 
 impl AsConsumer for Consumer {
@@ -468,270 +687,562 @@ impl TmqBuilder {
             .ok_or_else(|| DsnError::RequireParam("group.id".to_string()))?;
         let client_id = dsn.params.get("client.id").map(ToString::to_string);
         let offset_reset = dsn.params.get("auto.offset.reset").map(ToString::to_string);
+        let auto_commit = dsn.params.get("enable.auto.commit").map(ToString::to_string);
         let timeout = if let Some(timeout) = dsn.get("timeout") {
-            Timeout::from_str(&timeout).map_err(RawError::from_any)?
+            Timeout::from_param("timeout", timeout)?
         } else {
             Timeout::Duration(Duration::from_secs(5))
         };
+        let watermarks = Watermarks::from_param(dsn.get(Watermarks::PARAM).map(String::as_str))?;
+        let poll_interval =
+            PollInterval::from_param(dsn.get(PollInterval::PARAM).map(String::as_str))?;
+        let max_message_bytes = match dsn.get("max.message.bytes") {
+            Some(value) => Some(value.parse().map_err(|_| {
+                DsnError::InvalidParam("max.message.bytes".to_string(), value.to_string())
+            })?),
+            None => None,
+        };
+        let policy =
+            OversizedMessage::from_param(dsn.get(OversizedMessage::PARAM).map(String::as_str))?;
         let conf = TmqInit {
             group_id,
             client_id,
             offset_reset,
+            auto_commit,
         };
 
         Ok(Self {
             info,
             conf,
             timeout,
+            poll_interval: poll_interval.interval(),
+            watermark_interval: watermarks.interval(),
+            max_message_bytes,
+            oversized: Oversized {
+                policy,
+                on_skipped: None,
+            },
         })
     }
 
+    /// Limit the size of response frames, eg. of a message of a huge transaction, same as
+    /// `max.message.bytes` in DSN. Messages over it are handled by [TmqBuilder::on_oversized_message].
+    ///
+    /// The default limit is 64MiB of messages in frames of 16MiB by the websocket library.
+    pub fn max_message_bytes(mut self, limit: usize) -> Self {
+        self.max_message_bytes = Some(limit);
+        self
+    }
+
+    /// Set how messages over [TmqBuilder::max_message_bytes] are handled, fail fetching them by
+    /// default.
+    pub fn on_oversized_message(mut self, policy: OversizedMessage) -> Self {
+        self.oversized.policy = policy;
+        self
+    }
+
+    /// Set a callback to receive messages skipped by [OversizedMessage::Skip], after they are
+    /// committed.
+    pub fn on_message_skipped(
+        mut self,
+        f: impl Fn(&SkippedMessage) + Send + Sync + 'static,
+    ) -> Self {
+        self.oversized.on_skipped = Some(Arc::new(f));
+        self
+    }
+
+    fn ws_config(&self) -> Option<WebSocketConfig> {
+        let limit = self.max_message_bytes?;
+        Some(WebSocketConfig {
+            max_message_size: Some(limit),
+            max_frame_size: Some(limit),
+            ..Default::default()
+        })
+    }
+
+    /// Set a callback to receive connection state changes of consumers built by this builder,
+    /// the server version is empty in [ConnState::Connected] of consumers.
+    ///
+    /// Consumers are re-established only with an enabled [ReconnectPolicy](crate::ReconnectPolicy),
+    /// eg. `reconnect=true` in DSN.
+    pub fn on_state_change(mut self, f: impl Fn(ConnState) + Send + Sync + 'static) -> Self {
+        self.info = self.info.on_state_change(f);
+        self
+    }
+
     async fn build_consumer(&self) -> Result<Consumer> {
+        let state = ConnStateNotifier::new(self.info.state_listener.clone());
+        state.set(ConnState::Connecting);
         let url = self.info.to_tmq_url();
         // let (ws, _) = futures::executor::block_on(connect_async(url))?;
-        let (ws, _) = connect_async(&url).await?;
-        let (mut sender, mut reader) = ws.split();
+        let ws_config = self.ws_config();
+        let ws = match self.info.connect_ws(&url, ws_config).await {
+            Ok(ws) => ws,
+            Err(err) => {
+                let err = Error::from(err);
+                state.close(err.to_string());
+                return Err(err);
+            }
+        };
+        state.set(ConnState::Connected {
+            server_version: String::new(),
+        });
+        // The url is only logged from here, without the token.
+        let url = taos_query::redact_secrets(&url);
 
         let queries = Arc::new(HashMap::<ReqId, tokio::sync::oneshot::Sender<_>>::new());
 
-        let queries_sender = queries.clone();
-        let msg_handler = queries.clone();
-
-        let (ws, mut msg_recv) = tokio::sync::mpsc::channel::<Message>(100);
-        let ws2 = ws.clone();
+        let (ws_sender, msg_recv) = tokio::sync::mpsc::channel::<Message>(100);
 
         // Connection watcher
-        let (tx, mut rx) = watch::channel(false);
-        let mut close_listener = rx.clone();
+        let (tx, rx) = watch::channel(false);
+        let (epoch, epoch_listener) = watch::channel(0);
+        let req_id = Arc::new(AtomicU64::new(1));
+        let topics = Arc::new(Mutex::new(None));
+
+        let conn = TmqConnection {
+            info: self.info.clone(),
+            conf: self.conf.clone(),
+            url,
+            state,
+            queries: queries.clone(),
+            ws: ws_sender.clone(),
+            req_id: req_id.clone(),
+            topics: topics.clone(),
+            epoch,
+            ws_config,
+            oversized: self.oversized.policy,
+        };
+        let task = tokio::spawn(conn.run(ws, msg_recv, rx));
 
-        let sending_url = url.clone();
-        static PING_INTERVAL: u64 = 30;
-        const PING: &'static [u8] = b"TAOSX";
+        let consumer = Consumer {
+            conn: self.info.to_conn_request(),
+            tmq_conf: self.conf.clone(),
+            sender: WsTmqSender {
+                req_id,
+                queries,
+                sender: ws_sender,
+                timeout: Timeout::Duration(Duration::MAX),
+                epoch: epoch_listener,
+            },
+            // fetches,
+            close_signal: tx,
+            task: Some(task),
+            timeout: self.timeout,
+            poll_interval: self.poll_interval,
+            watermarks: Watermarks::new(self.watermark_interval),
+            topics,
+            oversized: self.oversized.clone(),
+        };
 
-        tokio::spawn(async move {
+        Ok(consumer)
+    }
+}
+
+const PING_INTERVAL: u64 = 30;
+const PING: &[u8] = b"TAOSX";
+
+/// The connection task of a consumer, it forwards messages to websocket and re-establishes the
+/// lost connection if enabled by the [ReconnectPolicy](crate::ReconnectPolicy).
+struct TmqConnection {
+    info: TaosBuilder,
+    conf: TmqInit,
+    /// Url without secrets, for logging.
+    url: String,
+    state: ConnStateNotifier,
+    queries: WsTmqAgent,
+    ws: WsSender,
+    req_id: Arc<AtomicU64>,
+    /// Topics subscribed by the consumer.
+    topics: Arc<Mutex<Option<Vec<String>>>>,
+    epoch: watch::Sender<u64>,
+    ws_config: Option<WebSocketConfig>,
+    /// Connections broken by oversized messages are re-established to skip them.
+    oversized: OversizedMessage,
+}
+
+impl TmqConnection {
+    async fn run(
+        self,
+        mut ws: WsStream,
+        mut msg_recv: tokio::sync::mpsc::Receiver<Message>,
+        mut close: watch::Receiver<bool>,
+    ) {
+        loop {
+            let (mut sender, reader) = ws.split();
+            let (reader_close, reader_close_listener) = watch::channel(false);
+            let mut reader_task = tokio::spawn(read_messages(
+                reader,
+                self.queries.clone(),
+                self.ws.clone(),
+                self.url.clone(),
+                reader_close_listener,
+            ));
             let mut interval = time::interval(Duration::from_secs(PING_INTERVAL));
 
-            loop {
+            let reason = 'ws: loop {
                 tokio::select! {
                     _ = interval.tick() => {
                         log::trace!("Check websocket message sender alive");
                         if let Err(err) = sender.send(Message::Ping(PING.to_vec())).await {
-                            log::error!("sending ping message to {sending_url} error: {err:?}");
-                            // let mut keys = Vec::new();
-                            let keys = msg_handler.iter().map(|r| *r.key()).collect_vec();
-
-                            // msg_handler.for_each_async(|k, _| {
-                            //     keys.push(*k);
-                            // }).await;
-                            for k in keys {
-                                if let Some((_, sender)) = msg_handler.remove(&k) {
-                                    let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(),
-                                        format!("WebSocket internal error: {err}"))));
-                                }
-                            }
+                            log::error!("sending ping message to {} error: {err:?}", self.url);
+                            self.fail_all(format!("WebSocket internal error: {err}"));
                         }
                     }
                     Some(msg) = msg_recv.recv() => {
                         if msg.is_close() {
-                            let _ = sender.send(msg).await;
-                            let _ = sender.close().await;
-                            break;
+                            let _ = time::timeout(CLOSE_TIMEOUT, async {
+                                let _ = sender.send(msg).await;
+                                sender.close().await
+                            })
+                            .await;
+                            let _ = reader_close.send(true);
+                            break 'ws None;
                         }
                         log::trace!("send message {msg:?}");
                         if let Err(err) = sender.send(msg).await {
-                            log::error!("sending message to {sending_url} error: {err:?}");
-                            let keys = msg_handler.iter().map(|r| *r.key()).collect_vec();
-                            for k in keys {
-                                if let Some((_, sender)) = msg_handler.remove(&k) {
-                                    let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(),
-                                        format!("WebSocket internal error: {err}"))));
-                                }
-                            }
+                            log::error!("sending message to {} error: {err:?}", self.url);
+                            self.fail_all(format!("WebSocket internal error: {err}"));
                         }
                         log::trace!("send message done");
                     }
-                    _ = rx.changed() => {
-                        let _= sender.send(Message::Close(None)).await;
-                        let _ = sender.close().await;
+                    end = &mut reader_task => {
+                        break 'ws Some(end.unwrap_or_else(|err| ReadEnd::Lost(err.to_string())));
+                    }
+                    _ = close.changed() => {
+                        let _ = time::timeout(CLOSE_TIMEOUT, async {
+                            let _ = sender.send(Message::Close(None)).await;
+                            sender.close().await
+                        })
+                        .await;
+                        let _ = reader_close.send(true);
                         log::trace!("close tmq sender");
-                        break;
+                        break 'ws None;
                     }
                 }
+            };
+            let policy = self.info.reconnect;
+            let (reason, max_retries) = match reason {
+                Some(ReadEnd::Oversized(reason)) if self.oversized == OversizedMessage::Skip => {
+                    (reason, policy.max_retries().max(1))
+                }
+                Some(ReadEnd::Lost(reason) | ReadEnd::Oversized(reason)) if policy.is_enabled() => {
+                    (reason, policy.max_retries())
+                }
+                Some(ReadEnd::Lost(reason) | ReadEnd::Oversized(reason)) => {
+                    self.state.close(reason);
+                    return;
+                }
+                None => {
+                    self.state.close("closed by client");
+                    return;
+                }
+            };
+            log::warn!("tmq connection lost: {reason}");
+            self.fail_all(reason.clone());
+
+            match self.reconnect(&mut close, max_retries).await {
+                Some(Ok(new)) => {
+                    ws = new;
+                    self.epoch.send_modify(|epoch| *epoch += 1);
+                    self.state.set(ConnState::Connected {
+                        server_version: String::new(),
+                    });
+                }
+                Some(Err(_)) => {
+                    self.state.close(reason);
+                    return;
+                }
+                None => {
+                    self.state.close("closed by client");
+                    return;
+                }
             }
-        });
+        }
+    }
 
-        tokio::spawn(async move {
-            let instant = Instant::now();
-            'ws: loop {
-                tokio::select! {
-                    Some(message) = reader.next() => {
-                        match message {
-                            Ok(message) => match message {
-                                Message::Text(text) => {
-                                    log::trace!("json response: {}", text);
-                                    let v: TmqRecv = serde_json::from_str(&text).expect(&text);
-                                    let (req_id, recv, ok) = v.ok();
-                                    match &recv {
-                                        TmqRecvData::Subscribe => {
-                                            log::trace!("subscribe with: {:?}", req_id);
-
-                                            if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                            {
-                                                let _ = sender.send(ok.map(|_|recv));
-                                            }  else {
-                                                log::warn!("subscribe message received but no receiver alive");
-                                            }
-                                        },
-                                        TmqRecvData::Poll(_) => {
-                                            if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                            {
-                                                let _ = sender.send(ok.map(|_|recv));
-                                            }  else {
-                                                log::warn!("poll message received but no receiver alive");
-                                            }
-                                        },
-                                        TmqRecvData::FetchJsonMeta { data }=> {
-                                            log::trace!("fetch json meta data: {:?}", data);
-                                            if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                            {
-                                                let _ = sender.send(ok.map(|_|recv));
-                                            }  else {
-                                                log::warn!("poll message received but no receiver alive");
-                                            }
-                                        }
-                                        TmqRecvData::FetchRaw { meta: _ }=> {
-                                            if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                            {
-                                                let _ = sender.send(ok.map(|_|recv));
-                                            }  else {
-                                                log::warn!("poll message received but no receiver alive");
-                                            }
-                                        }
-                                        TmqRecvData::Commit=> {
-                                            log::trace!("commit done: {:?}", recv);
-                                            if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                            {
-                                                let _ = sender.send(ok.map(|_|recv));
-                                            }  else {
-                                                log::warn!("poll message received but no receiver alive");
-                                            }
-                                        }
-                                        TmqRecvData::Fetch(fetch)=> {
-                                            log::trace!("fetch done: {:?}", fetch);
-                                            if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                            {
-                                                let _ = sender.send(ok.map(|_|recv));
-                                            }  else {
-                                                log::warn!("poll message received but no receiver alive");
-                                            }
-                                        }
-                                        _ => unreachable!("unknown tmq response"),
+    /// Fail all pending requests with `reason`.
+    fn fail_all(&self, reason: String) {
+        let keys = self.queries.iter().map(|r| *r.key()).collect_vec();
+        for k in keys {
+            if let Some((_, sender)) = self.queries.remove(&k) {
+                let _ = sender.send(Err(RawError::new(
+                    WS_ERROR_NO::CONN_CLOSED.as_code(),
+                    reason.clone(),
+                )));
+            }
+        }
+    }
+
+    /// Try to reconnect in `max_retries` attempts, returns `None` if closed by client while
+    /// reconnecting.
+    async fn reconnect(
+        &self,
+        close: &mut watch::Receiver<bool>,
+        max_retries: u32,
+    ) -> Option<Result<WsStream>> {
+        let policy = self.info.reconnect;
+        let mut last = Some(Err(WsError::ConnectionClosed.into()));
+        for attempt in 1..=max_retries {
+            self.state.set(ConnState::Reconnecting { attempt });
+            tokio::select! {
+                _ = time::sleep(policy.backoff(attempt)) => {}
+                _ = close.changed() => return None,
+            }
+            match self.resubscribe().await {
+                Ok(ws) => return Some(Ok(ws)),
+                Err(err) => {
+                    log::warn!("reconnect attempt {attempt} of tmq failed: {err}");
+                    last = Some(Err(err));
+                }
+            }
+        }
+        last
+    }
+
+    /// Open websocket and subscribe to the topics again, the subscription continues from
+    /// offsets committed by the group.
+    async fn resubscribe(&self) -> Result<WsStream> {
+        let mut ws = self
+            .info
+            .connect_ws(&self.info.to_tmq_url(), self.ws_config)
+            .await?;
+        let topics = self.topics.lock().unwrap().clone();
+        let topics = match topics {
+            Some(topics) => topics,
+            None => return Ok(ws),
+        };
+        let subscribe = TmqSend::Subscribe {
+            req_id: self.req_id.fetch_add(1, Ordering::SeqCst),
+            req: self.conf.clone(),
+            topics,
+            conn: self.info.to_conn_request(),
+        };
+        ws.send(subscribe.to_msg()).await?;
+        loop {
+            match time::timeout(Duration::from_secs(5), ws.next()).await {
+                Ok(Some(Ok(Message::Text(text)))) => {
+                    let (_, _, ok) = serde_json::from_str::<TmqRecv>(&text)?.ok();
+                    ok?;
+                    return Ok(ws);
+                }
+                Ok(Some(Ok(_))) => continue,
+                Ok(Some(Err(err))) => Err(err)?,
+                Ok(None) => Err(WsError::ConnectionClosed)?,
+                Err(_) => Err(Error::QueryTimeout("subscribe".to_string()))?,
+            }
+        }
+    }
+}
+
+/// Why the reader of a connection stopped.
+enum ReadEnd {
+    /// The connection is lost or closed.
+    Lost(String),
+    /// A response frame over the max message size broke the connection.
+    Oversized(String),
+}
+
+/// Read responses until the connection is lost or closed.
+async fn read_messages(
+    mut reader: futures::stream::SplitStream<WsStream>,
+    queries_sender: WsTmqAgent,
+    ws2: WsSender,
+    url: String,
+    mut close_listener: watch::Receiver<bool>,
+) -> ReadEnd {
+    let instant = Instant::now();
+    let reason = 'ws: loop {
+        tokio::select! {
+            Some(message) = reader.next() => {
+                match message {
+                    Ok(message) => match message {
+                        Message::Text(text) => {
+                            log::trace!("json response: {}", text);
+                            let mut frame = text.into_bytes();
+                            let v: TmqRecv = json::from_frame(&mut frame)
+                                .unwrap_or_else(|err| panic!("invalid tmq response: {err}"));
+                            let (req_id, recv, ok) = v.ok();
+                            match &recv {
+                                TmqRecvData::Subscribe => {
+                                    log::trace!("subscribe with: {:?}", req_id);
+
+                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
+                                    {
+                                        let _ = sender.send(ok.map(|_|recv));
+                                    }  else {
+                                        log::warn!("subscribe message received but no receiver alive");
+                                    }
+                                },
+                                TmqRecvData::Poll(_) => {
+                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
+                                    {
+                                        let _ = sender.send(ok.map(|_|recv));
+                                    }  else {
+                                        log::warn!("poll message received but no receiver alive");
+                                    }
+                                },
+                                TmqRecvData::FetchJsonMeta { data }=> {
+                                    log::trace!("fetch json meta data: {:?}", data);
+                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
+                                    {
+                                        let _ = sender.send(ok.map(|_|recv));
+                                    }  else {
+                                        log::warn!("poll message received but no receiver alive");
                                     }
                                 }
-                                Message::Binary(data) => {
-                                    // writeUint64(message.buffer, req.ReqID)
-                                    // writeUint64(message.buffer, req.MessageID)
-                                    // writeUint64(message.buffer, TMQRawMetaMessage)
-                                    // writeUint32(message.buffer, length)
-                                    // writeUint16(message.buffer, metaType)
-                                    let mut bytes = Bytes::from(data);
-                                    let part = bytes.slice(24..);
-                                    // dbg!(&bytes);
-                                    use bytes::Buf;
-                                    let timing = bytes.get_u64_le();
-                                    let req_id = bytes.get_u64_le();
-                                    let message_id = bytes.get_u64_le();
-
-
-                                    log::trace!("[{:.2}ms] receive binary message with req_id {} message_id {}",
-                                        Duration::from_nanos(timing).as_secs_f64() / 1000.,
-                                        req_id, message_id);
-
+                                TmqRecvData::FetchRaw { meta: _ }=> {
                                     if let Some((_, sender)) = queries_sender.remove(&req_id)
                                     {
-                                        sender.send(Ok(TmqRecvData::Bytes(part))).unwrap();
+                                        let _ = sender.send(ok.map(|_|recv));
                                     }  else {
                                         log::warn!("poll message received but no receiver alive");
                                     }
-
-
                                 }
-                                Message::Close(close) => {
-                                    log::warn!("websocket connection is closed (unexpected?)");
-
-                                    let keys = queries_sender.iter().map(|r| *r.key()).collect_vec();
-                                    let err = if let Some(close) = close {
-                                        format!("WebSocket internal error: {}", close.reason)
-                                    } else {
-                                        "WebSocket internal error, connection is reset by server".to_string()
-                                    };
-                                    for k in keys {
-                                        if let Some((_, sender)) = queries_sender.remove(&k) {
-                                            let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(), err.clone())));
-                                        }
+                                TmqRecvData::Commit=> {
+                                    log::trace!("commit done: {:?}", recv);
+                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
+                                    {
+                                        let _ = sender.send(ok.map(|_|recv));
+                                    }  else {
+                                        log::warn!("poll message received but no receiver alive");
                                     }
-                                    break 'ws;
                                 }
-                                Message::Ping(bytes) => {
-                                    ws2.send(Message::Pong(bytes)).await.unwrap();
+                                TmqRecvData::Fetch(fetch)=> {
+                                    log::trace!("fetch done: {:?}", fetch);
+                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
+                                    {
+                                        let _ = sender.send(ok.map(|_|recv));
+                                    }  else {
+                                        log::warn!("poll message received but no receiver alive");
+                                    }
                                 }
-                                Message::Pong(bytes) => {
-                                    if bytes == PING {
-                                        log::trace!("ping/pong handshake success");
-                                    } else {
-                                        // do nothing
-                                        log::warn!("received (unexpected) pong message, do nothing");
+                                TmqRecvData::Assignment { .. } => {
+                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
+                                    {
+                                        let _ = sender.send(ok.map(|_|recv));
+                                    }  else {
+                                        log::warn!("assignment message received but no receiver alive");
                                     }
                                 }
-                                Message::Frame(frame) => {
-                                    // do no`thing
-                                    log::warn!("received (unexpected) frame message, do nothing");
-                                    log::trace!("* frame data: {frame:?}");
+                                TmqRecvData::Seek => {
+                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
+                                    {
+                                        let _ = sender.send(ok.map(|_|recv));
+                                    }  else {
+                                        log::warn!("seek message received but no receiver alive");
+                                    }
                                 }
-                            },
-                            Err(err) => {
-                                log::error!("reading message from {url} error: {err:?}");
-                                // let mut keys = Vec::new();
-                                let keys = queries_sender.iter().map(|r| *r.key()).collect_vec();
-                                // queries_sender.for_each_async(|k, _| {
-                                //     keys.push(*k);
-                                // }).await;
-                                for k in keys {
-                                    if let Some((_, sender)) = queries_sender.remove(&k) {
-                                        let _ = sender.send(Err(RawError::new(
-                                            WS_ERROR_NO::CONN_CLOSED.as_code(),
-                                            format!("WebSocket internal error: {err}")
-                                        )));
+                                TmqRecvData::CommitOffset => {
+                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
+                                    {
+                                        let _ = sender.send(ok.map(|_|recv));
+                                    }  else {
+                                        log::warn!("commit offset message received but no receiver alive");
                                     }
                                 }
-                                break 'ws;
+                                _ => unreachable!("unknown tmq response"),
                             }
                         }
+                        Message::Binary(data) => {
+                            // writeUint64(message.buffer, req.ReqID)
+                            // writeUint64(message.buffer, req.MessageID)
+                            // writeUint64(message.buffer, TMQRawMetaMessage)
+                            // writeUint32(message.buffer, length)
+                            // writeUint16(message.buffer, metaType)
+                            let mut bytes = Bytes::from(data);
+                            let part = bytes.slice(24..);
+                            // dbg!(&bytes);
+                            use bytes::Buf;
+                            let timing = bytes.get_u64_le();
+                            let req_id = bytes.get_u64_le();
+                            let message_id = bytes.get_u64_le();
+
+
+                            log::trace!("[{:.2}ms] receive binary message with req_id {} message_id {}",
+                                Duration::from_nanos(timing).as_secs_f64() / 1000.,
+                                req_id, message_id);
+
+                            if let Some((_, sender)) = queries_sender.remove(&req_id)
+                            {
+                                let _ = sender.send(Ok(TmqRecvData::Bytes(part)));
+                            }  else {
+                                log::warn!("poll message received but no receiver alive");
+                            }
+
+
+                        }
+                        Message::Close(close) => {
+                            log::warn!("websocket connection is closed (unexpected?)");
+
+                            let keys = queries_sender.iter().map(|r| *r.key()).collect_vec();
+                            let err = if let Some(close) = close {
+                                format!("WebSocket internal error: {}", close.reason)
+                            } else {
+                                "WebSocket internal error, connection is reset by server".to_string()
+                            };
+                            for k in keys {
+                                if let Some((_, sender)) = queries_sender.remove(&k) {
+                                    let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(), err.clone())));
+                                }
+                            }
+                            break 'ws ReadEnd::Lost(err);
+                        }
+                        Message::Ping(bytes) => {
+                            let _ = ws2.send(Message::Pong(bytes)).await;
+                        }
+                        Message::Pong(bytes) => {
+                            if bytes == PING {
+                                log::trace!("ping/pong handshake success");
+                            } else {
+                                // do nothing
+                                log::warn!("received (unexpected) pong message, do nothing");
+                            }
+                        }
+                        Message::Frame(frame) => {
+                            // do no`thing
+                            log::warn!("received (unexpected) frame message, do nothing");
+                            log::trace!("* frame data: {frame:?}");
+                        }
+                    },
+                    Err(WsError::Capacity(CapacityError::MessageTooLong { size, max_size })) => {
+                        // Requests in flight fail, the oversized response is one of them.
+                        log::error!("response of {size} bytes from {url} exceeds the limit of {max_size} bytes");
+                        let keys = queries_sender.iter().map(|r| *r.key()).collect_vec();
+                        for k in keys {
+                            if let Some((_, sender)) = queries_sender.remove(&k) {
+                                let _ = sender.send(Ok(TmqRecvData::MessageTooLarge { size, limit: max_size }));
+                            }
+                        }
+                        break 'ws ReadEnd::Oversized(format!("message of {size} bytes exceeds the limit of {max_size} bytes"));
                     }
-                    _ = close_listener.changed() => {
-                        log::trace!("close reader task");
-                        break 'ws;
+                    Err(err) => {
+                        log::error!("reading message from {url} error: {err:?}");
+                        // let mut keys = Vec::new();
+                        let keys = queries_sender.iter().map(|r| *r.key()).collect_vec();
+                        // queries_sender.for_each_async(|k, _| {
+                        //     keys.push(*k);
+                        // }).await;
+                        for k in keys {
+                            if let Some((_, sender)) = queries_sender.remove(&k) {
+                                let _ = sender.send(Err(RawError::new(
+                                    WS_ERROR_NO::CONN_CLOSED.as_code(),
+                                    format!("WebSocket internal error: {err}")
+                                )));
+                            }
+                        }
+                        break 'ws ReadEnd::Lost(format!("WebSocket internal error: {err}"));
                     }
                 }
             }
-            log::trace!("Consuming done in {:?}", instant.elapsed());
-        });
-        let consumer = Consumer {
-            conn: self.info.to_conn_request(),
-            tmq_conf: self.conf.clone(),
-            sender: WsTmqSender {
-                req_id: Arc::new(AtomicU64::new(1)),
-                queries,
-                sender: ws,
-                timeout: Timeout::Duration(Duration::MAX),
-            },
-            // fetches,
-            close_signal: tx,
-            timeout: self.timeout,
-        };
-
-        Ok(consumer)
-    }
+            _ = close_listener.changed() => {
+                log::trace!("close reader task");
+                break 'ws ReadEnd::Lost("close signal received".to_string());
+            }
+        }
+    };
+    log::trace!("Consuming done in {:?}", instant.elapsed());
+    reason
 }
 
 pub struct Consumer {
@@ -739,11 +1250,33 @@ pub struct Consumer {
     tmq_conf: TmqInit,
     sender: WsTmqSender,
     close_signal: watch::Sender<bool>,
+    /// The connection task, awaited by [Consumer::close] only.
+    task: Option<JoinHandle<()>>,
     timeout: Timeout,
+    poll_interval: Duration,
+    watermarks: Watermarks,
+    /// Topics to subscribe again after reconnect.
+    topics: Arc<Mutex<Option<Vec<String>>>>,
+    oversized: Oversized,
+}
+
+impl Consumer {
+    /// Close the consumer gracefully, the close frame is sent and the connection task ends
+    /// before it returns, waiting at most a few seconds for an unresponsive server.
+    ///
+    /// Dropping the consumer only signals the connection task to close, which is safe in any
+    /// context, eg. on runtime shutdown, but doesn't wait for it.
+    pub async fn close(mut self) {
+        let _ = self.close_signal.send(true);
+        if let Some(task) = self.task.take() {
+            let _ = time::timeout(CLOSE_TIMEOUT, task).await;
+        }
+    }
 }
 
 impl Drop for Consumer {
     fn drop(&mut self) {
+        // only signal the connection task, it may be dropped already on runtime shutdown.
         let _ = self.close_signal.send(true);
     }
 }
@@ -753,6 +1286,11 @@ pub struct Offset {
     database: String,
     topic: String,
     vgroup_id: i32,
+    offset: Option<i64>,
+    /// Begin offset and high watermark of the vgroup at receive time.
+    watermark: Option<(i64, i64)>,
+    /// Creation time of the message in milliseconds.
+    timestamp: Option<i64>,
 }
 
 impl IsOffset for Offset {
@@ -767,6 +1305,22 @@ impl IsOffset for Offset {
     fn vgroup_id(&self) -> i32 {
         self.vgroup_id
     }
+
+    fn offset(&self) -> Option<i64> {
+        self.offset
+    }
+
+    fn begin(&self) -> Option<i64> {
+        self.watermark.map(|(begin, _)| begin)
+    }
+
+    fn high_watermark(&self) -> Option<i64> {
+        self.watermark.map(|(_, end)| end)
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp.map(Timestamp::Milliseconds)
+    }
 }
 
 #[derive(Debug, Error)]
@@ -777,7 +1331,7 @@ pub enum Error {
     FetchError(#[from] oneshot::error::RecvError),
     #[error("{0}")]
     SendError(#[from] tokio::sync::mpsc::error::SendError<Message>),
-    #[error(transparent)]
+    #[error("{0}")]
     SendTimeoutError(#[from] tokio::sync::mpsc::error::SendTimeoutError<Message>),
     #[error("{0}")]
     RecvTimeout(#[from] std::sync::mpsc::RecvTimeoutError),
@@ -791,6 +1345,20 @@ pub enum Error {
     TaosError(#[from] RawError),
     #[error("Receive timeout in {0}")]
     QueryTimeout(String),
+    #[error("Proxy {stage} failed: {cause}")]
+    Proxy { stage: ProxyStage, cause: String },
+    #[error("Message of {size} bytes exceeds the limit of {limit} bytes")]
+    MessageTooLarge { size: usize, limit: usize },
+}
+
+impl From<ConnectError> for Error {
+    fn from(err: ConnectError) -> Self {
+        match err {
+            ConnectError::Proxy { stage, cause } => Error::Proxy { stage, cause },
+            ConnectError::Tls(err) => Error::Dsn(err),
+            ConnectError::Ws(err) => Error::WsError(err),
+        }
+    }
 }
 
 unsafe impl Send for Error {}
@@ -803,6 +1371,15 @@ impl Error {
             _ => Code::Failed,
         }
     }
+
+    /// The request failed because the connection is lost.
+    fn is_conn_lost(&self) -> bool {
+        match self {
+            Error::TaosError(error) => error.code() == WS_ERROR_NO::CONN_CLOSED.as_code(),
+            Error::SendError(_) | Error::SendTimeoutError(_) => true,
+            _ => false,
+        }
+    }
     pub fn errstr(&self) -> String {
         match self {
             Error::TaosError(error) => error.message().to_string(),
@@ -820,6 +1397,562 @@ mod tests {
     use super::{TaosBuilder, TmqBuilder};
     use taos_query::prelude::tokio;
 
+    #[test]
+    fn timeout_params() {
+        use taos_query::tmq::Timeout;
+
+        let cases = [
+            ("", Some(Timeout::from_secs(5))),
+            ("&timeout=250", Some(Timeout::from_millis(250))),
+            ("&timeout=2s", Some(Timeout::from_secs(2))),
+            ("&timeout=never", Some(Timeout::Never)),
+            ("&timeout=0", Some(Timeout::None)),
+            ("&timeout=2h", None),
+            ("&watermark.refresh.interval=soon", None),
+        ];
+        for (params, expected) in cases {
+            let builder = TmqBuilder::new(format!("ws://localhost:6041?group.id=g{params}"));
+            match expected {
+                Some(timeout) => assert_eq!(builder.unwrap().timeout, timeout, "{params}"),
+                None => {
+                    let err = builder.err().expect(params).to_string();
+                    assert!(err.contains("integer milliseconds"), "{err}");
+                }
+            }
+        }
+        let err = TmqBuilder::new("ws://localhost:6041?group.id=g&poll.interval=0").err();
+        assert!(err.unwrap().to_string().contains("poll.interval"));
+    }
+
+    #[test]
+    fn tmq_frames() {
+        use super::messages::TmqRecv;
+
+        for frame in [
+            r#"{"code":0,"message":"","action":"subscribe","req_id":1}"#,
+            r#"{"code":0,"message":"","action":"poll","req_id":2,"have_message":true,"topic":"tmq_meters","database":"power","vgroup_id":3,"message_type":1,"message_id":4,"offset":120}"#,
+            r#"{"code":0,"message":"","action":"poll","req_id":3,"have_message":false,"topic":"","database":"","vgroup_id":0,"message_type":0,"message_id":0}"#,
+            r#"{"code":0,"message":"","action":"fetch","req_id":4,"completed":false,"table_name":"d0","fields_count":2,"fields_names":["ts","v"],"fields_types":[9,4],"fields_lengths":[8,4],"precision":0,"rows":10}"#,
+            r#"{"code":0,"message":"","action":"fetch_json_meta","req_id":5,"data":{"type":"create","tableName":"d0","tags":[{"name":"t1","type":4,"value":1.5e3}]}}"#,
+            r#"{"code":0,"message":"","action":"assignment","req_id":6,"assignment":[{"vgroup_id":3,"offset":120,"begin":0,"end":4096}]}"#,
+            r#"{"code":0,"message":"","action":"commit","req_id":7}"#,
+            r#"{"code":65535,"message":"topic \"meters\" not exist\t","action":"subscribe","req_id":8}"#,
+        ] {
+            crate::json::assert_same_as_serde_json::<TmqRecv>(frame);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reconnect_resubscribe() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::tmq::{AsAsyncConsumer, Timeout};
+        use taos_query::ConnState;
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Mock server: the first connection is dropped on the first poll.
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let subscribes = Arc::new(Mutex::new(Vec::new()));
+        let subscribed = subscribes.clone();
+        tokio::spawn(async move {
+            let mut n = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                n += 1;
+                let first = n == 1;
+                let subscribed = subscribed.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(message)) = ws.next().await {
+                        // Pings of the client are answered by tungstenite.
+                        let Message::Text(text) = message else {
+                            continue;
+                        };
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "subscribe" => {
+                                let args = &req["args"];
+                                subscribed.lock().unwrap().push((
+                                    args["group_id"].as_str().unwrap().to_string(),
+                                    args["topics"].clone(),
+                                ));
+                                format!(
+                                    r#"{{"code":0,"message":"","action":"subscribe","req_id":{req_id}}}"#
+                                )
+                            }
+                            "poll" if first => return,
+                            "poll" => format!(
+                                r#"{{"code":0,"message":"","action":"poll","req_id":{req_id},"have_message":false}}"#
+                            ),
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let cloned = events.clone();
+        let mut consumer = TmqBuilder::new(format!(
+            "ws://{addr}?group.id=g1&reconnect=true&reconnect.interval=10ms"
+        ))?
+        .on_state_change(move |state| cloned.lock().unwrap().push(state))
+        .build_consumer()
+        .await?;
+        consumer.subscribe(["topic1"]).await?;
+        // The poll is lost with the first connection and sent again after resubscribing.
+        let message = consumer.recv_timeout(Timeout::from_millis(300)).await?;
+        assert!(message.is_none());
+
+        let subscribe = ("g1".to_string(), serde_json::json!(["topic1"]));
+        assert_eq!(*subscribes.lock().unwrap(), [subscribe.clone(), subscribe]);
+        assert!(events
+            .lock()
+            .unwrap()
+            .contains(&ConnState::Reconnecting { attempt: 1 }));
+        Ok(())
+    }
+
+    #[test]
+    fn drop_on_runtime_shutdown() -> anyhow::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Instant;
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::tmq::{AsAsyncConsumer, Timeout};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        const WORKER: &str = "ws-tmq-shutdown";
+        let panics = crate::count_panics(WORKER);
+
+        // Mock server: polls are answered after a while without messages, counts close frames.
+        let server = tokio::runtime::Runtime::new()?;
+        let listener = server.block_on(TcpListener::bind("127.0.0.1:0"))?;
+        let addr = listener.local_addr()?;
+        let closes = Arc::new(AtomicUsize::new(0));
+        let closed = closes.clone();
+        server.spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let closed = closed.clone();
+                tokio::spawn(async move {
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    while let Some(Ok(message)) = ws.next().await {
+                        let text = match message {
+                            Message::Text(text) => text,
+                            Message::Close(_) => {
+                                closed.fetch_add(1, Ordering::SeqCst);
+                                break;
+                            }
+                            _ => continue,
+                        };
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "subscribe" => format!(
+                                r#"{{"code":0,"message":"","action":"subscribe","req_id":{req_id}}}"#
+                            ),
+                            "poll" => {
+                                tokio::time::sleep(Duration::from_millis(5)).await;
+                                format!(
+                                    r#"{{"code":0,"message":"","action":"poll","req_id":{req_id},"have_message":false}}"#
+                                )
+                            }
+                            _ => continue,
+                        };
+                        if ws.send(Message::Text(reply)).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        let builder = TmqBuilder::new(format!("ws://{addr}?group.id=g1"))?;
+
+        for i in 0..1000 {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name(WORKER)
+                .enable_all()
+                .build()?;
+            let consumer = runtime.block_on(async {
+                let mut consumer = builder.build_consumer().await?;
+                consumer.subscribe(["topic1"]).await?;
+                let consumer = Arc::new(consumer);
+                // a poll in flight when the runtime shuts down.
+                let cloned = consumer.clone();
+                tokio::spawn(async move { cloned.recv_timeout(Timeout::from_secs(1)).await });
+                anyhow::Ok(consumer)
+            })?;
+            // dropped by tasks torn down with the runtime, or out of any runtime after it.
+            let consumer = if i % 2 == 0 {
+                runtime.spawn(async move {
+                    let _consumer = consumer;
+                    std::future::pending::<()>().await
+                });
+                None
+            } else {
+                Some(consumer)
+            };
+            let shutdown = Instant::now();
+            runtime.shutdown_timeout(Duration::from_secs(1));
+            assert!(shutdown.elapsed() < Duration::from_millis(500), "{i}");
+            drop(consumer);
+        }
+        assert_eq!(panics.load(Ordering::SeqCst), 0);
+
+        // the close frame is sent before `close` returns.
+        let before = closes.load(Ordering::SeqCst);
+        server.block_on(async {
+            let mut consumer = builder.build_consumer().await?;
+            consumer.subscribe(["topic1"]).await?;
+            let closing = tokio::time::timeout(Duration::from_secs(3), consumer.close());
+            assert!(closing.await.is_ok());
+            for _ in 0..50 {
+                if closes.load(Ordering::SeqCst) > before {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert_eq!(closes.load(Ordering::SeqCst), before + 1);
+            anyhow::Ok(())
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stream_closed_by_handle() -> anyhow::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::tmq::AsAsyncConsumer;
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Mock server: no messages, counts polls and closed connections.
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let polls = Arc::new(AtomicUsize::new(0));
+        let closes = Arc::new(AtomicUsize::new(0));
+        let (polled, closed) = (polls.clone(), closes.clone());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (polled, closed) = (polled.clone(), closed.clone());
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(message)) = ws.next().await {
+                        let Message::Text(text) = message else {
+                            continue;
+                        };
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "subscribe" => format!(
+                                r#"{{"code":0,"message":"","action":"subscribe","req_id":{req_id}}}"#
+                            ),
+                            "poll" => {
+                                polled.fetch_add(1, Ordering::SeqCst);
+                                format!(
+                                    r#"{{"code":0,"message":"","action":"poll","req_id":{req_id},"have_message":false}}"#
+                                )
+                            }
+                            _ => continue,
+                        };
+                        if ws.send(Message::Text(reply)).await.is_err() {
+                            break;
+                        }
+                    }
+                    closed.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        let mut consumer = TmqBuilder::new(format!("ws://{addr}?group.id=g1&poll.interval=100ms"))?
+            .build_consumer()
+            .await?;
+        consumer.subscribe(["topic1"]).await?;
+        let mut stream = consumer.into_stream();
+        let handle = stream.handle();
+        let closing = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            handle.close()
+        });
+        // empty polls are skipped until closed.
+        let end = tokio::time::timeout(Duration::from_secs(5), stream.next()).await?;
+        assert!(end.is_none());
+        assert!(closing.await?);
+        // a poll in each interval instead of spinning.
+        let polled = polls.load(Ordering::SeqCst);
+        assert!((10..=30).contains(&polled), "{polled} polls in 2s");
+
+        drop(stream);
+        for _ in 0..50 {
+            if closes.load(Ordering::SeqCst) > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(closes.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn commit_message_offset() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::tmq::{AsAsyncConsumer, IsOffset, Timeout};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Mock server: one data message at offset 120, commits beyond it are rejected.
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let commits = Arc::new(Mutex::new(Vec::new()));
+        let committed = commits.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(message)) = ws.next().await {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let args = &req["args"];
+                let req_id = args["req_id"].as_u64().unwrap_or_default();
+                let reply = match req["action"].as_str().unwrap() {
+                    "subscribe" => format!(
+                        r#"{{"code":0,"message":"","action":"subscribe","req_id":{req_id}}}"#
+                    ),
+                    "poll" => format!(
+                        r#"{{"code":0,"message":"","action":"poll","req_id":{req_id},"have_message":true,"topic":"topic1","database":"db","vgroup_id":3,"message_type":1,"message_id":1,"offset":120}}"#
+                    ),
+                    "assignment" => format!(
+                        r#"{{"code":0,"message":"","action":"assignment","req_id":{req_id},"assignment":[]}}"#
+                    ),
+                    "commit_offset" => {
+                        let offset = args["offset"].as_i64().unwrap();
+                        committed.lock().unwrap().push((
+                            args["topic"].as_str().unwrap().to_string(),
+                            args["vgroup_id"].as_i64().unwrap(),
+                            offset,
+                        ));
+                        let code = if offset > 120 { 0x4000 } else { 0 };
+                        format!(
+                            r#"{{"code":{code},"message":"","action":"commit_offset","req_id":{req_id}}}"#
+                        )
+                    }
+                    _ => continue,
+                };
+                ws.send(Message::Text(reply)).await.unwrap();
+            }
+        });
+
+        let mut consumer = TmqBuilder::new(format!("ws://{addr}?group.id=g1"))?
+            .build_consumer()
+            .await?;
+        consumer.subscribe(["topic1"]).await?;
+        let (offset, _) = consumer
+            .recv_timeout(Timeout::from_millis(300))
+            .await?
+            .unwrap();
+        assert_eq!(offset.offset(), Some(120));
+        consumer.commit_message(&offset).await?;
+        consumer.commit_offset("topic1", 3, 100).await?;
+
+        let err = consumer.commit_offset("topic1", 3, 200).await.unwrap_err();
+        assert_eq!(err.errno(), 0x4000);
+        assert_eq!(
+            *commits.lock().unwrap(),
+            [
+                ("topic1".to_string(), 3, 120),
+                ("topic1".to_string(), 3, 100),
+                ("topic1".to_string(), 3, 200)
+            ]
+        );
+        Ok(())
+    }
+
+    /// Mock server of a data message at offset 120 of vgroup 3, its block and raw data are binary
+    /// frames of 4096 bytes. Returns the address and the commit and seek requests of all connections.
+    async fn oversized_server() -> anyhow::Result<(
+        std::net::SocketAddr,
+        std::sync::Arc<std::sync::Mutex<Vec<(String, i64)>>>,
+    )> {
+        use std::sync::{Arc, Mutex};
+
+        use futures::{SinkExt, StreamExt};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(message)) = ws.next().await {
+                        let Message::Text(text) = message else {
+                            continue;
+                        };
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let args = &req["args"];
+                        let req_id = args["req_id"].as_u64().unwrap_or_default();
+                        let action = req["action"].as_str().unwrap();
+                        let reply = match action {
+                            "subscribe" | "seek" | "commit_offset" => {
+                                if action != "subscribe" {
+                                    assert_eq!(args["topic"], "topic1");
+                                    assert_eq!(args["vgroup_id"], 3);
+                                    let offset = args["offset"].as_i64().unwrap();
+                                    recorded.lock().unwrap().push((action.to_string(), offset));
+                                }
+                                format!(
+                                    r#"{{"code":0,"message":"","action":"{action}","req_id":{req_id}}}"#
+                                )
+                            }
+                            "poll" => format!(
+                                r#"{{"code":0,"message":"","action":"poll","req_id":{req_id},"have_message":true,"topic":"topic1","database":"db","vgroup_id":3,"message_type":1,"message_id":1,"offset":120}}"#
+                            ),
+                            "assignment" => format!(
+                                r#"{{"code":0,"message":"","action":"assignment","req_id":{req_id},"assignment":[]}}"#
+                            ),
+                            "fetch" => format!(
+                                r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"completed":false,"table_name":"d0","fields_count":1,"fields_names":["ts"],"fields_types":[9],"fields_lengths":[8],"precision":0,"rows":512}}"#
+                            ),
+                            "fetch_block" | "fetch_raw" => {
+                                ws.send(Message::Binary(vec![0; 4096])).await.unwrap();
+                                continue;
+                            }
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+        Ok((addr, requests))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn oversized_message_error() -> anyhow::Result<()> {
+        use taos_query::tmq::{AsAsyncConsumer, MessageSet, Timeout};
+
+        let (addr, requests) = oversized_server().await?;
+        let mut consumer = TmqBuilder::new(format!("ws://{addr}?group.id=g1"))?
+            .max_message_bytes(1024)
+            .build_consumer()
+            .await?;
+        consumer.subscribe(["topic1"]).await?;
+        let (_, message) = consumer
+            .recv_timeout(Timeout::from_millis(300))
+            .await?
+            .unwrap();
+        let MessageSet::Data(data) = message else {
+            panic!("expect a data message");
+        };
+        let err = data.fetch_block().await.unwrap_err();
+        assert!(
+            matches!(
+                err,
+                super::Error::MessageTooLarge {
+                    size: 4096,
+                    limit: 1024
+                }
+            ),
+            "{err}"
+        );
+        assert!(requests.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn oversized_message_skip() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        use taos_query::tmq::{AsAsyncConsumer, IsAsyncData, MessageSet, Timeout};
+
+        use super::SkippedMessage;
+
+        let (addr, requests) = oversized_server().await?;
+        let skipped = Arc::new(Mutex::new(Vec::new()));
+        let cloned = skipped.clone();
+        let mut consumer = TmqBuilder::new(format!(
+            "ws://{addr}?group.id=g1&max.message.bytes=1024&oversized.message=skip"
+        ))?
+        .on_message_skipped(move |message| cloned.lock().unwrap().push(message.clone()))
+        .build_consumer()
+        .await?;
+        consumer.subscribe(["topic1"]).await?;
+        let (_, message) = consumer
+            .recv_timeout(Timeout::from_millis(300))
+            .await?
+            .unwrap();
+        let MessageSet::Data(data) = message else {
+            panic!("expect a data message");
+        };
+        // The connection is re-established to skip the message, without a reconnect policy.
+        assert!(data.fetch_block().await?.is_none());
+        let skip = SkippedMessage {
+            topic: "topic1".to_string(),
+            vgroup_id: 3,
+            offset: 120,
+            size: 4096,
+            limit: 1024,
+        };
+        assert_eq!(*skipped.lock().unwrap(), [skip.clone()]);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                ("commit_offset".to_string(), 121),
+                ("seek".to_string(), 121)
+            ]
+        );
+
+        // The raw data is missing, after the message is skipped again.
+        let err = data.as_raw_data().await.unwrap_err();
+        assert!(matches!(err, super::Error::MessageTooLarge { .. }), "{err}");
+        assert_eq!(skipped.lock().unwrap().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_params() {
+        use super::OversizedMessage;
+
+        let builder = TmqBuilder::new("ws://localhost:6041?group.id=g1").unwrap();
+        assert_eq!(builder.max_message_bytes, None);
+        assert_eq!(builder.oversized.policy, OversizedMessage::Error);
+        assert!(builder.ws_config().is_none());
+
+        let builder = TmqBuilder::new(
+            "ws://localhost:6041?group.id=g1&max.message.bytes=1048576&oversized.message=Skip",
+        )
+        .unwrap();
+        assert_eq!(builder.oversized.policy, OversizedMessage::Skip);
+        let config = builder.ws_config().unwrap();
+        assert_eq!(config.max_message_size, Some(1 << 20));
+        assert_eq!(config.max_frame_size, Some(1 << 20));
+
+        for dsn in [
+            "ws://localhost:6041?group.id=g1&max.message.bytes=1MB",
+            "ws://localhost:6041?group.id=g1&oversized.message=spill",
+        ] {
+            assert!(TmqBuilder::new(dsn).is_err(), "{dsn}");
+        }
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_ws_tmq_meta() -> anyhow::Result<()> {
         use taos_query::prelude::*;
@@ -1120,7 +2253,6 @@ mod tests {
         Ok(())
     }
 
-
     #[test]
     fn test_ws_tmq_metadata() -> anyhow::Result<()> {
         use taos_query::prelude::sync::*;
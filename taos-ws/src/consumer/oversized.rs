@@ -0,0 +1,72 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use taos_query::tmq::VGroupId;
+use taos_query::DsnError;
+
+/// What a consumer does with a message over its max message size, see
+/// [TmqBuilder::max_message_bytes](super::TmqBuilder::max_message_bytes).
+///
+/// The response frame of the message breaks the websocket connection, as it's not read into
+/// memory. Same as `oversized.message=error|skip` in DSN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum OversizedMessage {
+    /// Fail fetching the message with [Error::MessageTooLarge](super::Error::MessageTooLarge).
+    #[default]
+    Error,
+    /// Skip the message: the consumer re-dials, commits the offset next to the message and seeks
+    /// to it, then notifies [TmqBuilder::on_message_skipped](super::TmqBuilder::on_message_skipped).
+    ///
+    /// Blocks of a skipped data message end early, fetching its raw data or meta fails with
+    /// [Error::MessageTooLarge](super::Error::MessageTooLarge) as there is no payload.
+    Skip,
+}
+
+impl OversizedMessage {
+    pub(super) const PARAM: &'static str = "oversized.message";
+
+    pub(super) fn from_param(value: Option<&str>) -> Result<Self, DsnError> {
+        match value {
+            None => Ok(Self::default()),
+            Some(value) => match value.to_lowercase().as_str() {
+                "error" => Ok(Self::Error),
+                "skip" => Ok(Self::Skip),
+                _ => Err(DsnError::InvalidParam(
+                    Self::PARAM.to_string(),
+                    value.to_string(),
+                )),
+            },
+        }
+    }
+}
+
+/// A message skipped by [OversizedMessage::Skip].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedMessage {
+    pub topic: String,
+    pub vgroup_id: VGroupId,
+    /// Offset of the message, the committed one is next to it.
+    pub offset: i64,
+    /// Size of the response frame.
+    pub size: usize,
+    pub limit: usize,
+}
+
+type OnSkipped = Arc<dyn Fn(&SkippedMessage) + Send + Sync>;
+
+/// Policy of oversized messages with the callback of skipped ones.
+#[derive(Clone, Default)]
+pub(super) struct Oversized {
+    pub(super) policy: OversizedMessage,
+    pub(super) on_skipped: Option<OnSkipped>,
+}
+
+impl Debug for Oversized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Oversized")
+            .field("policy", &self.policy)
+            .field("on_skipped", &self.on_skipped.is_some())
+            .finish()
+    }
+}
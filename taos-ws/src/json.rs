@@ -0,0 +1,53 @@
+//! JSON of text frames from taosAdapter.
+//!
+//! With feature `simd-json`, frames are parsed in place by simd-json, which detects the SIMD
+//! instructions at runtime and falls back to a scalar implementation on CPUs without them.
+
+use serde::Deserialize;
+
+/// Parse a text frame, the bytes of `frame` may be modified in place.
+#[cfg(feature = "simd-json")]
+pub(crate) fn from_frame<'a, T: Deserialize<'a>>(frame: &'a mut [u8]) -> serde_json::Result<T> {
+    simd_json::serde::from_slice(frame).map_err(serde::de::Error::custom)
+}
+
+/// Parse a text frame, the bytes of `frame` may be modified in place.
+#[cfg(not(feature = "simd-json"))]
+pub(crate) fn from_frame<'a, T: Deserialize<'a>>(frame: &'a mut [u8]) -> serde_json::Result<T> {
+    serde_json::from_slice(frame)
+}
+
+/// Assert `frame` is parsed by [from_frame] the same as by `serde_json`.
+#[cfg(test)]
+pub(crate) fn assert_same_as_serde_json<T>(frame: &str)
+where
+    T: serde::de::DeserializeOwned + std::fmt::Debug,
+{
+    let expected: T = serde_json::from_str(frame).unwrap();
+    let mut bytes = frame.as_bytes().to_vec();
+    let parsed: T = from_frame(&mut bytes).unwrap();
+    assert_eq!(format!("{parsed:?}"), format!("{expected:?}"), "{frame}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::infra::WsRecv;
+
+    #[test]
+    fn query_frames() {
+        for frame in [
+            r#"{"code":0,"message":"","action":"conn","req_id":1}"#,
+            r#"{"code":0,"message":"","action":"version","version":"3.0.2.0"}"#,
+            r#"{"code":0,"message":"","action":"query","req_id":2,"id":7,"is_update":false,"affected_rows":0,"fields_count":3,"fields_names":["ts","current","location"],"fields_types":[9,6,8],"fields_lengths":[8,4,64],"precision":1,"timing":1234567}"#,
+            r#"{"code":0,"message":"","action":"query","req_id":3,"id":0,"is_update":true,"affected_rows":2,"warnings":[{"code":9750,"message":"column c1 coerced from int to double"}]}"#,
+            r#"{"code":0,"message":"","action":"fetch","req_id":4,"id":7,"completed":false,"lengths":[8,4,66],"rows":4096,"timing":98765}"#,
+            r#"{"code":0,"message":"","action":"fetch","req_id":5,"id":7,"completed":true,"rows":0}"#,
+            r#"{"code":0,"message":"","action":"fetch_json","req_id":7,"id":7,"data":[["2022-10-01T08:00:00.123+08:00",10.3,"\u00e9"]]}"#,
+            r#"{"code":9731,"message":"syntax error near \"selec\" é\n","action":"query","req_id":6}"#,
+            r#"{ "code" : 866 , "message" : "Database not exist" , "action" : "fetch_block" , "req_id" : 8 }"#,
+        ] {
+            assert_same_as_serde_json::<WsRecv>(frame);
+        }
+    }
+}
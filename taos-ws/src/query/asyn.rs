@@ -1,30 +1,37 @@
+use bytes::Bytes;
 use derive_more::Deref;
-use futures::stream::SplitStream;
+use futures::stream::{SplitSink, SplitStream};
 use futures::{FutureExt, SinkExt, StreamExt};
 // use scc::HashMap;
 use dashmap::DashMap as HashMap;
 use itertools::Itertools;
 use std::future::Future;
-use taos_query::common::{Field, Precision, RawBlock, RawMeta};
+use taos_query::common::{
+    BlockPool, ExecResult, Field, Precision, RawBlock, RawMeta, Warning, WarningListener,
+};
 use taos_query::prelude::{Code, RawError};
-use taos_query::util::InlinableWrite;
+use taos_query::util::{InlinableWrite, RateLimiter};
 use taos_query::{
     block_in_place_or_global, AsyncFetchable, AsyncQueryable, DeError, DsnError, IntoDsn,
 };
+use taos_query::{ConnState, ConnStateNotifier};
 use thiserror::Error;
 
 use taos_query::prelude::tokio;
 use tokio::net::TcpStream;
 use tokio::sync::watch;
+use tokio::task::JoinHandle;
 
 use tokio::time;
 use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 use tokio_tungstenite::tungstenite::Error as WsError;
-use tokio_tungstenite::{
-    connect_async_with_config, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
-};
+use tokio_tungstenite::{tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream};
 
+use super::resume::{QueryFingerprint, ResumeState};
 use super::{infra::*, TaosBuilder};
+use crate::proxy::{ConnectError, ProxyStage};
+use crate::reconnect::{use_database, ReconnectPolicy};
+use crate::{json, CLOSE_TIMEOUT};
 
 use std::fmt::Debug;
 use std::io::Write;
@@ -32,8 +39,8 @@ use std::mem::transmute;
 use std::pin::Pin;
 // use std::io::Write;
 use std::result::Result as StdResult;
-use std::sync::atomic::AtomicU64;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
 use std::time::{Duration, Instant};
 
@@ -63,6 +70,107 @@ struct Version(String);
 //         !self.0.starts_with("2")
 //     }
 // }
+/// Snapshot of in-flight and queued requests of a connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Requests sent and waiting for response.
+    pub in_flight: usize,
+    /// Requests waiting for a free slot.
+    pub queued: usize,
+}
+
+/// Counters of a connection since it's built, see [WsTaos::metrics].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WsMetrics {
+    /// Responses no request is waiting for, eg. of a cancelled or timed out request, or with
+    /// an unknown id, which are dropped instead of delivered.
+    pub dropped_stale_frames: u64,
+}
+
+/// Increase the counter while alive, so it's correct even if the request is cancelled.
+struct Counted<'a>(&'a AtomicUsize);
+
+impl<'a> Counted<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl<'a> Drop for Counted<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Limits in-flight requests of a connection, waiters are served in FIFO order.
+///
+/// It's shared by the query connection of a [Taos](super::Taos) and its stmt and schemaless
+/// connections.
+#[derive(Debug, Default)]
+pub(crate) struct QueryLimiter {
+    permits: Option<Arc<tokio::sync::Semaphore>>,
+    queue_timeout: Option<Duration>,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+}
+
+/// A slot of a [QueryLimiter], released when dropped.
+#[derive(Debug)]
+pub(crate) struct QuerySlot {
+    _permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    limiter: Arc<QueryLimiter>,
+}
+
+impl Drop for QuerySlot {
+    fn drop(&mut self) {
+        self.limiter.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl QueryLimiter {
+    /// The limiter configured by `info`, see [TaosBuilder::with_max_concurrent_queries].
+    pub(crate) fn of(info: &TaosBuilder) -> Arc<Self> {
+        Arc::new(Self {
+            permits: info
+                .max_concurrent_queries
+                .map(|max| Arc::new(tokio::sync::Semaphore::new(max))),
+            queue_timeout: info.queue_timeout,
+            ..Default::default()
+        })
+    }
+
+    /// Wait for a free slot, the slot is released when the returned guard is dropped.
+    pub(crate) async fn acquire(self: &Arc<Self>) -> Result<QuerySlot> {
+        let permit = match &self.permits {
+            Some(permits) => {
+                let _queued = Counted::new(&self.queued);
+                let acquire = permits.clone().acquire_owned();
+                let permit = match self.queue_timeout {
+                    Some(timeout) => time::timeout(timeout, acquire)
+                        .await
+                        .map_err(|_| Error::QueueTimeout(timeout))?,
+                    None => acquire.await,
+                };
+                Some(permit.expect("semaphore is never closed"))
+            }
+            None => None,
+        };
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Ok(QuerySlot {
+            _permit: permit,
+            limiter: self.clone(),
+        })
+    }
+
+    pub(crate) fn stats(&self) -> QueueStats {
+        QueueStats {
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            queued: self.queued.load(Ordering::SeqCst),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct WsQuerySender {
     version: Version,
@@ -70,6 +178,17 @@ struct WsQuerySender {
     results: Arc<QueryResMapper>,
     sender: WsSender,
     queries: QueryAgent,
+    limiter: Arc<QueryLimiter>,
+    /// Stale responses dropped by the reader, see [WsMetrics::dropped_stale_frames].
+    stale_frames: Arc<AtomicU64>,
+    /// Increased after each reconnect.
+    epoch: watch::Receiver<u64>,
+    reconnect: ReconnectPolicy,
+    /// Current database of the connection, restored after reconnect.
+    database: Arc<Mutex<Option<String>>>,
+    warning_listener: Option<WarningListener>,
+    /// Fetch rows in JSON, see [TaosBuilder::with_json_fetch].
+    json_fetch: bool,
 }
 
 impl WsQuerySender {
@@ -77,15 +196,55 @@ impl WsQuerySender {
         self.req_id
             .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
     }
+    /// Send the request and wait for the response, queries and writes failed by the lost
+    /// connection are sent again after reconnect if enabled by the [ReconnectPolicy].
     async fn send_recv(&self, msg: WsSend) -> Result<WsRecvData> {
+        let retry = match &msg {
+            WsSend::Query { .. } | WsSend::Binary(_) if self.reconnect.is_enabled() => {
+                Some(msg.clone())
+            }
+            _ => None,
+        };
+        let epoch = *self.epoch.borrow();
+        match self.send_recv_once(msg).await {
+            Err(err) if err.is_conn_lost() => match retry {
+                Some(msg) if self.wait_reconnected(epoch).await => {
+                    log::warn!("[req id: {:#x}] retry after reconnect: {err}", msg.req_id());
+                    self.send_recv_once(msg).await
+                }
+                _ => Err(err),
+            },
+            res => res,
+        }
+    }
+
+    /// Wait until the connection lost in `epoch` is re-established, `false` if it's closed.
+    async fn wait_reconnected(&self, epoch: u64) -> bool {
+        let mut listener = self.epoch.clone();
+        let reconnected = listener.wait_for(|current| *current > epoch).await;
+        reconnected.is_ok()
+    }
+
+    /// Keep the database of a successful `use db` statement to log in with after reconnect.
+    fn track_database(&self, sql: &str) {
+        if let Some(db) = use_database(sql) {
+            *self.database.lock().unwrap() = Some(db.to_string());
+        }
+    }
+
+    /// Send the request and wait for its response, a response not answering the request, eg. a
+    /// fetch of another result, is an [Error::ProtocolDesync].
+    async fn send_recv_once(&self, mut msg: WsSend) -> Result<WsRecvData> {
         let send_timeout = Duration::from_millis(1000);
         let req_id = msg.req_id();
+        // Hold the slot until the response is received.
+        let _slot = self.limiter.acquire().await?;
         let (tx, rx) = query_channel();
 
         self.queries.insert(req_id, tx);
 
-        match msg {
-            WsSend::FetchBlock(args) => {
+        match &mut msg {
+            &mut WsSend::FetchBlock(args) => {
                 log::trace!("[req id: {req_id}] prepare message {msg:?}");
                 if self.results.contains_key(&args.id) {
                     Err(RawError::from_any(format!(
@@ -99,6 +258,8 @@ impl WsQuerySender {
                 //
             }
             WsSend::Binary(bytes) => {
+                // only the kind of the request is kept to check the response.
+                let bytes = std::mem::take(bytes);
                 self.sender
                     .send_timeout(Message::Binary(bytes), send_timeout)
                     .await?;
@@ -110,24 +271,136 @@ impl WsQuerySender {
         }
         // handle the error
         log::trace!("[req id: {req_id}] message sent, wait for receiving");
-        Ok(rx.await.unwrap()?)
+        // the sender is dropped with the connection task, eg. on runtime shutdown.
+        let data = rx
+            .await
+            .map_err(|_| Error::WsClosed("connection task ended".to_string()))??;
+        if !msg.expects(&data) {
+            return Err(Error::ProtocolDesync(format!(
+                "[req id: {req_id:#x}] unexpected {} response of {msg:?}",
+                data.action()
+            )));
+        }
+        Ok(data)
+    }
+    fn notify_warnings(&self, warnings: &[Warning]) {
+        if let Some(listener) = &self.warning_listener {
+            listener.notify(warnings);
+        }
     }
+
     async fn send_only(&self, msg: WsSend) -> Result<()> {
         let send_timeout = Duration::from_millis(1000);
         self.sender.send_timeout(msg.to_msg(), send_timeout).await?;
         Ok(())
     }
+
+    /// Send the query and wrap the response into a result set.
+    async fn query_in(&self, db: Option<&str>, sql: &str) -> Result<ResultSet> {
+        self.query_with_req_id(db, sql, self.req_id()).await
+    }
+
+    async fn query_with_req_id(
+        &self,
+        db: Option<&str>,
+        sql: &str,
+        req_id: ReqId,
+    ) -> Result<ResultSet> {
+        log::debug!("[req id: {req_id:#x}] query with sql: {sql}");
+        let action = WsSend::Query {
+            req_id,
+            sql: sql.to_string(),
+            db: db.map(ToString::to_string),
+        };
+
+        let req = self.send_recv(action).await?;
+
+        let resp = match req {
+            WsRecvData::Query(resp) => resp,
+            _ => unreachable!(),
+        };
+
+        self.notify_warnings(&resp.warnings);
+        if db.is_none() {
+            self.track_database(sql);
+        }
+
+        let result_id = resp.id;
+        //  for drop task.
+        let (closer, rx) = oneshot::channel();
+        tokio::task::spawn(async move {
+            let t = Instant::now();
+            let _ = rx.await;
+            log::trace!("result {result_id} lives {:?}", t.elapsed());
+        });
+
+        if resp.fields_count > 0 {
+            let names = resp.fields_names.unwrap();
+            let types = resp.fields_types.unwrap();
+            let bytes = resp.fields_lengths.unwrap();
+            let fields: Vec<_> = names
+                .into_iter()
+                .zip(types)
+                .zip(bytes)
+                .map(|((name, ty), bytes)| Field::new(name, ty, bytes))
+                .collect();
+            Ok(ResultSet {
+                fields: Some(fields),
+                fields_count: resp.fields_count,
+                precision: resp.precision,
+                affected_rows: resp.affected_rows,
+                args: WsResArgs {
+                    req_id,
+                    id: resp.id,
+                },
+                summary: (0, 0),
+                sender: self.clone(),
+                timing: resp.timing,
+                block_future: None,
+                closer: Some(closer),
+                resume: None,
+                epoch: *self.epoch.borrow(),
+                desync: None,
+                warnings: resp.warnings,
+            })
+        } else {
+            Ok(ResultSet {
+                affected_rows: resp.affected_rows,
+                args: WsResArgs {
+                    req_id,
+                    id: resp.id,
+                },
+                fields: None,
+                fields_count: 0,
+                precision: resp.precision,
+                summary: (0, 0),
+                sender: self.clone(),
+                timing: resp.timing,
+                block_future: None,
+                closer: Some(closer),
+                resume: None,
+                epoch: *self.epoch.borrow(),
+                desync: None,
+                warnings: resp.warnings,
+            })
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct WsTaos {
     close_signal: watch::Sender<bool>,
+    /// The connection task, awaited by [WsTaos::close_gracefully] only.
+    task: Mutex<Option<JoinHandle<()>>>,
     sender: WsQuerySender,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Max bytes of a raw block write message, see [TaosBuilder::with_max_write_block_size].
+    max_write_block_size: usize,
 }
 impl Drop for WsTaos {
     fn drop(&mut self) {
         log::trace!("dropping connection");
-        // send close signal to reader/writer spawned tasks.
+        // only signal spawned tasks, they may be dropped already on runtime shutdown.
         let _ = self.close_signal.send(true);
     }
 }
@@ -143,6 +416,13 @@ pub struct ResultSet {
     timing: Duration,
     block_future: Option<Pin<Box<dyn Future<Output = Result<Option<RawBlock>>> + Send>>>,
     closer: Option<oneshot::Sender<()>>,
+    /// Set by [WsTaos::s_resumable_query_in] to resume the result after reconnect.
+    resume: Option<ResumeState>,
+    /// Connection epoch the result is executed in.
+    epoch: u64,
+    /// Set on an [Error::ProtocolDesync] of a fetch, the result fails with it ever after.
+    desync: Option<String>,
+    warnings: Vec<Warning>,
 }
 
 unsafe impl Sync for ResultSet {}
@@ -166,7 +446,10 @@ impl Drop for ResultSet {
             self.sender.queries.remove(&req_id);
         }
 
-        self.closer.take().unwrap().send(()).unwrap();
+        if let Some(closer) = self.closer.take() {
+            // the task waiting for it is gone on runtime shutdown.
+            let _ = closer.send(());
+        }
         // let _ = self
         //     .sender
         //     .blocking_send_only(WsSend::FreeResult(self.args));
@@ -192,9 +475,9 @@ pub enum Error {
     StdSendError(#[from] std::sync::mpsc::SendError<tokio_tungstenite::tungstenite::Message>),
     #[error("{0}")]
     RecvError(#[from] std::sync::mpsc::RecvError),
-    #[error(transparent)]
+    #[error("{0}")]
     RecvTimeout(#[from] std::sync::mpsc::RecvTimeoutError),
-    #[error(transparent)]
+    #[error("{0}")]
     SendTimeoutError(#[from] tokio::sync::mpsc::error::SendTimeoutError<Message>),
     #[error("Query timed out with sql: {0}")]
     QueryTimeout(String),
@@ -204,10 +487,36 @@ pub enum Error {
     DeError(#[from] DeError),
     #[error("WebSocket internal error: {0}")]
     WsError(#[from] WsError),
-    #[error(transparent)]
+    #[error("{0}")]
     IoError(#[from] std::io::Error),
     #[error("Websocket has been closed: {0}")]
     WsClosed(String),
+    #[error("Waited {0:?} for a free query slot")]
+    QueueTimeout(Duration),
+    #[error("Proxy {stage} failed: {cause}")]
+    Proxy { stage: ProxyStage, cause: String },
+    #[error("Result of snapshot {snapshot} changed when resuming at row {rows}")]
+    ResumeMismatch { snapshot: String, rows: usize },
+    #[error("Connection is re-established while fetching the result, query again: {0}")]
+    ReconnectedMidFetch(String),
+    #[error("Response is out of sync with the request: {0}")]
+    ProtocolDesync(String),
+}
+
+impl From<ConnectError> for Error {
+    fn from(err: ConnectError) -> Self {
+        match err {
+            ConnectError::Proxy { stage, cause } => Error::Proxy { stage, cause },
+            ConnectError::Tls(err) => Error::Dsn(err),
+            ConnectError::Ws(err) => Error::WsError(err),
+        }
+    }
+}
+
+impl From<taos_query::stmt::CoerceError> for Error {
+    fn from(err: taos_query::stmt::CoerceError) -> Self {
+        Error::TaosError(err.into())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -221,6 +530,11 @@ pub enum WS_ERROR_NO {
     RECV_MESSAGE_TIMEOUT = 0xE004,
     IO_ERROR = 0xE005,
     UNAUTHORIZED = 0xE006,
+    QUEUE_TIMEOUT = 0xE007,
+    PROXY_ERROR = 0xE008,
+    RESUME_MISMATCH = 0xE009,
+    RECONNECTED_MID_FETCH = 0xE00A,
+    PROTOCOL_DESYNC = 0xE00B,
 }
 
 impl WS_ERROR_NO {
@@ -239,9 +553,23 @@ impl Error {
             Error::WsError(_) => Code::new(WS_ERROR_NO::WEBSOCKET_ERROR as _),
             Error::SendTimeoutError(_) => Code::new(WS_ERROR_NO::SEND_MESSAGE_TIMEOUT as _),
             Error::RecvTimeout(_) => Code::new(WS_ERROR_NO::RECV_MESSAGE_TIMEOUT as _),
+            Error::QueueTimeout(_) => Code::new(WS_ERROR_NO::QUEUE_TIMEOUT as _),
+            Error::Proxy { .. } => Code::new(WS_ERROR_NO::PROXY_ERROR as _),
+            Error::ResumeMismatch { .. } => Code::new(WS_ERROR_NO::RESUME_MISMATCH as _),
+            Error::ReconnectedMidFetch(_) => Code::new(WS_ERROR_NO::RECONNECTED_MID_FETCH as _),
+            Error::ProtocolDesync(_) => Code::new(WS_ERROR_NO::PROTOCOL_DESYNC as _),
             _ => Code::Failed,
         }
     }
+
+    /// The request failed because the connection is lost.
+    fn is_conn_lost(&self) -> bool {
+        match self {
+            Error::TaosError(error) => error.code() == WS_ERROR_NO::CONN_CLOSED.as_code(),
+            Error::SendError(_) | Error::SendTimeoutError(_) | Error::WsClosed(_) => true,
+            _ => false,
+        }
+    }
     pub fn errstr(&self) -> String {
         match self {
             Error::TaosError(error) => error.message().to_string(),
@@ -252,90 +580,69 @@ impl Error {
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// Log a websocket frame when frame tracing is enabled by [TaosBuilder::native_log].
+fn trace_frame(direction: &str, message: &Message) {
+    match message {
+        Message::Text(text) => log::debug!(target: "taos_ws::frame", "{direction} text: {text}"),
+        Message::Binary(bytes) => {
+            log::debug!(target: "taos_ws::frame", "{direction} binary: {} bytes", bytes.len())
+        }
+        message => log::debug!(target: "taos_ws::frame", "{direction} {message:?}"),
+    }
+}
+
+/// Count and drop a response no request is waiting for, logged with frames.
+fn drop_stale_frame(stale_frames: &AtomicU64, frame: std::fmt::Arguments<'_>) {
+    stale_frames.fetch_add(1, Ordering::SeqCst);
+    log::debug!(target: "taos_ws::frame", "drop stale {frame}");
+}
+
 async fn read_queries(
     mut reader: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
     queries_sender: QueryAgent,
     fetches_sender: Arc<QueryResMapper>,
+    stale_frames: Arc<AtomicU64>,
     ws2: WsSender,
     is_v3: bool,
+    trace_frames: bool,
+    block_pool: Option<BlockPool>,
     mut close_listener: watch::Receiver<bool>,
-) {
-    'ws: loop {
+) -> String {
+    let reason = 'ws: loop {
         tokio::select! {
             Some(message) = reader.next() => {
+                if let (true, Ok(message)) = (trace_frames, &message) {
+                    trace_frame("recv", message);
+                }
                 match message {
                     Ok(message) => match message {
                         Message::Text(text) => {
                             log::trace!("received json response: {text}");
-                            let v: WsRecv = serde_json::from_str(&text).unwrap();
+                            let mut frame = text.into_bytes();
+                            let v: WsRecv = json::from_frame(&mut frame).unwrap();
                             let (req_id, data, ok) = v.ok();
-                            match &data {
-                                WsRecvData::Query(_) => {
-                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                    {
-                                        sender.send(ok.map(|_| data)).unwrap();
-                                    } else {
-                                        debug_assert!(!queries_sender.contains_key(&req_id));
-                                        log::warn!("req_id {req_id} not detected, message might be lost");
-                                    }
-                                }
-                                WsRecvData::Fetch(fetch) => {
-                                    let id = fetch.id;
-                                    if fetch.completed {
-                                        ws2.send(
-                                            WsSend::FreeResult(WsResArgs {
-                                                req_id,
-                                                id,
-                                            })
-                                            .to_msg(),
-                                        )
-                                        .await
-                                        .unwrap();
-                                    }
-                                    // dbg!(&queries_sender);
-                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                    {
-                                        sender.send(ok.map(|_| data)).unwrap();
-                                    } else {
-                                        log::warn!("req_id {req_id} not detected, message might be lost");
-                                    }
-                                }
-                                WsRecvData::FetchBlock => {
-                                    assert!(ok.is_err());
-                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                    {
-                                        sender.send(ok.map(|_| data)).unwrap();
-                                    } else {
-                                        log::warn!("req_id {req_id} not detected, message might be lost");
-                                    }
-                                }
-                                WsRecvData::WriteMeta => {
-                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                    {
-                                        sender.send(ok.map(|_| data)).unwrap();
-                                    } else {
-                                        log::warn!("req_id {req_id} not detected, message might be lost");
-                                    }
+                            if let WsRecvData::Fetch(fetch) = &data {
+                                if fetch.completed {
+                                    let args = WsResArgs { req_id, id: fetch.id };
+                                    let _ = ws2.send(WsSend::FreeResult(args).to_msg()).await;
                                 }
-                                WsRecvData::WriteRaw => {
-                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                    {
-                                        sender.send(ok.map(|_| data)).unwrap();
-                                    } else {
-                                        log::warn!("req_id {req_id} not detected, message might be lost");
-                                    }
-                                }
-                                WsRecvData::WriteRawBlock | WsRecvData::WriteRawBlockWithFields => {
-                                    if let Some((_, sender)) = queries_sender.remove(&req_id)
-                                    {
-                                        sender.send(ok.map(|_| data)).unwrap();
-                                    } else {
-                                        log::warn!("req_id {req_id} not detected, message might be lost");
-                                    }
+                            }
+                            let query = match &data {
+                                WsRecvData::Query(query) if ok.is_ok() => Some(WsResArgs { req_id, id: query.id }),
+                                _ => None,
+                            };
+                            let action = data.action();
+                            let received = match queries_sender.remove(&req_id) {
+                                Some((_, sender)) => sender.send(ok.map(|_| data)).is_ok(),
+                                None => false,
+                            };
+                            if !received {
+                                drop_stale_frame(&stale_frames, format_args!("{action} response of req_id {req_id:#x}"));
+                                if let Some(args) = query {
+                                    // the query future is dropped, free the result in place of it.
+                                    log::trace!("req_id {req_id} is cancelled, free result {}", args.id);
+                                    let _ = ws2.send(WsSend::FreeResult(args).to_msg()).await;
                                 }
-
-                                // Block type is for binary.
-                                _ => unreachable!(),
                             }
                         }
                         Message::Binary(block) => {
@@ -351,26 +658,28 @@ async fn read_queries(
                             };
 
                             let res_id = slice.read_u64().unwrap();
-                            if let Some((_, req_id)) =  fetches_sender.remove(&res_id) {
-                                if is_v3 {
-                                    // v3
-                                    if let Some((_, sender)) = queries_sender.remove(&req_id) {
-                                        log::trace!("send data to fetches with id {}", res_id);
-                                        sender.send(Ok(WsRecvData::Block { timing, raw: block[offset..].to_vec() })).unwrap();
-                                    } else {
-                                        log::warn!("req_id {res_id} not detected, message might be lost");
-                                    }
-                                } else {
-                                    // v2
-                                    if let Some((_, sender)) = queries_sender.remove(&req_id) {
-                                        log::trace!("send data to fetches with id {}", res_id);
-                                        sender.send(Ok(WsRecvData::BlockV2 { timing, raw: block[offset..].to_vec() })).unwrap();
+                            let copy = |data: &[u8]| match &block_pool {
+                                Some(pool) => pool.copy_from_slice(data),
+                                None => Bytes::copy_from_slice(data),
+                            };
+                            let sender = fetches_sender
+                                .remove(&res_id)
+                                .and_then(|(_, req_id)| queries_sender.remove(&req_id));
+                            let received = match sender {
+                                Some((_, sender)) => {
+                                    log::trace!("send data to fetches with id {}", res_id);
+                                    let raw = copy(&block[offset..]);
+                                    let data = if is_v3 {
+                                        WsRecvData::Block { timing, raw }
                                     } else {
-                                        log::warn!("req_id {res_id} not detected, message might be lost");
-                                    }
+                                        WsRecvData::BlockV2 { timing, raw }
+                                    };
+                                    sender.send(Ok(data)).is_ok()
                                 }
-                            } else {
-                                log::warn!("result id {res_id} not found");
+                                None => false,
+                            };
+                            if !received {
+                                drop_stale_frame(&stale_frames, format_args!("block of result {res_id}"));
                             }
                         }
                         Message::Close(close) => {
@@ -389,6 +698,7 @@ async fn read_queries(
                                         let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(), close.reason.to_string())));
                                     }
                                 }
+                                break 'ws format!("websocket received close frame: {close}");
                             } else {
                                 log::warn!("websocket connection is closed normally");
                                 let mut keys = Vec::new();
@@ -403,11 +713,11 @@ async fn read_queries(
                                         let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(), "received close message")));
                                     }
                                 }
+                                break 'ws "websocket connection is closed normally".to_string();
                             }
-                            break 'ws;
                         }
                         Message::Ping(bytes) => {
-                            ws2.send(Message::Pong(bytes)).await.unwrap();
+                            let _ = ws2.send(Message::Pong(bytes)).await;
                         }
                         Message::Pong(_) => {
                             // do nothing
@@ -433,7 +743,7 @@ async fn read_queries(
                                 let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(), err.to_string())));
                             }
                         }
-                        break 'ws;
+                        break 'ws format!("reading websocket error: {err}");
                     }
                 }
             }
@@ -451,12 +761,12 @@ async fn read_queries(
                         let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(), "close signal received")));
                     }
                 }
-                break 'ws;
+                break 'ws "close signal received".to_string();
             }
         }
-    }
+    };
     if queries_sender.is_empty() {
-        return;
+        return reason;
     }
 
     let mut keys = Vec::new();
@@ -470,9 +780,215 @@ async fn read_queries(
     //     .await;
     for k in keys {
         if let Some((_, sender)) = queries_sender.remove(&k) {
-            let _ = sender.send(Err(RawError::from_any("websocket connection is closed")));
+            let _ = sender.send(Err(RawError::new(
+                WS_ERROR_NO::CONN_CLOSED.as_code(),
+                "websocket connection is closed",
+            )));
+        }
+    }
+    reason
+}
+
+type WsStreamSender = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type WsStreamReader = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// Open websocket, check server version and login.
+async fn connect(info: &TaosBuilder) -> Result<(WsStreamSender, WsStreamReader, String)> {
+    let mut config = WebSocketConfig::default();
+    config.max_frame_size = Some(1024 * 1024 * 16);
+
+    let ws = info
+        .connect_ws(&info.to_query_url(), Some(config))
+        .await
+        .map_err(|err| match err {
+            ConnectError::Ws(err) if err.to_string().contains("401 Unauthorized") => {
+                Error::Unauthorized(taos_query::redact_secrets(&info.to_query_url()))
+            }
+            err => err.into(),
+        })?;
+    let req_id = 0;
+    let (mut sender, mut reader) = ws.split();
+
+    let version = WsSend::Version;
+    sender.send(version.to_msg()).await?;
+
+    let duration = Duration::from_secs(2);
+    let version = match tokio::time::timeout(duration, reader.next()).await {
+        Ok(Some(Ok(message))) => match message {
+            Message::Text(text) => {
+                let v: WsRecv = serde_json::from_str(&text).unwrap();
+                let (_, data, ok) = v.ok();
+                match data {
+                    WsRecvData::Version { version } => {
+                        ok?;
+                        version
+                    }
+                    _ => "2.x".to_string(),
+                }
+            }
+            _ => "2.x".to_string(),
+        },
+        _ => "2.x".to_string(),
+    };
+
+    let login = WsSend::Conn {
+        req_id,
+        req: info.to_conn_request(),
+    };
+    sender.send(login.to_msg()).await?;
+    if let Some(Ok(message)) = reader.next().await {
+        match message {
+            Message::Text(text) => {
+                let v: WsRecv = serde_json::from_str(&text).unwrap();
+                let (_req_id, data, ok) = v.ok();
+                match data {
+                    WsRecvData::Conn => ok?,
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
         }
     }
+    Ok((sender, reader, version))
+}
+
+/// The connection task, it forwards messages to websocket and re-establishes lost connection.
+struct WsConnection {
+    info: TaosBuilder,
+    state: ConnStateNotifier,
+    queries: QueryAgent,
+    fetches: Arc<QueryResMapper>,
+    ws: WsSender,
+    is_v3: bool,
+    /// Stale responses dropped by the reader, see [WsMetrics::dropped_stale_frames].
+    stale_frames: Arc<AtomicU64>,
+    epoch: watch::Sender<u64>,
+    /// Current database to log in with after reconnect.
+    database: Arc<Mutex<Option<String>>>,
+}
+
+impl WsConnection {
+    async fn run(
+        self,
+        mut sender: WsStreamSender,
+        mut reader: WsStreamReader,
+        mut msg_recv: tokio::sync::mpsc::Receiver<Message>,
+        mut close: watch::Receiver<bool>,
+    ) {
+        loop {
+            let (reader_close, reader_close_listener) = watch::channel(false);
+            let mut reader_task = tokio::spawn(read_queries(
+                reader,
+                self.queries.clone(),
+                self.fetches.clone(),
+                self.stale_frames.clone(),
+                self.ws.clone(),
+                self.is_v3,
+                self.info.trace_frames,
+                self.info.block_pool.clone(),
+                reader_close_listener,
+            ));
+
+            let reason = 'ws: loop {
+                tokio::select! {
+                    Some(msg) = msg_recv.recv() => {
+                        if self.info.trace_frames {
+                            trace_frame("send", &msg);
+                        }
+                        if let Err(err) = sender.send(msg).await {
+                            log::error!("send websocket message packet error: {}", err);
+                            let mut keys = Vec::new();
+                            self.queries.iter().for_each(|r| keys.push(*r.key()));
+                            for k in keys {
+                                if let Some((_, sender)) = self.queries.remove(&k) {
+                                    let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(), err.to_string())));
+                                }
+                            }
+                            let _ = reader_close.send(true);
+                            break 'ws Some(err.to_string());
+                        }
+                    }
+                    reason = &mut reader_task => {
+                        break 'ws Some(reason.unwrap_or_else(|err| err.to_string()));
+                    }
+                    _ = close.changed() => {
+                        let _ = time::timeout(CLOSE_TIMEOUT, sender.close()).await;
+                        let _ = reader_close.send(true);
+                        log::trace!("close sender task");
+                        break 'ws None;
+                    }
+                }
+            };
+            let reason = match reason {
+                Some(reason) => reason,
+                None => {
+                    self.state.close("closed by client");
+                    return;
+                }
+            };
+            log::warn!("websocket connection lost: {reason}");
+            // Requests sent after the reader stopped would never be answered.
+            let mut keys = Vec::new();
+            self.queries.iter().for_each(|r| keys.push(*r.key()));
+            for k in keys {
+                if let Some((_, sender)) = self.queries.remove(&k) {
+                    let _ = sender.send(Err(RawError::new(
+                        WS_ERROR_NO::CONN_CLOSED.as_code(),
+                        reason.clone(),
+                    )));
+                }
+            }
+            if !self.info.reconnect.is_enabled() {
+                self.state.close(reason);
+                return;
+            }
+
+            match self.reconnect(&mut close).await {
+                Some(Ok((s, r, version))) => {
+                    sender = s;
+                    reader = r;
+                    self.epoch.send_modify(|epoch| *epoch += 1);
+                    self.state.set(ConnState::Connected {
+                        server_version: version,
+                    });
+                }
+                Some(Err(_)) => {
+                    self.state.close(reason);
+                    return;
+                }
+                None => {
+                    self.state.close("closed by client");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Try to reconnect, returns `None` if closed by client while reconnecting.
+    async fn reconnect(
+        &self,
+        close: &mut watch::Receiver<bool>,
+    ) -> Option<Result<(WsStreamSender, WsStreamReader, String)>> {
+        let policy = self.info.reconnect;
+        let mut info = self.info.clone();
+        info.database = self.database.lock().unwrap().clone();
+        let mut last = Some(Err(Error::WsClosed("reconnect is disabled".to_string())));
+        for attempt in 1..=policy.max_retries() {
+            self.state.set(ConnState::Reconnecting { attempt });
+            tokio::select! {
+                _ = time::sleep(policy.backoff(attempt)) => {}
+                _ = close.changed() => return None,
+            }
+            match connect(&info).await {
+                Ok(conn) => return Some(Ok(conn)),
+                Err(err) => {
+                    log::warn!("reconnect attempt {attempt} failed: {err}");
+                    last = Some(Err(err));
+                }
+            }
+        }
+        last
+    }
 }
 
 impl WsTaos {
@@ -488,128 +1004,78 @@ impl WsTaos {
         Self::from_wsinfo(&info).await
     }
     pub(crate) async fn from_wsinfo(info: &TaosBuilder) -> Result<Self> {
-        let mut config = WebSocketConfig::default();
-        config.max_frame_size = Some(1024 * 1024 * 16);
+        let state = ConnStateNotifier::new(info.state_listener.clone());
+        Self::from_wsinfo_with_state(info, state, QueryLimiter::of(info)).await
+    }
 
-        let (ws, _) = connect_async_with_config(info.to_query_url(), Some(config))
-            .await
-            .map_err(|err| {
-                let err_string = err.to_string();
-                if err_string.contains("401 Unauthorized") {
-                    Error::Unauthorized(info.to_query_url())
-                } else {
-                    err.into()
-                }
-            })?;
-        let req_id = 0;
-        let (mut sender, mut reader) = ws.split();
-
-        let version = WsSend::Version;
-        sender.send(version.to_msg()).await?;
-
-        let duration = Duration::from_secs(2);
-        let version = match tokio::time::timeout(duration, reader.next()).await {
-            Ok(Some(Ok(message))) => match message {
-                Message::Text(text) => {
-                    let v: WsRecv = serde_json::from_str(&text).unwrap();
-                    let (_, data, ok) = v.ok();
-                    match data {
-                        WsRecvData::Version { version } => {
-                            ok?;
-                            version
-                        }
-                        _ => "2.x".to_string(),
-                    }
-                }
-                _ => "2.x".to_string(),
-            },
-            _ => "2.x".to_string(),
+    /// Connect with connection state changes notified by `state`, requests take slots of
+    /// `limiter`.
+    pub(crate) async fn from_wsinfo_with_state(
+        info: &TaosBuilder,
+        state: ConnStateNotifier,
+        limiter: Arc<QueryLimiter>,
+    ) -> Result<Self> {
+        state.set(ConnState::Connecting);
+        let (sender, reader, version) = match connect(info).await {
+            Ok(conn) => conn,
+            Err(err) => {
+                state.close(err.to_string());
+                return Err(err);
+            }
         };
+        state.set(ConnState::Connected {
+            server_version: version.clone(),
+        });
         let is_v3 = !version.starts_with("2");
 
-        let login = WsSend::Conn {
-            req_id,
-            req: info.to_conn_request(),
-        };
-        sender.send(login.to_msg()).await?;
-        if let Some(Ok(message)) = reader.next().await {
-            match message {
-                Message::Text(text) => {
-                    let v: WsRecv = serde_json::from_str(&text).unwrap();
-                    let (_req_id, data, ok) = v.ok();
-                    match data {
-                        WsRecvData::Conn => ok?,
-                        _ => unreachable!(),
-                    }
-                }
-                _ => unreachable!(),
-            }
-        }
-
         let queries2 = Arc::new(QueryInner::new());
 
         let fetches_sender = Arc::new(QueryResMapper::new());
         let results = fetches_sender.clone();
 
         let queries2_cloned = queries2.clone();
-        let queries3 = queries2.clone();
 
-        let (ws, mut msg_recv) = tokio::sync::mpsc::channel(100);
-        let ws2 = ws.clone();
+        let (ws, msg_recv) = tokio::sync::mpsc::channel(100);
 
         // Connection watcher
-        let (tx, mut rx) = watch::channel(false);
-        let close_listener = rx.clone();
-
-        tokio::spawn(async move {
-            let mut interval = time::interval(Duration::from_millis(10));
-
-            'ws: loop {
-                tokio::select! {
-                    _ = interval.tick() => {
-                        //
-                        // println!("10ms passed");
-                    }
-                    Some(msg) = msg_recv.recv() => {
-                        // dbg!(&msg);
-                        if let Err(err) = sender.send(msg).await {
-                                log::error!("send websocket message packet error: {}", err);
-                                let mut keys = Vec::new();
-                                queries3.iter().for_each(|r| keys.push(*r.key()));
-                                // queries3.for_each_async(|k, _| {
-                                //     keys.push(*k);
-                                // }).await;
-                                for k in keys {
-                                    if let Some((_, sender)) = queries3.remove(&k) {
-                                        let _ = sender.send(Err(RawError::new(WS_ERROR_NO::CONN_CLOSED.as_code(), err.to_string())));
-                                    }
-                                }
-                                break 'ws;
-                            }
-                    }
-                    _ = rx.changed() => {
-                        let _ = sender.close().await;
-                        log::trace!("close sender task");
-                        break 'ws;
-                    }
-                }
-            }
-        });
-
-        tokio::spawn(async move {
-            read_queries(reader, queries2, fetches_sender, ws2, is_v3, close_listener).await
-        });
+        let (tx, rx) = watch::channel(false);
+        let (epoch, epoch_listener) = watch::channel(0);
+        let database = Arc::new(Mutex::new(info.database.clone()));
+        let stale_frames = Arc::new(AtomicU64::new(0));
+
+        let conn = WsConnection {
+            info: info.clone(),
+            state,
+            queries: queries2,
+            fetches: fetches_sender,
+            ws: ws.clone(),
+            is_v3,
+            stale_frames: stale_frames.clone(),
+            epoch,
+            database: database.clone(),
+        };
+        let task = tokio::spawn(conn.run(sender, reader, msg_recv, rx));
         let ws_cloned = ws.clone();
 
         Ok(Self {
             close_signal: tx,
+            task: Mutex::new(Some(task)),
             sender: WsQuerySender {
                 version: Version(version),
                 req_id: Default::default(),
                 sender: ws_cloned,
                 queries: queries2_cloned,
                 results,
+                limiter,
+                stale_frames,
+                epoch: epoch_listener,
+                reconnect: info.reconnect,
+                database,
+                warning_listener: info.warning_listener.clone(),
+                json_fetch: info.json_fetch,
             },
+            rate_limiter: info.rate_limiter.clone(),
+            max_write_block_size: info.max_write_block_size,
         })
     }
 
@@ -633,134 +1099,154 @@ impl WsTaos {
             _ => unreachable!(),
         }
     }
+    /// Write a block into its table, blocks of messages larger than the max write block size
+    /// are written in chunks of rows, see [TaosBuilder::with_max_write_block_size].
     async fn s_write_raw_block(&self, raw: &RawBlock) -> Result<()> {
-        let req_id = self.sender.req_id();
-        let message_id = req_id;
-        // if self.version().starts_with('2') {
-        //     panic!("TDengine v2.x does not support to write_raw_block");
-        // }
-        if self.version().starts_with("3.0.1.") {
-            let raw_block_message = 4; // action number from `taosAdapter/controller/rest/const.go:L56`.
-
-            let mut meta = Vec::new();
-            meta.write_u64_le(req_id)?;
-            meta.write_u64_le(message_id)?;
-            meta.write_u64_le(raw_block_message as u64)?;
-            meta.write_u32_le(raw.nrows() as u32)?;
-            meta.write_inlined_str::<2>(raw.table_name().unwrap())?;
-            meta.write_all(raw.as_raw_bytes())?;
-
-            let len = meta.len();
-            log::trace!("write block with req_id: {req_id}, raw data len: {len}",);
-
-            match self.sender.send_recv(WsSend::Binary(meta)).await? {
-                WsRecvData::WriteRawBlock | WsRecvData::WriteRawBlockWithFields => Ok(()),
-                _ => Err(RawError::from_string("write raw block error"))?,
+        if raw.table_name().is_none() {
+            Err(RawError::from_string("write raw block without table name"))?;
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire(raw.nrows(), raw.raw_len()).await;
+        }
+        let limit = self.max_write_block_size;
+        let len = self.raw_block_message_len(raw);
+        if len <= limit || raw.nrows() <= 1 {
+            return self.write_raw_block_message(raw).await;
+        }
+        // rows are of variable sizes, chunks still too large are split in halves.
+        let rows = (raw.nrows() * limit / len).max(1);
+        log::trace!("write block of {len} bytes in chunks of {rows} rows");
+        let mut chunks = raw.chunks(rows).collect_vec();
+        chunks.reverse();
+        while let Some(chunk) = chunks.pop() {
+            if chunk.nrows() > 1 && self.raw_block_message_len(&chunk) > limit {
+                let (head, tail) = chunk.split_at(chunk.nrows() / 2);
+                chunks.push(tail);
+                chunks.push(head);
+            } else {
+                self.write_raw_block_message(&chunk).await?;
             }
+        }
+        Ok(())
+    }
+
+    /// Fields are sent with blocks having field names unless the server is 3.0.1.x.
+    fn with_fields(&self, raw: &RawBlock) -> bool {
+        !raw.field_names().is_empty() && !self.version().starts_with("3.0.1.")
+    }
+
+    fn raw_block_message_len(&self, raw: &RawBlock) -> usize {
+        let table_name = raw.table_name().unwrap_or_default();
+        let fields = if self.with_fields(raw) {
+            raw.ncols() * 72
         } else {
-            let raw_block_message = 5; // action number from `taosAdapter/controller/rest/const.go:L56`.
-
-            let mut meta = Vec::new();
-            meta.write_u64_le(req_id)?;
-            meta.write_u64_le(message_id)?;
-            meta.write_u64_le(raw_block_message as u64)?;
-            meta.write_u32_le(raw.nrows() as u32)?;
-            meta.write_inlined_str::<2>(raw.table_name().unwrap())?;
-            meta.write_all(raw.as_raw_bytes())?;
+            0
+        };
+        30 + table_name.len() + raw.raw_len() + fields
+    }
+
+    async fn write_raw_block_message(&self, raw: &RawBlock) -> Result<()> {
+        let req_id = self.sender.req_id();
+        let message_id = req_id;
+        let table_name = raw.table_name().unwrap_or_default();
+        // action numbers from `taosAdapter/controller/rest/const.go:L56`.
+        let (raw_block_message, fields) = if self.with_fields(raw) {
             let fields = raw
                 .fields()
                 .into_iter()
                 .map(|f| f.to_c_field())
                 .collect_vec();
+            (5, fields)
+        } else {
+            (4, Vec::new())
+        };
+        let fields =
+            unsafe { std::slice::from_raw_parts(fields.as_ptr() as *const u8, fields.len() * 72) };
 
-            let fields =
-                unsafe { std::slice::from_raw_parts(fields.as_ptr() as _, fields.len() * 72) };
-            meta.write_all(fields)?;
-            let len = meta.len();
-            log::trace!("write block with req_id: {req_id}, raw data len: {len}",);
+        // sized up front, so the block is written once into the message without regrowing it.
+        let mut meta = Vec::with_capacity(self.raw_block_message_len(raw));
+        meta.write_u64_le(req_id)?;
+        meta.write_u64_le(message_id)?;
+        meta.write_u64_le(raw_block_message as u64)?;
+        meta.write_u32_le(raw.nrows() as u32)?;
+        meta.write_inlined_str::<2>(table_name)?;
+        raw.write_vectored(&mut meta)?;
+        meta.write_all(fields)?;
+        let len = meta.len();
+        log::trace!("write block with req_id: {req_id}, raw data len: {len}",);
 
-            match self.sender.send_recv(WsSend::Binary(meta)).await? {
-                WsRecvData::WriteRawBlock | WsRecvData::WriteRawBlockWithFields => Ok(()),
-                _ => Err(RawError::from_string("write raw block error"))?,
-            }
+        match self.sender.send_recv(WsSend::Binary(meta)).await? {
+            WsRecvData::WriteRawBlock | WsRecvData::WriteRawBlockWithFields => Ok(()),
+            _ => Err(RawError::from_string("write raw block error"))?,
         }
     }
 
     pub async fn s_query(&self, sql: &str) -> Result<ResultSet> {
-        let req_id = self.sender.req_id();
-        let action = WsSend::Query {
-            req_id,
-            sql: sql.to_string(),
-        };
-
-        let req = self.sender.send_recv(action).await?;
+        self.s_query_in(None, sql).await
+    }
 
-        let resp = match req {
-            WsRecvData::Query(resp) => resp,
-            _ => unreachable!(),
-        };
+    /// Query in database `db` if set, which is sent along with the sql so the current database
+    /// of the connection is untouched.
+    pub async fn s_query_in(&self, db: Option<&str>, sql: &str) -> Result<ResultSet> {
+        self.sender.query_in(db, sql).await
+    }
 
-        let result_id = resp.id;
-        //  for drop task.
-        let (closer, rx) = oneshot::channel();
-        tokio::task::spawn(async move {
-            let t = Instant::now();
-            let _ = rx.await;
-            log::trace!("result {result_id} lives {:?}", t.elapsed());
-        });
+    /// Query with a request id of the caller instead of a generated one, to correlate with
+    /// server logs. It must not be used by another in-flight request of the connection.
+    pub async fn s_query_with_req_id(&self, sql: &str, req_id: u64) -> Result<ResultSet> {
+        if self.sender.queries.contains_key(&req_id) {
+            Err(RawError::from_string(format!(
+                "req id {req_id:#x} is used by an in-flight request"
+            )))?;
+        }
+        self.sender.query_with_req_id(None, sql, req_id).await
+    }
 
-        if resp.fields_count > 0 {
-            let names = resp.fields_names.unwrap();
-            let types = resp.fields_types.unwrap();
-            let bytes = resp.fields_lengths.unwrap();
-            let fields: Vec<_> = names
-                .into_iter()
-                .zip(types)
-                .zip(bytes)
-                .map(|((name, ty), bytes)| Field::new(name, ty, bytes))
-                .collect();
-            Ok(ResultSet {
-                fields: Some(fields),
-                fields_count: resp.fields_count,
-                precision: resp.precision,
-                affected_rows: resp.affected_rows,
-                args: WsResArgs {
-                    req_id,
-                    id: resp.id,
-                },
-                summary: (0, 0),
-                sender: self.sender.clone(),
-                timing: resp.timing,
-                block_future: None,
-                closer: Some(closer),
-            })
-        } else {
-            Ok(ResultSet {
-                affected_rows: resp.affected_rows,
-                args: WsResArgs {
-                    req_id,
-                    id: resp.id,
-                },
-                fields: None,
-                fields_count: 0,
-                precision: resp.precision,
-                summary: (0, 0),
-                sender: self.sender.clone(),
-                timing: resp.timing,
-                block_future: None,
-                closer: Some(closer),
-            })
+    /// Query that is resumed after reconnect, see [Taos::resumable_query](super::Taos::resumable_query).
+    pub async fn s_resumable_query_in(
+        &self,
+        db: Option<&str>,
+        sql: &str,
+        fingerprint: QueryFingerprint,
+    ) -> Result<ResultSet> {
+        if let Some(word) = super::resume::non_deterministic_word(sql) {
+            Err(RawError::from_string(format!(
+                "query with `{word}` could not be resumed: {sql}"
+            )))?;
         }
+        let epoch = *self.sender.epoch.borrow();
+        let mut rs = self.sender.query_in(db, sql).await?;
+        rs.resume = Some(ResumeState::new(db, sql, fingerprint, epoch));
+        Ok(rs)
     }
 
     pub async fn s_exec(&self, sql: &str) -> Result<usize> {
+        self.s_exec_in(None, sql).await
+    }
+
+    /// Execute in database `db` if set, see [WsTaos::s_query_in].
+    pub async fn s_exec_in(&self, db: Option<&str>, sql: &str) -> Result<usize> {
+        self.s_exec_with_warnings_in(db, sql)
+            .await
+            .map(|res| res.affected_rows())
+    }
+
+    /// Execute in database `db` if set, and keep warnings returned by the server.
+    pub async fn s_exec_with_warnings_in(&self, db: Option<&str>, sql: &str) -> Result<ExecResult> {
         let req_id = self.sender.req_id();
         let action = WsSend::Query {
             req_id,
             sql: sql.to_string(),
+            db: db.map(ToString::to_string),
         };
         match self.sender.send_recv(action).await? {
-            WsRecvData::Query(query) => Ok(query.affected_rows),
+            WsRecvData::Query(query) => {
+                self.sender.notify_warnings(&query.warnings);
+                if db.is_none() {
+                    self.sender.track_database(sql);
+                }
+                Ok(ExecResult::new(query.affected_rows, query.warnings))
+            }
             _ => unreachable!(),
         }
     }
@@ -768,10 +1254,145 @@ impl WsTaos {
     pub fn version(&self) -> &str {
         &self.sender.version.0
     }
+
+    /// In-flight and queued requests of this connection.
+    pub fn queue_stats(&self) -> QueueStats {
+        self.sender.limiter.stats()
+    }
+
+    /// Counters of this connection, eg. of stale responses dropped.
+    pub fn metrics(&self) -> WsMetrics {
+        WsMetrics {
+            dropped_stale_frames: self.sender.stale_frames.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Close the websocket, requests in flight fail as the connection is closed.
+    pub(crate) fn close(&self) {
+        let _ = self.close_signal.send(true);
+    }
+
+    /// Close the websocket and wait for the close frame to be sent and the connection task to
+    /// end, at most [CLOSE_TIMEOUT].
+    pub(crate) async fn close_gracefully(&self) {
+        self.close();
+        let task = self.task.lock().unwrap().take();
+        if let Some(task) = task {
+            let _ = time::timeout(CLOSE_TIMEOUT, task).await;
+        }
+    }
 }
 
 impl ResultSet {
+    /// Request id of the query, or of the query after reconnect if it's resumed.
+    pub fn req_id(&self) -> u64 {
+        self.args.req_id
+    }
+
     async fn fetch(&mut self) -> Result<Option<RawBlock>> {
+        if let Some(desync) = &self.desync {
+            return Err(Error::ProtocolDesync(desync.clone()));
+        }
+        let lost_mid_fetch = self.resume.is_none() && self.sender.reconnect.is_enabled();
+        if lost_mid_fetch && *self.sender.epoch.borrow() > self.epoch {
+            return Err(Error::ReconnectedMidFetch(
+                "the result is lost with the previous connection".to_string(),
+            ));
+        }
+        let mut res = self.fetch_once().await;
+        while let Err(err) = &res {
+            if !self.can_resume(err) || !self.wait_reconnected().await {
+                break;
+            }
+            res = self.resume().await;
+        }
+        if let Err(err) = &res {
+            if lost_mid_fetch
+                && err.is_conn_lost()
+                && self.sender.wait_reconnected(self.epoch).await
+            {
+                return Err(Error::ReconnectedMidFetch(err.to_string()));
+            }
+        }
+        if let Err(Error::ProtocolDesync(desync)) = &res {
+            // following responses of the result can't be trusted, other results are intact.
+            self.desync = Some(desync.clone());
+        }
+        if let (Some(resume), Ok(Some(block))) = (&mut self.resume, &res) {
+            resume.record(block);
+        }
+        res
+    }
+
+    /// Check if the result is resumable and count the resume.
+    fn can_resume(&mut self, err: &Error) -> bool {
+        match &mut self.resume {
+            Some(resume) if err.is_conn_lost() => {
+                if resume.resumes >= resume.fingerprint.max_resumes() {
+                    return false;
+                }
+                resume.resumes += 1;
+                log::warn!(
+                    "resuming result of snapshot {} at row {} after: {err}",
+                    resume.fingerprint.snapshot(),
+                    resume.delivered()
+                );
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Wait until the connection the result lives in is re-established, `false` if it's closed.
+    async fn wait_reconnected(&mut self) -> bool {
+        let resume = self.resume.as_mut().expect("resume state is set");
+        let mut epoch = self.sender.epoch.clone();
+        let reconnected = match epoch.wait_for(|epoch| *epoch > resume.epoch).await {
+            Ok(epoch) => Some(*epoch),
+            Err(_) => None,
+        };
+        match reconnected {
+            Some(epoch) => {
+                resume.epoch = epoch;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-execute the query and discard rows that have been delivered, returns the next block
+    /// after the previous position.
+    async fn resume(&mut self) -> Result<Option<RawBlock>> {
+        let resume = self.resume.as_ref().expect("resume state is set");
+        let (db, sql) = (resume.db.clone(), resume.sql.clone());
+        let (snapshot, rows) = (
+            resume.fingerprint.snapshot().to_string(),
+            resume.delivered(),
+        );
+        let mismatch = || Error::ResumeMismatch {
+            snapshot: snapshot.clone(),
+            rows,
+        };
+        let mut ff = resume.fast_forward();
+
+        let mut rs = self.sender.query_in(db.as_deref(), &sql).await?;
+        if rs.fields != self.fields {
+            return Err(mismatch());
+        }
+        // Continue with the new result, the one lost with the connection is dropped.
+        std::mem::swap(&mut self.args, &mut rs.args);
+        drop(rs);
+
+        while let Some(block) = self.fetch_once().await? {
+            if let Some(block) = ff.skip(block).map_err(|_| mismatch())? {
+                return Ok(Some(block));
+            }
+        }
+        ff.finish().map_err(|_| mismatch())?;
+        Ok(None)
+    }
+
+    async fn fetch_once(&mut self) -> Result<Option<RawBlock>> {
         let args = WsResArgs {
             req_id: self.sender.req_id(),
             id: self.args.id,
@@ -794,11 +1415,24 @@ impl ResultSet {
             id: self.args.id,
         };
 
+        if self.sender.json_fetch {
+            let data = match self.sender.send_recv(WsSend::FetchJson(args)).await? {
+                WsRecvData::FetchJson { data } => data,
+                data => panic!("unexpected result {data:?}"),
+            };
+            let fields = self.fields.as_ref().unwrap();
+            let raw = RawBlock::from_json_rows(fields, &data, self.precision)
+                .map_err(RawError::from_any)?;
+            self.timing = fetch_resp.timing;
+            return Ok(Some(raw));
+        }
+
         let fetch_block = WsSend::FetchBlock(args);
 
         match self.sender.send_recv(fetch_block).await? {
             WsRecvData::Block { timing, raw } => {
-                let mut raw = RawBlock::parse_from_raw_block(raw, self.precision);
+                let mut raw = RawBlock::try_parse_from_raw_block(raw, self.precision)
+                    .map_err(RawError::from_any)?;
 
                 raw.with_field_names(self.fields.as_ref().unwrap().iter().map(Field::name));
                 self.timing = timing + fetch_resp.timing;
@@ -852,6 +1486,10 @@ impl AsyncFetchable for ResultSet {
         self.summary
     }
 
+    fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
     fn update_summary(&mut self, nrows: usize) {
         self.summary.0 += 1;
         self.summary.1 += nrows;
@@ -908,6 +1546,10 @@ impl taos_query::Fetchable for ResultSet {
         self.summary
     }
 
+    fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
     fn update_summary(&mut self, nrows: usize) {
         self.summary.0 += 1;
         self.summary.1 += nrows;
@@ -930,6 +1572,14 @@ impl AsyncQueryable for WsTaos {
     ) -> StdResult<Self::AsyncResultSet, Self::Error> {
         self.s_query(sql.as_ref()).await
     }
+
+    async fn exec_with_warnings<T: AsRef<str> + Send + Sync>(
+        &self,
+        sql: T,
+    ) -> StdResult<ExecResult, Self::Error> {
+        self.s_exec_with_warnings_in(None, sql.as_ref()).await
+    }
+
     async fn write_raw_meta(&self, raw: &RawMeta) -> StdResult<(), Self::Error> {
         self.write_meta(raw).await
     }
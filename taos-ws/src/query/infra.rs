@@ -1,9 +1,11 @@
 use std::time::Duration;
 
+use bytes::Bytes;
+
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use serde_with::NoneAsEmptyString;
-use taos_query::common::{Precision, Ty};
+use taos_query::common::{Precision, Ty, Warning};
 use taos_query::prelude::RawError;
 
 pub type ReqId = u64;
@@ -12,7 +14,7 @@ pub type ReqId = u64;
 pub type ResId = u64;
 
 #[serde_as]
-#[derive(Debug, Serialize, Default, Clone)]
+#[derive(Serialize, Default, Clone)]
 pub struct WsConnReq {
     pub(crate) user: Option<String>,
     pub(crate) password: Option<String>,
@@ -20,6 +22,15 @@ pub struct WsConnReq {
     pub(crate) db: Option<String>,
 }
 
+impl std::fmt::Debug for WsConnReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WsConnReq")
+            .field("user", &self.user)
+            .field("db", &self.db)
+            .finish()
+    }
+}
+
 impl WsConnReq {
     #[cfg(test)]
     pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
@@ -41,7 +52,7 @@ pub struct WsResArgs {
     pub id: ResId,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone)]
 #[serde(tag = "action", content = "args")]
 #[serde(rename_all = "snake_case")]
 pub enum WsSend {
@@ -55,9 +66,16 @@ pub enum WsSend {
     Query {
         req_id: ReqId,
         sql: String,
+        /// Database to run the sql in, instead of the current one of the connection.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        db: Option<String>,
     },
     Fetch(WsResArgs),
     FetchBlock(WsResArgs),
+    /// Fetch rows of the block in JSON instead of binary, see [TaosBuilder::with_json_fetch].
+    ///
+    /// [TaosBuilder::with_json_fetch]: crate::TaosBuilder::with_json_fetch
+    FetchJson(WsResArgs),
     Binary(Vec<u8>),
     FreeResult(WsResArgs),
 }
@@ -66,14 +84,31 @@ impl WsSend {
     pub(crate) fn req_id(&self) -> ReqId {
         match self {
             WsSend::Conn { req_id, req: _ } => *req_id,
-            WsSend::Query { req_id, sql: _ } => *req_id,
+            WsSend::Query { req_id, .. } => *req_id,
             WsSend::Fetch(args) => args.req_id,
             WsSend::FetchBlock(args) => args.req_id,
+            WsSend::FetchJson(args) => args.req_id,
             WsSend::FreeResult(args) => args.req_id,
-            WsSend::Binary(bytes) => unsafe { *(bytes.as_ptr() as *const u64) as _ },
+            WsSend::Binary(bytes) => u64::from_le_bytes(bytes[..8].try_into().unwrap()) as _,
             _ => unreachable!(),
         }
     }
+
+    /// The response `data` answers this request, eg. a fetch of the same result, checked
+    /// before a response is delivered so one of another request is never taken as its own.
+    pub(crate) fn expects(&self, data: &WsRecvData) -> bool {
+        use WsRecvData::*;
+        match (self, data) {
+            (WsSend::Conn { .. }, Conn) | (WsSend::Query { .. }, Query(_)) => true,
+            (WsSend::Fetch(args), Fetch(fetch)) => fetch.id == args.id,
+            (WsSend::FetchJson(_), FetchJson { .. }) => true,
+            (WsSend::FetchBlock(_), FetchBlock | Block { .. } | BlockV2 { .. }) => true,
+            (WsSend::Binary(_), WriteMeta | WriteRaw | WriteRawBlock | WriteRawBlockWithFields) => {
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 unsafe impl Send for WsSend {}
@@ -98,6 +133,50 @@ fn test_serde_send() {
     assert_eq!(v, j);
 }
 
+#[test]
+fn test_serde_query_in_db() {
+    let query = |db: Option<&str>| WsSend::Query {
+        req_id: 2,
+        sql: "select * from tb1".to_string(),
+        db: db.map(ToString::to_string),
+    };
+    let v = serde_json::to_value(query(None)).unwrap();
+    let j = serde_json::json!({
+        "action": "query",
+        "args": {
+            "req_id": 2,
+            "sql": "select * from tb1"
+        }
+    });
+    assert_eq!(v, j);
+    let v = serde_json::to_value(query(Some("db1"))).unwrap();
+    assert_eq!(v["args"]["db"], "db1");
+}
+
+#[test]
+fn test_serde_fetch_json() {
+    let v = serde_json::to_value(WsSend::FetchJson(WsResArgs { req_id: 3, id: 7 })).unwrap();
+    let j = serde_json::json!({
+        "action": "fetch_json",
+        "args": {
+            "req_id": 3,
+            "id": 7
+        }
+    });
+    assert_eq!(v, j);
+
+    let json = r#"{"code":0,"message":"","action":"fetch_json","req_id":3,"id":7,"data":[["2022-10-01T08:00:00.123456789+08:00",10.3,null]]}"#;
+    let recv: WsRecv = serde_json::from_str(json).unwrap();
+    let (req_id, data, ok) = recv.ok();
+    assert!(ok.is_ok());
+    assert_eq!(req_id, 3);
+    let WsRecvData::FetchJson { data } = data else {
+        panic!("unexpected {data:?}");
+    };
+    assert_eq!(data[0][0], "2022-10-01T08:00:00.123456789+08:00");
+    assert_eq!(data[0].len(), 3);
+}
+
 #[derive(Debug, Serialize)]
 pub struct WsFetchArgs {
     req_id: ReqId,
@@ -118,6 +197,8 @@ pub struct WsQueryResp {
     pub precision: Precision,
     #[serde_as(as = "serde_with::DurationNanoSeconds")]
     pub timing: Duration,
+    /// Notices of a successful statement.
+    pub warnings: Vec<Warning>,
 }
 
 #[serde_as]
@@ -145,17 +226,24 @@ pub enum WsRecvData {
     Fetch(WsFetchResp),
     /// Will only produced by error
     FetchBlock,
+    /// Rows of the block in JSON, timestamps are strings in the timezone of the adapter.
+    FetchJson {
+        #[serde(default)]
+        data: Vec<Vec<serde_json::Value>>,
+    },
     Block {
         #[serde(default)]
         #[serde_as(as = "serde_with::DurationNanoSeconds")]
         timing: Duration,
-        raw: Vec<u8>,
+        #[serde(skip)]
+        raw: Bytes,
     },
     BlockV2 {
         #[serde(default)]
         #[serde_as(as = "serde_with::DurationNanoSeconds")]
         timing: Duration,
-        raw: Vec<u8>,
+        #[serde(skip)]
+        raw: Bytes,
     },
     WriteMeta,
     WriteRaw,
@@ -163,6 +251,26 @@ pub enum WsRecvData {
     WriteRawBlockWithFields,
 }
 
+impl WsRecvData {
+    /// Action of the response, for logs and errors.
+    pub(crate) fn action(&self) -> &'static str {
+        match self {
+            WsRecvData::Conn => "conn",
+            WsRecvData::Version { .. } => "version",
+            WsRecvData::Query(_) => "query",
+            WsRecvData::Fetch(_) => "fetch",
+            WsRecvData::FetchBlock | WsRecvData::Block { .. } | WsRecvData::BlockV2 { .. } => {
+                "fetch_block"
+            }
+            WsRecvData::FetchJson { .. } => "fetch_json",
+            WsRecvData::WriteMeta => "write_meta",
+            WsRecvData::WriteRaw => "write_raw",
+            WsRecvData::WriteRawBlock => "write_raw_block",
+            WsRecvData::WriteRawBlockWithFields => "write_raw_block_with_fields",
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Deserialize)]
 pub struct WsRecv {
@@ -1,33 +1,206 @@
+use std::sync::Arc;
+
 use once_cell::sync::OnceCell;
-use taos_query::{block_in_place_or_global, common::RawMeta, AsyncQueryable};
+use taos_query::{
+    block_in_place_or_global,
+    common::{ExecResult, RawMeta, SmlData},
+    util::RateLimiter,
+    AsyncQueryable, ConnState, ConnStateNotifier,
+};
 
 pub mod asyn;
 pub(crate) mod infra;
+mod resume;
 // pub mod sync;
 
 pub use asyn::Error;
+pub use asyn::QueueStats;
 pub use asyn::ResultSet;
+pub use asyn::WsMetrics;
+pub(crate) use asyn::QueryLimiter;
 pub(crate) use asyn::WsTaos;
 pub(crate) use infra::WsConnReq;
+pub use resume::QueryFingerprint;
 
+use crate::schemaless::WsSchemaless;
 use crate::TaosBuilder;
 
 #[derive(Debug)]
 pub struct Taos {
     pub(crate) dsn: TaosBuilder,
     pub(crate) async_client: OnceCell<WsTaos>,
+    pub(crate) state: ConnStateNotifier,
+    /// Connection of [Taos::put], connected on the first write.
+    schemaless: tokio::sync::OnceCell<WsSchemaless>,
+    /// In-flight requests of the query, stmt and schemaless connections.
+    pub(crate) limiter: Arc<QueryLimiter>,
 }
 
 impl Taos {
+    /// A connection of `dsn`, connected on the first request.
+    pub(crate) fn new(dsn: &TaosBuilder) -> Self {
+        Self::with_limiter(dsn, QueryLimiter::of(dsn))
+    }
+
+    /// A connection of `dsn` with requests limited by `limiter`, eg. of the stmt it's for.
+    pub(crate) fn with_limiter(dsn: &TaosBuilder, limiter: Arc<QueryLimiter>) -> Self {
+        Self {
+            dsn: dsn.clone(),
+            async_client: OnceCell::new(),
+            state: ConnStateNotifier::new(dsn.state_listener.clone()),
+            schemaless: tokio::sync::OnceCell::new(),
+            limiter,
+        }
+    }
+
     pub fn version(&self) -> &str {
         block_in_place_or_global(self.client()).version()
     }
 
+    /// Current connection state.
+    pub fn state(&self) -> ConnState {
+        self.state.state()
+    }
+
+    /// In-flight and queued requests, including the ones of stmts and schemaless writes of the
+    /// connection, see [TaosBuilder::with_max_concurrent_queries].
+    pub fn queue_stats(&self) -> QueueStats {
+        self.limiter.stats()
+    }
+
+    /// Counters of the connection, eg. of stale responses dropped, zero if not connected yet.
+    pub fn metrics(&self) -> WsMetrics {
+        self.async_client
+            .get()
+            .map(WsTaos::metrics)
+            .unwrap_or_default()
+    }
+
+    /// Close the connection now, requests in flight fail with connection closed errors and
+    /// the state becomes [ConnState::Closed] without reconnecting.
+    pub fn close(&self) {
+        if let Some(ws) = self.async_client.get() {
+            ws.close();
+        }
+        self.state.close("closed by client");
+    }
+
+    /// Close the connection gracefully, the close frame is sent and the background task ends
+    /// before it returns, waiting at most a few seconds for an unresponsive server.
+    ///
+    /// Dropping the connection only signals the background task to close, which is safe in any
+    /// context, eg. on runtime shutdown, but doesn't wait for it.
+    pub async fn close_gracefully(&self) {
+        if let Some(ws) = self.async_client.get() {
+            ws.close_gracefully().await;
+        }
+        self.state.close("closed by client");
+    }
+
+    /// The write limiter of the builder, see [TaosBuilder::with_rate_limit].
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.dsn.rate_limiter()
+    }
+
+    /// Max bytes of a raw block write message, see [TaosBuilder::with_max_write_block_size].
+    pub fn max_write_block_size(&self) -> usize {
+        self.dsn.max_write_block_size
+    }
+
+    /// Query in database `db` without changing the current database of the connection.
+    ///
+    /// The database is sent along with the sql in the query request, so there is no locking
+    /// or extra request as with the native connection.
+    pub async fn query_in(&self, db: &str, sql: &str) -> Result<ResultSet, Error> {
+        if let Some(ws) = self.async_client.get() {
+            ws.s_query_in(Some(db), sql).await
+        } else {
+            let async_client =
+                WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone()).await?;
+            self.async_client
+                .get_or_init(|| async_client)
+                .s_query_in(Some(db), sql)
+                .await
+        }
+    }
+
+    /// Query with a request id of the caller, see [ResultSet::req_id].
+    ///
+    /// Queries by [AsyncQueryable::query] use ids generated by the connection, so a fixed id
+    /// may be rejected if it's in use by an in-flight request.
+    pub async fn query_with_req_id(&self, sql: &str, req_id: u64) -> Result<ResultSet, Error> {
+        if let Some(ws) = self.async_client.get() {
+            ws.s_query_with_req_id(sql, req_id).await
+        } else {
+            let async_client =
+                WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone()).await?;
+            self.async_client
+                .get_or_init(|| async_client)
+                .s_query_with_req_id(sql, req_id)
+                .await
+        }
+    }
+
+    /// Query that survives a lost connection, for deterministic queries only.
+    ///
+    /// If the connection is lost while fetching, the sql is executed again after reconnect and
+    /// rows that have been delivered are discarded, so the result continues where it stopped.
+    /// Delivered rows are verified by a rolling hash, [Error::ResumeMismatch] is returned if
+    /// they changed underneath. Queries with `now`, `today()` or `rand()` are rejected.
+    pub async fn resumable_query(
+        &self,
+        sql: &str,
+        fingerprint: QueryFingerprint,
+    ) -> Result<ResultSet, Error> {
+        if let Some(ws) = self.async_client.get() {
+            ws.s_resumable_query_in(None, sql, fingerprint).await
+        } else {
+            let async_client =
+                WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone()).await?;
+            self.async_client
+                .get_or_init(|| async_client)
+                .s_resumable_query_in(None, sql, fingerprint)
+                .await
+        }
+    }
+
+    /// Execute in database `db`, see [Taos::query_in].
+    pub async fn exec_in(&self, db: &str, sql: &str) -> Result<usize, Error> {
+        if let Some(ws) = self.async_client.get() {
+            ws.s_exec_in(Some(db), sql).await
+        } else {
+            let async_client =
+                WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone()).await?;
+            self.async_client
+                .get_or_init(|| async_client)
+                .s_exec_in(Some(db), sql)
+                .await
+        }
+    }
+
+    /// Write schemaless data, tables are created or altered as needed.
+    ///
+    /// Data is sent on a connection to the schemaless endpoint of the adapter, which is
+    /// opened on the first write and shared by later ones.
+    pub async fn put(&self, data: &SmlData) -> Result<(), Error> {
+        let sml = self
+            .schemaless
+            .get_or_try_init(|| WsSchemaless::connect(&self.dsn, self.limiter.clone()))
+            .await?;
+        if let Some(limiter) = self.rate_limiter() {
+            let bytes = data.lines().iter().map(String::len).sum();
+            limiter.acquire(data.lines().len(), bytes).await;
+        }
+        sml.insert(data).await
+    }
+
     async fn client(&self) -> &WsTaos {
         if let Some(ws) = self.async_client.get() {
             ws
         } else {
-            let async_client = WsTaos::from_wsinfo(&self.dsn).await.unwrap();
+            let async_client = WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone())
+                .await
+                .unwrap();
             self.async_client.get_or_init(|| async_client)
         }
     }
@@ -49,7 +222,8 @@ impl taos_query::AsyncQueryable for Taos {
         if let Some(ws) = self.async_client.get() {
             ws.s_query(sql.as_ref()).await
         } else {
-            let async_client = WsTaos::from_wsinfo(&self.dsn).await?;
+            let async_client =
+                WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone()).await?;
             self.async_client
                 .get_or_init(|| async_client)
                 .s_query(sql.as_ref())
@@ -57,11 +231,28 @@ impl taos_query::AsyncQueryable for Taos {
         }
     }
 
+    async fn exec_with_warnings<T: AsRef<str> + Send + Sync>(
+        &self,
+        sql: T,
+    ) -> Result<ExecResult, Self::Error> {
+        if let Some(ws) = self.async_client.get() {
+            ws.s_exec_with_warnings_in(None, sql.as_ref()).await
+        } else {
+            let async_client =
+                WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone()).await?;
+            self.async_client
+                .get_or_init(|| async_client)
+                .s_exec_with_warnings_in(None, sql.as_ref())
+                .await
+        }
+    }
+
     async fn write_raw_meta(&self, raw: &RawMeta) -> Result<(), Self::Error> {
         if let Some(ws) = self.async_client.get() {
             ws.write_meta(raw).await
         } else {
-            let async_client = WsTaos::from_wsinfo(&self.dsn).await?;
+            let async_client =
+                WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone()).await?;
             self.async_client
                 .get_or_init(|| async_client)
                 .write_meta(raw)
@@ -73,7 +264,8 @@ impl taos_query::AsyncQueryable for Taos {
         if let Some(ws) = self.async_client.get() {
             ws.write_raw_block(block).await
         } else {
-            let async_client = WsTaos::from_wsinfo(&self.dsn).await?;
+            let async_client =
+                WsTaos::from_wsinfo_with_state(&self.dsn, self.state.clone(), self.limiter.clone()).await?;
             self.async_client
                 .get_or_init(|| async_client)
                 .write_raw_block(block)
@@ -91,6 +283,12 @@ impl taos_query::Queryable for Taos {
         let sql = sql.as_ref();
         block_in_place_or_global(<Self as AsyncQueryable>::query(self, sql))
     }
+
+    fn exec_with_warnings<T: AsRef<str>>(&self, sql: T) -> Result<ExecResult, Self::Error> {
+        let sql = sql.as_ref();
+        block_in_place_or_global(<Self as AsyncQueryable>::exec_with_warnings(self, sql))
+    }
+
     fn write_raw_meta(&self, meta: &RawMeta) -> Result<(), Self::Error> {
         block_in_place_or_global(<Self as AsyncQueryable>::write_raw_meta(self, meta))
     }
@@ -220,6 +418,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn ws_quote_ident() -> anyhow::Result<()> {
+        use taos_query::util::{quote_ident, quote_table_ref};
+        use taos_query::{Fetchable, Queryable};
+
+        let client = TaosBuilder::from_dsn("ws://localhost:6041/")?.build()?;
+        let db = "Ws-Quote.Ident";
+        client.exec(format!("drop database if exists {}", quote_ident(db)))?;
+        client.exec(format!("create database {}", quote_ident(db)))?;
+        client.exec(format!("use {}", quote_ident(db)))?;
+        client.exec("create stable `St.1` (`Ts` timestamp, `V-1` int) tags (`T.1` int)")?;
+
+        // Names of device ids, mixed with dots, dashes, spaces and cases.
+        let fragments = ["d", "D", ".", "-", " ", "1001", "数据", ":"];
+        let names = fragments
+            .iter()
+            .flat_map(|a| fragments.iter().map(move |b| format!("{a}{b}x")));
+        for (i, name) in names.enumerate() {
+            let table = quote_table_ref(db, &name);
+            client.exec(format!(
+                "insert into {table} using {} tags({i}) values(now, {i})",
+                quote_table_ref(db, "St.1"),
+            ))?;
+            let (tbname, v): (String, i32) = client
+                .query_one(format!("select tbname, `V-1` from {table}"))?
+                .unwrap();
+            assert_eq!((tbname.as_str(), v), (name.as_str(), i as i32));
+            assert!(client.describe(&name)?.names().any(|c| c == "V-1"));
+        }
+
+        client.exec(format!("drop database {}", quote_ident(db)))?;
+        Ok(())
+    }
+
     #[test]
     fn ws_sync() -> anyhow::Result<()> {
         use taos_query::{Fetchable, Queryable};
@@ -394,4 +626,1238 @@ mod tests {
         assert_eq!(client.exec("drop database ws_test_client").await?, 0);
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn state_listener_reconnect() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::ConnState;
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Mock server: handshake and login, the first connection is closed after login.
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let mut n = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                n += 1;
+                let drop_after_login = n == 1;
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let reply = if text.contains("\"version\"") {
+                            r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#
+                        } else {
+                            r#"{"code":0,"message":"","action":"conn","req_id":0}"#
+                        };
+                        ws.send(Message::Text(reply.to_string())).await.unwrap();
+                        if drop_after_login && reply.contains("conn") {
+                            let _ = ws.close(None).await;
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let cloned = events.clone();
+        let dsn = format!("ws://{addr}?reconnect=true&reconnect.interval=10ms");
+        let taos = TaosBuilder::from_dsn(dsn)?
+            .on_state_change(move |state| cloned.lock().unwrap().push(state))
+            .build()?;
+        taos.client().await;
+        for _ in 0..50 {
+            if events.lock().unwrap().len() >= 4 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(taos.state().is_connected());
+        drop(taos);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let connected = ConnState::Connected {
+            server_version: "3.0.0.0".to_string(),
+        };
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ConnState::Connecting,
+                connected.clone(),
+                ConnState::Reconnecting { attempt: 1 },
+                connected,
+                ConnState::Closed {
+                    reason: "closed by client".to_string()
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    /// Lost connections are re-dialed only when reconnect is opted in.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reconnect_is_opt_in() -> anyhow::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use futures::{SinkExt, StreamExt};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Mock server closing the first connection after login, returns the number of dials.
+        async fn mock_dropping() -> anyhow::Result<(std::net::SocketAddr, Arc<AtomicUsize>)> {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+            let dials = Arc::new(AtomicUsize::new(0));
+            let counter = dials.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let drop_after_login = counter.fetch_add(1, Ordering::SeqCst) == 0;
+                    tokio::spawn(async move {
+                        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                        while let Some(Ok(Message::Text(text))) = ws.next().await {
+                            let reply = if text.contains("\"version\"") {
+                                r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#
+                            } else {
+                                r#"{"code":0,"message":"","action":"conn","req_id":0}"#
+                            };
+                            ws.send(Message::Text(reply.to_string())).await.unwrap();
+                            if drop_after_login && reply.contains("conn") {
+                                let _ = ws.close(None).await;
+                                return;
+                            }
+                        }
+                    });
+                }
+            });
+            Ok((addr, dials))
+        }
+
+        for (params, redialed) in [
+            ("", false),
+            ("?reconnect=false", false),
+            ("?reconnect.max_retries=3&reconnect.interval=10ms", false),
+            ("?reconnect=true&reconnect.interval=10ms", true),
+        ] {
+            let (addr, dials) = mock_dropping().await?;
+            let taos = TaosBuilder::from_dsn(format!("ws://{addr}{params}"))?.build()?;
+            taos.client().await;
+            for _ in 0..20 {
+                if !taos.state().is_connected() || dials.load(Ordering::SeqCst) > 1 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert_eq!(dials.load(Ordering::SeqCst) > 1, redialed, "{params}");
+            assert_eq!(taos.state().is_connected(), redialed, "{params}");
+        }
+        Ok(())
+    }
+
+    /// Mock server replying to each query after `delay`, returns the address and the max number
+    /// of outstanding requests it has seen over all connections.
+    async fn mock_slow_server(
+        delay: std::time::Duration,
+    ) -> anyhow::Result<(
+        std::net::SocketAddr,
+        std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    )> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use futures::{SinkExt, StreamExt};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let max_outstanding = Arc::new(AtomicUsize::new(0));
+        let max = max_outstanding.clone();
+        let outstanding = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (max, outstanding) = (max.clone(), outstanding.clone());
+                tokio::spawn(async move {
+                    let ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    let (mut sink, mut stream) = ws.split();
+                    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+                    tokio::spawn(async move {
+                        while let Some(reply) = rx.recv().await {
+                            if sink.send(Message::Text(reply)).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                    while let Some(Ok(Message::Text(text))) = stream.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        match req["action"].as_str().unwrap() {
+                            "version" => tx.send(r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string()).unwrap(),
+                            "conn" => tx.send(r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string()).unwrap(),
+                            _ => {
+                                let n = outstanding.fetch_add(1, Ordering::SeqCst) + 1;
+                                max.fetch_max(n, Ordering::SeqCst);
+                                let (tx, outstanding) = (tx.clone(), outstanding.clone());
+                                tokio::spawn(async move {
+                                    tokio::time::sleep(delay).await;
+                                    outstanding.fetch_sub(1, Ordering::SeqCst);
+                                    let _ = tx.send(format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":{req_id},"is_update":true,"affected_rows":1,"fields_count":0,"precision":0,"timing":0}}"#));
+                                });
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        Ok((addr, max_outstanding))
+    }
+
+    #[test]
+    fn drop_on_runtime_shutdown() -> anyhow::Result<()> {
+        use std::sync::atomic::Ordering;
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        use futures::TryStreamExt;
+        use taos_query::{AsyncFetchable, AsyncQueryable, ConnState};
+
+        const WORKER: &str = "ws-query-shutdown";
+        let panics = crate::count_panics(WORKER);
+        let server = tokio::runtime::Runtime::new()?;
+        let (addr, _) = server.block_on(mock_result_server([8192, 8192], usize::MAX, 0))?;
+        let builder = TaosBuilder::from_dsn(format!("ws://{addr}"))?;
+
+        for i in 0..1000 {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name(WORKER)
+                .enable_all()
+                .build()?;
+            let held = runtime.block_on(async {
+                let taos = Arc::new(builder.build()?);
+                let rs = taos.query("select * from t").await?;
+                // a fetch in flight when the runtime shuts down.
+                let cloned = taos.clone();
+                tokio::spawn(async move {
+                    let mut rs = cloned.query("select * from t").await?;
+                    rs.blocks().try_for_each(|_| async { Ok(()) }).await
+                });
+                anyhow::Ok((taos, rs))
+            })?;
+            // dropped by tasks torn down with the runtime, or out of any runtime after it.
+            let held = if i % 2 == 0 {
+                runtime.spawn(async move {
+                    let _held = held;
+                    std::future::pending::<()>().await
+                });
+                None
+            } else {
+                Some(held)
+            };
+            let shutdown = Instant::now();
+            runtime.shutdown_timeout(Duration::from_secs(1));
+            assert!(shutdown.elapsed() < Duration::from_millis(500), "{i}");
+            drop(held);
+        }
+        assert_eq!(panics.load(Ordering::SeqCst), 0);
+
+        server.block_on(async {
+            let taos = builder.build()?;
+            taos.client().await;
+            let closing = tokio::time::timeout(Duration::from_secs(3), taos.close_gracefully());
+            assert!(closing.await.is_ok());
+            assert!(matches!(taos.state(), ConnState::Closed { .. }));
+            anyhow::Ok(())
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn max_concurrent_queries() -> anyhow::Result<()> {
+        use std::sync::atomic::Ordering;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use taos_query::AsyncQueryable;
+
+        use super::QueueStats;
+
+        let (addr, max_outstanding) = mock_slow_server(Duration::from_millis(20)).await?;
+        let taos = Arc::new(
+            TaosBuilder::from_dsn(format!("ws://{addr}?maxConcurrentQueries=4"))?.build()?,
+        );
+        taos.client().await;
+
+        let tasks: Vec<_> = (0..64)
+            .map(|_| {
+                let taos = taos.clone();
+                tokio::spawn(async move { taos.exec("insert into t1 values(now, 1)").await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let stats = taos.queue_stats();
+        assert!(stats.in_flight <= 4);
+        assert!(stats.queued > 0);
+        for task in tasks {
+            assert_eq!(task.await??, 1);
+        }
+        let max_outstanding = max_outstanding.load(Ordering::SeqCst);
+        assert!((1..=4).contains(&max_outstanding), "{max_outstanding}");
+        assert_eq!(taos.queue_stats(), QueueStats::default());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn max_concurrent_queries_with_schemaless() -> anyhow::Result<()> {
+        use std::sync::atomic::Ordering;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use taos_query::common::{SchemalessProtocol, SmlData};
+        use taos_query::AsyncQueryable;
+
+        // Schemaless writes are sent on another connection, but share the slots of queries.
+        let (addr, max_outstanding) = mock_slow_server(Duration::from_millis(20)).await?;
+        let taos = Arc::new(
+            TaosBuilder::from_dsn(format!("ws://{addr}?maxConcurrentQueries=1"))?.build()?,
+        );
+        let data = SmlData::builder(SchemalessProtocol::Line)
+            .line("st,t1=1 c1=1i32")
+            .build()?;
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                let (taos, data) = (taos.clone(), data.clone());
+                tokio::spawn(async move {
+                    if i % 2 == 0 {
+                        taos.put(&data).await
+                    } else {
+                        taos.exec("insert into t1 values(now, 1)").await.map(|_| ())
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await??;
+        }
+        assert_eq!(max_outstanding.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn queue_timeout() -> anyhow::Result<()> {
+        use std::time::Duration;
+
+        use taos_query::AsyncQueryable;
+
+        use super::asyn::{Error, WS_ERROR_NO};
+
+        let (addr, _) = mock_slow_server(Duration::from_millis(200)).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?
+            .with_max_concurrent_queries(1)
+            .with_queue_timeout(Duration::from_millis(20))
+            .build()?;
+        taos.client().await;
+
+        let (first, second) = tokio::join!(taos.exec("select 1"), async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            taos.exec("select 2").await
+        });
+        assert_eq!(first?, 1);
+        let err = second.unwrap_err();
+        assert!(matches!(err, Error::QueueTimeout(_)));
+        assert_eq!(err.errno(), WS_ERROR_NO::QUEUE_TIMEOUT.as_code());
+
+        assert!(TaosBuilder::from_dsn("ws://localhost:6041?maxConcurrentQueries=0").is_err());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn queue_timeout_params() -> anyhow::Result<()> {
+        use std::time::Duration;
+
+        use taos_query::AsyncQueryable;
+
+        use super::asyn::Error;
+
+        let (addr, _) = mock_slow_server(Duration::from_millis(200)).await?;
+        // queueTimeout value, and whether the second query times out in the queue.
+        let cases = [
+            ("20", true),
+            ("20ms", true),
+            ("0", true),
+            ("none", true),
+            ("1s", false),
+            ("1m", false),
+            ("never", false),
+        ];
+        for (value, timeout) in cases {
+            let taos = TaosBuilder::from_dsn(format!(
+                "ws://{addr}?maxConcurrentQueries=1&queueTimeout={value}"
+            ))?
+            .build()?;
+            taos.client().await;
+
+            let (first, second) = tokio::join!(taos.exec("select 1"), async {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                taos.exec("select 2").await
+            });
+            assert_eq!(first?, 1, "queueTimeout={value}");
+            match second {
+                Err(Error::QueueTimeout(_)) => assert!(timeout, "queueTimeout={value}"),
+                second => assert_eq!(second?, 1, "queueTimeout={value}"),
+            }
+        }
+
+        for value in ["", "5x", "-1", "1.5s"] {
+            let err = TaosBuilder::from_dsn(format!("ws://localhost:6041?queueTimeout={value}"))
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("queueTimeout"), "{err}");
+            assert!(err.contains("integer milliseconds"), "{err}");
+        }
+        Ok(())
+    }
+
+    /// Mock server serving a 100k-row result of `select * from t`, in blocks of `block_rows`
+    /// for each connection. The first connection is killed when fetching the block after
+    /// `kill_after` blocks, and `v` of connections after the first one is increased by `shift`.
+    async fn mock_result_server(
+        block_rows: [usize; 2],
+        kill_after: usize,
+        shift: i64,
+    ) -> anyhow::Result<(
+        std::net::SocketAddr,
+        std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    )> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::common::{views::views_to_raw_block, ColumnView};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        const ROWS: usize = 100_000;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let (block_rows, shift) = if n == 0 {
+                    (block_rows[0], 0)
+                } else {
+                    (block_rows[1], shift)
+                };
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    let (mut offset, mut fetched) = (0, 0);
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                            "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                            "query" => format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":1,"fields_count":4,"fields_names":["ts","v","d","s"],"fields_types":[9,5,7,8],"fields_lengths":[8,8,8,16],"precision":0}}"#),
+                            "fetch" if n == 0 && fetched == kill_after => return,
+                            "fetch" => {
+                                let rows = block_rows.min(ROWS - offset);
+                                format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"id":1,"completed":{},"rows":{rows}}}"#, rows == 0)
+                            }
+                            "fetch_block" => {
+                                let range = offset..(offset + block_rows).min(ROWS);
+                                offset = range.end;
+                                fetched += 1;
+                                let views = [
+                                    ColumnView::from_millis_timestamp(range.clone().map(|i| i as i64).collect()),
+                                    ColumnView::from_big_ints(range.clone().map(|i| i as i64 + shift).collect()),
+                                    ColumnView::from_doubles(range.clone().map(|i| i as f64).collect()),
+                                    ColumnView::from_varchar::<String, _, _, _>(
+                                        range.map(|i| format!("v{i:06}")).collect::<Vec<_>>(),
+                                    ),
+                                ];
+                                let mut bytes = Vec::new();
+                                bytes.extend(0u64.to_le_bytes());
+                                bytes.extend(1u64.to_le_bytes());
+                                bytes.extend(views_to_raw_block(&views));
+                                ws.send(Message::Binary(bytes)).await.unwrap();
+                                continue;
+                            }
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+        Ok((addr, connections))
+    }
+
+    /// Blocks of 2.x adapters are parsed in the v2 layout by the server version.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn v2_blocks() -> anyhow::Result<()> {
+        use futures::{SinkExt, StreamExt, TryStreamExt};
+        use taos_query::common::{views::views_to_raw_block, ColumnView, Precision, Value};
+        use taos_query::{AsyncFetchable, AsyncQueryable, RawBlock};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let views = [
+            ColumnView::from_millis_timestamp(vec![0, 1, 2]),
+            ColumnView::from_ints(vec![Some(1), None, Some(3)]),
+            ColumnView::from_varchar::<&str, _, _, _>([Some("a"), Some("bcd"), None]),
+            ColumnView::from_nchar::<&str, _, _, _>([None, Some("涛思"), Some("数据")]),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        block.with_field_names(["ts", "v", "s", "n"]);
+        let (v2, lengths) = block.to_raw_block_v2();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut fetched = false;
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                let reply = match req["action"].as_str().unwrap() {
+                    "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"2.6.0.0"}"#.to_string(),
+                    "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                    "query" => format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":1,"fields_count":4,"fields_names":["ts","v","s","n"],"fields_types":[9,4,8,10],"fields_lengths":[8,4,8,8],"precision":0}}"#),
+                    "fetch" if fetched => format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"id":1,"completed":true,"rows":0}}"#),
+                    "fetch" => format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"id":1,"completed":false,"lengths":{lengths:?},"rows":3}}"#),
+                    "fetch_block" => {
+                        fetched = true;
+                        // v2 frames have no timing before the result id.
+                        let mut bytes = 1u64.to_le_bytes().to_vec();
+                        bytes.extend(&v2);
+                        ws.send(Message::Binary(bytes)).await.unwrap();
+                        continue;
+                    }
+                    _ => continue,
+                };
+                ws.send(Message::Text(reply)).await.unwrap();
+            }
+        });
+
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let mut rs = taos.query("select * from t").await?;
+        let blocks: Vec<RawBlock> = rs.blocks().try_collect().await?;
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].field_names(), ["ts", "v", "s", "n"]);
+        let values = |block: &RawBlock| -> Vec<Vec<Value>> {
+            (0..block.nrows())
+                .map(|row| {
+                    (0..block.ncols())
+                        .map(|col| block.get_ref(row, col).unwrap().to_value())
+                        .collect()
+                })
+                .collect()
+        };
+        assert_eq!(values(&blocks[0]), values(&block));
+        Ok(())
+    }
+
+    /// Rows fetched in JSON are decoded into the same blocks as binary fetches.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn json_fetch() -> anyhow::Result<()> {
+        use futures::{SinkExt, StreamExt, TryStreamExt};
+        use taos_query::common::{views::views_to_raw_block, ColumnView, Precision};
+        use taos_query::{AsyncFetchable, AsyncQueryable, RawBlock};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let views = [
+            ColumnView::from_nanos_timestamp(vec![1664582400123456789, 1664582400123456790]),
+            ColumnView::from_doubles(vec![Some(10.3), None]),
+            ColumnView::from_nchar::<&str, _, _, _>([Some("涛思"), None]),
+        ];
+        let binary = views_to_raw_block(&views);
+        // rows of a response captured in JSON mode of the same query.
+        let rows = r#"[["2022-10-01T08:00:00.123456789+08:00",10.3,"涛思"],["2022-10-01T00:00:00.12345679Z",null,null]]"#;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let binary = binary.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    let mut fetched = false;
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                            "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                            "query" => format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":1,"fields_count":3,"fields_names":["ts","current","location"],"fields_types":[9,7,10],"fields_lengths":[8,8,16],"precision":2}}"#),
+                            "fetch" => format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"id":1,"completed":{fetched},"rows":2}}"#),
+                            "fetch_json" => {
+                                fetched = true;
+                                format!(r#"{{"code":0,"message":"","action":"fetch_json","req_id":{req_id},"id":1,"data":{rows}}}"#)
+                            }
+                            "fetch_block" => {
+                                fetched = true;
+                                let mut bytes = Vec::new();
+                                bytes.extend(0u64.to_le_bytes());
+                                bytes.extend(1u64.to_le_bytes());
+                                bytes.extend(&binary);
+                                ws.send(Message::Binary(bytes)).await.unwrap();
+                                continue;
+                            }
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let mut results = Vec::new();
+        let dsns = [format!("ws://{addr}"), format!("ws://{addr}?fetchJson=true")];
+        for dsn in dsns {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            let mut rs = taos.query("select * from t").await?;
+            assert_eq!(rs.precision(), Precision::Nanosecond);
+            let blocks: Vec<RawBlock> = rs.blocks().try_collect().await?;
+            assert_eq!(blocks.len(), 1);
+            results.push(blocks.into_iter().next().unwrap());
+        }
+        let (binary, json) = (&results[0], &results[1]);
+        assert_eq!(json.field_names(), binary.field_names());
+        assert_eq!(json.precision(), Precision::Nanosecond);
+        assert_eq!(json.to_values(), binary.to_values());
+        assert_eq!(
+            json.get_ref(1, 0).unwrap().to_value(),
+            binary.get_ref(1, 0).unwrap().to_value()
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resumable_query() -> anyhow::Result<()> {
+        use std::sync::atomic::Ordering;
+
+        use futures::TryStreamExt;
+        use taos_query::AsyncFetchable;
+
+        use super::QueryFingerprint;
+
+        // Killed after 5 blocks, resumed in the middle of a block of different size.
+        let (addr, connections) = mock_result_server([8192, 3200], 5, 0).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let mut rs = taos
+            .resumable_query("select * from t", QueryFingerprint::new("t"))
+            .await?;
+        let mut expected = 0;
+        let mut blocks = rs.blocks();
+        while let Some(block) = blocks.try_next().await? {
+            let (_, v) = block.column("v").unwrap();
+            for value in v.iter() {
+                assert_eq!(
+                    value.to_value(),
+                    taos_query::common::Value::BigInt(expected)
+                );
+                expected += 1;
+            }
+        }
+        assert_eq!(expected, 100_000);
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resumable_query_mismatch() -> anyhow::Result<()> {
+        use futures::TryStreamExt;
+        use taos_query::AsyncFetchable;
+
+        use super::asyn::{Error, WS_ERROR_NO};
+        use super::QueryFingerprint;
+
+        let (addr, _) = mock_result_server([8192, 8192], 5, 1).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+
+        let err = taos
+            .resumable_query(
+                "select * from t where ts > now - 1h",
+                QueryFingerprint::new("t"),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("`now`"), "{err}");
+
+        let mut rs = taos
+            .resumable_query("select * from t", QueryFingerprint::new("t@v1"))
+            .await?;
+        let mut blocks = rs.blocks();
+        let mut rows = 0;
+        let err = loop {
+            match blocks.try_next().await {
+                Ok(Some(block)) => rows += block.nrows(),
+                Ok(None) => panic!("changed data should not be resumed"),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(rows, 5 * 8192);
+        assert!(
+            matches!(
+                &err,
+                Error::ResumeMismatch { snapshot, rows } if snapshot == "t@v1" && *rows == 5 * 8192
+            ),
+            "{err:?}"
+        );
+        assert_eq!(err.errno(), WS_ERROR_NO::RESUME_MISMATCH.as_code());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn block_pool() -> anyhow::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use bytes::{Bytes, BytesMut};
+        use futures::TryStreamExt;
+        use taos_query::common::{BlockBufferPool, BlockPool};
+        use taos_query::{AsyncFetchable, AsyncQueryable};
+
+        #[derive(Default)]
+        struct Counting {
+            acquired: AtomicUsize,
+            recycled: AtomicUsize,
+        }
+
+        impl BlockBufferPool for Counting {
+            fn acquire(&self, len: usize) -> BytesMut {
+                self.acquired.fetch_add(1, Ordering::SeqCst);
+                BytesMut::with_capacity(len)
+            }
+
+            fn recycle(&self, _: Bytes) {
+                self.recycled.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let (addr, _) = mock_result_server([8192, 8192], usize::MAX, 0).await?;
+        let counting = Arc::new(Counting::default());
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?
+            .with_block_pool(BlockPool::new(counting.clone()))
+            .build()?;
+        let mut rs = taos.query("select * from t").await?;
+        let mut blocks = rs.blocks();
+        let mut last = None;
+        let mut n = 0;
+        while let Some(block) = blocks.try_next().await? {
+            last = Some(block);
+            n += 1;
+        }
+        assert_eq!(n, 13);
+        assert_eq!(counting.acquired.load(Ordering::SeqCst), n);
+        // all but the last block are dropped.
+        assert_eq!(counting.recycled.load(Ordering::SeqCst), n - 1);
+
+        // blocks outlive the connection.
+        drop(blocks);
+        drop(rs);
+        drop(taos);
+        let last = last.unwrap();
+        assert_eq!(last.nrows(), 100_000 - 12 * 8192);
+        let (_, s) = last.column("s").unwrap();
+        assert_eq!(
+            s.get(0).unwrap().to_value(),
+            taos_query::common::Value::VarChar("v098304".to_string())
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn warnings() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::common::Warning;
+        use taos_query::{AsyncFetchable, AsyncQueryable};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // The adapter reports coerced columns of a schemaless insert as warnings.
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let sql = req["args"]["sql"].as_str().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                            "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                            "query" if sql.starts_with("insert") => format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":0,"is_update":true,"affected_rows":2,"warnings":[{{"code":9750,"message":"column c1 coerced from int to double"}}]}}"#),
+                            "query" => format!(r#"{{"code":9731,"message":"syntax error","action":"query","req_id":{req_id},"warnings":[{{"code":1,"message":"ignored"}}]}}"#),
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let log = logged.clone();
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?
+            .on_warning(move |warning| log.lock().unwrap().push(warning.clone()))
+            .build()?;
+
+        let expected = [Warning::new(0x2616, "column c1 coerced from int to double")];
+        let sql = "insert into meters,t1=a c1=1i32 1626006833639";
+        let res = taos.exec_with_warnings(sql).await?;
+        assert_eq!(res.affected_rows(), 2);
+        assert_eq!(res.warnings(), expected);
+
+        let rs = taos.query(sql).await?;
+        assert_eq!(rs.affected_rows(), 2);
+        assert_eq!(rs.warnings(), expected);
+        assert_eq!(taos.exec(sql).await?, 2);
+
+        // Errors are reported as usual.
+        let err = taos.exec_with_warnings("select").await.unwrap_err();
+        assert_eq!(err.errno(), 0x2603);
+
+        assert_eq!(
+            logged.lock().unwrap().as_slice(),
+            [&expected[..]; 3].concat()
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rate_limited_put() -> anyhow::Result<()> {
+        use std::time::{Duration, Instant};
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::common::{SchemalessProtocol, SmlData};
+        use taos_query::util::RateLimit;
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let action = req["action"].as_str().unwrap();
+                        let reply = format!(
+                            r#"{{"code":0,"message":"","action":"{action}","req_id":{req_id}}}"#
+                        );
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        // The burst covers the first write, the second one waits for the budget.
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?
+            .with_rate_limit(RateLimit::rows_per_sec(4))
+            .build()?;
+        let data = SmlData::builder(SchemalessProtocol::Line)
+            .lines(["st,t1=1 c1=1i32", "st,t1=2 c1=2i32", "st,t1=3 c1=3i32"])
+            .build()?;
+        let start = Instant::now();
+        taos.put(&data).await?;
+        assert!(start.elapsed() < Duration::from_millis(500));
+        taos.put(&data).await?;
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancelled_queries() -> anyhow::Result<()> {
+        use std::collections::HashSet;
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::AsyncQueryable;
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Queries take 5ms, results are tracked until freed.
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let results = Arc::new(Mutex::new(HashSet::new()));
+        let running = results.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let running = running.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                            "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                            "query" => {
+                                tokio::time::sleep(Duration::from_millis(5)).await;
+                                let id = req_id + 10000;
+                                running.lock().unwrap().insert(id);
+                                format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":{id},"is_update":true,"affected_rows":1}}"#)
+                            }
+                            "free_result" => {
+                                let id = req["args"]["id"].as_u64().unwrap_or_default();
+                                running.lock().unwrap().remove(&id);
+                                continue;
+                            }
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        for _ in 0..100 {
+            tokio::select! {
+                _ = taos.query("insert into t1 values(now, 1)") => panic!("query should be slower"),
+                _ = tokio::time::sleep(Duration::from_millis(1)) => (),
+            }
+        }
+        // still works after the cancelled queries.
+        assert_eq!(taos.exec("insert into t1 values(now, 1)").await?, 1);
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        // the result of the last query is alive until the result set is dropped.
+        while results.lock().unwrap().len() > 1 && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(results.lock().unwrap().len(), 1, "results are not freed");
+        Ok(())
+    }
+
+    /// Mock server dropping the first connection on the first query other than `use`, returns
+    /// the address and the database of each login.
+    async fn mock_flaky_server() -> anyhow::Result<(
+        std::net::SocketAddr,
+        std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    )> {
+        use std::sync::{Arc, Mutex};
+
+        use futures::{SinkExt, StreamExt};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let logins = Arc::new(Mutex::new(Vec::new()));
+        let databases = logins.clone();
+        tokio::spawn(async move {
+            let mut n = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                n += 1;
+                let first = n == 1;
+                let databases = databases.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                            "conn" => {
+                                let db = req["args"]["db"].as_str().unwrap_or_default();
+                                databases.lock().unwrap().push(db.to_string());
+                                r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string()
+                            }
+                            "query" if first && !req["args"]["sql"].as_str().unwrap().starts_with("use") => return,
+                            "query" => format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":{req_id},"is_update":true,"affected_rows":1}}"#),
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+        Ok((addr, logins))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reconnect_retry_in_flight() -> anyhow::Result<()> {
+        use taos_query::AsyncQueryable;
+
+        use super::asyn::WS_ERROR_NO;
+
+        let (addr, logins) = mock_flaky_server().await?;
+        let taos = TaosBuilder::from_dsn(format!(
+            "ws://{addr}?reconnect=true&reconnect.interval=10ms"
+        ))?
+        .build()?;
+        taos.exec("use power").await?;
+        // The insert is lost with the first connection and sent again after reconnect.
+        assert_eq!(taos.exec("insert into d0 values(now, 1)").await?, 1);
+        assert_eq!(*logins.lock().unwrap(), ["", "power"]);
+
+        // Returned to the caller by default.
+        let (addr, _) = mock_flaky_server().await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let err = taos
+            .exec("insert into d0 values(now, 1)")
+            .await
+            .unwrap_err();
+        assert_eq!(err.errno(), WS_ERROR_NO::CONN_CLOSED.as_code());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reconnected_mid_fetch() -> anyhow::Result<()> {
+        use futures::TryStreamExt;
+        use taos_query::{AsyncFetchable, AsyncQueryable};
+
+        use super::asyn::{Error, WS_ERROR_NO};
+
+        let (addr, _) = mock_result_server([8192, 8192], 5, 0).await?;
+        let taos = TaosBuilder::from_dsn(format!(
+            "ws://{addr}?reconnect=true&reconnect.interval=10ms"
+        ))?
+        .build()?;
+        let mut rs = taos.query("select * from t").await?;
+        let mut blocks = rs.blocks();
+        let mut rows = 0;
+        let err = loop {
+            match blocks.try_next().await {
+                Ok(Some(block)) => rows += block.nrows(),
+                Ok(None) => panic!("the result is lost with the connection"),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(rows, 5 * 8192);
+        assert!(matches!(err, Error::ReconnectedMidFetch(_)), "{err:?}");
+        assert_eq!(err.errno(), WS_ERROR_NO::RECONNECTED_MID_FETCH.as_code());
+
+        // Query again on the new connection.
+        let mut rs = taos.query("select * from t").await?;
+        let rows: usize = rs
+            .blocks()
+            .map_ok(|block| block.nrows())
+            .try_collect::<Vec<_>>()
+            .await?
+            .into_iter()
+            .sum();
+        assert_eq!(rows, 100_000);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn write_raw_block_in_chunks() -> anyhow::Result<()> {
+        use std::sync::{Arc, Mutex};
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::common::views::views_to_raw_block;
+        use taos_query::common::{ColumnView, Precision, RawBlock};
+        use taos_query::AsyncQueryable;
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Blocks are recorded as (action, rows, message length), writes into table `missing`
+        // fail as the adapter does.
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let received = messages.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let received = received.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(message)) = ws.next().await {
+                        let reply = match message {
+                            Message::Text(text) => {
+                                let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                                match req["action"].as_str().unwrap() {
+                                    "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                                    "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                                    _ => continue,
+                                }
+                            }
+                            Message::Binary(bytes) => {
+                                let u64_at = |at: usize| {
+                                    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+                                };
+                                let (req_id, action) = (u64_at(0), u64_at(16));
+                                let rows = u32::from_le_bytes(bytes[24..28].try_into().unwrap());
+                                let len =
+                                    u16::from_le_bytes(bytes[28..30].try_into().unwrap()) as usize;
+                                let table = std::str::from_utf8(&bytes[30..30 + len]).unwrap();
+                                received
+                                    .lock()
+                                    .unwrap()
+                                    .push((action, rows as usize, bytes.len()));
+                                let code = if table == "missing" { 0x2662 } else { 0 };
+                                format!(
+                                    r#"{{"code":{code},"message":"","action":"write_raw_block","req_id":{req_id}}}"#
+                                )
+                            }
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}?maxWriteBlockSize=4096"))?.build()?;
+        let block = |table: Option<&str>, names: bool| {
+            let views = [
+                ColumnView::from_millis_timestamp((0..1000).collect()),
+                ColumnView::from_varchar::<String, _, _, _>(
+                    (0..1000)
+                        .map(|i| Some("v".repeat(i % 20)))
+                        .collect::<Vec<_>>(),
+                ),
+            ];
+            let mut block =
+                RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+            if let Some(table) = table {
+                block.with_table_name(table);
+            }
+            if names {
+                block.with_field_names(["ts", "v"]);
+            }
+            block
+        };
+
+        taos.write_raw_block(&block(Some("tb"), true)).await?;
+        let written = std::mem::take(&mut *messages.lock().unwrap());
+        assert!(written.len() > 1, "{written:?}");
+        assert!(written
+            .iter()
+            .all(|(action, _, len)| *action == 5 && *len <= 4096));
+        assert_eq!(written.iter().map(|(_, rows, _)| rows).sum::<usize>(), 1000);
+
+        // Fields are not sent without field names.
+        taos.write_raw_block(&block(Some("tb"), false)).await?;
+        let written = std::mem::take(&mut *messages.lock().unwrap());
+        assert!(written.iter().all(|(action, _, _)| *action == 4));
+        assert_eq!(written.iter().map(|(_, rows, _)| rows).sum::<usize>(), 1000);
+
+        let err = taos.write_raw_block(&block(None, true)).await.unwrap_err();
+        assert!(err.to_string().contains("without table name"), "{err}");
+        assert!(messages.lock().unwrap().is_empty());
+
+        // Server errors keep their codes, chunks after the failed one are not written.
+        let err = taos
+            .write_raw_block(&block(Some("missing"), true))
+            .await
+            .unwrap_err();
+        assert_eq!(err.errno(), 0x2662);
+        assert_eq!(messages.lock().unwrap().len(), 1);
+        Ok(())
+    }
+
+    /// Stale responses are dropped and counted, a response of another result fails the fetch.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stale_frames() -> anyhow::Result<()> {
+        use futures::{SinkExt, StreamExt, TryStreamExt};
+        use taos_query::common::{views::views_to_raw_block, ColumnView, Value};
+        use taos_query::{AsyncFetchable, AsyncQueryable, RawBlock};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        use super::asyn::{Error, WS_ERROR_NO};
+        use super::WsMetrics;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let (mut results, mut fetched) = (0, false);
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                let id = req["args"]["id"].as_u64().unwrap_or_default();
+                let reply = match req["action"].as_str().unwrap() {
+                    "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                    "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                    "query" => {
+                        results += 1;
+                        fetched = false;
+                        format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":{results},"fields_count":1,"fields_names":["v"],"fields_types":[4],"fields_lengths":[4],"precision":0}}"#)
+                    }
+                    // a response of a result of another query.
+                    "fetch" if req["args"]["id"] == 2 => format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"id":1,"completed":false,"rows":3}}"#),
+                    "fetch" => {
+                        // a late response of a request timed out before.
+                        let stale = format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{},"id":{id},"completed":false,"rows":3}}"#, req_id + 1000);
+                        ws.send(Message::Text(stale)).await.unwrap();
+                        format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"id":{id},"completed":{fetched},"rows":3}}"#)
+                    }
+                    "fetch_block" => {
+                        fetched = true;
+                        for (res_id, v) in [(99u64, 0), (id, id as i32)] {
+                            let mut bytes = Vec::new();
+                            bytes.extend(0u64.to_le_bytes());
+                            bytes.extend(res_id.to_le_bytes());
+                            bytes.extend(views_to_raw_block(&[ColumnView::from_ints(vec![v; 3])]));
+                            ws.send(Message::Binary(bytes)).await.unwrap();
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+                ws.send(Message::Text(reply)).await.unwrap();
+            }
+        });
+
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let values = |blocks: Vec<RawBlock>| -> Vec<Value> {
+            blocks
+                .iter()
+                .flat_map(|block| block.to_values())
+                .flatten()
+                .collect()
+        };
+        let mut rs = taos.query("select * from t1").await?;
+        let blocks: Vec<RawBlock> = rs.blocks().try_collect().await?;
+        assert_eq!(values(blocks), vec![Value::Int(1); 3]);
+        // stale responses of two fetches and the block of an unknown result.
+        let metrics = taos.metrics();
+        assert_eq!(
+            metrics,
+            WsMetrics {
+                dropped_stale_frames: 3
+            }
+        );
+
+        let mut rs = taos.query("select * from t2").await?;
+        let mut blocks = rs.blocks();
+        let err = blocks.try_next().await.unwrap_err();
+        assert!(matches!(err, Error::ProtocolDesync(_)), "{err:?}");
+        assert_eq!(err.errno(), WS_ERROR_NO::PROTOCOL_DESYNC.as_code());
+        let err = blocks.try_next().await.unwrap_err();
+        assert!(matches!(err, Error::ProtocolDesync(_)), "{err:?}");
+        drop(blocks);
+        drop(rs);
+
+        // other results of the connection are not affected.
+        let mut rs = taos.query("select * from t3").await?;
+        let blocks: Vec<RawBlock> = rs.blocks().try_collect().await?;
+        assert_eq!(values(blocks), vec![Value::Int(3); 3]);
+        assert_eq!(taos.metrics().dropped_stale_frames, 6);
+        Ok(())
+    }
 }
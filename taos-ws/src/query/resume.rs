@@ -0,0 +1,273 @@
+use taos_query::common::{views::views_to_raw_block, RawBlock};
+
+/// Declares a query deterministic, so its result could be resumed after reconnect, see
+/// [Taos::resumable_query](super::Taos::resumable_query).
+///
+/// The connector could not tell if the data has changed, it's the caller's promise that the
+/// query reads a fixed snapshot of data, eg. with an explicit time range of written data.
+/// Results are verified by a rolling hash of delivered rows after resuming anyway.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryFingerprint {
+    snapshot: String,
+    max_resumes: u32,
+}
+
+impl QueryFingerprint {
+    /// Name the data snapshot read by the query, eg. `meters@2023-01-01/2023-02-01`, it's
+    /// used in logs and [Error::ResumeMismatch](super::Error::ResumeMismatch).
+    pub fn new(snapshot: impl Into<String>) -> Self {
+        Self {
+            snapshot: snapshot.into(),
+            max_resumes: 3,
+        }
+    }
+
+    /// Max times to resume the result, 3 by default.
+    pub fn with_max_resumes(mut self, max: u32) -> Self {
+        self.max_resumes = max;
+        self
+    }
+
+    pub fn snapshot(&self) -> &str {
+        &self.snapshot
+    }
+
+    pub fn max_resumes(&self) -> u32 {
+        self.max_resumes
+    }
+}
+
+/// Functions that make results differ between executions.
+const NON_DETERMINISTIC: [&str; 3] = ["now", "today", "rand"];
+
+/// Find a non-deterministic function in `sql`, quoted strings and identifiers are skipped.
+pub(crate) fn non_deterministic_word(sql: &str) -> Option<&str> {
+    let bytes = sql.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            b if b.is_ascii_alphanumeric() || b == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                let word = &sql[start..i];
+                if NON_DETERMINISTIC
+                    .iter()
+                    .any(|f| f.eq_ignore_ascii_case(word))
+                {
+                    return Some(word);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// FNV-1a hash over all delivered rows, it does not depend on how rows are split into blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RollingHash(u64);
+
+impl Default for RollingHash {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl RollingHash {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Hash rows in `rows` of the block, type and nullness of each value are included.
+    pub(crate) fn write_rows(&mut self, block: &RawBlock, rows: std::ops::Range<usize>) {
+        for row in rows {
+            for col in 0..block.ncols() {
+                let (ty, len, ptr) = unsafe { block.get_raw_value_unchecked(row, col) };
+                if ptr.is_null() {
+                    self.write(&[ty as u8, 0]);
+                } else {
+                    self.write(&[ty as u8, 1]);
+                    self.write(&len.to_le_bytes());
+                    self.write(unsafe { std::slice::from_raw_parts(ptr as *const u8, len as _) });
+                }
+            }
+        }
+    }
+}
+
+/// Position of a resumable result, updated with each delivered block.
+#[derive(Debug)]
+pub(crate) struct ResumeState {
+    pub(crate) db: Option<String>,
+    pub(crate) sql: String,
+    pub(crate) fingerprint: QueryFingerprint,
+    pub(crate) resumes: u32,
+    /// Connection epoch the result is executed in.
+    pub(crate) epoch: u64,
+    delivered: usize,
+    hash: RollingHash,
+}
+
+impl ResumeState {
+    pub(crate) fn new(
+        db: Option<&str>,
+        sql: &str,
+        fingerprint: QueryFingerprint,
+        epoch: u64,
+    ) -> Self {
+        Self {
+            db: db.map(ToString::to_string),
+            sql: sql.to_string(),
+            fingerprint,
+            resumes: 0,
+            epoch,
+            delivered: 0,
+            hash: RollingHash::default(),
+        }
+    }
+
+    /// Rows delivered to the caller.
+    pub(crate) fn delivered(&self) -> usize {
+        self.delivered
+    }
+
+    pub(crate) fn record(&mut self, block: &RawBlock) {
+        self.hash.write_rows(block, 0..block.nrows());
+        self.delivered += block.nrows();
+    }
+
+    pub(crate) fn fast_forward(&self) -> FastForward {
+        FastForward {
+            remaining: self.delivered,
+            hash: RollingHash::default(),
+            expected: self.hash,
+        }
+    }
+}
+
+/// Discards rows of a re-executed result until the previous position.
+#[derive(Debug)]
+pub(crate) struct FastForward {
+    remaining: usize,
+    hash: RollingHash,
+    expected: RollingHash,
+}
+
+impl FastForward {
+    /// Skip delivered rows in `block`, returns rows after the previous position if any.
+    ///
+    /// Returns `Err` if the skipped rows differ from the delivered ones.
+    pub(crate) fn skip(&mut self, block: RawBlock) -> Result<Option<RawBlock>, ()> {
+        if self.remaining == 0 {
+            return Ok(Some(block));
+        }
+        let skip = self.remaining.min(block.nrows());
+        self.hash.write_rows(&block, 0..skip);
+        self.remaining -= skip;
+        if self.remaining > 0 {
+            return Ok(None);
+        }
+        if self.hash != self.expected {
+            return Err(());
+        }
+        if skip == block.nrows() {
+            return Ok(None);
+        }
+        let views: Vec<_> = block
+            .column_views()
+            .iter()
+            .map(|view| view.slice(skip..block.nrows()).unwrap())
+            .collect();
+        let mut rest =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), block.precision());
+        rest.with_field_names(block.field_names());
+        Ok(Some(rest))
+    }
+
+    /// The re-executed result is completed, `Err` if it ends before the previous position.
+    pub(crate) fn finish(&self) -> Result<(), ()> {
+        if self.remaining == 0 && self.hash == self.expected {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use taos_query::common::{ColumnView, Precision};
+
+    use super::*;
+
+    fn block(range: std::ops::Range<i64>) -> RawBlock {
+        let views = [
+            ColumnView::from_millis_timestamp(range.clone().collect()),
+            ColumnView::from_big_ints(range.clone().collect()),
+            ColumnView::from_doubles(range.clone().map(|i| i as f64).collect()),
+            ColumnView::from_varchar::<String, _, _, _>(
+                range.map(|i| format!("v{i:02}")).collect::<Vec<_>>(),
+            ),
+        ];
+        let mut block =
+            RawBlock::parse_from_raw_block(views_to_raw_block(&views), Precision::Millisecond);
+        block.with_field_names(["ts", "v", "d", "s"]);
+        block
+    }
+
+    #[test]
+    fn non_deterministic() {
+        let word = non_deterministic_word;
+        assert_eq!(word("select * from t where ts > NOW - 1h"), Some("NOW"));
+        assert_eq!(word("select rand() from t"), Some("rand"));
+        assert_eq!(word("select * from t where ts > today()"), Some("today"));
+        assert_eq!(word("select known, `now` from t where s = 'now'"), None);
+        assert_eq!(word("select * from t where s = 'it\\'s now'"), None);
+        assert_eq!(
+            word("select * from t where ts >= '2023-01-01' and ts < '2023-02-01'"),
+            None
+        );
+    }
+
+    #[test]
+    fn fast_forward_across_blocks() {
+        let mut state = ResumeState::new(None, "select * from t", QueryFingerprint::new("t"), 0);
+        state.record(&block(0..32));
+        state.record(&block(32..64));
+        assert_eq!(state.delivered(), 64);
+
+        // Split differently after re-execution.
+        let mut ff = state.fast_forward();
+        assert!(matches!(ff.skip(block(0..40)), Ok(None)));
+        let rest = ff.skip(block(40..80)).unwrap().unwrap();
+        assert_eq!(rest.nrows(), 16);
+        assert_eq!(rest.field_names(), ["ts", "v", "d", "s"]);
+        assert_eq!(rest.to_values(), block(64..80).to_values());
+        assert_eq!(ff.skip(block(80..96)).unwrap().unwrap().nrows(), 16);
+        assert_eq!(ff.finish(), Ok(()));
+
+        // Ends exactly at the position.
+        let mut ff = state.fast_forward();
+        assert!(matches!(ff.skip(block(0..64)), Ok(None)));
+        assert_eq!(ff.finish(), Ok(()));
+
+        // Data changed or the result is shorter.
+        let mut ff = state.fast_forward();
+        assert!(ff.skip(block(1..65)).is_err());
+        let mut ff = state.fast_forward();
+        assert!(matches!(ff.skip(block(0..32)), Ok(None)));
+        assert_eq!(ff.finish(), Err(()));
+    }
+}
@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use taos_query::tmq::Timeout;
+use taos_query::{Dsn, DsnError};
+
+/// How a lost websocket connection is re-established, see [TaosBuilder::with_reconnect].
+///
+/// Reconnect is opt-in, by default a lost connection is closed and requests failed by it are
+/// returned to the caller. With an enabled policy, eg. by
+/// `reconnect=true&reconnect.max_retries=5&reconnect.interval=2s` in DSN:
+///
+/// - query connections are re-dialed and logged in with the current database set by `use db`;
+/// - the query or write in flight is retried once on the new connection;
+/// - results being fetched fail with [Error::ReconnectedMidFetch](crate::query::Error::ReconnectedMidFetch)
+///   so the caller could re-run the query;
+/// - TMQ consumers are re-dialed too, and subscribe to their topics again to continue from
+///   offsets committed by the group.
+///
+/// Attempts wait `interval * 2^(attempt - 1)` at most `max_interval` before dialing, each attempt
+/// is notified to [TaosBuilder::on_state_change] as [ConnState::Reconnecting](taos_query::ConnState::Reconnecting).
+///
+/// [TaosBuilder::with_reconnect]: crate::TaosBuilder::with_reconnect
+/// [TaosBuilder::on_state_change]: crate::TaosBuilder::on_state_change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    enabled: bool,
+    max_retries: u32,
+    interval: Duration,
+    max_interval: Duration,
+}
+
+/// Disabled, the same as [ReconnectPolicy::disabled].
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_retries: 0,
+            interval: Duration::from_millis(100),
+            max_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Enabled policy of 5 retries from 500ms, same as `reconnect=true` in DSN.
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            max_retries: 5,
+            interval: Duration::from_millis(500),
+            ..Default::default()
+        }
+    }
+
+    /// Never reconnect, same as `reconnect=false` in DSN.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Max attempts to re-establish a lost connection, same as `reconnect.max_retries` in DSN.
+    pub fn with_max_retries(mut self, max: u32) -> Self {
+        self.max_retries = max;
+        self
+    }
+
+    /// Wait before the first attempt, doubled for each of the following ones. Same as
+    /// `reconnect.interval` in DSN, eg. `2s` or `500ms`.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Cap of the wait before each attempt, 30s by default.
+    pub fn with_max_interval(mut self, max: Duration) -> Self {
+        self.max_interval = max;
+        self
+    }
+
+    /// Requests and subscriptions are re-established after reconnect.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Wait before `attempt`, which starts from 1.
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use taos_ws::ReconnectPolicy;
+    /// let policy = ReconnectPolicy::enabled()
+    ///     .with_interval(Duration::from_secs(2))
+    ///     .with_max_interval(Duration::from_secs(10));
+    /// assert_eq!(policy.backoff(1), Duration::from_secs(2));
+    /// assert_eq!(policy.backoff(3), Duration::from_secs(8));
+    /// assert_eq!(policy.backoff(4), Duration::from_secs(10));
+    /// ```
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.interval
+            .checked_mul(factor)
+            .map_or(self.max_interval, |wait| wait.min(self.max_interval))
+    }
+
+    /// Take `reconnect`, `reconnect.max_retries` and `reconnect.interval` from `dsn`.
+    pub(crate) fn from_dsn(dsn: &mut Dsn) -> Result<Self, DsnError> {
+        let mut policy = match dsn.remove("reconnect") {
+            Some(value) => match value.to_lowercase().as_str() {
+                "true" | "1" => Self::enabled(),
+                "false" | "0" => Self::disabled(),
+                _ => Err(DsnError::InvalidParam("reconnect".to_string(), value))?,
+            },
+            None => Self::default(),
+        };
+        if let Some(value) = dsn.remove("reconnect.max_retries") {
+            policy.max_retries = value
+                .parse()
+                .map_err(|_| DsnError::InvalidParam("reconnect.max_retries".to_string(), value))?;
+        }
+        if let Some(value) = dsn.remove("reconnect.interval") {
+            policy.interval = Timeout::from_param("reconnect.interval", &value)?.as_duration();
+        }
+        Ok(policy)
+    }
+}
+
+/// Database of a `use db` statement, quotes of the name are removed.
+pub(crate) fn use_database(sql: &str) -> Option<&str> {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let (keyword, db) = sql.split_at(sql.find(char::is_whitespace)?);
+    if !keyword.eq_ignore_ascii_case("use") {
+        return None;
+    }
+    let db = db.trim_start();
+    let db = db
+        .strip_prefix('`')
+        .and_then(|db| db.strip_suffix('`'))
+        .unwrap_or(db);
+    if db.is_empty() || db.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_from_dsn() {
+        let policy = |dsn: &str| ReconnectPolicy::from_dsn(&mut dsn.parse().unwrap());
+        assert_eq!(
+            policy("ws://localhost:6041").unwrap(),
+            ReconnectPolicy::disabled()
+        );
+        assert_eq!(ReconnectPolicy::default().max_retries(), 0);
+        assert_eq!(
+            policy(
+                "ws://localhost:6041?reconnect=true&reconnect.max_retries=7&reconnect.interval=2s"
+            )
+            .unwrap(),
+            ReconnectPolicy::enabled()
+                .with_max_retries(7)
+                .with_interval(Duration::from_secs(2))
+        );
+        assert_eq!(
+            policy("ws://localhost:6041?reconnect=false")
+                .unwrap()
+                .max_retries(),
+            0
+        );
+        for dsn in [
+            "ws://localhost:6041?reconnect=yes",
+            "ws://localhost:6041?reconnect.max_retries=-1",
+            "ws://localhost:6041?reconnect.interval=2h",
+        ] {
+            let err = policy(dsn).unwrap_err().to_string();
+            assert!(err.contains("reconnect"), "{err}");
+        }
+
+        let policy = ReconnectPolicy::default();
+        let waits: Vec<_> = (1..=3).map(|attempt| policy.backoff(attempt)).collect();
+        assert_eq!(waits, [100, 200, 400].map(Duration::from_millis));
+        assert_eq!(policy.backoff(100), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn use_database_of_sql() {
+        assert_eq!(use_database("use power"), Some("power"));
+        assert_eq!(use_database(" USE `power`; "), Some("power"));
+        assert_eq!(use_database("use\tpower"), Some("power"));
+        assert_eq!(use_database("user power"), None);
+        assert_eq!(use_database("use"), None);
+        assert_eq!(use_database("select * from power.meters"), None);
+    }
+}
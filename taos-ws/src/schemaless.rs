@@ -0,0 +1,183 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, NoneAsEmptyString};
+
+use taos_query::common::SmlData;
+use taos_query::prelude::tokio;
+use taos_query::prelude::RawError;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+use crate::query::asyn::Error;
+use crate::query::infra::{ToMessage, WsConnReq};
+use crate::query::QueryLimiter;
+use crate::{TaosBuilder, WsStream};
+
+type ReqId = u64;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "action", content = "args")]
+#[serde(rename_all = "snake_case")]
+enum SmlSend {
+    Conn {
+        req_id: ReqId,
+        #[serde(flatten)]
+        req: WsConnReq,
+    },
+    Insert {
+        req_id: ReqId,
+        protocol: i32,
+        precision: &'static str,
+        data: String,
+        ttl: i32,
+    },
+}
+
+impl SmlSend {
+    fn insert(req_id: ReqId, data: &SmlData) -> Self {
+        SmlSend::Insert {
+            req_id,
+            protocol: data.protocol() as _,
+            precision: data.precision().as_str(),
+            data: data.payload(),
+            ttl: data.ttl().unwrap_or(0),
+        }
+    }
+}
+
+impl ToMessage for SmlSend {}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+struct SmlRecv {
+    code: i32,
+    #[serde_as(as = "NoneAsEmptyString")]
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    req_id: ReqId,
+}
+
+impl SmlRecv {
+    fn ok(self) -> Result<(), Error> {
+        if self.code == 0 {
+            Ok(())
+        } else {
+            let message = self.message.unwrap_or_default();
+            Err(RawError::new(self.code, message).into())
+        }
+    }
+}
+
+/// A connection to the schemaless endpoint, requests are sent one by one.
+pub(crate) struct WsSchemaless {
+    ws: tokio::sync::Mutex<WsStream>,
+    req_id: AtomicU64,
+    /// Shared with the query connection, see [TaosBuilder::with_max_concurrent_queries].
+    limiter: Arc<QueryLimiter>,
+}
+
+impl Debug for WsSchemaless {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WsSchemaless")
+            .field("req_id", &self.req_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WsSchemaless {
+    pub(crate) async fn connect(
+        info: &TaosBuilder,
+        limiter: Arc<QueryLimiter>,
+    ) -> Result<Self, Error> {
+        let ws = info.connect_ws(&info.to_schemaless_url(), None).await?;
+        let sml = Self {
+            ws: tokio::sync::Mutex::new(ws),
+            req_id: AtomicU64::new(0),
+            limiter,
+        };
+        let req_id = sml.req_id.fetch_add(1, Ordering::SeqCst);
+        sml.request(
+            req_id,
+            SmlSend::Conn {
+                req_id,
+                req: info.to_conn_request(),
+            },
+        )
+        .await?;
+        Ok(sml)
+    }
+
+    pub(crate) async fn insert(&self, data: &SmlData) -> Result<(), Error> {
+        let req_id = self.req_id.fetch_add(1, Ordering::SeqCst);
+        self.request(req_id, SmlSend::insert(req_id, data)).await
+    }
+
+    /// Send a request and wait for the response of it.
+    async fn request(&self, req_id: ReqId, send: SmlSend) -> Result<(), Error> {
+        let _slot = self.limiter.acquire().await?;
+        let mut ws = self.ws.lock().await;
+        ws.send(send.to_msg()).await?;
+        while let Some(message) = ws.next().await {
+            match message? {
+                Message::Text(text) => {
+                    log::trace!("schemaless response: {text}");
+                    let recv: SmlRecv = serde_json::from_str(&text).map_err(|err| {
+                        RawError::from_string(format!("invalid schemaless response: {err}"))
+                    })?;
+                    if recv.req_id == req_id {
+                        return recv.ok();
+                    }
+                    log::warn!("[{req_id}] skip schemaless response of req {}", recv.req_id);
+                }
+                Message::Ping(bytes) => ws.send(Message::Pong(bytes)).await?,
+                Message::Close(_) => break,
+                _ => log::warn!("received (unexpected) schemaless message, do nothing"),
+            }
+        }
+        Err(Error::WsClosed("schemaless connection closed".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use taos_query::common::{SchemalessPrecision, SchemalessProtocol};
+
+    use super::*;
+
+    #[test]
+    fn insert_message() -> anyhow::Result<()> {
+        let data = SmlData::builder(SchemalessProtocol::Line)
+            .precision(SchemalessPrecision::Microseconds)
+            .ttl(7)
+            .lines(["st,t1=1 c1=1i32 1648432611249000", "st,t1=2 c1=2i32"])
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(SmlSend::insert(3, &data))?,
+            serde_json::json!({
+                "action": "insert",
+                "args": {
+                    "req_id": 3,
+                    "protocol": 1,
+                    "precision": "u",
+                    "data": "st,t1=1 c1=1i32 1648432611249000\nst,t1=2 c1=2i32",
+                    "ttl": 7,
+                }
+            })
+        );
+
+        let recv: SmlRecv = serde_json::from_str(
+            r#"{"code":0,"message":"","action":"insert","req_id":3,"timing":1}"#,
+        )?;
+        assert!(recv.ok().is_ok());
+        let recv: SmlRecv = serde_json::from_str(
+            r#"{"code":12288,"message":"invalid data","action":"insert","req_id":3}"#,
+        )?;
+        let err = recv.ok().unwrap_err();
+        assert_eq!(err.errno(), 12288);
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use std::time::Duration;
+
+use socket2::{SockRef, TcpKeepalive};
+use taos_query::prelude::tokio::net::TcpStream;
+use taos_query::{Dsn, DsnError};
+
+/// TCP options of the sockets under websocket connections, applied before the websocket
+/// handshake, see [TaosBuilder::with_socket_options](crate::TaosBuilder::with_socket_options).
+///
+/// Nagle's algorithm is disabled by default, so small requests are sent without waiting for
+/// the acknowledgement of the previous ones. Others are left to the system unless set, eg. by
+/// `tcpNoDelay=false&tcpKeepAliveSecs=30&sendBufferSize=1048576` in DSN.
+///
+/// Native connections are opened by the C library, whose sockets can't be tuned from Rust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOptions {
+    nodelay: bool,
+    keepalive: Option<Duration>,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+    priority: Option<u32>,
+    tos: Option<u32>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            keepalive: None,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            priority: None,
+            tos: None,
+        }
+    }
+}
+
+impl SocketOptions {
+    /// DSN parameters of socket options.
+    pub const PARAMS: &'static [&'static str] = &[
+        "tcpNoDelay",
+        "tcpKeepAliveSecs",
+        "sendBufferSize",
+        "recvBufferSize",
+        "socketPriority",
+        "ipTos",
+    ];
+
+    /// Set `TCP_NODELAY`, same as `tcpNoDelay` in DSN, it's `true` by default.
+    pub fn with_nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Send TCP keepalive probes after the connection is idle for `idle`, same as
+    /// `tcpKeepAliveSecs` in DSN.
+    pub fn with_keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+
+    /// Set `SO_SNDBUF`, same as `sendBufferSize` in DSN. The system may round or double it.
+    pub fn with_send_buffer_size(mut self, bytes: usize) -> Self {
+        self.send_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Set `SO_RCVBUF`, same as `recvBufferSize` in DSN. The system may round or double it.
+    pub fn with_recv_buffer_size(mut self, bytes: usize) -> Self {
+        self.recv_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Set `SO_PRIORITY` of Linux, same as `socketPriority` in DSN. Connecting fails on other
+    /// systems.
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Set the type of service of IPv4 or the traffic class of IPv6, eg. `0x10` for low delay,
+    /// same as `ipTos` in DSN.
+    pub fn with_tos(mut self, tos: u8) -> Self {
+        self.tos = Some(tos as u32);
+        self
+    }
+
+    pub fn nodelay(&self) -> bool {
+        self.nodelay
+    }
+
+    pub fn keepalive(&self) -> Option<Duration> {
+        self.keepalive
+    }
+
+    /// Take socket options in [SocketOptions::PARAMS] from `dsn`.
+    pub(crate) fn from_dsn(dsn: &mut Dsn) -> Result<Self, DsnError> {
+        fn parse<T: std::str::FromStr>(
+            dsn: &mut Dsn,
+            name: &str,
+            valid: impl Fn(&T) -> bool,
+        ) -> Result<Option<T>, DsnError> {
+            dsn.remove(name)
+                .map(|value| match value.parse::<T>() {
+                    Ok(v) if valid(&v) => Ok(v),
+                    _ => Err(DsnError::InvalidParam(name.to_string(), value)),
+                })
+                .transpose()
+        }
+        let mut options = Self::default();
+        if let Some(value) = dsn.remove("tcpNoDelay") {
+            options.nodelay = match value.to_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => Err(DsnError::InvalidParam("tcpNoDelay".to_string(), value))?,
+            };
+        }
+        options.keepalive =
+            parse(dsn, "tcpKeepAliveSecs", |secs: &u64| *secs > 0)?.map(Duration::from_secs);
+        options.send_buffer_size = parse(dsn, "sendBufferSize", |bytes: &usize| *bytes > 0)?;
+        options.recv_buffer_size = parse(dsn, "recvBufferSize", |bytes: &usize| *bytes > 0)?;
+        options.priority = parse(dsn, "socketPriority", |_: &u32| true)?;
+        options.tos = parse(dsn, "ipTos", |_: &u8| true)?.map(u32::from);
+        Ok(options)
+    }
+
+    /// Apply the options to `stream`, the proxy tunnel if connected through a proxy.
+    pub(crate) fn apply(&self, stream: &TcpStream) -> std::io::Result<()> {
+        let socket = SockRef::from(stream);
+        socket.set_nodelay(self.nodelay)?;
+        if let Some(idle) = self.keepalive {
+            socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(idle))?;
+        }
+        if let Some(bytes) = self.send_buffer_size {
+            socket.set_send_buffer_size(bytes)?;
+        }
+        if let Some(bytes) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(bytes)?;
+        }
+        if let Some(priority) = self.priority {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            set_priority(stream, priority)?;
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("socket priority {priority} is only supported on Linux"),
+            ));
+        }
+        if let Some(tos) = self.tos {
+            match stream.peer_addr()? {
+                std::net::SocketAddr::V4(_) => socket.set_tos(tos)?,
+                #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+                std::net::SocketAddr::V6(_) => socket.set_tclass_v6(tos)?,
+                #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+                std::net::SocketAddr::V6(_) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        "traffic class of IPv6 is not supported on this system",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Set `SO_PRIORITY`, which socket2 has no setter of before 0.6.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_priority(stream: &TcpStream, priority: u32) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let value = priority as libc::c_int;
+    let ret = unsafe {
+        libc::setsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PRIORITY,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use taos_query::prelude::tokio;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    fn priority(stream: &TcpStream) -> std::io::Result<u32> {
+        use std::os::unix::io::AsRawFd;
+
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PRIORITY,
+                &mut value as *mut libc::c_int as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if ret == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(value as u32)
+    }
+
+    #[test]
+    fn options_from_dsn() {
+        let options = |dsn: &str| SocketOptions::from_dsn(&mut dsn.parse().unwrap());
+        assert_eq!(
+            options("ws://localhost:6041").unwrap(),
+            SocketOptions::default()
+        );
+        assert!(SocketOptions::default().nodelay());
+        assert_eq!(
+            options("ws://localhost:6041?tcpNoDelay=false&tcpKeepAliveSecs=30&sendBufferSize=65536&recvBufferSize=131072&socketPriority=0&ipTos=16")
+                .unwrap(),
+            SocketOptions::default()
+                .with_nodelay(false)
+                .with_keepalive(Duration::from_secs(30))
+                .with_send_buffer_size(65536)
+                .with_recv_buffer_size(131072)
+                .with_priority(0)
+                .with_tos(0x10)
+        );
+        for dsn in [
+            "ws://localhost:6041?tcpNoDelay=yes",
+            "ws://localhost:6041?tcpKeepAliveSecs=0",
+            "ws://localhost:6041?sendBufferSize=-1",
+            "ws://localhost:6041?ipTos=256",
+        ] {
+            assert!(options(dsn).is_err(), "{dsn}");
+        }
+    }
+
+    #[tokio::test]
+    async fn options_applied() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let stream = TcpStream::connect(listener.local_addr()?).await?;
+        let options = SocketOptions::default()
+            .with_keepalive(Duration::from_secs(42))
+            .with_send_buffer_size(64 * 1024)
+            .with_tos(0x10);
+        options.apply(&stream)?;
+
+        let socket = SockRef::from(&stream);
+        assert!(socket.nodelay()?);
+        assert!(socket.keepalive()?);
+        assert_eq!(socket.keepalive_time()?, Duration::from_secs(42));
+        // Linux doubles the buffer size for bookkeeping.
+        assert!(socket.send_buffer_size()? >= 64 * 1024);
+        assert_eq!(socket.tos()?, 0x10);
+        #[cfg(target_os = "linux")]
+        {
+            SocketOptions::default().with_priority(3).apply(&stream)?;
+            assert_eq!(priority(&stream)?, 3);
+        }
+
+        SocketOptions::default()
+            .with_nodelay(false)
+            .apply(&stream)?;
+        assert!(!socket.nodelay()?);
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@ use serde_with::NoneAsEmptyString;
 
 use crate::query::infra::{ToMessage, WsConnReq};
 use taos_query::prelude::RawError as Error;
+use taos_query::stmt::StmtField;
 
 pub type ReqId = u64;
 
@@ -111,6 +112,8 @@ pub enum StmtSend {
     },
     AddBatch(StmtArgs),
     Exec(StmtArgs),
+    GetColFields(StmtArgs),
+    GetTagFields(StmtArgs),
 }
 
 impl ToMessage for StmtSend {}
@@ -150,6 +153,39 @@ pub enum StmtRecvData {
         #[serde(default)]
         affected: usize,
     },
+    GetColFields {
+        #[serde(default)]
+        stmt_id: StmtId,
+        #[serde(default)]
+        fields: Option<Vec<StmtField>>,
+    },
+    GetTagFields {
+        #[serde(default)]
+        stmt_id: StmtId,
+        #[serde(default)]
+        fields: Option<Vec<StmtField>>,
+    },
+}
+
+/// Response of an unknown action, eg. from an older adapter, only the error is kept.
+#[serde_as]
+#[derive(Debug, Deserialize)]
+pub struct StmtRecvUnknown {
+    pub code: i32,
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub stmt_id: StmtId,
+}
+
+impl StmtRecvUnknown {
+    pub(crate) fn ok(self) -> StmtOk {
+        let code = if self.code == 0 { -1 } else { self.code };
+        let message = self
+            .message
+            .unwrap_or_else(|| "unknown stmt response".to_string());
+        StmtOk::Stmt(self.stmt_id, Err(Error::new(code, message)))
+    }
 }
 
 #[serde_as]
@@ -163,11 +199,19 @@ pub struct StmtRecv {
     pub data: StmtRecvData,
 }
 
+/// Result of a stmt request.
+#[derive(Debug)]
+pub enum StmtReply {
+    Done,
+    Affected(usize),
+    Fields(Vec<StmtField>),
+}
+
 #[derive(Debug)]
 pub enum StmtOk {
     Conn(Result<(), Error>),
     Init(ReqId, Result<StmtId, Error>),
-    Stmt(StmtId, Result<Option<usize>, Error>),
+    Stmt(StmtId, Result<StmtReply, Error>),
 }
 
 impl StmtRecv {
@@ -201,14 +245,22 @@ impl StmtRecv {
             | StmtRecvData::Bind { stmt_id }
             | StmtRecvData::AddBatch { stmt_id } => StmtOk::Stmt(stmt_id, {
                 if self.code == 0 {
-                    Ok(None)
+                    Ok(StmtReply::Done)
                 } else {
                     _e!()
                 }
             }),
             StmtRecvData::Exec { stmt_id, affected } => StmtOk::Stmt(stmt_id, {
                 if self.code == 0 {
-                    Ok(Some(affected))
+                    Ok(StmtReply::Affected(affected))
+                } else {
+                    _e!()
+                }
+            }),
+            StmtRecvData::GetColFields { stmt_id, fields }
+            | StmtRecvData::GetTagFields { stmt_id, fields } => StmtOk::Stmt(stmt_id, {
+                if self.code == 0 {
+                    Ok(StmtReply::Fields(fields.unwrap_or_default()))
                 } else {
                     _e!()
                 }
@@ -221,8 +273,48 @@ impl StmtRecv {
 mod tests {
     use anyhow::Ok;
 
+    use super::*;
+
     #[test]
     fn stmt() -> anyhow::Result<()> {
         Ok(())
     }
+
+    #[test]
+    fn col_fields() -> anyhow::Result<()> {
+        use taos_query::common::Ty;
+
+        let json = r#"{"code":0,"message":"","action":"get_col_fields","req_id":3,"timing":1,"stmt_id":1,
+            "fields":[{"name":"ts","field_type":9,"precision":0,"scale":0,"bytes":8},
+                      {"name":"c3","field_type":3,"precision":0,"scale":0,"bytes":2}]}"#;
+        let recv: StmtRecv = serde_json::from_str(json)?;
+        match recv.ok() {
+            StmtOk::Stmt(1, std::result::Result::Ok(StmtReply::Fields(fields))) => assert_eq!(
+                fields,
+                [
+                    StmtField::new("ts", Ty::Timestamp, 8),
+                    StmtField::new("c3", Ty::SmallInt, 2)
+                ]
+            ),
+            ok => panic!("unexpected {ok:?}"),
+        }
+
+        let json = r#"{"code":0,"message":"","action":"get_tag_fields","req_id":4,"stmt_id":1,
+            "fields":[{"name":"t1","field_type":9,"precision":1,"scale":0,"bytes":8}]}"#;
+        let recv: StmtRecv = serde_json::from_str(json)?;
+        match recv.ok() {
+            StmtOk::Stmt(1, std::result::Result::Ok(StmtReply::Fields(fields))) => assert_eq!(
+                fields,
+                [StmtField::new("t1", Ty::Timestamp, 8)
+                    .with_precision(taos_query::common::Precision::Microsecond)]
+            ),
+            ok => panic!("unexpected {ok:?}"),
+        }
+
+        let json = r#"{"code":65535,"message":"unknown action","action":"get_col_fields_v2","req_id":3,"stmt_id":1}"#;
+        assert!(serde_json::from_str::<StmtRecv>(json).is_err());
+        let recv: StmtRecvUnknown = serde_json::from_str(json)?;
+        assert!(matches!(recv.ok(), StmtOk::Stmt(1, Err(_))));
+        Ok(())
+    }
 }
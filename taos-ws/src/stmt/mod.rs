@@ -4,21 +4,27 @@ use itertools::Itertools;
 use dashmap::DashMap as HashMap;
 
 use taos_query::common::views::views_to_raw_block;
-use taos_query::common::ColumnView;
+use taos_query::common::{ColumnView, Value};
 use taos_query::prelude::{InlinableWrite, RawError};
-use taos_query::stmt::Bindable;
+use taos_query::stmt::{
+    bind_query_sql, broadcast_bind, is_insert_sql, validate_bind, Bindable, StmtField, TableBind,
+};
+use taos_query::util::RateLimiter;
 use taos_query::{block_in_place_or_global, IntoDsn, RawBlock};
 
 use taos_query::prelude::tokio;
 use tokio::sync::{oneshot, watch};
 
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use tokio_tungstenite::tungstenite::protocol::Message;
 
-use crate::query::asyn::Error;
+use crate::query::asyn::{Error, QuerySlot, ResultSet};
 use crate::query::infra::ToMessage;
+use crate::query::QueryLimiter;
 use crate::{Taos, TaosBuilder};
 use messages::*;
+use taos_query::AsyncQueryable;
 
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::result::Result as StdResult;
 
@@ -27,13 +33,19 @@ use std::sync::Arc;
 use std::time::Duration;
 
 mod messages;
+mod pipeline;
+pub use pipeline::StmtPipeline;
 
-type StmtResult = StdResult<Option<usize>, RawError>;
+type StmtResult = StdResult<StmtReply, RawError>;
 type StmtSender = std::sync::mpsc::SyncSender<StmtResult>;
 type StmtReceiver = std::sync::mpsc::Receiver<StmtResult>;
 
 type WsSender = tokio::sync::mpsc::Sender<Message>;
 
+/// Messages sent ahead of their replies by [Stmt::stmt_bind_tables] and [Stmt::pipeline], the
+/// replies are buffered without blocking the message handler.
+pub const PIPELINED_MESSAGES: usize = 64;
+
 trait ToJsonValue {
     fn to_json_value(&self) -> serde_json::Value;
 }
@@ -59,10 +71,26 @@ impl ToJsonValue for ColumnView {
             ColumnView::UInt(view) => serde_json::json!(view.to_vec()),
             ColumnView::UBigInt(view) => serde_json::json!(view.to_vec()),
             ColumnView::Json(view) => serde_json::json!(view.to_vec()),
+            ColumnView::VarBinary(_) | ColumnView::Geometry(_) | ColumnView::Null(_) => {
+                serde_json::Value::Array(self.iter().map(|v| v.to_json_value()).collect())
+            }
         }
     }
 }
 
+/// Tag value in the json form of `set_tags` request.
+///
+/// SQL NULL of any type is sent as `null`, and json tags are sent as json text, so
+/// `Value::Null(Ty::Json)` is a NULL tag while `Value::Json(serde_json::Value::Null)` is the
+/// json literal `null`.
+fn tag_to_json(tag: &Value) -> serde_json::Value {
+    match tag {
+        Value::Null(_) => serde_json::Value::Null,
+        Value::Json(json) => serde_json::Value::String(json.to_string()),
+        tag => tag.to_json_value(),
+    }
+}
+
 impl Bindable<super::Taos> for Stmt {
     type Error = Error;
 
@@ -71,7 +99,8 @@ impl Bindable<super::Taos> for Stmt {
         let database: Option<String> =
             <Taos as taos_query::Queryable>::query_one(taos, "select database()")?;
         dsn.database = database;
-        let mut stmt = block_in_place_or_global(Self::from_wsinfo(&dsn))?;
+        let mut stmt =
+            block_in_place_or_global(Self::from_wsinfo_with_limiter(&dsn, taos.limiter.clone()))?;
         block_in_place_or_global(stmt.stmt_init())?;
         Ok(stmt)
     }
@@ -86,14 +115,12 @@ impl Bindable<super::Taos> for Stmt {
         Ok(self)
     }
 
-    fn set_tags(
-        &mut self,
-        tags: &[taos_query::common::Value],
-    ) -> StdResult<&mut Self, Self::Error> {
-        let tags = tags
-            .into_iter()
-            .map(|tag| tag.to_json_value())
-            .collect_vec();
+    fn set_tags(&mut self, tags: &[Value]) -> StdResult<&mut Self, Self::Error> {
+        // normal tables have no tags, and the server expects no tags message for them.
+        if tags.is_empty() {
+            return Ok(self);
+        }
+        let tags = tags.iter().map(tag_to_json).collect_vec();
         block_in_place_or_global(self.stmt_set_tags(tags))?;
         Ok(self)
     }
@@ -110,6 +137,13 @@ impl Bindable<super::Taos> for Stmt {
         //     .collect_vec();
         // block_in_place_or_global(self.stmt_bind(columns))?;
 
+        if let Some(query) = &self.query {
+            self.bound = Some(bind_query_sql(query, params)?);
+            return Ok(self);
+        }
+        let broadcast = broadcast_bind(params);
+        let params = broadcast.as_deref().unwrap_or(params);
+        validate_bind(self.bound_columns(), params)?;
         block_in_place_or_global(self.stmt_bind_block(params))?;
         Ok(self)
     }
@@ -126,6 +160,18 @@ impl Bindable<super::Taos> for Stmt {
     fn affected_rows(&self) -> usize {
         self.affected_rows
     }
+
+    fn result_set(&mut self) -> StdResult<ResultSet, Self::Error> {
+        block_in_place_or_global(self.stmt_use_result())
+    }
+
+    fn bound_columns(&self) -> &[StmtField] {
+        self.fields.as_deref().unwrap_or_default()
+    }
+
+    fn bound_tags(&self) -> &[StmtField] {
+        self.tags.as_deref().unwrap_or_default()
+    }
 }
 
 pub struct Stmt {
@@ -138,6 +184,25 @@ pub struct Stmt {
     receiver: Option<StmtReceiver>,
     args: Option<StmtArgs>,
     affected_rows: usize,
+    /// Parameters of the prepared statement, `None` if not known by the server.
+    fields: Option<Vec<StmtField>>,
+    /// Tags of the prepared statement, `None` if not known by the server.
+    tags: Option<Vec<StmtField>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// In-flight requests, shared with the connection the stmt is initialized by, see
+    /// [TaosBuilder::with_max_concurrent_queries].
+    limiter: Arc<QueryLimiter>,
+    /// Rows and bytes bound since the last execution, taken from the rate limit on execution.
+    pending: (usize, usize),
+    /// Sql of the prepared query statement, `None` for inserts.
+    ///
+    /// The stmt protocol has no result sets, so parameters of queries are bound as literals
+    /// and the query runs on a query connection.
+    query: Option<String>,
+    /// The query with bound parameters.
+    bound: Option<String>,
+    result: Option<ResultSet>,
+    taos: Taos,
 }
 
 // pub struct WsAsyncStmt {
@@ -227,7 +292,15 @@ impl Drop for Stmt {
 
 impl Stmt {
     pub(crate) async fn from_wsinfo(info: &TaosBuilder) -> Result<Self> {
-        let (ws, _) = connect_async(info.to_stmt_url()).await?;
+        Self::from_wsinfo_with_limiter(info, QueryLimiter::of(info)).await
+    }
+
+    /// Connect with requests limited by `limiter`, eg. of the connection it's initialized by.
+    async fn from_wsinfo_with_limiter(
+        info: &TaosBuilder,
+        limiter: Arc<QueryLimiter>,
+    ) -> Result<Self> {
+        let ws = info.connect_ws(&info.to_stmt_url(), None).await?;
         let req_id = 0;
         let (mut sender, mut reader) = ws.split();
 
@@ -266,7 +339,10 @@ impl Stmt {
             loop {
                 tokio::select! {
                     Some(msg) = msg_recv.recv() => {
-                        sender.send(msg).await.unwrap();
+                        if let Err(err) = sender.send(msg).await {
+                            log::warn!("send stmt message error: {err}");
+                            break;
+                        }
                     }
                     _ = rx.changed() => {
                         log::trace!("close sender task");
@@ -285,8 +361,17 @@ impl Stmt {
                             Ok(message) => match message {
                                 Message::Text(text) => {
                                     log::trace!("json response: {}", text);
-                                    let v: StmtRecv = serde_json::from_str(&text).unwrap();
-                                    match v.ok() {
+                                    let ok = match serde_json::from_str::<StmtRecv>(&text) {
+                                        Ok(v) => v.ok(),
+                                        Err(err) => match serde_json::from_str::<StmtRecvUnknown>(&text) {
+                                            Ok(v) => v.ok(),
+                                            Err(_) => {
+                                                log::warn!("unknown stmt response: {err}");
+                                                continue;
+                                            }
+                                        },
+                                    };
+                                    match ok {
                                         StmtOk::Conn(_) => {
                                             log::warn!("[{req_id}] received connected response in message loop");
                                         },
@@ -294,7 +379,7 @@ impl Stmt {
                                             log::trace!("stmt init done: {{ req_id: {}, stmt_id: {:?}}}", req_id, stmt_id);
                                             if let Some((_, sender)) = queries_sender.remove(&req_id)
                                             {
-                                                sender.send(stmt_id).unwrap();
+                                                let _ = sender.send(stmt_id);
                                             }  else {
                                                 log::error!("Stmt init failed because req id {req_id} not exist");
                                             }
@@ -303,7 +388,7 @@ impl Stmt {
                                             if let Some(sender) = fetches_sender.get(&stmt_id) {
                                                 log::trace!("send data to fetches with id {}", stmt_id);
                                                 // let res = res.clone();
-                                                sender.send(res).unwrap();
+                                                let _ = sender.send(res);
                                             // }) {
 
                                             } else {
@@ -320,7 +405,7 @@ impl Stmt {
                                     break;
                                 }
                                 Message::Ping(bytes) => {
-                                    ws2.send(Message::Pong(bytes)).await.unwrap();
+                                    let _ = ws2.send(Message::Pong(bytes)).await;
                                 }
                                 Message::Pong(_) => {
                                     // do nothing
@@ -356,6 +441,15 @@ impl Stmt {
             receiver: None,
             args: None,
             affected_rows: 0,
+            fields: None,
+            tags: None,
+            rate_limiter: info.rate_limiter.clone(),
+            pending: (0, 0),
+            query: None,
+            bound: None,
+            result: None,
+            taos: Taos::with_limiter(info, limiter.clone()),
+            limiter,
         })
     }
     /// Build TDengine websocket client from dsn.
@@ -374,9 +468,15 @@ impl Stmt {
             .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
     }
 
+    /// Wait for a free slot of the limiter, held until the reply is received.
+    async fn slot(&self) -> Result<QuerySlot> {
+        self.limiter.acquire().await
+    }
+
     pub async fn stmt_init(&mut self) -> Result<&mut Self> {
         let req_id = self.req_id();
         let action = StmtSend::Init { req_id };
+        let _slot = self.slot().await?;
         let (tx, rx) = oneshot::channel();
         {
             self.queries.insert(req_id, tx);
@@ -385,7 +485,7 @@ impl Stmt {
         let stmt_id = rx.await??; // 1. RecvError, 2. TaosError
         let args = StmtArgs { req_id, stmt_id };
 
-        let (sender, receiver) = std::sync::mpsc::sync_channel(2);
+        let (sender, receiver) = std::sync::mpsc::sync_channel(PIPELINED_MESSAGES);
 
         let _ = self.fetches.insert(stmt_id, sender);
 
@@ -405,21 +505,99 @@ impl Stmt {
         self
     }
     pub async fn stmt_prepare(&mut self, sql: &str) -> Result<()> {
+        self.bound = None;
+        self.result = None;
+        if !is_insert_sql(sql) {
+            self.query = Some(sql.to_string());
+            self.fields = None;
+            self.tags = None;
+            return Ok(());
+        }
+        self.query = None;
         let prepare = StmtSend::Prepare {
             args: self.args.unwrap(),
             sql: sql.to_string(),
         };
+        self.fields = None;
+        self.tags = None;
+        let slot = self.slot().await?;
         self.ws.send(prepare.to_msg()).await?;
         let _ = self
             .receiver
             .as_ref()
             .unwrap()
             .recv_timeout(self.timeout)??;
+        drop(slot);
+        self.load_fields().await;
+        self.load_tags().await;
         Ok(())
     }
+
+    /// Parameters of the prepared statement, available after prepare or `set_tbname` for
+    /// `insert into ? ...`.
+    pub async fn stmt_get_col_fields(&mut self) -> Result<Vec<StmtField>> {
+        let message = StmtSend::GetColFields(self.args.unwrap());
+        let _slot = self.slot().await?;
+        self.ws.send_timeout(message.to_msg(), self.timeout).await?;
+        match self
+            .receiver
+            .as_ref()
+            .unwrap()
+            .recv_timeout(self.timeout)??
+        {
+            StmtReply::Fields(fields) => Ok(fields),
+            reply => Err(RawError::from_string(format!("unexpected reply: {reply:?}")).into()),
+        }
+    }
+
+    /// Tags of the prepared statement, available after prepare or `set_tbname` for
+    /// `insert into ? using ...`.
+    pub async fn stmt_get_tag_fields(&mut self) -> Result<Vec<StmtField>> {
+        let message = StmtSend::GetTagFields(self.args.unwrap());
+        let _slot = self.slot().await?;
+        self.ws.send_timeout(message.to_msg(), self.timeout).await?;
+        match self
+            .receiver
+            .as_ref()
+            .unwrap()
+            .recv_timeout(self.timeout)??
+        {
+            StmtReply::Fields(fields) => Ok(fields),
+            reply => Err(RawError::from_string(format!("unexpected reply: {reply:?}")).into()),
+        }
+    }
+
+    /// Fields are only used to validate binds, so errors, eg. from an older server or a
+    /// statement without table name, leave them unknown.
+    async fn load_fields(&mut self) {
+        match self.stmt_get_col_fields().await {
+            Ok(fields) if !fields.is_empty() => self.fields = Some(fields),
+            Ok(_) => self.fields = None,
+            Err(err) => {
+                log::trace!("stmt column fields are unknown: {err}");
+                self.fields = None;
+            }
+        }
+    }
+
+    /// Tags are only used to parse tags from strings, they're left unknown on errors too.
+    async fn load_tags(&mut self) {
+        match self.stmt_get_tag_fields().await {
+            Ok(tags) if !tags.is_empty() => self.tags = Some(tags),
+            Ok(_) => self.tags = None,
+            Err(err) => {
+                log::trace!("stmt tag fields are unknown: {err}");
+                self.tags = None;
+            }
+        }
+    }
     pub async fn stmt_add_batch(&mut self) -> Result<()> {
         log::trace!("add batch");
+        if self.query.is_some() {
+            return Ok(());
+        }
         let message = StmtSend::AddBatch(self.args.unwrap());
+        let _slot = self.slot().await?;
         self.ws.send(message.to_msg()).await?;
         let _ = self
             .receiver
@@ -429,10 +607,17 @@ impl Stmt {
         Ok(())
     }
     pub async fn stmt_bind(&mut self, columns: Vec<serde_json::Value>) -> Result<()> {
+        let rows = columns
+            .first()
+            .and_then(serde_json::Value::as_array)
+            .map_or(0, Vec::len);
         let message = StmtSend::Bind {
             args: self.args.unwrap(),
             columns: columns,
         };
+        self.pending.0 += rows;
+        self.pending.1 += message.to_msg().len();
+        let _slot = self.slot().await?;
         {
             log::trace!("bind with: {message:?}");
             log::trace!("bind string: {}", message.to_msg());
@@ -448,6 +633,20 @@ impl Stmt {
     }
 
     async fn stmt_bind_block(&mut self, columns: &[ColumnView]) -> Result<()> {
+        let message = self.bind_block_message(columns)?;
+        let _slot = self.slot().await?;
+        self.ws.send(message).await?;
+        let _ = self
+            .receiver
+            .as_ref()
+            .unwrap()
+            .recv_timeout(self.timeout)??;
+
+        Ok(())
+    }
+
+    /// The binary message to bind columns, counted in the pending rows and bytes.
+    fn bind_block_message(&mut self, columns: &[ColumnView]) -> Result<Message> {
         let args = self.args.unwrap();
 
         let mut bytes = Vec::new();
@@ -460,6 +659,8 @@ impl Stmt {
         bytes.write_u64_le(2)?; // bind: 2
 
         let block = views_to_raw_block(columns);
+        self.pending.0 += columns.first().map_or(0, ColumnView::len);
+        self.pending.1 += block.len();
 
         bytes.extend(&block);
         log::trace!("block: {:?}", block);
@@ -468,15 +669,77 @@ impl Stmt {
             "{:#?}",
             RawBlock::parse_from_raw_block(block, taos_query::prelude::Precision::Millisecond)
         );
+        Ok(Message::Binary(bytes))
+    }
 
-        self.ws.send(Message::Binary(bytes)).await?;
-        let _ = self
-            .receiver
-            .as_ref()
-            .unwrap()
-            .recv_timeout(self.timeout)??;
+    /// Set table name, tags, bind columns and add batch for each table, like
+    /// [Bindable::set_tbname] and others, but up to [PIPELINED_MESSAGES] messages are sent
+    /// before their replies are received, so tables don't cost a round trip per message.
+    ///
+    /// It fails with the index of the first table that fails, messages in flight are drained
+    /// before it returns. Columns are validated against known parameters before sending.
+    pub async fn stmt_bind_tables(
+        &mut self,
+        tables: &[TableBind<'_>],
+    ) -> StdResult<(), (usize, Error)> {
+        if self.query.is_some() {
+            let err = RawError::from_string("tables are only bound to insert statements");
+            return Err((0, err.into()));
+        }
+        let broadcast = tables
+            .iter()
+            .map(|table| broadcast_bind(table.columns))
+            .collect_vec();
+        let columns = |index: usize| broadcast[index].as_deref().unwrap_or(tables[index].columns);
+        for index in 0..tables.len() {
+            validate_bind(self.bound_columns(), columns(index))
+                .map_err(|err| (index, err.into()))?;
+        }
+        let args = self.args.unwrap();
+        let mut messages = Vec::with_capacity(tables.len() * 4);
+        for (index, table) in tables.iter().enumerate() {
+            let name = table.name.to_string();
+            messages.push((index, StmtSend::SetTableName { args, name }.to_msg()));
+            if !table.tags.is_empty() {
+                let tags = table.tags.iter().map(tag_to_json).collect_vec();
+                messages.push((index, StmtSend::SetTags { args, tags }.to_msg()));
+            }
+            let bind = self
+                .bind_block_message(columns(index))
+                .map_err(|err| (index, err))?;
+            messages.push((index, bind));
+            messages.push((index, StmtSend::AddBatch(args).to_msg()));
+        }
 
-        Ok(())
+        // The tables are bound as one request, see [StmtPipeline].
+        let _slot = self.slot().await.map_err(|err| (0, err))?;
+        let receiver = self.receiver.as_ref().unwrap();
+        let receive = || -> Result<()> {
+            receiver.recv_timeout(self.timeout)??;
+            Ok(())
+        };
+        let mut in_flight = VecDeque::with_capacity(PIPELINED_MESSAGES);
+        let mut failed = None;
+        for (index, message) in messages {
+            if in_flight.len() == PIPELINED_MESSAGES {
+                let sent = in_flight.pop_front().unwrap();
+                if let Err(err) = receive() {
+                    failed = Some((sent, err));
+                    break;
+                }
+            }
+            if let Err(err) = self.ws.send_timeout(message, self.timeout).await {
+                failed = Some((index, err.into()));
+                break;
+            }
+            in_flight.push_back(index);
+        }
+        for sent in in_flight {
+            if let Err(err) = receive() {
+                failed.get_or_insert((sent, err));
+            }
+        }
+        failed.map_or(Ok(()), Err)
     }
 
     /// Call bind and add batch.
@@ -490,12 +753,20 @@ impl Stmt {
             args: self.args.unwrap(),
             name: name.to_string(),
         };
+        let slot = self.slot().await?;
         self.ws.send_timeout(message.to_msg(), self.timeout).await?;
         let _ = self
             .receiver
             .as_ref()
             .unwrap()
             .recv_timeout(self.timeout)??;
+        drop(slot);
+        if self.fields.is_none() {
+            self.load_fields().await;
+        }
+        if self.tags.is_none() {
+            self.load_tags().await;
+        }
         Ok(())
     }
 
@@ -504,6 +775,7 @@ impl Stmt {
             args: self.args.unwrap(),
             tags: tags,
         };
+        let _slot = self.slot().await?;
         self.ws.send_timeout(message.to_msg(), self.timeout).await?;
         let _ = self.receiver.as_ref().unwrap().recv_timeout(self.timeout)?;
         Ok(())
@@ -511,9 +783,22 @@ impl Stmt {
 
     pub async fn stmt_exec(&mut self) -> Result<usize> {
         log::trace!("exec");
+        if let Some(query) = &self.query {
+            let sql = match self.bound.take() {
+                Some(sql) => sql,
+                None => bind_query_sql(query, &[])?,
+            };
+            self.result = Some(self.taos.query(sql).await?);
+            return Ok(0);
+        }
+        let (rows, bytes) = std::mem::take(&mut self.pending);
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire(rows, bytes).await;
+        }
         let message = StmtSend::Exec(self.args.unwrap());
+        let _slot = self.slot().await?;
         self.ws.send_timeout(message.to_msg(), self.timeout).await?;
-        if let Some(affected) = self
+        if let StmtReply::Affected(affected) = self
             .receiver
             .as_ref()
             .unwrap()
@@ -525,6 +810,19 @@ impl Stmt {
             panic!("")
         }
     }
+
+    /// Take the result set of the executed query statement.
+    pub async fn stmt_use_result(&mut self) -> Result<ResultSet> {
+        if self.query.is_none() {
+            return Err(RawError::from_string(
+                "insert statements have no result set, use affected rows instead",
+            )
+            .into());
+        }
+        self.result.take().ok_or_else(|| {
+            RawError::from_string("result set is only available once after execution").into()
+        })
+    }
 }
 
 #[cfg(test)]
@@ -536,6 +834,198 @@ mod tests {
 
     use taos_query::prelude::tokio;
 
+    #[test]
+    fn tag_to_json() {
+        use taos_query::common::{Ty, Value};
+
+        for ty in [
+            Ty::Bool,
+            Ty::TinyInt,
+            Ty::SmallInt,
+            Ty::Int,
+            Ty::BigInt,
+            Ty::UTinyInt,
+            Ty::USmallInt,
+            Ty::UInt,
+            Ty::UBigInt,
+            Ty::Float,
+            Ty::Double,
+            Ty::Timestamp,
+            Ty::VarChar,
+            Ty::NChar,
+            Ty::Json,
+        ] {
+            assert_eq!(super::tag_to_json(&Value::Null(ty)), json!(null));
+        }
+        assert_eq!(super::tag_to_json(&Value::Int(0)), json!(0));
+        assert_eq!(
+            super::tag_to_json(&Value::Json(serde_json::Value::Null)),
+            json!("null")
+        );
+        assert_eq!(
+            super::tag_to_json(&Value::Json(json!({"name": "value"}))),
+            json!(r#"{"name":"value"}"#)
+        );
+    }
+
+    /// Mock stmt server of `insert into ? values(?, ?)`, actions are recorded in the order they
+    /// are run, table `bad` fails and executions affect a row of each bind.
+    async fn mock_stmt_server(
+        actions: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    ) -> anyhow::Result<std::net::SocketAddr> {
+        use futures::{SinkExt, StreamExt};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut rows = 0;
+            while let Some(Ok(message)) = ws.next().await {
+                let req: serde_json::Value = match message {
+                    Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                    Message::Binary(_) => json!({"action": "bind", "args": {}}),
+                    _ => continue,
+                };
+                let action = req["action"].as_str().unwrap();
+                let args = &req["args"];
+                let (code, extra) = match action {
+                    "set_table_name" if args["name"] == "bad" => (1, json!({})),
+                    "set_table_name" => {
+                        actions
+                            .lock()
+                            .unwrap()
+                            .push(format!("{action} {}", args["name"]));
+                        (0, json!({}))
+                    }
+                    "bind" => {
+                        rows += 1;
+                        (0, json!({}))
+                    }
+                    "exec" => (0, json!({ "affected": std::mem::take(&mut rows) })),
+                    "get_col_fields" => {
+                        let fields = json!([
+                            {"name": "ts", "field_type": 9, "precision": 0, "scale": 0, "bytes": 8},
+                            {"name": "v", "field_type": 4, "precision": 0, "scale": 0, "bytes": 4},
+                        ]);
+                        (0, json!({ "fields": fields }))
+                    }
+                    _ => (0, json!({})),
+                };
+                if !matches!(action, "conn" | "init" | "prepare" | "set_table_name") {
+                    actions.lock().unwrap().push(action.to_string());
+                }
+                let mut reply = json!({
+                    "code": code,
+                    "message": if code == 0 { "" } else { "invalid table name" },
+                    "action": action,
+                    "req_id": args["req_id"].as_u64().unwrap_or_default(),
+                    "stmt_id": 1,
+                });
+                reply
+                    .as_object_mut()
+                    .unwrap()
+                    .extend(extra.as_object().unwrap().clone());
+                // replies of later messages are slower to show they're not reordered.
+                if action == "bind" {
+                    tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                }
+                ws.send(Message::Text(reply.to_string())).await.unwrap();
+            }
+        });
+        Ok(addr)
+    }
+
+    fn table_columns(i: i32) -> [taos_query::common::ColumnView; 2] {
+        use taos_query::common::ColumnView;
+
+        [
+            ColumnView::from_millis_timestamp(vec![1_700_000_000_000 + i as i64]),
+            ColumnView::from_ints(vec![i]),
+        ]
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn pipeline_in_order() -> anyhow::Result<()> {
+        let actions = Default::default();
+        let addr = mock_stmt_server(std::sync::Arc::clone(&actions)).await?;
+        let mut stmt = Stmt::from_dsn(format!("ws://{addr}")).await?;
+        stmt.s_stmt("insert into ? values(?, ?)").await?;
+        actions.lock().unwrap().clear();
+
+        // 100 tables of 4 operations, more than the replies in flight at a time.
+        let mut pipeline = stmt.pipeline();
+        for i in 0..100 {
+            pipeline.set_tbname(&format!("d{i}")).await;
+            pipeline.set_tags(&[]).await;
+            pipeline.bind(&table_columns(i)).await;
+            pipeline.add_batch().await.execute().await;
+        }
+        assert_eq!(pipeline.finish().await.map_err(|(_, err)| err)?, 100);
+        assert_eq!(stmt.affected_rows, 100);
+
+        let expected: Vec<_> = (0..100)
+            .flat_map(|i| {
+                [
+                    format!("set_table_name \"d{i}\""),
+                    "bind".into(),
+                    "add_batch".into(),
+                    "exec".into(),
+                ]
+            })
+            .collect();
+        assert_eq!(*actions.lock().unwrap(), expected);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn pipeline_failed_index() -> anyhow::Result<()> {
+        let actions = Default::default();
+        let addr = mock_stmt_server(std::sync::Arc::clone(&actions)).await?;
+        let mut stmt = Stmt::from_dsn(format!("ws://{addr}")).await?;
+        stmt.s_stmt("insert into ? values(?, ?)").await?;
+
+        let mut pipeline = stmt.pipeline();
+        for (i, name) in ["d0", "d1", "bad", "d3"].into_iter().enumerate() {
+            pipeline.set_tbname(name).await;
+            pipeline.bind(&table_columns(i as i32)).await;
+            pipeline.add_batch().await.execute().await;
+        }
+        let (index, err) = pipeline.finish().await.unwrap_err();
+        assert_eq!(index, 8);
+        assert!(err.to_string().contains("invalid table name"), "{err}");
+
+        // binds are validated before sending.
+        let mut pipeline = stmt.pipeline();
+        pipeline.set_tbname("d4").await;
+        pipeline
+            .bind(&table_columns(4)[..1])
+            .await
+            .add_batch()
+            .await;
+        let (index, err) = pipeline.finish().await.unwrap_err();
+        assert_eq!(index, 1);
+        assert!(
+            err.to_string().contains("bind 1 columns to 2 parameters"),
+            "{err}"
+        );
+
+        // replies in flight are drained by failed pipelines, the statement is still usable.
+        let mut pipeline = stmt.pipeline();
+        pipeline.set_tbname("d5").await;
+        pipeline
+            .bind(&table_columns(5))
+            .await
+            .add_batch()
+            .await
+            .execute()
+            .await;
+        assert_eq!(pipeline.finish().await.map_err(|(_, err)| err)?, 1);
+        Ok(())
+    }
+
     // Websocket tests should always use `multi_thread`
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_client() -> anyhow::Result<()> {
@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::result::Result as StdResult;
+
+use itertools::Itertools;
+use taos_query::common::{ColumnView, Value};
+use taos_query::prelude::RawError;
+use taos_query::stmt::{broadcast_bind, validate_bind, Bindable};
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+use crate::query::asyn::QuerySlot;
+use crate::query::infra::ToMessage;
+
+use super::{tag_to_json, Error, Result, Stmt, StmtReply, StmtSend, PIPELINED_MESSAGES};
+
+/// Operations of a pipeline, replies are checked against them in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    SetTableName,
+    SetTags,
+    Bind,
+    AddBatch,
+    Execute,
+}
+
+/// Pipelined operations of a websocket [Stmt], see [Stmt::pipeline].
+///
+/// Operations are sent without waiting for their replies, up to [PIPELINED_MESSAGES] replies
+/// are outstanding at a time. Operations are indexed from 0 in the order they're called,
+/// including the ones not sent after a failure.
+///
+/// Replies of a statement are not tagged by operation, so they're matched to operations in
+/// order: the adapter runs the messages of a statement one by one in the order received, and
+/// a reply that doesn't fit its operation, eg. affected rows of a bind, fails the pipeline.
+///
+/// A pipeline is one request of the concurrent limit of its connection, it takes a slot from
+/// its first operation until it's finished, see [crate::TaosBuilder::with_max_concurrent_queries].
+#[derive(Debug)]
+pub struct StmtPipeline<'a> {
+    stmt: &'a mut Stmt,
+    /// Operations sent and waiting for replies, with their indices.
+    in_flight: VecDeque<(usize, Operation)>,
+    /// Number of operations called.
+    operations: usize,
+    affected_rows: usize,
+    /// The first failed operation.
+    failed: Option<(usize, Error)>,
+    /// Slot of the limiter, taken by the first operation sent.
+    slot: Option<QuerySlot>,
+}
+
+impl Stmt {
+    /// Pipeline operations of the prepared insert statement, binds of the next table are sent
+    /// while the previous execution is in flight, so tables don't cost a round trip each.
+    ///
+    /// ```rust,no_run
+    /// # use taos_query::common::ColumnView;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let mut stmt = taos_ws::Stmt::from_dsn("ws://localhost:6041/power").await?;
+    /// stmt.s_stmt("insert into ? values(?, ?)").await?;
+    /// let mut pipeline = stmt.pipeline();
+    /// for i in 0..1000 {
+    ///     let columns = [
+    ///         ColumnView::from_millis_timestamp(vec![1700000000000]),
+    ///         ColumnView::from_ints(vec![i]),
+    ///     ];
+    ///     pipeline.set_tbname(&format!("d{i}")).await;
+    ///     pipeline.bind(&columns).await.add_batch().await.execute().await;
+    /// }
+    /// let affected = pipeline.finish().await.map_err(|(index, err)| {
+    ///     anyhow::anyhow!("operation {index} failed: {err}")
+    /// })?;
+    /// assert_eq!(affected, 1000);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pipeline(&mut self) -> StmtPipeline<'_> {
+        let failed = self.query.is_some().then(|| {
+            let err = RawError::from_string("pipelines are only for insert statements");
+            (0, err.into())
+        });
+        StmtPipeline {
+            stmt: self,
+            in_flight: VecDeque::with_capacity(PIPELINED_MESSAGES),
+            operations: 0,
+            affected_rows: 0,
+            failed,
+            slot: None,
+        }
+    }
+}
+
+impl StmtPipeline<'_> {
+    /// Set the table name, fields of `insert into ? ...` are not loaded by pipelines, so binds
+    /// are validated only if they're known before.
+    pub async fn set_tbname(&mut self, name: &str) -> &mut Self {
+        let Some(index) = self.next_index() else {
+            return self;
+        };
+        let message = StmtSend::SetTableName {
+            args: self.stmt.args.unwrap(),
+            name: name.to_string(),
+        };
+        self.send(index, Operation::SetTableName, Ok(message.to_msg()))
+            .await
+    }
+
+    /// Set tags of the table, nothing is sent if `tags` is empty, eg. of normal tables.
+    pub async fn set_tags(&mut self, tags: &[Value]) -> &mut Self {
+        let Some(index) = self.next_index() else {
+            return self;
+        };
+        if tags.is_empty() {
+            return self;
+        }
+        let message = StmtSend::SetTags {
+            args: self.stmt.args.unwrap(),
+            tags: tags.iter().map(tag_to_json).collect_vec(),
+        };
+        self.send(index, Operation::SetTags, Ok(message.to_msg()))
+            .await
+    }
+
+    pub async fn bind(&mut self, columns: &[ColumnView]) -> &mut Self {
+        let Some(index) = self.next_index() else {
+            return self;
+        };
+        let broadcast = broadcast_bind(columns);
+        let columns = broadcast.as_deref().unwrap_or(columns);
+        let message = validate_bind(self.stmt.bound_columns(), columns)
+            .map_err(Error::from)
+            .and_then(|_| self.stmt.bind_block_message(columns));
+        self.send(index, Operation::Bind, message).await
+    }
+
+    pub async fn add_batch(&mut self) -> &mut Self {
+        let Some(index) = self.next_index() else {
+            return self;
+        };
+        let message = StmtSend::AddBatch(self.stmt.args.unwrap());
+        self.send(index, Operation::AddBatch, Ok(message.to_msg()))
+            .await
+    }
+
+    /// Execute the batches added, affected rows are summed up by [StmtPipeline::finish].
+    pub async fn execute(&mut self) -> &mut Self {
+        let Some(index) = self.next_index() else {
+            return self;
+        };
+        let (rows, bytes) = std::mem::take(&mut self.stmt.pending);
+        if let Some(limiter) = &self.stmt.rate_limiter {
+            limiter.acquire(rows, bytes).await;
+        }
+        let message = StmtSend::Exec(self.stmt.args.unwrap());
+        self.send(index, Operation::Execute, Ok(message.to_msg()))
+            .await
+    }
+
+    /// Wait for replies of all operations, returns the affected rows of executions.
+    ///
+    /// It fails with the index of the first failed operation, operations after it are not
+    /// sent, but the ones already in flight are still run by the server and drained here.
+    pub async fn finish(mut self) -> StdResult<usize, (usize, Error)> {
+        self.drain();
+        match self.failed.take() {
+            Some(failed) => Err(failed),
+            None => Ok(self.affected_rows),
+        }
+    }
+
+    /// Index of the next operation, `None` if the pipeline has failed.
+    fn next_index(&mut self) -> Option<usize> {
+        let index = self.operations;
+        self.operations += 1;
+        self.failed.is_none().then_some(index)
+    }
+
+    async fn send(
+        &mut self,
+        index: usize,
+        operation: Operation,
+        message: Result<Message>,
+    ) -> &mut Self {
+        let message = match message {
+            Ok(message) => message,
+            Err(err) => {
+                self.failed = Some((index, err));
+                return self;
+            }
+        };
+        if self.in_flight.len() == PIPELINED_MESSAGES {
+            self.receive();
+            if self.failed.is_some() {
+                return self;
+            }
+        }
+        if self.slot.is_none() {
+            match self.stmt.slot().await {
+                Ok(slot) => self.slot = Some(slot),
+                Err(err) => {
+                    self.failed = Some((index, err));
+                    return self;
+                }
+            }
+        }
+        let stmt = &self.stmt;
+        if let Err(err) = stmt.ws.send_timeout(message, stmt.timeout).await {
+            self.failed = Some((index, err.into()));
+            return self;
+        }
+        self.in_flight.push_back((index, operation));
+        self
+    }
+
+    /// Receive the reply of the earliest operation in flight.
+    fn receive(&mut self) {
+        let Some((index, operation)) = self.in_flight.pop_front() else {
+            return;
+        };
+        let receiver = self.stmt.receiver.as_ref().unwrap();
+        let reply = match receiver.recv_timeout(self.stmt.timeout) {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(err)) => return self.fail(index, err.into()),
+            Err(err) => return self.fail(index, err.into()),
+        };
+        match (operation, reply) {
+            (Operation::Execute, StmtReply::Affected(affected)) => {
+                self.affected_rows += affected;
+                self.stmt.affected_rows += affected;
+            }
+            (operation, StmtReply::Done) if operation != Operation::Execute => (),
+            (operation, reply) => {
+                let err = RawError::from_string(format!(
+                    "reply {reply:?} is out of order of operation {operation:?}"
+                ));
+                self.fail(index, err.into());
+            }
+        }
+    }
+
+    fn drain(&mut self) {
+        while !self.in_flight.is_empty() {
+            self.receive();
+        }
+    }
+
+    /// Keep the first failure, later ones are logged only.
+    fn fail(&mut self, index: usize, err: Error) {
+        match &self.failed {
+            Some(_) => log::trace!("pipeline operation {index} failed after failure: {err}"),
+            None => self.failed = Some((index, err)),
+        }
+    }
+}
+
+impl Drop for StmtPipeline<'_> {
+    /// Replies in flight are drained so they're not taken by later operations of the statement.
+    fn drop(&mut self) {
+        self.drain();
+    }
+}
@@ -0,0 +1,465 @@
+//! TLS of `wss` connections with custom CA and client certificates.
+use std::fmt::Debug;
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+use std::path::Path;
+use std::path::PathBuf;
+
+use once_cell::sync::OnceCell;
+use taos_query::{Dsn, DsnError};
+use tokio_tungstenite::Connector;
+
+const CA: &str = "ssl.ca";
+const CERT: &str = "ssl.cert";
+const KEY: &str = "ssl.key";
+const INSECURE_SKIP_VERIFY: &str = "ssl.insecure_skip_verify";
+
+/// TLS settings of `wss` connections, see [TaosBuilder::with_tls](crate::TaosBuilder::with_tls).
+///
+/// Same as `ssl.ca`, `ssl.cert`, `ssl.key` and `ssl.insecure_skip_verify` in DSN, eg.
+/// `wss://host:6041?ssl.ca=/path/ca.pem&ssl.cert=/path/client.pem&ssl.key=/path/client.key`.
+/// Files are read when the connection is built, so missing files or invalid PEM fail
+/// [TBuilder::build](taos_query::TBuilder::build) with the parameter named.
+///
+/// ```rust
+/// # use taos_ws::TlsConfig;
+/// let tls = TlsConfig::new()
+///     .ca("/path/ca.pem")
+///     .client_auth("/path/client.pem", "/path/client.key");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    ca: Option<PathBuf>,
+    client_auth: Option<(PathBuf, PathBuf)>,
+    insecure_skip_verify: bool,
+}
+
+impl TlsConfig {
+    /// Verify servers by the system roots, without client certificates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify servers by CA certificates in the PEM file at `path` instead of the system roots.
+    pub fn ca(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca = Some(path.into());
+        self
+    }
+
+    /// Authenticate to the server with the certificate chain and the private key in PEM files.
+    ///
+    /// The key is PKCS#8, rustls also takes PKCS#1 RSA and SEC1 EC keys.
+    pub fn client_auth(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.client_auth = Some((cert.into(), key.into()));
+        self
+    }
+
+    /// Accept any server certificate, for development environments only.
+    pub fn insecure_skip_verify(mut self, skip: bool) -> Self {
+        self.insecure_skip_verify = skip;
+        self
+    }
+
+    /// Take `ssl.*` parameters from `dsn`, `None` if there are none.
+    pub(crate) fn from_dsn(dsn: &mut Dsn) -> Result<Option<Self>, DsnError> {
+        let ca = dsn.remove(CA);
+        let cert = dsn.remove(CERT);
+        let key = dsn.remove(KEY);
+        let insecure = dsn.remove(INSECURE_SKIP_VERIFY);
+        if ca.is_none() && cert.is_none() && key.is_none() && insecure.is_none() {
+            return Ok(None);
+        }
+        let mut tls = Self::new();
+        tls.ca = ca.map(PathBuf::from);
+        tls.client_auth = match (cert, key) {
+            (Some(cert), Some(key)) => Some((cert.into(), key.into())),
+            (Some(_), None) => return Err(DsnError::RequireParam(KEY.to_string())),
+            (None, Some(_)) => return Err(DsnError::RequireParam(CERT.to_string())),
+            (None, None) => None,
+        };
+        if let Some(value) = insecure {
+            tls.insecure_skip_verify = match value.to_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => Err(DsnError::InvalidParam(
+                    INSECURE_SKIP_VERIFY.to_string(),
+                    value,
+                ))?,
+            };
+        }
+        Ok(Some(tls))
+    }
+
+    /// Read the files and create the connector of the settings.
+    fn connector(&self) -> Result<Connector, DsnError> {
+        if self.insecure_skip_verify {
+            log::warn!(
+                "server certificates are not verified by {INSECURE_SKIP_VERIFY}, \
+                 never use it in production"
+            );
+        }
+        imp::connector(self)
+    }
+}
+
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+fn invalid(param: &str, path: &Path, cause: impl std::fmt::Display) -> DsnError {
+    DsnError::InvalidParam(param.to_string(), format!("{}: {cause}", path.display()))
+}
+
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+fn read(param: &str, path: &Path) -> Result<Vec<u8>, DsnError> {
+    std::fs::read(path).map_err(|err| invalid(param, path, err))
+}
+
+#[cfg(feature = "rustls")]
+mod imp {
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    use rustls::client::{ServerCertVerified, ServerCertVerifier};
+    use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+    use rustls_pemfile::Item;
+
+    use super::*;
+
+    /// Verifier of `ssl.insecure_skip_verify`, handshake signatures are still checked.
+    struct SkipVerify;
+
+    impl ServerCertVerifier for SkipVerify {
+        fn verify_server_cert(
+            &self,
+            _: &Certificate,
+            _: &[Certificate],
+            _: &ServerName,
+            _: &mut dyn Iterator<Item = &[u8]>,
+            _: &[u8],
+            _: SystemTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+    }
+
+    fn certs(param: &str, path: &Path) -> Result<Vec<Certificate>, DsnError> {
+        let pem = read(param, path)?;
+        let certs =
+            rustls_pemfile::certs(&mut pem.as_slice()).map_err(|err| invalid(param, path, err))?;
+        if certs.is_empty() {
+            return Err(invalid(param, path, "no PEM certificates"));
+        }
+        Ok(certs.into_iter().map(Certificate).collect())
+    }
+
+    fn private_key(path: &Path) -> Result<PrivateKey, DsnError> {
+        let pem = read(KEY, path)?;
+        let items =
+            rustls_pemfile::read_all(&mut pem.as_slice()).map_err(|err| invalid(KEY, path, err))?;
+        items
+            .into_iter()
+            .find_map(|item| match item {
+                Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+                _ => None,
+            })
+            .ok_or_else(|| invalid(KEY, path, "no PEM private key"))
+    }
+
+    pub(super) fn connector(tls: &TlsConfig) -> Result<Connector, DsnError> {
+        let mut roots = RootCertStore::empty();
+        match &tls.ca {
+            Some(path) => {
+                for cert in certs(CA, path)? {
+                    roots.add(&cert).map_err(|err| invalid(CA, path, err))?;
+                }
+            }
+            None => {
+                let native = rustls_native_certs::load_native_certs().unwrap_or_else(|err| {
+                    log::warn!("failed to load system root certificates: {err}");
+                    Vec::new()
+                });
+                roots.add_parsable_certificates(
+                    &native.into_iter().map(|c| c.0).collect::<Vec<_>>(),
+                );
+            }
+        }
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let mut config = match &tls.client_auth {
+            Some((cert, key)) => builder
+                .with_single_cert(certs(CERT, cert)?, private_key(key)?)
+                .map_err(|err| invalid(KEY, key, err))?,
+            None => builder.with_no_client_auth(),
+        };
+        if tls.insecure_skip_verify {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(SkipVerify));
+        }
+        Ok(Connector::Rustls(Arc::new(config)))
+    }
+}
+
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+mod imp {
+    use native_tls::{Certificate, Identity, TlsConnector};
+
+    use super::*;
+
+    const END_CERTIFICATE: &str = "-----END CERTIFICATE-----";
+
+    pub(super) fn connector(tls: &TlsConfig) -> Result<Connector, DsnError> {
+        let mut builder = TlsConnector::builder();
+        if let Some(path) = &tls.ca {
+            let pem = read(CA, path)?;
+            let pem = String::from_utf8_lossy(&pem);
+            // Certificate::from_pem takes the first certificate only.
+            let blocks: Vec<_> = pem
+                .split_inclusive(END_CERTIFICATE)
+                .filter(|block| block.contains(END_CERTIFICATE))
+                .collect();
+            if blocks.is_empty() {
+                return Err(invalid(CA, path, "no PEM certificates"));
+            }
+            builder.disable_built_in_roots(true);
+            for block in blocks {
+                let cert = Certificate::from_pem(block.as_bytes())
+                    .map_err(|err| invalid(CA, path, err))?;
+                builder.add_root_certificate(cert);
+            }
+        }
+        if let Some((cert, key)) = &tls.client_auth {
+            let identity = Identity::from_pkcs8(&read(CERT, cert)?, &read(KEY, key)?)
+                .map_err(|err| invalid(KEY, key, err))?;
+            builder.identity(identity);
+        }
+        if tls.insecure_skip_verify {
+            builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+        let connector = builder
+            .build()
+            .map_err(|err| DsnError::InvalidParam("ssl".to_string(), err.to_string()))?;
+        Ok(Connector::NativeTls(connector))
+    }
+}
+
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+mod imp {
+    use super::*;
+
+    pub(super) fn connector(tls: &TlsConfig) -> Result<Connector, DsnError> {
+        let param = match (&tls.ca, &tls.client_auth) {
+            (Some(_), _) => CA,
+            (None, Some(_)) => CERT,
+            (None, None) => INSECURE_SKIP_VERIFY,
+        };
+        Err(DsnError::InvalidParam(
+            param.to_string(),
+            "TLS requires feature rustls or native-tls".to_string(),
+        ))
+    }
+}
+
+/// [TlsConfig] of a builder with its connector created once.
+#[derive(Clone)]
+pub(crate) struct Tls {
+    pub(crate) config: TlsConfig,
+    connector: OnceCell<Connector>,
+}
+
+impl Debug for Tls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tls")
+            .field("config", &self.config)
+            .field("ready", &self.connector.get().is_some())
+            .finish()
+    }
+}
+
+impl Tls {
+    pub(crate) fn new(config: TlsConfig) -> Self {
+        Self {
+            config,
+            connector: OnceCell::new(),
+        }
+    }
+
+    pub(crate) fn connector(&self) -> Result<&Connector, DsnError> {
+        self.connector.get_or_try_init(|| self.config.connector())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TaosBuilder;
+    use taos_query::TBuilder;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/tls");
+
+    fn fixture(name: &str) -> String {
+        format!("{FIXTURES}/{name}")
+    }
+
+    #[test]
+    fn tls_params() {
+        let dsn = format!(
+            "wss://localhost:6041?ssl.ca={}&ssl.cert=client.pem&ssl.key=client.key&ssl.insecure_skip_verify=true",
+            fixture("ca.pem")
+        );
+        let builder = TaosBuilder::from_dsn(dsn).unwrap();
+        let expected = TlsConfig::new()
+            .ca(fixture("ca.pem"))
+            .client_auth("client.pem", "client.key")
+            .insecure_skip_verify(true);
+        assert_eq!(builder.tls(), Some(&expected));
+        assert!(TaosBuilder::from_dsn("wss://localhost:6041")
+            .unwrap()
+            .tls()
+            .is_none());
+
+        for (dsn, err) in [
+            (
+                "wss://localhost:6041?ssl.cert=client.pem",
+                "requires parameter: ssl.key",
+            ),
+            (
+                "wss://localhost:6041?ssl.key=client.key",
+                "requires parameter: ssl.cert",
+            ),
+            (
+                "wss://localhost:6041?ssl.insecure_skip_verify=yes",
+                "invalid parameter for ssl.insecure_skip_verify: yes",
+            ),
+            (
+                "ws://localhost:6041?ssl.ca=ca.pem",
+                "ssl.* parameters are only supported for wss scheme",
+            ),
+        ] {
+            let actual = TaosBuilder::from_dsn(dsn).unwrap_err().to_string();
+            assert!(actual.contains(err), "{dsn}: {actual}");
+        }
+    }
+
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    #[test]
+    fn invalid_tls_files() {
+        let build_err = |query: String| {
+            let dsn = format!("wss://localhost:6041?{query}");
+            let builder = TaosBuilder::from_dsn(dsn).unwrap();
+            builder.build().err().expect("build fails").to_string()
+        };
+        let missing = fixture("missing.pem");
+        let err = build_err(format!("ssl.ca={missing}"));
+        assert!(err.contains(&format!("ssl.ca: {missing}")), "{err}");
+        let err = build_err(format!("ssl.ca={}", fixture("client.key")));
+        assert!(err.contains("ssl.ca"), "{err}");
+        let err = build_err(format!(
+            "ssl.cert={}&ssl.key={}",
+            fixture("client.pem"),
+            fixture("ca.pem")
+        ));
+        assert!(err.contains("ssl.key"), "{err}");
+
+        let dsn = format!(
+            "wss://localhost:6041?ssl.ca={}&ssl.cert={}&ssl.key={}",
+            fixture("ca.pem"),
+            fixture("client.pem"),
+            fixture("client.key")
+        );
+        assert!(TaosBuilder::from_dsn(dsn).unwrap().build().is_ok());
+    }
+
+    #[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+    #[test]
+    fn tls_requires_feature() {
+        let dsn = format!("wss://localhost:6041?ssl.ca={}", fixture("ca.pem"));
+        let err = TaosBuilder::from_dsn(dsn).unwrap().build().err().unwrap();
+        assert!(
+            err.to_string().contains("ssl.ca: TLS requires feature"),
+            "{err}"
+        );
+    }
+
+    /// Websocket server over TLS by the test CA, requiring client certificates of it.
+    #[cfg(feature = "rustls")]
+    async fn mutual_tls_server() -> u16 {
+        use futures::{SinkExt, StreamExt};
+        use rustls::server::AllowAnyAuthenticatedClient;
+        use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+        use std::sync::Arc;
+        use tokio::net::TcpListener;
+
+        let pem = |name: &str| std::fs::read(fixture(name)).unwrap();
+        let certs = |name: &str| {
+            let certs = rustls_pemfile::certs(&mut pem(name).as_slice()).unwrap();
+            certs.into_iter().map(Certificate).collect::<Vec<_>>()
+        };
+        let mut roots = RootCertStore::empty();
+        roots.add(&certs("ca.pem")[0]).unwrap();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut pem("server.key").as_slice()).unwrap();
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots))
+            .with_single_cert(certs("server.pem"), PrivateKey(key[0].clone()))
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    while let Some(Ok(message)) = ws.next().await {
+                        if message.is_text() && ws.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    #[cfg(feature = "rustls")]
+    #[tokio::test]
+    async fn mutual_tls() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let port = mutual_tls_server().await;
+        let url = format!("wss://localhost:{port}/rest/ws");
+        let connect = |query: String| {
+            let url = url.clone();
+            async move {
+                let dsn = format!("wss://localhost:{port}?proxy=none&{query}");
+                let builder = TaosBuilder::from_dsn(dsn).unwrap();
+                let mut ws = builder.connect_ws(&url, None).await.ok()?;
+                ws.send(Message::Text("ping".to_string())).await.ok()?;
+                ws.next().await?.ok()
+            }
+        };
+        let client = format!(
+            "ssl.cert={}&ssl.key={}",
+            fixture("client.pem"),
+            fixture("client.key")
+        );
+
+        let ca = format!("ssl.ca={}", fixture("ca.pem"));
+        let echo = connect(format!("{ca}&{client}")).await;
+        assert_eq!(echo, Some(Message::Text("ping".to_string())));
+        // Without the client certificate.
+        assert!(connect(ca).await.is_none());
+        // The test CA is not in the system roots.
+        assert!(connect(client.clone()).await.is_none());
+        let insecure = connect(format!("{client}&ssl.insecure_skip_verify=true")).await;
+        assert!(insecure.is_some());
+    }
+}
@@ -0,0 +1,29 @@
+//! Run the throughput benchmarks against a deployment and print the reports.
+//!
+//! ```sh
+//! cargo run --release --example bench --features bench -- ws://localhost:6041 10 100000
+//! ```
+//!
+//! Arguments are the DSN, tables and rows of each table.
+use taos::bench::{self, BenchConfig};
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+    let mut args = std::env::args().skip(1);
+    let dsn = args
+        .next()
+        .unwrap_or_else(|| "ws://localhost:6041".to_string());
+    let mut config = BenchConfig::new();
+    if let Some(tables) = args.next() {
+        config = config.with_tables(tables.parse()?);
+    }
+    if let Some(rows) = args.next() {
+        config = config.with_rows(rows.parse()?);
+    }
+
+    for report in bench::run(dsn.as_str(), &config).await? {
+        println!("{report}");
+    }
+    Ok(())
+}
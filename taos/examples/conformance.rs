@@ -0,0 +1,31 @@
+//! Compare two backends of a deployment and print the report as JSON.
+//!
+//! ```sh
+//! cargo run --example conformance --features conformance -- taos://localhost:6030 ws://localhost:6041
+//! ```
+//!
+//! Exits with 1 if any scenario fails.
+use taos::conformance;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+    let mut args = std::env::args().skip(1);
+    let dsn_a = args
+        .next()
+        .unwrap_or_else(|| "taos://localhost:6030".to_string());
+    let dsn_b = args
+        .next()
+        .unwrap_or_else(|| "ws://localhost:6041".to_string());
+
+    let report = conformance::run(dsn_a.as_str(), dsn_b.as_str()).await?;
+    println!("{}", report.to_json());
+    for failure in report.failures() {
+        eprintln!("FAILED {}: {} diffs", failure.name, failure.diffs.len());
+    }
+    eprintln!("{} passed, {} failed", report.passed, report.failed);
+    if !report.is_passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
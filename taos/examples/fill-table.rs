@@ -0,0 +1,68 @@
+//! Fill a table with random data at a target rate.
+//!
+//! ```sh
+//! cargo run --example fill-table -- [rows per second] [total rows] [seed]
+//! ```
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use taos::taos_query::common::views::ColSchema;
+use taos::taos_query::testing::{random_block, RandomOpts};
+use taos::*;
+
+const BATCH_ROWS: usize = 1000;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let rate: usize = args.next().map(|s| s.parse()).transpose()?.unwrap_or(10_000);
+    let total: usize = args.next().map(|s| s.parse()).transpose()?.unwrap_or(100_000);
+    let seed: u64 = args.next().map(|s| s.parse()).transpose()?.unwrap_or(0);
+
+    let taos = TaosBuilder::from_dsn("taos://")?.build()?;
+    taos.exec_many([
+        "drop database if exists test_fill_table",
+        "create database test_fill_table keep 36500",
+        "use test_fill_table",
+        "create table tb1 (ts timestamp, c1 int, c2 double, c3 varchar(32))",
+    ])
+    .await?;
+    let schema = [
+        ColSchema::new(Ty::Timestamp, 8),
+        ColSchema::new(Ty::Int, 4),
+        ColSchema::new(Ty::Double, 8),
+        ColSchema::new(Ty::VarChar, 32),
+    ];
+
+    let mut stmt = Stmt::init(&taos)?;
+    stmt.prepare("insert into tb1 values(?, ?, ?, ?)")?;
+
+    let start = Instant::now();
+    let mut opts = RandomOpts::new().with_null_probability(0.01);
+    let mut written = 0;
+    let mut batch = 0;
+    while written < total {
+        let rows = BATCH_ROWS.min(total - written);
+        // Continue timestamps after the previous batch.
+        opts = opts.with_base_timestamp(1_640_995_200_000 + written as i64);
+        let block = random_block(&schema, rows, seed + batch, &opts);
+        written += stmt.bind(block.column_views())?.add_batch()?.execute()?;
+        batch += 1;
+
+        let expected = Duration::from_secs_f64(written as f64 / rate as f64);
+        if let Some(ahead) = expected.checked_sub(start.elapsed()) {
+            tokio::time::sleep(ahead).await;
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{written} rows in {:.2?}, {:.0} rows/s",
+        elapsed,
+        written as f64 / elapsed.as_secs_f64()
+    );
+
+    let count: Option<i64> = taos.query_one("select count(*) from tb1").await?;
+    assert_eq!(count, Some(total as i64));
+
+    Ok(())
+}
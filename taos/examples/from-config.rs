@@ -0,0 +1,21 @@
+//! Connect by a config file, `examples/config.json` by default.
+//!
+//! ```sh
+//! cargo run --example from-config --features config -- taos.json
+//! ```
+use taos::*;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    pretty_env_logger::formatted_timed_builder().init();
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| concat!(env!("CARGO_MANIFEST_DIR"), "/examples/config.json").into());
+    let config: ConnectConfig = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    log::info!("connect to {config}");
+
+    let taos = TaosBuilder::from_config(config)?.build()?;
+    let version = taos.server_version().await?;
+    println!("connected to {version}");
+    Ok(())
+}
@@ -0,0 +1,506 @@
+//! Throughput benchmarks of stmt inserts, query fetches and TMQ consumption, enabled by feature
+//! `bench`.
+//!
+//! Scenarios run against any DSN with the same seeded data, see [BenchConfig], so numbers of
+//! different deployments or backends are comparable:
+//!
+//! ```rust,no_run
+//! # #[tokio::main(flavor = "multi_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! use taos::bench::{self, BenchConfig};
+//!
+//! let config = BenchConfig::new().with_tables(10).with_rows(100_000);
+//! for report in bench::run("ws://localhost:6041", &config).await? {
+//!     println!("{report}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each scenario takes a latency sample per operation: a batch bound and executed by [Stmt], a
+//! block fetched by a query or a message consumed with its blocks. The first `warmup` samples
+//! are taken but not measured. Throughput is the measured rows and bytes over the time spent in
+//! measured operations, so generating data is not counted, and percentiles are of all measured
+//! samples.
+//!
+//! Data is written to database `bench` by default, which is dropped and recreated by
+//! [stmt_insert] and kept for inspection after the run. Stmt binds block the current thread, so
+//! run the scenarios in a multi-threaded runtime.
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+use taos_query::common::views::ColSchema;
+use taos_query::common::{ColumnView, RawBlock, Ty};
+use taos_query::prelude::{AsAsyncConsumer, IsAsyncData, Timeout, TryStreamExt};
+use taos_query::stmt::Bindable;
+use taos_query::testing::{random_block, RandomOpts};
+use taos_query::{AsyncFetchable, AsyncQueryable, IntoDsn, TBuilder};
+
+use crate::{Error, Stmt, Taos, TaosBuilder, TmqBuilder};
+
+/// Tables, rows and columns of the benchmark data.
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    database: String,
+    tables: usize,
+    rows: usize,
+    batch_rows: usize,
+    columns: Vec<ColSchema>,
+    warmup: usize,
+    seed: u64,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BenchConfig {
+    /// 10 tables of 10000 rows in batches of 1000 rows, of an int, a double and a varchar(16)
+    /// column besides the timestamp, in database `bench`, with 2 samples of warm-up.
+    pub fn new() -> Self {
+        Self {
+            database: "bench".to_string(),
+            tables: 10,
+            rows: 10_000,
+            batch_rows: 1000,
+            columns: vec![
+                ColSchema::new(Ty::Int, 4),
+                ColSchema::new(Ty::Double, 8),
+                ColSchema::new(Ty::VarChar, 16),
+            ],
+            warmup: 2,
+            seed: 42,
+        }
+    }
+
+    pub fn with_database(mut self, name: impl Into<String>) -> Self {
+        self.database = name.into();
+        self
+    }
+
+    /// Sub-tables of the super table `meters`.
+    pub fn with_tables(mut self, tables: usize) -> Self {
+        self.tables = tables;
+        self
+    }
+
+    /// Rows of each table.
+    pub fn with_rows(mut self, rows: usize) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    /// Rows of each stmt batch.
+    pub fn with_batch_rows(mut self, rows: usize) -> Self {
+        self.batch_rows = rows.max(1);
+        self
+    }
+
+    /// Columns besides the leading timestamp, named `c1`, `c2`, ...
+    pub fn with_columns(mut self, columns: impl IntoIterator<Item = ColSchema>) -> Self {
+        self.columns = columns.into_iter().collect();
+        self
+    }
+
+    /// Samples of each scenario to take before measuring.
+    pub fn with_warmup(mut self, samples: usize) -> Self {
+        self.warmup = samples;
+        self
+    }
+
+    /// Seed of random data, the same seed generates the same data.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// Rows written by [stmt_insert] to be measured, besides warm-up.
+    pub fn total_rows(&self) -> usize {
+        self.tables * self.rows
+    }
+
+    fn schema(&self) -> Vec<ColSchema> {
+        std::iter::once(ColSchema::new(Ty::Timestamp, 8))
+            .chain(self.columns.iter().copied())
+            .collect()
+    }
+
+    fn create_stable_sql(&self) -> String {
+        let columns: Vec<_> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, col)| match col.ty() {
+                ty if ty.is_var_type() => {
+                    format!("c{} {}({})", i + 1, ty.lowercase_name(), col.len())
+                }
+                ty => format!("c{} {}", i + 1, ty.lowercase_name()),
+            })
+            .collect();
+        let columns = std::iter::once("ts timestamp".to_string())
+            .chain(columns)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("create stable meters ({columns}) tags (t int)")
+    }
+
+    /// Rows `offset..offset + rows` of a table, the same for the same seed.
+    fn batch(&self, table: usize, offset: usize, rows: usize) -> RawBlock {
+        let opts = RandomOpts::new().with_base_timestamp(BASE_TS + offset as i64);
+        let seed = self
+            .seed
+            .wrapping_add((table as u64) << 32)
+            .wrapping_add(offset as u64);
+        random_block(&self.schema(), rows, seed, &opts)
+    }
+
+    /// Batches of rows of a table, as `(offset, rows)`.
+    fn batches(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.rows)
+            .step_by(self.batch_rows)
+            .map(|offset| (offset, self.batch_rows.min(self.rows - offset)))
+    }
+}
+
+/// Timestamps start at `2022-01-01T00:00:00Z` and increase by 1ms.
+const BASE_TS: i64 = 1_640_995_200_000;
+
+/// Sub-table of warm-up writes, which is excluded from queries and the topic.
+const WARMUP_TABLE: &str = "warmup";
+
+/// Percentiles of latency samples, by nearest rank.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl Percentiles {
+    /// Percentiles of `samples`, which are sorted in place. All zeros if there is none.
+    pub fn of(samples: &mut [Duration]) -> Self {
+        samples.sort_unstable();
+        // the smallest sample that at least `q` percent of samples are less than or equal to.
+        let rank = |q: usize| {
+            let n = samples.len();
+            let rank = n - n * (100 - q) / 100;
+            samples
+                .get(rank.saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        Self {
+            p50: rank(50),
+            p95: rank(95),
+            p99: rank(99),
+        }
+    }
+}
+
+/// Measured throughput and latencies of a scenario.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub scenario: String,
+    pub rows: u64,
+    pub bytes: u64,
+    /// Time spent in measured operations.
+    pub elapsed: Duration,
+    pub rows_per_sec: f64,
+    pub bytes_per_sec: f64,
+    /// Measured samples, one per operation.
+    pub samples: usize,
+    pub latency: Percentiles,
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Percentiles { p50, p95, p99 } = self.latency;
+        write!(
+            f,
+            "{}: {} rows, {:.2} MiB in {:.3?}, {:.0} rows/s, {:.2} MiB/s, latency p50 {p50:.3?} \
+             p95 {p95:.3?} p99 {p99:.3?} of {} samples",
+            self.scenario,
+            self.rows,
+            self.bytes as f64 / MIB,
+            self.elapsed,
+            self.rows_per_sec,
+            self.bytes_per_sec / MIB,
+            self.samples
+        )
+    }
+}
+
+const MIB: f64 = 1024. * 1024.;
+
+/// Samples of a scenario, the first `warmup` samples are dropped.
+#[derive(Debug)]
+struct Recorder {
+    warmup: usize,
+    rows: u64,
+    bytes: u64,
+    elapsed: Duration,
+    samples: Vec<Duration>,
+}
+
+impl Recorder {
+    fn new(warmup: usize) -> Self {
+        Self {
+            warmup,
+            rows: 0,
+            bytes: 0,
+            elapsed: Duration::ZERO,
+            samples: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, rows: usize, bytes: usize, latency: Duration) {
+        if self.warmup > 0 {
+            self.warmup -= 1;
+            return;
+        }
+        self.rows += rows as u64;
+        self.bytes += bytes as u64;
+        self.elapsed += latency;
+        self.samples.push(latency);
+    }
+
+    /// Rates are totals over the total time, not averages of the rates of samples.
+    fn report(mut self, scenario: &str) -> BenchReport {
+        let secs = self.elapsed.as_secs_f64();
+        let rate = |n: u64| if secs > 0. { n as f64 / secs } else { 0. };
+        BenchReport {
+            scenario: scenario.to_string(),
+            rows: self.rows,
+            bytes: self.bytes,
+            elapsed: self.elapsed,
+            rows_per_sec: rate(self.rows),
+            bytes_per_sec: rate(self.bytes),
+            samples: self.samples.len(),
+            latency: Percentiles::of(&mut self.samples),
+        }
+    }
+}
+
+fn raw_len(views: &[ColumnView]) -> usize {
+    views.iter().map(ColumnView::raw_len).sum()
+}
+
+/// Recreate the database and tables, and write all rows by stmt batches of `batch_rows`.
+///
+/// Warm-up batches are written to a sub-table of their own before the measured ones.
+pub async fn stmt_insert(taos: &Taos, config: &BenchConfig) -> Result<BenchReport, Error> {
+    let db = config.database();
+    taos.exec_many([
+        format!("drop topic if exists {db}"),
+        format!("drop database if exists {db}"),
+        format!("create database {db} keep 36500 wal_retention_period 3600"),
+        format!("use {db}"),
+        config.create_stable_sql(),
+        format!("create table {WARMUP_TABLE} using meters tags (-1)"),
+    ])
+    .await?;
+    for table in 0..config.tables {
+        taos.exec(format!("create table d{table} using meters tags ({table})"))
+            .await?;
+    }
+
+    let mut stmt = Stmt::init(taos)?;
+    let placeholders = vec!["?"; config.columns.len() + 1].join(", ");
+    stmt.prepare(format!("insert into ? values({placeholders})"))?;
+    let mut recorder = Recorder::new(config.warmup);
+    let warmup = (0..config.warmup).map(|i| (WARMUP_TABLE.to_string(), i * config.batch_rows));
+    let warmup = warmup.map(|(table, offset)| (table, usize::MAX, offset, config.batch_rows));
+    let measured = (0..config.tables).flat_map(|table| {
+        config
+            .batches()
+            .map(move |(offset, rows)| (format!("d{table}"), table, offset, rows))
+    });
+    for (name, table, offset, rows) in warmup.chain(measured) {
+        let block = config.batch(table, offset, rows);
+        let views = block.column_views();
+        let start = Instant::now();
+        stmt.set_tbname(&name)?
+            .bind(views)?
+            .add_batch()?
+            .execute()?;
+        recorder.record(rows, raw_len(views), start.elapsed());
+    }
+    Ok(recorder.report("stmt_insert"))
+}
+
+/// Query all rows written by [stmt_insert], a sample per block.
+pub async fn query_fetch(taos: &Taos, config: &BenchConfig) -> Result<BenchReport, Error> {
+    let sql = format!("select * from {}.meters where t >= 0", config.database());
+    let mut recorder = Recorder::new(config.warmup);
+    let mut start = Instant::now();
+    let mut rs = taos.query(sql).await?;
+    let mut blocks = rs.blocks();
+    while let Some(block) = blocks.try_next().await? {
+        recorder.record(
+            block.nrows(),
+            raw_len(block.column_views()),
+            start.elapsed(),
+        );
+        start = Instant::now();
+    }
+    Ok(recorder.report("query_fetch"))
+}
+
+/// Consume the rows written by [stmt_insert] by a topic of the database from the earliest
+/// offset, a sample per message with its blocks.
+///
+/// It ends when all rows are consumed or no message comes in 5 seconds.
+pub async fn tmq_consume(taos: &Taos, config: &BenchConfig) -> Result<BenchReport, Error> {
+    let db = config.database();
+    taos.exec_many([
+        format!("drop topic if exists {db}"),
+        format!("create topic {db} as select * from {db}.meters where t >= 0"),
+    ])
+    .await?;
+    let group = format!("{db}_{}", config.seed);
+    let mut consumer = TmqBuilder::from_taos(
+        taos,
+        [
+            ("group.id", group.as_str()),
+            ("auto.offset.reset", "earliest"),
+        ],
+    )?
+    .build()?;
+    consumer.subscribe([db]).await?;
+
+    let mut recorder = Recorder::new(config.warmup);
+    let mut consumed = 0;
+    let timeout = Timeout::Duration(Duration::from_secs(5));
+    while consumed < config.total_rows() {
+        let start = Instant::now();
+        let Some((offset, message)) = consumer.recv_timeout(timeout).await? else {
+            break;
+        };
+        let (mut rows, mut bytes) = (0, 0);
+        if let Some(data) = message.into_data() {
+            while let Some(block) = data.fetch_raw_block().await? {
+                rows += block.nrows();
+                bytes += raw_len(block.column_views());
+            }
+        }
+        recorder.record(rows, bytes, start.elapsed());
+        consumed += rows;
+        consumer.commit(offset).await?;
+    }
+    consumer.unsubscribe().await;
+    taos.exec(format!("drop topic {db}")).await?;
+    Ok(recorder.report("tmq_consume"))
+}
+
+/// Run all scenarios on a connection of `dsn`: [stmt_insert], [query_fetch] and [tmq_consume].
+pub async fn run(dsn: impl IntoDsn, config: &BenchConfig) -> Result<Vec<BenchReport>, Error> {
+    let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+    Ok(vec![
+        stmt_insert(&taos, config).await?,
+        query_fetch(&taos, config).await?,
+        tmq_consume(&taos, config).await?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use taos_query::common::{Timestamp, Value};
+
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn percentiles_nearest_rank() {
+        let mut samples: Vec<_> = (1..=100).rev().map(ms).collect();
+        let p = Percentiles::of(&mut samples);
+        assert_eq!((p.p50, p.p95, p.p99), (ms(50), ms(95), ms(99)));
+
+        let mut samples: Vec<_> = (1..=10).map(ms).collect();
+        let p = Percentiles::of(&mut samples);
+        assert_eq!((p.p50, p.p95, p.p99), (ms(5), ms(10), ms(10)));
+
+        assert_eq!(Percentiles::of(&mut [ms(7)]).p50, ms(7));
+        assert_eq!(Percentiles::of(&mut []), Percentiles::default());
+    }
+
+    #[test]
+    fn recorder_totals() {
+        let mut recorder = Recorder::new(2);
+        recorder.record(1_000_000, 1, ms(1));
+        recorder.record(1_000_000, 1, ms(1));
+        // 100 rows in 1s and 100 rows in 3s are 50 rows/s, not the average 66.7 rows/s.
+        recorder.record(100, 1000, ms(1000));
+        recorder.record(100, 1000, ms(3000));
+        let report = recorder.report("totals");
+        assert_eq!((report.rows, report.bytes, report.samples), (200, 2000, 2));
+        assert_eq!(report.elapsed, ms(4000));
+        assert_eq!(report.rows_per_sec, 50.);
+        assert_eq!(report.bytes_per_sec, 500.);
+
+        // percentiles are of all samples: p99 of 2 slow samples in 200 is fast, though p99 of
+        // the last 100 samples is slow and the average of p99 of both halves is 500.5ms.
+        let mut recorder = Recorder::new(0);
+        (0..198).for_each(|_| recorder.record(1, 0, ms(1)));
+        recorder.record(1, 0, ms(1000));
+        recorder.record(1, 0, ms(1000));
+        let latency = recorder.report("all").latency;
+        assert_eq!(
+            (latency.p50, latency.p95, latency.p99),
+            (ms(1), ms(1), ms(1))
+        );
+        let mut tail: Vec<_> = (0..98).map(|_| ms(1)).chain([ms(1000); 2]).collect();
+        assert_eq!(Percentiles::of(&mut tail).p99, ms(1000));
+
+        let empty = Recorder::new(1).report("empty");
+        assert_eq!((empty.rows_per_sec, empty.samples), (0., 0));
+    }
+
+    #[test]
+    fn seeded_batches() {
+        let config = BenchConfig::new().with_rows(2500);
+        let batches: Vec<_> = config.batches().collect();
+        assert_eq!(batches, [(0, 1000), (1000, 1000), (2000, 500)]);
+
+        let values =
+            |config: &BenchConfig, table, offset| config.batch(table, offset, 3).to_values();
+        assert_eq!(values(&config, 0, 0), values(&config.clone(), 0, 0));
+        assert_ne!(values(&config, 0, 0), values(&config, 1, 0));
+        assert_ne!(
+            values(&config, 0, 0),
+            values(&config.clone().with_seed(7), 0, 0)
+        );
+        let block = config.batch(1, 1000, 3);
+        assert_eq!(block.ncols(), 4);
+        let ts = block.column_views()[0].get(0).unwrap().to_value();
+        assert_eq!(
+            ts,
+            Value::Timestamp(Timestamp::Milliseconds(BASE_TS + 1000))
+        );
+
+        assert_eq!(
+            config.create_stable_sql(),
+            "create stable meters (ts timestamp, c1 int, c2 double, c3 binary(16)) tags (t int)"
+        );
+    }
+
+    #[test]
+    fn report_display() {
+        let mut recorder = Recorder::new(0);
+        recorder.record(1000, 2 * 1024 * 1024, ms(500));
+        recorder.record(1000, 0, ms(1500));
+        assert_eq!(
+            recorder.report("stmt_insert").to_string(),
+            "stmt_insert: 2000 rows, 2.00 MiB in 2.000s, 1000 rows/s, 1.00 MiB/s, \
+             latency p50 500.000ms p95 1.500s p99 1.500s of 2 samples"
+        );
+    }
+}
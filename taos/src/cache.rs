@@ -0,0 +1,319 @@
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::Arc;
+
+use futures::TryStreamExt;
+use taos_query::common::stream::{BlockStreamReader, BlockStreamWriter};
+use taos_query::common::{Field, Precision, RawBlock, RowView, Warning};
+use taos_query::prelude::tokio::io::{AsyncRead, AsyncWrite};
+use taos_query::prelude::{AsyncFetchable, RawError};
+use taos_query::DeserializeOwned;
+
+use crate::{Error, ResultSet};
+
+/// A result with all its blocks in memory, see [ResultSet::cache_blocks].
+///
+/// Rows are read by any number of independent iterators, eg. deserialized into different types,
+/// and clones share the blocks.
+#[derive(Debug, Clone)]
+pub struct CachedResultSet(Arc<Cached>);
+
+#[derive(Debug)]
+struct Cached {
+    req_id: Option<u64>,
+    affected_rows: i32,
+    precision: Precision,
+    fields: Vec<Field>,
+    warnings: Vec<Warning>,
+    truncated: bool,
+    blocks: Vec<RawBlock>,
+}
+
+impl ResultSet {
+    /// Fetch all the remaining blocks into memory, to read the result more than once.
+    ///
+    /// Blocks are counted against the limits of [QueryOptions](crate::QueryOptions) as fetched
+    /// by [blocks](AsyncFetchable::blocks), so a result exceeding them fails with
+    /// [Error::ResultLimitExceeded], or is cached [truncated](CachedResultSet::is_truncated)
+    /// in [LimitMode::Truncate](crate::LimitMode::Truncate).
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let taos = TaosBuilder::from_dsn("taos://localhost:6030")?.build()?;
+    /// let cached = taos.query("select ts, current from power.meters").await?.cache_blocks().await?;
+    /// let currents: Vec<f32> = cached
+    ///     .deserialize::<(i64, f32)>()
+    ///     .map(|row| row.map(|(_, current)| current))
+    ///     .collect::<Result<_, _>>()?;
+    /// let rows: Vec<(chrono::NaiveDateTime, f32)> = cached.deserialize().collect::<Result<_, _>>()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn cache_blocks(mut self) -> Result<CachedResultSet, Error> {
+        let blocks: Vec<RawBlock> = self.blocks().try_collect().await?;
+        Ok(CachedResultSet(Arc::new(Cached {
+            req_id: self.req_id(),
+            affected_rows: AsyncFetchable::affected_rows(&self),
+            precision: AsyncFetchable::precision(&self),
+            fields: AsyncFetchable::fields(&self).to_vec(),
+            warnings: AsyncFetchable::warnings(&self).to_vec(),
+            truncated: self.is_truncated(),
+            blocks,
+        })))
+    }
+}
+
+impl CachedResultSet {
+    /// Request id of the query, see [ResultSet::req_id].
+    pub fn req_id(&self) -> Option<u64> {
+        self.0.req_id
+    }
+
+    pub fn affected_rows(&self) -> i32 {
+        self.0.affected_rows
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.0.precision
+    }
+
+    /// Fields of the result, with the names renamed by
+    /// [dedup_field_names](ResultSet::dedup_field_names) if any.
+    pub fn fields(&self) -> &[Field] {
+        &self.0.fields
+    }
+
+    pub fn warnings(&self) -> &[Warning] {
+        &self.0.warnings
+    }
+
+    /// Numbers of blocks and rows cached.
+    pub fn summary(&self) -> (usize, usize) {
+        (self.0.blocks.len(), self.num_rows())
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.0.blocks.iter().map(RawBlock::nrows).sum()
+    }
+
+    /// The result was cut by the limits in [LimitMode::Truncate](crate::LimitMode::Truncate)
+    /// before it was cached, see [ResultSet::is_truncated].
+    pub fn is_truncated(&self) -> bool {
+        self.0.truncated
+    }
+
+    pub fn blocks(&self) -> std::slice::Iter<'_, RawBlock> {
+        self.0.blocks.iter()
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = RowView<'_>> {
+        self.blocks().flat_map(RawBlock::rows)
+    }
+
+    /// Deserialize rows into `T`, see [RawBlock::deserialize].
+    pub fn deserialize<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+    ) -> impl Iterator<Item = Result<T, RawError>> + 'a {
+        self.blocks().flat_map(RawBlock::deserialize::<T>)
+    }
+
+    /// Write the result into `inner` in the [block stream](taos_query::common::stream) format,
+    /// to be read back by [CachedResultSet::read_from] eg. in another process.
+    ///
+    /// The first frame is a block of no rows with the fields and precision of the result, so
+    /// results without blocks keep them too. Request id, affected rows, warnings and truncation
+    /// are not written.
+    pub async fn write_to<W: AsyncWrite + Unpin>(&self, inner: W) -> std::io::Result<W> {
+        let schemas: Vec<_> = self.fields().iter().map(Field::to_column_schema).collect();
+        let mut header = RawBlock::empty(&schemas, self.precision());
+        header.with_field_names(self.fields().iter().map(Field::name));
+
+        let mut writer = BlockStreamWriter::new(inner);
+        writer.write_block(&header).await?;
+        for block in self.blocks() {
+            writer.write_block(block).await?;
+        }
+        writer.finish().await
+    }
+
+    /// Read a result written by [CachedResultSet::write_to].
+    pub async fn read_from<R: AsyncRead + Unpin>(inner: R) -> std::io::Result<Self> {
+        let mut reader = BlockStreamReader::new(inner);
+        let Some(header) = reader.read_block().await? else {
+            return Err(IoError::new(
+                ErrorKind::InvalidData,
+                "no fields of the cached result",
+            ));
+        };
+        let mut blocks = Vec::new();
+        while let Some(block) = reader.read_block().await? {
+            blocks.push(block);
+        }
+        Ok(Self(Arc::new(Cached {
+            req_id: None,
+            affected_rows: 0,
+            precision: header.precision(),
+            fields: header.fields(),
+            warnings: Vec::new(),
+            truncated: false,
+            blocks,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use futures::{SinkExt, StreamExt};
+    use taos_query::common::{views::views_to_raw_block, ColumnView};
+    use taos_query::prelude::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+    use crate::TaosBuilder;
+
+    const SQL: &str = "select ts, v, name from cached";
+
+    /// Mock server of a result of `rows` rows in blocks of 3 rows.
+    async fn mock_result(rows: usize) -> anyhow::Result<SocketAddr> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    let mut offset = 0;
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                            "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                            "query" => {
+                                offset = 0;
+                                format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":1,"fields_count":3,"fields_names":["ts","v","name"],"fields_types":[9,5,8],"fields_lengths":[8,8,16],"precision":1}}"#)
+                            }
+                            "fetch" => {
+                                let n = 3.min(rows - offset);
+                                format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"id":1,"completed":{},"rows":{n}}}"#, n == 0)
+                            }
+                            "fetch_block" => {
+                                let range = offset..(offset + 3).min(rows);
+                                offset = range.end;
+                                let views = [
+                                    ColumnView::from_micros_timestamp(range.clone().map(|i| i as i64 * 1000).collect()),
+                                    ColumnView::from_big_ints(range.clone().map(|i| (i % 2 == 0).then_some(i as i64)).collect()),
+                                    ColumnView::from_varchar::<String, _, _, _>(range.map(|i| format!("d{i}")).collect::<Vec<_>>()),
+                                ];
+                                let mut bytes = Vec::new();
+                                bytes.extend(0u64.to_le_bytes());
+                                bytes.extend(1u64.to_le_bytes());
+                                bytes.extend(views_to_raw_block(&views));
+                                ws.send(Message::Binary(bytes)).await.unwrap();
+                                continue;
+                            }
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+        Ok(addr)
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Record {
+        name: String,
+        v: Option<i64>,
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cache_blocks() -> anyhow::Result<()> {
+        let addr = mock_result(10).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let cached = taos.query(SQL).await?.cache_blocks().await?;
+        assert_eq!(cached.summary(), (4, 10));
+        assert_eq!(cached.precision(), Precision::Microsecond);
+        let names: Vec<_> = cached.fields().iter().map(Field::name).collect();
+        assert_eq!(names, ["ts", "v", "name"]);
+
+        // two deserializations of the cache, each the same as a fresh query.
+        let tuples: Vec<(i64, Option<i64>, String)> =
+            cached.deserialize().collect::<Result<_, _>>()?;
+        let fresh: Vec<(i64, Option<i64>, String)> =
+            taos.query(SQL).await?.deserialize().try_collect().await?;
+        assert_eq!(tuples, fresh);
+        assert_eq!(tuples[2], (2000, Some(2), "d2".to_string()));
+
+        let clone = cached.clone();
+        let records: Vec<Record> = clone.deserialize().collect::<Result<_, _>>()?;
+        let fresh: Vec<Record> = taos.query(SQL).await?.deserialize().try_collect().await?;
+        assert_eq!(records, fresh);
+        assert_eq!(records.len(), 10);
+        assert_eq!(cached.rows().count(), 10);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cache_within_limits() -> anyhow::Result<()> {
+        let addr = mock_result(10).await?;
+        let options = QueryOptions::new().max_result_rows(5);
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+
+        let err = taos
+            .query_with_options(SQL, options)
+            .await?
+            .cache_blocks()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ResultLimitExceeded { .. }), "{err:?}");
+
+        let truncate = options.on_limit(LimitMode::Truncate);
+        let cached = taos
+            .query_with_options(SQL, truncate)
+            .await?
+            .cache_blocks()
+            .await?;
+        assert!(cached.is_truncated());
+        assert_eq!(cached.num_rows(), 5);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cache_round_trip() -> anyhow::Result<()> {
+        let addr = mock_result(7).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let cached = taos.query(SQL).await?.cache_blocks().await?;
+
+        let bytes = cached.write_to(Vec::new()).await?;
+        let read = CachedResultSet::read_from(bytes.as_slice()).await?;
+        assert_eq!(read.fields(), cached.fields());
+        assert_eq!(read.precision(), cached.precision());
+        assert_eq!(read.summary(), cached.summary());
+        let rows = |rs: &CachedResultSet| {
+            rs.deserialize::<(i64, Option<i64>, String)>()
+                .collect::<Result<Vec<_>, _>>()
+        };
+        assert_eq!(rows(&read)?, rows(&cached)?);
+
+        // fields and precision are kept without rows.
+        let empty = mock_result(0).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{empty}"))?.build()?;
+        let cached = taos.query(SQL).await?.cache_blocks().await?;
+        assert_eq!(cached.summary(), (0, 0));
+        let bytes = cached.write_to(Vec::new()).await?;
+        let read = CachedResultSet::read_from(bytes.as_slice()).await?;
+        assert_eq!(read.fields(), cached.fields());
+        assert_eq!(read.precision(), Precision::Microsecond);
+
+        let err = CachedResultSet::read_from(&b"TBLK\x01\xff"[..])
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        Ok(())
+    }
+}
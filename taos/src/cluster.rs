@@ -0,0 +1,264 @@
+use std::fmt::{self, Display};
+
+use taos_query::prelude::{AsyncFetchable, AsyncQueryable, Code, RawError};
+
+use crate::{Error, Taos};
+
+/// Codes of servers without `show cluster alive`, eg. a syntax error of 2.x or early 3.0.
+const UNSUPPORTED: [Code; 3] = [Code::new(0x2600), Code::new(0x0216), Code::new(0x0100)];
+
+/// Availability of a cluster, see [Taos::cluster_alive].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClusterAlive {
+    /// The cluster is unavailable.
+    No,
+    /// All dnodes and vnodes are available.
+    Fully,
+    /// Some dnodes or vnodes are offline, eg. a replica of a vgroup.
+    Partially,
+}
+
+impl ClusterAlive {
+    /// The value of `show cluster alive`, `None` if it's unknown.
+    pub const fn from_status(status: i32) -> Option<Self> {
+        match status {
+            0 => Some(Self::No),
+            1 => Some(Self::Fully),
+            2 => Some(Self::Partially),
+            _ => None,
+        }
+    }
+
+    /// Availability by numbers of ready dnodes in all, of servers without `show cluster alive`.
+    pub const fn from_dnodes(ready: usize, total: usize) -> Self {
+        match ready {
+            0 => Self::No,
+            ready if ready >= total => Self::Fully,
+            _ => Self::Partially,
+        }
+    }
+
+    pub const fn is_fully(&self) -> bool {
+        matches!(self, Self::Fully)
+    }
+}
+
+impl Display for ClusterAlive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::No => "unavailable",
+            Self::Fully => "fully available",
+            Self::Partially => "partially available",
+        })
+    }
+}
+
+/// Availability of a cluster with the raw numbers it's decided by, see [Taos::cluster_status].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClusterStatus {
+    pub alive: ClusterAlive,
+    /// Value of `show cluster alive`, `None` if the server doesn't support it.
+    pub status: Option<i32>,
+    /// Number of dnodes in `ready` status.
+    pub dnodes_ready: usize,
+    /// Number of dnodes of the cluster.
+    pub dnodes_total: usize,
+}
+
+impl Taos {
+    /// Availability of the whole cluster, not only of this connection, eg. to assert the
+    /// cluster is fully available before running a critical batch.
+    ///
+    /// It's `show cluster alive` of the server, or decided by the status of dnodes on servers
+    /// without the statement.
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let taos = TaosBuilder::from_dsn("ws://localhost:6041")?.build()?;
+    /// match taos.cluster_alive().await? {
+    ///     ClusterAlive::Fully => println!("go"),
+    ///     alive => anyhow::bail!("cluster is {alive}"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn cluster_alive(&self) -> Result<ClusterAlive, Error> {
+        if let Some(alive) = self
+            .show_cluster_alive()
+            .await?
+            .and_then(ClusterAlive::from_status)
+        {
+            return Ok(alive);
+        }
+        let (ready, total) = self.dnodes_ready().await?;
+        Ok(ClusterAlive::from_dnodes(ready, total))
+    }
+
+    /// The cluster is fully available, see [Taos::cluster_alive].
+    pub async fn is_cluster_healthy(&self) -> Result<bool, Error> {
+        Ok(self.cluster_alive().await?.is_fully())
+    }
+
+    /// Availability of the cluster like [Taos::cluster_alive], with the value of
+    /// `show cluster alive` and the numbers of dnodes, which are always queried.
+    pub async fn cluster_status(&self) -> Result<ClusterStatus, Error> {
+        let status = self.show_cluster_alive().await?;
+        let (dnodes_ready, dnodes_total) = self.dnodes_ready().await?;
+        let alive = status
+            .and_then(ClusterAlive::from_status)
+            .unwrap_or(ClusterAlive::from_dnodes(dnodes_ready, dnodes_total));
+        Ok(ClusterStatus {
+            alive,
+            status,
+            dnodes_ready,
+            dnodes_total,
+        })
+    }
+
+    /// Value of `show cluster alive`, `None` if the server doesn't support it.
+    async fn show_cluster_alive(&self) -> Result<Option<i32>, Error> {
+        match self.query_one::<_, i32>("show cluster alive").await {
+            Ok(status) => Ok(status),
+            Err(err) if UNSUPPORTED.contains(&err.code()) => {
+                log::debug!("show cluster alive is not supported, fall back to dnodes: {err}");
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Numbers of ready dnodes and all dnodes.
+    async fn dnodes_ready(&self) -> Result<(usize, usize), Error> {
+        use futures::TryStreamExt;
+
+        let mut rs = self.query("show dnodes").await?;
+        let (mut ready, mut total) = (0, 0);
+        while let Some(block) = rs.blocks().try_next().await? {
+            let Some((_, statuses)) = block.column("status") else {
+                return Err(RawError::from_string("no status of dnodes").into());
+            };
+            ready += statuses
+                .iter()
+                .filter(|status| status.as_bytes() == Some(b"ready"))
+                .count();
+            total += block.nrows();
+        }
+        Ok((ready, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use futures::{SinkExt, StreamExt};
+    use taos_query::common::{views::views_to_raw_block, ColumnView};
+    use taos_query::prelude::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+    use crate::TaosBuilder;
+
+    /// A server of `alive` in `show cluster alive`, or a syntax error if it's `None`, and of
+    /// dnodes in `statuses`.
+    async fn mock_cluster(alive: Option<i32>, statuses: &[&str]) -> anyhow::Result<SocketAddr> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let statuses: Vec<String> = statuses.iter().map(|s| s.to_string()).collect();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let statuses = statuses.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    let mut view = None;
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                            "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                            "query" => match (req["args"]["sql"].as_str().unwrap(), alive) {
+                                ("show cluster alive", None) => format!(r#"{{"code":9728,"message":"syntax error near \"alive\"","action":"query","req_id":{req_id}}}"#),
+                                ("show cluster alive", Some(alive)) => {
+                                    view = Some(ColumnView::from_ints(vec![alive]));
+                                    format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":1,"fields_count":1,"fields_names":["status"],"fields_types":[4],"fields_lengths":[4],"precision":0}}"#)
+                                }
+                                _ => {
+                                    view = Some(ColumnView::from_varchar::<String, _, _, _>(statuses.clone()));
+                                    format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":1,"fields_count":1,"fields_names":["status"],"fields_types":[8],"fields_lengths":[10],"precision":0}}"#)
+                                }
+                            },
+                            "fetch" => {
+                                let n = view.as_ref().map_or(0, ColumnView::len);
+                                format!(r#"{{"code":0,"message":"","action":"fetch","req_id":{req_id},"id":1,"completed":{},"rows":{n}}}"#, n == 0)
+                            }
+                            "fetch_block" => {
+                                let view = view.take().unwrap();
+                                let mut bytes = Vec::new();
+                                bytes.extend(0u64.to_le_bytes());
+                                bytes.extend(1u64.to_le_bytes());
+                                bytes.extend(views_to_raw_block(&[view]));
+                                ws.send(Message::Binary(bytes)).await.unwrap();
+                                continue;
+                            }
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+        Ok(addr)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cluster_alive() -> anyhow::Result<()> {
+        let cases = [
+            (Some(1), ClusterAlive::Fully),
+            (Some(2), ClusterAlive::Partially),
+            (Some(0), ClusterAlive::No),
+        ];
+        for (status, alive) in cases {
+            let addr = mock_cluster(status, &["ready", "offline", "ready"]).await?;
+            let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+            assert_eq!(taos.cluster_alive().await?, alive);
+            assert_eq!(taos.is_cluster_healthy().await?, alive.is_fully());
+            let status_of = taos.cluster_status().await?;
+            assert_eq!(status_of.alive, alive);
+            assert_eq!(status_of.status, status);
+            assert_eq!((status_of.dnodes_ready, status_of.dnodes_total), (2, 3));
+        }
+        Ok(())
+    }
+
+    /// Servers without `show cluster alive` are decided by the status of dnodes.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cluster_alive_fallback() -> anyhow::Result<()> {
+        let cases: [(&[&str], ClusterAlive); 3] = [
+            (&["ready", "ready"], ClusterAlive::Fully),
+            (&["ready", "offline"], ClusterAlive::Partially),
+            (&["offline"], ClusterAlive::No),
+        ];
+        for (statuses, alive) in cases {
+            let addr = mock_cluster(None, statuses).await?;
+            let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+            assert_eq!(taos.cluster_alive().await?, alive, "{statuses:?}");
+            let status = taos.cluster_status().await?;
+            assert_eq!(status.status, None);
+            assert_eq!(status.alive, alive);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn alive_of_dnodes() {
+        assert_eq!(ClusterAlive::from_dnodes(3, 3), ClusterAlive::Fully);
+        assert_eq!(ClusterAlive::from_dnodes(1, 3), ClusterAlive::Partially);
+        assert_eq!(ClusterAlive::from_dnodes(0, 0), ClusterAlive::No);
+        assert_eq!(ClusterAlive::from_status(3), None);
+        assert_eq!(ClusterAlive::Partially.to_string(), "partially available");
+    }
+}
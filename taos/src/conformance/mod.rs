@@ -0,0 +1,267 @@
+//! Cross-backend conformance suite, enabled by feature `conformance`.
+//!
+//! The suite runs the same scenarios on two backends, eg. native and websocket connections of a
+//! deployment, and reports where they disagree in affected rows, values, NULL handling or error
+//! codes. Scenarios cover typed CRUD and stmt binds of every column type, TMQ produce/consume
+//! and error cases.
+//!
+//! ```rust,no_run
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let report = taos::conformance::run("taos://localhost:6030", "ws://localhost:6041").await?;
+//! println!("{}", report.to_json());
+//! assert!(report.is_passed());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each backend works in its own database, `conformance_a` and `conformance_b` by default, which
+//! is dropped and recreated at start and kept for inspection after the run. Stmt binds block the
+//! current thread, so run the suite in a multi-threaded runtime.
+use std::time::Duration;
+
+use serde::Serialize;
+use taos_query::common::{ColumnView, Field, RawBlock, Value};
+use taos_query::prelude::{AsAsyncConsumer, IsAsyncData, Timeout, TryStreamExt};
+use taos_query::stmt::Bindable;
+use taos_query::{AsyncFetchable, AsyncQueryable, IntoDsn, TBuilder};
+
+use crate::{Error, Stmt, Taos, TaosBuilder, TmqBuilder};
+
+mod report;
+mod scenario;
+
+pub use report::{diff, Diff, Outcome, Report, ScenarioReport};
+pub use scenario::Scenario;
+
+/// Kind of a scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// Create, insert, update, delete and query by sql.
+    Crud,
+    /// Inserts by prepared statements.
+    Stmt,
+    /// Produce by inserts and consume by a TMQ consumer.
+    Tmq,
+    /// Statements that should fail with the same error code.
+    Error,
+}
+
+/// Fields and values of a query.
+#[derive(Debug, Clone, Default)]
+pub struct Rows {
+    pub fields: Vec<Field>,
+    pub values: Vec<Vec<Value>>,
+}
+
+/// A connection to run scenarios on, implemented by [Taos] of both native and websocket.
+///
+/// Implement it to check other connections, eg. one through a proxy.
+#[async_trait::async_trait]
+pub trait Backend: Send + Sync {
+    /// Name of the backend in reports.
+    fn label(&self) -> String;
+
+    /// Execute a statement, returns affected rows.
+    async fn exec(&self, sql: &str) -> Result<usize, Error>;
+
+    /// Query all the rows.
+    async fn query(&self, sql: &str) -> Result<Rows, Error>;
+
+    /// Bind `params` as a batch to a prepared insert and execute it, returns affected rows.
+    ///
+    /// `table` is the table name and tags of `insert into ? using ...`.
+    fn stmt_insert(
+        &self,
+        sql: &str,
+        table: Option<(&str, &[Value])>,
+        params: &[ColumnView],
+    ) -> Result<usize, Error>;
+
+    /// Consume a topic from the earliest offset in consumer group `group`, until no message
+    /// comes in `timeout`.
+    async fn consume(
+        &self,
+        topic: &str,
+        group: &str,
+        timeout: Duration,
+    ) -> Result<Vec<RawBlock>, Error>;
+}
+
+#[async_trait::async_trait]
+impl Backend for Taos {
+    fn label(&self) -> String {
+        match &self.1.protocol {
+            Some(protocol) => format!("{}+{protocol}", self.1.driver),
+            None => self.1.driver.clone(),
+        }
+    }
+
+    async fn exec(&self, sql: &str) -> Result<usize, Error> {
+        AsyncQueryable::exec(self, sql).await
+    }
+
+    async fn query(&self, sql: &str) -> Result<Rows, Error> {
+        let mut rs = AsyncQueryable::query(self, sql).await?;
+        let mut rows = Rows {
+            fields: rs.fields().to_vec(),
+            values: Vec::new(),
+        };
+        let mut blocks = rs.blocks();
+        while let Some(block) = blocks.try_next().await? {
+            rows.values.extend(block.to_values());
+        }
+        Ok(rows)
+    }
+
+    fn stmt_insert(
+        &self,
+        sql: &str,
+        table: Option<(&str, &[Value])>,
+        params: &[ColumnView],
+    ) -> Result<usize, Error> {
+        let mut stmt = Stmt::init(self)?;
+        stmt.prepare(sql)?;
+        if let Some((name, tags)) = table {
+            stmt.set_tbname_tags(name, tags)?;
+        }
+        stmt.bind(params)?.add_batch()?;
+        stmt.execute()
+    }
+
+    async fn consume(
+        &self,
+        topic: &str,
+        group: &str,
+        timeout: Duration,
+    ) -> Result<Vec<RawBlock>, Error> {
+        let mut consumer = TmqBuilder::from_taos(
+            self,
+            [("group.id", group), ("auto.offset.reset", "earliest")],
+        )?
+        .build()?;
+        consumer.subscribe([topic]).await?;
+        let mut blocks = Vec::new();
+        while let Some((offset, message)) =
+            consumer.recv_timeout(Timeout::Duration(timeout)).await?
+        {
+            if let Some(data) = message.into_data() {
+                while let Some(block) = data.fetch_raw_block().await? {
+                    blocks.push(block);
+                }
+            }
+            consumer.commit(offset).await?;
+        }
+        consumer.unsubscribe().await;
+        Ok(blocks)
+    }
+}
+
+/// Scenarios to run and where to run them.
+#[derive(Debug, Clone)]
+pub struct Suite {
+    scenarios: Vec<Scenario>,
+    database: String,
+    seed: u64,
+}
+
+impl Default for Suite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Suite {
+    /// All scenarios, in databases `conformance_a` and `conformance_b`.
+    pub fn new() -> Self {
+        Self {
+            scenarios: scenario::all(),
+            database: "conformance".to_string(),
+            seed: 42,
+        }
+    }
+
+    /// Prefix of the databases, backends use `{name}_a` and `{name}_b`.
+    pub fn with_database(mut self, name: impl Into<String>) -> Self {
+        self.database = name.into();
+        self
+    }
+
+    /// Seed of random data, the same seed generates the same data on both backends.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Keep the scenarios matching `f`, eg. by [Scenario::category].
+    pub fn retain(mut self, f: impl FnMut(&Scenario) -> bool) -> Self {
+        self.scenarios.retain(f);
+        self
+    }
+
+    pub fn scenarios(&self) -> &[Scenario] {
+        &self.scenarios
+    }
+
+    /// Run the scenarios on backend `a` and then `b`, and compare their outcomes.
+    ///
+    /// It fails only if a database could not be prepared, failures of scenarios are in the
+    /// report.
+    pub async fn run<A: Backend, B: Backend>(&self, a: &A, b: &B) -> Result<Report, Error> {
+        let db_a = format!("{}_a", self.database);
+        let db_b = format!("{}_b", self.database);
+        prepare(a, &db_a).await?;
+        prepare(b, &db_b).await?;
+
+        let mut reports = Vec::with_capacity(self.scenarios.len());
+        for (i, scenario) in self.scenarios.iter().enumerate() {
+            let seed = self.seed.wrapping_add(i as u64);
+            let outcome_a = scenario.run(a, &db_a, seed).await;
+            let outcome_b = scenario.run(b, &db_b, seed).await;
+            let diffs = diff(&outcome_a, &outcome_b);
+            let note = if scenario.category() == Category::Error {
+                (outcome_a.is_ok() || outcome_b.is_ok()).then(|| "expected an error".to_string())
+            } else if !outcome_a.is_ok() && diffs.is_empty() {
+                Some("failed on both backends".to_string())
+            } else {
+                None
+            };
+            log::debug!("scenario {}: {} diffs", scenario.name(), diffs.len());
+            reports.push(ScenarioReport {
+                name: scenario.name().to_string(),
+                category: scenario.category(),
+                passed: diffs.is_empty() && note.is_none(),
+                note,
+                diffs,
+                a: outcome_a,
+                b: outcome_b,
+            });
+        }
+        Ok(Report::new([a.label(), b.label()], reports))
+    }
+}
+
+/// Recreate the database of a backend and use it.
+async fn prepare<B: Backend>(backend: &B, db: &str) -> Result<(), Error> {
+    for topic in scenario::topics(db) {
+        backend
+            .exec(&format!("drop topic if exists {topic}"))
+            .await?;
+    }
+    backend
+        .exec(&format!("drop database if exists {db}"))
+        .await?;
+    backend
+        .exec(&format!("create database {db} keep 36500"))
+        .await?;
+    backend.exec(&format!("use {db}")).await?;
+    Ok(())
+}
+
+/// Run all scenarios on connections of `dsn_a` and `dsn_b`, see [Suite] for options.
+pub async fn run(dsn_a: impl IntoDsn, dsn_b: impl IntoDsn) -> Result<Report, Error> {
+    let a = TaosBuilder::from_dsn(dsn_a)?.build()?;
+    let b = TaosBuilder::from_dsn(dsn_b)?.build()?;
+    Suite::new().run(&a, &b).await
+}
@@ -0,0 +1,267 @@
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use super::Category;
+
+/// What a backend returned for a scenario.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Affected rows and query results of each step.
+    Ok(JsonValue),
+    /// A step failed, backends agree if they fail at the same step with the same code.
+    Error {
+        step: String,
+        code: String,
+        message: String,
+    },
+}
+
+impl Outcome {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Outcome::Ok(_))
+    }
+
+    fn to_json(&self) -> JsonValue {
+        serde_json::to_value(self).unwrap_or(JsonValue::Null)
+    }
+}
+
+/// A difference of outcomes at a JSON pointer, eg. `/rows/2/1`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diff {
+    pub path: String,
+    pub a: JsonValue,
+    pub b: JsonValue,
+}
+
+/// Differences of two outcomes, empty if the backends agree.
+///
+/// Error messages are not compared, they differ by backend even for the same error.
+pub fn diff(a: &Outcome, b: &Outcome) -> Vec<Diff> {
+    let mut diffs = Vec::new();
+    match (a, b) {
+        (Outcome::Ok(a), Outcome::Ok(b)) => diff_json("", a, b, &mut diffs),
+        (
+            Outcome::Error {
+                step: step_a,
+                code: code_a,
+                ..
+            },
+            Outcome::Error {
+                step: step_b,
+                code: code_b,
+                ..
+            },
+        ) => {
+            if step_a != step_b {
+                diffs.push(Diff {
+                    path: "/error/step".to_string(),
+                    a: step_a.as_str().into(),
+                    b: step_b.as_str().into(),
+                });
+            }
+            if code_a != code_b {
+                diffs.push(Diff {
+                    path: "/error/code".to_string(),
+                    a: code_a.as_str().into(),
+                    b: code_b.as_str().into(),
+                });
+            }
+        }
+        _ => diffs.push(Diff {
+            path: String::new(),
+            a: a.to_json(),
+            b: b.to_json(),
+        }),
+    }
+    diffs
+}
+
+fn diff_json(path: &str, a: &JsonValue, b: &JsonValue, diffs: &mut Vec<Diff>) {
+    match (a, b) {
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+            for (key, va) in a {
+                let path = format!("{path}/{key}");
+                match b.get(key) {
+                    Some(vb) => diff_json(&path, va, vb, diffs),
+                    None => diffs.push(Diff {
+                        path,
+                        a: va.clone(),
+                        b: JsonValue::Null,
+                    }),
+                }
+            }
+            for (key, vb) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                diffs.push(Diff {
+                    path: format!("{path}/{key}"),
+                    a: JsonValue::Null,
+                    b: vb.clone(),
+                });
+            }
+        }
+        (JsonValue::Array(a), JsonValue::Array(b)) => {
+            if a.len() != b.len() {
+                diffs.push(Diff {
+                    path: format!("{path}/length"),
+                    a: a.len().into(),
+                    b: b.len().into(),
+                });
+            }
+            for (i, (va, vb)) in a.iter().zip(b).enumerate() {
+                diff_json(&format!("{path}/{i}"), va, vb, diffs);
+            }
+        }
+        _ if a != b => diffs.push(Diff {
+            path: path.to_string(),
+            a: a.clone(),
+            b: b.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Result of a scenario on both backends.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub category: Category,
+    pub passed: bool,
+    /// Why the scenario failed though the outcomes agree, eg. an error case succeeded on both.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub diffs: Vec<Diff>,
+    pub a: Outcome,
+    pub b: Outcome,
+}
+
+/// Results of a conformance run, serialized by [Report::to_json].
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Labels of backend `a` and `b`.
+    pub backends: [String; 2],
+    pub passed: usize,
+    pub failed: usize,
+    pub scenarios: Vec<ScenarioReport>,
+}
+
+impl Report {
+    pub(super) fn new(backends: [String; 2], scenarios: Vec<ScenarioReport>) -> Self {
+        let passed = scenarios.iter().filter(|s| s.passed).count();
+        Self {
+            backends,
+            passed,
+            failed: scenarios.len() - passed,
+            scenarios,
+        }
+    }
+
+    /// All scenarios passed.
+    pub fn is_passed(&self) -> bool {
+        self.failed == 0
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ScenarioReport> {
+        self.scenarios.iter().filter(|s| !s.passed)
+    }
+
+    /// The report as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report should always be serialized")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn error(step: &str, code: &str) -> Outcome {
+        Outcome::Error {
+            step: step.to_string(),
+            code: code.to_string(),
+            message: format!("{step} failed"),
+        }
+    }
+
+    #[test]
+    fn diff_outcomes() {
+        let a = Outcome::Ok(json!({"inserted": 3, "rows": [[0, null], [1, "a"]]}));
+        assert!(diff(&a, &a.clone()).is_empty());
+
+        let b = Outcome::Ok(json!({"inserted": 2, "rows": [[0, 1]], "deleted": 1}));
+        assert_eq!(
+            diff(&a, &b),
+            [
+                Diff {
+                    path: "/inserted".to_string(),
+                    a: json!(3),
+                    b: json!(2)
+                },
+                Diff {
+                    path: "/rows/length".to_string(),
+                    a: json!(2),
+                    b: json!(1)
+                },
+                Diff {
+                    path: "/rows/0/1".to_string(),
+                    a: json!(null),
+                    b: json!(1)
+                },
+                Diff {
+                    path: "/deleted".to_string(),
+                    a: json!(null),
+                    b: json!(1)
+                },
+            ]
+        );
+
+        // messages are not compared.
+        let mut other = error("insert", "0x2603");
+        if let Outcome::Error { message, .. } = &mut other {
+            *message = "another message".to_string();
+        }
+        assert!(diff(&error("insert", "0x2603"), &other).is_empty());
+        assert_eq!(
+            diff(&error("insert", "0x2603"), &error("query", "0x2662"))
+                .iter()
+                .map(|d| d.path.as_str())
+                .collect::<Vec<_>>(),
+            ["/error/step", "/error/code"]
+        );
+
+        let diffs = diff(&a, &error("insert", "0x2603"));
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].path, "");
+        assert_eq!(diffs[0].b["error"]["code"], "0x2603");
+    }
+
+    #[test]
+    fn report_json() {
+        let scenario = |name: &str, passed| ScenarioReport {
+            name: name.to_string(),
+            category: Category::Crud,
+            passed,
+            note: None,
+            diffs: Vec::new(),
+            a: Outcome::Ok(json!({"inserted": 1})),
+            b: Outcome::Ok(json!({"inserted": 1})),
+        };
+        let report = Report::new(
+            ["taos".to_string(), "ws".to_string()],
+            vec![scenario("crud_int", true), scenario("crud_bool", false)],
+        );
+        assert!(!report.is_passed());
+        assert_eq!(report.failures().count(), 1);
+
+        let json: JsonValue = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["backends"], json!(["taos", "ws"]));
+        assert_eq!(json["passed"], 1);
+        assert_eq!(json["failed"], 1);
+        assert_eq!(json["scenarios"][1]["name"], "crud_bool");
+        assert_eq!(json["scenarios"][1]["category"], "crud");
+        assert_eq!(json["scenarios"][1]["a"], json!({"ok": {"inserted": 1}}));
+        assert!(json["scenarios"][1].get("note").is_none());
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde_json::{json, Value as JsonValue};
+use taos_query::common::{ColumnView, Ty, Value};
+use taos_query::testing::{random_column, RandomOpts};
+
+use super::{Backend, Category, Outcome, Rows};
+use crate::Error;
+
+/// Rows of each table in typed scenarios.
+const ROWS: usize = 8;
+/// First timestamp of rows, `2022-01-01T00:00:00Z` in milliseconds.
+const BASE_TS: i64 = 1_640_995_200_000;
+/// Length of `BINARY` and `NCHAR` columns.
+const STR_LEN: usize = 16;
+/// A consumer stops when no message comes in this time.
+const TMQ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Column types of typed CRUD and stmt scenarios.
+const TYPES: [Ty; 14] = [
+    Ty::Bool,
+    Ty::TinyInt,
+    Ty::SmallInt,
+    Ty::Int,
+    Ty::BigInt,
+    Ty::UTinyInt,
+    Ty::USmallInt,
+    Ty::UInt,
+    Ty::UBigInt,
+    Ty::Float,
+    Ty::Double,
+    Ty::Timestamp,
+    Ty::VarChar,
+    Ty::NChar,
+];
+
+/// Statements expected to fail, after the setup statements succeed.
+const ERRORS: [(&str, &[&str], &str); 7] = [
+    ("error_syntax", &[], "selec 1"),
+    ("error_table_not_exist", &[], "select * from no_such_table"),
+    (
+        "error_database_not_exist",
+        &[],
+        "select * from conformance_no_such_database.t",
+    ),
+    (
+        "error_duplicate_table",
+        &["create table dup (ts timestamp, v int)"],
+        "create table dup (ts timestamp, v int)",
+    ),
+    (
+        "error_string_overflow",
+        &["create table overflow (ts timestamp, v binary(4))"],
+        "insert into overflow values (1640995200000, 'abcdefgh')",
+    ),
+    (
+        "error_invalid_timestamp",
+        &["create table bad_ts (ts timestamp, v int)"],
+        "insert into bad_ts values ('not a timestamp', 1)",
+    ),
+    (
+        "error_column_count",
+        &["create table few (ts timestamp, v int)"],
+        "insert into few values (1640995200000, 1, 2)",
+    ),
+];
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Crud(Ty),
+    Stmt(Ty),
+    NullTags {
+        stmt: bool,
+    },
+    JsonTags,
+    Error {
+        setup: &'static [&'static str],
+        sql: &'static str,
+    },
+    StmtError,
+    Tmq {
+        filter: bool,
+    },
+}
+
+/// A scenario of the suite, run on both backends with the same data.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    name: String,
+    category: Category,
+    kind: Kind,
+}
+
+impl Scenario {
+    fn new(name: impl Into<String>, kind: Kind) -> Self {
+        let category = match kind {
+            Kind::Crud(_) | Kind::NullTags { stmt: false } | Kind::JsonTags => Category::Crud,
+            Kind::Stmt(_) | Kind::NullTags { stmt: true } => Category::Stmt,
+            Kind::Error { .. } | Kind::StmtError => Category::Error,
+            Kind::Tmq { .. } => Category::Tmq,
+        };
+        Self {
+            name: name.into(),
+            category,
+            kind,
+        }
+    }
+
+    /// Name of the scenario, unique in the suite, eg. `crud_int`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn category(&self) -> Category {
+        self.category
+    }
+
+    /// Run in the current database of `backend`, `db` is only for names of topics.
+    pub(super) async fn run<B: Backend>(&self, backend: &B, db: &str, seed: u64) -> Outcome {
+        let res = match &self.kind {
+            Kind::Crud(ty) => crud(backend, *ty, seed).await,
+            Kind::Stmt(ty) => stmt(backend, *ty, seed).await,
+            Kind::NullTags { stmt } => null_tags(backend, *stmt).await,
+            Kind::JsonTags => json_tags(backend).await,
+            Kind::Error { setup, sql } => error(backend, setup, sql).await,
+            Kind::StmtError => stmt_error(backend).await,
+            Kind::Tmq { filter } => tmq(backend, &topic_of(db, *filter), *filter).await,
+        };
+        match res {
+            Ok(value) => Outcome::Ok(value),
+            Err(StepError { step, error }) => Outcome::Error {
+                step: step.to_string(),
+                code: error.code().to_string(),
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+/// All scenarios of the suite.
+pub(super) fn all() -> Vec<Scenario> {
+    let mut scenarios = Vec::new();
+    for ty in TYPES {
+        scenarios.push(Scenario::new(format!("crud_{}", ident(ty)), Kind::Crud(ty)));
+    }
+    scenarios.push(Scenario::new(
+        "crud_null_tags",
+        Kind::NullTags { stmt: false },
+    ));
+    scenarios.push(Scenario::new("crud_json_tags", Kind::JsonTags));
+    for ty in TYPES {
+        scenarios.push(Scenario::new(format!("stmt_{}", ident(ty)), Kind::Stmt(ty)));
+    }
+    scenarios.push(Scenario::new(
+        "stmt_null_tags",
+        Kind::NullTags { stmt: true },
+    ));
+    for (name, setup, sql) in ERRORS {
+        scenarios.push(Scenario::new(name, Kind::Error { setup, sql }));
+    }
+    scenarios.push(Scenario::new("error_stmt_bind", Kind::StmtError));
+    scenarios.push(Scenario::new("tmq_consume", Kind::Tmq { filter: false }));
+    scenarios.push(Scenario::new(
+        "tmq_consume_filtered",
+        Kind::Tmq { filter: true },
+    ));
+    scenarios
+}
+
+/// Topics of tmq scenarios, topics are not in a database so they're named by it.
+pub(super) fn topics(db: &str) -> [String; 2] {
+    [topic_of(db, false), topic_of(db, true)]
+}
+
+fn topic_of(db: &str, filter: bool) -> String {
+    if filter {
+        format!("{db}_tmq_filtered")
+    } else {
+        format!("{db}_tmq")
+    }
+}
+
+/// A failed step of a scenario.
+struct StepError {
+    step: &'static str,
+    error: Error,
+}
+
+trait StepExt<T> {
+    fn at(self, step: &'static str) -> Result<T, StepError>;
+}
+
+impl<T> StepExt<T> for Result<T, Error> {
+    fn at(self, step: &'static str) -> Result<T, StepError> {
+        self.map_err(|error| StepError { step, error })
+    }
+}
+
+/// Lower-case type name for table and scenario names, eg. `int_unsigned`.
+fn ident(ty: Ty) -> String {
+    ty.name().to_lowercase().replace(' ', "_")
+}
+
+fn sql_type(ty: Ty) -> String {
+    match ty {
+        Ty::VarChar | Ty::NChar => format!("{}({STR_LEN})", ty.name()),
+        _ => ty.name().to_string(),
+    }
+}
+
+fn timestamps() -> ColumnView {
+    ColumnView::from_millis_timestamp((0..ROWS as i64).map(|i| BASE_TS + i).collect())
+}
+
+/// Seeded values of a column, about a quarter of them are NULL.
+fn column(ty: Ty, seed: u64) -> ColumnView {
+    let opts = RandomOpts::new()
+        .with_null_probability(0.25)
+        .with_string_len(0..=STR_LEN);
+    random_column(ty, ROWS, seed, &opts)
+}
+
+fn rows_json(rows: &Rows) -> JsonValue {
+    let fields: Vec<_> = rows
+        .fields
+        .iter()
+        .map(|f| json!({"name": f.name(), "type": f.ty().name(), "length": f.bytes()}))
+        .collect();
+    let values: Vec<Vec<_>> = rows
+        .values
+        .iter()
+        .map(|row| row.iter().map(Value::to_json_value).collect())
+        .collect();
+    json!({ "fields": fields, "rows": values })
+}
+
+async fn crud<B: Backend>(backend: &B, ty: Ty, seed: u64) -> Result<JsonValue, StepError> {
+    let table = format!("crud_{}", ident(ty));
+    backend
+        .exec(&format!(
+            "create table {table} (ts timestamp, v {})",
+            sql_type(ty)
+        ))
+        .await
+        .at("create")?;
+
+    let values: Vec<_> = column(ty, seed).iter().map(|v| v.to_value()).collect();
+    let rows: Vec<_> = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| format!("({}, {})", BASE_TS + i as i64, v.to_sql_value()))
+        .collect();
+    let inserted = backend
+        .exec(&format!("insert into {table} values {}", rows.join(" ")))
+        .await
+        .at("insert")?;
+    let selected = backend
+        .query(&format!("select * from {table}"))
+        .await
+        .at("select")?;
+
+    // overwrite the first row with the value of the last one.
+    let updated = backend
+        .exec(&format!(
+            "insert into {table} values ({BASE_TS}, {})",
+            values[ROWS - 1].to_sql_value()
+        ))
+        .await
+        .at("update")?;
+    let after_update = backend
+        .query(&format!("select v from {table} where ts = {BASE_TS}"))
+        .await
+        .at("select_updated")?;
+
+    let deleted = backend
+        .exec(&format!(
+            "delete from {table} where ts >= {}",
+            BASE_TS + ROWS as i64 / 2
+        ))
+        .await
+        .at("delete")?;
+    let remaining = backend
+        .query(&format!("select count(*) from {table}"))
+        .await
+        .at("count")?;
+
+    Ok(json!({
+        "inserted": inserted,
+        "selected": rows_json(&selected),
+        "updated": updated,
+        "after_update": rows_json(&after_update),
+        "deleted": deleted,
+        "remaining": rows_json(&remaining),
+    }))
+}
+
+async fn stmt<B: Backend>(backend: &B, ty: Ty, seed: u64) -> Result<JsonValue, StepError> {
+    let table = format!("stmt_{}", ident(ty));
+    backend
+        .exec(&format!(
+            "create table {table} (ts timestamp, v {})",
+            sql_type(ty)
+        ))
+        .await
+        .at("create")?;
+
+    let params = [timestamps(), column(ty, seed)];
+    let affected = backend
+        .stmt_insert(&format!("insert into {table} values(?, ?)"), None, &params)
+        .at("bind")?;
+    let selected = backend
+        .query(&format!("select * from {table}"))
+        .await
+        .at("select")?;
+    Ok(json!({ "affected": affected, "selected": rows_json(&selected) }))
+}
+
+async fn null_tags<B: Backend>(backend: &B, stmt: bool) -> Result<JsonValue, StepError> {
+    let stable = if stmt {
+        "stmt_null_tags"
+    } else {
+        "crud_null_tags"
+    };
+    let tags: Vec<_> = TYPES
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| format!("t{i} {}", sql_type(*ty)))
+        .collect();
+    backend
+        .exec(&format!(
+            "create stable {stable} (ts timestamp, v int) tags({})",
+            tags.join(", ")
+        ))
+        .await
+        .at("create")?;
+
+    let table = format!("{stable}_t1");
+    let affected = if stmt {
+        let placeholders = vec!["?"; TYPES.len()].join(", ");
+        let nulls: Vec<_> = TYPES.iter().map(|ty| Value::Null(*ty)).collect();
+        let params = [
+            ColumnView::from_millis_timestamp(vec![BASE_TS]),
+            ColumnView::from_ints(vec![1]),
+        ];
+        backend
+            .stmt_insert(
+                &format!("insert into ? using {stable} tags({placeholders}) values(?, ?)"),
+                Some((&table, &nulls)),
+                &params,
+            )
+            .at("bind")?
+    } else {
+        let nulls = vec!["NULL"; TYPES.len()].join(", ");
+        backend
+            .exec(&format!(
+                "insert into {table} using {stable} tags({nulls}) values({BASE_TS}, 1)"
+            ))
+            .await
+            .at("insert")?
+    };
+    let selected = backend
+        .query(&format!("select * from {stable}"))
+        .await
+        .at("select")?;
+    Ok(json!({ "affected": affected, "selected": rows_json(&selected) }))
+}
+
+async fn json_tags<B: Backend>(backend: &B) -> Result<JsonValue, StepError> {
+    backend
+        .exec("create stable crud_json_tags (ts timestamp, v int) tags(j json)")
+        .await
+        .at("create")?;
+    let sql = format!(
+        "insert into crud_json_tags_t1 using crud_json_tags \
+         tags('{{\"k\":1,\"s\":\"v\",\"n\":null}}') values({BASE_TS}, 1) \
+         crud_json_tags_t2 using crud_json_tags tags(NULL) values({}, 2)",
+        BASE_TS + 1
+    );
+    let inserted = backend.exec(&sql).await.at("insert")?;
+    let selected = backend
+        .query("select tbname, j, v from crud_json_tags order by ts")
+        .await
+        .at("select")?;
+    let filtered = backend
+        .query("select v from crud_json_tags where j->'k' = 1")
+        .await
+        .at("select_filtered")?;
+    Ok(json!({
+        "inserted": inserted,
+        "selected": rows_json(&selected),
+        "filtered": rows_json(&filtered),
+    }))
+}
+
+async fn error<B: Backend>(backend: &B, setup: &[&str], sql: &str) -> Result<JsonValue, StepError> {
+    for sql in setup {
+        backend.exec(sql).await.at("setup")?;
+    }
+    let affected = backend.exec(sql).await.at("error")?;
+    Ok(json!({ "affected": affected }))
+}
+
+/// Bind more columns than the statement has.
+async fn stmt_error<B: Backend>(backend: &B) -> Result<JsonValue, StepError> {
+    backend
+        .exec("create table stmt_error (ts timestamp, v int)")
+        .await
+        .at("setup")?;
+    let params = [timestamps(), column(Ty::Int, 0), column(Ty::Int, 1)];
+    let affected = backend
+        .stmt_insert("insert into stmt_error values(?, ?)", None, &params)
+        .at("error")?;
+    Ok(json!({ "affected": affected }))
+}
+
+async fn tmq<B: Backend>(backend: &B, topic: &str, filter: bool) -> Result<JsonValue, StepError> {
+    let stable = if filter { "tmq_filtered" } else { "tmq" };
+    backend
+        .exec(&format!("drop topic if exists {topic}"))
+        .await
+        .at("setup")?;
+    backend
+        .exec(&format!(
+            "create stable {stable} (ts timestamp, v int, s binary({STR_LEN})) tags(g int)"
+        ))
+        .await
+        .at("setup")?;
+    let query = if filter {
+        format!("select ts, v from {stable} where v > 2")
+    } else {
+        format!("select * from {stable}")
+    };
+    backend
+        .exec(&format!("create topic {topic} as {query}"))
+        .await
+        .at("create_topic")?;
+
+    let values = |g: i64| -> String {
+        (0..4)
+            .map(|i| format!("({}, {}, 's{i}')", BASE_TS + i, g * 10 + i))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let produced = backend
+        .exec(&format!(
+            "insert into {stable}_t0 using {stable} tags(0) values {} \
+             {stable}_t1 using {stable} tags(1) values {}",
+            values(0),
+            values(1)
+        ))
+        .await
+        .at("produce")?;
+
+    let consumed = backend
+        .consume(topic, &format!("{topic}_group"), TMQ_TIMEOUT)
+        .await;
+    // drop the topic even if consuming failed.
+    let dropped = backend.exec(&format!("drop topic {topic}")).await;
+    let blocks = consumed.at("consume")?;
+    dropped.at("drop_topic")?;
+
+    // messages of tables come in any order, rows are grouped by table.
+    let mut tables: BTreeMap<String, (Vec<String>, Vec<Vec<JsonValue>>)> = BTreeMap::new();
+    for block in &blocks {
+        let table = tables
+            .entry(block.table_name().unwrap_or_default().to_string())
+            .or_default();
+        table.0 = block.field_names().iter().map(|s| s.to_string()).collect();
+        table.1.extend(
+            block
+                .to_values()
+                .iter()
+                .map(|row| row.iter().map(Value::to_json_value).collect::<Vec<_>>()),
+        );
+    }
+    let consumed: BTreeMap<_, _> = tables
+        .into_iter()
+        .map(|(name, (fields, mut rows))| {
+            rows.sort_by_key(|row| row.first().and_then(JsonValue::as_i64));
+            (name, json!({ "fields": fields, "rows": rows }))
+        })
+        .collect();
+    Ok(json!({ "produced": produced, "consumed": consumed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn scenarios() {
+        let scenarios = all();
+        assert!(scenarios.len() >= 20);
+        let names: HashSet<_> = scenarios.iter().map(Scenario::name).collect();
+        assert_eq!(names.len(), scenarios.len(), "names should be unique");
+        assert!(names.contains("crud_int_unsigned"));
+        assert!(names.contains("stmt_nchar"));
+        for category in [
+            Category::Crud,
+            Category::Stmt,
+            Category::Tmq,
+            Category::Error,
+        ] {
+            assert!(scenarios.iter().any(|s| s.category() == category));
+        }
+        assert_eq!(
+            topics("conformance_a"),
+            ["conformance_a_tmq", "conformance_a_tmq_filtered"]
+        );
+    }
+
+    #[test]
+    fn seeded_columns() {
+        for ty in TYPES {
+            let values =
+                |seed| -> Vec<_> { column(ty, seed).iter().map(|v| v.to_value()).collect() };
+            assert_eq!(values(7).len(), ROWS);
+            assert_eq!(values(7), values(7), "{ty:?} should be deterministic");
+        }
+    }
+}
@@ -1,5 +1,32 @@
 pub use taos_query::prelude::*;
 pub use taos_query;
+pub use taos_query::migrate;
+/// Table export and import through dumps, eg. to back up a table into a file:
+///
+/// ```rust,no_run
+/// # use taos::*;
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let taos = TaosBuilder::from_dsn("ws://localhost:6041/power")?.build()?;
+/// let file = std::io::BufWriter::new(std::fs::File::create("d0.dump")?);
+/// let mut sink = transfer::WriteSink::new(file);
+/// transfer::export_table(&taos, "d0", &mut sink).await?;
+///
+/// let mut source = transfer::ReadSource::new(std::fs::File::open("d0.dump")?);
+/// transfer::import_table_as(&taos, &mut source, Some("d0_restored")).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub use taos_query::transfer;
+
+/// Live blocks and result sets for leak detection, see [taos_query::diagnostics].
+#[cfg(feature = "leak-track")]
+pub mod diagnostics {
+    pub use taos_query::diagnostics::{
+        live_counts, live_objects, DiagnosticsReport, LiveCounts, LiveObject, ObjectKind,
+        BACKTRACE_ENV,
+    };
+}
 
 pub type TaosPool = taos_query::prelude::Pool<TaosBuilder>;
 
@@ -17,16 +44,80 @@ mod stmt;
 #[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
 pub use stmt::Stmt;
 
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+mod schemaless;
+
 #[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
 mod tmq;
 #[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
-pub use tmq::{Consumer, Data, MessageSet, Meta, Offset, TmqBuilder};
+pub use tmq::{Consumer, Data, MessageSet, Meta, Offset, ParallelConsumer, TmqBuilder};
+
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+pub use taos_query::tmq::DeserializeDataError;
 
 #[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
 mod query;
 #[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
 pub use query::*;
 
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+mod cluster;
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+pub use cluster::{ClusterAlive, ClusterStatus};
+
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+mod cache;
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+pub use cache::CachedResultSet;
+
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+mod script;
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+pub use script::{ScriptOptions, ScriptProgress, ScriptReport, ScriptStatement};
+
+#[cfg(all(feature = "ws", any(feature = "native", feature = "optin")))]
+pub mod scatter;
+
+#[cfg(all(
+    feature = "config",
+    feature = "ws",
+    any(feature = "native", feature = "optin")
+))]
+pub mod config;
+#[cfg(all(
+    feature = "config",
+    feature = "ws",
+    any(feature = "native", feature = "optin")
+))]
+pub use config::{ConfigError, ConnectConfig, ConsumerConfig};
+
+#[cfg(all(
+    feature = "pool",
+    feature = "ws",
+    any(feature = "native", feature = "optin")
+))]
+mod pool;
+#[cfg(all(
+    feature = "pool",
+    feature = "ws",
+    any(feature = "native", feature = "optin")
+))]
+pub use pool::{AsyncPool, PooledTaos, TaosManager};
+
+#[cfg(all(
+    feature = "bench",
+    feature = "ws",
+    any(feature = "native", feature = "optin")
+))]
+pub mod bench;
+
+#[cfg(all(
+    feature = "conformance",
+    feature = "ws",
+    any(feature = "native", feature = "optin")
+))]
+pub mod conformance;
+
 #[cfg(all(feature = "ws", not(any(feature = "native", feature = "optin"))))]
 pub use taos_ws::*;
 
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use deadpool::managed::{self, Metrics, PoolError, RecycleError, RecycleResult};
+use taos_query::prelude::{tokio, AsyncQueryable, ConnState, DsnError, RawError, TBuilder};
+use taos_query::tmq::Timeout;
+
+use crate::{Error, Taos, TaosBuilder};
+
+/// Creates connections of an [AsyncPool] and checks them on checkout.
+#[derive(Debug)]
+pub struct TaosManager(Arc<TaosBuilder>, bool);
+
+impl TaosManager {
+    /// Fail if the cluster is required to be healthy but isn't fully available.
+    async fn check_cluster(&self, taos: &Taos) -> Result<(), Error> {
+        if self.1 {
+            let alive = taos.cluster_alive().await?;
+            if !alive.is_fully() {
+                return Err(RawError::from_string(format!("cluster is {alive}")).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl managed::Manager for TaosManager {
+    type Type = Taos;
+    type Error = Error;
+
+    async fn create(&self) -> Result<Taos, Error> {
+        let builder = self.0.clone();
+        let taos = tokio::task::spawn_blocking(move || builder.build())
+            .await
+            .map_err(RawError::from_any)??;
+        self.check_cluster(&taos).await?;
+        Ok(taos)
+    }
+
+    async fn recycle(&self, taos: &mut Taos, _: &Metrics) -> RecycleResult<Error> {
+        if let ConnState::Closed { reason } = taos.state() {
+            return Err(RecycleError::message(format!(
+                "connection closed: {reason}"
+            )));
+        }
+        AsyncQueryable::server_version(taos).await?;
+        self.check_cluster(taos).await?;
+        Ok(())
+    }
+}
+
+/// A connection checked out of an [AsyncPool], it derefs to [Taos] and goes back to the pool
+/// when it's dropped.
+pub type PooledTaos = managed::Object<TaosManager>;
+
+/// Async connection pool of [Taos], with feature `pool`.
+///
+/// Connections are created on demand up to the max size, and checked by
+/// `select server_version()` when they are checked out again. Broken ones, eg. websocket
+/// connections closed by the server, are dropped and replaced transparently. Idle connections
+/// are closed when the last clone of the pool is dropped.
+///
+/// Options are DSN parameters:
+///
+/// - `pool.max_size`: max number of connections, 4 times the CPU cores by default.
+/// - `pool.timeout`: max time to wait for a connection, eg. `5s` or `500ms`, no limit by
+///   default.
+/// - `pool.require_healthy_cluster`: `true` to check connections by [Taos::cluster_alive] too,
+///   they fail unless the cluster is fully available, `false` by default.
+///
+/// ```rust,no_run
+/// # use taos::*;
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let pool = TaosBuilder::from_dsn("ws://localhost:6041?pool.max_size=16&pool.timeout=5s")?
+///     .async_pool()?;
+/// let taos = pool.get().await?;
+/// taos.exec("create database if not exists power").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncPool(managed::Pool<TaosManager>);
+
+impl AsyncPool {
+    /// DSN parameter of the max number of connections.
+    pub const MAX_SIZE: &'static str = "pool.max_size";
+    /// DSN parameter of the timeout to wait for a connection.
+    pub const TIMEOUT: &'static str = "pool.timeout";
+    /// DSN parameter to require the cluster fully available on checkout.
+    pub const REQUIRE_HEALTHY_CLUSTER: &'static str = "pool.require_healthy_cluster";
+
+    pub(crate) fn new(builder: TaosBuilder) -> Result<Self, Error> {
+        let params = &builder.1.params;
+        let max_size = params
+            .get(Self::MAX_SIZE)
+            .map(|value| {
+                value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| {
+                        DsnError::InvalidParam(Self::MAX_SIZE.to_string(), value.to_string())
+                    })
+            })
+            .transpose()?;
+        let timeout = match params
+            .get(Self::TIMEOUT)
+            .map(|value| Timeout::from_param(Self::TIMEOUT, value))
+            .transpose()?
+        {
+            Some(Timeout::Duration(timeout)) => Some(timeout),
+            Some(Timeout::Never | Timeout::None) | None => None,
+        };
+        let healthy = match params
+            .get(Self::REQUIRE_HEALTHY_CLUSTER)
+            .map(String::as_str)
+        {
+            Some("true" | "1") => true,
+            Some("false" | "0") | None => false,
+            Some(value) => {
+                return Err(DsnError::InvalidParam(
+                    Self::REQUIRE_HEALTHY_CLUSTER.to_string(),
+                    value.to_string(),
+                )
+                .into())
+            }
+        };
+
+        let mut pool = managed::Pool::builder(TaosManager(Arc::new(builder), healthy))
+            .runtime(deadpool::Runtime::Tokio1)
+            .wait_timeout(timeout);
+        if let Some(max_size) = max_size {
+            pool = pool.max_size(max_size);
+        }
+        let pool = pool.build().map_err(RawError::from_any)?;
+        Ok(Self(pool))
+    }
+
+    /// Check out a connection, an idle one that is still alive or a new one.
+    pub async fn get(&self) -> Result<PooledTaos, Error> {
+        self.0.get().await.map_err(|err| match err {
+            PoolError::Backend(err) => err,
+            err => RawError::from_string(err.to_string()).into(),
+        })
+    }
+
+    /// Max number of connections.
+    pub fn max_size(&self) -> usize {
+        self.0.status().max_size
+    }
+
+    /// Max time to wait for a connection in [AsyncPool::get], `None` if there's no limit.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.0.timeouts().wait
+    }
+
+    /// Number of connections created and idle ones.
+    pub fn size(&self) -> (usize, usize) {
+        let status = self.0.status();
+        (status.size, status.available)
+    }
+
+    /// Close the pool and idle connections, checked out ones are closed when they are dropped.
+    pub fn close(&self) {
+        self.0.close()
+    }
+}
+
+impl TaosBuilder {
+    /// Build an [AsyncPool] of connections of this builder, configured by the `pool.*`
+    /// parameters of the DSN. No connection is opened until the first [AsyncPool::get].
+    pub fn async_pool(self) -> Result<AsyncPool, Error> {
+        AsyncPool::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use taos_query::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn pool_params() -> anyhow::Result<()> {
+        let pool = TaosBuilder::from_dsn("ws://localhost:6041?pool.max_size=4&pool.timeout=5s")?
+            .async_pool()?;
+        assert_eq!(pool.max_size(), 4);
+        assert_eq!(pool.timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(pool.size(), (0, 0));
+
+        let pool = TaosBuilder::from_dsn("ws://localhost:6041?pool.timeout=never")?.async_pool()?;
+        assert_eq!(pool.timeout(), None);
+        let dsn = "ws://localhost:6041?pool.require_healthy_cluster=true";
+        assert!(TaosBuilder::from_dsn(dsn)?.async_pool().is_ok());
+
+        for dsn in [
+            "ws://localhost:6041?pool.max_size=0",
+            "ws://localhost:6041?pool.max_size=many",
+            "ws://localhost:6041?pool.timeout=5x",
+            "ws://localhost:6041?pool.require_healthy_cluster=maybe",
+        ] {
+            assert!(TaosBuilder::from_dsn(dsn)?.async_pool().is_err(), "{dsn}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_pool_native() -> anyhow::Result<()> {
+        let dsn = std::env::var("TEST_DSN").unwrap_or("taos://".to_string());
+        async_pool_test(&dsn).await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_pool_ws() -> anyhow::Result<()> {
+        async_pool_test("ws://").await
+    }
+
+    /// Run more tasks than connections, connections are reused and capped by the max size.
+    async fn async_pool_test(dsn: &str) -> anyhow::Result<()> {
+        let mut dsn = Dsn::from_str(dsn)?;
+        dsn.set(AsyncPool::MAX_SIZE, "2");
+        dsn.set(AsyncPool::TIMEOUT, "10s");
+        let pool = TaosBuilder::from_dsn(&dsn)?.async_pool()?;
+
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let taos = pool.get().await?;
+                    let v: Option<i32> = taos.query_one(format!("select {i}")).await?;
+                    Ok::<_, Error>(v)
+                })
+            })
+            .collect();
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await??, Some(i as i32));
+        }
+        let (size, idle) = pool.size();
+        assert!(size <= 2 && idle == size);
+
+        let taos = pool.get().await?;
+        assert!(taos.server_version().await.is_ok());
+        assert_eq!(pool.size(), (size, idle - 1));
+        drop(taos);
+
+        pool.close();
+        assert!(pool.get().await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+//! Run the same query over many connections concurrently, eg. one per tenant database.
+//!
+//! ```rust,no_run
+//! # use taos::*;
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let tenants = ["t1", "t2", "t3"]
+//!     .iter()
+//!     .map(|db| TaosBuilder::from_dsn(format!("ws://localhost:6041/{db}"))?.build())
+//!     .collect::<Result<Vec<_>, _>>()?;
+//! for (index, result) in scatter::query_all(&tenants, "select count(*) from meters", 2).await {
+//!     println!("tenant {index}: {:?}", result.map(|blocks| blocks.len()));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::time::Duration;
+
+use futures::stream::{FuturesUnordered, StreamExt, TryStreamExt};
+use taos_query::common::{ColumnView, RawBlock};
+use taos_query::prelude::tokio;
+
+use crate::{AsyncFetchable, AsyncQueryable, Error, Taos};
+
+/// What to do with the other queries when a query fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// Cancel queries in flight and not started yet, results of them are left out.
+    FailFast,
+    /// Run all queries and collect their errors.
+    #[default]
+    CollectAll,
+}
+
+/// Options of [query_all_with] and [query_merged].
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use taos::scatter::{OnError, ScatterOptions};
+/// let options = ScatterOptions::new(8)
+///     .timeout(Duration::from_secs(5))
+///     .on_error(OnError::FailFast);
+/// assert_eq!(options.concurrency(), 8);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScatterOptions {
+    concurrency: usize,
+    timeout: Option<Duration>,
+    on_error: OnError,
+}
+
+impl ScatterOptions {
+    /// Run at most `concurrency` queries at a time, at least one.
+    pub const fn new(concurrency: usize) -> Self {
+        Self {
+            concurrency: if concurrency == 0 { 1 } else { concurrency },
+            timeout: None,
+            on_error: OnError::CollectAll,
+        }
+    }
+
+    pub const fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Fail a query of a connection with [Error::Timeout] if it's not fetched in `timeout`,
+    /// counted from the time it starts, not the time waiting for other queries.
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub const fn on_error(mut self, on_error: OnError) -> Self {
+        self.on_error = on_error;
+        self
+    }
+}
+
+/// Blocks of all connections by [query_merged], with errors of the failed ones.
+#[derive(Debug)]
+pub struct Merged {
+    /// Blocks in the order of connections, tagged with the index of their connection.
+    pub blocks: Vec<RawBlock>,
+    /// Errors by the index of connections, in order.
+    pub errors: Vec<(usize, Error)>,
+}
+
+/// Query `sql` over `connections` with at most `concurrency` queries at a time, and fetch all
+/// blocks. Results are by the index of connections in the input order, errors are collected
+/// without affecting other connections.
+pub async fn query_all(
+    connections: &[Taos],
+    sql: &str,
+    concurrency: usize,
+) -> Vec<(usize, Result<Vec<RawBlock>, Error>)> {
+    query_all_with(connections, sql, ScatterOptions::new(concurrency)).await
+}
+
+/// Query `sql` over `connections` by `options`, see [query_all].
+///
+/// With [OnError::FailFast], results end with the first failure, connections without a result
+/// are left out, so there's a result of each connection only if all succeed.
+pub async fn query_all_with(
+    connections: &[Taos],
+    sql: &str,
+    options: ScatterOptions,
+) -> Vec<(usize, Result<Vec<RawBlock>, Error>)> {
+    let slots = tokio::sync::Semaphore::new(options.concurrency);
+    let mut queries: FuturesUnordered<_> = connections
+        .iter()
+        .enumerate()
+        .map(|(index, taos)| {
+            let slots = &slots;
+            async move {
+                let _slot = slots.acquire().await.expect("slots are never closed");
+                let blocks = fetch_all(taos, sql);
+                let result = match options.timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, blocks)
+                        .await
+                        .unwrap_or(Err(Error::Timeout(timeout))),
+                    None => blocks.await,
+                };
+                (index, result)
+            }
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(connections.len());
+    while let Some((index, result)) = queries.next().await {
+        let failed = result.is_err();
+        results.push((index, result));
+        if failed && options.on_error == OnError::FailFast {
+            log::trace!("query of connection {index} failed, cancel the others");
+            break;
+        }
+    }
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results
+}
+
+/// Query `sql` over `connections` like [query_all_with], and tag rows of each connection with
+/// its index in an `INT` column named `source`, appended to the columns of the result.
+///
+/// Results that already have a column named `source` are errors of their connections.
+pub async fn query_merged(
+    connections: &[Taos],
+    sql: &str,
+    options: ScatterOptions,
+    source: &str,
+) -> Merged {
+    let mut merged = Merged {
+        blocks: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (index, result) in query_all_with(connections, sql, options).await {
+        match result {
+            Ok(blocks) => {
+                let tagged: Result<Vec<_>, _> = blocks
+                    .iter()
+                    .map(|block| {
+                        let tags = ColumnView::from_ints(vec![index as i32; block.nrows()]);
+                        block.append_column(source, tags)
+                    })
+                    .collect();
+                match tagged {
+                    Ok(tagged) => merged.blocks.extend(tagged),
+                    Err(err) => merged.errors.push((index, anyhow::Error::from(err).into())),
+                }
+            }
+            Err(err) => merged.errors.push((index, err)),
+        }
+    }
+    merged
+}
+
+async fn fetch_all(taos: &Taos, sql: &str) -> Result<Vec<RawBlock>, Error> {
+    let mut rs = taos.query(sql).await?;
+    rs.blocks().try_collect().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TBuilder, TaosBuilder};
+
+    const DATABASES: [&str; 3] = ["scatter_0", "scatter_1", "scatter_2"];
+
+    /// Three databases of `tb`, database `i` has `i + 1` rows.
+    async fn tenants() -> anyhow::Result<Vec<Taos>> {
+        let taos = TaosBuilder::from_dsn("ws://localhost:6041")?.build()?;
+        for (i, db) in DATABASES.iter().enumerate() {
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db}"),
+                format!("create table {db}.tb(ts timestamp, v int)"),
+            ])
+            .await?;
+            for row in 0..=i {
+                taos.exec(format!(
+                    "insert into {db}.tb values({}, {i})",
+                    1704067200000i64 + row as i64
+                ))
+                .await?;
+            }
+        }
+        DATABASES
+            .iter()
+            .map(|db| Ok(TaosBuilder::from_dsn(format!("ws://localhost:6041/{db}"))?.build()?))
+            .collect()
+    }
+
+    async fn drop_tenants(connections: &[Taos]) -> anyhow::Result<()> {
+        for db in DATABASES {
+            connections[0].exec(format!("drop database {db}")).await?;
+        }
+        Ok(())
+    }
+
+    fn rows(blocks: &[RawBlock]) -> usize {
+        blocks.iter().map(RawBlock::nrows).sum()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn scatter_in_order() -> anyhow::Result<()> {
+        let connections = tenants().await?;
+        let results = query_all(&connections, "select * from tb", 2).await;
+        let indices: Vec<_> = results.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, [0, 1, 2]);
+        for (index, result) in results {
+            assert_eq!(rows(&result?), index + 1);
+        }
+
+        let merged = query_merged(
+            &connections,
+            "select * from tb",
+            ScatterOptions::new(3),
+            "tenant",
+        )
+        .await;
+        assert!(merged.errors.is_empty());
+        assert_eq!(rows(&merged.blocks), 6);
+        let tenants: Vec<i32> = merged
+            .blocks
+            .iter()
+            .flat_map(|block| block.column_vec::<i32>("tenant").unwrap())
+            .map(Option::unwrap)
+            .collect();
+        assert_eq!(tenants, [0, 1, 1, 2, 2, 2]);
+        drop_tenants(&connections).await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn scatter_errors_isolated() -> anyhow::Result<()> {
+        let connections = tenants().await?;
+        connections[1].exec("drop table tb").await?;
+
+        let results = query_all(&connections, "select * from tb", 3).await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(rows(results[0].1.as_ref().unwrap()), 1);
+        assert!(results[1].1.is_err());
+        assert_eq!(rows(results[2].1.as_ref().unwrap()), 3);
+
+        let merged = query_merged(
+            &connections,
+            "select * from tb",
+            ScatterOptions::new(3),
+            "tenant",
+        )
+        .await;
+        assert_eq!(rows(&merged.blocks), 4);
+        assert_eq!(merged.errors.len(), 1);
+        assert_eq!(merged.errors[0].0, 1);
+
+        // Queries after the failure are not run with one at a time.
+        let options = ScatterOptions::new(1).on_error(OnError::FailFast);
+        let results = query_all_with(&connections, "select * from tb", options).await;
+        assert_eq!(results.len(), 2);
+        assert!(results[1].1.is_err());
+
+        let options = ScatterOptions::new(3).timeout(Duration::from_nanos(1));
+        let results = query_all_with(&connections, "select * from tb", options).await;
+        assert!(results
+            .iter()
+            .all(|(_, result)| matches!(result, Err(Error::Timeout(_)))));
+        drop_tenants(&connections).await
+    }
+}
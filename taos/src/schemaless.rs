@@ -0,0 +1,203 @@
+use taos_query::common::{SmlData, SmlError};
+
+use crate::{Error, Taos, TaosInner};
+
+impl Taos {
+    /// Write schemaless data of line protocol or OpenTSDB, tables are created or altered as
+    /// needed.
+    ///
+    /// Malformed lines are rejected with their numbers when [SmlData] is built, errors of valid
+    /// lines, eg. a conflicting column type, are returned by the server. NaN and infinite floats
+    /// are converted by [WriteOptions::non_finite](taos_query::common::WriteOptions::non_finite)
+    /// of the builder first, see [SmlData::finite], nothing is written if no line is left.
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let taos = TaosBuilder::from_dsn("taos://localhost:6030/power")?.build()?;
+    /// let data = SmlData::builder(SchemalessProtocol::Line)
+    ///     .precision(SchemalessPrecision::Milliseconds)
+    ///     .line("meters,location=SF,groupid=2 current=10.3,voltage=219i32 1648432611249")
+    ///     .build()?;
+    /// taos.put(&data).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn put(&self, data: &SmlData) -> Result<(), Error> {
+        let finite = match data.finite(self.3.non_finite()) {
+            Err(SmlError::Empty) => return Ok(()),
+            finite => finite?,
+        };
+        let data = finite.as_ref().unwrap_or(data);
+        self.guard(async {
+            match &self.0 {
+                TaosInner::Native(taos) => taos.put(data).map_err(Into::into),
+                TaosInner::Ws(taos) => taos.put(data).await.map_err(Into::into),
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use taos_query::common::SmlError;
+
+    use crate::TaosBuilder;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn put_native() -> anyhow::Result<()> {
+        let dsn = std::env::var("TEST_DSN").unwrap_or("taos://".to_string());
+        put_test(&dsn, "put_native").await
+    }
+
+    #[cfg(feature = "ws")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn put_ws() -> anyhow::Result<()> {
+        put_test("ws://", "put_ws").await
+    }
+
+    /// Write line protocol of two precisions, and read the auto-created subtable back.
+    async fn put_test(dsn: &str, db: &str) -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+
+        let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+        taos.exec_many([
+            format!("drop database if exists {db}"),
+            format!("create database {db} precision 'us'"),
+            format!("use {db}"),
+        ])
+        .await?;
+
+        let line = "meters,location=SF,groupid=2 current=10.3,voltage=219i32 1648432611249";
+        let millis = SmlData::builder(SchemalessProtocol::Line)
+            .precision(SchemalessPrecision::Milliseconds)
+            .line(line)
+            .build()?;
+        taos.put(&millis).await?;
+        let line = "meters,location=SF,groupid=2 current=12.6,voltage=218i32 1648432611250500";
+        let micros = SmlData::builder(SchemalessProtocol::Line)
+            .precision(Precision::Microsecond)
+            .ttl(10)
+            .line(line)
+            .build()?;
+        taos.put(&micros).await?;
+
+        let tables: Vec<String> = taos
+            .query("select distinct tbname from meters")
+            .await?
+            .deserialize::<String>()
+            .try_collect()
+            .await?;
+        assert_eq!(tables.len(), 1);
+        let rows: Vec<(i64, f64, i32)> = taos
+            .query(format!(
+                "select cast(ts as bigint), current, voltage from `{}` order by ts",
+                tables[0]
+            ))
+            .await?
+            .deserialize::<(i64, f64, i32)>()
+            .try_collect()
+            .await?;
+        assert_eq!(
+            rows,
+            [(1648432611249000, 10.3, 219), (1648432611250500, 12.6, 218)]
+        );
+
+        let err = SmlData::builder(SchemalessProtocol::Line)
+            .lines([line, "meters,location=SF current=1.0 1648432611251x"])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, SmlError::Invalid { line: 2, .. }));
+        let conflict = SmlData::builder(SchemalessProtocol::Line)
+            .line("meters,location=SF,groupid=2 current=\"high\" 1648432611252000")
+            .precision(SchemalessPrecision::Microseconds)
+            .build()?;
+        assert!(taos.put(&conflict).await.is_err());
+
+        taos.exec(format!("drop database {db}")).await?;
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn put_non_finite_native() -> anyhow::Result<()> {
+        let dsn = std::env::var("TEST_DSN").unwrap_or("taos://".to_string());
+        put_non_finite_test(&dsn, "put_non_finite_native").await
+    }
+
+    #[cfg(feature = "ws")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn put_non_finite_ws() -> anyhow::Result<()> {
+        put_non_finite_test("ws://", "put_non_finite_ws").await
+    }
+
+    /// Write NaN and infinities by each policy of the write options.
+    async fn put_non_finite_test(dsn: &str, db: &str) -> anyhow::Result<()> {
+        use taos_query::common::{NonFinitePolicy, WriteOptions};
+        use taos_query::prelude::*;
+
+        let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+        taos.exec_many([
+            format!("drop database if exists {db}"),
+            format!("create database {db}"),
+        ])
+        .await?;
+
+        let data = SmlData::builder(SchemalessProtocol::Line)
+            .precision(SchemalessPrecision::Milliseconds)
+            .lines([
+                "meters,location=SF current=1.5,voltage=219i32 1648432611249",
+                "meters,location=SF current=inf,voltage=220i32 1648432611250",
+                "meters,location=SF current=-inf,voltage=221i32 1648432611251",
+                "meters,location=SF current=nan,voltage=222i32 1648432611252",
+            ])
+            .build()?;
+        let mut rows = Vec::new();
+        for policy in [
+            NonFinitePolicy::Error,
+            NonFinitePolicy::Null,
+            NonFinitePolicy::Clamp,
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?
+                .with_write_options(WriteOptions::new().on_non_finite(policy))
+                .build()?;
+            taos.exec(format!("use {db}")).await?;
+            match taos.put(&data).await {
+                Ok(()) => {
+                    let values: Vec<(Option<f64>, i32)> = taos
+                        .query("select current, voltage from meters order by ts")
+                        .await?
+                        .deserialize::<(Option<f64>, i32)>()
+                        .try_collect()
+                        .await?;
+                    rows.push(values);
+                }
+                Err(err) => {
+                    assert_eq!(policy, NonFinitePolicy::Error);
+                    assert!(
+                        err.to_string()
+                            .contains("schemaless line 2 is invalid, field `current` is inf"),
+                        "{err}"
+                    );
+                }
+            }
+        }
+        assert_eq!(
+            rows[0],
+            [(Some(1.5), 219), (None, 220), (None, 221), (None, 222)]
+        );
+        assert_eq!(
+            rows[1],
+            [
+                (Some(1.5), 219),
+                (Some(f64::MAX), 220),
+                (Some(f64::MIN), 221),
+                (None, 222)
+            ]
+        );
+
+        taos.exec(format!("drop database {db}")).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use taos_query::prelude::AsyncFetchable;
+use taos_query::util::{generate_req_id, Shutdown};
+
+use crate::{Error, Taos};
+
+/// Progress of [Taos::exec_script], reported after each statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptProgress {
+    /// Index of the statement in the script.
+    pub index: usize,
+    /// Number of statements in the script.
+    pub total: usize,
+    pub last_duration: Duration,
+    /// Rows affected by the statement, `None` if it failed.
+    pub affected: Option<usize>,
+}
+
+/// Options of [Taos::exec_script].
+///
+/// By default the script stops at the first failed statement, like
+/// [exec_many](crate::AsyncQueryable::exec_many).
+pub struct ScriptOptions {
+    stop_on_error: bool,
+    start_index: usize,
+    progress: Option<Box<dyn Fn(ScriptProgress) + Send + Sync>>,
+    cancel: Shutdown,
+}
+
+impl Default for ScriptOptions {
+    fn default() -> Self {
+        Self {
+            stop_on_error: true,
+            start_index: 0,
+            progress: None,
+            cancel: Shutdown::new(),
+        }
+    }
+}
+
+impl fmt::Debug for ScriptOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptOptions")
+            .field("stop_on_error", &self.stop_on_error)
+            .field("start_index", &self.start_index)
+            .field("progress", &self.progress.is_some())
+            .field("cancelled", &self.cancel.is_triggered())
+            .finish()
+    }
+}
+
+impl ScriptOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop at the first failed statement, or run all the statements and report the failed
+    /// ones with `false`.
+    pub fn stop_on_error(mut self, stop: bool) -> Self {
+        self.stop_on_error = stop;
+        self
+    }
+
+    /// Skip statements before `index`, eg. [ScriptReport::resume_index] of a failed run.
+    pub fn start_index(mut self, index: usize) -> Self {
+        self.start_index = index;
+        self
+    }
+
+    /// Call `f` after each statement.
+    pub fn on_progress(mut self, f: impl Fn(ScriptProgress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Cancel the script when `cancel` is triggered, eg. from a signal handler.
+    pub fn cancel_on(mut self, cancel: Shutdown) -> Self {
+        self.cancel = cancel;
+        self
+    }
+}
+
+/// A statement run by [Taos::exec_script].
+#[derive(Debug)]
+pub struct ScriptStatement {
+    pub index: usize,
+    pub sql: String,
+    pub duration: Duration,
+    /// Rows affected by the statement, or [Error::Cancelled] if it's cancelled in flight.
+    pub result: Result<usize, Error>,
+}
+
+/// Outcome of [Taos::exec_script].
+#[derive(Debug)]
+pub struct ScriptReport {
+    /// Number of statements in the script.
+    pub total: usize,
+    /// Statements run, in order.
+    pub statements: Vec<ScriptStatement>,
+    /// The script is cancelled before all the statements are run.
+    pub cancelled: bool,
+    /// Index of the first statement to run to continue the script by
+    /// [ScriptOptions::start_index]: the failed one if the script stopped on error, the
+    /// cancelled one in flight, or the next one after the last statement run.
+    pub resume_index: usize,
+}
+
+impl ScriptReport {
+    /// All the statements from the start index are run, failed ones included unless the
+    /// script stopped on error.
+    pub fn is_complete(&self) -> bool {
+        self.resume_index >= self.total
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &ScriptStatement> {
+        self.statements.iter().filter(|stmt| stmt.result.is_err())
+    }
+
+    /// Rows affected by all the statements succeeded.
+    pub fn affected_rows(&self) -> usize {
+        self.statements
+            .iter()
+            .filter_map(|stmt| stmt.result.as_ref().ok())
+            .sum()
+    }
+}
+
+impl Taos {
+    /// Execute `statements` one by one with progress reports and cancellation, eg. a migration
+    /// script of thousands of statements.
+    ///
+    /// Cancellation is checked between statements. A statement in flight when it's triggered
+    /// is abandoned and killed on the server by [Taos::kill_query] if it's still running, it
+    /// may or may not be applied then. Failed statements, cancelled ones included, are in the
+    /// report rather than an error, so a run could continue by
+    /// [ScriptReport::resume_index].
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # use taos::taos_query::util::Shutdown;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let taos = TaosBuilder::from_dsn("ws://localhost:6041")?.build()?;
+    /// let script = std::fs::read_to_string("migrate.sql")?;
+    /// let statements: Vec<&str> = script.split(';').filter(|s| !s.trim().is_empty()).collect();
+    /// let cancel = Shutdown::new();
+    /// let options = ScriptOptions::new()
+    ///     .on_progress(|p| println!("{}/{} in {:?}", p.index + 1, p.total, p.last_duration))
+    ///     .cancel_on(cancel.clone());
+    /// let report = taos.exec_script(&statements, options).await;
+    /// for stmt in report.failed() {
+    ///     eprintln!("#{} `{}`: {:?}", stmt.index, stmt.sql, stmt.result);
+    /// }
+    /// if !report.is_complete() {
+    ///     println!("continue from {} by ScriptOptions::start_index", report.resume_index);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn exec_script<T, I>(&self, statements: I, options: ScriptOptions) -> ScriptReport
+    where
+        T: AsRef<str>,
+        I: IntoIterator<Item = T>,
+    {
+        let statements: Vec<T> = statements.into_iter().collect();
+        let total = statements.len();
+        let mut report = ScriptReport {
+            total,
+            statements: Vec::new(),
+            cancelled: false,
+            resume_index: options.start_index,
+        };
+        for (index, sql) in statements.iter().enumerate().skip(options.start_index) {
+            if options.cancel.is_triggered() {
+                report.cancelled = true;
+                break;
+            }
+            let sql = sql.as_ref();
+            let started = Instant::now();
+            let result = self.exec_cancellable(sql, &options.cancel).await;
+            let duration = started.elapsed();
+            let failed = result.is_err();
+            report.cancelled = matches!(result, Err(Error::Cancelled));
+            if let Some(progress) = &options.progress {
+                progress(ScriptProgress {
+                    index,
+                    total,
+                    last_duration: duration,
+                    affected: result.as_ref().ok().copied(),
+                });
+            }
+            if let Err(err) = &result {
+                log::warn!("statement {index} of the script failed: {err}");
+            }
+            report.statements.push(ScriptStatement {
+                index,
+                sql: sql.to_string(),
+                duration,
+                result,
+            });
+            if report.cancelled || (failed && options.stop_on_error) {
+                report.resume_index = index;
+                return report;
+            }
+            report.resume_index = index + 1;
+        }
+        report
+    }
+
+    /// Execute `sql` until `cancel` is triggered, the statement is killed on the server then.
+    async fn exec_cancellable(&self, sql: &str, cancel: &Shutdown) -> Result<usize, Error> {
+        let req_id = generate_req_id();
+        let exec = async {
+            let rs = self.query_with_req_id(sql, req_id).await?;
+            Ok(rs.affected_rows() as usize)
+        };
+        if let Some(res) = cancel.guard(exec).await {
+            return res;
+        }
+        match self.kill_query(req_id).await {
+            Ok(killed) => log::debug!("cancelled statement {req_id:#x}, killed: {killed}"),
+            Err(err) => log::warn!("failed to kill cancelled statement {req_id:#x}: {err}"),
+        }
+        Err(Error::Cancelled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use futures::{SinkExt, StreamExt};
+    use taos_query::prelude::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+    use crate::TaosBuilder;
+
+    /// A server of statements affecting a row each, counted in `executed`. Statements with
+    /// `bad` fail, and `slow` ones never return.
+    async fn mock_server(executed: Arc<AtomicUsize>) -> anyhow::Result<SocketAddr> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let executed = executed.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                        let reply = match req["action"].as_str().unwrap() {
+                            "version" => r#"{"code":0,"message":"","action":"version","req_id":0,"version":"3.0.0.0"}"#.to_string(),
+                            "conn" => r#"{"code":0,"message":"","action":"conn","req_id":0}"#.to_string(),
+                            "query" => match req["args"]["sql"].as_str().unwrap() {
+                                "slow" => continue,
+                                sql if sql.contains("bad") || sql == "show queries" => format!(r#"{{"code":9728,"message":"syntax error","action":"query","req_id":{req_id}}}"#),
+                                _ => {
+                                    executed.fetch_add(1, Ordering::SeqCst);
+                                    format!(r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":0,"is_update":true,"affected_rows":1}}"#)
+                                }
+                            },
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+        Ok(addr)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancel_and_resume() -> anyhow::Result<()> {
+        let executed = Arc::new(AtomicUsize::new(0));
+        let addr = mock_server(executed.clone()).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let script: Vec<String> = (0..100)
+            .map(|i| format!("insert into d0 values(now, {i})"))
+            .collect();
+
+        let cancel = Shutdown::new();
+        let reported = Arc::new(AtomicUsize::new(0));
+        let options = ScriptOptions::new().cancel_on(cancel.clone()).on_progress({
+            let reported = reported.clone();
+            move |progress| {
+                assert_eq!(progress.index, reported.fetch_add(1, Ordering::SeqCst));
+                assert_eq!((progress.total, progress.affected), (100, Some(1)));
+                if progress.index == 49 {
+                    cancel.trigger();
+                }
+            }
+        });
+        let report = taos.exec_script(&script, options).await;
+        assert!(report.cancelled && !report.is_complete());
+        assert_eq!(report.resume_index, 50);
+        let indexes: Vec<usize> = report.statements.iter().map(|s| s.index).collect();
+        assert_eq!(indexes, (0..50).collect::<Vec<_>>());
+        assert_eq!(report.statements[49].sql, script[49]);
+        assert_eq!(report.affected_rows(), 50);
+        assert_eq!(executed.load(Ordering::SeqCst), 50);
+        assert_eq!(reported.load(Ordering::SeqCst), 50);
+
+        let options = ScriptOptions::new().start_index(report.resume_index);
+        let report = taos.exec_script(&script, options).await;
+        assert!(!report.cancelled && report.is_complete());
+        assert_eq!(report.statements.first().map(|s| s.index), Some(50));
+        assert_eq!(report.statements.len(), 50);
+        assert_eq!(executed.load(Ordering::SeqCst), 100);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stop_on_error() -> anyhow::Result<()> {
+        let executed = Arc::new(AtomicUsize::new(0));
+        let addr = mock_server(executed.clone()).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let script = ["create table t0", "bad statement", "create table t1"];
+
+        let report = taos.exec_script(script, ScriptOptions::new()).await;
+        assert_eq!(report.statements.len(), 2);
+        assert_eq!(report.resume_index, 1);
+        assert!(!report.cancelled && !report.is_complete());
+        assert_eq!(report.failed().next().map(|s| s.index), Some(1));
+
+        let options = ScriptOptions::new().stop_on_error(false);
+        let report = taos.exec_script(script, options).await;
+        assert!(report.is_complete());
+        assert_eq!(report.statements.len(), 3);
+        assert_eq!(report.failed().count(), 1);
+        assert_eq!(executed.load(Ordering::SeqCst), 3);
+
+        let report = taos
+            .exec_script(script, ScriptOptions::new().start_index(5))
+            .await;
+        assert!(report.statements.is_empty() && report.is_complete());
+        Ok(())
+    }
+
+    /// A statement in flight is abandoned on cancellation, and it's the one to resume from.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancel_in_flight() -> anyhow::Result<()> {
+        let executed = Arc::new(AtomicUsize::new(0));
+        let addr = mock_server(executed.clone()).await?;
+        let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+        let cancel = Shutdown::new();
+        tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                cancel.trigger();
+            }
+        });
+        let script = ["create table t0", "slow", "create table t1"];
+        let options = ScriptOptions::new().cancel_on(cancel);
+        let report =
+            tokio::time::timeout(Duration::from_secs(5), taos.exec_script(script, options)).await?;
+        assert!(report.cancelled);
+        assert_eq!(report.resume_index, 1);
+        assert_eq!(report.statements.len(), 2);
+        assert!(matches!(report.statements[1].result, Err(Error::Cancelled)));
+        assert_eq!(executed.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
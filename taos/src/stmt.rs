@@ -1,7 +1,16 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use taos_query::common::WriteOptions;
 use taos_query::prelude::Value;
-use taos_query::stmt::Bindable;
+use taos_query::stmt::{
+    finite_bind, normalize_bind, using_stable, validate_tables, Bindable, StmtField, TableBind,
+};
+use taos_query::util::{AuditLog, Shutdown};
+use taos_query::{block_in_place_or_global, ConnState};
 
 use crate::sys::Stmt as NativeStmt;
+use crate::{guarded, ResultSet, ResultSetInner};
 use taos_query::prelude::ColumnView;
 use taos_ws::Stmt as WsStmt;
 enum StmtInner {
@@ -9,25 +18,40 @@ enum StmtInner {
     Ws(WsStmt),
 }
 
-pub struct Stmt(StmtInner);
+/// A prepared statement, with the audit log, write options and shutdown flag of the
+/// connection, the prepared sql and the table name waiting for its tags.
+pub struct Stmt(
+    StmtInner,
+    Option<Arc<AuditLog>>,
+    String,
+    WriteOptions,
+    Shutdown,
+    Option<String>,
+);
 
 impl Bindable<super::Taos> for Stmt {
     type Error = super::Error;
 
     fn init(taos: &super::Taos) -> Result<Self, Self::Error> {
-        match &taos.0 {
-            crate::TaosInner::Native(taos) => NativeStmt::init(taos)
-                .map(StmtInner::Native)
-                .map(Stmt)
-                .map_err(Into::into),
-            crate::TaosInner::Ws(taos) => WsStmt::init(taos)
-                .map(StmtInner::Ws)
-                .map(Stmt)
-                .map_err(Into::into),
-        }
+        taos.check_shutdown()?;
+        let inner = match &taos.0 {
+            crate::TaosInner::Native(taos) => StmtInner::Native(NativeStmt::init(taos)?),
+            crate::TaosInner::Ws(taos) => StmtInner::Ws(WsStmt::init(taos)?),
+        };
+        Ok(Stmt(
+            inner,
+            taos.2.clone(),
+            String::new(),
+            taos.3,
+            taos.4.clone(),
+            None,
+        ))
     }
 
     fn prepare<S: AsRef<str>>(&mut self, sql: S) -> Result<&mut Self, Self::Error> {
+        self.check_shutdown()?;
+        self.2 = sql.as_ref().to_string();
+        self.5 = None;
         match &mut self.0 {
             StmtInner::Native(stmt) => {
                 stmt.prepare(sql)?;
@@ -40,18 +64,21 @@ impl Bindable<super::Taos> for Stmt {
     }
 
     fn set_tbname<S: AsRef<str>>(&mut self, name: S) -> Result<&mut Self, Self::Error> {
+        self.check_shutdown()?;
         match &mut self.0 {
             StmtInner::Native(stmt) => {
-                stmt.set_tbname(name)?;
+                stmt.set_tbname(name.as_ref())?;
             }
             StmtInner::Ws(stmt) => {
-                stmt.set_tbname(name)?;
+                stmt.set_tbname(name.as_ref())?;
             }
         }
+        self.5 = Some(name.as_ref().to_string());
         Ok(self)
     }
 
     fn set_tags(&mut self, tags: &[Value]) -> Result<&mut Self, Self::Error> {
+        self.check_shutdown()?;
         match &mut self.0 {
             StmtInner::Native(stmt) => {
                 stmt.set_tags(tags)?;
@@ -60,10 +87,25 @@ impl Bindable<super::Taos> for Stmt {
                 stmt.set_tags(tags)?;
             }
         }
+        if !tags.is_empty() {
+            self.5 = None;
+        }
         Ok(self)
     }
 
     fn bind(&mut self, params: &[ColumnView]) -> Result<&mut Self, Self::Error> {
+        self.check_shutdown()?;
+        if let Some(table) = &self.5 {
+            self.check_tags(table, false)?;
+        }
+        let normalized = if self.3.normalizes_precision() {
+            normalize_bind(self.bound_columns(), params)?
+        } else {
+            None
+        };
+        let params = normalized.as_deref().unwrap_or(params);
+        let finite = finite_bind(self.bound_columns(), params, self.3.non_finite())?;
+        let params = finite.as_deref().unwrap_or(params);
         match &mut self.0 {
             StmtInner::Native(stmt) => {
                 stmt.bind(params)?;
@@ -76,6 +118,7 @@ impl Bindable<super::Taos> for Stmt {
     }
 
     fn add_batch(&mut self) -> Result<&mut Self, Self::Error> {
+        self.check_shutdown()?;
         match &mut self.0 {
             StmtInner::Native(stmt) => {
                 stmt.add_batch()?;
@@ -88,10 +131,25 @@ impl Bindable<super::Taos> for Stmt {
     }
 
     fn execute(&mut self) -> Result<usize, Self::Error> {
-        match &mut self.0 {
-            StmtInner::Native(stmt) => Ok(stmt.execute()?),
-            StmtInner::Ws(stmt) => Ok(stmt.execute()?),
+        self.check_shutdown()?;
+        let started = Instant::now();
+        let res: Result<usize, Self::Error> = match &mut self.0 {
+            StmtInner::Native(stmt) => stmt.execute().map_err(Into::into),
+            StmtInner::Ws(stmt) => stmt.execute().map_err(Into::into),
+        };
+        if let Some(log) = &self.1 {
+            log.record(
+                &self.2,
+                started,
+                res.as_ref()
+                    .map(|rows| Some(*rows))
+                    .map_err(Self::Error::code),
+            );
+            if matches!(&res, Err(err) if ConnState::is_disconnected_code(err.code())) {
+                log.dump();
+            }
         }
+        res
     }
 
     fn affected_rows(&self) -> usize {
@@ -100,6 +158,167 @@ impl Bindable<super::Taos> for Stmt {
             StmtInner::Ws(stmt) => stmt.affected_rows(),
         }
     }
+
+    fn result_set(&mut self) -> Result<ResultSet, Self::Error> {
+        self.check_shutdown()?;
+        let rs = match &mut self.0 {
+            StmtInner::Native(stmt) => ResultSet::from(ResultSetInner::Native(stmt.result_set()?)),
+            StmtInner::Ws(stmt) => ResultSet::from(ResultSetInner::Ws(stmt.result_set()?)),
+        };
+        Ok(rs.watch_shutdown(&self.4))
+    }
+
+    fn bound_columns(&self) -> &[StmtField] {
+        match &self.0 {
+            StmtInner::Native(stmt) => stmt.bound_columns(),
+            StmtInner::Ws(stmt) => stmt.bound_columns(),
+        }
+    }
+
+    fn bound_tags(&self) -> &[StmtField] {
+        match &self.0 {
+            StmtInner::Native(stmt) => stmt.bound_tags(),
+            StmtInner::Ws(stmt) => stmt.bound_tags(),
+        }
+    }
+}
+
+impl Stmt {
+    /// Bind tables of the same super table, eg. thousands of sub-tables of
+    /// `insert into ? using meters tags(?, ?) values(?, ?, ?)`, and execute them at once,
+    /// returns the affected rows of all tables.
+    ///
+    /// Tables must bind the same number and types of columns. Tags are not set if empty, eg. of
+    /// normal tables for `insert into ? values(?, ?)`.
+    /// Failures are [Error::TableBind](super::Error::TableBind) with the index of the table.
+    /// Websocket statements send the messages of tables without waiting for replies one by one,
+    /// see [taos_ws::Stmt::stmt_bind_tables].
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # use taos::taos_query::stmt::TableBind;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let taos = TaosBuilder::from_dsn("taos://localhost:6030")?.build()?;
+    /// let mut stmt = Stmt::init(&taos)?;
+    /// stmt.prepare("insert into ? using power.meters tags(?) values(?, ?)")?;
+    /// let (tags, columns): (Vec<_>, Vec<_>) = (0..1000)
+    ///     .map(|i| {
+    ///         let columns = [
+    ///             ColumnView::from_millis_timestamp(vec![1700000000000]),
+    ///             ColumnView::from_ints(vec![i]),
+    ///         ];
+    ///         ([Value::Int(i)], columns)
+    ///     })
+    ///     .unzip();
+    /// let names: Vec<_> = (0..1000).map(|i| format!("d{i}")).collect();
+    /// let tables: Vec<_> = (0..1000)
+    ///     .map(|i| TableBind {
+    ///         name: &names[i],
+    ///         tags: &tags[i],
+    ///         columns: &columns[i],
+    ///     })
+    ///     .collect();
+    /// assert_eq!(stmt.bind_all(&tables)?, 1000);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind_all(&mut self, tables: &[TableBind]) -> Result<usize, super::Error> {
+        let failed = |index: usize, err: super::Error| super::Error::TableBind {
+            index,
+            table: tables[index].name.to_string(),
+            source: Box::new(err),
+        };
+        self.check_shutdown()?;
+        validate_tables(tables).map_err(|(index, err)| failed(index, err.into()))?;
+        let Some((first, others)) = tables.split_first() else {
+            return Ok(0);
+        };
+        // the first table also loads parameters of `insert into ? using ...` to validate others.
+        self.bind_table(first).map_err(|err| failed(0, err))?;
+        let mut normalized = Vec::with_capacity(others.len());
+        for (index, table) in others.iter().enumerate() {
+            self.check_tags(table.name, !table.tags.is_empty())
+                .map_err(|err| failed(index + 1, err))?;
+            let columns = if self.3.normalizes_precision() {
+                normalize_bind(self.bound_columns(), table.columns)
+                    .map_err(|err| failed(index + 1, err.into()))?
+            } else {
+                None
+            };
+            let finite = finite_bind(
+                self.bound_columns(),
+                columns.as_deref().unwrap_or(table.columns),
+                self.3.non_finite(),
+            )
+            .map_err(|err| failed(index + 1, err.into()))?;
+            normalized.push(finite.or(columns));
+        }
+        let others: Vec<_> = others
+            .iter()
+            .zip(&normalized)
+            .map(|(table, columns)| TableBind {
+                columns: columns.as_deref().unwrap_or(table.columns),
+                ..*table
+            })
+            .collect();
+        match &mut self.0 {
+            StmtInner::Native(_) => {
+                for (index, table) in others.iter().enumerate() {
+                    self.bind_table(table)
+                        .map_err(|err| failed(index + 1, err))?;
+                }
+            }
+            StmtInner::Ws(stmt) => block_in_place_or_global(stmt.stmt_bind_tables(&others))
+                .map_err(|(index, err)| failed(index + 1, err.into()))?,
+        }
+        self.execute()
+    }
+
+    fn bind_table(&mut self, table: &TableBind) -> Result<(), super::Error> {
+        self.set_tbname(table.name)?;
+        if !table.tags.is_empty() {
+            self.set_tags(table.tags)?;
+        }
+        self.bind(table.columns)?.add_batch()?;
+        Ok(())
+    }
+
+    /// Tags are required if the prepare metadata has tags, ie. the table is of a super table
+    /// which is the one after `using` or the table itself for `insert into ? values(...)`.
+    fn check_tags(&self, table: &str, tags_set: bool) -> Result<(), super::Error> {
+        if tags_set || self.bound_tags().is_empty() {
+            return Ok(());
+        }
+        Err(super::Error::TagsRequired {
+            stable: using_stable(&self.2).unwrap_or(table).to_string(),
+        })
+    }
+
+    /// Async version of [Bindable::result_set].
+    pub async fn result_set_async(&mut self) -> Result<ResultSet, super::Error> {
+        let shutdown = self.4.clone();
+        let rs = guarded(&shutdown, async {
+            match &mut self.0 {
+                StmtInner::Native(stmt) => {
+                    Ok(ResultSet::from(ResultSetInner::Native(stmt.result_set()?)))
+                }
+                StmtInner::Ws(stmt) => Ok(ResultSet::from(ResultSetInner::Ws(
+                    stmt.stmt_use_result().await?,
+                ))),
+            }
+        })
+        .await?;
+        Ok(rs.watch_shutdown(&shutdown))
+    }
+
+    /// Fail fast if the connection is shut down, see [Taos::shutdown_now](super::Taos::shutdown_now).
+    fn check_shutdown(&self) -> Result<(), super::Error> {
+        if self.4.is_triggered() {
+            Err(super::Error::Shutdown)
+        } else {
+            Ok(())
+        }
+    }
 }
 
 #[cfg(test)]
@@ -183,6 +402,178 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bindable_ns_datetimes() -> anyhow::Result<()> {
+        use crate::sync::*;
+        use taos_query::common::Timestamp;
+
+        let dsn = std::env::var("TEST_DSN").unwrap_or("taos://localhost:6030".to_string());
+        let taos = TaosBuilder::from_dsn(&dsn)?.build()?;
+        taos.exec_many([
+            "drop database if exists taos_test_bindable_ns",
+            "create database taos_test_bindable_ns precision 'ns' keep 36500",
+            "use taos_test_bindable_ns",
+            "create table tb1 (ts timestamp, c1 int)",
+        ])?;
+
+        let max = Precision::Nanosecond.max_datetime();
+        let over = max + chrono::Duration::nanoseconds(1);
+        let err = ColumnView::from_datetimes(vec![over], Precision::Nanosecond).unwrap_err();
+        assert!(err.to_string().contains("out of range of precision ns"));
+
+        let dt = chrono::DateTime::parse_from_rfc3339("2262-04-11T23:47:16.854775806+08:00")?;
+        let mut stmt = Stmt::init(&taos)?;
+        stmt.prepare("insert into tb1 values(?, ?)")?;
+        let params = vec![
+            ColumnView::from_datetimes(vec![max.fixed_offset(), dt], Precision::Nanosecond)?,
+            ColumnView::from_ints(vec![1, 2]),
+        ];
+        let rows = stmt.bind(&params)?.add_batch()?.execute()?;
+        assert_eq!(rows, 2);
+
+        let values: Vec<(i64, i32)> = taos
+            .query("select cast(ts as bigint), c1 from tb1 order by c1")?
+            .deserialize()
+            .try_collect()?;
+        let expected = Timestamp::from_datetime(&dt, Precision::Nanosecond)?;
+        assert_eq!(values, [(i64::MAX, 1), (expected.as_raw_i64(), 2)]);
+
+        taos.exec("drop database taos_test_bindable_ns")?;
+        Ok(())
+    }
+
+    /// Bind milliseconds and microseconds into a database in nanoseconds, rejected unless
+    /// they are normalized by the write options.
+    #[test]
+    fn test_bind_mixed_precisions_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        for (db, dsn) in [
+            ("test_stmt_precisions_native", "taos://localhost:6030"),
+            ("test_stmt_precisions_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?
+                .with_write_options(WriteOptions::new().normalize_precision(true))
+                .build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} precision 'ns' keep 36500"),
+                format!("use {db}"),
+                "create table tb1 (ts timestamp, c1 int)".to_string(),
+            ])?;
+
+            let ms = || ColumnView::from_millis_timestamp(vec![1_704_067_200_001]);
+            let us = || ColumnView::from_micros_timestamp(vec![1_704_067_200_001_002]);
+            let strict = TaosBuilder::from_dsn(dsn)?.build()?;
+            strict.exec(format!("use {db}"))?;
+            let mut stmt = Stmt::init(&strict)?;
+            stmt.prepare("insert into tb1 values(?, ?)")?;
+            let err = stmt
+                .bind(&[ms(), ColumnView::from_ints(vec![1])])
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("in ns precision"), "{err}");
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into tb1 values(?, ?)")?;
+            stmt.bind(&[ms(), ColumnView::from_ints(vec![1])])?
+                .add_batch()?
+                .bind(&[us(), ColumnView::from_ints(vec![2])])?
+                .add_batch()?;
+            assert_eq!(stmt.execute()?, 2);
+
+            let values: Vec<(i64, i32)> = taos
+                .query("select cast(ts as bigint), c1 from tb1 order by c1")?
+                .deserialize()
+                .try_collect()?;
+            assert_eq!(
+                values,
+                [
+                    (1_704_067_200_001_000_000, 1),
+                    (1_704_067_200_001_002_000, 2)
+                ]
+            );
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
+    /// Bind NaN and infinities by each policy of the write options.
+    #[test]
+    fn test_bind_non_finite_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+        use taos_query::common::NonFinitePolicy;
+
+        for (db, dsn) in [
+            ("test_stmt_non_finite_native", "taos://localhost:6030"),
+            ("test_stmt_non_finite_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db}"),
+                format!("use {db}"),
+                "create table tb1 (ts timestamp, c1 float, c2 double)".to_string(),
+            ])?;
+            let params = |ts: i64| {
+                [
+                    ColumnView::from_millis_timestamp(vec![ts, ts + 1, ts + 2]),
+                    ColumnView::from_floats(vec![f32::INFINITY, 1.5, f32::NAN]),
+                    ColumnView::from_doubles(vec![f64::NEG_INFINITY, f64::NAN, 2.5]),
+                ]
+            };
+
+            let mut rows = Vec::new();
+            for (ts, policy) in [
+                (0, NonFinitePolicy::Error),
+                (10, NonFinitePolicy::Null),
+                (20, NonFinitePolicy::Clamp),
+            ] {
+                let taos = TaosBuilder::from_dsn(dsn)?
+                    .with_write_options(WriteOptions::new().on_non_finite(policy))
+                    .build()?;
+                taos.exec(format!("use {db}"))?;
+                let mut stmt = Stmt::init(&taos)?;
+                stmt.prepare("insert into tb1 values(?, ?, ?)")?;
+                match stmt.bind(&params(1_700_000_000_000 + ts)) {
+                    Ok(stmt) => {
+                        assert_eq!(stmt.add_batch()?.execute()?, 3);
+                    }
+                    Err(err) => {
+                        assert_eq!(policy, NonFinitePolicy::Error);
+                        assert!(
+                            err.to_string()
+                                .contains("column `c1` is bound with inf at row 0"),
+                            "{err}"
+                        );
+                    }
+                }
+                let values: Vec<(Option<f32>, Option<f64>)> = taos
+                    .query("select c1, c2 from tb1 order by ts")?
+                    .deserialize()
+                    .try_collect()?;
+                rows.push(values);
+            }
+            assert!(rows[0].is_empty());
+            assert_eq!(
+                rows[1],
+                [(None, None), (Some(1.5), None), (None, Some(2.5))]
+            );
+            assert_eq!(
+                &rows[2][3..],
+                [
+                    (Some(f32::MAX), Some(f64::MIN)),
+                    (Some(1.5), None),
+                    (None, Some(2.5))
+                ]
+            );
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_bindable() -> anyhow::Result<()> {
         use crate::*;
@@ -262,4 +653,524 @@ mod tests {
 
         Ok(())
     }
+
+    /// Bind NULL tags of every type and json literal `null`, compare tags read back through
+    /// native and websocket connections.
+    #[test]
+    fn test_null_tags_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        let tag_types = [
+            (Ty::Bool, "bool"),
+            (Ty::TinyInt, "tinyint"),
+            (Ty::SmallInt, "smallint"),
+            (Ty::Int, "int"),
+            (Ty::BigInt, "bigint"),
+            (Ty::UTinyInt, "tinyint unsigned"),
+            (Ty::USmallInt, "smallint unsigned"),
+            (Ty::UInt, "int unsigned"),
+            (Ty::UBigInt, "bigint unsigned"),
+            (Ty::Float, "float"),
+            (Ty::Double, "double"),
+            (Ty::Timestamp, "timestamp"),
+            (Ty::VarChar, "varchar(20)"),
+            (Ty::NChar, "nchar(20)"),
+        ];
+        let tags_def = tag_types
+            .iter()
+            .enumerate()
+            .map(|(i, (_, ty))| format!("t{i} {ty}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let tag_names = (0..tag_types.len())
+            .map(|i| format!("t{i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let placeholders = vec!["?"; tag_types.len()].join(", ");
+
+        let mut results = Vec::new();
+        for (db, dsn) in [
+            ("test_null_tags_native", "taos://localhost:6030"),
+            ("test_null_tags_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500"),
+                format!("use {db}"),
+                format!("create table st (ts timestamp, v int) tags({tags_def})"),
+                "create table stj (ts timestamp, v int) tags(j json)".to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            let values = [
+                ColumnView::from_millis_timestamp(vec![0]),
+                ColumnView::from_ints(vec![0]),
+            ];
+            stmt.prepare(format!(
+                "insert into ? using st tags({placeholders}) values(?, ?)"
+            ))?;
+            let nulls: Vec<_> = tag_types.iter().map(|(ty, _)| Value::Null(*ty)).collect();
+            stmt.set_tbname_tags("tb_null", &nulls)?
+                .bind(&values)?
+                .add_batch()?
+                .execute()?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into ? using stj tags(?) values(?, ?)")?;
+            stmt.set_tbname_tags("tb_sql_null", &[Value::Null(Ty::Json)])?
+                .bind(&values)?
+                .add_batch()?
+                .execute()?;
+            stmt.set_tbname_tags("tb_json_null", &[Value::Json(serde_json::Value::Null)])?
+                .bind(&values)?
+                .add_batch()?
+                .execute()?;
+
+            let tags = taos
+                .query(format!("select tags tbname, {tag_names} from st"))?
+                .to_rows_vec()?;
+            assert_eq!(tags.len(), 1);
+            assert!(tags[0][1..].iter().all(Value::is_null), "{tags:?}");
+            let mut json = taos
+                .query("select tags tbname, j from stj")?
+                .to_rows_vec()?;
+            json.sort_by_key(|row| row[0].to_string().unwrap_or_default());
+            assert!(json[1][1].is_null(), "{json:?}");
+            results.push((tags, json));
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        assert_eq!(results[0], results[1]);
+        Ok(())
+    }
+
+    /// Insert into a subset of columns, the other columns are NULL.
+    #[test]
+    fn test_column_projection_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        for (db, dsn) in [
+            ("test_stmt_projection_native", "taos://localhost:6030"),
+            ("test_stmt_projection_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500"),
+                format!("use {db}"),
+                "create table tb1 (ts timestamp, c1 bool, c2 tinyint, c3 smallint, c4 int, \
+                 c5 bigint, c6 tinyint unsigned, c7 smallint unsigned, c8 int unsigned, \
+                 c9 bigint unsigned, c10 float, c11 double, c12 varchar(100), c13 nchar(100))"
+                    .to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into tb1 (ts, c3, c7) values (?, ?, ?)")?;
+            let fields: Vec<_> = stmt
+                .bound_columns()
+                .iter()
+                .map(|f| (f.name().to_string(), f.ty()))
+                .collect();
+            assert_eq!(
+                fields,
+                [
+                    ("ts".to_string(), Ty::Timestamp),
+                    ("c3".to_string(), Ty::SmallInt),
+                    ("c7".to_string(), Ty::USmallInt),
+                ]
+            );
+
+            let ts = || ColumnView::from_millis_timestamp(vec![0, 1]);
+            let c3 = || ColumnView::from_small_ints(vec![1, 2]);
+            let c7 = || ColumnView::from_unsigned_small_ints(vec![Some(3), None]);
+            let err = stmt.bind(&[ts(), c7(), c3()]).err().unwrap();
+            assert!(err.to_string().contains("column `c3` is SMALLINT"), "{err}");
+            assert_eq!(stmt.bind(&[ts(), c3(), c7()])?.add_batch()?.execute()?, 2);
+
+            let rows = taos.query("select * from tb1")?.to_rows_vec()?;
+            assert_eq!(rows.len(), 2);
+            for row in &rows {
+                for (i, value) in row.iter().enumerate() {
+                    if ![0, 3, 7].contains(&i) {
+                        assert!(value.is_null(), "c{i}: {value:?}");
+                    }
+                }
+            }
+            assert_eq!(rows[0][3], Value::SmallInt(1));
+            assert_eq!(rows[0][7], Value::USmallInt(3));
+            assert!(rows[1][7].is_null());
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
+    /// Bind parameters of a query and fetch its result set, an insert has no result set.
+    #[test]
+    fn test_query_result_set_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        for (db, dsn) in [
+            ("test_stmt_query_native", "taos://localhost:6030"),
+            ("test_stmt_query_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500"),
+                format!("use {db}"),
+                "create table tb1 (ts timestamp, current float, location varchar(20))".to_string(),
+                "insert into tb1 values(1000, 1.5, 'a') (2000, 10.5, 'b') (3000, 12.5, 'c')"
+                    .to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare(
+                "select cast(ts as bigint), current, location from tb1 where ts > ? and current > ?",
+            )?;
+            stmt.bind(&[
+                ColumnView::from_millis_timestamp(vec![1000]),
+                ColumnView::from_floats(vec![10.0]),
+            ])?
+            .add_batch()?
+            .execute()?;
+            let rows: Vec<(i64, f32, String)> = stmt.result_set()?.deserialize().try_collect()?;
+            assert_eq!(
+                rows,
+                [(2000, 10.5, "b".to_string()), (3000, 12.5, "c".to_string())]
+            );
+
+            stmt.prepare("insert into tb1 values(?, ?, ?)")?;
+            stmt.bind(&[
+                ColumnView::from_millis_timestamp(vec![4000]),
+                ColumnView::from_floats(vec![1.0]),
+                ColumnView::from_varchar(vec!["d"]),
+            ])?
+            .add_batch()?
+            .execute()?;
+            let err = stmt.result_set().err().unwrap();
+            assert!(
+                err.to_string()
+                    .contains("insert statements have no result set"),
+                "{err}"
+            );
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
+    /// Tags of config strings are parsed by the tag types of the super table.
+    #[test]
+    fn test_tags_from_strs_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        for (db, dsn) in [
+            ("test_stmt_tag_strs_native", "taos://localhost:6030"),
+            ("test_stmt_tag_strs_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500 precision 'us'"),
+                format!("use {db}"),
+                "create stable st1 (ts timestamp, v int) tags (enabled bool, groupid tinyint, \
+                 since timestamp, location varchar(20))"
+                    .to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into ? using st1 tags(?, ?, ?, ?) values(?, ?)")?;
+            stmt.set_tbname("d1")?;
+            let tags: Vec<_> = stmt.bound_tags().iter().map(|f| f.ty()).collect();
+            assert_eq!(tags, [Ty::Bool, Ty::TinyInt, Ty::Timestamp, Ty::VarChar]);
+            let err = stmt
+                .set_tags_from_strs(&["true", "300", "2024-01-01", "SF"])
+                .err()
+                .unwrap();
+            assert!(
+                err.to_string().contains("tag `groupid` is TINYINT"),
+                "{err}"
+            );
+            stmt.set_tags_from_strs(&["true", "2", "2024-01-01T00:00:00.000001Z", "SF"])?
+                .bind(&[
+                    ColumnView::from_micros_timestamp(vec![0]),
+                    ColumnView::from_ints(vec![1]),
+                ])?
+                .add_batch()?
+                .execute()?;
+
+            let row: (bool, i8, i64, String) = taos
+                .query_one("select enabled, groupid, cast(since as bigint), location from d1")?
+                .unwrap();
+            assert_eq!(row, (true, 2, 1_704_067_200_000_001, "SF".to_string()));
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
+    /// Binary payloads in `BINARY` columns are read back as they are, not as UTF-8.
+    #[test]
+    fn test_binary_payload_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        #[derive(Debug, Deserialize)]
+        struct Message {
+            #[serde(with = "serde_bytes")]
+            payload: Vec<u8>,
+            backup: Option<serde_bytes::ByteBuf>,
+        }
+        // a protobuf message of `1: 150` and `2: [0xff, 0xfe, 0x00]`.
+        let payload: &[u8] = &[0x08, 0x96, 0x01, 0x12, 0x03, 0xff, 0xfe, 0x00];
+
+        for (db, dsn) in [
+            ("test_stmt_binary_payload_native", "taos://localhost:6030"),
+            ("test_stmt_binary_payload_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500"),
+                format!("use {db}"),
+                "create table tb (ts timestamp, payload binary(64), backup binary(64))".to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into tb values(?, ?, ?)")?;
+            stmt.bind(&[
+                ColumnView::from_millis_timestamp(vec![0, 1]),
+                ColumnView::from_varchar_bytes(vec![payload, payload]),
+                ColumnView::from_varchar_bytes::<&[u8], _, _, _>(vec![Some(payload), None]),
+            ])?
+            .add_batch()?
+            .execute()?;
+
+            let messages: Vec<Message> = taos
+                .query("select payload, backup from tb order by ts")?
+                .deserialize()
+                .try_collect()?;
+            assert_eq!(messages.len(), 2);
+            assert_eq!(messages[0].payload, payload);
+            assert_eq!(messages[0].backup.as_deref().map(|v| &v[..]), Some(payload));
+            assert_eq!(messages[1].payload, payload);
+            assert!(messages[1].backup.is_none());
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
+    /// Sub-tables bound in one execution, with the failed table reported by index.
+    #[test]
+    fn test_bind_all_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+        use taos_query::stmt::TableBind;
+
+        const TABLES: usize = 1000;
+        let names: Vec<_> = (0..TABLES).map(|i| format!("d{i}")).collect();
+        let tags: Vec<_> = (0..TABLES).map(|i| [Value::Int(i as i32)]).collect();
+        let columns: Vec<_> = (0..TABLES)
+            .map(|i| {
+                [
+                    ColumnView::from_millis_timestamp(vec![0, 1]),
+                    ColumnView::from_ints(vec![i as i32, -(i as i32)]),
+                ]
+            })
+            .collect();
+        let tables: Vec<_> = (0..TABLES)
+            .map(|i| TableBind {
+                name: &names[i],
+                tags: &tags[i],
+                columns: &columns[i],
+            })
+            .collect();
+
+        for (db, dsn) in [
+            ("test_stmt_bind_all_native", "taos://localhost:6030"),
+            ("test_stmt_bind_all_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500"),
+                format!("use {db}"),
+                "create stable st (ts timestamp, v int) tags (t int)".to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into ? using st tags(?) values(?, ?)")?;
+            assert_eq!(stmt.bind_all(&tables)?, TABLES * 2);
+            let (count, sum): (i64, i64) =
+                taos.query_one("select count(*), sum(t) from st")?.unwrap();
+            assert_eq!(count, TABLES as i64 * 2);
+            assert_eq!(sum, (0..TABLES as i64).sum::<i64>() * 2);
+
+            // a table of mismatched columns fails before anything is bound.
+            let bigints = [
+                ColumnView::from_millis_timestamp(vec![2]),
+                ColumnView::from_big_ints(vec![0]),
+            ];
+            let mut mismatched = tables[..3].to_vec();
+            mismatched[2].columns = &bigints;
+            let err = stmt.bind_all(&mismatched).unwrap_err();
+            assert!(
+                matches!(&err, crate::Error::TableBind { index: 2, table, .. } if table == "d2"),
+                "{err}"
+            );
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
+    /// Normal tables are bound without tags, while tables of super tables require them.
+    #[test]
+    fn test_normal_table_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        let values = [
+            ColumnView::from_millis_timestamp(vec![0]),
+            ColumnView::from_ints(vec![1]),
+        ];
+        for (db, dsn) in [
+            ("test_stmt_normal_table_native", "taos://localhost:6030"),
+            ("test_stmt_normal_table_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500"),
+                format!("use {db}"),
+                "create table ntb (ts timestamp, v int)".to_string(),
+                "create stable st (ts timestamp, v int) tags (t int)".to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into ? values(?, ?)")?;
+            stmt.set_tbname("ntb")?.bind(&values)?.add_batch()?;
+            assert_eq!(stmt.execute()?, 1);
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into ? using st tags(?) values(?, ?)")?;
+            stmt.set_tbname_tags("d0", &[Value::Int(0)])?
+                .bind(&values)?
+                .add_batch()?;
+            assert_eq!(stmt.execute()?, 1);
+
+            stmt.set_tbname("d1")?;
+            let err = stmt.bind(&values).map(|_| ()).unwrap_err();
+            assert!(
+                matches!(&err, crate::Error::TagsRequired { stable } if stable == "st"),
+                "{err}"
+            );
+
+            let count: Option<i64> = taos.query_one("select count(*) from ntb")?;
+            assert_eq!(count, Some(1));
+            let count: Option<i64> = taos.query_one("select count(*) from st")?;
+            assert_eq!(count, Some(1));
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
+    /// Bind varbinary and geometry columns with NULLs and empty bytes, read back as bytes.
+    #[test]
+    fn test_varbinary_geometry_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        // POINT(1 2) in WKB of little endian.
+        let point =
+            b"\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\xf0\x3f\x00\x00\x00\x00\x00\x00\x00\x40";
+        let bytes: [Option<&[u8]>; 3] = [Some(b"\x00\xff'\\"), None, Some(b"")];
+        for (db, dsn) in [
+            ("test_stmt_varbinary_native", "taos://localhost:6030"),
+            ("test_stmt_varbinary_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500"),
+                format!("use {db}"),
+                "create table tb1 (ts timestamp, vb varbinary(20), geo geometry(50))".to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into tb1 values(?, ?, ?)")?;
+            let params = vec![
+                ColumnView::from_millis_timestamp(vec![0, 1, 2]),
+                ColumnView::from_bytes::<&[u8], _, _, _>(bytes),
+                ColumnView::from_geobytes::<&[u8], _, _, _>([Some(&point[..]), None, None]),
+            ];
+            let rows = stmt.bind(&params)?.add_batch()?.execute()?;
+            assert_eq!(rows, 3);
+
+            let rows: Vec<(i64, Option<Vec<u8>>, Option<Vec<u8>>)> = taos
+                .query("select cast(ts as bigint), vb, geo from tb1 order by ts")?
+                .deserialize()
+                .try_collect()?;
+            assert_eq!(
+                rows,
+                [
+                    (0, bytes[0].map(<[u8]>::to_vec), Some(point.to_vec())),
+                    (1, None, None),
+                    (2, Some(vec![]), None),
+                ]
+            );
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        Ok(())
+    }
+
+    /// Bind a constant once with columns of 10k rows, it's repeated to all rows.
+    #[test]
+    fn test_broadcast_constant_cross_backend() -> anyhow::Result<()> {
+        use crate::sync::*;
+
+        const ROWS: i64 = 10_000;
+        let mut results = Vec::new();
+        for (db, dsn) in [
+            ("test_stmt_broadcast_native", "taos://localhost:6030"),
+            ("test_stmt_broadcast_ws", "ws://localhost:6041"),
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            taos.exec_many([
+                format!("drop database if exists {db}"),
+                format!("create database {db} keep 36500"),
+                format!("use {db}"),
+                "create table tb1 (ts timestamp, batch varchar(20), v int)".to_string(),
+            ])?;
+
+            let mut stmt = Stmt::init(&taos)?;
+            stmt.prepare("insert into tb1 values(?, ?, ?)")?;
+            let ts = || ColumnView::from_millis_timestamp((0..ROWS).collect());
+            let batch = || ColumnView::from_varchar::<&str, _, _, _>(vec!["batch-7"]);
+            let v = ColumnView::from_ints((0..ROWS as i32).collect());
+            let rows = stmt.bind(&[ts(), batch(), v])?.add_batch()?.execute()?;
+            assert_eq!(rows, ROWS as usize);
+
+            let short = ColumnView::from_ints(vec![1, 2]);
+            let err = stmt.bind(&[ts(), batch(), short]).err().unwrap();
+            assert!(
+                err.to_string().contains("column `v` is bound with 2 rows"),
+                "{err}"
+            );
+
+            let rows: Vec<(i64, String, i32)> = taos
+                .query("select cast(ts as bigint), batch, v from tb1 order by ts")?
+                .deserialize()
+                .try_collect()?;
+            assert_eq!(rows.len(), ROWS as usize);
+            assert!(rows.iter().all(|(_, batch, _)| batch == "batch-7"));
+            assert_eq!(rows[9_999], (9_999, "batch-7".to_string(), 9_999));
+            results.push(rows);
+
+            taos.exec(format!("drop database {db}"))?;
+        }
+        assert_eq!(results[0], results[1]);
+        Ok(())
+    }
 }
@@ -1,9 +1,15 @@
+use std::sync::Arc;
+
 use taos_query::{
     block_in_place_or_global,
-    prelude::{AsAsyncConsumer, RawMeta, TBuilder, Timeout},
-    RawBlock,
+    prelude::{AsAsyncConsumer, IsOffset, RawMeta, TBuilder, Timeout},
+    tmq::{OffsetStore, OffsetValue, ProcessError, ProcessOptions, ProcessReport, StoredOffsets},
+    util::Shutdown,
+    Dsn, RawBlock,
 };
 
+use crate::guarded;
+
 enum TmqBuilderInner {
     Native(crate::sys::TmqBuilder),
     Ws(taos_ws::consumer::TmqBuilder),
@@ -34,8 +40,100 @@ pub struct Data(DataInner);
 
 pub type MessageSet<Meta, Data> = taos_query::tmq::MessageSet<Meta, Data>;
 
-pub struct TmqBuilder(TmqBuilderInner);
-pub struct Consumer(ConsumerInner);
+/// An [OffsetStore] with the group of consumers saving offsets to it.
+#[derive(Clone)]
+struct GroupOffsetStore {
+    store: Arc<dyn OffsetStore>,
+    group_id: String,
+}
+
+pub struct TmqBuilder(TmqBuilderInner, Shutdown, Dsn, Option<GroupOffsetStore>);
+pub struct Consumer(ConsumerInner, Shutdown, Option<GroupOffsetStore>);
+
+/// Consumers of a group built by a [TmqBuilder] and polled at the same time, see
+/// [taos_query::tmq::ParallelConsumer].
+pub type ParallelConsumer = taos_query::tmq::ParallelConsumer<Consumer>;
+
+impl TmqBuilder {
+    /// Create a consumer builder with the same connection configuration of `taos`, only
+    /// TMQ-specific parameters like `group.id` and `auto.offset.reset` are required.
+    ///
+    /// The configuration (endpoints, auth, TLS, timezone and the other DSN parameters) is
+    /// cloned, the connection itself is not shared. Consumers are shut down along with `taos`,
+    /// see [Taos::shutdown_now](crate::Taos::shutdown_now).
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let taos = TaosBuilder::from_dsn("taos://localhost:6030?timezone=UTC")?.build()?;
+    /// let consumer = TmqBuilder::from_taos(&taos, [("group.id", "group1")])?.build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_taos<K, V>(
+        taos: &crate::Taos,
+        params: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, super::Error>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let mut builder = Self::from_dsn(tmq_dsn_of(taos, params))?;
+        builder.1 = taos.4.clone();
+        Ok(builder)
+    }
+
+    /// Keep offsets of the consumers in `store` instead of the server, eg. for checkpoints
+    /// coordinated with other systems.
+    ///
+    /// Consumers built with a store disable auto commit. On subscribe, they seek each assigned
+    /// vgroup to its stored offset, and [commit](AsAsyncConsumer::commit),
+    /// [commit_offset](Consumer::commit_offset) and [commit_message](Consumer::commit_message)
+    /// save the offset to the store before committing it to the server.
+    ///
+    /// ```rust,no_run
+    /// # use std::sync::Arc;
+    /// # use taos::*;
+    /// # use taos::taos_query::tmq::FileOffsetStore;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let store = Arc::new(FileOffsetStore::new("offsets.json"));
+    /// let mut consumer = TmqBuilder::from_dsn("taos://localhost:6030?group.id=sink")?
+    ///     .with_offset_store(store)?
+    ///     .build()?;
+    /// // resumes after the offsets saved by the last run.
+    /// consumer.subscribe(["topic"]).await?;
+    /// while let Some((offset, _message)) = consumer.recv_timeout(Timeout::from_secs(1)).await? {
+    ///     consumer.commit(offset).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_offset_store(self, store: Arc<dyn OffsetStore>) -> Result<Self, super::Error> {
+        let mut dsn = self.2;
+        let group_id = dsn
+            .get("group.id")
+            .cloned()
+            .ok_or_else(|| taos_query::DsnError::RequireParam("group.id".to_string()))?;
+        dsn.set("enable.auto.commit", "false");
+        let mut builder = Self::from_dsn(dsn)?;
+        builder.1 = self.1;
+        builder.3 = Some(GroupOffsetStore { store, group_id });
+        Ok(builder)
+    }
+}
+
+fn tmq_dsn_of<K, V>(taos: &crate::Taos, params: impl IntoIterator<Item = (K, V)>) -> taos_query::Dsn
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    let mut dsn = taos.1.clone();
+    for (k, v) in params {
+        dsn.set_param(k, v);
+    }
+    dsn
+}
 
 impl TBuilder for TmqBuilder {
     type Target = Consumer;
@@ -49,18 +147,21 @@ impl TBuilder for TmqBuilder {
     fn from_dsn<D: taos_query::IntoDsn>(dsn: D) -> Result<Self, Self::Error> {
         let dsn = dsn.into_dsn()?;
         // dbg!(&dsn);
-        match (dsn.driver.as_str(), dsn.protocol.as_deref()) {
-            ("ws" | "wss" | "http" | "https" | "taosws", _) => Ok(Self(TmqBuilderInner::Ws(
-                taos_ws::consumer::TmqBuilder::from_dsn(dsn)?,
-            ))),
-            ("taos" | "tmq", None) => Ok(Self(TmqBuilderInner::Native(
-                crate::sys::TmqBuilder::from_dsn(dsn)?,
-            ))),
-            ("taos" | "tmq", Some("ws" | "wss" | "http" | "https")) => Ok(Self(
-                TmqBuilderInner::Ws(taos_ws::consumer::TmqBuilder::from_dsn(dsn)?),
-            )),
-            (driver, _) => Err(taos_query::DsnError::InvalidDriver(driver.to_string()).into()),
-        }
+        let inner = match (dsn.driver.as_str(), dsn.protocol.as_deref()) {
+            ("ws" | "wss" | "http" | "https" | "taosws" | "taoswss", _) => {
+                TmqBuilderInner::Ws(taos_ws::consumer::TmqBuilder::from_dsn(&dsn)?)
+            }
+            ("taos" | "tmq", None) => {
+                TmqBuilderInner::Native(crate::sys::TmqBuilder::from_dsn(&dsn)?)
+            }
+            ("taos" | "tmq", Some("ws" | "wss" | "http" | "https")) => {
+                TmqBuilderInner::Ws(taos_ws::consumer::TmqBuilder::from_dsn(&dsn)?)
+            }
+            (driver, _) => {
+                return Err(taos_query::DsnError::InvalidDriver(driver.to_string()).into())
+            }
+        };
+        Ok(Self(inner, Shutdown::new(), dsn, None))
     }
 
     fn client_version() -> &'static str {
@@ -89,8 +190,16 @@ impl TBuilder for TmqBuilder {
 
     fn build(&self) -> Result<Self::Target, Self::Error> {
         match &self.0 {
-            TmqBuilderInner::Native(b) => Ok(Consumer(ConsumerInner::Native(b.build()?))),
-            TmqBuilderInner::Ws(b) => Ok(Consumer(ConsumerInner::Ws(b.build()?))),
+            TmqBuilderInner::Native(b) => Ok(Consumer(
+                ConsumerInner::Native(b.build()?),
+                self.1.clone(),
+                self.3.clone(),
+            )),
+            TmqBuilderInner::Ws(b) => Ok(Consumer(
+                ConsumerInner::Ws(b.build()?),
+                self.1.clone(),
+                self.3.clone(),
+            )),
         }
     }
 
@@ -136,6 +245,50 @@ impl taos_query::tmq::IsOffset for Offset {
             }
         }
     }
+
+    fn offset(&self) -> Option<i64> {
+        match &self.0 {
+            OffsetInner::Native(offset) => {
+                <crate::sys::tmq::Offset as taos_query::tmq::IsOffset>::offset(offset)
+            }
+            OffsetInner::Ws(offset) => {
+                <taos_ws::consumer::Offset as taos_query::tmq::IsOffset>::offset(offset)
+            }
+        }
+    }
+
+    fn begin(&self) -> Option<i64> {
+        match &self.0 {
+            OffsetInner::Native(offset) => {
+                <crate::sys::tmq::Offset as taos_query::tmq::IsOffset>::begin(offset)
+            }
+            OffsetInner::Ws(offset) => {
+                <taos_ws::consumer::Offset as taos_query::tmq::IsOffset>::begin(offset)
+            }
+        }
+    }
+
+    fn high_watermark(&self) -> Option<i64> {
+        match &self.0 {
+            OffsetInner::Native(offset) => {
+                <crate::sys::tmq::Offset as taos_query::tmq::IsOffset>::high_watermark(offset)
+            }
+            OffsetInner::Ws(offset) => {
+                <taos_ws::consumer::Offset as taos_query::tmq::IsOffset>::high_watermark(offset)
+            }
+        }
+    }
+
+    fn timestamp(&self) -> Option<taos_query::common::Timestamp> {
+        match &self.0 {
+            OffsetInner::Native(offset) => {
+                <crate::sys::tmq::Offset as taos_query::tmq::IsOffset>::timestamp(offset)
+            }
+            OffsetInner::Ws(offset) => {
+                <taos_ws::consumer::Offset as taos_query::tmq::IsOffset>::timestamp(offset)
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -208,6 +361,223 @@ impl taos_query::tmq::IsAsyncData for Data {
     }
 }
 
+impl Consumer {
+    /// Seek vgroups of `topics` assigned to the consumer to the offsets in the [OffsetStore] of
+    /// [TmqBuilder::with_offset_store].
+    async fn seek_to_stored(&self, topics: &[String]) -> Result<(), super::Error> {
+        let Some(GroupOffsetStore { store, group_id }) = &self.2 else {
+            return Ok(());
+        };
+        for topic in topics {
+            let stored = StoredOffsets::load(store.as_ref(), group_id, &[topic.as_str()]).await?;
+            for assignment in self.assignments(topic).await? {
+                if let Some(offset) = stored.get(topic, assignment.vgroup_id) {
+                    log::debug!(
+                        "seek vgroup {} of topic {topic} to stored offset {offset}",
+                        assignment.vgroup_id
+                    );
+                    self.offset_seek(topic, assignment.vgroup_id, offset)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Save an offset to be committed to the [OffsetStore] of [TmqBuilder::with_offset_store].
+    async fn save_offset(
+        &self,
+        topic: &str,
+        vgroup_id: taos_query::tmq::VGroupId,
+        offset: Option<i64>,
+    ) -> Result<(), super::Error> {
+        let (Some(GroupOffsetStore { store, group_id }), Some(offset)) = (&self.2, offset) else {
+            return Ok(());
+        };
+        let value = OffsetValue::new(group_id.as_str(), topic, vgroup_id, offset);
+        store.save(&[value]).await.map_err(Into::into)
+    }
+
+    /// Offsets of the vgroups of a subscribed topic assigned to the consumer.
+    ///
+    /// It's empty for native clients without `tmq_get_topic_assignment`.
+    pub async fn assignments(
+        &self,
+        topic: &str,
+    ) -> Result<Vec<taos_query::tmq::Assignment>, super::Error> {
+        guarded(&self.1, async {
+            match &self.0 {
+                ConsumerInner::Native(c) => c.assignments(topic).map_err(Into::into),
+                ConsumerInner::Ws(c) => c.assignments(topic).await.map_err(Into::into),
+            }
+        })
+        .await
+    }
+
+    /// Move the offset of the consumer in a vgroup of `topic`, eg. back to the `begin` of its
+    /// [assignment](Consumer::assignments) to consume the vgroup again.
+    ///
+    /// Offsets out of the range kept by the vgroup fail with the error of the server. Native
+    /// clients without `tmq_offset_seek` always fail.
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let mut consumer = TmqBuilder::from_dsn("taos://localhost:6030?group.id=rewind")?.build()?;
+    /// consumer.subscribe(["topic"]).await?;
+    /// for assignment in consumer.assignments("topic").await? {
+    ///     consumer
+    ///         .offset_seek("topic", assignment.vgroup_id, assignment.begin)
+    ///         .await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn offset_seek(
+        &self,
+        topic: &str,
+        vgroup_id: taos_query::tmq::VGroupId,
+        offset: i64,
+    ) -> Result<(), super::Error> {
+        guarded(&self.1, async {
+            match &self.0 {
+                ConsumerInner::Native(c) => {
+                    c.offset_seek(topic, vgroup_id, offset).map_err(Into::into)
+                }
+                ConsumerInner::Ws(c) => c
+                    .offset_seek(topic, vgroup_id, offset)
+                    .await
+                    .map_err(Into::into),
+            }
+        })
+        .await
+    }
+
+    /// Commit `offset` of a vgroup of `topic`, instead of the offset of a received message by
+    /// [commit](AsAsyncConsumer::commit). Eg. to commit an offset persisted by the application
+    /// after its sink acknowledged the messages before it.
+    ///
+    /// Errors of the server keep their codes. Native clients without `tmq_commit_offset_async`
+    /// always fail.
+    pub async fn commit_offset(
+        &self,
+        topic: &str,
+        vgroup_id: taos_query::tmq::VGroupId,
+        offset: i64,
+    ) -> Result<(), super::Error> {
+        self.save_offset(topic, vgroup_id, Some(offset)).await?;
+        guarded(&self.1, async {
+            match &self.0 {
+                ConsumerInner::Native(c) => c
+                    .commit_offset(topic, vgroup_id, offset)
+                    .await
+                    .map_err(Into::into),
+                ConsumerInner::Ws(c) => c
+                    .commit_offset(topic, vgroup_id, offset)
+                    .await
+                    .map_err(Into::into),
+            }
+        })
+        .await
+    }
+
+    /// Commit the offset of exactly the received message of `offset`, other messages polled
+    /// after it are not committed. The message is kept, eg. to commit it once more acknowledged.
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let mut consumer = TmqBuilder::from_dsn("taos://localhost:6030?group.id=sink")?.build()?;
+    /// consumer.subscribe(["topic"]).await?;
+    /// while let Some((offset, message)) = consumer.recv_timeout(Timeout::from_secs(1)).await? {
+    ///     // write `message` to the sink and wait for its acknowledgement.
+    ///     # drop(message);
+    ///     println!("acknowledged offset {:?}", offset.offset());
+    ///     consumer.commit_message(&offset).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn commit_message(&self, offset: &Offset) -> Result<(), super::Error> {
+        self.save_offset(offset.topic(), offset.vgroup_id(), offset.offset())
+            .await?;
+        guarded(&self.1, async {
+            match (&self.0, &offset.0) {
+                (ConsumerInner::Native(c), OffsetInner::Native(offset)) => {
+                    c.commit_message(offset).await.map_err(Into::into)
+                }
+                (ConsumerInner::Ws(c), OffsetInner::Ws(offset)) => {
+                    c.commit_message(offset).await.map_err(Into::into)
+                }
+                _ => unreachable!(),
+            }
+        })
+        .await
+    }
+
+    /// Process messages by `handler` with `workers` messages at a time, committing offsets of
+    /// each vgroup in order, see [process_concurrently_with](Consumer::process_concurrently_with).
+    pub async fn process_concurrently<F, Fut, E>(
+        &self,
+        workers: usize,
+        handler: F,
+    ) -> Result<ProcessReport, ProcessError<super::Error, E>>
+    where
+        F: Fn(MessageSet<Meta, Data>) -> Fut,
+        Fut: std::future::Future<Output = Result<(), E>>,
+    {
+        self.process_concurrently_with(ProcessOptions::new(workers), handler)
+            .await
+    }
+
+    /// Process messages by `handler` concurrently, and commit the offset of a vgroup only when
+    /// all messages before it are processed, see [taos_query::tmq::process_concurrently].
+    ///
+    /// ```rust,no_run
+    /// # use taos::*;
+    /// # use taos::taos_query::tmq::{DeadLetter, OnHandlerError, ProcessOptions};
+    /// # use taos::taos_query::util::Shutdown;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let mut consumer = TmqBuilder::from_dsn("taos://localhost:6030?group.id=sink")?.build()?;
+    /// consumer.subscribe(["topic"]).await?;
+    /// let shutdown = Shutdown::new();
+    /// let options = ProcessOptions::new(8)
+    ///     .max_in_flight_per_vgroup(4)
+    ///     .on_error(OnHandlerError::Skip(DeadLetter::new(|id, err: &Error| {
+    ///         eprintln!("dead letter {id:?}: {err}")
+    ///     })))
+    ///     .shutdown(shutdown.clone());
+    /// let report = consumer
+    ///     .process_concurrently_with(options, |message| async move {
+    ///         if let Some(data) = message.into_data() {
+    ///             for block in data {
+    ///                 let _block = block?;
+    ///                 // write the block to the sink.
+    ///             }
+    ///         }
+    ///         Ok(())
+    ///     })
+    ///     .await?;
+    /// println!("{} messages processed", report.processed);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn process_concurrently_with<F, Fut, E>(
+        &self,
+        options: ProcessOptions<E>,
+        handler: F,
+    ) -> Result<ProcessReport, ProcessError<super::Error, E>>
+    where
+        F: Fn(MessageSet<Meta, Data>) -> Fut,
+        Fut: std::future::Future<Output = Result<(), E>>,
+    {
+        taos_query::tmq::process_concurrently(self, options, handler).await
+    }
+}
+
 #[async_trait::async_trait]
 impl AsAsyncConsumer for Consumer {
     type Error = super::Error;
@@ -229,101 +599,124 @@ impl AsAsyncConsumer for Consumer {
         }
     }
 
+    fn poll_interval(&self) -> std::time::Duration {
+        match &self.0 {
+            ConsumerInner::Native(c) => <crate::sys::Consumer as AsAsyncConsumer>::poll_interval(c),
+            ConsumerInner::Ws(c) => {
+                <taos_ws::consumer::Consumer as AsAsyncConsumer>::poll_interval(c)
+            }
+        }
+    }
+
     async fn subscribe<T: Into<String>, I: IntoIterator<Item = T> + Send>(
         &mut self,
         topics: I,
     ) -> Result<(), Self::Error> {
-        match &mut self.0 {
-            ConsumerInner::Native(c) => {
-                <crate::sys::Consumer as AsAsyncConsumer>::subscribe(c, topics)
-                    .await
-                    .map_err(Into::into)
-            }
-            ConsumerInner::Ws(c) => {
-                <taos_ws::consumer::Consumer as AsAsyncConsumer>::subscribe(c, topics)
-                    .await
-                    .map_err(Into::into)
+        let topics: Vec<String> = topics.into_iter().map(Into::into).collect();
+        let shutdown = self.1.clone();
+        guarded(&shutdown, async {
+            match &mut self.0 {
+                ConsumerInner::Native(c) => {
+                    <crate::sys::Consumer as AsAsyncConsumer>::subscribe(c, topics.clone())
+                        .await
+                        .map_err(Into::into)
+                }
+                ConsumerInner::Ws(c) => {
+                    <taos_ws::consumer::Consumer as AsAsyncConsumer>::subscribe(c, topics.clone())
+                        .await
+                        .map_err(Into::into)
+                }
             }
-        }
+        })
+        .await?;
+        self.seek_to_stored(&topics).await
     }
 
     async fn recv_timeout(
         &self,
         timeout: Timeout,
     ) -> Result<Option<(Self::Offset, MessageSet<Self::Meta, Self::Data>)>, Self::Error> {
-        match &self.0 {
-            ConsumerInner::Native(c) => {
-                <crate::sys::Consumer as AsAsyncConsumer>::recv_timeout(c, timeout)
-                    .await
-                    .map_err(Into::into)
-                    .map(|msg| {
-                        msg.map(|(offset, msg)| {
-                            (
-                                Offset(OffsetInner::Native(offset)),
-                                match msg {
-                                    MessageSet::Meta(meta) => {
-                                        MessageSet::Meta(Meta(MetaInner::Native(meta)))
-                                    }
-                                    MessageSet::Data(data) => {
-                                        MessageSet::Data(Data(DataInner::Native(data)))
-                                    }
-                                    MessageSet::MetaData(meta, data) => MessageSet::MetaData(
-                                        Meta(MetaInner::Native(meta)),
-                                        Data(DataInner::Native(data)),
-                                    ),
-                                },
-                            )
-                        })
-                    })
-            }
-            ConsumerInner::Ws(c) => {
-                <taos_ws::consumer::Consumer as AsAsyncConsumer>::recv_timeout(c, timeout)
-                    .await
-                    .map_err(Into::into)
-                    .map(|msg| {
-                        msg.map(|(offset, msg)| {
-                            (
-                                Offset(OffsetInner::Ws(offset)),
-                                match msg {
-                                    taos_query::tmq::MessageSet::Meta(meta) => {
-                                        MessageSet::Meta(Meta(MetaInner::Ws(meta)))
-                                    }
-                                    taos_query::tmq::MessageSet::Data(data) => {
-                                        MessageSet::Data(Data(DataInner::Ws(data)))
-                                    }
-                                    taos_query::tmq::MessageSet::MetaData(meta, data) => {
-                                        MessageSet::MetaData(
-                                            Meta(MetaInner::Ws(meta)),
-                                            Data(DataInner::Ws(data)),
-                                        )
-                                    }
-                                },
-                            )
-                        })
-                    })
-            }
-        }
-    }
-
-    async fn commit(&self, offset: Self::Offset) -> Result<(), Self::Error> {
-        match &self.0 {
-            ConsumerInner::Native(c) => match offset.0 {
-                OffsetInner::Native(offset) => {
-                    <crate::sys::Consumer as AsAsyncConsumer>::commit(c, offset)
+        guarded(&self.1, async {
+            match &self.0 {
+                ConsumerInner::Native(c) => {
+                    <crate::sys::Consumer as AsAsyncConsumer>::recv_timeout(c, timeout)
                         .await
                         .map_err(Into::into)
+                        .map(|msg| {
+                            msg.map(|(offset, msg)| {
+                                (
+                                    Offset(OffsetInner::Native(offset)),
+                                    match msg {
+                                        MessageSet::Meta(meta) => {
+                                            MessageSet::Meta(Meta(MetaInner::Native(meta)))
+                                        }
+                                        MessageSet::Data(data) => {
+                                            MessageSet::Data(Data(DataInner::Native(data)))
+                                        }
+                                        MessageSet::MetaData(meta, data) => MessageSet::MetaData(
+                                            Meta(MetaInner::Native(meta)),
+                                            Data(DataInner::Native(data)),
+                                        ),
+                                    },
+                                )
+                            })
+                        })
                 }
-                OffsetInner::Ws(_) => unreachable!(),
-            },
-            ConsumerInner::Ws(c) => match offset.0 {
-                OffsetInner::Ws(offset) => {
-                    <taos_ws::consumer::Consumer as AsAsyncConsumer>::commit(c, offset)
+                ConsumerInner::Ws(c) => {
+                    <taos_ws::consumer::Consumer as AsAsyncConsumer>::recv_timeout(c, timeout)
                         .await
                         .map_err(Into::into)
+                        .map(|msg| {
+                            msg.map(|(offset, msg)| {
+                                (
+                                    Offset(OffsetInner::Ws(offset)),
+                                    match msg {
+                                        taos_query::tmq::MessageSet::Meta(meta) => {
+                                            MessageSet::Meta(Meta(MetaInner::Ws(meta)))
+                                        }
+                                        taos_query::tmq::MessageSet::Data(data) => {
+                                            MessageSet::Data(Data(DataInner::Ws(data)))
+                                        }
+                                        taos_query::tmq::MessageSet::MetaData(meta, data) => {
+                                            MessageSet::MetaData(
+                                                Meta(MetaInner::Ws(meta)),
+                                                Data(DataInner::Ws(data)),
+                                            )
+                                        }
+                                    },
+                                )
+                            })
+                        })
                 }
-                _ => unreachable!(),
-            },
-        }
+            }
+        })
+        .await
+    }
+
+    async fn commit(&self, offset: Self::Offset) -> Result<(), Self::Error> {
+        self.save_offset(offset.topic(), offset.vgroup_id(), offset.offset())
+            .await?;
+        guarded(&self.1, async {
+            match &self.0 {
+                ConsumerInner::Native(c) => match offset.0 {
+                    OffsetInner::Native(offset) => {
+                        <crate::sys::Consumer as AsAsyncConsumer>::commit(c, offset)
+                            .await
+                            .map_err(Into::into)
+                    }
+                    OffsetInner::Ws(_) => unreachable!(),
+                },
+                ConsumerInner::Ws(c) => match offset.0 {
+                    OffsetInner::Ws(offset) => {
+                        <taos_ws::consumer::Consumer as AsAsyncConsumer>::commit(c, offset)
+                            .await
+                            .map_err(Into::into)
+                    }
+                    _ => unreachable!(),
+                },
+            }
+        })
+        .await
     }
 }
 
@@ -359,6 +752,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn builder_from_taos() -> anyhow::Result<()> {
+        use taos_query::prelude::sync::*;
+
+        for dsn in [
+            "taos://localhost:6030/?timezone=UTC&configDir=/etc/taos",
+            "ws://localhost:6041/?timezone=UTC&conn_timeout=5s",
+        ] {
+            let taos = TaosBuilder::from_dsn(dsn)?.build()?;
+            let tmq = super::tmq_dsn_of(
+                &taos,
+                [("group.id", "group1"), ("auto.offset.reset", "earliest")],
+            );
+            let expected: Dsn =
+                format!("{dsn}&group.id=group1&auto.offset.reset=earliest").parse()?;
+            assert_eq!(tmq.to_string(), expected.to_string());
+
+            let _consumer = TmqBuilder::from_taos(&taos, [("group.id", "group1")])?.build()?;
+        }
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_ws_tmq_meta() -> anyhow::Result<()> {
         // pretty_env_logger::formatted_timed_builder()
@@ -514,4 +929,778 @@ mod tests {
         .await?;
         Ok(())
     }
+
+    /// Forward messages by raw bytes, replay the reconstructed messages into another database
+    /// and compare the results.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tmq_raw_bytes_forwarding() -> anyhow::Result<()> {
+        use taos_query::common::{RawData, RawMeta};
+        use taos_query::prelude::*;
+
+        for (url, db) in [
+            ("taos://localhost:6030", "tmq_raw_bytes_native"),
+            ("ws://localhost:6041", "tmq_raw_bytes_ws"),
+        ] {
+            let target = format!("{db}_target");
+            let taos = TaosBuilder::from_dsn(url)?.build()?;
+            taos.exec_many([
+                format!("drop topic if exists {db}"),
+                format!("drop database if exists {db}"),
+                format!("drop database if exists {target}"),
+                format!("create database {db}"),
+                format!("create database {target}"),
+                format!("create topic {db} with meta as database {db}"),
+                format!("use {db}"),
+                "create table stb(ts timestamp, v int, s varchar(10)) tags(t int)".to_string(),
+                "create table tb0 using stb tags(0)".to_string(),
+                "create table tb1 using stb tags(NULL)".to_string(),
+                "insert into tb0 values(1655793421375, 1, 'abc') tb1 values(1655793421376, NULL, NULL)"
+                    .to_string(),
+            ])
+            .await?;
+
+            let mut dsn = Dsn::from_str(url)?;
+            dsn.set("group.id", "raw_bytes");
+            dsn.set("auto.offset.reset", "earliest");
+            let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+            consumer.subscribe([db]).await?;
+
+            // The bridge side: forward bytes without decoding blocks.
+            let mut forwarded: Vec<Vec<u8>> = Vec::new();
+            {
+                let mut stream = consumer.stream_with_timeout(Timeout::from_secs(1));
+                while let Some((offset, message)) = stream.try_next().await? {
+                    let raw = message.raw_bytes().expect("raw bytes of message");
+                    forwarded.push(raw.into_owned());
+                    consumer.commit(offset).await?;
+                }
+            }
+            consumer.unsubscribe().await;
+            assert!(!forwarded.is_empty());
+
+            // The processing side: reconstruct and replay.
+            let target_taos = TaosBuilder::from_dsn(url)?.build()?;
+            target_taos.exec(format!("use {target}")).await?;
+            let mut rows = 0;
+            for bytes in forwarded {
+                let mut message: MessageSet<RawMeta, RawData> = MessageSet::from_raw_bytes(bytes)?;
+                if let Some(data) = message.data() {
+                    rows += data
+                        .blocks()?
+                        .iter()
+                        .map(|block| block.nrows())
+                        .sum::<usize>();
+                }
+                let raw = match message {
+                    MessageSet::Meta(meta) | MessageSet::MetaData(meta, _) => meta,
+                    MessageSet::Data(data) => RawMeta::from(data),
+                };
+                target_taos.write_raw_meta(&raw).await?;
+            }
+            assert_eq!(rows, 2);
+
+            let sql = "select tbname, t, * from {} order by ts";
+            let expected = taos
+                .query(sql.replace("{}", &format!("{db}.stb")))
+                .await?
+                .to_records()?;
+            let actual = target_taos
+                .query(sql.replace("{}", &format!("{target}.stb")))
+                .await?
+                .to_records()?;
+            assert_eq!(expected, actual);
+
+            taos.exec_many([
+                format!("drop topic {db}"),
+                format!("drop database {db}"),
+                format!("drop database {target}"),
+            ])
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Consume blocks by websocket, write them into another database in chunks and compare.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ws_write_raw_block_round_trip() -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+
+        let db = "ws_write_raw_block";
+        let target = format!("{db}_target");
+        let schema = [
+            "create table stb(ts timestamp, v int, s varchar(100)) tags(t int)",
+            "create table tb0 using stb tags(0)",
+            "create table tb1 using stb tags(1)",
+        ];
+        let taos = TaosBuilder::from_dsn("ws://localhost:6041")?.build()?;
+        taos.exec_many([
+            format!("drop topic if exists {db}"),
+            format!("drop database if exists {db}"),
+            format!("drop database if exists {target}"),
+            format!("create database {db} wal_retention_period 3600"),
+            format!("create database {target}"),
+            format!("create topic {db} as database {db}"),
+        ])
+        .await?;
+        for database in [db, &target] {
+            taos.exec(format!("use {database}")).await?;
+            taos.exec_many(schema).await?;
+        }
+        taos.exec(format!("use {db}")).await?;
+        for i in 0..1000 {
+            taos.exec(format!(
+                "insert into tb{} values({}, {i}, '{}')",
+                i % 2,
+                1704067200000i64 + i,
+                "s".repeat(i as usize % 100)
+            ))
+            .await?;
+        }
+
+        let mut dsn = Dsn::from_str("ws://localhost:6041")?;
+        dsn.set("group.id", db);
+        dsn.set("auto.offset.reset", "earliest");
+        let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+        consumer.subscribe([db]).await?;
+
+        // small messages, so blocks are written in chunks.
+        let target_taos = TaosBuilder::from_dsn(format!(
+            "ws://localhost:6041/{target}?maxWriteBlockSize=4096"
+        ))?
+        .build()?;
+        while let Some((offset, message)) = consumer.recv_timeout(Timeout::from_secs(2)).await? {
+            if let Some(data) = message.into_data() {
+                while let Some(block) = data.fetch_raw_block().await? {
+                    target_taos.write_raw_block(&block).await?;
+                }
+            }
+            consumer.commit(offset).await?;
+        }
+        consumer.unsubscribe().await;
+
+        let sql = "select tbname, t, * from {} order by tbname, ts";
+        let expected = taos
+            .query(sql.replace("{}", &format!("{db}.stb")))
+            .await?
+            .to_records()?;
+        let actual = taos
+            .query(sql.replace("{}", &format!("{target}.stb")))
+            .await?
+            .to_records()?;
+        assert_eq!(expected.len(), 1000);
+        assert_eq!(expected, actual);
+
+        taos.exec_many([
+            format!("drop topic {db}"),
+            format!("drop database {db}"),
+            format!("drop database {target}"),
+        ])
+        .await?;
+        Ok(())
+    }
+
+    /// A consumer that holds its assignment but stops polling is detected as stale.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tmq_stale_consumer() -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+        use taos_query::tmq::admin::{high_watermarks, StaleDetector};
+
+        for (url, db) in [
+            ("taos://localhost:6030", "tmq_stale_native"),
+            ("ws://localhost:6041", "tmq_stale_ws"),
+        ] {
+            let taos = TaosBuilder::from_dsn(url)?.build()?;
+            taos.exec_many([
+                format!("drop topic if exists {db}"),
+                format!("drop database if exists {db}"),
+                format!("create database {db} vgroups 1 wal_retention_period 3600"),
+                format!("create topic {db} as database {db}"),
+                format!("create table {db}.tb0(ts timestamp, v int)"),
+                format!("insert into {db}.tb0 values(now, 0)"),
+            ])
+            .await?;
+
+            let mut dsn = Dsn::from_str(url)?;
+            dsn.set("group.id", db);
+            dsn.set("auto.offset.reset", "earliest");
+            let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+            consumer.subscribe([db]).await?;
+            // poll and commit once, then stop polling.
+            if let Some((offset, _)) = consumer.recv_timeout(Timeout::from_secs(5)).await? {
+                consumer.commit(offset).await?;
+            }
+            for i in 1..10 {
+                taos.exec(format!("insert into {db}.tb0 values(now + {i}s, {i})"))
+                    .await?;
+            }
+
+            let mut detector = StaleDetector::new(Duration::from_secs(1));
+            let mut stale = Vec::new();
+            for _ in 0..2 {
+                let assignments = consumer.assignments(db).await?;
+                let watermarks = high_watermarks(db, &assignments);
+                let subscriptions: Vec<_> = taos
+                    .subscriptions()
+                    .await?
+                    .into_iter()
+                    .filter(|s| s.topic == db)
+                    .collect();
+                assert!(!subscriptions.is_empty());
+                stale = detector.find_stale(&subscriptions, &watermarks);
+                tokio::time::sleep(Duration::from_millis(1500)).await;
+            }
+            assert_eq!(stale.len(), 1, "{stale:?}");
+            assert_eq!(stale[0].subscription.consumer_group, db);
+            assert!(stale[0].backlog > 0);
+
+            consumer.unsubscribe().await;
+            taos.exec_many([format!("drop topic {db}"), format!("drop database {db}")])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Produce a burst and consume it slowly, the backlog should go down as it's consumed.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tmq_backlog() -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+
+        for (url, db) in [
+            ("taos://localhost:6030", "tmq_backlog_native"),
+            ("ws://localhost:6041", "tmq_backlog_ws"),
+        ] {
+            let taos = TaosBuilder::from_dsn(url)?.build()?;
+            taos.exec_many([
+                format!("drop topic if exists {db}"),
+                format!("drop database if exists {db}"),
+                format!("create database {db} vgroups 1 wal_retention_period 3600"),
+                format!("create topic {db} as database {db}"),
+                format!("use {db}"),
+                "create table tb0(ts timestamp, v int)".to_string(),
+            ])
+            .await?;
+            for batch in 0..20 {
+                let values: Vec<_> = (0..10)
+                    .map(|i| format!("(now + {}a, {i})", batch * 10 + i))
+                    .collect();
+                taos.exec(format!("insert into tb0 values {}", values.join(" ")))
+                    .await?;
+            }
+
+            let mut dsn = Dsn::from_str(url)?;
+            dsn.set("group.id", "backlog");
+            dsn.set("auto.offset.reset", "earliest");
+            dsn.set("watermark.refresh.interval", "100ms");
+            let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+            consumer.subscribe([db]).await?;
+
+            let mut backlogs = Vec::new();
+            while let Some((offset, message)) = consumer.recv_timeout(Timeout::from_secs(2)).await?
+            {
+                if let Some(data) = message.into_data() {
+                    while data.fetch_raw_block().await?.is_some() {}
+                }
+                if let Some(backlog) = offset.backlog() {
+                    backlogs.push(backlog);
+                }
+                consumer.commit(offset).await?;
+                tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+            }
+            consumer.unsubscribe().await;
+
+            dbg!(&backlogs);
+            assert!(backlogs.len() > 1, "backlog of {url} is not reported");
+            assert!(backlogs.windows(2).all(|w| w[1] <= w[0]));
+            assert!(backlogs.last() < backlogs.first());
+
+            taos.exec_many([format!("drop topic {db}"), format!("drop database {db}")])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Rows written 3s in the past, latency is measured by the creation time of messages if the
+    /// server reports it, or by the row timestamps.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tmq_latency() -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+        use taos_query::tmq::ConsumeReport;
+
+        for (url, db) in [
+            ("taos://localhost:6030", "tmq_latency_native"),
+            ("ws://localhost:6041", "tmq_latency_ws"),
+        ] {
+            let taos = TaosBuilder::from_dsn(url)?.build()?;
+            taos.exec_many([
+                format!("drop topic if exists {db}"),
+                format!("drop database if exists {db}"),
+                format!("create database {db} vgroups 1 wal_retention_period 3600"),
+                format!("create topic {db} as database {db}"),
+                format!("use {db}"),
+                "create table tb0(ts timestamp, v int)".to_string(),
+                "insert into tb0 values(now - 5s, 1)(now - 3s, 2)".to_string(),
+            ])
+            .await?;
+
+            let mut dsn = Dsn::from_str(url)?;
+            dsn.set("group.id", db);
+            dsn.set("auto.offset.reset", "earliest");
+            let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+            consumer.subscribe([db]).await?;
+
+            let mut report = ConsumeReport::new();
+            while let Some((offset, message)) = consumer.recv_timeout(Timeout::from_secs(2)).await?
+            {
+                report.record(&offset, &message);
+                if let Some(data) = message.into_data() {
+                    while let Some(block) = data.fetch_raw_block().await? {
+                        report.record_block(&offset, &block);
+                    }
+                }
+                consumer.commit(offset).await?;
+            }
+            consumer.unsubscribe().await;
+
+            let topic = &report.topics[db];
+            dbg!(&topic.latency);
+            assert_eq!(topic.latency.measured, topic.data, "latency of {url}");
+            assert!(topic.latency.max > Duration::ZERO);
+            if topic.latency.approximate == topic.latency.measured {
+                assert!(topic.latency.max >= Duration::from_secs(3));
+            }
+
+            taos.exec_many([format!("drop topic {db}"), format!("drop database {db}")])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Consume by a stream, which keeps waiting when idle and ends when closed by another task.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tmq_into_stream() -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+
+        for (url, db) in [
+            ("taos://localhost:6030", "tmq_stream_native"),
+            ("ws://localhost:6041", "tmq_stream_ws"),
+        ] {
+            let taos = TaosBuilder::from_dsn(url)?.build()?;
+            taos.exec_many([
+                format!("drop topic if exists {db}"),
+                format!("drop database if exists {db}"),
+                format!("create database {db} vgroups 1 wal_retention_period 3600"),
+                format!("create topic {db} as database {db}"),
+                format!("use {db}"),
+                "create table tb0(ts timestamp, v int)".to_string(),
+                "insert into tb0 values(now, 1)(now + 1s, 2)".to_string(),
+            ])
+            .await?;
+
+            let mut dsn = Dsn::from_str(url)?;
+            dsn.set("group.id", db);
+            dsn.set("auto.offset.reset", "earliest");
+            dsn.set("poll.interval", "200ms");
+            let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+            consumer.subscribe([db]).await?;
+            let mut stream = consumer.into_stream();
+            let handle = stream.handle();
+
+            let mut rows = 0;
+            while rows < 2 {
+                let (_offset, message) = stream.next().await.unwrap()?;
+                if let Some(data) = message.into_data() {
+                    while let Some(block) = data.fetch_raw_block().await? {
+                        rows += block.nrows();
+                    }
+                }
+            }
+            // idle polls are skipped until closed.
+            let closing = tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                handle.close()
+            });
+            assert!(
+                stream.next().await.is_none(),
+                "stream of {url} is not closed"
+            );
+            assert!(closing.await?);
+            drop(stream);
+
+            taos.exec_many([format!("drop topic {db}"), format!("drop database {db}")])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Consume a topic of 4 vgroups by 1 and 2 consumers, committing offsets by their owners.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tmq_parallel() -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+
+        use super::ParallelConsumer;
+
+        for (url, db) in [
+            ("taos://localhost:6030", "tmq_parallel_native"),
+            ("ws://localhost:6041", "tmq_parallel_ws"),
+        ] {
+            let taos = TaosBuilder::from_dsn(url)?.build()?;
+            taos.exec_many([
+                format!("drop topic if exists {db}"),
+                format!("drop database if exists {db}"),
+                format!("create database {db} vgroups 4 wal_retention_period 3600"),
+                format!("create topic {db} as database {db}"),
+                format!("use {db}"),
+                "create stable st(ts timestamp, v int) tags(t int)".to_string(),
+            ])
+            .await?;
+            let mut total = 0;
+            for table in 0..8 {
+                let values: Vec<_> = (0..1000)
+                    .map(|i| format!("(1700000000000 + {i}, {i})"))
+                    .collect();
+                taos.exec(format!(
+                    "insert into tb{table} using st tags({table}) values {}",
+                    values.join(" ")
+                ))
+                .await?;
+                total += values.len();
+            }
+
+            for parallelism in [1, 2] {
+                let mut dsn = Dsn::from_str(url)?;
+                dsn.set("group.id", format!("{db}_{parallelism}"));
+                dsn.set("auto.offset.reset", "earliest");
+                dsn.set("poll.interval", "200ms");
+                let builder = TmqBuilder::from_dsn(&dsn)?;
+                let consumer = ParallelConsumer::new(&builder, [db], parallelism).await?;
+                let started = std::time::Instant::now();
+                let mut stream = consumer.stream();
+                let mut rows = 0;
+                while rows < total {
+                    let (offset, message) = stream.next().await.unwrap()?;
+                    if let Some(data) = message.into_data() {
+                        while let Some(block) = data.fetch_raw_block().await? {
+                            rows += block.nrows();
+                        }
+                    }
+                    consumer.commit(offset).await?;
+                }
+                let elapsed = started.elapsed();
+                drop(stream);
+
+                let metrics = consumer.metrics();
+                log::info!(
+                    "{url} by {parallelism} consumers: {} rows/s, {metrics:?}",
+                    rows as f64 / elapsed.as_secs_f64()
+                );
+                assert_eq!(rows, total);
+                assert_eq!(metrics.instances.len(), parallelism);
+                for instance in &metrics.instances {
+                    assert!(instance.messages > 0, "{instance:?}");
+                    assert_eq!(instance.commits, instance.messages);
+                }
+                let vgroups: Vec<_> = metrics
+                    .instances
+                    .iter()
+                    .flat_map(|instance| &instance.vgroups)
+                    .collect();
+                assert_eq!(vgroups.len(), 4, "{vgroups:?}");
+                consumer.unsubscribe().await;
+            }
+
+            taos.exec_many([format!("drop topic {db}"), format!("drop database {db}")])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Consume all messages, seek back to the begin of each vgroup and consume them again.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tmq_offset_seek() -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+
+        for (url, db) in [
+            ("taos://localhost:6030", "tmq_seek_native"),
+            ("ws://localhost:6041", "tmq_seek_ws"),
+        ] {
+            let taos = TaosBuilder::from_dsn(url)?.build()?;
+            taos.exec_many([
+                format!("drop topic if exists {db}"),
+                format!("drop database if exists {db}"),
+                format!("create database {db} vgroups 2 wal_retention_period 3600"),
+                format!("create topic {db} as database {db}"),
+                format!("use {db}"),
+                "create table st(ts timestamp, v int) tags(t int)".to_string(),
+            ])
+            .await?;
+            for i in 0..10 {
+                taos.exec(format!(
+                    "insert into tb{i} using st tags({i}) values(now, {i})"
+                ))
+                .await?;
+            }
+
+            let mut dsn = Dsn::from_str(url)?;
+            dsn.set("group.id", db);
+            dsn.set("auto.offset.reset", "earliest");
+            let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+            consumer.subscribe([db]).await?;
+
+            async fn consume(consumer: &super::Consumer) -> anyhow::Result<Vec<(i32, i64)>> {
+                let mut messages = Vec::new();
+                while let Some((offset, message)) =
+                    consumer.recv_timeout(Timeout::from_secs(2)).await?
+                {
+                    if let Some(data) = message.into_data() {
+                        while data.fetch_raw_block().await?.is_some() {}
+                    }
+                    messages.push((offset.vgroup_id(), offset.offset().unwrap_or_default()));
+                    consumer.commit(offset).await?;
+                }
+                messages.sort_unstable();
+                Ok(messages)
+            }
+
+            let first = consume(&consumer).await?;
+            assert!(!first.is_empty());
+            let assignments = consumer.assignments(db).await?;
+            assert_eq!(assignments.len(), 2);
+            for assignment in &assignments {
+                consumer
+                    .offset_seek(db, assignment.vgroup_id, assignment.begin)
+                    .await?;
+            }
+            let second = consume(&consumer).await?;
+            assert_eq!(first, second);
+
+            let past_end = assignments[0].end + 100;
+            assert!(consumer
+                .offset_seek(db, assignments[0].vgroup_id, past_end)
+                .await
+                .is_err());
+
+            consumer.unsubscribe().await;
+            taos.exec_many([format!("drop topic {db}"), format!("drop database {db}")])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Typed rows of data messages, meta messages of the topic are skipped.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tmq_deserialize_data() -> anyhow::Result<()> {
+        use taos_query::prelude::*;
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Record {
+            v: i32,
+            s: Option<String>,
+        }
+
+        for (url, db) in [
+            ("taos://localhost:6030", "tmq_deserialize_native"),
+            ("ws://localhost:6041", "tmq_deserialize_ws"),
+        ] {
+            let taos = TaosBuilder::from_dsn(url)?.build()?;
+            taos.exec_many([
+                format!("drop topic if exists {db}"),
+                format!("drop database if exists {db}"),
+                format!("create database {db} wal_retention_period 3600"),
+                format!("create topic {db} with meta as database {db}"),
+                format!("use {db}"),
+                "create table tb(ts timestamp, v int, s varchar(10))".to_string(),
+            ])
+            .await?;
+            for i in 0..100 {
+                let s = if i % 2 == 0 {
+                    "null".to_string()
+                } else {
+                    format!("'s{i}'")
+                };
+                taos.exec(format!(
+                    "insert into tb values({}, {i}, {s})",
+                    1704067200000i64 + i
+                ))
+                .await?;
+            }
+
+            let mut dsn = Dsn::from_str(url)?;
+            dsn.set("group.id", db);
+            dsn.set("auto.offset.reset", "earliest");
+            let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+            consumer.subscribe([db]).await?;
+
+            let mut records = Vec::new();
+            while let Some((offset, message)) = consumer.recv_timeout(Timeout::from_secs(2)).await?
+            {
+                let mut rows = message.deserialize_data_async::<Record>();
+                while let Some(record) = rows.try_next().await? {
+                    records.push(record);
+                }
+                consumer.commit(offset).await?;
+            }
+            records.sort_unstable_by_key(|record| record.v);
+            assert_eq!(records.len(), 100);
+            assert_eq!(records[0], Record { v: 0, s: None });
+            assert_eq!(
+                records[99],
+                Record {
+                    v: 99,
+                    s: Some("s99".to_string())
+                }
+            );
+
+            consumer.unsubscribe().await;
+            taos.exec_many([format!("drop topic {db}"), format!("drop database {db}")])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Mock ws server of messages at offsets 1 to 5 of vgroup 3 of `topic1`, each connection
+    /// consumes from the beginning as the server keeps no commits. Returns the address and the
+    /// subscribe, seek and commit requests of all connections.
+    async fn offset_store_server() -> anyhow::Result<(
+        std::net::SocketAddr,
+        std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    )> {
+        use std::sync::{Arc, Mutex};
+
+        use futures::{SinkExt, StreamExt};
+        use taos_query::prelude::tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        taos_query::prelude::tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                taos_query::prelude::tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    let mut position = 0;
+                    while let Some(Ok(message)) = ws.next().await {
+                        let Message::Text(text) = message else {
+                            continue;
+                        };
+                        let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let args = &req["args"];
+                        let req_id = args["req_id"].as_u64().unwrap_or_default();
+                        let action = req["action"].as_str().unwrap();
+                        let reply = match action {
+                            "subscribe" => {
+                                recorded
+                                    .lock()
+                                    .unwrap()
+                                    .push(format!("subscribe auto_commit={}", args["auto_commit"]));
+                                format!(
+                                    r#"{{"code":0,"message":"","action":"subscribe","req_id":{req_id}}}"#
+                                )
+                            }
+                            "assignment" => format!(
+                                r#"{{"code":0,"message":"","action":"assignment","req_id":{req_id},"assignment":[{{"vgroup_id":3,"offset":{position},"begin":0,"end":5}}]}}"#
+                            ),
+                            "seek" => {
+                                position = args["offset"].as_i64().unwrap();
+                                recorded.lock().unwrap().push(format!("seek {position}"));
+                                format!(
+                                    r#"{{"code":0,"message":"","action":"seek","req_id":{req_id}}}"#
+                                )
+                            }
+                            "poll" if position < 5 => {
+                                position += 1;
+                                format!(
+                                    r#"{{"code":0,"message":"","action":"poll","req_id":{req_id},"have_message":true,"topic":"topic1","database":"db","vgroup_id":3,"message_type":1,"message_id":{position},"offset":{position}}}"#
+                                )
+                            }
+                            "poll" => format!(
+                                r#"{{"code":0,"message":"","action":"poll","req_id":{req_id},"have_message":false}}"#
+                            ),
+                            "commit" | "commit_offset" => {
+                                recorded.lock().unwrap().push(action.to_string());
+                                format!(
+                                    r#"{{"code":0,"message":"","action":"{action}","req_id":{req_id}}}"#
+                                )
+                            }
+                            _ => continue,
+                        };
+                        ws.send(Message::Text(reply)).await.unwrap();
+                    }
+                });
+            }
+        });
+        Ok((addr, requests))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn offset_store_crash_recovery() -> anyhow::Result<()> {
+        use std::sync::Arc;
+
+        use taos_query::prelude::*;
+        use taos_query::tmq::FileOffsetStore;
+
+        let (addr, requests) = offset_store_server().await?;
+        let dsn = format!("ws://{addr}?group.id=g1");
+        let path =
+            std::env::temp_dir().join(format!("taos-offset-store-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // consume and commit 3 messages, then crash without unsubscribing.
+        {
+            let store = Arc::new(FileOffsetStore::new(&path));
+            let mut consumer = TmqBuilder::from_dsn(&dsn)?
+                .with_offset_store(store)?
+                .build()?;
+            consumer.subscribe(["topic1"]).await?;
+            for expected in 1..=3 {
+                let (offset, _) = consumer
+                    .recv_timeout(Timeout::from_millis(300))
+                    .await?
+                    .unwrap();
+                assert_eq!(offset.offset(), Some(expected));
+                consumer.commit(offset).await?;
+            }
+        }
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                "subscribe auto_commit=\"false\"",
+                "commit",
+                "commit",
+                "commit"
+            ]
+        );
+        requests.lock().unwrap().clear();
+
+        // restart with the offsets in the file, and resume after the last saved one.
+        let store = Arc::new(FileOffsetStore::new(&path));
+        let mut consumer = TmqBuilder::from_dsn(&dsn)?
+            .with_offset_store(store.clone())?
+            .build()?;
+        consumer.subscribe(["topic1"]).await?;
+        let (offset, _) = consumer
+            .recv_timeout(Timeout::from_millis(300))
+            .await?
+            .unwrap();
+        assert_eq!(offset.offset(), Some(4));
+        consumer.commit_offset("topic1", 3, 4).await?;
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["subscribe auto_commit=\"false\"", "seek 3", "commit_offset"]
+        );
+        assert_eq!(
+            store.load("g1", "topic1").await?,
+            [taos_query::tmq::OffsetValue::new("g1", "topic1", 3, 4)]
+        );
+
+        // without a store, auto commit is left to the server.
+        let mut consumer = TmqBuilder::from_dsn(&dsn)?.build()?;
+        requests.lock().unwrap().clear();
+        consumer.subscribe(["topic1"]).await?;
+        assert_eq!(*requests.lock().unwrap(), ["subscribe auto_commit=null"]);
+
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
 }
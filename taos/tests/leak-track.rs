@@ -0,0 +1,82 @@
+//! Result sets leaked on purpose are reported with their creation sites.
+#![cfg(all(feature = "leak-track", feature = "ws"))]
+
+use futures::{SinkExt, StreamExt};
+use taos::diagnostics::{live_objects, ObjectKind, BACKTRACE_ENV};
+use taos::*;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Mock server answering every query as an insert of one row.
+async fn mock_server() -> anyhow::Result<std::net::SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(message)) = ws.next().await {
+                    let Message::Text(text) = message else {
+                        continue;
+                    };
+                    let req: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    let req_id = req["args"]["req_id"].as_u64().unwrap_or_default();
+                    let reply = match req["action"].as_str().unwrap() {
+                        "version" => format!(
+                            r#"{{"code":0,"message":"","action":"version","req_id":{req_id},"version":"3.0.0.0"}}"#
+                        ),
+                        "conn" => format!(
+                            r#"{{"code":0,"message":"","action":"conn","req_id":{req_id}}}"#
+                        ),
+                        "query" => format!(
+                            r#"{{"code":0,"message":"","action":"query","req_id":{req_id},"id":{req_id},"is_update":true,"affected_rows":1}}"#
+                        ),
+                        _ => continue,
+                    };
+                    let _ = ws.send(Message::Text(reply)).await;
+                }
+            });
+        }
+    });
+    Ok(addr)
+}
+
+/// Forget a result set, so it's never dropped.
+async fn leaky_query(taos: &Taos) -> anyhow::Result<()> {
+    let rs = taos.query("insert into tb values(now, 1)").await?;
+    std::mem::forget(rs);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leaked_result_sets() -> anyhow::Result<()> {
+    // set before any object is tracked, it's the only test of this binary.
+    std::env::set_var(BACKTRACE_ENV, "1");
+    let addr = mock_server().await?;
+    let taos = TaosBuilder::from_dsn(format!("ws://{addr}"))?.build()?;
+
+    let rs = taos.query("insert into tb values(now, 1)").await?;
+    assert_eq!(taos.live_counts().result_sets, 1);
+    drop(rs);
+    assert_eq!(taos.live_counts().result_sets, 0);
+
+    for _ in 0..3 {
+        leaky_query(&taos).await?;
+    }
+    assert_eq!(taos.live_counts().result_sets, 3);
+
+    let report = live_objects();
+    assert_eq!(report.counts.result_sets, 3);
+    let leaked: Vec<_> = report
+        .oldest
+        .iter()
+        .filter(|object| object.kind == ObjectKind::ResultSet)
+        .collect();
+    assert_eq!(leaked.len(), 3);
+    for object in leaked {
+        let backtrace = object.backtrace.as_deref().unwrap();
+        assert!(backtrace.contains("leaky_query"), "{backtrace}");
+    }
+    assert!(report.to_string().contains("ResultSet created"));
+    Ok(())
+}